@@ -0,0 +1,107 @@
+/*
+ * VHP C embedding API
+ *
+ * Link against the vhp dynamic library built by `cargo build --release`
+ * (target/release/libvhp.so, libvhp.dylib or vhp.dll).
+ *
+ *     vhp_engine *vhp = vhp_new();
+ *     if (vhp_eval(vhp, "<?php echo 'Hello';") == VHP_OK)
+ *         puts(vhp_get_output(vhp));
+ *     else
+ *         fprintf(stderr, "%s\n", vhp_get_error(vhp));
+ *     vhp_free(vhp);
+ *
+ * Values cross the boundary as JSON text. Strings returned by the engine
+ * stay valid until the next call on the same engine or vhp_free.
+ *
+ * Plugins are shared libraries exporting vhp_plugin_init (see below).
+ * They get their entry points from a vhp_plugin_api table rather than by
+ * linking, so the same plugin loads into the vhp binary
+ * (vhp --extension=plugin.so script.php) and into vhp_load_plugin.
+ */
+
+#ifndef VHP_H
+#define VHP_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define VHP_OK 0
+#define VHP_ERROR 1
+
+typedef struct VhpEngine vhp_engine;
+typedef struct VhpCall vhp_call;
+typedef struct Registry vhp_registry;
+
+/*
+ * A function implemented by the host. args_json is a JSON array of the
+ * PHP arguments. Report the result with vhp_call_return or
+ * vhp_call_error; without either the function returns null.
+ */
+typedef void (*vhp_function)(void *user_data, const char *args_json, vhp_call *call);
+
+/* Create an engine; free it with vhp_free. */
+vhp_engine *vhp_new(void);
+
+/*
+ * Run a script (starting with "<?php", like a file) in a fresh context.
+ * Returns VHP_OK when it runs to the end or calls exit(), otherwise
+ * VHP_ERROR.
+ */
+int vhp_eval(vhp_engine *engine, const char *source);
+
+/* Output of the last vhp_eval (cut at the first NUL byte). */
+const char *vhp_get_output(const vhp_engine *engine);
+
+/* Error of the last vhp_eval, or NULL if it succeeded. */
+const char *vhp_get_error(const vhp_engine *engine);
+
+/*
+ * Make function callable from scripts as name (case-insensitive).
+ * user_data is passed back on every call. Registering a name again
+ * replaces the previous function.
+ */
+int vhp_register_function(vhp_engine *engine, const char *name, vhp_function function,
+                          void *user_data);
+
+/*
+ * Load the plugin library at path; on failure returns VHP_ERROR with the
+ * reason in vhp_get_error. Plugins run native code: load trusted files only.
+ */
+int vhp_load_plugin(vhp_engine *engine, const char *path);
+
+/* Return a JSON value from a host function (copied). */
+void vhp_call_return(vhp_call *call, const char *json);
+
+/* Fail a host function with message (copied), stopping the script. */
+void vhp_call_error(vhp_call *call, const char *message);
+
+/* Free an engine and every string it returned. */
+void vhp_free(vhp_engine *engine);
+
+/*
+ * Entry points for plugins, valid for the life of the process.
+ * add_function and add_constant take the registry passed to
+ * vhp_plugin_init; constants are JSON values read with constant() and
+ * defined(). Both return VHP_OK or VHP_ERROR.
+ */
+typedef struct vhp_plugin_api {
+    int (*add_function)(vhp_registry *registry, const char *name, vhp_function function,
+                        void *user_data);
+    int (*add_constant)(vhp_registry *registry, const char *name, const char *json);
+    void (*call_return)(vhp_call *call, const char *json);
+    void (*call_error)(vhp_call *call, const char *message);
+} vhp_plugin_api;
+
+/*
+ * Exported by every plugin: add functions and constants to registry and
+ * return VHP_OK. Keep api to report results from the functions.
+ */
+typedef int (*vhp_plugin_init_fn)(vhp_registry *registry, const vhp_plugin_api *api);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* VHP_H */
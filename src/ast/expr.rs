@@ -1,42 +1,43 @@
 use super::ops::{AssignOp, BinaryOp, UnaryOp};
+use crate::vm::cache::codec::Codec;
 
 /// Array element with optional key
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct ArrayElement {
     pub key: Option<Box<Expr>>,
     pub value: Box<Expr>,
 }
 
 /// List element for destructuring with optional key
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct ListElement {
     pub key: Option<Box<Expr>>, // Optional key: "key" => $var
-    pub value: Box<Expr>,       // Variable or nested list
+    pub value: Box<Expr>,       // Writable target or nested list
 }
 
 /// Property modification for clone with syntax (PHP 8.4)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct PropertyModification {
     pub property: String,
     pub value: Box<Expr>,
 }
 
 /// Function/method call argument with optional name (PHP 8.0 named arguments)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct Argument {
     pub name: Option<String>, // None for positional, Some("name") for named
     pub value: Box<Expr>,
 }
 
 /// Match arm for match expressions (PHP 8.0)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct MatchArm {
     pub conditions: Vec<Expr>, // Multiple conditions separated by comma
     pub result: Box<Expr>,
 }
 
 /// Expressions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub enum Expr {
     // Literals
     String(String),
@@ -45,13 +46,13 @@ pub enum Expr {
     Bool(bool),
     Null,
 
-    // Heredoc string with variable interpolation
-    /// Contains the content with variable placeholders
-    Heredoc(String),
-
     // Variable
     Variable(String),
 
+    // Constant fetch: E_ALL, PREG_SET_ORDER
+    /// The name as written, looked up when the expression runs
+    Constant(String),
+
     // Array literal
     Array(Vec<ArrayElement>),
 
@@ -76,6 +77,11 @@ pub enum Expr {
         op: AssignOp,
         value: Box<Expr>,
     },
+    // Reference assignment: $var = &$source
+    AssignRef {
+        var: String,
+        source: Box<Expr>,
+    },
     // Array element assignment: $arr[key] = value
     ArrayAssign {
         array: Box<Expr>,
@@ -87,10 +93,10 @@ pub enum Expr {
     // Grouping
     Grouped(Box<Expr>),
 
-    // Ternary
+    // Ternary; `then_expr` is None for the short ternary `a ?: b`
     Ternary {
         condition: Box<Expr>,
-        then_expr: Box<Expr>,
+        then_expr: Option<Box<Expr>>,
         else_expr: Box<Expr>,
     },
 
@@ -112,6 +118,15 @@ pub enum Expr {
         args: Vec<Argument>,
     },
 
+    // Instantiation of a class named at runtime: new $class(args), new ($expr)(args)
+    NewDynamic {
+        class: Box<Expr>,
+        args: Vec<Argument>,
+    },
+
+    // Class name reference: ClassName::class, static::class
+    ClassReference(String),
+
     // Anonymous class instantiation (PHP 7.0): new class(...) extends X implements Y { ... }
     #[allow(dead_code)] // interfaces and traits parsed but not yet used
     NewAnonymousClass {
@@ -141,10 +156,24 @@ pub enum Expr {
         args: Vec<Argument>,
     },
 
+    // Nullsafe property access (PHP 8.0): $obj?->property, null when $obj is
+    NullsafePropertyAccess {
+        object: Box<Expr>,
+        property: String,
+    },
+
+    // Nullsafe method call (PHP 8.0): $obj?->method(args), null when $obj is
+    NullsafeMethodCall {
+        object: Box<Expr>,
+        method: String,
+        args: Vec<Argument>,
+    },
+
     // Property assignment: $obj->property = value
     PropertyAssign {
         object: Box<Expr>,
         property: String,
+        op: AssignOp,
         value: Box<Expr>,
     },
 
@@ -168,6 +197,7 @@ pub enum Expr {
     StaticPropertyAssign {
         class: String,
         property: String,
+        op: AssignOp,
         value: Box<Expr>,
     },
 
@@ -202,6 +232,12 @@ pub enum Expr {
         modifications: Vec<PropertyModification>,
     },
 
+    // Clone with an array of new property values (PHP 8.5): clone($obj, $properties)
+    CloneWithProperties {
+        object: Box<Expr>,
+        properties: Box<Expr>,
+    },
+
     // Placeholder for pipe operator: ... (PHP 8.5)
     Placeholder,
 
@@ -244,10 +280,11 @@ pub enum Expr {
     /// yield from $iterable
     YieldFrom(Box<Expr>),
 
-    // List destructuring (PHP 7.1+): list($a, $b) = $array
-    /// Contains the list elements with optional keys and the source array
+    // List destructuring (PHP 7.1+): list($a, $b) = $array, [$a, , $c] = $array
+    /// Contains the list elements with optional keys (None for a skipped
+    /// slot) and the source array
     ListDestructure {
-        elements: Vec<ListElement>,
+        elements: Vec<Option<ListElement>>,
         array: Box<Expr>,
     },
 
@@ -269,3 +306,17 @@ pub enum Expr {
     /// __TRAIT__ - Current trait name (or empty)
     MagicTrait,
 }
+
+impl Expr {
+    /// Whether this is a chain of `->`, `?->` and `[]` links with a `?->`
+    /// among them, which a null object on its left cuts short
+    pub fn is_nullsafe_chain(&self) -> bool {
+        match self {
+            Expr::NullsafePropertyAccess { .. } | Expr::NullsafeMethodCall { .. } => true,
+            Expr::PropertyAccess { object, .. }
+            | Expr::MethodCall { object, .. }
+            | Expr::ArrayAccess { array: object, .. } => object.is_nullsafe_chain(),
+            _ => false,
+        }
+    }
+}
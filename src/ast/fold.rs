@@ -0,0 +1,378 @@
+//! Owning AST transformation
+//!
+//! A `Folder` takes nodes by value and returns their replacements. The
+//! default methods call the matching `walk_*` function, which folds every
+//! child node and rebuilds the parent, so an override only has to handle
+//! the nodes it rewrites:
+//!
+//! ```
+//! use vhp::ast::fold::{self, Folder};
+//! use vhp::ast::Expr;
+//!
+//! /// Renames every call to `old()` into a call to `new()`
+//! struct Rename;
+//!
+//! impl Folder for Rename {
+//!     fn fold_expr(&mut self, expr: Expr) -> Expr {
+//!         match fold::walk_expr(self, expr) {
+//!             Expr::FunctionCall { name, args } if name == "old" => Expr::FunctionCall {
+//!                 name: "new".to_string(),
+//!                 args,
+//!             },
+//!             other => other,
+//!         }
+//!     }
+//! }
+//! ```
+
+mod expr;
+
+pub use expr::walk_expr;
+
+use super::{
+    Attribute, AttributeArgument, CatchClause, Expr, ForeachTarget, FunctionParam,
+    InterfaceConstant, InterfaceMethodSignature, Method, NamespaceBody, Property, PropertyHook,
+    PropertyHookBody, Stmt, SwitchCase,
+};
+
+pub trait Folder {
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        walk_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        walk_expr(self, expr)
+    }
+
+    fn fold_method(&mut self, method: Method) -> Method {
+        walk_method(self, method)
+    }
+
+    fn fold_property(&mut self, property: Property) -> Property {
+        walk_property(self, property)
+    }
+
+    fn fold_param(&mut self, param: FunctionParam) -> FunctionParam {
+        walk_param(self, param)
+    }
+
+    fn fold_attribute(&mut self, attribute: Attribute) -> Attribute {
+        walk_attribute(self, attribute)
+    }
+}
+
+/// Fold each statement of a block
+pub fn walk_block<F: Folder + ?Sized>(folder: &mut F, statements: Vec<Stmt>) -> Vec<Stmt> {
+    statements
+        .into_iter()
+        .map(|stmt| folder.fold_stmt(stmt))
+        .collect()
+}
+
+pub fn walk_stmt<F: Folder + ?Sized>(folder: &mut F, stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::Echo(exprs) => Stmt::Echo(walk_exprs(folder, exprs)),
+        Stmt::Expression(expr) => Stmt::Expression(folder.fold_expr(expr)),
+        Stmt::Throw(expr) => Stmt::Throw(folder.fold_expr(expr)),
+        Stmt::Return(value) => Stmt::Return(value.map(|value| folder.fold_expr(value))),
+        Stmt::Global(names) => Stmt::Global(names),
+        Stmt::If {
+            condition,
+            then_branch,
+            elseif_branches,
+            else_branch,
+        } => Stmt::If {
+            condition: folder.fold_expr(condition),
+            then_branch: walk_block(folder, then_branch),
+            elseif_branches: elseif_branches
+                .into_iter()
+                .map(|(condition, branch)| {
+                    (folder.fold_expr(condition), walk_block(folder, branch))
+                })
+                .collect(),
+            else_branch: else_branch.map(|branch| walk_block(folder, branch)),
+        },
+        Stmt::While { condition, body } => Stmt::While {
+            condition: folder.fold_expr(condition),
+            body: walk_block(folder, body),
+        },
+        Stmt::DoWhile { body, condition } => Stmt::DoWhile {
+            body: walk_block(folder, body),
+            condition: folder.fold_expr(condition),
+        },
+        Stmt::For {
+            init,
+            condition,
+            update,
+            body,
+        } => Stmt::For {
+            init: init.map(|expr| folder.fold_expr(expr)),
+            condition: condition.map(|expr| folder.fold_expr(expr)),
+            update: update.map(|expr| folder.fold_expr(expr)),
+            body: walk_block(folder, body),
+        },
+        Stmt::Foreach {
+            array,
+            key,
+            value,
+            by_ref,
+            body,
+        } => Stmt::Foreach {
+            array: folder.fold_expr(array),
+            key,
+            by_ref,
+            value: match value {
+                ForeachTarget::List(elements) => {
+                    ForeachTarget::List(expr::walk_list_elements(folder, elements))
+                }
+                value => value,
+            },
+            body: walk_block(folder, body),
+        },
+        Stmt::Switch { expr, cases } => Stmt::Switch {
+            expr: folder.fold_expr(expr),
+            cases: cases
+                .into_iter()
+                .map(|case| SwitchCase {
+                    value: case.value.map(|value| folder.fold_expr(value)),
+                    body: walk_block(folder, case.body),
+                })
+                .collect(),
+        },
+        Stmt::Function {
+            name,
+            params,
+            return_type,
+            body,
+            attributes,
+        } => Stmt::Function {
+            name,
+            params: walk_params(folder, params),
+            return_type,
+            body: walk_block(folder, body),
+            attributes: walk_attributes(folder, attributes),
+        },
+        Stmt::Interface {
+            name,
+            parents,
+            methods,
+            constants,
+            attributes,
+        } => Stmt::Interface {
+            name,
+            parents,
+            methods: methods
+                .into_iter()
+                .map(|method| InterfaceMethodSignature {
+                    params: walk_params(folder, method.params),
+                    attributes: walk_attributes(folder, method.attributes),
+                    ..method
+                })
+                .collect(),
+            constants: walk_constants(folder, constants),
+            attributes: walk_attributes(folder, attributes),
+        },
+        Stmt::Trait {
+            name,
+            uses,
+            properties,
+            methods,
+            attributes,
+        } => Stmt::Trait {
+            name,
+            uses,
+            properties: walk_properties(folder, properties),
+            methods: walk_methods(folder, methods),
+            attributes: walk_attributes(folder, attributes),
+        },
+        Stmt::Class {
+            name,
+            is_abstract,
+            is_final,
+            readonly,
+            parent,
+            interfaces,
+            trait_uses,
+            constants,
+            properties,
+            methods,
+            attributes,
+        } => Stmt::Class {
+            name,
+            is_abstract,
+            is_final,
+            readonly,
+            parent,
+            interfaces,
+            trait_uses,
+            constants: walk_constants(folder, constants),
+            properties: walk_properties(folder, properties),
+            methods: walk_methods(folder, methods),
+            attributes: walk_attributes(folder, attributes),
+        },
+        Stmt::Enum {
+            name,
+            backing_type,
+            interfaces,
+            cases,
+            constants,
+            methods,
+            attributes,
+        } => Stmt::Enum {
+            name,
+            backing_type,
+            interfaces,
+            cases: cases
+                .into_iter()
+                .map(|mut case| {
+                    case.value = case.value.map(|value| folder.fold_expr(value));
+                    case
+                })
+                .collect(),
+            constants: walk_constants(folder, constants),
+            methods: walk_methods(folder, methods),
+            attributes: walk_attributes(folder, attributes),
+        },
+        Stmt::TryCatch {
+            try_body,
+            catch_clauses,
+            finally_body,
+        } => Stmt::TryCatch {
+            try_body: walk_block(folder, try_body),
+            catch_clauses: catch_clauses
+                .into_iter()
+                .map(|clause| CatchClause {
+                    body: walk_block(folder, clause.body),
+                    ..clause
+                })
+                .collect(),
+            finally_body: finally_body.map(|body| walk_block(folder, body)),
+        },
+        Stmt::Namespace { name, body } => Stmt::Namespace {
+            name,
+            body: match body {
+                NamespaceBody::Braced(body) => NamespaceBody::Braced(walk_block(folder, body)),
+                NamespaceBody::Unbraced => NamespaceBody::Unbraced,
+            },
+        },
+        Stmt::Declare { directives, body } => Stmt::Declare {
+            directives,
+            body: body.map(|body| walk_block(folder, body)),
+        },
+        stmt @ (Stmt::Line(_)
+        | Stmt::Html(_)
+        | Stmt::Break
+        | Stmt::Continue
+        | Stmt::Use(_)
+        | Stmt::GroupUse(_)) => stmt,
+    }
+}
+
+pub fn walk_method<F: Folder + ?Sized>(folder: &mut F, method: Method) -> Method {
+    Method {
+        params: walk_params(folder, method.params),
+        body: walk_block(folder, method.body),
+        attributes: walk_attributes(folder, method.attributes),
+        ..method
+    }
+}
+
+pub fn walk_property<F: Folder + ?Sized>(folder: &mut F, property: Property) -> Property {
+    Property {
+        default: property.default.map(|default| folder.fold_expr(default)),
+        attributes: walk_attributes(folder, property.attributes),
+        hooks: property
+            .hooks
+            .into_iter()
+            .map(|hook| PropertyHook {
+                body: match hook.body {
+                    PropertyHookBody::Expression(expr) => {
+                        PropertyHookBody::Expression(Box::new(folder.fold_expr(*expr)))
+                    }
+                    PropertyHookBody::Block(body) => {
+                        PropertyHookBody::Block(walk_block(folder, body))
+                    }
+                },
+                ..hook
+            })
+            .collect(),
+        ..property
+    }
+}
+
+pub fn walk_param<F: Folder + ?Sized>(folder: &mut F, param: FunctionParam) -> FunctionParam {
+    FunctionParam {
+        default: param.default.map(|default| folder.fold_expr(default)),
+        attributes: walk_attributes(folder, param.attributes),
+        ..param
+    }
+}
+
+pub fn walk_attribute<F: Folder + ?Sized>(folder: &mut F, attribute: Attribute) -> Attribute {
+    Attribute {
+        arguments: attribute
+            .arguments
+            .into_iter()
+            .map(|argument| AttributeArgument {
+                value: folder.fold_expr(argument.value),
+                ..argument
+            })
+            .collect(),
+        ..attribute
+    }
+}
+
+fn walk_exprs<F: Folder + ?Sized>(folder: &mut F, exprs: Vec<Expr>) -> Vec<Expr> {
+    exprs
+        .into_iter()
+        .map(|expr| folder.fold_expr(expr))
+        .collect()
+}
+
+fn walk_params<F: Folder + ?Sized>(
+    folder: &mut F,
+    params: Vec<FunctionParam>,
+) -> Vec<FunctionParam> {
+    params
+        .into_iter()
+        .map(|param| folder.fold_param(param))
+        .collect()
+}
+
+fn walk_constants<F: Folder + ?Sized>(
+    folder: &mut F,
+    constants: Vec<InterfaceConstant>,
+) -> Vec<InterfaceConstant> {
+    constants
+        .into_iter()
+        .map(|constant| InterfaceConstant {
+            value: folder.fold_expr(constant.value),
+            attributes: walk_attributes(folder, constant.attributes),
+            ..constant
+        })
+        .collect()
+}
+
+fn walk_properties<F: Folder + ?Sized>(folder: &mut F, properties: Vec<Property>) -> Vec<Property> {
+    properties
+        .into_iter()
+        .map(|property| folder.fold_property(property))
+        .collect()
+}
+
+fn walk_methods<F: Folder + ?Sized>(folder: &mut F, methods: Vec<Method>) -> Vec<Method> {
+    methods
+        .into_iter()
+        .map(|method| folder.fold_method(method))
+        .collect()
+}
+
+fn walk_attributes<F: Folder + ?Sized>(
+    folder: &mut F,
+    attributes: Vec<Attribute>,
+) -> Vec<Attribute> {
+    attributes
+        .into_iter()
+        .map(|attribute| folder.fold_attribute(attribute))
+        .collect()
+}
@@ -0,0 +1,267 @@
+//! Expression folding
+
+use super::{walk_methods, walk_params, walk_properties, Folder};
+use crate::ast::{Argument, ArrayElement, Expr, ListElement, MatchArm, PropertyModification};
+
+pub fn walk_expr<F: Folder + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    match expr {
+        Expr::Array(elements) => Expr::Array(
+            elements
+                .into_iter()
+                .map(|element| ArrayElement {
+                    key: element.key.map(|key| boxed(folder, key)),
+                    value: boxed(folder, element.value),
+                })
+                .collect(),
+        ),
+        Expr::ArrayAccess { array, index } => Expr::ArrayAccess {
+            array: boxed(folder, array),
+            index: boxed(folder, index),
+        },
+        Expr::Binary { left, op, right } => Expr::Binary {
+            left: boxed(folder, left),
+            op,
+            right: boxed(folder, right),
+        },
+        Expr::Unary { op, expr } => Expr::Unary {
+            op,
+            expr: boxed(folder, expr),
+        },
+        Expr::Assign { var, op, value } => Expr::Assign {
+            var,
+            op,
+            value: boxed(folder, value),
+        },
+        Expr::AssignRef { var, source } => Expr::AssignRef {
+            var,
+            source: boxed(folder, source),
+        },
+        Expr::ArrayAssign {
+            array,
+            index,
+            op,
+            value,
+        } => Expr::ArrayAssign {
+            array: boxed(folder, array),
+            index: index.map(|index| boxed(folder, index)),
+            op,
+            value: boxed(folder, value),
+        },
+        Expr::Grouped(inner) => Expr::Grouped(boxed(folder, inner)),
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => Expr::Ternary {
+            condition: boxed(folder, condition),
+            then_expr: then_expr.map(|then_expr| boxed(folder, then_expr)),
+            else_expr: boxed(folder, else_expr),
+        },
+        Expr::FunctionCall { name, args } => Expr::FunctionCall {
+            name,
+            args: walk_args(folder, args),
+        },
+        Expr::CallableCall { callable, args } => Expr::CallableCall {
+            callable: boxed(folder, callable),
+            args: walk_args(folder, args),
+        },
+        Expr::New { class_name, args } => Expr::New {
+            class_name,
+            args: walk_args(folder, args),
+        },
+        Expr::NewDynamic { class, args } => Expr::NewDynamic {
+            class: boxed(folder, class),
+            args: walk_args(folder, args),
+        },
+        Expr::NewAnonymousClass {
+            constructor_args,
+            parent,
+            interfaces,
+            traits,
+            properties,
+            methods,
+        } => Expr::NewAnonymousClass {
+            constructor_args: walk_args(folder, constructor_args),
+            parent,
+            interfaces,
+            traits,
+            properties: walk_properties(folder, properties),
+            methods: walk_methods(folder, methods),
+        },
+        Expr::NewFiber { callback } => Expr::NewFiber {
+            callback: boxed(folder, callback),
+        },
+        Expr::PropertyAccess { object, property } => Expr::PropertyAccess {
+            object: boxed(folder, object),
+            property,
+        },
+        Expr::MethodCall {
+            object,
+            method,
+            args,
+        } => Expr::MethodCall {
+            object: boxed(folder, object),
+            method,
+            args: walk_args(folder, args),
+        },
+        Expr::NullsafePropertyAccess { object, property } => Expr::NullsafePropertyAccess {
+            object: boxed(folder, object),
+            property,
+        },
+        Expr::NullsafeMethodCall {
+            object,
+            method,
+            args,
+        } => Expr::NullsafeMethodCall {
+            object: boxed(folder, object),
+            method,
+            args: walk_args(folder, args),
+        },
+        Expr::PropertyAssign {
+            object,
+            property,
+            op,
+            value,
+        } => Expr::PropertyAssign {
+            object: boxed(folder, object),
+            property,
+            op,
+            value: boxed(folder, value),
+        },
+        Expr::StaticMethodCall {
+            class_name,
+            method,
+            args,
+        } => Expr::StaticMethodCall {
+            class_name,
+            method,
+            args: walk_args(folder, args),
+        },
+        Expr::StaticPropertyAssign {
+            class,
+            property,
+            op,
+            value,
+        } => Expr::StaticPropertyAssign {
+            class,
+            property,
+            op,
+            value: boxed(folder, value),
+        },
+        Expr::FiberSuspend { value } => Expr::FiberSuspend {
+            value: value.map(|value| boxed(folder, value)),
+        },
+        Expr::Match {
+            expr,
+            arms,
+            default,
+        } => Expr::Match {
+            expr: boxed(folder, expr),
+            arms: arms
+                .into_iter()
+                .map(|arm| MatchArm {
+                    conditions: arm
+                        .conditions
+                        .into_iter()
+                        .map(|condition| folder.fold_expr(condition))
+                        .collect(),
+                    result: boxed(folder, arm.result),
+                })
+                .collect(),
+            default: default.map(|default| boxed(folder, default)),
+        },
+        Expr::Clone { object } => Expr::Clone {
+            object: boxed(folder, object),
+        },
+        Expr::CloneWith {
+            object,
+            modifications,
+        } => Expr::CloneWith {
+            object: boxed(folder, object),
+            modifications: modifications
+                .into_iter()
+                .map(|modification| PropertyModification {
+                    property: modification.property,
+                    value: boxed(folder, modification.value),
+                })
+                .collect(),
+        },
+        Expr::CloneWithProperties { object, properties } => Expr::CloneWithProperties {
+            object: boxed(folder, object),
+            properties: boxed(folder, properties),
+        },
+        Expr::Spread(inner) => Expr::Spread(boxed(folder, inner)),
+        Expr::ArrowFunction { params, body } => Expr::ArrowFunction {
+            params: walk_params(folder, params),
+            body: boxed(folder, body),
+        },
+        Expr::CallableFromMethod { object, method } => Expr::CallableFromMethod {
+            object: boxed(folder, object),
+            method,
+        },
+        Expr::Throw(inner) => Expr::Throw(boxed(folder, inner)),
+        Expr::Yield { key, value } => Expr::Yield {
+            key: key.map(|key| boxed(folder, key)),
+            value: value.map(|value| boxed(folder, value)),
+        },
+        Expr::YieldFrom(inner) => Expr::YieldFrom(boxed(folder, inner)),
+        Expr::ListDestructure { elements, array } => Expr::ListDestructure {
+            elements: walk_list_elements(folder, elements),
+            array: boxed(folder, array),
+        },
+        expr @ (Expr::String(_)
+        | Expr::Integer(_)
+        | Expr::Float(_)
+        | Expr::Bool(_)
+        | Expr::Null
+        | Expr::Variable(_)
+        | Expr::This
+        | Expr::StaticPropertyAccess { .. }
+        | Expr::FiberGetCurrent
+        | Expr::EnumCase { .. }
+        | Expr::ClassReference(_)
+        | Expr::Placeholder
+        | Expr::CallableFromFunction(_)
+        | Expr::CallableFromStaticMethod { .. }
+        | Expr::Constant(_)
+        | Expr::MagicFile
+        | Expr::MagicLine(_)
+        | Expr::MagicDir
+        | Expr::MagicFunction
+        | Expr::MagicClass
+        | Expr::MagicMethod
+        | Expr::MagicNamespace
+        | Expr::MagicTrait) => expr,
+    }
+}
+
+/// Fold a boxed child, reusing its allocation
+pub(super) fn walk_list_elements<F: Folder + ?Sized>(
+    folder: &mut F,
+    elements: Vec<Option<ListElement>>,
+) -> Vec<Option<ListElement>> {
+    elements
+        .into_iter()
+        .map(|element| {
+            element.map(|element| ListElement {
+                key: element.key.map(|key| boxed(folder, key)),
+                value: boxed(folder, element.value),
+            })
+        })
+        .collect()
+}
+
+fn boxed<F: Folder + ?Sized>(folder: &mut F, mut expr: Box<Expr>) -> Box<Expr> {
+    let inner = std::mem::replace(&mut *expr, Expr::Null);
+    *expr = folder.fold_expr(inner);
+    expr
+}
+
+fn walk_args<F: Folder + ?Sized>(folder: &mut F, args: Vec<Argument>) -> Vec<Argument> {
+    args.into_iter()
+        .map(|arg| Argument {
+            name: arg.name,
+            value: boxed(folder, arg.value),
+        })
+        .collect()
+}
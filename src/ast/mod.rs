@@ -4,14 +4,17 @@
 //! and VM.
 
 mod expr;
+pub mod fold;
 mod ops;
 mod stmt;
+pub mod visit;
 
 pub use expr::{Argument, ArrayElement, Expr, ListElement, MatchArm, PropertyModification};
 pub use ops::{AssignOp, BinaryOp, UnaryOp};
 pub use stmt::{
     Attribute, AttributeArgument, CatchClause, DeclareDirective, EnumBackingType, EnumCase,
-    FunctionParam, GroupUse, InterfaceConstant, InterfaceMethodSignature, Method, NamespaceBody,
-    Program, Property, PropertyHook, PropertyHookBody, PropertyHookType, QualifiedName, Stmt,
-    SwitchCase, TraitResolution, TraitUse, TypeHint, UseItem, UseType, Visibility,
+    ForeachTarget, FunctionParam, GroupUse, InterfaceConstant, InterfaceMethodSignature, Method,
+    NamespaceBody, Program, Property, PropertyHook, PropertyHookBody, PropertyHookType,
+    QualifiedName, Stmt, SwitchCase, TraitResolution, TraitUse, TypeHint, UseItem, UseType,
+    Visibility,
 };
@@ -1,5 +1,6 @@
+use crate::vm::cache::codec::Codec;
 /// Binary operators
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Codec)]
 pub enum BinaryOp {
     // Arithmetic
     Add, // +
@@ -29,7 +30,11 @@ pub enum BinaryOp {
     Xor, // xor
 
     // Bitwise
-    BitwiseOr, // | (bitwise OR)
+    BitwiseOr,  // | (bitwise OR)
+    BitwiseAnd, // &
+    BitwiseXor, // ^
+    ShiftLeft,  // <<
+    ShiftRight, // >>
 
     // Null coalescing
     NullCoalesce, // ??
@@ -39,24 +44,56 @@ pub enum BinaryOp {
 }
 
 /// Unary operators
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Codec)]
 pub enum UnaryOp {
-    Neg,     // -
-    Not,     // !
-    PreInc,  // ++$x
-    PreDec,  // --$x
-    PostInc, // $x++
-    PostDec, // $x--
+    Neg,        // -
+    Not,        // !
+    BitwiseNot, // ~
+    PreInc,     // ++$x
+    PreDec,     // --$x
+    PostInc,    // $x++
+    PostDec,    // $x--
+    Silence,    // @
 }
 
 /// Assignment operators
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Codec)]
 pub enum AssignOp {
-    Assign,       // =
-    AddAssign,    // +=
-    SubAssign,    // -=
-    MulAssign,    // *=
-    DivAssign,    // /=
-    ModAssign,    // %=
-    ConcatAssign, // .=
+    Assign,             // =
+    AddAssign,          // +=
+    SubAssign,          // -=
+    MulAssign,          // *=
+    DivAssign,          // /=
+    ModAssign,          // %=
+    ConcatAssign,       // .=
+    PowAssign,          // **=
+    BitwiseAndAssign,   // &=
+    BitwiseOrAssign,    // |=
+    BitwiseXorAssign,   // ^=
+    ShiftLeftAssign,    // <<=
+    ShiftRightAssign,   // >>=
+    NullCoalesceAssign, // ??=
+}
+
+impl AssignOp {
+    /// The operation a compound assignment applies to the current value
+    /// and the assigned one (`$a += 1` stores `$a + 1`). `=` has none, and
+    /// `??=` only assigns when the current value is null.
+    pub fn binary_op(&self) -> Option<BinaryOp> {
+        match self {
+            AssignOp::Assign | AssignOp::NullCoalesceAssign => None,
+            AssignOp::AddAssign => Some(BinaryOp::Add),
+            AssignOp::SubAssign => Some(BinaryOp::Sub),
+            AssignOp::MulAssign => Some(BinaryOp::Mul),
+            AssignOp::DivAssign => Some(BinaryOp::Div),
+            AssignOp::ModAssign => Some(BinaryOp::Mod),
+            AssignOp::ConcatAssign => Some(BinaryOp::Concat),
+            AssignOp::PowAssign => Some(BinaryOp::Pow),
+            AssignOp::BitwiseAndAssign => Some(BinaryOp::BitwiseAnd),
+            AssignOp::BitwiseOrAssign => Some(BinaryOp::BitwiseOr),
+            AssignOp::BitwiseXorAssign => Some(BinaryOp::BitwiseXor),
+            AssignOp::ShiftLeftAssign => Some(BinaryOp::ShiftLeft),
+            AssignOp::ShiftRightAssign => Some(BinaryOp::ShiftRight),
+        }
+    }
 }
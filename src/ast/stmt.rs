@@ -1,7 +1,8 @@
-use super::expr::Expr;
+use super::expr::{Expr, ListElement};
+use crate::vm::cache::codec::Codec;
 
 /// Declare directive type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub enum DeclareDirective {
     /// strict_types=0 or strict_types=1
     StrictTypes(bool),
@@ -14,7 +15,7 @@ pub enum DeclareDirective {
 }
 
 /// Qualified name for namespace and class references (e.g., MyApp\Database\Connection)
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Codec)]
 pub struct QualifiedName {
     /// Path parts (e.g., ["MyApp", "Database", "Connection"])
     pub parts: Vec<String>,
@@ -37,7 +38,7 @@ impl QualifiedName {
 }
 
 /// Use statement type
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Codec)]
 pub enum UseType {
     Class,    // use Foo\Bar;
     Function, // use function Foo\helper;
@@ -45,7 +46,7 @@ pub enum UseType {
 }
 
 /// Single use import
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 #[allow(dead_code)] // use_type parsed but not yet used
 pub struct UseItem {
     pub name: QualifiedName,
@@ -54,14 +55,14 @@ pub struct UseItem {
 }
 
 /// Group use statement: use Foo\{Bar, Baz};
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct GroupUse {
     pub prefix: QualifiedName,
     pub items: Vec<UseItem>,
 }
 
 /// Namespace body style
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub enum NamespaceBody {
     /// Braced: namespace Foo { ... }
     Braced(Vec<Stmt>),
@@ -70,7 +71,7 @@ pub enum NamespaceBody {
 }
 
 /// Type hint for parameters and return values
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Codec)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum TypeHint {
     /// Simple type: int, string, float, bool, array, object, callable, mixed
@@ -120,7 +121,7 @@ impl TypeHint {
 }
 
 /// Visibility modifier for class members
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Codec)]
 pub enum Visibility {
     Public,
     Protected,
@@ -128,7 +129,7 @@ pub enum Visibility {
 }
 
 /// Attribute argument (can be positional or named)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct AttributeArgument {
     #[allow(dead_code)] // Will be used for reflection
     pub name: Option<String>, // None for positional, Some("name") for named
@@ -137,7 +138,7 @@ pub struct AttributeArgument {
 }
 
 /// Attribute metadata (PHP 8.0)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct Attribute {
     #[allow(dead_code)] // Will be used for reflection
     pub name: String,
@@ -146,14 +147,14 @@ pub struct Attribute {
 }
 
 /// Property hook type (PHP 8.4)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub enum PropertyHookType {
     Get,
     Set,
 }
 
 /// Property hook body can be expression or statements
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub enum PropertyHookBody {
     /// Short syntax: get => expr
     Expression(Box<Expr>),
@@ -162,19 +163,20 @@ pub enum PropertyHookBody {
 }
 
 /// Property hook definition (PHP 8.4)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct PropertyHook {
     pub hook_type: PropertyHookType,
     pub body: PropertyHookBody,
 }
 
 /// Class property definition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct Property {
     pub name: String,
     #[allow(dead_code)] // Will be used for visibility enforcement
     pub visibility: Visibility,
     pub write_visibility: Option<Visibility>, // PHP 8.4+ asymmetric visibility, None means same as read
+    pub type_hint: Option<TypeHint>,          // PHP 7.4+ typed properties
     pub default: Option<Expr>,
     pub readonly: bool,             // PHP 8.1+
     pub is_static: bool,            // PHP 5.0+
@@ -183,7 +185,7 @@ pub struct Property {
 }
 
 /// Class method definition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct Method {
     pub name: String,
     pub visibility: Visibility,
@@ -197,7 +199,7 @@ pub struct Method {
 }
 
 /// Interface method signature (no body)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct InterfaceMethodSignature {
     pub name: String,
     pub params: Vec<FunctionParam>,
@@ -207,9 +209,8 @@ pub struct InterfaceMethodSignature {
     pub attributes: Vec<Attribute>, // PHP 8.0+
 }
 
-/// Interface constant
-#[derive(Debug, Clone)]
-#[allow(dead_code)] // name and value parsed but not yet used
+/// Constant declared in an interface, class or enum body
+#[derive(Debug, Clone, Codec)]
 pub struct InterfaceConstant {
     pub name: String,
     pub value: Expr,
@@ -218,14 +219,14 @@ pub struct InterfaceConstant {
 }
 
 /// Enum case definition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct EnumCase {
     pub name: String,
     pub value: Option<Expr>, // Some(expr) for backed enums, None for pure enums
 }
 
 /// Enum backing type
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Codec)]
 pub enum EnumBackingType {
     None,   // Pure enum
     Int,    // Backed by integers
@@ -233,7 +234,7 @@ pub enum EnumBackingType {
 }
 
 /// Trait usage in class
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct TraitUse {
     pub traits: Vec<String>,
     #[allow(dead_code)] // Will be used for trait conflict resolution
@@ -241,7 +242,7 @@ pub struct TraitUse {
 }
 
 /// Conflict resolution for traits
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 #[allow(dead_code)] // Will be used for trait conflict resolution
 pub enum TraitResolution {
     InsteadOf {
@@ -258,8 +259,7 @@ pub enum TraitResolution {
 }
 
 /// Catch clause for try statement
-#[derive(Debug, Clone)]
-#[allow(dead_code)] // exception_types parsed but not yet used
+#[derive(Debug, Clone, Codec)]
 pub struct CatchClause {
     /// Exception types to catch (supports multi-catch with |)
     pub exception_types: Vec<String>,
@@ -270,9 +270,11 @@ pub struct CatchClause {
 }
 
 /// Statements
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 #[allow(clippy::large_enum_variant)]
 pub enum Stmt {
+    /// Source line of the statement that follows (used for coverage)
+    Line(usize),
     Echo(Vec<Expr>),
     Expression(Expr),
     Html(String),
@@ -301,13 +303,15 @@ pub enum Stmt {
     Foreach {
         array: Expr,
         key: Option<String>,
-        value: String,
+        value: ForeachTarget,
+        /// `as &$value`: changes to the variable are written back into
+        /// the array
+        by_ref: bool,
         body: Vec<Stmt>,
     },
     Switch {
         expr: Expr,
         cases: Vec<SwitchCase>,
-        default: Option<Vec<Stmt>>,
     },
     Break,
     Continue,
@@ -319,6 +323,8 @@ pub enum Stmt {
         attributes: Vec<Attribute>, // PHP 8.0+
     },
     Return(Option<Expr>),
+    /// `global $a, $b;`: the variables named, without `$`
+    Global(Vec<String>),
     Interface {
         name: String,
         parents: Vec<QualifiedName>,
@@ -341,6 +347,7 @@ pub enum Stmt {
         parent: Option<QualifiedName>,
         interfaces: Vec<QualifiedName>,
         trait_uses: Vec<TraitUse>,
+        constants: Vec<InterfaceConstant>,
         properties: Vec<Property>,
         methods: Vec<Method>,
         attributes: Vec<Attribute>, // PHP 8.0+
@@ -348,7 +355,9 @@ pub enum Stmt {
     Enum {
         name: String,
         backing_type: EnumBackingType,
+        interfaces: Vec<QualifiedName>,
         cases: Vec<EnumCase>,
+        constants: Vec<InterfaceConstant>,
         methods: Vec<Method>,       // Enums can have methods
         attributes: Vec<Attribute>, // PHP 8.0+
     },
@@ -377,15 +386,24 @@ pub enum Stmt {
     },
 }
 
-/// Switch case
-#[derive(Debug, Clone)]
+/// What a foreach loop assigns each value to
+#[derive(Debug, Clone, Codec)]
+pub enum ForeachTarget {
+    /// `as $value`
+    Variable(String),
+    /// `as [$a, $b]`, `as ['id' => $id]` or `as list($a, , $c)`
+    List(Vec<Option<ListElement>>),
+}
+
+/// Switch case, in source order; `default` is the case without a value
+#[derive(Debug, Clone, Codec)]
 pub struct SwitchCase {
-    pub value: Expr,
+    pub value: Option<Expr>,
     pub body: Vec<Stmt>,
 }
 
 /// Function parameter
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct FunctionParam {
     pub name: String,
     #[allow(dead_code)] // Will be used for type validation
@@ -0,0 +1,294 @@
+//! Read-only AST traversal
+//!
+//! A `Visitor` only overrides the nodes it cares about. The default methods
+//! call the matching `walk_*` function, which visits every child node in
+//! source order. An override can call `walk_*` itself to keep descending, or
+//! skip it to prune that subtree:
+//!
+//! ```
+//! use vhp::ast::visit::{self, Visitor};
+//! use vhp::ast::{Expr, Stmt};
+//!
+//! /// Counts calls, ignoring nested function declarations
+//! struct Calls(usize);
+//!
+//! impl Visitor for Calls {
+//!     fn visit_stmt(&mut self, stmt: &Stmt) {
+//!         if !matches!(stmt, Stmt::Function { .. }) {
+//!             visit::walk_stmt(self, stmt);
+//!         }
+//!     }
+//!
+//!     fn visit_expr(&mut self, expr: &Expr) {
+//!         if let Expr::FunctionCall { .. } = expr {
+//!             self.0 += 1;
+//!         }
+//!         visit::walk_expr(self, expr);
+//!     }
+//! }
+//! ```
+
+mod expr;
+
+pub use expr::walk_expr;
+
+use super::{
+    Attribute, Expr, ForeachTarget, FunctionParam, Method, NamespaceBody, Property,
+    PropertyHookBody, Stmt,
+};
+
+pub trait Visitor {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_method(&mut self, method: &Method) {
+        walk_method(self, method);
+    }
+
+    fn visit_property(&mut self, property: &Property) {
+        walk_property(self, property);
+    }
+
+    fn visit_param(&mut self, param: &FunctionParam) {
+        walk_param(self, param);
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        walk_attribute(self, attribute);
+    }
+}
+
+/// Visit each statement of a block
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, statements: &[Stmt]) {
+    for stmt in statements {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Line(_)
+        | Stmt::Html(_)
+        | Stmt::Break
+        | Stmt::Continue
+        | Stmt::Use(_)
+        | Stmt::GroupUse(_)
+        | Stmt::Global(_) => {}
+        Stmt::Echo(exprs) => {
+            for expr in exprs {
+                visitor.visit_expr(expr);
+            }
+        }
+        Stmt::Expression(expr) | Stmt::Throw(expr) => visitor.visit_expr(expr),
+        Stmt::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        Stmt::If {
+            condition,
+            then_branch,
+            elseif_branches,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            walk_block(visitor, then_branch);
+            for (condition, branch) in elseif_branches {
+                visitor.visit_expr(condition);
+                walk_block(visitor, branch);
+            }
+            if let Some(branch) = else_branch {
+                walk_block(visitor, branch);
+            }
+        }
+        Stmt::While { condition, body } => {
+            visitor.visit_expr(condition);
+            walk_block(visitor, body);
+        }
+        Stmt::DoWhile { body, condition } => {
+            walk_block(visitor, body);
+            visitor.visit_expr(condition);
+        }
+        Stmt::For {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            for expr in [init, condition, update].into_iter().flatten() {
+                visitor.visit_expr(expr);
+            }
+            walk_block(visitor, body);
+        }
+        Stmt::Foreach {
+            array, value, body, ..
+        } => {
+            visitor.visit_expr(array);
+            if let ForeachTarget::List(elements) = value {
+                expr::walk_list_elements(visitor, elements);
+            }
+            walk_block(visitor, body);
+        }
+        Stmt::Switch { expr, cases } => {
+            visitor.visit_expr(expr);
+            for case in cases {
+                if let Some(value) = &case.value {
+                    visitor.visit_expr(value);
+                }
+                walk_block(visitor, &case.body);
+            }
+        }
+        Stmt::Function {
+            params,
+            body,
+            attributes,
+            ..
+        } => {
+            walk_attributes(visitor, attributes);
+            for param in params {
+                visitor.visit_param(param);
+            }
+            walk_block(visitor, body);
+        }
+        Stmt::Interface {
+            methods,
+            constants,
+            attributes,
+            ..
+        } => {
+            walk_attributes(visitor, attributes);
+            for constant in constants {
+                walk_attributes(visitor, &constant.attributes);
+                visitor.visit_expr(&constant.value);
+            }
+            for method in methods {
+                walk_attributes(visitor, &method.attributes);
+                for param in &method.params {
+                    visitor.visit_param(param);
+                }
+            }
+        }
+        Stmt::Trait {
+            properties,
+            methods,
+            attributes,
+            ..
+        } => {
+            walk_attributes(visitor, attributes);
+            for property in properties {
+                visitor.visit_property(property);
+            }
+            for method in methods {
+                visitor.visit_method(method);
+            }
+        }
+        Stmt::Class {
+            constants,
+            properties,
+            methods,
+            attributes,
+            ..
+        } => {
+            walk_attributes(visitor, attributes);
+            for constant in constants {
+                walk_attributes(visitor, &constant.attributes);
+                visitor.visit_expr(&constant.value);
+            }
+            for property in properties {
+                visitor.visit_property(property);
+            }
+            for method in methods {
+                visitor.visit_method(method);
+            }
+        }
+        Stmt::Enum {
+            cases,
+            constants,
+            methods,
+            attributes,
+            ..
+        } => {
+            walk_attributes(visitor, attributes);
+            for case in cases {
+                if let Some(value) = &case.value {
+                    visitor.visit_expr(value);
+                }
+            }
+            for constant in constants {
+                walk_attributes(visitor, &constant.attributes);
+                visitor.visit_expr(&constant.value);
+            }
+            for method in methods {
+                visitor.visit_method(method);
+            }
+        }
+        Stmt::TryCatch {
+            try_body,
+            catch_clauses,
+            finally_body,
+        } => {
+            walk_block(visitor, try_body);
+            for clause in catch_clauses {
+                walk_block(visitor, &clause.body);
+            }
+            if let Some(body) = finally_body {
+                walk_block(visitor, body);
+            }
+        }
+        Stmt::Namespace { body, .. } => {
+            if let NamespaceBody::Braced(body) = body {
+                walk_block(visitor, body);
+            }
+        }
+        Stmt::Declare { body, .. } => {
+            if let Some(body) = body {
+                walk_block(visitor, body);
+            }
+        }
+    }
+}
+
+pub fn walk_method<V: Visitor + ?Sized>(visitor: &mut V, method: &Method) {
+    walk_attributes(visitor, &method.attributes);
+    for param in &method.params {
+        visitor.visit_param(param);
+    }
+    walk_block(visitor, &method.body);
+}
+
+pub fn walk_property<V: Visitor + ?Sized>(visitor: &mut V, property: &Property) {
+    walk_attributes(visitor, &property.attributes);
+    if let Some(default) = &property.default {
+        visitor.visit_expr(default);
+    }
+    for hook in &property.hooks {
+        match &hook.body {
+            PropertyHookBody::Expression(expr) => visitor.visit_expr(expr),
+            PropertyHookBody::Block(body) => walk_block(visitor, body),
+        }
+    }
+}
+
+pub fn walk_param<V: Visitor + ?Sized>(visitor: &mut V, param: &FunctionParam) {
+    walk_attributes(visitor, &param.attributes);
+    if let Some(default) = &param.default {
+        visitor.visit_expr(default);
+    }
+}
+
+pub fn walk_attribute<V: Visitor + ?Sized>(visitor: &mut V, attribute: &Attribute) {
+    for argument in &attribute.arguments {
+        visitor.visit_expr(&argument.value);
+    }
+}
+
+fn walk_attributes<V: Visitor + ?Sized>(visitor: &mut V, attributes: &[Attribute]) {
+    for attribute in attributes {
+        visitor.visit_attribute(attribute);
+    }
+}
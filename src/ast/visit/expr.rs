@@ -0,0 +1,185 @@
+//! Expression traversal
+
+use super::Visitor;
+use crate::ast::{Argument, Expr, ListElement};
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::String(_)
+        | Expr::Integer(_)
+        | Expr::Float(_)
+        | Expr::Bool(_)
+        | Expr::Null
+        | Expr::Variable(_)
+        | Expr::This
+        | Expr::StaticPropertyAccess { .. }
+        | Expr::FiberGetCurrent
+        | Expr::EnumCase { .. }
+        | Expr::ClassReference(_)
+        | Expr::Placeholder
+        | Expr::CallableFromFunction(_)
+        | Expr::CallableFromStaticMethod { .. }
+        | Expr::Constant(_)
+        | Expr::MagicFile
+        | Expr::MagicLine(_)
+        | Expr::MagicDir
+        | Expr::MagicFunction
+        | Expr::MagicClass
+        | Expr::MagicMethod
+        | Expr::MagicNamespace
+        | Expr::MagicTrait => {}
+        Expr::Array(elements) => {
+            for element in elements {
+                if let Some(key) = &element.key {
+                    visitor.visit_expr(key);
+                }
+                visitor.visit_expr(&element.value);
+            }
+        }
+        Expr::ArrayAccess { array, index } => {
+            visitor.visit_expr(array);
+            visitor.visit_expr(index);
+        }
+        Expr::Binary { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::Unary { expr, .. }
+        | Expr::Grouped(expr)
+        | Expr::NewFiber { callback: expr }
+        | Expr::PropertyAccess { object: expr, .. }
+        | Expr::NullsafePropertyAccess { object: expr, .. }
+        | Expr::Clone { object: expr }
+        | Expr::Spread(expr)
+        | Expr::CallableFromMethod { object: expr, .. }
+        | Expr::Throw(expr)
+        | Expr::YieldFrom(expr) => visitor.visit_expr(expr),
+        Expr::Assign { value, .. } | Expr::StaticPropertyAssign { value, .. } => {
+            visitor.visit_expr(value)
+        }
+        Expr::AssignRef { source, .. } => visitor.visit_expr(source),
+        Expr::ArrayAssign {
+            array,
+            index,
+            value,
+            ..
+        } => {
+            visitor.visit_expr(array);
+            if let Some(index) = index {
+                visitor.visit_expr(index);
+            }
+            visitor.visit_expr(value);
+        }
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            visitor.visit_expr(condition);
+            if let Some(then_expr) = then_expr {
+                visitor.visit_expr(then_expr);
+            }
+            visitor.visit_expr(else_expr);
+        }
+        Expr::FunctionCall { args, .. }
+        | Expr::New { args, .. }
+        | Expr::StaticMethodCall { args, .. } => walk_args(visitor, args),
+        Expr::CallableCall { callable, args }
+        | Expr::NewDynamic {
+            class: callable,
+            args,
+        } => {
+            visitor.visit_expr(callable);
+            walk_args(visitor, args);
+        }
+        Expr::MethodCall { object, args, .. } | Expr::NullsafeMethodCall { object, args, .. } => {
+            visitor.visit_expr(object);
+            walk_args(visitor, args);
+        }
+        Expr::NewAnonymousClass {
+            constructor_args,
+            properties,
+            methods,
+            ..
+        } => {
+            walk_args(visitor, constructor_args);
+            for property in properties {
+                visitor.visit_property(property);
+            }
+            for method in methods {
+                visitor.visit_method(method);
+            }
+        }
+        Expr::PropertyAssign { object, value, .. } => {
+            visitor.visit_expr(object);
+            visitor.visit_expr(value);
+        }
+        Expr::FiberSuspend { value } => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        Expr::Match {
+            expr,
+            arms,
+            default,
+        } => {
+            visitor.visit_expr(expr);
+            for arm in arms {
+                for condition in &arm.conditions {
+                    visitor.visit_expr(condition);
+                }
+                visitor.visit_expr(&arm.result);
+            }
+            if let Some(default) = default {
+                visitor.visit_expr(default);
+            }
+        }
+        Expr::CloneWith {
+            object,
+            modifications,
+        } => {
+            visitor.visit_expr(object);
+            for modification in modifications {
+                visitor.visit_expr(&modification.value);
+            }
+        }
+        Expr::CloneWithProperties { object, properties } => {
+            visitor.visit_expr(object);
+            visitor.visit_expr(properties);
+        }
+        Expr::ArrowFunction { params, body } => {
+            for param in params {
+                visitor.visit_param(param);
+            }
+            visitor.visit_expr(body);
+        }
+        Expr::Yield { key, value } => {
+            for expr in [key, value].into_iter().flatten() {
+                visitor.visit_expr(expr);
+            }
+        }
+        Expr::ListDestructure { elements, array } => {
+            walk_list_elements(visitor, elements);
+            visitor.visit_expr(array);
+        }
+    }
+}
+
+pub(super) fn walk_list_elements<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    elements: &[Option<ListElement>],
+) {
+    for element in elements.iter().flatten() {
+        if let Some(key) = &element.key {
+            visitor.visit_expr(key);
+        }
+        visitor.visit_expr(&element.value);
+    }
+}
+
+fn walk_args<V: Visitor + ?Sized>(visitor: &mut V, args: &[Argument]) {
+    for arg in args {
+        visitor.visit_expr(&arg.value);
+    }
+}
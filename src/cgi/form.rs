@@ -0,0 +1,226 @@
+//! Form data
+//!
+//! PHP fills `$_GET` from the query string, `$_POST` from a request body
+//! and `$_COOKIE` from the `Cookie` header the same way: each
+//! `name=value` pair is URL-decoded and stored under its name, where a
+//! name like `a[b][]` nests (`a[b][]=1&a[b][]=2` gives
+//! `['a' => ['b' => [1, 2]]]`). Dots and spaces in the top-level name
+//! become underscores.
+
+use crate::runtime::{ArrayKey, PhpArray, Value};
+
+/// A file sent in a `multipart/form-data` body
+pub struct Upload {
+    /// The name of the form field
+    pub field: String,
+    /// The file name the client gave, possibly with a path
+    pub filename: String,
+    pub content_type: String,
+    pub contents: Vec<u8>,
+}
+
+/// `input` with `%XX` escapes decoded and `+` as a space
+pub fn url_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The variables of a query string or `application/x-www-form-urlencoded`
+/// body
+pub fn parse_query(query: &str) -> Value {
+    parse_pairs(query.split('&'), true)
+}
+
+/// The cookies of a `Cookie` header. The first of two cookies with the
+/// same name wins.
+pub fn parse_cookies(header: &str) -> Value {
+    parse_pairs(header.split(';').map(str::trim_start), false)
+}
+
+/// The array of `name=value` pairs, replacing a variable given twice if
+/// `overwrite`
+fn parse_pairs<'a>(pairs: impl Iterator<Item = &'a str>, overwrite: bool) -> Value {
+    let mut entries = PhpArray::new();
+    for pair in pairs.filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = Value::String(url_decode(value));
+        insert(&mut entries, &url_decode(name), &[], value, overwrite);
+    }
+    Value::Array(entries)
+}
+
+/// Store `value` under the variable name `name`, with the keys of `inner`
+/// after its top-level name and before the keys in its brackets, as
+/// `$_FILES` stores `name`, `type` and the rest
+pub fn insert(entries: &mut PhpArray, name: &str, inner: &[&str], value: Value, overwrite: bool) {
+    let Some((base, keys)) = split_name(name) else {
+        return;
+    };
+    let path: Vec<Option<String>> = std::iter::once(Some(base))
+        .chain(inner.iter().map(|key| Some(key.to_string())))
+        .chain(keys)
+        .collect();
+    insert_path(entries, &path, value, overwrite);
+}
+
+/// A variable name's top-level name and the keys in the brackets after
+/// it, `None` for `[]`. A name that is empty gives nothing.
+fn split_name(name: &str) -> Option<(String, Vec<Option<String>>)> {
+    let name = name.trim_start_matches(' ');
+    let (base, mut rest) = match name.find('[') {
+        Some(open) if name[open..].contains(']') => name.split_at(open),
+        _ => (name, ""),
+    };
+    if base.is_empty() {
+        return None;
+    }
+    let base = base
+        .chars()
+        .map(|c| if matches!(c, ' ' | '.' | '[') { '_' } else { c })
+        .collect();
+    let mut keys = Vec::new();
+    while let Some(inner) = rest.strip_prefix('[') {
+        let Some(close) = inner.find(']') else {
+            break;
+        };
+        let key = &inner[..close];
+        keys.push((!key.is_empty()).then(|| key.to_string()));
+        rest = &inner[close + 1..];
+    }
+    Some((base, keys))
+}
+
+/// Store `value` under the keys of `path`, appending where a key is
+/// `None`
+fn insert_path(entries: &mut PhpArray, path: &[Option<String>], value: Value, overwrite: bool) {
+    let Some((first, rest)) = path.split_first() else {
+        return;
+    };
+    let key = match first {
+        Some(key) => ArrayKey::from_value(&Value::String(key.clone())),
+        None => ArrayKey::Integer(entries.next_index()),
+    };
+    if rest.is_empty() {
+        if overwrite || !entries.contains_key(&key) {
+            entries.insert(key, value);
+        }
+        return;
+    }
+    if !matches!(entries.get(&key), Some(Value::Array(_))) {
+        entries.insert(key.clone(), Value::Array(PhpArray::new()));
+    }
+    if let Some(Value::Array(inner)) = entries.get_mut(&key) {
+        insert_path(inner, rest, value, overwrite);
+    }
+}
+
+/// The `boundary` parameter of a `multipart/form-data` content type
+pub fn multipart_boundary(content_type: &str) -> Option<String> {
+    let (media_type, params) = content_type.split_once(';')?;
+    if !media_type
+        .trim()
+        .eq_ignore_ascii_case("multipart/form-data")
+    {
+        return None;
+    }
+    header_param(params, "boundary")
+}
+
+/// The parameter `name` of a header value's `; name=value` list
+fn header_param(params: &str, name: &str) -> Option<String> {
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// The fields and files of a `multipart/form-data` body
+pub fn parse_multipart(body: &[u8], boundary: &str) -> (Value, Vec<Upload>) {
+    let delimiter = format!("--{}", boundary);
+    let mut fields = PhpArray::new();
+    let mut uploads = Vec::new();
+    let Some(mut start) = find(body, delimiter.as_bytes(), 0) else {
+        return (Value::Array(fields), uploads);
+    };
+    loop {
+        start += delimiter.len();
+        if body[start..].starts_with(b"--") {
+            break;
+        }
+        let Some(end) = find(body, format!("\r\n{}", delimiter).as_bytes(), start) else {
+            break;
+        };
+        let part = body[start..end].strip_prefix(b"\r\n").unwrap_or(&[]);
+        if let Some(split) = find(part, b"\r\n\r\n", 0) {
+            let headers = String::from_utf8_lossy(&part[..split]);
+            let contents = &part[split + 4..];
+            add_part(&headers, contents, &mut fields, &mut uploads);
+        }
+        start = end + 2;
+    }
+    (Value::Array(fields), uploads)
+}
+
+/// Add a part of a multipart body, with its headers and contents, to the
+/// fields or the uploads
+fn add_part(headers: &str, contents: &[u8], fields: &mut PhpArray, uploads: &mut Vec<Upload>) {
+    let mut disposition = None;
+    let mut content_type = String::new();
+    for line in headers.split("\r\n") {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case("content-disposition") {
+            disposition = Some(value.to_string());
+        } else if name.trim().eq_ignore_ascii_case("content-type") {
+            content_type = value.trim().to_string();
+        }
+    }
+    let Some(disposition) = disposition else {
+        return;
+    };
+    let Some(field) = header_param(&disposition, "name") else {
+        return;
+    };
+    match header_param(&disposition, "filename") {
+        Some(filename) => uploads.push(Upload {
+            field,
+            filename,
+            content_type,
+            contents: contents.to_vec(),
+        }),
+        None => {
+            let value = Value::String(String::from_utf8_lossy(contents).into_owned());
+            insert(fields, &field, &[], value, true);
+        }
+    }
+}
+
+/// Where `needle` first occurs in `haystack` at or after `from`
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| position + from)
+}
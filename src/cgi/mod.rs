@@ -0,0 +1,210 @@
+//! CGI
+//!
+//! `vhp cgi` runs a script as a CGI/1.1 program (RFC 3875) behind a web
+//! server. The request comes in as meta-variables in the environment
+//! (`REQUEST_METHOD`, `QUERY_STRING`, `CONTENT_TYPE`, the headers as
+//! `HTTP_*`) and a body on stdin; `VM::set_request` turns it into the
+//! superglobals. The response goes out on stdout once the script ends:
+//! a `Status` line unless it is 200, the headers `header()` set, a blank
+//! line and the output.
+
+pub mod form;
+
+use crate::runtime::{PhpArray, Value};
+use form::Upload;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Uploads saved by this process so far, for unique file names
+static SAVED_UPLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// An HTTP request as a web server passes it to a CGI program
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    /// The meta-variables, which `$_SERVER` holds
+    pub variables: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(variables: Vec<(String, String)>, body: Vec<u8>) -> Self {
+        Self { variables, body }
+    }
+
+    /// The request passed to this process: the environment, and as many
+    /// bytes of stdin as `CONTENT_LENGTH` says
+    pub fn from_process() -> io::Result<Self> {
+        let mut variables: Vec<(String, String)> = std::env::vars().collect();
+        variables.sort();
+        let mut request = Self::new(variables, Vec::new());
+        let length = request
+            .variable("CONTENT_LENGTH")
+            .and_then(|length| length.trim().parse().ok())
+            .unwrap_or(0);
+        io::stdin().take(length).read_to_end(&mut request.body)?;
+        Ok(request)
+    }
+
+    /// The meta-variable `name`, if the server set it
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The request method, GET unless the server says otherwise
+    pub fn method(&self) -> &str {
+        self.variable("REQUEST_METHOD").unwrap_or("GET")
+    }
+
+    /// The script to run: `SCRIPT_FILENAME`, or else `PATH_TRANSLATED`
+    pub fn script_filename(&self) -> Option<&str> {
+        self.variable("SCRIPT_FILENAME")
+            .or_else(|| self.variable("PATH_TRANSLATED"))
+            .filter(|path| !path.is_empty())
+    }
+
+    /// `$_GET`: the variables of the query string
+    pub fn query(&self) -> Value {
+        form::parse_query(self.variable("QUERY_STRING").unwrap_or(""))
+    }
+
+    /// `$_COOKIE`: the cookies of the `Cookie` header
+    pub fn cookies(&self) -> Value {
+        form::parse_cookies(self.variable("HTTP_COOKIE").unwrap_or(""))
+    }
+
+    /// `$_POST` and the uploaded files: the form a POST request sends as
+    /// `application/x-www-form-urlencoded` or `multipart/form-data`
+    pub fn form(&self) -> (Value, Vec<Upload>) {
+        let content_type = self.variable("CONTENT_TYPE").unwrap_or("");
+        let media_type = content_type.split(';').next().unwrap_or("").trim();
+        if !self.method().eq_ignore_ascii_case("POST") {
+            (Value::Array(PhpArray::new()), Vec::new())
+        } else if media_type.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+            (
+                form::parse_query(&String::from_utf8_lossy(&self.body)),
+                Vec::new(),
+            )
+        } else if let Some(boundary) = form::multipart_boundary(content_type) {
+            form::parse_multipart(&self.body, &boundary)
+        } else {
+            (Value::Array(PhpArray::new()), Vec::new())
+        }
+    }
+}
+
+/// `$_FILES` for the uploads, saved in temporary files whose paths come
+/// second, for the host to remove when the script ends
+pub fn save_uploads(uploads: Vec<Upload>) -> io::Result<(Value, Vec<PathBuf>)> {
+    let mut files = PhpArray::new();
+    let mut paths = Vec::new();
+    for upload in uploads {
+        // UPLOAD_ERR_NO_FILE: the field was left empty
+        let (tmp_name, error) = if upload.filename.is_empty() {
+            (String::new(), 4)
+        } else {
+            let number = SAVED_UPLOADS.fetch_add(1, Ordering::Relaxed);
+            let name = format!("vhp{}_{}.tmp", std::process::id(), number);
+            let path = std::env::temp_dir().join(name);
+            std::fs::write(&path, &upload.contents)?;
+            let tmp_name = path.display().to_string();
+            paths.push(path);
+            (tmp_name, 0)
+        };
+        let basename = upload.filename.rsplit(['/', '\\']).next().unwrap_or("");
+        let size = if error == 0 { upload.contents.len() } else { 0 };
+        let info = [
+            ("name", Value::String(basename.to_string())),
+            ("full_path", Value::String(upload.filename.clone())),
+            ("type", Value::String(upload.content_type)),
+            ("tmp_name", Value::String(tmp_name)),
+            ("error", Value::Integer(error)),
+            ("size", Value::Integer(size as i64)),
+        ];
+        for (key, value) in info {
+            form::insert(&mut files, &upload.field, &[key], value, true);
+        }
+    }
+    Ok((Value::Array(files), paths))
+}
+
+/// `$_REQUEST`: `$_GET` with `$_POST` over it
+pub fn merge_request(get: &Value, post: &Value) -> Value {
+    let mut merged = match get {
+        Value::Array(entries) => entries.clone(),
+        _ => PhpArray::new(),
+    };
+    if let Value::Array(entries) = post {
+        for (key, value) in entries {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    Value::Array(merged)
+}
+
+/// Write a CGI response: the status unless it is 200, the headers (with
+/// an HTML content type unless they give one), a blank line and the body
+pub fn write_response(
+    out: &mut impl Write,
+    status: u16,
+    headers: &[String],
+    body: &[u8],
+) -> io::Result<()> {
+    if status != 200 {
+        write!(out, "Status: {} {}\r\n", status, reason_phrase(status))?;
+    }
+    for header in headers {
+        write!(out, "{}\r\n", header)?;
+    }
+    let has_content_type = headers.iter().any(|header| {
+        header
+            .split(':')
+            .next()
+            .is_some_and(|name| name.trim().eq_ignore_ascii_case("content-type"))
+    });
+    if !has_content_type {
+        write!(out, "Content-type: text/html; charset=UTF-8\r\n")?;
+    }
+    write!(out, "\r\n")?;
+    out.write_all(body)?;
+    out.flush()
+}
+
+/// The reason phrase of an HTTP status code
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        410 => "Gone",
+        413 => "Content Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Content",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,412 @@
+//! Statement walking for `vhp check`
+//!
+//! Variable types are tracked through straight-line code. Where control
+//! flow joins (after branches and loops) a variable keeps its type only if
+//! every path agrees on it; otherwise it becomes unknown.
+
+use std::collections::HashMap;
+
+use super::types::{accepts, hint_name, Ty};
+use super::{Signature, Symbols};
+use crate::ast::{
+    Argument, DeclareDirective, Expr, ForeachTarget, FunctionParam, NamespaceBody, Stmt, TypeHint,
+};
+
+pub fn check(statements: &[Stmt], symbols: &Symbols) -> Vec<(usize, String)> {
+    let strict = statements.iter().any(|stmt| {
+        matches!(stmt, Stmt::Declare { directives, body: None }
+            if directives.iter().any(|d| matches!(d, DeclareDirective::StrictTypes(true))))
+    });
+    let mut analyzer = Analyzer {
+        symbols,
+        strict,
+        line: 0,
+        found: Vec::new(),
+        env: HashMap::new(),
+        class: None,
+        function: None,
+    };
+    analyzer.block(statements);
+    analyzer.found
+}
+
+/// Known variable types; a variable that is missing is unknown
+pub(super) type Env = HashMap<String, Ty>;
+
+/// The function or method whose body is being checked
+struct Context {
+    display: String,
+    return_type: Option<TypeHint>,
+}
+
+pub(super) struct Analyzer<'a> {
+    pub(super) symbols: &'a Symbols,
+    /// declare(strict_types=1) at the top of the file
+    pub(super) strict: bool,
+    line: usize,
+    found: Vec<(usize, String)>,
+    pub(super) env: Env,
+    /// Class of `$this`, `self` and `static`
+    pub(super) class: Option<String>,
+    function: Option<Context>,
+}
+
+impl Analyzer<'_> {
+    pub(super) fn report(&mut self, message: String) {
+        self.found.push((self.line, message));
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Line(line) => self.line = *line,
+            Stmt::Echo(exprs) => exprs.iter().for_each(|e| {
+                self.expr(e);
+            }),
+            Stmt::Expression(expr) | Stmt::Throw(expr) => {
+                self.expr(expr);
+            }
+            Stmt::Return(expr) => {
+                let ty = expr.as_ref().map(|e| self.expr(e));
+                self.check_return(ty);
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                elseif_branches,
+                else_branch,
+            } => {
+                self.expr(condition);
+                let before = self.env.clone();
+                let mut paths = vec![self.branch(&before, then_branch)];
+                for (condition, branch) in elseif_branches {
+                    self.expr(condition);
+                    paths.push(self.branch(&before, branch));
+                }
+                match else_branch {
+                    Some(branch) => paths.push(self.branch(&before, branch)),
+                    None => paths.push(before),
+                }
+                self.env = merge(paths);
+            }
+            Stmt::While { condition, body } => {
+                self.expr(condition);
+                self.repeat(|a| a.block(body));
+            }
+            Stmt::DoWhile { body, condition } => self.repeat(|a| {
+                a.block(body);
+                a.expr(condition);
+            }),
+            Stmt::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.expr(init);
+                }
+                self.repeat(|a| {
+                    if let Some(condition) = condition {
+                        a.expr(condition);
+                    }
+                    a.block(body);
+                    if let Some(update) = update {
+                        a.expr(update);
+                    }
+                });
+            }
+            Stmt::Foreach {
+                array,
+                key,
+                value,
+                body,
+                ..
+            } => {
+                self.expr(array);
+                self.repeat(|a| {
+                    if let Some(key) = key {
+                        a.env.remove(key);
+                    }
+                    match value {
+                        ForeachTarget::Variable(name) => {
+                            a.env.remove(name);
+                        }
+                        ForeachTarget::List(elements) => a.forget_targets(elements),
+                    }
+                    a.block(body);
+                });
+            }
+            Stmt::Switch { expr, cases } => {
+                self.expr(expr);
+                let before = self.env.clone();
+                // A case is entered directly or by falling through the one above
+                for case in cases {
+                    if let Some(value) = &case.value {
+                        self.expr(value);
+                    }
+                    self.env = merge(vec![before.clone(), self.env.clone()]);
+                    self.block(&case.body);
+                }
+                self.env = merge(vec![before, self.env.clone()]);
+            }
+            Stmt::Function {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => self.function(name.clone(), params, return_type, body, None),
+            Stmt::Class {
+                name,
+                properties,
+                methods,
+                ..
+            }
+            | Stmt::Trait {
+                name,
+                properties,
+                methods,
+                ..
+            } => {
+                for property in properties {
+                    if let (Some(hint), Some(default)) = (&property.type_hint, &property.default) {
+                        let ty = self.expr(default);
+                        if !accepts(hint, &ty, true, self.symbols) {
+                            self.report(format!(
+                                "Cannot use {} as default value for property {}::${} of type {}",
+                                ty.name(),
+                                name,
+                                property.name,
+                                hint_name(hint)
+                            ));
+                        }
+                    }
+                }
+                self.methods(name, methods);
+            }
+            Stmt::Enum { name, methods, .. } => self.methods(name, methods),
+            Stmt::TryCatch {
+                try_body,
+                catch_clauses,
+                finally_body,
+            } => {
+                let before = self.env.clone();
+                self.block(try_body);
+                // An exception may be thrown anywhere in the try block
+                let thrown = merge(vec![before, self.env.clone()]);
+                let mut paths = vec![self.env.clone()];
+                for clause in catch_clauses {
+                    self.env = thrown.clone();
+                    let ty = match clause.exception_types.as_slice() {
+                        [class] => Ty::Object(class.trim_start_matches('\\').to_string()),
+                        _ => Ty::Unknown,
+                    };
+                    self.env.insert(clause.variable.clone(), ty);
+                    self.block(&clause.body);
+                    paths.push(std::mem::take(&mut self.env));
+                }
+                self.env = merge(paths);
+                if let Some(body) = finally_body {
+                    self.env = merge(vec![thrown, self.env.clone()]);
+                    self.block(body);
+                }
+            }
+            Stmt::Namespace {
+                body: NamespaceBody::Braced(body),
+                ..
+            } => self.block(body),
+            Stmt::Declare {
+                body: Some(body), ..
+            } => self.block(body),
+            _ => {}
+        }
+    }
+
+    /// Run one branch from the state before it and return the state after
+    fn branch(&mut self, before: &Env, body: &[Stmt]) -> Env {
+        self.env = before.clone();
+        self.block(body);
+        std::mem::take(&mut self.env)
+    }
+
+    /// Check a loop body, which may run zero or more times
+    ///
+    /// The body is first walked silently to learn which variables change
+    /// type between iterations, then walked again from the merged state.
+    fn repeat(&mut self, mut body: impl FnMut(&mut Self)) {
+        let before = self.env.clone();
+        let (line, found) = (self.line, self.found.len());
+        body(self);
+        self.found.truncate(found);
+        self.line = line;
+        self.env = merge(vec![before.clone(), self.env.clone()]);
+        body(self);
+        self.env = merge(vec![before, self.env.clone()]);
+    }
+
+    fn methods(&mut self, class: &str, methods: &[crate::ast::Method]) {
+        for method in methods {
+            let display = format!("{}::{}", class, method.name);
+            let class = Some(class.to_string());
+            self.function(
+                display,
+                &method.params,
+                &method.return_type,
+                &method.body,
+                class,
+            );
+        }
+    }
+
+    /// Check a function body in its own variable scope
+    fn function(
+        &mut self,
+        display: String,
+        params: &[FunctionParam],
+        return_type: &Option<TypeHint>,
+        body: &[Stmt],
+        class: Option<String>,
+    ) {
+        let mut env = Env::new();
+        for param in params {
+            env.insert(param.name.clone(), self.param_type(param, class.as_deref()));
+        }
+        let context = Context {
+            display,
+            return_type: return_type.clone(),
+        };
+        let outer_env = std::mem::replace(&mut self.env, env);
+        let outer_class = std::mem::replace(&mut self.class, class);
+        let outer_function = self.function.replace(context);
+        let line = self.line;
+
+        self.block(body);
+
+        self.line = line;
+        self.env = outer_env;
+        self.class = outer_class;
+        self.function = outer_function;
+    }
+
+    /// Type of a parameter inside the function body
+    pub(super) fn param_type(&self, param: &FunctionParam, class: Option<&str>) -> Ty {
+        match &param.type_hint {
+            _ if param.is_variadic => Ty::Array,
+            // `int $x = null` is implicitly nullable
+            Some(_) if matches!(param.default, Some(Expr::Null)) => Ty::Unknown,
+            Some(hint) => Ty::from_hint(hint, class),
+            None => Ty::Unknown,
+        }
+    }
+
+    fn check_return(&mut self, ty: Option<Ty>) {
+        let Some(Context {
+            display,
+            return_type: Some(hint),
+        }) = &self.function
+        else {
+            return;
+        };
+        let message = match (hint, ty) {
+            (TypeHint::Void, None) => return,
+            (TypeHint::Void, Some(ty)) => format!(
+                "{}(): Return value must be of type void, {} returned",
+                display,
+                ty.name()
+            ),
+            (TypeHint::Never, _) => {
+                format!("{}(): A never-returning function must not return", display)
+            }
+            // Generators return their final value through getReturn()
+            (TypeHint::Class(name), _) if is_generator_type(name) => return,
+            (TypeHint::Simple(name), _) if name.eq_ignore_ascii_case("iterable") => return,
+            (_, None) => format!(
+                "{}(): Return value must be of type {}, none returned",
+                display,
+                hint_name(hint)
+            ),
+            (_, Some(ty)) if !accepts(hint, &ty, self.strict, self.symbols) => format!(
+                "{}(): Return value must be of type {}, {} returned",
+                display,
+                hint_name(hint),
+                ty.name()
+            ),
+            _ => return,
+        };
+        self.report(message);
+    }
+
+    /// Check the arguments of a call to a declared function or method
+    pub(super) fn check_call(&mut self, signature: &Signature, args: &[Argument], types: &[Ty]) {
+        let positional = args.iter().take_while(|a| a.name.is_none()).count();
+        let unpacked = args.iter().any(|a| matches!(*a.value, Expr::Spread(_)));
+        let required = signature
+            .params
+            .iter()
+            .filter(|p| p.default.is_none() && !p.is_variadic)
+            .count();
+        if !unpacked && args.len() == positional && positional < required {
+            self.report(format!(
+                "Too few arguments to function {}(), {} passed in, at least {} expected",
+                signature.display, positional, required
+            ));
+        }
+        if unpacked {
+            return;
+        }
+
+        for (i, (arg, ty)) in args.iter().zip(types).enumerate() {
+            let param = match &arg.name {
+                Some(name) => signature.params.iter().find(|p| &p.name == name),
+                None => signature
+                    .params
+                    .get(i)
+                    .or_else(|| signature.params.last().filter(|p| p.is_variadic)),
+            };
+            let Some(param) = param else { continue };
+            if param.by_ref {
+                // The callee may store anything in the variable
+                if let Expr::Variable(name) = &*arg.value {
+                    self.env.remove(name);
+                }
+                continue;
+            }
+            let Some(hint) = &param.type_hint else {
+                continue;
+            };
+            let implicit_null = *ty == Ty::Null && matches!(param.default, Some(Expr::Null));
+            if !implicit_null && !accepts(hint, ty, self.strict, self.symbols) {
+                self.report(format!(
+                    "Argument {} passed to {}() must be of type {}, {} given",
+                    i + 1,
+                    signature.display,
+                    hint_name(hint),
+                    ty.name()
+                ));
+            }
+        }
+    }
+}
+
+fn is_generator_type(name: &str) -> bool {
+    let name = name.trim_start_matches('\\').to_lowercase();
+    matches!(
+        name.as_str(),
+        "generator" | "iterator" | "traversable" | "iteratoraggregate"
+    )
+}
+
+/// State after control flow joins: only types every path agrees on
+fn merge(mut paths: Vec<Env>) -> Env {
+    let Some(mut env) = paths.pop() else {
+        return Env::new();
+    };
+    env.retain(|name, ty| paths.iter().all(|path| path.get(name) == Some(ty)));
+    env
+}
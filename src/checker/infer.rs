@@ -0,0 +1,500 @@
+//! Expression type inference for `vhp check`
+
+use super::analysis::Analyzer;
+use super::types::{accepts, hint_name, is_numeric, Ty};
+use super::Signature;
+use crate::ast::{Argument, AssignOp, BinaryOp, Expr, ListElement, UnaryOp};
+
+/// Built-in functions that may change the type of a variable argument
+const BY_REF_BUILTINS: &[&str] = &[
+    "settype",
+    "preg_match",
+    "preg_match_all",
+    "parse_str",
+    "str_replace",
+    "str_ireplace",
+    "preg_replace",
+    "similar_text",
+    "sscanf",
+    "exec",
+];
+
+/// Built-in functions whose use makes every local variable unknown
+const DYNAMIC_BUILTINS: &[&str] = &["extract", "get_defined_vars", "compact", "parse_str"];
+
+impl Analyzer<'_> {
+    pub(super) fn expr(&mut self, expr: &Expr) -> Ty {
+        match expr {
+            Expr::String(s) => Ty::String(Some(is_numeric(s))),
+            Expr::Integer(_) | Expr::MagicLine(_) => Ty::Int,
+            Expr::Float(_) => Ty::Float,
+            Expr::Bool(_) => Ty::Bool,
+            Expr::Null => Ty::Null,
+            Expr::Constant(_) => Ty::Unknown,
+            Expr::MagicFile
+            | Expr::MagicDir
+            | Expr::MagicFunction
+            | Expr::MagicClass
+            | Expr::MagicMethod
+            | Expr::MagicNamespace
+            | Expr::MagicTrait
+            | Expr::ClassReference(_) => Ty::String(None),
+            Expr::Variable(name) if name == "this" => self.this(),
+            Expr::Variable(name) => self.env.get(name).cloned().unwrap_or(Ty::Unknown),
+            Expr::This => self.this(),
+            Expr::Array(elements) => {
+                for element in elements {
+                    if let Some(key) = &element.key {
+                        self.expr(key);
+                    }
+                    self.expr(&element.value);
+                }
+                Ty::Array
+            }
+            Expr::ArrayAccess { array, index } => {
+                self.expr(array);
+                self.expr(index);
+                Ty::Unknown
+            }
+            Expr::Binary { left, op, right } => {
+                let left = self.expr(left);
+                let right = self.expr(right);
+                binary(op, &left, &right)
+            }
+            Expr::Unary { op, expr } => {
+                let ty = self.expr(expr);
+                match op {
+                    UnaryOp::Not => Ty::Bool,
+                    UnaryOp::Silence => ty,
+                    UnaryOp::BitwiseNot if matches!(ty, Ty::String(_)) => Ty::String(None),
+                    UnaryOp::BitwiseNot if ty != Ty::Unknown => Ty::Int,
+                    _ if matches!(ty, Ty::Int | Ty::Float) => ty,
+                    UnaryOp::Neg => Ty::Unknown,
+                    _ => {
+                        // ++ and -- on a non-number may change its type
+                        if let Expr::Variable(name) = &**expr {
+                            self.env.remove(name);
+                        }
+                        Ty::Unknown
+                    }
+                }
+            }
+            Expr::Assign { var, op, value } => {
+                let value = self.expr(value);
+                let current = self.env.get(var).cloned().unwrap_or(Ty::Unknown);
+                let ty = match op.binary_op() {
+                    Some(op) => binary(&op, &current, &value),
+                    None if *op == AssignOp::NullCoalesceAssign => {
+                        binary(&BinaryOp::NullCoalesce, &current, &value)
+                    }
+                    None => value,
+                };
+                self.assign(var, ty.clone());
+                ty
+            }
+            Expr::AssignRef { var, source } => {
+                let ty = self.expr(source);
+                self.assign(var, ty.clone());
+                ty
+            }
+            Expr::ArrayAssign {
+                array,
+                index,
+                value,
+                ..
+            } => {
+                if let Some(index) = index {
+                    self.expr(index);
+                }
+                let value = self.expr(value);
+                self.expr(array);
+                // Writing an element creates the array, or changes a string offset
+                if let Expr::Variable(name) = &**array {
+                    let ty = match self.env.get(name) {
+                        Some(Ty::Array) | None => Ty::Array,
+                        Some(_) => Ty::Unknown,
+                    };
+                    self.assign(name, ty);
+                }
+                value
+            }
+            Expr::Grouped(expr) => self.expr(expr),
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => {
+                let condition_ty = self.expr(condition);
+                let then_ty = match then_expr {
+                    Some(then_expr) => self.expr(then_expr),
+                    None => condition_ty,
+                };
+                let else_ty = self.expr(else_expr);
+                same(then_ty, else_ty)
+            }
+            Expr::FunctionCall { name, args } => self.function_call(name, args),
+            Expr::CallableCall { callable, args } => {
+                self.expr(callable);
+                self.args(args);
+                Ty::Unknown
+            }
+            Expr::New { class_name, args } => {
+                let class = self.resolve_class(class_name);
+                let types = self.args(args);
+                let symbols = self.symbols;
+                if let Some(class) = &class {
+                    if let Some(constructor) = symbols.method(class, "__construct") {
+                        self.check_call(constructor, args, &types);
+                    }
+                }
+                class.map(Ty::Object).unwrap_or(Ty::Unknown)
+            }
+            Expr::NewDynamic { class, args } => {
+                self.expr(class);
+                self.args(args);
+                Ty::Unknown
+            }
+            Expr::NewAnonymousClass {
+                constructor_args, ..
+            } => {
+                self.args(constructor_args);
+                Ty::Unknown
+            }
+            Expr::NewFiber { callback } => {
+                self.expr(callback);
+                Ty::Object("Fiber".to_string())
+            }
+            Expr::PropertyAccess { object, property } => match self.expr(object) {
+                Ty::Object(class) => self
+                    .symbols
+                    .property(&class, property)
+                    .map(|hint| Ty::from_hint(hint, Some(&class)))
+                    .unwrap_or(Ty::Unknown),
+                _ => Ty::Unknown,
+            },
+            Expr::MethodCall {
+                object,
+                method,
+                args,
+            } => {
+                let class = match self.expr(object) {
+                    Ty::Object(class) => Some(class),
+                    _ => None,
+                };
+                self.method_call(class, method, args)
+            }
+            Expr::PropertyAssign {
+                object,
+                property,
+                op,
+                value,
+            } => {
+                let object = self.expr(object);
+                let value = self.expr(value);
+                if *op != AssignOp::Assign {
+                    return Ty::Unknown;
+                }
+                if let Ty::Object(class) = object {
+                    self.check_property(&class, property, &value);
+                }
+                value
+            }
+            Expr::StaticMethodCall {
+                class_name,
+                method,
+                args,
+            } => {
+                let class = self.resolve_class(class_name);
+                self.method_call(class, method, args)
+            }
+            Expr::StaticPropertyAccess { class, property } => match self.resolve_class(class) {
+                Some(class) => self
+                    .symbols
+                    .property(&class, property)
+                    .map(|hint| Ty::from_hint(hint, Some(&class)))
+                    .unwrap_or(Ty::Unknown),
+                None => Ty::Unknown,
+            },
+            Expr::StaticPropertyAssign {
+                class,
+                property,
+                op,
+                value,
+            } => {
+                let value = self.expr(value);
+                if *op != AssignOp::Assign {
+                    return Ty::Unknown;
+                }
+                if let Some(class) = self.resolve_class(class) {
+                    self.check_property(&class, property, &value);
+                }
+                value
+            }
+            Expr::FiberSuspend { value } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                Ty::Unknown
+            }
+            Expr::Match {
+                expr,
+                arms,
+                default,
+            } => {
+                self.expr(expr);
+                let mut result = None;
+                for arm in arms {
+                    arm.conditions.iter().for_each(|c| {
+                        self.expr(c);
+                    });
+                    let ty = self.expr(&arm.result);
+                    result = Some(result.map_or(ty.clone(), |r| same(r, ty)));
+                }
+                if let Some(default) = default {
+                    let ty = self.expr(default);
+                    result = Some(result.map_or(ty.clone(), |r| same(r, ty)));
+                }
+                result.unwrap_or(Ty::Unknown)
+            }
+            Expr::EnumCase {
+                enum_name,
+                case_name,
+            } => {
+                // Class constants use the same syntax as enum cases
+                let class = self.resolve_class(enum_name);
+                match class.as_deref().and_then(|c| self.symbols.class(c)) {
+                    Some(info) if info.cases.contains(case_name) => Ty::Object(info.name.clone()),
+                    _ => Ty::Unknown,
+                }
+            }
+            Expr::Clone { object } => self.expr(object),
+            Expr::CloneWith {
+                object,
+                modifications,
+            } => {
+                for modification in modifications {
+                    self.expr(&modification.value);
+                }
+                self.expr(object)
+            }
+            Expr::CloneWithProperties { object, properties } => {
+                self.expr(properties);
+                self.expr(object)
+            }
+            Expr::Spread(expr) | Expr::Throw(expr) | Expr::YieldFrom(expr) => {
+                self.expr(expr);
+                Ty::Unknown
+            }
+            // `?->` gives null when the object is, which the types can't say
+            Expr::NullsafePropertyAccess { object, .. }
+            | Expr::NullsafeMethodCall { object, .. } => {
+                self.expr(object);
+                Ty::Unknown
+            }
+            Expr::CallableFromMethod { object, .. } => {
+                self.expr(object);
+                Ty::Closure
+            }
+            Expr::ArrowFunction { params, body } => {
+                // Arrow functions capture the enclosing variables by value
+                let outer = self.env.clone();
+                for param in params {
+                    let ty = self.param_type(param, self.class.clone().as_deref());
+                    self.env.insert(param.name.clone(), ty);
+                }
+                self.expr(body);
+                self.env = outer;
+                Ty::Closure
+            }
+            Expr::CallableFromFunction(_) | Expr::CallableFromStaticMethod { .. } => Ty::Closure,
+            Expr::Yield { key, value } => {
+                for expr in key.iter().chain(value) {
+                    self.expr(expr);
+                }
+                Ty::Unknown
+            }
+            Expr::ListDestructure { elements, array } => {
+                self.expr(array);
+                self.forget_targets(elements);
+                Ty::Array
+            }
+            Expr::Placeholder | Expr::FiberGetCurrent => Ty::Unknown,
+        }
+    }
+
+    fn this(&self) -> Ty {
+        self.class.clone().map(Ty::Object).unwrap_or(Ty::Unknown)
+    }
+
+    fn assign(&mut self, name: &str, ty: Ty) {
+        if ty == Ty::Unknown {
+            self.env.remove(name);
+        } else {
+            self.env.insert(name.to_string(), ty);
+        }
+    }
+
+    /// Types of the call arguments, walked in order
+    fn args(&mut self, args: &[Argument]) -> Vec<Ty> {
+        args.iter().map(|arg| self.expr(&arg.value)).collect()
+    }
+
+    fn function_call(&mut self, name: &str, args: &[Argument]) -> Ty {
+        let types = self.args(args);
+        let lower = name.trim_start_matches('\\').to_lowercase();
+        let short = lower.rsplit('\\').next().unwrap_or(&lower);
+        let symbols = self.symbols;
+        if let Some(signature) = symbols.functions.get(short) {
+            self.check_call(signature, args, &types);
+            return self.returned(signature, None);
+        }
+
+        if DYNAMIC_BUILTINS.contains(&short) {
+            self.env.clear();
+        } else if BY_REF_BUILTINS.contains(&short) {
+            for arg in args {
+                if let Expr::Variable(name) = &*arg.value {
+                    self.env.remove(name);
+                }
+            }
+        }
+        builtin_return(short)
+    }
+
+    fn method_call(&mut self, class: Option<String>, method: &str, args: &[Argument]) -> Ty {
+        let types = self.args(args);
+        let symbols = self.symbols;
+        let Some(class) = class else {
+            return Ty::Unknown;
+        };
+        match symbols.method(&class, method) {
+            Some(signature) => {
+                self.check_call(signature, args, &types);
+                self.returned(signature, Some(&class))
+            }
+            None => Ty::Unknown,
+        }
+    }
+
+    /// Type of a call result, from the declared return type
+    fn returned(&self, signature: &Signature, class: Option<&str>) -> Ty {
+        signature
+            .return_type
+            .as_ref()
+            .map(|hint| Ty::from_hint(hint, class))
+            .unwrap_or(Ty::Unknown)
+    }
+
+    /// Class named in `new X`, `X::method()` and similar, if known
+    fn resolve_class(&self, name: &str) -> Option<String> {
+        match name.to_lowercase().as_str() {
+            "self" | "static" => self.class.clone(),
+            "parent" => {
+                let class = self.symbols.class(self.class.as_deref()?)?;
+                let parent = class.parents.first()?;
+                self.symbols.class(parent).map(|p| p.name.clone())
+            }
+            _ => Some(name.trim_start_matches('\\').to_string()),
+        }
+    }
+
+    fn check_property(&mut self, class: &str, property: &str, value: &Ty) {
+        let Some(hint) = self.symbols.property(class, property) else {
+            return;
+        };
+        if !accepts(hint, value, self.strict, self.symbols) {
+            let class = self.symbols.class(class).map_or(class, |c| c.name.as_str());
+            self.report(format!(
+                "Cannot assign {} to property {}::${} of type {}",
+                value.name(),
+                class,
+                property,
+                hint_name(hint)
+            ));
+        }
+    }
+
+    /// Variables written by list() destructuring
+    pub(super) fn forget_targets(&mut self, elements: &[Option<ListElement>]) {
+        for element in elements.iter().flatten() {
+            match &*element.value {
+                Expr::Variable(name) => {
+                    self.env.remove(name);
+                }
+                Expr::ListDestructure { elements, .. } => self.forget_targets(elements),
+                Expr::Array(items) => {
+                    for item in items {
+                        if let Expr::Variable(name) = &*item.value {
+                            self.env.remove(name);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn binary(op: &BinaryOp, left: &Ty, right: &Ty) -> Ty {
+    match op {
+        BinaryOp::Concat => Ty::String(None),
+        BinaryOp::Equal
+        | BinaryOp::Identical
+        | BinaryOp::NotEqual
+        | BinaryOp::NotIdentical
+        | BinaryOp::LessThan
+        | BinaryOp::GreaterThan
+        | BinaryOp::LessEqual
+        | BinaryOp::GreaterEqual
+        | BinaryOp::And
+        | BinaryOp::Or
+        | BinaryOp::Xor => Ty::Bool,
+        BinaryOp::BitwiseOr | BinaryOp::BitwiseAnd | BinaryOp::BitwiseXor => match (left, right) {
+            (Ty::String(_), Ty::String(_)) => Ty::String(None),
+            (Ty::Unknown, _) | (_, Ty::Unknown) => Ty::Unknown,
+            _ => Ty::Int,
+        },
+        BinaryOp::Spaceship | BinaryOp::Mod | BinaryOp::ShiftLeft | BinaryOp::ShiftRight => Ty::Int,
+        BinaryOp::Add if *left == Ty::Array && *right == Ty::Array => Ty::Array,
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Pow => match (left, right) {
+            (Ty::Int, Ty::Int) if *op != BinaryOp::Pow => Ty::Int,
+            (Ty::Int | Ty::Float, Ty::Int | Ty::Float)
+                if (*left == Ty::Float || *right == Ty::Float) =>
+            {
+                Ty::Float
+            }
+            _ => Ty::Unknown,
+        },
+        BinaryOp::NullCoalesce if *left == Ty::Null => right.clone(),
+        BinaryOp::NullCoalesce => same(left.clone(), right.clone()),
+        BinaryOp::Div | BinaryOp::Pipe => Ty::Unknown,
+    }
+}
+
+/// The common type of two alternatives, if they agree
+fn same(a: Ty, b: Ty) -> Ty {
+    match (a, b) {
+        (Ty::String(_), Ty::String(_)) => Ty::String(None),
+        (a, b) if a == b => a,
+        _ => Ty::Unknown,
+    }
+}
+
+/// Result type of common built-in functions that always return one type
+fn builtin_return(name: &str) -> Ty {
+    match name {
+        "strlen" | "mb_strlen" | "count" | "intval" | "ord" => Ty::Int,
+        "floatval" => Ty::Float,
+        "strval" | "strtoupper" | "strtolower" | "ucfirst" | "lcfirst" | "ucwords" | "trim"
+        | "ltrim" | "rtrim" | "str_repeat" | "str_pad" | "implode" | "join" | "sprintf"
+        | "substr" | "number_format" | "chr" | "dechex" | "gettype" | "get_class" => {
+            Ty::String(None)
+        }
+        "boolval" | "in_array" | "array_key_exists" | "function_exists" | "class_exists"
+        | "method_exists" | "str_contains" | "str_starts_with" | "str_ends_with" => Ty::Bool,
+        _ if name.starts_with("is_") => Ty::Bool,
+        "array_keys" | "array_values" | "array_map" | "array_merge" | "array_filter"
+        | "array_slice" | "array_reverse" | "explode" | "range" | "str_split" => Ty::Array,
+        _ => Ty::Unknown,
+    }
+}
@@ -0,0 +1,283 @@
+//! Static type checker for `vhp check`
+//!
+//! Collects the declared functions and classes of all given files, then
+//! infers expression types and compares them with parameter, return and
+//! property type hints, reporting calls and assignments that would throw
+//! a TypeError (or ArgumentCountError) at runtime.
+
+mod analysis;
+mod infer;
+mod types;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::ast::{FunctionParam, Method, NamespaceBody, Property, Stmt, TypeHint};
+use crate::linter::{discover, parse};
+use types::Classes;
+
+/// A would-be runtime type error
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeProblem {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+/// Parameters and return type of a function or method
+struct Signature {
+    /// Name used in messages: `foo` or `Foo::bar`
+    display: String,
+    params: Vec<FunctionParam>,
+    return_type: Option<TypeHint>,
+}
+
+/// A class, interface, trait or enum
+#[derive(Default)]
+struct ClassInfo {
+    name: String,
+    /// Parent class and implemented (or extended) interfaces
+    parents: Vec<String>,
+    methods: HashMap<String, Signature>,
+    properties: HashMap<String, TypeHint>,
+    /// Case names, for enums
+    cases: Vec<String>,
+}
+
+/// Declarations of all checked files, keyed by lowercased name
+#[derive(Default)]
+struct Symbols {
+    functions: HashMap<String, Signature>,
+    classes: HashMap<String, ClassInfo>,
+}
+
+/// Check every PHP file under `paths`
+///
+/// A function declared in one file is checked where another calls it:
+///
+/// ```
+/// use std::fs;
+///
+/// let dir = std::env::temp_dir().join(format!("vhp-check-doc-{}", std::process::id()));
+/// fs::create_dir_all(&dir).unwrap();
+/// let lib = r#"<?php
+/// function half(int $n): int {
+///     return intdiv($n, 2);
+/// }
+/// function size(): int {
+///     return [];
+/// }
+/// class User {
+///     public string $name = "";
+/// }
+/// "#;
+/// let main = r#"<?php
+/// echo half("4"), half("four"), half();
+/// $user = new User();
+/// $user->name = [];
+/// "#;
+/// fs::write(dir.join("lib.php"), lib).unwrap();
+/// fs::write(dir.join("main.php"), main).unwrap();
+///
+/// let problems = vhp::checker::check_paths(&[dir.clone()]).unwrap();
+/// let found: Vec<(usize, &str)> = problems.iter().map(|p| (p.line, p.message.as_str())).collect();
+/// assert_eq!(
+///     found,
+///     [
+///         (6, "size(): Return value must be of type int, array returned"),
+///         (2, "Argument 1 passed to half() must be of type int, string given"),
+///         (2, "Too few arguments to function half(), 0 passed in, at least 1 expected"),
+///         (4, "Cannot assign array to property User::$name of type string"),
+///     ]
+/// );
+/// # fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn check_paths(paths: &[PathBuf]) -> Result<Vec<TypeProblem>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        discover(path, &mut files)?;
+    }
+
+    let mut programs = Vec::new();
+    let mut symbols = Symbols::default();
+    for path in files {
+        let file = path.display().to_string();
+        let source = fs::read_to_string(&path)
+            .map_err(|e| format!("Error reading file '{}': {}", file, e))?;
+        let program = parse(&source).map_err(|e| format!("{}: {}", file, e))?;
+        symbols.collect(&program.statements);
+        programs.push((file, program));
+    }
+
+    let mut problems = Vec::new();
+    for (file, program) in &programs {
+        for (line, message) in analysis::check(&program.statements, &symbols) {
+            problems.push(TypeProblem {
+                file: file.clone(),
+                line,
+                message,
+            });
+        }
+    }
+    Ok(problems)
+}
+
+impl Symbols {
+    fn collect(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            match stmt {
+                Stmt::Function {
+                    name,
+                    params,
+                    return_type,
+                    body,
+                    ..
+                } => {
+                    self.functions.insert(
+                        name.to_lowercase(),
+                        Signature {
+                            display: name.clone(),
+                            params: params.clone(),
+                            return_type: return_type.clone(),
+                        },
+                    );
+                    self.collect(body);
+                }
+                Stmt::Class {
+                    name,
+                    parent,
+                    interfaces,
+                    properties,
+                    methods,
+                    ..
+                } => {
+                    let parents = parent
+                        .iter()
+                        .chain(interfaces)
+                        .filter_map(|q| q.last().cloned())
+                        .collect();
+                    self.add_class(name, parents, properties, methods);
+                }
+                Stmt::Interface { name, parents, .. } => {
+                    let parents = parents.iter().filter_map(|q| q.last().cloned()).collect();
+                    self.add_class(name, parents, &[], &[]);
+                }
+                Stmt::Trait {
+                    name,
+                    properties,
+                    methods,
+                    ..
+                } => self.add_class(name, Vec::new(), properties, methods),
+                Stmt::Enum {
+                    name,
+                    interfaces,
+                    cases,
+                    methods,
+                    ..
+                } => {
+                    let parents = interfaces
+                        .iter()
+                        .filter_map(|q| q.last().cloned())
+                        .collect();
+                    self.add_class(name, parents, &[], methods);
+                    if let Some(class) = self.classes.get_mut(&name.to_lowercase()) {
+                        class.cases = cases.iter().map(|c| c.name.clone()).collect();
+                    }
+                }
+                Stmt::Namespace {
+                    body: NamespaceBody::Braced(body),
+                    ..
+                } => self.collect(body),
+                Stmt::Declare {
+                    body: Some(body), ..
+                } => self.collect(body),
+                _ => {}
+            }
+        }
+    }
+
+    fn add_class(
+        &mut self,
+        name: &str,
+        parents: Vec<String>,
+        properties: &[Property],
+        methods: &[Method],
+    ) {
+        let mut class = ClassInfo {
+            name: name.to_string(),
+            parents,
+            ..ClassInfo::default()
+        };
+        for property in properties {
+            if let Some(hint) = &property.type_hint {
+                class.properties.insert(property.name.clone(), hint.clone());
+            }
+        }
+        for method in methods {
+            if method.name.eq_ignore_ascii_case("__construct") {
+                // Promoted constructor parameters declare properties
+                for param in method.params.iter().filter(|p| p.visibility.is_some()) {
+                    if let Some(hint) = &param.type_hint {
+                        class.properties.insert(param.name.clone(), hint.clone());
+                    }
+                }
+            }
+            class.methods.insert(
+                method.name.to_lowercase(),
+                Signature {
+                    display: format!("{}::{}", name, method.name),
+                    params: method.params.clone(),
+                    return_type: method.return_type.clone(),
+                },
+            );
+        }
+        self.classes.insert(name.to_lowercase(), class);
+    }
+
+    fn class(&self, name: &str) -> Option<&ClassInfo> {
+        let name = name.trim_start_matches('\\');
+        let short = name.rsplit('\\').next().unwrap_or(name);
+        self.classes.get(&short.to_lowercase())
+    }
+
+    /// Find a method in a class or its ancestors
+    fn method(&self, class: &str, method: &str) -> Option<&Signature> {
+        let info = self.class(class)?;
+        info.methods
+            .get(&method.to_lowercase())
+            .or_else(|| info.parents.iter().find_map(|p| self.method(p, method)))
+    }
+
+    /// Find a typed property in a class or its ancestors
+    fn property(&self, class: &str, property: &str) -> Option<&TypeHint> {
+        let info = self.class(class)?;
+        info.properties
+            .get(property)
+            .or_else(|| info.parents.iter().find_map(|p| self.property(p, property)))
+    }
+}
+
+impl Classes for Symbols {
+    fn is_subclass(&self, class: &str, ancestor: &str) -> Option<bool> {
+        let info = self.class(class)?;
+        let ancestor_short = ancestor.rsplit('\\').next().unwrap_or(ancestor);
+        if info.name.eq_ignore_ascii_case(ancestor_short) {
+            return Some(true);
+        }
+        let mut unknown = false;
+        for parent in &info.parents {
+            match self.is_subclass(parent, ancestor) {
+                Some(true) => return Some(true),
+                Some(false) => {}
+                None => unknown = true,
+            }
+        }
+        // An undeclared ancestor (e.g. a built-in class) might implement it
+        if unknown || self.class(ancestor).is_none() {
+            None
+        } else {
+            Some(false)
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Inferred types and type-hint compatibility
+//!
+//! Inference is deliberately shallow: anything that cannot be known
+//! without running the code is `Ty::Unknown`, and an unknown type is
+//! always accepted, so only definite TypeErrors are reported.
+
+use crate::ast::TypeHint;
+
+/// Statically inferred type of an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Float,
+    /// Whether the string is numeric, when it is a known literal
+    String(Option<bool>),
+    Bool,
+    Null,
+    Array,
+    /// Instance of a named class
+    Object(String),
+    Closure,
+    Unknown,
+}
+
+impl Ty {
+    /// Type from a declaration, as seen by code that uses the value
+    pub fn from_hint(hint: &TypeHint, class: Option<&str>) -> Ty {
+        match hint {
+            TypeHint::Simple(name) => match name.to_lowercase().as_str() {
+                "int" => Ty::Int,
+                "float" => Ty::Float,
+                "string" => Ty::String(None),
+                "bool" | "false" | "true" => Ty::Bool,
+                "array" => Ty::Array,
+                "null" => Ty::Null,
+                _ => Ty::Unknown,
+            },
+            TypeHint::Class(name) if name.eq_ignore_ascii_case("closure") => Ty::Closure,
+            TypeHint::Class(name) => Ty::Object(name.trim_start_matches('\\').to_string()),
+            TypeHint::SelfType | TypeHint::Static => class
+                .map(|c| Ty::Object(c.to_string()))
+                .unwrap_or(Ty::Unknown),
+            _ => Ty::Unknown,
+        }
+    }
+
+    /// Type name as PHP prints it in "X given" messages
+    pub fn name(&self) -> String {
+        match self {
+            Ty::Int => "int".to_string(),
+            Ty::Float => "float".to_string(),
+            Ty::String(_) => "string".to_string(),
+            Ty::Bool => "bool".to_string(),
+            Ty::Null => "null".to_string(),
+            Ty::Array => "array".to_string(),
+            Ty::Object(class) => class.clone(),
+            Ty::Closure => "Closure".to_string(),
+            Ty::Unknown => "mixed".to_string(),
+        }
+    }
+
+    fn is_scalar(&self) -> bool {
+        matches!(self, Ty::Int | Ty::Float | Ty::String(_) | Ty::Bool)
+    }
+}
+
+/// Whether a literal string is numeric ("12", " 1.5", "1e3")
+pub fn is_numeric(s: &str) -> bool {
+    let s = s.trim();
+    !s.is_empty() && s.parse::<f64>().is_ok() && !s.contains(['x', 'X', 'n', 'N', 'i', 'I'])
+}
+
+/// Answers class hierarchy questions for `accepts`
+pub trait Classes {
+    /// Whether `class` is `ancestor` or extends/implements it; None when
+    /// either class is not declared in the checked files
+    fn is_subclass(&self, class: &str, ancestor: &str) -> Option<bool>;
+}
+
+/// Whether a value of type `ty` can be passed where `hint` is declared
+///
+/// Returns false only for a definite TypeError. In coercive mode
+/// (strict_types=0) scalars convert into each other the way PHP does.
+pub fn accepts(hint: &TypeHint, ty: &Ty, strict: bool, classes: &dyn Classes) -> bool {
+    if *ty == Ty::Unknown {
+        return true;
+    }
+    match hint {
+        TypeHint::Nullable(inner) => *ty == Ty::Null || accepts(inner, ty, strict, classes),
+        TypeHint::Union(types) => {
+            // An exact match anywhere wins before coercion is considered
+            types.iter().any(|t| accepts(t, ty, true, classes))
+                || (!strict && types.iter().any(|t| accepts(t, ty, false, classes)))
+        }
+        TypeHint::Simple(name) => accepts_simple(&name.to_lowercase(), ty, strict),
+        TypeHint::Class(name) => match ty {
+            Ty::Object(class) => classes
+                .is_subclass(class, name.trim_start_matches('\\'))
+                .unwrap_or(true),
+            Ty::Closure => name
+                .trim_start_matches('\\')
+                .eq_ignore_ascii_case("closure"),
+            _ => false,
+        },
+        TypeHint::Intersection(_)
+        | TypeHint::DNF(_)
+        | TypeHint::SelfType
+        | TypeHint::ParentType
+        | TypeHint::Static => matches!(ty, Ty::Object(_) | Ty::Closure),
+        TypeHint::Void | TypeHint::Never => false,
+    }
+}
+
+fn accepts_simple(name: &str, ty: &Ty, strict: bool) -> bool {
+    match name {
+        "mixed" => true,
+        "null" | "void" => *ty == Ty::Null,
+        "int" | "float" if strict => *ty == Ty::Int || (name == "float" && *ty == Ty::Float),
+        "int" | "float" => ty.is_scalar() && *ty != Ty::String(Some(false)),
+        "string" if strict => matches!(ty, Ty::String(_)),
+        // Objects may implement __toString
+        "string" => ty.is_scalar() || matches!(ty, Ty::Object(_)),
+        "bool" if strict => *ty == Ty::Bool,
+        "bool" => ty.is_scalar(),
+        "false" | "true" => *ty == Ty::Bool,
+        "array" => *ty == Ty::Array,
+        "iterable" => matches!(ty, Ty::Array | Ty::Object(_)),
+        "object" => matches!(ty, Ty::Object(_) | Ty::Closure),
+        "callable" => matches!(ty, Ty::String(_) | Ty::Array | Ty::Object(_) | Ty::Closure),
+        // Class names written as simple hints
+        _ => matches!(ty, Ty::Object(_) | Ty::Closure),
+    }
+}
+
+/// A type hint as PHP prints it in error messages
+pub fn hint_name(hint: &TypeHint) -> String {
+    match hint {
+        TypeHint::Simple(name) | TypeHint::Class(name) => name.clone(),
+        TypeHint::Nullable(inner) => format!("?{}", hint_name(inner)),
+        TypeHint::Union(types) => types.iter().map(hint_name).collect::<Vec<_>>().join("|"),
+        TypeHint::Intersection(types) => types.iter().map(hint_name).collect::<Vec<_>>().join("&"),
+        TypeHint::DNF(groups) => groups
+            .iter()
+            .map(|group| {
+                let names: Vec<String> = group.iter().map(hint_name).collect();
+                if names.len() > 1 {
+                    format!("({})", names.join("&"))
+                } else {
+                    names.join("")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("|"),
+        TypeHint::Void => "void".to_string(),
+        TypeHint::Never => "never".to_string(),
+        TypeHint::Static => "static".to_string(),
+        TypeHint::SelfType => "self".to_string(),
+        TypeHint::ParentType => "parent".to_string(),
+    }
+}
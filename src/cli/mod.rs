@@ -0,0 +1,4 @@
+//! The commands of the `vhp` binary besides running a script
+
+pub mod serve;
+pub mod tools;
@@ -0,0 +1,92 @@
+//! `vhp cgi` and `vhp fcgi`: answering web requests
+//!
+//! A request runs its script like the command line does, with the
+//! request's superglobals, and the output becomes the response body.
+
+use crate::{run, Invocation, RunOptions};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use vhp::cgi::{self, Request};
+use vhp::diagnostic::Diagnostic;
+use vhp::server::fastcgi;
+use vhp::vm::superglobals::Response;
+
+/// Answer the CGI request in the environment with `filename`, or the
+/// script the request names
+pub fn run_cgi(
+    filename: Option<&str>,
+    options: RunOptions,
+) -> Result<Option<i32>, Box<Diagnostic>> {
+    let request = Request::from_process()
+        .map_err(|e| Diagnostic::new(format!("Cannot read request body: {}", e)))?;
+    respond(request, filename, options, &mut std::io::stdout())
+}
+
+/// Run `filename`, or the script the request names, and write the CGI
+/// response to `out`
+fn respond(
+    request: Request,
+    filename: Option<&str>,
+    options: RunOptions,
+    out: &mut impl Write,
+) -> Result<Option<i32>, Box<Diagnostic>> {
+    let write_error = |e: std::io::Error| Diagnostic::new(format!("Cannot write response: {}", e));
+    let filename = filename.or(request.script_filename()).map(str::to_string);
+    let Some((filename, source)) = filename.and_then(|filename| {
+        fs::read_to_string(&filename)
+            .ok()
+            .map(|source| (filename, source))
+    }) else {
+        let body = b"No input file specified.\n";
+        cgi::write_response(out, 404, &[], body).map_err(write_error)?;
+        return Ok(None);
+    };
+
+    let file_path = fs::canonicalize(&filename).unwrap_or_else(|_| PathBuf::from(&filename));
+    let file_path = file_path.to_str().unwrap_or(&filename);
+    let mut invocation = Invocation::Cgi {
+        request,
+        response: Response::default(),
+        uploads: Vec::new(),
+    };
+    let mut body = Vec::new();
+    let result = run(&source, file_path, options, &mut body, &mut invocation);
+    if let Invocation::Cgi {
+        response, uploads, ..
+    } = invocation
+    {
+        for upload in uploads {
+            let _ = fs::remove_file(upload);
+        }
+        let status = match result {
+            Err(_) => 500,
+            Ok(_) => response.status.unwrap_or(200),
+        };
+        cgi::write_response(out, status, &response.headers, &body).map_err(write_error)?;
+    }
+    result
+}
+
+/// Serve FastCGI, running each request with the run options in
+/// `run_args`, which are parsed afresh for every request
+pub fn run_fcgi(args: &[String], run_args: Vec<String>) -> Result<Option<i32>, Box<Diagnostic>> {
+    let options = fastcgi::Options::from_args(args).map_err(Diagnostic::new)?;
+    let server = fastcgi::Server::bind(&options.listen, options.workers)
+        .map_err(|e| Diagnostic::new(format!("Cannot listen on {}: {}", options.listen, e)))?;
+    server
+        .serve(move |request| {
+            let (run_options, _) = RunOptions::from_args(&run_args).unwrap_or_default();
+            let mut reply = fastcgi::Reply::default();
+            match respond(request, None, run_options, &mut reply.stdout) {
+                Ok(exit) => reply.app_status = exit.unwrap_or(0) as u32,
+                Err(diagnostic) => {
+                    reply.stderr = diagnostic.render().into_bytes();
+                    reply.app_status = 1;
+                }
+            }
+            reply
+        })
+        .map(|_| None)
+        .map_err(|e| Diagnostic::new(format!("FastCGI server failed: {}", e)).into())
+}
@@ -0,0 +1,97 @@
+//! `vhp test`, `vhp lint`, `vhp check` and `vhp compile`: the commands
+//! that work on PHP files without running them as scripts
+
+use std::path::PathBuf;
+use std::process;
+use vhp::linter::{LintOptions, Linter, Severity};
+use vhp::test_runner::{TestOptions, TestRunner};
+use vhp::vm::cache::codec::Unencodable;
+use vhp::vm::cache::BytecodeCache;
+
+pub fn run_tests(args: &[String]) -> Result<(), String> {
+    let options = TestOptions::from_args(args)?;
+    let runner = TestRunner::new(options);
+    let summary = runner.run_all()?;
+
+    if summary.failed > 0 || summary.errors > 0 || summary.xpassed > 0 {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+pub fn run_lint(args: &[String]) -> Result<(), String> {
+    let linter = Linter::new(LintOptions::from_args(args)?);
+    let diagnostics = linter.run()?;
+    linter.report(&diagnostics);
+
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+pub fn run_check(args: &[String]) -> Result<(), String> {
+    if let Some(flag) = args.iter().find(|a| a.starts_with('-')) {
+        return Err(format!("Unknown check option: {}", flag));
+    }
+    let paths: Vec<PathBuf> = if args.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        args.iter().map(PathBuf::from).collect()
+    };
+    let problems = vhp::checker::check_paths(&paths)?;
+    for problem in &problems {
+        println!(
+            "{}:{}: error: {}",
+            problem.file, problem.line, problem.message
+        );
+    }
+
+    if !problems.is_empty() {
+        eprintln!("{} type error(s)", problems.len());
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Compile the PHP files at `args` into the bytecode cache
+pub fn run_compile(args: &[String], cache_dir: Option<PathBuf>) -> Result<(), String> {
+    let mut cache_dir = cache_dir;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.split_once('=') {
+            Some(("--cache-dir", dir)) => cache_dir = Some(PathBuf::from(dir)),
+            _ if arg.starts_with('-') => return Err(format!("Unknown compile option: {}", arg)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+    let cache = BytecodeCache::new(cache_dir.ok_or("compile requires --cache-dir=<dir>")?);
+    let precompiled = cache.precompile(&paths)?;
+    for (path, error) in &precompiled.failed {
+        eprintln!("{}: {}", path.display(), error);
+    }
+    for (path, Unencodable(kind)) in &precompiled.uncacheable {
+        eprintln!(
+            "{}: not cached, it declares a constant holding {}",
+            path.display(),
+            kind
+        );
+    }
+    eprintln!(
+        "{} file(s) compiled into {}",
+        precompiled.cached.len(),
+        cache.dir().display()
+    );
+
+    if !precompiled.failed.is_empty() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Debugger command parsing
+
+/// A command entered at the debugger prompt
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Continue,
+    Step,
+    Next,
+    Finish,
+    /// Set a breakpoint at an optional file and a line
+    Break(Option<String>, usize),
+    /// Delete a breakpoint by number
+    Delete(usize),
+    Breakpoints,
+    /// Print one variable of the selected frame
+    Print(String),
+    Locals,
+    Globals,
+    Backtrace,
+    /// Select a frame by number (0 is the innermost)
+    Frame(usize),
+    Up,
+    Down,
+    List,
+    Help,
+    Quit,
+}
+
+/// Parse a line typed at the prompt
+pub fn parse(input: &str) -> Result<Command, String> {
+    let mut words = input.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let arg = words.next();
+    let command = match name {
+        "c" | "continue" => Command::Continue,
+        "s" | "step" => Command::Step,
+        "n" | "next" => Command::Next,
+        "o" | "out" | "finish" => Command::Finish,
+        "b" | "break" => parse_location(arg.ok_or("Usage: break [file:]line")?)?,
+        "d" | "delete" => Command::Delete(number(arg, "Usage: delete <number>")?),
+        "breakpoints" | "info" => Command::Breakpoints,
+        "p" | "print" => {
+            let var = arg.ok_or("Usage: print $variable")?;
+            Command::Print(var.trim_start_matches('$').to_string())
+        }
+        "locals" => Command::Locals,
+        "globals" => Command::Globals,
+        "bt" | "backtrace" => Command::Backtrace,
+        "f" | "frame" => Command::Frame(number(arg, "Usage: frame <number>")?),
+        "up" => Command::Up,
+        "down" => Command::Down,
+        "l" | "list" => Command::List,
+        "h" | "help" | "?" => Command::Help,
+        "q" | "quit" | "exit" => Command::Quit,
+        _ => return Err(format!("Unknown command: {} (type 'help')", name)),
+    };
+    Ok(command)
+}
+
+/// Parse `line` or `file:line`
+fn parse_location(spec: &str) -> Result<Command, String> {
+    let (file, line) = match spec.rsplit_once(':') {
+        Some((file, line)) => (Some(file.to_string()), line),
+        None => (None, spec),
+    };
+    let line = line
+        .parse()
+        .map_err(|_| format!("Invalid line number: {}", line))?;
+    Ok(Command::Break(file, line))
+}
+
+fn number(arg: Option<&str>, usage: &str) -> Result<usize, String> {
+    arg.and_then(|a| a.parse().ok())
+        .ok_or_else(|| usage.to_string())
+}
+
+pub const HELP: &str = "\
+Commands:
+  c, continue          Run until the next breakpoint
+  s, step              Run to the next statement, entering calls
+  n, next              Run to the next statement, stepping over calls
+  o, out, finish       Run until the current function returns
+  b, break [file:]line Set a breakpoint
+  d, delete <n>        Delete breakpoint n
+  breakpoints          List breakpoints
+  p, print $var        Show a variable in the selected frame
+  locals               Show all variables in the selected frame
+  globals              Show global variables
+  bt, backtrace        Show the call stack
+  f, frame <n>         Select frame n (0 is the innermost)
+  up, down             Select the caller / callee frame
+  l, list              Show source around the selected frame's line
+  q, quit              Stop the script
+An empty line repeats the previous command.";
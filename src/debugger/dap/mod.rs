@@ -0,0 +1,401 @@
+//! Debug Adapter Protocol server for `vhp dap`
+//!
+//! Editors start `vhp dap` as a debug adapter and talk to it over
+//! stdin/stdout (`session` serves other streams). The adapter takes breakpoints and exception filters until
+//! `configurationDone`, then runs the launched script with a `DapDebugger`
+//! attached; script output is forwarded as `output` events.
+
+mod protocol;
+mod variables;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use super::{same_file, Mode};
+use crate::runtime::Value;
+use crate::vm::debug::{DebugContext, DebugHook};
+use crate::vm::error::VmError;
+use crate::vm::VM;
+use protocol::{field, items, list, object, path, OutputWriter, Receiver, Sender};
+
+/// VHP runs scripts on a single thread
+const THREAD_ID: i64 = 1;
+
+/// Exception breakpoint filters offered to the client
+const EXCEPTION_FILTERS: [(&str, &str, bool); 2] = [
+    ("uncaught", "Uncaught Exceptions", true),
+    ("all", "All Exceptions", false),
+];
+
+/// Something the client can expand in the variables view
+enum Handle {
+    /// Variables of a frame, shown with a `$` prefix
+    Scope(Vec<(String, Value)>),
+    Value(Value),
+}
+
+/// The exception execution is stopped at
+struct StoppedException {
+    class: String,
+    message: String,
+    uncaught: bool,
+}
+
+/// Settings from the `launch` request
+struct Launch {
+    program: String,
+    stop_on_entry: bool,
+}
+
+pub struct DapDebugger {
+    receiver: Receiver,
+    sender: Sender,
+    mode: Mode,
+    /// Breakpoint lines by source path, replaced per file by `setBreakpoints`
+    breakpoints: HashMap<String, Vec<usize>>,
+    exception_filters: Vec<String>,
+    /// Variable references handed out since the last stop (reference = index + 1)
+    handles: Vec<Handle>,
+    exception: Option<StoppedException>,
+    stopped_before: bool,
+}
+
+/// Serve one debug session on stdin/stdout
+pub fn serve() -> Result<(), String> {
+    session(io::BufReader::new(io::stdin()), io::stdout())
+}
+
+/// Serve one debug session, reading requests from `input` and writing
+/// responses and events to `output`
+///
+/// ```
+/// let dir = std::env::temp_dir().join(format!("vhp-dap-doc-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let script = dir.join("add.php");
+/// let source = "<?php\nfunction add($a, $b) {\n    $sum = $a + $b;\n    return $sum;\n}\n$total = add(1, 2);\necho $total;\n";
+/// std::fs::write(&script, source).unwrap();
+///
+/// let requests = [
+///     r#"{"seq":1,"type":"request","command":"initialize","arguments":{"adapterID":"vhp"}}"#.to_string(),
+///     r#"{"seq":2,"type":"request","command":"setBreakpoints","arguments":{"source":{"path":"add.php"},"breakpoints":[{"line":3}]}}"#.to_string(),
+///     format!(r#"{{"seq":3,"type":"request","command":"launch","arguments":{{"program":{:?}}}}}"#, script),
+///     r#"{"seq":4,"type":"request","command":"configurationDone"}"#.to_string(),
+///     r#"{"seq":5,"type":"request","command":"scopes","arguments":{"frameId":2}}"#.to_string(),
+///     r#"{"seq":6,"type":"request","command":"variables","arguments":{"variablesReference":1}}"#.to_string(),
+///     r#"{"seq":7,"type":"request","command":"evaluate","arguments":{"expression":"$nope","frameId":2}}"#.to_string(),
+///     r#"{"seq":8,"type":"request","command":"next","arguments":{"threadId":1}}"#.to_string(),
+///     r#"{"seq":9,"type":"request","command":"continue","arguments":{"threadId":1}}"#.to_string(),
+///     r#"{"seq":10,"type":"request","command":"disconnect"}"#.to_string(),
+/// ];
+/// let input: String = requests
+///     .iter()
+///     .map(|json| format!("Content-Length: {}\r\n\r\n{}", json.len(), json))
+///     .collect();
+/// let output = std::fs::File::create(dir.join("output")).unwrap();
+/// vhp::debugger::dap::session(std::io::Cursor::new(input), output).unwrap();
+///
+/// let output = std::fs::read_to_string(dir.join("output")).unwrap();
+/// let messages: Vec<&str> = output
+///     .split("Content-Length: ")
+///     .filter_map(|message| Some(message.split_once("\r\n\r\n")?.1))
+///     .collect();
+/// assert!(messages[0].starts_with(r#"{"seq":1,"type":"response","request_seq":1,"success":true,"command":"initialize","body":{"supportsConfigurationDoneRequest":true,"#));
+/// assert_eq!(messages[1..], [
+///     r#"{"seq":2,"type":"event","event":"initialized"}"#,
+///     r#"{"seq":3,"type":"response","request_seq":2,"success":true,"command":"setBreakpoints","body":{"breakpoints":[{"verified":true,"line":3}]}}"#,
+///     r#"{"seq":4,"type":"response","request_seq":3,"success":true,"command":"launch"}"#,
+///     r#"{"seq":5,"type":"response","request_seq":4,"success":true,"command":"configurationDone"}"#,
+///     r#"{"seq":6,"type":"event","event":"stopped","body":{"reason":"breakpoint","threadId":1,"allThreadsStopped":true}}"#,
+///     r#"{"seq":7,"type":"response","request_seq":5,"success":true,"command":"scopes","body":{"scopes":[{"name":"Locals","variablesReference":1,"expensive":false},{"name":"Globals","variablesReference":2,"expensive":false}]}}"#,
+///     r#"{"seq":8,"type":"response","request_seq":6,"success":true,"command":"variables","body":{"variables":[{"name":"$a","value":"int(1)","type":"integer","variablesReference":0},{"name":"$b","value":"int(2)","type":"integer","variablesReference":0},{"name":"$sum","value":"NULL","type":"NULL","variablesReference":0}]}}"#,
+///     r#"{"seq":9,"type":"response","request_seq":7,"success":false,"command":"evaluate","message":"Undefined variable $nope"}"#,
+///     r#"{"seq":10,"type":"response","request_seq":8,"success":true,"command":"next"}"#,
+///     r#"{"seq":11,"type":"event","event":"stopped","body":{"reason":"step","threadId":1,"allThreadsStopped":true}}"#,
+///     r#"{"seq":12,"type":"response","request_seq":9,"success":true,"command":"continue","body":{"allThreadsContinued":true}}"#,
+///     r#"{"seq":13,"type":"event","event":"output","body":{"category":"stdout","output":"3"}}"#,
+///     r#"{"seq":14,"type":"event","event":"exited","body":{"exitCode":0}}"#,
+///     r#"{"seq":15,"type":"event","event":"terminated"}"#,
+///     r#"{"seq":16,"type":"response","request_seq":10,"success":true,"command":"disconnect"}"#,
+/// ]);
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn session(input: impl BufRead + 'static, output: impl Write + 'static) -> Result<(), String> {
+    let receiver = Receiver::new(input);
+    let sender = Sender::new(output);
+    let mut debugger = DapDebugger {
+        receiver: receiver.clone(),
+        sender: sender.clone(),
+        mode: Mode::Continue,
+        breakpoints: HashMap::new(),
+        exception_filters: default_filters(),
+        handles: Vec::new(),
+        exception: None,
+        stopped_before: false,
+    };
+
+    let mut launch = None;
+    let mut configured = false;
+    while launch.is_none() || !configured {
+        let Some(request) = receiver.next()? else {
+            return Ok(());
+        };
+        match command(&request) {
+            "initialize" => {
+                sender.respond(&request, Some(capabilities()), None);
+                sender.event("initialized", None);
+            }
+            "launch" => match path(&request, &["arguments", "program"]) {
+                Some(program) => {
+                    let stop_on_entry =
+                        path(&request, &["arguments", "stopOnEntry"]).is_some_and(Value::to_bool);
+                    launch = Some(Launch {
+                        program: program.to_string_val(),
+                        stop_on_entry,
+                    });
+                    sender.respond(&request, None, None);
+                }
+                None => sender.respond(&request, None, Some("No program to launch")),
+            },
+            "configurationDone" => {
+                configured = true;
+                sender.respond(&request, None, None);
+            }
+            "disconnect" | "terminate" => {
+                sender.respond(&request, None, None);
+                return Ok(());
+            }
+            _ => debugger.configure(&request),
+        }
+    }
+
+    let launch = launch.unwrap_or_else(|| unreachable!());
+    if launch.stop_on_entry {
+        debugger.mode = Mode::Step;
+    }
+    let exit_code = match run(&launch.program, debugger, sender.clone()) {
+        Ok(code) => code.unwrap_or(0),
+        Err(e) => {
+            sender.output("stderr", &format!("Error: {}\n", e));
+            1
+        }
+    };
+    sender.event(
+        "exited",
+        Some(object(vec![("exitCode", Value::Integer(exit_code as i64))])),
+    );
+    sender.event("terminated", None);
+
+    // Wait for the client to close the session
+    while let Some(request) = receiver.next()? {
+        sender.respond(&request, None, None);
+        if matches!(command(&request), "disconnect" | "terminate") {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Compile and run the launched script with the debugger attached
+fn run(program: &str, debugger: DapDebugger, sender: Sender) -> Result<Option<i32>, String> {
+    let source = fs::read_to_string(program)
+        .map_err(|e| format!("Error reading file '{}': {}", program, e))?;
+    let file_path = fs::canonicalize(program)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| program.to_string());
+
+    let compilation = crate::compile(&source, &file_path)?;
+
+    let mut vm = VM::new(OutputWriter(sender));
+    let main = vm.load(compilation);
+    vm.attach_debugger(Box::new(debugger));
+    Ok(crate::exit_status(vm.execute(main)).map_err(crate::Error::Runtime)?)
+}
+
+impl DapDebugger {
+    /// Handle requests that are valid both before launch and while paused
+    fn configure(&mut self, request: &Value) {
+        match command(request) {
+            "setBreakpoints" => {
+                let file = path(request, &["arguments", "source", "path"])
+                    .map(Value::to_string_val)
+                    .unwrap_or_default();
+                let lines: Vec<usize> = items(path(request, &["arguments", "breakpoints"]))
+                    .filter_map(|bp| field(bp, "line"))
+                    .map(|line| line.to_int() as usize)
+                    .collect();
+                let verified = lines
+                    .iter()
+                    .map(|line| {
+                        object(vec![
+                            ("verified", Value::Bool(true)),
+                            ("line", Value::Integer(*line as i64)),
+                        ])
+                    })
+                    .collect();
+                self.breakpoints.insert(file, lines);
+                let body = object(vec![("breakpoints", list(verified))]);
+                self.sender.respond(request, Some(body), None);
+            }
+            "setExceptionBreakpoints" => {
+                self.exception_filters = items(path(request, &["arguments", "filters"]))
+                    .map(Value::to_string_val)
+                    .collect();
+                self.sender.respond(request, None, None);
+            }
+            "threads" => {
+                let thread = object(vec![
+                    ("id", Value::Integer(THREAD_ID)),
+                    ("name", Value::String("main".to_string())),
+                ]);
+                let body = object(vec![("threads", list(vec![thread]))]);
+                self.sender.respond(request, Some(body), None);
+            }
+            name => {
+                let message = format!("Unsupported request: {}", name);
+                self.sender.respond(request, None, Some(&message));
+            }
+        }
+    }
+
+    fn breakpoint_hit(&self, context: &DebugContext) -> bool {
+        self.breakpoints
+            .iter()
+            .any(|(file, lines)| lines.contains(&context.line) && same_file(file, context.file()))
+    }
+
+    /// Report a stop and serve requests until execution resumes
+    fn pause(&mut self, context: &DebugContext, reason: &str) -> Result<(), VmError> {
+        self.stopped_before = true;
+        self.handles.clear();
+        let mut body = vec![
+            ("reason", Value::String(reason.to_string())),
+            ("threadId", Value::Integer(THREAD_ID)),
+            ("allThreadsStopped", Value::Bool(true)),
+        ];
+        if let Some(exception) = &self.exception {
+            body.push(("text", Value::String(exception.class.clone())));
+            body.push(("description", Value::String(exception.message.clone())));
+        }
+        self.sender.event("stopped", Some(object(body)));
+
+        loop {
+            let Some(request) = self.receiver.next().map_err(VmError::fatal)? else {
+                // The client went away: run to completion without stopping
+                self.mode = Mode::Continue;
+                self.breakpoints.clear();
+                self.exception_filters.clear();
+                return Ok(());
+            };
+            let mode = match command(&request) {
+                "continue" => Mode::Continue,
+                "next" => Mode::Next(context.depth()),
+                "stepIn" => Mode::Step,
+                "stepOut" => Mode::Finish(context.depth()),
+                "disconnect" | "terminate" => {
+                    self.sender.respond(&request, None, None);
+                    return Err(VmError::Exit(0));
+                }
+                _ => {
+                    self.inspect(&request, context);
+                    continue;
+                }
+            };
+            self.mode = mode;
+            self.exception = None;
+            let body = matches!(mode, Mode::Continue)
+                .then(|| object(vec![("allThreadsContinued", Value::Bool(true))]));
+            self.sender.respond(&request, body, None);
+            return Ok(());
+        }
+    }
+}
+
+impl DebugHook for DapDebugger {
+    fn statement(&mut self, context: &DebugContext) -> Result<(), VmError> {
+        let reason = if self.breakpoint_hit(context) {
+            "breakpoint"
+        } else {
+            let step = match self.mode {
+                Mode::Continue => false,
+                Mode::Step => true,
+                Mode::Next(depth) => context.depth() <= depth,
+                Mode::Finish(depth) => context.depth() < depth,
+            };
+            match (step, self.stopped_before) {
+                (false, _) => return Ok(()),
+                (true, false) => "entry",
+                (true, true) => "step",
+            }
+        };
+        self.pause(context, reason)
+    }
+
+    fn exception(
+        &mut self,
+        context: &DebugContext,
+        exception: &Value,
+        uncaught: bool,
+    ) -> Result<(), VmError> {
+        let filter = if uncaught { "uncaught" } else { "all" };
+        let enabled = self
+            .exception_filters
+            .iter()
+            .any(|f| f == filter || f == "all");
+        if !enabled {
+            return Ok(());
+        }
+        let (class, message) = match exception {
+            Value::Object(object) => (
+                object.class_name().to_string(),
+                object
+                    .get("message")
+                    .as_ref()
+                    .map(Value::to_string_val)
+                    .unwrap_or_default(),
+            ),
+            other => (other.get_type().to_string(), other.to_string_val()),
+        };
+        self.exception = Some(StoppedException {
+            class,
+            message,
+            uncaught,
+        });
+        self.pause(context, "exception")
+    }
+}
+
+fn command(request: &Value) -> &str {
+    match field(request, "command") {
+        Some(Value::String(command)) => command,
+        _ => "",
+    }
+}
+
+fn default_filters() -> Vec<String> {
+    EXCEPTION_FILTERS
+        .iter()
+        .filter(|(_, _, default)| *default)
+        .map(|(filter, _, _)| filter.to_string())
+        .collect()
+}
+
+fn capabilities() -> Value {
+    let filters = EXCEPTION_FILTERS
+        .iter()
+        .map(|(filter, label, default)| {
+            object(vec![
+                ("filter", Value::String(filter.to_string())),
+                ("label", Value::String(label.to_string())),
+                ("default", Value::Bool(*default)),
+            ])
+        })
+        .collect();
+    object(vec![
+        ("supportsConfigurationDoneRequest", Value::Bool(true)),
+        ("supportsEvaluateForHovers", Value::Bool(true)),
+        ("supportsExceptionInfoRequest", Value::Bool(true)),
+        ("supportsTerminateRequest", Value::Bool(true)),
+        ("exceptionBreakpointFilters", list(filters)),
+    ])
+}
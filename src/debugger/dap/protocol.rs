@@ -0,0 +1,196 @@
+//! DAP wire format: Content-Length framed JSON messages
+//!
+//! Messages are decoded into PHP arrays with the runtime's JSON functions,
+//! so requests are read with `field` and bodies are built with `object`.
+
+use std::cell::{Cell, RefCell};
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use crate::runtime::builtins::{json_decode, json_encode};
+use crate::runtime::{ArrayKey, Value};
+
+/// Read one message; Ok(None) at end of input
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    let json = Value::String(String::from_utf8_lossy(&body).into_owned());
+    Ok(Some(json_decode(&[json]).unwrap_or(Value::Null)))
+}
+
+/// Reads requests from the client
+///
+/// Clones share the input, so the session and the debugger attached to
+/// the script take turns reading it.
+#[derive(Clone)]
+pub struct Receiver {
+    input: Rc<RefCell<Box<dyn BufRead>>>,
+}
+
+impl Receiver {
+    pub fn new(input: impl BufRead + 'static) -> Self {
+        Self {
+            input: Rc::new(RefCell::new(Box::new(input))),
+        }
+    }
+
+    /// The next request; Ok(None) once the client closed the input
+    pub fn next(&self) -> Result<Option<Value>, String> {
+        read_message(&mut *self.input.borrow_mut()).map_err(|e| format!("DAP input error: {}", e))
+    }
+}
+
+/// Writes framed messages to the client, numbering them in order
+///
+/// Clones share the output and the sequence counter, so script output
+/// and debugger events can be sent from different places.
+#[derive(Clone)]
+pub struct Sender {
+    seq: Rc<Cell<i64>>,
+    output: Rc<RefCell<Box<dyn Write>>>,
+}
+
+impl Sender {
+    pub fn new(output: impl Write + 'static) -> Self {
+        Self {
+            seq: Rc::default(),
+            output: Rc::new(RefCell::new(Box::new(output))),
+        }
+    }
+
+    fn send(&self, kind: &str, mut fields: Vec<(&str, Value)>) {
+        self.seq.set(self.seq.get() + 1);
+        fields.insert(0, ("seq", Value::Integer(self.seq.get())));
+        fields.insert(1, ("type", Value::String(kind.to_string())));
+        let Ok(Value::String(json)) = json_encode(&[object(fields)]) else {
+            return;
+        };
+        let mut output = self.output.borrow_mut();
+        let _ = write!(output, "Content-Length: {}\r\n\r\n{}", json.len(), json);
+        let _ = output.flush();
+    }
+
+    /// Answer `request`, successfully unless `error` is given
+    pub fn respond(&self, request: &Value, body: Option<Value>, error: Option<&str>) {
+        let mut fields = vec![
+            (
+                "request_seq",
+                field(request, "seq").cloned().unwrap_or(Value::Null),
+            ),
+            ("success", Value::Bool(error.is_none())),
+            (
+                "command",
+                field(request, "command").cloned().unwrap_or(Value::Null),
+            ),
+        ];
+        if let Some(message) = error {
+            fields.push(("message", Value::String(message.to_string())));
+        }
+        if let Some(body) = body {
+            fields.push(("body", body));
+        }
+        self.send("response", fields);
+    }
+
+    pub fn event(&self, event: &str, body: Option<Value>) {
+        let mut fields = vec![("event", Value::String(event.to_string()))];
+        if let Some(body) = body {
+            fields.push(("body", body));
+        }
+        self.send("event", fields);
+    }
+
+    /// Send text to the client's debug console
+    pub fn output(&self, category: &str, text: &str) {
+        self.event(
+            "output",
+            Some(object(vec![
+                ("category", Value::String(category.to_string())),
+                ("output", Value::String(text.to_string())),
+            ])),
+        );
+    }
+}
+
+/// Script output, forwarded as `output` events
+pub struct OutputWriter(pub Sender);
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.0.output("stdout", &String::from_utf8_lossy(buf));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Build a JSON object from named fields
+pub fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Array(
+        fields
+            .into_iter()
+            .map(|(name, value)| (ArrayKey::String(name.to_string()), value))
+            .collect(),
+    )
+}
+
+/// Build a JSON array
+pub fn list(items: Vec<Value>) -> Value {
+    Value::Array(
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (ArrayKey::Integer(i as i64), value))
+            .collect(),
+    )
+}
+
+/// Look up a field of a decoded JSON object
+pub fn field<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    match value {
+        Value::Array(fields) => fields
+            .iter()
+            .find(|(key, _)| matches!(key, ArrayKey::String(k) if k == name))
+            .map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+/// Look up a nested field, e.g. `path(request, &["arguments", "source", "path"])`
+pub fn path<'a>(value: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names
+        .iter()
+        .try_fold(value, |value, name| field(value, name))
+}
+
+/// Items of a decoded JSON array (empty for anything else)
+pub fn items(value: Option<&Value>) -> impl Iterator<Item = &Value> {
+    let items = match value {
+        Some(Value::Array(items)) => items.as_slice(),
+        _ => &[],
+    };
+    items.iter().map(|(_, value)| value)
+}
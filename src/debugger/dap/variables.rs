@@ -0,0 +1,184 @@
+//! Answers to the requests that inspect a paused program: stack frames,
+//! scopes, variables, evaluation and exception details
+
+use super::protocol::{list, object, path};
+use super::{command, DapDebugger, Handle};
+use crate::debugger::summary;
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::debug::DebugContext;
+
+impl DapDebugger {
+    /// Answer a request about the paused program
+    pub(super) fn inspect(&mut self, request: &Value, context: &DebugContext) {
+        let frame = path(request, &["arguments", "frameId"])
+            .map(|id| id.to_int() as usize)
+            .and_then(|id| id.checked_sub(1))
+            .filter(|index| *index < context.frames.len())
+            .unwrap_or(context.frames.len() - 1);
+        let body = match command(request) {
+            "stackTrace" => stack_trace(context),
+            "scopes" => {
+                let mut scopes = Vec::new();
+                if frame > 0 {
+                    scopes.push(self.scope("Locals", context.variables(frame)));
+                }
+                scopes.push(self.scope("Globals", context.variables(0)));
+                object(vec![("scopes", list(scopes))])
+            }
+            "variables" => {
+                let reference = path(request, &["arguments", "variablesReference"])
+                    .map(|r| r.to_int() as usize)
+                    .unwrap_or(0);
+                let variables = self.children(reference);
+                object(vec![("variables", list(variables))])
+            }
+            "evaluate" => {
+                let expression = path(request, &["arguments", "expression"])
+                    .map(Value::to_string_val)
+                    .unwrap_or_default();
+                let name = expression.trim().trim_start_matches('$');
+                let Some((_, value)) = context
+                    .variables(frame)
+                    .into_iter()
+                    .find(|(n, _)| n == name)
+                else {
+                    let message = format!("Undefined variable ${}", name);
+                    self.sender.respond(request, None, Some(&message));
+                    return;
+                };
+                object(vec![
+                    ("result", Value::String(display(&value))),
+                    ("type", Value::String(value.get_type().to_string())),
+                    ("variablesReference", Value::Integer(self.reference(&value))),
+                ])
+            }
+            "exceptionInfo" => {
+                let Some(exception) = &self.exception else {
+                    self.sender
+                        .respond(request, None, Some("Not stopped at an exception"));
+                    return;
+                };
+                let mode = if exception.uncaught {
+                    "unhandled"
+                } else {
+                    "always"
+                };
+                object(vec![
+                    ("exceptionId", Value::String(exception.class.clone())),
+                    ("description", Value::String(exception.message.clone())),
+                    ("breakMode", Value::String(mode.to_string())),
+                ])
+            }
+            "pause" => {
+                self.sender.respond(request, None, None);
+                return;
+            }
+            _ => return self.configure(request),
+        };
+        self.sender.respond(request, Some(body), None);
+    }
+
+    fn scope(&mut self, name: &str, variables: Vec<(String, Value)>) -> Value {
+        self.handles.push(Handle::Scope(variables));
+        object(vec![
+            ("name", Value::String(name.to_string())),
+            (
+                "variablesReference",
+                Value::Integer(self.handles.len() as i64),
+            ),
+            ("expensive", Value::Bool(false)),
+        ])
+    }
+
+    /// A reference for expanding arrays and objects, 0 for scalars
+    fn reference(&mut self, value: &Value) -> i64 {
+        match value {
+            Value::Array(items) if !items.is_empty() => {}
+            Value::Object(object) if !object.lock().properties.is_empty() => {}
+            _ => return 0,
+        }
+        self.handles.push(Handle::Value(value.clone()));
+        self.handles.len() as i64
+    }
+
+    /// Entries of a scope, array or object as DAP variables
+    fn children(&mut self, reference: usize) -> Vec<Value> {
+        let entries: Vec<(String, Value)> =
+            match reference.checked_sub(1).and_then(|i| self.handles.get(i)) {
+                Some(Handle::Scope(variables)) => variables
+                    .iter()
+                    .map(|(name, value)| (format!("${}", name), value.clone()))
+                    .collect(),
+                Some(Handle::Value(Value::Array(items))) => items
+                    .iter()
+                    .map(|(key, value)| (array_key(key), value.clone()))
+                    .collect(),
+                Some(Handle::Value(Value::Object(object))) => {
+                    let mut properties: Vec<(String, Value)> = object
+                        .properties()
+                        .into_iter()
+                        .filter(|(name, _)| !name.starts_with("__"))
+                        .collect();
+                    properties.sort_by(|a, b| a.0.cmp(&b.0));
+                    properties
+                }
+                _ => Vec::new(),
+            };
+        entries
+            .into_iter()
+            .map(|(name, value)| {
+                object(vec![
+                    ("name", Value::String(name)),
+                    ("value", Value::String(display(&value))),
+                    ("type", Value::String(value.get_type().to_string())),
+                    ("variablesReference", Value::Integer(self.reference(&value))),
+                ])
+            })
+            .collect()
+    }
+}
+
+/// Call stack, innermost frame first; frame ids are stack indices + 1
+fn stack_trace(context: &DebugContext) -> Value {
+    let frames: Vec<Value> = (0..context.frames.len())
+        .rev()
+        .map(|index| {
+            let function = &context.frames[index].function;
+            let name = function.file.rsplit('/').next().unwrap_or_default();
+            let source = object(vec![
+                ("name", Value::String(name.to_string())),
+                ("path", Value::String(function.file.clone())),
+            ]);
+            object(vec![
+                ("id", Value::Integer(index as i64 + 1)),
+                ("name", Value::String(function.name.clone())),
+                ("source", source),
+                (
+                    "line",
+                    Value::Integer(context.frame_line(index).unwrap_or(0) as i64),
+                ),
+                ("column", Value::Integer(1)),
+            ])
+        })
+        .collect();
+    let total = frames.len() as i64;
+    object(vec![
+        ("stackFrames", list(frames)),
+        ("totalFrames", Value::Integer(total)),
+    ])
+}
+
+fn array_key(key: &ArrayKey) -> String {
+    match key {
+        ArrayKey::Integer(n) => n.to_string(),
+        ArrayKey::String(s) => s.clone(),
+    }
+}
+
+/// Value text for the variables view
+fn display(value: &Value) -> String {
+    match value {
+        Value::Object(object) => object.class_name().to_string(),
+        _ => summary(value),
+    }
+}
@@ -0,0 +1,395 @@
+//! Interactive console debugger for `vhp debug`
+//!
+//! Implements `DebugHook`: execution pauses at the first statement, at
+//! breakpoints and after step commands, then reads commands from stdin.
+//! The `dap` module serves the same hook to editors over the Debug Adapter
+//! Protocol.
+
+mod commands;
+pub mod dap;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use crate::runtime::builtins::output::var_dump;
+use crate::runtime::Value;
+use crate::vm::debug::{DebugContext, DebugHook};
+use crate::vm::error::VmError;
+use commands::{Command, HELP};
+
+/// Lines of source shown above and below the current line by `list`
+const LIST_CONTEXT: usize = 5;
+
+/// When execution should pause next
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// Only at breakpoints
+    Continue,
+    /// At the next statement
+    Step,
+    /// At the next statement at or above this call depth
+    Next(usize),
+    /// At the next statement above this call depth
+    Finish(usize),
+}
+
+struct Breakpoint {
+    file: String,
+    line: usize,
+}
+
+pub struct ConsoleDebugger {
+    /// Where commands come from, or None for stdin
+    input: Option<Box<dyn BufRead>>,
+    output: Box<dyn Write>,
+    mode: Mode,
+    breakpoints: Vec<Option<Breakpoint>>,
+    /// Frame selected for inspection, counted from the innermost (0)
+    selected: usize,
+    last_command: Option<Command>,
+    sources: HashMap<String, Vec<String>>,
+}
+
+impl Default for ConsoleDebugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsoleDebugger {
+    /// Create a debugger that pauses at the first statement
+    pub fn new() -> Self {
+        Self {
+            input: None,
+            output: Box::new(io::stdout()),
+            mode: Mode::Step,
+            breakpoints: Vec::new(),
+            selected: 0,
+            last_command: None,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Create a debugger reading commands from `input` and writing to
+    /// `output` instead of the terminal
+    ///
+    /// ```
+    /// use vhp::debugger::ConsoleDebugger;
+    ///
+    /// let dir = std::env::temp_dir().join(format!("vhp-debugger-doc-{}", std::process::id()));
+    /// std::fs::create_dir_all(&dir).unwrap();
+    /// let script = dir.join("add.php");
+    /// let source = "<?php\nfunction add($a, $b) {\n    $sum = $a + $b;\n    return $sum;\n}\n$total = add(1, 2);\necho $total;\n";
+    /// std::fs::write(&script, source).unwrap();
+    /// let path = script.to_string_lossy().into_owned();
+    ///
+    /// let commands = "break 3\nc\np $a\nbt\n\nlocals\nup\nlist\nnope\nfinish\nc\n";
+    /// let transcript = std::fs::File::create(dir.join("transcript")).unwrap();
+    /// let debugger = ConsoleDebugger::with_io(commands.as_bytes(), transcript);
+    ///
+    /// let mut output = Vec::new();
+    /// vhp::exit_status({
+    ///     let mut vm = vhp::vm::VM::new(&mut output);
+    ///     let main = vm.load(vhp::compile(source, &path).unwrap());
+    ///     vm.attach_debugger(Box::new(debugger));
+    ///     vm.execute(main)
+    /// })
+    /// .unwrap();
+    /// assert_eq!(output, b"3");
+    ///
+    /// let transcript = std::fs::read_to_string(dir.join("transcript")).unwrap();
+    /// assert_eq!(transcript.replace(&path, "add.php"), "\
+    /// ##0 <main> at add.php:6
+    ///    6 $total = add(1, 2);
+    /// (vhpdb) Breakpoint 1 at add.php:3
+    /// (vhpdb) Breakpoint 1 hit
+    /// ##0 add at add.php:3
+    ///    3     $sum = $a + $b;
+    /// (vhpdb) int(1)
+    /// (vhpdb) *#0 add at add.php:3
+    ///  #1 <main> at add.php:6
+    /// (vhpdb) *#0 add at add.php:3
+    ///  #1 <main> at add.php:6
+    /// (vhpdb) $a = int(1)
+    /// $b = int(2)
+    /// $sum = NULL
+    /// (vhpdb) #1 <main> at add.php:6
+    /// (vhpdb)       1 <?php
+    ///       2 function add($a, $b) {
+    ///       3     $sum = $a + $b;
+    ///       4     return $sum;
+    ///       5 }
+    /// ->    6 $total = add(1, 2);
+    ///       7 echo $total;
+    /// (vhpdb) Unknown command: nope (type 'help')
+    /// (vhpdb) #0 <main> at add.php:7
+    ///    7 echo $total;
+    /// (vhpdb) ");
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn with_io(input: impl BufRead + 'static, output: impl Write + 'static) -> Self {
+        Self {
+            input: Some(Box::new(input)),
+            output: Box::new(output),
+            ..Self::new()
+        }
+    }
+
+    fn should_pause(&self, context: &DebugContext) -> Option<String> {
+        let file = context.file();
+        let hit = self.breakpoints.iter().enumerate().find(|(_, bp)| {
+            bp.as_ref()
+                .is_some_and(|bp| bp.line == context.line && same_file(&bp.file, file))
+        });
+        if let Some((number, _)) = hit {
+            return Some(format!("Breakpoint {} hit", number + 1));
+        }
+        let pause = match self.mode {
+            Mode::Continue => false,
+            Mode::Step => true,
+            Mode::Next(depth) => context.depth() <= depth,
+            Mode::Finish(depth) => context.depth() < depth,
+        };
+        pause.then(String::new)
+    }
+
+    /// Handle one command; returns false when execution should resume
+    fn execute(&mut self, command: &Command, context: &DebugContext) -> Result<bool, VmError> {
+        let frame = context.frames.len() - 1 - self.selected;
+        match command {
+            Command::Continue => self.mode = Mode::Continue,
+            Command::Step => self.mode = Mode::Step,
+            Command::Next => self.mode = Mode::Next(context.depth()),
+            Command::Finish => self.mode = Mode::Finish(context.depth()),
+            Command::Break(file, line) => {
+                let file = file
+                    .clone()
+                    .unwrap_or_else(|| context.frames[frame].function.file.clone());
+                let _ = writeln!(
+                    self.output,
+                    "Breakpoint {} at {}:{}",
+                    self.breakpoints.len() + 1,
+                    file,
+                    line
+                );
+                self.breakpoints
+                    .push(Some(Breakpoint { file, line: *line }));
+                return Ok(true);
+            }
+            Command::Delete(number) => {
+                match number
+                    .checked_sub(1)
+                    .and_then(|i| self.breakpoints.get_mut(i))
+                {
+                    Some(bp @ Some(_)) => *bp = None,
+                    _ => {
+                        let _ = writeln!(self.output, "No breakpoint number {}", number);
+                    }
+                }
+                return Ok(true);
+            }
+            Command::Breakpoints => {
+                for (i, bp) in self.breakpoints.iter().enumerate() {
+                    if let Some(bp) = bp {
+                        let _ = writeln!(self.output, "{}  {}:{}", i + 1, bp.file, bp.line);
+                    }
+                }
+                return Ok(true);
+            }
+            Command::Print(name) => {
+                let _ = match context
+                    .variables(frame)
+                    .into_iter()
+                    .find(|(n, _)| n == name)
+                {
+                    Some((_, value)) => write!(self.output, "{}", dump(&value)),
+                    None => writeln!(self.output, "Undefined variable ${}", name),
+                };
+                return Ok(true);
+            }
+            Command::Locals => {
+                print_variables(&mut self.output, &context.variables(frame));
+                return Ok(true);
+            }
+            Command::Globals => {
+                print_variables(&mut self.output, &context.variables(0));
+                return Ok(true);
+            }
+            Command::Backtrace => {
+                self.print_backtrace(context);
+                return Ok(true);
+            }
+            Command::Frame(n) => return self.select(context, *n).map(|_| true),
+            Command::Up => return self.select(context, self.selected + 1).map(|_| true),
+            Command::Down => {
+                let n = self
+                    .selected
+                    .checked_sub(1)
+                    .ok_or_else(|| VmError::fatal("Already at the innermost frame"))?;
+                return self.select(context, n).map(|_| true);
+            }
+            Command::List => {
+                self.list(context, frame);
+                return Ok(true);
+            }
+            Command::Help => {
+                let _ = writeln!(self.output, "{}", HELP);
+                return Ok(true);
+            }
+            Command::Quit => return Err(VmError::Exit(0)),
+        }
+        Ok(false)
+    }
+
+    fn select(&mut self, context: &DebugContext, n: usize) -> Result<(), VmError> {
+        if n >= context.frames.len() {
+            return Err(VmError::fatal(format!("No frame {}", n)));
+        }
+        self.selected = n;
+        self.print_frame(context, n);
+        Ok(())
+    }
+
+    fn print_backtrace(&mut self, context: &DebugContext) {
+        for n in 0..context.frames.len() {
+            let marker = if n == self.selected { '*' } else { ' ' };
+            let _ = write!(self.output, "{}", marker);
+            self.print_frame(context, n);
+        }
+    }
+
+    fn print_frame(&mut self, context: &DebugContext, n: usize) {
+        let index = context.frames.len() - 1 - n;
+        let function = &context.frames[index].function;
+        let line = context.frame_line(index).unwrap_or(0);
+        let _ = writeln!(
+            self.output,
+            "#{} {} at {}:{}",
+            n, function.name, function.file, line
+        );
+    }
+
+    /// Source lines of a file, read once and cached
+    fn source(&mut self, file: &str) -> &[String] {
+        self.sources
+            .entry(file.to_string())
+            .or_insert_with(|| match fs::read_to_string(file) {
+                Ok(source) => source.lines().map(String::from).collect(),
+                Err(_) => Vec::new(),
+            })
+    }
+
+    fn print_source_line(&mut self, file: &str, line: usize) {
+        if let Some(text) = line.checked_sub(1).and_then(|i| self.source(file).get(i)) {
+            let text = format!("{:4} {}", line, text);
+            let _ = writeln!(self.output, "{}", text);
+        }
+    }
+
+    fn list(&mut self, context: &DebugContext, frame: usize) {
+        let file = context.frames[frame].function.file.clone();
+        let current = context.frame_line(frame).unwrap_or(1);
+        let lines = self.source(&file);
+        if lines.is_empty() {
+            let _ = writeln!(self.output, "Source of {} is not available", file);
+            return;
+        }
+        let first = current.saturating_sub(LIST_CONTEXT).max(1);
+        let last = (current + LIST_CONTEXT).min(lines.len());
+        let listing: Vec<String> = (first..=last)
+            .map(|number| {
+                let marker = if number == current { "->" } else { "  " };
+                format!("{} {:4} {}", marker, number, lines[number - 1])
+            })
+            .collect();
+        for line in listing {
+            let _ = writeln!(self.output, "{}", line);
+        }
+    }
+
+    fn read_command(&mut self) -> Option<Result<Command, String>> {
+        let _ = write!(self.output, "(vhpdb) ");
+        let _ = self.output.flush();
+        let mut input = String::new();
+        let read = match &mut self.input {
+            Some(commands) => commands.read_line(&mut input),
+            None => io::stdin().lock().read_line(&mut input),
+        };
+        match read {
+            Ok(0) | Err(_) => None,
+            Ok(_) if input.trim().is_empty() => self.last_command.clone().map(Ok),
+            Ok(_) => Some(commands::parse(input.trim())),
+        }
+    }
+}
+
+impl DebugHook for ConsoleDebugger {
+    fn statement(&mut self, context: &DebugContext) -> Result<(), VmError> {
+        let Some(reason) = self.should_pause(context) else {
+            return Ok(());
+        };
+        // Show the script's output so far before the prompt
+        let _ = io::stdout().flush();
+        if !reason.is_empty() {
+            let _ = writeln!(self.output, "{}", reason);
+        }
+        self.selected = 0;
+        self.print_frame(context, 0);
+        self.print_source_line(context.file(), context.line);
+
+        loop {
+            let command = match self.read_command() {
+                Some(Ok(command)) => command,
+                Some(Err(e)) => {
+                    let _ = writeln!(self.output, "{}", e);
+                    continue;
+                }
+                None => {
+                    // End of input: run to completion without stopping
+                    self.mode = Mode::Continue;
+                    self.breakpoints.clear();
+                    return Ok(());
+                }
+            };
+            self.last_command = Some(command.clone());
+            match self.execute(&command, context) {
+                Ok(true) => {}
+                Ok(false) => return Ok(()),
+                Err(e @ VmError::Exit(_)) => return Err(e),
+                Err(e) => {
+                    let _ = writeln!(self.output, "{}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Breakpoint files match by full path or by trailing path components
+fn same_file(breakpoint: &str, file: &str) -> bool {
+    file == breakpoint || file.ends_with(&format!("/{}", breakpoint))
+}
+
+fn dump(value: &Value) -> String {
+    let mut out = Vec::new();
+    let _ = var_dump(&mut out, std::slice::from_ref(value));
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// One-line summary of a value for variable listings
+fn summary(value: &Value) -> String {
+    match value {
+        Value::Array(items) => format!("array({})", items.len()),
+        Value::String(s) => format!("string({}) {:?}", s.len(), s),
+        _ => dump(value).lines().next().unwrap_or_default().to_string(),
+    }
+}
+
+fn print_variables(out: &mut impl Write, variables: &[(String, Value)]) {
+    if variables.is_empty() {
+        let _ = writeln!(out, "No variables");
+    }
+    for (name, value) in variables {
+        let _ = writeln!(out, "${} = {}", name, summary(value));
+    }
+}
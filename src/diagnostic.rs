@@ -0,0 +1,379 @@
+//! Structured error reports
+//!
+//! A `Diagnostic` is an error with a code naming its kind, the place in
+//! the source it points at, labels for that place and optional help.
+//! Every stage's error converts into one: `LexError`, `ParseError`,
+//! `CompileError`, `RuntimeError` and `vhp::Error`. Their `Display` is the
+//! diagnostic's one-line form ("message at line L, column C"), and
+//! `render` gives the full report with a source excerpt, and the calls
+//! in progress for runtime errors:
+//!
+//! ```
+//! use vhp::diagnostic::Diagnostic;
+//!
+//! let source = "<?php\necho 1 +;\n";
+//! let Err(error) = vhp::compile(source, "a.php") else { unreachable!() };
+//! let report = Diagnostic::from(&error).with_source("a.php", source).render();
+//! assert!(report.starts_with("Error[syntax]: "));
+//! assert!(report.contains(" --> a.php:2:"));
+//! assert!(report.contains("2 | echo 1 +;"));
+//! ```
+//!
+//! `render_colored` gives the same report with ANSI colors, for
+//! terminals; `use_color` tells whether stderr wants them.
+
+use std::fmt;
+use std::io::IsTerminal;
+
+/// Code of lexer and parser errors
+pub const SYNTAX: &str = "syntax";
+/// Code of errors the compiler finds in parsed code
+pub const COMPILE: &str = "compile";
+/// Code of errors raised while running a script
+pub const RUNTIME: &str = "runtime";
+
+/// Prefix the runners put before runtime errors
+const RUNTIME_PREFIX: &str = "VM error: ";
+
+/// Advice for calls to undefined functions
+pub(crate) const UNDEFINED_FUNCTION_HELP: &str =
+    "check the spelling, or require the file that declares the function";
+/// Advice for a time limit running out
+pub(crate) const TIME_LIMIT_HELP: &str =
+    "raise the limit with set_time_limit() or --max-execution-time";
+/// Advice for calls to functions the sandbox disables
+pub(crate) const DISABLED_FUNCTION_HELP: &str = "the function is listed in disable_functions";
+
+/// Longest stack shown in full; longer ones are cut in the middle
+const MAX_STACK_LINES: usize = 10;
+
+/// Calls shown from the bottom of a stack that was cut
+const STACK_TAIL_LINES: usize = 2;
+
+/// ANSI styles of the parts of a report
+const STYLE_ERROR: &str = "1;31";
+const STYLE_MESSAGE: &str = "1";
+const STYLE_GUTTER: &str = "1;34";
+const STYLE_NOTE: &str = "1;36";
+const STYLE_STACK: &str = "2";
+
+/// A place in the source: 1-based line and column (0 when only the line
+/// is known) and the number of characters covered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self {
+            line,
+            column,
+            len: 1,
+        }
+    }
+
+    /// The whole of `line`
+    pub fn line(line: usize) -> Self {
+        Self::new(line, 0)
+    }
+}
+
+/// Text shown under a span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub text: String,
+}
+
+/// An error with its code, location, labels and help
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Kind of error (`SYNTAX`, `RUNTIME`), if known
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// Where the error is, if known
+    pub span: Option<Span>,
+    /// Labels; the first one is shown at `span`
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+    /// Calls in progress, innermost first, as shown
+    pub stack: Vec<String>,
+    /// File and source the span refers to, for rendering
+    source: Option<Box<(String, String)>>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            code: None,
+            message: message.into(),
+            span: None,
+            labels: Vec::new(),
+            help: None,
+            stack: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// A syntax error at `line`, `column`
+    pub fn syntax(message: impl Into<String>, line: usize, column: usize) -> Self {
+        Self::new(message)
+            .with_code(SYNTAX)
+            .with_span(Span::new(line, column))
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Label the diagnostic's own span
+    pub fn with_label(mut self, text: impl Into<String>) -> Self {
+        if let Some(span) = self.span {
+            self.labels.push(Label {
+                span,
+                text: text.into(),
+            });
+        }
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_stack(mut self, stack: Vec<String>) -> Self {
+        self.stack = stack;
+        self
+    }
+
+    /// Attach the source the span points into, so `render` can quote it
+    pub fn with_source(mut self, file: &str, source: &str) -> Self {
+        self.source = Some(Box::new((file.to_string(), source.to_string())));
+        self
+    }
+
+    /// Recover a diagnostic from an error that has been turned into a
+    /// string in the one-line form
+    ///
+    /// Syntax errors end in "at line L, column C" (or "at line L");
+    /// runtime errors start with "VM error: ". Anything else becomes a
+    /// diagnostic with just a message.
+    pub fn from_error(error: &str) -> Self {
+        if let Some(message) = error.strip_prefix(RUNTIME_PREFIX) {
+            let diagnostic = Self::new(message).with_code(RUNTIME);
+            return match runtime_help(message) {
+                Some(help) => diagnostic.with_help(help),
+                None => diagnostic,
+            };
+        }
+        match split_location(error) {
+            Some((message, span, label)) => {
+                let diagnostic = Self::syntax(message, span.line, span.column);
+                match label {
+                    Some(label) => diagnostic.with_label(label),
+                    None => diagnostic,
+                }
+            }
+            None => Self::new(error),
+        }
+    }
+
+    /// The full report: a header, then the location and an excerpt of the
+    /// source with the span underlined when both are known, then the stack
+    /// and help
+    pub fn render(&self) -> String {
+        self.render_with(Palette { color: false })
+    }
+
+    /// The full report with ANSI colors
+    pub fn render_colored(&self) -> String {
+        self.render_with(Palette { color: true })
+    }
+
+    fn render_with(&self, palette: Palette) -> String {
+        let header = match self.code {
+            Some(code) => format!("Error[{}]", code),
+            None => "Error".to_string(),
+        };
+        let mut out = format!(
+            "{}{}\n",
+            palette.paint(STYLE_ERROR, &header),
+            palette.paint(STYLE_MESSAGE, &format!(": {}", self.message))
+        );
+        let location = self.span.zip(self.source.as_deref());
+        let number = location.map_or(String::new(), |(span, _)| span.line.to_string());
+        let gutter = " ".repeat(number.len().max(1));
+        let bar = palette.paint(STYLE_GUTTER, &format!("{} |", gutter));
+
+        if let Some((span, (file, source))) = location {
+            let arrow = palette.paint(STYLE_GUTTER, &format!("{}-->", gutter));
+            match span.column {
+                0 => out.push_str(&format!("{} {}:{}\n", arrow, file, span.line)),
+                column => out.push_str(&format!("{} {}:{}:{}\n", arrow, file, span.line, column)),
+            }
+            if let Some(text) = source.lines().nth(span.line.wrapping_sub(1)) {
+                let text = text.trim_end();
+                let numbered = palette.paint(STYLE_GUTTER, &format!("{} |", number));
+                out.push_str(&format!("{}\n", bar));
+                out.push_str(&format!("{} {}\n", numbered, text));
+                let (start, len) = underline(text, span);
+                let label = self.labels.first().map(|l| l.text.as_str()).unwrap_or("");
+                let marks = format!("{} {}", "^".repeat(len), label);
+                out.push_str(&format!(
+                    "{} {}{}\n",
+                    bar,
+                    " ".repeat(start),
+                    palette.paint(STYLE_ERROR, marks.trim_end())
+                ));
+            }
+        }
+        if self.stack.len() > 1 {
+            if location.is_some() {
+                out.push_str(&format!("{}\n", bar));
+            }
+            let label = palette.paint(STYLE_NOTE, "= stack:");
+            out.push_str(&format!("{} {}\n", gutter, label));
+            for line in condense_stack(&self.stack) {
+                out.push_str(&format!(
+                    "{}     {}\n",
+                    gutter,
+                    palette.paint(STYLE_STACK, &line)
+                ));
+            }
+        }
+        if let Some(help) = &self.help {
+            if location.is_some() {
+                out.push_str(&format!("{}\n", bar));
+            }
+            let label = palette.paint(STYLE_NOTE, "= help:");
+            out.push_str(&format!("{} {} {}\n", gutter, label, help));
+        }
+        out
+    }
+}
+
+/// Whether reports written to stderr should be colored: it is a
+/// terminal and `NO_COLOR` isn't set
+pub fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && std::io::stderr().is_terminal()
+}
+
+/// Whether and how a report is colored
+#[derive(Debug, Clone, Copy)]
+struct Palette {
+    color: bool,
+}
+
+impl Palette {
+    fn paint(self, style: &str, text: &str) -> String {
+        match self.color {
+            true => format!("\x1b[{}m{}\x1b[0m", style, text),
+            false => text.to_string(),
+        }
+    }
+}
+
+/// The lines of a stack, with runs of the same call (recursion) merged
+/// and the middle of a deep stack left out
+fn condense_stack(stack: &[String]) -> Vec<String> {
+    let mut runs: Vec<(&str, usize)> = Vec::new();
+    for call in stack {
+        match runs.last_mut() {
+            Some((last, count)) if *last == call => *count += 1,
+            _ => runs.push((call, 1)),
+        }
+    }
+    let mut lines: Vec<String> = runs
+        .into_iter()
+        .map(|(call, count)| match count {
+            1 => call.to_string(),
+            _ => format!("{} ({} times)", call, count),
+        })
+        .collect();
+    if lines.len() > MAX_STACK_LINES {
+        let head = MAX_STACK_LINES - STACK_TAIL_LINES - 1;
+        let hidden = lines.len() - head - STACK_TAIL_LINES;
+        lines.splice(head..head + hidden, [format!("... {} more calls", hidden)]);
+    }
+    lines
+}
+
+/// The one-line form: message, location and the first label
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        match self.span {
+            Some(Span {
+                line, column: 0, ..
+            }) => write!(f, " at line {}", line)?,
+            Some(Span { line, column, .. }) => write!(f, " at line {}, column {}", line, column)?,
+            None => {}
+        }
+        if let Some(label) = self.labels.first() {
+            write!(f, " ({})", label.text)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Diagnostic> for String {
+    fn from(diagnostic: Diagnostic) -> Self {
+        diagnostic.to_string()
+    }
+}
+
+impl From<String> for Diagnostic {
+    fn from(error: String) -> Self {
+        Self::from_error(&error)
+    }
+}
+
+/// Split "message at line L[, column C][ (label)]" into its parts
+fn split_location(error: &str) -> Option<(&str, Span, Option<&str>)> {
+    let (message, location) = error.rsplit_once(" at line ")?;
+    let (location, label) = match location.split_once(" (") {
+        Some((location, rest)) => (location, rest.strip_suffix(')')),
+        None => (location, None),
+    };
+    let (line, column) = match location.split_once(", column ") {
+        Some((line, column)) => (line, column.parse().ok()?),
+        None => (location, 0),
+    };
+    Some((message, Span::new(line.parse().ok()?, column), label))
+}
+
+/// Start and length, in characters, of the part of `text` to underline
+fn underline(text: &str, span: Span) -> (usize, usize) {
+    let width = text.chars().count();
+    if span.column == 0 {
+        let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+        return (indent, width.saturating_sub(indent).max(1));
+    }
+    let start = (span.column - 1).min(width);
+    (start, span.len.max(1))
+}
+
+/// Advice for common runtime errors, going by the message of one that
+/// has been turned into a string
+fn runtime_help(message: &str) -> Option<&'static str> {
+    if message.starts_with("Call to undefined function") {
+        Some(UNDEFINED_FUNCTION_HELP)
+    } else if message.starts_with(crate::vm::TIME_LIMIT_ERROR) {
+        Some(TIME_LIMIT_HELP)
+    } else if message.ends_with("has been disabled for security reasons") {
+        Some(DISABLED_FUNCTION_HELP)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,277 @@
+//! Embedding API
+//!
+//! The short path from PHP source to output for Rust programs that embed
+//! VHP. `run` does everything; `compile`, `VM::load` and `exit_status`
+//! are the same steps taken one at a time, for hosts that need to
+//! configure the VM (a filesystem, a time limit, a debugger) in between.
+//! An `Engine` keeps one VM across scripts, for hosts that use PHP as a
+//! scripting layer.
+//!
+//! ```
+//! let mut output = Vec::new();
+//! let exit = vhp::run("<?php echo 'Hello, ', strtoupper('vhp');", &mut output).unwrap();
+//! assert_eq!(String::from_utf8(output).unwrap(), "Hello, VHP");
+//! assert_eq!(exit, None);
+//! ```
+//!
+//! Step by step:
+//!
+//! ```
+//! use std::time::Duration;
+//! use vhp::vm::VM;
+//!
+//! let compilation = vhp::compile("<?php exit(3);", "script.php").unwrap();
+//! let mut vm = VM::new(std::io::sink());
+//! vm.set_time_limit(Some(Duration::from_secs(5)));
+//! let main = vm.load(compilation);
+//! assert_eq!(vhp::exit_status(vm.execute(main)), Ok(Some(3)));
+//! ```
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::runtime::Value;
+use crate::vm::compiler::{CompilationResult, CompileError, Compiler};
+use crate::vm::error::NativeError;
+use crate::vm::extension::NativeClass;
+use crate::vm::opcode::CompiledFunction;
+use crate::vm::{RuntimeError, VM};
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Why `run` or an `Engine` failed: the source didn't compile, or the
+/// script stopped with an error
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Compile(CompileError),
+    Runtime(RuntimeError),
+}
+
+impl From<CompileError> for Error {
+    fn from(error: CompileError) -> Self {
+        Self::Compile(error)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Self {
+        Self::Runtime(error)
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::Compile(error) => error.into(),
+            Error::Runtime(error) => error.into(),
+        }
+    }
+}
+
+/// The one-line form; runtime errors are prefixed with `VM error:`
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compile(error) => error.fmt(f),
+            Self::Runtime(error) => write!(f, "VM error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.to_string()
+    }
+}
+
+/// Lex, parse and compile `source`
+///
+/// `file_path` is what `__FILE__` and `__DIR__` report.
+pub fn compile(source: &str, file_path: &str) -> Result<CompilationResult, CompileError> {
+    let tokens = Lexer::new(source).tokenize()?;
+    let program = Parser::new(tokens).parse()?;
+    let compiler = Compiler::with_file_path("<main>".to_string(), file_path.to_string());
+    compiler.compile_program(&program)
+}
+
+/// Compile and run `source`, writing what it prints to `output`
+///
+/// Returns `Ok(None)` when the script runs to the end, `Ok(Some(code))`
+/// when it calls `exit()`, or the error that stopped it.
+pub fn run<W: Write>(source: &str, output: W) -> Result<Option<i32>, Error> {
+    let compilation = compile(source, "<main>")?;
+    let mut vm = VM::new(output);
+    let main = vm.load(compilation);
+    Ok(exit_status(vm.execute(main))?)
+}
+
+/// Interpret the result of `VM::execute` the way `run` reports it
+///
+/// An `exit()` becomes `Ok(Some(code))`; other errors are passed on.
+pub fn exit_status(result: Result<Value, RuntimeError>) -> Result<Option<i32>, RuntimeError> {
+    match result {
+        Ok(_) => Ok(None),
+        Err(RuntimeError::Exit(code)) => Ok(Some(code)),
+        Err(e) => Err(e),
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Register the built-in classes and everything `compilation`
+    /// declares, print its deprecation notices and return its main code
+    /// for `execute`
+    pub fn load(&mut self, compilation: CompilationResult) -> Arc<CompiledFunction> {
+        self.register_builtins();
+        self.register_functions(compilation.functions);
+        self.register_classes(compilation.classes);
+        self.register_interfaces(compilation.interfaces);
+        self.register_traits(compilation.traits);
+        self.register_enums(compilation.enums);
+        // Only a broken output fails this, and `execute` reports that
+        let _ = self.report_compile_deprecations(&compilation.deprecations);
+        compilation.main
+    }
+}
+
+/// A VM that keeps the functions, classes and global variables scripts
+/// declare from one `eval` to the next
+///
+/// `Engine::new()` collects what scripts print for `take_output`;
+/// `Engine::with_output` writes it elsewhere.
+///
+/// ```
+/// use vhp::runtime::Value;
+/// use vhp::Engine;
+///
+/// let mut engine = Engine::new();
+/// engine.set_global("name", Value::String("vhp".into()));
+/// engine.eval("<?php function greet($who) { return 'Hello, ' . $who; }").unwrap();
+///
+/// let greeting = engine.eval("<?php echo greet($name); return strlen($name);").unwrap();
+/// assert!(matches!(greeting, Value::Integer(3)));
+/// assert_eq!(engine.take_output(), "Hello, vhp");
+///
+/// let greeting = engine.call_function("greet", &[Value::String("Rust".into())]).unwrap();
+/// assert_eq!(greeting.to_string_val(), "Hello, Rust");
+/// ```
+///
+/// Scripts see each other's global variables, and a later declaration of
+/// a function or class replaces an earlier one:
+///
+/// ```
+/// let mut engine = vhp::Engine::new();
+/// engine.eval("<?php $count = 1;").unwrap();
+/// engine.eval("<?php $count++;").unwrap();
+/// assert_eq!(engine.global("count").map(|count| count.to_int()), Some(2));
+///
+/// let error = engine.eval("<?php missing();").unwrap_err();
+/// assert!(error.to_string().contains("missing"));
+/// ```
+pub struct Engine<W: Write = Vec<u8>> {
+    vm: VM<W>,
+}
+
+impl Engine {
+    /// An engine that keeps what scripts print until `take_output`
+    pub fn new() -> Self {
+        Self::with_output(Vec::new())
+    }
+
+    /// What scripts printed since the last call, as UTF-8 (invalid
+    /// sequences replaced)
+    pub fn take_output(&mut self) -> String {
+        let output = std::mem::take(self.vm.output_mut());
+        String::from_utf8_lossy(&output).into_owned()
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Engine<W> {
+    /// An engine whose scripts print to `output`
+    pub fn with_output(output: W) -> Self {
+        let mut vm = VM::new(output);
+        vm.register_builtins();
+        Self { vm }
+    }
+
+    /// The VM scripts run in, to configure it (a filesystem, a time limit,
+    /// extensions) or reach what `Engine` doesn't wrap
+    pub fn vm(&mut self) -> &mut VM<W> {
+        &mut self.vm
+    }
+
+    /// Compile and run a script (starting with `<?php`, like a file),
+    /// giving what its main code returns: the value of a top-level
+    /// `return`, or null
+    ///
+    /// An `exit()` fails with `RuntimeError::Exit`.
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        let compilation = compile(source, "<main>")?;
+        let main = self.vm.load(compilation);
+        Ok(self.vm.execute(main)?)
+    }
+
+    /// Call the function `name` (declared by a script, built in or
+    /// added by an extension) with `args`
+    pub fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        let callable = Value::String(name.to_string());
+        Ok(self.vm.call(callable, args.to_vec())?)
+    }
+
+    /// Make a Rust closure callable from scripts as the function `name`,
+    /// as `VM::register_function` does
+    ///
+    /// The closure fails with a `NativeError`: an exception scripts can
+    /// catch, or a fatal error that stops the script.
+    ///
+    /// ```
+    /// use vhp::runtime::Value;
+    /// use vhp::vm::error::NativeError;
+    ///
+    /// let mut engine = vhp::Engine::new();
+    /// engine.register_function("half", |args, _output| match args.first() {
+    ///     Some(Value::Integer(n)) if n % 2 == 0 => Ok(Value::Integer(n / 2)),
+    ///     Some(Value::Integer(_)) => Err(NativeError::exception(
+    ///         "InvalidArgumentException",
+    ///         "half() needs an even number",
+    ///     )),
+    ///     _ => Err(NativeError::Fatal("half() needs an integer".into())),
+    /// });
+    /// engine
+    ///     .eval("<?php echo half(8), ' '; try { half(3); } catch (InvalidArgumentException $e) { echo $e->getMessage(); }")
+    ///     .unwrap();
+    /// assert_eq!(engine.take_output(), "4 half() needs an even number");
+    /// let error = engine.eval("<?php try { half('x'); } catch (Exception $e) {}").unwrap_err();
+    /// assert_eq!(error.to_string(), "VM error: half() needs an integer");
+    /// ```
+    pub fn register_function<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[Value], &mut dyn Write) -> Result<Value, NativeError> + 'static,
+    {
+        self.vm.register_function(name, function);
+    }
+
+    /// Declare a class whose methods are Rust closures for the scripts run
+    /// after (see `NativeClass`)
+    pub fn register_class(&mut self, class: NativeClass) {
+        self.vm.registry_mut().add_native_class(class);
+    }
+
+    /// The global variable `name`, if it is set
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.vm.global(name)
+    }
+
+    /// Set the global variable `name` for the scripts run after
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.vm.set_global(name, value);
+    }
+}
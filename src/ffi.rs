@@ -0,0 +1,317 @@
+//! C embedding API
+//!
+//! The crate is also built as a C dynamic library (`libvhp.so`,
+//! `libvhp.dylib`, `vhp.dll`) exporting these functions, so hosts that
+//! are not written in Rust can run scripts. `include/vhp.h` declares
+//! them.
+//!
+//! Values cross the boundary as JSON text: a host function receives its
+//! arguments as a JSON array and answers with `vhp_call_return` (a JSON
+//! value) or `vhp_call_error` (a message). Both copy their argument, so
+//! the host keeps ownership of its strings.
+//!
+//! Each `vhp_eval` runs in a fresh script context: functions, classes and
+//! globals do not carry over, while registered host functions and loaded
+//! plugins do.
+
+mod plugin;
+
+pub use plugin::{load_plugin, VhpPluginApi};
+
+use crate::runtime::builtins::json::{json_decode, json_encode};
+use crate::runtime::Value;
+use crate::vm::error::NativeError;
+use crate::vm::extension::Registry;
+use crate::vm::VM;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// `vhp_eval` result: the script ran to the end or called `exit()`
+pub const VHP_OK: c_int = 0;
+/// `vhp_eval` result: the script failed; see `vhp_get_error`
+pub const VHP_ERROR: c_int = 1;
+
+/// A host function: `args_json` is a JSON array of the PHP arguments
+pub type VhpFunction =
+    unsafe extern "C" fn(user_data: *mut c_void, args_json: *const c_char, call: *mut VhpCall);
+
+/// An engine created by `vhp_new`
+pub struct VhpEngine {
+    registry: Registry,
+    output: CString,
+    error: Option<CString>,
+}
+
+/// The result a host function reports for one call
+pub struct VhpCall {
+    result: Result<Value, NativeError>,
+}
+
+struct HostCallback {
+    name: String,
+    function: VhpFunction,
+    user_data: *mut c_void,
+}
+
+impl HostCallback {
+    /// Add this callback to `registry` under its name
+    fn register(self, registry: &mut Registry) {
+        let name = self.name.clone();
+        registry.add_function(&name, move |args, _| self.call(args));
+    }
+
+    fn call(&self, args: &[Value]) -> Result<Value, NativeError> {
+        let args_json = match json_encode(&[Value::Array(
+            args.iter()
+                .enumerate()
+                .map(|(i, arg)| (crate::runtime::ArrayKey::Integer(i as i64), arg.clone()))
+                .collect(),
+        )])? {
+            Value::String(json) => json,
+            _ => "[]".to_string(),
+        };
+        let args_json = c_string(args_json.into_bytes());
+        let mut call = VhpCall {
+            result: Ok(Value::Null),
+        };
+        // SAFETY: the host registered `function` for use with `user_data`;
+        // both strings outlive the call
+        unsafe { (self.function)(self.user_data, args_json.as_ptr(), &mut call) };
+        call.result
+            .map_err(|e| NativeError::Fatal(format!("{}(): {}", self.name, e)))
+    }
+}
+
+/// Create an engine; free it with `vhp_free`
+#[no_mangle]
+pub extern "C" fn vhp_new() -> *mut VhpEngine {
+    Box::into_raw(Box::new(VhpEngine {
+        registry: Registry::new(),
+        output: CString::default(),
+        error: None,
+    }))
+}
+
+/// Run a script (starting with `<?php`, like a file) and keep its output
+///
+/// Returns `VHP_OK` or `VHP_ERROR`.
+///
+/// ```
+/// use std::ffi::CStr;
+/// use vhp::ffi::*;
+///
+/// unsafe {
+///     let engine = vhp_new();
+///     let status = vhp_eval(engine, c"<?php function hi() { echo 'hi'; } hi();".as_ptr());
+///     assert_eq!(status, VHP_OK);
+///     assert_eq!(CStr::from_ptr(vhp_get_output(engine)), c"hi");
+///     assert!(vhp_get_error(engine).is_null());
+///
+///     // The function is gone in the next script
+///     assert_eq!(vhp_eval(engine, c"<?php echo 'a'; hi();".as_ptr()), VHP_ERROR);
+///     assert_eq!(CStr::from_ptr(vhp_get_output(engine)), c"a");
+///     let error = CStr::from_ptr(vhp_get_error(engine)).to_str().unwrap();
+///     assert!(error.contains("Call to undefined function hi()"), "{}", error);
+///
+///     assert_eq!(vhp_eval(engine, std::ptr::null()), VHP_ERROR);
+///     vhp_free(engine);
+/// }
+/// ```
+///
+/// # Safety
+/// `engine` must come from `vhp_new` and `source` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vhp_eval(engine: *mut VhpEngine, source: *const c_char) -> c_int {
+    let (Some(engine), false) = (engine.as_mut(), source.is_null()) else {
+        return VHP_ERROR;
+    };
+    let source = CStr::from_ptr(source).to_string_lossy();
+    let registry = engine.registry.clone();
+
+    let mut output = Vec::new();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| -> Result<_, String> {
+        let compilation = crate::compile(&source, "<main>")?;
+        let mut vm = VM::new(&mut output);
+        vm.set_registry(registry);
+        let main = vm.load(compilation);
+        Ok(crate::exit_status(vm.execute(main)).map_err(crate::Error::Runtime)?)
+    }))
+    .unwrap_or_else(|_| Err("Internal error: the engine panicked".to_string()));
+
+    engine.output = c_string(output);
+    match result {
+        Ok(_) => {
+            engine.error = None;
+            VHP_OK
+        }
+        Err(e) => {
+            engine.error = Some(c_string(e.into_bytes()));
+            VHP_ERROR
+        }
+    }
+}
+
+/// Output of the last `vhp_eval`, valid until the next call or `vhp_free`
+///
+/// # Safety
+/// `engine` must come from `vhp_new`.
+#[no_mangle]
+pub unsafe extern "C" fn vhp_get_output(engine: *const VhpEngine) -> *const c_char {
+    match engine.as_ref() {
+        Some(engine) => engine.output.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Error of the last `vhp_eval`, or NULL if it succeeded; valid until the
+/// next call or `vhp_free`
+///
+/// # Safety
+/// `engine` must come from `vhp_new`.
+#[no_mangle]
+pub unsafe extern "C" fn vhp_get_error(engine: *const VhpEngine) -> *const c_char {
+    match engine.as_ref().and_then(|engine| engine.error.as_ref()) {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Make `function` callable from scripts as `name`
+///
+/// `user_data` is passed back to every call. Returns `VHP_OK`, or
+/// `VHP_ERROR` if an argument is NULL.
+///
+/// ```
+/// use std::ffi::{c_char, c_void, CStr, CString};
+/// use vhp::ffi::*;
+///
+/// /// Count the calls in `user_data` and answer with the arguments, or
+/// /// fail when there are none
+/// unsafe extern "C" fn echo(user_data: *mut c_void, args_json: *const c_char, call: *mut VhpCall) {
+///     *(user_data as *mut u32) += 1;
+///     match CStr::from_ptr(args_json).to_bytes() {
+///         b"[]" => vhp_call_error(call, c"needs arguments".as_ptr()),
+///         args => vhp_call_return(call, CString::new(args).unwrap().as_ptr()),
+///     }
+/// }
+///
+/// unsafe {
+///     let engine = vhp_new();
+///     let mut calls = 0u32;
+///     let user_data = &mut calls as *mut u32 as *mut c_void;
+///     assert_eq!(vhp_register_function(engine, c"host_echo".as_ptr(), Some(echo), user_data), VHP_OK);
+///     assert_eq!(vhp_register_function(engine, c"none".as_ptr(), None, user_data), VHP_ERROR);
+///
+///     let source = c"<?php $r = host_echo(1, 'two', [3]); echo $r[1], count($r), $r[2][0];";
+///     assert_eq!(vhp_eval(engine, source.as_ptr()), VHP_OK);
+///     assert_eq!(CStr::from_ptr(vhp_get_output(engine)), c"two33");
+///
+///     // Host functions stay registered across scripts
+///     assert_eq!(vhp_eval(engine, c"<?php host_echo();".as_ptr()), VHP_ERROR);
+///     let error = CStr::from_ptr(vhp_get_error(engine)).to_str().unwrap();
+///     assert!(error.contains("host_echo(): needs arguments"), "{}", error);
+///
+///     vhp_free(engine);
+///     assert_eq!(calls, 2);
+/// }
+/// ```
+///
+/// # Safety
+/// `engine` must come from `vhp_new`, `name` must be a NUL-terminated
+/// string, and `function` must be safe to call with `user_data` for as
+/// long as the engine lives.
+#[no_mangle]
+pub unsafe extern "C" fn vhp_register_function(
+    engine: *mut VhpEngine,
+    name: *const c_char,
+    function: Option<VhpFunction>,
+    user_data: *mut c_void,
+) -> c_int {
+    let (Some(engine), Some(function), false) = (engine.as_mut(), function, name.is_null()) else {
+        return VHP_ERROR;
+    };
+    HostCallback {
+        name: CStr::from_ptr(name).to_string_lossy().into_owned(),
+        function,
+        user_data,
+    }
+    .register(&mut engine.registry);
+    VHP_OK
+}
+
+/// Load the plugin library at `path` into the engine
+///
+/// Returns `VHP_OK`, or `VHP_ERROR` with the reason in `vhp_get_error`.
+///
+/// # Safety
+/// `engine` must come from `vhp_new` and `path` must be a NUL-terminated
+/// string. The library's initializer runs with the host's privileges.
+#[no_mangle]
+pub unsafe extern "C" fn vhp_load_plugin(engine: *mut VhpEngine, path: *const c_char) -> c_int {
+    let (Some(engine), false) = (engine.as_mut(), path.is_null()) else {
+        return VHP_ERROR;
+    };
+    let path = CStr::from_ptr(path).to_string_lossy().into_owned();
+    match load_plugin(std::path::Path::new(&path), &mut engine.registry) {
+        Ok(()) => {
+            engine.error = None;
+            VHP_OK
+        }
+        Err(e) => {
+            engine.error = Some(c_string(e.into_bytes()));
+            VHP_ERROR
+        }
+    }
+}
+
+/// Set the value a host function returns, as JSON text
+///
+/// Invalid JSON returns null to the script, as `json_decode` does.
+///
+/// # Safety
+/// `call` must be the pointer the host function received, and `json` a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vhp_call_return(call: *mut VhpCall, json: *const c_char) {
+    let (Some(call), false) = (call.as_mut(), json.is_null()) else {
+        return;
+    };
+    let json = CStr::from_ptr(json).to_string_lossy().into_owned();
+    call.result = json_decode(&[Value::String(json)]);
+}
+
+/// Make a host function fail with `message`, stopping the script
+///
+/// # Safety
+/// `call` must be the pointer the host function received, and `message`
+/// a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vhp_call_error(call: *mut VhpCall, message: *const c_char) {
+    let (Some(call), false) = (call.as_mut(), message.is_null()) else {
+        return;
+    };
+    call.result = Err(NativeError::Fatal(
+        CStr::from_ptr(message).to_string_lossy().into_owned(),
+    ));
+}
+
+/// Free an engine and the strings it handed out
+///
+/// # Safety
+/// `engine` must come from `vhp_new` (or be NULL) and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn vhp_free(engine: *mut VhpEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// A C string of `bytes`, cut at the first NUL byte
+fn c_string(mut bytes: Vec<u8>) -> CString {
+    if let Some(nul) = bytes.iter().position(|&b| b == 0) {
+        bytes.truncate(nul);
+    }
+    CString::new(bytes).unwrap_or_default()
+}
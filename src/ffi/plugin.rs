@@ -0,0 +1,154 @@
+//! Plugins: shared libraries that add functions and constants
+//!
+//! A plugin exports `vhp_plugin_init`, which is called once with the
+//! registry to fill and a table of entry points. The table lets plugins
+//! work without linking against VHP, so one library loads into the `vhp`
+//! binary and into any host using the C API alike. Libraries stay loaded
+//! for the life of the process since their functions may be called at any
+//! time.
+
+use super::{HostCallback, VhpCall, VhpFunction, VHP_ERROR, VHP_OK};
+use crate::runtime::builtins::json::json_decode;
+use crate::runtime::Value;
+use crate::vm::extension::Registry;
+use std::ffi::{c_char, c_int, c_void, CStr};
+use std::path::Path;
+
+/// Symbol every plugin exports
+const INIT_SYMBOL: &str = "vhp_plugin_init";
+
+/// Entry points handed to `vhp_plugin_init`, valid for the whole process
+#[repr(C)]
+pub struct VhpPluginApi {
+    pub add_function: unsafe extern "C" fn(
+        registry: *mut Registry,
+        name: *const c_char,
+        function: Option<VhpFunction>,
+        user_data: *mut c_void,
+    ) -> c_int,
+    pub add_constant: unsafe extern "C" fn(
+        registry: *mut Registry,
+        name: *const c_char,
+        json: *const c_char,
+    ) -> c_int,
+    pub call_return: unsafe extern "C" fn(call: *mut VhpCall, json: *const c_char),
+    pub call_error: unsafe extern "C" fn(call: *mut VhpCall, message: *const c_char),
+}
+
+static API: VhpPluginApi = VhpPluginApi {
+    add_function,
+    add_constant,
+    call_return: super::vhp_call_return,
+    call_error: super::vhp_call_error,
+};
+
+/// `vhp_plugin_init`: fill `registry`, returning `VHP_OK` on success
+type PluginInit = unsafe extern "C" fn(registry: *mut Registry, api: *const VhpPluginApi) -> c_int;
+
+unsafe extern "C" fn add_function(
+    registry: *mut Registry,
+    name: *const c_char,
+    function: Option<VhpFunction>,
+    user_data: *mut c_void,
+) -> c_int {
+    let (Some(registry), Some(function), false) = (registry.as_mut(), function, name.is_null())
+    else {
+        return VHP_ERROR;
+    };
+    HostCallback {
+        name: CStr::from_ptr(name).to_string_lossy().into_owned(),
+        function,
+        user_data,
+    }
+    .register(registry);
+    VHP_OK
+}
+
+unsafe extern "C" fn add_constant(
+    registry: *mut Registry,
+    name: *const c_char,
+    json: *const c_char,
+) -> c_int {
+    let (Some(registry), false, false) = (registry.as_mut(), name.is_null(), json.is_null()) else {
+        return VHP_ERROR;
+    };
+    let json = CStr::from_ptr(json).to_string_lossy().into_owned();
+    match json_decode(&[Value::String(json)]) {
+        Ok(value) => {
+            registry.add_constant(&CStr::from_ptr(name).to_string_lossy(), value);
+            VHP_OK
+        }
+        Err(_) => VHP_ERROR,
+    }
+}
+
+/// Load the plugin library at `path` and let it fill `registry`
+///
+/// Running a plugin runs its native code with the host's privileges, so
+/// only load trusted files.
+pub fn load_plugin(path: &Path, registry: &mut Registry) -> Result<(), String> {
+    let init = open(path)?;
+    // SAFETY: the library exports `vhp_plugin_init` with this signature;
+    // `API` is static and `registry` outlives the call
+    match unsafe { init(registry, &API) } {
+        VHP_OK => Ok(()),
+        status => Err(format!(
+            "Plugin {} failed to initialize (status {})",
+            path.display(),
+            status
+        )),
+    }
+}
+
+#[cfg(unix)]
+fn open(path: &Path) -> Result<PluginInit, String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    const RTLD_NOW: c_int = 2;
+
+    extern "C" {
+        fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlerror() -> *const c_char;
+    }
+
+    let last_error = || {
+        // SAFETY: dlerror returns NULL or a NUL-terminated message
+        let message = unsafe { dlerror() };
+        if message.is_null() {
+            "unknown error".to_string()
+        } else {
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        }
+    };
+
+    let file = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| format!("Invalid plugin path: {}", path.display()))?;
+    // SAFETY: both strings are NUL-terminated; the handle is never closed
+    let handle = unsafe { dlopen(file.as_ptr(), RTLD_NOW) };
+    if handle.is_null() {
+        return Err(format!("Cannot load plugin: {}", last_error()));
+    }
+    let symbol = CString::new(INIT_SYMBOL).unwrap_or_default();
+    let init = unsafe { dlsym(handle, symbol.as_ptr()) };
+    if init.is_null() {
+        return Err(format!(
+            "Plugin {} does not export {}",
+            path.display(),
+            INIT_SYMBOL
+        ));
+    }
+    // SAFETY: a plugin's `vhp_plugin_init` has the `PluginInit` signature
+    Ok(unsafe { std::mem::transmute::<*mut c_void, PluginInit>(init) })
+}
+
+#[cfg(not(unix))]
+fn open(path: &Path) -> Result<PluginInit, String> {
+    Err(format!(
+        "Cannot load plugin {}: plugins are not supported on this platform",
+        path.display()
+    ))
+}
@@ -0,0 +1,46 @@
+//! Fuzzing entry points
+//!
+//! Each function feeds arbitrary bytes through one stage of the pipeline
+//! and discards the result. Errors are expected for most inputs; the only
+//! failure a fuzzer should find is a panic (or a hang). The cargo-fuzz
+//! targets in `fuzz/` call these.
+
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::vm::VM;
+use std::time::Duration;
+
+/// Wall-clock limit for one `fuzz_compile_and_run` input
+const RUN_TIME_LIMIT: Duration = Duration::from_secs(1);
+
+/// Tokenize `data` as PHP source
+pub fn fuzz_lex(data: &[u8]) {
+    let source = String::from_utf8_lossy(data);
+    let _ = Lexer::new(&source).tokenize();
+}
+
+/// Tokenize and parse `data` as PHP source
+pub fn fuzz_parse(data: &[u8]) {
+    let source = String::from_utf8_lossy(data);
+    if let Ok(tokens) = Lexer::new(&source).tokenize() {
+        let _ = Parser::new(tokens).parse();
+    }
+}
+
+/// Tokenize, parse, compile and run `data` as PHP source
+///
+/// Output is discarded and each run is limited to one second. Runs are
+/// deterministic so that a crashing input reproduces. Scripts still have
+/// the same access to files and the environment as `vhp`.
+pub fn fuzz_compile_and_run(data: &[u8]) {
+    let source = String::from_utf8_lossy(data);
+    let Ok(compilation) = crate::compile(&source, "fuzz.php") else {
+        return;
+    };
+
+    crate::runtime::deterministic::enable();
+    let mut vm = VM::new(std::io::sink());
+    let main = vm.load(compilation);
+    vm.set_time_limit(Some(RUN_TIME_LIMIT));
+    let _ = vm.execute(main);
+}
@@ -0,0 +1,184 @@
+//! The tokens of a string or heredoc with interpolations
+//!
+//! PHP's tokenizer gives such a string as its quotes (a heredoc's opening
+//! line and closing label), the runs of text between interpolations as
+//! `T_ENCAPSED_AND_WHITESPACE`, and each interpolation's own tokens: a
+//! `$name` with its key or property, or the expression in `{$...}`
+//! (`T_CURLY_OPEN`) or `${...}` (`T_DOLLAR_OPEN_CURLY_BRACES`).
+
+use super::source_tokens::{is_ignorable, php_token, SourceToken};
+use super::Lexer;
+use std::ops::Range;
+
+/// A string with interpolations as its quotes, or a heredoc's opening
+/// line and closing label, around the tokens of its body
+pub(super) fn split_interpolated(token: SourceToken) -> Vec<SourceToken> {
+    let text = &token.text;
+    let (open, close, names) = if text.starts_with("<<<") {
+        let open = text.find('\n').map_or(text.len(), |i| i + 1);
+        let close = text.rfind('\n').map_or(text.len(), |i| i + 1).max(open);
+        (
+            open,
+            close,
+            (Some("T_START_HEREDOC"), Some("T_END_HEREDOC")),
+        )
+    } else {
+        (1, text.len().saturating_sub(1).max(1), (None, None))
+    };
+    let body = &text[open..close];
+    let mut parts = vec![(names.0, &text[..open])];
+    parts.extend(
+        body_tokens(body)
+            .into_iter()
+            .map(|(name, range)| (name, &body[range])),
+    );
+    parts.push((names.1, &text[close..]));
+
+    let (mut line, mut pos) = (token.line, token.pos);
+    parts
+        .into_iter()
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(name, text)| {
+            let part = SourceToken {
+                name,
+                text: text.to_string(),
+                line,
+                pos,
+            };
+            line += text.matches('\n').count();
+            pos += text.len();
+            part
+        })
+        .collect()
+}
+
+/// The tokens of an interpolated string's body and their byte ranges:
+/// its text, and each interpolation as `$name` with a key or property
+/// after it, `{$...}` or `${...}`
+fn body_tokens(body: &str) -> Vec<(Option<&'static str>, Range<usize>)> {
+    let starts_name = |c: Option<char>| c.is_some_and(|c| c.is_alphabetic() || c == '_');
+    let mut offsets: Vec<usize> = body.char_indices().map(|(i, _)| i).collect();
+    offsets.push(body.len());
+    let mut lexer = Lexer::new(body);
+    lexer.in_php = true;
+    let mut tokens = Vec::new();
+    let mut text = 0;
+    let push = |tokens: &mut Vec<_>, name, start: usize, end: usize| {
+        tokens.push((name, offsets[start]..offsets[end]));
+    };
+
+    while let Some(ch) = lexer.current() {
+        let start = lexer.pos;
+        let next = lexer.peek(1);
+        if ch == '\\' {
+            lexer.advance_by(2);
+            continue;
+        }
+        let simple = ch == '$' && starts_name(next);
+        if !simple && !matches!((ch, next), ('$', Some('{')) | ('{', Some('$'))) {
+            lexer.advance();
+            continue;
+        }
+        if text < start {
+            push(&mut tokens, Some("T_ENCAPSED_AND_WHITESPACE"), text, start);
+        }
+        if simple {
+            lexer.advance();
+            lexer.read_identifier();
+            push(&mut tokens, Some("T_VARIABLE"), start, lexer.pos);
+            let arrow = [
+                ("->", "T_OBJECT_OPERATOR"),
+                ("?->", "T_NULLSAFE_OBJECT_OPERATOR"),
+            ]
+            .into_iter()
+            .find(|(arrow, _)| lexer.matches_str(arrow) && starts_name(lexer.peek(arrow.len())));
+            if let Some((arrow, name)) = arrow {
+                let at = lexer.pos;
+                lexer.advance_by(arrow.len());
+                push(&mut tokens, Some(name), at, lexer.pos);
+                let at = lexer.pos;
+                lexer.read_identifier();
+                push(&mut tokens, Some("T_STRING"), at, lexer.pos);
+            } else if lexer.current() == Some('[') {
+                // `[`, then a key up to `]`
+                loop {
+                    let at = lexer.pos;
+                    let name = match lexer.current() {
+                        Some(c) if c.is_ascii_digit() => {
+                            lexer.read_number();
+                            Some("T_NUM_STRING")
+                        }
+                        Some('$') if starts_name(lexer.peek(1)) => {
+                            lexer.advance();
+                            lexer.read_identifier();
+                            Some("T_VARIABLE")
+                        }
+                        c if starts_name(c) => {
+                            lexer.read_identifier();
+                            Some("T_STRING")
+                        }
+                        Some(c) => {
+                            lexer.advance();
+                            if c == ']' {
+                                push(&mut tokens, None, at, lexer.pos);
+                                break;
+                            }
+                            None
+                        }
+                        None => break,
+                    };
+                    push(&mut tokens, name, at, lexer.pos);
+                }
+            }
+        } else {
+            let name = if ch == '{' {
+                "T_CURLY_OPEN"
+            } else {
+                "T_DOLLAR_OPEN_CURLY_BRACES"
+            };
+            lexer.advance_by(if ch == '{' { 1 } else { 2 });
+            push(&mut tokens, Some(name), start, lexer.pos);
+            let varname = ch == '$'
+                && starts_name(lexer.current())
+                && (lexer.input[lexer.pos..].iter())
+                    .find(|c| !(c.is_alphanumeric() || **c == '_'))
+                    .is_some_and(|c| matches!(c, '}' | '['));
+            if varname {
+                let at = lexer.pos;
+                lexer.read_identifier();
+                push(&mut tokens, Some("T_STRING_VARNAME"), at, lexer.pos);
+            }
+            // The expression's tokens, up to the `}` that closes it
+            let mut depth = 0usize;
+            let mut after_arrow = false;
+            while let Some(c) = lexer.current() {
+                let at = lexer.pos;
+                if c == '}' && depth == 0 {
+                    lexer.advance();
+                    push(&mut tokens, None, at, lexer.pos);
+                    break;
+                }
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                let name = php_token(&mut lexer, after_arrow);
+                if !is_ignorable(name) {
+                    after_arrow = name == Some("T_OBJECT_OPERATOR");
+                }
+                push(&mut tokens, name, at, lexer.pos);
+            }
+        }
+        text = lexer.pos;
+    }
+    if text < lexer.pos {
+        push(
+            &mut tokens,
+            Some("T_ENCAPSED_AND_WHITESPACE"),
+            text,
+            lexer.pos,
+        );
+    }
+    tokens
+}
@@ -0,0 +1,82 @@
+//! Lexer errors
+
+use crate::diagnostic::Diagnostic;
+use std::fmt;
+
+/// Why the lexer rejected the source, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    /// A character no token starts with
+    UnexpectedChar {
+        ch: char,
+        line: usize,
+        column: usize,
+    },
+    /// A `$` not followed by a variable name
+    MissingVariableName { line: usize, column: usize },
+    /// A quoted string still open at the end of the input
+    UnterminatedString { line: usize },
+    /// A `<<<` not followed by a valid heredoc or nowdoc label
+    InvalidHeredocLabel { line: usize },
+    /// A heredoc or nowdoc without its closing label
+    UnterminatedHeredoc { label: String, line: usize },
+}
+
+impl LexError {
+    pub fn line(&self) -> usize {
+        match self {
+            Self::UnexpectedChar { line, .. }
+            | Self::MissingVariableName { line, .. }
+            | Self::UnterminatedString { line }
+            | Self::InvalidHeredocLabel { line }
+            | Self::UnterminatedHeredoc { line, .. } => *line,
+        }
+    }
+
+    /// Column of the error, or 0 when only the line is known
+    pub fn column(&self) -> usize {
+        match self {
+            Self::UnexpectedChar { column, .. } | Self::MissingVariableName { column, .. } => {
+                *column
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::UnexpectedChar { ch, .. } => format!("Unexpected character '{}'", ch),
+            Self::MissingVariableName { .. } => "Expected variable name after '$'".to_string(),
+            Self::UnterminatedString { .. } => "Unterminated string starting".to_string(),
+            Self::InvalidHeredocLabel { .. } => "Expected heredoc/nowdoc identifier".to_string(),
+            Self::UnterminatedHeredoc { .. } => "Unterminated heredoc/nowdoc starting".to_string(),
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        let diagnostic = Diagnostic::syntax(error.message(), error.line(), error.column());
+        match error {
+            LexError::UnterminatedHeredoc { label, .. } => {
+                diagnostic.with_label(format!("missing closing marker: {}", label))
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+/// The one-line form, e.g. "Unexpected character '`' at line 2, column 5"
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Diagnostic::from(self).fmt(f)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+impl From<LexError> for String {
+    fn from(error: LexError) -> Self {
+        error.to_string()
+    }
+}
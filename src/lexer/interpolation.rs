@@ -0,0 +1,242 @@
+//! Interpolation in double-quoted strings and heredocs
+//!
+//! A double-quoted string without variables is a plain string token. One
+//! with them is read into its text and the tokens of each interpolated
+//! expression, which the parser joins with `.`:
+//!
+//! - `$name`, with one `[key]`, `->prop` or `?->prop` after it, where the
+//!   key is an integer, a bare word (a string key) or a variable
+//! - `{$...}`, any expression that starts with a variable
+//! - `${name}` and `${name[...]}`, the older spelling of `{$name...}`
+//!
+//! A heredoc's body is read the same way, up to its closing label instead
+//! of a quote, and without the label's indentation on each line.
+
+use crate::lexer::{LexError, Lexer};
+use crate::token::{StringPart, Token, TokenKind};
+
+/// Whether `ch` can start a variable or property name
+fn starts_name(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+/// Where the text of an interpolated string ends
+#[derive(Clone, Copy)]
+pub(super) enum Closing {
+    /// At a `"`, which is consumed
+    Quote,
+    /// At position `end`, the end of a heredoc's body; `indent` spaces or
+    /// tabs are left out at the start of every line
+    Body { end: usize, indent: usize },
+}
+
+impl Lexer {
+    /// Reads a double-quoted string, its opening quote not yet consumed
+    pub(super) fn read_double_quoted(&mut self) -> Result<TokenKind, LexError> {
+        self.advance(); // consume '"'
+        let parts = self.read_interpolated(Closing::Quote)?;
+        Ok(string_token(parts))
+    }
+
+    /// Reads the text and interpolations of a string up to its `closing`
+    pub(super) fn read_interpolated(
+        &mut self,
+        closing: Closing,
+    ) -> Result<Vec<StringPart>, LexError> {
+        let start_line = self.line;
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        if let Closing::Body { end, indent } = closing {
+            self.skip_indent(end, indent);
+        }
+
+        loop {
+            let ch = match (closing, self.current()) {
+                (Closing::Quote, Some('"')) => {
+                    self.advance();
+                    break;
+                }
+                (Closing::Body { end, .. }, _) if self.pos >= end => break,
+                (_, Some(ch)) => ch,
+                (_, None) => return Err(LexError::UnterminatedString { line: start_line }),
+            };
+            let part = match ch {
+                // A heredoc's `\"` is not an escape
+                '\\' if matches!(closing, Closing::Body { .. }) && self.peek(1) == Some('"') => {
+                    text.push('\\');
+                    self.advance();
+                    continue;
+                }
+                '\\' => {
+                    self.read_escape(&mut text);
+                    continue;
+                }
+                '\n' => {
+                    text.push(ch);
+                    self.advance();
+                    if let Closing::Body { end, indent } = closing {
+                        self.skip_indent(end, indent);
+                    }
+                    continue;
+                }
+                '$' if self.peek(1).is_some_and(starts_name) => self.read_simple_interpolation()?,
+                '$' if self.peek(1) == Some('{') => self.read_dollar_brace_interpolation()?,
+                '{' if self.peek(1) == Some('$') => {
+                    self.advance(); // consume '{'
+                    self.read_embedded_tokens(Vec::new())?
+                }
+                _ => {
+                    text.push(ch);
+                    self.advance();
+                    continue;
+                }
+            };
+            if !text.is_empty() {
+                parts.push(StringPart::Literal(std::mem::take(&mut text)));
+            }
+            parts.push(StringPart::Tokens(part));
+        }
+
+        if !text.is_empty() {
+            parts.push(StringPart::Literal(text));
+        }
+        Ok(parts)
+    }
+
+    /// Skip up to `indent` spaces or tabs, not going past `end`
+    pub(super) fn skip_indent(&mut self, end: usize, indent: usize) {
+        for _ in 0..indent {
+            if self.pos >= end || !matches!(self.current(), Some(' ' | '\t')) {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    /// `$name`, then at most one `[key]`, `->prop` or `?->prop`
+    fn read_simple_interpolation(&mut self) -> Result<Vec<Token>, LexError> {
+        let mut tokens = vec![self.token(|lexer| TokenKind::Variable(lexer.read_variable()))];
+
+        let arrow = if self.matches_str("->") {
+            Some((2, TokenKind::Arrow))
+        } else if self.matches_str("?->") {
+            Some((3, TokenKind::NullsafeArrow))
+        } else {
+            None
+        };
+        if let Some((length, arrow)) = arrow.filter(|&(n, _)| self.peek(n).is_some_and(starts_name))
+        {
+            tokens.push(self.token(|lexer| {
+                lexer.advance_by(length);
+                arrow
+            }));
+            tokens.push(self.token(|lexer| TokenKind::Identifier(lexer.read_identifier())));
+        } else if self.current() == Some('[') {
+            tokens.push(self.token(|lexer| {
+                lexer.advance();
+                TokenKind::LeftBracket
+            }));
+            tokens.push(self.read_simple_key()?);
+            if self.current() != Some(']') {
+                return Err(self.unexpected_in_string());
+            }
+            tokens.push(self.token(|lexer| {
+                lexer.advance();
+                TokenKind::RightBracket
+            }));
+        }
+        Ok(tokens)
+    }
+
+    /// The key of `$name[key]`: an integer, a variable, or a bare word,
+    /// which is a string key
+    fn read_simple_key(&mut self) -> Result<Token, LexError> {
+        let (line, column) = (self.line, self.column);
+        let kind = match self.current() {
+            Some('-') if self.peek(1).is_some_and(|ch| ch.is_ascii_digit()) => {
+                self.advance();
+                match self.read_number() {
+                    TokenKind::Integer(n) => TokenKind::Integer(-n),
+                    _ => return Err(self.unexpected_in_string()),
+                }
+            }
+            Some(ch) if ch.is_ascii_digit() => self.read_number(),
+            Some('$') if self.peek(1).is_some_and(starts_name) => {
+                TokenKind::Variable(self.read_variable())
+            }
+            Some(ch) if starts_name(ch) => TokenKind::String(self.read_identifier()),
+            _ => return Err(self.unexpected_in_string()),
+        };
+        Ok(Token::new(kind, line, column))
+    }
+
+    /// `${name}` or `${name[...]}`
+    fn read_dollar_brace_interpolation(&mut self) -> Result<Vec<Token>, LexError> {
+        self.advance_by(2); // consume '${'
+        if !self.current().is_some_and(starts_name) {
+            return Err(self.unexpected_in_string());
+        }
+        let name = self.token(|lexer| TokenKind::Variable(lexer.read_identifier()));
+        match self.current() {
+            Some('}') => {
+                self.advance();
+                Ok(vec![name])
+            }
+            Some('[') => self.read_embedded_tokens(vec![name]),
+            _ => Err(self.unexpected_in_string()),
+        }
+    }
+
+    /// The tokens of an expression up to the `}` that closes it, which is
+    /// consumed, after the `tokens` already read
+    fn read_embedded_tokens(&mut self, mut tokens: Vec<Token>) -> Result<Vec<Token>, LexError> {
+        let mut depth = 0usize;
+        loop {
+            self.skip_whitespace();
+            let (line, column) = (self.line, self.column);
+            let kind = match self.current() {
+                None => return Err(LexError::UnterminatedString { line }),
+                Some('}') if depth == 0 => {
+                    self.advance();
+                    return Ok(tokens);
+                }
+                Some(ch) => self.tokenize_php_element(ch, line, column)?,
+            };
+            match kind {
+                TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightBrace => depth -= 1,
+                _ => {}
+            }
+            tokens.push(Token::new(kind, line, column));
+        }
+    }
+
+    /// A token read by `read`, at the position it starts
+    fn token(&mut self, read: impl FnOnce(&mut Self) -> TokenKind) -> Token {
+        let (line, column) = (self.line, self.column);
+        Token::new(read(self), line, column)
+    }
+
+    /// The error for what stands where an interpolation needs something
+    /// else
+    fn unexpected_in_string(&self) -> LexError {
+        match self.current() {
+            Some(ch) => LexError::UnexpectedChar {
+                ch,
+                line: self.line,
+                column: self.column,
+            },
+            None => LexError::UnterminatedString { line: self.line },
+        }
+    }
+}
+
+/// A plain string token for text without interpolations, or else an
+/// interpolated one
+pub(super) fn string_token(parts: Vec<StringPart>) -> TokenKind {
+    match parts.as_slice() {
+        [] => TokenKind::String(String::new()),
+        [StringPart::Literal(text)] => TokenKind::String(text.clone()),
+        _ => TokenKind::InterpolatedString(parts),
+    }
+}
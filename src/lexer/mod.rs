@@ -1,7 +1,13 @@
+mod encapsed_tokens;
+mod error;
+mod interpolation;
 mod operators;
+pub mod source_tokens;
 mod strings;
+pub mod token_ids;
 
 use crate::token::{Token, TokenKind};
+pub use error::LexError;
 
 /// Lexical analyzer for VHP (Vibe-coded Hypertext Preprocessor)
 ///
@@ -267,7 +273,7 @@ impl Lexer {
     }
 
     /// Main tokenization loop. Processes the input and returns a vector of tokens.
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, LexError> {
         let mut tokens = Vec::new();
 
         while self.current().is_some() {
@@ -337,7 +343,7 @@ impl Lexer {
     }
 
     /// Handles tokenization when outside PHP tags (HTML mode).
-    fn handle_non_php_mode(&mut self, tokens: &mut Vec<Token>) -> Result<(), String> {
+    fn handle_non_php_mode(&mut self, tokens: &mut Vec<Token>) -> Result<(), LexError> {
         if self.matches_str("<?php") {
             let line = self.line;
             let column = self.column;
@@ -391,16 +397,13 @@ impl Lexer {
         ch: char,
         line: usize,
         column: usize,
-    ) -> Result<TokenKind, String> {
+    ) -> Result<TokenKind, LexError> {
         match ch {
             // Variables
             '$' => {
                 let name = self.read_variable();
                 if name.is_empty() {
-                    return Err(format!(
-                        "Expected variable name after '$' at line {}, column {}",
-                        line, column
-                    ));
+                    return Err(LexError::MissingVariableName { line, column });
                 }
                 Ok(TokenKind::Variable(name))
             }
@@ -445,13 +448,19 @@ impl Lexer {
                 Ok(TokenKind::Backslash)
             }
 
-            // Operators
-            '+' | '-' | '*' | '/' | '%' | '.' | '=' | '!' | '>' | '&' | '|' | '?' | ':' => {
-                self.read_operator(ch)
+            // Error suppression
+            '@' => {
+                self.advance();
+                Ok(TokenKind::At)
             }
 
+            // Operators
+            '+' | '-' | '*' | '/' | '%' | '.' | '=' | '!' | '>' | '&' | '|' | '^' | '~' | '?'
+            | ':' => self.read_operator(ch),
+
             // Strings
-            '"' | '\'' => {
+            '"' => self.read_double_quoted(),
+            '\'' => {
                 let s = self.read_string(ch)?;
                 Ok(TokenKind::String(s))
             }
@@ -461,25 +470,9 @@ impl Lexer {
                 if self.peek(1) == Some('<') && self.peek(2) == Some('<') {
                     self.advance_by(3); // consume <<<
                     self.skip_whitespace();
-                    let is_nowdoc = self.current() == Some('\'');
-                    let content = if is_nowdoc {
-                        self.advance(); // consume the opening quote
-                        self.read_heredoc_nowdoc(true)?
-                    } else {
-                        self.read_heredoc_nowdoc(false)?
-                    };
-                    if is_nowdoc {
-                        // Nowdoc - no variable interpolation
-                        Ok(TokenKind::String(content))
-                    } else {
-                        // Heredoc - variable interpolation
-                        Ok(TokenKind::Heredoc(content))
-                    }
+                    self.read_heredoc()
                 } else {
-                    Err(format!(
-                        "Unexpected '<' at line {}, column {}",
-                        line, column
-                    ))
+                    self.read_operator(ch)
                 }
             }
 
@@ -497,10 +490,7 @@ impl Lexer {
                 }
             }
 
-            _ => Err(format!(
-                "Unexpected character '{}' at line {}, column {}",
-                ch, line, column
-            )),
+            _ => Err(LexError::UnexpectedChar { ch, line, column }),
         }
     }
 }
@@ -2,11 +2,12 @@
 ///
 /// Handles recognition and tokenization of all operators including:
 /// - Arithmetic operators (+, -, *, /, %, **)
-/// - Comparison operators (<, >, <=, >=, <=>, ==, !=, ===, !==)
-/// - Assignment operators (=, +=, -=, *=, /=, %=, .=)
+/// - Comparison operators (<, >, <=, >=, <=>, ==, !=, <>, ===, !==)
+/// - Assignment operators (=, +=, -=, *=, /=, %=, .=, **=, &=, |=, ^=, <<=, >>=, ??=)
 /// - Logical operators (&&, ||, and, or, xor)
+/// - Bitwise operators (&, |, ^, ~, <<, >>)
 /// - Special operators (=>, ::, ->, ??, |>)
-use crate::lexer::Lexer;
+use crate::lexer::{LexError, Lexer};
 use crate::token::TokenKind;
 
 impl Lexer {
@@ -20,7 +21,7 @@ impl Lexer {
     ///
     /// # Returns
     /// A TokenKind representing the recognized operator
-    pub fn read_operator(&mut self, ch: char) -> Result<TokenKind, String> {
+    pub fn read_operator(&mut self, ch: char) -> Result<TokenKind, LexError> {
         let line = self.line;
         let column = self.column;
 
@@ -57,7 +58,12 @@ impl Lexer {
                 self.advance();
                 if self.current() == Some('*') {
                     self.advance();
-                    TokenKind::Pow
+                    if self.current() == Some('=') {
+                        self.advance();
+                        TokenKind::PowAssign
+                    } else {
+                        TokenKind::Pow
+                    }
                 } else if self.current() == Some('=') {
                     self.advance();
                     TokenKind::MulAssign
@@ -135,10 +141,21 @@ impl Lexer {
                 }
             }
 
-            // Less-than and related operators
+            // Less-than, left shift and related operators
             '<' => {
                 self.advance();
-                if self.current() == Some('=') {
+                if self.current() == Some('<') {
+                    self.advance();
+                    if self.current() == Some('=') {
+                        self.advance();
+                        TokenKind::ShiftLeftAssign
+                    } else {
+                        TokenKind::ShiftLeft
+                    }
+                } else if self.current() == Some('>') {
+                    self.advance();
+                    TokenKind::NotEqual
+                } else if self.current() == Some('=') {
                     self.advance();
                     if self.current() == Some('>') {
                         self.advance();
@@ -151,10 +168,18 @@ impl Lexer {
                 }
             }
 
-            // Greater-than operator
+            // Greater-than and right shift operators
             '>' => {
                 self.advance();
-                if self.current() == Some('=') {
+                if self.current() == Some('>') {
+                    self.advance();
+                    if self.current() == Some('=') {
+                        self.advance();
+                        TokenKind::ShiftRightAssign
+                    } else {
+                        TokenKind::ShiftRight
+                    }
+                } else if self.current() == Some('=') {
                     self.advance();
                     TokenKind::GreaterEqual
                 } else {
@@ -162,15 +187,18 @@ impl Lexer {
                 }
             }
 
-            // Logical AND
+            // Logical and bitwise AND
             '&' => {
                 self.advance();
                 if self.current() == Some('&') {
                     self.advance();
                     TokenKind::And
+                } else if self.current() == Some('=') {
+                    self.advance();
+                    TokenKind::BitwiseAndAssign
                 } else {
-                    // Single & for by-reference - represented as identifier
-                    TokenKind::Identifier("&".to_string())
+                    // Single & for by-reference, intersection types and bitwise AND
+                    TokenKind::BitwiseAnd
                 }
             }
 
@@ -183,18 +211,44 @@ impl Lexer {
                 } else if self.current() == Some('>') {
                     self.advance();
                     TokenKind::Pipe
+                } else if self.current() == Some('=') {
+                    self.advance();
+                    TokenKind::BitwiseOrAssign
                 } else {
                     // Single pipe | for multi-catch and bitwise OR
                     TokenKind::BitwiseOr
                 }
             }
 
-            // Null coalesce operator
+            // Bitwise XOR and NOT
+            '^' => {
+                self.advance();
+                if self.current() == Some('=') {
+                    self.advance();
+                    TokenKind::BitwiseXorAssign
+                } else {
+                    TokenKind::BitwiseXor
+                }
+            }
+            '~' => {
+                self.advance();
+                TokenKind::BitwiseNot
+            }
+
+            // Null coalesce and nullsafe operators
             '?' => {
                 self.advance();
                 if self.current() == Some('?') {
                     self.advance();
-                    TokenKind::NullCoalesce
+                    if self.current() == Some('=') {
+                        self.advance();
+                        TokenKind::NullCoalesceAssign
+                    } else {
+                        TokenKind::NullCoalesce
+                    }
+                } else if self.current() == Some('-') && self.peek(1) == Some('>') {
+                    self.advance_by(2);
+                    TokenKind::NullsafeArrow
                 } else {
                     TokenKind::QuestionMark
                 }
@@ -212,12 +266,7 @@ impl Lexer {
             }
 
             // Single-character punctuation (handled elsewhere in main lexer)
-            _ => {
-                return Err(format!(
-                    "Unexpected operator character '{}' at line {}, column {}",
-                    ch, line, column
-                ))
-            }
+            _ => return Err(LexError::UnexpectedChar { ch, line, column }),
         };
 
         Ok(token_kind)
@@ -0,0 +1,394 @@
+//! The source as PHP's tokenizer extension sees it
+//!
+//! `tokenize` runs the lexer over the source and keeps what it skips: the
+//! whitespace, comments and inline HTML between tokens, so the texts of
+//! the tokens put together give back the source. Each token gets the
+//! `T_*` name PHP's lexer gives it; the lexer's keyword tokens, the
+//! qualified names and the `&` before a variable are named the way PHP
+//! names them. A string or heredoc with interpolations is split into its
+//! quotes (or its opening and closing lines), its text and the tokens of
+//! each interpolation. A source the lexer can't read still tokenizes: a stray
+//! character becomes `T_BAD_CHARACTER` and an unterminated string
+//! `T_ENCAPSED_AND_WHITESPACE` to the end.
+
+use super::encapsed_tokens::split_interpolated;
+use super::token_ids::token_id;
+use super::{LexError, Lexer};
+use crate::token::TokenKind;
+
+/// A token with its text, the line it starts on and its byte offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceToken {
+    /// The `T_*` name, or None for a single-character token
+    pub name: Option<&'static str>,
+    pub text: String,
+    pub line: usize,
+    pub pos: usize,
+}
+
+impl SourceToken {
+    /// The token's id: its `T_*` id, or its character's code
+    pub fn id(&self) -> i64 {
+        match self.name {
+            Some(name) => token_id(name).unwrap_or(0),
+            None => self.text.chars().next().map_or(0, |c| c as i64),
+        }
+    }
+}
+
+/// What `kind_name` calls a string with interpolations, which `tokenize`
+/// splits into PHP's tokens
+const INTERPOLATED: &str = "interpolated";
+
+/// Words the lexer reads as identifiers that PHP has keyword tokens for
+const KEYWORDS: &[(&str, &str)] = &[
+    ("exit", "T_EXIT"),
+    ("die", "T_EXIT"),
+    ("print", "T_PRINT"),
+    ("isset", "T_ISSET"),
+    ("unset", "T_UNSET"),
+    ("empty", "T_EMPTY"),
+    ("eval", "T_EVAL"),
+    ("include", "T_INCLUDE"),
+    ("include_once", "T_INCLUDE_ONCE"),
+    ("require", "T_REQUIRE"),
+    ("require_once", "T_REQUIRE_ONCE"),
+    ("instanceof", "T_INSTANCEOF"),
+    ("global", "T_GLOBAL"),
+    ("var", "T_VAR"),
+    ("list", "T_LIST"),
+    ("array", "T_ARRAY"),
+    ("callable", "T_CALLABLE"),
+    ("goto", "T_GOTO"),
+    ("enddeclare", "T_ENDDECLARE"),
+    ("__halt_compiler", "T_HALT_COMPILER"),
+];
+
+/// Split `source` into PHP's tokens
+pub fn tokenize(source: &str) -> Vec<SourceToken> {
+    let mut lexer = Lexer::new(source);
+    let mut offsets: Vec<usize> = source.char_indices().map(|(i, _)| i).collect();
+    offsets.push(source.len());
+    let mut tokens: Vec<SourceToken> = Vec::new();
+    while lexer.current().is_some() {
+        let (start, line) = (lexer.pos, lexer.line);
+        let after_arrow = tokens
+            .iter()
+            .rev()
+            .find(|token| !is_ignorable(token.name))
+            .is_some_and(|token| token.name == Some("T_OBJECT_OPERATOR"));
+        let name = if lexer.in_php {
+            php_token(&mut lexer, after_arrow)
+        } else {
+            html_token(&mut lexer)
+        };
+        let text: String = lexer.input[start..lexer.pos].iter().collect();
+        let token = SourceToken {
+            name,
+            text,
+            line,
+            pos: offsets[start],
+        };
+        match token.name {
+            Some("T_START_HEREDOC") if token.text.starts_with("<<<") => {
+                tokens.extend(split_heredoc(token))
+            }
+            Some(INTERPOLATED) => tokens.extend(split_interpolated(token)),
+            _ => tokens.push(token),
+        }
+    }
+    tokens
+}
+
+/// Whether a token is whitespace, a comment or an opening tag
+pub fn is_ignorable(name: Option<&str>) -> bool {
+    matches!(
+        name,
+        Some("T_WHITESPACE" | "T_COMMENT" | "T_DOC_COMMENT" | "T_OPEN_TAG")
+    )
+}
+
+/// An opening tag, with the newline or space after `<?php`, or inline HTML
+fn html_token(lexer: &mut Lexer) -> Option<&'static str> {
+    if lexer.matches_str("<?php") {
+        lexer.advance_by(5);
+        if lexer.matches_str("\r\n") {
+            lexer.advance_by(2);
+        } else if lexer.current().is_some_and(char::is_whitespace) {
+            lexer.advance();
+        }
+        lexer.in_php = true;
+        Some("T_OPEN_TAG")
+    } else if lexer.matches_str("<?=") {
+        lexer.advance_by(3);
+        lexer.in_php = true;
+        Some("T_OPEN_TAG_WITH_ECHO")
+    } else {
+        lexer.read_html();
+        Some("T_INLINE_HTML")
+    }
+}
+
+/// A token inside PHP tags; `after_arrow` reads keywords as names, as
+/// PHP does for `$object->class`
+pub(super) fn php_token(lexer: &mut Lexer, after_arrow: bool) -> Option<&'static str> {
+    let ch = lexer.current()?;
+    let start = lexer.pos;
+    if ch.is_whitespace() {
+        lexer.skip_whitespace();
+        return Some("T_WHITESPACE");
+    }
+    if lexer.matches_str("?>") {
+        lexer.advance_by(2);
+        if lexer.matches_str("\r\n") {
+            lexer.advance_by(2);
+        } else if lexer.current() == Some('\n') {
+            lexer.advance();
+        }
+        lexer.in_php = false;
+        return Some("T_CLOSE_TAG");
+    }
+    if lexer.matches_str("#[") {
+        lexer.advance_by(2);
+        return Some("T_ATTRIBUTE");
+    }
+    if lexer.matches_str("//") || ch == '#' {
+        while lexer.current().is_some_and(|c| c != '\n') && !lexer.matches_str("?>") {
+            lexer.advance();
+        }
+        return Some("T_COMMENT");
+    }
+    if lexer.matches_str("/*") {
+        let doc = lexer.matches_str("/**") && lexer.peek(3).is_some_and(char::is_whitespace);
+        lexer.skip_multi_line_comment();
+        return Some(if doc { "T_DOC_COMMENT" } else { "T_COMMENT" });
+    }
+    if ch == '&' && !matches!(lexer.peek(1), Some('&' | '=')) {
+        lexer.advance();
+        let mut offset = 0;
+        while lexer.peek(offset).is_some_and(char::is_whitespace) {
+            offset += 1;
+        }
+        let before_variable =
+            lexer.peek(offset) == Some('$') || (0..3).all(|i| lexer.peek(offset + i) == Some('.'));
+        return Some(if before_variable {
+            "T_AMPERSAND_FOLLOWED_BY_VAR_OR_VARARG"
+        } else {
+            "T_AMPERSAND_NOT_FOLLOWED_BY_VAR_OR_VARARG"
+        });
+    }
+    let starts_name = |c: Option<char>| c.is_some_and(|c| c.is_alphabetic() || c == '_');
+    if starts_name(Some(ch)) || (ch == '\\' && starts_name(lexer.peek(1))) {
+        return Some(name(lexer, after_arrow));
+    }
+    if ch == '$' && !starts_name(lexer.peek(1)) {
+        lexer.advance();
+        return None;
+    }
+    let (line, column) = (lexer.line, lexer.column);
+    match lexer.tokenize_php_element(ch, line, column) {
+        Ok(kind) => kind_name(&kind, &lexer.input[start..lexer.pos]),
+        Err(LexError::UnexpectedChar { .. }) => {
+            lexer.advance();
+            Some("T_BAD_CHARACTER")
+        }
+        Err(_) => {
+            while lexer.current().is_some() {
+                lexer.advance();
+            }
+            Some("T_ENCAPSED_AND_WHITESPACE")
+        }
+    }
+}
+
+/// A name, keyword or magic constant, qualified names included
+fn name(lexer: &mut Lexer, after_arrow: bool) -> &'static str {
+    let mut text = String::new();
+    if lexer.current() == Some('\\') {
+        lexer.advance();
+        text.push('\\');
+    }
+    loop {
+        text.push_str(&lexer.read_identifier());
+        let more = lexer.peek(1).is_some_and(|c| c.is_alphabetic() || c == '_');
+        if lexer.current() != Some('\\') || !more {
+            break;
+        }
+        lexer.advance();
+        text.push('\\');
+    }
+    if text.starts_with('\\') {
+        return "T_NAME_FULLY_QUALIFIED";
+    }
+    if text.to_lowercase().starts_with("namespace\\") {
+        return "T_NAME_RELATIVE";
+    }
+    if text.contains('\\') {
+        return "T_NAME_QUALIFIED";
+    }
+    if after_arrow {
+        return "T_STRING";
+    }
+    let lower = text.to_lowercase();
+    if let Some((_, token)) = KEYWORDS.iter().find(|(word, _)| *word == lower) {
+        return token;
+    }
+    let kind = lexer.keyword_or_identifier(&text);
+    if kind == TokenKind::Yield {
+        let mut offset = 0;
+        while lexer.peek(offset).is_some_and(char::is_whitespace) {
+            offset += 1;
+        }
+        let from: String = (0..5).filter_map(|i| lexer.peek(offset + i)).collect();
+        let ends = !lexer
+            .peek(offset + 4)
+            .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        if offset > 0 && from.to_lowercase().starts_with("from") && ends {
+            lexer.advance_by(offset + 4);
+            return "T_YIELD_FROM";
+        }
+    }
+    let text: Vec<char> = text.chars().collect();
+    kind_name(&kind, &text).unwrap_or("T_STRING")
+}
+
+/// The `T_*` name of a token the lexer read from `text`, which tells a
+/// heredoc from a quoted string, `&&` from `and` and `||` from `or`
+fn kind_name(kind: &TokenKind, text: &[char]) -> Option<&'static str> {
+    use TokenKind::*;
+    Some(match kind {
+        Variable(_) => "T_VARIABLE",
+        Integer(_) => "T_LNUMBER",
+        Float(_) => "T_DNUMBER",
+        String(_) if text.starts_with(&['<', '<', '<']) => "T_START_HEREDOC",
+        String(_) => "T_CONSTANT_ENCAPSED_STRING",
+        InterpolatedString(_) => INTERPOLATED,
+        Identifier(_) | True | False | Null | Fiber | With | Get | Set | Parent | From => {
+            "T_STRING"
+        }
+        Echo => "T_ECHO",
+        If => "T_IF",
+        Else => "T_ELSE",
+        Elseif => "T_ELSEIF",
+        While => "T_WHILE",
+        For => "T_FOR",
+        Foreach => "T_FOREACH",
+        As => "T_AS",
+        Switch => "T_SWITCH",
+        Case => "T_CASE",
+        Default => "T_DEFAULT",
+        Break => "T_BREAK",
+        Continue => "T_CONTINUE",
+        Do => "T_DO",
+        Yield => "T_YIELD",
+        Endif => "T_ENDIF",
+        Endwhile => "T_ENDWHILE",
+        Endfor => "T_ENDFOR",
+        Endforeach => "T_ENDFOREACH",
+        Endswitch => "T_ENDSWITCH",
+        Function => "T_FUNCTION",
+        Fn => "T_FN",
+        Return => "T_RETURN",
+        Match => "T_MATCH",
+        Class => "T_CLASS",
+        New => "T_NEW",
+        Public => "T_PUBLIC",
+        Private => "T_PRIVATE",
+        Protected => "T_PROTECTED",
+        Extends => "T_EXTENDS",
+        Interface => "T_INTERFACE",
+        Implements => "T_IMPLEMENTS",
+        Trait => "T_TRAIT",
+        Use => "T_USE",
+        Insteadof => "T_INSTEADOF",
+        Readonly => "T_READONLY",
+        Enum => "T_ENUM",
+        Clone => "T_CLONE",
+        Abstract => "T_ABSTRACT",
+        Final => "T_FINAL",
+        Static => "T_STATIC",
+        Namespace => "T_NAMESPACE",
+        Const => "T_CONST",
+        Declare => "T_DECLARE",
+        Try => "T_TRY",
+        Catch => "T_CATCH",
+        Finally => "T_FINALLY",
+        Throw => "T_THROW",
+        PlusAssign => "T_PLUS_EQUAL",
+        MinusAssign => "T_MINUS_EQUAL",
+        MulAssign => "T_MUL_EQUAL",
+        DivAssign => "T_DIV_EQUAL",
+        ModAssign => "T_MOD_EQUAL",
+        ConcatAssign => "T_CONCAT_EQUAL",
+        PowAssign => "T_POW_EQUAL",
+        BitwiseAndAssign => "T_AND_EQUAL",
+        BitwiseOrAssign => "T_OR_EQUAL",
+        BitwiseXorAssign => "T_XOR_EQUAL",
+        ShiftLeftAssign => "T_SL_EQUAL",
+        ShiftRightAssign => "T_SR_EQUAL",
+        NullCoalesceAssign => "T_COALESCE_EQUAL",
+        Pow => "T_POW",
+        Equal => "T_IS_EQUAL",
+        Identical => "T_IS_IDENTICAL",
+        NotEqual => "T_IS_NOT_EQUAL",
+        NotIdentical => "T_IS_NOT_IDENTICAL",
+        LessEqual => "T_IS_SMALLER_OR_EQUAL",
+        GreaterEqual => "T_IS_GREATER_OR_EQUAL",
+        Spaceship => "T_SPACESHIP",
+        And if text == ['&', '&'] => "T_BOOLEAN_AND",
+        And => "T_LOGICAL_AND",
+        Or if text == ['|', '|'] => "T_BOOLEAN_OR",
+        Or => "T_LOGICAL_OR",
+        Xor => "T_LOGICAL_XOR",
+        ShiftLeft => "T_SL",
+        ShiftRight => "T_SR",
+        Increment => "T_INC",
+        Decrement => "T_DEC",
+        NullCoalesce => "T_COALESCE",
+        DoubleArrow => "T_DOUBLE_ARROW",
+        Arrow => "T_OBJECT_OPERATOR",
+        NullsafeArrow => "T_NULLSAFE_OBJECT_OPERATOR",
+        DoubleColon => "T_PAAMAYIM_NEKUDOTAYIM",
+        Pipe => "T_PIPE",
+        Ellipsis => "T_ELLIPSIS",
+        Backslash => "T_NS_SEPARATOR",
+        MagicFile => "T_FILE",
+        MagicLine => "T_LINE",
+        MagicDir => "T_DIR",
+        MagicFunction => "T_FUNC_C",
+        MagicClass => "T_CLASS_C",
+        MagicMethod => "T_METHOD_C",
+        MagicNamespace => "T_NS_C",
+        MagicTrait => "T_TRAIT_C",
+        _ => return None,
+    })
+}
+
+/// A heredoc or nowdoc as its opening line, its body and its closing label
+fn split_heredoc(token: SourceToken) -> Vec<SourceToken> {
+    let text = &token.text;
+    let open = text.find('\n').map_or(text.len(), |i| i + 1);
+    let close = text.rfind('\n').map_or(text.len(), |i| i + 1).max(open);
+    let mut parts = vec![(Some("T_START_HEREDOC"), &text[..open])];
+    if close > open {
+        parts.push((Some("T_ENCAPSED_AND_WHITESPACE"), &text[open..close]));
+    }
+    if close < text.len() {
+        parts.push((Some("T_END_HEREDOC"), &text[close..]));
+    }
+    let (mut line, mut pos) = (token.line, token.pos);
+    parts
+        .into_iter()
+        .map(|(name, text)| {
+            let part = SourceToken {
+                name,
+                text: text.to_string(),
+                line,
+                pos,
+            };
+            line += text.matches('\n').count();
+            pos += text.len();
+            part
+        })
+        .collect()
+}
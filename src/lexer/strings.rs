@@ -2,7 +2,9 @@
 ///
 /// Handles parsing of string literals with proper escape sequence support
 /// for both single-quoted and double-quoted strings.
-use crate::lexer::Lexer;
+use super::interpolation::{string_token, Closing};
+use crate::lexer::{LexError, Lexer};
+use crate::token::TokenKind;
 
 impl Lexer {
     /// Parses a string literal starting from the current position.
@@ -15,8 +17,8 @@ impl Lexer {
     ///
     /// # Returns
     /// * `Ok(String)` - The parsed string content with escape sequences processed
-    /// * `Err(String)` - Error message if the string is not properly terminated
-    pub fn read_string(&mut self, quote: char) -> Result<String, String> {
+    /// * `Err(LexError)` - If the string is not properly terminated
+    pub fn read_string(&mut self, quote: char) -> Result<String, LexError> {
         let start_line = self.line;
         self.advance(); // consume opening quote
         let mut value = String::new();
@@ -27,24 +29,7 @@ impl Lexer {
                 return Ok(value);
             } else if ch == '\\' && quote == '"' {
                 // Only process escape sequences in double-quoted strings
-                self.advance();
-                if let Some(escaped) = self.current() {
-                    let (push_backslash, push_escaped) = match escaped {
-                        'n' => (false, '\n'),
-                        't' => (false, '\t'),
-                        'r' => (false, '\r'),
-                        '\\' => (false, '\\'),
-                        '\'' => (false, '\''),
-                        '"' => (false, '"'),
-                        '$' => (false, '$'),
-                        _ => (true, escaped), // Keep backslash for unrecognized escapes
-                    };
-                    if push_backslash {
-                        value.push('\\');
-                    }
-                    value.push(push_escaped);
-                    self.advance();
-                }
+                self.read_escape(&mut value);
             } else if ch == '\\' && quote == '\'' {
                 // In single-quoted strings, only \' and \\ are escapes
                 self.advance();
@@ -65,268 +50,120 @@ impl Lexer {
             }
         }
 
-        Err(format!(
-            "Unterminated string starting at line {}",
-            start_line
-        ))
+        Err(LexError::UnterminatedString { line: start_line })
     }
 
-    /// Reads a heredoc or nowdoc string.
-    pub fn read_heredoc_nowdoc(&mut self, is_nowdoc: bool) -> Result<String, String> {
-        let start_line = self.line;
-        let mut marker = String::new();
-
-        if is_nowdoc {
-            while let Some(ch) = self.current() {
-                if ch == '\'' {
-                    self.advance();
-                    break;
-                } else if ch.is_alphanumeric() || ch == '_' {
-                    marker.push(ch);
-                    self.advance();
-                } else {
-                    return Err(format!("Expected nowdoc identifier at line {}", start_line));
-                }
-            }
-        } else {
-            while let Some(ch) = self.current() {
-                if ch.is_alphanumeric() || ch == '_' {
-                    marker.push(ch);
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
-        }
-
-        if marker.is_empty() {
-            return Err(format!(
-                "Expected heredoc/nowdoc identifier at line {}",
-                start_line
-            ));
-        }
-
-        while let Some(ch) = self.current() {
-            if ch == '\n' {
-                self.advance();
-                break;
+    /// Reads the escape sequence of a double-quoted string at its
+    /// backslash into `value`; the backslash of an unrecognized escape is
+    /// kept
+    pub(super) fn read_escape(&mut self, value: &mut String) {
+        self.advance(); // consume '\\'
+        if let Some(escaped) = self.current() {
+            let (push_backslash, push_escaped) = match escaped {
+                'n' => (false, '\n'),
+                't' => (false, '\t'),
+                'r' => (false, '\r'),
+                '\\' => (false, '\\'),
+                '\'' => (false, '\''),
+                '"' => (false, '"'),
+                '$' => (false, '$'),
+                _ => (true, escaped),
+            };
+            if push_backslash {
+                value.push('\\');
             }
+            value.push(push_escaped);
             self.advance();
         }
+    }
 
-        let mut chars: Vec<char> = Vec::new();
-
-        while let Some(ch) = self.current() {
-            if ch == '\n' {
-                let mut pos = self.pos + 1;
-                let mut is_end_marker = true;
-                for expected_ch in marker.chars() {
-                    if let Some(&actual_ch) = self.input.get(pos) {
-                        if actual_ch != expected_ch {
-                            is_end_marker = false;
-                            break;
-                        }
-                        pos += 1;
-                    } else {
-                        is_end_marker = false;
-                        break;
-                    }
-                }
-
-                if is_end_marker {
-                    if let Some(&next_ch) = self.input.get(pos) {
-                        if next_ch == ';'
-                            || next_ch == '\n'
-                            || (!next_ch.is_alphanumeric() && next_ch != '_')
-                        {
-                            self.pos = pos;
-                            if is_nowdoc {
-                                return Ok(chars.iter().collect());
-                            } else {
-                                return Ok(self.process_heredoc_content(&chars));
-                            }
-                        }
-                    }
-                }
-
-                chars.push(ch);
-                self.advance();
-            } else {
-                chars.push(ch);
+    /// Reads a heredoc or nowdoc after its `<<<`. A nowdoc (`<<<'EOD'`) is
+    /// plain text; a heredoc (`<<<EOD` or `<<<"EOD"`) is interpolated like
+    /// a double-quoted string. The body is the lines up to the closing
+    /// label, which may be indented: as much indentation is left out of
+    /// every line.
+    pub(super) fn read_heredoc(&mut self) -> Result<TokenKind, LexError> {
+        let start_line = self.line;
+        let quote = match self.current() {
+            Some(quote @ ('\'' | '"')) => {
                 self.advance();
+                Some(quote)
             }
+            _ => None,
+        };
+        let mut label = String::new();
+        while let Some(ch) = self
+            .current()
+            .filter(|&ch| ch.is_alphanumeric() || ch == '_')
+        {
+            label.push(ch);
+            self.advance();
         }
-
-        Err(format!(
-            "Unterminated heredoc/nowdoc starting at line {} (missing closing marker: {})",
-            start_line, marker
-        ))
-    }
-
-    fn process_heredoc_content(&self, chars: &[char]) -> String {
-        let mut result = String::new();
-        let mut i = 0;
-
-        while i < chars.len() {
-            let ch = chars[i];
-
-            if ch == '\\' && i + 1 < chars.len() {
-                let escaped = chars[i + 1];
-                let (push_backslash, push_escaped) = match escaped {
-                    'n' => (false, '\n'),
-                    't' => (false, '\t'),
-                    'r' => (false, '\r'),
-                    '\\' => (false, '\\'),
-                    '\'' => (false, '\''),
-                    '"' => (false, '"'),
-                    '$' => (false, '$'),
-                    _ => (true, escaped),
-                };
-                if push_backslash {
-                    result.push('\\');
-                }
-                result.push(push_escaped);
-                i += 2;
-            } else if ch == '$' {
-                if i + 1 < chars.len() {
-                    let next_ch = chars[i + 1];
-                    if next_ch == '{' {
-                        result.push_str(&self.parse_heredoc_brace_var(chars, &mut i));
-                    } else if next_ch.is_alphanumeric() || next_ch == '_' {
-                        result.push_str(&self.parse_heredoc_simple_var(chars, &mut i));
-                    } else if next_ch == '$' {
-                        result.push('$');
-                        i += 2;
-                    } else {
-                        result.push(ch);
-                        i += 1;
-                    }
-                } else {
-                    result.push(ch);
-                    i += 1;
-                }
-            } else {
-                result.push(ch);
-                i += 1;
+        if let Some(quote) = quote {
+            if self.current() != Some(quote) {
+                return Err(LexError::InvalidHeredocLabel { line: start_line });
             }
+            self.advance();
         }
-
-        result
-    }
-
-    fn parse_heredoc_brace_var(&self, chars: &[char], i: &mut usize) -> String {
-        *i += 2;
-        let mut var_name = String::new();
-        let mut in_array = false;
-        let mut brace_depth = 1;
-
-        while *i < chars.len() {
-            match chars[*i] {
-                '{' if !in_array => {
-                    brace_depth += 1;
-                    var_name.push('{');
-                    *i += 1;
-                }
-                '}' if !in_array => {
-                    brace_depth -= 1;
-                    if brace_depth == 0 {
-                        *i += 1;
-                        return format!("\x00${{{}}}\x00", var_name);
-                    } else {
-                        var_name.push('}');
-                        *i += 1;
-                    }
-                }
-                '[' if !in_array => {
-                    in_array = true;
-                    var_name.push('[');
-                    *i += 1;
-                }
-                ']' if in_array => {
-                    in_array = false;
-                    var_name.push(']');
-                    *i += 1;
-                }
-                _ => {
-                    var_name.push(chars[*i]);
-                    *i += 1;
-                }
-            }
+        if label.is_empty() {
+            return Err(LexError::InvalidHeredocLabel { line: start_line });
         }
 
-        chars.iter().collect()
-    }
-
-    fn parse_heredoc_simple_var(&self, chars: &[char], i: &mut usize) -> String {
-        *i += 1;
-        let mut var_name = String::new();
-        let mut chars_consumed = 0;
-
-        while *i + chars_consumed < chars.len() {
-            let ch = chars[*i + chars_consumed];
-            if ch.is_alphanumeric() || ch == '_' {
-                var_name.push(ch);
-                chars_consumed += 1;
-            } else {
+        // The body starts on the next line
+        while let Some(ch) = self.current() {
+            self.advance();
+            if ch == '\n' {
                 break;
             }
         }
 
-        *i += chars_consumed;
-
-        if var_name.is_empty() {
-            return "$".to_string();
-        }
-
-        if *i < chars.len() && chars[*i] == '[' {
-            let mut array_access = String::new();
-            let mut bracket_depth = 1;
-            *i += 1;
-
-            while *i < chars.len() && bracket_depth > 0 {
-                match chars[*i] {
-                    '[' => {
-                        bracket_depth += 1;
-                        array_access.push('[');
-                        *i += 1;
-                    }
-                    ']' => {
-                        bracket_depth -= 1;
-                        if bracket_depth == 0 {
-                            array_access.push(']');
-                            *i += 1;
-                        } else {
-                            array_access.push(']');
-                            *i += 1;
-                        }
-                    }
-                    _ => {
-                        array_access.push(chars[*i]);
-                        *i += 1;
-                    }
+        let Some((label_line, indent)) = self.find_closing_label(&label) else {
+            return Err(LexError::UnterminatedHeredoc {
+                label,
+                line: start_line,
+            });
+        };
+        // The newline before the closing label is not part of the body
+        let end = label_line.saturating_sub(1).max(self.pos);
+        let closing = Closing::Body { end, indent };
+        let token = if quote == Some('\'') {
+            let mut text = String::new();
+            self.skip_indent(end, indent);
+            while self.pos < end {
+                let ch = self.advance().unwrap_or_default();
+                text.push(ch);
+                if ch == '\n' {
+                    self.skip_indent(end, indent);
                 }
             }
+            TokenKind::String(text)
+        } else {
+            string_token(self.read_interpolated(closing)?)
+        };
+        self.advance_by(label_line + indent + label.chars().count() - self.pos);
+        Ok(token)
+    }
 
-            return format!("\x00${}{}\x00", var_name, array_access);
-        }
-
-        if *i + 1 < chars.len() && chars[*i] == '-' && chars[*i + 1] == '>' {
-            let mut prop_access = String::from("->");
-            *i += 2;
-
-            while *i < chars.len() {
-                let ch = chars[*i];
-                if ch.is_alphanumeric() || ch == '_' {
-                    prop_access.push(ch);
-                    *i += 1;
-                } else {
-                    break;
-                }
+    /// The position of the line the closing `label` is on, from the
+    /// current position on, and the spaces or tabs before the label
+    fn find_closing_label(&self, label: &str) -> Option<(usize, usize)> {
+        let label: Vec<char> = label.chars().collect();
+        let mut line = self.pos;
+        while line < self.input.len() {
+            let indent = self.input[line..]
+                .iter()
+                .take_while(|&&ch| ch == ' ' || ch == '\t')
+                .count();
+            let at = line + indent;
+            let ends = !self
+                .input
+                .get(at + label.len())
+                .is_some_and(|&ch| ch.is_alphanumeric() || ch == '_');
+            if self.input[at..].starts_with(&label) && ends {
+                return Some((line, indent));
             }
-
-            return format!("\x00${}{}\x00", var_name, prop_access);
+            line = at + self.input[at..].iter().position(|&ch| ch == '\n')? + 1;
         }
-
-        format!("\x00${}\x00", var_name)
+        None
     }
 }
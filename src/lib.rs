@@ -0,0 +1,52 @@
+//! VHP: Vibe-coded Hypertext Preprocessor
+//!
+//! Library crate behind the `vhp` binary, for Rust programs that embed
+//! the engine. The binary (`src/main.rs`) only parses arguments and calls
+//! into this crate.
+//!
+//! Running a script takes one call:
+//!
+//! ```
+//! let mut output = Vec::new();
+//! vhp::run("<?php echo 1 + 2;", &mut output).unwrap();
+//! assert_eq!(output, b"3");
+//! ```
+//!
+//! `Engine` keeps one VM across scripts, for hosts that evaluate PHP as
+//! a scripting layer and call its functions from Rust.
+//!
+//! The pipeline stages are public too: `lexer` (source to tokens),
+//! `parser` (tokens to the `ast`), `vm::compiler` (AST to bytecode) and
+//! `vm` (execution, with built-ins in `runtime`). `compile` and
+//! `VM::load` join them up for hosts that configure the VM before running
+//! it. The tools built on the same pipeline are here as well: the
+//! `debugger`, the static `linter` and `checker`, the source `printer`,
+//! the .vhpt `test_runner`, and the `fuzz` and `wasm` entry points. `ffi`
+//! is the C API of the dynamic library built alongside, and `cgi` and
+//! `server` answer web requests.
+
+// Lets `#[vhp_builtin]` and `#[derive(Codec)]` expansions name `::vhp`
+// inside this crate too
+extern crate self as vhp;
+
+pub mod ast;
+pub mod cgi;
+pub mod checker;
+pub mod debugger;
+pub mod diagnostic;
+mod engine;
+pub mod ffi;
+pub mod fuzz;
+pub mod lexer;
+pub mod linter;
+pub mod parser;
+pub mod printer;
+pub mod runtime;
+pub mod server;
+pub mod test_runner;
+pub mod token;
+pub mod vm;
+pub mod wasm;
+
+pub use engine::{compile, exit_status, run, Engine, Error};
+pub use vhp_macros::vhp_builtin;
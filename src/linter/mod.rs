@@ -0,0 +1,352 @@
+//! Static linter for `vhp lint`
+//!
+//! Parses PHP files without running them and checks them against a set of
+//! rules. Most rules walk the AST (`rules`); missing visibility modifiers
+//! are not recorded in the AST, so that rule scans the token stream
+//! (`visibility`). Each rule can be turned off or reported as a warning or
+//! an error.
+
+mod options;
+mod rules;
+mod visibility;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{NamespaceBody, Program, Stmt};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::runtime::builtins::json_encode;
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::compiler::CompileError;
+pub use options::{LintFormat, LintOptions, CONFIG_FILE};
+
+/// A lint check that can be configured by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// The file does not lex or parse
+    Syntax,
+    /// A local variable is assigned but never read
+    UnusedVariable,
+    /// Statements after return, throw, break, continue or exit
+    UnreachableCode,
+    /// `=` used as a whole if/while/for condition
+    AssignmentInCondition,
+    /// Class member declared without public/protected/private
+    MissingVisibility,
+    /// Call to a function that is neither built in nor declared
+    UndefinedFunction,
+    /// A function parameter the body never reads
+    UnusedParameter,
+    /// A private method or property its class never uses
+    UnusedPrivateMember,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 8] = [
+        Rule::Syntax,
+        Rule::UnusedVariable,
+        Rule::UnreachableCode,
+        Rule::AssignmentInCondition,
+        Rule::MissingVisibility,
+        Rule::UndefinedFunction,
+        Rule::UnusedParameter,
+        Rule::UnusedPrivateMember,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Syntax => "syntax",
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::AssignmentInCondition => "assignment-in-condition",
+            Rule::MissingVisibility => "missing-visibility",
+            Rule::UndefinedFunction => "undefined-function",
+            Rule::UnusedParameter => "unused-parameter",
+            Rule::UnusedPrivateMember => "unused-private-member",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::Syntax | Rule::UndefinedFunction => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Off,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Off => "off",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Severity> {
+        match name {
+            "off" | "none" | "ignore" => Some(Severity::Off),
+            "warn" | "warning" => Some(Severity::Warning),
+            "error" | "deny" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+/// One finding, located by file and line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+}
+
+pub struct Linter {
+    options: LintOptions,
+}
+
+impl Linter {
+    pub fn new(options: LintOptions) -> Self {
+        Self { options }
+    }
+
+    /// Lint every file under the configured paths
+    ///
+    /// Functions declared in any of the files count as defined in all of
+    /// them, since they may be loaded together with require().
+    ///
+    /// ```
+    /// use vhp::linter::{LintOptions, Linter, Rule, Severity};
+    ///
+    /// let dir = std::env::temp_dir().join(format!("vhp-lint-doc-{}", std::process::id()));
+    /// std::fs::create_dir_all(&dir).unwrap();
+    /// std::fs::write(dir.join("helpers.php"), "<?php function helper() { return 1; }").unwrap();
+    /// std::fs::write(dir.join("broken.php"), "<?php echo (;").unwrap();
+    /// std::fs::write(
+    ///     dir.join("main.php"),
+    ///     r#"<?php
+    /// function greet($name, $unused) {
+    ///     $greeting = "Hello";
+    ///     return "Hi " . $name;
+    ///     echo "never";
+    /// }
+    /// if ($x = greet(helper(), 2)) {
+    ///     missing_function();
+    /// }
+    /// class Box {
+    ///     private $secret;
+    ///     function open() { return 1; }
+    /// }
+    /// "#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let args = ["--rule".to_string(), "unreachable-code=error".to_string()];
+    /// let mut options = LintOptions::from_args(&args).unwrap();
+    /// options.paths = vec![dir.clone()];
+    /// options.severities.insert(Rule::AssignmentInCondition, Severity::Off);
+    /// let found: Vec<_> = Linter::new(options)
+    ///     .run()
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|d| (d.file.rsplit('/').next().unwrap().to_string(), d.line, d.rule, d.severity))
+    ///     .collect();
+    /// assert_eq!(
+    ///     found,
+    ///     [
+    ///         ("broken.php".to_string(), 1, Rule::Syntax, Severity::Error),
+    ///         ("main.php".to_string(), 2, Rule::UnusedParameter, Severity::Warning),
+    ///         ("main.php".to_string(), 3, Rule::UnusedVariable, Severity::Warning),
+    ///         ("main.php".to_string(), 5, Rule::UnreachableCode, Severity::Error),
+    ///         ("main.php".to_string(), 8, Rule::UndefinedFunction, Severity::Error),
+    ///         ("main.php".to_string(), 10, Rule::UnusedPrivateMember, Severity::Warning),
+    ///         ("main.php".to_string(), 12, Rule::MissingVisibility, Severity::Warning),
+    ///     ]
+    /// );
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn run(&self) -> Result<Vec<Diagnostic>, String> {
+        let mut files = Vec::new();
+        for path in &self.options.paths {
+            discover(path, &mut files)?;
+        }
+
+        let mut parsed = Vec::new();
+        let mut diagnostics = Vec::new();
+        let mut functions = HashSet::new();
+        for path in files {
+            let file = path.display().to_string();
+            let source = fs::read_to_string(&path)
+                .map_err(|e| format!("Error reading file '{}': {}", file, e))?;
+            match parse(&source) {
+                Ok(program) => {
+                    collect_functions(&program.statements, &mut functions);
+                    parsed.push((file, source, program));
+                }
+                Err(e) => diagnostics.push(Diagnostic {
+                    line: e.line(),
+                    file,
+                    rule: Rule::Syntax,
+                    severity: self.options.severity(Rule::Syntax),
+                    message: e.to_string(),
+                }),
+            }
+        }
+
+        for (file, source, program) in &parsed {
+            let mut found = rules::check(&program.statements, &functions);
+            found.extend(visibility::check(source));
+            diagnostics.extend(found.into_iter().map(|(line, rule, message)| Diagnostic {
+                file: file.clone(),
+                line,
+                rule,
+                severity: self.options.severity(rule),
+                message,
+            }));
+        }
+
+        diagnostics.retain(|d| d.severity != Severity::Off);
+        diagnostics.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        Ok(diagnostics)
+    }
+
+    /// Print diagnostics in the configured format
+    pub fn report(&self, diagnostics: &[Diagnostic]) {
+        match self.options.format {
+            LintFormat::Text => {
+                for d in diagnostics {
+                    println!(
+                        "{}:{}: {}[{}]: {}",
+                        d.file,
+                        d.line,
+                        d.severity.name(),
+                        d.rule.name(),
+                        d.message
+                    );
+                }
+                let errors = count(diagnostics, Severity::Error);
+                let warnings = count(diagnostics, Severity::Warning);
+                if errors + warnings > 0 {
+                    eprintln!("{} error(s), {} warning(s)", errors, warnings);
+                }
+            }
+            LintFormat::Json => println!("{}", to_json(diagnostics)),
+        }
+    }
+}
+
+fn count(diagnostics: &[Diagnostic], severity: Severity) -> usize {
+    diagnostics
+        .iter()
+        .filter(|d| d.severity == severity)
+        .count()
+}
+
+pub(crate) fn parse(source: &str) -> Result<Program, CompileError> {
+    let tokens = Lexer::new(source).tokenize()?;
+    Ok(Parser::new(tokens).parse()?)
+}
+
+/// Lowercased names of all functions declared at file or namespace level
+fn collect_functions(statements: &[Stmt], functions: &mut HashSet<String>) {
+    for stmt in statements {
+        match stmt {
+            Stmt::Function { name, body, .. } => {
+                functions.insert(name.to_lowercase());
+                // Functions declared inside functions exist once the outer one runs
+                collect_functions(body, functions);
+            }
+            Stmt::Namespace {
+                body: NamespaceBody::Braced(body),
+                ..
+            } => collect_functions(body, functions),
+            Stmt::If {
+                then_branch,
+                elseif_branches,
+                else_branch,
+                ..
+            } => {
+                // Conditional declarations: if (!function_exists('f')) { function f() {} }
+                collect_functions(then_branch, functions);
+                for (_, branch) in elseif_branches {
+                    collect_functions(branch, functions);
+                }
+                if let Some(branch) = else_branch {
+                    collect_functions(branch, functions);
+                }
+            }
+            Stmt::Declare {
+                body: Some(body), ..
+            } => collect_functions(body, functions),
+            _ => {}
+        }
+    }
+}
+
+/// Add `path` if it is a file, or every *.php file below it if it is a directory
+pub(crate) fn discover(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if path.is_file() {
+        if !files.iter().any(|f| f == path) {
+            files.push(path.to_path_buf());
+        }
+        return Ok(());
+    }
+    if !path.is_dir() {
+        return Err(format!("Path does not exist: {:?}", path));
+    }
+    let entries = fs::read_dir(path).map_err(|e| format!("Error reading {:?}: {}", path, e))?;
+    let mut entries: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden || entry.ends_with("target") {
+            continue;
+        }
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "php") {
+            discover(&entry, files)?;
+        }
+    }
+    Ok(())
+}
+
+fn to_json(diagnostics: &[Diagnostic]) -> String {
+    let items = diagnostics
+        .iter()
+        .enumerate()
+        .map(|(i, d)| {
+            let fields = [
+                ("file", Value::String(d.file.clone())),
+                ("line", Value::Integer(d.line as i64)),
+                ("rule", Value::String(d.rule.name().to_string())),
+                ("severity", Value::String(d.severity.name().to_string())),
+                ("message", Value::String(d.message.clone())),
+            ];
+            let object = fields
+                .into_iter()
+                .map(|(key, value)| (ArrayKey::String(key.to_string()), value))
+                .collect();
+            (ArrayKey::Integer(i as i64), Value::Array(object))
+        })
+        .collect();
+    match json_encode(&[Value::Array(items)]) {
+        Ok(Value::String(json)) => json,
+        _ => "[]".to_string(),
+    }
+}
@@ -0,0 +1,147 @@
+//! Command-line options and rule configuration for `vhp lint`
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Rule, Severity};
+
+/// Configuration file read from the working directory when present
+pub const CONFIG_FILE: &str = ".vhplint";
+
+/// How diagnostics are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LintFormat {
+    /// `file:line: severity[rule]: message`
+    #[default]
+    Text,
+    /// A JSON array of diagnostic objects
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    /// PHP files and/or directories searched for *.php files
+    pub paths: Vec<PathBuf>,
+    pub format: LintFormat,
+    /// Severity of each rule, starting from `Rule::default_severity`
+    pub severities: HashMap<Rule, Severity>,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            paths: vec![PathBuf::from(".")],
+            format: LintFormat::Text,
+            severities: Rule::ALL
+                .iter()
+                .map(|rule| (*rule, rule.default_severity()))
+                .collect(),
+        }
+    }
+}
+
+impl LintOptions {
+    /// Parse the arguments following `vhp lint`
+    ///
+    /// Rule severities come from `.vhplint` (or `--config <file>`), then
+    /// from `--rule name=severity` flags, which take precedence.
+    /// `--deny-warnings` then turns every warning into an error, so CI
+    /// fails on them.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = LintOptions::default();
+        let mut paths = Vec::new();
+        let mut config = None;
+        let mut overrides = Vec::new();
+        let mut deny_warnings = false;
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| iter.next().cloned())
+                    .ok_or_else(|| format!("{} requires a value", flag))
+            };
+
+            match flag {
+                "--format" => {
+                    options.format = match value()?.as_str() {
+                        "text" => LintFormat::Text,
+                        "json" => LintFormat::Json,
+                        other => return Err(format!("Unknown lint format: {}", other)),
+                    }
+                }
+                "--json" => options.format = LintFormat::Json,
+                "--config" => config = Some(PathBuf::from(value()?)),
+                "--rule" => overrides.push(value()?),
+                "--deny-warnings" => deny_warnings = true,
+                _ if flag.starts_with('-') => {
+                    return Err(format!("Unknown lint option: {}", arg));
+                }
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+
+        if !paths.is_empty() {
+            options.paths = paths;
+        }
+        match config {
+            Some(path) => options.load_config(&path)?,
+            None if Path::new(CONFIG_FILE).is_file() => {
+                options.load_config(Path::new(CONFIG_FILE))?
+            }
+            None => {}
+        }
+        for setting in &overrides {
+            options.set(setting)?;
+        }
+        if deny_warnings {
+            for severity in options.severities.values_mut() {
+                if *severity == Severity::Warning {
+                    *severity = Severity::Error;
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.severities
+            .get(&rule)
+            .copied()
+            .unwrap_or(rule.default_severity())
+    }
+
+    /// Read `rule = severity` lines; blank lines and `#`/`;` comments are ignored
+    fn load_config(&mut self, path: &Path) -> Result<(), String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading lint config {:?}: {}", path, e))?;
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            self.set(line)
+                .map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))?;
+        }
+        Ok(())
+    }
+
+    /// Apply one `rule=severity` setting
+    fn set(&mut self, setting: &str) -> Result<(), String> {
+        let (name, severity) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("Expected rule=severity, got '{}'", setting))?;
+        let rule = Rule::from_name(name.trim())
+            .ok_or_else(|| format!("Unknown lint rule: {}", name.trim()))?;
+        let severity = Severity::from_name(severity.trim())
+            .ok_or_else(|| format!("Unknown severity: {}", severity.trim()))?;
+        self.severities.insert(rule, severity);
+        Ok(())
+    }
+}
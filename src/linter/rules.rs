@@ -0,0 +1,334 @@
+//! AST rules: unused variables and parameters, unreachable code,
+//! assignment in condition, undefined functions and unused private
+//! members
+//!
+//! Statements carry no positions, so findings use the line of the nearest
+//! preceding `Stmt::Line` marker.
+
+use std::collections::HashSet;
+
+mod expressions;
+mod members;
+
+use super::Rule;
+use crate::ast::{
+    AssignOp, Expr, FunctionParam, Method, NamespaceBody, Property, PropertyHookBody, Stmt,
+};
+
+pub fn check(statements: &[Stmt], functions: &HashSet<String>) -> Vec<(usize, Rule, String)> {
+    let mut checker = Checker {
+        functions,
+        line: 0,
+        found: Vec::new(),
+        scope: None,
+        member_uses: None,
+    };
+    checker.block(statements);
+    checker.found
+}
+
+/// Variable use inside one function body
+#[derive(Default)]
+struct Scope {
+    /// Variables assigned with `=`, with the line of the first assignment
+    assigned: Vec<(String, usize)>,
+    read: HashSet<String>,
+    params: HashSet<String>,
+    /// compact()/extract()/get_defined_vars()/func_get_args() may touch
+    /// any variable
+    dynamic: bool,
+}
+
+struct Checker<'a> {
+    functions: &'a HashSet<String>,
+    line: usize,
+    found: Vec<(usize, Rule, String)>,
+    /// Function being checked; None at file level, where variables are
+    /// globals that other files may use
+    scope: Option<Scope>,
+    /// Members referred to in the class being checked, if any (see
+    /// `members`)
+    member_uses: Option<HashSet<String>>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, rule: Rule, message: String) {
+        self.found.push((self.line, rule, message));
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        let mut ended_by = None;
+        let mut reported = false;
+        for stmt in statements {
+            if let Stmt::Line(line) = stmt {
+                self.line = *line;
+                continue;
+            }
+            if let Some(keyword) = ended_by {
+                if !reported && !is_declaration(stmt) {
+                    self.report(
+                        Rule::UnreachableCode,
+                        format!("Unreachable code after {}", keyword),
+                    );
+                    reported = true;
+                }
+            }
+            self.stmt(stmt);
+            ended_by = ended_by.or(terminator(stmt));
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Line(_) | Stmt::Html(_) | Stmt::Break | Stmt::Continue => {}
+            Stmt::Use(_) | Stmt::GroupUse(_) | Stmt::Interface { .. } => {}
+            Stmt::Echo(exprs) => exprs.iter().for_each(|e| self.expr(e)),
+            Stmt::Expression(expr) | Stmt::Throw(expr) => self.expr(expr),
+            Stmt::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                }
+            }
+            Stmt::Global(names) => names.iter().for_each(|name| self.write(name)),
+            Stmt::If {
+                condition,
+                then_branch,
+                elseif_branches,
+                else_branch,
+            } => {
+                self.condition(condition);
+                self.block(then_branch);
+                for (condition, branch) in elseif_branches {
+                    self.condition(condition);
+                    self.block(branch);
+                }
+                if let Some(branch) = else_branch {
+                    self.block(branch);
+                }
+            }
+            Stmt::While { condition, body } => {
+                self.condition(condition);
+                self.block(body);
+            }
+            Stmt::DoWhile { body, condition } => {
+                self.block(body);
+                self.condition(condition);
+            }
+            Stmt::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.expr(init);
+                }
+                if let Some(condition) = condition {
+                    self.condition(condition);
+                }
+                if let Some(update) = update {
+                    self.expr(update);
+                }
+                self.block(body);
+            }
+            // Loop variables are not tracked: `foreach ($a as $k => $unused)` is common
+            Stmt::Foreach { array, body, .. } => {
+                self.expr(array);
+                self.block(body);
+            }
+            Stmt::Switch { expr, cases } => {
+                self.expr(expr);
+                for case in cases {
+                    if let Some(value) = &case.value {
+                        self.expr(value);
+                    }
+                    self.block(&case.body);
+                }
+            }
+            Stmt::Function { params, body, .. } => self.function(params, body, true),
+            Stmt::Class {
+                name,
+                trait_uses,
+                constants,
+                properties,
+                methods,
+                ..
+            } => {
+                for constant in constants {
+                    self.expr(&constant.value);
+                }
+                self.class(name, trait_uses, properties, methods);
+            }
+            Stmt::Trait {
+                properties,
+                methods,
+                ..
+            } => self.members(properties, methods),
+            Stmt::Enum {
+                cases,
+                constants,
+                methods,
+                ..
+            } => {
+                for case in cases {
+                    if let Some(value) = &case.value {
+                        self.expr(value);
+                    }
+                }
+                for constant in constants {
+                    self.expr(&constant.value);
+                }
+                self.members(&[], methods);
+            }
+            Stmt::TryCatch {
+                try_body,
+                catch_clauses,
+                finally_body,
+            } => {
+                self.block(try_body);
+                for clause in catch_clauses {
+                    self.block(&clause.body);
+                }
+                if let Some(body) = finally_body {
+                    self.block(body);
+                }
+            }
+            Stmt::Namespace { body, .. } => {
+                if let NamespaceBody::Braced(body) = body {
+                    self.block(body);
+                }
+            }
+            Stmt::Declare { body, .. } => {
+                if let Some(body) = body {
+                    self.block(body);
+                }
+            }
+        }
+    }
+
+    fn members(&mut self, properties: &[Property], methods: &[Method]) {
+        for property in properties {
+            if let Some(default) = &property.default {
+                self.expr(default);
+            }
+            for hook in &property.hooks {
+                match &hook.body {
+                    PropertyHookBody::Expression(expr) => self.expr(expr),
+                    PropertyHookBody::Block(body) => self.function(&[], body, false),
+                }
+            }
+        }
+        for method in methods {
+            self.function(&method.params, &method.body, false);
+        }
+    }
+
+    /// Check a function body in its own variable scope, and whether it
+    /// reads its parameters unless they are a method's, whose signature
+    /// may be dictated by a parent or an interface
+    fn function(&mut self, params: &[FunctionParam], body: &[Stmt], check_params: bool) {
+        for param in params {
+            if let Some(default) = &param.default {
+                self.expr(default);
+            }
+        }
+        let outer = self.scope.replace(Scope {
+            params: params.iter().map(|p| p.name.clone()).collect(),
+            ..Scope::default()
+        });
+        let line = self.line;
+        self.block(body);
+        self.line = line;
+
+        let scope = std::mem::replace(&mut self.scope, outer).unwrap_or_default();
+        if scope.dynamic {
+            return;
+        }
+        if check_params {
+            for param in params {
+                // By-reference parameters may only be written, for the caller;
+                // a leading underscore marks a parameter unused on purpose
+                let written = scope.assigned.iter().any(|(n, _)| *n == param.name);
+                if !scope.read.contains(&param.name) && !written && !param.name.starts_with('_') {
+                    self.found.push((
+                        line,
+                        Rule::UnusedParameter,
+                        format!("Parameter ${} is never used", param.name),
+                    ));
+                }
+            }
+        }
+        for (name, line) in scope.assigned {
+            if !scope.read.contains(&name) && !scope.params.contains(&name) {
+                self.found.push((
+                    line,
+                    Rule::UnusedVariable,
+                    format!("Variable ${} is assigned but never used", name),
+                ));
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr) {
+        let mut inner = condition;
+        while let Expr::Grouped(expr) = inner {
+            inner = expr;
+        }
+        if matches!(
+            inner,
+            Expr::Assign {
+                op: AssignOp::Assign,
+                ..
+            }
+        ) {
+            self.report(
+                Rule::AssignmentInCondition,
+                "Assignment used as a condition; did you mean '=='?".to_string(),
+            );
+        }
+        self.expr(condition);
+    }
+
+    fn read(&mut self, name: &str) {
+        if let Some(scope) = &mut self.scope {
+            scope.read.insert(name.to_string());
+        }
+    }
+
+    fn write(&mut self, name: &str) {
+        let line = self.line;
+        if let Some(scope) = &mut self.scope {
+            if !scope.assigned.iter().any(|(n, _)| n == name) {
+                scope.assigned.push((name.to_string(), line));
+            }
+        }
+    }
+}
+
+/// Keyword that ends control flow when `stmt` runs
+fn terminator(stmt: &Stmt) -> Option<&'static str> {
+    match stmt {
+        Stmt::Return(_) => Some("return"),
+        Stmt::Throw(_) | Stmt::Expression(Expr::Throw(_)) => Some("throw"),
+        Stmt::Break => Some("break"),
+        Stmt::Continue => Some("continue"),
+        Stmt::Expression(Expr::FunctionCall { name, .. })
+            if matches!(name.to_lowercase().as_str(), "exit" | "die") =>
+        {
+            Some("exit")
+        }
+        _ => None,
+    }
+}
+
+/// Declarations are hoisted, so they are never unreachable
+fn is_declaration(stmt: &Stmt) -> bool {
+    matches!(
+        stmt,
+        Stmt::Function { .. }
+            | Stmt::Class { .. }
+            | Stmt::Interface { .. }
+            | Stmt::Trait { .. }
+            | Stmt::Enum { .. }
+    )
+}
@@ -0,0 +1,273 @@
+//! Expression walking for the AST rules: variable reads and writes and
+//! function call checks
+
+use super::{Checker, Rule};
+use crate::ast::{Argument, AssignOp, Expr};
+use crate::vm::builtins::is_builtin;
+
+/// Functions the VM dispatches itself rather than through the builtin table
+const RUNTIME_FUNCTIONS: &[&str] = &[
+    "require",
+    "require_once",
+    "include",
+    "include_once",
+    "eval",
+    "load_psr4_class",
+];
+
+impl Checker<'_> {
+    fn call(&mut self, name: &str, args: &[Argument]) {
+        let lower = name.trim_start_matches('\\').to_lowercase();
+        let short = lower.rsplit('\\').next().unwrap_or_default().to_string();
+        match short.as_str() {
+            "extract" | "get_defined_vars" | "func_get_args" | "func_get_arg" => {
+                if let Some(scope) = &mut self.scope {
+                    scope.dynamic = true;
+                }
+            }
+            "compact" => {
+                for arg in args {
+                    if let Expr::String(var) = arg.value.as_ref() {
+                        self.read(var);
+                    }
+                }
+            }
+            _ => {}
+        }
+        let known = self.functions.contains(&lower)
+            || self.functions.contains(&short)
+            || is_builtin(&short)
+            || RUNTIME_FUNCTIONS.contains(&short.as_str());
+        if !known {
+            self.report(
+                Rule::UndefinedFunction,
+                format!("Call to undefined function {}()", name),
+            );
+        }
+    }
+
+    fn args(&mut self, args: &[Argument]) {
+        for arg in args {
+            self.expr(&arg.value);
+        }
+    }
+
+    /// Destructuring target: variables are written, anything else is evaluated
+    fn target(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(name) => self.write(name),
+            Expr::Array(elements) => elements.iter().for_each(|e| self.target(&e.value)),
+            Expr::ListDestructure { elements, .. } => elements
+                .iter()
+                .flatten()
+                .for_each(|e| self.target(&e.value)),
+            other => self.expr(other),
+        }
+    }
+
+    pub(super) fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(name) => self.read(name),
+            Expr::String(value) => {
+                self.use_member(value, false);
+                self.use_member(value, true);
+            }
+            Expr::Assign { var, op, value } => {
+                self.expr(value);
+                match op {
+                    AssignOp::Assign => self.write(var),
+                    _ => self.read(var),
+                }
+            }
+            Expr::AssignRef { var, source } => {
+                // Binding a variable defines it
+                match source.as_ref() {
+                    Expr::Variable(name) => self.write(name),
+                    source => self.expr(source),
+                }
+                self.write(var);
+            }
+            Expr::ArrayAssign {
+                array,
+                index,
+                value,
+                ..
+            } => {
+                self.expr(array);
+                if let Some(index) = index {
+                    self.expr(index);
+                }
+                self.expr(value);
+            }
+            Expr::ListDestructure { elements, array } => {
+                self.expr(array);
+                for element in elements.iter().flatten() {
+                    if let Some(key) = &element.key {
+                        self.expr(key);
+                    }
+                    self.target(&element.value);
+                }
+            }
+            Expr::FunctionCall { name, args } => {
+                self.call(name, args);
+                self.args(args);
+            }
+            Expr::CallableFromFunction(name) => self.call(name, &[]),
+            Expr::Array(elements) => {
+                for element in elements {
+                    if let Some(key) = &element.key {
+                        self.expr(key);
+                    }
+                    self.expr(&element.value);
+                }
+            }
+            Expr::ArrayAccess { array, index } => {
+                self.expr(array);
+                self.expr(index);
+            }
+            Expr::Binary { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Unary { expr, .. }
+            | Expr::Grouped(expr)
+            | Expr::Spread(expr)
+            | Expr::Throw(expr)
+            | Expr::YieldFrom(expr)
+            | Expr::Clone { object: expr }
+            | Expr::NewFiber { callback: expr } => self.expr(expr),
+            Expr::PropertyAccess { object, property }
+            | Expr::NullsafePropertyAccess { object, property } => {
+                self.use_member(property, false);
+                self.expr(object);
+            }
+            Expr::CallableFromMethod { object, method } => {
+                self.use_member(method, true);
+                self.expr(object);
+            }
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => {
+                self.expr(condition);
+                if let Some(then_expr) = then_expr {
+                    self.expr(then_expr);
+                }
+                self.expr(else_expr);
+            }
+            Expr::CallableCall { callable, args }
+            | Expr::NewDynamic {
+                class: callable,
+                args,
+            } => {
+                self.expr(callable);
+                self.args(args);
+            }
+            Expr::New { args, .. } => self.args(args),
+            Expr::StaticMethodCall { method, args, .. } => {
+                self.use_member(method, true);
+                self.args(args);
+            }
+            Expr::NewAnonymousClass {
+                constructor_args,
+                properties,
+                methods,
+                ..
+            } => {
+                self.args(constructor_args);
+                self.members(properties, methods);
+            }
+            Expr::MethodCall {
+                object,
+                method,
+                args,
+            }
+            | Expr::NullsafeMethodCall {
+                object,
+                method,
+                args,
+            } => {
+                self.use_member(method, true);
+                self.expr(object);
+                self.args(args);
+            }
+            Expr::PropertyAssign {
+                object,
+                property,
+                value,
+                ..
+            } => {
+                self.use_member(property, false);
+                self.expr(object);
+                self.expr(value);
+            }
+            Expr::StaticPropertyAssign {
+                property, value, ..
+            } => {
+                self.use_member(property, false);
+                self.expr(value);
+            }
+            Expr::StaticPropertyAccess { property, .. } => self.use_member(property, false),
+            Expr::CallableFromStaticMethod { method, .. } => self.use_member(method, true),
+            Expr::FiberSuspend { value } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Expr::Match {
+                expr,
+                arms,
+                default,
+            } => {
+                self.expr(expr);
+                for arm in arms {
+                    arm.conditions.iter().for_each(|c| self.expr(c));
+                    self.expr(&arm.result);
+                }
+                if let Some(default) = default {
+                    self.expr(default);
+                }
+            }
+            Expr::CloneWith {
+                object,
+                modifications,
+            } => {
+                self.expr(object);
+                modifications.iter().for_each(|m| self.expr(&m.value));
+            }
+            Expr::CloneWithProperties { object, properties } => {
+                self.expr(object);
+                self.expr(properties);
+            }
+            // Arrow functions capture the enclosing scope by value
+            Expr::ArrowFunction { body, .. } => self.expr(body),
+            Expr::Yield { key, value } => {
+                if let Some(key) = key {
+                    self.expr(key);
+                }
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Expr::Integer(_)
+            | Expr::Float(_)
+            | Expr::Bool(_)
+            | Expr::Null
+            | Expr::This
+            | Expr::FiberGetCurrent
+            | Expr::EnumCase { .. }
+            | Expr::ClassReference(_)
+            | Expr::Constant(_)
+            | Expr::Placeholder
+            | Expr::MagicFile
+            | Expr::MagicLine(_)
+            | Expr::MagicDir
+            | Expr::MagicFunction
+            | Expr::MagicClass
+            | Expr::MagicMethod
+            | Expr::MagicNamespace
+            | Expr::MagicTrait => {}
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Unused private members: private methods and properties of a class
+//! that nothing in the class refers to
+//!
+//! Private members can only be used from inside the class, on `$this` or
+//! on another instance, so any access by name counts, whatever the object.
+//! String literals count too, for callables like `[$this, 'name']`.
+//! Classes using traits are skipped, as trait code may use the members.
+
+use super::{Checker, Rule};
+use crate::ast::{Method, Property, TraitUse, Visibility};
+use std::collections::HashSet;
+
+impl Checker<'_> {
+    /// Check a class body, then report the private members it never uses
+    pub(super) fn class(
+        &mut self,
+        name: &str,
+        trait_uses: &[TraitUse],
+        properties: &[Property],
+        methods: &[Method],
+    ) {
+        let line = self.line;
+        let outer = self.member_uses.replace(HashSet::new());
+        self.members(properties, methods);
+        let used = std::mem::replace(&mut self.member_uses, outer).unwrap_or_default();
+        if !trait_uses.is_empty() {
+            return;
+        }
+
+        for method in methods {
+            if method.visibility == Visibility::Private
+                && !method.name.starts_with("__")
+                && !used.contains(&method.name.to_lowercase())
+            {
+                self.found.push((
+                    line,
+                    Rule::UnusedPrivateMember,
+                    format!("Private method {}::{}() is never used", name, method.name),
+                ));
+            }
+        }
+        let promoted = methods
+            .iter()
+            .filter(|m| m.name.eq_ignore_ascii_case("__construct"))
+            .flat_map(|m| &m.params)
+            .filter(|p| p.visibility == Some(Visibility::Private))
+            .map(|p| &p.name);
+        let declared = properties
+            .iter()
+            .filter(|p| p.visibility == Visibility::Private)
+            .map(|p| &p.name);
+        for property in declared.chain(promoted) {
+            if !used.contains(property) {
+                self.found.push((
+                    line,
+                    Rule::UnusedPrivateMember,
+                    format!("Private property {}::${} is never used", name, property),
+                ));
+            }
+        }
+    }
+
+    /// Note a method, property or string used in the class being checked;
+    /// method names are case-insensitive
+    pub(super) fn use_member(&mut self, name: &str, is_method: bool) {
+        if let Some(uses) = &mut self.member_uses {
+            match is_method {
+                true => uses.insert(name.to_lowercase()),
+                false => uses.insert(name.to_string()),
+            };
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! missing-visibility: class members declared without an access modifier
+//!
+//! The parser fills in `Visibility::Public` when no modifier is written, so
+//! this rule works on tokens: it tracks class/trait/enum bodies by brace
+//! depth and looks at the modifiers in front of each member declaration.
+
+use super::Rule;
+use crate::lexer::Lexer;
+use crate::token::{Token, TokenKind};
+
+pub fn check(source: &str) -> Vec<(usize, Rule, String)> {
+    let Ok(tokens) = Lexer::new(source).tokenize() else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    // Brace depths of the enclosing class bodies
+    let mut bodies: Vec<usize> = Vec::new();
+    let mut depth = 0;
+    let mut class_pending = false;
+    // Tokens of the member declaration being read, since the last ; { or }
+    let mut header: Vec<&TokenKind> = Vec::new();
+    // Whether the current declaration's member was already seen
+    let mut declared = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let in_body = bodies.last() == Some(&depth);
+        match &token.kind {
+            TokenKind::Class | TokenKind::Trait | TokenKind::Enum => {
+                // Foo::class is a constant, not a declaration
+                let after_colons = i > 0 && tokens[i - 1].kind == TokenKind::DoubleColon;
+                class_pending |= !after_colons;
+            }
+            TokenKind::Interface => class_pending = false,
+            TokenKind::LeftBrace => {
+                depth += 1;
+                if class_pending {
+                    bodies.push(depth);
+                    class_pending = false;
+                }
+                header.clear();
+                declared = false;
+                continue;
+            }
+            TokenKind::RightBrace => {
+                if bodies.last() == Some(&depth) {
+                    bodies.pop();
+                }
+                depth = depth.saturating_sub(1);
+                header.clear();
+                declared = false;
+                continue;
+            }
+            TokenKind::Semicolon => {
+                header.clear();
+                declared = false;
+                continue;
+            }
+            _ => {}
+        }
+        if !in_body || declared {
+            header.push(&token.kind);
+            continue;
+        }
+
+        let member = match &token.kind {
+            TokenKind::Function => Some(("Method", member_name(&tokens[i + 1..]))),
+            TokenKind::Const => Some(("Constant", member_name(&tokens[i + 1..]))),
+            TokenKind::Variable(name) if is_declaration(&header) => {
+                Some(("Property", format!("${}", name)))
+            }
+            _ => None,
+        };
+        if let Some((kind, name)) = member {
+            declared = true;
+            if !header.iter().any(|kind| is_visibility(kind)) && !is_excluded(&header) {
+                found.push((
+                    token.line,
+                    Rule::MissingVisibility,
+                    format!("{} {} has no visibility modifier", kind, name),
+                ));
+            }
+        }
+        header.push(&token.kind);
+    }
+    found
+}
+
+fn member_name(rest: &[Token]) -> String {
+    match rest.first().map(|t| &t.kind) {
+        Some(TokenKind::Identifier(name)) => name.clone(),
+        _ => String::from("(anonymous)"),
+    }
+}
+
+fn is_visibility(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Public | TokenKind::Protected | TokenKind::Private
+    )
+}
+
+/// A property is declared by modifiers (or `var`) and an optional type
+fn is_declaration(header: &[&TokenKind]) -> bool {
+    !header.is_empty()
+        && !header
+            .iter()
+            .any(|kind| matches!(kind, TokenKind::Assign | TokenKind::LeftParen))
+}
+
+/// Trait uses, enum cases and `var` properties (which are public)
+fn is_excluded(header: &[&TokenKind]) -> bool {
+    header.iter().any(|kind| {
+        matches!(kind, TokenKind::Use | TokenKind::Case)
+            || matches!(kind, TokenKind::Identifier(name) if name.eq_ignore_ascii_case("var"))
+    })
+}
@@ -1,71 +1,271 @@
-mod ast;
-mod lexer;
-mod parser;
-mod runtime;
-mod test_runner;
-mod token;
-mod vm;
-
-use lexer::Lexer;
-use parser::Parser;
+mod cli;
+
 use std::env;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::path::PathBuf;
 use std::process;
-use test_runner::TestRunner;
+use vhp::cgi::Request;
+use vhp::debugger::ConsoleDebugger;
+use vhp::diagnostic::{self, Diagnostic};
+use vhp::runtime::builtins::{ini, spl};
+use vhp::vm::cache::BytecodeCache;
+use vhp::vm::debug::DebugHook;
+use vhp::vm::extension::Registry;
+use vhp::vm::profiler::CountingAllocator;
+use vhp::vm::sandbox::Sandbox;
+use vhp::vm::superglobals::Response;
+use vhp::vm::VM;
 
-/// Run source with bytecode VM
-/// Returns Ok(None) on normal completion, Ok(Some(exit_code)) when exit() is called, or Err on error
-fn run(source: &str, file_path: &str) -> Result<Option<i32>, String> {
-    use vm::compiler::Compiler;
-    use vm::VM;
+/// Counts allocations so that `--profile` can report memory per function
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Stack values shown per line by `--trace-opcodes` without a depth
+const DEFAULT_TRACE_DEPTH: usize = 4;
+
+/// How a script is run
+#[derive(Default)]
+struct RunOptions {
+    debugger: Option<Box<dyn DebugHook>>,
+    /// Plugin libraries to load before running
+    extensions: Vec<PathBuf>,
+    /// `disable_functions`: comma-separated functions scripts can't call
+    disable_functions: String,
+    /// `open_basedir`: directories scripts' file access is limited to
+    open_basedir: String,
+    /// `max_execution_time` in seconds, if given
+    max_execution_time: Option<String>,
+    /// Freeze the clock and seed randomness for reproducible output
+    deterministic: bool,
+    /// Print runtime counters to stderr when the script ends
+    stats: bool,
+    /// Write a callgrind profile to this file
+    profile: Option<PathBuf>,
+    /// Log executed opcodes to stderr, showing this many stack values
+    trace_opcodes: Option<usize>,
+    /// INI file of directives to load instead of `./vhp.ini`
+    ini_file: Option<PathBuf>,
+    /// PSR-4 mappings of a namespace prefix to the directory of its classes
+    autoload: Vec<(String, String)>,
+    /// Directory to keep compiled files in, reused while their source is
+    /// unchanged
+    cache_dir: Option<PathBuf>,
+}
+
+impl RunOptions {
+    /// Parse the flags given before the script or subcommand, returning
+    /// how many arguments were consumed
+    fn from_args(args: &[String]) -> Result<(Self, usize), String> {
+        let mut options = RunOptions::default();
+        let mut consumed = 0;
+        for arg in args {
+            match arg.split_once('=') {
+                _ if arg == "--profile" => {
+                    let file = format!("callgrind.out.{}", process::id());
+                    options.profile = Some(PathBuf::from(file));
+                }
+                Some(("--profile", file)) => options.profile = Some(PathBuf::from(file)),
+                Some(("--extension", file)) => options.extensions.push(PathBuf::from(file)),
+                Some(("--ini", file)) => options.ini_file = Some(PathBuf::from(file)),
+                Some(("--cache-dir", dir)) => options.cache_dir = Some(PathBuf::from(dir)),
+                Some(("--autoload", mapping)) => match mapping.split_once(':') {
+                    Some((dir, prefix))
+                        if !dir.is_empty() && !prefix.trim_matches('\\').is_empty() =>
+                    {
+                        options.autoload.push((prefix.to_string(), dir.to_string()))
+                    }
+                    _ => return Err(format!("Invalid --autoload mapping: {}", mapping)),
+                },
+                Some(("--disable-functions", names)) => options.disable_functions = names.into(),
+                Some(("--open-basedir", dirs)) => options.open_basedir = dirs.into(),
+                Some(("--max-execution-time", seconds)) => {
+                    if seconds.trim().parse::<f64>().map_or(true, |s| s < 0.0) {
+                        return Err(format!("Invalid --max-execution-time value: {}", seconds));
+                    }
+                    options.max_execution_time = Some(seconds.into());
+                }
+                _ if arg == "--deterministic" => options.deterministic = true,
+                _ if arg == "--stats" => options.stats = true,
+                _ if arg == "--trace-opcodes" => options.trace_opcodes = Some(DEFAULT_TRACE_DEPTH),
+                Some(("--trace-opcodes", depth)) => {
+                    let depth = depth
+                        .parse()
+                        .map_err(|_| format!("Invalid --trace-opcodes stack depth: {}", depth))?;
+                    options.trace_opcodes = Some(depth);
+                }
+                _ if arg.starts_with("--") && arg != "--help" => {
+                    return Err(format!("Unknown option: {}", arg));
+                }
+                _ => break,
+            }
+            consumed += 1;
+        }
+        Ok((options, consumed))
+    }
+}
 
-    // Lexical analysis
-    let mut lexer = Lexer::new(source);
-    let tokens = lexer.tokenize()?;
+/// What a script is run for, which gives it its superglobals
+enum Invocation {
+    /// From the command line, with the script and its arguments
+    Cli(Vec<String>),
+    /// To answer a CGI request; the response and the uploaded files to
+    /// remove are filled in as the script ends
+    Cgi {
+        request: Request,
+        response: Response,
+        uploads: Vec<PathBuf>,
+    },
+}
 
-    // Parsing
-    let mut parser = Parser::new(tokens);
-    let program = parser.parse()?;
+/// Run source with bytecode VM, optionally under a debugger or profiler
+/// Returns Ok(None) on normal completion, Ok(Some(exit_code)) when exit() is called, or Err on error
+fn run<W: Write>(
+    source: &str,
+    file_path: &str,
+    options: RunOptions,
+    output: W,
+    invocation: &mut Invocation,
+) -> Result<Option<i32>, Box<Diagnostic>> {
+    let cache = options.cache_dir.as_ref().map(BytecodeCache::new);
+    let compilation = match &cache {
+        Some(cache) => cache.compile(source, file_path),
+        None => vhp::compile(source, file_path),
+    }
+    .map_err(|e| Diagnostic::from(&e).with_source(file_path, source))?;
+
+    let mut registry = Registry::new();
+    for path in &options.extensions {
+        vhp::ffi::load_plugin(path, &mut registry).map_err(Diagnostic::from)?;
+    }
 
-    // Compile to bytecode
-    let compiler = Compiler::with_file_path("<main>".to_string(), file_path.to_string());
-    let compilation = compiler.compile_program(&program)?;
+    if options.deterministic {
+        vhp::runtime::deterministic::enable();
+    }
 
-    // Execute with VM
-    let output = std::io::stdout();
+    load_ini_file(options.ini_file.as_deref())?;
+    for (prefix, dir) in &options.autoload {
+        spl::add_psr4_mapping(prefix, dir);
+    }
     let mut vm_instance = VM::new(output);
-    vm_instance.register_builtins();
-    vm_instance.register_functions(compilation.functions);
-    vm_instance.register_classes(compilation.classes);
-    vm_instance.register_interfaces(compilation.interfaces);
-    vm_instance.register_traits(compilation.traits);
-    vm_instance.register_enums(compilation.enums);
-
-    match vm_instance.execute(compilation.main) {
-        Ok(_) => Ok(None),
-        Err(e) if e.starts_with("__EXIT__:") => {
-            let parts: Vec<&str> = e.splitn(2, ':').collect();
-            let code = parts
-                .get(1)
-                .and_then(|s| s.parse::<i32>().ok())
-                .unwrap_or(0);
-            Ok(Some(code))
+    vm_instance.set_registry(registry);
+    if let Some(cache) = cache {
+        vm_instance.set_bytecode_cache(cache);
+    }
+    match invocation {
+        Invocation::Cli(argv) => vm_instance.set_cli_arguments(argv),
+        Invocation::Cgi {
+            request, uploads, ..
+        } => {
+            *uploads = vm_instance
+                .set_request(request)
+                .map_err(|e| Diagnostic::new(format!("Cannot save uploaded file: {}", e)))?;
         }
-        Err(e) => Err(format!("VM error: {}", e)),
     }
-}
+    ini::set_ini_value("disable_functions", &options.disable_functions);
+    ini::set_ini_value("open_basedir", &options.open_basedir);
+    vm_instance.set_sandbox(Sandbox::from_directives(
+        &options.disable_functions,
+        &options.open_basedir,
+    ));
+    if let Some(seconds) = &options.max_execution_time {
+        ini::set_ini_value("max_execution_time", seconds);
+    }
+    vm_instance.apply_max_execution_time();
+    let main = vm_instance.load(compilation);
+    if let Some(debugger) = options.debugger {
+        vm_instance.attach_debugger(debugger);
+    }
+    if options.profile.is_some() {
+        vm_instance.enable_profiler();
+    }
+    if options.stats {
+        ini::set_ini_value("vhp.stats", "1");
+        vm_instance.enable_stats();
+    }
+    if let Some(depth) = options.trace_opcodes {
+        vm_instance.enable_opcode_trace(Box::new(std::io::stderr()), depth);
+    }
+
+    let result = vm_instance.execute(main);
+    if let Invocation::Cgi { response, .. } = invocation {
+        *response = vm_instance.response().clone();
+    }
 
-fn run_tests(test_dir: &str, verbose: bool) -> Result<(), String> {
-    let path = Path::new(test_dir);
-    let runner = TestRunner::new(path, verbose);
-    let summary = runner.run_all()?;
+    if let (Some(path), Some(profile)) = (&options.profile, vm_instance.take_profile()) {
+        let mut file = fs::File::create(path)
+            .map_err(|e| Diagnostic::new(format!("Error writing profile {:?}: {}", path, e)))?;
+        profile
+            .write_callgrind(&mut file, file_path)
+            .map_err(|e| Diagnostic::new(format!("Error writing profile {:?}: {}", path, e)))?;
+    }
 
-    if summary.failed > 0 || summary.errors > 0 {
-        process::exit(1);
+    if let Some(stats) = vm_instance.stats() {
+        let _ = stats.write_report(&mut std::io::stderr());
     }
 
-    Ok(())
+    vhp::exit_status(result).map_err(|e| {
+        let diagnostic = Diagnostic::from(&e);
+        match e.location() {
+            Some(location) => {
+                let text = match location.file == file_path {
+                    true => source.to_string(),
+                    false => fs::read_to_string(&location.file).unwrap_or_default(),
+                };
+                diagnostic.with_source(&location.file, &text)
+            }
+            None => diagnostic,
+        }
+        .into()
+    })
+}
+
+/// Set the directives of an INI file: the one given with `--ini`, or
+/// `vhp.ini` in the current directory if there is one
+fn load_ini_file(file: Option<&std::path::Path>) -> Result<(), Box<Diagnostic>> {
+    let path = file.unwrap_or(std::path::Path::new("vhp.ini"));
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(_) if file.is_none() => return Ok(()),
+        Err(e) => {
+            let message = format!("Cannot read INI file '{}': {}", path.display(), e);
+            return Err(Box::new(Diagnostic::new(message)));
+        }
+    };
+    ini::load_ini(&source).map(|_| ()).map_err(|error| {
+        let message = error.describe(&path.display().to_string());
+        Box::new(Diagnostic::new(message.trim_end()))
+    })
+}
+
+/// Read and run a PHP file with the arguments after it, exiting if it
+/// can't be read
+fn run_file(
+    filename: &str,
+    args: &[String],
+    options: RunOptions,
+) -> Result<Option<i32>, Box<Diagnostic>> {
+    match fs::read_to_string(filename) {
+        Ok(source) => {
+            let file_path = fs::canonicalize(filename).unwrap_or_else(|_| PathBuf::from(filename));
+            let argv = std::iter::once(filename.to_string())
+                .chain(args.iter().cloned())
+                .collect();
+            let mut invocation = Invocation::Cli(argv);
+            let file_path = file_path.to_str().unwrap_or(filename);
+            run(
+                &source,
+                file_path,
+                options,
+                std::io::stdout(),
+                &mut invocation,
+            )
+        }
+        Err(e) => fail(&Diagnostic::new(format!(
+            "Cannot read file '{}': {}",
+            filename, e
+        ))),
+    }
 }
 
 fn print_usage(program: &str) {
@@ -75,12 +275,89 @@ fn print_usage(program: &str) {
     );
     eprintln!();
     eprintln!("Usage:");
-    eprintln!("  {} <file.php>              Run a PHP file", program);
+    eprintln!(
+        "  {} <file.php> [args...]     Run a PHP file ($argv holds the arguments)",
+        program
+    );
+    eprintln!(
+        "  {} run <file.php> [args...] Run a PHP file, run options may follow run",
+        program
+    );
     eprintln!("  {} -r <code>               Run code directly", program);
-    eprintln!("  {} test [dir|file] [-v]    Run .vhpt tests", program);
+    eprintln!(
+        "  {} debug <file.php>        Run a PHP file in the debugger",
+        program
+    );
+    eprintln!(
+        "  {} dap                      Serve the Debug Adapter Protocol on stdio",
+        program
+    );
+    eprintln!(
+        "  {} cgi [file.php]           Answer a CGI request (script from SCRIPT_FILENAME)",
+        program
+    );
+    eprintln!(
+        "  {} fcgi [--listen <addr>]   Serve FastCGI (default 127.0.0.1:9000)",
+        program
+    );
+    eprintln!("  {} test [paths...] [-v]    Run .vhpt tests", program);
+    eprintln!(
+        "  {} lint [paths...]         Check PHP files without running them",
+        program
+    );
+    eprintln!(
+        "  {} check [paths...]        Report type errors without running code",
+        program
+    );
+    eprintln!(
+        "  {} compile [paths...]      Compile PHP files into the --cache-dir ahead of time",
+        program
+    );
+    eprintln!();
+    eprintln!("Run options (before the file):");
+    eprintln!(
+        "  --profile[=<file>]         Write a callgrind profile (default callgrind.out.<pid>)"
+    );
+    eprintln!("  --extension=<file>         Load a plugin library (repeatable)");
+    eprintln!("  --ini=<file>               Load directives from an INI file (default ./vhp.ini)");
+    eprintln!("  --cache-dir=<dir>          Keep compiled files in a directory and reuse them");
+    eprintln!(
+        "  --autoload=<dir>:<prefix>  Load classes of a namespace prefix PSR-4 style (repeatable)"
+    );
+    eprintln!("  --disable-functions=<list> Comma-separated functions scripts may not call");
+    eprintln!("  --open-basedir=<dirs>      Limit file access to these directories");
+    eprintln!("  --max-execution-time=<s>   Abort scripts running longer (0 for no limit)");
+    eprintln!("  --deterministic            Fixed clock and random seed, for reproducible output");
+    eprintln!("  --stats                    Print opcode, call and allocation counters to stderr");
     eprintln!();
     eprintln!("Options:");
     eprintln!("  -v, --verbose              Verbose test output");
+    eprintln!("  --filter <pattern>         Only run tests whose name or path matches");
+    eprintln!("  --junit <file>             Write a JUnit XML report");
+    eprintln!("  --tap                      Print results in TAP version 13 format");
+    eprintln!("  --timeout <seconds>        Per-test time limit (default 10, 0 disables)");
+    eprintln!("  --deterministic            Run each test with a fixed clock and random seed");
+    eprintln!(
+        "  --round-trip               Check that each test's code prints back to the same tree"
+    );
+    eprintln!("  --coverage <file>          Write line coverage as lcov (or HTML for *.html)");
+    eprintln!(
+        "  --bless                    Rewrite --EXPECT-- of failing tests with actual output"
+    );
+    eprintln!("  -y, --yes                  Bless without asking for confirmation");
+    eprintln!("  --seed <n>                 Shuffle tests with this seed (to reproduce an order)");
+    eprintln!("  --no-shuffle               Run tests in file order instead of randomly");
+    eprintln!("  --failed                   Rerun only the tests that failed last time");
+    eprintln!();
+    eprintln!("FastCGI options:");
+    eprintln!("  --listen <addr>            Address to listen on (default 127.0.0.1:9000)");
+    eprintln!("  --workers <n>              Requests answered at once (default: CPU count)");
+    eprintln!();
+    eprintln!("Lint options:");
+    eprintln!("  --format <text|json>       Diagnostic output format (default text)");
+    eprintln!("  --rule <name>=<severity>   Set a rule to off, warning or error");
+    eprintln!("  --config <file>            Read rule=severity lines (default .vhplint)");
+    eprintln!("  --deny-warnings            Report warnings as errors (exit status 1)");
     eprintln!();
     eprintln!("Test file format (.vhpt):");
     eprintln!("  --TEST--                   Test name (required)");
@@ -89,61 +366,94 @@ fn print_usage(program: &str) {
     eprintln!("  --EXPECT--                 Expected output (required unless --EXPECT_ERROR--)");
     eprintln!("  --EXPECT_ERROR--           Expected error message");
     eprintln!("  --SKIPIF--                 Reason to skip this test");
+    eprintln!("  --INI--                    key=value directives for this test");
+    eprintln!("  --ENV--                    KEY=value environment variables for this test");
+    eprintln!("  --CLEAN--                  Cleanup code run after the test");
+    eprintln!("  --XFAIL--                  Reason the test is expected to fail");
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 2 {
+    let (mut options, consumed) = match RunOptions::from_args(&args[1..]) {
+        Ok(parsed) => parsed,
+        Err(e) => fail(&Diagnostic::new(e)),
+    };
+    let rest = &args[1 + consumed..];
+
+    if rest.is_empty() {
         print_usage(&args[0]);
         process::exit(1);
     }
 
-    let result = match args[1].as_str() {
+    let result = match rest[0].as_str() {
         "-r" => {
-            if args.len() < 3 {
-                eprintln!("Error: -r requires code argument");
-                process::exit(1);
+            if rest.len() < 2 {
+                fail(&Diagnostic::new("-r requires code argument"));
             }
-            let code = format!("<?php {}", &args[2]);
-            run(&code, "<main>")
+            let code = format!("<?php {}", &rest[1]);
+            let argv = std::iter::once("Standard input code".to_string())
+                .chain(rest[2..].iter().cloned())
+                .collect();
+            let mut invocation = Invocation::Cli(argv);
+            run(&code, "<main>", options, std::io::stdout(), &mut invocation)
         }
-        "test" => {
-            let verbose = args.iter().any(|a| a == "-v" || a == "--verbose");
-            let test_dir = args
-                .iter()
-                .skip(2)
-                .find(|a| !a.starts_with('-'))
-                .map(|s| s.as_str())
-                .unwrap_or("tests");
-            match run_tests(test_dir, verbose) {
-                Ok(_) => Ok(None),
-                Err(e) => Err(e),
+        "run" => {
+            // Run options may follow `run` too
+            let flags = [&args[1..1 + consumed], &rest[1..]].concat();
+            let (options, all_consumed) =
+                RunOptions::from_args(&flags).unwrap_or_else(|e| fail(&Diagnostic::new(e)));
+            let rest = &rest[1 + all_consumed - consumed..];
+            if rest.is_empty() {
+                fail(&Diagnostic::new("run requires a file argument"));
             }
+            run_file(&rest[0], &rest[1..], options)
         }
+        "debug" => {
+            if rest.len() < 2 {
+                fail(&Diagnostic::new("debug requires a file argument"));
+            }
+            options.debugger = Some(Box::new(ConsoleDebugger::new()));
+            run_file(&rest[1], &rest[2..], options)
+        }
+        "dap" => vhp::debugger::dap::serve()
+            .map(|_| None)
+            .map_err(|e| Diagnostic::from(e).into()),
+        "lint" => cli::tools::run_lint(&rest[1..])
+            .map(|_| None)
+            .map_err(|e| Diagnostic::from(e).into()),
+        "check" => cli::tools::run_check(&rest[1..])
+            .map(|_| None)
+            .map_err(|e| Diagnostic::from(e).into()),
+        "compile" => cli::tools::run_compile(&rest[1..], options.cache_dir)
+            .map(|_| None)
+            .map_err(|e| Diagnostic::from(e).into()),
+        "test" => cli::tools::run_tests(&rest[1..])
+            .map(|_| None)
+            .map_err(|e| Diagnostic::from(e).into()),
         "-h" | "--help" => {
             print_usage(&args[0]);
             Ok(None)
         }
-        filename => match fs::read_to_string(filename) {
-            Ok(source) => {
-                let file_path =
-                    fs::canonicalize(filename).unwrap_or_else(|_| PathBuf::from(filename));
-                run(&source, file_path.to_str().unwrap_or(filename))
-            }
-            Err(e) => {
-                eprintln!("Error reading file '{}': {}", filename, e);
-                process::exit(1);
-            }
-        },
+        "cgi" => cli::serve::run_cgi(rest.get(1).map(String::as_str), options),
+        "fcgi" => cli::serve::run_fcgi(&rest[1..], args[1..1 + consumed].to_vec()),
+        filename => run_file(filename, &rest[1..], options),
     };
 
     match result {
         Ok(Some(exit_code)) => process::exit(exit_code),
         Ok(None) => {}
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            process::exit(1);
-        }
+        Err(diagnostic) => fail(&diagnostic),
     }
 }
+
+/// Print an error report to stderr, colored unless it isn't a terminal or
+/// `NO_COLOR` is set, and exit with status 1
+fn fail(diagnostic: &Diagnostic) -> ! {
+    let report = match diagnostic::use_color() {
+        true => diagnostic.render_colored(),
+        false => diagnostic.render(),
+    };
+    eprint!("{}", report);
+    process::exit(1);
+}
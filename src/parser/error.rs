@@ -0,0 +1,128 @@
+//! Parser errors
+
+use crate::diagnostic::Diagnostic;
+use crate::token::TokenKind;
+use std::fmt;
+
+/// Why the parser rejected the tokens, and where
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The grammar required something else here ("Expected ';' after
+    /// expression"), with the token found instead when known
+    Expected {
+        message: String,
+        found: Option<TokenKind>,
+        line: usize,
+        column: usize,
+    },
+    /// A token that doesn't belong where it appears
+    Unexpected {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    /// Code that parses but the language forbids, such as a variadic
+    /// parameter with a default value
+    Invalid {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+impl ParseError {
+    pub fn expected(message: impl Into<String>, line: usize, column: usize) -> Self {
+        Self::Expected {
+            message: message.into(),
+            found: None,
+            line,
+            column,
+        }
+    }
+
+    pub fn unexpected(message: impl Into<String>, line: usize, column: usize) -> Self {
+        Self::Unexpected {
+            message: message.into(),
+            line,
+            column,
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>, line: usize, column: usize) -> Self {
+        Self::Invalid {
+            message: message.into(),
+            line,
+            column,
+        }
+    }
+
+    /// Record the token found where something else was expected
+    pub fn found(self, kind: &TokenKind) -> Self {
+        match self {
+            Self::Expected {
+                message,
+                line,
+                column,
+                ..
+            } => Self::Expected {
+                message,
+                found: Some(kind.clone()),
+                line,
+                column,
+            },
+            other => other,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Expected { message, .. }
+            | Self::Unexpected { message, .. }
+            | Self::Invalid { message, .. } => message,
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        match self {
+            Self::Expected { line, .. }
+            | Self::Unexpected { line, .. }
+            | Self::Invalid { line, .. } => *line,
+        }
+    }
+
+    pub fn column(&self) -> usize {
+        match self {
+            Self::Expected { column, .. }
+            | Self::Unexpected { column, .. }
+            | Self::Invalid { column, .. } => *column,
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        let diagnostic = Diagnostic::syntax(error.message(), error.line(), error.column());
+        match error {
+            ParseError::Expected {
+                found: Some(found), ..
+            } => diagnostic.with_label(format!("found {:?}", found)),
+            _ => diagnostic,
+        }
+    }
+}
+
+/// The one-line form, e.g. "Expected ';' after expression at line 3,
+/// column 9 (found RightBrace)"
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Diagnostic::from(self).fmt(f)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(error: ParseError) -> Self {
+        error.to_string()
+    }
+}
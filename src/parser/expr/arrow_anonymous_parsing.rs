@@ -4,19 +4,20 @@
 
 use super::ExprParser;
 use crate::ast::{Expr, Visibility};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> ExprParser<'a> {
     /// Parse arrow function: fn(params) => expression
     /// PHP 7.4+ feature for short closures
-    pub(crate) fn parse_arrow_function(&mut self) -> Result<Expr, String> {
+    pub(crate) fn parse_arrow_function(&mut self) -> Result<Expr, ParseError> {
         self.consume(TokenKind::LeftParen, "Expected '(' after 'fn'")?;
 
         let mut params = Vec::new();
 
         if !self.check(&TokenKind::RightParen) {
             loop {
-                let by_ref = if self.check(&TokenKind::And) {
+                let by_ref = if self.check(&TokenKind::BitwiseAnd) {
                     self.advance();
                     true
                 } else {
@@ -35,10 +36,10 @@ impl<'a> ExprParser<'a> {
                     self.advance();
                     n
                 } else {
-                    return Err(format!(
-                        "Expected parameter name at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected parameter name",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 };
 
@@ -82,7 +83,7 @@ impl<'a> ExprParser<'a> {
     }
 
     /// Parse anonymous class: new class(...) extends X implements Y { ... }
-    pub(crate) fn parse_anonymous_class(&mut self) -> Result<Expr, String> {
+    pub(crate) fn parse_anonymous_class(&mut self) -> Result<Expr, ParseError> {
         self.consume(TokenKind::Class, "Expected 'class'")?;
 
         let constructor_args = if self.check(&TokenKind::LeftParen) {
@@ -104,10 +105,10 @@ impl<'a> ExprParser<'a> {
                 self.advance();
                 Some(name)
             } else {
-                return Err(format!(
-                    "Expected parent class name after 'extends' at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected parent class name after 'extends'",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         } else {
@@ -122,10 +123,10 @@ impl<'a> ExprParser<'a> {
                     interfaces.push(name.clone());
                     self.advance();
                 } else {
-                    return Err(format!(
-                        "Expected interface name at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected interface name",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
 
@@ -156,10 +157,10 @@ impl<'a> ExprParser<'a> {
                         trait_names.push(name.clone());
                         stmt_parser.advance();
                     } else {
-                        return Err(format!(
-                            "Expected trait name at line {}, column {}",
+                        return Err(ParseError::expected(
+                            "Expected trait name",
                             stmt_parser.current().line,
-                            stmt_parser.current().column
+                            stmt_parser.current().column,
                         ));
                     }
 
@@ -5,13 +5,14 @@
 
 use super::{parse_postfix, ExprParser};
 use crate::ast::{Argument, Expr};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> ExprParser<'a> {
     /// Parse function/method arguments with support for named arguments (PHP 8.0)
     /// Syntax: expr, expr, name: expr, name: expr
     /// Rule: positional arguments must come before named arguments
-    pub(crate) fn parse_arguments(&mut self) -> Result<Vec<Argument>, String> {
+    pub(crate) fn parse_arguments(&mut self) -> Result<Vec<Argument>, ParseError> {
         let mut args = Vec::new();
         let mut seen_named = false;
 
@@ -42,10 +43,10 @@ impl<'a> ExprParser<'a> {
                     seen_named = true;
                 } else {
                     if seen_named {
-                        return Err(format!(
-                            "Positional arguments cannot follow named arguments at line {}, column {}",
+                        return Err(ParseError::invalid(
+                            "Positional arguments cannot follow named arguments",
                             self.current().line,
-                            self.current().column
+                            self.current().column,
                         ));
                     }
 
@@ -86,7 +87,7 @@ impl<'a> ExprParser<'a> {
     }
 
     /// Parse function call
-    pub(crate) fn parse_function_call(&mut self, name: String) -> Result<Expr, String> {
+    pub(crate) fn parse_function_call(&mut self, name: String) -> Result<Expr, ParseError> {
         self.advance();
 
         if self.check(&TokenKind::Ellipsis) {
@@ -112,7 +113,7 @@ impl<'a> ExprParser<'a> {
     }
 
     /// Parse static method call or enum case access
-    pub(crate) fn parse_static_access(&mut self, class_name: String) -> Result<Expr, String> {
+    pub(crate) fn parse_static_access(&mut self, class_name: String) -> Result<Expr, ParseError> {
         self.advance();
 
         if let TokenKind::Variable(prop_name) = &self.current().kind {
@@ -125,6 +126,12 @@ impl<'a> ExprParser<'a> {
             return parse_postfix(self, expr);
         }
 
+        if self.check(&TokenKind::Class) {
+            self.advance();
+            let expr = Expr::ClassReference(class_name);
+            return parse_postfix(self, expr);
+        }
+
         let method_or_case = if let TokenKind::Identifier(id) = &self.current().kind {
             let id = id.clone();
             self.advance();
@@ -133,10 +140,10 @@ impl<'a> ExprParser<'a> {
             self.advance();
             "from".to_string()
         } else {
-            return Err(format!(
-                "Expected method, case name, or property after '::' at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected method, case name, or property after '::'",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -209,7 +216,7 @@ impl<'a> ExprParser<'a> {
     }
 
     /// Parse new object instantiation
-    pub(crate) fn parse_new_object(&mut self) -> Result<Expr, String> {
+    pub(crate) fn parse_new_object(&mut self) -> Result<Expr, ParseError> {
         self.advance();
 
         if self.check(&TokenKind::Class) {
@@ -236,10 +243,10 @@ impl<'a> ExprParser<'a> {
                         class_name_parts.push(part.clone());
                         self.advance();
                     } else {
-                        return Err(format!(
-                            "Expected identifier after '\\' at line {}, column {}",
+                        return Err(ParseError::expected(
+                            "Expected identifier after '\\'",
                             self.current().line,
-                            self.current().column
+                            self.current().column,
                         ));
                     }
                 }
@@ -254,11 +261,28 @@ impl<'a> ExprParser<'a> {
                 self.advance();
                 "Fiber".to_string()
             }
+            TokenKind::Static => {
+                self.advance();
+                "static".to_string()
+            }
+            TokenKind::Parent => {
+                self.advance();
+                "parent".to_string()
+            }
+            TokenKind::Variable(_) | TokenKind::LeftParen => {
+                let class = self.parse_new_class_expression()?;
+                let args = self.parse_new_arguments()?;
+                let new_expr = Expr::NewDynamic {
+                    class: Box::new(class),
+                    args,
+                };
+                return parse_postfix(self, new_expr);
+            }
             _ => {
-                return Err(format!(
-                    "Expected class name after 'new' at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected class name after 'new'",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         };
@@ -273,6 +297,45 @@ impl<'a> ExprParser<'a> {
             return parse_postfix(self, fiber_expr);
         }
 
+        let args = self.parse_new_arguments()?;
+        let new_expr = Expr::New { class_name, args };
+        parse_postfix(self, new_expr)
+    }
+
+    /// Parse the class of `new $class` or `new ($expr)`: a variable, with
+    /// any property fetches after it, or a parenthesized expression
+    fn parse_new_class_expression(&mut self) -> Result<Expr, ParseError> {
+        if self.check(&TokenKind::LeftParen) {
+            self.advance();
+            let class = self.parse_expression(super::super::precedence::Precedence::None)?;
+            self.consume(TokenKind::RightParen, "Expected ')' after class expression")?;
+            return Ok(class);
+        }
+
+        let TokenKind::Variable(name) = self.advance().kind else {
+            unreachable!("checked by the caller");
+        };
+        let mut class = Expr::Variable(name);
+        while self.check(&TokenKind::Arrow) {
+            self.advance();
+            let TokenKind::Identifier(property) = &self.current().kind else {
+                return Err(ParseError::expected(
+                    "Expected property name after '->'",
+                    self.current().line,
+                    self.current().column,
+                ));
+            };
+            class = Expr::PropertyAccess {
+                object: Box::new(class),
+                property: property.clone(),
+            };
+            self.advance();
+        }
+        Ok(class)
+    }
+
+    /// Parse the optional constructor arguments after `new Class`
+    fn parse_new_arguments(&mut self) -> Result<Vec<Argument>, ParseError> {
         let mut args = Vec::new();
         if self.check(&TokenKind::LeftParen) {
             self.advance();
@@ -282,8 +345,6 @@ impl<'a> ExprParser<'a> {
                 "Expected ')' after constructor arguments",
             )?;
         }
-
-        let new_expr = Expr::New { class_name, args };
-        parse_postfix(self, new_expr)
+        Ok(args)
     }
 }
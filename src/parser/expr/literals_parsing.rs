@@ -1,14 +1,17 @@
 //! Literal and variable parsing for expressions
 //!
-//! Handles parsing of literals (int, float, string, bool, null) and simple variables.
+//! Handles parsing of literals (int, float, string, bool, null), interpolated
+//! strings and simple variables.
 
 use super::{parse_postfix, ExprParser};
-use crate::ast::Expr;
-use crate::token::TokenKind;
+use crate::ast::{BinaryOp, Expr};
+use crate::parser::precedence::Precedence;
+use crate::parser::ParseError;
+use crate::token::{StringPart, Token, TokenKind};
 
 impl<'a> ExprParser<'a> {
     /// Parse literal expressions (integers, floats, strings, booleans, null)
-    pub(crate) fn parse_literal(&mut self) -> Result<Expr, String> {
+    pub(crate) fn parse_literal(&mut self) -> Result<Expr, ParseError> {
         let token = self.current().clone();
 
         match &token.kind {
@@ -27,11 +30,6 @@ impl<'a> ExprParser<'a> {
                 self.advance();
                 Ok(Expr::String(s))
             }
-            TokenKind::Heredoc(s) => {
-                let s = s.clone();
-                self.advance();
-                Ok(Expr::Heredoc(s))
-            }
             TokenKind::True => {
                 self.advance();
                 Ok(Expr::Bool(true))
@@ -44,15 +42,41 @@ impl<'a> ExprParser<'a> {
                 self.advance();
                 Ok(Expr::Null)
             }
-            _ => Err(format!(
-                "Expected literal but found {:?} at line {}, column {}",
-                token.kind, token.line, token.column
+            _ => Err(ParseError::expected(
+                format!("Expected literal but found {:?}", token.kind),
+                token.line,
+                token.column,
             )),
         }
     }
 
+    /// Parse a double-quoted string with interpolation into the
+    /// concatenation of its parts: "Hi $name!" is "Hi " . $name . "!"
+    pub(crate) fn parse_interpolated_string(&mut self) -> Result<Expr, ParseError> {
+        let TokenKind::InterpolatedString(parts) = self.advance().kind else {
+            unreachable!("parse_interpolated_string called on another token");
+        };
+        let mut parts = parts.iter().map(|part| match part {
+            StringPart::Literal(text) => Ok(Expr::String(text.clone())),
+            StringPart::Tokens(tokens) => parse_interpolated(tokens),
+        });
+        // A lone expression still makes a string: "$n" is "" . $n
+        let mut result = match parts.len() {
+            1 => Expr::String(String::new()),
+            _ => parts.next().unwrap()?,
+        };
+        for part in parts {
+            result = Expr::Binary {
+                left: Box::new(result),
+                op: BinaryOp::Concat,
+                right: Box::new(part?),
+            };
+        }
+        Ok(result)
+    }
+
     /// Parse variable expression
-    pub(crate) fn parse_variable(&mut self) -> Result<Expr, String> {
+    pub(crate) fn parse_variable(&mut self) -> Result<Expr, ParseError> {
         if let TokenKind::Variable(name) = &self.current().kind {
             let name = name.clone();
             self.advance();
@@ -63,16 +87,16 @@ impl<'a> ExprParser<'a> {
             };
             parse_postfix(self, expr)
         } else {
-            Err(format!(
-                "Expected variable at line {}, column {}",
+            Err(ParseError::expected(
+                "Expected variable",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ))
         }
     }
 
     /// Parse array literal: [elem1, elem2] or [key => value, ...]
-    pub(crate) fn parse_array_literal(&mut self) -> Result<Expr, String> {
+    pub(crate) fn parse_array_literal(&mut self) -> Result<Expr, ParseError> {
         self.advance();
         let mut elements = Vec::new();
 
@@ -111,7 +135,7 @@ impl<'a> ExprParser<'a> {
     }
 
     /// Parse grouped expression: (expr)
-    pub(crate) fn parse_grouped(&mut self) -> Result<Expr, String> {
+    pub(crate) fn parse_grouped(&mut self) -> Result<Expr, ParseError> {
         self.advance();
         let expr = self.parse_expression(super::super::precedence::Precedence::None)?;
         self.consume(TokenKind::RightParen, "Expected ')' after expression")?;
@@ -119,3 +143,18 @@ impl<'a> ExprParser<'a> {
         parse_postfix(self, grouped)
     }
 }
+
+/// The expression an interpolation's tokens make, which must be all of them
+fn parse_interpolated(tokens: &[Token]) -> Result<Expr, ParseError> {
+    let mut pos = 0;
+    let mut parser = ExprParser::new(tokens, &mut pos);
+    let expr = parser.parse_expression(Precedence::None)?;
+    if let Some(token) = tokens.get(pos) {
+        return Err(ParseError::unexpected(
+            format!("Unexpected {:?} in string interpolation", token.kind),
+            token.line,
+            token.column,
+        ));
+    }
+    Ok(expr)
+}
@@ -13,14 +13,16 @@ mod arrow_anonymous_parsing;
 mod callable_parsing;
 mod literals_parsing;
 mod postfix;
+mod primary;
 mod special;
 
 use super::precedence::{get_precedence, is_right_assoc, Precedence};
 use crate::ast::{AssignOp, BinaryOp, Expr};
+use crate::parser::ParseError;
 use crate::token::{Token, TokenKind};
 
 pub use postfix::parse_postfix;
-pub use special::{parse_clone, parse_list, parse_match};
+pub use special::{is_list_pattern, parse_clone, parse_list, parse_list_pattern, parse_match};
 
 pub struct ExprParser<'a> {
     tokens: &'a [Token],
@@ -52,17 +54,14 @@ impl<'a> ExprParser<'a> {
         std::mem::discriminant(&self.current().kind) == std::mem::discriminant(kind)
     }
 
-    pub fn consume(&mut self, kind: TokenKind, msg: &str) -> Result<Token, String> {
+    pub fn consume(&mut self, kind: TokenKind, msg: &str) -> Result<Token, ParseError> {
         if self.check(&kind) {
             Ok(self.advance())
         } else {
-            Err(format!(
-                "{} at line {}, column {} (found {:?})",
-                msg,
-                self.current().line,
-                self.current().column,
-                self.current().kind
-            ))
+            Err(
+                ParseError::expected(msg, self.current().line, self.current().column)
+                    .found(&self.current().kind),
+            )
         }
     }
 
@@ -89,6 +88,10 @@ impl<'a> ExprParser<'a> {
             TokenKind::Or => Some(BinaryOp::Or),
             TokenKind::Xor => Some(BinaryOp::Xor),
             TokenKind::BitwiseOr => Some(BinaryOp::BitwiseOr),
+            TokenKind::BitwiseAnd => Some(BinaryOp::BitwiseAnd),
+            TokenKind::BitwiseXor => Some(BinaryOp::BitwiseXor),
+            TokenKind::ShiftLeft => Some(BinaryOp::ShiftLeft),
+            TokenKind::ShiftRight => Some(BinaryOp::ShiftRight),
             TokenKind::NullCoalesce => Some(BinaryOp::NullCoalesce),
             TokenKind::Pipe => Some(BinaryOp::Pipe),
             _ => None,
@@ -105,6 +108,13 @@ impl<'a> ExprParser<'a> {
             TokenKind::DivAssign => Some(AssignOp::DivAssign),
             TokenKind::ModAssign => Some(AssignOp::ModAssign),
             TokenKind::ConcatAssign => Some(AssignOp::ConcatAssign),
+            TokenKind::PowAssign => Some(AssignOp::PowAssign),
+            TokenKind::BitwiseAndAssign => Some(AssignOp::BitwiseAndAssign),
+            TokenKind::BitwiseOrAssign => Some(AssignOp::BitwiseOrAssign),
+            TokenKind::BitwiseXorAssign => Some(AssignOp::BitwiseXorAssign),
+            TokenKind::ShiftLeftAssign => Some(AssignOp::ShiftLeftAssign),
+            TokenKind::ShiftRightAssign => Some(AssignOp::ShiftRightAssign),
+            TokenKind::NullCoalesceAssign => Some(AssignOp::NullCoalesceAssign),
             _ => None,
         }
     }
@@ -118,21 +128,13 @@ impl<'a> ExprParser<'a> {
         }
     }
 
-    /// Parse primary expression (literals, variables, grouped expressions, etc.)
-    pub fn parse_primary(&mut self) -> Result<Expr, String> {
-        let token = self.current().clone();
-
-        match &token.kind {
-            TokenKind::Integer(_n) => self.parse_literal(),
-            TokenKind::Float(_n) => self.parse_literal(),
-            TokenKind::String(_s) => self.parse_literal(),
-            TokenKind::Heredoc(_s) => self.parse_literal(),
-            TokenKind::True => self.parse_literal(),
-            TokenKind::False => self.parse_literal(),
-            TokenKind::Null => self.parse_literal(),
-            TokenKind::LeftBracket => self.parse_array_literal(),
-            TokenKind::Variable(_) => self.parse_variable(),
-            TokenKind::LeftParen => self.parse_grouped(),
+    /// Parse unary expression
+    pub fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        match &self.current().kind {
+            TokenKind::Clone => {
+                self.advance();
+                parse_clone(self)
+            }
             TokenKind::Minus => {
                 self.advance();
                 let expr = self.parse_unary()?;
@@ -149,165 +151,11 @@ impl<'a> ExprParser<'a> {
                     expr: Box::new(expr),
                 })
             }
-            TokenKind::Increment => {
-                self.advance();
-                if let TokenKind::Variable(name) = &self.current().kind {
-                    let name = name.clone();
-                    self.advance();
-                    Ok(Expr::Unary {
-                        op: crate::ast::UnaryOp::PreInc,
-                        expr: Box::new(Expr::Variable(name)),
-                    })
-                } else {
-                    Err(format!(
-                        "Expected variable after '++' at line {}, column {}",
-                        self.current().line,
-                        self.current().column
-                    ))
-                }
-            }
-            TokenKind::Decrement => {
-                self.advance();
-                if let TokenKind::Variable(name) = &self.current().kind {
-                    let name = name.clone();
-                    self.advance();
-                    Ok(Expr::Unary {
-                        op: crate::ast::UnaryOp::PreDec,
-                        expr: Box::new(Expr::Variable(name)),
-                    })
-                } else {
-                    Err(format!(
-                        "Expected variable after '--' at line {}, column {}",
-                        self.current().line,
-                        self.current().column
-                    ))
-                }
-            }
-            TokenKind::Identifier(name) => {
-                // Check if this is the 'list' keyword for list() destructuring
-                if name.to_lowercase() == "list" {
-                    let list_expr = parse_list(self)?;
-                    return parse_postfix(self, list_expr);
-                }
-
-                self.advance();
-
-                // Check if this is a qualified name (namespace/class path)
-                let mut qualified_name = name.clone();
-                while self.check(&TokenKind::Backslash) {
-                    self.advance(); // consume '\'
-                    if let TokenKind::Identifier(next_part) = &self.current().kind {
-                        qualified_name.push('\\');
-                        qualified_name.push_str(next_part);
-                        self.advance();
-                    } else {
-                        return Err(format!(
-                            "Expected identifier after '\\' at line {}",
-                            self.current().line
-                        ));
-                    }
-                }
-
-                if self.check(&TokenKind::DoubleColon) {
-                    self.parse_static_access(qualified_name)
-                } else if self.check(&TokenKind::LeftParen) {
-                    self.parse_function_call(qualified_name)
-                } else {
-                    Err(format!(
-                        "Unexpected identifier '{}' at line {}, column {}",
-                        qualified_name, token.line, token.column
-                    ))
-                }
-            }
-            TokenKind::Match => {
-                self.advance();
-                let arrow_func = self.parse_arrow_function()?;
-                parse_postfix(self, arrow_func)
-            }
-            TokenKind::Throw => {
-                self.advance();
-                let expr = self.parse_unary()?;
-                Ok(Expr::Throw(Box::new(expr)))
-            }
-            TokenKind::Yield => {
-                self.advance();
-                let mut key: Option<Box<Expr>> = None;
-                let value;
-
-                if self.check(&TokenKind::From) {
-                    self.advance();
-                    let expr = self.parse_unary()?;
-                    return Ok(Expr::YieldFrom(Box::new(expr)));
-                }
-
-                let first_expr = self.parse_unary()?;
-                if self.check(&TokenKind::DoubleArrow) {
-                    self.advance();
-                    key = Some(Box::new(first_expr));
-                    value = Some(Box::new(self.parse_unary()?));
-                } else {
-                    value = Some(Box::new(first_expr));
-                }
-
-                Ok(Expr::Yield { key, value })
-            }
-            // Magic constants
-            TokenKind::MagicFile => {
-                self.advance();
-                Ok(Expr::MagicFile)
-            }
-            TokenKind::MagicLine => {
-                let line = token.line;
-                self.advance();
-                Ok(Expr::MagicLine { 0: line })
-            }
-            TokenKind::MagicDir => {
-                self.advance();
-                Ok(Expr::MagicDir)
-            }
-            TokenKind::MagicFunction => {
-                self.advance();
-                Ok(Expr::MagicFunction)
-            }
-            TokenKind::MagicClass => {
-                self.advance();
-                Ok(Expr::MagicClass)
-            }
-            TokenKind::MagicMethod => {
-                self.advance();
-                Ok(Expr::MagicMethod)
-            }
-            TokenKind::MagicNamespace => {
-                self.advance();
-                Ok(Expr::MagicNamespace)
-            }
-            TokenKind::MagicTrait => {
-                self.advance();
-                Ok(Expr::MagicTrait)
-            }
-            _ => Err(format!(
-                "Expected expression but found {:?} at line {}, column {}",
-                token.kind, token.line, token.column
-            )),
-        }
-    }
-
-    /// Parse unary expression
-    pub fn parse_unary(&mut self) -> Result<Expr, String> {
-        match &self.current().kind {
-            TokenKind::Minus => {
-                self.advance();
-                let expr = self.parse_unary()?;
-                Ok(Expr::Unary {
-                    op: crate::ast::UnaryOp::Neg,
-                    expr: Box::new(expr),
-                })
-            }
-            TokenKind::Not => {
+            TokenKind::At => {
                 self.advance();
                 let expr = self.parse_unary()?;
                 Ok(Expr::Unary {
-                    op: crate::ast::UnaryOp::Not,
+                    op: crate::ast::UnaryOp::Silence,
                     expr: Box::new(expr),
                 })
             }
@@ -321,10 +169,10 @@ impl<'a> ExprParser<'a> {
                         expr: Box::new(Expr::Variable(name)),
                     })
                 } else {
-                    Err(format!(
-                        "Expected variable after '++' at line {}, column {}",
+                    Err(ParseError::expected(
+                        "Expected variable after '++'",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ))
                 }
             }
@@ -338,10 +186,10 @@ impl<'a> ExprParser<'a> {
                         expr: Box::new(Expr::Variable(name)),
                     })
                 } else {
-                    Err(format!(
-                        "Expected variable after '--' at line {}, column {}",
+                    Err(ParseError::expected(
+                        "Expected variable after '--'",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ))
                 }
             }
@@ -350,7 +198,7 @@ impl<'a> ExprParser<'a> {
     }
 
     /// Pratt parser for expressions with precedence climbing
-    pub fn parse_expression(&mut self, min_prec: Precedence) -> Result<Expr, String> {
+    pub fn parse_expression(&mut self, min_prec: Precedence) -> Result<Expr, ParseError> {
         let mut left = self.parse_unary()?;
 
         loop {
@@ -365,12 +213,23 @@ impl<'a> ExprParser<'a> {
             // Handle ternary operator
             if matches!(op_token.kind, TokenKind::QuestionMark) {
                 self.advance();
-                let then_expr = self.parse_expression(Precedence::None)?;
+                let is_short = self.check(&TokenKind::Colon);
+                if let Expr::Ternary { then_expr, .. } = &left {
+                    if let Some(message) = nested_ternary_error(then_expr.is_none(), is_short) {
+                        return Err(ParseError::invalid(message, op_token.line, op_token.column));
+                    }
+                }
+                let then_expr = if is_short {
+                    None
+                } else {
+                    Some(Box::new(self.parse_expression(Precedence::None)?))
+                };
                 self.consume(TokenKind::Colon, "Expected ':' in ternary expression")?;
-                let else_expr = self.parse_expression(Precedence::Assignment)?;
+                // Ternaries group to the left, so the else branch stops at the next `?`
+                let else_expr = self.parse_expression(Precedence::Ternary)?;
                 left = Expr::Ternary {
                     condition: Box::new(left),
-                    then_expr: Box::new(then_expr),
+                    then_expr,
                     else_expr: Box::new(else_expr),
                 };
                 continue;
@@ -378,9 +237,28 @@ impl<'a> ExprParser<'a> {
 
             // Handle assignment operators
             if let Some(assign_op) = self.token_to_assignop(&op_token.kind) {
+                if left.is_nullsafe_chain() {
+                    return Err(ParseError::invalid(
+                        "Can't use nullsafe operator in write context",
+                        op_token.line,
+                        op_token.column,
+                    ));
+                }
                 match &left {
                     Expr::Variable(name) => {
                         self.advance();
+                        // $a = &$b
+                        if matches!(assign_op, AssignOp::Assign)
+                            && self.check(&TokenKind::BitwiseAnd)
+                        {
+                            self.advance();
+                            let source = self.parse_expression(Precedence::None)?;
+                            left = Expr::AssignRef {
+                                var: name.clone(),
+                                source: Box::new(source),
+                            };
+                            continue;
+                        }
                         let right = self.parse_expression(Precedence::None)?;
                         left = Expr::Assign {
                             var: name.clone(),
@@ -394,6 +272,13 @@ impl<'a> ExprParser<'a> {
                         let right = self.parse_expression(Precedence::None)?;
                         // Check if this is append syntax ($arr[] = ...)
                         let index_opt = if self.is_array_append(&left) {
+                            if !matches!(assign_op, AssignOp::Assign) {
+                                return Err(ParseError::invalid(
+                                    "Cannot use [] for reading",
+                                    op_token.line,
+                                    op_token.column,
+                                ));
+                            }
                             None
                         } else {
                             Some(index.clone())
@@ -407,35 +292,24 @@ impl<'a> ExprParser<'a> {
                         continue;
                     }
                     Expr::PropertyAccess { object, property } => {
-                        // Only support simple assignment for properties
-                        if !matches!(assign_op, AssignOp::Assign) {
-                            return Err(format!(
-                                "Compound assignment not supported for properties at line {}, column {}",
-                                op_token.line, op_token.column
-                            ));
-                        }
                         self.advance();
                         let right = self.parse_expression(Precedence::None)?;
                         left = Expr::PropertyAssign {
                             object: object.clone(),
                             property: property.clone(),
+                            op: assign_op,
                             value: Box::new(right),
                         };
                         continue;
                     }
                     Expr::StaticPropertyAccess { class, property } => {
                         // Static property assignment: ClassName::$prop = value
-                        if !matches!(assign_op, AssignOp::Assign) {
-                            return Err(format!(
-                                "Compound assignment not supported for static properties at line {}, column {}",
-                                op_token.line, op_token.column
-                            ));
-                        }
                         self.advance();
                         let right = self.parse_expression(Precedence::None)?;
                         left = Expr::StaticPropertyAssign {
                             class: class.clone(),
                             property: property.clone(),
+                            op: assign_op,
                             value: Box::new(right),
                         };
                         continue;
@@ -443,9 +317,10 @@ impl<'a> ExprParser<'a> {
                     Expr::ListDestructure { elements, .. } => {
                         // list($a, $b) = $array
                         if !matches!(assign_op, AssignOp::Assign) {
-                            return Err(format!(
-                                "Compound assignment not supported for list() destructuring at line {}, column {}",
-                                op_token.line, op_token.column
+                            return Err(ParseError::invalid(
+                                "Compound assignment not supported for list() destructuring",
+                                op_token.line,
+                                op_token.column,
                             ));
                         }
                         self.advance();
@@ -457,10 +332,7 @@ impl<'a> ExprParser<'a> {
                         continue;
                     }
                     _ => {
-                        return Err(format!(
-                            "Left side of assignment must be a variable, array element, property, static property, or list() at line {}, column {}",
-                            op_token.line, op_token.column
-                        ));
+                        return Err(ParseError::invalid("Left side of assignment must be a variable, array element, property, static property, or list()", op_token.line, op_token.column));
                     }
                 }
             }
@@ -491,3 +363,23 @@ impl<'a> ExprParser<'a> {
         Ok(left)
     }
 }
+
+/// The error for a ternary whose condition is an unparenthesized ternary,
+/// which PHP 8 rejects unless both are short ternaries (`a ?: b ?: c`)
+fn nested_ternary_error(inner_is_short: bool, outer_is_short: bool) -> Option<&'static str> {
+    match (inner_is_short, outer_is_short) {
+        (true, true) => None,
+        (false, false) => Some(
+            "Unparenthesized `a ? b : c ? d : e` is not supported. \
+             Use either `(a ? b : c) ? d : e` or `a ? b : (c ? d : e)`",
+        ),
+        (true, false) => Some(
+            "Unparenthesized `a ?: b ? c : d` is not supported. \
+             Use either `(a ?: b) ? c : d` or `a ?: (b ? c : d)`",
+        ),
+        (false, true) => Some(
+            "Unparenthesized `a ? b : c ?: d` is not supported. \
+             Use either `(a ? b : c) ?: d` or `a ? b : (c ?: d)`",
+        ),
+    }
+}
@@ -5,11 +5,55 @@
 //! expressions and can be chained together.
 
 use super::ExprParser;
-use crate::ast::{Expr, UnaryOp};
+use crate::ast::{Argument, Expr, UnaryOp};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
+/// The name a token gives a property or method after `->`: an identifier,
+/// or one of the keywords that name members of built-in classes, as in
+/// `$generator->throw($e)`
+fn member_name(kind: &TokenKind) -> Option<String> {
+    let name = match kind {
+        TokenKind::Identifier(name) => return Some(name.clone()),
+        TokenKind::Throw => "throw",
+        TokenKind::Get => "get",
+        TokenKind::Set => "set",
+        TokenKind::Default => "default",
+        TokenKind::Match => "match",
+        TokenKind::Class => "class",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+/// `$object->method(args)`, or `$object?->method(args)`
+fn method_call(object: Expr, method: String, args: Vec<Argument>, nullsafe: bool) -> Expr {
+    let object = Box::new(object);
+    match nullsafe {
+        true => Expr::NullsafeMethodCall {
+            object,
+            method,
+            args,
+        },
+        false => Expr::MethodCall {
+            object,
+            method,
+            args,
+        },
+    }
+}
+
+/// `$object->property`, or `$object?->property`
+fn property_access(object: Expr, property: String, nullsafe: bool) -> Expr {
+    let object = Box::new(object);
+    match nullsafe {
+        true => Expr::NullsafePropertyAccess { object, property },
+        false => Expr::PropertyAccess { object, property },
+    }
+}
+
 /// Parse postfix operations (array access, property access, method calls, increment/decrement)
-pub fn parse_postfix(parser: &mut ExprParser, mut expr: Expr) -> Result<Expr, String> {
+pub fn parse_postfix(parser: &mut ExprParser, mut expr: Expr) -> Result<Expr, ParseError> {
     loop {
         match &parser.current().kind {
             TokenKind::LeftBracket => {
@@ -34,17 +78,17 @@ pub fn parse_postfix(parser: &mut ExprParser, mut expr: Expr) -> Result<Expr, St
                     };
                 }
             }
-            TokenKind::Arrow => {
-                parser.advance(); // consume '->'
-                let member = if let TokenKind::Identifier(name) = &parser.current().kind {
-                    let name = name.clone();
+            TokenKind::Arrow | TokenKind::NullsafeArrow => {
+                let nullsafe = parser.check(&TokenKind::NullsafeArrow);
+                parser.advance(); // consume '->' or '?->'
+                let member = if let Some(name) = member_name(&parser.current().kind) {
                     parser.advance();
                     name
                 } else {
-                    return Err(format!(
-                        "Expected property or method name after '->' at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected property or method name after '->'",
                         parser.current().line,
-                        parser.current().column
+                        parser.current().column,
                     ));
                 };
 
@@ -54,49 +98,35 @@ pub fn parse_postfix(parser: &mut ExprParser, mut expr: Expr) -> Result<Expr, St
 
                     // Check for first-class callable: $obj->method(...)
                     // Must be ONLY ... with no other arguments
-                    if parser.check(&TokenKind::Ellipsis) {
-                        let start_pos = *parser.pos;
-                        parser.advance(); // consume '...'
-
-                        if parser.check(&TokenKind::RightParen) {
-                            parser.advance(); // consume ')'
-                            expr = Expr::CallableFromMethod {
-                                object: Box::new(expr),
-                                method: member,
-                            };
-                        } else {
-                            // Not a first-class callable, rewind
-                            *parser.pos = start_pos;
-                            // Regular method call
-                            let args = parser.parse_arguments()?;
-                            parser.consume(
-                                TokenKind::RightParen,
-                                "Expected ')' after method arguments",
-                            )?;
-                            expr = Expr::MethodCall {
-                                object: Box::new(expr),
-                                method: member,
-                                args,
-                            };
+                    if parser.check(&TokenKind::Ellipsis)
+                        && matches!(
+                            parser.tokens.get(*parser.pos + 1).map(|t| &t.kind),
+                            Some(TokenKind::RightParen)
+                        )
+                    {
+                        if nullsafe {
+                            return Err(ParseError::invalid(
+                                "Cannot combine nullsafe operator with Closure creation",
+                                parser.current().line,
+                                parser.current().column,
+                            ));
                         }
+                        parser.advance(); // consume '...'
+                        parser.advance(); // consume ')'
+                        expr = Expr::CallableFromMethod {
+                            object: Box::new(expr),
+                            method: member,
+                        };
                     } else {
-                        // Regular method call
                         let args = parser.parse_arguments()?;
                         parser.consume(
                             TokenKind::RightParen,
                             "Expected ')' after method arguments",
                         )?;
-                        expr = Expr::MethodCall {
-                            object: Box::new(expr),
-                            method: member,
-                            args,
-                        };
+                        expr = method_call(expr, member, args, nullsafe);
                     }
                 } else {
-                    expr = Expr::PropertyAccess {
-                        object: Box::new(expr),
-                        property: member,
-                    };
+                    expr = property_access(expr, member, nullsafe);
                 }
             }
             TokenKind::Increment => {
@@ -0,0 +1,248 @@
+//! Primary expression dispatch
+//!
+//! Picks the parser for the expression the current token starts:
+//! literals, variables, calls, `new`, closures, `match` and the rest.
+
+use super::{is_list_pattern, parse_list, parse_match, parse_postfix, ExprParser};
+use crate::ast::Expr;
+use crate::parser::precedence::Precedence;
+use crate::parser::ParseError;
+use crate::token::TokenKind;
+
+impl<'a> ExprParser<'a> {
+    /// Parse primary expression (literals, variables, grouped expressions, etc.)
+    pub fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.current().clone();
+
+        match &token.kind {
+            TokenKind::Integer(_n) => self.parse_literal(),
+            TokenKind::Float(_n) => self.parse_literal(),
+            TokenKind::String(_s) => self.parse_literal(),
+            TokenKind::InterpolatedString(_) => self.parse_interpolated_string(),
+            TokenKind::True => self.parse_literal(),
+            TokenKind::False => self.parse_literal(),
+            TokenKind::Null => self.parse_literal(),
+            TokenKind::LeftBracket if is_list_pattern(self) => parse_list(self),
+            TokenKind::LeftBracket => self.parse_array_literal(),
+            TokenKind::Variable(_) => self.parse_variable(),
+            TokenKind::LeftParen => self.parse_grouped(),
+            TokenKind::Minus => {
+                self.advance();
+                let expr = self.parse_unary()?;
+                Ok(Expr::Unary {
+                    op: crate::ast::UnaryOp::Neg,
+                    expr: Box::new(expr),
+                })
+            }
+            TokenKind::Not => {
+                self.advance();
+                let expr = self.parse_unary()?;
+                Ok(Expr::Unary {
+                    op: crate::ast::UnaryOp::Not,
+                    expr: Box::new(expr),
+                })
+            }
+            TokenKind::BitwiseNot => {
+                self.advance();
+                let expr = self.parse_unary()?;
+                Ok(Expr::Unary {
+                    op: crate::ast::UnaryOp::BitwiseNot,
+                    expr: Box::new(expr),
+                })
+            }
+            TokenKind::At => {
+                self.advance();
+                let expr = self.parse_unary()?;
+                Ok(Expr::Unary {
+                    op: crate::ast::UnaryOp::Silence,
+                    expr: Box::new(expr),
+                })
+            }
+            TokenKind::Increment => {
+                self.advance();
+                if let TokenKind::Variable(name) = &self.current().kind {
+                    let name = name.clone();
+                    self.advance();
+                    Ok(Expr::Unary {
+                        op: crate::ast::UnaryOp::PreInc,
+                        expr: Box::new(Expr::Variable(name)),
+                    })
+                } else {
+                    Err(ParseError::expected(
+                        "Expected variable after '++'",
+                        self.current().line,
+                        self.current().column,
+                    ))
+                }
+            }
+            TokenKind::Decrement => {
+                self.advance();
+                if let TokenKind::Variable(name) = &self.current().kind {
+                    let name = name.clone();
+                    self.advance();
+                    Ok(Expr::Unary {
+                        op: crate::ast::UnaryOp::PreDec,
+                        expr: Box::new(Expr::Variable(name)),
+                    })
+                } else {
+                    Err(ParseError::expected(
+                        "Expected variable after '--'",
+                        self.current().line,
+                        self.current().column,
+                    ))
+                }
+            }
+            TokenKind::Identifier(name) => {
+                // Check if this is the 'list' keyword for list() destructuring
+                if name.to_lowercase() == "list" {
+                    let list_expr = parse_list(self)?;
+                    return parse_postfix(self, list_expr);
+                }
+
+                self.advance();
+
+                // Check if this is a qualified name (namespace/class path)
+                let mut qualified_name = name.clone();
+                while self.check(&TokenKind::Backslash) {
+                    self.advance(); // consume '\'
+                    if let TokenKind::Identifier(next_part) = &self.current().kind {
+                        qualified_name.push('\\');
+                        qualified_name.push_str(next_part);
+                        self.advance();
+                    } else {
+                        return Err(ParseError::expected(
+                            "Expected identifier after '\\'",
+                            self.current().line,
+                            0,
+                        ));
+                    }
+                }
+
+                if self.check(&TokenKind::DoubleColon) {
+                    self.parse_static_access(qualified_name)
+                } else if self.check(&TokenKind::LeftParen) {
+                    self.parse_function_call(qualified_name)
+                } else {
+                    Ok(Expr::Constant(qualified_name))
+                }
+            }
+            TokenKind::Fn => {
+                self.advance();
+                let arrow_func = self.parse_arrow_function()?;
+                parse_postfix(self, arrow_func)
+            }
+            TokenKind::Match => {
+                let match_expr = parse_match(self)?;
+                parse_postfix(self, match_expr)
+            }
+            TokenKind::New => self.parse_new_object(),
+            TokenKind::Static | TokenKind::Parent
+                if matches!(
+                    self.tokens.get(*self.pos + 1).map(|t| &t.kind),
+                    Some(TokenKind::DoubleColon)
+                ) =>
+            {
+                let class_name = if self.check(&TokenKind::Static) {
+                    "static"
+                } else {
+                    "parent"
+                };
+                self.advance();
+                self.parse_static_access(class_name.to_string())
+            }
+            TokenKind::Fiber
+                if matches!(
+                    self.tokens.get(*self.pos + 1).map(|t| &t.kind),
+                    Some(TokenKind::DoubleColon)
+                ) =>
+            {
+                self.advance();
+                self.parse_static_access("Fiber".to_string())
+            }
+            TokenKind::Throw => {
+                self.advance();
+                let expr = self.parse_unary()?;
+                Ok(Expr::Throw(Box::new(expr)))
+            }
+            TokenKind::Yield => {
+                self.advance();
+                let mut key: Option<Box<Expr>> = None;
+                let value;
+
+                if self.check(&TokenKind::From) {
+                    self.advance();
+                    let expr = self.parse_expression(Precedence::None)?;
+                    return Ok(Expr::YieldFrom(Box::new(expr)));
+                }
+
+                // A bare `yield`, as in `$sent = yield;`, yields null
+                if [
+                    TokenKind::Semicolon,
+                    TokenKind::RightParen,
+                    TokenKind::RightBracket,
+                    TokenKind::Comma,
+                ]
+                .iter()
+                .any(|kind| self.check(kind))
+                {
+                    return Ok(Expr::Yield {
+                        key: None,
+                        value: None,
+                    });
+                }
+
+                // Everything up to `=>` or the end of the expression is
+                // yielded: `$x = yield $a + 1` yields `$a + 1`
+                let first_expr = self.parse_expression(Precedence::None)?;
+                if self.check(&TokenKind::DoubleArrow) {
+                    self.advance();
+                    key = Some(Box::new(first_expr));
+                    value = Some(Box::new(self.parse_expression(Precedence::None)?));
+                } else {
+                    value = Some(Box::new(first_expr));
+                }
+
+                Ok(Expr::Yield { key, value })
+            }
+            // Magic constants
+            TokenKind::MagicFile => {
+                self.advance();
+                Ok(Expr::MagicFile)
+            }
+            TokenKind::MagicLine => {
+                let line = token.line;
+                self.advance();
+                Ok(Expr::MagicLine { 0: line })
+            }
+            TokenKind::MagicDir => {
+                self.advance();
+                Ok(Expr::MagicDir)
+            }
+            TokenKind::MagicFunction => {
+                self.advance();
+                Ok(Expr::MagicFunction)
+            }
+            TokenKind::MagicClass => {
+                self.advance();
+                Ok(Expr::MagicClass)
+            }
+            TokenKind::MagicMethod => {
+                self.advance();
+                Ok(Expr::MagicMethod)
+            }
+            TokenKind::MagicNamespace => {
+                self.advance();
+                Ok(Expr::MagicNamespace)
+            }
+            TokenKind::MagicTrait => {
+                self.advance();
+                Ok(Expr::MagicTrait)
+            }
+            _ => Err(ParseError::expected(
+                format!("Expected expression but found {:?}", token.kind),
+                token.line,
+                token.column,
+            )),
+        }
+    }
+}
@@ -5,10 +5,11 @@
 
 use super::ExprParser;
 use crate::ast::{Expr, ListElement, MatchArm, PropertyModification};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 /// Parse match expression: match ($expr) { cond1, cond2 => result, default => result }
-pub fn parse_match(parser: &mut ExprParser) -> Result<Expr, String> {
+pub fn parse_match(parser: &mut ExprParser) -> Result<Expr, ParseError> {
     parser.advance(); // consume 'match'
     parser.consume(TokenKind::LeftParen, "Expected '(' after 'match'")?;
     let expr = parser.parse_expression(super::super::precedence::Precedence::None)?;
@@ -88,7 +89,29 @@ pub fn parse_match(parser: &mut ExprParser) -> Result<Expr, String> {
 /// Parse clone or clone with expression
 /// clone $obj
 /// clone $obj with { prop: value, ... }
-pub fn parse_clone(parser: &mut ExprParser) -> Result<Expr, String> {
+/// clone($obj, ['prop' => value, ...])
+pub fn parse_clone(parser: &mut ExprParser) -> Result<Expr, ParseError> {
+    // clone($obj, $properties): only a second argument tells it apart from
+    // cloning a parenthesized expression
+    if parser.check(&TokenKind::LeftParen) {
+        let start_pos = *parser.pos;
+        parser.advance(); // consume '('
+        let object = parser.parse_expression(super::super::precedence::Precedence::None)?;
+        if parser.check(&TokenKind::Comma) {
+            parser.advance(); // consume ','
+            let properties = parser.parse_expression(super::super::precedence::Precedence::None)?;
+            if parser.check(&TokenKind::Comma) {
+                parser.advance(); // trailing comma
+            }
+            parser.consume(TokenKind::RightParen, "Expected ')' after clone arguments")?;
+            return Ok(Expr::CloneWithProperties {
+                object: Box::new(object),
+                properties: Box::new(properties),
+            });
+        }
+        *parser.pos = start_pos;
+    }
+
     // Parse the object expression
     let object = Box::new(parser.parse_unary()?);
 
@@ -98,9 +121,10 @@ pub fn parse_clone(parser: &mut ExprParser) -> Result<Expr, String> {
 
         // Expect opening brace
         if !parser.check(&TokenKind::LeftBrace) {
-            return Err(format!(
-                "Expected '{{' after 'with' at line {}",
-                parser.current().line
+            return Err(ParseError::expected(
+                "Expected '{' after 'with'",
+                parser.current().line,
+                0,
             ));
         }
         parser.advance(); // consume '{'
@@ -119,9 +143,10 @@ pub fn parse_clone(parser: &mut ExprParser) -> Result<Expr, String> {
             let property = match &parser.current().kind {
                 TokenKind::Identifier(name) => name.clone(),
                 _ => {
-                    return Err(format!(
-                        "Expected property name at line {}",
-                        parser.current().line
+                    return Err(ParseError::expected(
+                        "Expected property name",
+                        parser.current().line,
+                        0,
                     ))
                 }
             };
@@ -129,9 +154,10 @@ pub fn parse_clone(parser: &mut ExprParser) -> Result<Expr, String> {
 
             // Expect colon
             if !parser.check(&TokenKind::Colon) {
-                return Err(format!(
-                    "Expected ':' after property name at line {}",
-                    parser.current().line
+                return Err(ParseError::expected(
+                    "Expected ':' after property name",
+                    parser.current().line,
+                    0,
                 ));
             }
             parser.advance(); // consume ':'
@@ -154,17 +180,19 @@ pub fn parse_clone(parser: &mut ExprParser) -> Result<Expr, String> {
                 parser.advance();
                 break;
             } else {
-                return Err(format!(
-                    "Expected ',' or '}}' after property value at line {}",
-                    parser.current().line
+                return Err(ParseError::expected(
+                    "Expected ',' or '}' after property value",
+                    parser.current().line,
+                    0,
                 ));
             }
         }
 
         if modifications.is_empty() {
-            return Err(format!(
-                "Clone with syntax requires at least one property modification at line {}",
-                parser.current().line
+            return Err(ParseError::invalid(
+                "Clone with syntax requires at least one property modification",
+                parser.current().line,
+                0,
             ));
         }
 
@@ -178,150 +206,119 @@ pub fn parse_clone(parser: &mut ExprParser) -> Result<Expr, String> {
     }
 }
 
-/// Parse list() destructuring: list($a, $b) = $array
-/// Supports: list($a, $b), list("key" => $a, "b" => $b), list($a, list($b, $c))
-pub fn parse_list(parser: &mut ExprParser) -> Result<Expr, String> {
-    parser.advance(); // consume 'list'
+/// Parse a destructuring pattern, `list(...)` or `[...]`, before its `=`:
+/// list($a, , $c), ['x' => $x, 'y' => [$y1, $y2]], [$obj->a, $arr[]]
+pub fn parse_list(parser: &mut ExprParser) -> Result<Expr, ParseError> {
+    Ok(Expr::ListDestructure {
+        elements: parse_list_pattern(parser)?,
+        // Placeholder, replaced by the array once the `=` is parsed
+        array: Box::new(Expr::Null),
+    })
+}
 
-    parser.consume(TokenKind::LeftParen, "Expected '(' after 'list'")?;
+/// The elements of a `list(...)` or `[...]` pattern, None for a skipped
+/// slot. Every target must be writable, and either all elements have a
+/// key or none has.
+pub fn parse_list_pattern(parser: &mut ExprParser) -> Result<Vec<Option<ListElement>>, ParseError> {
+    let close = if parser.check(&TokenKind::LeftBracket) {
+        parser.advance(); // consume '['
+        TokenKind::RightBracket
+    } else {
+        parser.advance(); // consume 'list'
+        parser.consume(TokenKind::LeftParen, "Expected '(' after 'list'")?;
+        TokenKind::RightParen
+    };
 
     let mut elements = Vec::new();
+    while !parser.check(&close) {
+        if parser.check(&TokenKind::Comma) {
+            parser.advance();
+            elements.push(None);
+            continue;
+        }
+        let mut token = parser.current().clone();
+        let mut key = None;
+        let mut value = parse_list_target(parser)?;
+        if parser.check(&TokenKind::DoubleArrow) {
+            parser.advance();
+            token = parser.current().clone();
+            key = Some(Box::new(value));
+            value = parse_list_target(parser)?;
+        }
+        if !is_writable(&value) {
+            return Err(ParseError::invalid(
+                "Assignments can only happen to writable values",
+                token.line,
+                token.column,
+            ));
+        }
+        elements.push(Some(ListElement {
+            key,
+            value: Box::new(value),
+        }));
+        if !parser.check(&close) {
+            parser.consume(
+                TokenKind::Comma,
+                "Expected ',' between destructuring targets",
+            )?;
+        }
+    }
+    let end = parser.advance(); // consume ')' or ']'
+
+    let keyed = elements
+        .iter()
+        .flatten()
+        .filter(|e| e.key.is_some())
+        .count();
+    if keyed > 0 && keyed < elements.iter().flatten().count() {
+        return Err(ParseError::invalid(
+            "Cannot mix keyed and unkeyed array entries in assignments",
+            end.line,
+            end.column,
+        ));
+    }
+    Ok(elements)
+}
 
-    // Handle empty list: list()
-    if !parser.check(&TokenKind::RightParen) {
-        loop {
-            if parser.check(&TokenKind::RightParen) {
-                break;
-            }
-
-            // Check for key => value syntax
-            if parser.check(&TokenKind::DoubleArrow) {
-                return Err(format!(
-                    "Unexpected '=>' in list at line {}, column {}",
-                    parser.current().line,
-                    parser.current().column
-                ));
-            }
-
-            // Check if this is a nested list or a variable
-            if parser.check(&TokenKind::Identifier(String::new())) {
-                let ident = match &parser.current().kind {
-                    TokenKind::Identifier(name) => name.clone(),
-                    _ => unreachable!(),
-                };
-
-                // Check if it's actually 'list' for nested destructuring
-                if ident.to_lowercase() == "list" {
-                    // Parse nested list
-                    let nested = parse_list(parser)?;
-                    elements.push(ListElement {
-                        key: None,
-                        value: Box::new(nested),
-                    });
-                } else {
-                    return Err(format!(
-                        "Expected variable or 'list' in list() at line {}, column {}",
-                        parser.current().line,
-                        parser.current().column
-                    ));
-                }
-            } else if parser.check(&TokenKind::Variable(String::new())) {
-                // Simple variable: $a
-                if let TokenKind::Variable(name) = &parser.current().kind {
-                    let name = name.clone();
-                    parser.advance();
-                    elements.push(ListElement {
-                        key: None,
-                        value: Box::new(Expr::Variable(name)),
-                    });
-                }
-            } else if parser.check(&TokenKind::String(String::new())) {
-                // Key => variable syntax: "key" => $var
-                // Parse the key
-                let key_token = parser.current().clone();
-                let key = match &key_token.kind {
-                    TokenKind::String(s) => {
-                        let k = s.clone();
-                        parser.advance();
-                        k
-                    }
-                    _ => unreachable!(),
-                };
-
-                // Expect =>
-                parser.consume(
-                    TokenKind::DoubleArrow,
-                    "Expected '=>' after string key in list()",
-                )?;
-
-                // Parse the value (must be variable or nested list)
-                if parser.check(&TokenKind::Identifier(String::new())) {
-                    let ident = match &parser.current().kind {
-                        TokenKind::Identifier(name) => name.clone(),
-                        _ => unreachable!(),
-                    };
-
-                    if ident.to_lowercase() == "list" {
-                        let nested = parse_list(parser)?;
-                        elements.push(ListElement {
-                            key: Some(Box::new(Expr::String(key))),
-                            value: Box::new(nested),
-                        });
-                    } else {
-                        return Err(format!(
-                            "Expected 'list' after '=>' in list() at line {}, column {}",
-                            parser.current().line,
-                            parser.current().column
-                        ));
-                    }
-                } else if parser.check(&TokenKind::Variable(String::new())) {
-                    if let TokenKind::Variable(name) = &parser.current().kind {
-                        let name = name.clone();
-                        parser.advance();
-                        elements.push(ListElement {
-                            key: Some(Box::new(Expr::String(key))),
-                            value: Box::new(Expr::Variable(name)),
-                        });
-                    }
-                } else {
-                    return Err(format!(
-                        "Expected variable or 'list' after '=>' in list() at line {}, column {}",
-                        parser.current().line,
-                        parser.current().column
-                    ));
-                }
-            } else {
-                return Err(format!(
-                    "Expected variable or 'list' in list() at line {}, column {}",
-                    parser.current().line,
-                    parser.current().column
-                ));
-            }
-
-            // Check for comma or closing paren
-            if parser.check(&TokenKind::Comma) {
-                parser.advance();
-                if parser.check(&TokenKind::RightParen) {
-                    break;
+/// Whether the parser is at a `[...]` pattern: a `[` closed by a `]`
+/// that an `=` follows
+pub fn is_list_pattern(parser: &ExprParser) -> bool {
+    let mut depth = 0usize;
+    for (i, token) in parser.tokens.iter().enumerate().skip(*parser.pos) {
+        match token.kind {
+            TokenKind::LeftBracket => depth += 1,
+            TokenKind::RightBracket => {
+                depth -= 1;
+                if depth == 0 {
+                    return matches!(
+                        parser.tokens.get(i + 1).map(|t| &t.kind),
+                        Some(TokenKind::Assign)
+                    );
                 }
-            } else if parser.check(&TokenKind::RightParen) {
-                break;
-            } else {
-                return Err(format!(
-                    "Expected ',' or ')' in list() at line {}, column {}",
-                    parser.current().line,
-                    parser.current().column
-                ));
             }
+            TokenKind::Eof => return false,
+            _ => {}
         }
     }
+    false
+}
 
-    parser.consume(TokenKind::RightParen, "Expected ')' to close list()")?;
+/// A key or target of a pattern: a nested pattern or an expression
+fn parse_list_target(parser: &mut ExprParser) -> Result<Expr, ParseError> {
+    match &parser.current().kind {
+        TokenKind::LeftBracket => parse_list(parser),
+        TokenKind::Identifier(name) if name.eq_ignore_ascii_case("list") => parse_list(parser),
+        _ => parser.parse_expression(super::super::precedence::Precedence::None),
+    }
+}
 
-    // Create a placeholder that will be used in assignment context
-    // The actual array expression comes after the = operator
-    Ok(Expr::ListDestructure {
-        elements,
-        array: Box::new(Expr::Null), // Placeholder, will be replaced during assignment parsing
-    })
+/// Whether a pattern can assign to `target`
+fn is_writable(target: &Expr) -> bool {
+    match target {
+        Expr::Variable(_) | Expr::StaticPropertyAccess { .. } | Expr::ListDestructure { .. } => {
+            true
+        }
+        Expr::ArrayAccess { .. } | Expr::PropertyAccess { .. } => !target.is_nullsafe_chain(),
+        _ => false,
+    }
 }
@@ -3,6 +3,7 @@
 //! This module contains the recursive descent parser that transforms
 //! tokens into an AST.
 
+mod error;
 mod expr;
 mod precedence;
 mod stmt;
@@ -11,6 +12,8 @@ use crate::ast::Program;
 use crate::token::{Token, TokenKind};
 use stmt::StmtParser;
 
+pub use error::ParseError;
+
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
@@ -30,14 +33,12 @@ impl Parser {
         std::mem::discriminant(&current.kind) == std::mem::discriminant(kind)
     }
 
-    pub fn parse(&mut self) -> Result<Program, String> {
+    pub fn parse(&mut self) -> Result<Program, ParseError> {
         let mut statements = Vec::new();
 
         while !self.check(&TokenKind::Eof) {
             let mut stmt_parser = StmtParser::new(&self.tokens, &mut self.pos);
-            if let Some(stmt) = stmt_parser.parse_statement()? {
-                statements.push(stmt);
-            }
+            stmt_parser.parse_statement_into(&mut statements)?;
         }
 
         Ok(Program { statements })
@@ -15,13 +15,16 @@ pub enum Precedence {
     And = 6,          // && and
     Xor = 7,          // xor
     BitwiseOr = 8,    // | (bitwise OR)
-    Equality = 9,     // == === != !==
-    Comparison = 10,  // < > <= >= <=>
-    Concat = 11,      // .
-    AddSub = 12,      // + -
-    MulDiv = 13,      // * / %
-    Pow = 14,         // ** (right associative)
-    Unary = 15,       // ! - ++ --
+    BitwiseXor = 9,   // ^
+    BitwiseAnd = 10,  // &
+    Equality = 11,    // == === != !==
+    Comparison = 12,  // < > <= >= <=>
+    Concat = 13,      // .
+    Shift = 14,       // << >>
+    AddSub = 15,      // + -
+    MulDiv = 16,      // * / %
+    Pow = 17,         // ** (right associative)
+    Unary = 18,       // ! - ~ ++ --
 }
 
 /// Get precedence for a token kind
@@ -33,7 +36,14 @@ pub fn get_precedence(kind: &TokenKind) -> Precedence {
         | TokenKind::MulAssign
         | TokenKind::DivAssign
         | TokenKind::ModAssign
-        | TokenKind::ConcatAssign => Precedence::Assignment,
+        | TokenKind::ConcatAssign
+        | TokenKind::PowAssign
+        | TokenKind::BitwiseAndAssign
+        | TokenKind::BitwiseOrAssign
+        | TokenKind::BitwiseXorAssign
+        | TokenKind::ShiftLeftAssign
+        | TokenKind::ShiftRightAssign
+        | TokenKind::NullCoalesceAssign => Precedence::Assignment,
 
         TokenKind::QuestionMark => Precedence::Ternary,
         TokenKind::Pipe => Precedence::Pipe,
@@ -43,6 +53,8 @@ pub fn get_precedence(kind: &TokenKind) -> Precedence {
         TokenKind::And => Precedence::And,
         TokenKind::Xor => Precedence::Xor,
         TokenKind::BitwiseOr => Precedence::BitwiseOr,
+        TokenKind::BitwiseXor => Precedence::BitwiseXor,
+        TokenKind::BitwiseAnd => Precedence::BitwiseAnd,
 
         TokenKind::Equal | TokenKind::Identical | TokenKind::NotEqual | TokenKind::NotIdentical => {
             Precedence::Equality
@@ -55,6 +67,7 @@ pub fn get_precedence(kind: &TokenKind) -> Precedence {
         | TokenKind::Spaceship => Precedence::Comparison,
 
         TokenKind::Concat => Precedence::Concat,
+        TokenKind::ShiftLeft | TokenKind::ShiftRight => Precedence::Shift,
         TokenKind::Plus | TokenKind::Minus => Precedence::AddSub,
         TokenKind::Mul | TokenKind::Div | TokenKind::Mod => Precedence::MulDiv,
         TokenKind::Pow => Precedence::Pow,
@@ -75,6 +88,13 @@ pub fn is_right_assoc(kind: &TokenKind) -> bool {
             | TokenKind::DivAssign
             | TokenKind::ModAssign
             | TokenKind::ConcatAssign
+            | TokenKind::PowAssign
+            | TokenKind::BitwiseAndAssign
+            | TokenKind::BitwiseOrAssign
+            | TokenKind::BitwiseXorAssign
+            | TokenKind::ShiftLeftAssign
+            | TokenKind::ShiftRightAssign
+            | TokenKind::NullCoalesceAssign
             | TokenKind::NullCoalesce
     )
 }
@@ -4,12 +4,13 @@
 
 use super::{Precedence, StmtParser};
 use crate::ast::{Attribute, AttributeArgument, Stmt};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> StmtParser<'a> {
     /// Parse attributes: #[AttributeName(args)] or #[AttributeName]
     /// Can have multiple attributes: #[Attr1] #[Attr2(arg)] or #[Attr1, Attr2]
-    pub(crate) fn parse_attributes(&mut self) -> Result<Vec<Attribute>, String> {
+    pub(crate) fn parse_attributes(&mut self) -> Result<Vec<Attribute>, ParseError> {
         let mut attributes = Vec::new();
 
         while self.check(&TokenKind::Hash) {
@@ -29,10 +30,10 @@ impl<'a> StmtParser<'a> {
                     self.advance();
                     name
                 } else {
-                    return Err(format!(
-                        "Expected attribute name at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected attribute name",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 };
 
@@ -89,7 +90,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse echo statement
-    pub(crate) fn parse_echo(&mut self) -> Result<Stmt, String> {
+    pub(crate) fn parse_echo(&mut self) -> Result<Stmt, ParseError> {
         self.advance();
         let mut expressions = Vec::new();
 
@@ -103,10 +104,10 @@ impl<'a> StmtParser<'a> {
         if self.check(&TokenKind::Semicolon) {
             self.advance();
         } else if !self.check(&TokenKind::CloseTag) && !self.check(&TokenKind::Eof) {
-            return Err(format!(
-                "Expected ';' or '?>' after echo at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected ';' or '?>' after echo",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         }
 
@@ -114,17 +115,17 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse throw statement
-    pub(crate) fn parse_throw(&mut self) -> Result<Option<Stmt>, String> {
+    pub(crate) fn parse_throw(&mut self) -> Result<Option<Stmt>, ParseError> {
         self.advance();
         let expr = self.parse_expression(Precedence::None)?;
 
         if self.check(&TokenKind::Semicolon) {
             self.advance();
         } else if !self.check(&TokenKind::CloseTag) && !self.check(&TokenKind::Eof) {
-            return Err(format!(
-                "Expected ';' after throw at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected ';' after throw",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         }
 
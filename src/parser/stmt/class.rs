@@ -2,6 +2,7 @@
 //!
 //! Handles parsing of class declarations including:
 //! - Class declaration with extends/implements
+//! - Class constants
 //! - Class properties
 //! - Class methods
 //! - Visibility modifiers
@@ -10,6 +11,7 @@
 
 use super::StmtParser;
 use crate::ast::{Stmt, Visibility};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> StmtParser<'a> {
@@ -28,7 +30,7 @@ impl<'a> StmtParser<'a> {
         &self,
         read: Visibility,
         write: Visibility,
-    ) -> Result<(), String> {
+    ) -> Result<(), ParseError> {
         use crate::ast::Visibility::*;
 
         let valid = match (read, write) {
@@ -44,10 +46,10 @@ impl<'a> StmtParser<'a> {
         };
 
         if !valid {
-            Err(format!(
-                "Write visibility must be more restrictive than read visibility at line {}, column {}",
+            Err(ParseError::invalid(
+                "Write visibility must be more restrictive than read visibility",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ))
         } else {
             Ok(())
@@ -55,7 +57,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse class declaration
-    pub fn parse_class(&mut self) -> Result<Stmt, String> {
+    pub fn parse_class(&mut self) -> Result<Stmt, ParseError> {
         // Parse class modifiers in any order: abstract, final, readonly
         let mut is_abstract = false;
         let mut is_final = false;
@@ -64,20 +66,20 @@ impl<'a> StmtParser<'a> {
         loop {
             if self.check(&TokenKind::Abstract) {
                 if is_final {
-                    return Err(format!(
-                        "Cannot use 'abstract' with 'final' at line {}, column {}",
+                    return Err(ParseError::invalid(
+                        "Cannot use 'abstract' with 'final'",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
                 is_abstract = true;
                 self.advance();
             } else if self.check(&TokenKind::Final) {
                 if is_abstract {
-                    return Err(format!(
-                        "Cannot use 'final' with 'abstract' at line {}, column {}",
+                    return Err(ParseError::invalid(
+                        "Cannot use 'final' with 'abstract'",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
                 is_final = true;
@@ -97,10 +99,10 @@ impl<'a> StmtParser<'a> {
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected class name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected class name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -134,6 +136,7 @@ impl<'a> StmtParser<'a> {
             trait_uses.push(self.parse_trait_use()?);
         }
 
+        let mut constants = Vec::new();
         let mut properties = Vec::new();
         let mut methods = Vec::new();
 
@@ -144,11 +147,13 @@ impl<'a> StmtParser<'a> {
             // Check for abstract modifier
             let member_is_abstract = if self.check(&TokenKind::Abstract) {
                 if !is_abstract {
-                    return Err(format!(
-                        "Non-abstract class '{}' cannot contain abstract methods at line {}, column {}",
-                        name,
+                    return Err(ParseError::invalid(
+                        format!(
+                            "Non-abstract class '{}' cannot contain abstract methods",
+                            name
+                        ),
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
                 self.advance();
@@ -160,10 +165,10 @@ impl<'a> StmtParser<'a> {
             // Check for final modifier for method
             let member_is_final = if self.check(&TokenKind::Final) {
                 if member_is_abstract {
-                    return Err(format!(
-                        "Cannot use 'final' with 'abstract' at line {}, column {}",
+                    return Err(ParseError::invalid(
+                        "Cannot use 'final' with 'abstract'",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
                 self.advance();
@@ -183,6 +188,12 @@ impl<'a> StmtParser<'a> {
             // Parse first visibility modifier
             let first_visibility = self.parse_visibility();
 
+            if self.check(&TokenKind::Const) {
+                self.advance(); // consume 'const'
+                constants.push(self.parse_class_constant(attributes)?);
+                continue;
+            }
+
             // Check for asymmetric visibility: read_visibility write_visibility(set)
             // Example: public private(set) means public read, private write
             let (read_visibility, write_visibility) = if self.check(&TokenKind::Public)
@@ -207,17 +218,17 @@ impl<'a> StmtParser<'a> {
                         // first_visibility is read, second_vis is write
                         (first_visibility, Some(second_vis))
                     } else {
-                        return Err(format!(
-                            "Expected 'set' after '(' in asymmetric visibility at line {}, column {}",
+                        return Err(ParseError::expected(
+                            "Expected 'set' after '(' in asymmetric visibility",
                             self.current().line,
-                            self.current().column
+                            self.current().column,
                         ));
                     }
                 } else {
-                    return Err(format!(
-                        "Expected '(set)' after second visibility modifier at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected '(set)' after second visibility modifier",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
             } else {
@@ -257,16 +268,16 @@ impl<'a> StmtParser<'a> {
 
             // Validate: readonly and asymmetric visibility are incompatible
             if readonly && write_visibility.is_some() {
-                return Err(format!(
-                    "Readonly properties cannot have asymmetric visibility at line {}, column {}",
+                return Err(ParseError::invalid(
+                    "Readonly properties cannot have asymmetric visibility",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
 
             // Parse type hints if present (for property types)
             // Note: Currently type hints are parsed but not yet enforced for properties
-            let _property_type = if let TokenKind::Identifier(_) = &self.current().kind {
+            let property_type = if let TokenKind::Identifier(_) = &self.current().kind {
                 Some(self.parse_type_hint()?)
             } else if self.check(&TokenKind::QuestionMark) {
                 Some(self.parse_type_hint()?)
@@ -284,25 +295,26 @@ impl<'a> StmtParser<'a> {
                 // Parse property with readonly and static modifiers
                 let mut prop = self.parse_property(read_visibility)?;
                 prop.write_visibility = write_visibility;
+                prop.type_hint = property_type;
                 prop.readonly = readonly;
                 prop.is_static = is_static;
                 prop.attributes = attributes;
 
                 // Validation: property hooks and asymmetric visibility are incompatible
                 if !prop.hooks.is_empty() && write_visibility.is_some() {
-                    return Err(format!(
-                        "Property hooks cannot be combined with asymmetric visibility at line {}, column {}",
+                    return Err(ParseError::invalid(
+                        "Property hooks cannot be combined with asymmetric visibility",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
 
                 properties.push(prop);
             } else {
-                return Err(format!(
-                    "Expected property or method in class at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected property or method in class",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         }
@@ -313,13 +325,7 @@ impl<'a> StmtParser<'a> {
         if readonly {
             for property in &properties {
                 if property.readonly {
-                    return Err(format!(
-                        "Property '{}' cannot have explicit 'readonly' modifier in readonly class '{}' at line {}, column {}",
-                        property.name,
-                        name,
-                        self.current().line,
-                        self.current().column
-                    ));
+                    return Err(ParseError::invalid(format!("Property '{}' cannot have explicit 'readonly' modifier in readonly class '{}'", property.name, name), self.current().line, self.current().column));
                 }
             }
         }
@@ -332,6 +338,7 @@ impl<'a> StmtParser<'a> {
             parent,
             interfaces,
             trait_uses,
+            constants,
             properties,
             methods,
             attributes: Vec::new(),
@@ -9,19 +9,21 @@
 //! - switch statements
 //! - break and continue statements
 
+use super::super::expr::{parse_list_pattern, ExprParser};
 use super::super::precedence::Precedence;
 use super::StmtParser;
-use crate::ast::{Stmt, SwitchCase};
+use crate::ast::{ForeachTarget, Stmt, SwitchCase};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> StmtParser<'a> {
     /// Parse if statement
-    pub fn parse_if(&mut self) -> Result<Stmt, String> {
+    pub fn parse_if(&mut self) -> Result<Stmt, ParseError> {
         self.parse_if_internal(false)
     }
 
     /// Internal parse if statement with flag for nested else-if
-    fn parse_if_internal(&mut self, is_nested_else_if: bool) -> Result<Stmt, String> {
+    fn parse_if_internal(&mut self, is_nested_else_if: bool) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'if'
         self.consume(TokenKind::LeftParen, "Expected '(' after 'if'")?;
         let condition = self.parse_expression(Precedence::None)?;
@@ -62,7 +64,7 @@ impl<'a> StmtParser<'a> {
                 TokenKind::Endif,
                 "Expected 'endif' to close alternative if syntax",
             )?;
-            self.consume(TokenKind::Semicolon, "Expected ';' after 'endif'")?;
+            self.consume_statement_end("Expected ';' after 'endif'")?;
         }
 
         Ok(Stmt::If {
@@ -74,7 +76,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse while statement
-    pub fn parse_while(&mut self) -> Result<Stmt, String> {
+    pub fn parse_while(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'while'
         self.consume(TokenKind::LeftParen, "Expected '(' after 'while'")?;
         let condition = self.parse_expression(Precedence::None)?;
@@ -88,14 +90,14 @@ impl<'a> StmtParser<'a> {
                 TokenKind::Endwhile,
                 "Expected 'endwhile' to close alternative while syntax",
             )?;
-            self.consume(TokenKind::Semicolon, "Expected ';' after 'endwhile'")?;
+            self.consume_statement_end("Expected ';' after 'endwhile'")?;
         }
 
         Ok(Stmt::While { condition, body })
     }
 
     /// Parse do-while statement
-    pub fn parse_do_while(&mut self) -> Result<Stmt, String> {
+    pub fn parse_do_while(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'do'
         let body = self.parse_block()?;
         self.consume(TokenKind::While, "Expected 'while' after do block")?;
@@ -111,7 +113,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse for statement
-    pub fn parse_for(&mut self) -> Result<Stmt, String> {
+    pub fn parse_for(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'for'
         self.consume(TokenKind::LeftParen, "Expected '(' after 'for'")?;
 
@@ -144,7 +146,7 @@ impl<'a> StmtParser<'a> {
                 TokenKind::Endfor,
                 "Expected 'endfor' to close alternative for syntax",
             )?;
-            self.consume(TokenKind::Semicolon, "Expected ';' after 'endfor'")?;
+            self.consume_statement_end("Expected ';' after 'endfor'")?;
         }
 
         Ok(Stmt::For {
@@ -156,41 +158,38 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse foreach statement
-    pub fn parse_foreach(&mut self) -> Result<Stmt, String> {
+    pub fn parse_foreach(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'foreach'
         self.consume(TokenKind::LeftParen, "Expected '(' after 'foreach'")?;
 
         let array = self.parse_expression(Precedence::None)?;
         self.consume(TokenKind::As, "Expected 'as' in foreach")?;
 
-        let first_var = if let TokenKind::Variable(name) = &self.current().kind {
-            let name = name.clone();
-            self.advance();
-            name
-        } else {
-            return Err(format!(
-                "Expected variable after 'as' at line {}, column {}",
-                self.current().line,
-                self.current().column
-            ));
-        };
+        let first_token = self.current().clone();
+        let first_by_ref = self.parse_foreach_by_ref()?;
+        let first = self.parse_foreach_target("Expected variable after 'as'")?;
 
-        let (key, value) = if self.check(&TokenKind::DoubleArrow) {
-            self.advance(); // consume '=>'
-
-            if let TokenKind::Variable(val_name) = &self.current().kind {
-                let val_name = val_name.clone();
-                self.advance();
-                (Some(first_var), val_name)
-            } else {
-                return Err(format!(
-                    "Expected variable after '=>' at line {}, column {}",
-                    self.current().line,
-                    self.current().column
+        let (key, value, by_ref) = if self.check(&TokenKind::DoubleArrow) {
+            let ForeachTarget::Variable(key) = first else {
+                return Err(ParseError::invalid(
+                    "Cannot use list as key element",
+                    first_token.line,
+                    first_token.column,
+                ));
+            };
+            if first_by_ref {
+                return Err(ParseError::invalid(
+                    "Key element cannot be a reference",
+                    first_token.line,
+                    first_token.column,
                 ));
             }
+            self.advance(); // consume '=>'
+            let by_ref = self.parse_foreach_by_ref()?;
+            let value = self.parse_foreach_target("Expected variable after '=>'")?;
+            (Some(key), value, by_ref)
         } else {
-            (None, first_var)
+            (None, first, first_by_ref)
         };
 
         self.consume(TokenKind::RightParen, "Expected ')' after foreach")?;
@@ -203,19 +202,59 @@ impl<'a> StmtParser<'a> {
                 TokenKind::Endforeach,
                 "Expected 'endforeach' to close alternative foreach syntax",
             )?;
-            self.consume(TokenKind::Semicolon, "Expected ';' after 'endforeach'")?;
+            self.consume_statement_end("Expected ';' after 'endforeach'")?;
         }
 
         Ok(Stmt::Foreach {
             array,
             key,
             value,
+            by_ref,
             body,
         })
     }
 
+    /// Consume the `&` of a by-reference foreach value, which must be a
+    /// variable
+    fn parse_foreach_by_ref(&mut self) -> Result<bool, ParseError> {
+        if !self.check(&TokenKind::BitwiseAnd) {
+            return Ok(false);
+        }
+        self.advance();
+        if !matches!(self.current().kind, TokenKind::Variable(_)) {
+            return Err(ParseError::expected(
+                "Expected variable after '&'",
+                self.current().line,
+                self.current().column,
+            ));
+        }
+        Ok(true)
+    }
+
+    /// Parse what a foreach loop assigns to: a variable, or a `[...]` or
+    /// `list(...)` pattern to destructure each value with
+    fn parse_foreach_target(&mut self, expected: &str) -> Result<ForeachTarget, ParseError> {
+        let token = self.current().clone();
+        match &token.kind {
+            TokenKind::Variable(name) => {
+                self.advance();
+                Ok(ForeachTarget::Variable(name.clone()))
+            }
+            TokenKind::LeftBracket => self.parse_foreach_pattern(),
+            TokenKind::Identifier(name) if name.eq_ignore_ascii_case("list") => {
+                self.parse_foreach_pattern()
+            }
+            _ => Err(ParseError::expected(expected, token.line, token.column)),
+        }
+    }
+
+    fn parse_foreach_pattern(&mut self) -> Result<ForeachTarget, ParseError> {
+        let mut expr_parser = ExprParser::new(self.tokens, self.pos);
+        parse_list_pattern(&mut expr_parser).map(ForeachTarget::List)
+    }
+
     /// Parse switch statement
-    pub fn parse_switch(&mut self) -> Result<Stmt, String> {
+    pub fn parse_switch(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'switch'
         self.consume(TokenKind::LeftParen, "Expected '(' after 'switch'")?;
         let expr = self.parse_expression(Precedence::None)?;
@@ -232,57 +271,59 @@ impl<'a> StmtParser<'a> {
             self.consume(TokenKind::LeftBrace, "Expected '{' or ':' after switch")?;
         }
 
+        // A template closes the tag after `switch (...):` and opens it
+        // again for the first case
+        while match &self.current().kind {
+            TokenKind::OpenTag | TokenKind::CloseTag => true,
+            TokenKind::Html(html) => html.trim().is_empty(),
+            _ => false,
+        } {
+            self.advance();
+        }
+
         let mut cases = Vec::new();
-        let mut default = None;
+        let mut has_default = false;
 
         while !self.check(&TokenKind::RightBrace) && !self.check(&TokenKind::Eof) {
             if using_alt_syntax && self.check(&TokenKind::Endswitch) {
                 break;
             }
 
-            if self.check(&TokenKind::Case) {
+            let value = if self.check(&TokenKind::Case) {
                 self.advance();
                 let value = self.parse_expression(Precedence::None)?;
                 self.consume(TokenKind::Colon, "Expected ':' after case value")?;
-
-                let mut body = Vec::new();
-                while !self.check(&TokenKind::Case)
-                    && !self.check(&TokenKind::Default)
-                    && !self.check(&TokenKind::RightBrace)
-                    && !self.check(&TokenKind::Eof)
-                {
-                    if using_alt_syntax && self.check(&TokenKind::Endswitch) {
-                        break;
-                    }
-
-                    if let Some(stmt) = self.parse_statement()? {
-                        body.push(stmt);
-                    }
-                }
-
-                cases.push(SwitchCase { value, body });
+                Some(value)
             } else if self.check(&TokenKind::Default) {
+                if has_default {
+                    return Err(ParseError::invalid(
+                        "Switch statements may only contain one default clause",
+                        self.current().line,
+                        self.current().column,
+                    ));
+                }
+                has_default = true;
                 self.advance();
                 self.consume(TokenKind::Colon, "Expected ':' after 'default'")?;
-
-                let mut body = Vec::new();
-                while !self.check(&TokenKind::Case)
-                    && !self.check(&TokenKind::RightBrace)
-                    && !self.check(&TokenKind::Eof)
-                {
-                    if using_alt_syntax && self.check(&TokenKind::Endswitch) {
-                        break;
-                    }
-
-                    if let Some(stmt) = self.parse_statement()? {
-                        body.push(stmt);
-                    }
-                }
-
-                default = Some(body);
+                None
             } else {
                 break;
+            };
+
+            let mut body = Vec::new();
+            while !self.check(&TokenKind::Case)
+                && !self.check(&TokenKind::Default)
+                && !self.check(&TokenKind::RightBrace)
+                && !self.check(&TokenKind::Eof)
+            {
+                if using_alt_syntax && self.check(&TokenKind::Endswitch) {
+                    break;
+                }
+
+                self.parse_statement_into(&mut body)?;
             }
+
+            cases.push(SwitchCase { value, body });
         }
 
         if using_alt_syntax {
@@ -290,20 +331,16 @@ impl<'a> StmtParser<'a> {
                 TokenKind::Endswitch,
                 "Expected 'endswitch' to close alternative switch syntax",
             )?;
-            self.consume(TokenKind::Semicolon, "Expected ';' after 'endswitch'")?;
+            self.consume_statement_end("Expected ';' after 'endswitch'")?;
         } else {
             self.consume(TokenKind::RightBrace, "Expected '}' after switch")?;
         }
 
-        Ok(Stmt::Switch {
-            expr,
-            cases,
-            default,
-        })
+        Ok(Stmt::Switch { expr, cases })
     }
 
     /// Parse break statement
-    pub fn parse_break(&mut self) -> Result<Stmt, String> {
+    pub fn parse_break(&mut self) -> Result<Stmt, ParseError> {
         self.advance();
         if self.check(&TokenKind::Semicolon) {
             self.advance();
@@ -312,7 +349,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse continue statement
-    pub fn parse_continue(&mut self) -> Result<Stmt, String> {
+    pub fn parse_continue(&mut self) -> Result<Stmt, ParseError> {
         self.advance();
         if self.check(&TokenKind::Semicolon) {
             self.advance();
@@ -1,15 +1,16 @@
 //! Declaration statement parsing
 //!
-//! Handles parsing of function declarations and return statements.
+//! Handles parsing of function declarations, return and global statements.
 
 use super::super::precedence::Precedence;
 use super::StmtParser;
 use crate::ast::{FunctionParam, Stmt};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> StmtParser<'a> {
     /// Parse function declaration
-    pub fn parse_function(&mut self) -> Result<Stmt, String> {
+    pub fn parse_function(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'function'
 
         let name = if let TokenKind::Identifier(name) = &self.current().kind {
@@ -17,10 +18,10 @@ impl<'a> StmtParser<'a> {
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected function name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected function name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -47,16 +48,10 @@ impl<'a> StmtParser<'a> {
                     None
                 };
 
-                let by_ref = if let TokenKind::Identifier(s) = &self.current().kind {
-                    if s == "&" {
-                        self.advance();
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                };
+                let by_ref = self.check(&TokenKind::BitwiseAnd);
+                if by_ref {
+                    self.advance();
+                }
 
                 // Check for variadic: ...
                 let is_variadic = if self.check(&TokenKind::Ellipsis) {
@@ -71,19 +66,19 @@ impl<'a> StmtParser<'a> {
                     self.advance();
                     name
                 } else {
-                    return Err(format!(
-                        "Expected parameter name at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected parameter name",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 };
 
                 let default = if self.check(&TokenKind::Assign) {
                     if is_variadic {
-                        return Err(format!(
-                            "Variadic parameter cannot have a default value at line {}, column {}",
+                        return Err(ParseError::invalid(
+                            "Variadic parameter cannot have a default value",
                             self.current().line,
-                            self.current().column
+                            self.current().column,
                         ));
                     }
                     self.advance();
@@ -94,10 +89,10 @@ impl<'a> StmtParser<'a> {
 
                 // Variadic must be the last parameter
                 if found_variadic {
-                    return Err(format!(
-                        "Only the last parameter can be variadic at line {}, column {}",
+                    return Err(ParseError::invalid(
+                        "Only the last parameter can be variadic",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
                 if is_variadic {
@@ -136,9 +131,7 @@ impl<'a> StmtParser<'a> {
 
         let mut body = Vec::new();
         while !self.check(&TokenKind::RightBrace) && !self.check(&TokenKind::Eof) {
-            if let Some(stmt) = self.parse_statement()? {
-                body.push(stmt);
-            }
+            self.parse_statement_into(&mut body)?;
         }
 
         self.consume(TokenKind::RightBrace, "Expected '}' after function body")?;
@@ -153,7 +146,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse return statement
-    pub fn parse_return(&mut self) -> Result<Stmt, String> {
+    pub fn parse_return(&mut self) -> Result<Stmt, ParseError> {
         self.advance();
 
         let value = if self.check(&TokenKind::Semicolon)
@@ -171,4 +164,29 @@ impl<'a> StmtParser<'a> {
 
         Ok(Stmt::Return(value))
     }
+
+    /// Parse `global $a, $b;`
+    pub fn parse_global(&mut self) -> Result<Stmt, ParseError> {
+        self.advance(); // consume 'global'
+
+        let mut names = Vec::new();
+        loop {
+            let TokenKind::Variable(name) = &self.current().kind else {
+                return Err(ParseError::expected(
+                    "Expected variable after 'global'",
+                    self.current().line,
+                    self.current().column,
+                ));
+            };
+            names.push(name.clone());
+            self.advance();
+            if !self.check(&TokenKind::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        self.consume_statement_end("Expected ';' after global statement")?;
+        Ok(Stmt::Global(names))
+    }
 }
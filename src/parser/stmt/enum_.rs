@@ -1,18 +1,19 @@
 //! Enum definition parsing
 //!
 //! Handles parsing of enum declarations including:
-//! - Enum declaration with backing type
+//! - Enum declaration with backing type and implemented interfaces
 //! - Enum cases (pure and backed)
-//! - Enum methods
+//! - Enum constants and methods
 
 use super::super::precedence::Precedence;
 use super::StmtParser;
-use crate::ast::{EnumBackingType, EnumCase, FunctionParam, Method, Stmt, Visibility};
+use crate::ast::{EnumBackingType, EnumCase, Stmt};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> StmtParser<'a> {
-    /// Parse enum declaration: enum Name: type { case Value; case Value = expr; ... }
-    pub fn parse_enum(&mut self) -> Result<Stmt, String> {
+    /// Parse enum declaration: enum Name: type implements I { case Value; case Value = expr; ... }
+    pub fn parse_enum(&mut self) -> Result<Stmt, ParseError> {
         self.consume(TokenKind::Enum, "Expected 'enum' keyword")?;
 
         // Parse enum name
@@ -21,10 +22,10 @@ impl<'a> StmtParser<'a> {
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected enum name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected enum name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -38,33 +39,42 @@ impl<'a> StmtParser<'a> {
                     "int" => EnumBackingType::Int,
                     "string" => EnumBackingType::String,
                     _ => {
-                        return Err(format!(
-                            "Invalid enum backing type '{}'. Only 'int' and 'string' are supported at line {}, column {}",
-                            type_name,
-                            self.current().line,
-                            self.current().column
-                        ));
+                        return Err(ParseError::invalid(format!("Invalid enum backing type '{}'. Only 'int' and 'string' are supported", type_name), self.current().line, self.current().column));
                     }
                 };
                 self.advance(); // consume type name
                 backing
             } else {
-                return Err(format!(
-                    "Expected backing type (int or string) at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected backing type (int or string)",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         } else {
             EnumBackingType::None
         };
 
+        let mut interfaces = Vec::new();
+        if self.check(&TokenKind::Implements) {
+            self.advance();
+            loop {
+                interfaces.push(self.parse_qualified_name()?);
+
+                if !self.check(&TokenKind::Comma) {
+                    break;
+                }
+                self.advance();
+            }
+        }
+
         self.consume(TokenKind::LeftBrace, "Expected '{' after enum name")?;
 
         let mut cases = Vec::new();
+        let mut constants = Vec::new();
         let mut methods = Vec::new();
 
-        // Parse cases and methods
+        // Parse cases, constants and methods
         while !self.check(&TokenKind::RightBrace) && !self.check(&TokenKind::Eof) {
             // Check for case or method
             if self.check(&TokenKind::Case) {
@@ -76,30 +86,30 @@ impl<'a> StmtParser<'a> {
                     self.advance();
                     name
                 } else {
-                    return Err(format!(
-                        "Expected case name at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected case name",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 };
 
                 // Check for value assignment (backed enums)
                 let value = if self.check(&TokenKind::Assign) {
                     if backing_type == EnumBackingType::None {
-                        return Err(format!(
-                            "Pure enum cannot have case values at line {}, column {}",
+                        return Err(ParseError::invalid(
+                            "Pure enum cannot have case values",
                             self.current().line,
-                            self.current().column
+                            self.current().column,
                         ));
                     }
                     self.advance(); // consume '='
                     Some(self.parse_expression(Precedence::None)?)
                 } else {
                     if backing_type != EnumBackingType::None {
-                        return Err(format!(
-                            "Backed enum must have case values at line {}, column {}",
+                        return Err(ParseError::invalid(
+                            "Backed enum must have case values",
                             self.current().line,
-                            self.current().column
+                            self.current().column,
                         ));
                     }
                     None
@@ -111,114 +121,57 @@ impl<'a> StmtParser<'a> {
                     name: case_name,
                     value,
                 });
-            } else if self.check(&TokenKind::Public)
-                || self.check(&TokenKind::Private)
-                || self.check(&TokenKind::Protected)
-            {
-                // Parse method (enums can have methods)
-                let visibility = match &self.current().kind {
-                    TokenKind::Public => Visibility::Public,
-                    TokenKind::Private => Visibility::Private,
-                    TokenKind::Protected => Visibility::Protected,
-                    _ => unreachable!(),
+            } else {
+                // Constants and methods, with their modifiers
+                let attributes = self.parse_attributes()?;
+                let visibility = self.parse_visibility();
+                if self.check(&TokenKind::Const) {
+                    self.advance(); // consume 'const'
+                    constants.push(self.parse_class_constant(attributes)?);
+                    continue;
+                }
+                let is_final = if self.check(&TokenKind::Final) {
+                    self.advance();
+                    true
+                } else {
+                    false
                 };
-                self.advance();
-
-                self.consume(
-                    TokenKind::Function,
-                    "Expected 'function' after visibility modifier in enum",
-                )?;
-
-                let method_name = if let TokenKind::Identifier(id) = &self.current().kind {
-                    let name = id.clone();
+                let is_static = if self.check(&TokenKind::Static) {
                     self.advance();
-                    name
+                    true
                 } else {
-                    return Err(format!(
-                        "Expected method name at line {}, column {}",
+                    false
+                };
+                if !self.check(&TokenKind::Function) {
+                    return Err(ParseError::expected(
+                        "Expected 'case', constant or method declaration in enum",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
-                };
-
-                self.consume(TokenKind::LeftParen, "Expected '(' after method name")?;
-
-                let mut params = Vec::new();
-                if !self.check(&TokenKind::RightParen) {
-                    loop {
-                        // Parse parameter
-                        let param_name = if let TokenKind::Variable(var) = &self.current().kind {
-                            let name = var.clone();
-                            self.advance();
-                            name
-                        } else {
-                            return Err(format!(
-                                "Expected parameter name at line {}, column {}",
-                                self.current().line,
-                                self.current().column
-                            ));
-                        };
-
-                        let default = if self.check(&TokenKind::Assign) {
-                            self.advance();
-                            Some(self.parse_expression(Precedence::None)?)
-                        } else {
-                            None
-                        };
-
-                        params.push(FunctionParam {
-                            name: param_name,
-                            type_hint: None,
-                            default,
-                            by_ref: false,
-                            is_variadic: false,
-                            visibility: None,
-                            readonly: false,
-                            attributes: Vec::new(),
-                        });
-
-                        if !self.check(&TokenKind::Comma) {
-                            break;
-                        }
-                        self.advance(); // consume ','
-                    }
                 }
-
-                self.consume(TokenKind::RightParen, "Expected ')' after parameters")?;
-                self.consume(TokenKind::LeftBrace, "Expected '{' before method body")?;
-
-                let body = self.parse_block()?;
-
-                methods.push(Method {
-                    name: method_name,
-                    visibility,
-                    is_static: false,
-                    is_abstract: false,
-                    is_final: false,
-                    params,
-                    return_type: None,
-                    body,
-                    attributes: Vec::new(),
-                });
-            } else {
-                return Err(format!(
-                    "Expected 'case' or method declaration in enum at line {}, column {}",
-                    self.current().line,
-                    self.current().column
-                ));
+                let mut method = self.parse_method(visibility, false, is_final)?;
+                method.is_static = is_static;
+                method.attributes = attributes;
+                methods.push(method);
             }
         }
 
         self.consume(TokenKind::RightBrace, "Expected '}' after enum body")?;
 
         if cases.is_empty() {
-            return Err(format!("Enum '{}' must have at least one case", name));
+            return Err(ParseError::invalid(
+                format!("Enum '{}' must have at least one case", name),
+                self.current().line,
+                self.current().column,
+            ));
         }
 
         Ok(Stmt::Enum {
             name,
             backing_type,
+            interfaces,
             cases,
+            constants,
             methods,
             attributes: Vec::new(),
         })
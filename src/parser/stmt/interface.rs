@@ -8,11 +8,12 @@
 use super::super::precedence::Precedence;
 use super::StmtParser;
 use crate::ast::{FunctionParam, InterfaceConstant, InterfaceMethodSignature, Stmt};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> StmtParser<'a> {
     /// Parse interface declaration
-    pub fn parse_interface(&mut self) -> Result<Stmt, String> {
+    pub fn parse_interface(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'interface'
 
         let name = if let TokenKind::Identifier(name) = &self.current().kind {
@@ -20,10 +21,10 @@ impl<'a> StmtParser<'a> {
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected interface name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected interface name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -68,10 +69,10 @@ impl<'a> StmtParser<'a> {
                     self.advance();
                     name
                 } else {
-                    return Err(format!(
-                        "Expected constant name at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected constant name",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 };
 
@@ -92,10 +93,10 @@ impl<'a> StmtParser<'a> {
                 method.attributes = attributes;
                 methods.push(method);
             } else {
-                return Err(format!(
-                    "Expected method or constant in interface at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected method or constant in interface",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         }
@@ -112,7 +113,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse interface method signature (no body)
-    fn parse_interface_method(&mut self) -> Result<InterfaceMethodSignature, String> {
+    fn parse_interface_method(&mut self) -> Result<InterfaceMethodSignature, ParseError> {
         self.advance(); // consume 'function'
 
         let name = if let TokenKind::Identifier(name) = &self.current().kind {
@@ -120,10 +121,10 @@ impl<'a> StmtParser<'a> {
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected method name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected method name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -152,10 +153,10 @@ impl<'a> StmtParser<'a> {
                     self.advance();
                     name
                 } else {
-                    return Err(format!(
-                        "Expected parameter name at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected parameter name",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 };
 
@@ -206,7 +207,7 @@ impl<'a> StmtParser<'a> {
 
     /// Parse interface constant (attributes are already parsed by caller)
     #[allow(dead_code)]
-    fn parse_interface_constant(&mut self) -> Result<InterfaceConstant, String> {
+    fn parse_interface_constant(&mut self) -> Result<InterfaceConstant, ParseError> {
         // consume 'const'
         self.advance();
 
@@ -215,10 +216,10 @@ impl<'a> StmtParser<'a> {
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected constant name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected constant name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
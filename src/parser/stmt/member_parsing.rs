@@ -1,9 +1,14 @@
 //! Property and method parsing for classes and traits
 //!
-//! Handles parsing of class/trait properties and methods.
+//! Handles parsing of class/trait properties and methods, and of class
+//! and enum constants.
 
 use super::StmtParser;
-use crate::ast::{Method, Property, PropertyHook, PropertyHookBody, PropertyHookType, Visibility};
+use crate::ast::{
+    Attribute, InterfaceConstant, Method, Property, PropertyHook, PropertyHookBody,
+    PropertyHookType, Visibility,
+};
+use crate::parser::ParseError;
 
 impl<'a> StmtParser<'a> {
     /// Parse visibility modifier (public, private, protected)
@@ -26,16 +31,16 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse class property (shared between class and trait)
-    pub fn parse_property(&mut self, visibility: Visibility) -> Result<Property, String> {
+    pub fn parse_property(&mut self, visibility: Visibility) -> Result<Property, ParseError> {
         let name = if let crate::token::TokenKind::Variable(name) = &self.current().kind {
             let name = name.clone();
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected property name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected property name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -45,6 +50,7 @@ impl<'a> StmtParser<'a> {
                 name,
                 visibility,
                 write_visibility: None,
+                type_hint: None,
                 default: None,
                 readonly: false,
                 is_static: false,
@@ -68,6 +74,7 @@ impl<'a> StmtParser<'a> {
             name,
             visibility,
             write_visibility: None,
+            type_hint: None,
             default,
             readonly: false,
             is_static: false,
@@ -77,12 +84,12 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse property hooks (PHP 8.4)
-    fn parse_property_hooks(&mut self) -> Result<Vec<PropertyHook>, String> {
+    fn parse_property_hooks(&mut self) -> Result<Vec<PropertyHook>, ParseError> {
         if !self.check(&crate::token::TokenKind::LeftBrace) {
-            return Err(format!(
-                "Expected '{{' at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected '{'",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         }
         self.advance();
@@ -99,9 +106,10 @@ impl<'a> StmtParser<'a> {
                 self.advance();
                 PropertyHookType::Set
             } else {
-                return Err(format!(
-                    "Expected 'get' or 'set' in property hook at line {}",
-                    self.current().line
+                return Err(ParseError::expected(
+                    "Expected 'get' or 'set' in property hook",
+                    self.current().line,
+                    0,
                 ));
             };
 
@@ -109,9 +117,10 @@ impl<'a> StmtParser<'a> {
                 self.advance();
                 let expr = self.parse_expression(super::super::precedence::Precedence::None)?;
                 if !self.check(&crate::token::TokenKind::Semicolon) {
-                    return Err(format!(
-                        "Expected ';' after property hook expression at line {}",
-                        self.current().line
+                    return Err(ParseError::expected(
+                        "Expected ';' after property hook expression",
+                        self.current().line,
+                        0,
                     ));
                 }
                 self.advance();
@@ -123,23 +132,23 @@ impl<'a> StmtParser<'a> {
                 while !self.check(&crate::token::TokenKind::RightBrace)
                     && !self.check(&crate::token::TokenKind::Eof)
                 {
-                    if let Some(stmt) = self.parse_statement()? {
-                        statements.push(stmt);
-                    }
+                    self.parse_statement_into(&mut statements)?;
                 }
 
                 if !self.check(&crate::token::TokenKind::RightBrace) {
-                    return Err(format!(
-                        "Expected '}}' after property hook block at line {}",
-                        self.current().line
+                    return Err(ParseError::expected(
+                        "Expected '}' after property hook block",
+                        self.current().line,
+                        0,
                     ));
                 }
                 self.advance();
                 PropertyHookBody::Block(statements)
             } else {
-                return Err(format!(
-                    "Expected '=>' or '{{' after hook type at line {}",
-                    self.current().line
+                return Err(ParseError::expected(
+                    "Expected '=>' or '{' after hook type",
+                    self.current().line,
+                    0,
                 ));
             };
 
@@ -147,16 +156,20 @@ impl<'a> StmtParser<'a> {
         }
 
         if !self.check(&crate::token::TokenKind::RightBrace) {
-            return Err(format!(
-                "Expected '}}' at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected '}'",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         }
         self.advance();
 
         if hooks.is_empty() {
-            return Err("Property hooks cannot be empty".to_string());
+            return Err(ParseError::invalid(
+                "Property hooks cannot be empty",
+                self.current().line,
+                self.current().column,
+            ));
         }
 
         let get_count = hooks
@@ -169,10 +182,18 @@ impl<'a> StmtParser<'a> {
             .count();
 
         if get_count > 1 {
-            return Err("Duplicate 'get' hook in property".to_string());
+            return Err(ParseError::invalid(
+                "Duplicate 'get' hook in property",
+                self.current().line,
+                self.current().column,
+            ));
         }
         if set_count > 1 {
-            return Err("Duplicate 'set' hook in property".to_string());
+            return Err(ParseError::invalid(
+                "Duplicate 'set' hook in property",
+                self.current().line,
+                self.current().column,
+            ));
         }
 
         Ok(hooks)
@@ -184,7 +205,7 @@ impl<'a> StmtParser<'a> {
         visibility: Visibility,
         is_abstract_method: bool,
         is_final_method: bool,
-    ) -> Result<Method, String> {
+    ) -> Result<Method, ParseError> {
         self.advance();
 
         let name = if let crate::token::TokenKind::Identifier(name) = &self.current().kind {
@@ -192,10 +213,10 @@ impl<'a> StmtParser<'a> {
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected method name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected method name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -233,10 +254,10 @@ impl<'a> StmtParser<'a> {
                         crate::token::TokenKind::Public
                         | crate::token::TokenKind::Protected
                         | crate::token::TokenKind::Private => {
-                            return Err(format!(
-                                "Constructor property promotion can only be used in __construct at line {}, column {}",
+                            return Err(ParseError::invalid(
+                                "Constructor property promotion can only be used in __construct",
                                 self.current().line,
-                                self.current().column
+                                self.current().column,
                             ));
                         }
                         _ => None,
@@ -265,16 +286,10 @@ impl<'a> StmtParser<'a> {
                     None
                 };
 
-                let by_ref = if let crate::token::TokenKind::Identifier(s) = &self.current().kind {
-                    if s == "&" {
-                        self.advance();
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                };
+                let by_ref = self.check(&crate::token::TokenKind::BitwiseAnd);
+                if by_ref {
+                    self.advance();
+                }
 
                 let is_variadic = if self.check(&crate::token::TokenKind::Ellipsis) {
                     self.advance();
@@ -289,19 +304,19 @@ impl<'a> StmtParser<'a> {
                         self.advance();
                         name
                     } else {
-                        return Err(format!(
-                            "Expected parameter name at line {}, column {}",
+                        return Err(ParseError::expected(
+                            "Expected parameter name",
                             self.current().line,
-                            self.current().column
+                            self.current().column,
                         ));
                     };
 
                 let default = if self.check(&crate::token::TokenKind::Assign) {
                     if is_variadic {
-                        return Err(format!(
-                            "Variadic parameter cannot have a default value at line {}, column {}",
+                        return Err(ParseError::invalid(
+                            "Variadic parameter cannot have a default value",
                             self.current().line,
-                            self.current().column
+                            self.current().column,
                         ));
                     }
                     self.advance();
@@ -311,10 +326,10 @@ impl<'a> StmtParser<'a> {
                 };
 
                 if found_variadic {
-                    return Err(format!(
-                        "Only the last parameter can be variadic at line {}, column {}",
+                    return Err(ParseError::invalid(
+                        "Only the last parameter can be variadic",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
                 if is_variadic {
@@ -367,9 +382,7 @@ impl<'a> StmtParser<'a> {
             while !self.check(&crate::token::TokenKind::RightBrace)
                 && !self.check(&crate::token::TokenKind::Eof)
             {
-                if let Some(stmt) = self.parse_statement()? {
-                    body.push(stmt);
-                }
+                self.parse_statement_into(&mut body)?;
             }
 
             self.consume(
@@ -391,4 +404,39 @@ impl<'a> StmtParser<'a> {
             attributes: Vec::new(),
         })
     }
+
+    /// Parse the rest of a class or enum constant, after `const`:
+    /// `NAME = expr;`
+    pub(super) fn parse_class_constant(
+        &mut self,
+        attributes: Vec<Attribute>,
+    ) -> Result<InterfaceConstant, ParseError> {
+        let name = if let crate::token::TokenKind::Identifier(name) = &self.current().kind {
+            let name = name.clone();
+            self.advance();
+            name
+        } else {
+            return Err(ParseError::expected(
+                "Expected constant name",
+                self.current().line,
+                self.current().column,
+            ));
+        };
+
+        self.consume(
+            crate::token::TokenKind::Assign,
+            "Expected '=' after constant name",
+        )?;
+        let value = self.parse_expression(super::super::precedence::Precedence::None)?;
+        self.consume(
+            crate::token::TokenKind::Semicolon,
+            "Expected ';' after constant value",
+        )?;
+
+        Ok(InterfaceConstant {
+            name,
+            value,
+            attributes,
+        })
+    }
 }
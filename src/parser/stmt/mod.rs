@@ -23,6 +23,7 @@ pub mod type_parsing;
 use super::expr::ExprParser;
 use super::precedence::Precedence;
 use crate::ast::{Expr, Stmt};
+use crate::parser::ParseError;
 use crate::token::{Token, TokenKind};
 
 pub struct StmtParser<'a> {
@@ -55,35 +56,30 @@ impl<'a> StmtParser<'a> {
         std::mem::discriminant(&self.current().kind) == std::mem::discriminant(kind)
     }
 
-    pub fn consume(&mut self, kind: TokenKind, msg: &str) -> Result<Token, String> {
+    pub fn consume(&mut self, kind: TokenKind, msg: &str) -> Result<Token, ParseError> {
         if self.check(&kind) {
             Ok(self.advance())
         } else {
-            Err(format!(
-                "{} at line {}, column {} (found {:?})",
-                msg,
-                self.current().line,
-                self.current().column,
-                self.current().kind
-            ))
+            Err(
+                ParseError::expected(msg, self.current().line, self.current().column)
+                    .found(&self.current().kind),
+            )
         }
     }
 
-    pub fn parse_expression(&mut self, min_prec: Precedence) -> Result<Expr, String> {
+    pub fn parse_expression(&mut self, min_prec: Precedence) -> Result<Expr, ParseError> {
         let mut expr_parser = ExprParser::new(self.tokens, self.pos);
         expr_parser.parse_expression(min_prec)
     }
 
     /// Parse a block of statements enclosed in braces or a single statement
-    pub fn parse_block(&mut self) -> Result<Vec<Stmt>, String> {
+    pub fn parse_block(&mut self) -> Result<Vec<Stmt>, ParseError> {
         if self.check(&TokenKind::LeftBrace) {
             self.advance(); // consume '{'
             let mut statements = Vec::new();
 
             while !self.check(&TokenKind::RightBrace) && !self.check(&TokenKind::Eof) {
-                if let Some(stmt) = self.parse_statement()? {
-                    statements.push(stmt);
-                }
+                self.parse_statement_into(&mut statements)?;
             }
 
             self.consume(TokenKind::RightBrace, "Expected '}' after block")?;
@@ -107,42 +103,43 @@ impl<'a> StmtParser<'a> {
                     _ => {}
                 }
 
-                if let Some(stmt) = self.parse_statement()? {
-                    statements.push(stmt);
-                }
+                self.parse_statement_into(&mut statements)?;
             }
 
             Ok(statements)
         } else {
             // Single statement
             let mut statements = Vec::new();
-            if let Some(stmt) = self.parse_statement()? {
-                statements.push(stmt);
-            }
+            self.parse_statement_into(&mut statements)?;
             Ok(statements)
         }
     }
 
     /// Parse expression statement
-    pub fn parse_expression_statement(&mut self) -> Result<Stmt, String> {
+    pub fn parse_expression_statement(&mut self) -> Result<Stmt, ParseError> {
         let expr = self.parse_expression(Precedence::None)?;
+        self.consume_statement_end("Expected ';' after expression")?;
+        Ok(Stmt::Expression(expr))
+    }
 
+    /// End a statement with ';', which a closing tag or the end of the
+    /// file may stand in for, as in `<?php endif ?>`
+    pub fn consume_statement_end(&mut self, message: &str) -> Result<(), ParseError> {
         if self.check(&TokenKind::Semicolon) {
             self.advance();
         } else if !self.check(&TokenKind::CloseTag) && !self.check(&TokenKind::Eof) {
-            return Err(format!(
-                "Expected ';' after expression at line {}, column {}",
+            return Err(ParseError::expected(
+                message,
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         }
-
-        Ok(Stmt::Expression(expr))
+        Ok(())
     }
 
     /// Parse try/catch/finally statement
     /// try { ... } catch (ExceptionType $e) { ... } finally { ... }
-    pub fn parse_try(&mut self) -> Result<Stmt, String> {
+    pub fn parse_try(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'try'
 
         // Parse try block
@@ -163,10 +160,10 @@ impl<'a> StmtParser<'a> {
                     exception_types.push(name.clone());
                     self.advance();
                 } else {
-                    return Err(format!(
-                        "Expected exception type at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected exception type",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
 
@@ -184,10 +181,10 @@ impl<'a> StmtParser<'a> {
                 self.advance();
                 name
             } else {
-                return Err(format!(
-                    "Expected exception variable at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected exception variable",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             };
 
@@ -209,10 +206,10 @@ impl<'a> StmtParser<'a> {
 
         // Must have at least one catch or finally
         if catch_clauses.is_empty() && finally_body.is_none() {
-            return Err(format!(
-                "Try must have at least one catch or finally block at line {}, column {}",
+            return Err(ParseError::invalid(
+                "Try must have at least one catch or finally block",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         }
 
@@ -223,8 +220,19 @@ impl<'a> StmtParser<'a> {
         })
     }
 
+    /// Parse a statement and append it to `statements`, preceded by a
+    /// `Stmt::Line` marker recording the line it starts on
+    pub fn parse_statement_into(&mut self, statements: &mut Vec<Stmt>) -> Result<(), ParseError> {
+        let line = self.current().line;
+        if let Some(stmt) = self.parse_statement()? {
+            statements.push(Stmt::Line(line));
+            statements.push(stmt);
+        }
+        Ok(())
+    }
+
     /// Main statement dispatcher
-    pub fn parse_statement(&mut self) -> Result<Option<Stmt>, String> {
+    pub fn parse_statement(&mut self) -> Result<Option<Stmt>, ParseError> {
         // Parse any attributes that may precede declarations
         let attributes = self.parse_attributes()?;
 
@@ -394,24 +402,37 @@ impl<'a> StmtParser<'a> {
                 Ok(Some(Stmt::Html(html)))
             }
             TokenKind::Eof => Ok(None),
+            TokenKind::Identifier(ref word)
+                if word.eq_ignore_ascii_case("global")
+                    && matches!(
+                        self.tokens.get(*self.pos + 1).map(|t| &t.kind),
+                        Some(TokenKind::Variable(_))
+                    ) =>
+            {
+                Ok(Some(self.parse_global()?))
+            }
             TokenKind::Variable(_)
             | TokenKind::Integer(_)
             | TokenKind::Float(_)
             | TokenKind::String(_)
-            | TokenKind::Heredoc(_)
+            | TokenKind::InterpolatedString(_)
             | TokenKind::True
             | TokenKind::False
             | TokenKind::Null
             | TokenKind::LeftParen
+            | TokenKind::LeftBracket
             | TokenKind::Minus
             | TokenKind::Not
+            | TokenKind::At
             | TokenKind::Increment
             | TokenKind::Decrement
             | TokenKind::Identifier(_)
+            | TokenKind::Fiber
             | TokenKind::New => Ok(Some(self.parse_expression_statement()?)),
-            _ => Err(format!(
-                "Unexpected token {:?} at line {}, column {}",
-                token.kind, token.line, token.column
+            _ => Err(ParseError::unexpected(
+                format!("Unexpected token {:?}", token.kind),
+                token.line,
+                token.column,
             )),
         }
     }
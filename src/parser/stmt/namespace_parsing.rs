@@ -5,12 +5,13 @@
 
 use super::StmtParser;
 use crate::ast::{DeclareDirective, NamespaceBody, Stmt, UseItem, UseType};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> StmtParser<'a> {
     /// Parse namespace declaration
     /// namespace Foo\Bar { ... } or namespace Foo\Bar; or namespace { ... }
-    pub fn parse_namespace(&mut self) -> Result<Stmt, String> {
+    pub fn parse_namespace(&mut self) -> Result<Stmt, ParseError> {
         self.advance();
 
         let name = if self.check(&TokenKind::LeftBrace) || self.check(&TokenKind::Semicolon) {
@@ -23,9 +24,7 @@ impl<'a> StmtParser<'a> {
             self.advance();
             let mut stmts = Vec::new();
             while !self.check(&TokenKind::RightBrace) && !self.check(&TokenKind::Eof) {
-                if let Some(stmt) = self.parse_statement()? {
-                    stmts.push(stmt);
-                }
+                self.parse_statement_into(&mut stmts)?;
             }
             self.consume(TokenKind::RightBrace, "Expected '}' after namespace body")?;
             NamespaceBody::Braced(stmts)
@@ -41,7 +40,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse qualified name (e.g., Foo\Bar\Baz or \Foo\Bar\Baz)
-    pub fn parse_qualified_name(&mut self) -> Result<crate::ast::QualifiedName, String> {
+    pub fn parse_qualified_name(&mut self) -> Result<crate::ast::QualifiedName, ParseError> {
         use crate::ast::QualifiedName;
 
         let is_fully_qualified = if self.check(&TokenKind::Backslash) {
@@ -57,10 +56,10 @@ impl<'a> StmtParser<'a> {
             parts.push(name.clone());
             self.advance();
         } else {
-            return Err(format!(
-                "Expected identifier in qualified name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected identifier in qualified name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         }
 
@@ -77,10 +76,10 @@ impl<'a> StmtParser<'a> {
                 parts.push(name.clone());
                 self.advance();
             } else {
-                return Err(format!(
-                    "Expected identifier after '\\' at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected identifier after '\\'",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         }
@@ -90,7 +89,7 @@ impl<'a> StmtParser<'a> {
 
     /// Parse declare statement
     /// declare(strict_types=1); or declare(strict_types=1) { ... }
-    pub fn parse_declare(&mut self) -> Result<Stmt, String> {
+    pub fn parse_declare(&mut self) -> Result<Stmt, ParseError> {
         self.advance();
         self.consume(TokenKind::LeftParen, "Expected '(' after 'declare'")?;
 
@@ -102,10 +101,10 @@ impl<'a> StmtParser<'a> {
                 self.advance();
                 name
             } else {
-                return Err(format!(
-                    "Expected directive name at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected directive name",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             };
 
@@ -119,10 +118,10 @@ impl<'a> StmtParser<'a> {
                         crate::ast::Expr::Integer(0) => DeclareDirective::StrictTypes(false),
                         crate::ast::Expr::Integer(1) => DeclareDirective::StrictTypes(true),
                         _ => {
-                            return Err(format!(
-                                "strict_types value must be 0 or 1 at line {}, column {}",
+                            return Err(ParseError::invalid(
+                                "strict_types value must be 0 or 1",
                                 self.current().line,
-                                self.current().column
+                                self.current().column,
                             ))
                         }
                     }
@@ -133,10 +132,10 @@ impl<'a> StmtParser<'a> {
                     match value {
                         crate::ast::Expr::String(s) => DeclareDirective::Encoding(s),
                         _ => {
-                            return Err(format!(
-                                "encoding value must be a string at line {}, column {}",
+                            return Err(ParseError::invalid(
+                                "encoding value must be a string",
                                 self.current().line,
-                                self.current().column
+                                self.current().column,
                             ))
                         }
                     }
@@ -147,20 +146,19 @@ impl<'a> StmtParser<'a> {
                     match value {
                         crate::ast::Expr::Integer(n) => DeclareDirective::Ticks(n),
                         _ => {
-                            return Err(format!(
-                                "ticks value must be an integer at line {}, column {}",
+                            return Err(ParseError::invalid(
+                                "ticks value must be an integer",
                                 self.current().line,
-                                self.current().column
+                                self.current().column,
                             ))
                         }
                     }
                 }
                 _ => {
-                    return Err(format!(
-                        "Unknown declare directive: {} at line {}, column {}",
-                        name,
+                    return Err(ParseError::invalid(
+                        format!("Unknown declare directive: {}", name),
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ))
                 }
             };
@@ -182,9 +180,7 @@ impl<'a> StmtParser<'a> {
             self.advance();
             let mut stmts = Vec::new();
             while !self.check(&TokenKind::RightBrace) && !self.check(&TokenKind::Eof) {
-                if let Some(stmt) = self.parse_statement()? {
-                    stmts.push(stmt);
-                }
+                self.parse_statement_into(&mut stmts)?;
             }
             self.consume(TokenKind::RightBrace, "Expected '}' after declare block")?;
             Some(stmts)
@@ -199,13 +195,15 @@ impl<'a> StmtParser<'a> {
                     }
                 }
                 if self.check(&TokenKind::Eof) {
-                    return Err("Expected 'enddeclare' to close declare statement".to_string());
-                }
-                if let Some(stmt) = self.parse_statement()? {
-                    stmts.push(stmt);
+                    return Err(ParseError::expected(
+                        "Expected 'enddeclare' to close declare statement",
+                        self.current().line,
+                        self.current().column,
+                    ));
                 }
+                self.parse_statement_into(&mut stmts)?;
             }
-            self.consume(TokenKind::Semicolon, "Expected ';' after 'enddeclare'")?;
+            self.consume_statement_end("Expected ';' after 'enddeclare'")?;
             Some(stmts)
         } else {
             self.consume(TokenKind::Semicolon, "Expected ';' after declare")?;
@@ -218,7 +216,7 @@ impl<'a> StmtParser<'a> {
     /// Parse use statement
     /// use Foo\Bar; use Foo\Bar as Baz; use function Foo\bar; use const Foo\BAR;
     /// use Foo\{Bar, Baz}; (group use)
-    pub fn parse_use_statement(&mut self) -> Result<Stmt, String> {
+    pub fn parse_use_statement(&mut self) -> Result<Stmt, ParseError> {
         self.advance();
 
         let default_type = if self.check(&TokenKind::Function) {
@@ -236,10 +234,10 @@ impl<'a> StmtParser<'a> {
         if self.check(&TokenKind::Backslash) {
             self.advance();
             if !self.check(&TokenKind::LeftBrace) {
-                return Err(format!(
-                    "Expected '{{' after '\\' in use statement at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected '{' after '\\' in use statement",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         }
@@ -257,10 +255,10 @@ impl<'a> StmtParser<'a> {
                 self.advance();
                 Some(alias_name)
             } else {
-                return Err(format!(
-                    "Expected identifier after 'as' at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected identifier after 'as'",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         } else {
@@ -283,10 +281,10 @@ impl<'a> StmtParser<'a> {
                     self.advance();
                     Some(alias_name)
                 } else {
-                    return Err(format!(
-                        "Expected identifier after 'as' at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected identifier after 'as'",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
             } else {
@@ -308,7 +306,7 @@ impl<'a> StmtParser<'a> {
         &mut self,
         prefix: crate::ast::QualifiedName,
         default_type: crate::ast::UseType,
-    ) -> Result<Stmt, String> {
+    ) -> Result<Stmt, ParseError> {
         use crate::ast::{GroupUse, UseItem, UseType};
 
         self.advance();
@@ -334,10 +332,10 @@ impl<'a> StmtParser<'a> {
                     self.advance();
                     Some(alias_name)
                 } else {
-                    return Err(format!(
-                        "Expected identifier after 'as' at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected identifier after 'as'",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
             } else {
@@ -8,11 +8,12 @@
 
 use super::StmtParser;
 use crate::ast::{Stmt, TraitResolution, TraitUse};
+use crate::parser::ParseError;
 use crate::token::TokenKind;
 
 impl<'a> StmtParser<'a> {
     /// Parse trait declaration
-    pub fn parse_trait(&mut self) -> Result<Stmt, String> {
+    pub fn parse_trait(&mut self) -> Result<Stmt, ParseError> {
         self.advance(); // consume 'trait'
 
         let name = if let TokenKind::Identifier(name) = &self.current().kind {
@@ -20,10 +21,10 @@ impl<'a> StmtParser<'a> {
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected trait name at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected trait name",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -54,10 +55,10 @@ impl<'a> StmtParser<'a> {
                 prop.attributes = attributes;
                 properties.push(prop);
             } else {
-                return Err(format!(
-                    "Expected property or method in trait at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected property or method in trait",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
         }
@@ -74,7 +75,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse trait use statement
-    pub fn parse_trait_use(&mut self) -> Result<TraitUse, String> {
+    pub fn parse_trait_use(&mut self) -> Result<TraitUse, ParseError> {
         self.advance(); // consume 'use'
 
         let mut traits = Vec::new();
@@ -83,10 +84,10 @@ impl<'a> StmtParser<'a> {
                 traits.push(trait_name.clone());
                 self.advance();
             } else {
-                return Err(format!(
-                    "Expected trait name after 'use' at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected trait name after 'use'",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             }
 
@@ -122,16 +123,16 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse trait resolution (insteadof or as clause)
-    fn parse_trait_resolution(&mut self) -> Result<TraitResolution, String> {
+    fn parse_trait_resolution(&mut self) -> Result<TraitResolution, ParseError> {
         let first_id = if let TokenKind::Identifier(name) = &self.current().kind {
             let name = name.clone();
             self.advance();
             name
         } else {
-            return Err(format!(
-                "Expected name in resolution at line {}, column {}",
+            return Err(ParseError::expected(
+                "Expected name in resolution",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ));
         };
 
@@ -143,10 +144,10 @@ impl<'a> StmtParser<'a> {
                 self.advance();
                 name
             } else {
-                return Err(format!(
-                    "Expected method name after '::' at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected method name after '::'",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             };
             (Some(first_id), method_name)
@@ -164,10 +165,10 @@ impl<'a> StmtParser<'a> {
                     excluded_traits.push(excluded_trait.clone());
                     self.advance();
                 } else {
-                    return Err(format!(
-                        "Expected trait name after 'insteadof' at line {}, column {}",
+                    return Err(ParseError::expected(
+                        "Expected trait name after 'insteadof'",
                         self.current().line,
-                        self.current().column
+                        self.current().column,
                     ));
                 }
 
@@ -204,10 +205,10 @@ impl<'a> StmtParser<'a> {
                 self.advance();
                 name
             } else {
-                return Err(format!(
-                    "Expected alias name after 'as' at line {}, column {}",
+                return Err(ParseError::expected(
+                    "Expected alias name after 'as'",
                     self.current().line,
-                    self.current().column
+                    self.current().column,
                 ));
             };
 
@@ -223,10 +224,10 @@ impl<'a> StmtParser<'a> {
                 visibility,
             })
         } else {
-            Err(format!(
-                "Expected 'insteadof' or 'as' in trait resolution at line {}, column {}",
+            Err(ParseError::expected(
+                "Expected 'insteadof' or 'as' in trait resolution",
                 self.current().line,
-                self.current().column
+                self.current().column,
             ))
         }
     }
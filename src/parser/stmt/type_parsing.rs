@@ -9,11 +9,12 @@
 
 use super::StmtParser;
 use crate::ast::TypeHint;
+use crate::parser::ParseError;
 
 impl<'a> StmtParser<'a> {
     /// Parse a type hint
     /// Supports: int, string, ?int, int|string, array, callable, ClassName, Iterator&Countable, (A&B)|C
-    pub fn parse_type_hint(&mut self) -> Result<TypeHint, String> {
+    pub fn parse_type_hint(&mut self) -> Result<TypeHint, ParseError> {
         let nullable = if self.check(&crate::token::TokenKind::QuestionMark) {
             self.advance();
             true
@@ -26,39 +27,31 @@ impl<'a> StmtParser<'a> {
         if self.check(&crate::token::TokenKind::BitwiseOr) {
             let types_or_dnf = self.parse_union_or_dnf(base_type)?;
             if nullable {
-                return Err(
-                    "Cannot use nullable syntax with union types, use |null instead".to_string(),
-                );
+                return Err(ParseError::invalid(
+                    "Cannot use nullable syntax with union types, use |null instead",
+                    self.current().line,
+                    self.current().column,
+                ));
             }
             return Ok(types_or_dnf);
         }
 
-        if let crate::token::TokenKind::Identifier(next_id) = &self
-            .tokens
-            .get(*self.pos)
-            .map(|t| &t.kind)
-            .unwrap_or(&crate::token::TokenKind::Eof)
-        {
-            if next_id == "&" {
-                if let Some(after_amp) = self.tokens.get(*self.pos + 1) {
-                    if matches!(after_amp.kind, crate::token::TokenKind::Identifier(_)) {
-                        let mut types = vec![base_type.clone()];
-                        while let crate::token::TokenKind::Identifier(amp) = &self.current().kind {
-                            if amp == "&" {
-                                self.advance();
-                                types.push(self.parse_single_type()?);
-                            } else {
-                                break;
-                            }
-                        }
-                        if types.len() > 1 {
-                            if nullable {
-                                return Err("Cannot use nullable syntax with intersection types"
-                                    .to_string());
-                            }
-                            return Ok(TypeHint::Intersection(types));
-                        }
+        if self.check(&crate::token::TokenKind::BitwiseAnd) {
+            if let Some(after_amp) = self.tokens.get(*self.pos + 1) {
+                if matches!(after_amp.kind, crate::token::TokenKind::Identifier(_)) {
+                    let mut types = vec![base_type.clone()];
+                    while self.check(&crate::token::TokenKind::BitwiseAnd) {
+                        self.advance();
+                        types.push(self.parse_single_type()?);
                     }
+                    if nullable {
+                        return Err(ParseError::invalid(
+                            "Cannot use nullable syntax with intersection types",
+                            self.current().line,
+                            self.current().column,
+                        ));
+                    }
+                    return Ok(TypeHint::Intersection(types));
                 }
             }
         }
@@ -71,20 +64,16 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse a type component - either a single type or a parenthesized intersection
-    fn parse_type_component(&mut self) -> Result<TypeHint, String> {
+    fn parse_type_component(&mut self) -> Result<TypeHint, ParseError> {
         if self.check(&crate::token::TokenKind::LeftParen) {
             self.advance();
 
             let first_type = self.parse_single_type()?;
             let mut types = vec![first_type];
 
-            while let crate::token::TokenKind::Identifier(amp) = &self.current().kind {
-                if amp == "&" {
-                    self.advance();
-                    types.push(self.parse_single_type()?);
-                } else {
-                    break;
-                }
+            while self.check(&crate::token::TokenKind::BitwiseAnd) {
+                self.advance();
+                types.push(self.parse_single_type()?);
             }
 
             self.consume(
@@ -103,7 +92,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse union or DNF type after seeing the first component and |
-    fn parse_union_or_dnf(&mut self, first: TypeHint) -> Result<TypeHint, String> {
+    fn parse_union_or_dnf(&mut self, first: TypeHint) -> Result<TypeHint, ParseError> {
         let mut components = vec![first.clone()];
         let has_intersection = matches!(first, TypeHint::Intersection(_));
 
@@ -120,7 +109,7 @@ impl<'a> StmtParser<'a> {
         }
 
         if has_intersection {
-            let dnf_components: Result<Vec<Vec<TypeHint>>, String> = components
+            let dnf_components: Result<Vec<Vec<TypeHint>>, ParseError> = components
                 .into_iter()
                 .map(|comp| match comp {
                     TypeHint::Intersection(types) => Ok(types),
@@ -135,7 +124,7 @@ impl<'a> StmtParser<'a> {
     }
 
     /// Parse a single type (without union/intersection)
-    fn parse_single_type(&mut self) -> Result<TypeHint, String> {
+    fn parse_single_type(&mut self) -> Result<TypeHint, ParseError> {
         if let crate::token::TokenKind::Identifier(name) = &self.current().kind {
             let type_name = name.to_lowercase();
             let original_name = name.clone();
@@ -162,7 +151,12 @@ impl<'a> StmtParser<'a> {
                 _ => Ok(TypeHint::Class(original_name)),
             }
         } else {
-            Err(format!("Expected type name, got {:?}", self.current().kind))
+            Err(ParseError::expected(
+                "Expected type name",
+                self.current().line,
+                self.current().column,
+            )
+            .found(&self.current().kind))
         }
     }
 }
@@ -0,0 +1,177 @@
+//! AST-to-source pretty printer
+//!
+//! Turns a parsed program (or any statement or expression) back into PHP
+//! source that the parser accepts again. Output is laid out PSR-12 style:
+//! four-space indentation, braces on their own line for functions and
+//! classes, and parentheses only where the precedence of the tree needs
+//! them (plus wherever the source had them, kept as `Expr::Grouped`).
+//!
+//! Comments and original formatting are not part of the AST, so printing
+//! is a normalization rather than a reproduction of the input.
+//!
+//! ```
+//! use vhp::printer;
+//!
+//! let source = printer::round_trip("<?php echo 1+2*3;").unwrap();
+//! assert_eq!(source, "<?php\necho 1 + 2 * 3;\n");
+//! ```
+
+mod decl;
+mod expr;
+mod precedence;
+mod stmt;
+
+use crate::ast::fold::{self, Folder};
+use crate::ast::{Expr, Program, Stmt};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+/// Print a whole program, including its inline HTML
+pub fn print_program(program: &Program) -> String {
+    print_statements(&program.statements)
+}
+
+/// Print a sequence of top-level statements as a complete source file
+pub fn print_statements(statements: &[Stmt]) -> String {
+    let mut printer = Printer::new();
+    printer.block(statements);
+    printer.out
+}
+
+/// Print a single expression, without a trailing semicolon
+pub fn print_expr(expr: &Expr) -> String {
+    let mut printer = Printer::new();
+    printer.in_php = true;
+    printer.expr(expr);
+    printer.out
+}
+
+/// Quote a string as a single-quoted PHP literal
+pub fn quote_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for ch in value.chars() {
+        if ch == '\\' || ch == '\'' {
+            quoted.push('\\');
+        }
+        quoted.push(ch);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Format a float so that it reads back as the same float, never as an int
+pub fn float_literal(value: f64) -> String {
+    if value.is_nan() {
+        return "NAN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "INF" } else { "-INF" }.to_string();
+    }
+    let text = value.to_string();
+    if text.contains('.') {
+        text
+    } else {
+        format!("{}.0", text)
+    }
+}
+
+/// Parse `source`, print it, and check that the printed source parses to
+/// the same tree
+///
+/// Line markers and redundant parentheses are ignored in the comparison.
+/// Returns the printed source, or an error describing the first failing
+/// stage.
+pub fn round_trip(source: &str) -> Result<String, String> {
+    let original = parse(source)?;
+    let printed = print_program(&original);
+    let reparsed = parse(&printed).map_err(|e| format!("Printed source does not parse: {}", e))?;
+
+    let expected = format!("{:?}", normalize(original.statements));
+    let actual = format!("{:?}", normalize(reparsed.statements));
+    if expected != actual {
+        return Err(format!(
+            "Printed source parses to a different tree:\n{}",
+            printed
+        ));
+    }
+    Ok(printed)
+}
+
+fn parse(source: &str) -> Result<Program, String> {
+    let tokens = Lexer::new(source).tokenize()?;
+    Ok(Parser::new(tokens).parse()?)
+}
+
+fn normalize(statements: Vec<Stmt>) -> Vec<Stmt> {
+    fold::walk_block(&mut Normalize, statements)
+}
+
+/// Drops the parts of a tree that depend on layout rather than meaning
+struct Normalize;
+
+impl Folder for Normalize {
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::Line(_) => Stmt::Line(0),
+            other => fold::walk_stmt(self, other),
+        }
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match fold::walk_expr(self, expr) {
+            Expr::Grouped(inner) => *inner,
+            Expr::MagicLine(_) => Expr::MagicLine(0),
+            other => other,
+        }
+    }
+}
+
+/// Source writer shared by the statement, declaration and expression
+/// printers
+struct Printer {
+    out: String,
+    indent: usize,
+    /// Whether the output is currently inside `<?php ... ?>`
+    in_php: bool,
+}
+
+impl Printer {
+    fn new() -> Self {
+        Self {
+            out: String::new(),
+            indent: 0,
+            in_php: false,
+        }
+    }
+
+    /// Start a new indented line of PHP, opening a PHP tag first if the
+    /// output is in inline HTML
+    fn line(&mut self) {
+        if !self.in_php {
+            self.out.push_str("<?php\n");
+            self.in_php = true;
+        }
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.out.push('\n');
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    /// Print `items` separated by `", "`
+    fn list<T>(&mut self, items: &[T], mut each: impl FnMut(&mut Self, &T)) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.push(", ");
+            }
+            each(self, item);
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! Declaration printing: functions, classes, interfaces, traits and enums
+
+use super::stmt::qualified_name;
+use super::Printer;
+use crate::ast::{
+    Attribute, EnumBackingType, FunctionParam, InterfaceConstant, Method, Property,
+    PropertyHookBody, PropertyHookType, Stmt, TraitResolution, TraitUse, TypeHint, Visibility,
+};
+
+impl Printer {
+    pub(super) fn function(
+        &mut self,
+        name: &str,
+        params: &[FunctionParam],
+        return_type: Option<&TypeHint>,
+        body: &[Stmt],
+        attributes: &[Attribute],
+    ) {
+        self.attributes(attributes);
+        self.line();
+        self.push(&format!("function {}", name));
+        self.signature(params, return_type);
+        self.end_line();
+        self.declaration_body(body);
+    }
+
+    pub(super) fn class(&mut self, stmt: &Stmt) {
+        let Stmt::Class {
+            name,
+            is_abstract,
+            is_final,
+            readonly,
+            parent,
+            interfaces,
+            trait_uses,
+            constants,
+            properties,
+            methods,
+            attributes,
+        } = stmt
+        else {
+            return;
+        };
+        self.attributes(attributes);
+        self.line();
+        for (set, keyword) in [
+            (*is_abstract, "abstract "),
+            (*is_final, "final "),
+            (*readonly, "readonly "),
+        ] {
+            if set {
+                self.push(keyword);
+            }
+        }
+        self.push(&format!("class {}", name));
+        if let Some(parent) = parent {
+            self.push(&format!(" extends {}", qualified_name(parent)));
+        }
+        if !interfaces.is_empty() {
+            let names: Vec<String> = interfaces.iter().map(qualified_name).collect();
+            self.push(&format!(" implements {}", names.join(", ")));
+        }
+        self.end_line();
+        self.line();
+        self.members_body(trait_uses, constants, properties, methods);
+        self.end_line();
+    }
+
+    pub(super) fn interface(&mut self, stmt: &Stmt) {
+        let Stmt::Interface {
+            name,
+            parents,
+            methods,
+            constants,
+            attributes,
+        } = stmt
+        else {
+            return;
+        };
+        self.attributes(attributes);
+        self.line();
+        self.push(&format!("interface {}", name));
+        if !parents.is_empty() {
+            let names: Vec<String> = parents.iter().map(qualified_name).collect();
+            self.push(&format!(" extends {}", names.join(", ")));
+        }
+        self.end_line();
+        self.open_body();
+        for constant in constants {
+            self.constant(constant);
+        }
+        for method in methods {
+            self.attributes(&method.attributes);
+            self.line();
+            self.push(&format!("public function {}", method.name));
+            self.signature(&method.params, method.return_type.as_ref());
+            self.push(";");
+            self.end_line();
+        }
+        self.close_body();
+    }
+
+    pub(super) fn trait_decl(&mut self, stmt: &Stmt) {
+        let Stmt::Trait {
+            name,
+            uses,
+            properties,
+            methods,
+            attributes,
+        } = stmt
+        else {
+            return;
+        };
+        self.attributes(attributes);
+        self.line();
+        self.push(&format!("trait {}", name));
+        self.end_line();
+        let uses = if uses.is_empty() {
+            Vec::new()
+        } else {
+            vec![TraitUse {
+                traits: uses.clone(),
+                resolutions: Vec::new(),
+            }]
+        };
+        self.line();
+        self.members_body(&uses, &[], properties, methods);
+        self.end_line();
+    }
+
+    pub(super) fn enum_decl(&mut self, stmt: &Stmt) {
+        let Stmt::Enum {
+            name,
+            backing_type,
+            interfaces,
+            cases,
+            constants,
+            methods,
+            attributes,
+        } = stmt
+        else {
+            return;
+        };
+        self.attributes(attributes);
+        self.line();
+        self.push(&format!("enum {}", name));
+        match backing_type {
+            EnumBackingType::None => {}
+            EnumBackingType::Int => self.push(": int"),
+            EnumBackingType::String => self.push(": string"),
+        }
+        if !interfaces.is_empty() {
+            let names: Vec<String> = interfaces.iter().map(qualified_name).collect();
+            self.push(&format!(" implements {}", names.join(", ")));
+        }
+        self.end_line();
+        self.open_body();
+        for case in cases {
+            self.line();
+            self.push(&format!("case {}", case.name));
+            if let Some(value) = &case.value {
+                self.push(" = ");
+                self.expr(value);
+            }
+            self.push(";");
+            self.end_line();
+        }
+        for constant in constants {
+            self.constant(constant);
+        }
+        for method in methods {
+            self.end_line();
+            self.method(method);
+        }
+        self.close_body();
+    }
+
+    /// The `{ ... }` of a class, trait or anonymous class: trait uses,
+    /// then properties, then methods separated by blank lines. The line
+    /// holding the closing brace is left open.
+    pub(super) fn members_body(
+        &mut self,
+        trait_uses: &[TraitUse],
+        constants: &[InterfaceConstant],
+        properties: &[Property],
+        methods: &[Method],
+    ) {
+        self.push("{");
+        self.end_line();
+        self.indent += 1;
+        for trait_use in trait_uses {
+            self.trait_use(trait_use);
+        }
+        let has_fields = !constants.is_empty() || !properties.is_empty();
+        if !trait_uses.is_empty() && has_fields {
+            self.end_line();
+        }
+        for constant in constants {
+            self.constant(constant);
+        }
+        for property in properties {
+            self.property(property);
+        }
+        for (i, method) in methods.iter().enumerate() {
+            if i > 0 || !trait_uses.is_empty() || has_fields {
+                self.end_line();
+            }
+            self.method(method);
+        }
+        self.indent -= 1;
+        self.line();
+        self.push("}");
+    }
+
+    fn constant(&mut self, constant: &InterfaceConstant) {
+        self.attributes(&constant.attributes);
+        self.line();
+        self.push(&format!("const {} = ", constant.name));
+        self.expr(&constant.value);
+        self.push(";");
+        self.end_line();
+    }
+
+    fn open_body(&mut self) {
+        self.line();
+        self.push("{");
+        self.end_line();
+        self.indent += 1;
+    }
+
+    fn close_body(&mut self) {
+        self.indent -= 1;
+        self.line();
+        self.push("}");
+        self.end_line();
+    }
+
+    /// A function or method body with the opening brace on its own line
+    fn declaration_body(&mut self, body: &[Stmt]) {
+        self.open_body();
+        self.block(body);
+        self.close_body();
+    }
+
+    fn trait_use(&mut self, trait_use: &TraitUse) {
+        self.line();
+        self.push(&format!("use {}", trait_use.traits.join(", ")));
+        if trait_use.resolutions.is_empty() {
+            self.push(";");
+            self.end_line();
+            return;
+        }
+        self.push(" {");
+        self.end_line();
+        self.indent += 1;
+        for resolution in &trait_use.resolutions {
+            self.line();
+            match resolution {
+                TraitResolution::InsteadOf {
+                    trait_name,
+                    method,
+                    excluded_traits,
+                } => self.push(&format!(
+                    "{}::{} insteadof {};",
+                    trait_name,
+                    method,
+                    excluded_traits.join(", ")
+                )),
+                TraitResolution::Alias {
+                    trait_name,
+                    method,
+                    alias,
+                    visibility,
+                } => {
+                    if let Some(trait_name) = trait_name {
+                        self.push(&format!("{}::", trait_name));
+                    }
+                    self.push(&format!("{} as ", method));
+                    if let Some(visibility) = visibility {
+                        self.push(visibility_keyword(*visibility));
+                        if !alias.is_empty() {
+                            self.push(" ");
+                        }
+                    }
+                    self.push(&format!("{};", alias));
+                }
+            }
+            self.end_line();
+        }
+        self.indent -= 1;
+        self.line();
+        self.push("}");
+        self.end_line();
+    }
+
+    fn property(&mut self, property: &Property) {
+        self.attributes(&property.attributes);
+        self.line();
+        self.push(visibility_keyword(property.visibility));
+        if let Some(write) = property.write_visibility {
+            self.push(&format!(" {}(set)", visibility_keyword(write)));
+        }
+        if property.is_static {
+            self.push(" static");
+        }
+        if property.readonly {
+            self.push(" readonly");
+        }
+        if let Some(type_hint) = &property.type_hint {
+            self.push(&format!(" {}", type_hint_source(type_hint)));
+        }
+        self.push(&format!(" ${}", property.name));
+        if property.hooks.is_empty() {
+            if let Some(default) = &property.default {
+                self.push(" = ");
+                self.expr(default);
+            }
+            self.push(";");
+            self.end_line();
+            return;
+        }
+        self.push(" {");
+        self.end_line();
+        self.indent += 1;
+        for hook in &property.hooks {
+            self.line();
+            self.push(match hook.hook_type {
+                PropertyHookType::Get => "get",
+                PropertyHookType::Set => "set",
+            });
+            match &hook.body {
+                PropertyHookBody::Expression(expr) => {
+                    self.push(" => ");
+                    self.expr(expr);
+                    self.push(";");
+                }
+                PropertyHookBody::Block(body) => self.braced(body),
+            }
+            self.end_line();
+        }
+        self.indent -= 1;
+        self.line();
+        self.push("}");
+        self.end_line();
+    }
+
+    fn method(&mut self, method: &Method) {
+        self.attributes(&method.attributes);
+        self.line();
+        if method.is_abstract {
+            self.push("abstract ");
+        }
+        if method.is_final {
+            self.push("final ");
+        }
+        self.push(visibility_keyword(method.visibility));
+        if method.is_static {
+            self.push(" static");
+        }
+        self.push(&format!(" function {}", method.name));
+        self.signature(&method.params, method.return_type.as_ref());
+        if method.is_abstract {
+            self.push(";");
+            self.end_line();
+        } else {
+            self.end_line();
+            self.declaration_body(&method.body);
+        }
+    }
+
+    /// `(params)` and the optional `: return type`
+    pub(super) fn signature(&mut self, params: &[FunctionParam], return_type: Option<&TypeHint>) {
+        self.push("(");
+        self.list(params, |p, param| p.param(param));
+        self.push(")");
+        if let Some(return_type) = return_type {
+            self.push(&format!(": {}", type_hint_source(return_type)));
+        }
+    }
+
+    fn param(&mut self, param: &FunctionParam) {
+        for attribute in &param.attributes {
+            self.attribute(attribute);
+            self.push(" ");
+        }
+        if let Some(visibility) = param.visibility {
+            self.push(visibility_keyword(visibility));
+            self.push(" ");
+        }
+        if param.readonly {
+            self.push("readonly ");
+        }
+        if let Some(type_hint) = &param.type_hint {
+            self.push(&type_hint_source(type_hint));
+            self.push(" ");
+        }
+        if param.by_ref {
+            self.push("&");
+        }
+        if param.is_variadic {
+            self.push("...");
+        }
+        self.push(&format!("${}", param.name));
+        if let Some(default) = &param.default {
+            self.push(" = ");
+            self.expr(default);
+        }
+    }
+
+    /// Attributes on their own lines above a declaration
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.line();
+            self.attribute(attribute);
+            self.end_line();
+        }
+    }
+
+    fn attribute(&mut self, attribute: &Attribute) {
+        self.push(&format!("#[{}", attribute.name));
+        if !attribute.arguments.is_empty() {
+            self.push("(");
+            self.list(&attribute.arguments, |p, argument| {
+                if let Some(name) = &argument.name {
+                    p.push(&format!("{}: ", name));
+                }
+                p.expr(&argument.value);
+            });
+            self.push(")");
+        }
+        self.push("]");
+    }
+}
+
+fn visibility_keyword(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Public => "public",
+        Visibility::Protected => "protected",
+        Visibility::Private => "private",
+    }
+}
+
+/// Source form of a type declaration
+fn type_hint_source(type_hint: &TypeHint) -> String {
+    match type_hint {
+        TypeHint::Simple(name) | TypeHint::Class(name) => name.clone(),
+        TypeHint::Nullable(inner) => format!("?{}", type_hint_source(inner)),
+        TypeHint::Union(types) => join_types(types, "|"),
+        TypeHint::Intersection(types) => join_types(types, "&"),
+        TypeHint::DNF(groups) => groups
+            .iter()
+            .map(|group| match group.as_slice() {
+                [single] => type_hint_source(single),
+                _ => format!("({})", join_types(group, "&")),
+            })
+            .collect::<Vec<_>>()
+            .join("|"),
+        TypeHint::Void => "void".to_string(),
+        TypeHint::Never => "never".to_string(),
+        TypeHint::Static => "static".to_string(),
+        TypeHint::SelfType => "self".to_string(),
+        TypeHint::ParentType => "parent".to_string(),
+    }
+}
+
+fn join_types(types: &[TypeHint], separator: &str) -> String {
+    types
+        .iter()
+        .map(type_hint_source)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
@@ -0,0 +1,436 @@
+//! Expression printing
+//!
+//! Parentheses are added from binding strengths that follow the parser's
+//! precedence table. Where the parser and PHP disagree (`xor`, `|>`, `??`,
+//! `throw` and `yield` operands), the printer parenthesizes so that both
+//! read the output the same way.
+
+use super::precedence::*;
+use super::{float_literal, quote_string, Printer};
+use crate::ast::{
+    Argument, ArrayElement, AssignOp, BinaryOp, Expr, ListElement, MatchArm, UnaryOp,
+};
+
+impl Printer {
+    pub(super) fn expr(&mut self, expr: &Expr) {
+        self.expr_at(expr, LOWEST);
+    }
+
+    /// Print `expr`, parenthesized if it binds looser than `min`
+    fn expr_at(&mut self, expr: &Expr, min: u8) {
+        if binding(expr) < min {
+            self.push("(");
+            self.expr_inner(expr);
+            self.push(")");
+        } else {
+            self.expr_inner(expr);
+        }
+    }
+
+    fn expr_inner(&mut self, expr: &Expr) {
+        match expr {
+            Expr::String(value) => self.push(&quote_string(value)),
+            Expr::Integer(value) => self.push(&value.to_string()),
+            Expr::Float(value) => self.push(&float_literal(*value)),
+            Expr::Bool(value) => self.push(if *value { "true" } else { "false" }),
+            Expr::Null => self.push("null"),
+            Expr::Variable(name) => self.push(&format!("${}", name)),
+            Expr::This => self.push("$this"),
+            Expr::Array(elements) => {
+                self.push("[");
+                self.list(elements, |p, element: &ArrayElement| {
+                    if let Some(key) = &element.key {
+                        p.expr(key);
+                        p.push(" => ");
+                    }
+                    p.expr(&element.value);
+                });
+                self.push("]");
+            }
+            Expr::ArrayAccess { array, index } => {
+                self.expr_at(array, PRIMARY);
+                self.push("[");
+                self.expr(index);
+                self.push("]");
+            }
+            Expr::Binary { left, op, right } => self.binary(left, op, right),
+            Expr::Unary { op, expr } => self.unary(op, expr),
+            Expr::Assign { var, op, value } => {
+                self.push(&format!("${}", var));
+                self.assign_value(op, value);
+            }
+            Expr::AssignRef { var, source } => {
+                self.push(&format!("${} = &", var));
+                self.expr_at(source, PRIMARY);
+            }
+            Expr::ArrayAssign {
+                array,
+                index,
+                op,
+                value,
+            } => {
+                self.expr_at(array, PRIMARY);
+                self.push("[");
+                if let Some(index) = index {
+                    self.expr(index);
+                }
+                self.push("]");
+                self.assign_value(op, value);
+            }
+            Expr::PropertyAssign {
+                object,
+                property,
+                op,
+                value,
+            } => {
+                self.expr_at(object, PRIMARY);
+                self.push(&format!("->{}", property));
+                self.assign_value(op, value);
+            }
+            Expr::StaticPropertyAssign {
+                class,
+                property,
+                op,
+                value,
+            } => {
+                self.push(&format!("{}::${}", class, property));
+                self.assign_value(op, value);
+            }
+            Expr::ListDestructure { elements, array } => {
+                self.list_pattern(elements);
+                self.assign_value(&AssignOp::Assign, array);
+            }
+            Expr::Grouped(inner) => {
+                self.push("(");
+                self.expr(inner);
+                self.push(")");
+            }
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => {
+                self.expr_at(condition, COALESCE);
+                match then_expr {
+                    Some(then_expr) => {
+                        self.push(" ? ");
+                        self.expr(then_expr);
+                        self.push(" : ");
+                    }
+                    None => self.push(" ?: "),
+                }
+                self.expr_at(else_expr, COALESCE);
+            }
+            Expr::FunctionCall { name, args } => {
+                self.push(name);
+                self.args(args);
+            }
+            Expr::CallableCall { callable, args } => {
+                self.expr_at(callable, PRIMARY);
+                self.args(args);
+            }
+            Expr::New { class_name, args } => {
+                self.push(&format!("new {}", class_name));
+                self.args(args);
+            }
+            Expr::NewDynamic { class, args } => {
+                self.push("new ");
+                if matches!(**class, Expr::Variable(_) | Expr::PropertyAccess { .. }) {
+                    self.expr(class);
+                } else {
+                    self.push("(");
+                    self.expr(class);
+                    self.push(")");
+                }
+                self.args(args);
+            }
+            Expr::NewAnonymousClass {
+                constructor_args,
+                parent,
+                interfaces,
+                traits,
+                properties,
+                methods,
+            } => {
+                self.push("new class");
+                if !constructor_args.is_empty() {
+                    self.args(constructor_args);
+                }
+                if let Some(parent) = parent {
+                    self.push(&format!(" extends {}", parent));
+                }
+                if !interfaces.is_empty() {
+                    self.push(&format!(" implements {}", interfaces.join(", ")));
+                }
+                self.push(" ");
+                self.members_body(traits, &[], properties, methods);
+            }
+            Expr::NewFiber { callback } => {
+                self.push("new Fiber(");
+                self.expr(callback);
+                self.push(")");
+            }
+            Expr::PropertyAccess { object, property } => {
+                self.expr_at(object, PRIMARY);
+                self.push(&format!("->{}", property));
+            }
+            Expr::MethodCall {
+                object,
+                method,
+                args,
+            } => {
+                self.expr_at(object, PRIMARY);
+                self.push(&format!("->{}", method));
+                self.args(args);
+            }
+            Expr::NullsafePropertyAccess { object, property } => {
+                self.expr_at(object, PRIMARY);
+                self.push(&format!("?->{}", property));
+            }
+            Expr::NullsafeMethodCall {
+                object,
+                method,
+                args,
+            } => {
+                self.expr_at(object, PRIMARY);
+                self.push(&format!("?->{}", method));
+                self.args(args);
+            }
+            Expr::StaticMethodCall {
+                class_name,
+                method,
+                args,
+            } => {
+                self.push(&format!("{}::{}", class_name, method));
+                self.args(args);
+            }
+            Expr::StaticPropertyAccess { class, property } => {
+                self.push(&format!("{}::${}", class, property))
+            }
+            Expr::FiberSuspend { value } => {
+                self.push("Fiber::suspend(");
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                self.push(")");
+            }
+            Expr::FiberGetCurrent => self.push("Fiber::getCurrent()"),
+            Expr::Match {
+                expr,
+                arms,
+                default,
+            } => self.match_expr(expr, arms, default.as_deref()),
+            Expr::EnumCase {
+                enum_name,
+                case_name,
+            } => self.push(&format!("{}::{}", enum_name, case_name)),
+            Expr::ClassReference(class_name) => self.push(&format!("{}::class", class_name)),
+            Expr::Clone { object } => {
+                self.push("clone ");
+                self.expr_at(object, PRIMARY);
+            }
+            Expr::CloneWith {
+                object,
+                modifications,
+            } => {
+                self.push("clone ");
+                self.expr_at(object, PRIMARY);
+                self.push(" with { ");
+                self.list(modifications, |p, modification| {
+                    p.push(&format!("{}: ", modification.property));
+                    p.expr(&modification.value);
+                });
+                self.push(" }");
+            }
+            Expr::CloneWithProperties { object, properties } => {
+                self.push("clone(");
+                self.expr(object);
+                self.push(", ");
+                self.expr(properties);
+                self.push(")");
+            }
+            Expr::Placeholder => self.push("..."),
+            Expr::Spread(inner) => {
+                self.push("...");
+                self.expr_at(inner, UNARY);
+            }
+            Expr::ArrowFunction { params, body } => {
+                self.push("fn");
+                self.signature(params, None);
+                self.push(" => ");
+                self.expr(body);
+            }
+            Expr::CallableFromFunction(name) => self.push(&format!("{}(...)", name)),
+            Expr::CallableFromMethod { object, method } => {
+                self.expr_at(object, PRIMARY);
+                self.push(&format!("->{}(...)", method));
+            }
+            Expr::CallableFromStaticMethod { class, method } => {
+                self.push(&format!("{}::{}(...)", class, method))
+            }
+            Expr::Throw(inner) => {
+                self.push("throw ");
+                self.expr_at(inner, UNARY);
+            }
+            Expr::Yield { key, value } => {
+                self.push("yield");
+                if let Some(key) = key {
+                    self.push(" ");
+                    self.expr_at(key, UNARY);
+                    self.push(" =>");
+                }
+                if let Some(value) = value {
+                    self.push(" ");
+                    self.expr_at(value, UNARY);
+                }
+            }
+            Expr::YieldFrom(inner) => {
+                self.push("yield from ");
+                self.expr_at(inner, UNARY);
+            }
+            Expr::Constant(name) => self.push(name),
+            Expr::MagicFile => self.push("__FILE__"),
+            Expr::MagicLine(_) => self.push("__LINE__"),
+            Expr::MagicDir => self.push("__DIR__"),
+            Expr::MagicFunction => self.push("__FUNCTION__"),
+            Expr::MagicClass => self.push("__CLASS__"),
+            Expr::MagicMethod => self.push("__METHOD__"),
+            Expr::MagicNamespace => self.push("__NAMESPACE__"),
+            Expr::MagicTrait => self.push("__TRAIT__"),
+        }
+    }
+
+    fn binary(&mut self, left: &Expr, op: &BinaryOp, right: &Expr) {
+        let (prec, symbol) = binary_op(op);
+        let (left_min, right_min) = match op {
+            // Right associative
+            BinaryOp::Pow => (prec + 1, prec),
+            // Right associative in PHP, and the parser reads the right side
+            // at `||` strength
+            BinaryOp::NullCoalesce => (prec + 1, OR + 1),
+            // Non-associative
+            BinaryOp::Equal
+            | BinaryOp::Identical
+            | BinaryOp::NotEqual
+            | BinaryOp::NotIdentical
+            | BinaryOp::LessThan
+            | BinaryOp::GreaterThan
+            | BinaryOp::LessEqual
+            | BinaryOp::GreaterEqual
+            | BinaryOp::Spaceship => (prec + 1, prec + 1),
+            // PHP binds `|>` tighter than comparisons; keep chains flat
+            BinaryOp::Pipe => {
+                let left_min = if matches!(
+                    left,
+                    Expr::Binary {
+                        op: BinaryOp::Pipe,
+                        ..
+                    }
+                ) {
+                    LOWEST
+                } else {
+                    CONCAT
+                };
+                (left_min, CONCAT)
+            }
+            _ => (prec, prec + 1),
+        };
+        self.expr_at(left, left_min);
+        self.push(&format!(" {} ", symbol));
+        self.expr_at(right, right_min);
+    }
+
+    fn unary(&mut self, op: &UnaryOp, operand: &Expr) {
+        match op {
+            UnaryOp::Neg | UnaryOp::Not | UnaryOp::BitwiseNot | UnaryOp::Silence => {
+                self.push(match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::Not => "!",
+                    UnaryOp::BitwiseNot => "~",
+                    _ => "@",
+                });
+                // `- -$x` rather than the decrement `--$x`
+                if *op == UnaryOp::Neg && starts_with_minus(operand) {
+                    self.push(" ");
+                }
+                self.expr_at(operand, UNARY);
+            }
+            UnaryOp::PreInc | UnaryOp::PreDec => {
+                self.push(if *op == UnaryOp::PreInc { "++" } else { "--" });
+                self.expr_at(operand, PRIMARY);
+            }
+            UnaryOp::PostInc | UnaryOp::PostDec => {
+                self.expr_at(operand, PRIMARY);
+                self.push(if *op == UnaryOp::PostInc { "++" } else { "--" });
+            }
+        }
+    }
+
+    /// ` op= value`, where an open-ended value (arrow function, throw,
+    /// yield) needs no parentheses because nothing follows it
+    fn assign_value(&mut self, op: &AssignOp, value: &Expr) {
+        self.push(&format!(" {} ", assign_op(op)));
+        if is_open_ended(value) {
+            self.expr(value);
+        } else {
+            self.expr_at(value, ASSIGN);
+        }
+    }
+
+    fn args(&mut self, args: &[Argument]) {
+        self.push("(");
+        self.list(args, |p, arg| {
+            if let Some(name) = &arg.name {
+                p.push(&format!("{}: ", name));
+            }
+            p.expr(&arg.value);
+        });
+        self.push(")");
+    }
+
+    /// A skipped slot prints as nothing between its commas
+    pub(super) fn list_pattern(&mut self, elements: &[Option<ListElement>]) {
+        self.push("list(");
+        self.list(elements, |p, element| {
+            let Some(element) = element else {
+                return;
+            };
+            if let Some(key) = &element.key {
+                p.expr(key);
+                p.push(" => ");
+            }
+            match element.value.as_ref() {
+                // Nested patterns carry a placeholder instead of a source
+                Expr::ListDestructure { elements, .. } => p.list_pattern(elements),
+                value => p.expr(value),
+            }
+        });
+        self.push(")");
+    }
+
+    fn match_expr(&mut self, subject: &Expr, arms: &[MatchArm], default: Option<&Expr>) {
+        self.push("match (");
+        self.expr(subject);
+        self.push(") {");
+        self.end_line();
+        self.indent += 1;
+        for arm in arms {
+            self.line();
+            self.list(&arm.conditions, |p, condition| p.expr(condition));
+            self.push(" => ");
+            self.expr(&arm.result);
+            self.push(",");
+            self.end_line();
+        }
+        if let Some(default) = default {
+            self.line();
+            self.push("default => ");
+            self.expr(default);
+            self.push(",");
+            self.end_line();
+        }
+        self.indent -= 1;
+        self.line();
+        self.push("}");
+    }
+}
@@ -0,0 +1,132 @@
+//! Binding strengths and operator spellings for the expression printer
+//!
+//! The strengths follow the parser's precedence table, lowest first.
+
+use crate::ast::{AssignOp, BinaryOp, Expr, UnaryOp};
+
+/// Operators that are only safe at the top of an expression
+pub(super) const LOWEST: u8 = 0;
+pub(super) const ASSIGN: u8 = 1;
+pub(super) const PIPE: u8 = 2;
+pub(super) const TERNARY: u8 = 3;
+pub(super) const COALESCE: u8 = 4;
+pub(super) const OR: u8 = 5;
+pub(super) const AND: u8 = 6;
+pub(super) const XOR: u8 = 7;
+pub(super) const BIT_OR: u8 = 8;
+pub(super) const BIT_XOR: u8 = 9;
+pub(super) const BIT_AND: u8 = 10;
+pub(super) const EQUALITY: u8 = 11;
+pub(super) const COMPARISON: u8 = 12;
+pub(super) const CONCAT: u8 = 13;
+pub(super) const SHIFT: u8 = 14;
+pub(super) const ADD: u8 = 15;
+pub(super) const MUL: u8 = 16;
+pub(super) const POW: u8 = 17;
+pub(super) const UNARY: u8 = 18;
+/// Variables, literals, calls and member access
+pub(super) const PRIMARY: u8 = 19;
+
+/// How tightly an expression holds together when it appears as an operand
+pub(super) fn binding(expr: &Expr) -> u8 {
+    match expr {
+        // PHP reads `xor` below assignment
+        Expr::Binary {
+            op: BinaryOp::Xor, ..
+        } => LOWEST,
+        Expr::Binary { op, .. } => binary_op(op).0,
+        Expr::Assign { .. }
+        | Expr::AssignRef { .. }
+        | Expr::ArrayAssign { .. }
+        | Expr::PropertyAssign { .. }
+        | Expr::StaticPropertyAssign { .. }
+        | Expr::ListDestructure { .. } => ASSIGN,
+        Expr::Ternary { .. } => TERNARY,
+        Expr::Unary {
+            op: UnaryOp::PostInc | UnaryOp::PostDec,
+            ..
+        } => PRIMARY,
+        Expr::Unary { .. }
+        | Expr::New { .. }
+        | Expr::NewDynamic { .. }
+        | Expr::NewFiber { .. }
+        | Expr::NewAnonymousClass { .. }
+        | Expr::Clone { .. }
+        | Expr::CloneWith { .. } => UNARY,
+        Expr::Integer(value) if *value < 0 => UNARY,
+        Expr::Float(value) if value.is_sign_negative() => UNARY,
+        expr if is_open_ended(expr) => LOWEST,
+        Expr::Spread(_) => LOWEST,
+        _ => PRIMARY,
+    }
+}
+
+/// Prefix forms that extend as far right as possible
+pub(super) fn is_open_ended(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::ArrowFunction { .. } | Expr::Throw(_) | Expr::Yield { .. } | Expr::YieldFrom(_)
+    )
+}
+
+pub(super) fn starts_with_minus(expr: &Expr) -> bool {
+    match expr {
+        Expr::Unary {
+            op: UnaryOp::Neg | UnaryOp::PreDec,
+            ..
+        } => true,
+        Expr::Integer(value) => *value < 0,
+        Expr::Float(value) => value.is_sign_negative(),
+        _ => false,
+    }
+}
+
+pub(super) fn binary_op(op: &BinaryOp) -> (u8, &'static str) {
+    match op {
+        BinaryOp::Add => (ADD, "+"),
+        BinaryOp::Sub => (ADD, "-"),
+        BinaryOp::Mul => (MUL, "*"),
+        BinaryOp::Div => (MUL, "/"),
+        BinaryOp::Mod => (MUL, "%"),
+        BinaryOp::Pow => (POW, "**"),
+        BinaryOp::Concat => (CONCAT, "."),
+        BinaryOp::Equal => (EQUALITY, "=="),
+        BinaryOp::Identical => (EQUALITY, "==="),
+        BinaryOp::NotEqual => (EQUALITY, "!="),
+        BinaryOp::NotIdentical => (EQUALITY, "!=="),
+        BinaryOp::LessThan => (COMPARISON, "<"),
+        BinaryOp::GreaterThan => (COMPARISON, ">"),
+        BinaryOp::LessEqual => (COMPARISON, "<="),
+        BinaryOp::GreaterEqual => (COMPARISON, ">="),
+        BinaryOp::Spaceship => (COMPARISON, "<=>"),
+        BinaryOp::And => (AND, "&&"),
+        BinaryOp::Or => (OR, "||"),
+        BinaryOp::Xor => (XOR, "xor"),
+        BinaryOp::BitwiseOr => (BIT_OR, "|"),
+        BinaryOp::BitwiseXor => (BIT_XOR, "^"),
+        BinaryOp::BitwiseAnd => (BIT_AND, "&"),
+        BinaryOp::ShiftLeft => (SHIFT, "<<"),
+        BinaryOp::ShiftRight => (SHIFT, ">>"),
+        BinaryOp::NullCoalesce => (COALESCE, "??"),
+        BinaryOp::Pipe => (PIPE, "|>"),
+    }
+}
+
+pub(super) fn assign_op(op: &AssignOp) -> &'static str {
+    match op {
+        AssignOp::Assign => "=",
+        AssignOp::AddAssign => "+=",
+        AssignOp::SubAssign => "-=",
+        AssignOp::MulAssign => "*=",
+        AssignOp::DivAssign => "/=",
+        AssignOp::ModAssign => "%=",
+        AssignOp::ConcatAssign => ".=",
+        AssignOp::PowAssign => "**=",
+        AssignOp::BitwiseAndAssign => "&=",
+        AssignOp::BitwiseOrAssign => "|=",
+        AssignOp::BitwiseXorAssign => "^=",
+        AssignOp::ShiftLeftAssign => "<<=",
+        AssignOp::ShiftRightAssign => ">>=",
+        AssignOp::NullCoalesceAssign => "??=",
+    }
+}
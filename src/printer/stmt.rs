@@ -0,0 +1,365 @@
+//! Statement printing
+
+use super::Printer;
+use crate::ast::{
+    CatchClause, DeclareDirective, Expr, ForeachTarget, GroupUse, NamespaceBody, QualifiedName,
+    Stmt, SwitchCase, UseItem, UseType,
+};
+
+impl Printer {
+    /// Print statements one per line, with a blank line around
+    /// declarations
+    pub(super) fn block(&mut self, statements: &[Stmt]) {
+        let mut previous: Option<&Stmt> = None;
+        for stmt in statements {
+            if matches!(stmt, Stmt::Line(_)) {
+                continue;
+            }
+            if let Some(previous) = previous {
+                if self.in_php
+                    && !matches!(stmt, Stmt::Html(_))
+                    && (is_declaration(previous) || is_declaration(stmt))
+                {
+                    self.end_line();
+                }
+            }
+            self.stmt(stmt);
+            previous = Some(stmt);
+        }
+    }
+
+    /// Print ` {`, the indented statements, and the closing `}` without
+    /// ending its line, so that `else`, `catch` or `while` can follow it
+    pub(super) fn braced(&mut self, statements: &[Stmt]) {
+        self.push(" {");
+        self.end_line();
+        self.indent += 1;
+        self.block(statements);
+        self.indent -= 1;
+        self.line();
+        self.push("}");
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Line(_) => {}
+            Stmt::Html(html) => {
+                if self.in_php {
+                    self.push("?>");
+                    self.in_php = false;
+                }
+                self.push(html);
+            }
+            Stmt::Echo(exprs) => {
+                self.line();
+                self.push("echo ");
+                self.list(exprs, |p, expr| p.expr(expr));
+                self.push(";");
+                self.end_line();
+            }
+            Stmt::Expression(expr) => self.simple("", Some(expr)),
+            Stmt::Return(value) => self.simple("return", value.as_ref()),
+            Stmt::Global(names) => {
+                self.line();
+                let names: Vec<String> = names.iter().map(|name| format!("${}", name)).collect();
+                self.push(&format!("global {};", names.join(", ")));
+                self.end_line();
+            }
+            Stmt::Throw(expr) => self.simple("throw", Some(expr)),
+            Stmt::Break => self.simple("break", None),
+            Stmt::Continue => self.simple("continue", None),
+            Stmt::If {
+                condition,
+                then_branch,
+                elseif_branches,
+                else_branch,
+            } => {
+                self.line();
+                self.if_chain(condition, then_branch, elseif_branches, else_branch);
+                self.end_line();
+            }
+            Stmt::While { condition, body } => {
+                self.line();
+                self.push("while (");
+                self.expr(condition);
+                self.push(")");
+                self.braced(body);
+                self.end_line();
+            }
+            Stmt::DoWhile { body, condition } => {
+                self.line();
+                self.push("do");
+                self.braced(body);
+                self.push(" while (");
+                self.expr(condition);
+                self.push(");");
+                self.end_line();
+            }
+            Stmt::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                self.line();
+                self.push("for (");
+                for (i, clause) in [init, condition, update].into_iter().enumerate() {
+                    if i > 0 {
+                        self.push(";");
+                        if clause.is_some() {
+                            self.push(" ");
+                        }
+                    }
+                    if let Some(clause) = clause {
+                        self.expr(clause);
+                    }
+                }
+                self.push(")");
+                self.braced(body);
+                self.end_line();
+            }
+            Stmt::Foreach {
+                array,
+                key,
+                value,
+                by_ref,
+                body,
+            } => {
+                self.line();
+                self.push("foreach (");
+                self.expr(array);
+                self.push(" as ");
+                if let Some(key) = key {
+                    self.push(&format!("${} => ", key));
+                }
+                if *by_ref {
+                    self.push("&");
+                }
+                match value {
+                    ForeachTarget::Variable(name) => self.push(&format!("${}", name)),
+                    ForeachTarget::List(elements) => self.list_pattern(elements),
+                }
+                self.push(")");
+                self.braced(body);
+                self.end_line();
+            }
+            Stmt::Switch { expr, cases } => self.switch(expr, cases),
+            Stmt::TryCatch {
+                try_body,
+                catch_clauses,
+                finally_body,
+            } => self.try_catch(try_body, catch_clauses, finally_body.as_deref()),
+            Stmt::Namespace { name, body } => self.namespace(name.as_ref(), body),
+            Stmt::Use(items) => {
+                self.line();
+                self.push("use ");
+                if let Some(first) = items.first() {
+                    self.push(use_keyword(&first.use_type));
+                }
+                self.list(items, |p, item| p.push(&use_item(item)));
+                self.push(";");
+                self.end_line();
+            }
+            Stmt::GroupUse(group) => self.group_use(group),
+            Stmt::Declare { directives, body } => {
+                self.line();
+                self.push("declare(");
+                self.list(directives, |p, directive| {
+                    p.push(&declare_directive(directive))
+                });
+                self.push(")");
+                match body {
+                    Some(body) => self.braced(body),
+                    None => self.push(";"),
+                }
+                self.end_line();
+            }
+            Stmt::Function {
+                name,
+                params,
+                return_type,
+                body,
+                attributes,
+            } => self.function(name, params, return_type.as_ref(), body, attributes),
+            Stmt::Class { .. } => self.class(stmt),
+            Stmt::Interface { .. } => self.interface(stmt),
+            Stmt::Trait { .. } => self.trait_decl(stmt),
+            Stmt::Enum { .. } => self.enum_decl(stmt),
+        }
+    }
+
+    /// A one-line statement: keyword, optional expression, semicolon
+    fn simple(&mut self, keyword: &str, expr: Option<&Expr>) {
+        self.line();
+        self.push(keyword);
+        if let Some(expr) = expr {
+            if !keyword.is_empty() {
+                self.push(" ");
+            }
+            self.expr(expr);
+        }
+        self.push(";");
+        self.end_line();
+    }
+
+    /// Print an if statement from `if` to its final `}`. An else branch
+    /// that is exactly one nested if (how the parser stores `else if`) is
+    /// printed back as `else if`.
+    fn if_chain(
+        &mut self,
+        condition: &Expr,
+        then_branch: &[Stmt],
+        elseif_branches: &[(Expr, Vec<Stmt>)],
+        else_branch: &Option<Vec<Stmt>>,
+    ) {
+        self.push("if (");
+        self.expr(condition);
+        self.push(")");
+        self.braced(then_branch);
+        for (condition, branch) in elseif_branches {
+            self.push(" elseif (");
+            self.expr(condition);
+            self.push(")");
+            self.braced(branch);
+        }
+        match else_branch.as_deref() {
+            Some(
+                [Stmt::If {
+                    condition,
+                    then_branch,
+                    elseif_branches,
+                    else_branch,
+                }],
+            ) => {
+                self.push(" else ");
+                self.if_chain(condition, then_branch, elseif_branches, else_branch);
+            }
+            Some(branch) => {
+                self.push(" else");
+                self.braced(branch);
+            }
+            None => {}
+        }
+    }
+
+    fn switch(&mut self, expr: &Expr, cases: &[SwitchCase]) {
+        self.line();
+        self.push("switch (");
+        self.expr(expr);
+        self.push(") {");
+        self.end_line();
+        self.indent += 1;
+        for case in cases {
+            self.line();
+            match &case.value {
+                Some(value) => {
+                    self.push("case ");
+                    self.expr(value);
+                    self.push(":");
+                }
+                None => self.push("default:"),
+            }
+            self.end_line();
+            self.indented(&case.body);
+        }
+        self.indent -= 1;
+        self.line();
+        self.push("}");
+        self.end_line();
+    }
+
+    fn indented(&mut self, statements: &[Stmt]) {
+        self.indent += 1;
+        self.block(statements);
+        self.indent -= 1;
+    }
+
+    fn try_catch(&mut self, body: &[Stmt], clauses: &[CatchClause], finally: Option<&[Stmt]>) {
+        self.line();
+        self.push("try");
+        self.braced(body);
+        for clause in clauses {
+            self.push(&format!(
+                " catch ({} ${})",
+                clause.exception_types.join(" | "),
+                clause.variable
+            ));
+            self.braced(&clause.body);
+        }
+        if let Some(finally) = finally {
+            self.push(" finally");
+            self.braced(finally);
+        }
+        self.end_line();
+    }
+
+    fn namespace(&mut self, name: Option<&QualifiedName>, body: &NamespaceBody) {
+        self.line();
+        self.push("namespace");
+        if let Some(name) = name {
+            self.push(" ");
+            self.push(&qualified_name(name));
+        }
+        match body {
+            NamespaceBody::Braced(body) => self.braced(body),
+            NamespaceBody::Unbraced => self.push(";"),
+        }
+        self.end_line();
+    }
+
+    fn group_use(&mut self, group: &GroupUse) {
+        self.line();
+        self.push(&format!("use {}\\{{", qualified_name(&group.prefix)));
+        self.list(&group.items, |p, item| {
+            p.push(use_keyword(&item.use_type));
+            p.push(&use_item(item));
+        });
+        self.push("};");
+        self.end_line();
+    }
+}
+
+fn is_declaration(stmt: &Stmt) -> bool {
+    matches!(
+        stmt,
+        Stmt::Function { .. }
+            | Stmt::Class { .. }
+            | Stmt::Interface { .. }
+            | Stmt::Trait { .. }
+            | Stmt::Enum { .. }
+    )
+}
+
+pub(super) fn qualified_name(name: &QualifiedName) -> String {
+    let path = name.parts.join("\\");
+    if name.is_fully_qualified {
+        format!("\\{}", path)
+    } else {
+        path
+    }
+}
+
+fn use_keyword(use_type: &UseType) -> &'static str {
+    match use_type {
+        UseType::Class => "",
+        UseType::Function => "function ",
+        UseType::Constant => "const ",
+    }
+}
+
+fn use_item(item: &UseItem) -> String {
+    match &item.alias {
+        Some(alias) => format!("{} as {}", qualified_name(&item.name), alias),
+        None => qualified_name(&item.name),
+    }
+}
+
+fn declare_directive(directive: &DeclareDirective) -> String {
+    match directive {
+        DeclareDirective::StrictTypes(strict) => format!("strict_types={}", *strict as u8),
+        DeclareDirective::Encoding(encoding) => {
+            format!("encoding={}", super::quote_string(encoding))
+        }
+        DeclareDirective::Ticks(ticks) => format!("ticks={}", ticks),
+    }
+}
@@ -4,14 +4,14 @@
 //! - array_basic: Basic array access functions
 //! - array_search: Search and lookup functions
 //! - array_manipulation: Array manipulation functions
-//! - array_callbacks: Callback-based array functions
+//! - array_callbacks: Sums and products
 
 pub use super::array_basic::{
     array_first, array_keys, array_last, array_pop, array_push, array_shift, array_slice,
     array_unshift, array_values, count,
 };
 
-pub use super::array_callbacks::{array_filter, array_map, array_reduce, array_sum};
+pub use super::array_callbacks::array_sum;
 
 pub use super::array_manipulation::{array_merge, array_reverse, array_unique, range};
 
@@ -1,11 +1,14 @@
 //! Basic array access functions
 
-use crate::runtime::{ArrayKey, Value};
+use crate::runtime::{ArrayKey, PhpArray, Value};
+use crate::vm::error::NativeError;
 
 /// count - Count all elements in an array
-pub fn count(args: &[Value]) -> Result<Value, String> {
+pub fn count(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("count() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "count() expects exactly 1 parameter",
+        ));
     }
     match &args[0] {
         Value::Array(arr) => Ok(Value::Integer(arr.len() as i64)),
@@ -15,40 +18,32 @@ pub fn count(args: &[Value]) -> Result<Value, String> {
 }
 
 /// array_push - Push one or more elements onto the end of array
-pub fn array_push(args: &[Value]) -> Result<Value, String> {
+pub fn array_push(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_push() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_push() expects at least 2 parameters",
+        ));
     }
     match &args[0] {
         Value::Array(arr) => {
             let mut new_arr = arr.clone();
-            let max_key = new_arr
-                .iter()
-                .filter_map(|(k, _)| {
-                    if let ArrayKey::Integer(i) = k {
-                        Some(*i)
-                    } else {
-                        None
-                    }
-                })
-                .max()
-                .unwrap_or(-1);
-
-            let mut next_key = max_key + 1;
             for value in args.iter().skip(1) {
-                new_arr.push((ArrayKey::Integer(next_key), value.clone()));
-                next_key += 1;
+                new_arr.push(value.clone()).map_err(NativeError::fatal)?;
             }
             Ok(Value::Integer(new_arr.len() as i64))
         }
-        _ => Err("array_push() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_push() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_pop - Pop element off the end of array
-pub fn array_pop(args: &[Value]) -> Result<Value, String> {
+pub fn array_pop(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_pop() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_pop() expects exactly 1 parameter",
+        ));
     }
 
     match &args[0] {
@@ -59,14 +54,18 @@ pub fn array_pop(args: &[Value]) -> Result<Value, String> {
                 Ok(arr.last().map(|(_, v)| v.clone()).unwrap_or(Value::Null))
             }
         }
-        _ => Err("array_pop() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_pop() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_shift - Shift an element off the beginning of array
-pub fn array_shift(args: &[Value]) -> Result<Value, String> {
+pub fn array_shift(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_shift() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_shift() expects exactly 1 parameter",
+        ));
     }
 
     match &args[0] {
@@ -77,14 +76,18 @@ pub fn array_shift(args: &[Value]) -> Result<Value, String> {
                 Ok(arr.first().map(|(_, v)| v.clone()).unwrap_or(Value::Null))
             }
         }
-        _ => Err("array_shift() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_shift() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_unshift - Prepend one or more elements to the beginning of an array
-pub fn array_unshift(args: &[Value]) -> Result<Value, String> {
+pub fn array_unshift(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_unshift() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_unshift() expects at least 2 parameters",
+        ));
     }
 
     match &args[0] {
@@ -92,14 +95,18 @@ pub fn array_unshift(args: &[Value]) -> Result<Value, String> {
             let new_count = arr.len() + args.len() - 1;
             Ok(Value::Integer(new_count as i64))
         }
-        _ => Err("array_unshift() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_unshift() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_keys - Return all the keys of an array
-pub fn array_keys(args: &[Value]) -> Result<Value, String> {
+pub fn array_keys(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_keys() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_keys() expects at least 1 parameter",
+        ));
     }
 
     match &args[0] {
@@ -115,16 +122,20 @@ pub fn array_keys(args: &[Value]) -> Result<Value, String> {
                     (ArrayKey::Integer(i as i64), key_val)
                 })
                 .collect();
-            Ok(Value::Array(keys))
+            Ok(Value::Array(keys.into()))
         }
-        _ => Err("array_keys() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_keys() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_values - Return all the values of an array
-pub fn array_values(args: &[Value]) -> Result<Value, String> {
+pub fn array_values(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_values() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_values() expects at least 1 parameter",
+        ));
     }
 
     match &args[0] {
@@ -134,22 +145,26 @@ pub fn array_values(args: &[Value]) -> Result<Value, String> {
                 .enumerate()
                 .map(|(i, (_, v))| (ArrayKey::Integer(i as i64), v.clone()))
                 .collect();
-            Ok(Value::Array(values))
+            Ok(Value::Array(values.into()))
         }
-        _ => Err("array_values() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_values() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_slice - Returns a slice of an array
-pub fn array_slice(args: &[Value]) -> Result<Value, String> {
+pub fn array_slice(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_slice() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_slice() expects at least 2 parameters",
+        ));
     }
     match &args[0] {
         Value::Array(arr) => {
             let offset = match &args[1] {
                 Value::Integer(n) => *n,
-                _ => return Err("array_slice() offset must be integer".to_string()),
+                _ => return Err(NativeError::fatal("array_slice() offset must be integer")),
             };
             let length = args.get(2).and_then(|v| match v {
                 Value::Integer(n) => Some(*n),
@@ -161,7 +176,7 @@ pub fn array_slice(args: &[Value]) -> Result<Value, String> {
                 offset as usize
             };
             if start >= arr.len() {
-                return Ok(Value::Array(Vec::new()));
+                return Ok(Value::Array(PhpArray::new()));
             }
             let arr_len = arr.len() as i64;
             let end = match length {
@@ -169,22 +184,26 @@ pub fn array_slice(args: &[Value]) -> Result<Value, String> {
                 Some(len) => (start as i64 + len).min(arr_len) as usize,
                 None => arr_len as usize,
             }
-            .min(arr.len());
+            .clamp(start, arr.len());
             let result: Vec<(ArrayKey, Value)> = arr[start..end]
                 .iter()
                 .enumerate()
                 .map(|(i, v)| (ArrayKey::Integer(i as i64), v.1.clone()))
                 .collect();
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("array_slice() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_slice() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_first - Get first value of an array (PHP 8.5)
-pub fn array_first(args: &[Value]) -> Result<Value, String> {
+pub fn array_first(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_first() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_first() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -195,14 +214,18 @@ pub fn array_first(args: &[Value]) -> Result<Value, String> {
                 Ok(arr.first().map(|(_, v)| v.clone()).unwrap_or(Value::Null))
             }
         }
-        _ => Err("array_first() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_first() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_last - Get last value of an array (PHP 8.5)
-pub fn array_last(args: &[Value]) -> Result<Value, String> {
+pub fn array_last(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_last() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_last() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -213,6 +236,8 @@ pub fn array_last(args: &[Value]) -> Result<Value, String> {
                 Ok(arr.last().map(|(_, v)| v.clone()).unwrap_or(Value::Null))
             }
         }
-        _ => Err("array_last() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_last() expects parameter 1 to be array",
+        )),
     }
 }
@@ -1,18 +1,25 @@
 //! Array chunking and padding functions
 
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
 
 /// array_chunk - Split an array into chunks
-pub fn array_chunk(args: &[Value]) -> Result<Value, String> {
+pub fn array_chunk(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_chunk() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_chunk() expects at least 2 parameters",
+        ));
     }
     let preserve_keys = args.get(2).map(|v| v.to_bool()).unwrap_or(false);
     match &args[0] {
         Value::Array(arr) => {
             let size = match &args[1] {
                 Value::Integer(n) if *n > 0 => *n as usize,
-                _ => return Err("array_chunk() size must be positive integer".to_string()),
+                _ => {
+                    return Err(NativeError::fatal(
+                        "array_chunk() size must be positive integer",
+                    ))
+                }
             };
             let mut chunks: Vec<(ArrayKey, Value)> = Vec::new();
             let mut current_chunk: Vec<(ArrayKey, Value)> = Vec::new();
@@ -24,24 +31,34 @@ pub fn array_chunk(args: &[Value]) -> Result<Value, String> {
                     current_chunk.push((ArrayKey::Integer(i as i64), v.clone()));
                 }
                 if current_chunk.len() == size {
-                    chunks.push((ArrayKey::Integer(chunk_index), Value::Array(current_chunk)));
+                    chunks.push((
+                        ArrayKey::Integer(chunk_index),
+                        Value::Array(current_chunk.into()),
+                    ));
                     current_chunk = Vec::new();
                     chunk_index += 1;
                 }
             }
             if !current_chunk.is_empty() {
-                chunks.push((ArrayKey::Integer(chunk_index), Value::Array(current_chunk)));
+                chunks.push((
+                    ArrayKey::Integer(chunk_index),
+                    Value::Array(current_chunk.into()),
+                ));
             }
-            Ok(Value::Array(chunks))
+            Ok(Value::Array(chunks.into()))
         }
-        _ => Err("array_chunk() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_chunk() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_pad - Pad array to the specified length with a value
-pub fn array_pad(args: &[Value]) -> Result<Value, String> {
+pub fn array_pad(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_pad() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_pad() expects at least 2 parameters",
+        ));
     }
     let pad_count = args[1].to_int();
     let pad_value = args.get(2).cloned().unwrap_or(Value::Null);
@@ -52,7 +69,7 @@ pub fn array_pad(args: &[Value]) -> Result<Value, String> {
                 let mut result = arr.clone();
                 let to_add = (pad_count - arr_len) as usize;
                 for _ in 0..to_add {
-                    result.push((ArrayKey::Integer(result.len() as i64), pad_value.clone()));
+                    result.push(pad_value.clone()).map_err(NativeError::fatal)?;
                 }
                 Ok(Value::Array(result))
             } else if pad_count < -arr_len {
@@ -65,25 +82,29 @@ pub fn array_pad(args: &[Value]) -> Result<Value, String> {
                     let new_key = ArrayKey::Integer((i as i64) + (to_prepend as i64));
                     result.push((new_key, v.clone()));
                 }
-                Ok(Value::Array(result))
+                Ok(Value::Array(result.into()))
             } else {
                 Ok(args[0].clone())
             }
         }
-        _ => Err("array_pad() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_pad() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_splice - Remove a portion of the array and replace it with something else
-pub fn array_splice(args: &[Value]) -> Result<Value, String> {
+pub fn array_splice(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_splice() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_splice() expects at least 2 parameters",
+        ));
     }
     match &args[0] {
         Value::Array(arr) => {
             let offset = match &args[1] {
                 Value::Integer(n) => *n,
-                _ => return Err("array_splice() offset must be integer".to_string()),
+                _ => return Err(NativeError::fatal("array_splice() offset must be integer")),
             };
             let length = args.get(2).and_then(|v| match v {
                 Value::Integer(n) => Some(*n),
@@ -94,14 +115,14 @@ pub fn array_splice(args: &[Value]) -> Result<Value, String> {
             let start = if offset < 0 {
                 (arr_len + offset).max(0) as usize
             } else {
-                offset as usize
+                offset.min(arr_len) as usize
             };
             let end = match length {
                 Some(len) if len < 0 => (arr_len + len).max(0) as usize,
                 Some(len) => (start as i64 + len).min(arr_len) as usize,
                 None => arr_len as usize,
             }
-            .min(arr.len());
+            .clamp(start, arr.len());
             if let Some(repl) = replacement {
                 let mut final_result: Vec<(ArrayKey, Value)> = Vec::new();
                 for (i, (_, v)) in arr.iter().enumerate() {
@@ -128,7 +149,7 @@ pub fn array_splice(args: &[Value]) -> Result<Value, String> {
                             .push((ArrayKey::Integer(final_result.len() as i64), v.clone()));
                     }
                 }
-                Ok(Value::Array(final_result))
+                Ok(Value::Array(final_result.into()))
             } else {
                 let _removed: Vec<(ArrayKey, Value)> = arr[start..end]
                     .iter()
@@ -141,9 +162,11 @@ pub fn array_splice(args: &[Value]) -> Result<Value, String> {
                         result.push((ArrayKey::Integer(result.len() as i64), v.clone()));
                     }
                 }
-                Ok(Value::Array(result))
+                Ok(Value::Array(result.into()))
             }
         }
-        _ => Err("array_splice() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_splice() expects parameter 1 to be array",
+        )),
     }
 }
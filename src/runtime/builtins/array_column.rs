@@ -1,11 +1,14 @@
 //! Array column and value operations
 
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
 
 /// array_column - Return the values from a single column in the input array
-pub fn array_column(args: &[Value]) -> Result<Value, String> {
+pub fn array_column(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_column() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_column() expects at least 1 parameter",
+        ));
     }
     let column_key = args.get(1).map(|v| v.to_string_val()).unwrap_or_default();
     let index_key = args.get(2).map(|v| v.to_string_val());
@@ -49,16 +52,20 @@ pub fn array_column(args: &[Value]) -> Result<Value, String> {
                     }
                 }
             }
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("array_column() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_column() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_flip - Exchanges all keys with their associated values
-pub fn array_flip(args: &[Value]) -> Result<Value, String> {
+pub fn array_flip(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_flip() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_flip() expects exactly 1 parameter",
+        ));
     }
     match &args[0] {
         Value::Array(arr) => {
@@ -71,16 +78,20 @@ pub fn array_flip(args: &[Value]) -> Result<Value, String> {
                 };
                 result.push((new_key, v.clone()));
             }
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("array_flip() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_flip() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_count_values - Counts all the values of an array
-pub fn array_count_values(args: &[Value]) -> Result<Value, String> {
+pub fn array_count_values(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_count_values() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_count_values() expects exactly 1 parameter",
+        ));
     }
     match &args[0] {
         Value::Array(arr) => {
@@ -115,8 +126,10 @@ pub fn array_count_values(args: &[Value]) -> Result<Value, String> {
                     counts.push((key, Value::Integer(1)));
                 }
             }
-            Ok(Value::Array(counts))
+            Ok(Value::Array(counts.into()))
         }
-        _ => Err("array_count_values() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_count_values() expects parameter 1 to be array",
+        )),
     }
 }
@@ -1,11 +1,14 @@
 //! Array creation functions
 
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
 
 /// array_fill - Fill an array with values
-pub fn array_fill(args: &[Value]) -> Result<Value, String> {
+pub fn array_fill(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_fill() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_fill() expects at least 2 parameters",
+        ));
     }
     let start_index = args
         .get(2)
@@ -17,7 +20,9 @@ pub fn array_fill(args: &[Value]) -> Result<Value, String> {
     let count = args[0].to_int();
     let value = args[1].clone();
     if count < 0 {
-        return Err("array_fill(): Number of elements must be non-negative".to_string());
+        return Err(NativeError::fatal(
+            "array_fill(): Number of elements must be non-negative",
+        ));
     }
     let mut result: Vec<(ArrayKey, Value)> = Vec::new();
     for i in 0..count {
@@ -28,13 +33,15 @@ pub fn array_fill(args: &[Value]) -> Result<Value, String> {
         };
         result.push((key, value.clone()));
     }
-    Ok(Value::Array(result))
+    Ok(Value::Array(result.into()))
 }
 
 /// array_fill_keys - Fill an array with values, specifying keys
-pub fn array_fill_keys(args: &[Value]) -> Result<Value, String> {
+pub fn array_fill_keys(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_fill_keys() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_fill_keys() expects at least 2 parameters",
+        ));
     }
     let value = args[0].clone();
     match &args[1] {
@@ -55,23 +62,27 @@ pub fn array_fill_keys(args: &[Value]) -> Result<Value, String> {
                     (key, value.clone())
                 })
                 .collect();
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("array_fill_keys() expects parameter 2 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_fill_keys() expects parameter 2 to be array",
+        )),
     }
 }
 
 /// array_combine - Creates an array by using one array for keys and another for its values
-pub fn array_combine(args: &[Value]) -> Result<Value, String> {
+pub fn array_combine(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_combine() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_combine() expects exactly 2 parameters",
+        ));
     }
     match (&args[0], &args[1]) {
         (Value::Array(keys), Value::Array(values)) => {
             if keys.len() != values.len() {
-                return Err(
-                    "array_combine(): Number of elements in each array must be equal".to_string(),
-                );
+                return Err(NativeError::value_error(
+                    "array_combine(): Argument #1 ($keys) and argument #2 ($values) must have the same number of elements",
+                ));
             }
             let result: Vec<(ArrayKey, Value)> = keys
                 .iter()
@@ -90,8 +101,10 @@ pub fn array_combine(args: &[Value]) -> Result<Value, String> {
                     (key, v.clone())
                 })
                 .collect();
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("array_combine() expects both parameters to be arrays".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_combine() expects both parameters to be arrays",
+        )),
     }
 }
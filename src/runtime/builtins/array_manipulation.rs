@@ -1,11 +1,14 @@
 //! Array manipulation functions
 
-use crate::runtime::{ArrayKey, Value};
+use crate::runtime::{ArrayKey, PhpArray, Value};
+use crate::vm::error::NativeError;
 
 /// array_reverse - Return an array with elements in reverse order
-pub fn array_reverse(args: &[Value]) -> Result<Value, String> {
+pub fn array_reverse(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_reverse() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_reverse() expects at least 1 parameter",
+        ));
     }
 
     let preserve_keys = args.get(1).map(|v| v.to_bool()).unwrap_or(false);
@@ -27,45 +30,43 @@ pub fn array_reverse(args: &[Value]) -> Result<Value, String> {
                         (new_key, v.clone())
                     })
                     .collect();
-                Ok(Value::Array(reversed))
+                Ok(Value::Array(reversed.into()))
             }
         }
-        _ => Err("array_reverse() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_reverse() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_merge - Merge one or more arrays
-pub fn array_merge(args: &[Value]) -> Result<Value, String> {
+pub fn array_merge(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_merge() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_merge() expects at least 1 parameter",
+        ));
     }
 
-    let mut result: Vec<(ArrayKey, Value)> = Vec::new();
-    let mut next_int_key: i64 = 0;
-
+    let mut result = PhpArray::new();
     for arg in args {
         match arg {
             Value::Array(arr) => {
                 for (k, v) in arr {
                     match k {
                         ArrayKey::Integer(_) => {
-                            result.push((ArrayKey::Integer(next_int_key), v.clone()));
-                            next_int_key += 1;
+                            result.push(v.clone()).map_err(NativeError::fatal)?
                         }
-                        ArrayKey::String(s) => {
-                            if let Some(pos) = result
-                                .iter()
-                                .position(|(rk, _)| matches!(rk, ArrayKey::String(rs) if rs == s))
-                            {
-                                result[pos] = (k.clone(), v.clone());
-                            } else {
-                                result.push((k.clone(), v.clone()));
-                            }
+                        ArrayKey::String(_) => {
+                            result.insert(k.clone(), v.clone());
                         }
                     }
                 }
             }
-            _ => return Err("array_merge() expects all parameters to be arrays".to_string()),
+            _ => {
+                return Err(NativeError::fatal(
+                    "array_merge() expects all parameters to be arrays",
+                ))
+            }
         }
     }
 
@@ -73,9 +74,11 @@ pub fn array_merge(args: &[Value]) -> Result<Value, String> {
 }
 
 /// range - Create an array containing a range of elements
-pub fn range(args: &[Value]) -> Result<Value, String> {
+pub fn range(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("range() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "range() expects at least 2 parameters",
+        ));
     }
 
     let start = args[0].to_int();
@@ -83,7 +86,9 @@ pub fn range(args: &[Value]) -> Result<Value, String> {
     let step = args.get(2).map(|v| v.to_int()).unwrap_or(1);
 
     if step == 0 {
-        return Err("range(): step exceeds the specified range".to_string());
+        return Err(NativeError::fatal(
+            "range(): step exceeds the specified range",
+        ));
     }
 
     let mut result: Vec<(ArrayKey, Value)> = Vec::new();
@@ -105,13 +110,15 @@ pub fn range(args: &[Value]) -> Result<Value, String> {
         }
     }
 
-    Ok(Value::Array(result))
+    Ok(Value::Array(result.into()))
 }
 
 /// array_unique - Removes duplicate values from an array
-pub fn array_unique(args: &[Value]) -> Result<Value, String> {
+pub fn array_unique(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_unique() expects exactly 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_unique() expects exactly 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -139,8 +146,10 @@ pub fn array_unique(args: &[Value]) -> Result<Value, String> {
                     result.push((ArrayKey::Integer(result.len() as i64), value.clone()));
                 }
             }
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("array_unique() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_unique() expects parameter 1 to be array",
+        )),
     }
 }
@@ -1,13 +1,16 @@
 //! Array randomization functions
 
-use crate::runtime::{ArrayKey, Value};
+use crate::runtime::{random, ArrayKey, Value};
+use crate::vm::error::NativeError;
 
 /// shuffle - Shuffle an array randomly
 ///
 /// Returns true on success, false on failure.
-pub fn shuffle(args: &[Value]) -> Result<Value, String> {
+pub fn shuffle(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("shuffle() expects exactly 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "shuffle() expects exactly 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -15,15 +18,13 @@ pub fn shuffle(args: &[Value]) -> Result<Value, String> {
             let mut values: Vec<(ArrayKey, Value)> =
                 arr.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
 
-            let n = values.len();
-            for i in 0..n {
-                let j = fastrand::usize(0..n);
-                values.swap(i, j);
-            }
+            random::with_rng(|rng| rng.shuffle(&mut values));
 
-            Ok(Value::Array(values))
+            Ok(Value::Array(values.into()))
         }
-        _ => Err("shuffle() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "shuffle() expects parameter 1 to be array",
+        )),
     }
 }
 
@@ -31,44 +32,34 @@ pub fn shuffle(args: &[Value]) -> Result<Value, String> {
 ///
 /// If called without the second parameter, returns a random key.
 /// If called with num_req, returns an array of random keys.
-pub fn array_rand(args: &[Value]) -> Result<Value, String> {
+pub fn array_rand(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("array_rand() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_rand() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
         Value::Array(arr) => {
             if arr.is_empty() {
-                return Err("array_rand(): Array is empty".to_string());
+                return Err(NativeError::fatal("array_rand(): Array is empty"));
             }
 
             let num_keys = args.get(1).map(|v| v.to_int()).unwrap_or(1);
             let num_keys = if num_keys < 1 { 1 } else { num_keys as usize };
             let num_keys = num_keys.min(arr.len());
 
-            use std::time::{SystemTime, UNIX_EPOCH};
-            let seed = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_nanos() as u64)
-                .unwrap_or(0);
-
-            let mut rng = fastrand::Rng::new();
-            rng.seed(seed);
-
             let keys: Vec<ArrayKey> = arr.iter().map(|(k, _)| k.clone()).collect();
 
             if num_keys == 1 {
-                let random_idx = rng.usize(0..keys.len());
+                let random_idx = random::with_rng(|rng| rng.usize(0..keys.len()));
                 Ok(match &keys[random_idx] {
                     ArrayKey::Integer(n) => Value::Integer(*n),
                     ArrayKey::String(s) => Value::String(s.clone()),
                 })
             } else {
                 let mut indices: Vec<usize> = (0..keys.len()).collect();
-                for i in 0..indices.len() {
-                    let j = rng.usize(0..indices.len());
-                    indices.swap(i, j);
-                }
+                random::with_rng(|rng| rng.shuffle(&mut indices));
 
                 let result: Vec<(ArrayKey, Value)> = indices[..num_keys]
                     .iter()
@@ -83,9 +74,11 @@ pub fn array_rand(args: &[Value]) -> Result<Value, String> {
                         )
                     })
                     .collect();
-                Ok(Value::Array(result))
+                Ok(Value::Array(result.into()))
             }
         }
-        _ => Err("array_rand() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_rand() expects parameter 1 to be array",
+        )),
     }
 }
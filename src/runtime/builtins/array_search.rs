@@ -1,11 +1,16 @@
 //! Array search and lookup functions
 
+use crate::runtime::builtins::native::Array;
 use crate::runtime::{ArrayKey, Value};
+use crate::vhp_builtin;
+use crate::vm::error::NativeError;
 
 /// in_array - Checks if a value exists in an array
-pub fn in_array(args: &[Value]) -> Result<Value, String> {
+pub fn in_array(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("in_array() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "in_array() expects at least 2 parameters",
+        ));
     }
 
     let needle = &args[0];
@@ -22,14 +27,18 @@ pub fn in_array(args: &[Value]) -> Result<Value, String> {
             });
             Ok(Value::Bool(found))
         }
-        _ => Err("in_array() expects parameter 2 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "in_array() expects parameter 2 to be array",
+        )),
     }
 }
 
 /// array_search - Searches array for a given value and returns the key
-pub fn array_search(args: &[Value]) -> Result<Value, String> {
+pub fn array_search(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_search() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_search() expects at least 2 parameters",
+        ));
     }
 
     let needle = &args[0];
@@ -52,14 +61,18 @@ pub fn array_search(args: &[Value]) -> Result<Value, String> {
             }
             Ok(Value::Bool(false))
         }
-        _ => Err("array_search() expects parameter 2 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_search() expects parameter 2 to be array",
+        )),
     }
 }
 
 /// array_key_exists - Checks if the given key or index exists in an array
-pub fn array_key_exists(args: &[Value]) -> Result<Value, String> {
+pub fn array_key_exists(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_key_exists() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_key_exists() expects exactly 2 parameters",
+        ));
     }
 
     let key = match &args[0] {
@@ -69,10 +82,30 @@ pub fn array_key_exists(args: &[Value]) -> Result<Value, String> {
     };
 
     match &args[1] {
-        Value::Array(arr) => {
-            let exists = arr.iter().any(|(k, _)| k == &key);
-            Ok(Value::Bool(exists))
-        }
-        _ => Err("array_key_exists() expects parameter 2 to be array".to_string()),
+        Value::Array(arr) => Ok(Value::Bool(arr.contains_key(&key))),
+        _ => Err(NativeError::fatal(
+            "array_key_exists() expects parameter 2 to be array",
+        )),
     }
 }
+
+/// array_is_list - Checks whether the keys are 0, 1, 2... in order
+#[vhp_builtin("array_is_list")]
+pub fn array_is_list(array: Array) -> bool {
+    array
+        .iter()
+        .enumerate()
+        .all(|(i, (key, _))| *key == ArrayKey::Integer(i as i64))
+}
+
+/// array_key_first - Gets the first key of an array, or null if empty
+#[vhp_builtin("array_key_first")]
+pub fn array_key_first(array: Array) -> Option<Value> {
+    array.first().map(|(key, _)| key.to_value())
+}
+
+/// array_key_last - Gets the last key of an array, or null if empty
+#[vhp_builtin("array_key_last")]
+pub fn array_key_last(array: Array) -> Option<Value> {
+    array.last().map(|(key, _)| key.to_value())
+}
@@ -1,16 +1,19 @@
 //! Array set operations (diff and intersect)
 
-use crate::runtime::{ArrayKey, Value};
+use crate::runtime::{ArrayKey, PhpArray, Value};
+use crate::vm::error::NativeError;
 
 /// array_diff - Computes the difference of arrays
-pub fn array_diff(args: &[Value]) -> Result<Value, String> {
+pub fn array_diff(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_diff() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_diff() expects at least 2 parameters",
+        ));
     }
     let mut result: Vec<(ArrayKey, Value)> = Vec::new();
     match &args[0] {
         Value::Array(arr1) => {
-            let arrays: Vec<&Vec<(ArrayKey, Value)>> = args
+            let arrays: Vec<&PhpArray> = args
                 .iter()
                 .skip(1)
                 .filter_map(|a| match a {
@@ -26,21 +29,25 @@ pub fn array_diff(args: &[Value]) -> Result<Value, String> {
                 }
                 result.push((ArrayKey::Integer(result.len() as i64), v.clone()));
             }
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("array_diff() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_diff() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// array_intersect - Computes the intersection of arrays
-pub fn array_intersect(args: &[Value]) -> Result<Value, String> {
+pub fn array_intersect(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("array_intersect() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "array_intersect() expects at least 2 parameters",
+        ));
     }
     let mut result: Vec<(ArrayKey, Value)> = Vec::new();
     match &args[0] {
         Value::Array(arr1) => {
-            let arrays: Vec<&Vec<(ArrayKey, Value)>> = args
+            let arrays: Vec<&PhpArray> = args
                 .iter()
                 .skip(1)
                 .filter_map(|a| match a {
@@ -60,8 +67,10 @@ pub fn array_intersect(args: &[Value]) -> Result<Value, String> {
                     result.push((ArrayKey::Integer(result.len() as i64), v.clone()));
                 }
             }
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("array_intersect() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "array_intersect() expects parameter 1 to be array",
+        )),
     }
 }
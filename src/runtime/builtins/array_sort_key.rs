@@ -1,13 +1,16 @@
 //! Array key-based sorting functions
 
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
 
 /// ksort - Sort an array by keys in ascending order
 ///
 /// Returns true on success, false on failure.
-pub fn ksort(args: &[Value]) -> Result<Value, String> {
+pub fn ksort(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("ksort() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "ksort() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -51,18 +54,22 @@ pub fn ksort(args: &[Value]) -> Result<Value, String> {
                 }
             }
 
-            Ok(Value::Array(pairs))
+            Ok(Value::Array(pairs.into()))
         }
-        _ => Err("ksort() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "ksort() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// krsort - Sort an array by keys in descending order
 ///
 /// Returns true on success, false on failure.
-pub fn krsort(args: &[Value]) -> Result<Value, String> {
+pub fn krsort(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("krsort() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "krsort() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -106,8 +113,10 @@ pub fn krsort(args: &[Value]) -> Result<Value, String> {
                 }
             }
 
-            Ok(Value::Array(pairs))
+            Ok(Value::Array(pairs.into()))
         }
-        _ => Err("krsort() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "krsort() expects parameter 1 to be array",
+        )),
     }
 }
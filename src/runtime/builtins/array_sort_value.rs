@@ -1,6 +1,8 @@
 //! Array value-based sorting functions
 
+use crate::runtime::builtins::locale;
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
 
 fn compare_values(a: &Value, b: &Value) -> std::cmp::Ordering {
     match (a, b) {
@@ -34,9 +36,11 @@ fn compare_values(a: &Value, b: &Value) -> std::cmp::Ordering {
 /// Returns true on success, false on failure.
 ///
 /// PHP equivalent: sort($array, $flags = SORT_REGULAR)
-pub fn sort(args: &[Value]) -> Result<Value, String> {
+pub fn sort(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("sort() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "sort() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -64,6 +68,9 @@ pub fn sort(args: &[Value]) -> Result<Value, String> {
                             .then_with(|| a_str.len().cmp(&b_str.len()))
                     });
                 }
+                5 => {
+                    values.sort_by(|a, b| locale::compare(&a.to_string_val(), &b.to_string_val()));
+                }
                 _ => {
                     values.sort_by(|a, b| compare_values(a, b));
                 }
@@ -74,18 +81,20 @@ pub fn sort(args: &[Value]) -> Result<Value, String> {
                 .enumerate()
                 .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
                 .collect();
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("sort() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal("sort() expects parameter 1 to be array")),
     }
 }
 
 /// rsort - Sort an array in descending order
 ///
 /// Returns true on success, false on failure.
-pub fn rsort(args: &[Value]) -> Result<Value, String> {
+pub fn rsort(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("rsort() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "rsort() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -110,6 +119,9 @@ pub fn rsort(args: &[Value]) -> Result<Value, String> {
                             .then_with(|| b_str.len().cmp(&a_str.len()))
                     });
                 }
+                5 => {
+                    values.sort_by(|a, b| locale::compare(&b.to_string_val(), &a.to_string_val()));
+                }
                 _ => {
                     values.sort_by(|a, b| compare_values(a, b).reverse());
                 }
@@ -120,18 +132,22 @@ pub fn rsort(args: &[Value]) -> Result<Value, String> {
                 .enumerate()
                 .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
                 .collect();
-            Ok(Value::Array(result))
+            Ok(Value::Array(result.into()))
         }
-        _ => Err("rsort() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "rsort() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// asort - Sort an array in ascending order, preserving keys
 ///
 /// Returns true on success, false on failure.
-pub fn asort(args: &[Value]) -> Result<Value, String> {
+pub fn asort(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("asort() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "asort() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -157,23 +173,32 @@ pub fn asort(args: &[Value]) -> Result<Value, String> {
                             .then_with(|| a_str.len().cmp(&b_str.len()))
                     });
                 }
+                5 => {
+                    pairs.sort_by(|(_, a), (_, b)| {
+                        locale::compare(&a.to_string_val(), &b.to_string_val())
+                    });
+                }
                 _ => {
                     pairs.sort_by(|(_, a), (_, b)| compare_values(a, b));
                 }
             }
 
-            Ok(Value::Array(pairs))
+            Ok(Value::Array(pairs.into()))
         }
-        _ => Err("asort() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "asort() expects parameter 1 to be array",
+        )),
     }
 }
 
 /// arsort - Sort an array in descending order, preserving keys
 ///
 /// Returns true on success, false on failure.
-pub fn arsort(args: &[Value]) -> Result<Value, String> {
+pub fn arsort(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("arsort() expects at least 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "arsort() expects at least 1 parameter, 0 given",
+        ));
     }
 
     match &args[0] {
@@ -199,13 +224,20 @@ pub fn arsort(args: &[Value]) -> Result<Value, String> {
                             .then_with(|| b_str.len().cmp(&a_str.len()))
                     });
                 }
+                5 => {
+                    pairs.sort_by(|(_, a), (_, b)| {
+                        locale::compare(&b.to_string_val(), &a.to_string_val())
+                    });
+                }
                 _ => {
                     pairs.sort_by(|(_, a), (_, b)| compare_values(a, b).reverse());
                 }
             }
 
-            Ok(Value::Array(pairs))
+            Ok(Value::Array(pairs.into()))
         }
-        _ => Err("arsort() expects parameter 1 to be array".to_string()),
+        _ => Err(NativeError::fatal(
+            "arsort() expects parameter 1 to be array",
+        )),
     }
 }
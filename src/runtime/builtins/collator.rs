@@ -0,0 +1,267 @@
+//! Locale-aware string comparison (an intl subset)
+//!
+//! Collator compares strings the way dictionaries order them rather than
+//! by bytes: letters first by their base letter, then by accent, then by
+//! case, lowercase first. Spaces sort before punctuation, punctuation
+//! before digits and digits before letters. Accented Latin letters and a
+//! few expansions (`ß`, `æ`, `œ`) are known, as are the letters Spanish
+//! and the Nordic languages sort on their own; other characters sort
+//! after the Latin letters by code point.
+//!
+//! A collator is an object of the built-in `Collator` class whose methods
+//! call these functions with the object first. Without references, the
+//! sorting ones return the sorted array.
+
+use crate::runtime::builtins::locale_data::canonical_locale;
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::{ArrayKey, ObjectInstance, ObjectRef, Value};
+use crate::vm::error::NativeError;
+use std::cmp::Ordering;
+
+/// `Collator::PRIMARY`: base letters only
+pub const PRIMARY: i64 = 0;
+/// `Collator::SECONDARY`: base letters and accents
+pub const SECONDARY: i64 = 1;
+/// `Collator::TERTIARY`: base letters, accents and case, the default
+pub const TERTIARY: i64 = 2;
+
+/// `Collator::SORT_REGULAR`
+pub const SORT_REGULAR: i64 = 0;
+/// `Collator::SORT_STRING`
+pub const SORT_STRING: i64 = 1;
+/// `Collator::SORT_NUMERIC`
+pub const SORT_NUMERIC: i64 = 2;
+
+/// Punctuation and symbols in the order they sort
+const PUNCTUATION: &str = "_-,;:!?.'\"()[]{}@*/\\&#%`^+<=>|~$";
+
+/// Accented letters by accent, with their base letters. The accents are
+/// in the order they sort, after the unaccented letter.
+const ACCENTS: &[(&str, &str)] = &[
+    ("áéíóúýćńśźĺŕ", "aeiouycnszlr"),
+    ("àèìòù", "aeiou"),
+    ("ăğŭ", "agu"),
+    ("âêîôûĉĝĥĵŝŵŷ", "aeioucghjswy"),
+    ("čďěňřšťž", "cdenrstz"),
+    ("åů", "au"),
+    ("äëïöüÿ", "aeiouy"),
+    ("őű", "ou"),
+    ("ãñõ", "ano"),
+    ("żė", "ze"),
+    ("çşţ", "cst"),
+    ("ąęį", "aei"),
+    ("āēīōū", "aeiou"),
+    ("øłđ", "old"),
+];
+
+/// Letters languages sort as letters of their own, in order after the
+/// letter given. Letters grouped together are variants of one letter.
+const TAILORINGS: &[(&[&str], char, &[&str])] = &[
+    (&["es"], 'n', &["ñ"]),
+    (&["sv", "fi"], 'z', &["å", "äæ", "öø"]),
+    (&["da", "nb", "nn", "no"], 'z', &["æä", "øö", "å"]),
+];
+
+/// A collation element: primary, secondary and tertiary weights
+type Element = (u32, u8, u8);
+
+/// The primary weight of a character without accents
+fn primary(c: char) -> u32 {
+    if c.is_whitespace() {
+        return 1;
+    }
+    if let Some(position) = PUNCTUATION.find(c) {
+        return 0x10 + position as u32;
+    }
+    if let Some(digit) = c.to_digit(10) {
+        return 0x100 + digit;
+    }
+    if c.is_ascii_lowercase() {
+        return 0x1000 + (c as u32 - 'a' as u32) * 0x10;
+    }
+    0x10000 + c as u32
+}
+
+/// The base letter of an accented letter and the rank of its accent
+fn decompose(c: char) -> (char, u8) {
+    for (rank, (accented, bases)) in ACCENTS.iter().enumerate() {
+        if let Some(position) = accented.chars().position(|a| a == c) {
+            let base = bases.chars().nth(position).unwrap_or(c);
+            return (base, rank as u8 + 1);
+        }
+    }
+    (c, 0)
+}
+
+/// The collation elements of a string in a language
+fn elements(s: &str, language: &str) -> Vec<Element> {
+    let tailoring = TAILORINGS
+        .iter()
+        .find(|(languages, _, _)| languages.contains(&language));
+    let mut elements = Vec::new();
+    for c in s.chars() {
+        let case = c.is_uppercase() as u8;
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let tailored = tailoring.and_then(|(_, after, letters)| {
+            letters.iter().enumerate().find_map(|(rank, variants)| {
+                let variant = variants.chars().position(|v| v == lower)?;
+                Some((primary(*after) + 1 + rank as u32, variant as u8, case))
+            })
+        });
+        if let Some(element) = tailored {
+            elements.push(element);
+            continue;
+        }
+        let expansion = match lower {
+            'ß' => Some("ss"),
+            'æ' => Some("ae"),
+            'œ' => Some("oe"),
+            _ => None,
+        };
+        if let Some(expansion) = expansion {
+            elements.extend(expansion.chars().map(|e| (primary(e), 0, case + 2)));
+            continue;
+        }
+        let (base, accent) = decompose(lower);
+        elements.push((primary(base), accent, case));
+    }
+    elements
+}
+
+/// Compare two strings in a language up to a strength
+pub(crate) fn collate(a: &str, b: &str, language: &str, strength: i64) -> Ordering {
+    let (a, b) = (elements(a, language), elements(b, language));
+    let primaries = a.iter().map(|e| e.0).cmp(b.iter().map(|e| e.0));
+    let secondaries = || a.iter().map(|e| e.1).cmp(b.iter().map(|e| e.1));
+    let tertiaries = || a.iter().map(|e| e.2).cmp(b.iter().map(|e| e.2));
+    match strength {
+        PRIMARY => primaries,
+        SECONDARY => primaries.then_with(secondaries),
+        _ => primaries.then_with(secondaries).then_with(tertiaries),
+    }
+}
+
+/// The numeric value of a number or numeric string
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(n) => Some(*n as f64),
+        Value::Float(f) => Some(*f),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// The collator argument of a `collator_*()` function
+fn collator_argument<'a>(function: &str, args: &'a [Value]) -> Result<&'a ObjectRef, NativeError> {
+    match args.first() {
+        Some(Value::Object(object)) if object.is_instance_of("Collator") => Ok(object),
+        other => Err(NativeError::type_error(format!(
+            "{}(): Argument #1 ($object) must be of type Collator, {} given",
+            function,
+            other.map_or("null", |value| value.type_name())
+        ))),
+    }
+}
+
+/// A collator's language and strength
+fn settings(collator: &ObjectRef) -> (String, i64) {
+    let locale = collator
+        .get("__locale")
+        .as_ref()
+        .map(Value::to_string_val)
+        .unwrap_or_default();
+    let language = locale.split('_').next().unwrap_or_default().to_string();
+    let strength = collator
+        .get("__strength")
+        .map_or(TERTIARY, |strength| strength.to_int());
+    (language, strength)
+}
+
+/// Compare two values the way a collator sorts them with `flags`
+fn compare(a: &Value, b: &Value, language: &str, strength: i64, flags: i64) -> Ordering {
+    let numbers = match flags {
+        SORT_NUMERIC => Some((a.to_float(), b.to_float())),
+        SORT_STRING => None,
+        _ => numeric(a).zip(numeric(b)),
+    };
+    match numbers {
+        Some((x, y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        None => collate(&a.to_string_val(), &b.to_string_val(), language, strength),
+    }
+}
+
+/// collator_create - Create a collator
+pub fn collator_create(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("collator_create", args.len(), 1, Some(1))?;
+    let mut collator = ObjectInstance::new("Collator".to_string());
+    let locale = canonical_locale(&args[0].to_string_val());
+    collator
+        .properties
+        .insert("__locale".to_string(), Value::String(locale));
+    collator
+        .properties
+        .insert("__strength".to_string(), Value::Integer(TERTIARY));
+    Ok(Value::Object(collator.into()))
+}
+
+/// collator_get_locale - The locale of a collator
+pub fn collator_get_locale(args: &[Value]) -> Result<Value, NativeError> {
+    let collator = collator_argument("collator_get_locale", args)?;
+    Ok(collator.get("__locale").unwrap_or(Value::Bool(false)))
+}
+
+/// collator_get_strength - The strength of a collator
+pub fn collator_get_strength(args: &[Value]) -> Result<Value, NativeError> {
+    let collator = collator_argument("collator_get_strength", args)?;
+    Ok(Value::Integer(settings(collator).1))
+}
+
+/// collator_compare - Compare two strings: -1, 0 or 1
+pub fn collator_compare(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("collator_compare", args.len(), 3, Some(3))?;
+    let collator = collator_argument("collator_compare", args)?;
+    let (language, strength) = settings(collator);
+    let ordering = collate(
+        &args[1].to_string_val(),
+        &args[2].to_string_val(),
+        &language,
+        strength,
+    );
+    Ok(Value::Integer(ordering as i64))
+}
+
+/// collator_sort - Sort the values of an array
+///
+/// Returns the sorted array with its keys renumbered.
+pub fn collator_sort(args: &[Value]) -> Result<Value, NativeError> {
+    let mut entries = sorted_entries("collator_sort", args)?;
+    for (index, entry) in entries.iter_mut().enumerate() {
+        entry.0 = ArrayKey::Integer(index as i64);
+    }
+    Ok(Value::Array(entries.into()))
+}
+
+/// collator_asort - Sort the values of an array, keeping their keys
+///
+/// Returns the sorted array.
+pub fn collator_asort(args: &[Value]) -> Result<Value, NativeError> {
+    Ok(Value::Array(sorted_entries("collator_asort", args)?.into()))
+}
+
+/// The entries of a `collator_*sort()` array in order
+fn sorted_entries(function: &str, args: &[Value]) -> Result<Vec<(ArrayKey, Value)>, NativeError> {
+    check_arity(function, args.len(), 2, Some(3))?;
+    let collator = collator_argument(function, args)?;
+    let Value::Array(entries) = &args[1] else {
+        return Err(NativeError::type_error(format!(
+            "{}(): Argument #2 ($array) must be of type array, {} given",
+            function,
+            args[1].type_name()
+        )));
+    };
+    let (language, strength) = settings(collator);
+    let flags = args.get(2).map_or(SORT_REGULAR, Value::to_int);
+    let mut entries = entries.clone().into_vec();
+    entries.sort_by(|(_, a), (_, b)| compare(a, b, &language, strength, flags));
+    Ok(entries)
+}
@@ -1,6 +1,8 @@
 //! Date formatting functions
 
-use crate::runtime::Value;
+use crate::runtime::builtins::locale;
+use crate::runtime::{clock, Value};
+use crate::vm::error::NativeError;
 use chrono::{DateTime, Datelike, Timelike, Utc};
 
 /// gmdate() - Format GMT/UTC date
@@ -9,27 +11,30 @@ use chrono::{DateTime, Datelike, Timelike, Utc};
 /// using the Greenwich Mean Time (GMT).
 ///
 /// PHP equivalent: gmdate($format, $timestamp)
-pub fn gmdate(args: &[Value]) -> Result<Value, String> {
+pub fn gmdate(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("gmdate() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "gmdate() expects at least 1 parameter",
+        ));
     }
 
     let format = match &args[0] {
         Value::String(s) => s.clone(),
-        _ => return Err("gmdate() expects parameter 1 to be string".to_string()),
+        _ => {
+            return Err(NativeError::fatal(
+                "gmdate() expects parameter 1 to be string",
+            ))
+        }
     };
 
-    let timestamp = args.get(1).map(|v| v.to_int()).unwrap_or_else(|| {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64
-    });
+    let timestamp = args
+        .get(1)
+        .map(|v| v.to_int())
+        .unwrap_or_else(|| clock::since_epoch().as_secs() as i64);
 
     let dt = match DateTime::from_timestamp(timestamp, 0) {
         Some(d) => d,
-        None => return Err("gmdate(): Invalid timestamp".to_string()),
+        None => return Err(NativeError::fatal("gmdate(): Invalid timestamp")),
     };
 
     let formatted = format_gmdate(&format, dt);
@@ -115,30 +120,34 @@ fn format_gmdate(format: &str, dt: DateTime<Utc>) -> String {
 
 /// gmstrftime() - Format date/time according to locale
 ///
-/// Format the time/date according to locale settings.
+/// Format the time/date according to locale settings: the month and
+/// weekday names are LC_TIME's.
 ///
 /// PHP equivalent: gmstrftime($format, $timestamp)
-pub fn gmstrftime(args: &[Value]) -> Result<Value, String> {
+pub fn gmstrftime(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("gmstrftime() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "gmstrftime() expects at least 1 parameter",
+        ));
     }
 
     let format = match &args[0] {
         Value::String(s) => s.clone(),
-        _ => return Err("gmstrftime() expects parameter 1 to be string".to_string()),
+        _ => {
+            return Err(NativeError::fatal(
+                "gmstrftime() expects parameter 1 to be string",
+            ))
+        }
     };
 
-    let timestamp = args.get(1).map(|v| v.to_int()).unwrap_or_else(|| {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64
-    });
+    let timestamp = args
+        .get(1)
+        .map(|v| v.to_int())
+        .unwrap_or_else(|| clock::since_epoch().as_secs() as i64);
 
     let dt = match DateTime::from_timestamp(timestamp, 0) {
         Some(d) => d,
-        None => return Err("gmstrftime(): Invalid timestamp".to_string()),
+        None => return Err(NativeError::fatal("gmstrftime(): Invalid timestamp")),
     };
 
     let formatted = format_gmstrftime(&format, dt);
@@ -164,46 +173,18 @@ fn format_gmstrftime(format: &str, dt: DateTime<Utc>) -> String {
                 'H' => result.push_str(&format!("{:02}", dt.hour())),
                 'M' => result.push_str(&format!("{:02}", dt.minute())),
                 'S' => result.push_str(&format!("{:02}", dt.second())),
-                'A' => {
-                    let weekdays = [
-                        "Sunday",
-                        "Monday",
-                        "Tuesday",
-                        "Wednesday",
-                        "Thursday",
-                        "Friday",
-                        "Saturday",
-                    ];
-                    result.push_str(weekdays[dt.weekday().num_days_from_sunday() as usize]);
-                }
-                'a' => {
-                    let weekday_abbr = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
-                    result.push_str(weekday_abbr[dt.weekday().num_days_from_sunday() as usize]);
-                }
-                'B' => {
-                    let months = [
-                        "January",
-                        "February",
-                        "March",
-                        "April",
-                        "May",
-                        "June",
-                        "July",
-                        "August",
-                        "September",
-                        "October",
-                        "November",
-                        "December",
-                    ];
-                    result.push_str(months[(dt.month0()) as usize]);
-                }
-                'b' => {
-                    let months_abbr = [
-                        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
-                        "Nov", "Dec",
-                    ];
-                    result.push_str(months_abbr[(dt.month0()) as usize]);
-                }
+                'A' => result.push_str(locale::time_name(
+                    true,
+                    false,
+                    dt.weekday().num_days_from_sunday() as usize,
+                )),
+                'a' => result.push_str(locale::time_name(
+                    true,
+                    true,
+                    dt.weekday().num_days_from_sunday() as usize,
+                )),
+                'B' => result.push_str(locale::time_name(false, false, dt.month0() as usize)),
+                'b' => result.push_str(locale::time_name(false, true, dt.month0() as usize)),
                 _ => {
                     result.push('%');
                     result.push(next);
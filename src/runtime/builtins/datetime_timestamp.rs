@@ -1,6 +1,7 @@
 //! Timestamp functions
 
-use crate::runtime::Value;
+use crate::runtime::{clock, Value};
+use crate::vm::error::NativeError;
 use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Timelike, Utc};
 
 /// time() - Current Unix timestamp
@@ -9,17 +10,14 @@ use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Timelike, Utc};
 /// the Unix Epoch (January 1 1970 00:00:00 GMT).
 ///
 /// PHP equivalent: time()
-pub fn time(args: &[Value]) -> Result<Value, String> {
+pub fn time(args: &[Value]) -> Result<Value, NativeError> {
     if !args.is_empty() {
-        return Err("time() expects exactly 0 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "time() expects exactly 0 parameters",
+        ));
     }
 
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let duration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| format!("System time error: {}", e))?;
-
-    Ok(Value::Integer(duration.as_secs() as i64))
+    Ok(Value::Integer(clock::since_epoch().as_secs() as i64))
 }
 
 /// mktime() - Get Unix timestamp from date components
@@ -27,22 +25,22 @@ pub fn time(args: &[Value]) -> Result<Value, String> {
 /// Returns the Unix timestamp corresponding to the arguments given.
 ///
 /// PHP equivalent: mktime($hour, $min, $sec, $month, $day, $year, $is_dst)
-pub fn mktime(args: &[Value]) -> Result<Value, String> {
+pub fn mktime(args: &[Value]) -> Result<Value, NativeError> {
     let hour = args.get(0).map(|v| v.to_int()).unwrap_or(0) as i32;
     let minute = args.get(1).map(|v| v.to_int()).unwrap_or(0) as i32;
     let second = args.get(2).map(|v| v.to_int()).unwrap_or(0) as i32;
     let month = args
         .get(3)
         .map(|v| v.to_int())
-        .unwrap_or_else(|| Utc::now().month() as i64) as i32;
+        .unwrap_or_else(|| now().month() as i64) as i32;
     let day = args
         .get(4)
         .map(|v| v.to_int())
-        .unwrap_or_else(|| Utc::now().day() as i64) as i32;
+        .unwrap_or_else(|| now().day() as i64) as i32;
     let year = args
         .get(5)
         .map(|v| v.to_int())
-        .unwrap_or_else(|| Utc::now().year() as i64) as i32;
+        .unwrap_or_else(|| now().year() as i64) as i32;
 
     if month < 1 || month > 12 {
         return Ok(Value::Bool(false));
@@ -68,23 +66,26 @@ pub fn mktime(args: &[Value]) -> Result<Value, String> {
 /// Parses an English textual datetime description into a Unix timestamp.
 ///
 /// PHP equivalent: strtotime($time, $now)
-pub fn strtotime(args: &[Value]) -> Result<Value, String> {
+pub fn strtotime(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("strtotime() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "strtotime() expects at least 1 parameter",
+        ));
     }
 
     let time_str = match &args[0] {
         Value::String(s) => s.clone(),
-        _ => return Err("strtotime() expects parameter 1 to be string".to_string()),
+        _ => {
+            return Err(NativeError::fatal(
+                "strtotime() expects parameter 1 to be string",
+            ))
+        }
     };
 
-    let base_ts = args.get(1).map(|v| v.to_int()).unwrap_or_else(|| {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64
-    });
+    let base_ts = args
+        .get(1)
+        .map(|v| v.to_int())
+        .unwrap_or_else(|| clock::since_epoch().as_secs() as i64);
 
     let result = parse_time_string(&time_str, base_ts)?;
 
@@ -95,7 +96,7 @@ pub fn strtotime(args: &[Value]) -> Result<Value, String> {
     }
 }
 
-fn parse_time_string(input: &str, base_ts: i64) -> Result<Option<i64>, String> {
+fn parse_time_string(input: &str, base_ts: i64) -> Result<Option<i64>, NativeError> {
     let trimmed = input.trim();
 
     if trimmed.is_empty() {
@@ -206,3 +207,8 @@ fn parse_relative_time(input: &str) -> Option<(bool, i64, String)> {
 
     Some((sign, num, unit.to_string()))
 }
+
+/// The current time, read through the runtime clock
+fn now() -> DateTime<Utc> {
+    DateTime::from_timestamp(clock::since_epoch().as_secs() as i64, 0).unwrap_or_default()
+}
@@ -0,0 +1,435 @@
+//! Character encoding conversion: iconv() and mb_convert_encoding()
+//!
+//! Strings are UTF-8 text, so text in another encoding is held as a byte
+//! string: one character per byte, U+0000 to U+00FF, the way `chr()`
+//! builds them. Converting to UTF-8 gives text again. UTF-8, ISO-8859-1,
+//! Windows-1252, ASCII and UTF-16 (big or little endian) are supported.
+
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
+
+/// A supported encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Ascii,
+    Latin1,
+    Windows1252,
+    /// UTF-16 in the order a byte order mark gives, else big endian. On
+    /// output iconv writes a mark and little endian, mbstring big endian.
+    Utf16,
+    Utf16Be,
+    Utf16Le,
+}
+
+/// The encoding an encoding name or alias names
+fn encoding(name: &str) -> Option<Encoding> {
+    let name = name.trim().to_ascii_uppercase().replace('_', "-");
+    Some(match name.as_str() {
+        "UTF-8" | "UTF8" => Encoding::Utf8,
+        "ASCII" | "US-ASCII" => Encoding::Ascii,
+        "ISO-8859-1" | "ISO8859-1" | "LATIN1" | "LATIN-1" => Encoding::Latin1,
+        "WINDOWS-1252" | "CP1252" => Encoding::Windows1252,
+        "UTF-16" | "UTF16" => Encoding::Utf16,
+        "UTF-16BE" => Encoding::Utf16Be,
+        "UTF-16LE" => Encoding::Utf16Le,
+        _ => return None,
+    })
+}
+
+/// Windows-1252's characters for bytes 0x80 to 0x9F; the five unassigned
+/// bytes have none
+const WINDOWS_1252: [Option<char>; 32] = [
+    Some('€'),
+    None,
+    Some('‚'),
+    Some('ƒ'),
+    Some('„'),
+    Some('…'),
+    Some('†'),
+    Some('‡'),
+    Some('ˆ'),
+    Some('‰'),
+    Some('Š'),
+    Some('‹'),
+    Some('Œ'),
+    None,
+    Some('Ž'),
+    None,
+    None,
+    Some('‘'),
+    Some('’'),
+    Some('“'),
+    Some('”'),
+    Some('•'),
+    Some('–'),
+    Some('—'),
+    Some('˜'),
+    Some('™'),
+    Some('š'),
+    Some('›'),
+    Some('œ'),
+    None,
+    Some('ž'),
+    Some('Ÿ'),
+];
+
+/// Spellings `//TRANSLIT` falls back to for characters an encoding lacks
+const TRANSLITERATIONS: &[(char, &str)] = &[
+    ('‘', "'"),
+    ('’', "'"),
+    ('‚', "'"),
+    ('“', "\""),
+    ('”', "\""),
+    ('„', "\""),
+    ('–', "-"),
+    ('—', "-"),
+    ('…', "..."),
+    ('•', "o"),
+    ('€', "EUR"),
+    ('£', "GBP"),
+    ('©', "(C)"),
+    ('®', "(R)"),
+    ('™', "(TM)"),
+    ('«', "<<"),
+    ('»', ">>"),
+    ('ß', "ss"),
+    ('æ', "ae"),
+    ('Æ', "AE"),
+    ('œ', "oe"),
+    ('Œ', "OE"),
+    ('ø', "o"),
+    ('Ø', "O"),
+    ('ł', "l"),
+    ('Ł', "L"),
+    ('\u{a0}', " "),
+];
+
+/// Base letters of accented Latin letters, for `//TRANSLIT`
+const ACCENTED: &[(&str, char)] = &[
+    ("àáâãäåāăą", 'a'),
+    ("ÀÁÂÃÄÅĀĂĄ", 'A'),
+    ("çćĉċč", 'c'),
+    ("ÇĆĈĊČ", 'C'),
+    ("ďđ", 'd'),
+    ("ĎĐ", 'D'),
+    ("èéêëēĕėęě", 'e'),
+    ("ÈÉÊËĒĔĖĘĚ", 'E'),
+    ("ĝğġģ", 'g'),
+    ("ĜĞĠĢ", 'G'),
+    ("ìíîïĩīĭįı", 'i'),
+    ("ÌÍÎÏĨĪĬĮİ", 'I'),
+    ("ñńņň", 'n'),
+    ("ÑŃŅŇ", 'N'),
+    ("òóôõöōŏő", 'o'),
+    ("ÒÓÔÕÖŌŎŐ", 'O'),
+    ("ŕŗř", 'r'),
+    ("ŔŖŘ", 'R'),
+    ("śŝşš", 's'),
+    ("ŚŜŞŠ", 'S'),
+    ("ţť", 't'),
+    ("ŢŤ", 'T'),
+    ("ùúûüũūŭůűų", 'u'),
+    ("ÙÚÛÜŨŪŬŮŰŲ", 'U'),
+    ("ýÿŷ", 'y'),
+    ("ÝŸŶ", 'Y'),
+    ("źżž", 'z'),
+    ("ŹŻŽ", 'Z'),
+];
+
+/// The bytes of a string in an encoding: its UTF-8 for UTF-8 text, else
+/// one byte per character, as `chr()` gives them
+fn bytes(s: &str, from: Encoding) -> Vec<u8> {
+    if from == Encoding::Utf8 {
+        return s.as_bytes().to_vec();
+    }
+    let mut bytes = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        match u8::try_from(c as u32) {
+            Ok(byte) => bytes.push(byte),
+            Err(_) => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    bytes
+}
+
+/// The characters in bytes of an encoding; `None` marks an invalid byte
+/// sequence
+fn decode(bytes: &[u8], from: Encoding) -> Vec<Option<char>> {
+    match from {
+        Encoding::Utf8 => {
+            let mut chars = Vec::new();
+            for chunk in bytes.utf8_chunks() {
+                chars.extend(chunk.valid().chars().map(Some));
+                if !chunk.invalid().is_empty() {
+                    chars.push(None);
+                }
+            }
+            chars
+        }
+        Encoding::Ascii => bytes
+            .iter()
+            .map(|&b| b.is_ascii().then_some(b as char))
+            .collect(),
+        Encoding::Latin1 => bytes.iter().map(|&b| Some(b as char)).collect(),
+        Encoding::Windows1252 => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9f => WINDOWS_1252[b as usize - 0x80],
+                _ => Some(b as char),
+            })
+            .collect(),
+        Encoding::Utf16 | Encoding::Utf16Be | Encoding::Utf16Le => {
+            let (mut bytes, mut big_endian) = (bytes, from != Encoding::Utf16Le);
+            if from == Encoding::Utf16 {
+                match bytes {
+                    [0xfe, 0xff, rest @ ..] => bytes = rest,
+                    [0xff, 0xfe, rest @ ..] => (bytes, big_endian) = (rest, false),
+                    _ => {}
+                }
+            }
+            let units = bytes.chunks(2).map(|pair| match (pair, big_endian) {
+                ([high, low], true) | ([low, high], false) => {
+                    Some(u16::from_be_bytes([*high, *low]))
+                }
+                _ => None,
+            });
+            let mut chars = Vec::new();
+            let mut units = units.peekable();
+            while let Some(unit) = units.next() {
+                let Some(unit) = unit else {
+                    chars.push(None);
+                    continue;
+                };
+                let pair = match (unit, units.peek()) {
+                    (0xd800..=0xdbff, Some(Some(low @ 0xdc00..=0xdfff))) => Some(*low),
+                    _ => None,
+                };
+                if pair.is_some() {
+                    units.next();
+                }
+                let decoded = char::decode_utf16(std::iter::once(unit).chain(pair)).next();
+                chars.push(decoded.and_then(Result::ok));
+            }
+            chars
+        }
+    }
+}
+
+/// A character in an encoding, as bytes; `None` if the encoding lacks it
+fn encode_char(c: char, to: Encoding, out: &mut Vec<u8>) -> Option<()> {
+    let code = c as u32;
+    match to {
+        Encoding::Utf8 => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        Encoding::Ascii if code < 0x80 => out.push(code as u8),
+        Encoding::Latin1 if code <= 0xff => out.push(code as u8),
+        Encoding::Windows1252 => {
+            let byte = match code {
+                0x80..=0x9f => return None,
+                0..=0xff => code as u8,
+                _ => 0x80 + WINDOWS_1252.iter().position(|m| *m == Some(c))? as u8,
+            };
+            out.push(byte);
+        }
+        Encoding::Utf16 | Encoding::Utf16Be | Encoding::Utf16Le => {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                let pair = if to == Encoding::Utf16Le {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                };
+                out.extend_from_slice(&pair);
+            }
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// The spelling `//TRANSLIT` gives a character
+fn transliterate(c: char) -> String {
+    if let Some((_, spelling)) = TRANSLITERATIONS.iter().find(|(t, _)| *t == c) {
+        return spelling.to_string();
+    }
+    ACCENTED
+        .iter()
+        .find(|(accented, _)| accented.contains(c))
+        .map_or_else(|| "?".to_string(), |(_, base)| base.to_string())
+}
+
+/// Bytes of an encoding as a string: UTF-8 as text, others as a byte
+/// string
+fn string(bytes: Vec<u8>, to: Encoding) -> String {
+    if to == Encoding::Utf8 {
+        return String::from_utf8(bytes).unwrap_or_default();
+    }
+    bytes.into_iter().map(char::from).collect()
+}
+
+/// Convert a string between encodings; `None` if the input is invalid
+/// or has a character the target lacks, unless `substitute` replaces
+/// them. With `translit` missing characters are spelled with others.
+fn convert(
+    s: &str,
+    from: Encoding,
+    to: Encoding,
+    translit: bool,
+    substitute: Option<&str>,
+) -> Option<String> {
+    let mut out = Vec::new();
+    for c in decode(&bytes(s, from), from) {
+        let Some(c) = c else {
+            match substitute {
+                Some(substitute) => substitute.chars().for_each(|s| {
+                    encode_char(s, to, &mut out);
+                }),
+                None => return None,
+            }
+            continue;
+        };
+        if encode_char(c, to, &mut out).is_some() {
+            continue;
+        }
+        let replacement = match (translit, substitute) {
+            (true, _) => transliterate(c),
+            (false, Some(substitute)) => substitute.to_string(),
+            (false, None) => return None,
+        };
+        for r in replacement.chars() {
+            if encode_char(r, to, &mut out).is_none() {
+                encode_char('?', to, &mut out);
+            }
+        }
+    }
+    Some(string(out, to))
+}
+
+/// Why iconv() gave false
+pub enum IconvFailure {
+    /// An encoding it doesn't know, raised as a warning
+    WrongEncoding(String, String),
+    /// Input it couldn't convert, raised as a notice
+    IllegalCharacter,
+}
+
+impl IconvFailure {
+    pub fn message(&self) -> String {
+        match self {
+            IconvFailure::WrongEncoding(from, to) => format!(
+                "iconv(): Wrong encoding, conversion from \"{}\" to \"{}\" is not allowed",
+                from, to
+            ),
+            IconvFailure::IllegalCharacter => {
+                "iconv(): Detected an illegal character in input string".to_string()
+            }
+        }
+    }
+}
+
+/// iconv - Convert a string from one encoding to another
+///
+/// `//TRANSLIT` after the target encoding spells characters it lacks with
+/// similar ones, and `//IGNORE` drops what can't be converted.
+pub fn iconv(args: &[Value]) -> Result<Result<Value, IconvFailure>, NativeError> {
+    check_arity("iconv", args.len(), 3, Some(3))?;
+    let from_name = args[0].to_string_val();
+    let to_name = args[1].to_string_val();
+    let mut parts = to_name.split("//");
+    let target = parts.next().unwrap_or_default();
+    let (mut translit, mut ignore) = (false, false);
+    for flag in parts {
+        match flag.to_ascii_uppercase().as_str() {
+            "TRANSLIT" => translit = true,
+            "IGNORE" => ignore = true,
+            _ => {}
+        }
+    }
+    let (Some(from), Some(to)) = (encoding(&from_name), encoding(target)) else {
+        return Ok(Err(IconvFailure::WrongEncoding(from_name, to_name)));
+    };
+    // iconv's UTF-16 is little endian after a byte order mark
+    let (to, mark) = match to {
+        Encoding::Utf16 => (Encoding::Utf16Le, "\u{ff}\u{fe}"),
+        to => (to, ""),
+    };
+    let substitute = ignore.then_some("");
+    Ok(
+        match convert(&args[2].to_string_val(), from, to, translit, substitute) {
+            Some(converted) => Ok(Value::String(format!("{}{}", mark, converted))),
+            None => Err(IconvFailure::IllegalCharacter),
+        },
+    )
+}
+
+/// The encodings `mb_convert_encoding()` may convert from: a name, a
+/// comma-separated list or an array of names
+fn from_encodings(value: Option<&Value>) -> Result<Vec<Encoding>, NativeError> {
+    let names: Vec<String> = match value {
+        None | Some(Value::Null) => return Ok(vec![Encoding::Utf8]),
+        Some(Value::Array(names)) => names.iter().map(|(_, n)| n.to_string_val()).collect(),
+        Some(names) => names
+            .to_string_val()
+            .split(',')
+            .map(str::to_string)
+            .collect(),
+    };
+    names
+        .iter()
+        .map(|name| {
+            encoding(name).ok_or_else(|| {
+                NativeError::value_error(format!(
+                    "mb_convert_encoding(): Argument #3 ($from_encoding) contains invalid encoding \"{}\"",
+                    name.trim()
+                ))
+            })
+        })
+        .collect()
+}
+
+/// A string converted by `mb_convert_encoding()`: from the first of the
+/// encodings it is valid in, or the first one with invalid input
+/// replaced by `?`
+fn mb_convert(s: &str, to: Encoding, from: &[Encoding]) -> String {
+    let valid = from
+        .iter()
+        .find(|&&f| decode(&bytes(s, f), f).iter().all(Option::is_some));
+    let from = valid.or(from.first()).copied().unwrap_or(Encoding::Utf8);
+    convert(s, from, to, false, Some("?")).unwrap_or_default()
+}
+
+/// mb_convert_encoding - Convert a string, or the keys and values of an
+/// array, from one encoding to another
+///
+/// Characters the target encoding lacks and invalid input become `?`.
+pub fn mb_convert_encoding(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("mb_convert_encoding", args.len(), 2, Some(3))?;
+    let to_name = args[1].to_string_val();
+    let to = encoding(&to_name).ok_or_else(|| {
+        NativeError::value_error(format!(
+            "mb_convert_encoding(): Argument #2 ($to_encoding) must be a valid encoding, \"{}\" given",
+            to_name
+        ))
+    })?;
+    let from = from_encodings(args.get(2))?;
+    Ok(convert_value(&args[0], to, &from))
+}
+
+fn convert_value(value: &Value, to: Encoding, from: &[Encoding]) -> Value {
+    match value {
+        Value::Array(entries) => Value::Array(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        ArrayKey::String(k) => ArrayKey::String(mb_convert(k, to, from)),
+                        key => key.clone(),
+                    };
+                    (key, convert_value(value, to, from))
+                })
+                .collect(),
+        ),
+        Value::String(s) => Value::String(mb_convert(s, to, from)),
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,64 @@
+//! Environment variable functions
+//!
+//! WebAssembly has no process environment: there `$_ENV` is empty,
+//! `getenv()` finds nothing and `putenv()` fails.
+
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
+
+/// Build the $_ENV array from the current process environment
+pub fn env_array() -> Value {
+    let mut vars: Vec<(String, String)> = if cfg!(target_arch = "wasm32") {
+        Vec::new()
+    } else {
+        std::env::vars().collect()
+    };
+    vars.sort();
+    Value::Array(
+        vars.into_iter()
+            .map(|(k, v)| (ArrayKey::String(k), Value::String(v)))
+            .collect(),
+    )
+}
+
+/// getenv - Gets the value of a single or all environment variables
+pub fn getenv(args: &[Value]) -> Result<Value, NativeError> {
+    match args.first() {
+        None | Some(Value::Null) => Ok(env_array()),
+        Some(_) if cfg!(target_arch = "wasm32") => Ok(Value::Bool(false)),
+        Some(name) => Ok(std::env::var(name.to_string_val())
+            .map(Value::String)
+            .unwrap_or(Value::Bool(false))),
+    }
+}
+
+/// putenv - Sets the value of an environment variable
+///
+/// Accepts "NAME=value" to set and "NAME" to unset.
+pub fn putenv(args: &[Value]) -> Result<Value, NativeError> {
+    if args.is_empty() {
+        return Err(NativeError::argument_count_error(
+            "putenv() expects exactly 1 parameter, 0 given",
+        ));
+    }
+    let assignment = args[0].to_string_val();
+    let name = assignment.split('=').next().unwrap_or_default();
+    if name.is_empty() {
+        return Err(NativeError::value_error(
+            "putenv(): Argument #1 ($assignment) must have a valid syntax",
+        ));
+    }
+    if assignment.contains('\0') {
+        return Err(NativeError::value_error(
+            "putenv(): Argument #1 ($assignment) must not contain any null bytes",
+        ));
+    }
+    if cfg!(target_arch = "wasm32") {
+        return Ok(Value::Bool(false));
+    }
+    match assignment.split_once('=') {
+        Some((name, value)) => std::env::set_var(name, value),
+        None => std::env::remove_var(name),
+    }
+    Ok(Value::Bool(true))
+}
@@ -0,0 +1,117 @@
+//! var_export built-in function
+//!
+//! Scalars are written with the source printer's literal helpers, so the
+//! output reads back as the same value.
+
+use crate::printer::{float_literal, quote_string};
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
+use std::io::Write;
+
+/// var_export - Outputs or returns a parsable string representation of a variable
+pub fn var_export<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, NativeError> {
+    if args.is_empty() {
+        return Err(NativeError::argument_count_error(
+            "var_export() expects at least 1 parameter",
+        ));
+    }
+    let return_output = args.len() >= 2 && args[1].to_bool();
+
+    let mut out = String::new();
+    export_value(&mut out, &args[0], 0, &mut Vec::new());
+
+    if return_output {
+        Ok(Value::String(out))
+    } else {
+        write!(output, "{}", out)?;
+        Ok(Value::Null)
+    }
+}
+
+/// `objects` are the ids of the objects being exported, around `value`
+fn export_value(out: &mut String, value: &Value, indent: usize, objects: &mut Vec<usize>) {
+    let prefix = "  ".repeat(indent);
+    match value {
+        Value::Reference(r) => export_value(out, &r.get(), indent, objects),
+        Value::Null => out.push_str("NULL"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Integer(n) => out.push_str(&n.to_string()),
+        Value::Float(n) => out.push_str(&float_literal(*n)),
+        Value::String(s) => out.push_str(&quote_string(s)),
+        Value::Array(arr) => {
+            out.push_str("array (\n");
+            for (key, val) in arr {
+                let key = match key {
+                    ArrayKey::Integer(n) => n.to_string(),
+                    ArrayKey::String(s) => quote_string(s),
+                };
+                export_entry(
+                    out,
+                    &format!("{}  {}", prefix, key),
+                    val,
+                    indent + 1,
+                    objects,
+                );
+            }
+            out.push_str(&format!("{})", prefix));
+        }
+        // PHP can't export an object inside itself either
+        Value::Object(obj) if objects.contains(&obj.id()) => out.push_str("NULL"),
+        Value::Object(obj) => {
+            let properties = obj.properties();
+            let mut properties: Vec<_> = properties.iter().collect();
+            properties.sort_by(|a, b| a.0.cmp(b.0));
+            if obj.class_name().eq_ignore_ascii_case("stdClass") {
+                out.push_str("(object) array(\n");
+            } else {
+                out.push_str(&format!("\\{}::__set_state(array(\n", obj.class_name()));
+            }
+            objects.push(obj.id());
+            for (name, val) in properties {
+                let key = format!("{}   {}", prefix, quote_string(name));
+                export_entry(out, &key, val, indent + 1, objects);
+            }
+            objects.pop();
+            if obj.class_name().eq_ignore_ascii_case("stdClass") {
+                out.push_str(&format!("{})", prefix));
+            } else {
+                out.push_str(&format!("{}))", prefix));
+            }
+        }
+        Value::EnumCase {
+            enum_name,
+            case_name,
+            ..
+        } => out.push_str(&format!("\\{}::{}", enum_name, case_name)),
+        Value::Exception(exc) => out.push_str(&format!(
+            "\\{}::__set_state(array(\n{}   'message' => {},\n{}   'code' => {},\n{}))",
+            exc.class_name,
+            prefix,
+            quote_string(&exc.message),
+            prefix,
+            exc.code,
+            prefix
+        )),
+        Value::Closure(_) => out.push_str("\\Closure::__set_state(array(\n))"),
+        Value::Fiber(_) => out.push_str("\\Fiber::__set_state(array(\n))"),
+        Value::Generator(_) => out.push_str("\\Generator::__set_state(array(\n))"),
+    }
+}
+
+/// One `key => value,` line; arrays and objects start on the next line
+fn export_entry(
+    out: &mut String,
+    key: &str,
+    value: &Value,
+    indent: usize,
+    objects: &mut Vec<usize>,
+) {
+    out.push_str(key);
+    out.push_str(" => ");
+    if matches!(value, Value::Array(_) | Value::Object(_)) {
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent));
+    }
+    export_value(out, value, indent, objects);
+    out.push_str(",\n");
+}
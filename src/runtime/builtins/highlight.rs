@@ -0,0 +1,137 @@
+//! highlight_string and the HTML highlighting highlight_file shares
+//!
+//! The source is split with `lexer::source_tokens` and each run of tokens
+//! of one kind is wrapped in a `<span>` of the color its `highlight.*`
+//! directive gives, as PHP 8.3 does: inside `<pre><code>`, with the
+//! newlines and spaces as they are. Inline HTML goes without a span and
+//! whitespace keeps the color of the token before it.
+
+use crate::lexer::source_tokens::{self, SourceToken};
+use crate::runtime::builtins::ini::get_ini_value;
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::Value;
+use crate::vm::error::NativeError;
+use std::io::Write;
+
+/// The `highlight.*` directives and their default colors
+pub const DIRECTIVES: &[(&str, &str)] = &[
+    ("highlight.comment", "#FF8000"),
+    ("highlight.default", "#0000BB"),
+    ("highlight.html", "#000000"),
+    ("highlight.keyword", "#007700"),
+    ("highlight.string", "#DD0000"),
+];
+
+/// Which directive colors a token
+#[derive(Clone, Copy, PartialEq)]
+enum Color {
+    Comment,
+    Default,
+    Html,
+    Keyword,
+    String,
+}
+
+impl Color {
+    fn value(self) -> String {
+        let (directive, default) = DIRECTIVES[self as usize];
+        get_ini_value(directive).unwrap_or_else(|| default.to_string())
+    }
+}
+
+/// The color of a token, or None for whitespace, which takes the color
+/// of the one before
+fn color(token: &SourceToken) -> Option<Color> {
+    let Some(name) = token.name else {
+        return Some(match token.text.as_str() {
+            "\"" => Color::String,
+            _ => Color::Keyword,
+        });
+    };
+    Some(match name {
+        "T_WHITESPACE" => return None,
+        "T_INLINE_HTML" => Color::Html,
+        "T_COMMENT" | "T_DOC_COMMENT" => Color::Comment,
+        "T_CONSTANT_ENCAPSED_STRING" | "T_ENCAPSED_AND_WHITESPACE" => Color::String,
+        // The tags, the magic constants and the tokens with a value
+        "T_OPEN_TAG"
+        | "T_OPEN_TAG_WITH_ECHO"
+        | "T_CLOSE_TAG"
+        | "T_LINE"
+        | "T_FILE"
+        | "T_DIR"
+        | "T_TRAIT_C"
+        | "T_METHOD_C"
+        | "T_FUNC_C"
+        | "T_NS_C"
+        | "T_CLASS_C"
+        | "T_STRING"
+        | "T_VARIABLE"
+        | "T_LNUMBER"
+        | "T_DNUMBER"
+        | "T_NAME_QUALIFIED"
+        | "T_NAME_FULLY_QUALIFIED"
+        | "T_NAME_RELATIVE"
+        | "T_STRING_VARNAME"
+        | "T_NUM_STRING" => Color::Default,
+        _ => Color::Keyword,
+    })
+}
+
+/// `text` with `<`, `>` and `&` escaped
+fn escape(text: &str, html: &mut String) {
+    for c in text.chars() {
+        match c {
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '&' => html.push_str("&amp;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+/// PHP source as highlighted HTML
+pub fn highlight(source: &str) -> String {
+    let mut html = format!("<pre><code style=\"color: {}\">", Color::Html.value());
+    let mut last = Color::Html;
+    for token in source_tokens::tokenize(source) {
+        if let Some(next) = color(&token).filter(|next| *next != last) {
+            if last != Color::Html {
+                html.push_str("</span>");
+            }
+            if next != Color::Html {
+                html.push_str(&format!("<span style=\"color: {}\">", next.value()));
+            }
+            last = next;
+        }
+        escape(&token.text, &mut html);
+    }
+    if last != Color::Html {
+        html.push_str("</span>");
+    }
+    html.push_str("</code></pre>");
+    html
+}
+
+/// Write highlighted HTML, or return it when `$return` is true
+pub fn output_highlighted<W: Write>(
+    output: &mut W,
+    source: &str,
+    return_output: bool,
+) -> Result<Value, NativeError> {
+    let html = highlight(source);
+    if return_output {
+        return Ok(Value::String(html));
+    }
+    write!(output, "{}", html)?;
+    Ok(Value::Bool(true))
+}
+
+/// highlight_string - Syntax highlighting of a string of PHP
+///
+/// PHP equivalent: highlight_string($string, $return = false)
+pub fn highlight_string<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("highlight_string", args.len(), 1, Some(2))?;
+    let return_output = args.get(1).is_some_and(Value::to_bool);
+    output_highlighted(output, &args[0].to_string_val(), return_output)
+}
@@ -0,0 +1,185 @@
+//! INI configuration functions
+//!
+//! This module keeps the engine's runtime configuration directives
+//! (error_reporting, display_errors, memory_limit, ...) and exposes
+//! ini_get/ini_set/ini_restore and error_reporting to scripts, and loads
+//! directives from INI files such as `vhp.ini`.
+
+use crate::runtime::builtins::highlight;
+use crate::runtime::builtins::ini_parser::{self, IniError, ScannerMode};
+use crate::runtime::Value;
+use crate::vm::error::NativeError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use thread_local::ThreadLocal;
+
+/// E_ALL bitmask reported by error_reporting() by default
+pub const E_ALL: i64 = 32767;
+
+/// The error level constants, which INI values may combine
+/// (`error_reporting = E_ALL & ~E_NOTICE`)
+const ERROR_CONSTANTS: &[(&str, i64)] = &[
+    ("E_ERROR", 1),
+    ("E_WARNING", 2),
+    ("E_PARSE", 4),
+    ("E_NOTICE", 8),
+    ("E_CORE_ERROR", 16),
+    ("E_CORE_WARNING", 32),
+    ("E_COMPILE_ERROR", 64),
+    ("E_COMPILE_WARNING", 128),
+    ("E_USER_ERROR", 256),
+    ("E_USER_WARNING", 512),
+    ("E_USER_NOTICE", 1024),
+    ("E_STRICT", 2048),
+    ("E_RECOVERABLE_ERROR", 4096),
+    ("E_DEPRECATED", 8192),
+    ("E_USER_DEPRECATED", 16384),
+    ("E_ALL", E_ALL),
+];
+
+/// The value of an error level constant
+pub fn error_constant(name: &str) -> Option<Value> {
+    ERROR_CONSTANTS
+        .iter()
+        .find(|(constant, _)| *constant == name)
+        .map(|(_, level)| Value::Integer(*level))
+}
+
+/// Directives only the host sets (see `vm::sandbox` and `vm::stats`);
+/// ini_set() refuses them
+const SYSTEM_DIRECTIVES: &[&str] = &["disable_functions", "open_basedir", "vhp.stats"];
+
+lazy_static::lazy_static! {
+    /// The directives, per thread like the script reading them
+    static ref INI_SETTINGS: ThreadLocal<Mutex<HashMap<String, String>>> = ThreadLocal::new();
+}
+
+/// This thread's directives
+fn settings() -> &'static Mutex<HashMap<String, String>> {
+    INI_SETTINGS.get_or(|| Mutex::new(default_settings()))
+}
+
+/// Built-in directives and their default values
+fn default_settings() -> HashMap<String, String> {
+    [
+        ("error_reporting", E_ALL.to_string()),
+        ("display_errors", "1".to_string()),
+        ("log_errors", "0".to_string()),
+        ("memory_limit", "128M".to_string()),
+        ("max_execution_time", "0".to_string()),
+        ("precision", "14".to_string()),
+        ("serialize_precision", "-1".to_string()),
+        ("date.timezone", "UTC".to_string()),
+        ("default_charset", "UTF-8".to_string()),
+        ("include_path", ".".to_string()),
+        ("disable_functions", String::new()),
+        ("open_basedir", String::new()),
+        ("vhp.stats", "0".to_string()),
+        ("vhp.deprecation.dynamic_properties", "1".to_string()),
+        ("vhp.deprecation.implicit_nullable", "1".to_string()),
+        ("vhp.errors_as_exceptions", "0".to_string()),
+    ]
+    .into_iter()
+    .chain(
+        highlight::DIRECTIVES
+            .iter()
+            .map(|(name, color)| (*name, color.to_string())),
+    )
+    .map(|(k, v)| (k.to_string(), v))
+    .collect()
+}
+
+/// Reset all directives to their defaults (useful for testing)
+pub fn reset_ini_settings() {
+    let mut settings = settings().lock().unwrap();
+    *settings = default_settings();
+}
+
+/// Get the current value of a directive
+pub fn get_ini_value(name: &str) -> Option<String> {
+    settings().lock().unwrap().get(name).cloned()
+}
+
+/// Set a directive, returning its previous value (None if it was unknown)
+pub fn set_ini_value(name: &str, value: &str) -> Option<String> {
+    settings()
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), value.to_string())
+}
+
+/// ini_get - Gets the value of a configuration option
+pub fn ini_get(args: &[Value]) -> Result<Value, NativeError> {
+    if args.is_empty() {
+        return Err(NativeError::argument_count_error(
+            "ini_get() expects exactly 1 parameter, 0 given",
+        ));
+    }
+    let name = args[0].to_string_val();
+    Ok(get_ini_value(&name)
+        .map(Value::String)
+        .unwrap_or(Value::Bool(false)))
+}
+
+/// ini_set - Sets the value of a configuration option
+///
+/// Returns the old value on success, false if the directive is unknown
+/// or only the host may set it.
+pub fn ini_set(args: &[Value]) -> Result<Value, NativeError> {
+    if args.len() < 2 {
+        return Err(NativeError::argument_count_error(format!(
+            "ini_set() expects exactly 2 parameters, {} given",
+            args.len()
+        )));
+    }
+    let name = args[0].to_string_val();
+    if get_ini_value(&name).is_none() || SYSTEM_DIRECTIVES.contains(&name.as_str()) {
+        return Ok(Value::Bool(false));
+    }
+    let value = args[1].to_string_val();
+    Ok(set_ini_value(&name, &value)
+        .map(Value::String)
+        .unwrap_or(Value::Bool(false)))
+}
+
+/// ini_restore - Restores the value of a configuration option to its default
+pub fn ini_restore(args: &[Value]) -> Result<Value, NativeError> {
+    if args.is_empty() {
+        return Err(NativeError::argument_count_error(
+            "ini_restore() expects exactly 1 parameter, 0 given",
+        ));
+    }
+    let name = args[0].to_string_val();
+    if SYSTEM_DIRECTIVES.contains(&name.as_str()) {
+        return Ok(Value::Null);
+    }
+    if let Some(default) = default_settings().get(&name) {
+        set_ini_value(&name, default);
+    }
+    Ok(Value::Null)
+}
+
+/// error_reporting - Sets which PHP errors are reported
+///
+/// Returns the old error_reporting level.
+pub fn error_reporting(args: &[Value]) -> Result<Value, NativeError> {
+    let old = get_ini_value("error_reporting")
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(E_ALL);
+    if let Some(level) = args.first() {
+        if !matches!(level, Value::Null) {
+            set_ini_value("error_reporting", &level.to_int().to_string());
+        }
+    }
+    Ok(Value::Integer(old))
+}
+
+/// Set the directives of an INI file's text, sections ignored, returning
+/// how many were set
+pub fn load_ini(source: &str) -> Result<usize, IniError> {
+    let entries = ini_parser::parse(source, false, ScannerMode::Normal, &error_constant)?;
+    for (name, value) in &entries {
+        set_ini_value(&name.to_string(), &value.to_string_val());
+    }
+    Ok(entries.len())
+}
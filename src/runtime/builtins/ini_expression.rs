@@ -0,0 +1,83 @@
+//! Constant expressions in INI values, like `E_ALL & ~E_NOTICE`
+
+use crate::runtime::Value;
+
+pub(crate) fn is_name(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// An integer expression like `E_ALL & ~E_NOTICE`: `|` binds loosest,
+/// then `^`, then `&`, with `~` and `!` on single operands
+pub(crate) struct Expression<'a> {
+    tokens: Vec<String>,
+    pos: usize,
+    constant: &'a dyn Fn(&str) -> Option<Value>,
+}
+
+impl<'a> Expression<'a> {
+    pub(crate) fn new(text: &str, constant: &'a dyn Fn(&str) -> Option<Value>) -> Self {
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        for c in text.chars() {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' {
+                word.push(c);
+                continue;
+            }
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            if !c.is_whitespace() {
+                tokens.push(c.to_string());
+            }
+        }
+        if !word.is_empty() {
+            tokens.push(word);
+        }
+        Expression {
+            tokens,
+            pos: 0,
+            constant,
+        }
+    }
+
+    pub(crate) fn evaluate(mut self) -> Option<i64> {
+        let value = self.binary(0)?;
+        (self.pos == self.tokens.len()).then_some(value)
+    }
+
+    fn binary(&mut self, level: usize) -> Option<i64> {
+        const OPERATORS: [&str; 3] = ["|", "^", "&"];
+        if level == OPERATORS.len() {
+            return self.unary();
+        }
+        let mut value = self.binary(level + 1)?;
+        while self.tokens.get(self.pos).map(String::as_str) == Some(OPERATORS[level]) {
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            value = match level {
+                0 => value | right,
+                1 => value ^ right,
+                _ => value & right,
+            };
+        }
+        Some(value)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        let token = self.tokens.get(self.pos)?.clone();
+        self.pos += 1;
+        match token.as_str() {
+            "~" => self.unary().map(|n| !n),
+            "!" => self.unary().map(|n| (n == 0) as i64),
+            "(" => {
+                let value = self.binary(0)?;
+                (self.tokens.get(self.pos)? == ")").then_some(())?;
+                self.pos += 1;
+                Some(value)
+            }
+            word if is_name(word) => (self.constant)(word).map(|value| value.to_int()),
+            number => number.parse().ok(),
+        }
+    }
+}
@@ -0,0 +1,471 @@
+//! Reading INI text into arrays
+//!
+//! Used by parse_ini_string/parse_ini_file and by the engine's own
+//! `vhp.ini` loader (`ini::load_ini`). Values are read according to a
+//! scanner mode:
+//!
+//! - normal: quoted strings are taken as written, `${NAME}` expands to
+//!   an environment variable or directive, and unquoted values are
+//!   trimmed, with `true`/`on`/`yes` giving "1", `false`/`off`/`no`/
+//!   `none`/`null` giving "", constants their values and expressions
+//!   over `|`, `&`, `^`, `~` and `!` their result
+//! - raw: values are taken as written, without their surrounding quotes
+//! - typed: like normal, but booleans, null and integers keep their type
+
+use crate::runtime::builtins::ini;
+use crate::runtime::builtins::ini_expression::{is_name, Expression};
+use crate::runtime::{ArrayKey, PhpArray, Value};
+
+/// How values are read: `INI_SCANNER_NORMAL`, `INI_SCANNER_RAW` or
+/// `INI_SCANNER_TYPED`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScannerMode {
+    Normal,
+    Raw,
+    Typed,
+}
+
+impl ScannerMode {
+    pub fn from_int(mode: i64) -> Option<Self> {
+        match mode {
+            0 => Some(ScannerMode::Normal),
+            1 => Some(ScannerMode::Raw),
+            2 => Some(ScannerMode::Typed),
+            _ => None,
+        }
+    }
+}
+
+/// A syntax error and the line it's on
+#[derive(Debug, Clone, PartialEq)]
+pub struct IniError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl IniError {
+    /// The warning for the error in the named file ("Unknown" for a string)
+    pub fn describe(&self, file: &str) -> String {
+        format!(
+            "syntax error, {} in {} on line {}\n",
+            self.message, file, self.line
+        )
+    }
+}
+
+/// Parse INI text into an array, with an array per section when
+/// `sections` is set. `constant` gives the value of a constant by name.
+pub fn parse(
+    source: &str,
+    sections: bool,
+    mode: ScannerMode,
+    constant: &dyn Fn(&str) -> Option<Value>,
+) -> Result<PhpArray, IniError> {
+    let mut parser = Parser {
+        chars: source.chars().filter(|c| *c != '\r').collect(),
+        pos: 0,
+        line: 1,
+        mode,
+        constant,
+    };
+    let mut root = PhpArray::new();
+    let mut section: Option<ArrayKey> = None;
+    while let Some(statement) = parser.statement()? {
+        match statement {
+            Statement::Section(name) if sections => {
+                let key = ArrayKey::from_value(&Value::String(name));
+                set(&mut root, key.clone(), None, Value::Array(PhpArray::new()));
+                section = Some(key);
+            }
+            Statement::Section(_) => {}
+            Statement::Entry(key, offset, value) => {
+                let entries = section.as_ref().and_then(|name| root.get_mut(name));
+                match entries {
+                    Some(Value::Array(entries)) => set(entries, key, offset, *value),
+                    _ => set(&mut root, key, offset, *value),
+                }
+            }
+        }
+    }
+    Ok(root)
+}
+
+/// Set `key` (or `key[offset]`, appending for `key[]`) in `entries`
+fn set(entries: &mut PhpArray, key: ArrayKey, offset: Option<String>, value: Value) {
+    let value = match offset {
+        None => value,
+        Some(offset) => {
+            let mut items = match entries.get(&key) {
+                Some(Value::Array(items)) => items.clone(),
+                _ => PhpArray::new(),
+            };
+            let item_key = if offset.is_empty() {
+                ArrayKey::Integer(items.next_index())
+            } else {
+                ArrayKey::from_value(&Value::String(offset))
+            };
+            items.insert(item_key, value);
+            Value::Array(items)
+        }
+    };
+    entries.insert(key, value);
+}
+
+enum Statement {
+    Section(String),
+    /// A key, its `[offset]` if any, and its value
+    Entry(ArrayKey, Option<String>, Box<Value>),
+}
+
+/// Characters a key can't contain
+const RESERVED: &str = "?{}|&~!()^\"";
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    mode: ScannerMode,
+    constant: &'a dyn Fn(&str) -> Option<Value>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn advance(&mut self) {
+        if self.peek() == Some('\n') {
+            self.line += 1;
+        }
+        self.pos += 1;
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, IniError> {
+        Err(IniError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn unexpected<T>(&self) -> Result<T, IniError> {
+        match self.peek() {
+            None => self.error("unexpected end of file"),
+            Some('\n') => self.error("unexpected end of line"),
+            Some(c) => self.error(format!("unexpected '{}'", c)),
+        }
+    }
+
+    fn skip_blanks(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.advance();
+        }
+    }
+
+    /// Skip a `;` comment up to the line break
+    fn skip_comment(&mut self) {
+        if self.peek() == Some(';') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.advance();
+            }
+        }
+    }
+
+    /// The rest of the line must be blank or a comment
+    fn end_line(&mut self) -> Result<(), IniError> {
+        self.skip_blanks();
+        self.skip_comment();
+        match self.peek() {
+            None | Some('\n') => Ok(()),
+            _ => self.unexpected(),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Option<Statement>, IniError> {
+        loop {
+            while matches!(self.peek(), Some(' ' | '\t' | '\n')) {
+                self.advance();
+            }
+            match self.peek() {
+                None => return Ok(None),
+                Some(';') => self.skip_comment(),
+                Some('[') => return self.section().map(Some),
+                Some(_) => {
+                    if let Some(entry) = self.entry()? {
+                        return Ok(Some(entry));
+                    }
+                }
+            }
+        }
+    }
+
+    fn section(&mut self) -> Result<Statement, IniError> {
+        self.advance();
+        let mut name = String::new();
+        loop {
+            match self.peek() {
+                Some(']') => break,
+                None | Some('\n') => return self.error("unexpected end of line, expecting ']'"),
+                Some('"') => name.push_str(&self.double_quoted()?),
+                Some(c) => {
+                    name.push(c);
+                    self.advance();
+                }
+            }
+        }
+        self.advance();
+        self.end_line()?;
+        Ok(Statement::Section(name.trim().to_string()))
+    }
+
+    /// A `key = value` line; a key without a value is skipped
+    fn entry(&mut self) -> Result<Option<Statement>, IniError> {
+        let mut key = String::new();
+        while let Some(c) = self.peek() {
+            if matches!(c, '=' | '[' | '\n' | ';') {
+                break;
+            }
+            if RESERVED.contains(c) {
+                return self.unexpected();
+            }
+            key.push(c);
+            self.advance();
+        }
+        let key = key.trim().to_string();
+        let offset = if self.peek() == Some('[') {
+            self.advance();
+            let mut offset = String::new();
+            while let Some(c) = self.peek() {
+                if c == ']' || c == '\n' {
+                    break;
+                }
+                offset.push(c);
+                self.advance();
+            }
+            if self.peek() != Some(']') {
+                return self.error("unexpected end of line, expecting ']'");
+            }
+            self.advance();
+            self.skip_blanks();
+            Some(offset.trim().trim_matches('"').to_string())
+        } else {
+            None
+        };
+        if self.peek() != Some('=') {
+            self.end_line()?;
+            return Ok(None);
+        }
+        if key.is_empty() {
+            return self.unexpected();
+        }
+        self.advance();
+        let value = self.value()?;
+        self.end_line()?;
+        let key = ArrayKey::from_value(&Value::String(key));
+        Ok(Some(Statement::Entry(key, offset, Box::new(value))))
+    }
+
+    fn value(&mut self) -> Result<Value, IniError> {
+        self.skip_blanks();
+        if self.mode == ScannerMode::Raw {
+            return self.raw_value();
+        }
+        // Each part of the value, and whether it was quoted
+        let mut parts: Vec<(String, bool)> = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some('\n' | ';') => break,
+                Some('"') => {
+                    let text = self.double_quoted()?;
+                    parts.push((text, true));
+                }
+                Some('\'') => {
+                    let text = self.single_quoted()?;
+                    parts.push((text, true));
+                }
+                Some('$') if self.chars.get(self.pos + 1) == Some(&'{') => {
+                    let text = self.variable()?;
+                    parts.push((text, true));
+                }
+                Some(_) => {
+                    let mut text = String::new();
+                    while let Some(c) = self.peek() {
+                        if matches!(c, '\n' | ';' | '"' | '\'')
+                            || (c == '$' && self.chars.get(self.pos + 1) == Some(&'{'))
+                        {
+                            break;
+                        }
+                        if c == '=' {
+                            return self.unexpected();
+                        }
+                        text.push(c);
+                        self.advance();
+                    }
+                    let text = text.trim();
+                    if !text.is_empty() {
+                        parts.push((text.to_string(), false));
+                    }
+                }
+            }
+        }
+        match parts.as_slice() {
+            [] => Ok(Value::String(String::new())),
+            [(text, false)] => self.unquoted(text),
+            _ => {
+                let mut value = String::new();
+                for (text, quoted) in &parts {
+                    if *quoted {
+                        value.push_str(text);
+                    } else {
+                        value.push_str(&self.unquoted(text)?.to_string_val());
+                    }
+                }
+                Ok(Value::String(value))
+            }
+        }
+    }
+
+    /// The rest of the line up to a comment, without the quotes around
+    /// it; a `;` between quotes doesn't start a comment
+    fn raw_value(&mut self) -> Result<Value, IniError> {
+        let mut text = String::new();
+        let mut quote = None;
+        while let Some(c) = self.peek() {
+            match (c, quote) {
+                ('\n' | ';', None) => break,
+                ('"' | '\'', None) => quote = Some(c),
+                (c, Some(open)) if c == open => quote = None,
+                _ => {}
+            }
+            text.push(c);
+            self.advance();
+        }
+        if quote.is_some() {
+            return self.unterminated();
+        }
+        let text = text.trim();
+        let unquoted = ['"', '\'']
+            .iter()
+            .find_map(|q| text.strip_prefix(*q)?.strip_suffix(*q))
+            .unwrap_or(text);
+        Ok(Value::String(unquoted.to_string()))
+    }
+
+    fn unterminated<T>(&self) -> Result<T, IniError> {
+        self.error("unexpected end of file, expecting TC_DOLLAR_CURLY or TC_QUOTED_STRING or '\"'")
+    }
+
+    fn double_quoted(&mut self) -> Result<String, IniError> {
+        self.advance();
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return self.unterminated(),
+                Some('"') => {
+                    self.advance();
+                    return Ok(text);
+                }
+                Some('\\') if matches!(self.chars.get(self.pos + 1), Some('"' | '\\' | '\'')) => {
+                    self.advance();
+                    text.extend(self.peek());
+                    self.advance();
+                }
+                Some('$') if self.chars.get(self.pos + 1) == Some(&'{') => {
+                    text.push_str(&self.variable()?);
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn single_quoted(&mut self) -> Result<String, IniError> {
+        self.advance();
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return self.unterminated(),
+                Some('\'') => {
+                    self.advance();
+                    return Ok(text);
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    /// `${NAME}`: an environment variable, or else a directive
+    fn variable(&mut self) -> Result<String, IniError> {
+        self.pos += 2;
+        let mut name = String::new();
+        loop {
+            match self.peek() {
+                Some('}') => break,
+                None | Some('\n') => return self.error("unexpected end of line, expecting '}'"),
+                Some(c) => {
+                    name.push(c);
+                    self.advance();
+                }
+            }
+        }
+        self.advance();
+        Ok(std::env::var(&name)
+            .ok()
+            .or_else(|| ini::get_ini_value(&name))
+            .unwrap_or_default())
+    }
+
+    /// An unquoted value: a boolean or null word, a constant, an
+    /// expression, or else the text itself
+    fn unquoted(&self, text: &str) -> Result<Value, IniError> {
+        let typed = self.mode == ScannerMode::Typed;
+        match text.to_ascii_lowercase().as_str() {
+            "true" | "on" | "yes" => {
+                return Ok(if typed {
+                    Value::Bool(true)
+                } else {
+                    Value::String("1".into())
+                })
+            }
+            "false" | "off" | "no" | "none" => {
+                return Ok(if typed {
+                    Value::Bool(false)
+                } else {
+                    Value::String(String::new())
+                })
+            }
+            "null" => {
+                return Ok(if typed {
+                    Value::Null
+                } else {
+                    Value::String(String::new())
+                })
+            }
+            _ => {}
+        }
+        if is_name(text) {
+            if let Some(value) = (self.constant)(text) {
+                return Ok(Value::String(value.to_string_val()));
+            }
+        } else if text.contains(['|', '&', '^', '~', '!', '(', ')']) {
+            return match Expression::new(text, self.constant).evaluate() {
+                Some(n) => Ok(if typed {
+                    Value::Integer(n)
+                } else {
+                    Value::String(n.to_string())
+                }),
+                None => self.error(format!("unexpected '{}'", text)),
+            };
+        }
+        if typed {
+            if let Ok(n) = text.parse::<i64>() {
+                return Ok(Value::Integer(n));
+            }
+        }
+        Ok(Value::String(text.to_string()))
+    }
+}
@@ -0,0 +1,372 @@
+//! Locale-aware number formatting (an intl subset)
+//!
+//! NumberFormatter for the decimal, currency and percent styles, with
+//! the separators, grouping and symbol placement of a handful of locales
+//! instead of the ICU data. Other locales format as `en_US` does.
+//!
+//! A formatter is an object of the built-in `NumberFormatter` class whose
+//! methods call these functions, PHP's procedural aliases, with the
+//! object first. It keeps the canonical locale, the style and the
+//! attributes set on it.
+
+use crate::runtime::builtins::locale_data::{canonical_locale, currency, locale_data, LocaleData};
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::{ArrayKey, ObjectInstance, ObjectRef, PhpArray, Value};
+use crate::vm::error::NativeError;
+
+/// `NumberFormatter::DECIMAL`
+pub const DECIMAL: i64 = 1;
+/// `NumberFormatter::CURRENCY`
+pub const CURRENCY: i64 = 2;
+/// `NumberFormatter::PERCENT`
+pub const PERCENT: i64 = 3;
+
+/// `NumberFormatter::GROUPING_USED`
+pub const GROUPING_USED: i64 = 1;
+/// `NumberFormatter::MAX_FRACTION_DIGITS`
+pub const MAX_FRACTION_DIGITS: i64 = 6;
+/// `NumberFormatter::MIN_FRACTION_DIGITS`
+pub const MIN_FRACTION_DIGITS: i64 = 7;
+/// `NumberFormatter::FRACTION_DIGITS`
+pub const FRACTION_DIGITS: i64 = 8;
+
+/// `NumberFormatter::TYPE_DEFAULT`
+pub const TYPE_DEFAULT: i64 = 0;
+/// `NumberFormatter::TYPE_INT32`
+pub const TYPE_INT32: i64 = 1;
+/// `NumberFormatter::TYPE_INT64`
+pub const TYPE_INT64: i64 = 2;
+/// `NumberFormatter::TYPE_DOUBLE`
+pub const TYPE_DOUBLE: i64 = 3;
+
+/// A property of a formatter object
+fn property(formatter: &ObjectRef, name: &str) -> Value {
+    formatter.get(name).unwrap_or(Value::Null)
+}
+
+/// The formatter argument of a `numfmt_*()` function
+fn formatter_argument<'a>(function: &str, args: &'a [Value]) -> Result<&'a ObjectRef, NativeError> {
+    match args.first() {
+        Some(Value::Object(object)) if object.is_instance_of("NumberFormatter") => Ok(object),
+        other => Err(NativeError::type_error(format!(
+            "{}(): Argument #1 ($formatter) must be of type NumberFormatter, {} given",
+            function,
+            other.map_or("null", |value| value.type_name())
+        ))),
+    }
+}
+
+/// numfmt_create - Create a number formatter
+pub fn numfmt_create(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("numfmt_create", args.len(), 2, Some(3))?;
+    let style = args[1].to_int();
+    if !matches!(style, DECIMAL | CURRENCY | PERCENT) {
+        return Err(NativeError::value_error("NumberFormatter::__construct(): Argument #2 ($style) must be one of NumberFormatter::DECIMAL, NumberFormatter::CURRENCY or NumberFormatter::PERCENT"));
+    }
+    let mut formatter = ObjectInstance::new("NumberFormatter".to_string());
+    let properties = [
+        (
+            "__locale",
+            Value::String(canonical_locale(&args[0].to_string_val())),
+        ),
+        ("__style", Value::Integer(style)),
+        ("__attributes", Value::Array(PhpArray::new())),
+    ];
+    for (name, value) in properties {
+        formatter.properties.insert(name.to_string(), value);
+    }
+    Ok(Value::Object(formatter.into()))
+}
+
+/// numfmt_get_locale - The locale of a number formatter
+pub fn numfmt_get_locale(args: &[Value]) -> Result<Value, NativeError> {
+    let formatter = formatter_argument("numfmt_get_locale", args)?;
+    Ok(property(formatter, "__locale"))
+}
+
+/// numfmt_get_attribute - An attribute of a number formatter: the value
+/// set, or its style's default
+pub fn numfmt_get_attribute(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("numfmt_get_attribute", args.len(), 2, Some(2))?;
+    let formatter = formatter_argument("numfmt_get_attribute", args)?;
+    let settings = Settings::of(formatter, None);
+    let value = match args[1].to_int() {
+        GROUPING_USED => settings.grouping_used as i64,
+        MAX_FRACTION_DIGITS | FRACTION_DIGITS => settings.max_fraction as i64,
+        MIN_FRACTION_DIGITS => settings.min_fraction as i64,
+        _ => return Ok(Value::Bool(false)),
+    };
+    Ok(Value::Integer(value))
+}
+
+/// How a formatter writes numbers
+struct Settings {
+    locale: &'static LocaleData,
+    style: i64,
+    min_fraction: usize,
+    max_fraction: usize,
+    grouping_used: bool,
+}
+
+impl Settings {
+    /// The settings of a formatter, for amounts of `currency` if given
+    fn of(formatter: &ObjectRef, currency_code: Option<&str>) -> Settings {
+        let locale = locale_data(&property(formatter, "__locale").to_string_val());
+        let style = property(formatter, "__style").to_int();
+        let (mut min_fraction, mut max_fraction) = match style {
+            CURRENCY => {
+                let code = currency_code.unwrap_or(locale.currency_code);
+                let digits = currency(locale, code).1;
+                (digits, digits)
+            }
+            PERCENT => (0, 0),
+            _ => (0, 3),
+        };
+        let mut grouping_used = true;
+        if let Value::Array(attributes) = property(formatter, "__attributes") {
+            for (key, value) in attributes {
+                let ArrayKey::Integer(attribute) = key else {
+                    continue;
+                };
+                let n = value.to_int().max(0) as usize;
+                match attribute {
+                    GROUPING_USED => grouping_used = value.to_bool(),
+                    MAX_FRACTION_DIGITS => {
+                        max_fraction = n;
+                        min_fraction = min_fraction.min(n);
+                    }
+                    MIN_FRACTION_DIGITS => {
+                        min_fraction = n;
+                        max_fraction = max_fraction.max(n);
+                    }
+                    FRACTION_DIGITS => (min_fraction, max_fraction) = (n, n),
+                    _ => {}
+                }
+            }
+        }
+        Settings {
+            locale,
+            style,
+            min_fraction,
+            max_fraction,
+            grouping_used,
+        }
+    }
+
+    /// A number without its sign, rounded half to even as ICU does: on the
+    /// shortest decimal form of a float
+    fn digits(&self, value: &Value) -> String {
+        let (integer, fraction) = match value {
+            Value::Integer(n) => (n.unsigned_abs().to_string(), String::new()),
+            _ => {
+                let float = value.to_float().abs();
+                if float.is_nan() {
+                    return "NaN".to_string();
+                }
+                if float.is_infinite() {
+                    return "∞".to_string();
+                }
+                let decimal = format!("{}", float);
+                match decimal.split_once('.') {
+                    Some((integer, fraction)) => (integer.to_string(), fraction.to_string()),
+                    None => (decimal, String::new()),
+                }
+            }
+        };
+        let (integer, mut fraction) = round_half_even(&integer, &fraction, self.max_fraction);
+        while fraction.len() > self.min_fraction && fraction.ends_with('0') {
+            fraction.pop();
+        }
+        while fraction.len() < self.min_fraction {
+            fraction.push('0');
+        }
+        let integer = if self.grouping_used {
+            group(&integer, self.locale)
+        } else {
+            integer
+        };
+        if fraction.is_empty() {
+            integer
+        } else {
+            format!("{}{}{}", integer, self.locale.decimal, fraction)
+        }
+    }
+}
+
+/// Round a decimal number, given by its integer and fraction digits, to
+/// `places` fraction digits, half to even
+fn round_half_even(integer: &str, fraction: &str, places: usize) -> (String, String) {
+    if fraction.len() <= places {
+        return (integer.to_string(), fraction.to_string());
+    }
+    let (kept, dropped) = fraction.split_at(places);
+    let mut digits: Vec<u8> = integer.bytes().chain(kept.bytes()).collect();
+    let first_dropped = dropped.as_bytes()[0];
+    let exactly_half = first_dropped == b'5' && dropped[1..].bytes().all(|d| d == b'0');
+    let odd = digits.last().is_some_and(|d| (d - b'0') % 2 == 1);
+    if first_dropped > b'5' || (first_dropped == b'5' && (!exactly_half || odd)) {
+        let mut position = digits.len();
+        loop {
+            if position == 0 {
+                digits.insert(0, b'1');
+                break;
+            }
+            position -= 1;
+            if digits[position] == b'9' {
+                digits[position] = b'0';
+            } else {
+                digits[position] += 1;
+                break;
+            }
+        }
+    }
+    let split = digits.len() - places;
+    let digits = String::from_utf8(digits).unwrap_or_default();
+    (digits[..split].to_string(), digits[split..].to_string())
+}
+
+/// The integer digits with the locale's group separators
+fn group(integer: &str, locale: &LocaleData) -> String {
+    let (first, rest) = locale.grouping;
+    if integer.len() <= first || (locale.min_grouping_two && integer.len() == first + 1) {
+        return integer.to_string();
+    }
+    let (head, last) = integer.split_at(integer.len() - first);
+    let mut groups = vec![last];
+    let mut head = head;
+    while head.len() > rest {
+        let (before, group) = head.split_at(head.len() - rest);
+        groups.push(group);
+        head = before;
+    }
+    groups.push(head);
+    groups.reverse();
+    groups.join(locale.group)
+}
+
+/// A number as a formatter writes it, in the currency given or the
+/// locale's
+fn format_value(settings: &Settings, value: &Value, currency_code: Option<&str>) -> String {
+    let locale = settings.locale;
+    let negative = match value {
+        Value::Integer(n) => *n < 0,
+        _ => value.to_float() < 0.0,
+    };
+    let (patterns, symbol) = match settings.style {
+        CURRENCY => {
+            let code = currency_code.unwrap_or(locale.currency_code);
+            let mut symbol = currency(locale, code).0;
+            // An alphabetic symbol right before the number gets a space
+            if symbol.ends_with(|c: char| c.is_ascii_alphabetic())
+                && locale.currency.0.contains("¤#")
+            {
+                symbol.push('\u{a0}');
+            }
+            (locale.currency, symbol)
+        }
+        PERCENT => (locale.percent, String::new()),
+        _ => (("#", "-#"), String::new()),
+    };
+    let value = match (settings.style, value) {
+        (PERCENT, Value::Integer(n)) => Value::Integer(n.saturating_mul(100)),
+        (PERCENT, value) => Value::Float(value.to_float() * 100.0),
+        (_, value) => value.clone(),
+    };
+    let pattern = if negative { patterns.1 } else { patterns.0 };
+    pattern
+        .replace('¤', &symbol)
+        .replace('#', &settings.digits(&value))
+}
+
+/// The number argument of a `numfmt_*()` function
+fn number_argument(function: &str, value: &Value) -> Result<Value, NativeError> {
+    match value {
+        Value::Integer(_) | Value::Float(_) => Ok(value.clone()),
+        Value::Bool(_) | Value::Null => Ok(Value::Integer(value.to_int())),
+        Value::String(s) if s.trim().parse::<f64>().is_ok() => Ok(match s.trim().parse::<i64>() {
+            Ok(n) => Value::Integer(n),
+            Err(_) => Value::Float(value.to_float()),
+        }),
+        other => Err(NativeError::type_error(format!(
+            "{}(): Argument #2 ($num) must be of type int|float, {} given",
+            function,
+            other.type_name()
+        ))),
+    }
+}
+
+/// numfmt_format - Format a number
+pub fn numfmt_format(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("numfmt_format", args.len(), 2, Some(3))?;
+    let formatter = formatter_argument("numfmt_format", args)?;
+    let value = number_argument("numfmt_format", &args[1])?;
+    let value = match args.get(2).map_or(TYPE_DEFAULT, Value::to_int) {
+        TYPE_INT32 | TYPE_INT64 => Value::Integer(value.to_int()),
+        TYPE_DOUBLE => Value::Float(value.to_float()),
+        _ => value,
+    };
+    let settings = Settings::of(formatter, None);
+    Ok(Value::String(format_value(&settings, &value, None)))
+}
+
+/// numfmt_format_currency - Format an amount of a currency
+pub fn numfmt_format_currency(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("numfmt_format_currency", args.len(), 3, Some(3))?;
+    let formatter = formatter_argument("numfmt_format_currency", args)?;
+    let value = number_argument("numfmt_format_currency", &args[1])?;
+    let code = args[2].to_string_val();
+    let mut settings = Settings::of(formatter, Some(&code));
+    if settings.style != CURRENCY {
+        let digits = currency(settings.locale, &code).1;
+        (settings.min_fraction, settings.max_fraction) = (digits, digits);
+        settings.style = CURRENCY;
+    }
+    let value = Value::Float(value.to_float());
+    Ok(Value::String(format_value(&settings, &value, Some(&code))))
+}
+
+/// numfmt_parse - Read a number written the way a formatter writes
+/// them; false if the string holds none
+pub fn numfmt_parse(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("numfmt_parse", args.len(), 2, Some(3))?;
+    let formatter = formatter_argument("numfmt_parse", args)?;
+    let settings = Settings::of(formatter, None);
+    let text = args[1].to_string_val();
+    let mut number = String::new();
+    // Symbols and spaces before the number are skipped
+    let mut rest =
+        text.trim_start_matches(|c: char| !c.is_ascii_digit() && !matches!(c, '-' | '\u{2212}'));
+    if let Some(unsigned) = rest
+        .strip_prefix('-')
+        .or_else(|| rest.strip_prefix('\u{2212}'))
+    {
+        number.push('-');
+        rest = unsigned.trim_start_matches(|c: char| !c.is_ascii_digit());
+    }
+    let decimal = settings.locale.decimal;
+    let group = settings.locale.group;
+    let mut seen_decimal = false;
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() {
+            number.push(c);
+        } else if rest.starts_with(decimal) && !seen_decimal {
+            seen_decimal = true;
+            number.push('.');
+            rest = &rest[decimal.len()..];
+            continue;
+        } else if !(settings.grouping_used && rest.starts_with(group) && !seen_decimal) {
+            break;
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    let Ok(mut parsed) = number.parse::<f64>() else {
+        return Ok(Value::Bool(false));
+    };
+    if settings.style == PERCENT {
+        parsed /= 100.0;
+    }
+    Ok(match args.get(2).map_or(TYPE_DOUBLE, Value::to_int) {
+        TYPE_INT32 | TYPE_INT64 => Value::Integer(parsed as i64),
+        _ => Value::Float(parsed),
+    })
+}
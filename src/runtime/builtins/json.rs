@@ -1,11 +1,14 @@
 //! JSON encoding and decoding functions
 
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
 
 /// json_encode - Returns a JSON representation of a value
-pub fn json_encode(args: &[Value]) -> Result<Value, String> {
+pub fn json_encode(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("json_encode() expects exactly 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "json_encode() expects exactly 1 parameter, 0 given",
+        ));
     }
 
     let result = value_to_json(&args[0], 0)?;
@@ -13,9 +16,11 @@ pub fn json_encode(args: &[Value]) -> Result<Value, String> {
 }
 
 /// json_decode - Decodes a JSON string
-pub fn json_decode(args: &[Value]) -> Result<Value, String> {
+pub fn json_decode(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("json_decode() expects exactly 1 parameter, 0 given".to_string());
+        return Err(NativeError::argument_count_error(
+            "json_decode() expects exactly 1 parameter, 0 given",
+        ));
     }
 
     let json_str = args[0].to_string_val();
@@ -28,13 +33,14 @@ pub fn json_decode(args: &[Value]) -> Result<Value, String> {
     }
 }
 
-fn value_to_json(value: &Value, depth: u32) -> Result<String, String> {
+fn value_to_json(value: &Value, depth: u32) -> Result<String, NativeError> {
     // Prevent stack overflow from circular references
     if depth > 64 {
-        return Err("Maximum nesting level of 64 reached".to_string());
+        return Err(NativeError::fatal("Maximum nesting level of 64 reached"));
     }
 
     match value {
+        Value::Reference(r) => value_to_json(&r.get(), depth),
         Value::Null => Ok("null".to_string()),
         Value::Bool(b) => Ok(if *b { "true" } else { "false" }.to_string()),
         Value::Integer(n) => Ok(n.to_string()),
@@ -158,17 +164,17 @@ fn parse_value(input: &str) -> Result<(Value, &str), String> {
     }
 }
 
-fn parse_json(json_str: &str) -> Result<Value, String> {
+fn parse_json(json_str: &str) -> Result<Value, NativeError> {
     let trimmed = json_str.trim();
 
     if trimmed.is_empty() {
-        return Err("Empty JSON string".to_string());
+        return Err(NativeError::fatal("Empty JSON string"));
     }
 
-    let (value, remaining) = parse_value(trimmed)?;
+    let (value, remaining) = parse_value(trimmed).map_err(NativeError::fatal)?;
 
     if !remaining.trim().is_empty() {
-        return Err("Extra characters after JSON value".to_string());
+        return Err(NativeError::fatal("Extra characters after JSON value"));
     }
 
     Ok(value)
@@ -241,7 +247,7 @@ fn parse_object(input: &str) -> Result<(Value, &str), String> {
     rest = &rest[whitespace_count..];
 
     if let Some(stripped) = rest.strip_prefix('}') {
-        return Ok((Value::Array(arr), stripped));
+        return Ok((Value::Array(arr.into()), stripped));
     }
 
     loop {
@@ -284,7 +290,7 @@ fn parse_object(input: &str) -> Result<(Value, &str), String> {
         if let Some(stripped) = rest.strip_prefix(',') {
             rest = stripped;
         } else if let Some(stripped) = rest.strip_prefix('}') {
-            return Ok((Value::Array(arr), stripped));
+            return Ok((Value::Array(arr.into()), stripped));
         } else {
             return Err("Expected ',' or '}' in object".to_string());
         }
@@ -304,7 +310,7 @@ fn parse_array(input: &str) -> Result<(Value, &str), String> {
     rest = &rest[whitespace_count..];
 
     if let Some(stripped) = rest.strip_prefix(']') {
-        return Ok((Value::Array(arr), stripped));
+        return Ok((Value::Array(arr.into()), stripped));
     }
 
     let mut index: i64 = 0;
@@ -330,7 +336,7 @@ fn parse_array(input: &str) -> Result<(Value, &str), String> {
         if let Some(stripped) = rest.strip_prefix(',') {
             rest = stripped;
         } else if let Some(stripped) = rest.strip_prefix(']') {
-            return Ok((Value::Array(arr), stripped));
+            return Ok((Value::Array(arr.into()), stripped));
         } else {
             return Err("Expected ',' or ']' in array".to_string());
         }
@@ -0,0 +1,298 @@
+//! setlocale() and the locale-sensitive functions
+//!
+//! The engine keeps a locale per category, all "C" when a run starts,
+//! like PHP before a script calls setlocale(). The locales setlocale()
+//! accepts are "C", "POSIX" and the ones in `locale_data`, written in any
+//! of the usual forms (`de_DE`, `de_DE.UTF-8`, `de_DE@euro`).
+//!
+//! - LC_CTYPE: strtoupper() and the like change only ASCII letters in
+//!   the C locale, and every letter in the others
+//! - LC_NUMERIC: the decimal point of sprintf()'s `%f` and localeconv()
+//! - LC_TIME: the month and weekday names of gmstrftime()
+//! - LC_COLLATE: strcoll() and sorting with SORT_LOCALE_STRING compare
+//!   bytes in the C locale and the way `Collator` does in the others
+//! - LC_MONETARY: the currency part of localeconv()
+
+use crate::runtime::builtins::collator::{collate, TERTIARY};
+use crate::runtime::builtins::locale_data::{
+    canonical_locale, currency, is_known, locale_data, LocaleData,
+};
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
+use std::cmp::Ordering;
+use std::sync::Mutex;
+use thread_local::ThreadLocal;
+
+/// `LC_CTYPE`
+pub const LC_CTYPE: i64 = 0;
+/// `LC_NUMERIC`
+pub const LC_NUMERIC: i64 = 1;
+/// `LC_TIME`
+pub const LC_TIME: i64 = 2;
+/// `LC_COLLATE`
+pub const LC_COLLATE: i64 = 3;
+/// `LC_MONETARY`
+pub const LC_MONETARY: i64 = 4;
+/// `LC_MESSAGES`
+pub const LC_MESSAGES: i64 = 5;
+/// `LC_ALL`
+pub const LC_ALL: i64 = 6;
+
+/// The categories, in the order `LC_ALL` lists them
+const CATEGORIES: &[(&str, i64)] = &[
+    ("LC_CTYPE", LC_CTYPE),
+    ("LC_NUMERIC", LC_NUMERIC),
+    ("LC_TIME", LC_TIME),
+    ("LC_COLLATE", LC_COLLATE),
+    ("LC_MONETARY", LC_MONETARY),
+    ("LC_MESSAGES", LC_MESSAGES),
+];
+
+lazy_static::lazy_static! {
+    /// The locale of each category, per thread like the script setting them
+    static ref LOCALES: ThreadLocal<Mutex<Vec<String>>> = ThreadLocal::new();
+}
+
+/// This thread's locales
+fn locales() -> &'static Mutex<Vec<String>> {
+    LOCALES.get_or(|| Mutex::new(vec!["C".to_string(); CATEGORIES.len()]))
+}
+
+/// The value of an `LC_*` constant
+pub fn constant(name: &str) -> Option<Value> {
+    match name {
+        "LC_ALL" => Some(Value::Integer(LC_ALL)),
+        _ => CATEGORIES
+            .iter()
+            .find(|(constant, _)| *constant == name)
+            .map(|(_, category)| Value::Integer(*category)),
+    }
+}
+
+/// Put every category back in the C locale
+pub fn reset() {
+    let mut locales = locales().lock().unwrap();
+    locales
+        .iter_mut()
+        .for_each(|locale| *locale = "C".to_string());
+}
+
+/// The locale of a category, as setlocale() was given it
+pub fn current(category: i64) -> String {
+    locales().lock().unwrap()[category as usize].clone()
+}
+
+/// The data of a category's locale, or None in the C locale
+fn data(category: i64) -> Option<&'static LocaleData> {
+    let locale = current(category);
+    is_known(&locale).then(|| locale_data(&canonical_locale(&locale)))
+}
+
+/// The language of a category's locale, or None in the C locale
+fn language(category: i64) -> Option<String> {
+    data(category).map(|data| data.name.split('_').next().unwrap_or_default().to_string())
+}
+
+/// `s` in uppercase, as LC_CTYPE has it
+pub fn to_upper(s: &str) -> String {
+    match data(LC_CTYPE) {
+        Some(_) => s.to_uppercase(),
+        None => s.to_ascii_uppercase(),
+    }
+}
+
+/// `s` in lowercase, as LC_CTYPE has it
+pub fn to_lower(s: &str) -> String {
+    match data(LC_CTYPE) {
+        Some(_) => s.to_lowercase(),
+        None => s.to_ascii_lowercase(),
+    }
+}
+
+/// The decimal point of LC_NUMERIC
+pub fn decimal_point() -> &'static str {
+    data(LC_NUMERIC).map_or(".", |data| data.decimal)
+}
+
+/// The name of a month (from 0) or, with `weekday`, a weekday (from
+/// Sunday) in LC_TIME
+pub fn time_name(weekday: bool, abbreviated: bool, index: usize) -> &'static str {
+    let locale = data(LC_TIME).map_or("en", |data| data.name);
+    crate::runtime::builtins::locale_data::time_name(locale, weekday, abbreviated, index)
+}
+
+/// Compare two strings as LC_COLLATE orders them
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match language(LC_COLLATE) {
+        Some(language) => collate(a, b, &language, TERTIARY).then_with(|| a.cmp(b)),
+        None => a.cmp(b),
+    }
+}
+
+/// The locale names setlocale() was given: strings, arrays of them, or both
+fn candidates(args: &[Value]) -> Vec<String> {
+    let mut names = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Array(entries) => {
+                names.extend(entries.iter().map(|(_, value)| value.to_string_val()))
+            }
+            value => names.push(value.to_string_val()),
+        }
+    }
+    names
+}
+
+/// The locale "" names for a category: LC_ALL, the category's variable
+/// or LANG from the environment, or "C"
+fn from_environment(category: i64) -> String {
+    let variable = CATEGORIES
+        .iter()
+        .find(|(_, c)| *c == category)
+        .map_or("LC_ALL", |(name, _)| name);
+    ["LC_ALL", variable, "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_else(|| "C".to_string())
+}
+
+/// What setlocale() reports for a category; for LC_ALL the shared
+/// locale, or every category's when they differ
+fn report(category: i64) -> String {
+    let locales = locales().lock().unwrap();
+    if category != LC_ALL {
+        return locales[category as usize].clone();
+    }
+    if locales.iter().all(|locale| *locale == locales[0]) {
+        return locales[0].clone();
+    }
+    CATEGORIES
+        .iter()
+        .zip(locales.iter())
+        .map(|((name, _), locale)| format!("{}={}", name, locale))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// setlocale - Set the locale of a category, or of all of them
+///
+/// Tries each locale in turn and returns the first one known, or false
+/// if none is. "0" returns the current locale and "" takes it from the
+/// environment.
+///
+/// PHP equivalent: setlocale($category, $locales, ...$rest)
+pub fn setlocale(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("setlocale", args.len(), 2, None)?;
+    let category = args[0].to_int();
+    if !(LC_CTYPE..=LC_ALL).contains(&category) {
+        return Err(NativeError::value_error("setlocale(): Argument #1 ($category) must be LC_ALL, LC_COLLATE, LC_CTYPE, LC_MONETARY, LC_NUMERIC, LC_TIME, or LC_MESSAGES"));
+    }
+    for name in candidates(&args[1..]) {
+        if name == "0" {
+            return Ok(Value::String(report(category)));
+        }
+        let name = if name.is_empty() {
+            from_environment(category)
+        } else {
+            name
+        };
+        let known =
+            matches!(name.as_str(), "C" | "POSIX" | "C.UTF-8" | "C.utf8") || is_known(&name);
+        if !known {
+            continue;
+        }
+        let mut locales = locales().lock().unwrap();
+        match category {
+            LC_ALL => locales.iter_mut().for_each(|locale| *locale = name.clone()),
+            _ => locales[category as usize] = name,
+        }
+        drop(locales);
+        return Ok(Value::String(report(category)));
+    }
+    Ok(Value::Bool(false))
+}
+
+/// A `grouping` array: the digits in the first group, then in the others
+fn grouping(data: Option<&LocaleData>) -> Value {
+    let groups = data.map_or(vec![], |data| vec![data.grouping.0, data.grouping.1]);
+    Value::Array(
+        groups
+            .into_iter()
+            .enumerate()
+            .map(|(i, digits)| (ArrayKey::Integer(i as i64), Value::Integer(digits as i64)))
+            .collect(),
+    )
+}
+
+/// localeconv - The numeric and monetary formatting of the locale
+///
+/// The numeric part comes from LC_NUMERIC and the monetary part from
+/// LC_MONETARY; in the C locale the strings are empty (but for the
+/// decimal point) and the numbers 127.
+///
+/// PHP equivalent: localeconv()
+pub fn localeconv(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("localeconv", args.len(), 0, Some(0))?;
+    let numeric = data(LC_NUMERIC);
+    let monetary = data(LC_MONETARY);
+    let string = |s: &str| Value::String(s.to_string());
+    let (symbol, digits) = monetary.map_or((String::new(), 127), |data| {
+        let (symbol, digits) = currency(data, data.currency_code);
+        (symbol, digits as i64)
+    });
+    let precedes = monetary.map_or(127, |data| i64::from(data.currency.0.starts_with('¤')));
+    let spaced = monetary.map_or(127, |data| i64::from(data.currency.0.contains('\u{a0}')));
+    let sign_position = monetary.map_or(127, |_| 1);
+    let entries = [
+        (
+            "decimal_point",
+            string(numeric.map_or(".", |data| data.decimal)),
+        ),
+        (
+            "thousands_sep",
+            string(numeric.map_or("", |data| data.group)),
+        ),
+        (
+            "int_curr_symbol",
+            string(&monetary.map_or(String::new(), |data| format!("{} ", data.currency_code))),
+        ),
+        ("currency_symbol", Value::String(symbol)),
+        (
+            "mon_decimal_point",
+            string(monetary.map_or("", |data| data.decimal)),
+        ),
+        (
+            "mon_thousands_sep",
+            string(monetary.map_or("", |data| data.group)),
+        ),
+        ("positive_sign", string("")),
+        ("negative_sign", string(monetary.map_or("", |_| "-"))),
+        ("int_frac_digits", Value::Integer(digits)),
+        ("frac_digits", Value::Integer(digits)),
+        ("p_cs_precedes", Value::Integer(precedes)),
+        ("p_sep_by_space", Value::Integer(spaced)),
+        ("n_cs_precedes", Value::Integer(precedes)),
+        ("n_sep_by_space", Value::Integer(spaced)),
+        ("p_sign_posn", Value::Integer(sign_position)),
+        ("n_sign_posn", Value::Integer(sign_position)),
+        ("grouping", grouping(numeric)),
+        ("mon_grouping", grouping(monetary)),
+    ];
+    Ok(Value::Array(
+        entries
+            .into_iter()
+            .map(|(key, value)| (ArrayKey::String(key.to_string()), value))
+            .collect(),
+    ))
+}
+
+/// strcoll - Compare two strings as LC_COLLATE orders them
+///
+/// PHP equivalent: strcoll($string1, $string2)
+pub fn strcoll(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("strcoll", args.len(), 2, Some(2))?;
+    let ordering = compare(&args[0].to_string_val(), &args[1].to_string_val());
+    Ok(Value::Integer(ordering as i64))
+}
@@ -0,0 +1,287 @@
+//! What the locales VHP knows write numbers and currencies with
+//!
+//! NumberFormatter formats with this data, and the locales setlocale()
+//! accepts are the ones listed here.
+
+/// How a locale writes numbers
+pub(crate) struct LocaleData {
+    pub(crate) name: &'static str,
+    pub(crate) decimal: &'static str,
+    pub(crate) group: &'static str,
+    /// Digits in the first group and in the groups before it
+    pub(crate) grouping: (usize, usize),
+    /// Whether a four-digit integer part goes ungrouped
+    pub(crate) min_grouping_two: bool,
+    /// Currency patterns for positive and negative amounts: `¤` is the
+    /// symbol, `#` the number
+    pub(crate) currency: (&'static str, &'static str),
+    /// Percent patterns for positive and negative values
+    pub(crate) percent: (&'static str, &'static str),
+    /// The currency `format()` uses in the currency style
+    pub(crate) currency_code: &'static str,
+}
+
+const EN_US: LocaleData = LocaleData {
+    name: "en_US",
+    decimal: ".",
+    group: ",",
+    grouping: (3, 3),
+    min_grouping_two: false,
+    currency: ("¤#", "-¤#"),
+    percent: ("#%", "-#%"),
+    currency_code: "USD",
+};
+
+const LOCALES: &[LocaleData] = &[
+    EN_US,
+    LocaleData {
+        name: "en_GB",
+        currency_code: "GBP",
+        ..EN_US
+    },
+    LocaleData {
+        name: "en_IN",
+        grouping: (3, 2),
+        currency_code: "INR",
+        ..EN_US
+    },
+    LocaleData {
+        name: "de_DE",
+        decimal: ",",
+        group: ".",
+        currency: ("#\u{a0}¤", "-#\u{a0}¤"),
+        percent: ("#\u{a0}%", "-#\u{a0}%"),
+        currency_code: "EUR",
+        ..EN_US
+    },
+    LocaleData {
+        name: "de_CH",
+        group: "’",
+        currency: ("¤\u{a0}#", "¤-#"),
+        currency_code: "CHF",
+        ..EN_US
+    },
+    LocaleData {
+        name: "fr_FR",
+        decimal: ",",
+        group: "\u{202f}",
+        currency: ("#\u{a0}¤", "-#\u{a0}¤"),
+        percent: ("#\u{202f}%", "-#\u{202f}%"),
+        currency_code: "EUR",
+        ..EN_US
+    },
+    LocaleData {
+        name: "es_ES",
+        decimal: ",",
+        group: ".",
+        min_grouping_two: true,
+        currency: ("#\u{a0}¤", "-#\u{a0}¤"),
+        percent: ("#\u{a0}%", "-#\u{a0}%"),
+        currency_code: "EUR",
+        ..EN_US
+    },
+    LocaleData {
+        name: "it_IT",
+        decimal: ",",
+        group: ".",
+        currency: ("#\u{a0}¤", "-#\u{a0}¤"),
+        currency_code: "EUR",
+        ..EN_US
+    },
+    LocaleData {
+        name: "nl_NL",
+        decimal: ",",
+        group: ".",
+        currency: ("¤\u{a0}#", "¤\u{a0}-#"),
+        currency_code: "EUR",
+        ..EN_US
+    },
+    LocaleData {
+        name: "pt_BR",
+        decimal: ",",
+        group: ".",
+        currency: ("¤\u{a0}#", "-¤\u{a0}#"),
+        currency_code: "BRL",
+        ..EN_US
+    },
+    LocaleData {
+        name: "sv_SE",
+        decimal: ",",
+        group: "\u{a0}",
+        currency: ("#\u{a0}¤", "\u{2212}#\u{a0}¤"),
+        percent: ("#\u{a0}%", "\u{2212}#\u{a0}%"),
+        currency_code: "SEK",
+        ..EN_US
+    },
+    LocaleData {
+        name: "ja_JP",
+        currency_code: "JPY",
+        ..EN_US
+    },
+];
+
+/// Currencies: code, symbol and fraction digits
+const CURRENCIES: &[(&str, &str, usize)] = &[
+    ("USD", "$", 2),
+    ("EUR", "€", 2),
+    ("GBP", "£", 2),
+    ("JPY", "¥", 0),
+    ("BRL", "R$", 2),
+    ("INR", "₹", 2),
+    ("CHF", "CHF", 2),
+    ("CAD", "CA$", 2),
+    ("AUD", "A$", 2),
+    ("CNY", "CN¥", 2),
+    ("KRW", "₩", 0),
+    ("SEK", "SEK", 2),
+];
+
+/// Symbols a locale writes a currency with differently: locale,
+/// currency, symbol
+const LOCAL_SYMBOLS: &[(&str, &str, &str)] = &[
+    ("en_GB", "USD", "US$"),
+    ("en_IN", "USD", "US$"),
+    ("fr_FR", "USD", "$US"),
+    ("pt_BR", "USD", "US$"),
+    ("ja_JP", "JPY", "￥"),
+    ("sv_SE", "SEK", "kr"),
+];
+
+/// The canonical name of a locale, `language_REGION`, from forms like
+/// `de-DE`, `de_DE.UTF-8` or `de_DE@euro`
+pub fn canonical_locale(locale: &str) -> String {
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    let mut parts = locale.split(['_', '-']);
+    let language = parts.next().unwrap_or_default().to_lowercase();
+    match parts.next() {
+        Some(region) if !region.is_empty() => format!("{}_{}", language, region.to_uppercase()),
+        _ => language,
+    }
+}
+
+/// The data of a locale: its own, its language's, or `en_US`'s
+pub(crate) fn locale_data(locale: &str) -> &'static LocaleData {
+    let language = locale.split('_').next().unwrap_or_default();
+    LOCALES
+        .iter()
+        .find(|data| data.name == locale)
+        .or_else(|| LOCALES.iter().find(|data| data.name.starts_with(language)))
+        .unwrap_or(&LOCALES[0])
+}
+
+/// Symbol and fraction digits of a currency in a locale
+pub(crate) fn currency(locale: &LocaleData, code: &str) -> (String, usize) {
+    let code = code.to_uppercase();
+    let (symbol, digits) = CURRENCIES
+        .iter()
+        .find(|(c, _, _)| *c == code)
+        .map_or((code.as_str(), 2), |(_, symbol, digits)| (*symbol, *digits));
+    let symbol = LOCAL_SYMBOLS
+        .iter()
+        .find(|(l, c, _)| *l == locale.name && *c == code)
+        .map_or(symbol, |(_, _, symbol)| *symbol);
+    (symbol.to_string(), digits)
+}
+
+/// Whether setlocale() accepts `locale` for a category: one listed here,
+/// in any of the forms `canonical_locale` reads
+pub(crate) fn is_known(locale: &str) -> bool {
+    let name = canonical_locale(locale);
+    LOCALES.iter().any(|data| data.name == name)
+}
+
+/// Month and weekday names by language, as strftime() writes them: full
+/// months, abbreviated months, full weekdays and abbreviated weekdays,
+/// from January and from Sunday
+const TIME_NAMES: &[(&str, [&str; 4])] = &[
+    (
+        "en",
+        [
+            "January February March April May June July August September October November December",
+            "Jan Feb Mar Apr May Jun Jul Aug Sep Oct Nov Dec",
+            "Sunday Monday Tuesday Wednesday Thursday Friday Saturday",
+            "Sun Mon Tue Wed Thu Fri Sat",
+        ],
+    ),
+    (
+        "de",
+        [
+            "Januar Februar März April Mai Juni Juli August September Oktober November Dezember",
+            "Jan Feb Mär Apr Mai Jun Jul Aug Sep Okt Nov Dez",
+            "Sonntag Montag Dienstag Mittwoch Donnerstag Freitag Samstag",
+            "So Mo Di Mi Do Fr Sa",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "janvier février mars avril mai juin juillet août septembre octobre novembre décembre",
+            "janv. févr. mars avril mai juin juil. août sept. oct. nov. déc.",
+            "dimanche lundi mardi mercredi jeudi vendredi samedi",
+            "dim. lun. mar. mer. jeu. ven. sam.",
+        ],
+    ),
+    (
+        "es",
+        [
+            "enero febrero marzo abril mayo junio julio agosto septiembre octubre noviembre diciembre",
+            "ene feb mar abr may jun jul ago sep oct nov dic",
+            "domingo lunes martes miércoles jueves viernes sábado",
+            "dom lun mar mié jue vie sáb",
+        ],
+    ),
+    (
+        "it",
+        [
+            "gennaio febbraio marzo aprile maggio giugno luglio agosto settembre ottobre novembre dicembre",
+            "gen feb mar apr mag giu lug ago set ott nov dic",
+            "domenica lunedì martedì mercoledì giovedì venerdì sabato",
+            "dom lun mar mer gio ven sab",
+        ],
+    ),
+    (
+        "nl",
+        [
+            "januari februari maart april mei juni juli augustus september oktober november december",
+            "jan feb mrt apr mei jun jul aug sep okt nov dec",
+            "zondag maandag dinsdag woensdag donderdag vrijdag zaterdag",
+            "zo ma di wo do vr za",
+        ],
+    ),
+    (
+        "pt",
+        [
+            "janeiro fevereiro março abril maio junho julho agosto setembro outubro novembro dezembro",
+            "jan fev mar abr mai jun jul ago set out nov dez",
+            "domingo segunda terça quarta quinta sexta sábado",
+            "dom seg ter qua qui sex sáb",
+        ],
+    ),
+    (
+        "sv",
+        [
+            "januari februari mars april maj juni juli augusti september oktober november december",
+            "jan feb mar apr maj jun jul aug sep okt nov dec",
+            "söndag måndag tisdag onsdag torsdag fredag lördag",
+            "sön mån tis ons tor fre lör",
+        ],
+    ),
+];
+
+/// Month (`weekday` false) or weekday name `index` of `locale`, from 0,
+/// in English for the languages not listed
+pub(crate) fn time_name(
+    locale: &str,
+    weekday: bool,
+    abbreviated: bool,
+    index: usize,
+) -> &'static str {
+    let language = locale.split('_').next().unwrap_or_default();
+    let names = TIME_NAMES
+        .iter()
+        .find(|(l, _)| *l == language)
+        .unwrap_or(&TIME_NAMES[0])
+        .1;
+    let list = names[usize::from(weekday) * 2 + usize::from(abbreviated)];
+    list.split(' ').nth(index).unwrap_or_default()
+}
@@ -1,11 +1,14 @@
 //! Math built-in functions
 
-use crate::runtime::Value;
+use crate::runtime::{random, Value};
+use crate::vm::error::NativeError;
 
 /// abs - Absolute value
-pub fn abs(args: &[Value]) -> Result<Value, String> {
+pub fn abs(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("abs() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "abs() expects exactly 1 parameter",
+        ));
     }
     match &args[0] {
         Value::Integer(n) => Ok(Value::Integer(n.abs())),
@@ -15,25 +18,31 @@ pub fn abs(args: &[Value]) -> Result<Value, String> {
 }
 
 /// ceil - Round fractions up
-pub fn ceil(args: &[Value]) -> Result<Value, String> {
+pub fn ceil(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("ceil() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "ceil() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().ceil()))
 }
 
 /// floor - Round fractions down
-pub fn floor(args: &[Value]) -> Result<Value, String> {
+pub fn floor(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("floor() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "floor() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().floor()))
 }
 
 /// round - Rounds a float
-pub fn round(args: &[Value]) -> Result<Value, String> {
+pub fn round(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("round() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "round() expects at least 1 parameter",
+        ));
     }
     let val = args[0].to_float();
     let precision = if args.len() >= 2 {
@@ -46,9 +55,11 @@ pub fn round(args: &[Value]) -> Result<Value, String> {
 }
 
 /// max - Find highest value
-pub fn max(args: &[Value]) -> Result<Value, String> {
+pub fn max(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("max() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "max() expects at least 1 parameter",
+        ));
     }
     let mut max_val = args[0].to_float();
     for arg in args.iter().skip(1) {
@@ -65,9 +76,11 @@ pub fn max(args: &[Value]) -> Result<Value, String> {
 }
 
 /// min - Find lowest value
-pub fn min(args: &[Value]) -> Result<Value, String> {
+pub fn min(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("min() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "min() expects at least 1 parameter",
+        ));
     }
     let mut min_val = args[0].to_float();
     for arg in args.iter().skip(1) {
@@ -84,9 +97,11 @@ pub fn min(args: &[Value]) -> Result<Value, String> {
 }
 
 /// pow - Exponential expression
-pub fn pow(args: &[Value]) -> Result<Value, String> {
+pub fn pow(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("pow() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "pow() expects exactly 2 parameters",
+        ));
     }
     let base = args[0].to_float();
     let exp = args[1].to_float();
@@ -99,21 +114,17 @@ pub fn pow(args: &[Value]) -> Result<Value, String> {
 }
 
 /// sqrt - Square root
-pub fn sqrt(args: &[Value]) -> Result<Value, String> {
+pub fn sqrt(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("sqrt() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "sqrt() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().sqrt()))
 }
 
 /// rand - Generate a random integer
-pub fn rand(args: &[Value]) -> Result<Value, String> {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_nanos())
-        .unwrap_or(0);
-
+pub fn rand(args: &[Value]) -> Result<Value, NativeError> {
     let (min, max) = if args.len() >= 2 {
         (args[0].to_int(), args[1].to_int())
     } else if args.len() == 1 {
@@ -122,9 +133,8 @@ pub fn rand(args: &[Value]) -> Result<Value, String> {
         (0, i32::MAX as i64)
     };
 
-    let range = (max - min + 1) as u128;
-    let random = if range > 0 {
-        min + ((seed % range) as i64)
+    let random = if max > min {
+        random::with_rng(|rng| rng.i64(min..=max))
     } else {
         min
     };
@@ -133,63 +143,77 @@ pub fn rand(args: &[Value]) -> Result<Value, String> {
 }
 
 /// sin - Sine of an angle in radians
-pub fn sin(args: &[Value]) -> Result<Value, String> {
+pub fn sin(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("sin() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "sin() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().sin()))
 }
 
 /// cos - Cosine of an angle in radians
-pub fn cos(args: &[Value]) -> Result<Value, String> {
+pub fn cos(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("cos() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "cos() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().cos()))
 }
 
 /// tan - Tangent of an angle in radians
-pub fn tan(args: &[Value]) -> Result<Value, String> {
+pub fn tan(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("tan() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "tan() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().tan()))
 }
 
 /// log - Natural logarithm
 #[allow(dead_code)]
-pub fn log(args: &[Value]) -> Result<Value, String> {
+pub fn log(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("log() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "log() expects exactly 1 parameter",
+        ));
     }
     let val = args[0].to_float();
     if val <= 0.0 {
-        return Err("log() argument must be greater than 0".to_string());
+        return Err(NativeError::fatal("log() argument must be greater than 0"));
     }
     Ok(Value::Float(val.ln()))
 }
 
 /// log10 - Base-10 logarithm
-pub fn log10(args: &[Value]) -> Result<Value, String> {
+pub fn log10(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("log10() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "log10() expects exactly 1 parameter",
+        ));
     }
     let val = args[0].to_float();
     if val <= 0.0 {
-        return Err("log10() argument must be greater than 0".to_string());
+        return Err(NativeError::fatal(
+            "log10() argument must be greater than 0",
+        ));
     }
     Ok(Value::Float(val.log10()))
 }
 
 /// exp - Exponential function
-pub fn exp(args: &[Value]) -> Result<Value, String> {
+pub fn exp(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("exp() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "exp() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().exp()))
 }
 
 /// pi - Mathematical constant
-pub fn pi(_args: &[Value]) -> Result<Value, String> {
+pub fn pi(_args: &[Value]) -> Result<Value, NativeError> {
     Ok(Value::Float(std::f64::consts::PI))
 }
@@ -2,62 +2,80 @@
 
 #![allow(clippy::manual_range_contains)]
 
-use crate::runtime::Value;
+use crate::runtime::{random, Value};
+use crate::vhp_builtin;
+use crate::vm::error::NativeError;
 
 /// deg2rad - Converts degrees to radians
-pub fn deg2rad(args: &[Value]) -> Result<Value, String> {
+pub fn deg2rad(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("deg2rad() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "deg2rad() expects exactly 1 parameter",
+        ));
     }
     let degrees = args[0].to_float();
     Ok(Value::Float(degrees * std::f64::consts::PI / 180.0))
 }
 
 /// rad2deg - Converts radians to degrees
-pub fn rad2deg(args: &[Value]) -> Result<Value, String> {
+pub fn rad2deg(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("rad2deg() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "rad2deg() expects exactly 1 parameter",
+        ));
     }
     let radians = args[0].to_float();
     Ok(Value::Float(radians * 180.0 / std::f64::consts::PI))
 }
 
 /// asin - Arc sine of a number
-pub fn asin(args: &[Value]) -> Result<Value, String> {
+pub fn asin(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("asin() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "asin() expects exactly 1 parameter",
+        ));
     }
     let val = args[0].to_float();
     if val < -1.0 || val > 1.0 {
-        return Err("asin(): Argument must be in the range [-1, 1]".to_string());
+        return Err(NativeError::fatal(
+            "asin(): Argument must be in the range [-1, 1]",
+        ));
     }
     Ok(Value::Float(val.asin()))
 }
 
 /// acos - Arc cosine of a number
-pub fn acos(args: &[Value]) -> Result<Value, String> {
+pub fn acos(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("acos() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "acos() expects exactly 1 parameter",
+        ));
     }
     let val = args[0].to_float();
     if val < -1.0 || val > 1.0 {
-        return Err("acos(): Argument must be in the range [-1, 1]".to_string());
+        return Err(NativeError::fatal(
+            "acos(): Argument must be in the range [-1, 1]",
+        ));
     }
     Ok(Value::Float(val.acos()))
 }
 
 /// atan - Arc tangent of a number
-pub fn atan(args: &[Value]) -> Result<Value, String> {
+pub fn atan(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("atan() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "atan() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().atan()))
 }
 
 /// atan2 - Arc tangent of two numbers
-pub fn atan2(args: &[Value]) -> Result<Value, String> {
+pub fn atan2(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("atan2() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "atan2() expects exactly 2 parameters",
+        ));
     }
     let y = args[0].to_float();
     let x = args[1].to_float();
@@ -65,33 +83,41 @@ pub fn atan2(args: &[Value]) -> Result<Value, String> {
 }
 
 /// sinh - Hyperbolic sine
-pub fn sinh(args: &[Value]) -> Result<Value, String> {
+pub fn sinh(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("sinh() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "sinh() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().sinh()))
 }
 
 /// cosh - Hyperbolic cosine
-pub fn cosh(args: &[Value]) -> Result<Value, String> {
+pub fn cosh(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("cosh() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "cosh() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().cosh()))
 }
 
 /// tanh - Hyperbolic tangent
-pub fn tanh(args: &[Value]) -> Result<Value, String> {
+pub fn tanh(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("tanh() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "tanh() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float().tanh()))
 }
 
 /// hypot - Calculate the length of the hypotenuse
-pub fn hypot(args: &[Value]) -> Result<Value, String> {
+pub fn hypot(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("hypot() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "hypot() expects exactly 2 parameters",
+        ));
     }
     let x = args[0].to_float();
     let y = args[1].to_float();
@@ -99,59 +125,74 @@ pub fn hypot(args: &[Value]) -> Result<Value, String> {
 }
 
 /// fmod - Returns the floating point remainder of a division
-pub fn fmod(args: &[Value]) -> Result<Value, String> {
+pub fn fmod(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("fmod() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "fmod() expects exactly 2 parameters",
+        ));
     }
     let x = args[0].to_float();
     let y = args[1].to_float();
     if y == 0.0 {
-        return Err("fmod(): Division by zero".to_string());
+        return Err(NativeError::fatal("fmod(): Division by zero"));
     }
     Ok(Value::Float(x - (x / y).floor() * y))
 }
 
 /// intdiv - Integer division
-pub fn intdiv(args: &[Value]) -> Result<Value, String> {
+pub fn intdiv(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("intdiv() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "intdiv() expects exactly 2 parameters",
+        ));
     }
     let dividend = args[0].to_int();
     let divisor = args[1].to_int();
     if divisor == 0 {
-        return Err("intdiv(): Division by zero".to_string());
+        return Err(NativeError::exception(
+            "DivisionByZeroError",
+            "Division by zero",
+        ));
     }
     Ok(Value::Integer(dividend / divisor))
 }
 
 /// is_finite - Checks if a float is finite
-pub fn is_finite(args: &[Value]) -> Result<Value, String> {
+pub fn is_finite(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_finite() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_finite() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(args[0].to_float().is_finite()))
 }
 
 /// is_infinite - Checks if a float is infinite
-pub fn is_infinite(args: &[Value]) -> Result<Value, String> {
+pub fn is_infinite(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_infinite() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_infinite() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(args[0].to_float().is_infinite()))
 }
 
 /// is_nan - Checks if a float is not a number
-pub fn is_nan(args: &[Value]) -> Result<Value, String> {
+pub fn is_nan(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_nan() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_nan() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(args[0].to_float().is_nan()))
 }
 
 /// bindec - Binary to decimal
-pub fn bindec(args: &[Value]) -> Result<Value, String> {
+pub fn bindec(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("bindec() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "bindec() expects exactly 1 parameter",
+        ));
     }
     let binary_str = args[0].to_string_val();
     match i64::from_str_radix(&binary_str, 2) {
@@ -161,36 +202,44 @@ pub fn bindec(args: &[Value]) -> Result<Value, String> {
 }
 
 /// decbin - Decimal to binary
-pub fn decbin(args: &[Value]) -> Result<Value, String> {
+pub fn decbin(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("decbin() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "decbin() expects exactly 1 parameter",
+        ));
     }
     let n = args[0].to_int();
     Ok(Value::String(format!("{:b}", n)))
 }
 
 /// decoct - Decimal to octal
-pub fn decoct(args: &[Value]) -> Result<Value, String> {
+pub fn decoct(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("decoct() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "decoct() expects exactly 1 parameter",
+        ));
     }
     let n = args[0].to_int();
     Ok(Value::String(format!("{:o}", n)))
 }
 
 /// dechex - Decimal to hexadecimal
-pub fn dechex(args: &[Value]) -> Result<Value, String> {
+pub fn dechex(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("dechex() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "dechex() expects exactly 1 parameter",
+        ));
     }
     let n = args[0].to_int();
     Ok(Value::String(format!("{:x}", n)))
 }
 
 /// hexdec - Hexadecimal to decimal
-pub fn hexdec(args: &[Value]) -> Result<Value, String> {
+pub fn hexdec(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("hexdec() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "hexdec() expects exactly 1 parameter",
+        ));
     }
     let hex_str = args[0].to_string_val();
     match i64::from_str_radix(&hex_str, 16) {
@@ -200,9 +249,11 @@ pub fn hexdec(args: &[Value]) -> Result<Value, String> {
 }
 
 /// octdec - Octal to decimal
-pub fn octdec(args: &[Value]) -> Result<Value, String> {
+pub fn octdec(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("octdec() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "octdec() expects exactly 1 parameter",
+        ));
     }
     let oct_str = args[0].to_string_val();
     match i64::from_str_radix(&oct_str, 8) {
@@ -212,16 +263,20 @@ pub fn octdec(args: &[Value]) -> Result<Value, String> {
 }
 
 /// base_convert - Convert a number between arbitrary bases
-pub fn base_convert(args: &[Value]) -> Result<Value, String> {
+pub fn base_convert(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 3 {
-        return Err("base_convert() expects at least 3 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "base_convert() expects at least 3 parameters",
+        ));
     }
     let num = args[0].to_string_val();
     let from_base = args[1].to_int() as u32;
     let to_base = args[2].to_int() as u32;
 
     if from_base < 2 || from_base > 36 || to_base < 2 || to_base > 36 {
-        return Err("base_convert(): Invalid base (must be between 2 and 36)".to_string());
+        return Err(NativeError::fatal(
+            "base_convert(): Invalid base (must be between 2 and 36)",
+        ));
     }
 
     match i64::from_str_radix(&num, from_base) {
@@ -231,38 +286,52 @@ pub fn base_convert(args: &[Value]) -> Result<Value, String> {
 }
 
 /// getrandmax - Returns the maximum value that can be returned by rand()
-pub fn getrandmax(_args: &[Value]) -> Result<Value, String> {
+pub fn getrandmax(_args: &[Value]) -> Result<Value, NativeError> {
     Ok(Value::Integer(i32::MAX as i64))
 }
 
 /// getrandseed - Get random seed (returns 0 in VHP)
 #[allow(dead_code)]
-pub fn getrandseed(_args: &[Value]) -> Result<Value, String> {
+pub fn getrandseed(_args: &[Value]) -> Result<Value, NativeError> {
     Ok(Value::Integer(0))
 }
 
-/// srand - Seed the random number generator (no-op in VHP)
-pub fn srand(_args: &[Value]) -> Result<Value, String> {
-    Ok(Value::Null)
+/// srand - Seed the random number generator (alias of mt_srand)
+pub fn srand(args: &[Value]) -> Result<Value, NativeError> {
+    mt_srand(args)
 }
 
-/// mt_srand - Seed the mt random number generator (no-op in VHP)
-pub fn mt_srand(_args: &[Value]) -> Result<Value, String> {
+/// mt_srand - Seed the random number generator, from the clock when no
+/// seed is given
+pub fn mt_srand(args: &[Value]) -> Result<Value, NativeError> {
+    random::seed(args.first().map(|seed| seed.to_int() as u64));
     Ok(Value::Null)
 }
 
 /// mt_getrandmax - Returns the maximum value that can be returned by mt_rand()
-pub fn mt_getrandmax(_args: &[Value]) -> Result<Value, String> {
+pub fn mt_getrandmax(_args: &[Value]) -> Result<Value, NativeError> {
     Ok(Value::Integer(i32::MAX as i64))
 }
 
 /// lcg_value - Linear congruential generator value
-pub fn lcg_value(_args: &[Value]) -> Result<Value, String> {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-    let val = (seed % 1000000) as f64 / 1000000.0;
-    Ok(Value::Float(val))
+pub fn lcg_value(_args: &[Value]) -> Result<Value, NativeError> {
+    Ok(Value::Float(random::with_rng(|rng| rng.f64())))
+}
+
+/// fdiv - Divides two numbers according to IEEE 754 (INF or NAN on zero)
+#[vhp_builtin("fdiv")]
+pub fn fdiv(num1: f64, num2: f64) -> f64 {
+    num1 / num2
+}
+
+/// log1p - log(1 + num), accurate even when num is close to zero
+#[vhp_builtin("log1p")]
+pub fn log1p(num: f64) -> f64 {
+    num.ln_1p()
+}
+
+/// expm1 - exp(num) - 1, accurate even when num is close to zero
+#[vhp_builtin("expm1")]
+pub fn expm1(num: f64) -> f64 {
+    num.exp_m1()
 }
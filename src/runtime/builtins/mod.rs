@@ -14,23 +14,68 @@ pub mod array_set_ops;
 pub mod array_sort_key;
 pub mod array_sort_value;
 pub mod array_sorting;
+pub mod collator;
 pub mod datetime;
 pub mod datetime_format;
 pub mod datetime_timestamp;
+pub mod encoding;
+pub mod env;
+pub mod export;
 pub mod fileio;
+pub mod highlight;
+pub mod ini;
+pub mod ini_expression;
+pub mod ini_parser;
+pub mod intl;
 pub mod json;
+pub mod locale;
+pub mod locale_data;
 pub mod math;
 pub mod math_extra;
+pub mod native;
 pub mod output;
 pub mod pcre;
 pub mod spl;
 pub mod string;
 pub mod string_extra;
+pub mod tokenizer;
 pub mod type_extra;
 pub mod types;
+pub mod xml;
+pub mod xml_parser;
+pub mod xpath;
+pub mod xpath_parser;
+pub mod yaml;
+pub mod yaml_parser;
+pub mod yaml_scanner;
 
 pub use json::{json_decode, json_encode};
 
+/// Built-ins declared with `#[vhp_builtin]`, dispatched by `native::declared`
+pub const DECLARED_BUILTINS: &[native::Builtin] = &[
+    // Math functions
+    math_extra::FDIV,
+    math_extra::LOG1P,
+    math_extra::EXPM1,
+    // String functions
+    string_extra::SUBSTR_COUNT,
+    string_extra::STRPBRK,
+    // Array functions
+    array_callbacks::ARRAY_PRODUCT,
+    array_search::ARRAY_IS_LIST,
+    array_search::ARRAY_KEY_FIRST,
+    array_search::ARRAY_KEY_LAST,
+];
+
+/// The value of a core constant: an error level, a locale category, a
+/// token id or a PCRE flag
+pub fn core_constant(name: &str) -> Option<crate::runtime::Value> {
+    ini::error_constant(name)
+        .or_else(|| locale::constant(name))
+        .or_else(|| tokenizer::constant(name))
+        .or_else(|| pcre::constant(name))
+}
+
 #[allow(unused_imports)]
 pub use array_basic::{
     array_first, array_keys, array_last, array_pop, array_push, array_shift, array_slice,
@@ -38,7 +83,7 @@ pub use array_basic::{
 };
 
 #[allow(unused_imports)]
-pub use array_callbacks::{array_filter, array_map, array_reduce, array_sum};
+pub use array_callbacks::array_sum;
 
 #[allow(unused_imports)]
 pub use array_chunking::{array_chunk, array_pad, array_splice};
@@ -92,6 +137,7 @@ pub use fileio::{
 pub use spl::{
     get_include_path, set_include_path, spl_autoload_functions, spl_autoload_register,
     spl_autoload_register_psr4, spl_autoload_registered_psr4, spl_autoload_unregister,
+    spl_object_hash, spl_object_id,
 };
 
 #[allow(unused_imports)]
@@ -102,14 +148,13 @@ pub use pcre::{
 
 #[allow(unused_imports)]
 pub use types::{
-    boolval, empty, floatval, gettype, intval, is_array, is_bool, is_callable, is_float, is_int,
-    is_null, is_numeric, is_string, isset, strval, unset,
+    boolval, empty, floatval, gettype, intval, is_array, is_bool, is_float, is_int, is_null,
+    is_numeric, is_string, isset, strval, unset,
 };
 
 #[allow(unused_imports)]
 pub use type_extra::{
-    class_alias, class_exists, func_get_arg, func_get_args, func_num_args, get_class,
-    get_class_methods, get_class_vars, get_declared_classes, get_declared_interfaces,
-    get_declared_traits, get_defined_functions, get_object_vars, get_parent_class,
-    interface_exists, is_a, is_subclass_of, method_exists, property_exists, trait_exists,
+    class_alias, func_get_arg, func_get_args, func_num_args, get_class, get_class_vars,
+    get_declared_classes, get_declared_interfaces, get_declared_traits, get_defined_functions,
+    is_a, is_subclass_of,
 };
@@ -0,0 +1,298 @@
+//! Support for built-ins declared with `#[vhp_builtin]`
+//!
+//! The attribute turns a typed Rust function into a `Builtin` whose
+//! wrapper validates and converts the PHP arguments with the helpers
+//! below. Conversions follow PHP's coercive typing mode: an `int`
+//! parameter takes `5`, `5.0`, `"5"` or `true`, but rejects arrays and
+//! non-numeric strings with a "must be of type" error. Null still
+//! coerces to a scalar parameter, which PHP only deprecates.
+//!
+//! Extensions declare their functions the same way:
+//!
+//! ```
+//! use vhp::runtime::builtins::native::Variadic;
+//! use vhp::vhp_builtin;
+//! use vhp::vm::error::NativeError;
+//! use vhp::vm::VM;
+//!
+//! /// Joins its arguments with `separator`
+//! #[vhp_builtin("glue")]
+//! fn glue(separator: String, pieces: Variadic<String>) -> String {
+//!     pieces.0.join(&separator)
+//! }
+//!
+//! let compilation = vhp::compile("<?php echo glue('-', 'a', 1, 2.5);", "x.php").unwrap();
+//! let mut output = Vec::new();
+//! let mut vm = VM::new(&mut output);
+//! vm.registry_mut().add_builtin(&GLUE);
+//! let main = vm.load(compilation);
+//! vm.execute(main).unwrap();
+//! assert_eq!(output, b"a-1-2.5");
+//!
+//! let error = (GLUE.call)(&[]).unwrap_err();
+//! assert_eq!(
+//!     error,
+//!     NativeError::argument_count_error("glue() expects at least 1 argument, 0 given")
+//! );
+//! ```
+
+use crate::runtime::{PhpArray, Value};
+use crate::vm::error::NativeError;
+
+/// A built-in declared with `#[vhp_builtin]`
+#[derive(Clone, Copy)]
+pub struct Builtin {
+    /// Lowercase PHP name
+    pub name: &'static str,
+    /// Wrapper taking the PHP arguments
+    pub call: fn(&[Value]) -> Result<Value, NativeError>,
+}
+
+/// The remaining arguments, for a last `...$values` parameter
+pub struct Variadic<T>(pub Vec<T>);
+
+/// A PHP array, as built-ins receive and return it
+pub type Array = PhpArray;
+
+/// Conversion of a PHP argument to a parameter type
+pub trait FromArg: Sized {
+    /// PHP type named in errors
+    const TYPE: &'static str;
+
+    /// Convert a passed value, or None if it can't be coerced
+    fn from_value(value: &Value) -> Option<Self>;
+
+    /// Convert an argument that may have been left out
+    fn from_arg(value: Option<&Value>) -> Option<Self> {
+        value.and_then(Self::from_value)
+    }
+}
+
+impl FromArg for Value {
+    const TYPE: &'static str = "mixed";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromArg for bool {
+    const TYPE: &'static str = "bool";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null
+            | Value::Bool(_)
+            | Value::Integer(_)
+            | Value::Float(_)
+            | Value::String(_) => Some(value.to_bool()),
+            _ => None,
+        }
+    }
+}
+
+impl FromArg for i64 {
+    const TYPE: &'static str = "int";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null | Value::Bool(_) | Value::Integer(_) => Some(value.to_int()),
+            Value::Float(f) if f.is_finite() => Some(*f as i64),
+            Value::String(s) if is_numeric(s) => Some(value.to_int()),
+            _ => None,
+        }
+    }
+}
+
+impl FromArg for f64 {
+    const TYPE: &'static str = "float";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Float(_) => {
+                Some(value.to_float())
+            }
+            Value::String(s) if is_numeric(s) => Some(value.to_float()),
+            _ => None,
+        }
+    }
+}
+
+impl FromArg for String {
+    const TYPE: &'static str = "string";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null
+            | Value::Bool(_)
+            | Value::Integer(_)
+            | Value::Float(_)
+            | Value::String(_) => Some(value.to_string_val()),
+            _ => None,
+        }
+    }
+}
+
+impl FromArg for Array {
+    const TYPE: &'static str = "array";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Array(array) => Some(array.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// An optional parameter: None when left out or passed null
+impl<T: FromArg> FromArg for Option<T> {
+    const TYPE: &'static str = T::TYPE;
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+
+    fn from_arg(value: Option<&Value>) -> Option<Self> {
+        match value {
+            None => Some(None),
+            Some(value) => Self::from_value(value),
+        }
+    }
+}
+
+/// Conversion of a built-in's result to a PHP value
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Null
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::Integer(self)
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Float(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoValue for Array {
+    fn into_value(self) -> Value {
+        Value::Array(self)
+    }
+}
+
+/// `None` becomes null
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or(Value::Null, IntoValue::into_value)
+    }
+}
+
+/// Fail unless `given` arguments fit between `min` and `max`
+pub fn check_arity(
+    name: &str,
+    given: usize,
+    min: usize,
+    max: Option<usize>,
+) -> Result<(), NativeError> {
+    let (bound, expected) = match max {
+        _ if given < min && max == Some(min) => ("exactly", min),
+        _ if given < min => ("at least", min),
+        Some(max) if given > max && max == min => ("exactly", max),
+        Some(max) if given > max => ("at most", max),
+        _ => return Ok(()),
+    };
+    Err(NativeError::argument_count_error(format!(
+        "{}() expects {} {} argument{}, {} given",
+        name,
+        bound,
+        expected,
+        if expected == 1 { "" } else { "s" },
+        given
+    )))
+}
+
+/// Convert argument `index` (named `param`) of `name`
+pub fn arg<T: FromArg>(
+    name: &str,
+    args: &[Value],
+    index: usize,
+    param: &str,
+) -> Result<T, NativeError> {
+    let value = args.get(index);
+    T::from_arg(value).ok_or_else(|| type_error::<T>(name, index, param, value))
+}
+
+/// Convert the arguments from `index` on, for a variadic parameter
+pub fn variadic<T: FromArg>(
+    name: &str,
+    args: &[Value],
+    index: usize,
+    param: &str,
+) -> Result<Variadic<T>, NativeError> {
+    let values = args.get(index..).unwrap_or_default();
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            T::from_value(value).ok_or_else(|| type_error::<T>(name, index + i, param, Some(value)))
+        })
+        .collect::<Result<_, _>>()
+        .map(Variadic)
+}
+
+fn type_error<T: FromArg>(
+    name: &str,
+    index: usize,
+    param: &str,
+    value: Option<&Value>,
+) -> NativeError {
+    NativeError::type_error(format!(
+        "{}(): Argument #{} (${}) must be of type {}, {} given",
+        name,
+        index + 1,
+        param,
+        T::TYPE,
+        value.map_or("null", Value::type_name)
+    ))
+}
+
+/// The declared core built-in called `name` (lowercase)
+pub fn declared(name: &str) -> Option<&'static Builtin> {
+    super::DECLARED_BUILTINS.iter().find(|b| b.name == name)
+}
+
+/// Whether `s` is a numeric string (leading and trailing whitespace allowed)
+fn is_numeric(s: &str) -> bool {
+    let s = s.trim();
+    !s.is_empty() && s.parse::<f64>().is_ok() && !s.contains(['i', 'I', 'n', 'N'])
+}
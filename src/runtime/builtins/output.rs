@@ -1,103 +1,101 @@
 //! Output built-in functions
 
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
 use std::io::Write;
 
 /// print - Output a string
-pub fn print<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, String> {
+pub fn print<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("print() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "print() expects exactly 1 parameter",
+        ));
     }
-    write!(output, "{}", args[0].to_output_string()).map_err(|e| e.to_string())?;
+    write!(output, "{}", args[0].to_output_string())?;
     Ok(Value::Integer(1))
 }
 
 /// var_dump - Dumps information about a variable
-pub fn var_dump<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, String> {
+pub fn var_dump<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, NativeError> {
     for arg in args {
-        var_dump_value(output, arg, 0)?;
+        var_dump_value(output, arg, 0, &mut Vec::new())?;
     }
     Ok(Value::Null)
 }
 
-fn var_dump_value<W: Write>(output: &mut W, value: &Value, indent: usize) -> Result<(), String> {
+/// `objects` are the ids of the objects being dumped, around `value`
+fn var_dump_value<W: Write>(
+    output: &mut W,
+    value: &Value,
+    indent: usize,
+    objects: &mut Vec<usize>,
+) -> std::io::Result<()> {
     let prefix = "  ".repeat(indent);
     match value {
+        Value::Reference(r) => var_dump_value(output, &r.get(), indent, objects)?,
         Value::Null => {
-            writeln!(output, "{}NULL", prefix).map_err(|e| e.to_string())?;
+            writeln!(output, "{}NULL", prefix)?;
         }
         Value::Bool(b) => {
-            writeln!(output, "{}bool({})", prefix, b).map_err(|e| e.to_string())?;
+            writeln!(output, "{}bool({})", prefix, b)?;
         }
         Value::Integer(n) => {
-            writeln!(output, "{}int({})", prefix, n).map_err(|e| e.to_string())?;
+            writeln!(output, "{}int({})", prefix, n)?;
         }
         Value::Float(n) => {
-            writeln!(output, "{}float({})", prefix, n).map_err(|e| e.to_string())?;
+            writeln!(output, "{}float({})", prefix, n)?;
         }
         Value::String(s) => {
-            writeln!(output, "{}string({}) \"{}\"", prefix, s.len(), s)
-                .map_err(|e| e.to_string())?;
+            writeln!(output, "{}string({}) \"{}\"", prefix, s.len(), s)?;
         }
         Value::Array(arr) => {
-            writeln!(output, "{}array({}) {{", prefix, arr.len()).map_err(|e| e.to_string())?;
+            writeln!(output, "{}array({}) {{", prefix, arr.len())?;
             for (key, val) in arr {
                 match key {
                     ArrayKey::Integer(n) => {
-                        writeln!(output, "{}  [{}]=>", prefix, n).map_err(|e| e.to_string())?;
+                        writeln!(output, "{}  [{}]=>", prefix, n)?;
                     }
                     ArrayKey::String(s) => {
-                        writeln!(output, "{}  [\"{}\"]=>", prefix, s).map_err(|e| e.to_string())?;
+                        writeln!(output, "{}  [\"{}\"]=>", prefix, s)?;
                     }
                 }
-                var_dump_value(output, val, indent + 1)?;
+                var_dump_value(output, val, indent + 1, objects)?;
             }
-            writeln!(output, "{}}}", prefix).map_err(|e| e.to_string())?;
+            writeln!(output, "{}}}", prefix)?;
+        }
+        Value::Object(obj) if objects.contains(&obj.id()) => {
+            writeln!(output, "{}*RECURSION*", prefix)?;
         }
         Value::Object(obj) => {
+            let properties = obj.properties();
             writeln!(
                 output,
-                "{}object({})#1 ({}) {{",
+                "{}object({})#{} ({}) {{",
                 prefix,
-                obj.class_name,
-                obj.properties.len()
-            )
-            .map_err(|e| e.to_string())?;
-            for (key, val) in &obj.properties {
-                writeln!(output, "{}  [\"{}\"]=>", prefix, key).map_err(|e| e.to_string())?;
-                var_dump_value(output, val, indent + 1)?;
+                obj.class_name(),
+                obj.id(),
+                properties.len()
+            )?;
+            objects.push(obj.id());
+            for (key, val) in &properties {
+                writeln!(output, "{}  [\"{}\"]=>", prefix, key)?;
+                var_dump_value(output, val, indent + 1, objects)?;
             }
-            writeln!(output, "{}}}", prefix).map_err(|e| e.to_string())?;
+            objects.pop();
+            writeln!(output, "{}}}", prefix)?;
         }
         Value::Fiber(fiber) => {
-            writeln!(output, "{}object(Fiber#{:06}) {{", prefix, fiber.id)
-                .map_err(|e| e.to_string())?;
-            writeln!(output, "{}  state: {:?}", prefix, fiber.state).map_err(|e| e.to_string())?;
-            writeln!(output, "{}}}", prefix).map_err(|e| e.to_string())?;
+            writeln!(output, "{}object(Fiber#{:06}) {{", prefix, fiber.id)?;
+            writeln!(output, "{}  state: {:?}", prefix, fiber.state)?;
+            writeln!(output, "{}}}", prefix)?;
         }
         Value::Closure(_) => {
-            writeln!(output, "{}object(Closure)#1 {{", prefix).map_err(|e| e.to_string())?;
-            writeln!(output, "{}}}", prefix).map_err(|e| e.to_string())?;
+            writeln!(output, "{}object(Closure)#1 {{", prefix)?;
+            writeln!(output, "{}}}", prefix)?;
         }
         Value::Generator(gen) => {
-            writeln!(output, "{}object(Generator)#{:06} {{", prefix, gen.id)
-                .map_err(|e| e.to_string())?;
-            writeln!(
-                output,
-                "{}  yielded_values: {}",
-                prefix,
-                gen.yielded_values.len()
-            )
-            .map_err(|e| e.to_string())?;
-            writeln!(output, "{}  current_index: {}", prefix, gen.current_index)
-                .map_err(|e| e.to_string())?;
-            writeln!(output, "{}  finished: {}", prefix, gen.finished)
-                .map_err(|e| e.to_string())?;
-            if let Some(ref ret) = gen.return_value {
-                writeln!(output, "{}  return_value: {:?}", prefix, ret)
-                    .map_err(|e| e.to_string())?;
-            }
-            writeln!(output, "{}}}", prefix).map_err(|e| e.to_string())?;
+            writeln!(output, "{}object(Generator)#{} (0) {{", prefix, gen.id)?;
+            writeln!(output, "{}}}", prefix)?;
         }
         Value::EnumCase {
             enum_name,
@@ -105,17 +103,14 @@ fn var_dump_value<W: Write>(output: &mut W, value: &Value, indent: usize) -> Res
             backing_value,
         } => {
             if let Some(val) = backing_value {
-                writeln!(output, "{}enum({}::{}): ", prefix, enum_name, case_name)
-                    .map_err(|e| e.to_string())?;
-                var_dump_value(output, val, indent)?;
+                writeln!(output, "{}enum({}::{}): ", prefix, enum_name, case_name)?;
+                var_dump_value(output, val, indent, objects)?;
             } else {
-                writeln!(output, "{}enum({}::{})", prefix, enum_name, case_name)
-                    .map_err(|e| e.to_string())?;
+                writeln!(output, "{}enum({}::{})", prefix, enum_name, case_name)?;
             }
         }
         Value::Exception(exc) => {
-            writeln!(output, "{}object({})#1 (2) {{", prefix, exc.class_name)
-                .map_err(|e| e.to_string())?;
+            writeln!(output, "{}object({})#1 (2) {{", prefix, exc.class_name)?;
             writeln!(
                 output,
                 "{}  [\"message\"]=>\n{}  string({}) \"{}\"",
@@ -123,38 +118,39 @@ fn var_dump_value<W: Write>(output: &mut W, value: &Value, indent: usize) -> Res
                 prefix,
                 exc.message.len(),
                 exc.message
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
             writeln!(
                 output,
                 "{}  [\"code\"]=>\n{}  int({})",
                 prefix, prefix, exc.code
-            )
-            .map_err(|e| e.to_string())?;
-            writeln!(output, "{}}}", prefix).map_err(|e| e.to_string())?;
+            )?;
+            writeln!(output, "{}}}", prefix)?;
         }
     }
     Ok(())
 }
 
 /// print_r - Prints human-readable information about a variable
-pub fn print_r<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, String> {
+pub fn print_r<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("print_r() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "print_r() expects at least 1 parameter",
+        ));
     }
     let return_output = args.len() >= 2 && args[1].to_bool();
 
-    let out = print_r_value(&args[0], 0);
+    let out = print_r_value(&args[0], 0, &mut Vec::new());
 
     if return_output {
         Ok(Value::String(out))
     } else {
-        write!(output, "{}", out).map_err(|e| e.to_string())?;
+        write!(output, "{}", out)?;
         Ok(Value::Bool(true))
     }
 }
 
-fn print_r_value(value: &Value, indent: usize) -> String {
+/// `objects` are the ids of the objects being printed, around `value`
+fn print_r_value(value: &Value, indent: usize, objects: &mut Vec<usize>) -> String {
     let prefix = "    ".repeat(indent);
     match value {
         Value::Array(arr) => {
@@ -165,7 +161,7 @@ fn print_r_value(value: &Value, indent: usize) -> String {
                     ArrayKey::Integer(n) => n.to_string(),
                     ArrayKey::String(s) => s.clone(),
                 };
-                let val_str = print_r_value(val, indent + 1);
+                let val_str = print_r_value(val, indent + 1, objects);
                 result.push_str(&format!(
                     "{}    [{}] => {}\n",
                     prefix,
@@ -176,11 +172,15 @@ fn print_r_value(value: &Value, indent: usize) -> String {
             result.push_str(&format!("{})\n", prefix));
             result
         }
+        Value::Object(obj) if objects.contains(&obj.id()) => {
+            format!("{} Object\n *RECURSION*", obj.class_name())
+        }
         Value::Object(obj) => {
-            let mut result = format!("{} Object\n", obj.class_name);
+            let mut result = format!("{} Object\n", obj.class_name());
             result.push_str(&format!("{}(\n", prefix));
-            for (key, val) in &obj.properties {
-                let val_str = print_r_value(val, indent + 1);
+            objects.push(obj.id());
+            for (key, val) in &obj.properties() {
+                let val_str = print_r_value(val, indent + 1, objects);
                 result.push_str(&format!(
                     "{}    [{}] => {}\n",
                     prefix,
@@ -188,6 +188,7 @@ fn print_r_value(value: &Value, indent: usize) -> String {
                     val_str.trim_start()
                 ));
             }
+            objects.pop();
             result.push_str(&format!("{})\n", prefix));
             result
         }
@@ -196,9 +197,9 @@ fn print_r_value(value: &Value, indent: usize) -> String {
 }
 
 /// printf - Output a formatted string
-pub fn printf<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, String> {
+pub fn printf<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, NativeError> {
     let result = super::string::sprintf(args)?;
-    write!(output, "{}", result.to_string_val()).map_err(|e| e.to_string())?;
+    write!(output, "{}", result.to_string_val())?;
     Ok(Value::Integer(result.to_string_val().len() as i64))
 }
 
@@ -208,14 +209,16 @@ pub fn printf<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, String>
 /// - No arguments: terminate with exit code 0
 /// - String argument: output the string and terminate with exit code 0
 /// - Integer argument: terminate with that exit code
-pub fn exit<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, String> {
+///
+/// Gives the exit code, which the VM stops the script with.
+pub fn exit<W: Write>(output: &mut W, args: &[Value]) -> Result<i32, NativeError> {
     let exit_code = match args.len() {
         0 => 0,
         1 => {
             match &args[0] {
                 Value::String(s) => {
                     // Output the string and exit with code 0
-                    write!(output, "{}", s).map_err(|e| e.to_string())?;
+                    write!(output, "{}", s)?;
                     0
                 }
                 Value::Integer(n) => {
@@ -248,21 +251,22 @@ pub fn exit<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, String> {
                 }
                 _ => {
                     // Other types: convert to string and output, exit with code 0
-                    write!(output, "{}", args[0].to_output_string()).map_err(|e| e.to_string())?;
+                    write!(output, "{}", args[0].to_output_string())?;
                     0
                 }
             }
         }
         _ => {
-            return Err("exit() expects at most 1 parameter".to_string());
+            return Err(NativeError::argument_count_error(
+                "exit() expects at most 1 parameter",
+            ));
         }
     };
 
-    // Return special error to signal script termination
-    Err(format!("__EXIT__:{}", exit_code))
+    Ok(exit_code as i32)
 }
 
 /// die - Alias for exit()
-pub fn die<W: Write>(output: &mut W, args: &[Value]) -> Result<Value, String> {
+pub fn die<W: Write>(output: &mut W, args: &[Value]) -> Result<i32, NativeError> {
     exit(output, args)
 }
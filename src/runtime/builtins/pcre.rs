@@ -1,13 +1,16 @@
 use crate::runtime::value::array_key::ArrayKey;
-use crate::runtime::Value;
+use crate::runtime::{PhpArray, Value};
+use crate::vm::error::NativeError;
 
 fn value_to_string_val(v: &Value) -> String {
     v.to_string_val()
 }
 
-pub fn preg_quote(args: &[Value]) -> Result<Value, String> {
+pub fn preg_quote(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 1 {
-        return Err("preg_quote() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "preg_quote() expects at least 1 parameter",
+        ));
     }
     let str = value_to_string_val(&args[0]);
     let delimiter = if args.len() > 1 {
@@ -43,68 +46,81 @@ pub fn preg_quote(args: &[Value]) -> Result<Value, String> {
     Ok(Value::String(result))
 }
 
-pub fn preg_match(args: &[Value]) -> Result<Value, String> {
-    if args.len() < 2 {
-        return Err("preg_match() expects at least 2 parameters".to_string());
+/// `PREG_PATTERN_ORDER`: preg_match_all() lists each group's matches
+const PREG_PATTERN_ORDER: i64 = 1;
+/// `PREG_SET_ORDER`: preg_match_all() lists each match's groups
+const PREG_SET_ORDER: i64 = 2;
+/// `PREG_OFFSET_CAPTURE`: each group comes with its byte offset
+const PREG_OFFSET_CAPTURE: i64 = 256;
+
+/// The value of a `PREG_*` constant
+pub fn constant(name: &str) -> Option<Value> {
+    match name {
+        "PREG_PATTERN_ORDER" => Some(Value::Integer(PREG_PATTERN_ORDER)),
+        "PREG_SET_ORDER" => Some(Value::Integer(PREG_SET_ORDER)),
+        "PREG_OFFSET_CAPTURE" => Some(Value::Integer(PREG_OFFSET_CAPTURE)),
+        _ => None,
     }
-    let pattern = value_to_string_val(&args[0]);
-    let subject = value_to_string_val(&args[1]);
-    let _flags = if args.len() > 3 { args[3].to_int() } else { 0 };
-    let offset = if args.len() > 4 { args[4].to_int() } else { 0 };
-
-    let (php_pattern, regex_flags) = parse_pattern(&pattern);
-
-    let re = regex::RegexBuilder::new(&php_pattern)
-        .case_insensitive(regex_flags.ignore_case)
-        .multi_line(regex_flags.multi_line)
-        .dot_matches_new_line(regex_flags.dot_all)
-        .build()
-        .map_err(|_| "Invalid regex pattern")?;
+}
 
-    let start = if offset < 0 {
-        if offset.abs() as usize > subject.len() {
-            return Ok(Value::Integer(0));
-        }
-        subject.len() - offset.abs() as usize
-    } else {
-        offset as usize
-    };
+/// Write `matches` through the by-reference `$matches` argument, if the
+/// call passed one
+fn set_matches(args: &[Value], matches: PhpArray) {
+    if let Some(Value::Reference(reference)) = args.get(2) {
+        reference.set(Value::Array(matches));
+    }
+}
 
-    if start > subject.len() {
-        return Ok(Value::Integer(0));
+/// A group as `$matches` holds it: its text, or its text and offset
+fn group_value(text: &str, offset: usize, offset_capture: bool) -> Value {
+    let text = Value::String(text.to_string());
+    match offset_capture {
+        true => Value::Array([text, Value::Integer(offset as i64)].into_iter().collect()),
+        false => text,
     }
+}
 
-    let subject_sub = &subject[start..];
-
-    if let Some(m) = re.find(subject_sub) {
-        if args.len() > 2 && args[2] != Value::Null {
-            let mut matches_array: Vec<(ArrayKey, Value)> = Vec::new();
-            matches_array.push((ArrayKey::Integer(0), Value::String(m.as_str().to_string())));
-            matches_array.push((
-                ArrayKey::Integer(1),
-                Value::String((m.start() as i64 + start as i64).to_string()),
-            ));
-            matches_array.push((
-                ArrayKey::Integer(2),
-                Value::String((m.end() as i64 + start as i64).to_string()),
-            ));
-            Ok(Value::Array(matches_array))
-        } else {
-            Ok(Value::Integer(1))
+/// A match's groups, each named one under its name and then its number.
+/// A group that took no part is "", and left out if no later group did.
+fn match_groups(
+    re: &regex::Regex,
+    caps: &regex::Captures,
+    start: usize,
+    offset_capture: bool,
+) -> PhpArray {
+    let last = (0..caps.len())
+        .rev()
+        .find(|&i| caps.get(i).is_some())
+        .unwrap_or(0);
+    let mut groups = PhpArray::new();
+    for (i, name) in re.capture_names().enumerate().take(last + 1) {
+        let value = match caps.get(i) {
+            Some(m) => group_value(m.as_str(), m.start() + start, offset_capture),
+            None => group_value("", 0, offset_capture),
+        };
+        if let Some(name) = name {
+            groups.insert(ArrayKey::String(name.to_string()), value.clone());
         }
-    } else {
-        Ok(Value::Integer(0))
+        groups.insert(ArrayKey::Integer(i as i64), value);
     }
+    groups
 }
 
-pub fn preg_match_all(args: &[Value]) -> Result<Value, String> {
+/// The pattern's regex and the subject from `offset` on, or None if the
+/// offset is past the subject's end or inside a character
+fn prepare<'s>(
+    name: &str,
+    args: &[Value],
+    subject: &'s str,
+) -> Result<(regex::Regex, usize, Option<&'s str>), NativeError> {
     if args.len() < 2 {
-        return Err("preg_match_all() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(format!(
+            "{}() expects at least 2 parameters",
+            name
+        )));
     }
     let pattern = value_to_string_val(&args[0]);
-    let subject = value_to_string_val(&args[1]);
-    let _flags = if args.len() > 3 { args[3].to_int() } else { 0 };
-    let offset = if args.len() > 4 { args[4].to_int() } else { 0 };
+    let offset = args.get(4).map_or(0, Value::to_int);
 
     let (php_pattern, regex_flags) = parse_pattern(&pattern);
 
@@ -113,52 +129,81 @@ pub fn preg_match_all(args: &[Value]) -> Result<Value, String> {
         .multi_line(regex_flags.multi_line)
         .dot_matches_new_line(regex_flags.dot_all)
         .build()
-        .map_err(|_| "Invalid regex pattern")?;
+        .map_err(|_| NativeError::fatal("Invalid regex pattern"))?;
 
     let start = if offset < 0 {
-        if offset.abs() as usize > subject.len() {
-            return Ok(Value::Integer(0));
-        }
-        subject.len() - offset.abs() as usize
+        subject.len().checked_sub(offset.unsigned_abs() as usize)
     } else {
-        offset as usize
+        Some(offset as usize)
     };
+    let Some(start) = start else {
+        return Ok((re, 0, None));
+    };
+    // Offsets inside a multi-byte character match nothing
+    Ok((re, start, subject.get(start..)))
+}
 
-    if start > subject.len() {
-        return Ok(Value::Integer(0));
+pub fn preg_match(args: &[Value]) -> Result<Value, NativeError> {
+    let subject = args.get(1).map(value_to_string_val).unwrap_or_default();
+    let (re, start, subject_sub) = prepare("preg_match", args, &subject)?;
+    let offset_capture = args.get(3).map_or(0, Value::to_int) & PREG_OFFSET_CAPTURE != 0;
+
+    match subject_sub.and_then(|sub| re.captures(sub)) {
+        Some(caps) => {
+            set_matches(args, match_groups(&re, &caps, start, offset_capture));
+            Ok(Value::Integer(1))
+        }
+        None => {
+            set_matches(args, PhpArray::new());
+            Ok(Value::Integer(0))
+        }
     }
+}
 
-    let subject_sub = &subject[start..];
-    let matches_vec: Vec<_> = re.find_iter(subject_sub).collect();
-    let count = matches_vec.len();
-
-    if args.len() > 2 && args[2] != Value::Null {
-        let mut result: Vec<(ArrayKey, Value)> = Vec::new();
-        for m in &matches_vec {
-            let mut match_data: Vec<(ArrayKey, Value)> = Vec::new();
-            match_data.push((ArrayKey::Integer(0), Value::String(m.as_str().to_string())));
-            match_data.push((
-                ArrayKey::Integer(1),
-                Value::String((m.start() as i64 + start as i64).to_string()),
-            ));
-            match_data.push((
-                ArrayKey::Integer(2),
-                Value::String((m.end() as i64 + start as i64).to_string()),
-            ));
-            result.push((
-                ArrayKey::Integer(result.len() as i64),
-                Value::Array(match_data),
-            ));
+pub fn preg_match_all(args: &[Value]) -> Result<Value, NativeError> {
+    let subject = args.get(1).map(value_to_string_val).unwrap_or_default();
+    let (re, start, subject_sub) = prepare("preg_match_all", args, &subject)?;
+    let flags = args.get(3).map_or(0, Value::to_int);
+    let offset_capture = flags & PREG_OFFSET_CAPTURE != 0;
+
+    let all: Vec<_> = subject_sub
+        .map(|sub| re.captures_iter(sub).collect())
+        .unwrap_or_default();
+
+    let mut matches = PhpArray::new();
+    if flags & PREG_SET_ORDER != 0 {
+        for caps in &all {
+            let groups = match_groups(&re, caps, start, offset_capture);
+            matches.insert(
+                ArrayKey::Integer(matches.len() as i64),
+                Value::Array(groups),
+            );
         }
-        Ok(Value::Array(result))
     } else {
-        Ok(Value::Integer(count as i64))
+        for (i, name) in re.capture_names().enumerate() {
+            let group: PhpArray = all
+                .iter()
+                .map(|caps| match caps.get(i) {
+                    Some(m) => group_value(m.as_str(), m.start() + start, offset_capture),
+                    None => group_value("", 0, offset_capture),
+                })
+                .collect();
+            let group = Value::Array(group);
+            if let Some(name) = name {
+                matches.insert(ArrayKey::String(name.to_string()), group.clone());
+            }
+            matches.insert(ArrayKey::Integer(i as i64), group);
+        }
     }
+    set_matches(args, matches);
+    Ok(Value::Integer(all.len() as i64))
 }
 
-pub fn preg_split(args: &[Value]) -> Result<Value, String> {
+pub fn preg_split(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("preg_split() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "preg_split() expects at least 2 parameters",
+        ));
     }
     let pattern = value_to_string_val(&args[0]);
     let subject = value_to_string_val(&args[1]);
@@ -172,7 +217,7 @@ pub fn preg_split(args: &[Value]) -> Result<Value, String> {
         .multi_line(regex_flags.multi_line)
         .dot_matches_new_line(regex_flags.dot_all)
         .build()
-        .map_err(|_| "Invalid regex pattern")?;
+        .map_err(|_| NativeError::fatal("Invalid regex pattern"))?;
 
     let parts: Vec<Value> = if limit == 0 {
         Vec::new()
@@ -195,9 +240,11 @@ pub fn preg_split(args: &[Value]) -> Result<Value, String> {
     ))
 }
 
-pub fn preg_replace(args: &[Value]) -> Result<Value, String> {
+pub fn preg_replace(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 3 {
-        return Err("preg_replace() expects at least 3 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "preg_replace() expects at least 3 parameters",
+        ));
     }
     let pattern = value_to_string_val(&args[0]);
     let replacement = value_to_string_val(&args[1]);
@@ -211,7 +258,7 @@ pub fn preg_replace(args: &[Value]) -> Result<Value, String> {
         .multi_line(regex_flags.multi_line)
         .dot_matches_new_line(regex_flags.dot_all)
         .build()
-        .map_err(|_| "Invalid regex pattern")?;
+        .map_err(|_| NativeError::fatal("Invalid regex pattern"))?;
 
     let result = if limit < 0 {
         re.replace_all(&subject, &replacement).to_string()
@@ -225,9 +272,11 @@ pub fn preg_replace(args: &[Value]) -> Result<Value, String> {
     Ok(Value::String(result))
 }
 
-pub fn preg_replace_callback(args: &[Value]) -> Result<Value, String> {
+pub fn preg_replace_callback(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 3 {
-        return Err("preg_replace_callback() expects at least 3 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "preg_replace_callback() expects at least 3 parameters",
+        ));
     }
     let pattern = value_to_string_val(&args[0]);
     let subject = value_to_string_val(&args[2]);
@@ -240,14 +289,16 @@ pub fn preg_replace_callback(args: &[Value]) -> Result<Value, String> {
         .multi_line(regex_flags.multi_line)
         .dot_matches_new_line(regex_flags.dot_all)
         .build()
-        .map_err(|_| "Invalid regex pattern")?;
+        .map_err(|_| NativeError::fatal("Invalid regex pattern"))?;
 
     Ok(Value::String(subject))
 }
 
-pub fn preg_grep(args: &[Value]) -> Result<Value, String> {
+pub fn preg_grep(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("preg_grep() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "preg_grep() expects at least 2 parameters",
+        ));
     }
     let pattern = value_to_string_val(&args[0]);
     let _flags = if args.len() > 2 { args[2].to_int() } else { 0 };
@@ -259,11 +310,15 @@ pub fn preg_grep(args: &[Value]) -> Result<Value, String> {
         .multi_line(regex_flags.multi_line)
         .dot_matches_new_line(regex_flags.dot_all)
         .build()
-        .map_err(|_| "Invalid regex pattern")?;
+        .map_err(|_| NativeError::fatal("Invalid regex pattern"))?;
 
     let input_vec = match &args[1] {
         Value::Array(arr) => arr.clone(),
-        _ => return Err("preg_grep() expects parameter 2 to be array".to_string()),
+        _ => {
+            return Err(NativeError::fatal(
+                "preg_grep() expects parameter 2 to be array",
+            ))
+        }
     };
 
     let result: Vec<(ArrayKey, Value)> = input_vec
@@ -276,7 +331,7 @@ pub fn preg_grep(args: &[Value]) -> Result<Value, String> {
         .map(|(i, (_k, v))| (ArrayKey::Integer(i as i64), v.clone()))
         .collect();
 
-    Ok(Value::Array(result))
+    Ok(Value::Array(result.into()))
 }
 
 struct RegexFlags {
@@ -303,11 +358,12 @@ fn parse_pattern(pattern: &str) -> (String, RegexFlags) {
     }
 
     let delimiter = pattern.chars().next().unwrap();
-    let end_pos = pattern[1..].find(delimiter).map(|i| i + 1);
+    let start = delimiter.len_utf8();
+    let end_pos = pattern[start..].find(delimiter).map(|i| i + start);
 
     if let Some(pos) = end_pos {
-        let pattern_str = &pattern[1..pos];
-        let modifier_str = &pattern[pos + 1..];
+        let pattern_str = &pattern[start..pos];
+        let modifier_str = &pattern[pos + start..];
 
         let mut new_pattern = String::new();
 
@@ -2,6 +2,7 @@
 
 use crate::ast::{Attribute, AttributeArgument, Expr};
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
 
 /// Convert an Attribute AST node to a runtime Value (associative array)
 /// Format: ["name" => "AttributeName", "arguments" => [...]]
@@ -84,7 +85,7 @@ fn expr_to_simple_value(expr: &Expr) -> Value {
 pub fn get_class_attributes(
     args: &[Value],
     classes: &std::collections::HashMap<String, crate::runtime::ClassDefinition>,
-) -> Result<Value, String> {
+) -> Result<Value, NativeError> {
     if args.len() != 1 {
         return Err("get_class_attributes() expects exactly 1 parameter".to_string());
     }
@@ -110,7 +111,7 @@ pub fn get_class_attributes(
 pub fn get_method_attributes(
     args: &[Value],
     classes: &std::collections::HashMap<String, crate::runtime::ClassDefinition>,
-) -> Result<Value, String> {
+) -> Result<Value, NativeError> {
     if args.len() != 2 {
         return Err("get_method_attributes() expects exactly 2 parameters".to_string());
     }
@@ -145,7 +146,7 @@ pub fn get_method_attributes(
 pub fn get_property_attributes(
     args: &[Value],
     classes: &std::collections::HashMap<String, crate::runtime::ClassDefinition>,
-) -> Result<Value, String> {
+) -> Result<Value, NativeError> {
     if args.len() != 2 {
         return Err("get_property_attributes() expects exactly 2 parameters".to_string());
     }
@@ -185,7 +186,7 @@ pub fn get_property_attributes(
 pub fn get_function_attributes(
     args: &[Value],
     functions: &std::collections::HashMap<String, crate::runtime::UserFunction>,
-) -> Result<Value, String> {
+) -> Result<Value, NativeError> {
     if args.len() != 1 {
         return Err("get_function_attributes() expects exactly 1 parameter".to_string());
     }
@@ -216,7 +217,7 @@ pub fn get_function_attributes(
 pub fn get_parameter_attributes(
     args: &[Value],
     functions: &std::collections::HashMap<String, crate::runtime::UserFunction>,
-) -> Result<Value, String> {
+) -> Result<Value, NativeError> {
     if args.len() != 2 {
         return Err("get_parameter_attributes() expects exactly 2 parameters".to_string());
     }
@@ -261,7 +262,7 @@ pub fn get_parameter_attributes(
 pub fn get_method_parameter_attributes(
     args: &[Value],
     classes: &std::collections::HashMap<String, crate::runtime::ClassDefinition>,
-) -> Result<Value, String> {
+) -> Result<Value, NativeError> {
     if args.len() != 3 {
         return Err("get_method_parameter_attributes() expects exactly 3 parameters".to_string());
     }
@@ -310,7 +311,7 @@ pub fn get_method_parameter_attributes(
 pub fn get_interface_attributes(
     args: &[Value],
     interfaces: &std::collections::HashMap<String, crate::runtime::InterfaceDefinition>,
-) -> Result<Value, String> {
+) -> Result<Value, NativeError> {
     if args.len() != 1 {
         return Err("get_interface_attributes() expects exactly 1 parameter".to_string());
     }
@@ -336,7 +337,7 @@ pub fn get_interface_attributes(
 pub fn get_trait_attributes(
     args: &[Value],
     traits: &std::collections::HashMap<String, crate::runtime::TraitDefinition>,
-) -> Result<Value, String> {
+) -> Result<Value, NativeError> {
     if args.len() != 1 {
         return Err("get_trait_attributes() expects exactly 1 parameter".to_string());
     }
@@ -7,15 +7,34 @@
 #![allow(dead_code)]
 
 use crate::runtime::{ArrayKey, Closure, Value};
+use crate::vm::error::NativeError;
+use crate::vm::error::VmError;
 use crate::vm::VM;
 use std::io::Write;
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
+use thread_local::ThreadLocal;
 
+// Per thread, like the rest of a script's state, so that the workers of
+// a FastCGI server don't share them (see `server::fastcgi`)
 lazy_static::lazy_static! {
-    pub static ref AUTOLOADERS: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
-    pub static ref INCLUDE_PATH: Mutex<Vec<String>> = Mutex::new(vec![".".to_string()]);
-    pub static ref PSR4_REGISTRY: Arc<Mutex<Vec<(String, String)>>> =
-        Arc::new(Mutex::new(Vec::new()));
+    static ref AUTOLOADERS: ThreadLocal<Mutex<Vec<Value>>> = ThreadLocal::new();
+    static ref INCLUDE_PATH: ThreadLocal<Mutex<Vec<String>>> = ThreadLocal::new();
+    static ref PSR4_REGISTRY: ThreadLocal<Mutex<Vec<(String, String)>>> = ThreadLocal::new();
+}
+
+/// This thread's registered autoloaders
+fn autoloaders() -> &'static Mutex<Vec<Value>> {
+    AUTOLOADERS.get_or_default()
+}
+
+/// This thread's include_path
+fn include_path() -> &'static Mutex<Vec<String>> {
+    INCLUDE_PATH.get_or(|| Mutex::new(vec![".".to_string()]))
+}
+
+/// This thread's PSR-4 mappings
+fn psr4_registry() -> &'static Mutex<Vec<(String, String)>> {
+    PSR4_REGISTRY.get_or_default()
 }
 
 /// Normalize class name (remove leading backslash)
@@ -31,18 +50,22 @@ pub fn normalize_class_name(name: &str) -> String {
 ///
 /// Register a given function as implementation of __autoload(). This function
 /// will be called when PHP tries to use a class that hasn't been defined yet.
-pub fn spl_autoload_register(args: &[Value]) -> Result<Value, String> {
+pub fn spl_autoload_register(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("spl_autoload_register() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "spl_autoload_register() expects at least 1 parameter",
+        ));
     }
 
     let callback = &args[0];
 
     if !is_callable(callback) {
-        return Err("spl_autoload_register() expects a valid callback".to_string());
+        return Err(NativeError::fatal(
+            "spl_autoload_register() expects a valid callback",
+        ));
     }
 
-    let mut autoloaders = AUTOLOADERS.lock().unwrap();
+    let mut autoloaders = autoloaders().lock().unwrap();
     autoloaders.push(callback.clone());
     drop(autoloaders);
 
@@ -52,18 +75,22 @@ pub fn spl_autoload_register(args: &[Value]) -> Result<Value, String> {
 /// spl_autoload_unregister - Unregister an autoloader function
 ///
 /// Unregister a function that was registered with spl_autoload_register().
-pub fn spl_autoload_unregister(args: &[Value]) -> Result<Value, String> {
+pub fn spl_autoload_unregister(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("spl_autoload_unregister() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "spl_autoload_unregister() expects exactly 1 parameter",
+        ));
     }
 
     let callback = &args[0];
 
     if !is_callable(callback) {
-        return Err("spl_autoload_unregister() expects a valid callback".to_string());
+        return Err(NativeError::fatal(
+            "spl_autoload_unregister() expects a valid callback",
+        ));
     }
 
-    let mut autoloaders = AUTOLOADERS.lock().unwrap();
+    let mut autoloaders = autoloaders().lock().unwrap();
     let pos = autoloaders
         .iter()
         .position(|c| callbacks_equal(c, callback));
@@ -81,9 +108,9 @@ pub fn spl_autoload_unregister(args: &[Value]) -> Result<Value, String> {
 /// spl_autoload_functions - Return all registered autoload functions
 ///
 /// Returns an array of all registered autoload functions.
-pub fn spl_autoload_functions(args: &[Value]) -> Result<Value, String> {
+pub fn spl_autoload_functions(args: &[Value]) -> Result<Value, NativeError> {
     let _args = args;
-    let autoloaders = AUTOLOADERS.lock().unwrap();
+    let autoloaders = autoloaders().lock().unwrap();
     let result: Vec<Value> = autoloaders.iter().cloned().collect();
     drop(autoloaders);
     Ok(Value::Array(
@@ -95,19 +122,42 @@ pub fn spl_autoload_functions(args: &[Value]) -> Result<Value, String> {
     ))
 }
 
+/// spl_object_id - The number of an object, unique among live objects
+pub fn spl_object_id(args: &[Value]) -> Result<Value, NativeError> {
+    object_handle("spl_object_id", args).map(|id| Value::Integer(id as i64))
+}
+
+/// spl_object_hash - The number of an object as a 32-character string
+pub fn spl_object_hash(args: &[Value]) -> Result<Value, NativeError> {
+    object_handle("spl_object_hash", args).map(|id| Value::String(format!("{:016x}{:016x}", id, 0)))
+}
+
+fn object_handle(function: &str, args: &[Value]) -> Result<usize, NativeError> {
+    match args.first() {
+        Some(Value::Object(object)) => Ok(object.id()),
+        other => Err(NativeError::type_error(format!(
+            "{}(): Argument #1 ($object) must be of type object, {} given",
+            function,
+            other.map_or("null", |arg| arg.type_name())
+        ))),
+    }
+}
+
 /// spl_autoload_call - Try to load a class by calling all registered autoloaders
 ///
 /// This function is called internally when a class is not found.
 /// It attempts to load the class by calling each registered autoloader.
 pub fn spl_autoload_call<W: Write>(vm: &mut VM<W>, class_name: &str) -> bool {
-    let autoloaders = AUTOLOADERS.lock().unwrap();
+    let autoloaders = autoloaders().lock().unwrap();
     let autoloaders_copy: Vec<Value> = autoloaders.iter().cloned().collect();
     drop(autoloaders);
 
     let normalized_name = normalize_class_name(class_name);
 
+    // Each autoloader gets a turn until one of them declares the class
     for autoloader in autoloaders_copy.iter() {
-        if call_autoloader(vm, autoloader, &normalized_name) {
+        if call_autoloader(vm, autoloader, &normalized_name) && vm.is_declared("", &normalized_name)
+        {
             return true;
         }
     }
@@ -173,7 +223,7 @@ fn call_named_function<W: Write>(
     vm: &mut VM<W>,
     name: &str,
     args: &[Value],
-) -> Result<Value, String> {
+) -> Result<Value, VmError> {
     vm.call_function(name, args)
 }
 
@@ -183,7 +233,7 @@ fn call_static_method<W: Write>(
     class: &str,
     method: &str,
     args: &[Value],
-) -> Result<Value, String> {
+) -> Result<Value, VmError> {
     let method_name = format!("{}::{}", class.trim_start_matches('\\'), method);
     vm.call_function(&method_name, args)
 }
@@ -193,18 +243,20 @@ fn call_closure<W: Write>(
     vm: &mut VM<W>,
     closure: &Closure,
     args: &[Value],
-) -> Result<Value, String> {
+) -> Result<Value, VmError> {
     vm.call_closure(closure, args)
 }
 
 /// set_include_path - Set the include_path configuration option
-pub fn set_include_path(args: &[Value]) -> Result<Value, String> {
+pub fn set_include_path(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("set_include_path() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "set_include_path() expects exactly 1 parameter",
+        ));
     }
 
     let path = args[0].to_string_val();
-    let mut include_path_guard = INCLUDE_PATH.lock().unwrap();
+    let mut include_path_guard = include_path().lock().unwrap();
     let old_path = include_path_guard.join(":");
     *include_path_guard = if path.is_empty() {
         Vec::new()
@@ -216,23 +268,28 @@ pub fn set_include_path(args: &[Value]) -> Result<Value, String> {
 }
 
 /// get_include_path - Get the current include_path
-pub fn get_include_path(args: &[Value]) -> Result<Value, String> {
+pub fn get_include_path(args: &[Value]) -> Result<Value, NativeError> {
     let _args = args;
-    let include_path_guard = INCLUDE_PATH.lock().unwrap();
+    let include_path_guard = include_path().lock().unwrap();
     let paths = include_path_guard.clone();
     drop(include_path_guard);
     Ok(Value::String(paths.join(":")))
 }
 
+/// Put the include path back to the current directory
+pub fn reset_include_path() {
+    *include_path().lock().unwrap() = vec![".".to_string()];
+}
+
 /// Check if autoloaders are registered
 pub fn has_autoloaders() -> bool {
-    let autoloaders = AUTOLOADERS.lock().unwrap();
+    let autoloaders = autoloaders().lock().unwrap();
     !autoloaders.is_empty()
 }
 
 /// Get the include path as a vector
 pub fn get_include_path_vec() -> Vec<String> {
-    let include_path_guard = INCLUDE_PATH.lock().unwrap();
+    let include_path_guard = include_path().lock().unwrap();
     let paths = include_path_guard.clone();
     drop(include_path_guard);
     if paths.is_empty() {
@@ -244,7 +301,7 @@ pub fn get_include_path_vec() -> Vec<String> {
 
 /// Clear all autoloaders (useful for testing)
 pub fn clear_autoloaders() {
-    let mut autoloaders = AUTOLOADERS.lock().unwrap();
+    let mut autoloaders = autoloaders().lock().unwrap();
     autoloaders.clear();
 }
 
@@ -270,32 +327,35 @@ pub fn clear_autoloaders() {
 /// // Now MyApp\Models\User maps to /src/Models/User.php
 /// $user = new MyApp\Models\User();
 /// ```
-pub fn spl_autoload_register_psr4(args: &[Value]) -> Result<Value, String> {
+pub fn spl_autoload_register_psr4(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("spl_autoload_register_psr4() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "spl_autoload_register_psr4() expects at least 2 parameters",
+        ));
     }
 
     let prefix = args[0].to_string_val();
     let base_dir = args[1].to_string_val();
 
     if prefix.is_empty() {
-        return Err("spl_autoload_register_psr4(): namespace prefix cannot be empty".to_string());
+        return Err(NativeError::fatal(
+            "spl_autoload_register_psr4(): namespace prefix cannot be empty",
+        ));
     }
 
-    // Ensure prefix ends with backslash for consistent matching
-    let normalized_prefix = if !prefix.ends_with('\\') {
-        format!("{}\\", prefix)
-    } else {
-        prefix
-    };
-
-    let mut registry = PSR4_REGISTRY.lock().unwrap();
-    registry.push((normalized_prefix, base_dir));
-    drop(registry);
-
+    add_psr4_mapping(&prefix, &base_dir);
     Ok(Value::Bool(true))
 }
 
+/// Map the namespace prefix to a base directory, as
+/// `spl_autoload_register_psr4()` and the `--autoload` flag do
+pub fn add_psr4_mapping(prefix: &str, base_dir: &str) {
+    // Ensure prefix ends with backslash for consistent matching
+    let prefix = prefix.trim_start_matches('\\').trim_end_matches('\\');
+    let mut registry = psr4_registry().lock().unwrap();
+    registry.push((format!("{}\\", prefix), base_dir.to_string()));
+}
+
 /// Find the PSR-4 mapping for a given class name
 ///
 /// Searches through registered PSR-4 mappings and returns the matching
@@ -307,7 +367,7 @@ pub fn spl_autoload_register_psr4(args: &[Value]) -> Result<Value, String> {
 /// # Returns
 /// Some((prefix, base_dir)) if a matching prefix is found, None otherwise
 pub fn find_psr4_mapping(class_name: &str) -> Option<(String, String)> {
-    let registry = PSR4_REGISTRY.lock().unwrap();
+    let registry = psr4_registry().lock().unwrap();
     let mut best_match: Option<(String, String)> = None;
     let mut best_prefix_len = 0;
 
@@ -354,22 +414,25 @@ pub fn namespace_to_path(class_name: &str, base_dir: &str, prefix: &str) -> Stri
 /// Get all registered PSR-4 mappings
 ///
 /// Returns an array of all registered PSR-4 namespace prefix mappings.
-pub fn spl_autoload_registered_psr4(args: &[Value]) -> Result<Value, String> {
+pub fn spl_autoload_registered_psr4(args: &[Value]) -> Result<Value, NativeError> {
     let _args = args;
-    let registry = PSR4_REGISTRY.lock().unwrap();
+    let registry = psr4_registry().lock().unwrap();
     let result: Vec<Value> = registry
         .iter()
         .map(|(prefix, base_dir)| {
-            Value::Array(vec![
-                (
-                    ArrayKey::String("prefix".to_string()),
-                    Value::String(prefix.clone()),
-                ),
-                (
-                    ArrayKey::String("path".to_string()),
-                    Value::String(base_dir.clone()),
-                ),
-            ])
+            Value::Array(
+                vec![
+                    (
+                        ArrayKey::String("prefix".to_string()),
+                        Value::String(prefix.clone()),
+                    ),
+                    (
+                        ArrayKey::String("path".to_string()),
+                        Value::String(base_dir.clone()),
+                    ),
+                ]
+                .into(),
+            )
         })
         .collect();
     drop(registry);
@@ -385,6 +448,6 @@ pub fn spl_autoload_registered_psr4(args: &[Value]) -> Result<Value, String> {
 
 /// Clear all PSR-4 registrations (useful for testing)
 pub fn clear_psr4_registry() {
-    let mut registry = PSR4_REGISTRY.lock().unwrap();
+    let mut registry = psr4_registry().lock().unwrap();
     registry.clear();
 }
@@ -1,22 +1,35 @@
 //! String built-in functions
 
+use crate::runtime::builtins::locale;
+use crate::runtime::builtins::native::arg;
 use crate::runtime::Value;
+use crate::vm::error::NativeError;
+
+/// Longest string that str_repeat() and str_pad() will build (PHP's default
+/// 128M memory_limit)
+const MAX_STRING_LEN: usize = 128 * 1024 * 1024;
 
 /// strlen - Get string length
-pub fn strlen(args: &[Value]) -> Result<Value, String> {
+pub fn strlen(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("strlen() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "strlen() expects exactly 1 parameter",
+        ));
     }
-    Ok(Value::Integer(args[0].to_string_val().len() as i64))
+    Ok(Value::Integer(
+        arg::<String>("strlen", args, 0, "string")?.len() as i64,
+    ))
 }
 
 /// substr - Return part of a string
-pub fn substr(args: &[Value]) -> Result<Value, String> {
+pub fn substr(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("substr() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "substr() expects at least 2 parameters",
+        ));
     }
-    let s = args[0].to_string_val();
-    let start = args[1].to_int();
+    let s = arg::<String>("substr", args, 0, "string")?;
+    let start = arg::<i64>("substr", args, 1, "offset")?;
     let chars: Vec<char> = s.chars().collect();
     let len = chars.len() as i64;
 
@@ -27,9 +40,9 @@ pub fn substr(args: &[Value]) -> Result<Value, String> {
     };
 
     let result = if args.len() >= 3 {
-        let length = args[2].to_int();
+        let length = arg::<i64>("substr", args, 2, "length")?;
         if length < 0 {
-            let end_idx = ((len + length) as usize).max(start_idx);
+            let end_idx = ((len + length).max(0) as usize).max(start_idx);
             chars[start_idx..end_idx].iter().collect()
         } else {
             chars[start_idx..].iter().take(length as usize).collect()
@@ -42,63 +55,105 @@ pub fn substr(args: &[Value]) -> Result<Value, String> {
 }
 
 /// strtoupper - Make a string uppercase
-pub fn strtoupper(args: &[Value]) -> Result<Value, String> {
+pub fn strtoupper(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("strtoupper() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "strtoupper() expects exactly 1 parameter",
+        ));
     }
-    Ok(Value::String(args[0].to_string_val().to_uppercase()))
+    Ok(Value::String(locale::to_upper(&arg::<String>(
+        "strtoupper",
+        args,
+        0,
+        "string",
+    )?)))
 }
 
 /// strtolower - Make a string lowercase
-pub fn strtolower(args: &[Value]) -> Result<Value, String> {
+pub fn strtolower(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("strtolower() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "strtolower() expects exactly 1 parameter",
+        ));
     }
-    Ok(Value::String(args[0].to_string_val().to_lowercase()))
+    Ok(Value::String(locale::to_lower(&arg::<String>(
+        "strtolower",
+        args,
+        0,
+        "string",
+    )?)))
 }
 
 /// trim - Strip whitespace from beginning and end
-pub fn trim(args: &[Value]) -> Result<Value, String> {
+pub fn trim(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("trim() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "trim() expects at least 1 parameter",
+        ));
     }
-    Ok(Value::String(args[0].to_string_val().trim().to_string()))
+    Ok(Value::String(
+        arg::<String>("trim", args, 0, "string")?.trim().to_string(),
+    ))
 }
 
 /// ltrim - Strip whitespace from beginning
-pub fn ltrim(args: &[Value]) -> Result<Value, String> {
+pub fn ltrim(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("ltrim() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "ltrim() expects at least 1 parameter",
+        ));
     }
     Ok(Value::String(
-        args[0].to_string_val().trim_start().to_string(),
+        arg::<String>("ltrim", args, 0, "string")?
+            .trim_start()
+            .to_string(),
     ))
 }
 
 /// rtrim - Strip whitespace from end
-pub fn rtrim(args: &[Value]) -> Result<Value, String> {
+pub fn rtrim(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("rtrim() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "rtrim() expects at least 1 parameter",
+        ));
     }
     Ok(Value::String(
-        args[0].to_string_val().trim_end().to_string(),
+        arg::<String>("rtrim", args, 0, "string")?
+            .trim_end()
+            .to_string(),
     ))
 }
 
 /// str_repeat - Repeat a string
-pub fn str_repeat(args: &[Value]) -> Result<Value, String> {
+pub fn str_repeat(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("str_repeat() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "str_repeat() expects exactly 2 parameters",
+        ));
+    }
+    let s = arg::<String>("str_repeat", args, 0, "string")?;
+    let times = arg::<i64>("str_repeat", args, 1, "times")?;
+    if times < 0 {
+        return Err(NativeError::value_error(
+            "str_repeat(): Argument #2 ($times) must be greater than or equal to 0",
+        ));
+    }
+    let times = times as usize;
+    match s.len().checked_mul(times) {
+        Some(len) if len <= MAX_STRING_LEN => Ok(Value::String(s.repeat(times))),
+        _ => Err(NativeError::fatal(format!(
+            "str_repeat(): Result is too big, maximum {} allowed",
+            MAX_STRING_LEN
+        ))),
     }
-    let s = args[0].to_string_val();
-    let times = args[1].to_int().max(0) as usize;
-    Ok(Value::String(s.repeat(times)))
 }
 
 /// str_replace - Replace all occurrences of search with replace
-pub fn str_replace(args: &[Value]) -> Result<Value, String> {
+pub fn str_replace(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 3 {
-        return Err("str_replace() expects at least 3 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "str_replace() expects at least 3 parameters",
+        ));
     }
     let search = args[0].to_string_val();
     let replace = args[1].to_string_val();
@@ -107,12 +162,14 @@ pub fn str_replace(args: &[Value]) -> Result<Value, String> {
 }
 
 /// strpos - Find position of first occurrence
-pub fn strpos(args: &[Value]) -> Result<Value, String> {
+pub fn strpos(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("strpos() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "strpos() expects at least 2 parameters",
+        ));
     }
-    let haystack = args[0].to_string_val();
-    let needle = args[1].to_string_val();
+    let haystack = arg::<String>("strpos", args, 0, "haystack")?;
+    let needle = arg::<String>("strpos", args, 1, "needle")?;
     match haystack.find(&needle) {
         Some(pos) => Ok(Value::Integer(pos as i64)),
         None => Ok(Value::Bool(false)),
@@ -120,75 +177,87 @@ pub fn strpos(args: &[Value]) -> Result<Value, String> {
 }
 
 /// str_contains - Check if string contains substring
-pub fn str_contains(args: &[Value]) -> Result<Value, String> {
+pub fn str_contains(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("str_contains() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "str_contains() expects exactly 2 parameters",
+        ));
     }
-    let haystack = args[0].to_string_val();
-    let needle = args[1].to_string_val();
+    let haystack = arg::<String>("str_contains", args, 0, "haystack")?;
+    let needle = arg::<String>("str_contains", args, 1, "needle")?;
     Ok(Value::Bool(haystack.contains(&needle)))
 }
 
 /// str_starts_with - Check if string starts with substring
-pub fn str_starts_with(args: &[Value]) -> Result<Value, String> {
+pub fn str_starts_with(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("str_starts_with() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "str_starts_with() expects exactly 2 parameters",
+        ));
     }
-    let haystack = args[0].to_string_val();
-    let needle = args[1].to_string_val();
+    let haystack = arg::<String>("str_starts_with", args, 0, "haystack")?;
+    let needle = arg::<String>("str_starts_with", args, 1, "needle")?;
     Ok(Value::Bool(haystack.starts_with(&needle)))
 }
 
 /// str_ends_with - Check if string ends with substring
-pub fn str_ends_with(args: &[Value]) -> Result<Value, String> {
+pub fn str_ends_with(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("str_ends_with() expects exactly 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "str_ends_with() expects exactly 2 parameters",
+        ));
     }
-    let haystack = args[0].to_string_val();
-    let needle = args[1].to_string_val();
+    let haystack = arg::<String>("str_ends_with", args, 0, "haystack")?;
+    let needle = arg::<String>("str_ends_with", args, 1, "needle")?;
     Ok(Value::Bool(haystack.ends_with(&needle)))
 }
 
 /// ucfirst - Make first character uppercase
-pub fn ucfirst(args: &[Value]) -> Result<Value, String> {
+pub fn ucfirst(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("ucfirst() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "ucfirst() expects exactly 1 parameter",
+        ));
     }
-    let s = args[0].to_string_val();
+    let s = arg::<String>("ucfirst", args, 0, "string")?;
     let mut chars = s.chars();
     let result = match chars.next() {
-        Some(c) => c.to_uppercase().to_string() + chars.as_str(),
+        Some(c) => locale::to_upper(&c.to_string()) + chars.as_str(),
         None => String::new(),
     };
     Ok(Value::String(result))
 }
 
 /// lcfirst - Make first character lowercase
-pub fn lcfirst(args: &[Value]) -> Result<Value, String> {
+pub fn lcfirst(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("lcfirst() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "lcfirst() expects exactly 1 parameter",
+        ));
     }
-    let s = args[0].to_string_val();
+    let s = arg::<String>("lcfirst", args, 0, "string")?;
     let mut chars = s.chars();
     let result = match chars.next() {
-        Some(c) => c.to_lowercase().to_string() + chars.as_str(),
+        Some(c) => locale::to_lower(&c.to_string()) + chars.as_str(),
         None => String::new(),
     };
     Ok(Value::String(result))
 }
 
 /// ucwords - Uppercase first character of each word
-pub fn ucwords(args: &[Value]) -> Result<Value, String> {
+pub fn ucwords(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("ucwords() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "ucwords() expects at least 1 parameter",
+        ));
     }
-    let s = args[0].to_string_val();
+    let s = arg::<String>("ucwords", args, 0, "string")?;
     let result: String = s
         .split(' ')
         .map(|word| {
             let mut chars = word.chars();
             match chars.next() {
-                Some(c) => c.to_uppercase().to_string() + chars.as_str(),
+                Some(c) => locale::to_upper(&c.to_string()) + chars.as_str(),
                 None => String::new(),
             }
         })
@@ -198,28 +267,32 @@ pub fn ucwords(args: &[Value]) -> Result<Value, String> {
 }
 
 /// strrev - Reverse a string
-pub fn strrev(args: &[Value]) -> Result<Value, String> {
+pub fn strrev(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("strrev() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "strrev() expects exactly 1 parameter",
+        ));
     }
-    let s = args[0].to_string_val();
+    let s = arg::<String>("strrev", args, 0, "string")?;
     Ok(Value::String(s.chars().rev().collect()))
 }
 
 /// str_pad - Pad a string to a certain length
-pub fn str_pad(args: &[Value]) -> Result<Value, String> {
+pub fn str_pad(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("str_pad() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "str_pad() expects at least 2 parameters",
+        ));
     }
-    let s = args[0].to_string_val();
-    let length = args[1].to_int() as usize;
+    let s = arg::<String>("str_pad", args, 0, "string")?;
+    let length = arg::<i64>("str_pad", args, 1, "length")?.max(0) as usize;
     let pad_string = if args.len() >= 3 {
-        args[2].to_string_val()
+        arg::<String>("str_pad", args, 2, "pad_string")?
     } else {
         " ".to_string()
     };
     let pad_type = if args.len() >= 4 {
-        args[3].to_int()
+        arg::<i64>("str_pad", args, 3, "pad_type")?
     } else {
         1 // STR_PAD_RIGHT
     };
@@ -227,6 +300,12 @@ pub fn str_pad(args: &[Value]) -> Result<Value, String> {
     if s.len() >= length || pad_string.is_empty() {
         return Ok(Value::String(s));
     }
+    if length > MAX_STRING_LEN {
+        return Err(NativeError::fatal(format!(
+            "str_pad(): Result is too big, maximum {} allowed",
+            MAX_STRING_LEN
+        )));
+    }
 
     let pad_needed = length - s.len();
     let pad_chars: Vec<char> = pad_string.chars().collect();
@@ -267,21 +346,25 @@ pub fn str_pad(args: &[Value]) -> Result<Value, String> {
 }
 
 /// explode - Split a string by delimiter (stub - requires arrays)
-pub fn explode(args: &[Value]) -> Result<Value, String> {
+pub fn explode(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("explode() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "explode() expects at least 2 parameters",
+        ));
     }
-    let _delimiter = args[0].to_string_val();
-    let string = args[1].to_string_val();
+    let _delimiter = arg::<String>("explode", args, 0, "separator")?;
+    let string = arg::<String>("explode", args, 1, "string")?;
     // For now, just return the original string
     // Full implementation requires array support
     Ok(Value::String(string))
 }
 
 /// implode - Join array elements with a string
-pub fn implode(args: &[Value]) -> Result<Value, String> {
+pub fn implode(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("implode() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "implode() expects at least 1 parameter",
+        ));
     }
 
     let separator = if args.len() >= 2 {
@@ -303,18 +386,26 @@ pub fn implode(args: &[Value]) -> Result<Value, String> {
         }
         _ => {
             if args.len() >= 2 {
-                Err("implode() expects parameter 2 to be array".to_string())
+                Err(NativeError::fatal(
+                    "implode() expects parameter 2 to be array",
+                ))
             } else {
-                Err("implode() expects parameter 1 to be array".to_string())
+                Err(NativeError::fatal(
+                    "implode() expects parameter 1 to be array",
+                ))
             }
         }
     }
 }
 
 /// sprintf - Return a formatted string
-pub fn sprintf(args: &[Value]) -> Result<Value, String> {
+///
+/// `%f` writes the decimal point of LC_NUMERIC, `%F` always a dot.
+pub fn sprintf(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("sprintf() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "sprintf() expects at least 1 parameter",
+        ));
     }
     let format = args[0].to_string_val();
     let mut arg_idx = 1;
@@ -344,12 +435,33 @@ pub fn sprintf(args: &[Value]) -> Result<Value, String> {
                     }
                     i += 2;
                 }
-                'f' => {
+                '.' | 'f' | 'F' => {
+                    let digits: String = chars[i + 2..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect();
+                    let (precision, conversion) = match chars[i + 1] {
+                        '.' => (digits.parse().unwrap_or(0), chars.get(i + 2 + digits.len())),
+                        _ => (6, chars.get(i + 1)),
+                    };
+                    let Some(&conversion @ ('f' | 'F')) = conversion else {
+                        output.push(chars[i]);
+                        i += 1;
+                        continue;
+                    };
                     if arg_idx < args.len() {
-                        output.push_str(&format!("{:.6}", args[arg_idx].to_float()));
+                        let number = format!("{:.*}", precision, args[arg_idx].to_float());
+                        match conversion {
+                            'f' => output.push_str(&number.replace('.', locale::decimal_point())),
+                            _ => output.push_str(&number),
+                        }
                         arg_idx += 1;
                     }
-                    i += 2;
+                    i += if chars[i + 1] == '.' {
+                        3 + digits.len()
+                    } else {
+                        2
+                    };
                 }
                 _ => {
                     output.push(chars[i]);
@@ -364,24 +476,3 @@ pub fn sprintf(args: &[Value]) -> Result<Value, String> {
 
     Ok(Value::String(output))
 }
-
-/// chr - Generate a single-byte string from a number
-pub fn chr(args: &[Value]) -> Result<Value, String> {
-    if args.is_empty() {
-        return Err("chr() expects exactly 1 parameter".to_string());
-    }
-    let code = args[0].to_int() as u8;
-    Ok(Value::String((code as char).to_string()))
-}
-
-/// ord - Convert first byte of string to value
-pub fn ord(args: &[Value]) -> Result<Value, String> {
-    if args.is_empty() {
-        return Err("ord() expects exactly 1 parameter".to_string());
-    }
-    let s = args[0].to_string_val();
-    match s.chars().next() {
-        Some(c) => Ok(Value::Integer(c as i64)),
-        None => Ok(Value::Integer(0)),
-    }
-}
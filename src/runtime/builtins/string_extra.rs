@@ -1,11 +1,16 @@
 //! Additional String built-in functions
 
+use crate::runtime::builtins::native::arg;
 use crate::runtime::Value;
+use crate::vhp_builtin;
+use crate::vm::error::NativeError;
 
 /// htmlspecialchars - Convert special characters to HTML entities
-pub fn htmlspecialchars(args: &[Value]) -> Result<Value, String> {
+pub fn htmlspecialchars(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("htmlspecialchars() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "htmlspecialchars() expects at least 1 parameter",
+        ));
     }
     let s = args[0].to_string_val();
     let flags = args.get(1).map(|v| v.to_int()).unwrap_or(2); // ENT_COMPAT | ENT_HTML401
@@ -37,18 +42,22 @@ pub fn htmlspecialchars(args: &[Value]) -> Result<Value, String> {
 }
 
 /// htmlentities - Convert all applicable characters to HTML entities
-pub fn htmlentities(args: &[Value]) -> Result<Value, String> {
+pub fn htmlentities(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("htmlentities() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "htmlentities() expects at least 1 parameter",
+        ));
     }
     // For now, just call htmlspecialchars
     htmlspecialchars(args)
 }
 
 /// nl2br - Inserts HTML line breaks before all newlines in a string
-pub fn nl2br(args: &[Value]) -> Result<Value, String> {
+pub fn nl2br(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("nl2br() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "nl2br() expects at least 1 parameter",
+        ));
     }
     let s = args[0].to_string_val();
     let is_xhtml = args.get(1).map(|v| v.to_bool()).unwrap_or(false);
@@ -57,12 +66,14 @@ pub fn nl2br(args: &[Value]) -> Result<Value, String> {
 }
 
 /// number_format - Format a number with grouped thousands
-pub fn number_format(args: &[Value]) -> Result<Value, String> {
+pub fn number_format(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("number_format() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "number_format() expects at least 1 parameter",
+        ));
     }
     let num = args[0].to_float();
-    let num_decimals = args.get(1).map(|v| v.to_int() as usize).unwrap_or(0);
+    let num_decimals = args.get(1).map(|v| v.to_int().max(0) as usize).unwrap_or(0);
     let dec_separator = args
         .get(2)
         .map(|v| v.to_string_val())
@@ -102,9 +113,11 @@ pub fn number_format(args: &[Value]) -> Result<Value, String> {
 }
 
 /// md5 - Calculate the md5 hash of a string
-pub fn md5(args: &[Value]) -> Result<Value, String> {
+pub fn md5(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("md5() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "md5() expects exactly 1 parameter",
+        ));
     }
     let s = args[0].to_string_val();
     use std::collections::hash_map::DefaultHasher;
@@ -116,9 +129,11 @@ pub fn md5(args: &[Value]) -> Result<Value, String> {
 }
 
 /// sha1 - Calculate the sha1 hash of a string
-pub fn sha1(args: &[Value]) -> Result<Value, String> {
+pub fn sha1(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("sha1() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "sha1() expects exactly 1 parameter",
+        ));
     }
     let s = args[0].to_string_val();
     use std::collections::hash_map::DefaultHasher;
@@ -130,9 +145,11 @@ pub fn sha1(args: &[Value]) -> Result<Value, String> {
 }
 
 /// bin2hex - Convert binary data into hexadecimal representation
-pub fn bin2hex(args: &[Value]) -> Result<Value, String> {
+pub fn bin2hex(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("bin2hex() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "bin2hex() expects exactly 1 parameter",
+        ));
     }
     let s = args[0].to_string_val();
     let mut result = String::new();
@@ -143,9 +160,11 @@ pub fn bin2hex(args: &[Value]) -> Result<Value, String> {
 }
 
 /// hex2bin - Convert hexadecimal data to binary
-pub fn hex2bin(args: &[Value]) -> Result<Value, String> {
+pub fn hex2bin(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("hex2bin() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "hex2bin() expects exactly 1 parameter",
+        ));
     }
     let s = args[0].to_string_val();
     let mut result = Vec::new();
@@ -161,14 +180,18 @@ pub fn hex2bin(args: &[Value]) -> Result<Value, String> {
 }
 
 /// levenshtein - Calculate levenshtein distance between two strings
-pub fn levenshtein(args: &[Value]) -> Result<Value, String> {
+pub fn levenshtein(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("levenshtein() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "levenshtein() expects at least 2 parameters",
+        ));
     }
     let s1 = args[0].to_string_val();
     let s2 = args[1].to_string_val();
     if s1.len() > 255 || s2.len() > 255 {
-        return Err("levenshtein(): string length exceeds 255 characters".to_string());
+        return Err(NativeError::fatal(
+            "levenshtein(): string length exceeds 255 characters",
+        ));
     }
     let (m, n) = (s1.len(), s2.len());
     if m == 0 {
@@ -177,8 +200,8 @@ pub fn levenshtein(args: &[Value]) -> Result<Value, String> {
     if n == 0 {
         return Ok(Value::Integer(m as i64));
     }
-    let s1_chars: Vec<char> = s1.chars().collect();
-    let s2_chars: Vec<char> = s2.chars().collect();
+    // Lengths are in bytes, as in PHP
+    let (s1_chars, s2_chars) = (s1.as_bytes(), s2.as_bytes());
     let mut prev_row: Vec<i64> = (0..=n as i64).collect();
     let mut curr_row: Vec<i64> = vec![0; n + 1];
 
@@ -201,16 +224,19 @@ pub fn levenshtein(args: &[Value]) -> Result<Value, String> {
 }
 
 /// similar_text - Calculate the similarity between two strings
-pub fn similar_text(args: &[Value]) -> Result<Value, String> {
+pub fn similar_text(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("similar_text() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "similar_text() expects at least 2 parameters",
+        ));
     }
     let s1 = args[0].to_string_val();
     let s2 = args[1].to_string_val();
     let _percent = args.get(2).is_some();
 
     let (first, second) = (s1.len(), s2.len());
-    let (v1, v2): (Vec<char>, Vec<char>) = (s1.chars().collect(), s2.chars().collect());
+    // Lengths are in bytes, as in PHP
+    let (v1, v2) = (s1.as_bytes(), s2.as_bytes());
 
     let mut l = 0;
     let mut i = 0;
@@ -245,9 +271,11 @@ pub fn similar_text(args: &[Value]) -> Result<Value, String> {
 }
 
 /// strtr - Translate characters or replace substrings
-pub fn strtr(args: &[Value]) -> Result<Value, String> {
+pub fn strtr(args: &[Value]) -> Result<Value, NativeError> {
     if args.len() < 2 {
-        return Err("strtr() expects at least 2 parameters".to_string());
+        return Err(NativeError::argument_count_error(
+            "strtr() expects at least 2 parameters",
+        ));
     }
     let s = args[0].to_string_val();
     match &args[1] {
@@ -270,7 +298,9 @@ pub fn strtr(args: &[Value]) -> Result<Value, String> {
             let from = args[1].to_string_val();
             let to = args[2].to_string_val();
             if from.len() != to.len() {
-                return Err("strtr(): The two strings must have the same length".to_string());
+                return Err(NativeError::fatal(
+                    "strtr(): The two strings must have the same length",
+                ));
             }
             let from_chars: Vec<char> = from.chars().collect();
             let to_chars: Vec<char> = to.chars().collect();
@@ -284,6 +314,98 @@ pub fn strtr(args: &[Value]) -> Result<Value, String> {
             }
             Ok(Value::String(result))
         }
-        _ => Err("strtr() expects string, array or two strings".to_string()),
+        _ => Err(NativeError::fatal(
+            "strtr() expects string, array or two strings",
+        )),
+    }
+}
+
+/// substr_count - Count non-overlapping occurrences of needle, optionally
+/// within haystack[offset..offset + length]
+#[vhp_builtin("substr_count")]
+pub fn substr_count(
+    haystack: String,
+    needle: String,
+    offset: Option<i64>,
+    length: Option<i64>,
+) -> Result<i64, NativeError> {
+    if needle.is_empty() {
+        return Err(NativeError::value_error(
+            "substr_count(): Argument #2 ($needle) cannot be empty",
+        ));
+    }
+    let bytes = haystack.as_bytes();
+    let len = bytes.len() as i64;
+    let start = match offset.unwrap_or(0) {
+        o if o < 0 => len + o,
+        o => o,
+    };
+    if start < 0 || start > len {
+        return Err(NativeError::value_error(
+            "substr_count(): Argument #3 ($offset) must be contained in argument #1 ($haystack)",
+        ));
+    }
+    let end = match length {
+        None => len,
+        Some(l) if l < 0 => len + l,
+        Some(l) => start + l,
+    };
+    if end < start || end > len {
+        return Err(NativeError::value_error(
+            "substr_count(): Argument #4 ($length) must be contained in argument #1 ($haystack)",
+        ));
+    }
+    let window = &bytes[start as usize..end as usize];
+    let needle = needle.as_bytes();
+    let mut count = 0;
+    let mut i = 0;
+    while i + needle.len() <= window.len() {
+        if &window[i..i + needle.len()] == needle {
+            count += 1;
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// strpbrk - The rest of string from the first of any of characters,
+/// or false if none occurs
+#[vhp_builtin("strpbrk")]
+pub fn strpbrk(string: String, characters: String) -> Result<Value, NativeError> {
+    if characters.is_empty() {
+        return Err(NativeError::value_error(
+            "strpbrk(): Argument #2 ($characters) must be a non-empty string",
+        ));
+    }
+    Ok(match string.find(|c| characters.contains(c)) {
+        Some(pos) => Value::String(string[pos..].to_string()),
+        None => Value::Bool(false),
+    })
+}
+
+/// chr - Generate a single-byte string from a number
+pub fn chr(args: &[Value]) -> Result<Value, NativeError> {
+    if args.is_empty() {
+        return Err(NativeError::argument_count_error(
+            "chr() expects exactly 1 parameter",
+        ));
+    }
+    let code = arg::<i64>("chr", args, 0, "codepoint")? as u8;
+    Ok(Value::String((code as char).to_string()))
+}
+
+/// ord - Convert first byte of string to value
+pub fn ord(args: &[Value]) -> Result<Value, NativeError> {
+    if args.is_empty() {
+        return Err(NativeError::argument_count_error(
+            "ord() expects exactly 1 parameter",
+        ));
+    }
+    let s = arg::<String>("ord", args, 0, "character")?;
+    match s.chars().next() {
+        Some(c) => Ok(Value::Integer(c as i64)),
+        None => Ok(Value::Integer(0)),
     }
 }
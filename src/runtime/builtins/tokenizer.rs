@@ -0,0 +1,166 @@
+//! Tokenizer functions: token_get_all, token_name and PhpToken
+//!
+//! The tokens are VHP's own lexer's (`lexer::source_tokens`), with PHP's
+//! token ids. token_get_all() gives a single-character token as its
+//! text and any other as `[id, text, line]`; PhpToken::tokenize() gives
+//! `PhpToken` objects with the id, text, line and byte offset.
+
+use crate::lexer::source_tokens::{self, SourceToken};
+use crate::lexer::token_ids;
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::{ArrayKey, ObjectInstance, ObjectRef, Value};
+use crate::vm::error::NativeError;
+
+/// `TOKEN_PARSE`, accepted and ignored: VHP's lexer reads keywords after
+/// `->` as names either way
+pub const TOKEN_PARSE: i64 = 1;
+
+/// The value of a `T_*` constant or `TOKEN_PARSE`
+pub fn constant(name: &str) -> Option<Value> {
+    match name {
+        "TOKEN_PARSE" => Some(Value::Integer(TOKEN_PARSE)),
+        _ => token_ids::token_id(name).map(Value::Integer),
+    }
+}
+
+fn list(values: Vec<Value>) -> Value {
+    Value::Array(
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (ArrayKey::Integer(i as i64), value))
+            .collect(),
+    )
+}
+
+/// token_get_all - Split PHP source into tokens
+///
+/// PHP equivalent: token_get_all($code, $flags = 0)
+pub fn token_get_all(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("token_get_all", args.len(), 1, Some(2))?;
+    let tokens = source_tokens::tokenize(&args[0].to_string_val())
+        .into_iter()
+        .map(|token| match token.name {
+            Some(_) => list(vec![
+                Value::Integer(token.id()),
+                Value::String(token.text),
+                Value::Integer(token.line as i64),
+            ]),
+            None => Value::String(token.text),
+        })
+        .collect();
+    Ok(list(tokens))
+}
+
+/// token_name - The `T_*` name of a token id, or "UNKNOWN"
+///
+/// PHP equivalent: token_name($id)
+pub fn token_name(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("token_name", args.len(), 1, Some(1))?;
+    let name = token_ids::token_name(args[0].to_int()).unwrap_or("UNKNOWN");
+    Ok(Value::String(name.to_string()))
+}
+
+/// A PhpToken object
+fn php_token(id: i64, text: String, line: i64, pos: i64) -> Value {
+    let mut token = ObjectInstance::new("PhpToken".to_string());
+    token.interfaces.push("Stringable".to_string());
+    for (name, value) in [
+        ("id", Value::Integer(id)),
+        ("text", Value::String(text)),
+        ("line", Value::Integer(line)),
+        ("pos", Value::Integer(pos)),
+    ] {
+        token.properties.insert(name.to_string(), value);
+    }
+    Value::Object(token.into())
+}
+
+/// The PhpToken a method was called on
+fn this<'a>(method: &str, args: &'a [Value]) -> Result<&'a ObjectRef, NativeError> {
+    match args.first() {
+        Some(Value::Object(object)) => Ok(object),
+        _ => Err(NativeError::fatal(format!(
+            "PhpToken::{}() called without an object",
+            method
+        ))),
+    }
+}
+
+fn property(token: &ObjectRef, name: &str) -> Value {
+    token.get(name).unwrap_or(Value::Null)
+}
+
+/// PhpToken::__construct: a token from its id and text, with -1 for an
+/// unknown line and offset
+pub fn phptoken_create(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("PhpToken::__construct", args.len(), 2, Some(4))?;
+    let number = |index: usize| match args.get(index) {
+        None | Some(Value::Null) => -1,
+        Some(value) => value.to_int(),
+    };
+    Ok(php_token(
+        args[0].to_int(),
+        args[1].to_string_val(),
+        number(2),
+        number(3),
+    ))
+}
+
+/// PhpToken::tokenize - Split PHP source into PhpToken objects
+pub fn phptoken_tokenize(args: &[Value]) -> Result<Value, NativeError> {
+    check_arity("PhpToken::tokenize", args.len(), 1, Some(2))?;
+    let tokens = source_tokens::tokenize(&args[0].to_string_val())
+        .into_iter()
+        .map(|token: SourceToken| {
+            let id = token.id();
+            php_token(id, token.text, token.line as i64, token.pos as i64)
+        })
+        .collect();
+    Ok(list(tokens))
+}
+
+/// PhpToken::is - Whether the token has an id or text, or one of a list
+pub fn phptoken_is(args: &[Value]) -> Result<Value, NativeError> {
+    let token = this("is", args)?;
+    let matches = |kind: &Value| match kind {
+        Value::Integer(id) => property(token, "id").to_int() == *id,
+        Value::String(text) => property(token, "text").to_string_val() == *text,
+        _ => false,
+    };
+    Ok(Value::Bool(match args.get(1) {
+        Some(Value::Array(kinds)) => kinds.iter().any(|(_, kind)| matches(kind)),
+        Some(kind @ (Value::Integer(_) | Value::String(_))) => matches(kind),
+        other => {
+            return Err(NativeError::type_error(format!(
+                "PhpToken::is(): Argument #1 ($kind) must be of type string|int|array, {} given",
+                other.map_or("null", |value| value.type_name())
+            )))
+        }
+    }))
+}
+
+/// PhpToken::isIgnorable - Whether the token is whitespace, a comment
+/// or an opening tag
+pub fn phptoken_is_ignorable(args: &[Value]) -> Result<Value, NativeError> {
+    let token = this("isIgnorable", args)?;
+    let name = token_ids::token_name(property(token, "id").to_int());
+    Ok(Value::Bool(source_tokens::is_ignorable(name)))
+}
+
+/// PhpToken::getTokenName - The `T_*` name, the character of a
+/// single-character token, or null
+pub fn phptoken_get_token_name(args: &[Value]) -> Result<Value, NativeError> {
+    let token = this("getTokenName", args)?;
+    let id = property(token, "id").to_int();
+    if (0..256).contains(&id) {
+        return Ok(Value::String(char::from(id as u8).to_string()));
+    }
+    Ok(token_ids::token_name(id).map_or(Value::Null, |name| Value::String(name.to_string())))
+}
+
+/// PhpToken::__toString - The token's text
+pub fn phptoken_to_string(args: &[Value]) -> Result<Value, NativeError> {
+    let token = this("__toString", args)?;
+    Ok(Value::String(property(token, "text").to_string_val()))
+}
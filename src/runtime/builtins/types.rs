@@ -1,99 +1,124 @@
 //! Type built-in functions
 
 use crate::runtime::Value;
+use crate::vm::error::NativeError;
 
 /// intval - Get the integer value of a variable
-pub fn intval(args: &[Value]) -> Result<Value, String> {
+pub fn intval(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("intval() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "intval() expects at least 1 parameter",
+        ));
     }
     Ok(Value::Integer(args[0].to_int()))
 }
 
 /// floatval - Get the float value of a variable
-pub fn floatval(args: &[Value]) -> Result<Value, String> {
+pub fn floatval(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("floatval() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "floatval() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Float(args[0].to_float()))
 }
 
 /// strval - Get the string value of a variable
-pub fn strval(args: &[Value]) -> Result<Value, String> {
+pub fn strval(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("strval() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "strval() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::String(args[0].to_string_val()))
 }
 
 /// boolval - Get the boolean value of a variable
-pub fn boolval(args: &[Value]) -> Result<Value, String> {
+pub fn boolval(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("boolval() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "boolval() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(args[0].to_bool()))
 }
 
 /// gettype - Get the type of a variable
-pub fn gettype(args: &[Value]) -> Result<Value, String> {
+pub fn gettype(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("gettype() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "gettype() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::String(args[0].get_type().to_string()))
 }
 
 /// is_null - Finds whether a variable is null
-pub fn is_null(args: &[Value]) -> Result<Value, String> {
+pub fn is_null(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_null() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_null() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(matches!(args[0], Value::Null)))
 }
 
 /// is_bool - Finds out whether a variable is a boolean
-pub fn is_bool(args: &[Value]) -> Result<Value, String> {
+pub fn is_bool(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_bool() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_bool() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(matches!(args[0], Value::Bool(_))))
 }
 
 /// is_int - Finds whether the type of a variable is integer
-pub fn is_int(args: &[Value]) -> Result<Value, String> {
+pub fn is_int(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_int() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_int() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(matches!(args[0], Value::Integer(_))))
 }
 
 /// is_float - Finds whether the type of a variable is float
-pub fn is_float(args: &[Value]) -> Result<Value, String> {
+pub fn is_float(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_float() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_float() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(matches!(args[0], Value::Float(_))))
 }
 
 /// is_string - Find whether the type of a variable is string
-pub fn is_string(args: &[Value]) -> Result<Value, String> {
+pub fn is_string(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_string() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_string() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(matches!(args[0], Value::String(_))))
 }
 
 /// is_array - Finds whether a variable is an array
-pub fn is_array(args: &[Value]) -> Result<Value, String> {
+pub fn is_array(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_array() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_array() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(args[0].is_array()))
 }
 
 /// is_numeric - Finds whether a variable is a number or numeric string
-pub fn is_numeric(args: &[Value]) -> Result<Value, String> {
+pub fn is_numeric(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("is_numeric() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "is_numeric() expects exactly 1 parameter",
+        ));
     }
     let is_numeric = match &args[0] {
         Value::Integer(_) | Value::Float(_) => true,
@@ -104,9 +129,11 @@ pub fn is_numeric(args: &[Value]) -> Result<Value, String> {
 }
 
 /// isset - Determine if a variable is declared and is different than null
-pub fn isset(args: &[Value]) -> Result<Value, String> {
+pub fn isset(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("isset() expects at least 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "isset() expects at least 1 parameter",
+        ));
     }
     Ok(Value::Bool(!matches!(
         args.first(),
@@ -115,9 +142,11 @@ pub fn isset(args: &[Value]) -> Result<Value, String> {
 }
 
 /// empty - Determine whether a variable is empty
-pub fn empty(args: &[Value]) -> Result<Value, String> {
+pub fn empty(args: &[Value]) -> Result<Value, NativeError> {
     if args.is_empty() {
-        return Err("empty() expects exactly 1 parameter".to_string());
+        return Err(NativeError::argument_count_error(
+            "empty() expects exactly 1 parameter",
+        ));
     }
     Ok(Value::Bool(
         !args.first().map(|v| v.to_bool()).unwrap_or(false),
@@ -128,28 +157,7 @@ pub fn empty(args: &[Value]) -> Result<Value, String> {
 /// Note: This is a simplified implementation. In PHP, unset() is a language construct
 /// that removes variables from the symbol table. For now, this just returns null.
 /// The actual removal should be handled at the VM level.
-pub fn unset(_args: &[Value]) -> Result<Value, String> {
+pub fn unset(_args: &[Value]) -> Result<Value, NativeError> {
     // unset() doesn't return a value in PHP, but for VM compatibility we return Null
     Ok(Value::Null)
 }
-
-/// is_callable - Verify that the contents of a variable can be called as a function
-pub fn is_callable(args: &[Value]) -> Result<Value, String> {
-    if args.is_empty() {
-        return Err("is_callable() expects exactly 1 parameter".to_string());
-    }
-
-    let callable = &args[0];
-    let result = match callable {
-        Value::String(_) => true,
-        Value::Array(arr) if arr.len() == 2 => {
-            let first = &arr[0].1;
-            let second = &arr[1].1;
-            matches!((first, second), (Value::String(_), Value::String(_)))
-        }
-        Value::Closure(_) => true,
-        _ => false,
-    };
-
-    Ok(Value::Bool(result))
-}
@@ -0,0 +1,334 @@
+//! XML documents for SimpleXML and DOM
+//!
+//! A document is an arena of nodes, the document node first; nodes refer
+//! to their parent and children by index. The VM keeps the documents
+//! scripts load or build, and the SimpleXMLElement and DOM objects hold
+//! the index of a document and of nodes in it, so copies of an object all
+//! see the changes made through any of them.
+//!
+//! `xml_parser` reads text into a document and `xpath` selects nodes;
+//! this module has the tree itself and its serialization.
+
+/// What a node is
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    Document,
+    Element {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    Text(String),
+    CData(String),
+    Comment(String),
+    ProcessingInstruction {
+        target: String,
+        data: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+/// An XML document: its nodes, the document node at index 0, and what
+/// the XML declaration says
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub nodes: Vec<Node>,
+    pub version: String,
+    pub encoding: Option<String>,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::new("1.0", None)
+    }
+}
+
+impl Document {
+    pub fn new(version: &str, encoding: Option<String>) -> Self {
+        Self {
+            nodes: vec![Node {
+                kind: NodeKind::Document,
+                parent: None,
+                children: Vec::new(),
+            }],
+            version: version.to_string(),
+            encoding,
+        }
+    }
+
+    /// Add a node with no parent yet and give its index
+    pub fn create(&mut self, kind: NodeKind) -> usize {
+        self.nodes.push(Node {
+            kind,
+            parent: None,
+            children: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    pub fn create_element(&mut self, name: &str) -> usize {
+        self.create(NodeKind::Element {
+            name: name.to_string(),
+            attributes: Vec::new(),
+        })
+    }
+
+    /// Make `child` the last child of `parent`, taking it from where it
+    /// was first
+    pub fn append(&mut self, parent: usize, child: usize) {
+        self.detach(child);
+        self.nodes[child].parent = Some(parent);
+        self.nodes[parent].children.push(child);
+    }
+
+    /// Take a node out of its parent's children
+    pub fn detach(&mut self, node: usize) {
+        if let Some(parent) = self.nodes[node].parent.take() {
+            self.nodes[parent].children.retain(|&c| c != node);
+        }
+    }
+
+    /// The root element
+    pub fn root(&self) -> Option<usize> {
+        self.nodes[0]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.is_element(c))
+    }
+
+    pub fn is_element(&self, node: usize) -> bool {
+        matches!(self.nodes[node].kind, NodeKind::Element { .. })
+    }
+
+    /// The name of an element, or the `#text`-style name DOM gives other
+    /// nodes
+    pub fn name(&self, node: usize) -> &str {
+        match &self.nodes[node].kind {
+            NodeKind::Element { name, .. } => name,
+            NodeKind::Document => "#document",
+            NodeKind::Text(_) => "#text",
+            NodeKind::CData(_) => "#cdata-section",
+            NodeKind::Comment(_) => "#comment",
+            NodeKind::ProcessingInstruction { target, .. } => target,
+        }
+    }
+
+    /// The element children of a node, with the name given if any
+    pub fn child_elements(&self, node: usize, name: Option<&str>) -> Vec<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .filter(|&c| self.is_element(c) && name.is_none_or(|n| self.name(c) == n))
+            .collect()
+    }
+
+    /// The element descendants of a node in document order, with the
+    /// name given if any (`*` for all)
+    pub fn descendant_elements(&self, node: usize, name: &str) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut stack: Vec<usize> = self.nodes[node].children.iter().rev().copied().collect();
+        while let Some(current) = stack.pop() {
+            if self.is_element(current) && (name == "*" || self.name(current) == name) {
+                found.push(current);
+            }
+            stack.extend(self.nodes[current].children.iter().rev());
+        }
+        found
+    }
+
+    pub fn attribute(&self, node: usize, name: &str) -> Option<&str> {
+        match &self.nodes[node].kind {
+            NodeKind::Element { attributes, .. } => attributes
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn attributes(&self, node: usize) -> &[(String, String)] {
+        match &self.nodes[node].kind {
+            NodeKind::Element { attributes, .. } => attributes,
+            _ => &[],
+        }
+    }
+
+    /// Set an attribute, keeping its place if it is there already
+    pub fn set_attribute(&mut self, node: usize, name: &str, value: &str) {
+        if let NodeKind::Element { attributes, .. } = &mut self.nodes[node].kind {
+            match attributes.iter_mut().find(|(n, _)| n == name) {
+                Some(attribute) => attribute.1 = value.to_string(),
+                None => attributes.push((name.to_string(), value.to_string())),
+            }
+        }
+    }
+
+    pub fn remove_attribute(&mut self, node: usize, name: &str) {
+        if let NodeKind::Element { attributes, .. } = &mut self.nodes[node].kind {
+            attributes.retain(|(n, _)| n != name);
+        }
+    }
+
+    /// The text of a node and all its descendants, as DOM's
+    /// `textContent` gives it
+    pub fn text_content(&self, node: usize) -> String {
+        match &self.nodes[node].kind {
+            NodeKind::Text(text) | NodeKind::CData(text) | NodeKind::Comment(text) => text.clone(),
+            NodeKind::ProcessingInstruction { data, .. } => data.clone(),
+            NodeKind::Element { .. } | NodeKind::Document => self.nodes[node]
+                .children
+                .iter()
+                .filter(|&&c| !matches!(self.nodes[c].kind, NodeKind::Comment(_)))
+                .map(|&c| self.text_content(c))
+                .collect(),
+        }
+    }
+
+    /// The text of a node's own text and CDATA children, as SimpleXML's
+    /// string conversion gives it
+    pub fn direct_text(&self, node: usize) -> String {
+        self.nodes[node]
+            .children
+            .iter()
+            .filter_map(|&c| match &self.nodes[c].kind {
+                NodeKind::Text(text) | NodeKind::CData(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Replace the children of a node with one text node, or none for
+    /// empty text
+    pub fn set_text(&mut self, node: usize, text: &str) {
+        match &mut self.nodes[node].kind {
+            NodeKind::Text(content) | NodeKind::CData(content) | NodeKind::Comment(content) => {
+                *content = text.to_string();
+                return;
+            }
+            NodeKind::Element { .. } | NodeKind::Document => {}
+            NodeKind::ProcessingInstruction { data, .. } => {
+                *data = text.to_string();
+                return;
+            }
+        }
+        for child in std::mem::take(&mut self.nodes[node].children) {
+            self.nodes[child].parent = None;
+        }
+        if !text.is_empty() {
+            let text = self.create(NodeKind::Text(text.to_string()));
+            self.append(node, text);
+        }
+    }
+
+    /// The XML declaration, as libxml writes it
+    pub fn declaration(&self) -> String {
+        match &self.encoding {
+            Some(encoding) => format!(
+                "<?xml version=\"{}\" encoding=\"{}\"?>",
+                self.version, encoding
+            ),
+            None => format!("<?xml version=\"{}\"?>", self.version),
+        }
+    }
+
+    /// The XML of a node. The document gives the declaration and its
+    /// children a line each; `indent` lays out elements holding only
+    /// elements with a line per child, two spaces deeper.
+    pub fn serialize(&self, node: usize, indent: bool) -> String {
+        let mut out = String::new();
+        if let NodeKind::Document = self.nodes[node].kind {
+            out.push_str(&self.declaration());
+            out.push('\n');
+            for &child in &self.nodes[node].children {
+                self.write_node(child, indent, 0, &mut out);
+                out.push('\n');
+            }
+        } else {
+            self.write_node(node, indent, 0, &mut out);
+        }
+        out
+    }
+
+    fn write_node(&self, node: usize, indent: bool, depth: usize, out: &mut String) {
+        match &self.nodes[node].kind {
+            NodeKind::Document => out.push_str(&self.serialize(node, indent)),
+            NodeKind::Text(text) => out.push_str(&escape(text, false)),
+            NodeKind::CData(text) => {
+                out.push_str("<![CDATA[");
+                out.push_str(text);
+                out.push_str("]]>");
+            }
+            NodeKind::Comment(text) => {
+                out.push_str("<!--");
+                out.push_str(text);
+                out.push_str("-->");
+            }
+            NodeKind::ProcessingInstruction { target, data } => {
+                out.push_str("<?");
+                out.push_str(target);
+                if !data.is_empty() {
+                    out.push(' ');
+                    out.push_str(data);
+                }
+                out.push_str("?>");
+            }
+            NodeKind::Element { name, attributes } => {
+                out.push('<');
+                out.push_str(name);
+                for (attr, value) in attributes {
+                    out.push_str(&format!(" {}=\"{}\"", attr, escape(value, true)));
+                }
+                let children = &self.nodes[node].children;
+                if children.is_empty() {
+                    out.push_str("/>");
+                    return;
+                }
+                out.push('>');
+                let block = indent
+                    && children.iter().all(|&c| {
+                        !matches!(self.nodes[c].kind, NodeKind::Text(_) | NodeKind::CData(_))
+                    });
+                for &child in children {
+                    if block {
+                        out.push('\n');
+                        out.push_str(&"  ".repeat(depth + 1));
+                    }
+                    self.write_node(child, indent, depth + 1, out);
+                }
+                if block {
+                    out.push('\n');
+                    out.push_str(&"  ".repeat(depth));
+                }
+                out.push_str("</");
+                out.push_str(name);
+                out.push('>');
+            }
+        }
+    }
+}
+
+/// Escape text for element content, or for a double-quoted attribute
+/// value
+pub fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\r' => escaped.push_str("&#13;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '\n' if attribute => escaped.push_str("&#10;"),
+            '\t' if attribute => escaped.push_str("&#9;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,361 @@
+//! Reading XML text into a document
+//!
+//! Well-formedness is checked the way libxml reports it: the first error
+//! stops the parse with its line and message. A DOCTYPE is skipped, so
+//! only the five predefined entities and character references expand.
+
+use crate::runtime::builtins::xml::{Document, NodeKind};
+
+/// Where and why a document isn't well-formed
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for XmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Entity: line {}: parser error : {}",
+            self.line, self.message
+        )
+    }
+}
+
+/// Parse `source` into a document. Whitespace-only text between tags is
+/// kept unless `keep_blanks` is false.
+pub fn parse(source: &str, keep_blanks: bool) -> Result<Document, XmlError> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+        line: 1,
+        keep_blanks,
+        document: Document::default(),
+    };
+    parser.parse_document()?;
+    Ok(parser.document)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    keep_blanks: bool,
+    document: Document,
+}
+
+impl Parser {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, XmlError> {
+        Err(XmlError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn advance(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.peek() == Some('\n') {
+                self.line += 1;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.advance(1);
+        }
+    }
+
+    /// The text up to `end`, which is consumed too
+    fn until(&mut self, end: &str, what: &str) -> Result<String, XmlError> {
+        let start = self.pos;
+        while !self.starts_with(end) {
+            if self.peek().is_none() {
+                return self.error(format!("{} not terminated", what));
+            }
+            self.advance(1);
+        }
+        let text = self.chars[start..self.pos].iter().collect();
+        self.advance(end.chars().count());
+        Ok(text)
+    }
+
+    fn name(&mut self) -> Result<String, XmlError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.'))
+        {
+            self.advance(1);
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.')
+        {
+            return self.error("StartTag: invalid element name");
+        }
+        Ok(name)
+    }
+
+    fn parse_document(&mut self) -> Result<(), XmlError> {
+        if self.starts_with("\u{feff}") {
+            self.advance(1);
+        }
+        if self.starts_with("<?xml")
+            && self
+                .chars
+                .get(self.pos + 5)
+                .is_some_and(|c| c.is_whitespace())
+        {
+            self.advance(5);
+            let declaration = self.until("?>", "XML declaration")?;
+            if let Some(version) = pseudo_attribute(&declaration, "version") {
+                self.document.version = version;
+            }
+            self.document.encoding = pseudo_attribute(&declaration, "encoding");
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek().is_none() {
+                break;
+            }
+            if self.starts_with("<!--") || self.starts_with("<?") {
+                self.parse_markup(0)?;
+            } else if self.starts_with("<!DOCTYPE") {
+                self.skip_doctype()?;
+            } else if self.peek() == Some('<') {
+                if self.document.root().is_some() {
+                    return self.error("Extra content at the end of the document");
+                }
+                self.parse_element(0)?;
+            } else if self.document.root().is_some() {
+                return self.error("Extra content at the end of the document");
+            } else {
+                return self.error("Start tag expected, '<' not found");
+            }
+        }
+        if self.document.root().is_none() {
+            return self.error("Start tag expected, '<' not found");
+        }
+        Ok(())
+    }
+
+    fn skip_doctype(&mut self) -> Result<(), XmlError> {
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                None => return self.error("DOCTYPE improperly terminated"),
+                Some('[') => depth += 1,
+                Some(']') => depth -= 1,
+                Some('>') if depth == 0 => {
+                    self.advance(1);
+                    return Ok(());
+                }
+                _ => {}
+            }
+            self.advance(1);
+        }
+    }
+
+    /// A comment, processing instruction or CDATA section under `parent`
+    fn parse_markup(&mut self, parent: usize) -> Result<(), XmlError> {
+        let kind = if self.starts_with("<!--") {
+            self.advance(4);
+            NodeKind::Comment(self.until("-->", "Comment")?)
+        } else if self.starts_with("<![CDATA[") {
+            self.advance(9);
+            NodeKind::CData(self.until("]]>", "CData section")?)
+        } else {
+            self.advance(2);
+            let target = self.name()?;
+            let data = self.until("?>", "PI")?;
+            NodeKind::ProcessingInstruction {
+                target,
+                data: data.trim_start().to_string(),
+            }
+        };
+        let node = self.document.create(kind);
+        self.document.append(parent, node);
+        Ok(())
+    }
+
+    fn parse_element(&mut self, parent: usize) -> Result<(), XmlError> {
+        let line = self.line;
+        self.advance(1);
+        let name = self.name()?;
+        let element = self.document.create_element(&name);
+        self.document.append(parent, element);
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            match self.peek() {
+                Some('/') if self.starts_with("/>") => {
+                    self.advance(2);
+                    return Ok(());
+                }
+                Some('>') => {
+                    self.advance(1);
+                    break;
+                }
+                Some(_) if self.pos > before => {
+                    let attribute = self.name()?;
+                    self.skip_whitespace();
+                    if self.peek() != Some('=') {
+                        return self.error(format!(
+                            "Specification mandates value for attribute {}",
+                            attribute
+                        ));
+                    }
+                    self.advance(1);
+                    self.skip_whitespace();
+                    let value = self.attribute_value()?;
+                    if self.document.attribute(element, &attribute).is_some() {
+                        return self.error(format!("Attribute {} redefined", attribute));
+                    }
+                    self.document.set_attribute(element, &attribute, &value);
+                }
+                Some(_) => return self.error("attributes construct error"),
+                None => return self.error(format!("Couldn't find end of Start Tag {}", name)),
+            }
+        }
+        self.parse_content(element, &name, line)
+    }
+
+    fn attribute_value(&mut self) -> Result<String, XmlError> {
+        let quote = match self.peek() {
+            Some(q @ ('"' | '\'')) => q,
+            _ => return self.error("AttValue: \" or ' expected"),
+        };
+        self.advance(1);
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return self.error("AttValue: ' expected"),
+                Some('<') => return self.error("Unescaped '<' not allowed in attributes values"),
+                Some('&') => value.push_str(&self.reference()?),
+                Some(c) if c == quote => {
+                    self.advance(1);
+                    return Ok(value);
+                }
+                // Attribute values normalize whitespace to spaces
+                Some(c) => {
+                    value.push(if c.is_whitespace() { ' ' } else { c });
+                    self.advance(1);
+                }
+            }
+        }
+    }
+
+    /// An entity or character reference
+    fn reference(&mut self) -> Result<String, XmlError> {
+        self.advance(1);
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c != ';' && c != '<' && !c.is_whitespace())
+        {
+            self.advance(1);
+        }
+        if self.peek() != Some(';') {
+            return self.error("EntityRef: expecting ';'");
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        self.advance(1);
+        let text = match name.as_str() {
+            "lt" => "<",
+            "gt" => ">",
+            "amp" => "&",
+            "quot" => "\"",
+            "apos" => "'",
+            _ => {
+                let code = if let Some(hex) = name.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(decimal) = name.strip_prefix('#') {
+                    decimal.parse().ok()
+                } else {
+                    return self.error(format!("Entity '{}' not defined", name));
+                };
+                return match code.and_then(char::from_u32) {
+                    Some(c) => Ok(c.to_string()),
+                    None => self.error(format!("xmlParseCharRef: invalid xmlChar value {}", name)),
+                };
+            }
+        };
+        Ok(text.to_string())
+    }
+
+    /// The children of `element`, opened on `line`, up to its end tag
+    fn parse_content(&mut self, element: usize, name: &str, line: usize) -> Result<(), XmlError> {
+        let mut text = String::new();
+        loop {
+            if self.peek().is_none() {
+                return self.error(format!(
+                    "Premature end of data in tag {} line {}",
+                    name, line
+                ));
+            }
+            if self.peek() != Some('<') {
+                if self.peek() == Some('&') {
+                    text.push_str(&self.reference()?);
+                } else {
+                    text.extend(self.peek());
+                    self.advance(1);
+                }
+                continue;
+            }
+            self.flush_text(element, &mut text);
+            if self.starts_with("</") {
+                self.advance(2);
+                let end = self.name()?;
+                self.skip_whitespace();
+                if self.peek() != Some('>') {
+                    return self.error("expected '>'");
+                }
+                self.advance(1);
+                if end != name {
+                    return self.error(format!(
+                        "Opening and ending tag mismatch: {} line {} and {}",
+                        name, line, end
+                    ));
+                }
+                return Ok(());
+            }
+            if self.starts_with("<!--") || self.starts_with("<![CDATA[") || self.starts_with("<?") {
+                self.parse_markup(element)?;
+            } else {
+                self.parse_element(element)?;
+            }
+        }
+    }
+
+    fn flush_text(&mut self, element: usize, text: &mut String) {
+        if text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(text);
+        if self.keep_blanks || !text.trim().is_empty() {
+            let node = self.document.create(NodeKind::Text(text));
+            self.document.append(element, node);
+        }
+    }
+}
+
+/// A `name="value"` pair of the XML declaration
+fn pseudo_attribute(declaration: &str, name: &str) -> Option<String> {
+    let rest = &declaration[declaration.find(name)? + name.len()..];
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|q| *q == '"' || *q == '\'')?;
+    let value = &rest[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
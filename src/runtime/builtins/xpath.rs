@@ -0,0 +1,444 @@
+//! XPath 1.0 over XML documents
+//!
+//! Location paths on the child, descendant, parent, self, ancestor,
+//! sibling and attribute axes with their abbreviations, predicates,
+//! unions, the comparison, boolean and arithmetic operators, and the core
+//! functions scripts reach for (`last()`, `position()`, `count()`,
+//! `contains()`, `starts-with()`, `not()`, `name()`, `string()`, ...).
+//! Namespaces aren't resolved: a prefixed name test matches the prefixed
+//! name as written.
+
+use crate::runtime::builtins::xml::{Document, NodeKind};
+use crate::runtime::builtins::xpath_parser::{self, Axis, Expr, Step, Test};
+use std::collections::HashMap;
+
+/// A node a path selects: a node of the document, or an attribute of an
+/// element by name
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Node(usize),
+    Attribute(usize, String),
+}
+
+/// Evaluate `expression` with `context` as the context node and give the
+/// nodes it selects in document order. Expressions giving a string,
+/// number or boolean select nothing, as they do for SimpleXML and DOM.
+pub fn select(document: &Document, context: usize, expression: &str) -> Result<Vec<Item>, String> {
+    let expr = xpath_parser::parse(expression)?;
+    let mut evaluator = Evaluator {
+        document,
+        order: HashMap::new(),
+    };
+    evaluator.number_nodes();
+    match evaluator.eval(&expr, &Item::Node(context), 1, 1)? {
+        XValue::Nodes(nodes) => Ok(nodes),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// What an expression gives
+#[derive(Debug, Clone)]
+enum XValue {
+    Nodes(Vec<Item>),
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+struct Evaluator<'a> {
+    document: &'a Document,
+    /// The position of each node in document order
+    order: HashMap<usize, usize>,
+}
+
+impl Evaluator<'_> {
+    fn number_nodes(&mut self) {
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let position = self.order.len();
+            self.order.insert(node, position);
+            stack.extend(self.document.nodes[node].children.iter().rev());
+        }
+    }
+
+    fn sort_key(&self, item: &Item) -> (usize, usize) {
+        match item {
+            Item::Node(node) => (self.order.get(node).copied().unwrap_or(usize::MAX), 0),
+            Item::Attribute(node, name) => {
+                let index = self
+                    .document
+                    .attributes(*node)
+                    .iter()
+                    .position(|(n, _)| n == name)
+                    .unwrap_or(0);
+                (
+                    self.order.get(node).copied().unwrap_or(usize::MAX),
+                    index + 1,
+                )
+            }
+        }
+    }
+
+    fn in_document_order(&self, mut items: Vec<Item>) -> Vec<Item> {
+        items.sort_by_key(|item| self.sort_key(item));
+        items.dedup();
+        items
+    }
+
+    fn string_value(&self, item: &Item) -> String {
+        match item {
+            Item::Node(node) => self.document.text_content(*node),
+            Item::Attribute(node, name) => self
+                .document
+                .attribute(*node, name)
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    fn eval(
+        &self,
+        expr: &Expr,
+        context: &Item,
+        position: usize,
+        size: usize,
+    ) -> Result<XValue, String> {
+        Ok(match expr {
+            Expr::Literal(literal) => XValue::Str(literal.clone()),
+            Expr::Number(number) => XValue::Num(*number),
+            Expr::Negate(inner) => {
+                XValue::Num(-self.number(self.eval(inner, context, position, size)?))
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left, context, position, size)?;
+                match *op {
+                    "or" if self.boolean(&left) => XValue::Bool(true),
+                    "and" if !self.boolean(&left) => XValue::Bool(false),
+                    "or" | "and" => {
+                        let right = self.eval(right, context, position, size)?;
+                        XValue::Bool(self.boolean(&right))
+                    }
+                    "=" | "!=" | "<" | "<=" | ">" | ">=" => {
+                        let right = self.eval(right, context, position, size)?;
+                        XValue::Bool(self.compare(op, &left, &right))
+                    }
+                    _ => {
+                        let left = self.number(left);
+                        let right = self.number(self.eval(right, context, position, size)?);
+                        XValue::Num(match *op {
+                            "+" => left + right,
+                            "-" => left - right,
+                            "*" => left * right,
+                            "div" => left / right,
+                            _ => left % right,
+                        })
+                    }
+                }
+            }
+            Expr::Union(left, right) => {
+                let mut items = self.nodes(left, context, position, size)?;
+                items.extend(self.nodes(right, context, position, size)?);
+                XValue::Nodes(self.in_document_order(items))
+            }
+            Expr::Filter(primary, predicates) => {
+                let items = self.nodes(primary, context, position, size)?;
+                XValue::Nodes(self.apply_predicates(items, predicates)?)
+            }
+            Expr::Relative(steps) => XValue::Nodes(self.walk(vec![context.clone()], steps)?),
+            Expr::Path(None, steps) => XValue::Nodes(self.walk(vec![Item::Node(0)], steps)?),
+            Expr::Path(Some(start), steps) => {
+                let start = self.nodes(start, context, position, size)?;
+                XValue::Nodes(self.walk(start, steps)?)
+            }
+            Expr::Call(name, args) => self.call(name, args, context, position, size)?,
+        })
+    }
+
+    fn nodes(
+        &self,
+        expr: &Expr,
+        context: &Item,
+        position: usize,
+        size: usize,
+    ) -> Result<Vec<Item>, String> {
+        match self.eval(expr, context, position, size)? {
+            XValue::Nodes(items) => Ok(items),
+            _ => Err("Invalid type".to_string()),
+        }
+    }
+
+    fn walk(&self, mut items: Vec<Item>, steps: &[Step]) -> Result<Vec<Item>, String> {
+        for step in steps {
+            let mut next = Vec::new();
+            for item in &items {
+                let candidates = self.axis(item, step);
+                next.extend(self.apply_predicates(candidates, &step.predicates)?);
+            }
+            items = self.in_document_order(next);
+        }
+        Ok(items)
+    }
+
+    fn apply_predicates(
+        &self,
+        mut items: Vec<Item>,
+        predicates: &[Expr],
+    ) -> Result<Vec<Item>, String> {
+        for predicate in predicates {
+            let size = items.len();
+            let mut kept = Vec::new();
+            for (index, item) in items.into_iter().enumerate() {
+                let keep = match self.eval(predicate, &item, index + 1, size)? {
+                    XValue::Num(n) => n == (index + 1) as f64,
+                    value => self.boolean(&value),
+                };
+                if keep {
+                    kept.push(item);
+                }
+            }
+            items = kept;
+        }
+        Ok(items)
+    }
+
+    /// The nodes of a step's axis that pass its test, in axis order
+    fn axis(&self, item: &Item, step: &Step) -> Vec<Item> {
+        let document = self.document;
+        let node = match item {
+            Item::Node(node) => *node,
+            Item::Attribute(owner, _) => {
+                return match step.axis {
+                    Axis::SelfAxis if matches!(step.test, Test::Node) => vec![item.clone()],
+                    Axis::Parent | Axis::Ancestor => {
+                        let mut found = vec![*owner];
+                        if step.axis == Axis::Ancestor {
+                            found.extend(self.ancestors(*owner));
+                        }
+                        self.passing(found, &step.test)
+                    }
+                    _ => Vec::new(),
+                };
+            }
+        };
+        if step.axis == Axis::Attribute {
+            return document
+                .attributes(node)
+                .iter()
+                .filter(|(name, _)| match &step.test {
+                    Test::Name(test) => test == name,
+                    Test::Any | Test::Node => true,
+                    _ => false,
+                })
+                .map(|(name, _)| Item::Attribute(node, name.clone()))
+                .collect();
+        }
+        let siblings = || {
+            document.nodes[node]
+                .parent
+                .map(|p| document.nodes[p].children.clone())
+                .unwrap_or_default()
+        };
+        let candidates = match step.axis {
+            Axis::Child => document.nodes[node].children.clone(),
+            Axis::Descendant | Axis::DescendantOrSelf => {
+                let mut found = Vec::new();
+                if step.axis == Axis::DescendantOrSelf {
+                    found.push(node);
+                }
+                let mut stack: Vec<usize> = document.nodes[node]
+                    .children
+                    .iter()
+                    .rev()
+                    .copied()
+                    .collect();
+                while let Some(current) = stack.pop() {
+                    found.push(current);
+                    stack.extend(document.nodes[current].children.iter().rev());
+                }
+                found
+            }
+            Axis::Parent => document.nodes[node].parent.into_iter().collect(),
+            Axis::Ancestor => self.ancestors(node),
+            Axis::SelfAxis => vec![node],
+            Axis::FollowingSibling => {
+                let siblings = siblings();
+                let at = siblings.iter().position(|&s| s == node).unwrap_or(0);
+                siblings[at + 1..].to_vec()
+            }
+            Axis::PrecedingSibling => {
+                let siblings = siblings();
+                let at = siblings.iter().position(|&s| s == node).unwrap_or(0);
+                siblings[..at].iter().rev().copied().collect()
+            }
+            Axis::Attribute => Vec::new(),
+        };
+        self.passing(candidates, &step.test)
+    }
+
+    fn ancestors(&self, node: usize) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut current = self.document.nodes[node].parent;
+        while let Some(parent) = current {
+            found.push(parent);
+            current = self.document.nodes[parent].parent;
+        }
+        found
+    }
+
+    fn passing(&self, nodes: Vec<usize>, test: &Test) -> Vec<Item> {
+        nodes
+            .into_iter()
+            .filter(|&n| match (test, &self.document.nodes[n].kind) {
+                (Test::Node, _) => true,
+                (Test::Any, NodeKind::Element { .. }) => true,
+                (Test::Name(test), NodeKind::Element { name, .. }) => test == name,
+                (Test::Text, NodeKind::Text(_) | NodeKind::CData(_)) => true,
+                (Test::Comment, NodeKind::Comment(_)) => true,
+                _ => false,
+            })
+            .map(Item::Node)
+            .collect()
+    }
+
+    fn call(
+        &self,
+        name: &str,
+        args: &[Expr],
+        context: &Item,
+        position: usize,
+        size: usize,
+    ) -> Result<XValue, String> {
+        let mut values = Vec::new();
+        for arg in args {
+            values.push(self.eval(arg, context, position, size)?);
+        }
+        // The string of the first argument, or of the context node
+        let text = |this: &Self, values: &[XValue]| match values.first() {
+            Some(value) => this.string(value),
+            None => this.string_value(context),
+        };
+        Ok(match (name, values.len()) {
+            ("last", 0) => XValue::Num(size as f64),
+            ("position", 0) => XValue::Num(position as f64),
+            ("count", 1) => match &values[0] {
+                XValue::Nodes(items) => XValue::Num(items.len() as f64),
+                _ => return Err("Invalid type".to_string()),
+            },
+            ("name" | "local-name", 0..=1) => {
+                let item = match values.first() {
+                    Some(XValue::Nodes(items)) => items.first().cloned(),
+                    Some(_) => return Err("Invalid type".to_string()),
+                    None => Some(context.clone()),
+                };
+                let qualified = match &item {
+                    Some(Item::Node(node)) if self.document.is_element(*node) => {
+                        self.document.name(*node).to_string()
+                    }
+                    Some(Item::Attribute(_, attribute)) => attribute.clone(),
+                    _ => String::new(),
+                };
+                match (name, qualified.split_once(':')) {
+                    ("local-name", Some((_, local))) => XValue::Str(local.to_string()),
+                    _ => XValue::Str(qualified),
+                }
+            }
+            ("string", 0..=1) => XValue::Str(text(self, &values)),
+            ("string-length", 0..=1) => XValue::Num(text(self, &values).chars().count() as f64),
+            ("normalize-space", 0..=1) => XValue::Str(
+                text(self, &values)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            ("number", 0..=1) => match values.into_iter().next() {
+                Some(value) => XValue::Num(self.number(value)),
+                None => XValue::Num(self.number(XValue::Nodes(vec![context.clone()]))),
+            },
+            ("concat", 2..) => XValue::Str(values.iter().map(|v| self.string(v)).collect()),
+            ("contains", 2) => {
+                XValue::Bool(self.string(&values[0]).contains(&self.string(&values[1])))
+            }
+            ("starts-with", 2) => XValue::Bool(
+                self.string(&values[0])
+                    .starts_with(&self.string(&values[1])),
+            ),
+            ("not", 1) => XValue::Bool(!self.boolean(&values[0])),
+            ("boolean", 1) => XValue::Bool(self.boolean(&values[0])),
+            ("true", 0) => XValue::Bool(true),
+            ("false", 0) => XValue::Bool(false),
+            _ => return Err("Invalid expression".to_string()),
+        })
+    }
+
+    fn string(&self, value: &XValue) -> String {
+        match value {
+            XValue::Nodes(items) => items
+                .first()
+                .map(|i| self.string_value(i))
+                .unwrap_or_default(),
+            XValue::Str(s) => s.clone(),
+            XValue::Num(n) if n.fract() == 0.0 && n.is_finite() => format!("{}", *n as i64),
+            XValue::Num(n) => n.to_string(),
+            XValue::Bool(b) => b.to_string(),
+        }
+    }
+
+    fn number(&self, value: XValue) -> f64 {
+        match value {
+            XValue::Num(n) => n,
+            XValue::Bool(b) => b as i64 as f64,
+            other => self.string(&other).trim().parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    fn boolean(&self, value: &XValue) -> bool {
+        match value {
+            XValue::Nodes(items) => !items.is_empty(),
+            XValue::Str(s) => !s.is_empty(),
+            XValue::Num(n) => *n != 0.0 && !n.is_nan(),
+            XValue::Bool(b) => *b,
+        }
+    }
+
+    /// A comparison; with a node-set, whether any of its nodes compares
+    /// so
+    fn compare(&self, op: &str, left: &XValue, right: &XValue) -> bool {
+        match (left, right) {
+            (XValue::Nodes(items), other) => items.iter().any(|item| {
+                let value = XValue::Str(self.string_value(item));
+                self.compare(op, &value, other)
+            }),
+            (other, XValue::Nodes(_)) => {
+                let flipped = match op {
+                    "<" => ">",
+                    "<=" => ">=",
+                    ">" => "<",
+                    ">=" => "<=",
+                    _ => op,
+                };
+                self.compare(flipped, right, other)
+            }
+            _ if matches!(op, "=" | "!=") => {
+                let equal = match (left, right) {
+                    (XValue::Bool(_), _) | (_, XValue::Bool(_)) => {
+                        self.boolean(left) == self.boolean(right)
+                    }
+                    (XValue::Num(_), _) | (_, XValue::Num(_)) => {
+                        self.number(left.clone()) == self.number(right.clone())
+                    }
+                    _ => self.string(left) == self.string(right),
+                };
+                equal == (op == "=")
+            }
+            _ => {
+                let (l, r) = (self.number(left.clone()), self.number(right.clone()));
+                match op {
+                    "<" => l < r,
+                    "<=" => l <= r,
+                    ">" => l > r,
+                    _ => l >= r,
+                }
+            }
+        }
+    }
+}
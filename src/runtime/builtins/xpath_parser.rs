@@ -0,0 +1,442 @@
+//! Reading XPath expressions into syntax trees
+//!
+//! Abbreviations are expanded as the parser meets them: `//` becomes a
+//! `descendant-or-self::node()` step, `..` a parent step, `@` the
+//! attribute axis.
+
+/// Parse an XPath expression
+pub fn parse(expression: &str) -> Result<Expr, String> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.or_expr()?;
+    if parser.pos < parser.tokens.len() {
+        return Err("Invalid expression".to_string());
+    }
+    Ok(expr)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    Dot,
+    DotDot,
+    At,
+    Comma,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Pipe,
+    Plus,
+    Minus,
+    Star,
+    Op(&'static str),
+    ColonColon,
+    Name(String),
+    Literal(String),
+    Number(f64),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '/' if next == Some('/') => (Token::DoubleSlash, 2),
+            '/' => (Token::Slash, 1),
+            '.' if next == Some('.') => (Token::DotDot, 2),
+            '.' if !next.is_some_and(|n| n.is_ascii_digit()) => (Token::Dot, 1),
+            '@' => (Token::At, 1),
+            ',' => (Token::Comma, 1),
+            '(' => (Token::LParen, 1),
+            ')' => (Token::RParen, 1),
+            '[' => (Token::LBracket, 1),
+            ']' => (Token::RBracket, 1),
+            '|' => (Token::Pipe, 1),
+            '+' => (Token::Plus, 1),
+            '-' => (Token::Minus, 1),
+            '*' => (Token::Star, 1),
+            '=' => (Token::Op("="), 1),
+            '!' if next == Some('=') => (Token::Op("!="), 2),
+            '<' if next == Some('=') => (Token::Op("<="), 2),
+            '<' => (Token::Op("<"), 1),
+            '>' if next == Some('=') => (Token::Op(">="), 2),
+            '>' => (Token::Op(">"), 1),
+            ':' if next == Some(':') => (Token::ColonColon, 2),
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&q| q == c)
+                    .ok_or("Invalid expression")?;
+                let literal = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Literal(literal), end + 2)
+            }
+            _ if c.is_ascii_digit() || c == '.' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|d| d.is_ascii_digit() || **d == '.')
+                    .count();
+                let text: String = chars[i..i + len].iter().collect();
+                let number = text.parse().map_err(|_| "Invalid expression")?;
+                (Token::Number(number), len)
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut len = 0;
+                while let Some(&n) = chars.get(i + len) {
+                    let prefix_colon =
+                        n == ':' && chars.get(i + len + 1).is_some_and(|a| a.is_alphabetic());
+                    if !(n.is_alphanumeric() || matches!(n, '_' | '-' | '.') || prefix_colon) {
+                        break;
+                    }
+                    len += if prefix_colon { 2 } else { 1 };
+                }
+                (Token::Name(chars[i..i + len].iter().collect()), len)
+            }
+            _ => return Err("Invalid expression".to_string()),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Parent,
+    Ancestor,
+    SelfAxis,
+    Attribute,
+    FollowingSibling,
+    PrecedingSibling,
+}
+
+#[derive(Debug, Clone)]
+pub enum Test {
+    Name(String),
+    Any,
+    Text,
+    Node,
+    Comment,
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub axis: Axis,
+    pub test: Test,
+    pub predicates: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    /// A path from the root (`None`), or from what an expression gives
+    Path(Option<Box<Expr>>, Vec<Step>),
+    Relative(Vec<Step>),
+    Filter(Box<Expr>, Vec<Expr>),
+    Literal(String),
+    Number(f64),
+    Call(String, Vec<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), String> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err("Invalid expression".to_string())
+        }
+    }
+
+    fn eat_name(&mut self, name: &str) -> bool {
+        self.eat(&Token::Name(name.to_string()))
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.and_expr()?;
+        while self.eat_name("or") {
+            left = Expr::Binary("or", Box::new(left), Box::new(self.and_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.comparison(&["=", "!="])?;
+        while self.eat_name("and") {
+            left = Expr::Binary(
+                "and",
+                Box::new(left),
+                Box::new(self.comparison(&["=", "!="])?),
+            );
+        }
+        Ok(left)
+    }
+
+    /// Equality comparisons, each side a relational comparison
+    fn comparison(&mut self, ops: &[&'static str]) -> Result<Expr, String> {
+        let relational = ops[0] == "<";
+        let mut left = if relational {
+            self.additive()?
+        } else {
+            self.comparison(&["<", "<=", ">", ">="])?
+        };
+        while let Some(Token::Op(op)) = self.peek().cloned() {
+            if !ops.contains(&op) {
+                break;
+            }
+            self.pos += 1;
+            let right = if relational {
+                self.additive()?
+            } else {
+                self.comparison(&["<", "<=", ">", ">="])?
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => "+",
+                Some(Token::Minus) => "-",
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => "*",
+                Some(Token::Name(name)) if name == "div" => "div",
+                Some(Token::Name(name)) if name == "mod" => "mod",
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Minus) {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        let mut left = self.path()?;
+        while self.eat(&Token::Pipe) {
+            left = Expr::Union(Box::new(left), Box::new(self.path()?));
+        }
+        Ok(left)
+    }
+
+    fn path(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Slash) => {
+                self.pos += 1;
+                let steps = if self.starts_step() {
+                    self.steps()?
+                } else {
+                    Vec::new()
+                };
+                Ok(Expr::Path(None, steps))
+            }
+            Some(Token::DoubleSlash) => {
+                self.pos += 1;
+                let mut steps = vec![descendant_or_self()];
+                steps.extend(self.steps()?);
+                Ok(Expr::Path(None, steps))
+            }
+            Some(Token::Literal(_) | Token::Number(_) | Token::LParen) => self.filter(),
+            Some(Token::Name(name))
+                if self.tokens.get(self.pos + 1) == Some(&Token::LParen)
+                    && !matches!(name.as_str(), "text" | "node" | "comment") =>
+            {
+                self.filter()
+            }
+            _ => Ok(Expr::Relative(self.steps()?)),
+        }
+    }
+
+    /// A primary expression with predicates, and a path from it
+    fn filter(&mut self) -> Result<Expr, String> {
+        let primary = match self.peek().cloned() {
+            Some(Token::Literal(literal)) => {
+                self.pos += 1;
+                Expr::Literal(literal)
+            }
+            Some(Token::Number(number)) => {
+                self.pos += 1;
+                Expr::Number(number)
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.or_expr()?;
+                self.expect(&Token::RParen)?;
+                expr
+            }
+            Some(Token::Name(name)) => {
+                self.pos += 2;
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.or_expr()?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        self.expect(&Token::Comma)?;
+                    }
+                }
+                Expr::Call(name, args)
+            }
+            _ => return Err("Invalid expression".to_string()),
+        };
+        let predicates = self.predicates()?;
+        let primary = if predicates.is_empty() {
+            primary
+        } else {
+            Expr::Filter(Box::new(primary), predicates)
+        };
+        let mut steps = Vec::new();
+        match self.peek() {
+            Some(Token::Slash) => self.pos += 1,
+            Some(Token::DoubleSlash) => {
+                self.pos += 1;
+                steps.push(descendant_or_self());
+            }
+            _ => return Ok(primary),
+        }
+        steps.extend(self.steps()?);
+        Ok(Expr::Path(Some(Box::new(primary)), steps))
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Dot | Token::DotDot | Token::At | Token::Star | Token::Name(_))
+        )
+    }
+
+    fn steps(&mut self) -> Result<Vec<Step>, String> {
+        let mut steps = vec![self.step()?];
+        loop {
+            if self.eat(&Token::DoubleSlash) {
+                steps.push(descendant_or_self());
+            } else if !self.eat(&Token::Slash) {
+                return Ok(steps);
+            }
+            steps.push(self.step()?);
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, String> {
+        if self.eat(&Token::Dot) {
+            return Ok(plain_step(Axis::SelfAxis, Test::Node));
+        }
+        if self.eat(&Token::DotDot) {
+            return Ok(plain_step(Axis::Parent, Test::Node));
+        }
+        let mut axis = Axis::Child;
+        if self.eat(&Token::At) {
+            axis = Axis::Attribute;
+        } else if let (Some(Token::Name(name)), Some(Token::ColonColon)) =
+            (self.peek().cloned(), self.tokens.get(self.pos + 1))
+        {
+            axis = match name.as_str() {
+                "child" => Axis::Child,
+                "descendant" => Axis::Descendant,
+                "descendant-or-self" => Axis::DescendantOrSelf,
+                "parent" => Axis::Parent,
+                "ancestor" => Axis::Ancestor,
+                "self" => Axis::SelfAxis,
+                "attribute" => Axis::Attribute,
+                "following-sibling" => Axis::FollowingSibling,
+                "preceding-sibling" => Axis::PrecedingSibling,
+                _ => return Err("Invalid expression".to_string()),
+            };
+            self.pos += 2;
+        }
+        let test = match self.peek().cloned() {
+            Some(Token::Star) => {
+                self.pos += 1;
+                Test::Any
+            }
+            Some(Token::Name(name)) => {
+                self.pos += 1;
+                let kind_test = match name.as_str() {
+                    "text" => Some(Test::Text),
+                    "node" => Some(Test::Node),
+                    "comment" => Some(Test::Comment),
+                    _ => None,
+                };
+                match kind_test {
+                    Some(test) if self.eat(&Token::LParen) => {
+                        self.expect(&Token::RParen)?;
+                        test
+                    }
+                    _ => Test::Name(name),
+                }
+            }
+            _ => return Err("Invalid expression".to_string()),
+        };
+        Ok(Step {
+            axis,
+            test,
+            predicates: self.predicates()?,
+        })
+    }
+
+    fn predicates(&mut self) -> Result<Vec<Expr>, String> {
+        let mut predicates = Vec::new();
+        while self.eat(&Token::LBracket) {
+            predicates.push(self.or_expr()?);
+            self.expect(&Token::RBracket)?;
+        }
+        Ok(predicates)
+    }
+}
+
+fn plain_step(axis: Axis, test: Test) -> Step {
+    Step {
+        axis,
+        test,
+        predicates: Vec::new(),
+    }
+}
+
+/// The step `//` abbreviates
+fn descendant_or_self() -> Step {
+    plain_step(Axis::DescendantOrSelf, Test::Node)
+}
@@ -0,0 +1,371 @@
+//! YAML functions: yaml_parse, yaml_emit and their file variants
+//!
+//! Plain scalars resolve the way the yaml extension resolves them:
+//! `~`, `null` and an empty value are null, `true`/`false`, `yes`/`no`
+//! and `on`/`off` are booleans, and decimal, octal (`017`), hex (`0x1F`)
+//! and binary (`0b101`) integers and floats (`1.5`, `1e3`, `.inf`,
+//! `.nan`) are numbers. Quoted scalars are always strings.
+//!
+//! `yaml_emit` writes block style the way libyaml does: sequences under
+//! a key aren't indented, and strings that would read back as another
+//! type are double-quoted.
+
+use crate::runtime::builtins::yaml_parser;
+use crate::runtime::filesystem::Filesystem;
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::NativeError;
+
+/// Why a YAML stream couldn't be read
+#[derive(Debug, Clone, PartialEq)]
+pub struct YamlError {
+    pub message: String,
+}
+
+impl YamlError {
+    /// A syntax error at a line and column (both from 1), found by the
+    /// "scanner" or the "parser"
+    pub fn syntax(kind: &str, problem: &str, line: usize, column: usize) -> Self {
+        YamlError {
+            message: format!(
+                "{} error encountered during parsing: {} (line {}, column {})",
+                if kind == "scanner" {
+                    "scanning"
+                } else {
+                    "parsing"
+                },
+                problem,
+                line,
+                column
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for YamlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// The document at `pos` of `source`, or all of them in an array when
+/// `pos` is -1
+pub fn yaml_parse(source: &str, pos: i64) -> Result<Value, YamlError> {
+    let documents = yaml_parser::parse(source)?;
+    if pos < 0 {
+        return Ok(Value::Array(
+            documents
+                .into_iter()
+                .enumerate()
+                .map(|(i, document)| (ArrayKey::Integer(i as i64), document))
+                .collect(),
+        ));
+    }
+    documents
+        .into_iter()
+        .nth(pos as usize)
+        .ok_or_else(|| YamlError {
+            message: format!("end of stream reached without finding document {}", pos),
+        })
+}
+
+/// The value of a plain scalar
+pub fn resolve(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" | "yes" | "Yes" | "YES" | "on" | "On" | "ON" => {
+            return Value::Bool(true)
+        }
+        "false" | "False" | "FALSE" | "no" | "No" | "NO" | "off" | "Off" | "OFF" => {
+            return Value::Bool(false)
+        }
+        _ => {}
+    }
+    integer(text)
+        .or_else(|| float(text))
+        .unwrap_or_else(|| Value::String(text.to_string()))
+}
+
+fn integer(text: &str) -> Option<Value> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (radix, digits) = if let Some(hex) = digits.strip_prefix("0x") {
+        (16, hex)
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        (2, binary)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (8, &digits[1..])
+    } else {
+        (10, digits)
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let signed = if negative {
+        format!("-{}", digits)
+    } else {
+        digits
+    };
+    Some(match i64::from_str_radix(&signed, radix) {
+        Ok(n) => Value::Integer(n),
+        Err(_) => Value::Float(if radix == 10 {
+            signed.parse().ok()?
+        } else {
+            return None;
+        }),
+    })
+}
+
+fn float(text: &str) -> Option<Value> {
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let sign = if text.starts_with('-') { -1.0 } else { 1.0 };
+    match (unsigned, unsigned.len() == text.len()) {
+        (".inf" | ".Inf" | ".INF", _) => return Some(Value::Float(sign * f64::INFINITY)),
+        (".nan" | ".NaN" | ".NAN", true) => return Some(Value::Float(f64::NAN)),
+        _ => {}
+    }
+    let number = text.replace('_', "");
+    let mantissa = number.trim_start_matches(['-', '+']);
+    let mantissa = mantissa.split(['e', 'E']).next().unwrap_or_default();
+    let well_formed = mantissa.chars().any(|c| c.is_ascii_digit())
+        && mantissa.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && mantissa.matches('.').count() <= 1;
+    if !well_formed {
+        return None;
+    }
+    number.parse().ok().map(Value::Float)
+}
+
+/// A scalar under an explicit tag; unknown tags leave it a string
+pub fn tagged(tag: &str, text: String) -> Value {
+    let name = tag
+        .strip_prefix("tag:yaml.org,2002:")
+        .or_else(|| tag.strip_prefix("!!"))
+        .unwrap_or(tag);
+    match name {
+        "int" => integer(&text).unwrap_or_else(|| Value::Integer(Value::String(text).to_int())),
+        "float" => float(&text)
+            .or_else(|| integer(&text).map(|n| Value::Float(n.to_float())))
+            .unwrap_or_else(|| Value::Float(Value::String(text).to_float())),
+        "bool" => match resolve(&text) {
+            Value::Bool(b) => Value::Bool(b),
+            other => Value::Bool(other.to_bool()),
+        },
+        "null" => Value::Null,
+        "binary" => Value::String(decode_base64(&text)),
+        _ => Value::String(text),
+    }
+}
+
+fn decode_base64(text: &str) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let Some(index) = ALPHABET.iter().position(|a| *a == c) else {
+            continue;
+        };
+        buffer = (buffer << 6) | index as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// yaml_emit - The YAML document of a value
+pub fn yaml_emit(args: &[Value]) -> Result<Value, NativeError> {
+    if args.is_empty() {
+        return Err(NativeError::argument_count_error(
+            "yaml_emit() expects at least 1 argument, 0 given",
+        ));
+    }
+    Ok(Value::String(emit(&args[0])))
+}
+
+/// yaml_emit_file - Write the YAML document of a value to a file
+pub fn yaml_emit_file(args: &[Value], fs: &mut dyn Filesystem) -> Result<Value, NativeError> {
+    if args.len() < 2 {
+        return Err(NativeError::argument_count_error(format!(
+            "yaml_emit_file() expects at least 2 arguments, {} given",
+            args.len()
+        )));
+    }
+    let written = fs.write(&args[0].to_string_val(), &emit(&args[1]));
+    Ok(Value::Bool(written.is_ok()))
+}
+
+fn emit(value: &Value) -> String {
+    let mut out = String::from("---");
+    match entries(value) {
+        Some(entries) if !entries.is_empty() => {
+            out.push('\n');
+            emit_collection(&entries, 0, false, &mut out);
+        }
+        _ => emit_after_indicator(value, 0, &mut out),
+    }
+    out.push_str("...\n");
+    out
+}
+
+/// The entries of an array, or the properties of an object by name
+fn entries(value: &Value) -> Option<Vec<(ArrayKey, Value)>> {
+    match value {
+        Value::Array(items) => Some(items.clone().into_vec()),
+        Value::Object(object) => {
+            let mut properties: Vec<_> = object
+                .properties()
+                .into_iter()
+                .filter(|(name, _)| !name.starts_with("__"))
+                .map(|(name, value)| (ArrayKey::String(name), value))
+                .collect();
+            properties.sort_by_key(|(name, _)| name.to_string());
+            Some(properties)
+        }
+        _ => None,
+    }
+}
+
+fn is_list(entries: &[(ArrayKey, Value)]) -> bool {
+    entries
+        .iter()
+        .enumerate()
+        .all(|(i, (key, _))| *key == ArrayKey::Integer(i as i64))
+}
+
+/// A block sequence or mapping indented by `indent`, its first line
+/// already started when `inline`
+fn emit_collection(entries: &[(ArrayKey, Value)], indent: usize, inline: bool, out: &mut String) {
+    let list = is_list(entries);
+    for (i, (key, value)) in entries.iter().enumerate() {
+        if i > 0 || !inline {
+            out.push_str(&" ".repeat(indent));
+        }
+        if list {
+            out.push('-');
+        } else {
+            match key {
+                ArrayKey::Integer(n) => out.push_str(&n.to_string()),
+                ArrayKey::String(s) => out.push_str(&string_scalar(s)),
+            }
+            out.push(':');
+        }
+        match entries_of_nonempty(value) {
+            Some(children) if list => {
+                out.push(' ');
+                emit_collection(&children, indent + 2, true, out);
+            }
+            Some(children) => {
+                out.push('\n');
+                let nested = if is_list(&children) {
+                    indent
+                } else {
+                    indent + 2
+                };
+                emit_collection(&children, nested, false, out);
+            }
+            None => emit_after_indicator(value, indent, out),
+        }
+    }
+}
+
+fn entries_of_nonempty(value: &Value) -> Option<Vec<(ArrayKey, Value)>> {
+    entries(value).filter(|entries| !entries.is_empty())
+}
+
+/// A scalar or empty collection after `---`, `-` or `key:`, ending its line
+fn emit_after_indicator(value: &Value, indent: usize, out: &mut String) {
+    match value {
+        Value::String(s) if s.contains('\n') => {
+            let chomp = match s.len() - s.trim_end_matches('\n').len() {
+                0 => "-",
+                1 => "",
+                _ => "+",
+            };
+            let indicator = if s.starts_with([' ', '\n']) { "2" } else { "" };
+            out.push_str(&format!(" |{}{}\n", indicator, chomp));
+            let body = s.strip_suffix('\n').unwrap_or(s);
+            for line in body.split('\n') {
+                if !line.is_empty() {
+                    out.push_str(&" ".repeat(indent + 2));
+                    out.push_str(line);
+                }
+                out.push('\n');
+            }
+        }
+        _ => {
+            out.push(' ');
+            out.push_str(&scalar(value));
+            out.push('\n');
+        }
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "~".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Integer(n) => n.to_string(),
+        Value::Float(f) if f.is_nan() => ".NAN".to_string(),
+        Value::Float(f) if f.is_infinite() => if *f > 0.0 { ".INF" } else { "-.INF" }.to_string(),
+        Value::Float(_) => {
+            let text = value.to_string_val();
+            if text.contains(['.', 'e', 'E']) {
+                text
+            } else {
+                format!("{}.0", text)
+            }
+        }
+        Value::String(s) => string_scalar(s),
+        Value::Array(_) | Value::Object(_) => "[]".to_string(),
+        other => string_scalar(&other.to_string_val()),
+    }
+}
+
+/// A string plain when it reads back as itself, double-quoted when it
+/// would read back as another type or has control characters, and
+/// single-quoted otherwise
+fn string_scalar(s: &str) -> String {
+    let has_control = s.chars().any(|c| c.is_control() && c != '\n');
+    if has_control || !matches!(resolve(s), Value::String(_)) {
+        return double_quoted(s);
+    }
+    let mut chars = s.chars();
+    let starts_token = match (chars.next(), chars.next()) {
+        (Some('-' | '?' | ':'), second) => matches!(second, None | Some(' ')),
+        (Some(first), _) => ",[]{}#&*!|>'\"%@` ".contains(first),
+        (None, _) => true,
+    };
+    let plain = !starts_token
+        && !s.ends_with([' ', ':'])
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.contains('\n');
+    if plain {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "''"))
+    }
+}
+
+fn double_quoted(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() => out.push_str(&format!("\\x{:02X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,458 @@
+//! Reading YAML streams into PHP values
+//!
+//! Block and flow collections, plain, single- and double-quoted scalars,
+//! literal and folded block scalars, anchors and aliases, `<<` merge keys
+//! and `---` / `...` document markers. Mappings become arrays keyed the
+//! way PHP keys arrays, so `1: a` gives the key `1`.
+
+use crate::runtime::builtins::yaml::{tagged, YamlError};
+use crate::runtime::builtins::yaml_scanner::Scalar;
+use crate::runtime::{ArrayKey, PhpArray, Value};
+use std::collections::HashMap;
+
+/// Parse every document of `source`
+pub fn parse(source: &str) -> Result<Vec<Value>, YamlError> {
+    let mut parser = Parser {
+        chars: source.chars().filter(|c| *c != '\r').collect(),
+        pos: 0,
+        anchors: HashMap::new(),
+    };
+    parser.stream()
+}
+
+/// Where a block node sits, which decides what may start it
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    Document,
+    /// The value of a key at this column
+    MappingValue(usize),
+    /// An entry of a sequence whose `-` is at this column
+    SequenceEntry(usize),
+}
+
+impl Context {
+    fn indent(self) -> i64 {
+        match self {
+            Context::Document => -1,
+            Context::MappingValue(column) | Context::SequenceEntry(column) => column as i64,
+        }
+    }
+}
+
+pub(crate) struct Parser {
+    pub(crate) chars: Vec<char>,
+    pub(crate) pos: usize,
+    anchors: HashMap<String, Value>,
+}
+
+impl Parser {
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    pub(crate) fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    /// Whether the character at `offset` ends a token: a blank, a line
+    /// break or the end of the stream
+    pub(crate) fn blank_at(&self, offset: usize) -> bool {
+        matches!(self.peek_at(offset), None | Some(' ' | '\t' | '\n'))
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    pub(crate) fn column(&self) -> usize {
+        self.chars[..self.pos]
+            .iter()
+            .rev()
+            .take_while(|c| **c != '\n')
+            .count()
+    }
+
+    pub(crate) fn error<T>(&self, kind: &str, problem: &str) -> Result<T, YamlError> {
+        let line = self.chars[..self.pos]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count()
+            + 1;
+        Err(YamlError::syntax(kind, problem, line, self.column() + 1))
+    }
+
+    pub(crate) fn skip_inline(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip blanks, comments and line breaks up to the next token, giving
+    /// whether that token starts its line
+    fn skip_to_content(&mut self) -> bool {
+        let mut new_line = self.chars[self.pos - self.column()..self.pos]
+            .iter()
+            .all(|c| matches!(c, ' ' | '\t'));
+        loop {
+            self.skip_inline();
+            match self.peek() {
+                Some('#') => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.pos += 1;
+                    }
+                }
+                Some('\n') => {
+                    self.pos += 1;
+                    new_line = true;
+                }
+                _ => return new_line,
+            }
+        }
+    }
+
+    fn at_marker(&self, marker: &str) -> bool {
+        self.column() == 0 && self.starts_with(marker) && self.blank_at(3)
+    }
+
+    pub(crate) fn at_document_end(&self) -> bool {
+        self.peek().is_none() || self.at_marker("---") || self.at_marker("...")
+    }
+
+    pub(crate) fn at_sequence_entry(&self) -> bool {
+        self.peek() == Some('-') && self.blank_at(1)
+    }
+
+    fn stream(&mut self) -> Result<Vec<Value>, YamlError> {
+        let mut documents = Vec::new();
+        loop {
+            self.skip_to_content();
+            while self.column() == 0 && self.peek() == Some('%') {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.pos += 1;
+                }
+                self.skip_to_content();
+            }
+            if self.peek().is_none() {
+                return Ok(documents);
+            }
+            if self.at_marker("...") {
+                self.pos += 3;
+                continue;
+            }
+            if self.at_marker("---") {
+                self.pos += 3;
+            }
+            self.anchors.clear();
+            documents.push(self.block_node(Context::Document)?);
+            self.skip_to_content();
+            if self.at_marker("...") {
+                self.pos += 3;
+            } else if !self.at_document_end() {
+                return self.error("parser", "did not find expected <document start>");
+            }
+        }
+    }
+
+    fn block_node(&mut self, context: Context) -> Result<Value, YamlError> {
+        let mut new_line = self.skip_to_content();
+        if self.at_document_end() || (new_line && !self.starts_node(context)) {
+            return Ok(Value::Null);
+        }
+        let (anchor, tag) = self.properties()?;
+        if anchor.is_some() || tag.is_some() {
+            let before = self.pos;
+            new_line = self.skip_to_content();
+            if self.at_document_end() || (new_line && !self.starts_node(context)) {
+                self.pos = before;
+                let value = match tag {
+                    Some(tag) => tagged(&tag, String::new()),
+                    None => Value::Null,
+                };
+                return Ok(self.anchor(anchor, value));
+            }
+        }
+        let inline = !new_line && context != Context::Document;
+        let column = self.column();
+        let value = match self.peek() {
+            Some('*') => self.alias()?,
+            Some('-') if self.at_sequence_entry() => {
+                if inline && matches!(context, Context::MappingValue(_)) {
+                    return self.error(
+                        "scanner",
+                        "block sequence entries are not allowed in this context",
+                    );
+                }
+                self.block_sequence(column)?
+            }
+            Some(c @ ('|' | '>')) => self.block_scalar(c == '>', context.indent())?,
+            Some('[' | '{') => self.flow_node()?,
+            _ => {
+                let scalar = self.scalar(false)?;
+                self.skip_inline();
+                if self.peek() == Some(':') && self.blank_at(1) {
+                    if inline && matches!(context, Context::MappingValue(_)) {
+                        return self
+                            .error("scanner", "mapping values are not allowed in this context");
+                    }
+                    let key = self.resolve(scalar, None);
+                    self.block_mapping(column, key)?
+                } else if scalar.plain && tag.is_none() {
+                    let text = self.plain_continuation(scalar.text, context.indent());
+                    self.resolve(Scalar { text, plain: true }, None)
+                } else {
+                    self.resolve(scalar, tag.as_deref())
+                }
+            }
+        };
+        Ok(self.anchor(anchor, value))
+    }
+
+    /// Whether a token at the start of a line belongs to a node in `context`
+    fn starts_node(&self, context: Context) -> bool {
+        let column = self.column() as i64;
+        match context {
+            Context::MappingValue(indent) if self.at_sequence_entry() => column >= indent as i64,
+            _ => column > context.indent(),
+        }
+    }
+
+    fn anchor(&mut self, anchor: Option<String>, value: Value) -> Value {
+        if let Some(name) = anchor {
+            self.anchors.insert(name, value.clone());
+        }
+        value
+    }
+
+    /// An anchor (`&name`) and a tag (`!!str`) before a node
+    fn properties(&mut self) -> Result<(Option<String>, Option<String>), YamlError> {
+        let (mut anchor, mut tag) = (None, None);
+        loop {
+            match self.peek() {
+                Some('&') if anchor.is_none() => {
+                    self.pos += 1;
+                    anchor = Some(self.name("anchor")?);
+                }
+                Some('!') if tag.is_none() => tag = Some(self.name("tag")?),
+                _ => return Ok((anchor, tag)),
+            }
+            self.skip_inline();
+        }
+    }
+
+    fn name(&mut self, what: &str) -> Result<String, YamlError> {
+        let start = self.pos;
+        while !self.blank_at(0) && !matches!(self.peek(), Some(',' | '[' | ']' | '{' | '}')) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return self.error(
+                "scanner",
+                &format!(
+                    "did not find expected alphabetic or numeric character while scanning an {}",
+                    what
+                ),
+            );
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn alias(&mut self) -> Result<Value, YamlError> {
+        self.pos += 1;
+        let start = self.pos;
+        let name = self.name("alias")?;
+        match self.anchors.get(&name) {
+            Some(value) => Ok(value.clone()),
+            None => {
+                self.pos = start;
+                self.error("parser", &format!("alias {} is not registered", name))
+            }
+        }
+    }
+
+    fn block_mapping(&mut self, column: usize, first_key: Value) -> Result<Value, YamlError> {
+        let mut entries = PhpArray::new();
+        let mut key = first_key;
+        loop {
+            self.pos += 1;
+            let value = self.block_node(Context::MappingValue(column))?;
+            self.skip_inline();
+            if self.peek() == Some(':') {
+                return self.error("scanner", "mapping values are not allowed in this context");
+            }
+            match (&key, value) {
+                (Value::String(name), value) if name == "<<" => merge(&mut entries, value),
+                (key, value) => {
+                    entries.insert(ArrayKey::from_value(key), value);
+                }
+            }
+            let new_line = self.skip_to_content();
+            if self.at_document_end() || (new_line && self.column() < column) {
+                return Ok(Value::Array(entries));
+            }
+            if !new_line || self.column() > column {
+                return self.error("parser", "did not find expected key");
+            }
+            if self.at_sequence_entry() {
+                return self.error("parser", "did not find expected key");
+            }
+            let (anchor, _) = self.properties()?;
+            let scalar = match self.peek() {
+                Some('*') => Scalar {
+                    text: self.alias()?.to_string_val(),
+                    plain: false,
+                },
+                _ => self.scalar(false)?,
+            };
+            key = self.resolve(scalar, None);
+            key = self.anchor(anchor, key);
+            self.skip_inline();
+            if self.peek() != Some(':') || !self.blank_at(1) {
+                return self.error("scanner", "could not find expected ':'");
+            }
+        }
+    }
+
+    fn block_sequence(&mut self, column: usize) -> Result<Value, YamlError> {
+        let mut items = Vec::new();
+        loop {
+            self.pos += 1;
+            let value = self.block_node(Context::SequenceEntry(column))?;
+            items.push((ArrayKey::Integer(items.len() as i64), value));
+            let new_line = self.skip_to_content();
+            if self.at_document_end() || (new_line && self.column() < column) {
+                return Ok(Value::Array(items.into()));
+            }
+            if !new_line || self.column() > column {
+                return self.error("parser", "did not find expected '-' indicator");
+            }
+            if !self.at_sequence_entry() {
+                // A key of the mapping this sequence is the value of
+                return Ok(Value::Array(items.into()));
+            }
+        }
+    }
+
+    fn flow_skip(&mut self) {
+        loop {
+            self.skip_inline();
+            match self.peek() {
+                Some('\n') => self.pos += 1,
+                Some('#') => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn flow_node(&mut self) -> Result<Value, YamlError> {
+        self.flow_skip();
+        let (anchor, tag) = self.properties()?;
+        self.flow_skip();
+        let value = match self.peek() {
+            Some('[') => self.flow_collection(']')?,
+            Some('{') => self.flow_collection('}')?,
+            Some('*') => self.alias()?,
+            _ => {
+                let scalar = self.scalar(true)?;
+                self.resolve(scalar, tag.as_deref())
+            }
+        };
+        Ok(self.anchor(anchor, value))
+    }
+
+    /// A flow sequence (`[a, b]`) or mapping (`{a: 1}`), ended by `close`
+    fn flow_collection(&mut self, close: char) -> Result<Value, YamlError> {
+        self.pos += 1;
+        let mut entries = PhpArray::new();
+        loop {
+            self.flow_skip();
+            if self.peek() == Some(close) {
+                self.pos += 1;
+                return Ok(Value::Array(entries));
+            }
+            let entry = self.flow_node()?;
+            self.flow_skip();
+            let pair = if self.peek() == Some(':') {
+                self.pos += 1;
+                self.flow_skip();
+                let value = match self.peek() {
+                    Some(',') => Value::Null,
+                    Some(c) if c == close => Value::Null,
+                    _ => self.flow_node()?,
+                };
+                Some(value)
+            } else {
+                None
+            };
+            match (close, pair) {
+                ('}', value) => {
+                    let value = value.unwrap_or(Value::Null);
+                    match &entry {
+                        Value::String(name) if name == "<<" => merge(&mut entries, value),
+                        _ => {
+                            entries.insert(ArrayKey::from_value(&entry), value);
+                        }
+                    }
+                }
+                (_, Some(value)) => {
+                    let pair = vec![(ArrayKey::from_value(&entry), value)];
+                    let key = ArrayKey::Integer(entries.len() as i64);
+                    entries.insert(key, Value::Array(pair.into()));
+                }
+                (_, None) => {
+                    entries.insert(ArrayKey::Integer(entries.len() as i64), entry);
+                }
+            }
+            self.flow_skip();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(c) if c == close => {}
+                _ => {
+                    return self.error(
+                        "parser",
+                        &format!("did not find expected ',' or '{}'", close),
+                    )
+                }
+            }
+        }
+    }
+
+    fn resolve(&self, scalar: Scalar, tag: Option<&str>) -> Value {
+        match tag {
+            Some(tag) => tagged(tag, scalar.text),
+            None if scalar.plain => super::yaml::resolve(&scalar.text),
+            None => Value::String(scalar.text),
+        }
+    }
+}
+
+/// Add the keys of a `<<` mapping, or list of mappings, the mapping
+/// doesn't have yet
+fn merge(entries: &mut PhpArray, value: Value) {
+    let Value::Array(items) = value else {
+        return;
+    };
+    let is_list = items
+        .iter()
+        .all(|(_, item)| matches!(item, Value::Array(_)))
+        && items.is_list();
+    let sources = if is_list && !items.is_empty() {
+        items.into_iter().map(|(_, item)| item).collect()
+    } else {
+        vec![Value::Array(items)]
+    };
+    for source in sources {
+        if let Value::Array(source) = source {
+            for (key, value) in source {
+                if !entries.contains_key(&key) {
+                    entries.insert(key, value);
+                }
+            }
+        }
+    }
+}
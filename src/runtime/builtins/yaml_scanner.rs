@@ -0,0 +1,333 @@
+//! Scanning YAML scalars
+//!
+//! Plain scalars end at `: `, ` #` and line breaks (and at flow
+//! indicators inside flow collections); quoted scalars and block
+//! scalars fold their line breaks the way the YAML spec does.
+
+use crate::runtime::builtins::yaml::YamlError;
+use crate::runtime::builtins::yaml_parser::Parser;
+use crate::runtime::Value;
+
+/// A scalar as written, resolved once its tag is known
+pub(crate) struct Scalar {
+    pub(crate) text: String,
+    pub(crate) plain: bool,
+}
+
+impl Parser {
+    /// A quoted or plain scalar on one line; in a flow collection plain
+    /// scalars also end at `,`, `[`, `]`, `{` and `}`
+    pub(crate) fn scalar(&mut self, flow: bool) -> Result<Scalar, YamlError> {
+        match self.peek() {
+            Some('"') => self
+                .double_quoted()
+                .map(|text| Scalar { text, plain: false }),
+            Some('\'') => self
+                .single_quoted()
+                .map(|text| Scalar { text, plain: false }),
+            Some('@' | '`') => self.error("scanner", "found character that cannot start any token"),
+            _ => Ok(Scalar {
+                text: self.plain_line(flow),
+                plain: true,
+            }),
+        }
+    }
+
+    fn plain_line(&mut self, flow: bool) -> String {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let ends = match c {
+                '\n' => true,
+                ':' => {
+                    self.blank_at(1) || (flow && matches!(self.peek_at(1), Some(',' | ']' | '}')))
+                }
+                '#' => self.pos > start && matches!(self.chars[self.pos - 1], ' ' | '\t'),
+                ',' | '[' | ']' | '{' | '}' => flow,
+                _ => false,
+            };
+            if ends {
+                break;
+            }
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.trim_end().to_string()
+    }
+
+    /// Fold the lines of a plain scalar indented past `indent` onto `text`
+    pub(crate) fn plain_continuation(&mut self, mut text: String, indent: i64) -> String {
+        loop {
+            let end = self.pos;
+            self.skip_inline();
+            if self.peek() != Some('\n') {
+                self.pos = end;
+                return text;
+            }
+            let mut breaks = 0;
+            while self.peek() == Some('\n') {
+                self.pos += 1;
+                breaks += 1;
+                self.skip_inline();
+            }
+            let ends = self.column() as i64 <= indent
+                || self.at_document_end()
+                || self.peek() == Some('#')
+                || (indent < 0 && self.at_sequence_entry());
+            if ends {
+                self.pos = end;
+                return text;
+            }
+            let line = self.plain_line(false);
+            if breaks == 1 {
+                text.push(' ');
+            } else {
+                text.push_str(&"\n".repeat(breaks - 1));
+            }
+            text.push_str(&line);
+        }
+    }
+
+    fn double_quoted(&mut self) -> Result<String, YamlError> {
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    return self.error(
+                        "scanner",
+                        "found unexpected end of stream while scanning a quoted scalar",
+                    )
+                }
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') if self.peek_at(1) == Some('\n') => {
+                    self.pos += 2;
+                    self.skip_inline();
+                }
+                Some('\\') => {
+                    let escape = self.escape()?;
+                    text.push(escape);
+                }
+                Some('\n') => self.fold_quoted_break(&mut text),
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, YamlError> {
+        let c = self.peek_at(1).unwrap_or('\0');
+        self.pos += 2;
+        let digits = match c {
+            'x' => 2,
+            'u' => 4,
+            'U' => 8,
+            _ => {
+                return Ok(match c {
+                    '0' => '\0',
+                    'a' => '\u{7}',
+                    'b' => '\u{8}',
+                    't' | '\t' => '\t',
+                    'n' => '\n',
+                    'v' => '\u{b}',
+                    'f' => '\u{c}',
+                    'r' => '\r',
+                    'e' => '\u{1b}',
+                    'N' => '\u{85}',
+                    '_' => '\u{a0}',
+                    'L' => '\u{2028}',
+                    'P' => '\u{2029}',
+                    ' ' | '"' | '/' | '\\' => c,
+                    _ => {
+                        self.pos -= 1;
+                        return self.error(
+                            "scanner",
+                            "found unknown escape character while parsing a quoted scalar",
+                        );
+                    }
+                })
+            }
+        };
+        let hex: String = self.chars[self.pos..(self.pos + digits).min(self.chars.len())]
+            .iter()
+            .collect();
+        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+            Some(c) if hex.len() == digits => {
+                self.pos += digits;
+                Ok(c)
+            }
+            _ => self.error(
+                "scanner",
+                "did not find expected hexdecimal number while parsing a quoted scalar",
+            ),
+        }
+    }
+
+    fn single_quoted(&mut self) -> Result<String, YamlError> {
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    return self.error(
+                        "scanner",
+                        "found unexpected end of stream while scanning a quoted scalar",
+                    )
+                }
+                Some('\'') if self.peek_at(1) == Some('\'') => {
+                    text.push('\'');
+                    self.pos += 2;
+                }
+                Some('\'') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\n') => self.fold_quoted_break(&mut text),
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// A line break inside a quoted scalar folds to a space, or to the
+    /// line breaks of the empty lines after it
+    fn fold_quoted_break(&mut self, text: &mut String) {
+        text.truncate(text.trim_end_matches([' ', '\t']).len());
+        let mut breaks = 0;
+        while self.peek() == Some('\n') {
+            self.pos += 1;
+            breaks += 1;
+            self.skip_inline();
+        }
+        if breaks == 1 {
+            text.push(' ');
+        } else {
+            text.push_str(&"\n".repeat(breaks - 1));
+        }
+    }
+
+    /// A literal (`|`) or folded (`>`) block scalar, indented past `indent`
+    pub(crate) fn block_scalar(&mut self, folded: bool, indent: i64) -> Result<Value, YamlError> {
+        self.pos += 1;
+        let (mut chomp, mut explicit) = (' ', None);
+        while let Some(c) = self.peek() {
+            match c {
+                '+' | '-' => chomp = c,
+                '1'..='9' => explicit = c.to_digit(10),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        self.skip_inline();
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.pos += 1;
+            }
+        }
+        match self.peek() {
+            None => {}
+            Some('\n') => self.pos += 1,
+            Some(_) => {
+                return self.error(
+                    "scanner",
+                    "did not find expected comment or line break while scanning a block scalar",
+                )
+            }
+        }
+        let content_indent = match explicit {
+            Some(n) => (indent.max(0) + n as i64) as usize,
+            None => self.detect_indent(indent),
+        };
+
+        // Each content line, None for an empty one
+        let mut lines: Vec<Option<String>> = Vec::new();
+        loop {
+            let line_start = self.pos;
+            if self.peek().is_none() || self.at_document_end() {
+                break;
+            }
+            let mut spaces = 0;
+            while self.peek() == Some(' ') {
+                self.pos += 1;
+                spaces += 1;
+            }
+            if matches!(self.peek(), None | Some('\n')) {
+                lines.push(None);
+            } else if spaces >= content_indent && content_indent as i64 > indent {
+                self.pos = line_start + content_indent;
+                let start = self.pos;
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.pos += 1;
+                }
+                lines.push(Some(self.chars[start..self.pos].iter().collect()));
+            } else {
+                self.pos = line_start;
+                break;
+            }
+            if self.peek() == Some('\n') {
+                self.pos += 1;
+            }
+        }
+        // Leave the last line break for the caller to see a new line
+        if self.pos > 0 && self.chars[self.pos - 1] == '\n' {
+            self.pos -= 1;
+        }
+        Ok(Value::String(block_text(&lines, folded, chomp)))
+    }
+
+    /// The indentation of the first non-empty line of a block scalar
+    fn detect_indent(&self, indent: i64) -> usize {
+        let mut pos = self.pos;
+        let mut spaces = 0;
+        while let Some(c) = self.chars.get(pos) {
+            match c {
+                ' ' => spaces += 1,
+                '\n' => spaces = 0,
+                _ => break,
+            }
+            pos += 1;
+        }
+        spaces.max((indent + 1).max(0) as usize)
+    }
+}
+
+/// The text of a block scalar's lines, folded and chomped
+fn block_text(lines: &[Option<String>], folded: bool, chomp: char) -> String {
+    let mut text = String::new();
+    let mut breaks = 0;
+    let mut started = false;
+    let mut previous_indented = false;
+    for line in lines {
+        let Some(line) = line else {
+            breaks += 1;
+            continue;
+        };
+        let indented = line.starts_with([' ', '\t']);
+        if !started {
+            text.push_str(&"\n".repeat(breaks));
+        } else if !folded || indented || previous_indented {
+            text.push_str(&"\n".repeat(breaks + 1));
+        } else if breaks == 0 {
+            text.push(' ');
+        } else {
+            text.push_str(&"\n".repeat(breaks));
+        }
+        text.push_str(line);
+        breaks = 0;
+        started = true;
+        previous_indented = indented;
+    }
+    match chomp {
+        '-' => text,
+        '+' if started => text + &"\n".repeat(breaks + 1),
+        '+' => "\n".repeat(breaks),
+        _ if started => text + "\n",
+        _ => text,
+    }
+}
@@ -0,0 +1,82 @@
+//! Wall-clock time for built-ins
+//!
+//! `std::time::SystemTime::now()` and `Instant::now()` panic on
+//! wasm32-unknown-unknown, so the time and random-seed built-ins, time
+//! limits, timers and the profiler read the clock here instead, and
+//! sleep with `sleep`, since `std::thread::sleep()` panics there too. On
+//! WebAssembly the host supplies the time through the `vhp_now_ms`
+//! import (the JS glue passes `Date.now()`).
+//!
+//! The wall clock can be frozen at a fixed time for reproducible runs
+//! (see `runtime::deterministic`); the monotonic clock keeps running.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Time reported instead of the real clock while frozen
+static FROZEN: Mutex<Option<Duration>> = Mutex::new(None);
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn vhp_now_ms() -> f64;
+}
+
+/// Report `at` as the current time from now on (None for the real clock)
+pub fn freeze(at: Option<Duration>) {
+    *FROZEN.lock().unwrap() = at;
+}
+
+/// Time elapsed since the Unix epoch
+pub fn since_epoch() -> Duration {
+    FROZEN.lock().unwrap().unwrap_or_else(real_since_epoch)
+}
+
+/// Time elapsed on a clock that never goes back, since some fixed point,
+/// for measuring durations and deadlines
+pub fn monotonic() -> Duration {
+    real_monotonic()
+}
+
+/// Block for `duration`
+pub fn sleep(duration: Duration) {
+    real_sleep(duration)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn real_sleep(duration: Duration) {
+    std::thread::sleep(duration)
+}
+
+/// WebAssembly has no threads to park, so wait on the host's clock
+#[cfg(target_arch = "wasm32")]
+fn real_sleep(duration: Duration) {
+    let until = real_monotonic().saturating_add(duration);
+    while real_monotonic() < until {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn real_monotonic() -> Duration {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn real_monotonic() -> Duration {
+    real_since_epoch()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn real_since_epoch() -> Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn real_since_epoch() -> Duration {
+    // SAFETY: the import takes no arguments and only returns a number
+    let millis = unsafe { vhp_now_ms() };
+    Duration::from_secs_f64(millis.max(0.0) / 1000.0)
+}
@@ -0,0 +1,31 @@
+//! Deterministic mode, for byte-stable output across runs
+//!
+//! Golden-output tests and fuzz reproductions need a script to print the
+//! same thing every time. Deterministic mode freezes the clock at
+//! `FIXED_TIME`, so `time()` and `date()` never move, and seeds the shared
+//! random generator with `SEED`, so `rand()`, `shuffle()` and the like
+//! draw the same numbers. Object ids printed by `var_dump()` don't depend
+//! on the run, and scripts have no access to temporary paths, so nothing
+//! else needs pinning.
+
+use super::{clock, random};
+use std::time::Duration;
+
+/// 2000-01-01 00:00:00 UTC, the time deterministic runs see
+pub const FIXED_TIME: Duration = Duration::from_secs(946_684_800);
+
+/// Seed of the random generator in deterministic runs
+pub const SEED: u64 = 0;
+
+/// Freeze the clock and reseed the random generator; call before each
+/// run that must be reproducible
+pub fn enable() {
+    clock::freeze(Some(FIXED_TIME));
+    random::seed(Some(SEED));
+}
+
+/// Go back to the real clock and a clock-seeded random generator
+pub fn disable() {
+    clock::freeze(None);
+    random::seed(None);
+}
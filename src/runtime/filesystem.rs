@@ -0,0 +1,272 @@
+//! Filesystem access for scripts
+//!
+//! Everything a script can do to files (the file I/O built-ins, `require`
+//! and PSR-4 autoloading) goes through the `Filesystem` trait, so an
+//! embedder can decide what "disk" means. `NativeFilesystem` is the real
+//! one; `MemoryFilesystem` keeps files in a map and is the default on
+//! WebAssembly, where there is no disk. `RestrictedFilesystem` wraps
+//! either to confine scripts to some directories (`open_basedir`).
+
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// What scripts can learn about a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub is_file: bool,
+    pub is_dir: bool,
+    /// Size in bytes
+    pub len: u64,
+    /// Modification time as a Unix timestamp, when known
+    pub modified: Option<i64>,
+}
+
+/// File operations available to scripts
+pub trait Filesystem {
+    fn read_to_string(&self, path: &str) -> Result<String>;
+    fn write(&mut self, path: &str, contents: &str) -> Result<()>;
+    fn metadata(&self, path: &str) -> Result<FileMetadata>;
+    fn remove_file(&mut self, path: &str) -> Result<()>;
+
+    /// The absolute path with symlinks resolved, if the path exists and
+    /// the filesystem has links to resolve
+    fn canonicalize(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// The filesystem a new VM uses: the host's disk, or an empty in-memory
+/// filesystem on WebAssembly
+pub fn default_filesystem() -> Box<dyn Filesystem> {
+    if cfg!(target_arch = "wasm32") {
+        Box::new(MemoryFilesystem::default())
+    } else {
+        Box::new(NativeFilesystem)
+    }
+}
+
+/// The host's filesystem, through `std::fs`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NativeFilesystem;
+
+impl Filesystem for NativeFilesystem {
+    fn read_to_string(&self, path: &str) -> Result<String> {
+        // A file that isn't UTF-8 is read as a byte string, one character
+        // per byte, for iconv() to convert
+        let bytes = std::fs::read(path)?;
+        Ok(String::from_utf8(bytes)
+            .unwrap_or_else(|e| e.into_bytes().into_iter().map(char::from).collect()))
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64);
+        Ok(FileMetadata {
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified,
+        })
+    }
+
+    fn remove_file(&mut self, path: &str) -> Result<()> {
+        std::fs::remove_file(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+        std::fs::canonicalize(path).ok()
+    }
+}
+
+/// Files kept in memory, keyed by path
+///
+/// Directories are not stored: a path is a directory when some file lives
+/// under it.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFilesystem {
+    files: BTreeMap<String, String>,
+}
+
+impl MemoryFilesystem {
+    /// Add or replace a file
+    pub fn insert(&mut self, path: impl Into<String>, contents: impl Into<String>) {
+        self.files.insert(path.into(), contents.into());
+    }
+
+    /// The contents of a file, if it exists
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        self.files.keys().any(|file| file.starts_with(&prefix))
+    }
+}
+
+impl Filesystem for MemoryFilesystem {
+    fn read_to_string(&self, path: &str) -> Result<String> {
+        self.get(path)
+            .map(str::to_string)
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> Result<()> {
+        if self.is_dir(path) {
+            return Err(Error::other(format!("{}: Is a directory", path)));
+        }
+        self.insert(path, contents);
+        Ok(())
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        if let Some(contents) = self.get(path) {
+            return Ok(FileMetadata {
+                is_file: true,
+                is_dir: false,
+                len: contents.len() as u64,
+                modified: None,
+            });
+        }
+        if self.is_dir(path) {
+            return Ok(FileMetadata {
+                is_file: false,
+                is_dir: true,
+                len: 0,
+                modified: None,
+            });
+        }
+        Err(not_found(path))
+    }
+
+    fn remove_file(&mut self, path: &str) -> Result<()> {
+        self.files
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+}
+
+/// A filesystem that only reaches files under some base directories
+///
+/// Paths are made absolute and resolved (`..` and, where the inner
+/// filesystem has them, symlinks) before the check, so neither can be
+/// used to step outside.
+pub struct RestrictedFilesystem {
+    inner: Box<dyn Filesystem>,
+    base_dirs: Vec<PathBuf>,
+}
+
+impl RestrictedFilesystem {
+    pub fn new(inner: Box<dyn Filesystem>, base_dirs: &[PathBuf]) -> Self {
+        let mut restricted = Self {
+            inner,
+            base_dirs: Vec::new(),
+        };
+        restricted.base_dirs = base_dirs
+            .iter()
+            .map(|dir| restricted.resolve(dir))
+            .collect();
+        restricted
+    }
+
+    /// `path` made absolute, normalized, and with symlinks resolved in
+    /// its longest existing ancestor
+    fn resolve(&self, path: &Path) -> PathBuf {
+        let absolute = if path.is_absolute() || cfg!(target_arch = "wasm32") {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir().unwrap_or_default().join(path)
+        };
+        let mut normalized = PathBuf::new();
+        for component in absolute.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+
+        let mut existing = normalized.as_path();
+        let mut rest = Vec::new();
+        loop {
+            if let Some(real) = self.inner.canonicalize(existing) {
+                return rest.iter().rev().fold(real, |path, name| path.join(name));
+            }
+            match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    rest.push(name);
+                    existing = parent;
+                }
+                _ => return normalized,
+            }
+        }
+    }
+
+    fn check(&self, path: &str) -> Result<()> {
+        let resolved = self.resolve(Path::new(path));
+        if self.base_dirs.iter().any(|dir| resolved.starts_with(dir)) {
+            return Ok(());
+        }
+        let allowed: Vec<String> = self
+            .base_dirs
+            .iter()
+            .map(|dir| dir.display().to_string())
+            .collect();
+        Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "open_basedir restriction in effect. File({}) is not within the allowed path(s): ({})",
+                path,
+                allowed.join(PATH_LIST_SEPARATOR)
+            ),
+        ))
+    }
+}
+
+impl Filesystem for RestrictedFilesystem {
+    fn read_to_string(&self, path: &str) -> Result<String> {
+        self.check(path)?;
+        self.inner.read_to_string(path)
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> Result<()> {
+        self.check(path)?;
+        self.inner.write(path, contents)
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        self.check(path)?;
+        self.inner.metadata(path)
+    }
+
+    fn remove_file(&mut self, path: &str) -> Result<()> {
+        self.check(path)?;
+        self.inner.remove_file(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+        self.inner.canonicalize(path)
+    }
+}
+
+/// Separator of directory lists such as `open_basedir`
+pub const PATH_LIST_SEPARATOR: &str = if cfg!(windows) { ";" } else { ":" };
+
+fn not_found(path: &str) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("{}: No such file or directory", path),
+    )
+}
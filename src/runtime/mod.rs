@@ -4,10 +4,16 @@
 //! used by the bytecode VM.
 
 pub mod builtins;
+pub mod clock;
+pub mod deterministic;
+pub mod filesystem;
+pub mod random;
 mod value;
 
+pub use value::object_instance::reset_object_handles;
 pub use value::{
-    ArrayKey, Closure, ClosureBody, GeneratorInstance, ObjectInstance, Value, YIELD_COLLECTOR,
+    ArrayKey, Closure, ClosureBody, GeneratorInstance, ObjectInstance, ObjectRef, PhpArray,
+    Reference, Value,
 };
 
 /// User-defined function definition
@@ -0,0 +1,22 @@
+//! Random numbers for built-ins
+//!
+//! `rand()`, `shuffle()` and the other random built-ins draw from one
+//! shared generator. It is seeded from the clock on first use, or
+//! explicitly by `mt_srand()`, `srand()` and deterministic mode, after
+//! which the same numbers come out on every run.
+
+use super::clock;
+use std::sync::Mutex;
+
+static RNG: Mutex<Option<fastrand::Rng>> = Mutex::new(None);
+
+/// Restart the generator from `seed` (None to seed from the clock)
+pub fn seed(seed: Option<u64>) {
+    *RNG.lock().unwrap() = seed.map(fastrand::Rng::with_seed);
+}
+
+/// Run `f` with the shared generator
+pub fn with_rng<T>(f: impl FnOnce(&mut fastrand::Rng) -> T) -> T {
+    let mut rng = RNG.lock().unwrap();
+    f(rng.get_or_insert_with(|| fastrand::Rng::with_seed(clock::since_epoch().as_nanos() as u64)))
+}
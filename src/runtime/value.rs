@@ -1,31 +1,18 @@
 //! Runtime value representation for VHP
 
-use std::cell::RefCell;
 use std::collections::HashMap;
 
 pub mod array_key;
+pub mod increment;
 pub mod object_instance;
+pub mod php_array;
+pub mod reference;
 pub mod value_helpers;
 
 pub use array_key::ArrayKey;
-pub use object_instance::{ExceptionValue, ObjectInstance};
-
-thread_local! {
-    pub static YIELD_COLLECTOR: RefCell<GeneratorYieldCollector> = const { RefCell::new(GeneratorYieldCollector { yielded_values: Vec::new(), return_value: None }) };
-}
-
-#[derive(Debug, Clone)]
-pub struct GeneratorYieldCollector {
-    pub yielded_values: Vec<(Option<Value>, Option<Value>)>,
-    pub return_value: Option<Value>,
-}
-
-impl GeneratorYieldCollector {
-    pub fn clear(&mut self) {
-        self.yielded_values.clear();
-        self.return_value = None;
-    }
-}
+pub use object_instance::{ExceptionValue, ObjectInstance, ObjectRef};
+pub use php_array::PhpArray;
+pub use reference::Reference;
 
 /// Closure (arrow function or anonymous function)
 #[derive(Debug, Clone)]
@@ -83,17 +70,12 @@ pub struct CallFrame {
     pub current_statement: usize,
 }
 
+/// A generator: the VM keeps its suspended frame under `id`, so copies
+/// of the value all resume the same body
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct GeneratorInstance {
     pub id: usize,
     pub function_name: String,
-    pub yielded_values: Vec<(Option<Value>, Option<Value>)>,
-    pub current_index: usize,
-    pub is_rewound: bool,
-    pub finished: bool,
-    pub return_value: Option<Value>,
-    pub sent_value: Option<Value>,
 }
 
 /// Runtime value representation
@@ -105,8 +87,8 @@ pub enum Value {
     Integer(i64),
     Float(f64),
     String(String),
-    Array(Vec<(ArrayKey, Value)>),
-    Object(ObjectInstance),
+    Array(PhpArray),
+    Object(ObjectRef),
     Fiber(Box<FiberInstance>),
     Closure(Box<Closure>),
     Generator(Box<GeneratorInstance>),
@@ -116,6 +98,8 @@ pub enum Value {
         backing_value: Option<Box<Value>>,
     },
     Exception(ExceptionValue),
+    /// A variable bound to others (see `reference`)
+    Reference(Reference),
 }
 
 impl PartialEq for Value {
@@ -145,7 +129,7 @@ impl Value {
             }
             Value::String(s) => s.clone(),
             Value::Array(_) => "Array".to_string(),
-            Value::Object(obj) => format!("Object({})", obj.class_name),
+            Value::Object(obj) => format!("Object({})", obj.class_name()),
             Value::Fiber(fiber) => format!("Object(Fiber#{:06})", fiber.id),
             Value::Closure(_) => "Object(Closure)".to_string(),
             Value::Generator(gen) => format!("Object(Generator#{:06})", gen.id),
@@ -155,6 +139,7 @@ impl Value {
                 ..
             } => format!("{}::{}", enum_name, case_name),
             Value::Exception(exc) => format!("Object({})", exc.class_name),
+            Value::Reference(r) => r.get().to_output_string(),
         }
     }
 
@@ -58,7 +58,7 @@ impl ArrayKey {
                 }
             }
             super::Value::Array(_) => ArrayKey::String("Array".to_string()),
-            super::Value::Object(obj) => ArrayKey::String(format!("Object({})", obj.class_name)),
+            super::Value::Object(obj) => ArrayKey::String(format!("Object({})", obj.class_name())),
             super::Value::Fiber(fiber) => {
                 ArrayKey::String(format!("Object(Fiber#{:06})", fiber.id))
             }
@@ -72,6 +72,7 @@ impl ArrayKey {
                 ..
             } => ArrayKey::String(format!("{}::{}", enum_name, case_name)),
             super::Value::Exception(exc) => ArrayKey::String(format!("Object({})", exc.class_name)),
+            super::Value::Reference(r) => ArrayKey::from_value(&r.get()),
         }
     }
 
@@ -0,0 +1,88 @@
+//! `++` and `--` on values
+//!
+//! Numbers step by one, an integer turning into a float past `PHP_INT_MAX`
+//! or `PHP_INT_MIN`. Numeric strings step as the number they hold. Other
+//! strings increment like Perl's: the last letter or digit moves on,
+//! carrying into the one before ("a9" becomes "b0", "Zz" becomes "AAa"),
+//! and decrementing leaves them as they are. `null` increments to 1 but
+//! decrements to `null`, and booleans don't change.
+
+use super::Value;
+
+impl Value {
+    /// The value `++` gives
+    pub fn increment(&self) -> Result<Value, String> {
+        match self {
+            Value::Null => Ok(Value::Integer(1)),
+            Value::Bool(_) => Ok(self.clone()),
+            Value::Integer(n) => Ok(n
+                .checked_add(1)
+                .map_or(Value::Float(*n as f64 + 1.0), Value::Integer)),
+            Value::Float(f) => Ok(Value::Float(f + 1.0)),
+            Value::String(s) if s.is_empty() => Ok(Value::String("1".to_string())),
+            Value::String(s) => match parse_numeric(s) {
+                Some(number) => number.increment(),
+                None => Ok(Value::String(increment_string(s))),
+            },
+            _ => Err(format!("Cannot increment {}", self.type_name())),
+        }
+    }
+
+    /// The value `--` gives
+    pub fn decrement(&self) -> Result<Value, String> {
+        match self {
+            Value::Null | Value::Bool(_) => Ok(self.clone()),
+            Value::Integer(n) => Ok(n
+                .checked_sub(1)
+                .map_or(Value::Float(*n as f64 - 1.0), Value::Integer)),
+            Value::Float(f) => Ok(Value::Float(f - 1.0)),
+            Value::String(s) if s.is_empty() => Ok(Value::Integer(-1)),
+            Value::String(s) => match parse_numeric(s) {
+                Some(number) => number.decrement(),
+                None => Ok(self.clone()),
+            },
+            _ => Err(format!("Cannot decrement {}", self.type_name())),
+        }
+    }
+}
+
+/// The number a numeric string holds (surrounding whitespace allowed)
+fn parse_numeric(s: &str) -> Option<Value> {
+    let s = s.trim();
+    if let Ok(n) = s.parse::<i64>() {
+        return Some(Value::Integer(n));
+    }
+    if s.contains(['i', 'I', 'n', 'N']) {
+        return None;
+    }
+    s.parse::<f64>().ok().map(Value::Float)
+}
+
+/// Increment a non-numeric string: from the end, 'z', 'Z' and '9' wrap
+/// around and carry into the character before, up to the first one that
+/// isn't a letter or digit. A carry out of the first character adds one
+/// of the same kind in front.
+fn increment_string(s: &str) -> String {
+    let mut bytes = s.as_bytes().to_vec();
+    let mut prefix = None;
+    for i in (0..bytes.len()).rev() {
+        let (wrapped, first) = match bytes[i] {
+            b'z' => (b'a', b'a'),
+            b'Z' => (b'A', b'A'),
+            b'9' => (b'0', b'1'),
+            b'a'..=b'y' | b'A'..=b'Y' | b'0'..=b'8' => {
+                bytes[i] += 1;
+                break;
+            }
+            _ => break,
+        };
+        bytes[i] = wrapped;
+        if i == 0 {
+            prefix = Some(first);
+        }
+    }
+    if let Some(first) = prefix {
+        bytes.insert(0, first);
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -1,4 +1,60 @@
+//! Objects
+//!
+//! An `ObjectInstance` is the state of an object: its class and its
+//! properties. A value holds an `ObjectRef` to it, and copying the value
+//! copies the reference, not the object, so that every variable, property
+//! and array element holding it sees the same object, as in PHP.
+
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use thread_local::ThreadLocal;
+
+// Per thread, like the rest of a script's state, so that the workers of
+// a FastCGI server number their objects apart (see `server::fastcgi`)
+lazy_static::lazy_static! {
+    static ref HANDLES: ThreadLocal<Mutex<Handles>> = ThreadLocal::new();
+}
+
+/// The numbers given to live objects. As in PHP, they start at 1 and the
+/// number of a freed object goes to the next one created.
+#[derive(Default)]
+struct Handles {
+    next: usize,
+    free: Vec<usize>,
+    /// Bumped by a reset, so that objects numbered before it don't hand
+    /// their numbers back
+    generation: usize,
+}
+
+impl Handles {
+    fn get() -> MutexGuard<'static, Handles> {
+        let handles = HANDLES.get_or_default();
+        handles
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn take() -> (usize, usize) {
+        let mut handles = Self::get();
+        let handle = match handles.free.pop() {
+            Some(handle) => handle,
+            None => {
+                handles.next += 1;
+                handles.next
+            }
+        };
+        (handle, handles.generation)
+    }
+}
+
+/// Number the objects of the next script on this thread from 1 again
+pub fn reset_object_handles() {
+    let mut handles = Handles::get();
+    handles.next = 0;
+    handles.free.clear();
+    handles.generation += 1;
+}
 
 #[derive(Debug, Clone)]
 pub struct ExceptionValue {
@@ -60,3 +116,105 @@ impl ObjectInstance {
             .any(|iface| iface.eq_ignore_ascii_case(class_name))
     }
 }
+
+/// A reference to an object, shared by every copy of it
+#[derive(Clone)]
+pub struct ObjectRef {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// The class, which never changes, so it can be read without locking
+    class_name: String,
+    handle: usize,
+    generation: usize,
+    instance: Mutex<ObjectInstance>,
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        let mut handles = Handles::get();
+        if handles.generation == self.generation {
+            handles.free.push(self.handle);
+        }
+    }
+}
+
+impl ObjectRef {
+    pub fn new(instance: ObjectInstance) -> Self {
+        let (handle, generation) = Handles::take();
+        Self {
+            shared: Arc::new(Shared {
+                class_name: instance.class_name.clone(),
+                handle,
+                generation,
+                instance: Mutex::new(instance),
+            }),
+        }
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.shared.class_name
+    }
+
+    /// The object's state, to read or change. Like a `RefCell`, it panics
+    /// if the object is already locked further up the stack, so the guard
+    /// must not be held across a call back into the VM.
+    pub fn lock(&self) -> MutexGuard<'_, ObjectInstance> {
+        match self.shared.instance.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                panic!("object of class {} already in use", self.class_name())
+            }
+        }
+    }
+
+    /// A copy of the property's value
+    pub fn get(&self, name: &str) -> Option<super::Value> {
+        self.lock().properties.get(name).cloned()
+    }
+
+    pub fn set(&self, name: impl Into<String>, value: super::Value) {
+        self.lock().properties.insert(name.into(), value);
+    }
+
+    /// A copy of the properties, to walk without keeping the object locked
+    pub fn properties(&self) -> HashMap<String, super::Value> {
+        self.lock().properties.clone()
+    }
+
+    pub fn is_instance_of(&self, class_name: &str) -> bool {
+        self.lock().is_instance_of(class_name)
+    }
+
+    /// Whether both refer to the same object (`===`)
+    pub fn ptr_eq(&self, other: &ObjectRef) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    /// A number telling the object apart from every other live object,
+    /// the one `var_dump` prints after `#` and `spl_object_id` returns
+    pub fn id(&self) -> usize {
+        self.shared.handle
+    }
+
+    /// A new object with a copy of this one's state (`clone`)
+    pub fn duplicate(&self) -> Self {
+        Self::new(self.lock().clone())
+    }
+}
+
+impl From<ObjectInstance> for ObjectRef {
+    fn from(instance: ObjectInstance) -> Self {
+        Self::new(instance)
+    }
+}
+
+impl fmt::Debug for ObjectRef {
+    /// The class and identity only: objects can refer to each other in a
+    /// cycle
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.class_name(), self.id())
+    }
+}
@@ -0,0 +1,328 @@
+//! PHP arrays
+//!
+//! An ordered map from integer and string keys to values. The entries are
+//! kept in insertion order, with a hash index from key to position, except
+//! while the array is a list (keys 0, 1, 2... in order), where the key is
+//! the position and no index is needed: PHP's packed and hash arrays.
+//!
+//! The entries are shared between copies of an array, and copied by the
+//! first copy written to (copy on write), so passing and assigning an
+//! array is cheap however big it is.
+//!
+//! The entries can be read as a slice of `(key, value)` pairs. Writes go
+//! through methods that keep the index in step; `edit` hands out the
+//! entries to reorder or rewrite, and reindexes them afterwards.
+
+use super::{ArrayKey, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A PHP array
+#[derive(Clone, Default)]
+pub struct PhpArray {
+    table: Arc<Table>,
+}
+
+#[derive(Clone, Default)]
+struct Table {
+    entries: Vec<(ArrayKey, Value)>,
+    /// Position of each key; `None` while the array is a list
+    index: Option<HashMap<ArrayKey, usize>>,
+    /// The key `push` gives: one more than the largest integer key the
+    /// array had, which removing it doesn't lower
+    next_index: Option<i64>,
+}
+
+impl Table {
+    fn position(&self, key: &ArrayKey) -> Option<usize> {
+        match (&self.index, key) {
+            (Some(index), _) => index.get(key).copied(),
+            (None, ArrayKey::Integer(n)) => {
+                usize::try_from(*n).ok().filter(|&n| n < self.entries.len())
+            }
+            (None, ArrayKey::String(_)) => None,
+        }
+    }
+
+    /// Index the keys, once the array stops being a list
+    fn index_mut(&mut self) -> &mut HashMap<ArrayKey, usize> {
+        let entries = &self.entries;
+        self.index.get_or_insert_with(|| {
+            let keys = entries.iter().map(|(key, _)| key.clone());
+            keys.zip(0..).collect()
+        })
+    }
+
+    fn note_key(&mut self, key: &ArrayKey) {
+        if let ArrayKey::Integer(n) = key {
+            let next = n.saturating_add(1);
+            self.next_index = Some(self.next_index.map_or(next, |i| i.max(next)));
+        }
+    }
+
+    /// Rebuild the index and the next key after the entries changed,
+    /// keeping the first position and last value of a repeated key
+    fn reindex(&mut self) {
+        let is_list = self
+            .entries
+            .iter()
+            .zip(0..)
+            .all(|((key, _), i)| *key == ArrayKey::Integer(i));
+        self.next_index = None;
+        if is_list {
+            self.index = None;
+            self.next_index = Some(self.entries.len() as i64);
+            return;
+        }
+        let entries = std::mem::take(&mut self.entries);
+        self.index = Some(HashMap::with_capacity(entries.len()));
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+
+    fn insert(&mut self, key: ArrayKey, value: Value) -> Option<Value> {
+        if let Some(position) = self.position(&key) {
+            return Some(std::mem::replace(&mut self.entries[position].1, value));
+        }
+        let position = self.entries.len();
+        if self.index.is_some() || key != ArrayKey::Integer(position as i64) {
+            self.index_mut().insert(key.clone(), position);
+        }
+        self.note_key(&key);
+        self.entries.push((key, value));
+        None
+    }
+}
+
+impl PhpArray {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let table = Table {
+            entries: Vec::with_capacity(capacity),
+            ..Table::default()
+        };
+        Self {
+            table: Arc::new(table),
+        }
+    }
+
+    /// The entries as they are, for a foreach loop to walk, even where a
+    /// key repeats (the children of an XML element, by name); a repeated
+    /// key is looked up at its first position
+    pub fn walk(entries: Vec<(ArrayKey, Value)>) -> Self {
+        let mut table = Table::default();
+        let mut index = HashMap::with_capacity(entries.len());
+        for (position, (key, _)) in entries.iter().enumerate() {
+            index.entry(key.clone()).or_insert(position);
+            table.note_key(key);
+        }
+        table.entries = entries;
+        table.index = Some(index);
+        Self {
+            table: Arc::new(table),
+        }
+    }
+
+    /// The entries, which are this copy's alone from now on
+    fn table_mut(&mut self) -> &mut Table {
+        Arc::make_mut(&mut self.table)
+    }
+
+    pub fn as_slice(&self) -> &[(ArrayKey, Value)] {
+        &self.table.entries
+    }
+
+    /// The value under `key`
+    pub fn get(&self, key: &ArrayKey) -> Option<&Value> {
+        let position = self.table.position(key)?;
+        Some(&self.table.entries[position].1)
+    }
+
+    pub fn get_mut(&mut self, key: &ArrayKey) -> Option<&mut Value> {
+        let position = self.table.position(key)?;
+        Some(&mut self.table_mut().entries[position].1)
+    }
+
+    pub fn contains_key(&self, key: &ArrayKey) -> bool {
+        self.table.position(key).is_some()
+    }
+
+    /// The position of `key` among the entries
+    pub fn position(&self, key: &ArrayKey) -> Option<usize> {
+        self.table.position(key)
+    }
+
+    /// Set the value under `key`, where the key already is or else at the
+    /// end, giving the value it replaced
+    pub fn insert(&mut self, key: ArrayKey, value: Value) -> Option<Value> {
+        self.table_mut().insert(key, value)
+    }
+
+    /// The key `push` gives: one more than the largest integer key the
+    /// array had, or 0
+    pub fn next_index(&self) -> i64 {
+        self.table.next_index.unwrap_or(0)
+    }
+
+    /// Append the value under the next integer key (`$array[] = $value`),
+    /// failing if the largest key is taken
+    pub fn push(&mut self, value: Value) -> Result<(), String> {
+        let next = self.next_index();
+        if self.table.next_index == Some(i64::MAX) && self.contains_key(&ArrayKey::Integer(next)) {
+            return Err(
+                "Cannot add element to the array as the next element is already occupied"
+                    .to_string(),
+            );
+        }
+        self.insert(ArrayKey::Integer(next), value);
+        Ok(())
+    }
+
+    /// Remove the entry under `key`, giving its value
+    pub fn remove(&mut self, key: &ArrayKey) -> Option<Value> {
+        let position = self.table.position(key)?;
+        let table = self.table_mut();
+        // A list stays one without its last entry
+        if table.index.is_some() || position + 1 < table.entries.len() {
+            let index = table.index_mut();
+            index.remove(key);
+            for later in index.values_mut().filter(|p| **p > position) {
+                *later -= 1;
+            }
+        }
+        let (_, value) = table.entries.remove(position);
+        Some(value)
+    }
+
+    /// The values, in order, to change in place
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.table_mut().entries.iter_mut().map(|(_, value)| value)
+    }
+
+    /// The entries in order, with their values to change in place
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&ArrayKey, &mut Value)> {
+        self.table_mut()
+            .entries
+            .iter_mut()
+            .map(|(key, value)| (&*key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &ArrayKey> {
+        self.table.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.table.entries.iter().map(|(_, value)| value)
+    }
+
+    /// Change the entries as a vector (to sort, splice or rekey them); the
+    /// array is indexed anew afterwards, a repeated key keeping its first
+    /// position and last value
+    pub fn edit<T>(&mut self, edit: impl FnOnce(&mut Vec<(ArrayKey, Value)>) -> T) -> T {
+        let table = self.table_mut();
+        let result = edit(&mut table.entries);
+        table.reindex();
+        result
+    }
+
+    /// Keep only the entries `keep` is true for
+    pub fn retain(&mut self, mut keep: impl FnMut(&ArrayKey, &Value) -> bool) {
+        self.edit(|entries| entries.retain(|(key, value)| keep(key, value)));
+    }
+
+    /// Remove and give the last entry
+    pub fn pop(&mut self) -> Option<(ArrayKey, Value)> {
+        let (key, _) = self.table.entries.last()?;
+        let key = key.clone();
+        let value = self.remove(&key)?;
+        Some((key, value))
+    }
+
+    pub fn into_vec(self) -> Vec<(ArrayKey, Value)> {
+        match Arc::try_unwrap(self.table) {
+            Ok(table) => table.entries,
+            Err(shared) => shared.entries.clone(),
+        }
+    }
+
+    /// Whether the keys are 0, 1, 2... in order (`array_is_list()`)
+    pub fn is_list(&self) -> bool {
+        self.table.index.is_none()
+            || (0..)
+                .zip(self.keys())
+                .all(|(i, key)| *key == ArrayKey::Integer(i))
+    }
+}
+
+impl Deref for PhpArray {
+    type Target = [(ArrayKey, Value)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.table.entries
+    }
+}
+
+impl fmt::Debug for PhpArray {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl From<Vec<(ArrayKey, Value)>> for PhpArray {
+    fn from(entries: Vec<(ArrayKey, Value)>) -> Self {
+        let mut table = Table {
+            entries,
+            ..Table::default()
+        };
+        table.reindex();
+        Self {
+            table: Arc::new(table),
+        }
+    }
+}
+
+impl FromIterator<(ArrayKey, Value)> for PhpArray {
+    fn from_iter<I: IntoIterator<Item = (ArrayKey, Value)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut array = PhpArray::with_capacity(iter.size_hint().0);
+        let table = array.table_mut();
+        for (key, value) in iter {
+            table.insert(key, value);
+        }
+        array
+    }
+}
+
+impl FromIterator<Value> for PhpArray {
+    /// A list of the values
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        (0..)
+            .zip(iter)
+            .map(|(i, value)| (ArrayKey::Integer(i), value))
+            .collect()
+    }
+}
+
+impl IntoIterator for PhpArray {
+    type Item = (ArrayKey, Value);
+    type IntoIter = std::vec::IntoIter<(ArrayKey, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PhpArray {
+    type Item = &'a (ArrayKey, Value);
+    type IntoIter = std::slice::Iter<'a, (ArrayKey, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.table.entries.iter()
+    }
+}
@@ -0,0 +1,133 @@
+//! References
+//!
+//! `$b = &$a` makes two variables one: both slots hold a `Reference` to
+//! the same value, and writing either writes it. A by-reference parameter
+//! and `global $x` bind a variable the same way. A reference lives only
+//! in a variable (and on the stack while a variable is being passed to a
+//! function); reading the variable gives the value it refers to.
+//!
+//! A variable can also be bound to an element of an array held by another
+//! reference, or to a property of an object: reading it reads the element
+//! or property, and writing it writes them, creating the element (and the
+//! array, in place of null) if there is none yet.
+
+use super::{ArrayKey, ObjectRef, PhpArray, Value};
+use std::fmt;
+use std::sync::{Arc, Mutex, TryLockError};
+
+/// A value shared by the variables bound to it
+#[derive(Clone)]
+pub struct Reference {
+    target: Target,
+}
+
+#[derive(Clone)]
+enum Target {
+    Value(Arc<Mutex<Value>>),
+    Element(Box<Reference>, ArrayKey),
+    Property(ObjectRef, String),
+}
+
+impl Reference {
+    pub fn new(value: Value) -> Self {
+        Self {
+            target: Target::Value(Arc::new(Mutex::new(value))),
+        }
+    }
+
+    /// The element at `key` of the array `array` refers to
+    pub fn element(array: Reference, key: ArrayKey) -> Self {
+        Self {
+            target: Target::Element(Box::new(array), key),
+        }
+    }
+
+    /// The property `name` of `object`
+    pub fn property(object: ObjectRef, name: String) -> Self {
+        Self {
+            target: Target::Property(object, name),
+        }
+    }
+
+    /// Change the value in place. Like `ObjectRef::lock`, it panics if the
+    /// value is already being changed, so `change` must not call back into
+    /// the VM.
+    pub fn update<T>(&self, change: impl FnOnce(&mut Value) -> T) -> T {
+        match &self.target {
+            Target::Value(value) => {
+                let mut guard = match value.try_lock() {
+                    Ok(guard) => guard,
+                    Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                    Err(TryLockError::WouldBlock) => panic!("reference already in use"),
+                };
+                change(&mut guard)
+            }
+            _ => {
+                let mut value = self.get();
+                let result = change(&mut value);
+                self.set(value);
+                result
+            }
+        }
+    }
+
+    /// A copy of the value
+    pub fn get(&self) -> Value {
+        match &self.target {
+            Target::Value(_) => self.update(|value| value.clone()),
+            Target::Element(array, key) => match array.get() {
+                Value::Array(array) => array.get(key).cloned().unwrap_or(Value::Null),
+                _ => Value::Null,
+            },
+            Target::Property(object, name) => object.get(name).unwrap_or(Value::Null),
+        }
+    }
+
+    pub fn set(&self, value: Value) {
+        match &self.target {
+            Target::Value(_) => self.update(|current| *current = value),
+            Target::Element(array, key) => array.update(|array| {
+                if matches!(array, Value::Null) {
+                    *array = Value::Array(PhpArray::new());
+                }
+                if let Value::Array(array) = array {
+                    array.insert(key.clone(), value);
+                }
+            }),
+            Target::Property(object, name) => object.set(name.clone(), value),
+        }
+    }
+
+    /// Whether another variable (or argument) holds the reference too. An
+    /// element or a property is always held by its array or object.
+    pub fn is_shared(&self) -> bool {
+        match &self.target {
+            Target::Value(value) => Arc::strong_count(value) > 1,
+            _ => true,
+        }
+    }
+
+    /// Whether both are the same reference
+    pub fn ptr_eq(&self, other: &Reference) -> bool {
+        match (&self.target, &other.target) {
+            (Target::Value(a), Target::Value(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    /// The value itself, or the value it refers to
+    pub fn unref(self) -> Value {
+        match self {
+            Value::Reference(r) => r.get(),
+            value => value,
+        }
+    }
+}
+
+impl fmt::Debug for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "&{:?}", self.get())
+    }
+}
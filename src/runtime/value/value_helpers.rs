@@ -13,6 +13,7 @@ impl super::Value {
             super::Value::Generator(_) => true,
             super::Value::EnumCase { .. } => true,
             super::Value::Exception(_) => true,
+            super::Value::Reference(r) => r.get().to_bool(),
         }
     }
 
@@ -42,6 +43,7 @@ impl super::Value {
             super::Value::Generator(_) => 0,
             super::Value::EnumCase { .. } => 1,
             super::Value::Exception(_) => 1,
+            super::Value::Reference(r) => r.get().to_int(),
         }
     }
 
@@ -71,6 +73,7 @@ impl super::Value {
             super::Value::Generator(_) => 0.0,
             super::Value::EnumCase { .. } => 1.0,
             super::Value::Exception(_) => 1.0,
+            super::Value::Reference(r) => r.get().to_float(),
         }
     }
 
@@ -94,7 +97,7 @@ impl super::Value {
             }
             super::Value::String(s) => s.clone(),
             super::Value::Array(_) => "Array".to_string(),
-            super::Value::Object(obj) => format!("Object({})", obj.class_name),
+            super::Value::Object(obj) => format!("Object({})", obj.class_name()),
             super::Value::Fiber(_) => "Object(Fiber)".to_string(),
             super::Value::Closure(_) => "Object(Closure)".to_string(),
             super::Value::Generator(_) => "Object(Generator)".to_string(),
@@ -104,10 +107,17 @@ impl super::Value {
                 ..
             } => format!("{}::{}", enum_name, case_name),
             super::Value::Exception(exc) => format!("Object({})", exc.class_name),
+            super::Value::Reference(r) => r.get().to_string_val(),
         }
     }
 
     pub fn type_equals(&self, other: &super::Value) -> bool {
+        if let super::Value::Reference(r) = self {
+            return r.get().type_equals(other);
+        }
+        if let super::Value::Reference(r) = other {
+            return self.type_equals(&r.get());
+        }
         match (self, other) {
             (super::Value::Null, super::Value::Null) => true,
             (super::Value::Bool(a), super::Value::Bool(b)) => a == b,
@@ -125,9 +135,7 @@ impl super::Value {
                 }
                 true
             }
-            (super::Value::Object(a), super::Value::Object(b)) => {
-                a.class_name == b.class_name && a.properties == b.properties
-            }
+            (super::Value::Object(a), super::Value::Object(b)) => a.ptr_eq(b),
             (super::Value::Fiber(a), super::Value::Fiber(b)) => a.id == b.id,
             (super::Value::Closure(_), super::Value::Closure(_)) => false,
             (super::Value::Generator(a), super::Value::Generator(b)) => a.id == b.id,
@@ -151,6 +159,12 @@ impl super::Value {
     }
 
     pub fn loose_equals(&self, other: &super::Value) -> bool {
+        if let super::Value::Reference(r) = self {
+            return r.get().loose_equals(other);
+        }
+        if let super::Value::Reference(r) = other {
+            return self.loose_equals(&r.get());
+        }
         match (self, other) {
             (super::Value::Null, super::Value::Null) => true,
             (super::Value::Null, super::Value::Bool(b))
@@ -191,7 +205,8 @@ impl super::Value {
                 true
             }
             (super::Value::Object(a), super::Value::Object(b)) => {
-                a.class_name == b.class_name && a.properties == b.properties
+                a.ptr_eq(b)
+                    || (a.class_name() == b.class_name() && a.properties() == b.properties())
             }
             (super::Value::Fiber(a), super::Value::Fiber(b)) => a.id == b.id,
             (super::Value::Closure(_), super::Value::Closure(_)) => false,
@@ -229,6 +244,7 @@ impl super::Value {
             super::Value::Generator(_) => "object",
             super::Value::EnumCase { .. } => "object",
             super::Value::Exception(_) => "object",
+            super::Value::Reference(r) => r.get().get_type(),
         }
     }
 
@@ -241,7 +257,7 @@ impl super::Value {
             super::Value::Float(_) => "float",
             super::Value::String(_) => "string",
             super::Value::Array(_) => "array",
-            super::Value::Object(obj) => Box::leak(obj.class_name.clone().into_boxed_str()),
+            super::Value::Object(obj) => Box::leak(obj.class_name().to_string().into_boxed_str()),
             super::Value::Fiber(_) => "Fiber",
             super::Value::Closure(_) => "Closure",
             super::Value::Generator(_) => "Generator",
@@ -249,6 +265,7 @@ impl super::Value {
                 Box::leak(enum_name.clone().into_boxed_str())
             }
             super::Value::Exception(exc) => Box::leak(exc.class_name.clone().into_boxed_str()),
+            super::Value::Reference(r) => r.get().type_name(),
         }
     }
 
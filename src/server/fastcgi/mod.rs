@@ -0,0 +1,433 @@
+//! FastCGI server
+//!
+//! `vhp fcgi --listen 127.0.0.1:9000` answers the requests a web server
+//! such as nginx passes over FastCGI, the way php-fpm does. The server
+//! sends the CGI meta-variables as params and the body as stdin (see
+//! `record`); the script's response goes back on stdout, and what went
+//! wrong, for the server's error log, on stderr.
+//!
+//! A fixed pool of worker threads takes connections off the listener, one
+//! connection and one request at a time each: connections are not
+//! multiplexed. Every request runs in a VM of its own, so its globals and
+//! output start empty, and the state the runtime keeps per thread outside
+//! the VM (autoloaders, `require_once` files, INI directives, locales,
+//! the include path) is reset before it runs.
+//!
+//! SIGINT, SIGTERM and SIGQUIT shut the server down gracefully: it stops
+//! accepting connections, lets the requests already running finish, and
+//! returns from `Server::serve`.
+
+pub mod record;
+
+use crate::cgi::Request;
+use crate::runtime::builtins::{ini, locale, spl};
+use record::{Begin, Record};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Where `vhp fcgi` listens unless told otherwise, as php-fpm does
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:9000";
+
+/// How often an idle listener or connection checks for a shutdown
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Stack of a worker thread, as deep as the main thread's on Linux, for
+/// scripts that recurse
+const WORKER_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Set once the server is to shut down
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+/// What answering a request gave
+#[derive(Debug, Clone, Default)]
+pub struct Reply {
+    /// The CGI response: status, headers, a blank line and the body
+    pub stdout: Vec<u8>,
+    /// Errors for the web server to log
+    pub stderr: Vec<u8>,
+    /// The exit status of the script
+    pub app_status: u32,
+}
+
+/// How `vhp fcgi` serves
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The address to listen on
+    pub listen: String,
+    /// How many requests are answered at once
+    pub workers: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            listen: DEFAULT_LISTEN.to_string(),
+            workers: thread::available_parallelism().map_or(4, |n| n.get()),
+        }
+    }
+}
+
+impl Options {
+    /// Parse the arguments following `vhp fcgi`
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| iter.next().cloned())
+                    .ok_or_else(|| format!("{} requires a value", flag))
+            };
+            match flag {
+                "--listen" => options.listen = value()?,
+                "--workers" => {
+                    let workers = value()?;
+                    options.workers = workers
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("Invalid --workers value: {}", workers))?;
+                }
+                _ => return Err(format!("Unknown fcgi option: {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// A FastCGI listener and the size of its worker pool
+pub struct Server {
+    listener: TcpListener,
+    workers: usize,
+}
+
+impl Server {
+    /// Listen on `address` with `workers` worker threads
+    pub fn bind(address: &str, workers: usize) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            workers: workers.max(1),
+        })
+    }
+
+    /// The address the server listens on
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answer requests with `handler` until the server is shut down
+    ///
+    /// ```
+    /// use std::net::TcpStream;
+    /// use vhp::server::fastcgi::{self, record, Reply, Server};
+    ///
+    /// let server = Server::bind("127.0.0.1:0", 2).unwrap();
+    /// let address = server.local_addr().unwrap();
+    /// let serving = std::thread::spawn(move || {
+    ///     server.serve(|request| {
+    ///         let body = String::from_utf8_lossy(&request.body).into_owned();
+    ///         let response = format!("Content-Type: text/plain\r\n\r\n{} {}", request.method(), body);
+    ///         Reply { stdout: response.into_bytes(), ..Reply::default() }
+    ///     })
+    /// });
+    ///
+    /// // What nginx sends for a POST: begin as a responder, params, stdin
+    /// let mut connection = TcpStream::connect(address).unwrap();
+    /// let begin = [0, record::RESPONDER as u8, 0, 0, 0, 0, 0, 0];
+    /// record::write_record(&mut connection, record::BEGIN_REQUEST, 1, &begin).unwrap();
+    /// let params = record::encode_pairs(&[("REQUEST_METHOD".to_string(), "POST".to_string())]);
+    /// record::write_stream(&mut connection, record::PARAMS, 1, &params).unwrap();
+    /// record::write_stream(&mut connection, record::STDIN, 1, b"name=vhp").unwrap();
+    ///
+    /// let mut kinds = Vec::new();
+    /// let mut stdout = Vec::new();
+    /// while let Some(reply) = record::read_record(&mut connection).unwrap() {
+    ///     assert_eq!(reply.request_id, 1);
+    ///     kinds.push(reply.kind);
+    ///     match reply.kind {
+    ///         record::STDOUT => stdout.extend(reply.content),
+    ///         record::END_REQUEST => {
+    ///             assert_eq!(reply.content, [0, 0, 0, 0, record::REQUEST_COMPLETE, 0, 0, 0]);
+    ///         }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// assert_eq!(kinds, [record::STDOUT, record::STDOUT, record::END_REQUEST]);
+    /// assert_eq!(stdout, b"Content-Type: text/plain\r\n\r\nPOST name=vhp");
+    ///
+    /// fastcgi::shutdown();
+    /// serving.join().unwrap().unwrap();
+    /// ```
+    pub fn serve<H>(self, handler: H) -> io::Result<()>
+    where
+        H: Fn(Request) -> Reply + Send + Sync + 'static,
+    {
+        handle_signals();
+        self.listener.set_nonblocking(true)?;
+        let (sender, receiver) = mpsc::channel::<TcpStream>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let handler = Arc::new(handler);
+        let pool = self.workers;
+        let mut workers = Vec::new();
+        for number in 0..pool {
+            let receiver = Arc::clone(&receiver);
+            let handler = Arc::clone(&handler);
+            let worker = thread::Builder::new()
+                .name(format!("fcgi-worker-{}", number))
+                .stack_size(WORKER_STACK_SIZE)
+                .spawn(move || work(&receiver, &*handler, pool))?;
+            workers.push(worker);
+        }
+
+        while !SHUTDOWN.load(Ordering::SeqCst) {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if stream.set_nonblocking(false).is_ok() {
+                        let _ = sender.send(stream);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                // Out of file descriptors and the like: wait for them
+                Err(e) => {
+                    eprintln!("vhp fcgi: accept failed: {}", e);
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+        drop(sender);
+        for worker in workers {
+            let _ = worker.join();
+        }
+        Ok(())
+    }
+}
+
+/// Shut down the servers of this process gracefully, as a signal does
+pub fn shutdown() {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+/// Shut down on SIGINT, SIGTERM and SIGQUIT
+#[cfg(unix)]
+fn handle_signals() {
+    use std::os::raw::c_int;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+    }
+
+    extern "C" fn on_signal(_: c_int) {
+        SHUTDOWN.store(true, Ordering::SeqCst);
+    }
+
+    const SIGINT: c_int = 2;
+    const SIGQUIT: c_int = 3;
+    const SIGTERM: c_int = 15;
+    for signum in [SIGINT, SIGQUIT, SIGTERM] {
+        unsafe { signal(signum, on_signal) };
+    }
+}
+
+#[cfg(not(unix))]
+fn handle_signals() {}
+
+/// A worker: serve connections until the listener stops handing them out
+fn work(
+    receiver: &Mutex<mpsc::Receiver<TcpStream>>,
+    handler: &dyn Fn(Request) -> Reply,
+    workers: usize,
+) {
+    loop {
+        let Ok(stream) = receiver.lock().unwrap().recv() else {
+            return;
+        };
+        if let Err(e) = serve_connection(stream, handler, workers) {
+            if !matches!(
+                e.kind(),
+                io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::BrokenPipe
+            ) {
+                eprintln!("vhp fcgi: {}", e);
+            }
+        }
+    }
+}
+
+/// Answer the requests on a connection until the web server closes it,
+/// doesn't ask to keep it, or the server shuts down
+fn serve_connection(
+    stream: TcpStream,
+    handler: &dyn Fn(Request) -> Reply,
+    workers: usize,
+) -> io::Result<()> {
+    let mut input = BufReader::new(stream.try_clone()?);
+    let mut output = BufWriter::new(stream);
+    while wait_for_record(&input)? {
+        let Some(record) = record::read_record(&mut input)? else {
+            return Ok(());
+        };
+        let keep_conn = match record.kind {
+            record::BEGIN_REQUEST => {
+                let Some(begin) = Begin::parse(&record.content) else {
+                    continue;
+                };
+                let id = record.request_id;
+                if begin.role != record::RESPONDER {
+                    record::write_end_request(&mut output, id, 0, record::UNKNOWN_ROLE)?;
+                } else if let Some(request) = read_request(&mut input, &mut output, id, workers)? {
+                    let reply = answer(handler, request);
+                    record::write_stream(&mut output, record::STDOUT, id, &reply.stdout)?;
+                    if !reply.stderr.is_empty() {
+                        record::write_stream(&mut output, record::STDERR, id, &reply.stderr)?;
+                    }
+                    record::write_end_request(
+                        &mut output,
+                        id,
+                        reply.app_status,
+                        record::REQUEST_COMPLETE,
+                    )?;
+                } else {
+                    // Aborted before it ran
+                    record::write_end_request(&mut output, id, 0, record::REQUEST_COMPLETE)?;
+                }
+                begin.keep_conn
+            }
+            _ if record.request_id == 0 => {
+                answer_management(&mut output, &record, workers)?;
+                true
+            }
+            // Left over from a request already answered
+            _ => true,
+        };
+        output.flush()?;
+        if !keep_conn {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Wait until a record comes in; false if the connection closed or the
+/// server is shutting down first
+fn wait_for_record(input: &BufReader<TcpStream>) -> io::Result<bool> {
+    if !input.buffer().is_empty() {
+        return Ok(true);
+    }
+    let stream = input.get_ref();
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    let ready = loop {
+        match stream.peek(&mut [0]) {
+            Ok(read) => break read > 0,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                ) =>
+            {
+                if SHUTDOWN.load(Ordering::SeqCst) {
+                    break false;
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    stream.set_read_timeout(None)?;
+    Ok(ready)
+}
+
+/// Read the params and stdin of the request `id`, answering the records
+/// of other requests and management records that come in between. None
+/// if the web server aborted the request.
+fn read_request(
+    input: &mut BufReader<TcpStream>,
+    output: &mut impl Write,
+    id: u16,
+    workers: usize,
+) -> io::Result<Option<Request>> {
+    let mut params = Vec::new();
+    let mut body = Vec::new();
+    let (mut params_done, mut stdin_done) = (false, false);
+    while !(params_done && stdin_done) {
+        let Some(record) = record::read_record(input)? else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        match record.kind {
+            _ if record.request_id == 0 => answer_management(output, &record, workers)?,
+            record::BEGIN_REQUEST if record.request_id != id => {
+                let other = record.request_id;
+                record::write_end_request(output, other, 0, record::CANT_MPX_CONN)?;
+            }
+            _ if record.request_id != id => {}
+            record::ABORT_REQUEST => return Ok(None),
+            record::PARAMS => {
+                params_done = record.content.is_empty();
+                params.extend_from_slice(&record.content);
+            }
+            record::STDIN => {
+                stdin_done = record.content.is_empty();
+                body.extend_from_slice(&record.content);
+            }
+            _ => {}
+        }
+    }
+    Ok(Some(Request::new(record::decode_pairs(&params), body)))
+}
+
+/// Run the handler on a fresh slate; a panic gives a 500
+fn answer(handler: &dyn Fn(Request) -> Reply, request: Request) -> Reply {
+    reset_request_state();
+    panic::catch_unwind(AssertUnwindSafe(|| handler(request))).unwrap_or_else(|_| Reply {
+        stdout: b"Status: 500 Internal Server Error\r\n\r\n".to_vec(),
+        stderr: b"vhp fcgi: the script panicked\n".to_vec(),
+        app_status: 1,
+    })
+}
+
+/// Forget what earlier requests on this thread left outside their VM
+fn reset_request_state() {
+    spl::clear_autoloaders();
+    spl::clear_psr4_registry();
+    spl::reset_include_path();
+    crate::vm::clear_required_files();
+    crate::runtime::reset_object_handles();
+    ini::reset_ini_settings();
+    locale::reset();
+}
+
+/// Answer a management record: `GET_VALUES` with what the server
+/// supports, anything else as a type it doesn't know
+fn answer_management(output: &mut impl Write, record: &Record, workers: usize) -> io::Result<()> {
+    if record.kind != record::GET_VALUES {
+        let mut content = [0u8; 8];
+        content[0] = record.kind;
+        return record::write_record(output, record::UNKNOWN_TYPE, 0, &content);
+    }
+    let values: Vec<(String, String)> = record::decode_pairs(&record.content)
+        .into_iter()
+        .filter_map(|(name, _)| {
+            let value = match name.as_str() {
+                "FCGI_MAX_CONNS" | "FCGI_MAX_REQS" => workers.to_string(),
+                "FCGI_MPXS_CONNS" => "0".to_string(),
+                _ => return None,
+            };
+            Some((name, value))
+        })
+        .collect();
+    let content = record::encode_pairs(&values);
+    record::write_record(output, record::GET_VALUES_RESULT, 0, &content)
+}
@@ -0,0 +1,225 @@
+//! FastCGI records
+//!
+//! Everything on a FastCGI connection travels in records: an 8-byte
+//! header (version, type, request id, content length, padding length)
+//! followed by up to 65535 bytes of content and the padding. A stream
+//! such as a request's params or stdin is a run of records of its type
+//! ended by an empty one. Params are name-value pairs, each length in one
+//! byte below 128 and in four bytes, high bit set, from 128 on.
+
+use std::io::{self, Read, Write};
+
+pub const VERSION: u8 = 1;
+
+pub const BEGIN_REQUEST: u8 = 1;
+pub const ABORT_REQUEST: u8 = 2;
+pub const END_REQUEST: u8 = 3;
+pub const PARAMS: u8 = 4;
+pub const STDIN: u8 = 5;
+pub const STDOUT: u8 = 6;
+pub const STDERR: u8 = 7;
+pub const DATA: u8 = 8;
+pub const GET_VALUES: u8 = 9;
+pub const GET_VALUES_RESULT: u8 = 10;
+pub const UNKNOWN_TYPE: u8 = 11;
+
+/// The role of a web server's application that answers requests
+pub const RESPONDER: u16 = 1;
+/// `BEGIN_REQUEST` flag: leave the connection open after the request
+pub const KEEP_CONN: u8 = 1;
+
+/// `END_REQUEST` protocol statuses
+pub const REQUEST_COMPLETE: u8 = 0;
+pub const CANT_MPX_CONN: u8 = 1;
+pub const UNKNOWN_ROLE: u8 = 3;
+
+/// The most content one record holds
+const MAX_CONTENT: usize = u16::MAX as usize;
+
+/// A record as read off a connection, without its padding
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub kind: u8,
+    /// 0 for management records, which belong to no request
+    pub request_id: u16,
+    pub content: Vec<u8>,
+}
+
+/// What a `BEGIN_REQUEST` record asks for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Begin {
+    pub role: u16,
+    pub keep_conn: bool,
+}
+
+impl Begin {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        let role = u16::from_be_bytes([*content.first()?, *content.get(1)?]);
+        let flags = *content.get(2)?;
+        Some(Self {
+            role,
+            keep_conn: flags & KEEP_CONN != 0,
+        })
+    }
+}
+
+/// Read the next record, or None if the connection closed before one
+///
+/// ```
+/// use vhp::server::fastcgi::record::{read_record, write_record, write_stream, Record, STDOUT};
+///
+/// let mut wire = Vec::new();
+/// write_record(&mut wire, STDOUT, 7, b"hello").unwrap();
+/// assert_eq!(&wire[..8], &[1, STDOUT, 0, 7, 0, 5, 3, 0]);
+/// assert_eq!(wire.len(), 16);
+///
+/// // A stream longer than a record spans several, then ends empty
+/// let body = vec![b'x'; 70_000];
+/// write_stream(&mut wire, STDOUT, 7, &body).unwrap();
+///
+/// let mut input = wire.as_slice();
+/// let first = read_record(&mut input).unwrap().unwrap();
+/// assert_eq!(first, Record { kind: STDOUT, request_id: 7, content: b"hello".to_vec() });
+/// let lengths: Vec<usize> = std::iter::from_fn(|| read_record(&mut input).unwrap())
+///     .map(|record| record.content.len())
+///     .collect();
+/// assert_eq!(lengths, [65535, 4465, 0]);
+/// assert!(input.is_empty());
+/// ```
+pub fn read_record(input: &mut impl Read) -> io::Result<Option<Record>> {
+    let mut header = [0u8; 8];
+    match input.read_exact(&mut header[..1]) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    input.read_exact(&mut header[1..])?;
+    if header[0] != VERSION {
+        let message = format!("Unsupported FastCGI version {}", header[0]);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    let request_id = u16::from_be_bytes([header[2], header[3]]);
+    let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+    let mut content = vec![0; length + header[6] as usize];
+    input.read_exact(&mut content)?;
+    content.truncate(length);
+    Ok(Some(Record {
+        kind: header[1],
+        request_id,
+        content,
+    }))
+}
+
+/// Write one record, padded to a multiple of 8 bytes
+pub fn write_record(
+    out: &mut impl Write,
+    kind: u8,
+    request_id: u16,
+    content: &[u8],
+) -> io::Result<()> {
+    let length = content.len() as u16;
+    let padding = (8 - content.len() % 8) % 8;
+    let [id_high, id_low] = request_id.to_be_bytes();
+    let [length_high, length_low] = length.to_be_bytes();
+    out.write_all(&[
+        VERSION,
+        kind,
+        id_high,
+        id_low,
+        length_high,
+        length_low,
+        padding as u8,
+        0,
+    ])?;
+    out.write_all(content)?;
+    out.write_all(&[0; 8][..padding])
+}
+
+/// Write `data` on a stream in as many records as it takes, followed by
+/// the empty record that ends the stream
+pub fn write_stream(
+    out: &mut impl Write,
+    kind: u8,
+    request_id: u16,
+    data: &[u8],
+) -> io::Result<()> {
+    for chunk in data.chunks(MAX_CONTENT) {
+        write_record(out, kind, request_id, chunk)?;
+    }
+    write_record(out, kind, request_id, &[])
+}
+
+/// Write the `END_REQUEST` record of a request
+pub fn write_end_request(
+    out: &mut impl Write,
+    request_id: u16,
+    app_status: u32,
+    protocol_status: u8,
+) -> io::Result<()> {
+    let mut content = [0u8; 8];
+    content[..4].copy_from_slice(&app_status.to_be_bytes());
+    content[4] = protocol_status;
+    write_record(out, END_REQUEST, request_id, &content)
+}
+
+/// Encode name-value pairs
+///
+/// ```
+/// use vhp::server::fastcgi::record::{decode_pairs, encode_pairs};
+///
+/// let long = "x".repeat(200);
+/// let pairs = vec![
+///     ("REQUEST_METHOD".to_string(), "GET".to_string()),
+///     ("QUERY_STRING".to_string(), long.clone()),
+/// ];
+/// let encoded = encode_pairs(&pairs);
+/// assert_eq!(&encoded[..2], &[14, 3]);
+/// assert_eq!(&encoded[19..24], &[12, 0x80, 0, 0, 200]);
+/// assert_eq!(decode_pairs(&encoded), pairs);
+/// ```
+pub fn encode_pairs(pairs: &[(String, String)]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for (name, value) in pairs {
+        for length in [name.len(), value.len()] {
+            match length {
+                0..=127 => encoded.push(length as u8),
+                _ => encoded.extend_from_slice(&(length as u32 | 0x8000_0000).to_be_bytes()),
+            }
+        }
+        encoded.extend_from_slice(name.as_bytes());
+        encoded.extend_from_slice(value.as_bytes());
+    }
+    encoded
+}
+
+/// Decode name-value pairs, up to where they stop making sense
+pub fn decode_pairs(mut input: &[u8]) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    while !input.is_empty() {
+        let Some((name_length, rest)) = decode_length(input) else {
+            break;
+        };
+        let Some((value_length, rest)) = decode_length(rest) else {
+            break;
+        };
+        if rest.len() < name_length + value_length {
+            break;
+        }
+        let name = String::from_utf8_lossy(&rest[..name_length]).into_owned();
+        let value = String::from_utf8_lossy(&rest[name_length..name_length + value_length]);
+        pairs.push((name, value.into_owned()));
+        input = &rest[name_length + value_length..];
+    }
+    pairs
+}
+
+/// A pair's length and what follows it
+fn decode_length(input: &[u8]) -> Option<(usize, &[u8])> {
+    let first = *input.first()?;
+    if first < 0x80 {
+        return Some((first as usize, &input[1..]));
+    }
+    let bytes: [u8; 4] = input.get(..4)?.try_into().ok()?;
+    let length = u32::from_be_bytes(bytes) & 0x7fff_ffff;
+    Some((length as usize, &input[4..]))
+}
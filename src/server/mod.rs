@@ -0,0 +1,6 @@
+//! Servers
+//!
+//! Long-running hosts that answer requests from a web server, as opposed
+//! to `cgi`, where each request starts a process of its own.
+
+pub mod fastcgi;
@@ -0,0 +1,115 @@
+//! `--bless`: rewrite the --EXPECT-- section of failing tests
+//!
+//! Only plain `--EXPECT--` sections are rewritten; `--EXPECTF--` patterns
+//! and `--EXPECT_ERROR--` expectations have to be updated by hand.
+//! Each test is confirmed interactively unless `--yes` was given.
+
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use super::test_case::is_section_header;
+use super::{TestRecord, TestResult, TestSummary};
+
+/// Answer to a bless prompt
+enum Answer {
+    Yes,
+    No,
+    All,
+    Quit,
+}
+
+/// Offer to update every failing test whose output mismatched
+pub fn bless_failures(summary: &TestSummary, confirm: bool) -> Result<(), String> {
+    let failing: Vec<(&TestRecord, &String)> = summary
+        .records
+        .iter()
+        .filter_map(|record| match &record.result {
+            TestResult::Fail { actual, .. } => Some((record, actual)),
+            _ => None,
+        })
+        .collect();
+    if failing.is_empty() {
+        return Ok(());
+    }
+    if confirm && !io::stdin().is_terminal() {
+        return Err(
+            "--bless needs an interactive terminal; pass --yes to bless without confirmation"
+                .to_string(),
+        );
+    }
+
+    let mut confirm = confirm;
+    let mut blessed = 0;
+    for (record, actual) in failing {
+        let content = fs::read_to_string(&record.path)
+            .map_err(|e| format!("Failed to read test file {:?}: {}", record.path, e))?;
+        let Some(updated) = replace_expect(&content, actual) else {
+            eprintln!("Skipping {}: no --EXPECT-- section to update", record.file);
+            continue;
+        };
+
+        if confirm {
+            match ask(&format!("Bless {} ({})?", record.file, record.name))? {
+                Answer::Yes => {}
+                Answer::No => continue,
+                Answer::All => confirm = false,
+                Answer::Quit => break,
+            }
+        }
+
+        fs::write(&record.path, updated)
+            .map_err(|e| format!("Failed to write test file {:?}: {}", record.path, e))?;
+        blessed += 1;
+    }
+
+    eprintln!("Blessed {} test(s)", blessed);
+    Ok(())
+}
+
+/// Replace the body of the --EXPECT-- section, keeping every other section
+fn replace_expect(content: &str, actual: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.iter().position(|line| *line == "--EXPECT--")?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| is_section_header(line))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+
+    let mut out = String::new();
+    for line in &lines[..=start] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    let expected = actual.trim().replace("\r\n", "\n");
+    if !expected.is_empty() {
+        out.push_str(&expected);
+        out.push('\n');
+    }
+    for line in &lines[end..] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    Some(out)
+}
+
+fn ask(question: &str) -> Result<Answer, String> {
+    loop {
+        eprint!("{} [y]es/[n]o/[a]ll/[q]uit: ", question);
+        io::stderr().flush().map_err(|e| e.to_string())?;
+        let mut reply = String::new();
+        let read = io::stdin()
+            .lock()
+            .read_line(&mut reply)
+            .map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Ok(Answer::Quit);
+        }
+        match reply.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Answer::Yes),
+            "n" | "no" | "" => return Ok(Answer::No),
+            "a" | "all" => return Ok(Answer::All),
+            "q" | "quit" => return Ok(Answer::Quit),
+            _ => continue,
+        }
+    }
+}
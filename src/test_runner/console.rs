@@ -0,0 +1,150 @@
+//! Human-readable console output for test runs
+
+use super::diff::unified_diff;
+use super::{Reporter, TestRecord, TestResult, TestSummary};
+
+/// Colored dots (or one line per test when verbose) and a failure summary
+pub struct ConsoleReporter {
+    pub verbose: bool,
+}
+
+impl Reporter for ConsoleReporter {
+    fn start(&mut self, count: usize, seed: Option<u64>) {
+        match seed {
+            Some(seed) => println!("Running {} tests (seed {})...\n", count, seed),
+            None => println!("Running {} tests...\n", count),
+        }
+    }
+
+    fn empty(&mut self, message: &str) {
+        println!("{}", message);
+    }
+
+    fn result(&mut self, _number: usize, record: &TestRecord) {
+        print_progress(record, self.verbose);
+    }
+
+    fn finish(&mut self, summary: &TestSummary) {
+        if !self.verbose {
+            println!();
+        }
+        println!();
+        print_summary(summary);
+        if let (Some(seed), false) = (summary.seed, summary.failures.is_empty()) {
+            println!("Reproduce this order with --seed {}", seed);
+        }
+    }
+}
+
+/// Print progress for one finished test (a dot, or a line in verbose mode)
+pub fn print_progress(record: &TestRecord, verbose: bool) {
+    let name = &record.name;
+    match &record.result {
+        TestResult::Pass => {
+            if verbose {
+                println!("  \x1b[32mPASS\x1b[0m {}", name);
+            } else {
+                print!("\x1b[32m.\x1b[0m");
+            }
+        }
+        TestResult::Fail { .. } => {
+            if verbose {
+                println!("  \x1b[31mFAIL\x1b[0m {}", name);
+            } else {
+                print!("\x1b[31mF\x1b[0m");
+            }
+        }
+        TestResult::Error(err) => {
+            if verbose {
+                println!("  \x1b[31mERROR\x1b[0m {}: {}", name, err);
+            } else {
+                print!("\x1b[31mE\x1b[0m");
+            }
+        }
+        TestResult::ExpectedFail(reason) => {
+            if verbose {
+                println!("  \x1b[33mXFAIL\x1b[0m {}: {}", name, reason);
+            } else {
+                print!("\x1b[33mx\x1b[0m");
+            }
+        }
+        TestResult::UnexpectedPass(reason) => {
+            if verbose {
+                println!("  \x1b[31mXPASS\x1b[0m {}: {}", name, reason);
+            } else {
+                print!("\x1b[31mX\x1b[0m");
+            }
+        }
+        TestResult::Timeout { limit, .. } => {
+            if verbose {
+                println!(
+                    "  \x1b[31mTIMEOUT\x1b[0m {}: exceeded {}s",
+                    name,
+                    limit.as_secs_f64()
+                );
+            } else {
+                print!("\x1b[31mT\x1b[0m");
+            }
+        }
+        TestResult::Skipped(reason) => {
+            if verbose {
+                println!("  \x1b[33mSKIP\x1b[0m {}: {}", name, reason);
+            } else {
+                print!("\x1b[33mS\x1b[0m");
+            }
+        }
+    }
+}
+
+/// Print failure details and the final summary line
+pub fn print_summary(summary: &TestSummary) {
+    // Print failures in detail
+    if !summary.failures.is_empty() {
+        println!("\n\x1b[31mFailures:\x1b[0m\n");
+        // Failures are recorded in the same order as the failing test records
+        let failing = summary.records.iter().filter(|r| r.result.is_failure());
+        for (i, ((name, expected, actual), record)) in
+            summary.failures.iter().zip(failing).enumerate()
+        {
+            println!("{}. {}", i + 1, name);
+            let diff = match &record.result {
+                TestResult::Fail { .. } => unified_diff(&normalize(expected), &normalize(actual)),
+                _ => None,
+            };
+            match diff {
+                Some(diff) => print!("   {}", diff.trim_end().replace('\n', "\n   ")),
+                None => {
+                    println!("   Expected:\n   {}", expected.replace('\n', "\n   "));
+                    print!("   Actual:\n   {}", actual.replace('\n', "\n   "));
+                }
+            }
+            println!("\n");
+        }
+    }
+
+    // Print summary line
+    let status_color = if summary.failed > 0 || summary.errors > 0 || summary.xpassed > 0 {
+        "\x1b[31m" // Red
+    } else if summary.skipped > 0 || summary.xfailed > 0 {
+        "\x1b[33m" // Yellow
+    } else {
+        "\x1b[32m" // Green
+    };
+
+    println!(
+        "{}Tests: {} total, {} passed, {} failed, {} errors, {} skipped, {} xfailed, {} xpassed\x1b[0m",
+        status_color,
+        summary.total,
+        summary.passed,
+        summary.failed,
+        summary.errors,
+        summary.skipped,
+        summary.xfailed,
+        summary.xpassed
+    );
+}
+
+/// Apply the same normalization as output comparison before diffing
+fn normalize(text: &str) -> String {
+    text.trim().replace("\r\n", "\n")
+}
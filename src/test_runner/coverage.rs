@@ -0,0 +1,138 @@
+//! Line coverage reports
+//!
+//! `--coverage <file>` writes an lcov tracefile, or a self-contained HTML
+//! page when the file name ends in `.html`. Line numbers of test code
+//! refer to the .vhpt file, so reports line up with the test sources.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::vm::coverage::Coverage;
+
+/// Write the coverage report, choosing the format from the file extension
+pub fn write_report(path: &Path, coverage: &Coverage) -> Result<(), String> {
+    let is_html = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"));
+    let report = if is_html {
+        render_html(coverage)
+    } else {
+        render_lcov(coverage)
+    };
+    fs::write(path, report)
+        .map_err(|e| format!("Failed to write coverage report {:?}: {}", path, e))
+}
+
+/// Number of (hit, executable) lines across all files
+pub fn totals(coverage: &Coverage) -> (usize, usize) {
+    coverage.files.values().fold((0, 0), |(hit, total), lines| {
+        let (h, t) = line_counts(lines.values());
+        (hit + h, total + t)
+    })
+}
+
+/// Render an lcov tracefile
+pub fn render_lcov(coverage: &Coverage) -> String {
+    let mut out = String::new();
+    for (file, lines) in &coverage.files {
+        let _ = writeln!(out, "TN:vhp");
+        let _ = writeln!(out, "SF:{}", file);
+        for (line, hits) in lines {
+            let _ = writeln!(out, "DA:{},{}", line, hits);
+        }
+        let (hit, total) = line_counts(lines.values());
+        let _ = writeln!(out, "LH:{}", hit);
+        let _ = writeln!(out, "LF:{}", total);
+        let _ = writeln!(out, "end_of_record");
+    }
+    out
+}
+
+/// Render a single HTML page with a summary table and annotated sources
+pub fn render_html(coverage: &Coverage) -> String {
+    let (hit, total) = totals(coverage);
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>VHP coverage</title>\n\
+         <style>body{font-family:sans-serif}table{border-collapse:collapse}\
+         td,th{padding:2px 8px;text-align:left}pre{margin:0}\
+         .hit{background:#dfd}.miss{background:#fdd}.num{color:#888;text-align:right}</style>\n\
+         </head><body>\n",
+    );
+    let _ = writeln!(
+        html,
+        "<h1>VHP coverage: {} ({} of {} lines)</h1>",
+        percent(hit, total),
+        hit,
+        total
+    );
+
+    html.push_str("<table>\n<tr><th>File</th><th>Lines</th><th>Coverage</th></tr>\n");
+    for (index, (file, lines)) in coverage.files.iter().enumerate() {
+        let (h, t) = line_counts(lines.values());
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"#f{}\">{}</a></td><td>{}/{}</td><td>{}</td></tr>",
+            index,
+            escape(file),
+            h,
+            t,
+            percent(h, t)
+        );
+    }
+    html.push_str("</table>\n");
+
+    for (index, (file, lines)) in coverage.files.iter().enumerate() {
+        let _ = writeln!(html, "<h2 id=\"f{}\">{}</h2>", index, escape(file));
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(_) => {
+                html.push_str("<p>Source not available.</p>\n");
+                continue;
+            }
+        };
+        html.push_str("<table>\n");
+        for (number, text) in source.lines().enumerate() {
+            let number = number + 1;
+            let (class, hits) = match lines.get(&number) {
+                Some(0) => (" class=\"miss\"", "0".to_string()),
+                Some(hits) => (" class=\"hit\"", hits.to_string()),
+                None => ("", String::new()),
+            };
+            let _ = writeln!(
+                html,
+                "<tr{}><td class=\"num\">{}</td><td class=\"num\">{}</td><td><pre>{}</pre></td></tr>",
+                class,
+                number,
+                hits,
+                escape(text)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// Format a line rate as a percentage ("n/a" when nothing is executable)
+pub fn percent(hit: usize, total: usize) -> String {
+    if total == 0 {
+        "n/a".to_string()
+    } else {
+        format!("{:.1}%", hit as f64 * 100.0 / total as f64)
+    }
+}
+
+fn line_counts<'a>(hits: impl Iterator<Item = &'a u64>) -> (usize, usize) {
+    hits.fold((0, 0), |(hit, total), &count| {
+        (hit + usize::from(count > 0), total + 1)
+    })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,111 @@
+//! Line-based unified diff for EXPECT mismatches
+
+/// Lines of unchanged context shown around each change
+const CONTEXT: usize = 3;
+
+/// Above this many line pairs the LCS table gets too large; show both blobs instead
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Render a colored unified diff from `expected` to `actual`.
+///
+/// Returns None when the inputs are too large to diff.
+pub fn unified_diff(expected: &str, actual: &str) -> Option<String> {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    if old.len().saturating_mul(new.len()) > MAX_CELLS {
+        return None;
+    }
+
+    let ops = diff_ops(&old, &new);
+    let mut out = String::from("\x1b[31m--- expected\x1b[0m\n\x1b[32m+++ actual\x1b[0m\n");
+
+    // Walk the edit script, emitting hunks around each run of changes
+    let mut i = 0;
+    while i < ops.len() {
+        if ops[i].0 == Op::Equal {
+            i += 1;
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT);
+        let mut end = i;
+        // Extend the hunk while the next change is within 2 * CONTEXT lines
+        loop {
+            while end < ops.len() && ops[end].0 != Op::Equal {
+                end += 1;
+            }
+            let next_change = ops[end..].iter().position(|(op, _, _)| *op != Op::Equal);
+            match next_change {
+                Some(gap) if gap <= 2 * CONTEXT => end += gap,
+                _ => break,
+            }
+        }
+        let stop = (end + CONTEXT).min(ops.len());
+        write_hunk(&mut out, &ops[start..stop], &old, &new);
+        i = stop;
+    }
+
+    Some(out)
+}
+
+fn write_hunk(out: &mut String, hunk: &[(Op, usize, usize)], old: &[&str], new: &[&str]) {
+    let (_, old_start, new_start) = hunk[0];
+    let old_count = hunk.iter().filter(|(op, _, _)| *op != Op::Insert).count();
+    let new_count = hunk.iter().filter(|(op, _, _)| *op != Op::Delete).count();
+    out.push_str(&format!(
+        "\x1b[36m@@ -{},{} +{},{} @@\x1b[0m\n",
+        old_start + 1,
+        old_count,
+        new_start + 1,
+        new_count
+    ));
+    for &(op, old_idx, new_idx) in hunk {
+        match op {
+            Op::Equal => out.push_str(&format!(" {}\n", old[old_idx])),
+            Op::Delete => out.push_str(&format!("\x1b[31m-{}\x1b[0m\n", old[old_idx])),
+            Op::Insert => out.push_str(&format!("\x1b[32m+{}\x1b[0m\n", new[new_idx])),
+        }
+    }
+}
+
+/// Compute an edit script via longest common subsequence.
+///
+/// Each entry is (operation, index into old, index into new); for inserts
+/// and deletes the index on the other side is where the line would go.
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<(Op, usize, usize)> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j] = length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            ops.push((Op::Equal, i, j));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push((Op::Delete, i, j));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, i, j));
+            j += 1;
+        }
+    }
+    ops
+}
@@ -0,0 +1,78 @@
+//! Test file discovery
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A discovered .vhpt file
+#[derive(Debug, Clone)]
+pub struct TestFile {
+    /// Path used to read the file
+    pub path: PathBuf,
+    /// Path shown in reports (relative to the directory it was found in)
+    pub display: String,
+}
+
+/// Discover .vhpt files under each root (a directory or a single file)
+pub fn discover_tests(roots: &[PathBuf]) -> Result<Vec<TestFile>, String> {
+    let mut tests: Vec<TestFile> = Vec::new();
+
+    for root in roots {
+        if root.is_file() {
+            // Single file - check if it has .vhpt extension
+            if root.extension().is_none_or(|ext| ext != "vhpt") {
+                return Err(format!("File must have .vhpt extension: {:?}", root));
+            }
+            let display = root
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| root.display().to_string());
+            push_unique(&mut tests, root.clone(), display);
+        } else if root.is_dir() {
+            // Directory - discover recursively
+            let mut found = Vec::new();
+            discover_recursive(root, &mut found)?;
+            found.sort();
+            for path in found {
+                let display = path
+                    .strip_prefix(root)
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string();
+                push_unique(&mut tests, path, display);
+            }
+        } else {
+            return Err(format!("Path does not exist: {:?}", root));
+        }
+    }
+
+    Ok(tests)
+}
+
+/// Add a test unless the same file was already reached through another root
+fn push_unique(tests: &mut Vec<TestFile>, path: PathBuf, display: String) {
+    if !tests.iter().any(|t| t.path == path) {
+        tests.push(TestFile { path, display });
+    }
+}
+
+fn discover_recursive(dir: &Path, tests: &mut Vec<PathBuf>) -> Result<(), String> {
+    if !dir.exists() {
+        return Err(format!("Test directory does not exist: {:?}", dir));
+    }
+
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read directory {:?}: {}", dir, e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            discover_recursive(&path, tests)?;
+        } else if path.extension().is_some_and(|ext| ext == "vhpt") {
+            tests.push(path);
+        }
+    }
+
+    Ok(())
+}
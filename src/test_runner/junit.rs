@@ -0,0 +1,154 @@
+//! JUnit XML report output
+//!
+//! Produces the de-facto standard format understood by CI systems:
+//! one `<testsuite>` per test directory, one `<testcase>` per .vhpt file.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::{TestRecord, TestResult, TestSummary};
+
+/// Write the JUnit report for a finished run
+pub fn write_report(path: &Path, summary: &TestSummary) -> Result<(), String> {
+    fs::write(path, render(summary))
+        .map_err(|e| format!("Failed to write JUnit report {:?}: {}", path, e))
+}
+
+/// Render a summary as JUnit XML
+pub fn render(summary: &TestSummary) -> String {
+    // Group records by directory, keeping discovery order
+    let mut suites: Vec<(String, Vec<&TestRecord>)> = Vec::new();
+    for record in &summary.records {
+        let suite = suite_name(record);
+        match suites.iter_mut().find(|(name, _)| *name == suite) {
+            Some((_, records)) => records.push(record),
+            None => suites.push((suite, vec![record])),
+        }
+    }
+
+    let total_time: f64 = summary
+        .records
+        .iter()
+        .map(|r| r.duration.as_secs_f64())
+        .sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"vhp\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.6}\">",
+        summary.records.len(),
+        summary.failed + summary.xpassed,
+        summary.errors,
+        summary.skipped + summary.xfailed,
+        total_time
+    );
+
+    for (name, records) in &suites {
+        let count = |f: fn(&TestResult) -> bool| records.iter().filter(|r| f(&r.result)).count();
+        let time: f64 = records.iter().map(|r| r.duration.as_secs_f64()).sum();
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.6}\">",
+            escape(name),
+            records.len(),
+            count(|r| matches!(
+                r,
+                TestResult::Fail { .. } | TestResult::UnexpectedPass(_) | TestResult::Timeout { .. }
+            )),
+            count(|r| matches!(r, TestResult::Error(_))),
+            count(|r| matches!(r, TestResult::Skipped(_) | TestResult::ExpectedFail(_))),
+            time
+        );
+        for record in records {
+            write_case(&mut xml, name, record);
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn write_case(xml: &mut String, suite: &str, record: &TestRecord) {
+    let _ = write!(
+        xml,
+        "    <testcase name=\"{}\" classname=\"{}\" file=\"{}\" time=\"{:.6}\"",
+        escape(&record.name),
+        escape(suite),
+        escape(&record.file),
+        record.duration.as_secs_f64()
+    );
+
+    let body = match &record.result {
+        TestResult::Pass => None,
+        TestResult::Fail { expected, actual } => Some(format!(
+            "<failure message=\"Output mismatch\">Expected:\n{}\n\nActual:\n{}</failure>",
+            escape(expected),
+            escape(actual)
+        )),
+        TestResult::Error(err) => Some(format!(
+            "<error message=\"{}\">{}</error>",
+            escape(first_line(err)),
+            escape(err)
+        )),
+        TestResult::Skipped(reason) => Some(format!("<skipped message=\"{}\"/>", escape(reason))),
+        TestResult::ExpectedFail(reason) => {
+            Some(format!("<skipped message=\"XFAIL: {}\"/>", escape(reason)))
+        }
+        TestResult::Timeout { limit, output } => Some(format!(
+            "<failure message=\"Timed out after {}s\">Partial output:\n{}</failure>",
+            limit.as_secs_f64(),
+            escape(output)
+        )),
+        TestResult::UnexpectedPass(reason) => Some(format!(
+            "<failure message=\"XPASS: test marked XFAIL passed\">{}</failure>",
+            escape(reason)
+        )),
+    };
+
+    match body {
+        Some(body) => {
+            let _ = writeln!(xml, ">\n      {}\n    </testcase>", body);
+        }
+        None => xml.push_str("/>\n"),
+    }
+}
+
+/// Suite name for a test: its relative directory with `/` replaced by `.`,
+/// or the name of the directory it lives in for top-level tests
+fn suite_name(record: &TestRecord) -> String {
+    match Path::new(&record.file)
+        .parent()
+        .map(|p| p.to_string_lossy())
+    {
+        Some(dir) if !dir.is_empty() => dir.replace(['/', '\\'], "."),
+        _ => record
+            .path
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "vhp".to_string()),
+    }
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or("")
+}
+
+/// Escape text for XML attributes and content, dropping invalid control characters
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' | '\r' | '\t' => out.push(c),
+            c if (c as u32) < 0x20 || c == '\x1b' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
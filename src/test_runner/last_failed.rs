@@ -0,0 +1,53 @@
+//! Cache of failing tests for `vhp test --failed`
+//!
+//! After every run the cache holds the test files that failed most recently:
+//! tests that ran are removed or re-added according to their new outcome,
+//! tests that didn't run keep their previous entry.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::TestSummary;
+
+/// Cache file, relative to the working directory
+pub const CACHE_FILE: &str = ".vhp-test-failures";
+
+/// Test files recorded as failing, in the order they were saved
+pub fn load(cache: &Path) -> Vec<PathBuf> {
+    fs::read_to_string(cache)
+        .map(|content| {
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Merge the outcome of a run into the cache
+pub fn update(cache: &Path, summary: &TestSummary) -> Result<(), String> {
+    let mut failed: Vec<PathBuf> = load(cache)
+        .into_iter()
+        .filter(|path| !summary.records.iter().any(|r| r.path == *path))
+        .collect();
+    failed.extend(
+        summary
+            .records
+            .iter()
+            .filter(|r| r.result.is_failure())
+            .map(|r| r.path.clone()),
+    );
+
+    if failed.is_empty() {
+        if cache.exists() {
+            fs::remove_file(cache).map_err(|e| format!("Failed to remove {:?}: {}", cache, e))?;
+        }
+        return Ok(());
+    }
+    let content: String = failed
+        .iter()
+        .map(|path| format!("{}\n", path.display()))
+        .collect();
+    fs::write(cache, content).map_err(|e| format!("Failed to write {:?}: {}", cache, e))
+}
@@ -0,0 +1,78 @@
+//! Output comparison for EXPECT and EXPECTF sections
+
+pub fn compare_output(actual: &str, expected: &str) -> bool {
+    // Normalize line endings and trim
+    let actual = actual.trim().replace("\r\n", "\n");
+    let expected = expected.trim().replace("\r\n", "\n");
+
+    // Check for EXPECTF patterns (%s, %d, %%)
+    if expected.contains('%') {
+        return match_pattern(&actual, expected.as_str());
+    }
+
+    actual == expected
+}
+
+/// Match actual output against a pattern with placeholders: %s (any
+/// text), %d (one or more digits) and %% (a literal %)
+fn match_pattern(actual: &str, pattern: &str) -> bool {
+    let actual: Vec<char> = actual.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    placeholder_match(&pattern, &actual)
+}
+
+fn placeholder_match(mut pattern: &[char], mut text: &[char]) -> bool {
+    loop {
+        match pattern {
+            [] => return text.is_empty(),
+            ['%', 's', rest @ ..] => {
+                return (0..=text.len()).any(|i| placeholder_match(rest, &text[i..]));
+            }
+            ['%', 'd', rest @ ..] => {
+                let digits = text.iter().take_while(|c| c.is_ascii_digit()).count();
+                return (1..=digits).any(|i| placeholder_match(rest, &text[i..]));
+            }
+            ['%', '%', rest @ ..] | [_, rest @ ..] => {
+                let expected = if pattern.starts_with(&['%', '%']) {
+                    '%'
+                } else {
+                    pattern[0]
+                };
+                if text.first() != Some(&expected) {
+                    return false;
+                }
+                pattern = rest;
+                text = &text[1..];
+            }
+        }
+    }
+}
+
+/// Check whether a test matches a --filter pattern
+///
+/// Patterns containing `*` or `?` are globs matched against the whole
+/// candidate; anything else is a case-insensitive substring match.
+pub fn matches_filter(pattern: &str, candidates: &[&str]) -> bool {
+    if pattern.contains(['*', '?']) {
+        let pattern: Vec<char> = pattern.chars().collect();
+        candidates.iter().any(|candidate| {
+            let text: Vec<char> = candidate.chars().collect();
+            glob_match(&pattern, &text)
+        })
+    } else {
+        let pattern = pattern.to_lowercase();
+        candidates
+            .iter()
+            .any(|candidate| candidate.to_lowercase().contains(&pattern))
+    }
+}
+
+/// Match `*` (any run of characters) and `?` (any single character)
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len()).any(|i| glob_match(&pattern[1..], &text[i..])),
+        Some('?') => !text.is_empty() && glob_match(&pattern[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
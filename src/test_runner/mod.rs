@@ -0,0 +1,263 @@
+//! .vhpt test runner
+//!
+//! Discovers `.vhpt` files, runs each one through the VM and reports
+//! results in a compact, verbose or TAP format. Tools embedding the runner
+//! can call `TestRunner::run_with` with their own `Reporter` and inspect
+//! the returned per-test records.
+
+mod bless;
+mod console;
+mod coverage;
+mod diff;
+mod discovery;
+mod junit;
+mod last_failed;
+mod matching;
+mod options;
+mod reporter;
+mod round_trip;
+mod tap;
+mod test_case;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub use crate::vm::coverage::Coverage;
+pub use console::ConsoleReporter;
+use discovery::{discover_tests, TestFile};
+use matching::matches_filter;
+pub use options::{OutputFormat, TestOptions};
+pub use reporter::{Reporter, SilentReporter};
+pub use tap::TapReporter;
+pub use test_case::{TestCase, TestResult};
+
+/// A discovered test file with its parsed contents (or parse error)
+type LoadedTest = (TestFile, Result<TestCase, String>);
+
+pub struct TestRunner {
+    options: TestOptions,
+}
+
+#[derive(Debug, Default)]
+pub struct TestSummary {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+    pub skipped: usize,
+    pub xfailed: usize,
+    pub xpassed: usize,
+    pub failures: Vec<(String, String, String)>, // (name, expected, actual)
+    pub records: Vec<TestRecord>,
+    /// Seed of the shuffled order (None when tests ran in file order)
+    pub seed: Option<u64>,
+    /// Line coverage, when requested with --coverage
+    pub coverage: Option<Coverage>,
+}
+
+/// Outcome of a single test, kept for report writers
+#[derive(Debug, Clone)]
+pub struct TestRecord {
+    /// Test name from --TEST-- (or the file path if the file didn't parse)
+    pub name: String,
+    /// Path relative to the directory the test was discovered in
+    pub file: String,
+    /// Path the test was read from
+    pub path: PathBuf,
+    pub duration: Duration,
+    pub result: TestResult,
+}
+
+impl TestRunner {
+    pub fn new(options: TestOptions) -> Self {
+        Self { options }
+    }
+
+    /// Test files to run: discovered under the paths, or the last failures
+    fn test_files(&self) -> Result<Vec<TestFile>, String> {
+        if !self.options.failed {
+            return discover_tests(&self.options.paths);
+        }
+        let roots = &self.options.paths;
+        Ok(last_failed::load(Path::new(last_failed::CACHE_FILE))
+            .into_iter()
+            .filter(|path| path.is_file() && roots.iter().any(|root| path.starts_with(root)))
+            .map(|path| TestFile {
+                display: path.display().to_string(),
+                path,
+            })
+            .collect())
+    }
+
+    /// Find the tests to run and apply the name/path filter
+    fn load_tests(&self) -> Result<Vec<LoadedTest>, String> {
+        let mut tests = Vec::new();
+        for file in self.test_files()? {
+            let content = fs::read_to_string(&file.path)
+                .map_err(|e| format!("Failed to read test file {:?}: {}", file.path, e))?;
+            let test_case = TestCase::parse(&content, &file.display);
+
+            if let Some(pattern) = &self.options.filter {
+                let name = test_case.as_ref().map(|t| t.name.as_str()).unwrap_or("");
+                if !matches_filter(pattern, &[&file.display, name]) {
+                    continue;
+                }
+            }
+            tests.push((file, test_case));
+        }
+        Ok(tests)
+    }
+
+    /// Run the tests, print results in the configured format and write
+    /// any requested reports
+    pub fn run_all(&self) -> Result<TestSummary, String> {
+        let summary = match self.options.format {
+            OutputFormat::Console => self.run_with(&mut ConsoleReporter {
+                verbose: self.options.verbose,
+            })?,
+            OutputFormat::Tap => self.run_with(&mut TapReporter)?,
+        };
+
+        // Round trips don't run the tests, so they say nothing of which
+        // ones fail
+        if !self.options.round_trip {
+            if let Err(e) = last_failed::update(Path::new(last_failed::CACHE_FILE), &summary) {
+                eprintln!("Warning: {}", e);
+            }
+        }
+
+        if let Some(path) = &self.options.junit {
+            junit::write_report(path, &summary)?;
+        }
+
+        if self.options.bless {
+            bless::bless_failures(&summary, !self.options.yes)?;
+        }
+
+        if let (Some(path), Some(lines)) = (&self.options.coverage, &summary.coverage) {
+            coverage::write_report(path, lines)?;
+            if self.options.format == OutputFormat::Console {
+                let (hit, total) = coverage::totals(lines);
+                println!(
+                    "Coverage: {} ({} of {} lines), report written to {}",
+                    coverage::percent(hit, total),
+                    hit,
+                    total,
+                    path.display()
+                );
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Run the tests, reporting progress to `reporter`
+    ///
+    /// Nothing is printed and no report files are written; the returned
+    /// summary holds one record per test, in the order they ran.
+    pub fn run_with(&self, reporter: &mut dyn Reporter) -> Result<TestSummary, String> {
+        let mut tests = self.load_tests()?;
+        let mut summary = TestSummary::default();
+
+        if tests.is_empty() {
+            let message = match &self.options.filter {
+                Some(pattern) => format!("No tests match filter {:?}", pattern),
+                None if self.options.failed => {
+                    format!("No previously failed tests in {:?}", self.options.paths)
+                }
+                None => format!("No tests found in {:?}", self.options.paths),
+            };
+            reporter.empty(&message);
+            return Ok(summary);
+        }
+
+        summary.seed = self.shuffle(&mut tests);
+        reporter.start(tests.len(), summary.seed);
+
+        let mut lines = self.options.coverage.as_ref().map(|_| Coverage::default());
+        for (index, (file, parsed)) in tests.iter().enumerate() {
+            let started = Instant::now();
+            let (name, result) = match parsed {
+                Ok(test_case) if self.options.round_trip => {
+                    (test_case.name.clone(), test_case.round_trip())
+                }
+                Ok(test_case) => {
+                    let full_path = file.path.display().to_string();
+                    let result = test_case.run(&full_path, &self.options, lines.as_mut());
+                    (test_case.name.clone(), result)
+                }
+                Err(e) => (file.display.clone(), TestResult::Error(e.clone())),
+            };
+
+            let record = TestRecord {
+                name,
+                file: file.display.clone(),
+                path: file.path.clone(),
+                duration: started.elapsed(),
+                result,
+            };
+            Self::tally(&mut summary, &record, parsed.is_err());
+            reporter.result(index + 1, &record);
+            summary.records.push(record);
+        }
+
+        summary.coverage = lines;
+        reporter.finish(&summary);
+        Ok(summary)
+    }
+
+    /// Shuffle the tests unless disabled, returning the seed that was used
+    fn shuffle(&self, tests: &mut [LoadedTest]) -> Option<u64> {
+        if !self.options.shuffle {
+            return None;
+        }
+        let seed = self.options.seed.unwrap_or_else(|| fastrand::u64(..));
+        fastrand::Rng::with_seed(seed).shuffle(tests);
+        Some(seed)
+    }
+
+    /// Update the summary counters and failure list for one test
+    fn tally(summary: &mut TestSummary, record: &TestRecord, invalid_file: bool) {
+        summary.total += 1;
+        let name = record.name.clone();
+        match &record.result {
+            TestResult::Pass => summary.passed += 1,
+            TestResult::Fail { expected, actual } => {
+                summary.failed += 1;
+                summary
+                    .failures
+                    .push((name, expected.clone(), actual.clone()));
+            }
+            TestResult::Error(err) => {
+                summary.errors += 1;
+                let expected = if invalid_file {
+                    "Valid test file"
+                } else {
+                    "No error"
+                };
+                summary
+                    .failures
+                    .push((name, expected.to_string(), err.clone()));
+            }
+            TestResult::ExpectedFail(_) => summary.xfailed += 1,
+            TestResult::UnexpectedPass(reason) => {
+                summary.xpassed += 1;
+                summary.failures.push((
+                    name,
+                    format!("Failure (XFAIL: {})", reason),
+                    "Test passed; remove the --XFAIL-- section".to_string(),
+                ));
+            }
+            TestResult::Skipped(_) => summary.skipped += 1,
+            TestResult::Timeout { limit, output } => {
+                summary.failed += 1;
+                summary.failures.push((
+                    name,
+                    format!("Completion within {}s", limit.as_secs_f64()),
+                    format!("Timed out; partial output:\n{}", output),
+                ));
+            }
+        }
+    }
+}
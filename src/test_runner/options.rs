@@ -0,0 +1,143 @@
+//! Command-line options for `vhp test`
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Wall-clock limit per test unless overridden with --timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How progress and results are printed to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Colored dots (or one line per test with --verbose) and a summary
+    #[default]
+    Console,
+    /// Test Anything Protocol, version 13
+    Tap,
+}
+
+/// Options controlling which tests run and how results are reported
+#[derive(Debug, Clone)]
+pub struct TestOptions {
+    /// Test directories and/or individual .vhpt files
+    pub paths: Vec<PathBuf>,
+    pub verbose: bool,
+    pub format: OutputFormat,
+    /// Only run tests whose name or path matches (substring or glob)
+    pub filter: Option<String>,
+    /// Write a JUnit XML report to this path
+    pub junit: Option<PathBuf>,
+    /// Per-test wall-clock limit (None disables it)
+    pub timeout: Option<Duration>,
+    /// Write a line coverage report (lcov, or HTML for *.html) to this path
+    pub coverage: Option<PathBuf>,
+    /// Rewrite --EXPECT-- sections of failing tests with the actual output
+    pub bless: bool,
+    /// Bless without asking for confirmation
+    pub yes: bool,
+    /// Run tests in random order to expose inter-test dependencies
+    pub shuffle: bool,
+    /// Seed for the random order (a fresh one is picked when None)
+    pub seed: Option<u64>,
+    /// Only run the tests that failed in previous runs
+    pub failed: bool,
+    /// Run each test with a frozen clock and a fixed random seed
+    pub deterministic: bool,
+    /// Check that the code of each test prints back to the same tree
+    /// instead of running it
+    pub round_trip: bool,
+}
+
+impl Default for TestOptions {
+    fn default() -> Self {
+        Self {
+            paths: vec![PathBuf::from("tests")],
+            verbose: false,
+            format: OutputFormat::Console,
+            filter: None,
+            junit: None,
+            timeout: Some(DEFAULT_TIMEOUT),
+            coverage: None,
+            bless: false,
+            yes: false,
+            shuffle: true,
+            seed: None,
+            failed: false,
+            deterministic: false,
+            round_trip: false,
+        }
+    }
+}
+
+impl TestOptions {
+    /// Parse the arguments following `vhp test`
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = TestOptions::default();
+        let mut paths = Vec::new();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| iter.next().cloned())
+                    .ok_or_else(|| format!("{} requires a value", flag))
+            };
+
+            match flag {
+                "-v" | "--verbose" => options.verbose = true,
+                "--tap" => options.format = OutputFormat::Tap,
+                "--filter" => options.filter = Some(value()?),
+                "--junit" => options.junit = Some(PathBuf::from(value()?)),
+                "--timeout" => options.timeout = parse_timeout(&value()?)?,
+                "--coverage" => options.coverage = Some(PathBuf::from(value()?)),
+                "--bless" => options.bless = true,
+                "-y" | "--yes" => options.yes = true,
+                "--no-shuffle" => options.shuffle = false,
+                "--failed" => options.failed = true,
+                "--deterministic" => options.deterministic = true,
+                "--round-trip" => options.round_trip = true,
+                "--seed" => {
+                    let seed = value()?;
+                    options.seed = Some(
+                        seed.parse()
+                            .map_err(|_| format!("Invalid --seed value: {}", seed))?,
+                    );
+                }
+                _ if flag.starts_with('-') => {
+                    return Err(format!("Unknown test option: {}", arg));
+                }
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+
+        if !paths.is_empty() {
+            options.paths = paths;
+        }
+        if options.seed.is_some() && !options.shuffle {
+            return Err("--seed cannot be combined with --no-shuffle".to_string());
+        }
+        if options.yes && !options.bless {
+            return Err("--yes only applies together with --bless".to_string());
+        }
+        if options.round_trip && (options.bless || options.coverage.is_some()) {
+            return Err("--round-trip cannot be combined with --bless or --coverage".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Parse a --timeout value in seconds; 0 disables the limit
+fn parse_timeout(value: &str) -> Result<Option<Duration>, String> {
+    let seconds: f64 = value
+        .parse()
+        .ok()
+        .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+        .ok_or_else(|| format!("Invalid --timeout value: {}", value))?;
+    Ok((seconds > 0.0).then(|| Duration::from_secs_f64(seconds)))
+}
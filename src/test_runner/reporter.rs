@@ -0,0 +1,27 @@
+//! Progress reporting hooks
+//!
+//! `TestRunner::run_with` reports through this trait, so external tools can
+//! observe a run without parsing console output. All methods default to
+//! doing nothing.
+
+use super::{TestRecord, TestSummary};
+
+/// Receives events while tests run
+pub trait Reporter {
+    /// Called before the first test; `seed` is set when the order was shuffled
+    fn start(&mut self, _count: usize, _seed: Option<u64>) {}
+
+    /// Called instead of `start` when there is nothing to run
+    fn empty(&mut self, _message: &str) {}
+
+    /// Called after each test with its 1-based position in the run
+    fn result(&mut self, _number: usize, _record: &TestRecord) {}
+
+    /// Called after the last test
+    fn finish(&mut self, _summary: &TestSummary) {}
+}
+
+/// Reporter that prints nothing
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {}
@@ -0,0 +1,30 @@
+//! `vhp test --round-trip`: the pretty printer over the test corpus
+//!
+//! Instead of running a test, its --FILE-- code is parsed, printed and
+//! parsed again, and the test passes if both parses give the same tree
+//! (see `printer::round_trip`). Code that doesn't parse in the first
+//! place, like the tests of syntax errors, is skipped.
+
+use super::test_case::{TestCase, TestResult};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::printer;
+
+impl TestCase {
+    /// Check that the test's code prints back to the same tree
+    pub fn round_trip(&self) -> TestResult {
+        let parses = Lexer::new(&self.code)
+            .tokenize()
+            .is_ok_and(|tokens| Parser::new(tokens).parse().is_ok());
+        if !parses {
+            return TestResult::Skipped("the code does not parse".to_string());
+        }
+        match printer::round_trip(&self.code) {
+            Ok(_) => TestResult::Pass,
+            Err(error) => TestResult::Fail {
+                expected: "Printed source parsing to the same tree".to_string(),
+                actual: error,
+            },
+        }
+    }
+}
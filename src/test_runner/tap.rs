@@ -0,0 +1,121 @@
+//! TAP (Test Anything Protocol) version 13 output
+//!
+//! Failures carry a YAML diagnostic block with the expected and actual
+//! output. SKIPIF tests map to `# SKIP` and XFAIL tests to `# TODO`.
+
+use super::{Reporter, TestRecord, TestResult, TestSummary};
+
+/// Reporter printing TAP version 13 to stdout
+pub struct TapReporter;
+
+impl Reporter for TapReporter {
+    fn start(&mut self, count: usize, seed: Option<u64>) {
+        print_plan(count, None);
+        if let Some(seed) = seed {
+            println!("# seed {}", seed);
+        }
+    }
+
+    fn empty(&mut self, message: &str) {
+        print_plan(0, Some(message));
+    }
+
+    fn result(&mut self, number: usize, record: &TestRecord) {
+        print_result(number, record);
+    }
+
+    fn finish(&mut self, summary: &TestSummary) {
+        print_totals(summary);
+    }
+}
+
+/// Print the version line and plan (`1..0 # SKIP reason` for an empty run)
+pub fn print_plan(count: usize, skip_reason: Option<&str>) {
+    println!("TAP version 13");
+    match skip_reason {
+        Some(reason) => println!("1..{} # SKIP {}", count, reason),
+        None => println!("1..{}", count),
+    }
+}
+
+/// Print the test point for one finished test
+pub fn print_result(number: usize, record: &TestRecord) {
+    let description = escape_description(&record.name);
+    match &record.result {
+        TestResult::Pass => println!("ok {} - {}", number, description),
+        TestResult::Fail { expected, actual } => {
+            println!("not ok {} - {}", number, description);
+            print_diagnostics(
+                record,
+                "Output mismatch",
+                &[("expected", expected), ("actual", actual)],
+            );
+        }
+        TestResult::Error(err) => {
+            println!("not ok {} - {}", number, description);
+            print_diagnostics(record, "Error", &[("error", err)]);
+        }
+        TestResult::Timeout { limit, output } => {
+            println!("not ok {} - {}", number, description);
+            let message = format!("Timed out after {}s", limit.as_secs_f64());
+            print_diagnostics(record, &message, &[("output", output)]);
+        }
+        TestResult::Skipped(reason) => {
+            println!(
+                "ok {} - {} # SKIP {}",
+                number,
+                description,
+                single_line(reason)
+            );
+        }
+        TestResult::ExpectedFail(reason) => {
+            println!(
+                "not ok {} - {} # TODO {}",
+                number,
+                description,
+                single_line(reason)
+            );
+        }
+        TestResult::UnexpectedPass(reason) => {
+            println!(
+                "ok {} - {} # TODO {}",
+                number,
+                description,
+                single_line(reason)
+            );
+        }
+    }
+}
+
+/// Print run totals as TAP comments
+pub fn print_totals(summary: &TestSummary) {
+    println!("# tests {}", summary.total);
+    println!("# pass {}", summary.passed);
+    println!("# fail {}", summary.failed + summary.xpassed);
+    println!("# error {}", summary.errors);
+    println!("# skip {}", summary.skipped);
+    println!("# todo {}", summary.xfailed + summary.xpassed);
+}
+
+/// Print a YAML diagnostic block below a failed test point
+fn print_diagnostics(record: &TestRecord, message: &str, fields: &[(&str, &String)]) {
+    println!("  ---");
+    println!("  message: {:?}", message);
+    println!("  file: {:?}", record.file);
+    for (key, value) in fields {
+        println!("  {}: |", key);
+        for line in value.lines() {
+            println!("    {}", line);
+        }
+    }
+    println!("  ...");
+}
+
+/// `#` starts a directive in TAP, so it must be escaped in descriptions
+fn escape_description(name: &str) -> String {
+    single_line(name).replace('#', "\\#")
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
@@ -0,0 +1,466 @@
+//! Test case parsing and execution for .vhpt files
+
+use crate::cgi::Request;
+use crate::runtime::builtins::{ini, locale};
+use crate::runtime::deterministic;
+use crate::vm::coverage::Coverage;
+use crate::vm::sandbox::Sandbox;
+use crate::vm::{RuntimeError, VM};
+use std::time::{Duration, Instant};
+
+use super::matching::compare_output;
+use super::options::TestOptions;
+
+#[derive(Debug, Default)]
+pub struct TestCase {
+    pub name: String,
+    #[allow(dead_code)]
+    pub description: String,
+    #[allow(dead_code)]
+    pub file: String,
+    pub code: String,
+    pub expected: Option<String>,
+    pub expected_error: Option<String>,
+    pub skip: Option<String>,
+    /// INI directives applied to the engine for this test only
+    pub ini: Vec<(String, String)>,
+    /// Environment variables set for the duration of this test
+    pub env: Vec<(String, String)>,
+    /// Query string of the CGI request the test answers
+    pub get: Option<String>,
+    /// Form body of the CGI request the test answers
+    pub post: Option<String>,
+    /// `Content-Type` line, then the body, of the CGI request the test
+    /// answers
+    pub post_raw: Option<String>,
+    /// `Cookie` header of the CGI request the test answers
+    pub cookie: Option<String>,
+    /// Cleanup code executed after the test, whatever its outcome
+    pub clean: Option<String>,
+    /// Reason this test is expected to fail
+    pub xfail: Option<String>,
+    /// Line of the .vhpt file on which the --FILE-- code starts
+    pub code_line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum TestResult {
+    Pass,
+    Fail {
+        expected: String,
+        actual: String,
+    },
+    Error(String),
+    Skipped(String),
+    /// An XFAIL test failed as expected
+    ExpectedFail(String),
+    /// An XFAIL test passed; the XFAIL marker should be removed
+    UnexpectedPass(String),
+    /// The test exceeded its time limit; carries the output produced so far
+    Timeout {
+        limit: Duration,
+        output: String,
+    },
+}
+
+/// Why running test code did not complete normally
+enum RunError {
+    /// Lexer, parser, compiler or runtime error
+    Failed(String),
+    /// The time limit was hit; carries the output produced so far
+    TimedOut(String),
+}
+
+impl From<String> for RunError {
+    fn from(error: String) -> Self {
+        RunError::Failed(error)
+    }
+}
+
+impl TestResult {
+    /// Short machine-readable status label
+    pub fn status(&self) -> &'static str {
+        match self {
+            TestResult::Pass => "pass",
+            TestResult::Fail { .. } => "fail",
+            TestResult::Error(_) => "error",
+            TestResult::Skipped(_) => "skip",
+            TestResult::ExpectedFail(_) => "xfail",
+            TestResult::UnexpectedPass(_) => "xpass",
+            TestResult::Timeout { .. } => "timeout",
+        }
+    }
+
+    /// Whether this result counts against the run (fail, error or XPASS)
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            TestResult::Fail { .. }
+                | TestResult::Error(_)
+                | TestResult::UnexpectedPass(_)
+                | TestResult::Timeout { .. }
+        )
+    }
+}
+
+impl TestCase {
+    pub fn parse(content: &str, file_path: &str) -> Result<Self, String> {
+        let mut test = TestCase {
+            file: file_path.to_string(),
+            ..Default::default()
+        };
+
+        let mut current_section: Option<&str> = None;
+        let mut current_content = String::new();
+
+        for (index, line) in content.lines().enumerate() {
+            if is_section_header(line) {
+                // Save previous section
+                if let Some(section) = current_section {
+                    Self::set_section(&mut test, section, &current_content)?;
+                }
+
+                // Start new section
+                current_section = Some(line.trim_matches('-'));
+                current_content = String::new();
+                if current_section == Some("FILE") {
+                    test.code_line = index + 2;
+                }
+            } else if current_section.is_some() {
+                if current_section == Some("FILE")
+                    && current_content.is_empty()
+                    && line.trim().is_empty()
+                {
+                    // Leading blank lines are dropped; keep code_line on the code
+                    test.code_line += 1;
+                }
+                if !current_content.is_empty() {
+                    current_content.push('\n');
+                }
+                current_content.push_str(line);
+            }
+        }
+
+        // Save last section
+        if let Some(section) = current_section {
+            Self::set_section(&mut test, section, &current_content)?;
+        }
+
+        // Validation
+        if test.name.is_empty() {
+            return Err(format!(
+                "Test file {} is missing --TEST-- section",
+                file_path
+            ));
+        }
+        if test.code.is_empty() {
+            return Err(format!(
+                "Test file {} is missing --FILE-- section",
+                file_path
+            ));
+        }
+        if test.expected.is_none() && test.expected_error.is_none() {
+            return Err(format!(
+                "Test file {} is missing --EXPECT-- or --EXPECTF-- or --EXPECT_ERROR-- section",
+                file_path
+            ));
+        }
+
+        Ok(test)
+    }
+
+    fn set_section(test: &mut TestCase, section: &str, content: &str) -> Result<(), String> {
+        let content = content.trim();
+        match section {
+            "TEST" => test.name = content.to_string(),
+            "DESCRIPTION" => test.description = content.to_string(),
+            "FILE" => test.code = content.to_string(),
+            "EXPECT" | "EXPECTF" => test.expected = Some(content.to_string()),
+            "EXPECT_ERROR" => test.expected_error = Some(content.to_string()),
+            "SKIPIF" => test.skip = Some(content.to_string()),
+            "INI" => test.ini = Self::parse_key_values(content, section)?,
+            "ENV" => test.env = Self::parse_key_values(content, section)?,
+            "GET" => test.get = Some(content.to_string()),
+            "POST" => test.post = Some(content.to_string()),
+            "POST_RAW" => test.post_raw = Some(content.to_string()),
+            "COOKIE" => test.cookie = Some(content.to_string()),
+            "CLEAN" => test.clean = Some(content.to_string()),
+            "XFAIL" => test.xfail = Some(content.to_string()),
+            _ => {} // Ignore unknown sections for forward compatibility
+        }
+        Ok(())
+    }
+
+    /// Parse `key=value` lines; blank lines and `;` comments are ignored
+    fn parse_key_values(content: &str, section: &str) -> Result<Vec<(String, String)>, String> {
+        let mut directives = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid --{}-- entry: {}", section, line))?;
+            let value = value.trim().trim_matches('"');
+            directives.push((key.trim().to_string(), value.to_string()));
+        }
+        Ok(directives)
+    }
+
+    /// The CGI request the test answers, if it has a --GET--, --POST--,
+    /// --POST_RAW-- or --COOKIE-- section
+    fn request(&self, full_path: &str) -> Option<Request> {
+        if self.get.is_none()
+            && self.post.is_none()
+            && self.post_raw.is_none()
+            && self.cookie.is_none()
+        {
+            return None;
+        }
+        let mut variables: Vec<(String, String)> = std::env::vars().collect();
+        variables.sort();
+        let mut set = |name: &str, value: &str| variables.push((name.into(), value.into()));
+        set("SCRIPT_FILENAME", full_path);
+        set("QUERY_STRING", self.get.as_deref().unwrap_or(""));
+        if let Some(cookie) = &self.cookie {
+            set("HTTP_COOKIE", cookie);
+        }
+        let body = match (&self.post, &self.post_raw) {
+            (Some(post), _) => {
+                set("CONTENT_TYPE", "application/x-www-form-urlencoded");
+                Some(post.clone())
+            }
+            (None, Some(raw)) => {
+                let (content_type, body) = raw.split_once('\n').unwrap_or((raw, ""));
+                let content_type = content_type.split_once(':').map_or("", |(_, v)| v.trim());
+                set("CONTENT_TYPE", content_type);
+                Some(body.replace('\n', "\r\n"))
+            }
+            (None, None) => None,
+        };
+        let method = if body.is_some() { "POST" } else { "GET" };
+        set("REQUEST_METHOD", method);
+        let body = body.unwrap_or_default().into_bytes();
+        set("CONTENT_LENGTH", &body.len().to_string());
+        Some(Request::new(variables, body))
+    }
+
+    /// Run the test, aborting it if it runs longer than the `timeout`
+    /// option, deterministically if the `deterministic` option is set
+    ///
+    /// When `coverage` is given, the lines executed by the --FILE-- code are
+    /// added to it, numbered as lines of the .vhpt file.
+    pub fn run(
+        &self,
+        full_path: &str,
+        options: &TestOptions,
+        coverage: Option<&mut Coverage>,
+    ) -> TestResult {
+        let timeout = options.timeout;
+        // Check skip condition
+        if let Some(reason) = &self.skip {
+            return TestResult::Skipped(reason.clone());
+        }
+
+        // Run the code with full path for magic constants
+        let saved_env = apply_env(&self.env);
+        let mut lines = coverage.as_ref().map(|_| Coverage::default());
+        let request = self.request(full_path);
+        let result = run_code(
+            &self.code,
+            full_path,
+            request.as_ref(),
+            &self.ini,
+            options,
+            lines.as_mut(),
+        );
+        if let Some(clean) = &self.clean {
+            // Cleanup output and errors never affect the test result
+            let _ = run_code(clean, full_path, None, &self.ini, options, None);
+        }
+        restore_env(saved_env);
+
+        if let (Some(coverage), Some(mut lines)) = (coverage, lines) {
+            lines.relocate(full_path, self.code_line.saturating_sub(1));
+            coverage.merge(lines);
+        }
+
+        let result = match result {
+            Err(RunError::TimedOut(output)) => TestResult::Timeout {
+                limit: timeout.unwrap_or_default(),
+                output,
+            },
+            Err(RunError::Failed(error)) => self.check_result(Err(error)),
+            Ok(output) => self.check_result(Ok(output)),
+        };
+        match &self.xfail {
+            Some(reason) => match result {
+                TestResult::Pass => TestResult::UnexpectedPass(reason.clone()),
+                TestResult::Fail { .. } | TestResult::Error(_) | TestResult::Timeout { .. } => {
+                    TestResult::ExpectedFail(reason.clone())
+                }
+                other => other,
+            },
+            None => result,
+        }
+    }
+
+    /// Compare the outcome of running the test code against its expectations
+    fn check_result(&self, result: Result<String, String>) -> TestResult {
+        match result {
+            Ok(output) => {
+                if let Some(expected_error) = &self.expected_error {
+                    TestResult::Fail {
+                        expected: format!("Error: {}", expected_error),
+                        actual: output,
+                    }
+                } else if let Some(expected) = &self.expected {
+                    if compare_output(&output, expected) {
+                        TestResult::Pass
+                    } else {
+                        TestResult::Fail {
+                            expected: expected.clone(),
+                            actual: output,
+                        }
+                    }
+                } else {
+                    TestResult::Error("No expected output specified".to_string())
+                }
+            }
+            Err(error) => {
+                if let Some(expected_error) = &self.expected_error {
+                    if error.contains(expected_error) {
+                        TestResult::Pass
+                    } else {
+                        TestResult::Fail {
+                            expected: expected_error.clone(),
+                            actual: error,
+                        }
+                    }
+                } else {
+                    TestResult::Error(error)
+                }
+            }
+        }
+    }
+}
+
+/// Whether `line` starts a section: `--NAME--` with a name of capital
+/// letters and underscores, so a multipart boundary isn't taken for one
+pub(super) fn is_section_header(line: &str) -> bool {
+    line.len() > 4
+        && line.starts_with("--")
+        && line.ends_with("--")
+        && line[2..line.len() - 2]
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c == '_')
+}
+
+/// Set the test's environment variables, returning the previous values
+fn apply_env(env: &[(String, String)]) -> Vec<(String, Option<String>)> {
+    env.iter()
+        .map(|(key, value)| {
+            let previous = std::env::var(key).ok();
+            std::env::set_var(key, value);
+            (key.clone(), previous)
+        })
+        .collect()
+}
+
+/// Restore environment variables saved by `apply_env`
+fn restore_env(saved: Vec<(String, Option<String>)>) {
+    for (key, previous) in saved.into_iter().rev() {
+        match previous {
+            Some(value) => std::env::set_var(&key, value),
+            None => std::env::remove_var(&key),
+        }
+    }
+}
+
+fn run_code(
+    source: &str,
+    full_path: &str,
+    request: Option<&Request>,
+    ini: &[(String, String)],
+    options: &TestOptions,
+    mut coverage: Option<&mut Coverage>,
+) -> Result<String, RunError> {
+    let timeout = options.timeout;
+    // Clear global registries for test isolation
+    crate::runtime::builtins::spl::clear_autoloaders();
+    crate::runtime::builtins::spl::clear_psr4_registry();
+    crate::vm::clear_required_files();
+    crate::runtime::reset_object_handles();
+    if options.deterministic {
+        deterministic::enable();
+    } else {
+        deterministic::disable();
+    }
+
+    // Apply per-test INI directives on top of the defaults
+    ini::reset_ini_settings();
+    locale::reset();
+    for (key, value) in ini {
+        ini::set_ini_value(key, value);
+    }
+
+    // Compile with the full path for magic constants
+    let compilation = crate::compile(source, full_path).map_err(String::from)?;
+
+    // Execute with VM
+    let mut output = Vec::new();
+    let mut vm = VM::new(&mut output);
+    let uploads = match request {
+        Some(request) => vm
+            .set_request(request)
+            .map_err(|e| format!("Cannot save uploaded file: {}", e))?,
+        None => {
+            vm.set_cli_arguments(&[full_path.to_string()]);
+            Vec::new()
+        }
+    };
+    // The runner's limit runs from here, so the time it allows has passed
+    // once it fires
+    let started = Instant::now();
+    vm.set_time_limit(timeout);
+    vm.apply_max_execution_time();
+    if ini::get_ini_value("vhp.stats").is_some_and(|v| v == "1") {
+        vm.enable_stats();
+    }
+    vm.set_sandbox(Sandbox::from_directives(
+        &ini::get_ini_value("disable_functions").unwrap_or_default(),
+        &ini::get_ini_value("open_basedir").unwrap_or_default(),
+    ));
+    if let Some(coverage) = coverage.as_deref_mut() {
+        coverage.add_compilation(&compilation);
+        vm.enable_coverage();
+    }
+    let main = vm.load(compilation);
+    let result = vm.execute(main);
+    if let (Some(coverage), Some(hits)) = (coverage, vm.take_coverage()) {
+        coverage.merge(hits);
+    }
+    drop(vm);
+    for upload in uploads {
+        let _ = std::fs::remove_file(upload);
+    }
+
+    // Handle exit() as a special case - it's not an error, just termination
+    match result {
+        Ok(_) => {}
+        Err(RuntimeError::Exit(_)) => {
+            // exit() was called - this is expected behavior, not an error
+        }
+        // The runner's own limit, as opposed to a max_execution_time the
+        // test sets, is a timeout rather than a failure
+        Err(RuntimeError::TimeLimit { .. }) if timeout.is_some_and(|t| started.elapsed() >= t) => {
+            return Err(RunError::TimedOut(
+                String::from_utf8_lossy(&output).into_owned(),
+            ));
+        }
+        Err(e) => return Err(RunError::Failed(format!("VM error: {}", e))),
+    }
+
+    String::from_utf8(output).map_err(|e| RunError::Failed(format!("Output encoding error: {}", e)))
+}
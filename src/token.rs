@@ -77,19 +77,28 @@ pub enum TokenKind {
     Identifier(String), // function names, etc.
 
     // Literals
-    String(String),  // "string" or 'string'
-    Heredoc(String), // heredoc/nowdoc content (distinguishes from quoted strings)
-    Integer(i64),    // 123
-    Float(f64),      // 1.23
+    String(String), // "string", 'string', or a heredoc or nowdoc
+
+    // Double-quoted string with interpolation: "Hello, $name!"
+    InterpolatedString(Vec<StringPart>),
+    Integer(i64), // 123
+    Float(f64),   // 1.23
 
     // Assignment Operators
-    Assign,       // =
-    PlusAssign,   // +=
-    MinusAssign,  // -=
-    MulAssign,    // *=
-    DivAssign,    // /=
-    ModAssign,    // %=
-    ConcatAssign, // .=
+    Assign,             // =
+    PlusAssign,         // +=
+    MinusAssign,        // -=
+    MulAssign,          // *=
+    DivAssign,          // /=
+    ModAssign,          // %=
+    ConcatAssign,       // .=
+    PowAssign,          // **=
+    BitwiseAndAssign,   // &=
+    BitwiseOrAssign,    // |=
+    BitwiseXorAssign,   // ^=
+    ShiftLeftAssign,    // <<=
+    ShiftRightAssign,   // >>=
+    NullCoalesceAssign, // ??=
 
     // Arithmetic Operators
     Plus,  // +
@@ -120,12 +129,23 @@ pub enum TokenKind {
     Xor, // xor
 
     // Bitwise Operators
-    BitwiseOr, // | (used in multi-catch and bitwise operations)
+    BitwiseOr,  // | (used in multi-catch and bitwise operations)
+    BitwiseAnd, // & (used in by-reference parameters, intersection types and bitwise operations)
+    BitwiseXor, // ^
+    BitwiseNot, // ~
+    ShiftLeft,  // <<
+    ShiftRight, // >>
+
+    // Error Control Operator
+    At, // @
 
     // Increment/Decrement
     Increment, // ++
     Decrement, // --
 
+    // Nullsafe Operator (PHP 8.0)
+    NullsafeArrow, // ?->
+
     // Punctuation
     Semicolon,    // ;
     Comma,        // ,
@@ -161,7 +181,17 @@ pub enum TokenKind {
     Eof,
 }
 
-#[derive(Debug, Clone)]
+/// A piece of a double-quoted string with interpolation
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    /// Text, with its escape sequences processed
+    Literal(String),
+    /// The tokens of an interpolated expression: `$name`, `$a[0]`,
+    /// `$obj->prop`, or what is between the braces of `{$...}`
+    Tokens(Vec<Token>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub kind: TokenKind,
     pub line: usize,
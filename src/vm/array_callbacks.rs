@@ -1,324 +1,180 @@
-//! VM-aware array callback functions
+//! Array functions that call back into the script
 //!
-//! This module provides array_map, array_filter, and array_reduce
-//! which require VM access to execute callbacks.
+//! `array_map()`, `array_filter()` and `array_reduce()` call their
+//! callback for each element, and `usort()`, `uasort()` and `uksort()`
+//! for each comparison, with `VM::call_callable()`, so any callable
+//! works: a function name, a `"Class::method"` string, a `[$object,
+//! 'method']` array or a closure.
 
+use crate::runtime::builtins::native::{self, Array};
 use crate::runtime::{ArrayKey, Value};
-use crate::vm::callback_helpers::{call_callback, is_callable};
+use crate::vm::error::VmError;
 use crate::vm::VM;
+use std::cmp::Ordering;
 use std::io::Write;
 
-impl<W: Write> VM<W> {
-    /// array_map - Apply callback to each element of array
-    ///
-    /// Applies the callback to each element of the given array and returns
-    /// a new array with the results.
-    ///
-    /// PHP equivalent:
-    ///   $result = array_map(fn($x) => $x * 2, $array);
-    ///
-    /// Returns array with keys reindexed from 0
-    pub fn array_map(&mut self, args: &[Value]) -> Result<Value, String> {
-        if args.len() < 2 {
-            return Err("array_map() expects at least 2 parameters".to_string());
-        }
+/// `array_filter()` mode passing the key instead of the value
+const USE_KEY: i64 = 2;
+/// `array_filter()` mode passing both the value and the key
+const USE_BOTH: i64 = 1;
 
-        // Determine which argument is the callback and which is the array
-        let (callback, array) = match (&args[0], &args[1]) {
-            (Value::Closure(_) | Value::String(_), Value::Array(arr)) => (&args[0], arr),
-            (Value::Array(arr), Value::Closure(_) | Value::String(_)) => (&args[1], arr),
-            (Value::Array(_), Value::Array(_)) => {
-                return Err(
-                    "array_map() expects parameter 1 or 2 to be a valid callback".to_string(),
-                );
-            }
-            _ => {
-                return Err("array_map() expects parameter 1 to be a valid callback".to_string());
-            }
+impl<W: Write> VM<W> {
+    /// `array_map()`: the results of the callback for each element, or for
+    /// the elements at each position of several arrays. A null callback
+    /// zips the arrays instead.
+    pub(crate) fn array_map(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        native::check_arity("array_map", args.len(), 2, None)?;
+        let callback = match &args[0] {
+            Value::Null => None,
+            callback => Some(self.callback_argument("array_map", 1, callback)?),
         };
-
-        if !is_callable(callback) {
-            return Err("array_map() expects parameter 1 to be a valid callback".to_string());
-        }
-
-        let mut result = Vec::new();
-        for (i, (_, value)) in array.iter().enumerate() {
-            let mapped_value = call_callback(self, callback, std::slice::from_ref(value))?;
-            result.push((ArrayKey::Integer(i as i64), mapped_value));
+        let arrays = (1..args.len())
+            .map(|i| {
+                let param = if i == 1 { "array" } else { "arrays" };
+                native::arg::<Array>("array_map", args, i, param)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // One array keeps its keys
+        if let [array] = arrays.as_slice() {
+            let Some(callback) = callback else {
+                return Ok(Value::Array(array.clone()));
+            };
+            let mut result = Array::with_capacity(array.len());
+            for (key, value) in array.iter() {
+                let mapped = self.call_callable(&callback, std::slice::from_ref(value))?;
+                result.insert(key.clone(), mapped);
+            }
+            return Ok(Value::Array(result));
+        }
+
+        let len = arrays.iter().map(|array| array.len()).max().unwrap_or(0);
+        let mut result = Array::with_capacity(len);
+        for i in 0..len {
+            let values: Vec<Value> = arrays
+                .iter()
+                .map(|array| {
+                    array
+                        .as_slice()
+                        .get(i)
+                        .map_or(Value::Null, |(_, v)| v.clone())
+                })
+                .collect();
+            let mapped = match &callback {
+                Some(callback) => self.call_callable(callback, &values)?,
+                None => Value::Array(values.into_iter().collect()),
+            };
+            result.insert(ArrayKey::Integer(i as i64), mapped);
         }
-
         Ok(Value::Array(result))
     }
 
-    /// array_filter - Filter array elements using callback
-    ///
-    /// Iterates over each value in the array, passing them to the callback.
-    /// If the callback returns true, the value is included in the result array.
-    ///
-    /// PHP equivalent:
-    ///   $even = array_filter($array, fn($x) => $x % 2 == 0);
-    ///
-    /// Note: Arguments are passed with array first, callback second
-    /// (due to stack-based argument passing)
-    ///
-    /// Preserves original keys
-    pub fn array_filter(&mut self, args: &[Value]) -> Result<Value, String> {
-        if args.len() < 2 {
-            return Err("array_filter() expects at least 2 parameters".to_string());
-        }
-
-        // args[0] is the array, args[1] is the callback (reversed from PHP order)
-        let array = match &args[0] {
-            Value::Array(arr) => arr,
-            _ => return Err("array_filter() expects parameter 1 to be array".to_string()),
+    /// `array_filter()`: the elements the callback accepts, or the truthy
+    /// ones without a callback, keeping their keys
+    pub(crate) fn array_filter(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        native::check_arity("array_filter", args.len(), 1, Some(3))?;
+        let array: Array = native::arg("array_filter", args, 0, "array")?;
+        let callback = match args.get(1) {
+            None | Some(Value::Null) => None,
+            Some(callback) => Some(self.callback_argument("array_filter", 2, callback)?),
         };
+        let mode = native::arg::<Option<i64>>("array_filter", args, 2, "mode")?.unwrap_or(0);
 
-        let callback = &args[1];
-
-        if !is_callable(callback) {
-            return Err("array_filter() expects parameter 2 to be a valid callback".to_string());
-        }
-
-        let mut result = Vec::new();
+        let mut result = Array::new();
         for (key, value) in array.iter() {
-            let keep = call_callback(self, callback, std::slice::from_ref(value))?;
-            let should_keep = match &keep {
-                Value::Bool(b) => *b,
-                Value::Integer(n) => *n != 0,
-                Value::Float(f) => *f != 0.0,
-                Value::String(s) => !s.is_empty(),
-                Value::Array(arr) => !arr.is_empty(),
-                Value::Object(_) => true,
-                Value::EnumCase { .. } => true,
-                Value::Exception(_) => true,
-                Value::Fiber(_) => true,
-                Value::Closure(_) => true,
-                Value::Generator(_) => true,
-                Value::Null => false,
+            let keep = match &callback {
+                None => value.to_bool(),
+                Some(callback) => {
+                    let args = match mode {
+                        USE_KEY => vec![key.to_value()],
+                        USE_BOTH => vec![value.clone(), key.to_value()],
+                        _ => vec![value.clone()],
+                    };
+                    self.call_callable(callback, &args)?.to_bool()
+                }
             };
-            if should_keep {
-                result.push((key.clone(), value.clone()));
+            if keep {
+                result.insert(key.clone(), value.clone());
             }
         }
-
         Ok(Value::Array(result))
     }
 
-    /// array_reduce - Reduce array to single value using callback
-    ///
-    /// Iteratively reduces the array to a single value using the callback.
-    /// The callback receives the accumulated value and current element.
-    ///
-    /// PHP equivalent:
-    ///   $sum = array_reduce($array, fn($carry, $item) => $carry + $item, 0);
-    ///
-    /// Note: Arguments are passed with array first, callback second, then initial
-    /// (due to stack-based argument passing)
-    ///
-    /// If initial value is not provided and array is empty, returns NULL.
-    /// If initial value is not provided and array has elements, uses first element as initial.
-    pub fn array_reduce(&mut self, args: &[Value]) -> Result<Value, String> {
-        if args.len() < 2 {
-            return Err("array_reduce() expects at least 2 parameters".to_string());
-        }
-
-        // args[0] is the array, args[1] is the callback (reversed from PHP order)
-        let array = match &args[0] {
-            Value::Array(arr) => arr,
-            _ => return Err("array_reduce() expects parameter 1 to be array".to_string()),
-        };
-
-        let callback = &args[1];
-
-        if !is_callable(callback) {
-            return Err("array_reduce() expects parameter 2 to be a valid callback".to_string());
-        }
-
-        if array.is_empty() {
-            return Ok(args.get(2).cloned().unwrap_or(Value::Null));
-        }
-
-        let mut accumulator = match args.get(2) {
-            Some(initial) => initial.clone(),
-            None => array[0].1.clone(),
-        };
-
-        let start_idx = if args.len() < 3 { 1 } else { 0 };
-
-        for (_, value) in array.iter().skip(start_idx) {
-            accumulator = call_callback(self, callback, &[accumulator, value.clone()])?;
-        }
-
-        Ok(accumulator)
+    /// `array_reduce()`: fold the elements into one value with the
+    /// callback, starting from `$initial`
+    pub(crate) fn array_reduce(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        native::check_arity("array_reduce", args.len(), 2, Some(3))?;
+        let array: Array = native::arg("array_reduce", args, 0, "array")?;
+        let callback = self.callback_argument("array_reduce", 2, &args[1])?;
+        let mut carry = args.get(2).cloned().unwrap_or(Value::Null);
+        for (_, value) in array.iter() {
+            carry = self.call_callable(&callback, &[carry, value.clone()])?;
+        }
+        Ok(carry)
     }
 
-    /// usort - Sort an array by values using a user-defined comparison function
-    ///
-    /// Sorts array by its values using a comparison function.
-    /// If the comparison function returns 0, the order is undefined.
-    /// Returns true on success, false on failure.
-    ///
-    /// PHP equivalent:
-    ///   usort($array, fn($a, $b) => $a <=> $b);
-    pub fn usort(&mut self, args: &[Value]) -> Result<Value, String> {
-        if args.len() < 2 {
-            return Err("usort() expects at least 2 parameters".to_string());
-        }
-
-        let array = match &args[0] {
-            Value::Array(arr) => arr,
-            _ => return Err("usort() expects parameter 1 to be array".to_string()),
-        };
-
-        let callback = &args[1];
-
-        if !is_callable(callback) {
-            return Err("usort() expects parameter 2 to be a valid callback".to_string());
-        }
-
-        let mut values: Vec<Value> = array.iter().map(|(_, v)| v.clone()).collect();
-
-        values.sort_by(|a, b| {
-            let cmp_result = call_callback(self, callback, &[a.clone(), b.clone()]);
-            match cmp_result {
-                Ok(Value::Integer(n)) => {
-                    if n < 0 {
-                        std::cmp::Ordering::Less
-                    } else if n > 0 {
-                        std::cmp::Ordering::Greater
-                    } else {
-                        std::cmp::Ordering::Equal
-                    }
-                }
-                Ok(Value::Float(f)) => {
-                    if f < 0.0 {
-                        std::cmp::Ordering::Less
-                    } else if f > 0.0 {
-                        std::cmp::Ordering::Greater
-                    } else {
-                        std::cmp::Ordering::Equal
-                    }
-                }
-                _ => std::cmp::Ordering::Equal,
+    /// `usort()`: sort the values with the callback, renumbering them
+    pub(crate) fn usort(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        self.sort_with_callback("usort", args, |mut entries| {
+            for (i, (key, _)) in entries.iter_mut().enumerate() {
+                *key = ArrayKey::Integer(i as i64);
             }
-        });
-
-        let result_array: Vec<(ArrayKey, Value)> = values
-            .into_iter()
-            .enumerate()
-            .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
-            .collect();
-        Ok(Value::Array(result_array))
+            entries
+        })
     }
 
-    /// uasort - Sort an array by values using a user-defined comparison function, preserving keys
-    ///
-    /// Sorts array by its values using a comparison function while preserving keys.
-    /// Returns true on success, false on failure.
-    pub fn uasort(&mut self, args: &[Value]) -> Result<Value, String> {
-        if args.len() < 2 {
-            return Err("uasort() expects at least 2 parameters".to_string());
-        }
-
-        let array = match &args[0] {
-            Value::Array(arr) => arr,
-            _ => return Err("uasort() expects parameter 1 to be array".to_string()),
-        };
-
-        let callback = &args[1];
-
-        if !is_callable(callback) {
-            return Err("uasort() expects parameter 2 to be a valid callback".to_string());
-        }
-
-        let mut pairs: Vec<(ArrayKey, Value)> =
-            array.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
-
-        pairs.sort_by(|(_, a), (_, b)| {
-            let cmp_result = call_callback(self, callback, &[a.clone(), b.clone()]);
-            match cmp_result {
-                Ok(Value::Integer(n)) => {
-                    if n < 0 {
-                        std::cmp::Ordering::Less
-                    } else if n > 0 {
-                        std::cmp::Ordering::Greater
-                    } else {
-                        std::cmp::Ordering::Equal
-                    }
-                }
-                Ok(Value::Float(f)) => {
-                    if f < 0.0 {
-                        std::cmp::Ordering::Less
-                    } else if f > 0.0 {
-                        std::cmp::Ordering::Greater
-                    } else {
-                        std::cmp::Ordering::Equal
-                    }
-                }
-                _ => std::cmp::Ordering::Equal,
-            }
-        });
-
-        Ok(Value::Array(pairs))
+    /// `uasort()`: sort the values with the callback, keeping their keys
+    pub(crate) fn uasort(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        self.sort_with_callback("uasort", args, |entries| entries)
     }
 
-    /// uksort - Sort an array by keys using a user-defined comparison function
-    ///
-    /// Sorts array by its keys using a comparison function.
-    /// Returns true on success, false on failure.
-    pub fn uksort(&mut self, args: &[Value]) -> Result<Value, String> {
-        if args.len() < 2 {
-            return Err("uksort() expects at least 2 parameters".to_string());
-        }
-
-        let array = match &args[0] {
-            Value::Array(arr) => arr,
-            _ => return Err("uksort() expects parameter 1 to be array".to_string()),
-        };
-
-        let callback = &args[1];
-
-        if !is_callable(callback) {
-            return Err("uksort() expects parameter 2 to be a valid callback".to_string());
-        }
-
-        let mut pairs: Vec<(ArrayKey, Value)> =
-            array.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    /// `uksort()`: sort by key with the callback
+    pub(crate) fn uksort(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        self.sort_with_callback("uksort", args, |entries| entries)
+    }
 
-        pairs.sort_by(|(a_key, _), (b_key, _)| {
-            let a_key_val = match a_key {
-                ArrayKey::Integer(n) => Value::Integer(*n),
-                ArrayKey::String(s) => Value::String(s.clone()),
-            };
-            let b_key_val = match b_key {
-                ArrayKey::Integer(n) => Value::Integer(*n),
-                ArrayKey::String(s) => Value::String(s.clone()),
+    /// Sort the array the first argument refers to with the comparison
+    /// callback, then `finish` the sorted entries
+    fn sort_with_callback(
+        &mut self,
+        name: &str,
+        args: &[Value],
+        finish: impl FnOnce(Vec<(ArrayKey, Value)>) -> Vec<(ArrayKey, Value)>,
+    ) -> Result<Value, VmError> {
+        native::check_arity(name, args.len(), 2, Some(2))?;
+        let array: Array = native::arg(name, &[args[0].clone().unref()], 0, "array")?;
+        let callback = self.callback_argument(name, 2, &args[1])?;
+        let by_key = name == "uksort";
+
+        // The callback can't fail the sort midway, so the first error
+        // stops further calls and is given once the sort is over
+        let mut error = None;
+        let mut entries = array.into_vec();
+        entries.sort_by(|(a_key, a), (b_key, b)| {
+            if error.is_some() {
+                return Ordering::Equal;
+            }
+            let pair = match by_key {
+                true => [a_key.to_value(), b_key.to_value()],
+                false => [a.clone(), b.clone()],
             };
-
-            let cmp_result = call_callback(self, callback, &[a_key_val, b_key_val]);
-            match cmp_result {
-                Ok(Value::Integer(n)) => {
-                    if n < 0 {
-                        std::cmp::Ordering::Less
-                    } else if n > 0 {
-                        std::cmp::Ordering::Greater
-                    } else {
-                        std::cmp::Ordering::Equal
-                    }
+            match self.call_callable(&callback, &pair) {
+                Ok(order) => order
+                    .to_float()
+                    .partial_cmp(&0.0)
+                    .unwrap_or(Ordering::Equal),
+                Err(e) => {
+                    error = Some(e);
+                    Ordering::Equal
                 }
-                Ok(Value::Float(f)) => {
-                    if f < 0.0 {
-                        std::cmp::Ordering::Less
-                    } else if f > 0.0 {
-                        std::cmp::Ordering::Greater
-                    } else {
-                        std::cmp::Ordering::Equal
-                    }
-                }
-                _ => std::cmp::Ordering::Equal,
             }
         });
-
-        Ok(Value::Array(pairs))
+        if let Some(error) = error {
+            return Err(error);
+        }
+        if let Value::Reference(array) = &args[0] {
+            array.set(Value::Array(finish(entries).into()));
+        }
+        Ok(Value::Bool(true))
     }
 }
@@ -4,17 +4,27 @@
 //! methods, and closures from the autoloader context.
 
 use crate::runtime::{Closure, Value};
-use crate::vm::frame::CallFrame;
+use crate::vm::error::VmError;
+use crate::vm::execution::ControlFlow;
+use crate::vm::frame::{CallFrame, Completion};
 
 use super::VM;
 
 impl<W: std::io::Write> VM<W> {
     /// Call a named function with arguments (used by autoloader)
     /// Returns the function result or an error
-    pub fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value, String> {
+    pub fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value, VmError> {
         let normalized = name.trim_start_matches('\\').to_string();
 
         if let Some(func) = self.get_function(&normalized) {
+            if func.is_generator {
+                let mut frame = CallFrame::new(func.clone(), self.stack.len());
+                for (slot, arg) in frame.locals.iter_mut().zip(args) {
+                    *slot = arg.clone();
+                }
+                return Ok(super::ops::create_generator(self, frame));
+            }
+
             for arg in args.iter().rev() {
                 self.stack.push(arg.clone());
             }
@@ -28,19 +38,19 @@ impl<W: std::io::Write> VM<W> {
                 }
             }
 
-            self.frames.push(frame);
-            let result = self.execute_function()?;
-            self.frames.pop();
+            self.push_frame(frame);
+            let result = self.run_nested(Self::execute_function)?;
+            self.pop_frame();
 
             Ok(result)
         } else {
-            Err(format!("Function '{}' not found", name))
+            Err(VmError::fatal(format!("Function '{}' not found", name)))
         }
     }
 
     /// Call a closure with arguments (used by autoloader)
     /// Returns the closure result or an error
-    pub fn call_closure(&mut self, closure: &Closure, args: &[Value]) -> Result<Value, String> {
+    pub fn call_closure(&mut self, closure: &Closure, args: &[Value]) -> Result<Value, VmError> {
         let func_name = match &closure.body {
             crate::runtime::ClosureBody::FunctionRef(name) => name.clone(),
             crate::runtime::ClosureBody::MethodRef {
@@ -53,7 +63,9 @@ impl<W: std::io::Write> VM<W> {
                 method_name,
             } => format!("{}::{}", class_name, method_name),
             crate::runtime::ClosureBody::Expression(_) => {
-                return Err("Expression closures not yet supported in autoloader".to_string());
+                return Err(VmError::fatal(
+                    "Expression closures not yet supported in autoloader",
+                ));
             }
         };
 
@@ -87,20 +99,26 @@ impl<W: std::io::Write> VM<W> {
                 }
             }
 
-            self.frames.push(frame);
-            let result = self.execute_function()?;
-            self.frames.pop();
+            self.push_frame(frame);
+            let result = self.run_nested(Self::execute_function)?;
+            self.pop_frame();
 
             Ok(result)
         } else {
-            Err(format!("Closure function '{}' not found", func_name))
+            Err(VmError::fatal(format!(
+                "Closure function '{}' not found",
+                func_name
+            )))
         }
     }
 
     /// Execute the current top frame's function
-    fn execute_function(&mut self) -> Result<Value, String> {
+    fn execute_function(&mut self) -> Result<Value, VmError> {
         loop {
-            let frame = self.frames.last_mut().expect("No call frame");
+            let frame = self
+                .frames
+                .last_mut()
+                .ok_or_else(|| VmError::fatal("No call frame available"))?;
             let ip = frame.ip;
 
             if ip >= frame.function.bytecode.len() {
@@ -110,13 +128,13 @@ impl<W: std::io::Write> VM<W> {
             let opcode = frame.function.bytecode[frame.ip].clone();
             frame.ip += 1;
 
-            match self.execute_opcode(opcode) {
-                Ok(()) => {}
-                Err(e) => {
-                    if e.starts_with("__RETURN__") {
-                        return Ok(Value::Null);
-                    }
-                    return Err(e);
+            match self.execute_opcode(opcode)? {
+                ControlFlow::Next => {}
+                ControlFlow::Return => return Ok(Value::Null),
+                ControlFlow::Break => super::ops::execute_loop_jump(self, Completion::Break)?,
+                ControlFlow::Continue => super::ops::execute_loop_jump(self, Completion::Continue)?,
+                flow @ (ControlFlow::Yield | ControlFlow::Suspend) => {
+                    return Err(flow.outside_its_loop())
                 }
             }
         }
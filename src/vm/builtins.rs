@@ -1,247 +1,85 @@
 //! Built-in function bridge for the VM
 //!
-//! This module provides a bridge between the VM and the runtime's
-//! built-in function implementations.
+//! Every `Registry` starts out with the core built-ins (`register_core`),
+//! so the VM looks functions up in one place, and an extension can
+//! replace a core function by registering its own under the name. The
+//! functions that need the VM, like the ones taking a callback or reading
+//! the filesystem, are run by the VM and listed in `VM_FUNCTIONS`;
+//! `BUILTIN_FUNCTIONS` names them all, for the compiler.
+
+mod names;
+
+pub use names::{BUILTIN_FUNCTIONS, PHPTOKEN_FUNCTIONS, VM_FUNCTIONS};
 
 use crate::runtime::builtins;
+use crate::runtime::filesystem::Filesystem;
 use crate::runtime::Value;
+use crate::vm::error::NativeError;
+use crate::vm::extension::Registry;
 use std::io::Write;
 
-/// List of all built-in function names (lowercase for case-insensitive matching)
-pub const BUILTIN_FUNCTIONS: &[&str] = &[
-    // String functions
-    "strlen",
-    "substr",
-    "strtoupper",
-    "strtolower",
-    "trim",
-    "ltrim",
-    "rtrim",
-    "str_repeat",
-    "str_replace",
-    "strpos",
-    "str_contains",
-    "str_starts_with",
-    "str_ends_with",
-    "ucfirst",
-    "lcfirst",
-    "ucwords",
-    "strrev",
-    "str_pad",
-    "explode",
-    "implode",
-    "join",
-    "sprintf",
-    "chr",
-    "ord",
-    "htmlspecialchars",
-    "htmlentities",
-    "nl2br",
-    "number_format",
-    "md5",
-    "sha1",
-    "bin2hex",
-    "hex2bin",
-    "levenshtein",
-    "similar_text",
-    "strtr",
-    // JSON functions
-    "json_encode",
-    "json_decode",
-    // File I/O functions
-    "file_get_contents",
-    "file_put_contents",
-    "file_exists",
-    "is_file",
-    "is_dir",
-    "filemtime",
-    "filesize",
-    "unlink",
-    "is_readable",
-    "is_writable",
-    "exit",
-    "die",
-    // Date/Time functions
-    "time",
-    "mktime",
-    "strtotime",
-    "gmdate",
-    "gmstrftime",
-    // Math functions
-    "abs",
-    "ceil",
-    "floor",
-    "round",
-    "max",
-    "min",
-    "pow",
-    "sqrt",
-    "rand",
-    "mt_rand",
-    "sin",
-    "cos",
-    "tan",
-    "log10",
-    "exp",
-    "pi",
-    "deg2rad",
-    "rad2deg",
-    "asin",
-    "acos",
-    "atan",
-    "atan2",
-    "sinh",
-    "cosh",
-    "tanh",
-    "hypot",
-    "fmod",
-    "intdiv",
-    "is_finite",
-    "is_infinite",
-    "is_nan",
-    "bindec",
-    "decbin",
-    "decoct",
-    "dechex",
-    "hexdec",
-    "octdec",
-    "base_convert",
-    "getrandmax",
-    "mt_getrandmax",
-    "lcg_value",
-    // Type functions
-    "intval",
-    "floatval",
-    "doubleval",
-    "strval",
-    "boolval",
-    "gettype",
-    "is_null",
-    "is_bool",
-    "is_int",
-    "is_integer",
-    "is_long",
-    "is_float",
-    "is_double",
-    "is_real",
-    "is_string",
-    "is_array",
-    "is_numeric",
-    "is_callable",
-    "isset",
-    "empty",
-    "unset",
-    "get_class",
-    "get_parent_class",
-    "get_class_methods",
-    "get_class_vars",
-    "get_object_vars",
-    "method_exists",
-    "property_exists",
-    "class_exists",
-    "interface_exists",
-    "trait_exists",
-    "is_a",
-    "is_subclass_of",
-    "get_declared_classes",
-    "get_declared_interfaces",
-    "get_declared_traits",
-    "class_alias",
-    "get_defined_functions",
-    "func_num_args",
-    "func_get_arg",
-    "func_get_args",
-    // Array functions
-    "count",
-    "sizeof",
-    "array_push",
-    "array_pop",
-    "array_shift",
-    "array_unshift",
-    "array_keys",
-    "array_values",
-    "in_array",
-    "array_search",
-    "array_reverse",
-    "array_merge",
-    "array_key_exists",
-    "range",
-    "array_first",
-    "array_last",
-    "array_map",
-    "array_filter",
-    "array_reduce",
-    "array_slice",
-    "array_sum",
-    "array_unique",
-    "array_fill",
-    "array_fill_keys",
-    "array_combine",
-    "array_chunk",
-    "array_pad",
-    "array_splice",
-    "array_diff",
-    "array_intersect",
-    "array_column",
-    "array_flip",
-    "array_count_values",
-    "sort",
-    "rsort",
-    "asort",
-    "arsort",
-    "ksort",
-    "krsort",
-    "shuffle",
-    "array_rand",
-    // SPL autoload functions
-    "spl_autoload_register",
-    "spl_autoload_unregister",
-    "spl_autoload_functions",
-    "spl_autoload_register_psr4",
-    "spl_autoload_registered_psr4",
-    "load_psr4_class",
-    "set_include_path",
-    "get_include_path",
-    // Dynamic code execution
-    "eval",
-    // PCRE functions
-    "preg_match",
-    "preg_match_all",
-    "preg_replace",
-    "preg_replace_callback",
-    "preg_split",
-    "preg_grep",
-    "preg_quote",
-    // File inclusion functions
-    "require",
-    "require_once",
-    // Output functions (handled separately since they need writer)
-    "print",
-    "var_dump",
-    "print_r",
-    "printf",
-    // Reflection functions (handled in VM)
-    "get_class_attributes",
-    "get_property_attributes",
-    "get_method_attributes",
-    "get_method_parameter_attributes",
-    "get_function_attributes",
-    "get_parameter_attributes",
-    "get_interface_attributes",
-    "get_trait_attributes",
-];
-
 /// Check if a function name is a built-in function
 pub fn is_builtin(name: &str) -> bool {
     let lower = name.to_lowercase();
-    BUILTIN_FUNCTIONS.contains(&lower.as_str())
+    BUILTIN_FUNCTIONS.contains(&lower.as_str()) || builtins::native::declared(&lower).is_some()
 }
 
-/// Call a built-in function with the given arguments
-/// Returns the result value or an error message
-pub fn call_builtin<W: Write>(name: &str, args: &[Value], output: &mut W) -> Result<Value, String> {
-    let lower_name = name.to_lowercase();
-    match lower_name.as_str() {
+/// The by-reference parameters of built-in functions, by position. The
+/// function gets a reference there to write through.
+const REF_PARAMS: &[(&str, usize)] = &[
+    ("preg_match", 2),
+    ("preg_match_all", 2),
+    ("usort", 0),
+    ("uasort", 0),
+    ("uksort", 0),
+];
+
+/// Whether parameter `pos` of the built-in function `name` takes a reference
+pub fn takes_ref(name: &str, pos: usize) -> bool {
+    let lower = name.to_lowercase();
+    REF_PARAMS.contains(&(lower.as_str(), pos))
+}
+
+/// Add the core built-ins to `registry`
+pub(crate) fn register_core(registry: &mut Registry) {
+    let core = BUILTIN_FUNCTIONS.iter().chain(PHPTOKEN_FUNCTIONS);
+    for &name in core.filter(|name| !VM_FUNCTIONS.contains(name)) {
+        registry.add_function(name, move |args, mut output| {
+            call_core(name, args, &mut output)
+        });
+    }
+    for builtin in builtins::DECLARED_BUILTINS {
+        registry.add_builtin(builtin);
+    }
+}
+
+/// Call the built-in file function `name` on `filesystem`, or return None
+/// if it isn't one
+pub(crate) fn call_file_function(
+    name: &str,
+    args: &[Value],
+    filesystem: &mut dyn Filesystem,
+) -> Option<Result<Value, NativeError>> {
+    let result = match name {
+        "file_get_contents" => builtins::fileio::file_get_contents(args, filesystem),
+        "file_put_contents" => builtins::fileio::file_put_contents(args, filesystem),
+        "file_exists" => builtins::fileio::file_exists(args, filesystem),
+        "is_file" => builtins::fileio::is_file(args, filesystem),
+        "is_dir" => builtins::fileio::is_dir(args, filesystem),
+        "filemtime" => builtins::fileio::filemtime(args, filesystem),
+        "filesize" => builtins::fileio::filesize(args, filesystem),
+        "unlink" => builtins::fileio::unlink(args, filesystem),
+        "is_readable" => builtins::fileio::is_readable(args, filesystem),
+        "is_writable" => builtins::fileio::is_writable(args, filesystem),
+        "yaml_emit_file" => builtins::yaml::yaml_emit_file(args, filesystem),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Call the core built-in `name` that needs nothing but the output
+fn call_core<W: Write>(name: &str, args: &[Value], output: &mut W) -> Result<Value, NativeError> {
+    match name {
         // String functions
         "strlen" => builtins::string::strlen(args),
         "substr" => builtins::string::substr(args),
@@ -264,8 +102,8 @@ pub fn call_builtin<W: Write>(name: &str, args: &[Value], output: &mut W) -> Res
         "explode" => builtins::string::explode(args),
         "implode" | "join" => builtins::string::implode(args),
         "sprintf" => builtins::string::sprintf(args),
-        "chr" => builtins::string::chr(args),
-        "ord" => builtins::string::ord(args),
+        "chr" => builtins::string_extra::chr(args),
+        "ord" => builtins::string_extra::ord(args),
         "htmlspecialchars" => builtins::string_extra::htmlspecialchars(args),
         "htmlentities" => builtins::string_extra::htmlentities(args),
         "nl2br" => builtins::string_extra::nl2br(args),
@@ -282,21 +120,25 @@ pub fn call_builtin<W: Write>(name: &str, args: &[Value], output: &mut W) -> Res
         "json_encode" => builtins::json_encode(args),
         "json_decode" => builtins::json_decode(args),
 
-        // File I/O functions
-        "file_get_contents" => builtins::fileio::file_get_contents(args),
-        "file_put_contents" => builtins::fileio::file_put_contents(args, output),
-        "file_exists" => builtins::fileio::file_exists(args),
-        "is_file" => builtins::fileio::is_file(args),
-        "is_dir" => builtins::fileio::is_dir(args),
-        "filemtime" => builtins::fileio::filemtime(args),
-        "filesize" => builtins::fileio::filesize(args),
-        "unlink" => builtins::fileio::unlink(args),
-        "is_readable" => builtins::fileio::is_readable(args),
-        "is_writable" => builtins::fileio::is_writable(args),
+        // YAML functions
+        "yaml_emit" => builtins::yaml::yaml_emit(args),
 
-        // Output functions (need writer)
-        "exit" => builtins::output::exit(output, args),
-        "die" => builtins::output::die(output, args),
+        // Encoding conversion
+        "mb_convert_encoding" => builtins::encoding::mb_convert_encoding(args),
+
+        // Intl functions
+        "numfmt_create" => builtins::intl::numfmt_create(args),
+        "numfmt_format" => builtins::intl::numfmt_format(args),
+        "numfmt_format_currency" => builtins::intl::numfmt_format_currency(args),
+        "numfmt_parse" => builtins::intl::numfmt_parse(args),
+        "numfmt_get_locale" => builtins::intl::numfmt_get_locale(args),
+        "numfmt_get_attribute" => builtins::intl::numfmt_get_attribute(args),
+        "collator_create" => builtins::collator::collator_create(args),
+        "collator_compare" => builtins::collator::collator_compare(args),
+        "collator_sort" => builtins::collator::collator_sort(args),
+        "collator_asort" => builtins::collator::collator_asort(args),
+        "collator_get_locale" => builtins::collator::collator_get_locale(args),
+        "collator_get_strength" => builtins::collator::collator_get_strength(args),
 
         // Date/Time functions
         "time" => builtins::datetime::time(args),
@@ -305,6 +147,21 @@ pub fn call_builtin<W: Write>(name: &str, args: &[Value], output: &mut W) -> Res
         "gmdate" => builtins::datetime::gmdate(args),
         "gmstrftime" => builtins::datetime::gmstrftime(args),
 
+        // Locale functions
+        "setlocale" => builtins::locale::setlocale(args),
+        "localeconv" => builtins::locale::localeconv(args),
+        "strcoll" => builtins::locale::strcoll(args),
+
+        // Tokenizer functions, and the PhpToken methods'
+        "token_get_all" => builtins::tokenizer::token_get_all(args),
+        "token_name" => builtins::tokenizer::token_name(args),
+        "phptoken_create" => builtins::tokenizer::phptoken_create(args),
+        "phptoken_tokenize" => builtins::tokenizer::phptoken_tokenize(args),
+        "phptoken_is" => builtins::tokenizer::phptoken_is(args),
+        "phptoken_is_ignorable" => builtins::tokenizer::phptoken_is_ignorable(args),
+        "phptoken_get_token_name" => builtins::tokenizer::phptoken_get_token_name(args),
+        "phptoken_to_string" => builtins::tokenizer::phptoken_to_string(args),
+
         // Math functions
         "abs" => builtins::math::abs(args),
         "ceil" => builtins::math::ceil(args),
@@ -362,19 +219,14 @@ pub fn call_builtin<W: Write>(name: &str, args: &[Value], output: &mut W) -> Res
         "is_string" => builtins::types::is_string(args),
         "is_array" => builtins::types::is_array(args),
         "is_numeric" => builtins::types::is_numeric(args),
-        "is_callable" => builtins::types::is_callable(args),
         "isset" => builtins::types::isset(args),
         "empty" => builtins::types::empty(args),
         "unset" => builtins::types::unset(args),
         "get_class" => builtins::type_extra::get_class(args),
-        "get_parent_class" => builtins::type_extra::get_parent_class(args),
-        "get_class_methods" => builtins::type_extra::get_class_methods(args),
         "get_class_vars" => builtins::type_extra::get_class_vars(args),
-        "get_object_vars" => builtins::type_extra::get_object_vars(args),
-        "method_exists" => builtins::type_extra::method_exists(args),
-        "property_exists" => builtins::type_extra::property_exists(args),
-        "class_exists" => builtins::type_extra::class_exists(args),
-        // interface_exists and trait_exists handled by VM in call_reflection_or_builtin
+        // The *_exists functions and the introspection functions
+        // (get_object_vars, method_exists, ...) are handled by the VM in
+        // call_reflection_or_builtin
         "is_a" => builtins::type_extra::is_a(args),
         "is_subclass_of" => builtins::type_extra::is_subclass_of(args),
         "get_declared_classes" => builtins::type_extra::get_declared_classes(args),
@@ -402,9 +254,6 @@ pub fn call_builtin<W: Write>(name: &str, args: &[Value], output: &mut W) -> Res
         "range" => builtins::array::range(args),
         "array_first" => builtins::array::array_first(args),
         "array_last" => builtins::array::array_last(args),
-        "array_map" => Ok(builtins::array::array_map(args)?),
-        "array_filter" => Ok(builtins::array::array_filter(args)?),
-        "array_reduce" => builtins::array::array_reduce(args),
         "array_slice" => builtins::array::array_slice(args),
         "array_sum" => builtins::array::array_sum(args),
         "array_unique" => builtins::array::array_unique(args),
@@ -436,9 +285,21 @@ pub fn call_builtin<W: Write>(name: &str, args: &[Value], output: &mut W) -> Res
         "spl_autoload_functions" => builtins::spl::spl_autoload_functions(args),
         "spl_autoload_register_psr4" => builtins::spl::spl_autoload_register_psr4(args),
         "spl_autoload_registered_psr4" => builtins::spl::spl_autoload_registered_psr4(args),
+        "spl_object_id" => builtins::spl::spl_object_id(args),
+        "spl_object_hash" => builtins::spl::spl_object_hash(args),
         "set_include_path" => builtins::spl::set_include_path(args),
         "get_include_path" => builtins::spl::get_include_path(args),
 
+        // Environment functions
+        "getenv" => builtins::env::getenv(args),
+        "putenv" => builtins::env::putenv(args),
+
+        // INI configuration functions
+        "ini_get" => builtins::ini::ini_get(args),
+        "ini_set" => builtins::ini::ini_set(args),
+        "ini_restore" => builtins::ini::ini_restore(args),
+        "error_reporting" => builtins::ini::error_reporting(args),
+
         // PCRE functions
         "preg_match" => Ok(builtins::pcre::preg_match(&args)?),
         "preg_match_all" => Ok(builtins::pcre::preg_match_all(&args)?),
@@ -452,8 +313,14 @@ pub fn call_builtin<W: Write>(name: &str, args: &[Value], output: &mut W) -> Res
         "print" => builtins::output::print(output, args),
         "var_dump" => builtins::output::var_dump(output, args),
         "print_r" => builtins::output::print_r(output, args),
+        "highlight_string" => builtins::highlight::highlight_string(output, args),
+        "var_export" => builtins::export::var_export(output, args),
         "printf" => builtins::output::printf(output, args),
 
-        _ => Err(format!("Unknown built-in function: {}", name)),
+        // The VM runs the others itself
+        _ => Err(NativeError::fatal(format!(
+            "Unknown built-in function: {}",
+            name
+        ))),
     }
 }
@@ -0,0 +1,395 @@
+//! The names of the core built-in functions
+//!
+//! Functions declared with `#[vhp_builtin]` aren't listed here: they are
+//! found in the native function table instead.
+
+/// List of all built-in function names (lowercase for case-insensitive matching)
+pub const BUILTIN_FUNCTIONS: &[&str] = &[
+    // String functions
+    "strlen",
+    "substr",
+    "strtoupper",
+    "strtolower",
+    "trim",
+    "ltrim",
+    "rtrim",
+    "str_repeat",
+    "str_replace",
+    "strpos",
+    "str_contains",
+    "str_starts_with",
+    "str_ends_with",
+    "ucfirst",
+    "lcfirst",
+    "ucwords",
+    "strrev",
+    "str_pad",
+    "explode",
+    "implode",
+    "join",
+    "sprintf",
+    "chr",
+    "ord",
+    "htmlspecialchars",
+    "htmlentities",
+    "nl2br",
+    "number_format",
+    "md5",
+    "sha1",
+    "bin2hex",
+    "hex2bin",
+    "levenshtein",
+    "similar_text",
+    "strtr",
+    // JSON functions
+    "json_encode",
+    "json_decode",
+    // Encoding conversion (iconv handled in VM)
+    "iconv",
+    "mb_convert_encoding",
+    // Intl functions
+    "numfmt_create",
+    "numfmt_format",
+    "numfmt_format_currency",
+    "numfmt_parse",
+    "numfmt_get_locale",
+    "numfmt_get_attribute",
+    "collator_create",
+    "collator_compare",
+    "collator_sort",
+    "collator_asort",
+    "collator_get_locale",
+    "collator_get_strength",
+    // XML functions (handled in VM)
+    "simplexml_load_string",
+    "simplexml_load_file",
+    "simplexml_import_dom",
+    // YAML functions (parsing handled in VM)
+    "yaml_parse",
+    "yaml_parse_file",
+    "yaml_emit",
+    "yaml_emit_file",
+    // File I/O functions
+    "file_get_contents",
+    "file_put_contents",
+    "file_exists",
+    "is_file",
+    "is_dir",
+    "filemtime",
+    "filesize",
+    "unlink",
+    "is_readable",
+    "is_writable",
+    "exit",
+    "die",
+    // Date/Time functions
+    "time",
+    "mktime",
+    "strtotime",
+    "gmdate",
+    "gmstrftime",
+    // Locale functions
+    "setlocale",
+    "localeconv",
+    "strcoll",
+    // Tokenizer functions
+    "token_get_all",
+    "token_name",
+    // Math functions
+    "abs",
+    "ceil",
+    "floor",
+    "round",
+    "max",
+    "min",
+    "pow",
+    "sqrt",
+    "rand",
+    "mt_rand",
+    "sin",
+    "cos",
+    "tan",
+    "log10",
+    "exp",
+    "pi",
+    "deg2rad",
+    "rad2deg",
+    "asin",
+    "acos",
+    "atan",
+    "atan2",
+    "sinh",
+    "cosh",
+    "tanh",
+    "hypot",
+    "fmod",
+    "intdiv",
+    "is_finite",
+    "is_infinite",
+    "is_nan",
+    "bindec",
+    "decbin",
+    "decoct",
+    "dechex",
+    "hexdec",
+    "octdec",
+    "base_convert",
+    "getrandmax",
+    "mt_getrandmax",
+    "srand",
+    "mt_srand",
+    "lcg_value",
+    // Type functions
+    "intval",
+    "floatval",
+    "doubleval",
+    "strval",
+    "boolval",
+    "gettype",
+    "is_null",
+    "is_bool",
+    "is_int",
+    "is_integer",
+    "is_long",
+    "is_float",
+    "is_double",
+    "is_real",
+    "is_string",
+    "is_array",
+    "is_numeric",
+    "is_callable",
+    "isset",
+    "empty",
+    "unset",
+    "get_class",
+    "get_parent_class",
+    "get_class_methods",
+    "get_class_vars",
+    "get_object_vars",
+    "method_exists",
+    "property_exists",
+    "class_exists",
+    "interface_exists",
+    "trait_exists",
+    "enum_exists",
+    "function_exists",
+    "is_a",
+    "is_subclass_of",
+    "class_implements",
+    "get_declared_classes",
+    "get_declared_interfaces",
+    "get_declared_traits",
+    "class_alias",
+    "get_defined_functions",
+    "func_num_args",
+    "func_get_arg",
+    "func_get_args",
+    // Array functions
+    "count",
+    "sizeof",
+    "array_push",
+    "array_pop",
+    "array_shift",
+    "array_unshift",
+    "array_keys",
+    "array_values",
+    "in_array",
+    "array_search",
+    "array_reverse",
+    "array_merge",
+    "array_key_exists",
+    "range",
+    "array_first",
+    "array_last",
+    "array_map",
+    "array_filter",
+    "array_reduce",
+    "usort",
+    "uasort",
+    "uksort",
+    "array_slice",
+    "array_sum",
+    "array_unique",
+    "array_fill",
+    "array_fill_keys",
+    "array_combine",
+    "array_chunk",
+    "array_pad",
+    "array_splice",
+    "array_diff",
+    "array_intersect",
+    "array_column",
+    "array_flip",
+    "array_count_values",
+    "sort",
+    "rsort",
+    "asort",
+    "arsort",
+    "ksort",
+    "krsort",
+    "shuffle",
+    "array_rand",
+    // SPL autoload functions
+    "spl_autoload_register",
+    "spl_autoload_unregister",
+    "spl_autoload_functions",
+    "spl_autoload_register_psr4",
+    "spl_autoload_registered_psr4",
+    "spl_object_id",
+    "spl_object_hash",
+    "load_psr4_class",
+    "set_include_path",
+    "get_include_path",
+    // Environment functions
+    "getenv",
+    "putenv",
+    // INI configuration functions
+    "ini_get",
+    "ini_set",
+    "ini_restore",
+    "error_reporting",
+    // INI parsing (handled in VM)
+    "parse_ini_string",
+    "parse_ini_file",
+    // Time limits (handled in VM)
+    "set_time_limit",
+    // Runtime counters (handled in VM)
+    "vhp_engine_stats",
+    // Shutdown functions and the last error (handled in VM)
+    "register_shutdown_function",
+    "error_get_last",
+    "error_clear_last",
+    // Event loop tasks (handled in VM)
+    "async",
+    // The HTTP response (handled in VM)
+    "header",
+    "header_remove",
+    "headers_list",
+    "http_response_code",
+    // Dynamic code execution
+    "eval",
+    // Constants (handled in VM)
+    "constant",
+    "defined",
+    // PCRE functions
+    "preg_match",
+    "preg_match_all",
+    "preg_replace",
+    "preg_replace_callback",
+    "preg_split",
+    "preg_grep",
+    "preg_quote",
+    // File inclusion functions
+    "require",
+    "require_once",
+    // Output functions (handled separately since they need writer)
+    "print",
+    "var_dump",
+    "print_r",
+    "var_export",
+    "printf",
+    // Syntax highlighting (highlight_file handled in VM)
+    "highlight_string",
+    "highlight_file",
+    "show_source",
+    // Reflection functions (handled in VM)
+    "get_class_attributes",
+    "get_property_attributes",
+    "get_method_attributes",
+    "get_method_parameter_attributes",
+    "get_function_attributes",
+    "get_parameter_attributes",
+    "get_interface_attributes",
+    "get_trait_attributes",
+];
+
+/// The functions the methods of `PhpToken` call with `$this` first. They
+/// are in the registry for the methods to find, but scripts don't call
+/// them.
+pub const PHPTOKEN_FUNCTIONS: &[&str] = &[
+    "phptoken_create",
+    "phptoken_tokenize",
+    "phptoken_is",
+    "phptoken_is_ignorable",
+    "phptoken_get_token_name",
+    "phptoken_to_string",
+];
+
+/// The built-in functions the VM runs itself, because they need its
+/// state or its filesystem. They aren't in the registry.
+pub const VM_FUNCTIONS: &[&str] = &[
+    // Reflection
+    "get_class_attributes",
+    "get_property_attributes",
+    "get_method_attributes",
+    "get_method_parameter_attributes",
+    "get_function_attributes",
+    "get_parameter_attributes",
+    "get_interface_attributes",
+    "get_trait_attributes",
+    "class_exists",
+    "interface_exists",
+    "trait_exists",
+    "enum_exists",
+    "function_exists",
+    "is_callable",
+    "get_object_vars",
+    "get_class_methods",
+    "method_exists",
+    "property_exists",
+    "get_parent_class",
+    "class_implements",
+    // Loading and running code
+    "require",
+    "require_once",
+    "load_psr4_class",
+    "eval",
+    "exit",
+    "die",
+    "constant",
+    "defined",
+    "set_time_limit",
+    "vhp_engine_stats",
+    "register_shutdown_function",
+    "async",
+    // Callbacks
+    "array_map",
+    "array_filter",
+    "array_reduce",
+    "usort",
+    "uasort",
+    "uksort",
+    // Errors and configuration
+    "error_get_last",
+    "error_clear_last",
+    "ini_set",
+    "ini_restore",
+    // HTTP headers
+    "header",
+    "header_remove",
+    "headers_list",
+    "http_response_code",
+    // Encodings and documents
+    "iconv",
+    "simplexml_load_string",
+    "simplexml_load_file",
+    "simplexml_import_dom",
+    "parse_ini_string",
+    "parse_ini_file",
+    "yaml_parse",
+    "yaml_parse_file",
+    "highlight_file",
+    "show_source",
+    // Files
+    "file_get_contents",
+    "file_put_contents",
+    "file_exists",
+    "is_file",
+    "is_dir",
+    "filemtime",
+    "filesize",
+    "unlink",
+    "is_readable",
+    "is_writable",
+    "yaml_emit_file",
+];
@@ -0,0 +1,401 @@
+//! The binary format of cached bytecode
+//!
+//! Compact and positional: integers are LEB128 varints (signed ones
+//! zigzagged first), strings and sequences are a length and then their
+//! elements, and an `Option` or an enum is a tag and then what it holds.
+//! Nothing names the fields, so a reader must be built from the same
+//! types as the writer; `SHAPE` is what the cache fingerprints to make
+//! sure of that (see `cache::fingerprint`).
+//!
+//! `#[derive(Codec)]` implements `Codec` field by field for the compiled
+//! code and the AST it keeps. Runtime values can only be encoded as far
+//! as they are plain data: a constant holding an object makes the file
+//! it comes from uncacheable, and it is compiled on every run instead.
+
+use crate::runtime::{ArrayKey, PhpArray, Value};
+use crate::vm::name_table::NameTable;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::Arc;
+
+pub use vhp_macros::Codec;
+
+/// Why a value can't be cached: the kind of value it is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unencodable(pub &'static str);
+
+/// A type that can be written to the cache and read back
+pub trait Codec: Sized {
+    /// The type's names and field types, for the format's fingerprint
+    const SHAPE: &'static str = "";
+
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable>;
+
+    /// None if the input ends early or doesn't fit the type
+    fn decode(input: &mut Decoder<'_>) -> Option<Self>;
+}
+
+/// The bytes being written
+#[derive(Debug, Default)]
+pub struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn varint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+
+    /// The variant of an enum
+    pub fn tag(&mut self, tag: u32) {
+        self.varint(tag as u64);
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.varint(bytes.len() as u64);
+        self.bytes.extend_from_slice(bytes);
+    }
+}
+
+/// The bytes being read
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Whether everything was read
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn varint(&mut self) -> Option<u64> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.bytes.split_first()?;
+            self.bytes = rest;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return Some(n);
+            }
+        }
+        None
+    }
+
+    pub fn tag(&mut self) -> Option<u32> {
+        self.varint()?.try_into().ok()
+    }
+
+    /// A length, which can't be more than the bytes left
+    fn length(&mut self) -> Option<usize> {
+        let length = self.varint()? as usize;
+        (length <= self.bytes.len()).then_some(length)
+    }
+
+    pub fn bytes(&mut self) -> Option<&'a [u8]> {
+        let length = self.length()?;
+        let (bytes, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Some(bytes)
+    }
+}
+
+macro_rules! unsigned {
+    ($($ty:ty),*) => {$(
+        impl Codec for $ty {
+            fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+                out.varint(*self as u64);
+                Ok(())
+            }
+
+            fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+                input.varint()?.try_into().ok()
+            }
+        }
+    )*};
+}
+
+unsigned!(u8, u16, u32, u64, usize);
+
+impl Codec for i64 {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        out.varint(((*self << 1) ^ (*self >> 63)) as u64);
+        Ok(())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        let n = input.varint()?;
+        Some((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+}
+
+impl Codec for bool {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        out.tag(*self as u32);
+        Ok(())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        match input.tag()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl Codec for f64 {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        out.bytes.extend_from_slice(&self.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        let bytes = input.bytes.get(..8)?.try_into().ok()?;
+        input.bytes = &input.bytes[8..];
+        Some(f64::from_bits(u64::from_le_bytes(bytes)))
+    }
+}
+
+impl Codec for String {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        out.bytes(self.as_bytes());
+        Ok(())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        String::from_utf8(input.bytes()?.to_vec()).ok()
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        match self {
+            None => out.tag(0),
+            Some(value) => {
+                out.tag(1);
+                value.encode(out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        match input.tag()? {
+            0 => Some(None),
+            1 => Some(Some(T::decode(input)?)),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Codec> Codec for Box<T> {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        (**self).encode(out)
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        T::decode(input).map(Box::new)
+    }
+}
+
+impl<T: Codec> Codec for Arc<T> {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        (**self).encode(out)
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        T::decode(input).map(Arc::new)
+    }
+}
+
+impl<A: Codec, B: Codec> Codec for (A, B) {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        self.0.encode(out)?;
+        self.1.encode(out)
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        Some((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+/// Encode the elements of a sequence after their count
+fn encode_all<'a, T: Codec + 'a>(
+    out: &mut Encoder,
+    count: usize,
+    mut items: impl Iterator<Item = &'a T>,
+) -> Result<(), Unencodable> {
+    out.varint(count as u64);
+    items.try_for_each(|item| item.encode(out))
+}
+
+/// Decode the elements of a sequence after their count
+fn decode_all<T: Codec, C: FromIterator<T>>(input: &mut Decoder<'_>) -> Option<C> {
+    // Every element takes at least a byte, which bounds the count
+    let count = input.length()?;
+    (0..count).map(|_| T::decode(input)).collect()
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        encode_all(out, self.len(), self.iter())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        decode_all(input)
+    }
+}
+
+impl<T: Codec + Eq + Hash> Codec for HashSet<T> {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        encode_all(out, self.len(), self.iter())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        decode_all(input)
+    }
+}
+
+impl<K: Codec + Eq + Hash, V: Codec> Codec for HashMap<K, V> {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        out.varint(self.len() as u64);
+        for (key, value) in self {
+            key.encode(out)?;
+            value.encode(out)?;
+        }
+        Ok(())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        decode_all::<(K, V), _>(input)
+    }
+}
+
+impl<T: Codec> Codec for NameTable<T> {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        out.varint(self.len() as u64);
+        for (name, value) in self.iter() {
+            name.encode(out)?;
+            value.encode(out)?;
+        }
+        Ok(())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        let entries: Vec<(String, T)> = decode_all(input)?;
+        let mut table = NameTable::new();
+        for (name, value) in entries {
+            table.insert(name, value);
+        }
+        Some(table)
+    }
+}
+
+impl Codec for ArrayKey {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        match self {
+            ArrayKey::Integer(n) => {
+                out.tag(0);
+                n.encode(out)
+            }
+            ArrayKey::String(s) => {
+                out.tag(1);
+                s.encode(out)
+            }
+        }
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        match input.tag()? {
+            0 => Some(ArrayKey::Integer(i64::decode(input)?)),
+            1 => Some(ArrayKey::String(String::decode(input)?)),
+            _ => None,
+        }
+    }
+}
+
+impl Codec for PhpArray {
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        encode_all(out, self.len(), self.iter())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        decode_all::<(ArrayKey, Value), _>(input)
+    }
+}
+
+/// The values a compiler evaluates constants and defaults to
+impl Codec for Value {
+    const SHAPE: &'static str = "Value{Null|Bool|Integer|Float|String|Array|EnumCase}";
+
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        match self {
+            Value::Null => out.tag(0),
+            Value::Bool(b) => {
+                out.tag(1);
+                b.encode(out)?;
+            }
+            Value::Integer(n) => {
+                out.tag(2);
+                n.encode(out)?;
+            }
+            Value::Float(f) => {
+                out.tag(3);
+                f.encode(out)?;
+            }
+            Value::String(s) => {
+                out.tag(4);
+                s.encode(out)?;
+            }
+            Value::Array(entries) => {
+                out.tag(5);
+                entries.encode(out)?;
+            }
+            Value::EnumCase {
+                enum_name,
+                case_name,
+                backing_value,
+            } => {
+                out.tag(6);
+                enum_name.encode(out)?;
+                case_name.encode(out)?;
+                backing_value.encode(out)?;
+            }
+            other => return Err(Unencodable(other.get_type())),
+        }
+        Ok(())
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        Some(match input.tag()? {
+            0 => Value::Null,
+            1 => Value::Bool(bool::decode(input)?),
+            2 => Value::Integer(i64::decode(input)?),
+            3 => Value::Float(f64::decode(input)?),
+            4 => Value::String(String::decode(input)?),
+            5 => Value::Array(PhpArray::decode(input)?),
+            6 => Value::EnumCase {
+                enum_name: String::decode(input)?,
+                case_name: String::decode(input)?,
+                backing_value: Option::decode(input)?,
+            },
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,337 @@
+//! Bytecode cache
+//!
+//! Lexing, parsing and compiling a file takes longer than loading what
+//! came out of it last time. With a cache directory (`--cache-dir`), the
+//! compilation of each file is kept in a file of its own, named after a
+//! hash of the file's path, and reused for as long as the source hashes
+//! the same. `vhp compile <dir>` fills the cache ahead of time.
+//!
+//! An entry starts with a fingerprint of the format, built from the
+//! `SHAPE` of every type it holds and the VHP version, so a build whose
+//! opcodes or AST differ never reads what another build wrote. Entries
+//! that don't match are compiled afresh and overwritten; a compilation
+//! that can't be encoded (see `codec`) is simply not cached.
+
+pub mod codec;
+
+use crate::ast::*;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::runtime::Value;
+use crate::vm::class::{
+    CompiledClass, CompiledEnum, CompiledInterface, CompiledProperty, CompiledTrait,
+};
+use crate::vm::compiler::{CompilationResult, CompileError, Compiler};
+use crate::vm::deprecation::{CompileDeprecation, Deprecation};
+use crate::vm::opcode::{CastType, CompiledFunction, Constant, Opcode};
+use codec::{Codec, Decoder, Encoder, Unencodable};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What every entry starts with
+const MAGIC: &[u8; 4] = b"VHPC";
+
+/// The extension of entries
+const EXTENSION: &str = "vhpc";
+
+/// A directory of compiled files
+#[derive(Debug, Clone)]
+pub struct BytecodeCache {
+    dir: PathBuf,
+}
+
+/// What `BytecodeCache::precompile` did with each file
+#[derive(Debug, Default)]
+pub struct Precompiled {
+    /// Files now in the cache
+    pub cached: Vec<PathBuf>,
+    /// Files compiled but holding values the cache can't store
+    pub uncacheable: Vec<(PathBuf, Unencodable)>,
+    /// Files that didn't compile, with the error
+    pub failed: Vec<(PathBuf, CompileError)>,
+}
+
+impl BytecodeCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Lex, parse and compile `source` as `crate::compile` does, unless
+    /// the cache has its compilation already; a fresh compilation is
+    /// added to the cache
+    ///
+    /// ```
+    /// use vhp::vm::cache::BytecodeCache;
+    ///
+    /// let dir = std::env::temp_dir().join(format!("vhp-cache-doc-{}", std::process::id()));
+    /// let cache = BytecodeCache::new(&dir);
+    /// let source = "<?php function twice($n) { return $n * 2; } echo twice(21);";
+    /// cache.compile(source, "/srv/app/index.php").unwrap();
+    /// assert!(cache.load("<main>", "/srv/app/index.php", source).is_some());
+    ///
+    /// let mut output = Vec::new();
+    /// vhp::exit_status({
+    ///     let mut vm = vhp::vm::VM::new(&mut output);
+    ///     let main = vm.load(cache.compile(source, "/srv/app/index.php").unwrap());
+    ///     vm.execute(main)
+    /// })
+    /// .unwrap();
+    /// assert_eq!(output, b"42");
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn compile(
+        &self,
+        source: &str,
+        file_path: &str,
+    ) -> Result<CompilationResult, CompileError> {
+        let name = "<main>";
+        if let Some(compilation) = self.load(name, file_path, source) {
+            return Ok(compilation);
+        }
+        let tokens = Lexer::new(source).tokenize()?;
+        let program = Parser::new(tokens).parse()?;
+        let compiler = Compiler::with_file_path(name.to_string(), file_path.to_string());
+        let compilation = compiler.compile_program(&program)?;
+        // A cache that can't be written only costs the time it saves
+        let _ = self.store(file_path, source, &compilation);
+        Ok(compilation)
+    }
+
+    /// The compilation of `source` at `file_path`, if the cache has it,
+    /// with its main code named `name`
+    ///
+    /// ```
+    /// use vhp::vm::cache::BytecodeCache;
+    ///
+    /// let dir = std::env::temp_dir().join(format!("vhp-cache-load-doc-{}", std::process::id()));
+    /// let cache = BytecodeCache::new(&dir);
+    /// let before = "<?php echo 1;";
+    /// let after = "<?php echo 2;";
+    /// assert!(cache.load("<main>", "/app/a.php", before).is_none());
+    ///
+    /// let compilation = vhp::compile(before, "/app/a.php").unwrap();
+    /// assert!(cache.store("/app/a.php", before, &compilation).unwrap());
+    /// let hit = cache.load("page", "/app/a.php", before).unwrap();
+    /// assert_eq!(hit.main.name, "page");
+    ///
+    /// // An edited file, or another file, misses
+    /// assert!(cache.load("<main>", "/app/a.php", after).is_none());
+    /// assert!(cache.load("<main>", "/app/b.php", before).is_none());
+    ///
+    /// // Compiling the edited file replaces the entry
+    /// cache.compile(after, "/app/a.php").unwrap();
+    /// assert!(cache.load("<main>", "/app/a.php", after).is_some());
+    /// assert!(cache.load("<main>", "/app/a.php", before).is_none());
+    ///
+    /// // And so does an entry cut short
+    /// let entry = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    /// let bytes = std::fs::read(&entry).unwrap();
+    /// std::fs::write(&entry, &bytes[..bytes.len() - 1]).unwrap();
+    /// assert!(cache.load("<main>", "/app/a.php", after).is_none());
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn load(&self, name: &str, file_path: &str, source: &str) -> Option<CompilationResult> {
+        let bytes = fs::read(self.entry(file_path)).ok()?;
+        let mut input = Decoder::new(bytes.strip_prefix(MAGIC)?);
+        let header = Header::decode(&mut input)?;
+        if header != Header::new(file_path, source) {
+            return None;
+        }
+        let mut compilation = CompilationResult::decode(&mut input)?;
+        if !input.is_empty() {
+            return None;
+        }
+        Arc::make_mut(&mut compilation.main).name = name.to_string();
+        Some(compilation)
+    }
+
+    /// Keep the compilation of `source` at `file_path`; false if it holds
+    /// values the cache can't store
+    pub fn store(
+        &self,
+        file_path: &str,
+        source: &str,
+        compilation: &CompilationResult,
+    ) -> io::Result<bool> {
+        let mut out = Encoder::new();
+        let encoded = Header::new(file_path, source)
+            .encode(&mut out)
+            .and_then(|()| compilation.encode(&mut out));
+        if encoded.is_err() {
+            return Ok(false);
+        }
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(out.into_bytes());
+
+        // Written aside and renamed, so that no one reads half an entry
+        fs::create_dir_all(&self.dir)?;
+        let entry = self.entry(file_path);
+        let partial = entry.with_extension(format!("{}.{}", EXTENSION, std::process::id()));
+        fs::write(&partial, bytes)?;
+        fs::rename(&partial, &entry)?;
+        Ok(true)
+    }
+
+    /// Compile the *.php files at or below `paths` into the cache
+    ///
+    /// ```
+    /// use vhp::vm::cache::BytecodeCache;
+    ///
+    /// let root = std::env::temp_dir().join(format!("vhp-precompile-doc-{}", std::process::id()));
+    /// std::fs::create_dir_all(root.join("src")).unwrap();
+    /// std::fs::write(root.join("src/ok.php"), "<?php echo 'ok';").unwrap();
+    /// std::fs::write(root.join("src/broken.php"), "<?php echo ;").unwrap();
+    ///
+    /// let cache = BytecodeCache::new(root.join("cache"));
+    /// let precompiled = cache.precompile(&[root.join("src")]).unwrap();
+    /// assert_eq!(precompiled.cached, [root.join("src/ok.php")]);
+    /// assert_eq!(precompiled.failed.len(), 1);
+    /// assert_eq!(precompiled.failed[0].0, root.join("src/broken.php"));
+    ///
+    /// let path = std::fs::canonicalize(root.join("src/ok.php")).unwrap();
+    /// let source = std::fs::read_to_string(&path).unwrap();
+    /// assert!(cache.load("<main>", &path.to_string_lossy(), &source).is_some());
+    /// # std::fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn precompile(&self, paths: &[PathBuf]) -> Result<Precompiled, String> {
+        let mut files = Vec::new();
+        for path in paths {
+            crate::linter::discover(path, &mut files)?;
+        }
+        let mut precompiled = Precompiled::default();
+        for path in files {
+            let source = fs::read_to_string(&path)
+                .map_err(|e| format!("Error reading file '{}': {}", path.display(), e))?;
+            let file_path = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            let file_path = file_path.to_string_lossy();
+            let compilation = match crate::compile(&source, &file_path) {
+                Ok(compilation) => compilation,
+                Err(error) => {
+                    precompiled.failed.push((path, error));
+                    continue;
+                }
+            };
+            let mut out = Encoder::new();
+            if let Err(unencodable) = compilation.encode(&mut out) {
+                precompiled.uncacheable.push((path, unencodable));
+                continue;
+            }
+            self.store(&file_path, &source, &compilation)
+                .map_err(|e| format!("Error writing to {}: {}", self.dir.display(), e))?;
+            precompiled.cached.push(path);
+        }
+        Ok(precompiled)
+    }
+
+    /// The entry of the file at `file_path`
+    fn entry(&self, file_path: &str) -> PathBuf {
+        let name = format!("{:016x}.{}", fnv1a(file_path.as_bytes()), EXTENSION);
+        self.dir.join(name)
+    }
+}
+
+/// What an entry was written for
+#[derive(Debug, PartialEq)]
+struct Header {
+    fingerprint: u64,
+    file_path: String,
+    source_length: usize,
+    source_hash: u64,
+}
+
+impl Header {
+    fn new(file_path: &str, source: &str) -> Self {
+        Self {
+            fingerprint: fingerprint(),
+            file_path: file_path.to_string(),
+            source_length: source.len(),
+            source_hash: fnv1a(source.as_bytes()),
+        }
+    }
+
+    fn encode(&self, out: &mut Encoder) -> Result<(), Unencodable> {
+        self.fingerprint.encode(out)?;
+        self.file_path.encode(out)?;
+        self.source_length.encode(out)?;
+        self.source_hash.encode(out)
+    }
+
+    fn decode(input: &mut Decoder<'_>) -> Option<Self> {
+        Some(Self {
+            fingerprint: u64::decode(input)?,
+            file_path: String::decode(input)?,
+            source_length: usize::decode(input)?,
+            source_hash: u64::decode(input)?,
+        })
+    }
+}
+
+/// The format of entries: the shapes of the types they hold and the VHP
+/// version
+fn fingerprint() -> u64 {
+    let shapes = [
+        CompilationResult::SHAPE,
+        CompiledFunction::SHAPE,
+        Opcode::SHAPE,
+        CastType::SHAPE,
+        Constant::SHAPE,
+        CompiledClass::SHAPE,
+        CompiledProperty::SHAPE,
+        CompiledInterface::SHAPE,
+        CompiledTrait::SHAPE,
+        CompiledEnum::SHAPE,
+        CompileDeprecation::SHAPE,
+        Deprecation::SHAPE,
+        Value::SHAPE,
+        Expr::SHAPE,
+        ArrayElement::SHAPE,
+        ListElement::SHAPE,
+        PropertyModification::SHAPE,
+        Argument::SHAPE,
+        MatchArm::SHAPE,
+        BinaryOp::SHAPE,
+        UnaryOp::SHAPE,
+        AssignOp::SHAPE,
+        Stmt::SHAPE,
+        DeclareDirective::SHAPE,
+        QualifiedName::SHAPE,
+        UseType::SHAPE,
+        UseItem::SHAPE,
+        GroupUse::SHAPE,
+        NamespaceBody::SHAPE,
+        TypeHint::SHAPE,
+        Visibility::SHAPE,
+        AttributeArgument::SHAPE,
+        Attribute::SHAPE,
+        PropertyHookType::SHAPE,
+        PropertyHookBody::SHAPE,
+        PropertyHook::SHAPE,
+        Property::SHAPE,
+        Method::SHAPE,
+        InterfaceMethodSignature::SHAPE,
+        InterfaceConstant::SHAPE,
+        EnumCase::SHAPE,
+        EnumBackingType::SHAPE,
+        TraitUse::SHAPE,
+        TraitResolution::SHAPE,
+        CatchClause::SHAPE,
+        ForeachTarget::SHAPE,
+        SwitchCase::SHAPE,
+        FunctionParam::SHAPE,
+        env!("CARGO_PKG_VERSION"),
+    ];
+    fnv1a(shapes.join("\n").as_bytes())
+}
+
+/// The 64-bit FNV-1a hash, which is the same on every platform and build
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,191 @@
+//! Callable values
+//!
+//! PHP calls a value as a function when it is a closure, the name of a
+//! function, a `"Class::method"` string, a `['Class', 'method']` or
+//! `[$object, 'method']` array, or an object with an `__invoke()` method.
+//! `resolve_callable()` works out what such a value calls, so that
+//! `is_callable()`, the `callable` type and calling the value agree on
+//! which values are callable, and `call_callable()` calls one for the
+//! built-ins that take a callback.
+
+use super::opcode::CompiledFunction;
+use super::{ops, VM};
+use crate::runtime::builtins::native;
+use crate::runtime::{Closure, Value};
+use crate::vm::error::VmError;
+use std::io::Write;
+use std::sync::Arc;
+
+/// What a callable value calls
+pub(crate) enum CallTarget {
+    /// A user function
+    Function(Arc<CompiledFunction>),
+    /// A built-in or extension function
+    Native(String),
+    /// A method, called with `object` as `$this`
+    Method {
+        object: Value,
+        method: Arc<CompiledFunction>,
+    },
+    /// A static method of `class_name`
+    StaticMethod {
+        class_name: String,
+        method: Arc<CompiledFunction>,
+    },
+    /// A closure, or a first-class callable
+    Closure(Box<Closure>),
+}
+
+impl<W: Write> VM<W> {
+    /// What `callable` calls, or the error calling it gives
+    pub(crate) fn resolve_callable(&self, callable: &Value) -> Result<CallTarget, VmError> {
+        match callable {
+            Value::String(name) => match name.split_once("::") {
+                Some((class_name, method_name)) => {
+                    self.resolve_static_callable(class_name, method_name)
+                }
+                None => self.resolve_function_callable(name),
+            },
+            Value::Array(elements) => {
+                let [(_, target), (_, method_name)] = elements.as_slice() else {
+                    return Err(VmError::fatal(
+                        "Array callback must have exactly two elements",
+                    ));
+                };
+                let Value::String(method_name) = method_name else {
+                    return Err(VmError::fatal("Second array member is not a valid method"));
+                };
+                match target {
+                    Value::String(class_name) => {
+                        self.resolve_static_callable(class_name, method_name)
+                    }
+                    Value::Object(instance) => self
+                        .find_method_in_chain(instance.class_name(), method_name)
+                        .map(|method| CallTarget::Method {
+                            object: target.clone(),
+                            method,
+                        })
+                        .ok_or_else(|| {
+                            VmError::error(format!(
+                                "Call to undefined method {}::{}()",
+                                instance.class_name(),
+                                method_name
+                            ))
+                        }),
+                    _ => Err(VmError::fatal(
+                        "First array member is not a valid class name or object",
+                    )),
+                }
+            }
+            Value::Closure(closure) => Ok(CallTarget::Closure(closure.clone())),
+            Value::Object(instance) => self
+                .find_method_in_chain(instance.class_name(), "__invoke")
+                .map(|method| CallTarget::Method {
+                    object: callable.clone(),
+                    method,
+                })
+                .ok_or_else(|| {
+                    VmError::fatal(format!(
+                        "Object of class {} is not callable",
+                        instance.class_name()
+                    ))
+                }),
+            _ => Err(VmError::fatal("Value not callable")),
+        }
+    }
+
+    /// Call `callable` with `args` and give what it returns, for the
+    /// built-ins that take a callback
+    pub(crate) fn call_callable(
+        &mut self,
+        callable: &Value,
+        args: &[Value],
+    ) -> Result<Value, VmError> {
+        let depth = self.frames.len();
+        self.stack.extend(args.iter().cloned());
+        self.stack.push(callable.clone());
+        ops::execute_call_callable(self, args.len() as u8)?;
+        if self.frames.len() > depth {
+            return self.run_nested(Self::run_frame);
+        }
+        self.stack.pop().ok_or_else(VmError::stack_underflow)
+    }
+
+    /// Whether `callable` can be called
+    pub(crate) fn is_callable_value(&self, callable: &Value) -> bool {
+        self.resolve_callable(callable).is_ok()
+    }
+
+    /// `is_callable()`: whether a value can be called, or with
+    /// `$syntax_only`, whether it has the form of a callable
+    pub(crate) fn is_callable(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        native::check_arity("is_callable", args.len(), 1, Some(2))?;
+        let callable = &args[0];
+        if args.get(1).is_some_and(Value::to_bool) {
+            let well_formed = match callable {
+                Value::String(_) | Value::Closure(_) => true,
+                Value::Array(elements) => matches!(
+                    elements.as_slice(),
+                    [
+                        (_, Value::String(_) | Value::Object(_)),
+                        (_, Value::String(_))
+                    ]
+                ),
+                _ => self.is_callable_value(callable),
+            };
+            return Ok(Value::Bool(well_formed));
+        }
+        self.autoload_callable_class(callable);
+        Ok(Value::Bool(self.is_callable_value(callable)))
+    }
+
+    /// Give the autoloaders a chance to declare the class a
+    /// `"Class::method"` string or a `['Class', 'method']` array names
+    pub(crate) fn autoload_callable_class(&mut self, callable: &Value) {
+        let class_name = match callable {
+            Value::String(name) => name.split_once("::").map(|(class_name, _)| class_name),
+            Value::Array(elements) => match elements.as_slice() {
+                [(_, Value::String(class_name)), _] => Some(class_name.as_str()),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(class_name) = class_name {
+            let class_name = Self::normalize_class_name(class_name);
+            self.get_class_with_autoload(&class_name);
+        }
+    }
+
+    fn resolve_function_callable(&self, name: &str) -> Result<CallTarget, VmError> {
+        let name = name.trim_start_matches('\\');
+        if let Some(function) = self.get_function(name) {
+            Ok(CallTarget::Function(function))
+        } else if self.is_native_function(name) {
+            Ok(CallTarget::Native(name.to_string()))
+        } else {
+            Err(VmError::undefined_function(name))
+        }
+    }
+
+    fn resolve_static_callable(
+        &self,
+        class_name: &str,
+        method_name: &str,
+    ) -> Result<CallTarget, VmError> {
+        let class_name = Self::normalize_class_name(&self.resolve_class_keyword(class_name)?);
+        if !self.classes.contains_key(&class_name) {
+            return Err(VmError::class_not_found(&class_name));
+        }
+        match self.find_static_method_in_chain(&class_name, method_name) {
+            Some((method, false)) => Ok(CallTarget::StaticMethod { class_name, method }),
+            Some((_, true)) => Err(VmError::fatal(format!(
+                "Non-static method {}::{}() cannot be called statically",
+                class_name, method_name
+            ))),
+            None => Err(VmError::error(format!(
+                "Call to undefined method {}::{}()",
+                class_name, method_name
+            ))),
+        }
+    }
+}
@@ -3,12 +3,13 @@
 //! This module defines the compiled representations of OOP constructs.
 
 use crate::ast::{Attribute, Property, TypeHint, Visibility};
+use crate::vm::cache::codec::Codec;
 use crate::vm::opcode::CompiledFunction;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Compiled class definition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 #[allow(dead_code)] // name and constants fields not yet used
 pub struct CompiledClass {
     pub name: String,
@@ -24,6 +25,10 @@ pub struct CompiledClass {
     pub static_properties: HashMap<String, crate::runtime::Value>,
     pub readonly_static_properties: std::collections::HashSet<String>,
     pub constants: HashMap<String, crate::runtime::Value>,
+    /// Names of the constants the class declares, in declaration order
+    pub constant_order: Vec<String>,
+    /// Names of the methods the class declares, in declaration order
+    pub method_order: Vec<String>,
     pub method_visibility: HashMap<String, Visibility>,
     pub method_finals: HashMap<String, bool>,
     pub method_abstracts: HashMap<String, bool>,
@@ -46,6 +51,8 @@ impl CompiledClass {
             static_properties: HashMap::new(),
             readonly_static_properties: std::collections::HashSet::new(),
             constants: HashMap::new(),
+            constant_order: Vec::new(),
+            method_order: Vec::new(),
             method_visibility: HashMap::new(),
             method_finals: HashMap::new(),
             method_abstracts: HashMap::new(),
@@ -65,8 +72,8 @@ impl CompiledClass {
 }
 
 /// Compiled property definition
-#[derive(Debug, Clone)]
-#[allow(dead_code)] // visibility and type_hint fields not yet used
+#[derive(Debug, Clone, Codec)]
+#[allow(dead_code)] // visibility field not yet used
 pub struct CompiledProperty {
     pub name: String,
     pub visibility: Visibility,
@@ -83,22 +90,12 @@ pub struct CompiledProperty {
 }
 
 impl CompiledProperty {
-    pub fn from_ast(prop: &Property, readonly_class: bool) -> Self {
-        // Try to evaluate simple default values at compile time
-        let default = prop.default.as_ref().and_then(|expr| {
-            use crate::ast::Expr;
-            match expr {
-                Expr::Integer(n) => Some(crate::runtime::Value::Integer(*n)),
-                Expr::Float(n) => Some(crate::runtime::Value::Float(*n)),
-                Expr::String(s) => Some(crate::runtime::Value::String(s.clone())),
-                Expr::Bool(b) => Some(crate::runtime::Value::Bool(*b)),
-                Expr::Null => Some(crate::runtime::Value::Null),
-                Expr::Array(elements) if elements.is_empty() => {
-                    Some(crate::runtime::Value::Array(Vec::new()))
-                }
-                _ => None, // Complex expressions need runtime evaluation
-            }
-        });
+    /// A property whose default, if it has one, evaluated to `default`
+    pub fn from_ast(
+        prop: &Property,
+        readonly_class: bool,
+        default: Option<crate::runtime::Value>,
+    ) -> Self {
         Self {
             name: prop.name.clone(),
             visibility: prop.visibility,
@@ -106,7 +103,7 @@ impl CompiledProperty {
             default,
             readonly: prop.readonly || readonly_class,
             is_static: prop.is_static,
-            type_hint: None, // Would need to be extracted from AST
+            type_hint: prop.type_hint.clone(),
             attributes: prop.attributes.clone(),
             get_hook: None, // Will be set by compiler if property has hooks
             set_hook: None,
@@ -115,13 +112,15 @@ impl CompiledProperty {
 }
 
 /// Compiled interface definition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 #[allow(dead_code)] // name and constants fields not yet used
 pub struct CompiledInterface {
     pub name: String,
     pub parents: Vec<String>,
     pub method_signatures: Vec<(String, u8)>, // (name, param_count)
     pub constants: HashMap<String, crate::runtime::Value>,
+    /// Names of the constants the interface declares, in declaration order
+    pub constant_order: Vec<String>,
     pub attributes: Vec<Attribute>,
 }
 
@@ -132,19 +131,22 @@ impl CompiledInterface {
             parents: Vec::new(),
             method_signatures: Vec::new(),
             constants: HashMap::new(),
+            constant_order: Vec::new(),
             attributes: Vec::new(),
         }
     }
 }
 
 /// Compiled trait definition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 #[allow(dead_code)] // name field not yet used
 pub struct CompiledTrait {
     pub name: String,
     pub uses: Vec<String>,
     pub properties: Vec<CompiledProperty>,
     pub methods: HashMap<String, Arc<CompiledFunction>>,
+    /// Names of the methods the trait declares, in declaration order
+    pub method_order: Vec<String>,
     pub attributes: Vec<Attribute>,
 }
 
@@ -155,21 +157,28 @@ impl CompiledTrait {
             uses: Vec::new(),
             properties: Vec::new(),
             methods: HashMap::new(),
+            method_order: Vec::new(),
             attributes: Vec::new(),
         }
     }
 }
 
 /// Compiled enum definition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 #[allow(dead_code)] // name and backing_type fields not yet used
 pub struct CompiledEnum {
     pub name: String,
     pub backing_type: crate::ast::EnumBackingType,
+    pub interfaces: Vec<String>,
     pub cases: HashMap<String, Option<crate::runtime::Value>>,
     pub case_order: Vec<String>, // Preserves insertion order for cases() method
+    pub constants: HashMap<String, crate::runtime::Value>,
+    /// Names of the constants the enum declares, in declaration order
+    pub constant_order: Vec<String>,
     pub methods: HashMap<String, Arc<CompiledFunction>>,
     pub static_methods: HashMap<String, Arc<CompiledFunction>>,
+    /// Names of the methods the enum declares, in declaration order
+    pub method_order: Vec<String>,
     pub attributes: Vec<Attribute>,
 }
 
@@ -178,10 +187,14 @@ impl CompiledEnum {
         Self {
             name,
             backing_type,
+            interfaces: Vec::new(),
             cases: HashMap::new(),
             case_order: Vec::new(),
+            constants: HashMap::new(),
+            constant_order: Vec::new(),
             methods: HashMap::new(),
             static_methods: HashMap::new(),
+            method_order: Vec::new(),
             attributes: Vec::new(),
         }
     }
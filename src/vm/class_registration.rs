@@ -1,21 +1,59 @@
-use crate::runtime::Value;
+use crate::runtime::{PhpArray, Value};
 use crate::vm::class::CompiledClass;
 use crate::vm::class::CompiledProperty;
+use crate::vm::name_table::NameTable;
 use crate::vm::opcode::Opcode;
-use std::collections::HashMap;
 use std::sync::Arc;
 
-pub fn register_builtin_classes(classes: &mut HashMap<String, Arc<CompiledClass>>) {
+/// The built-in subclasses of `Exception` and `Error`, with their parents
+pub const BUILTIN_THROWABLES: &[(&str, &str)] = &[
+    ("LogicException", "Exception"),
+    ("BadFunctionCallException", "LogicException"),
+    ("BadMethodCallException", "BadFunctionCallException"),
+    ("DomainException", "LogicException"),
+    ("InvalidArgumentException", "LogicException"),
+    ("LengthException", "LogicException"),
+    ("OutOfRangeException", "LogicException"),
+    ("RuntimeException", "Exception"),
+    ("OutOfBoundsException", "RuntimeException"),
+    ("OverflowException", "RuntimeException"),
+    ("RangeException", "RuntimeException"),
+    ("UnderflowException", "RuntimeException"),
+    ("UnexpectedValueException", "RuntimeException"),
+    ("JsonException", "Exception"),
+    ("ReflectionException", "Exception"),
+    ("DOMException", "Exception"),
+    ("TypeError", "Error"),
+    ("ArgumentCountError", "TypeError"),
+    ("ValueError", "Error"),
+    ("ArithmeticError", "Error"),
+    ("DivisionByZeroError", "ArithmeticError"),
+    ("UnhandledMatchError", "Error"),
+    ("FiberError", "Error"),
+];
+
+/// Whether `name` is `Exception`, `Error` or one of their built-in
+/// subclasses
+pub fn is_builtin_throwable(name: &str) -> bool {
+    matches!(name, "Exception" | "Error" | "ErrorException")
+        || BUILTIN_THROWABLES.iter().any(|(n, _)| *n == name)
+}
+
+pub fn register_builtin_classes(classes: &mut NameTable<Arc<CompiledClass>>) {
     register_exception_class(classes);
     register_error_class(classes);
-    register_type_error(classes);
-    register_invalid_argument_exception(classes);
-    register_unhandled_match_error(classes);
-    register_fiber_class(classes);
+    register_throwable_subclasses(classes);
+    register_error_exception(classes);
+    crate::vm::fiber::register_fiber_class(classes);
     register_attribute_classes(classes);
+    crate::vm::reflection::register_reflection_classes(classes);
+    crate::vm::event_loop::register_event_loop_classes(classes);
+    crate::vm::intl::register_intl_classes(classes);
+    crate::vm::xml::register_xml_classes(classes);
+    crate::vm::tokenizer::register_tokenizer_classes(classes);
 }
 
-fn register_attribute_classes(classes: &mut std::collections::HashMap<String, Arc<CompiledClass>>) {
+fn register_attribute_classes(classes: &mut NameTable<Arc<CompiledClass>>) {
     let mut nodiscard = CompiledClass::new("NoDiscard".to_string());
     nodiscard.is_abstract = false;
     nodiscard.is_final = true;
@@ -47,283 +85,139 @@ fn register_attribute_classes(classes: &mut std::collections::HashMap<String, Ar
     classes.insert("\\Deprecated".to_string(), Arc::new(deprecated_ns));
 }
 
-fn register_exception_class(classes: &mut std::collections::HashMap<String, Arc<CompiledClass>>) {
-    let mut exception = CompiledClass::new("Exception".to_string());
-
-    exception.properties.push(CompiledProperty {
-        name: "message".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::String(String::new())),
-        readonly: false,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
-
-    exception.properties.push(CompiledProperty {
-        name: "code".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::Integer(0)),
-        readonly: false,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
+fn register_exception_class(classes: &mut NameTable<Arc<CompiledClass>>) {
+    register_throwable_class(classes, "Exception");
+}
 
-    exception.properties.push(CompiledProperty {
-        name: "trace".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::Array(Vec::new())),
-        readonly: true,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
+fn register_error_class(classes: &mut NameTable<Arc<CompiledClass>>) {
+    register_throwable_class(classes, "Error");
+}
 
-    let mut construct = CompiledFunction::new("Exception::__construct".to_string());
-    construct.param_count = 2;
+/// Register `Exception` or `Error`, the roots of the Throwable classes
+///
+/// The engine sets `file`, `line` and `trace` when the object is
+/// created.
+fn register_throwable_class(classes: &mut NameTable<Arc<CompiledClass>>, name: &str) {
+    use crate::ast::Visibility;
+
+    let mut class = CompiledClass::new(name.to_string());
+
+    let properties = [
+        ("message", Visibility::Private, Value::String(String::new())),
+        ("code", Visibility::Private, Value::Integer(0)),
+        ("file", Visibility::Protected, Value::String(String::new())),
+        ("line", Visibility::Protected, Value::Integer(0)),
+        ("trace", Visibility::Private, Value::Array(PhpArray::new())),
+        ("previous", Visibility::Private, Value::Null),
+    ];
+    for (prop_name, visibility, default) in properties {
+        class.properties.push(CompiledProperty {
+            name: prop_name.to_string(),
+            visibility,
+            write_visibility: None,
+            default: Some(default),
+            readonly: prop_name == "trace",
+            is_static: false,
+            type_hint: None,
+            attributes: Vec::new(),
+            get_hook: None,
+            set_hook: None,
+        });
+    }
+
+    let mut construct = CompiledFunction::new(format!("{}::__construct", name));
+    construct.param_count = 3;
     construct.required_param_count = 0;
-    construct.local_count = 3;
+    construct.local_count = 4;
     construct.local_names = vec![
         "this".to_string(),
         "message".to_string(),
         "code".to_string(),
+        "previous".to_string(),
     ];
-    construct.strings.push("message".to_string());
-    construct.strings.push("code".to_string());
-    construct.bytecode.push(Opcode::LoadFast(1));
-    construct.bytecode.push(Opcode::StoreThisProperty(0));
-    construct.bytecode.push(Opcode::LoadFast(2));
-    construct.bytecode.push(Opcode::StoreThisProperty(1));
+    for (slot, prop_name) in ["message", "code", "previous"].iter().enumerate() {
+        construct.strings.push(prop_name.to_string());
+        construct.bytecode.push(Opcode::LoadFast(slot as u16 + 1));
+        construct
+            .bytecode
+            .push(Opcode::StoreThisProperty(slot as u32));
+        construct.bytecode.push(Opcode::Pop);
+    }
     construct.bytecode.push(Opcode::ReturnNull);
-    exception
+    class
         .methods
         .insert("__construct".to_string(), Arc::new(construct));
 
-    let mut get_message = CompiledFunction::new("Exception::getMessage".to_string());
-    get_message.param_count = 0;
-    get_message.local_count = 1;
-    get_message.local_names = vec!["this".to_string()];
-    get_message.strings.push("message".to_string());
-    get_message.bytecode.push(Opcode::LoadThis);
-    get_message.bytecode.push(Opcode::LoadProperty(0));
-    get_message.bytecode.push(Opcode::Return);
-    exception
-        .methods
-        .insert("getMessage".to_string(), Arc::new(get_message));
-
-    let mut get_code = CompiledFunction::new("Exception::getCode".to_string());
-    get_code.param_count = 0;
-    get_code.local_count = 1;
-    get_code.local_names = vec!["this".to_string()];
-    get_code.strings.push("code".to_string());
-    get_code.bytecode.push(Opcode::LoadThis);
-    get_code.bytecode.push(Opcode::LoadProperty(0));
-    get_code.bytecode.push(Opcode::Return);
-    exception
-        .methods
-        .insert("getCode".to_string(), Arc::new(get_code));
-
-    // getTrace() method - uses string index 0 for "trace"
-    let mut get_trace = CompiledFunction::new("Exception::getTrace".to_string());
-    get_trace.param_count = 0;
-    get_trace.local_count = 1;
-    get_trace.local_names = vec!["this".to_string()];
-    get_trace.strings.push("trace".to_string());
-    get_trace.bytecode.push(Opcode::LoadThis);
-    get_trace.bytecode.push(Opcode::LoadProperty(0));
-    get_trace.bytecode.push(Opcode::Return);
-    exception
-        .methods
-        .insert("getTrace".to_string(), Arc::new(get_trace));
-
-    // getTraceAsString() method - uses string index 0 for "trace"
-    let mut get_trace_as_string = CompiledFunction::new("Exception::getTraceAsString".to_string());
+    let getters = [
+        ("getMessage", "message"),
+        ("getCode", "code"),
+        ("getPrevious", "previous"),
+        ("getFile", "file"),
+        ("getLine", "line"),
+        ("getTrace", "trace"),
+    ];
+    for (method_name, prop_name) in getters {
+        let mut getter = CompiledFunction::new(format!("{}::{}", name, method_name));
+        getter.param_count = 0;
+        getter.local_count = 1;
+        getter.local_names = vec!["this".to_string()];
+        getter.strings.push(prop_name.to_string());
+        getter.bytecode.push(Opcode::LoadThis);
+        getter.bytecode.push(Opcode::LoadProperty(0));
+        getter.bytecode.push(Opcode::Return);
+        class
+            .methods
+            .insert(method_name.to_string(), Arc::new(getter));
+    }
+
+    let mut get_trace_as_string = CompiledFunction::new(format!("{}::getTraceAsString", name));
     get_trace_as_string.param_count = 0;
     get_trace_as_string.local_count = 1;
     get_trace_as_string.local_names = vec!["this".to_string()];
     get_trace_as_string.strings.push("trace".to_string());
     get_trace_as_string.bytecode.push(Opcode::LoadThis);
     get_trace_as_string.bytecode.push(Opcode::LoadProperty(0));
+    get_trace_as_string.bytecode.push(Opcode::FormatTrace);
     get_trace_as_string.bytecode.push(Opcode::Return);
-    exception.methods.insert(
+    class.methods.insert(
         "getTraceAsString".to_string(),
         Arc::new(get_trace_as_string),
     );
 
-    classes.insert("Exception".to_string(), Arc::new(exception));
-}
-
-fn register_error_class(classes: &mut std::collections::HashMap<String, Arc<CompiledClass>>) {
-    let mut error = CompiledClass::new("Error".to_string());
-
-    error.properties.push(CompiledProperty {
-        name: "message".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::String(String::new())),
-        readonly: false,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
-
-    error.properties.push(CompiledProperty {
-        name: "code".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::Integer(0)),
-        readonly: false,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
-
-    let mut error_construct = CompiledFunction::new("Error::__construct".to_string());
-    error_construct.param_count = 2;
-    error_construct.required_param_count = 0;
-    error_construct.local_count = 3;
-    error_construct.local_names = vec![
-        "this".to_string(),
-        "message".to_string(),
-        "code".to_string(),
-    ];
-    error_construct.bytecode.push(Opcode::LoadFast(1));
-    error_construct.strings.push("message".to_string());
-    error_construct.bytecode.push(Opcode::StoreThisProperty(0));
-    error_construct.bytecode.push(Opcode::LoadFast(2));
-    error_construct.strings.push("code".to_string());
-    error_construct.bytecode.push(Opcode::StoreThisProperty(1));
-    error_construct.bytecode.push(Opcode::ReturnNull);
-    error
-        .methods
-        .insert("__construct".to_string(), Arc::new(error_construct));
-
-    let mut error_get_message = CompiledFunction::new("Error::getMessage".to_string());
-    error_get_message.param_count = 0;
-    error_get_message.local_count = 1;
-    error_get_message.local_names = vec!["this".to_string()];
-    error_get_message.strings.push("message".to_string());
-    error_get_message.bytecode.push(Opcode::LoadThis);
-    error_get_message.bytecode.push(Opcode::LoadProperty(0));
-    error_get_message.bytecode.push(Opcode::Return);
-    error
+    let mut to_string = CompiledFunction::new(format!("{}::__toString", name));
+    to_string.param_count = 0;
+    to_string.local_count = 1;
+    to_string.local_names = vec!["this".to_string()];
+    to_string.bytecode.push(Opcode::LoadThis);
+    to_string.bytecode.push(Opcode::FormatThrowable);
+    to_string.bytecode.push(Opcode::Return);
+    class
         .methods
-        .insert("getMessage".to_string(), Arc::new(error_get_message));
+        .insert("__toString".to_string(), Arc::new(to_string));
 
-    let mut error_get_code = CompiledFunction::new("Error::getCode".to_string());
-    error_get_code.param_count = 0;
-    error_get_code.local_count = 1;
-    error_get_code.local_names = vec!["this".to_string()];
-    error_get_code.strings.push("code".to_string());
-    error_get_code.bytecode.push(Opcode::LoadThis);
-    error_get_code.bytecode.push(Opcode::LoadProperty(0));
-    error_get_code.bytecode.push(Opcode::Return);
-    error
-        .methods
-        .insert("getCode".to_string(), Arc::new(error_get_code));
-
-    classes.insert("Error".to_string(), Arc::new(error));
+    classes.insert(name.to_string(), Arc::new(class));
 }
 
-fn register_type_error(classes: &mut std::collections::HashMap<String, Arc<CompiledClass>>) {
-    let mut type_error = CompiledClass::new("TypeError".to_string());
-    type_error.parent = Some("Error".to_string());
-    classes.insert("TypeError".to_string(), Arc::new(type_error));
+fn register_throwable_subclasses(classes: &mut NameTable<Arc<CompiledClass>>) {
+    for (name, parent) in BUILTIN_THROWABLES {
+        let mut class = CompiledClass::new(name.to_string());
+        class.parent = Some(parent.to_string());
+        classes.insert(name.to_string(), Arc::new(class));
+    }
 }
 
-fn register_invalid_argument_exception(
-    classes: &mut std::collections::HashMap<String, Arc<CompiledClass>>,
-) {
-    let mut invalid_arg = CompiledClass::new("InvalidArgumentException".to_string());
-    invalid_arg.parent = Some("Exception".to_string());
-    classes.insert(
-        "InvalidArgumentException".to_string(),
-        Arc::new(invalid_arg),
-    );
-}
-
-fn register_unhandled_match_error(
-    classes: &mut std::collections::HashMap<String, Arc<CompiledClass>>,
-) {
-    let mut unhandled_match = CompiledClass::new("UnhandledMatchError".to_string());
-    unhandled_match.parent = Some("Error".to_string());
-    classes.insert("UnhandledMatchError".to_string(), Arc::new(unhandled_match));
-}
-
-fn register_fiber_class(classes: &mut std::collections::HashMap<String, Arc<CompiledClass>>) {
-    let mut fiber = CompiledClass::new("Fiber".to_string());
-
-    fiber.properties.push(CompiledProperty {
-        name: "__callback".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::Null),
-        readonly: false,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
-
-    fiber.properties.push(CompiledProperty {
-        name: "__started".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::Bool(false)),
-        readonly: false,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
-
-    fiber.properties.push(CompiledProperty {
-        name: "__suspended".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::Bool(false)),
-        readonly: false,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
-
-    fiber.properties.push(CompiledProperty {
-        name: "__terminated".to_string(),
-        visibility: crate::ast::Visibility::Private,
-        write_visibility: None,
-        default: Some(Value::Bool(false)),
-        readonly: false,
-        is_static: false,
-        type_hint: None,
-        attributes: Vec::new(),
-        get_hook: None,
-        set_hook: None,
-    });
+/// Register `ErrorException`, the exception for errors a handler or
+/// `vhp.errors_as_exceptions` turns into exceptions
+fn register_error_exception(classes: &mut NameTable<Arc<CompiledClass>>) {
+    let mut class = CompiledClass::new("ErrorException".to_string());
+    class.parent = Some("Exception".to_string());
 
-    fiber.properties.push(CompiledProperty {
-        name: "__return_value".to_string(),
-        visibility: crate::ast::Visibility::Private,
+    // E_ERROR unless the constructor says otherwise
+    class.properties.push(CompiledProperty {
+        name: "severity".to_string(),
+        visibility: crate::ast::Visibility::Protected,
         write_visibility: None,
-        default: Some(Value::Null),
+        default: Some(Value::Integer(1)),
         readonly: false,
         is_static: false,
         type_hint: None,
@@ -332,135 +226,54 @@ fn register_fiber_class(classes: &mut std::collections::HashMap<String, Arc<Comp
         set_hook: None,
     });
 
-    let mut construct = CompiledFunction::new("Fiber::__construct".to_string());
-    construct.param_count = 1;
-    construct.required_param_count = 1;
-    construct.local_count = 2;
-    construct.local_names = vec!["this".to_string(), "callback".to_string()];
-    construct.bytecode.push(Opcode::LoadFast(1));
-    construct.strings.push("__callback".to_string());
-    construct.bytecode.push(Opcode::StoreThisProperty(0));
+    // __construct($message, $code, $severity, $filename, $line, $previous)
+    let mut construct = CompiledFunction::new("ErrorException::__construct".to_string());
+    construct.param_count = 6;
+    construct.required_param_count = 0;
+    construct.local_count = 7;
+    construct.local_names = [
+        "this", "message", "code", "severity", "filename", "line", "previous",
+    ]
+    .iter()
+    .map(|name| name.to_string())
+    .collect();
+    construct.strings = ["message", "code", "previous", "severity", "file", "line"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    for (slot, prop_idx) in [(1, 0), (2, 1), (6, 2)] {
+        construct.bytecode.push(Opcode::LoadFast(slot));
+        construct.bytecode.push(Opcode::StoreThisProperty(prop_idx));
+        construct.bytecode.push(Opcode::Pop);
+    }
+    // The rest keep their defaults when passed null
+    for (slot, prop_idx) in [(3, 3), (4, 4), (5, 5)] {
+        let start = construct.bytecode.len() as u32;
+        construct.bytecode.push(Opcode::LoadFast(slot));
+        construct.bytecode.push(Opcode::JumpIfNotNull(start + 4));
+        construct.bytecode.push(Opcode::Pop);
+        construct.bytecode.push(Opcode::Jump(start + 6));
+        construct.bytecode.push(Opcode::StoreThisProperty(prop_idx));
+        construct.bytecode.push(Opcode::Pop);
+    }
     construct.bytecode.push(Opcode::ReturnNull);
-    fiber
+    class
         .methods
         .insert("__construct".to_string(), Arc::new(construct));
 
-    let mut start = CompiledFunction::new("Fiber::start".to_string());
-    start.param_count = 0;
-    start.local_count = 1;
-    start.local_names = vec!["this".to_string()];
-    start.strings.push("__started".to_string());
-    start.bytecode.push(Opcode::PushTrue);
-    start.bytecode.push(Opcode::LoadThis);
-    start.bytecode.push(Opcode::StoreThisProperty(0));
-    start.strings.push("__terminated".to_string());
-    start.bytecode.push(Opcode::PushTrue);
-    start.bytecode.push(Opcode::LoadThis);
-    start.bytecode.push(Opcode::StoreThisProperty(1));
-    start.strings.push("__callback".to_string());
-    start.bytecode.push(Opcode::LoadThis);
-    start.bytecode.push(Opcode::LoadProperty(2));
-    // Set current fiber before calling callback
-    start.bytecode.push(Opcode::Dup);
-    start.bytecode.push(Opcode::SetCurrentFiber);
-    start.bytecode.push(Opcode::CallCallable(0));
-    start.bytecode.push(Opcode::Dup);
-    start.bytecode.push(Opcode::LoadFast(0));
-    start.bytecode.push(Opcode::Swap);
-    start.strings.push("__return_value".to_string());
-    start.bytecode.push(Opcode::StoreThisProperty(3));
-    start.bytecode.push(Opcode::LoadFast(0));
-    start.bytecode.push(Opcode::LoadProperty(3));
-    start.bytecode.push(Opcode::Return);
-    fiber.methods.insert("start".to_string(), Arc::new(start));
-
-    let mut get_return = CompiledFunction::new("Fiber::getReturn".to_string());
-    get_return.param_count = 0;
-    get_return.local_count = 1;
-    get_return.local_names = vec!["this".to_string()];
-    get_return.strings.push("__return_value".to_string());
-    get_return.bytecode.push(Opcode::LoadThis);
-    get_return.bytecode.push(Opcode::LoadProperty(0));
-    get_return.bytecode.push(Opcode::Return);
-    fiber
-        .methods
-        .insert("getReturn".to_string(), Arc::new(get_return));
-
-    let mut is_started = CompiledFunction::new("Fiber::isStarted".to_string());
-    is_started.param_count = 0;
-    is_started.local_count = 1;
-    is_started.local_names = vec!["this".to_string()];
-    is_started.strings.push("__started".to_string());
-    is_started.bytecode.push(Opcode::LoadThis);
-    is_started.bytecode.push(Opcode::LoadProperty(0));
-    is_started.bytecode.push(Opcode::Return);
-    fiber
-        .methods
-        .insert("isStarted".to_string(), Arc::new(is_started));
-
-    let mut is_suspended = CompiledFunction::new("Fiber::isSuspended".to_string());
-    is_suspended.param_count = 0;
-    is_suspended.local_count = 1;
-    is_suspended.local_names = vec!["this".to_string()];
-    is_suspended.strings.push("__suspended".to_string());
-    is_suspended.bytecode.push(Opcode::LoadThis);
-    is_suspended.bytecode.push(Opcode::LoadProperty(0));
-    is_suspended.bytecode.push(Opcode::Return);
-    fiber
+    let mut get_severity = CompiledFunction::new("ErrorException::getSeverity".to_string());
+    get_severity.param_count = 0;
+    get_severity.local_count = 1;
+    get_severity.local_names = vec!["this".to_string()];
+    get_severity.strings.push("severity".to_string());
+    get_severity.bytecode.push(Opcode::LoadThis);
+    get_severity.bytecode.push(Opcode::LoadProperty(0));
+    get_severity.bytecode.push(Opcode::Return);
+    class
         .methods
-        .insert("isSuspended".to_string(), Arc::new(is_suspended));
-
-    let mut is_terminated = CompiledFunction::new("Fiber::isTerminated".to_string());
-    is_terminated.param_count = 0;
-    is_terminated.local_count = 1;
-    is_terminated.local_names = vec!["this".to_string()];
-    is_terminated.strings.push("__terminated".to_string());
-    is_terminated.bytecode.push(Opcode::LoadThis);
-    is_terminated.bytecode.push(Opcode::LoadProperty(0));
-    is_terminated.bytecode.push(Opcode::Return);
-    fiber
-        .methods
-        .insert("isTerminated".to_string(), Arc::new(is_terminated));
-
-    let mut get_current = CompiledFunction::new("Fiber::getCurrent".to_string());
-    get_current.param_count = 0;
-    get_current.local_count = 0;
-    get_current.bytecode.push(Opcode::GetCurrentFiber);
-    get_current.bytecode.push(Opcode::Return);
-    fiber
-        .static_methods
-        .insert("getCurrent".to_string(), Arc::new(get_current));
-
-    let mut suspend = CompiledFunction::new("Fiber::suspend".to_string());
-    suspend.param_count = 1;
-    suspend.required_param_count = 1;
-    suspend.local_count = 1;
-    suspend.local_names = vec!["this".to_string(), "value".to_string()];
-    suspend.bytecode.push(Opcode::PushNull);
-    suspend.bytecode.push(Opcode::Return);
-    fiber
-        .methods
-        .insert("suspend".to_string(), Arc::new(suspend));
-
-    let mut resume = CompiledFunction::new("Fiber::resume".to_string());
-    resume.param_count = 1;
-    resume.required_param_count = 0;
-    resume.local_count = 2;
-    resume.local_names = vec!["this".to_string(), "value".to_string()];
-    resume.bytecode.push(Opcode::PushNull);
-    resume.bytecode.push(Opcode::Return);
-    fiber.methods.insert("resume".to_string(), Arc::new(resume));
-
-    let mut throw = CompiledFunction::new("Fiber::throw".to_string());
-    throw.param_count = 1;
-    throw.required_param_count = 1;
-    throw.local_count = 2;
-    throw.local_names = vec!["this".to_string(), "exception".to_string()];
-    throw.bytecode.push(Opcode::PushNull);
-    throw.bytecode.push(Opcode::Return);
-    fiber.methods.insert("throw".to_string(), Arc::new(throw));
+        .insert("getSeverity".to_string(), Arc::new(get_severity));
 
-    classes.insert("Fiber".to_string(), Arc::new(fiber));
+    classes.insert("ErrorException".to_string(), Arc::new(class));
 }
 
 use crate::vm::opcode::CompiledFunction;
@@ -1,5 +1,7 @@
+use crate::vm::cache::codec::Codec;
+
 /// Compiled function representation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 pub struct CompiledFunction {
     /// Function name
     pub name: String,
@@ -31,6 +33,10 @@ pub struct CompiledFunction {
     pub attributes: Vec<crate::ast::Attribute>,
     /// Whether strict_types=1 was enabled when this function was compiled
     pub strict_types: bool,
+    /// Source file this function was compiled from
+    pub file: String,
+    /// Line table: (bytecode offset, source line) at each statement start
+    pub lines: Vec<(usize, usize)>,
 }
 
 impl CompiledFunction {
@@ -52,12 +58,37 @@ impl CompiledFunction {
             parameters: Vec::new(),
             attributes: Vec::new(),
             strict_types: false,
+            file: String::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Type of the parameter receiving argument `index`; arguments past
+    /// the last parameter of a variadic function take its type
+    pub fn param_type(&self, index: usize) -> Option<&crate::ast::TypeHint> {
+        match self.param_types.get(index) {
+            Some(type_hint) => type_hint.as_ref(),
+            None if self.is_variadic => self.param_types.last()?.as_ref(),
+            None => None,
         }
     }
+
+    /// Line table entries of the statements starting at bytecode offset `ip`
+    pub fn statements_at(&self, ip: usize) -> &[(usize, usize)] {
+        let start = self.lines.partition_point(|(offset, _)| *offset < ip);
+        let end = self.lines.partition_point(|(offset, _)| *offset <= ip);
+        &self.lines[start..end]
+    }
+
+    /// Source line of the statement containing bytecode offset `ip`
+    pub fn line_at(&self, ip: usize) -> Option<usize> {
+        let end = self.lines.partition_point(|(offset, _)| *offset <= ip);
+        end.checked_sub(1).map(|i| self.lines[i].1)
+    }
 }
 
 /// Constant value in the constant pool
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Codec)]
 #[allow(dead_code)] // All variants defined for future use
 pub enum Constant {
     Null,
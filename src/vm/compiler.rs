@@ -3,39 +3,59 @@
 //! This module compiles PHP AST into bytecode for the VM to execute.
 
 mod assignment_compilation;
+mod class_checks;
 mod class_compilation;
+mod class_constants;
 mod compiler_types;
+pub(crate) mod const_expr;
+mod definite_assignment;
+mod deprecations;
+mod element_assignment;
+mod error;
 mod expr;
 mod expr_helpers;
 mod functions;
+mod globals_array;
 mod if_match;
 mod interface_compilation;
 mod loops;
+mod magic_constants;
+mod nullsafe;
 mod object_access_compilation;
+mod references;
+mod scans;
 mod stmt;
 mod trait_enum_compilation;
 mod try_catch;
 
-use crate::ast::{BinaryOp, Expr, FunctionParam, Method, Program, Stmt, UnaryOp};
+use crate::ast::{BinaryOp, Expr, ForeachTarget, FunctionParam, Method, Program, Stmt, UnaryOp};
+use crate::vm::cache::codec::Codec;
 use crate::vm::class::{CompiledClass, CompiledEnum, CompiledInterface, CompiledTrait};
+use crate::vm::deprecation::CompileDeprecation;
+use crate::vm::name_table::NameTable;
 use crate::vm::opcode::{CompiledFunction, Opcode};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+pub use error::CompileError;
+
 /// Result of compiling a program
+#[derive(Codec)]
 pub struct CompilationResult {
     /// Main function bytecode
     pub main: Arc<CompiledFunction>,
     /// User-defined functions (name -> compiled function)
-    pub functions: HashMap<String, Arc<CompiledFunction>>,
+    pub functions: NameTable<Arc<CompiledFunction>>,
     /// Class definitions
-    pub classes: HashMap<String, Arc<CompiledClass>>,
+    pub classes: NameTable<Arc<CompiledClass>>,
     /// Interface definitions
-    pub interfaces: HashMap<String, Arc<CompiledInterface>>,
+    pub interfaces: NameTable<Arc<CompiledInterface>>,
     /// Trait definitions
-    pub traits: HashMap<String, Arc<CompiledTrait>>,
+    pub traits: NameTable<Arc<CompiledTrait>>,
     /// Enum definitions
-    pub enums: HashMap<String, Arc<CompiledEnum>>,
+    pub enums: NameTable<Arc<CompiledEnum>>,
+    /// Deprecated constructs found while compiling, reported on load
+    pub deprecations: Vec<CompileDeprecation>,
 }
 
 /// Compiler state for generating bytecode
@@ -54,15 +74,15 @@ pub struct Compiler {
     /// Continue target stack (for continue statements)
     continue_targets: Vec<usize>,
     /// Compiled functions collected during compilation
-    functions: HashMap<String, Arc<CompiledFunction>>,
+    functions: NameTable<Arc<CompiledFunction>>,
     /// Compiled classes collected during compilation
-    classes: HashMap<String, Arc<CompiledClass>>,
+    classes: NameTable<Arc<CompiledClass>>,
     /// Compiled interfaces collected during compilation
-    interfaces: HashMap<String, Arc<CompiledInterface>>,
+    interfaces: NameTable<Arc<CompiledInterface>>,
     /// Compiled traits collected during compilation
-    traits: HashMap<String, Arc<CompiledTrait>>,
+    traits: NameTable<Arc<CompiledTrait>>,
     /// Compiled enums collected during compilation
-    enums: HashMap<String, Arc<CompiledEnum>>,
+    enums: NameTable<Arc<CompiledEnum>>,
     /// Whether strict_types=1 is active for this compilation unit
     strict_types: bool,
     /// Current namespace (for prefixing class/function names)
@@ -75,39 +95,55 @@ pub struct Compiler {
     current_class: Option<String>,
     /// Current trait name (for __TRAIT__ magic constant)
     current_trait: Option<String>,
+    /// Variables definitely assigned at this point of the body (see
+    /// `definite_assignment`)
+    assigned: Option<HashSet<String>>,
+    /// Nesting depth of the statement being compiled
+    stmt_depth: usize,
+    /// Depth of the statements whose function declarations exist from the
+    /// start: the file's top level, or a braced namespace or declare block
+    /// in it. Functions declared deeper are declared when the statement
+    /// runs; in a function body, that is all of them.
+    declaration_depth: usize,
+    /// Source line of the statement being compiled
+    line: usize,
+    /// Deprecated constructs found so far
+    deprecations: Vec<CompileDeprecation>,
 }
 
 impl Compiler {
-    /// Create a new compiler for a function
-    pub fn new(name: String) -> Self {
-        Self::with_file_path(name, "<main>".to_string())
-    }
-
     /// Create a new compiler for a function with a specific file path
     pub fn with_file_path(name: String, file_path: String) -> Self {
+        let mut function = CompiledFunction::new(name);
+        function.file = file_path.clone();
         Self {
-            function: CompiledFunction::new(name),
+            function,
             string_table: HashMap::new(),
             locals: HashMap::new(),
             next_local: 0,
             break_targets: Vec::new(),
             continue_targets: Vec::new(),
-            functions: HashMap::new(),
-            classes: HashMap::new(),
-            interfaces: HashMap::new(),
-            traits: HashMap::new(),
-            enums: HashMap::new(),
+            functions: NameTable::new(),
+            classes: NameTable::new(),
+            interfaces: NameTable::new(),
+            traits: NameTable::new(),
+            enums: NameTable::new(),
             strict_types: false,
             current_namespace: None,
             use_aliases: HashMap::new(),
             current_file_path: file_path,
             current_class: None,
             current_trait: None,
+            assigned: None,
+            stmt_depth: 0,
+            declaration_depth: 1,
+            line: 0,
+            deprecations: Vec::new(),
         }
     }
 
     /// Compile a program to main function and all user-defined functions
-    pub fn compile_program(mut self, program: &Program) -> Result<CompilationResult, String> {
+    pub fn compile_program(mut self, program: &Program) -> Result<CompilationResult, CompileError> {
         // Compile all statements
         for stmt in &program.statements {
             self.compile_stmt(stmt)?;
@@ -123,12 +159,13 @@ impl Compiler {
             interfaces: self.interfaces,
             traits: self.traits,
             enums: self.enums,
+            deprecations: self.deprecations,
         })
     }
 
     /// Compile a statement
-    fn compile_stmt(&mut self, stmt: &crate::ast::Stmt) -> Result<(), String> {
-        self.compile_stmt_internal(stmt)
+    fn compile_stmt(&mut self, stmt: &crate::ast::Stmt) -> Result<(), CompileError> {
+        self.compile_tracked_stmt(stmt)
     }
 
     /// Compile an if statement with elseif and else branches
@@ -138,15 +175,15 @@ impl Compiler {
         then_branch: &[Stmt],
         elseif_branches: &[(Expr, Vec<Stmt>)],
         else_branch: &Option<Vec<Stmt>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_if_internal(condition, then_branch, elseif_branches, else_branch)
     }
 
-    fn compile_while(&mut self, condition: &Expr, body: &[Stmt]) -> Result<(), String> {
+    fn compile_while(&mut self, condition: &Expr, body: &[Stmt]) -> Result<(), CompileError> {
         self.compile_while_internal(condition, body)
     }
 
-    fn compile_do_while(&mut self, body: &[Stmt], condition: &Expr) -> Result<(), String> {
+    fn compile_do_while(&mut self, body: &[Stmt], condition: &Expr) -> Result<(), CompileError> {
         self.compile_do_while_internal(body, condition)
     }
 
@@ -156,7 +193,7 @@ impl Compiler {
         condition: &Option<Expr>,
         update: &Option<Expr>,
         body: &[Stmt],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_for_internal(init, condition, update, body)
     }
 
@@ -164,13 +201,14 @@ impl Compiler {
         &mut self,
         array: &Expr,
         key: &Option<String>,
-        value: &str,
+        value: &ForeachTarget,
+        by_ref: bool,
         body: &[Stmt],
-    ) -> Result<(), String> {
-        self.compile_foreach_internal(array, key, value, body)
+    ) -> Result<(), CompileError> {
+        self.compile_foreach_internal(array, key, value, by_ref, body)
     }
 
-    fn compile_expr(&mut self, expr: &Expr) -> Result<(), String> {
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
         self.compile_expr_internal(expr)
     }
 
@@ -180,12 +218,12 @@ impl Compiler {
         left: &Expr,
         op: &BinaryOp,
         right: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_binary_op_internal(left, op, right)
     }
 
     /// Compile a unary operation
-    fn compile_unary_op(&mut self, op: &UnaryOp, operand: &Expr) -> Result<(), String> {
+    fn compile_unary_op(&mut self, op: &UnaryOp, operand: &Expr) -> Result<(), CompileError> {
         self.compile_unary_op_internal(op, operand)
     }
 
@@ -193,9 +231,9 @@ impl Compiler {
     fn compile_ternary(
         &mut self,
         condition: &Expr,
-        then_val: &Expr,
+        then_val: Option<&Expr>,
         else_val: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_ternary_internal(condition, then_val, else_val)
     }
 
@@ -205,7 +243,7 @@ impl Compiler {
         expr: &Expr,
         arms: &[crate::ast::MatchArm],
         default: &Option<Box<Expr>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_match_internal(expr, arms, default)
     }
 
@@ -214,7 +252,7 @@ impl Compiler {
         &mut self,
         params: &[FunctionParam],
         body: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_arrow_function_internal(params, body)
     }
 
@@ -279,72 +317,21 @@ impl Compiler {
         slot
     }
 
-    /// Get the current file path for __FILE__ magic constant
-    fn file_path(&self) -> String {
-        self.current_file_path.clone()
-    }
-
-    /// Get the directory of the current file for __DIR__ magic constant
-    fn dir_path(&self) -> String {
-        let path = &self.current_file_path;
-        if path == "<main>" {
-            return ".".to_string();
-        }
-        // Get parent directory
-        if let Some(pos) = path.rfind('/') {
-            path[..pos].to_string()
-        } else if let Some(pos) = path.rfind('\\') {
-            path[..pos].to_string()
-        } else {
-            ".".to_string()
-        }
-    }
-
-    /// Get the current function name for __FUNCTION__ magic constant
-    fn function_name(&self) -> String {
-        let name = &self.function.name;
-        if name == "<main>" || name == "<test>" {
-            return "".to_string();
-        }
-        name.clone()
-    }
-
-    /// Get the current class name for __CLASS__ magic constant
-    fn class_name(&self) -> String {
-        self.current_class.clone().unwrap_or("".to_string())
-    }
-
-    /// Get the current method name for __METHOD__ magic constant
-    fn method_name(&self) -> String {
-        if let Some(ref class) = self.current_class {
-            let func_name = self.function.name.clone();
-            if func_name == "<main>" || func_name == "<test>" {
-                return "".to_string();
-            }
-            // func_name is "ClassName::methodName", extract just the method name part
-            if let Some(pos) = func_name.find("::") {
-                let method_part = &func_name[pos + 2..];
-                format!("{}::{}", class, method_part)
-            } else {
-                // No :: separator, use as-is
-                format!("{}::{}", class, func_name)
-            }
-        } else {
-            "".to_string()
-        }
-    }
-
-    /// Get the current namespace for __NAMESPACE__ magic constant
-    fn namespace(&self) -> String {
-        self.current_namespace.clone().unwrap_or("".to_string())
-    }
-
-    /// Get the current trait name for __TRAIT__ magic constant
-    fn trait_name(&self) -> String {
-        self.current_trait.clone().unwrap_or("".to_string())
+    /// Create a compiler for a nested function body in the same source file
+    fn child_compiler(&self, name: String) -> Compiler {
+        let mut compiler = Compiler::with_file_path(name, self.current_file_path.clone());
+        compiler.line = self.line;
+        compiler.declaration_depth = 0;
+        compiler.strict_types = self.strict_types;
+        compiler.function.strict_types = self.strict_types;
+        compiler
     }
 
     /// Compile a function definition
+    ///
+    /// A nested declaration, as in `if (!function_exists('f')) { function
+    /// f() {} }`, is kept under a name of its own until `DeclareFunction`
+    /// declares it.
     fn compile_function(
         &mut self,
         name: &str,
@@ -352,17 +339,30 @@ impl Compiler {
         return_type: &Option<crate::ast::TypeHint>,
         body: &[Stmt],
         attributes: &[crate::ast::Attribute],
-    ) -> Result<(), String> {
-        self.compile_function_internal(name, params, return_type, body, attributes)
+    ) -> Result<(), CompileError> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static CONDITIONAL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let compiled =
+            self.compile_function_internal(name, params, return_type, body, attributes)?;
+        if self.stmt_depth == self.declaration_depth {
+            self.functions.insert(name.to_string(), compiled);
+        } else {
+            let id = CONDITIONAL_COUNTER.fetch_add(1, Ordering::SeqCst);
+            let key = format!("{}#{}", name, id);
+            self.functions.insert(key.clone(), compiled);
+            let key_idx = self.intern_string(key);
+            self.emit(Opcode::DeclareFunction(key_idx));
+        }
+        Ok(())
     }
 
     fn compile_switch(
         &mut self,
         expr: &Expr,
         cases: &[crate::ast::SwitchCase],
-        default: &Option<Vec<Stmt>>,
-    ) -> Result<(), String> {
-        self.compile_switch_internal(expr, cases, default)
+    ) -> Result<(), CompileError> {
+        self.compile_switch_internal(expr, cases)
     }
 
     fn compile_try_catch(
@@ -370,7 +370,7 @@ impl Compiler {
         try_body: &[Stmt],
         catch_clauses: &[crate::ast::CatchClause],
         finally_body: &Option<Vec<Stmt>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_try_catch_internal(try_body, catch_clauses, finally_body)
     }
 
@@ -385,10 +385,11 @@ impl Compiler {
         parent: &Option<crate::ast::QualifiedName>,
         interfaces: &[crate::ast::QualifiedName],
         trait_uses: &[crate::ast::TraitUse],
+        constants: &[crate::ast::InterfaceConstant],
         properties: &[crate::ast::Property],
         methods: &[Method],
         attributes: &[crate::ast::Attribute],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_class_internal(
             name,
             is_abstract,
@@ -397,6 +398,7 @@ impl Compiler {
             parent,
             interfaces,
             trait_uses,
+            constants,
             properties,
             methods,
             attributes,
@@ -410,7 +412,7 @@ impl Compiler {
         methods: &[crate::ast::InterfaceMethodSignature],
         constants: &[crate::ast::InterfaceConstant],
         attributes: &[crate::ast::Attribute],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_interface_internal(name, parents, methods, constants, attributes)
     }
 
@@ -421,18 +423,29 @@ impl Compiler {
         properties: &[crate::ast::Property],
         methods: &[Method],
         attributes: &[crate::ast::Attribute],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_trait_internal(name, uses, properties, methods, attributes)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn compile_enum(
         &mut self,
         name: &str,
         backing_type: &crate::ast::EnumBackingType,
+        interfaces: &[crate::ast::QualifiedName],
         cases: &[crate::ast::EnumCase],
+        constants: &[crate::ast::InterfaceConstant],
         methods: &[Method],
         attributes: &[crate::ast::Attribute],
-    ) -> Result<(), String> {
-        self.compile_enum_internal(name, backing_type, cases, methods, attributes)
+    ) -> Result<(), CompileError> {
+        self.compile_enum_internal(
+            name,
+            backing_type,
+            interfaces,
+            cases,
+            constants,
+            methods,
+            attributes,
+        )
     }
 }
@@ -1,74 +1,81 @@
-use super::Compiler;
+use super::globals_array::is_globals;
+use super::{CompileError, Compiler};
 
-use crate::ast::{Argument, Expr, ListElement};
+use crate::ast::{Argument, AssignOp, Expr, ListElement};
 use crate::vm::opcode::Opcode;
 
 impl Compiler {
+    /// Compile an assignment to a variable. A compound assignment applies
+    /// its operation to the current value, and `??=` only evaluates and
+    /// assigns the value when the variable is null or unset.
     pub(crate) fn compile_assign(
         &mut self,
         var: &str,
-        op: &crate::ast::AssignOp,
+        op: &AssignOp,
         value: &Expr,
-    ) -> Result<(), String> {
-        use crate::ast::AssignOp;
-
-        if *op != AssignOp::Assign {
-            if let Some(&slot) = self.locals.get(var) {
-                self.emit(Opcode::LoadFast(slot));
-            } else {
-                let idx = self.intern_string(var.to_string());
-                self.emit(Opcode::LoadVar(idx));
-            }
+    ) -> Result<(), CompileError> {
+        if var == "GLOBALS" {
+            return Err(self.globals_write_error());
         }
-
-        self.compile_expr(value)?;
-
-        match op {
-            AssignOp::Assign => {}
-            AssignOp::AddAssign => {
-                self.emit(Opcode::Add);
-            }
-            AssignOp::SubAssign => {
-                self.emit(Opcode::Sub);
-            }
-            AssignOp::MulAssign => {
-                self.emit(Opcode::Mul);
-            }
-            AssignOp::DivAssign => {
-                self.emit(Opcode::Div);
-            }
-            AssignOp::ModAssign => {
-                self.emit(Opcode::Mod);
+        let mut skip_jump = None;
+        match op.binary_op() {
+            Some(binary) => {
+                self.emit_load_variable(var);
+                self.compile_expr(value)?;
+                self.emit_binary_opcode(&binary);
             }
-            AssignOp::ConcatAssign => {
-                self.emit(Opcode::Concat);
+            None if *op == AssignOp::NullCoalesceAssign => {
+                self.compile_quiet_fetch(&Expr::Variable(var.to_string()))?;
+                skip_jump = Some(self.emit_jump(Opcode::JumpIfNotNull(0)));
+                self.emit(Opcode::Pop);
+                self.compile_expr(value)?;
             }
-        };
-
-        if !self.locals.contains_key(var) {
-            let slot = self.next_local;
-            self.locals.insert(var.to_string(), slot);
-            self.next_local += 1;
-            self.function.local_count = self.next_local;
-            self.function.local_names.push(var.to_string());
+            None => self.compile_expr(value)?,
         }
 
-        if let Some(&slot) = self.locals.get(var) {
-            self.emit(Opcode::Dup);
-            self.emit(Opcode::StoreFast(slot));
-        } else {
-            let idx = self.intern_string(var.to_string());
-            self.emit(Opcode::Dup);
-            self.emit(Opcode::StoreVar(idx));
-        }
+        let slot = self.allocate_local(var.to_string());
+        self.emit(Opcode::StoreFast(slot));
 
+        if let Some(jump) = skip_jump {
+            self.patch_jump(jump);
+        }
         Ok(())
     }
 
+    /// Compile a compound assignment to `target` through `assign`, which
+    /// compiles a plain assignment of the value it's given: `$o->p += 1`
+    /// assigns `$o->p + 1`, and `$o->p ??= 1` is `$o->p ?? ($o->p = 1)`
+    pub(crate) fn compile_compound_assign(
+        &mut self,
+        target: Expr,
+        op: &AssignOp,
+        value: &Expr,
+        assign: impl FnOnce(&mut Self, &Expr) -> Result<(), CompileError>,
+    ) -> Result<(), CompileError> {
+        match op.binary_op() {
+            Some(binary) => {
+                let value = Expr::Binary {
+                    left: Box::new(target),
+                    op: binary,
+                    right: Box::new(value.clone()),
+                };
+                assign(self, &value)
+            }
+            None => {
+                self.compile_quiet_fetch(&target)?;
+                let skip_jump = self.emit_jump(Opcode::JumpIfNotNull(0));
+                self.emit(Opcode::Pop);
+                assign(self, value)?;
+                self.patch_jump(skip_jump);
+                Ok(())
+            }
+        }
+    }
+
     pub(crate) fn compile_array_literal(
         &mut self,
         elements: &[crate::ast::ArrayElement],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         let count = elements.len();
         for elem in elements {
             if let Some(key_expr) = &elem.key {
@@ -87,41 +94,47 @@ impl Compiler {
         &mut self,
         name: &str,
         args: &[Argument],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         if name.to_lowercase() == "unset" {
             for arg in args {
                 match arg.value.as_ref() {
+                    target if target.is_nullsafe_chain() => {
+                        return Err(self.invalid("Can't use nullsafe operator in write context"));
+                    }
                     Expr::PropertyAccess { object, property } => {
                         let prop_idx = self.intern_string(property.clone());
-
-                        if let Expr::Variable(var_name) = object.as_ref() {
-                            if let Some(&slot) = self.locals.get(var_name) {
-                                self.emit(Opcode::UnsetPropertyOnLocal(slot, prop_idx));
-                            } else {
-                                let var_idx = self.intern_string(var_name.clone());
-                                self.emit(Opcode::UnsetPropertyOnGlobal(var_idx, prop_idx));
-                            }
-                        } else {
-                            self.compile_expr(object)?;
-                            self.emit(Opcode::UnsetProperty(prop_idx));
-                        }
+                        self.compile_expr(object)?;
+                        self.emit(Opcode::UnsetProperty(prop_idx));
+                        self.emit(Opcode::Pop);
                     }
                     Expr::Variable(var_name) => {
                         if let Some(&slot) = self.locals.get(var_name) {
-                            self.emit(Opcode::PushNull);
-                            self.emit(Opcode::StoreFast(slot));
+                            self.emit(Opcode::UnsetFast(slot));
                         } else {
                             let idx = self.intern_string(var_name.clone());
                             self.emit(Opcode::UnsetVar(idx));
                         }
                     }
-                    Expr::ArrayAccess { array, index } => {
-                        self.compile_expr(array)?;
+                    Expr::ArrayAccess { array, index } if is_globals(array) => {
                         self.compile_expr(index)?;
-                        self.emit(Opcode::UnsetArrayElement);
+                        self.emit(Opcode::UnsetGlobal);
                     }
+                    Expr::ArrayAccess { array, index } => match array.as_ref() {
+                        Expr::Variable(var_name) if self.locals.contains_key(var_name) => {
+                            let slot = self.locals[var_name];
+                            self.compile_expr(index)?;
+                            self.emit(Opcode::UnsetArrayElementOnLocal(slot));
+                        }
+                        _ => {
+                            self.compile_expr(array)?;
+                            self.compile_expr(index)?;
+                            self.emit(Opcode::UnsetArrayElement);
+                        }
+                    },
                     _ => {
-                        return Err(format!("Cannot unset expression: {:?}", arg.value));
+                        return Err(
+                            self.invalid(format!("Cannot unset expression: {:?}", arg.value))
+                        );
                     }
                 }
             }
@@ -132,18 +145,8 @@ impl Compiler {
         if name.to_lowercase() == "isset" && args.len() == 1 {
             if let Expr::PropertyAccess { object, property } = args[0].value.as_ref() {
                 let prop_idx = self.intern_string(property.clone());
-
-                if let Expr::Variable(var_name) = object.as_ref() {
-                    if let Some(&slot) = self.locals.get(var_name) {
-                        self.emit(Opcode::IssetPropertyOnLocal(slot, prop_idx));
-                    } else {
-                        let var_idx = self.intern_string(var_name.clone());
-                        self.emit(Opcode::IssetPropertyOnGlobal(var_idx, prop_idx));
-                    }
-                } else {
-                    self.compile_expr(object)?;
-                    self.emit(Opcode::IssetProperty(prop_idx));
-                }
+                self.compile_expr(object)?;
+                self.emit(Opcode::IssetProperty(prop_idx));
                 return Ok(());
             }
         }
@@ -191,127 +194,21 @@ impl Compiler {
             let name_idx = self.intern_string(name.to_string());
             self.emit(Opcode::CallNamed(name_idx));
         } else {
-            for arg in args {
-                self.compile_expr(&arg.value)?;
-            }
+            let quiet = Self::is_quiet_call(name);
+            let builtin = crate::vm::builtins::is_builtin(name);
             let name_idx = self.intern_string(name.to_string());
-            self.emit(Opcode::Call(name_idx, args.len() as u8));
-        }
-
-        Ok(())
-    }
-
-    pub(crate) fn compile_array_assign(
-        &mut self,
-        array: &Expr,
-        index: &Option<Box<Expr>>,
-        op: &crate::ast::AssignOp,
-        value: &Expr,
-    ) -> Result<(), String> {
-        use crate::ast::AssignOp;
-
-        if *op != AssignOp::Assign {
-            return Err("Compound array assignment not yet implemented".to_string());
-        }
-
-        match array {
-            Expr::Variable(var_name) => {
-                if let Some(&slot) = self.locals.get(var_name) {
-                    self.emit(Opcode::LoadFast(slot));
-                } else {
-                    let idx = self.intern_string(var_name.clone());
-                    self.emit(Opcode::LoadVar(idx));
-                }
-
-                if let Some(key_expr) = index {
-                    self.compile_expr(key_expr)?;
-                } else {
-                    self.emit(Opcode::PushNull);
-                }
-
-                self.compile_expr(value)?;
-
-                if index.is_some() {
-                    self.emit(Opcode::ArraySet);
-                } else {
-                    self.emit(Opcode::Swap);
-                    self.emit(Opcode::Pop);
-                    self.emit(Opcode::ArrayAppend);
-                }
-
-                if let Some(&slot) = self.locals.get(var_name) {
-                    self.emit(Opcode::Dup);
-                    self.emit(Opcode::StoreFast(slot));
-                } else {
-                    let idx = self.intern_string(var_name.clone());
-                    self.emit(Opcode::Dup);
-                    self.emit(Opcode::StoreVar(idx));
-                }
-            }
-            Expr::PropertyAccess { object, property } => {
-                let is_this = matches!(object.as_ref(), Expr::This);
-
-                self.compile_expr(object)?;
-                let prop_idx = self.intern_string(property.clone());
-                self.emit(Opcode::LoadProperty(prop_idx));
-
-                if let Some(key_expr) = index {
-                    self.compile_expr(key_expr)?;
-                } else {
-                    self.emit(Opcode::PushNull);
-                }
-
-                self.compile_expr(value)?;
-
-                if index.is_some() {
-                    self.emit(Opcode::ArraySet);
-                } else {
-                    self.emit(Opcode::Swap);
-                    self.emit(Opcode::Pop);
-                    self.emit(Opcode::ArrayAppend);
-                }
-
-                if is_this {
-                    self.emit(Opcode::StoreThisProperty(prop_idx));
-                } else {
-                    self.compile_expr(object)?;
-                    self.emit(Opcode::Swap);
-                    self.emit(Opcode::StoreProperty(prop_idx));
-
-                    if let Expr::Variable(var_name) = object.as_ref() {
-                        if let Some(&slot) = self.locals.get(var_name) {
-                            self.emit(Opcode::StoreFast(slot));
-                        } else {
-                            let idx = self.intern_string(var_name.clone());
-                            self.emit(Opcode::StoreVar(idx));
-                        }
-                    }
-                }
-            }
-            Expr::StaticPropertyAccess { class, property } => {
-                let class_idx = self.intern_string(class.clone());
-                let prop_idx = self.intern_string(property.clone());
-                self.emit(Opcode::LoadStaticProp(class_idx, prop_idx));
-
-                if let Some(key_expr) = index {
-                    self.compile_expr(key_expr)?;
-                } else {
-                    self.emit(Opcode::PushNull);
-                }
-
-                self.compile_expr(value)?;
-
-                if index.is_some() {
-                    self.emit(Opcode::ArraySet);
+            for (pos, arg) in args.iter().enumerate() {
+                if quiet {
+                    self.compile_quiet_fetch(&arg.value)?;
+                } else if builtin && crate::vm::builtins::takes_ref(name, pos) {
+                    self.compile_ref_arg(&arg.value)?;
+                } else if builtin {
+                    self.compile_expr(&arg.value)?;
                 } else {
-                    self.emit(Opcode::Swap);
-                    self.emit(Opcode::Pop);
-                    self.emit(Opcode::ArrayAppend);
+                    self.compile_arg_checked(&arg.value, Opcode::IsRefArg(name_idx, pos as u8))?;
                 }
-
-                self.emit(Opcode::StoreStaticProp(class_idx, prop_idx));
             }
-            _ => return Err("Complex array assignment not yet implemented".to_string()),
+            self.emit(Opcode::Call(name_idx, args.len() as u8));
         }
 
         Ok(())
@@ -320,127 +217,77 @@ impl Compiler {
     /// Compile list() destructuring: list($a, $b) = $array
     pub(crate) fn compile_list_destructure(
         &mut self,
-        elements: &[ListElement],
+        elements: &[Option<ListElement>],
         array: &Expr,
-    ) -> Result<(), String> {
-        // Compile the array expression
+    ) -> Result<(), CompileError> {
+        // The array is kept aside first, so `[$a, $b] = [$b, $a]` swaps
         self.compile_expr(array)?;
-
-        // Store array in a temporary local variable
-        let array_slot = self.allocate_local("__list_array__".to_string());
+        let array_slot = self.allocate_local(format!("__list_array_{}__", self.current_offset()));
         self.emit(Opcode::StoreFast(array_slot));
+        self.emit(Opcode::Pop);
 
-        // For each element, extract the value and assign to variable
-        for (index, element) in elements.iter().enumerate() {
-            match &*element.value {
-                Expr::Variable(var_name) => {
-                    // Load array from slot and get element at index
-                    self.emit(Opcode::LoadFast(array_slot));
-                    self.emit(Opcode::PushInt(index as i64));
-                    self.emit(Opcode::ArrayGet);
-
-                    // Store in variable
-                    if !self.locals.contains_key(var_name) {
-                        let slot = self.next_local;
-                        self.locals.insert(var_name.clone(), slot);
-                        self.next_local += 1;
-                        self.function.local_count = self.next_local;
-                        self.function.local_names.push(var_name.clone());
-                    }
+        self.compile_list_destructure_internal(elements, array_slot)?;
 
-                    if let Some(&slot) = self.locals.get(var_name) {
-                        self.emit(Opcode::StoreFast(slot));
-                    } else {
-                        let idx = self.intern_string(var_name.clone());
-                        self.emit(Opcode::StoreVar(idx));
-                    }
-                }
-                Expr::ListDestructure {
-                    elements: nested_elements,
-                    ..
-                } => {
-                    // Nested list destructuring
-                    self.emit(Opcode::LoadFast(array_slot));
-                    self.emit(Opcode::PushInt(index as i64));
-                    self.emit(Opcode::ArrayGet);
-
-                    let nested_array_slot = self.allocate_local("__list_nested__".to_string());
-                    self.emit(Opcode::StoreFast(nested_array_slot));
-
-                    self.compile_list_destructure_internal(nested_elements, nested_array_slot)?;
-
-                    self.emit(Opcode::PushNull);
-                    self.emit(Opcode::StoreFast(nested_array_slot));
-                }
-                _ => {
-                    return Err(format!(
-                        "list() elements must be variables or nested lists, got {:?}",
-                        element.value
-                    ));
-                }
-            }
-        }
-
-        // Reload the array to return it (list() returns the array for chaining)
+        // The assignment's value is the whole array
         self.emit(Opcode::LoadFast(array_slot));
-
-        // Note: We don't clean up the temporary array variable here
-        // because it will be cleaned up when the function ends
-        // and cleaning it would overwrite the value we need to return
-
+        self.emit(Opcode::UnsetFast(array_slot));
         Ok(())
     }
 
-    /// Internal helper for nested list destructuring with a specific array source
-    fn compile_list_destructure_internal(
+    /// Assign the elements of the array in `array_slot` to a list()
+    /// pattern's targets, by key or else by position (a skipped slot still
+    /// takes one), and destructure nested patterns the same way. Every
+    /// target of a value that is not an array is assigned null.
+    pub(crate) fn compile_list_destructure_internal(
         &mut self,
-        elements: &[ListElement],
+        elements: &[Option<ListElement>],
         array_slot: u16,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         for (index, element) in elements.iter().enumerate() {
-            match &*element.value {
-                Expr::Variable(var_name) => {
-                    // Load array from slot and get element at index
-                    self.emit(Opcode::LoadFast(array_slot));
+            let Some(element) = element else {
+                continue;
+            };
+            self.emit(Opcode::LoadFast(array_slot));
+            match &element.key {
+                Some(key) => self.compile_expr(key)?,
+                None => {
                     self.emit(Opcode::PushInt(index as i64));
-                    self.emit(Opcode::ArrayGet);
-
-                    // Store in variable
-                    if !self.locals.contains_key(var_name) {
-                        let slot = self.next_local;
-                        self.locals.insert(var_name.clone(), slot);
-                        self.next_local += 1;
-                        self.function.local_count = self.next_local;
-                        self.function.local_names.push(var_name.clone());
-                    }
+                }
+            }
+            self.emit(Opcode::ListGet);
 
-                    if let Some(&slot) = self.locals.get(var_name) {
-                        self.emit(Opcode::StoreFast(slot));
-                    } else {
-                        let idx = self.intern_string(var_name.clone());
-                        self.emit(Opcode::StoreVar(idx));
-                    }
+            match &*element.value {
+                Expr::Variable(var_name) => {
+                    let slot = self.allocate_local(var_name.clone());
+                    self.emit(Opcode::StoreFast(slot));
+                    self.emit(Opcode::Pop);
                 }
                 Expr::ListDestructure {
                     elements: nested_elements,
                     ..
                 } => {
-                    // Nested list destructuring
-                    self.emit(Opcode::LoadFast(array_slot));
-                    self.emit(Opcode::PushInt(index as i64));
-                    self.emit(Opcode::ArrayGet);
-
-                    let nested_array_slot = self.allocate_local("__list_nested__".to_string());
+                    // Each level of nesting needs its own temporary
+                    let nested_array_slot =
+                        self.allocate_local(format!("__list_nested_{}__", array_slot));
                     self.emit(Opcode::StoreFast(nested_array_slot));
+                    self.emit(Opcode::Pop);
                     self.compile_list_destructure_internal(nested_elements, nested_array_slot)?;
-                    self.emit(Opcode::PushNull);
-                    self.emit(Opcode::StoreFast(nested_array_slot));
+                    self.emit(Opcode::UnsetFast(nested_array_slot));
                 }
-                _ => {
-                    return Err(format!(
-                        "list() elements must be variables or nested lists, got {:?}",
-                        element.value
-                    ));
+                target => {
+                    // An element or a property is assigned the value as
+                    // `$target = $value` would be
+                    let name = format!("__list_value_{}__", array_slot);
+                    let slot = self.allocate_local(name.clone());
+                    self.emit(Opcode::StoreFast(slot));
+                    self.emit(Opcode::Pop);
+                    let assignment = list_target_assignment(target, Expr::Variable(name))
+                        .ok_or_else(|| {
+                            self.invalid("Assignments can only happen to writable values")
+                        })?;
+                    self.compile_expr(&assignment)?;
+                    self.emit(Opcode::Pop);
+                    self.emit(Opcode::UnsetFast(slot));
                 }
             }
         }
@@ -448,3 +295,30 @@ impl Compiler {
         Ok(())
     }
 }
+
+/// The assignment of `value` to a pattern's element or property target
+fn list_target_assignment(target: &Expr, value: Expr) -> Option<Expr> {
+    let op = AssignOp::Assign;
+    let value = Box::new(value);
+    Some(match target {
+        Expr::ArrayAccess { array, index } => Expr::ArrayAssign {
+            array: array.clone(),
+            index: (!matches!(**index, Expr::Null)).then(|| index.clone()),
+            op,
+            value,
+        },
+        Expr::PropertyAccess { object, property } => Expr::PropertyAssign {
+            object: object.clone(),
+            property: property.clone(),
+            op,
+            value,
+        },
+        Expr::StaticPropertyAccess { class, property } => Expr::StaticPropertyAssign {
+            class: class.clone(),
+            property: property.clone(),
+            op,
+            value,
+        },
+        _ => return None,
+    })
+}
@@ -0,0 +1,101 @@
+//! Checks a class declaration must pass against what it extends,
+//! implements and uses: `#[\Override]` methods override something, and a
+//! concrete class implements every abstract and interface method
+
+use super::{CompileError, Compiler};
+use crate::ast::Method;
+use crate::vm::class::CompiledClass;
+
+impl Compiler {
+    /// Fail if `method` has the `#[\Override]` attribute but no parent
+    /// class, interface or trait of the class `name` declares it
+    pub(super) fn check_override(
+        &self,
+        name: &str,
+        parent: &Option<String>,
+        class: &CompiledClass,
+        method: &Method,
+    ) -> Result<(), CompileError> {
+        let has_override_attr = method
+            .attributes
+            .iter()
+            .any(|attr| attr.name == "Override" || attr.name == "\\Override");
+        if !has_override_attr {
+            return Ok(());
+        }
+
+        let mut current_parent = parent.clone();
+        while let Some(parent_class) = current_parent.and_then(|p| self.classes.get(&p)) {
+            if parent_class.methods.contains_key(&method.name)
+                || parent_class.static_methods.contains_key(&method.name)
+            {
+                return Ok(());
+            }
+            current_parent = parent_class.parent.clone();
+        }
+
+        let in_interface = class.interfaces.iter().any(|iface_name| {
+            self.interfaces.get(iface_name).is_some_and(|iface_def| {
+                iface_def
+                    .method_signatures
+                    .iter()
+                    .any(|(name, _)| name == &method.name)
+            })
+        });
+        let in_trait = class.traits.iter().any(|trait_name| {
+            self.traits
+                .get(trait_name)
+                .is_some_and(|trait_def| trait_def.methods.contains_key(&method.name))
+        });
+        if in_interface || in_trait {
+            return Ok(());
+        }
+
+        Err(self.invalid(format!(
+            "{}::{} has #[\\Override] attribute, but no matching parent method exists",
+            name, method.name
+        )))
+    }
+
+    /// Fail unless the concrete class `name` implements the abstract
+    /// methods of its parent and the methods of its interfaces
+    pub(super) fn check_implemented(
+        &self,
+        name: &str,
+        parent: &Option<String>,
+        interfaces: &[String],
+        class: &CompiledClass,
+    ) -> Result<(), CompileError> {
+        let has_method = |method_name: &String| {
+            class.methods.contains_key(method_name)
+                || class.static_methods.contains_key(method_name)
+        };
+
+        if let Some(parent_name) = parent {
+            if let Some(parent_class) = self.classes.get(parent_name) {
+                for (method_name, is_abs) in &parent_class.method_abstracts {
+                    if *is_abs && !has_method(method_name) {
+                        return Err(self.invalid(format!(
+                            "Class '{}' must implement abstract method '{}' from class '{}'",
+                            name, method_name, parent_name
+                        )));
+                    }
+                }
+            }
+        }
+
+        for interface in interfaces {
+            if let Some(iface_def) = self.interfaces.get(interface) {
+                for (method_name, _param_count) in &iface_def.method_signatures {
+                    if !has_method(method_name) {
+                        return Err(self.invalid(format!(
+                            "Class '{}' does not implement method '{}' from interface '{}'",
+                            name, method_name, interface
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
-use super::Compiler;
+use super::class_constants::ConstantScope;
+use super::{scans, CompileError, Compiler};
 
-use crate::ast::{Attribute, Method, QualifiedName, TraitUse};
+use crate::ast::{Attribute, InterfaceConstant, Method, QualifiedName, TraitUse};
 use crate::vm::opcode::Opcode;
 use std::sync::Arc;
 
@@ -15,10 +16,11 @@ impl Compiler {
         parent: &Option<QualifiedName>,
         interfaces: &[QualifiedName],
         trait_uses: &[TraitUse],
+        constants: &[InterfaceConstant],
         properties: &[crate::ast::Property],
         methods: &[Method],
         attributes: &[Attribute],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         use crate::vm::class::CompiledClass;
 
         let qualified_name = if let Some(ref ns) = self.current_namespace {
@@ -31,21 +33,14 @@ impl Compiler {
 
         if let Some(ref parent_name) = resolved_parent {
             let parent_name_str: &str = parent_name.as_str();
-            let is_builtin = matches!(
-                parent_name_str,
-                "Exception"
-                    | "Error"
-                    | "TypeError"
-                    | "InvalidArgumentException"
-                    | "UnhandledMatchError"
-            );
+            let is_builtin = crate::vm::class_registration::is_builtin_throwable(parent_name_str);
 
             if let Some(parent_class) = self.classes.get(parent_name) {
                 if parent_class.is_final {
-                    return Err(format!("cannot extend final class {}", parent_name));
+                    return Err(self.invalid(format!("cannot extend final class {}", parent_name)));
                 }
             } else if !is_builtin {
-                return Err(format!("Parent class '{}' not found", parent_name));
+                return Err(self.invalid(format!("Parent class '{}' not found", parent_name)));
             }
         }
 
@@ -61,16 +56,32 @@ impl Compiler {
         compiled_class.parent = resolved_parent.clone();
         compiled_class.interfaces = resolved_interfaces.clone();
         compiled_class.traits = trait_uses.iter().flat_map(|t| t.traits.clone()).collect();
-        compiled_class.attributes = attributes.to_vec();
+        compiled_class.constant_order = constants.iter().map(|c| c.name.clone()).collect();
+        compiled_class.method_order = methods.iter().map(|m| m.name.clone()).collect();
 
         for iface_name in &resolved_interfaces {
             if !self.interfaces.contains_key(iface_name) {
-                return Err(format!("Interface '{}' not found", iface_name));
+                return Err(self.invalid(format!("Interface '{}' not found", iface_name)));
             }
         }
 
+        let inherited =
+            self.inherited_constants(resolved_parent.iter().chain(&resolved_interfaces));
+        let constants = self.constant_values(
+            &qualified_name,
+            resolved_parent.as_deref(),
+            inherited,
+            constants,
+        )?;
+        let scope = ConstantScope {
+            name: &qualified_name,
+            parent: resolved_parent.as_deref(),
+            constants: &constants,
+        };
+        compiled_class.attributes = self.link_attributes(attributes, Some(&scope));
+
         for prop in properties {
-            let mut compiled_prop = crate::vm::class::CompiledProperty::from_ast(prop, readonly);
+            let mut compiled_prop = self.compile_property(prop, readonly, &scope)?;
 
             for hook in &prop.hooks {
                 let hook_method_name = match hook.hook_type {
@@ -82,7 +93,7 @@ impl Compiler {
                     }
                 };
 
-                let mut hook_compiler = Compiler::new(hook_method_name.clone());
+                let mut hook_compiler = self.child_compiler(hook_method_name.clone());
 
                 hook_compiler.locals.insert("this".to_string(), 0);
                 hook_compiler.function.local_names.push("this".to_string());
@@ -119,6 +130,7 @@ impl Compiler {
                 for (inner_name, inner_func) in hook_compiler.functions.drain() {
                     self.functions.insert(inner_name, inner_func);
                 }
+                self.adopt_deprecations(&mut hook_compiler);
 
                 let compiled_hook = Arc::new(hook_compiler.function);
 
@@ -166,8 +178,8 @@ impl Compiler {
                             default: None,
                             readonly: param.readonly || readonly,
                             is_static: false,
-                            type_hint: None,
-                            attributes: param.attributes.clone(),
+                            type_hint: param.type_hint.clone(),
+                            attributes: self.link_attributes(&param.attributes, Some(&scope)),
                             get_hook: None,
                             set_hook: None,
                         };
@@ -208,11 +220,11 @@ impl Compiler {
             if defining_traits.len() > 1 {
                 let class_defines_method = methods.iter().any(|m| &m.name == method_name);
                 if !class_defines_method {
-                    return Err(format!(
+                    return Err(self.invalid(format!(
                         "Trait method conflict: {} is defined in multiple traits ({})",
                         method_name,
                         defining_traits.join(", ")
-                    ));
+                    )));
                 }
             }
         }
@@ -223,7 +235,7 @@ impl Compiler {
             .collect();
         let mut interfaces: Vec<String> = interfaces_to_check
             .iter()
-            .filter_map(|n| self.interfaces.get(*n).map(|iface| iface.name.clone()))
+            .filter_map(|n| self.interfaces.get(n).map(|iface| iface.name.clone()))
             .collect();
 
         let mut parent_interfaces: Vec<String> = Vec::new();
@@ -247,10 +259,10 @@ impl Compiler {
 
         for method in methods {
             if method.is_abstract && !is_abstract {
-                return Err(format!(
+                return Err(self.invalid(format!(
                     "Cannot declare method {}::{} as abstract if class is not abstract",
                     name, method.name
-                ));
+                )));
             }
 
             if method.is_final && !is_abstract {
@@ -270,73 +282,19 @@ impl Compiler {
                             .copied()
                             .unwrap_or(false)
                     {
-                        return Err(format!(
+                        return Err(self.invalid(format!(
                             "Cannot override final method {}::{}",
                             parent_name, method.name
-                        ));
+                        )));
                     }
                 }
             }
 
-            let has_override_attr = method
-                .attributes
-                .iter()
-                .any(|attr| attr.name == "Override" || attr.name == "\\Override");
-
-            if has_override_attr {
-                let mut found_parent_method = false;
-
-                let mut current_parent = resolved_parent.clone();
-                while let Some(parent_name) = current_parent {
-                    if let Some(parent_class) = self.classes.get(&parent_name) {
-                        if parent_class.methods.contains_key(&method.name)
-                            || parent_class.static_methods.contains_key(&method.name)
-                        {
-                            found_parent_method = true;
-                            break;
-                        }
-                        current_parent = parent_class.parent.clone();
-                    } else {
-                        break;
-                    }
-                }
-
-                if !found_parent_method {
-                    for iface_name in &compiled_class.interfaces {
-                        if let Some(iface_def) = self.interfaces.get(iface_name) {
-                            if iface_def
-                                .method_signatures
-                                .iter()
-                                .any(|(name, _)| name == &method.name)
-                            {
-                                found_parent_method = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                if !found_parent_method {
-                    for trait_name in &compiled_class.traits {
-                        if let Some(trait_def) = self.traits.get(trait_name) {
-                            if trait_def.methods.contains_key(&method.name) {
-                                found_parent_method = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                if !found_parent_method {
-                    return Err(format!(
-                        "{}::{} has #[\\Override] attribute, but no matching parent method exists",
-                        name, method.name
-                    ));
-                }
-            }
+            self.check_override(name, &resolved_parent, &compiled_class, method)?;
 
             let method_name = format!("{}::{}", qualified_name, method.name);
-            let mut method_compiler = Compiler::new(method_name.clone());
+            self.check_parameter_defaults(&method_name, &method.params);
+            let mut method_compiler = self.child_compiler(method_name.clone());
 
             // Copy namespace, use aliases, and class context from parent compiler
             method_compiler.current_namespace = self.current_namespace.clone();
@@ -375,14 +333,17 @@ impl Compiler {
                 .map(|t| method_compiler.resolve_type_hint(t));
             method_compiler.function.is_variadic = method.params.iter().any(|p| p.is_variadic);
 
-            method_compiler.function.parameters = method.params.clone();
-            method_compiler.function.attributes = method.attributes.clone();
+            method_compiler.function.parameters = self.link_params(&method.params, Some(&scope));
+            method_compiler.function.attributes =
+                self.link_attributes(&method.attributes, Some(&scope));
 
             for param in &method.params {
-                method_compiler
-                    .function
-                    .param_types
-                    .push(param.type_hint.clone());
+                method_compiler.function.param_types.push(
+                    param
+                        .type_hint
+                        .as_ref()
+                        .map(|t| method_compiler.resolve_type_hint(t)),
+                );
             }
 
             for (i, param) in method.params.iter().enumerate() {
@@ -403,10 +364,10 @@ impl Compiler {
             if method.name == "__construct" && !method.is_static {
                 for (i, param) in method.params.iter().enumerate() {
                     if param.visibility.is_some() {
-                        let slot = param_start + i as u16;
-                        method_compiler.emit(Opcode::LoadFast(slot));
+                        method_compiler.emit(Opcode::LoadFast(param_start + i as u16));
                         let prop_idx = method_compiler.intern_string(param.name.clone());
                         method_compiler.emit(Opcode::StoreThisProperty(prop_idx));
+                        method_compiler.emit(Opcode::Pop);
                     }
                 }
             }
@@ -415,11 +376,14 @@ impl Compiler {
                 method_compiler.compile_stmt(stmt)?;
             }
 
+            method_compiler.function.is_generator = scans::contains_yield(&method.body);
+
             method_compiler.emit(Opcode::ReturnNull);
 
             for (inner_name, inner_func) in method_compiler.functions.drain() {
                 self.functions.insert(inner_name, inner_func);
             }
+            self.adopt_deprecations(&mut method_compiler);
 
             let compiled = Arc::new(method_compiler.function);
             compiled_class
@@ -442,41 +406,10 @@ impl Compiler {
         }
 
         if !is_abstract {
-            if let Some(ref parent_name) = resolved_parent {
-                if let Some(parent_class) = self.classes.get(parent_name) {
-                    for (method_name, is_abs) in &parent_class.method_abstracts {
-                        if *is_abs {
-                            let has_method = compiled_class.methods.contains_key(method_name)
-                                || compiled_class.static_methods.contains_key(method_name);
-                            if !has_method {
-                                return Err(format!(
-                                    "Class '{}' must implement abstract method '{}' from class '{}'",
-                                    name, method_name, parent_name
-                                ));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        if !is_abstract {
-            for interface in interfaces {
-                if let Some(iface_def) = self.interfaces.get(&interface) {
-                    for (method_name, _param_count) in &iface_def.method_signatures {
-                        let has_method = compiled_class.methods.contains_key(method_name)
-                            || compiled_class.static_methods.contains_key(method_name);
-                        if !has_method {
-                            return Err(format!(
-                                "Class '{}' does not implement method '{}' from interface '{}'",
-                                name, method_name, interface
-                            ));
-                        }
-                    }
-                }
-            }
+            self.check_implemented(name, &resolved_parent, &interfaces, &compiled_class)?;
         }
 
+        compiled_class.constants = constants;
         self.classes
             .insert(qualified_name, Arc::new(compiled_class));
         Ok(())
@@ -0,0 +1,202 @@
+//! Class constants and the other constant expressions of a declaration
+//!
+//! Class and interface constants, property defaults and attribute
+//! arguments are evaluated when their declaration is compiled. They may
+//! refer to the constants of the declaration itself (`self::NAME`), of
+//! its parent (`parent::NAME`), and of the classes, interfaces and enums
+//! compiled before it. A class's constants include those it inherits, so
+//! `Child::NAME` finds a constant of `Parent` without walking the chain.
+
+use super::{const_expr, CompileError, Compiler};
+use crate::ast::{Attribute, AttributeArgument, Expr, FunctionParam, InterfaceConstant, Property};
+use crate::runtime::Value;
+use crate::vm::class::CompiledProperty;
+use std::collections::HashMap;
+
+/// The declaration a constant expression belongs to
+pub(super) struct ConstantScope<'a> {
+    /// Its qualified name, which `self` and `static` stand for
+    pub name: &'a str,
+    /// The class `parent` stands for
+    pub parent: Option<&'a str>,
+    /// Its constants known so far, inherited ones included
+    pub constants: &'a HashMap<String, Value>,
+}
+
+impl Compiler {
+    /// The constant `item` of a class, interface or enum compiled so far,
+    /// or the case `item` of an enum
+    pub(super) fn class_constant(&self, class: &str, item: &str) -> Option<Value> {
+        if let Some(enum_def) = self.enums.get(class) {
+            if let Some(backing) = enum_def.cases.get(item) {
+                return Some(Value::EnumCase {
+                    enum_name: enum_def.name.clone(),
+                    case_name: item.to_string(),
+                    backing_value: backing.clone().map(Box::new),
+                });
+            }
+            return enum_def.constants.get(item).cloned();
+        }
+        if let Some(class_def) = self.classes.get(class) {
+            return class_def.constants.get(item).cloned();
+        }
+        self.interfaces.get(class)?.constants.get(item).cloned()
+    }
+
+    /// The value of a constant expression, if it is one
+    pub(super) fn evaluate_constant(
+        &self,
+        expr: &Expr,
+        scope: Option<&ConstantScope>,
+    ) -> Option<Value> {
+        const_expr::evaluate(expr, &|class, item| match (class, scope) {
+            ("self" | "static", Some(scope)) if item == "class" => {
+                Some(Value::String(scope.name.to_string()))
+            }
+            ("parent", Some(scope)) if item == "class" => {
+                Some(Value::String(scope.parent?.to_string()))
+            }
+            (_, _) if item == "class" => Some(Value::String(self.qualify_class_name(class))),
+            ("self" | "static", Some(scope)) => scope.constants.get(item).cloned(),
+            ("parent", Some(scope)) => self.class_constant(scope.parent?, item),
+            _ => {
+                let qualified = self.qualify_class_name(class);
+                match scope {
+                    Some(scope) if qualified.eq_ignore_ascii_case(scope.name) => {
+                        scope.constants.get(item).cloned()
+                    }
+                    _ => self
+                        .class_constant(&qualified, item)
+                        .or_else(|| self.class_constant(class.trim_start_matches('\\'), item)),
+                }
+            }
+        })
+    }
+
+    /// The constants of the declaration `name`: `inherited`, then its own
+    /// `constants`, which may refer to each other in any order
+    pub(super) fn constant_values(
+        &self,
+        name: &str,
+        parent: Option<&str>,
+        inherited: HashMap<String, Value>,
+        constants: &[InterfaceConstant],
+    ) -> Result<HashMap<String, Value>, CompileError> {
+        let mut values = inherited;
+        let mut pending: Vec<&InterfaceConstant> = constants.iter().collect();
+        for constant in &pending {
+            values.remove(&constant.name);
+        }
+        loop {
+            let scope = ConstantScope {
+                name,
+                parent,
+                constants: &values,
+            };
+            let evaluated: Vec<(String, Value)> = pending
+                .iter()
+                .filter_map(|constant| {
+                    let value = self.evaluate_constant(&constant.value, Some(&scope))?;
+                    Some((constant.name.clone(), value))
+                })
+                .collect();
+            if evaluated.is_empty() {
+                break;
+            }
+            pending.retain(|constant| !evaluated.iter().any(|(n, _)| *n == constant.name));
+            values.extend(evaluated);
+        }
+        match pending.first() {
+            Some(constant) => Err(self.invalid(format!(
+                "Constant expression of {}::{} contains invalid operations",
+                name, constant.name
+            ))),
+            None => Ok(values),
+        }
+    }
+
+    /// The constants a class or interface inherits from `parents`
+    pub(super) fn inherited_constants<'a>(
+        &self,
+        parents: impl IntoIterator<Item = &'a String>,
+    ) -> HashMap<String, Value> {
+        let mut inherited = HashMap::new();
+        for parent in parents {
+            let constants = match self.classes.get(parent) {
+                Some(class_def) => &class_def.constants,
+                None => match self.interfaces.get(parent) {
+                    Some(interface) => &interface.constants,
+                    None => continue,
+                },
+            };
+            for (name, value) in constants {
+                inherited
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        inherited
+    }
+
+    /// A property of the declaration `scope`, with its default evaluated
+    pub(super) fn compile_property(
+        &self,
+        prop: &Property,
+        readonly_class: bool,
+        scope: &ConstantScope,
+    ) -> Result<CompiledProperty, CompileError> {
+        let default = match &prop.default {
+            Some(expr) => Some(self.evaluate_constant(expr, Some(scope)).ok_or_else(|| {
+                self.invalid(format!(
+                    "Constant expression of {}::${} contains invalid operations",
+                    scope.name, prop.name
+                ))
+            })?),
+            None => None,
+        };
+        let mut compiled_prop = CompiledProperty::from_ast(prop, readonly_class, default);
+        compiled_prop.attributes = self.link_attributes(&prop.attributes, Some(scope));
+        Ok(compiled_prop)
+    }
+
+    /// `params` with their attributes linked
+    pub(super) fn link_params(
+        &self,
+        params: &[FunctionParam],
+        scope: Option<&ConstantScope>,
+    ) -> Vec<FunctionParam> {
+        params
+            .iter()
+            .map(|param| FunctionParam {
+                attributes: self.link_attributes(&param.attributes, scope),
+                ..param.clone()
+            })
+            .collect()
+    }
+
+    /// `attributes` with the arguments that are constant expressions
+    /// replaced by their values, for reflection to read back
+    pub(super) fn link_attributes(
+        &self,
+        attributes: &[Attribute],
+        scope: Option<&ConstantScope>,
+    ) -> Vec<Attribute> {
+        attributes
+            .iter()
+            .map(|attribute| Attribute {
+                name: attribute.name.clone(),
+                arguments: attribute
+                    .arguments
+                    .iter()
+                    .map(|argument| AttributeArgument {
+                        name: argument.name.clone(),
+                        value: self
+                            .evaluate_constant(&argument.value, scope)
+                            .and_then(|value| const_expr::literal(&value))
+                            .unwrap_or_else(|| argument.value.clone()),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
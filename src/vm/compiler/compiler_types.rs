@@ -85,7 +85,11 @@ impl Compiler {
             TypeHint::Void => TypeHint::Void,
             TypeHint::Never => TypeHint::Never,
             TypeHint::Static => TypeHint::Static,
-            TypeHint::SelfType => TypeHint::SelfType,
+            // A trait's `self` is the class using it, known only at run time
+            TypeHint::SelfType => match (&self.current_class, &self.current_trait) {
+                (Some(class), None) => TypeHint::Class(class.clone()),
+                _ => TypeHint::SelfType,
+            },
             TypeHint::ParentType => TypeHint::ParentType,
         }
     }
@@ -0,0 +1,193 @@
+//! Constant expressions
+//!
+//! The values the compiler can work out from an expression alone:
+//! literals, arrays of them, the arithmetic, string, bitwise and logical
+//! operators over them, the core constants (`E_ALL`), and the constants
+//! `lookup` resolves, given the class and the name of a `Class::NAME`
+//! (the class may be `self`, and the name is `class` for `Class::class`).
+//! Anything else, or an operation that would fail at runtime, has no
+//! constant value.
+
+use crate::ast::{ArrayElement, BinaryOp, Expr, UnaryOp};
+use crate::runtime::builtins;
+use crate::runtime::{ArrayKey, PhpArray, Value};
+
+/// The value of a constant expression, if it is one
+pub(crate) fn evaluate(expr: &Expr, lookup: &dyn Fn(&str, &str) -> Option<Value>) -> Option<Value> {
+    match expr {
+        Expr::Integer(n) => Some(Value::Integer(*n)),
+        Expr::Float(f) => Some(Value::Float(*f)),
+        Expr::String(s) => Some(Value::String(s.clone())),
+        Expr::Bool(b) => Some(Value::Bool(*b)),
+        Expr::Null => Some(Value::Null),
+        Expr::Grouped(inner) => evaluate(inner, lookup),
+        Expr::EnumCase {
+            enum_name,
+            case_name,
+        } => lookup(enum_name, case_name),
+        Expr::ClassReference(class) => lookup(class, "class"),
+        Expr::Constant(name) => builtins::core_constant(name),
+        Expr::Array(elements) => {
+            let mut entries = PhpArray::new();
+            for element in elements {
+                let value = evaluate(&element.value, lookup)?;
+                match &element.key {
+                    Some(key) => {
+                        entries.insert(ArrayKey::from_value(&evaluate(key, lookup)?), value);
+                    }
+                    None => entries.push(value).ok()?,
+                }
+            }
+            Some(Value::Array(entries))
+        }
+        Expr::Unary { op, expr } => unary(op, evaluate(expr, lookup)?),
+        Expr::Binary { left, op, right } => {
+            binary(op, evaluate(left, lookup)?, evaluate(right, lookup)?)
+        }
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            let condition = evaluate(condition, lookup)?;
+            match then_expr {
+                _ if !condition.to_bool() => evaluate(else_expr, lookup),
+                Some(then_expr) => evaluate(then_expr, lookup),
+                None => Some(condition),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// An expression that evaluates back to `value`, if there is one
+pub(super) fn literal(value: &Value) -> Option<Expr> {
+    let expr = match value {
+        Value::Integer(n) => Expr::Integer(*n),
+        Value::Float(f) => Expr::Float(*f),
+        Value::String(s) => Expr::String(s.clone()),
+        Value::Bool(b) => Expr::Bool(*b),
+        Value::Null => Expr::Null,
+        Value::Array(entries) => Expr::Array(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        ArrayKey::Integer(n) => Expr::Integer(*n),
+                        ArrayKey::String(s) => Expr::String(s.clone()),
+                    };
+                    Some(ArrayElement {
+                        key: Some(Box::new(key)),
+                        value: Box::new(literal(value)?),
+                    })
+                })
+                .collect::<Option<_>>()?,
+        ),
+        Value::EnumCase {
+            enum_name,
+            case_name,
+            ..
+        } => Expr::EnumCase {
+            enum_name: enum_name.clone(),
+            case_name: case_name.clone(),
+        },
+        _ => return None,
+    };
+    Some(expr)
+}
+
+fn unary(op: &UnaryOp, value: Value) -> Option<Value> {
+    match (op, &value) {
+        (UnaryOp::Neg, Value::Integer(n)) => Some(
+            n.checked_neg()
+                .map_or(Value::Float(-(*n as f64)), Value::Integer),
+        ),
+        (UnaryOp::Neg, Value::Float(f)) => Some(Value::Float(-f)),
+        (UnaryOp::Not, _) => Some(Value::Bool(!value.to_bool())),
+        (UnaryOp::BitwiseNot, Value::Integer(n)) => Some(Value::Integer(!n)),
+        _ => None,
+    }
+}
+
+fn binary(op: &BinaryOp, left: Value, right: Value) -> Option<Value> {
+    let value = match op {
+        BinaryOp::Add => arithmetic(&left, &right, i64::checked_add, |a, b| a + b)?,
+        BinaryOp::Sub => arithmetic(&left, &right, i64::checked_sub, |a, b| a - b)?,
+        BinaryOp::Mul => arithmetic(&left, &right, i64::checked_mul, |a, b| a * b)?,
+        BinaryOp::Div => match (&left, &right) {
+            (_, divisor) if divisor.to_float() == 0.0 => return None,
+            (Value::Integer(a), Value::Integer(b)) if a.checked_rem(*b) == Some(0) => {
+                Value::Integer(a / b)
+            }
+            _ => Value::Float(left.to_float() / right.to_float()),
+        },
+        BinaryOp::Mod => Value::Integer(left.to_int().checked_rem(right.to_int())?),
+        BinaryOp::Pow => match (&left, &right) {
+            (Value::Integer(a), Value::Integer(b)) if *b >= 0 => u32::try_from(*b)
+                .ok()
+                .and_then(|b| a.checked_pow(b))
+                .map_or(Value::Float((*a as f64).powf(*b as f64)), Value::Integer),
+            _ => Value::Float(left.to_float().powf(right.to_float())),
+        },
+        BinaryOp::Concat => Value::String(left.to_string_val() + &right.to_string_val()),
+        BinaryOp::BitwiseAnd => Value::Integer(integer(&left)? & integer(&right)?),
+        BinaryOp::BitwiseOr => Value::Integer(integer(&left)? | integer(&right)?),
+        BinaryOp::BitwiseXor => Value::Integer(integer(&left)? ^ integer(&right)?),
+        BinaryOp::ShiftLeft | BinaryOp::ShiftRight => {
+            let (value, count) = (integer(&left)?, integer(&right)?);
+            match (op, count) {
+                (_, ..0) => return None,
+                (BinaryOp::ShiftLeft, 64..) => Value::Integer(0),
+                (BinaryOp::ShiftLeft, _) => Value::Integer(value << count),
+                (_, 64..) => Value::Integer(value >> 63),
+                _ => Value::Integer(value >> count),
+            }
+        }
+        BinaryOp::Equal => Value::Bool(left.loose_equals(&right)),
+        BinaryOp::NotEqual => Value::Bool(!left.loose_equals(&right)),
+        BinaryOp::Identical => Value::Bool(left == right),
+        BinaryOp::NotIdentical => Value::Bool(left != right),
+        BinaryOp::And => Value::Bool(left.to_bool() && right.to_bool()),
+        BinaryOp::Or => Value::Bool(left.to_bool() || right.to_bool()),
+        BinaryOp::Xor => Value::Bool(left.to_bool() != right.to_bool()),
+        BinaryOp::NullCoalesce => match left {
+            Value::Null => right,
+            _ => left,
+        },
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// `+`, `-` or `*`: integers stay integers unless the result overflows
+fn arithmetic(
+    left: &Value,
+    right: &Value,
+    int_op: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+) -> Option<Value> {
+    if !is_number(left) || !is_number(right) {
+        return None;
+    }
+    let float = || Value::Float(float_op(left.to_float(), right.to_float()));
+    Some(match (left, right) {
+        (Value::Integer(a), Value::Integer(b)) => int_op(*a, *b).map_or_else(float, Value::Integer),
+        _ => float(),
+    })
+}
+
+/// The operand of a bitwise operator, which must be an integer here
+fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Integer(n) => Some(*n),
+        Value::Bool(_) | Value::Null => Some(value.to_int()),
+        _ => None,
+    }
+}
+
+fn is_number(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Integer(_) | Value::Float(_) | Value::Bool(_) | Value::Null
+    )
+}
@@ -0,0 +1,153 @@
+//! Definite assignment analysis for undefined variable warnings
+//!
+//! Reading a variable that was never assigned prints "Warning: Undefined
+//! variable". Checking costs a little at runtime, so variable reads only
+//! compile to the checked loads (`LoadFastChecked`, `LoadVarChecked`)
+//! where the variable might not be assigned yet. A variable is definitely
+//! assigned if it is a parameter, a captured variable or `$this`, or if a
+//! statement at the top level of the body assigned it earlier; whatever
+//! is assigned inside a branch or a loop may not have been.
+//!
+//! Reads in `isset()`, `empty()` and on the left of `??` never warn,
+//! neither about the variable nor about missing array keys.
+
+use super::globals_array::is_globals;
+use super::{CompileError, Compiler};
+
+use crate::ast::{Expr, Stmt};
+use crate::vm::opcode::Opcode;
+use std::collections::HashSet;
+
+/// Variables PHP defines in every scope
+pub(super) const SUPERGLOBALS: &[&str] = &[
+    "GLOBALS", "_SERVER", "_GET", "_POST", "_FILES", "_COOKIE", "_SESSION", "_REQUEST", "_ENV",
+];
+
+/// Built-ins whose arguments are read without warnings
+const QUIET_FUNCTIONS: &[&str] = &["isset", "empty"];
+
+impl Compiler {
+    /// Variables definitely assigned before the statement being compiled,
+    /// seeded with the locals that exist before the body (parameters,
+    /// captures and `$this`)
+    fn definitely_assigned(&mut self) -> &mut HashSet<String> {
+        let locals = &self.locals;
+        self.assigned
+            .get_or_insert_with(|| locals.keys().cloned().collect())
+    }
+
+    /// Compile a statement, tracking what the body's top-level statements
+    /// assign
+    pub(crate) fn compile_tracked_stmt(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        self.definitely_assigned();
+        self.stmt_depth += 1;
+        let result = self.compile_stmt_internal(stmt);
+        self.stmt_depth -= 1;
+        if self.stmt_depth == 0 {
+            let mut names = Vec::new();
+            match stmt {
+                Stmt::Expression(expr) => assigned_by(expr, &mut names),
+                Stmt::Global(globals) => names.extend(globals.iter().cloned()),
+                _ => {}
+            }
+            self.definitely_assigned().extend(names);
+        }
+        result
+    }
+
+    /// Whether `name` is surely assigned by the time the statement being
+    /// compiled runs
+    pub(crate) fn is_definitely_assigned(&mut self, name: &str) -> bool {
+        self.definitely_assigned().contains(name)
+    }
+
+    /// Load a variable a script reads, warning if it may be undefined
+    pub(crate) fn emit_load_variable(&mut self, name: &str) {
+        if name == "GLOBALS" {
+            self.emit(Opcode::LoadGlobals);
+            return;
+        }
+        let checked = !SUPERGLOBALS.contains(&name) && !self.definitely_assigned().contains(name);
+        match (self.locals.get(name).copied(), checked) {
+            (Some(slot), false) => self.emit(Opcode::LoadFast(slot)),
+            (Some(slot), true) => self.emit(Opcode::LoadFastChecked(slot)),
+            (None, false) => {
+                let idx = self.intern_string(name.to_string());
+                self.emit(Opcode::LoadVar(idx))
+            }
+            (None, true) => {
+                let idx = self.intern_string(name.to_string());
+                self.emit(Opcode::LoadVarChecked(idx))
+            }
+        };
+    }
+
+    /// Compile an expression that is read without undefined variable or
+    /// array key warnings, as `isset()` and `??` read it (index
+    /// expressions still warn)
+    pub(crate) fn compile_quiet_fetch(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            chain if chain.is_nullsafe_chain() => self.compile_nullsafe_chain(chain, true)?,
+            Expr::Variable(name) if name == "GLOBALS" => self.emit_load_variable(name),
+            Expr::ArrayAccess { array, index } if is_globals(array) => {
+                self.compile_global_fetch(index, true)?;
+            }
+            Expr::Variable(name) => {
+                match self.locals.get(name) {
+                    Some(&slot) => self.emit(Opcode::LoadFast(slot)),
+                    None => {
+                        let idx = self.intern_string(name.clone());
+                        self.emit(Opcode::LoadVar(idx))
+                    }
+                };
+            }
+            Expr::ArrayAccess { array, index } => {
+                self.compile_quiet_fetch(array)?;
+                self.compile_expr(index)?;
+                self.emit(Opcode::ArrayGet);
+            }
+            Expr::PropertyAccess { object, property } => {
+                self.compile_expr(object)?;
+                let prop_idx = self.intern_string(property.clone());
+                self.emit(Opcode::LoadPropertyQuiet(prop_idx));
+            }
+            Expr::Grouped(inner) => self.compile_quiet_fetch(inner)?,
+            _ => self.compile_expr(expr)?,
+        }
+        Ok(())
+    }
+
+    /// Whether the arguments of a call to `name` are read quietly
+    pub(crate) fn is_quiet_call(name: &str) -> bool {
+        QUIET_FUNCTIONS.contains(&name.to_lowercase().as_str())
+    }
+}
+
+/// Variables an expression statement always assigns
+fn assigned_by(expr: &Expr, names: &mut Vec<String>) {
+    match expr {
+        Expr::Assign { var, value, .. } => {
+            names.push(var.clone());
+            assigned_by(value, names);
+        }
+        Expr::AssignRef { var, source } => {
+            names.push(var.clone());
+            // Binding an element defines the array's variable too
+            let mut source = source.as_ref();
+            while let Expr::ArrayAccess { array, .. } = source {
+                source = array;
+            }
+            if let Expr::Variable(name) = source {
+                names.push(name.clone());
+            }
+        }
+        Expr::ArrayAssign { array, value, .. } => {
+            if let Expr::Variable(name) = array.as_ref() {
+                names.push(name.clone());
+            }
+            assigned_by(value, names);
+        }
+        Expr::Grouped(inner) => assigned_by(inner, names),
+        _ => {}
+    }
+}
@@ -0,0 +1,51 @@
+//! Deprecated constructs found while compiling
+//!
+//! They are reported when the compiled code is loaded (see
+//! `vm::deprecation`), at the line of the statement that declares them.
+
+use super::Compiler;
+
+use crate::ast::{Expr, FunctionParam, TypeHint};
+use crate::vm::deprecation::{CompileDeprecation, Deprecation};
+
+impl Compiler {
+    /// Note the parameters of `function` that default to `null` but
+    /// whose type doesn't accept it
+    pub(crate) fn check_parameter_defaults(&mut self, function: &str, params: &[FunctionParam]) {
+        for param in params {
+            let Some(type_hint) = &param.type_hint else {
+                continue;
+            };
+            if matches!(param.default, Some(Expr::Null)) && !admits_null(type_hint) {
+                self.deprecations.push(CompileDeprecation {
+                    kind: Deprecation::ImplicitlyNullableParameter,
+                    message: format!(
+                        "{}(): Implicitly marking parameter ${} as nullable is deprecated, \
+                         the explicit nullable type must be used instead",
+                        function, param.name
+                    ),
+                    file: self.current_file_path.clone(),
+                    line: self.line,
+                });
+            }
+        }
+    }
+
+    /// Take over what the compiler of a nested body found
+    pub(crate) fn adopt_deprecations(&mut self, child: &mut Compiler) {
+        self.deprecations.append(&mut child.deprecations);
+    }
+}
+
+/// Whether `null` is a value of the type
+fn admits_null(type_hint: &TypeHint) -> bool {
+    match type_hint {
+        TypeHint::Nullable(_) => true,
+        TypeHint::Simple(name) => matches!(name.to_lowercase().as_str(), "null" | "mixed"),
+        TypeHint::Union(types) => types.iter().any(admits_null),
+        TypeHint::DNF(groups) => groups
+            .iter()
+            .any(|group| group.len() == 1 && admits_null(&group[0])),
+        _ => false,
+    }
+}
@@ -0,0 +1,262 @@
+//! Compiling assignments to array elements: `$a[$k] = $v`, `$a[] = $v`,
+//! `$a['x']['y'] += $v` and `$o->list[$k] ??= $v`
+//!
+//! The target is split into what holds the outermost array (a variable,
+//! a property, a static property or a `$GLOBALS` element) and the keys
+//! down to the element. `AssignDimFast` changes a local's array in place;
+//! any other holder's array is loaded after the keys and the value, and
+//! stored back once `AssignDim` has changed it. A compound assignment
+//! evaluates the keys (and the object or global name holding the array)
+//! once, into temporaries it reads the element through and frees after.
+//! The assignment's value is the value assigned.
+
+use super::globals_array::is_globals;
+use super::{CompileError, Compiler};
+use crate::ast::{AssignOp, Expr};
+use crate::vm::opcode::Opcode;
+
+/// What holds the array an element assignment writes to
+enum Holder<'e> {
+    /// A variable with a local slot
+    Local(&'e str),
+    /// A variable outside the local slots
+    Named(&'e str),
+    /// A property of `$this`
+    ThisProperty(&'e str),
+    /// A property of another object, kept in a temporary: the temporary's
+    /// name, and the property
+    Property(String, &'e str),
+    /// A static property: class and property
+    StaticProperty(&'e str, &'e str),
+    /// `$GLOBALS[$name]`, with the name kept in a temporary
+    Global(String),
+}
+
+impl Compiler {
+    /// Compile `$array[$index] op= $value`, or `$array[] = $value` if there
+    /// is no index
+    pub(crate) fn compile_array_assign(
+        &mut self,
+        array: &Expr,
+        index: &Option<Box<Expr>>,
+        op: &AssignOp,
+        value: &Expr,
+    ) -> Result<(), CompileError> {
+        if is_globals(array) {
+            return self.compile_global_assign(index, op, value);
+        }
+        if index.is_none() && *op != AssignOp::Assign {
+            return Err(self.invalid("Cannot use [] for reading"));
+        }
+
+        // The keys, outermost first, down from what holds the array
+        let mut keys: Vec<&Expr> = index.iter().map(|index| index.as_ref()).collect();
+        let mut holder = array;
+        while let Expr::ArrayAccess { array, index } = holder {
+            if is_globals(array) {
+                break;
+            }
+            keys.push(index);
+            holder = array;
+        }
+        keys.reverse();
+        let depth =
+            u8::try_from(keys.len()).map_err(|_| self.unsupported("Too many nested array keys"))?;
+        let append = index.is_none();
+
+        let site = self.current_offset();
+        let mut temps = Vec::new();
+        let holder = self.compile_holder(holder, site, &mut temps)?;
+
+        // A compound assignment reads the element through its keys' values
+        let read_keys = match op {
+            AssignOp::Assign => None,
+            _ => {
+                let mut names = Vec::new();
+                for (level, key) in keys.iter().enumerate() {
+                    let name = format!("__element_key_{}_{}__", site, level);
+                    self.compile_temporary(key, &name)?;
+                    temps.push(name.clone());
+                    names.push(name);
+                }
+                Some(names)
+            }
+        };
+
+        let mut skip_jump = None;
+        if let (AssignOp::NullCoalesceAssign, Some(names)) = (op, &read_keys) {
+            let element = Self::element_read(&holder, names);
+            self.compile_quiet_fetch(&element)?;
+            skip_jump = Some(self.emit_jump(Opcode::JumpIfNotNull(0)));
+            self.emit(Opcode::Pop);
+        }
+
+        match &read_keys {
+            Some(names) => {
+                for name in names {
+                    let slot = self.allocate_local(name.clone());
+                    self.emit(Opcode::LoadFast(slot));
+                }
+            }
+            None => {
+                for key in &keys {
+                    self.compile_expr(key)?;
+                }
+            }
+        }
+        match (op.binary_op(), &read_keys) {
+            (Some(binary), Some(names)) => {
+                let element = Self::element_read(&holder, names);
+                self.compile_expr(&Expr::Binary {
+                    left: Box::new(element),
+                    op: binary,
+                    right: Box::new(value.clone()),
+                })?;
+            }
+            _ => self.compile_expr(value)?,
+        }
+
+        self.compile_holder_write(&holder, depth, append);
+
+        if let Some(jump) = skip_jump {
+            self.patch_jump(jump);
+        }
+        for name in temps {
+            let slot = self.allocate_local(name);
+            self.emit(Opcode::UnsetFast(slot));
+        }
+        Ok(())
+    }
+
+    /// Work out what holds the array, evaluating the object or the global
+    /// name that does into a temporary
+    fn compile_holder<'e>(
+        &mut self,
+        holder: &'e Expr,
+        site: usize,
+        temps: &mut Vec<String>,
+    ) -> Result<Holder<'e>, CompileError> {
+        Ok(match holder {
+            Expr::Variable(name) if self.locals.contains_key(name) => Holder::Local(name),
+            Expr::Variable(name) => Holder::Named(name),
+            Expr::PropertyAccess { object, property } if matches!(**object, Expr::This) => {
+                Holder::ThisProperty(property)
+            }
+            Expr::PropertyAccess { object, property } => {
+                let name = format!("__element_object_{}__", site);
+                self.compile_temporary(object, &name)?;
+                temps.push(name.clone());
+                Holder::Property(name, property)
+            }
+            Expr::StaticPropertyAccess { class, property } => {
+                Holder::StaticProperty(class, property)
+            }
+            Expr::ArrayAccess { array, index } if is_globals(array) => {
+                let name = format!("__element_global_{}__", site);
+                self.compile_temporary(index, &name)?;
+                temps.push(name.clone());
+                Holder::Global(name)
+            }
+            target if target.is_nullsafe_chain() => {
+                return Err(self.invalid("Can't use nullsafe operator in write context"))
+            }
+            _ => return Err(self.invalid("Cannot use temporary expression in write context")),
+        })
+    }
+
+    /// Evaluate `expr` into the temporary local `name`
+    fn compile_temporary(&mut self, expr: &Expr, name: &str) -> Result<(), CompileError> {
+        self.compile_expr(expr)?;
+        let slot = self.allocate_local(name.to_string());
+        self.emit(Opcode::StoreFast(slot));
+        self.emit(Opcode::Pop);
+        Ok(())
+    }
+
+    /// The element at the keys in the temporaries `keys`, to read
+    fn element_read(holder: &Holder<'_>, keys: &[String]) -> Expr {
+        let mut element = match holder {
+            Holder::Local(name) | Holder::Named(name) => Expr::Variable(name.to_string()),
+            Holder::ThisProperty(property) => Expr::PropertyAccess {
+                object: Box::new(Expr::This),
+                property: property.to_string(),
+            },
+            Holder::Property(object, property) => Expr::PropertyAccess {
+                object: Box::new(Expr::Variable(object.clone())),
+                property: property.to_string(),
+            },
+            Holder::StaticProperty(class, property) => Expr::StaticPropertyAccess {
+                class: class.to_string(),
+                property: property.to_string(),
+            },
+            Holder::Global(name) => Expr::ArrayAccess {
+                array: Box::new(Expr::Variable("GLOBALS".to_string())),
+                index: Box::new(Expr::Variable(name.clone())),
+            },
+        };
+        for key in keys {
+            element = Expr::ArrayAccess {
+                array: Box::new(element),
+                index: Box::new(Expr::Variable(key.clone())),
+            };
+        }
+        element
+    }
+
+    /// With the keys and the value on the stack, assign the element and
+    /// leave the value
+    fn compile_holder_write(&mut self, holder: &Holder<'_>, depth: u8, append: bool) {
+        let container = match holder {
+            Holder::Local(name) => {
+                let slot = self.allocate_local(name.to_string());
+                self.emit(Opcode::AssignDimFast(slot, depth, append));
+                return;
+            }
+            Holder::Named(name) => {
+                let idx = self.intern_string(name.to_string());
+                self.emit(Opcode::LoadVar(idx));
+                self.emit(Opcode::AssignDim(depth, append));
+                self.emit(Opcode::StoreVar(idx));
+                None
+            }
+            Holder::ThisProperty(property) => {
+                let idx = self.intern_string(property.to_string());
+                self.emit(Opcode::LoadThis);
+                self.emit(Opcode::LoadPropertyForWrite(idx));
+                self.emit(Opcode::AssignDim(depth, append));
+                self.emit(Opcode::StoreThisProperty(idx));
+                None
+            }
+            Holder::Property(object, property) => {
+                let slot = self.allocate_local(object.clone());
+                let idx = self.intern_string(property.to_string());
+                self.emit(Opcode::LoadFast(slot));
+                self.emit(Opcode::LoadPropertyForWrite(idx));
+                Some((slot, Opcode::StoreProperty(idx)))
+            }
+            Holder::StaticProperty(class, property) => {
+                let class_idx = self.intern_string(class.to_string());
+                let prop_idx = self.intern_string(property.to_string());
+                self.emit(Opcode::LoadStaticProp(class_idx, prop_idx));
+                self.emit(Opcode::AssignDim(depth, append));
+                self.emit(Opcode::StoreStaticProp(class_idx, prop_idx));
+                None
+            }
+            Holder::Global(name) => {
+                let slot = self.allocate_local(name.clone());
+                self.emit(Opcode::LoadFast(slot));
+                self.emit(Opcode::FetchGlobalQuiet);
+                Some((slot, Opcode::AssignGlobal))
+            }
+        };
+        // An object's property and a global are stored back with the
+        // object or the name under the array
+        if let Some((slot, store)) = container {
+            self.emit(Opcode::AssignDim(depth, append));
+            self.emit(Opcode::LoadFast(slot));
+            self.emit(Opcode::Swap);
+            self.emit(store);
+        }
+        self.emit(Opcode::Pop);
+    }
+}
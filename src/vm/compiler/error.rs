@@ -0,0 +1,112 @@
+//! Compilation errors
+
+use super::Compiler;
+use crate::diagnostic::{self, Diagnostic, Span};
+use crate::lexer::LexError;
+use crate::parser::ParseError;
+use std::fmt;
+
+/// Why source failed to compile, and where
+///
+/// `crate::compile` runs the lexer and parser too, so their errors are
+/// compilation errors as well.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    Lex(LexError),
+    Parse(ParseError),
+    /// A construct the compiler doesn't support yet
+    Unsupported {
+        message: String,
+        line: usize,
+    },
+    /// Code that is only found wrong once the program is compiled, such as
+    /// a class extending a final class
+    Invalid {
+        message: String,
+        line: usize,
+    },
+}
+
+impl CompileError {
+    pub fn message(&self) -> String {
+        match self {
+            Self::Lex(error) => error.message(),
+            Self::Parse(error) => error.message().to_string(),
+            Self::Unsupported { message, .. } | Self::Invalid { message, .. } => message.clone(),
+        }
+    }
+
+    /// Line of the error, or 0 when unknown
+    pub fn line(&self) -> usize {
+        match self {
+            Self::Lex(error) => error.line(),
+            Self::Parse(error) => error.line(),
+            Self::Unsupported { line, .. } | Self::Invalid { line, .. } => *line,
+        }
+    }
+}
+
+impl From<LexError> for CompileError {
+    fn from(error: LexError) -> Self {
+        Self::Lex(error)
+    }
+}
+
+impl From<ParseError> for CompileError {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(error: &CompileError) -> Self {
+        match error {
+            CompileError::Lex(error) => error.into(),
+            CompileError::Parse(error) => error.into(),
+            CompileError::Unsupported { message, line }
+            | CompileError::Invalid { message, line } => {
+                let diagnostic = Diagnostic::new(message.as_str()).with_code(diagnostic::COMPILE);
+                match line {
+                    0 => diagnostic,
+                    line => diagnostic.with_span(Span::line(*line)),
+                }
+            }
+        }
+    }
+}
+
+/// The one-line form, e.g. "cannot extend final class Base at line 7"
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Diagnostic::from(self).fmt(f)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl From<CompileError> for String {
+    fn from(error: CompileError) -> Self {
+        error.to_string()
+    }
+}
+
+impl Compiler {
+    /// Line of the statement being compiled, or 0 before the first one
+    fn current_line(&self) -> usize {
+        self.function.lines.last().map_or(0, |&(_, line)| line)
+    }
+
+    pub(super) fn unsupported(&self, message: impl Into<String>) -> CompileError {
+        CompileError::Unsupported {
+            message: message.into(),
+            line: self.current_line(),
+        }
+    }
+
+    pub(super) fn invalid(&self, message: impl Into<String>) -> CompileError {
+        CompileError::Invalid {
+            message: message.into(),
+            line: self.current_line(),
+        }
+    }
+}
@@ -1,10 +1,11 @@
-use super::Compiler;
+use super::globals_array::is_globals;
+use super::{CompileError, Compiler};
 
 use crate::ast::Expr;
 use crate::vm::opcode::Opcode;
 
 impl Compiler {
-    pub(crate) fn compile_expr_internal(&mut self, expr: &Expr) -> Result<(), String> {
+    pub(crate) fn compile_expr_internal(&mut self, expr: &Expr) -> Result<(), CompileError> {
         match expr {
             Expr::Null => {
                 self.emit(Opcode::PushNull);
@@ -26,20 +27,19 @@ impl Compiler {
                 let idx = self.intern_string(s.clone());
                 self.emit(Opcode::PushString(idx));
             }
-            Expr::Heredoc(content) => {
-                self.compile_heredoc(content)?;
-            }
             Expr::Variable(name) => {
-                if let Some(&slot) = self.locals.get(name) {
-                    self.emit(Opcode::LoadFast(slot));
-                } else {
-                    let idx = self.intern_string(name.clone());
-                    self.emit(Opcode::LoadVar(idx));
-                }
+                self.emit_load_variable(name);
+            }
+            Expr::Constant(name) => {
+                let idx = self.intern_string(name.clone());
+                self.emit(Opcode::FetchConstant(idx));
             }
             Expr::Assign { var, op, value } => {
                 self.compile_assign(var, op, value)?;
             }
+            Expr::AssignRef { var, source } => {
+                self.compile_assign_ref(var, source)?;
+            }
             Expr::Binary { left, op, right } => {
                 self.compile_binary_op(left, op, right)?;
             }
@@ -49,17 +49,29 @@ impl Compiler {
             Expr::Array(elements) => {
                 self.compile_array_literal(elements)?;
             }
+            // A `?->` left of a `->` or `[]`, which a null can cut short
+            Expr::PropertyAccess { .. } | Expr::MethodCall { .. } | Expr::ArrayAccess { .. }
+                if expr.is_nullsafe_chain() =>
+            {
+                self.compile_nullsafe_chain(expr, false)?;
+            }
+            Expr::NullsafePropertyAccess { .. } | Expr::NullsafeMethodCall { .. } => {
+                self.compile_nullsafe_chain(expr, false)?;
+            }
+            Expr::ArrayAccess { array, index } if is_globals(array) => {
+                self.compile_global_fetch(index, false)?;
+            }
             Expr::ArrayAccess { array, index } => {
                 self.compile_expr(array)?;
                 self.compile_expr(index)?;
-                self.emit(Opcode::ArrayGet);
+                self.emit(Opcode::ArrayGetChecked);
             }
             Expr::Ternary {
                 condition,
                 then_expr,
                 else_expr,
             } => {
-                self.compile_ternary(condition, then_expr, else_expr)?;
+                self.compile_ternary(condition, then_expr.as_deref(), else_expr)?;
             }
             Expr::FunctionCall { name, args } => {
                 self.compile_function_call(name, args)?;
@@ -78,15 +90,22 @@ impl Compiler {
             Expr::New { class_name, args } => {
                 self.compile_new_object(class_name, args)?;
             }
+            Expr::NewDynamic { class, args } => {
+                self.compile_new_dynamic(class, args)?;
+            }
+            Expr::ClassReference(class_name) => {
+                self.compile_class_reference(class_name);
+            }
             Expr::PropertyAccess { object, property } => {
                 self.compile_property_access(object, property)?;
             }
             Expr::PropertyAssign {
                 object,
                 property,
+                op,
                 value,
             } => {
-                self.compile_property_assign(object, property, value)?;
+                self.compile_property_assign(object, property, op, value)?;
             }
             Expr::MethodCall {
                 object,
@@ -108,9 +127,10 @@ impl Compiler {
             Expr::StaticPropertyAssign {
                 class,
                 property,
+                op,
                 value,
             } => {
-                self.compile_static_property_assign(class, property, value)?;
+                self.compile_static_property_assign(class, property, op, value)?;
             }
             Expr::This => {
                 self.emit(Opcode::LoadThis);
@@ -130,7 +150,20 @@ impl Compiler {
                 enum_name,
                 case_name,
             } => {
-                let enum_idx = self.intern_string(enum_name.clone());
+                // Classes and interfaces are declared under their qualified
+                // names, enums under the names they're declared with. A
+                // class declared nowhere yet may be autoloaded by its
+                // qualified name.
+                let qualified = self.qualify_class_name(enum_name);
+                let class_name = if self.enums.contains_key(enum_name)
+                    && !self.classes.contains_key(&qualified)
+                    && !self.interfaces.contains_key(&qualified)
+                {
+                    enum_name.clone()
+                } else {
+                    qualified
+                };
+                let enum_idx = self.intern_string(class_name);
                 let case_idx = self.intern_string(case_name.clone());
                 self.emit(Opcode::LoadEnumCase(enum_idx, case_idx));
             }
@@ -142,11 +175,13 @@ impl Compiler {
                 self.emit(Opcode::Throw);
             }
             Expr::Yield { key, value } => {
-                if let Some(k) = key {
-                    self.compile_expr(k)?;
-                }
-                if let Some(v) = value {
-                    self.compile_expr(v)?;
+                for part in [key, value] {
+                    match part {
+                        Some(expr) => self.compile_expr(expr)?,
+                        None => {
+                            self.emit(Opcode::PushNull);
+                        }
+                    }
                 }
                 self.emit(Opcode::Yield);
             }
@@ -160,7 +195,7 @@ impl Compiler {
             }
             Expr::CallableCall { callable, args } => {
                 for arg in args {
-                    self.compile_expr(&arg.value)?;
+                    self.compile_arg(&arg.value)?;
                 }
                 self.compile_expr(callable)?;
                 self.emit(Opcode::CallCallable(args.len() as u8));
@@ -176,7 +211,7 @@ impl Compiler {
                 self.emit(Opcode::CreateMethodClosure);
             }
             Expr::CallableFromStaticMethod { class, method } => {
-                let class_idx = self.intern_string(class.clone());
+                let class_idx = self.intern_class_name(class);
                 let method_idx = self.intern_string(method.clone());
                 self.emit(Opcode::PushString(class_idx));
                 self.emit(Opcode::PushString(method_idx));
@@ -202,6 +237,7 @@ impl Compiler {
                 } else {
                     self.emit(Opcode::PushNull);
                 }
+                self.emit(Opcode::SuspendFiber);
             }
             Expr::FiberGetCurrent => {
                 self.emit(Opcode::GetCurrentFiber);
@@ -212,8 +248,11 @@ impl Compiler {
             } => {
                 self.compile_clone_with(object, modifications)?;
             }
+            Expr::CloneWithProperties { object, properties } => {
+                self.compile_clone_with_properties(object, properties)?;
+            }
             Expr::Placeholder => {
-                return Err("Pipe placeholder not yet implemented".to_string());
+                return Err(self.unsupported("Pipe placeholder not yet implemented"));
             }
             // Magic constants
             Expr::MagicFile => {
@@ -268,48 +307,4 @@ impl Compiler {
         }
         Ok(())
     }
-
-    /// Compile heredoc string with variable interpolation
-    fn compile_heredoc(&mut self, content: &str) -> Result<(), String> {
-        let parts: Vec<&str> = content.split("\x00").collect();
-
-        if parts.len() == 1 {
-            let idx = self.intern_string(content.to_string());
-            self.emit(Opcode::PushString(idx));
-        } else {
-            let var_count = (parts.len() - 1) / 2;
-            let mut var_placeholders = Vec::new();
-
-            for (i, part) in parts.iter().enumerate() {
-                if i % 2 == 1 {
-                    let var_str = *part;
-                    if var_str.starts_with('$') {
-                        var_placeholders.push(var_str[1..].to_string());
-                    }
-                }
-            }
-
-            let mut var_idx = 0;
-            for (i, part) in parts.iter().enumerate() {
-                if i % 2 == 0 {
-                    if !part.is_empty() {
-                        let idx = self.intern_string(part.to_string());
-                        self.emit(Opcode::PushString(idx));
-                    }
-                } else if var_idx < var_placeholders.len() {
-                    let var_name = &var_placeholders[var_idx];
-                    var_idx += 1;
-                    if let Some(&slot) = self.locals.get(var_name) {
-                        self.emit(Opcode::LoadFast(slot));
-                    } else {
-                        let idx = self.intern_string(var_name.clone());
-                        self.emit(Opcode::LoadVar(idx));
-                    }
-                }
-            }
-
-            self.emit(Opcode::HeredocInterpolate(var_count as u16));
-        }
-        Ok(())
-    }
 }
@@ -1,4 +1,4 @@
-use super::Compiler;
+use super::{CompileError, Compiler};
 use crate::ast::{BinaryOp, Expr, UnaryOp};
 use crate::vm::opcode::Opcode;
 
@@ -9,7 +9,7 @@ impl Compiler {
         left: &Expr,
         op: &BinaryOp,
         right: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         match op {
             BinaryOp::And => {
                 self.compile_expr(left)?;
@@ -30,7 +30,7 @@ impl Compiler {
                 return Ok(());
             }
             BinaryOp::NullCoalesce => {
-                self.compile_expr(left)?;
+                self.compile_quiet_fetch(left)?;
                 self.emit(Opcode::Dup);
                 let jump_if_not_null = self.emit_jump(Opcode::JumpIfNotNull(0));
                 self.emit(Opcode::Pop);
@@ -77,7 +77,7 @@ impl Compiler {
                     }
                     _ => {
                         return Err(
-                            "Pipe operator right-hand side must be a function call".to_string()
+                            self.invalid("Pipe operator right-hand side must be a function call")
                         )
                     }
                 }
@@ -88,7 +88,13 @@ impl Compiler {
 
         self.compile_expr(left)?;
         self.compile_expr(right)?;
+        self.emit_binary_opcode(op);
+
+        Ok(())
+    }
 
+    /// Emit the opcode of a binary operation whose operands are on the stack
+    pub(crate) fn emit_binary_opcode(&mut self, op: &BinaryOp) {
         match op {
             BinaryOp::Add => self.emit(Opcode::Add),
             BinaryOp::Sub => self.emit(Opcode::Sub),
@@ -109,11 +115,15 @@ impl Compiler {
             BinaryOp::And => self.emit(Opcode::And),
             BinaryOp::Or => self.emit(Opcode::Or),
             BinaryOp::Xor => self.emit(Opcode::Xor),
-            BinaryOp::NullCoalesce => unreachable!("Handled above"),
-            _ => return Err(format!("Binary operator not yet implemented: {:?}", op)),
+            BinaryOp::BitwiseAnd => self.emit(Opcode::BitwiseAnd),
+            BinaryOp::BitwiseOr => self.emit(Opcode::BitwiseOr),
+            BinaryOp::BitwiseXor => self.emit(Opcode::BitwiseXor),
+            BinaryOp::ShiftLeft => self.emit(Opcode::ShiftLeft),
+            BinaryOp::ShiftRight => self.emit(Opcode::ShiftRight),
+            BinaryOp::NullCoalesce | BinaryOp::Pipe => {
+                unreachable!("Compiled as control flow")
+            }
         };
-
-        Ok(())
     }
 
     /// Compile a unary operation
@@ -121,7 +131,7 @@ impl Compiler {
         &mut self,
         op: &UnaryOp,
         operand: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         match op {
             UnaryOp::Not => {
                 self.compile_expr(operand)?;
@@ -131,20 +141,23 @@ impl Compiler {
                 self.compile_expr(operand)?;
                 self.emit(Opcode::Neg);
             }
+            UnaryOp::BitwiseNot => {
+                self.compile_expr(operand)?;
+                self.emit(Opcode::BitwiseNot);
+            }
+            UnaryOp::Silence => {
+                self.emit(Opcode::BeginSilence);
+                self.compile_expr(operand)?;
+                self.emit(Opcode::EndSilence);
+            }
             UnaryOp::PreInc | UnaryOp::PreDec => match operand {
                 Expr::Variable(var_name) => {
-                    if let Some(&slot) = self.locals.get(var_name) {
-                        self.emit(Opcode::LoadFast(slot));
-                    } else {
-                        let idx = self.intern_string(var_name.clone());
-                        self.emit(Opcode::LoadVar(idx));
-                    }
+                    self.emit_load_variable(var_name);
 
-                    self.emit(Opcode::PushInt(1));
                     if matches!(op, UnaryOp::PreInc) {
-                        self.emit(Opcode::Add);
+                        self.emit(Opcode::Increment);
                     } else {
-                        self.emit(Opcode::Sub);
+                        self.emit(Opcode::Decrement);
                     }
 
                     self.emit(Opcode::Dup);
@@ -162,11 +175,10 @@ impl Compiler {
 
                     self.emit(Opcode::LoadStaticProp(class_idx, prop_idx));
 
-                    self.emit(Opcode::PushInt(1));
                     if matches!(op, UnaryOp::PreInc) {
-                        self.emit(Opcode::Add);
+                        self.emit(Opcode::Increment);
                     } else {
-                        self.emit(Opcode::Sub);
+                        self.emit(Opcode::Decrement);
                     }
 
                     self.emit(Opcode::Dup);
@@ -174,24 +186,18 @@ impl Compiler {
                     self.emit(Opcode::StoreStaticProp(class_idx, prop_idx));
                     self.emit(Opcode::Pop);
                 }
-                _ => return Err("Increment/decrement requires a variable".to_string()),
+                _ => return Err(self.invalid("Increment/decrement requires a variable")),
             },
             UnaryOp::PostInc | UnaryOp::PostDec => match operand {
                 Expr::Variable(var_name) => {
-                    if let Some(&slot) = self.locals.get(var_name) {
-                        self.emit(Opcode::LoadFast(slot));
-                    } else {
-                        let idx = self.intern_string(var_name.clone());
-                        self.emit(Opcode::LoadVar(idx));
-                    }
+                    self.emit_load_variable(var_name);
 
                     self.emit(Opcode::Dup);
 
-                    self.emit(Opcode::PushInt(1));
                     if matches!(op, UnaryOp::PostInc) {
-                        self.emit(Opcode::Add);
+                        self.emit(Opcode::Increment);
                     } else {
-                        self.emit(Opcode::Sub);
+                        self.emit(Opcode::Decrement);
                     }
 
                     if let Some(&slot) = self.locals.get(var_name) {
@@ -210,17 +216,16 @@ impl Compiler {
 
                     self.emit(Opcode::Dup);
 
-                    self.emit(Opcode::PushInt(1));
                     if matches!(op, UnaryOp::PostInc) {
-                        self.emit(Opcode::Add);
+                        self.emit(Opcode::Increment);
                     } else {
-                        self.emit(Opcode::Sub);
+                        self.emit(Opcode::Decrement);
                     }
 
                     self.emit(Opcode::StoreStaticProp(class_idx, prop_idx));
                     self.emit(Opcode::Pop);
                 }
-                _ => return Err("Increment/decrement requires a variable".to_string()),
+                _ => return Err(self.invalid("Increment/decrement requires a variable")),
             },
         };
 
@@ -231,11 +236,21 @@ impl Compiler {
     pub fn compile_ternary_internal(
         &mut self,
         condition: &Expr,
-        then_val: &Expr,
+        then_val: Option<&Expr>,
         else_val: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_expr(condition)?;
 
+        // `a ?: b` keeps the condition's value rather than evaluating `a` again
+        let Some(then_val) = then_val else {
+            self.emit(Opcode::Dup);
+            let end_jump = self.emit_jump(Opcode::JumpIfTrue(0));
+            self.emit(Opcode::Pop);
+            self.compile_expr(else_val)?;
+            self.patch_jump(end_jump);
+            return Ok(());
+        };
+
         let else_jump = self.emit_jump(Opcode::JumpIfFalse(0));
 
         self.compile_expr(then_val)?;
@@ -0,0 +1,79 @@
+//! `$GLOBALS` compilation
+//!
+//! `$GLOBALS` compiles to `LoadGlobals`, which builds the array of the
+//! global variables, and an element of it to the opcodes that read,
+//! assign and unset the variable its key names. As of PHP 8.1 nothing
+//! else may write to `$GLOBALS` itself.
+
+use super::{CompileError, Compiler};
+use crate::ast::{AssignOp, Expr};
+use crate::vm::opcode::Opcode;
+
+/// Whether `expr` is `$GLOBALS`
+pub(super) fn is_globals(expr: &Expr) -> bool {
+    matches!(expr, Expr::Variable(name) if name == "GLOBALS")
+}
+
+impl Compiler {
+    /// The error for a write to `$GLOBALS` other than to one of its elements
+    pub(crate) fn globals_write_error(&self) -> CompileError {
+        self.invalid("$GLOBALS can only be modified using the $GLOBALS[$name] = $value syntax")
+    }
+
+    /// `$GLOBALS[$name]`, read as `isset()` and `??` read it when `quiet`
+    pub(crate) fn compile_global_fetch(
+        &mut self,
+        index: &Expr,
+        quiet: bool,
+    ) -> Result<(), CompileError> {
+        self.compile_expr(index)?;
+        self.emit(if quiet {
+            Opcode::FetchGlobalQuiet
+        } else {
+            Opcode::FetchGlobal
+        });
+        Ok(())
+    }
+
+    /// `$GLOBALS[$name] = $value`, or a compound assignment to it, which
+    /// evaluates the name once
+    pub(crate) fn compile_global_assign(
+        &mut self,
+        index: &Option<Box<Expr>>,
+        op: &AssignOp,
+        value: &Expr,
+    ) -> Result<(), CompileError> {
+        let Some(index) = index else {
+            return Err(self.globals_write_error());
+        };
+        self.compile_expr(index)?;
+        if *op == AssignOp::Assign {
+            self.compile_expr(value)?;
+            self.emit(Opcode::AssignGlobal);
+            return Ok(());
+        }
+
+        // StoreFast leaves the name on the stack as well
+        let slot = self.allocate_local("__global_name__".to_string());
+        self.emit(Opcode::StoreFast(slot));
+        match op.binary_op() {
+            Some(binary) => {
+                self.emit(Opcode::LoadFast(slot));
+                self.emit(Opcode::FetchGlobal);
+                self.compile_expr(value)?;
+                self.emit_binary_opcode(&binary);
+                self.emit(Opcode::AssignGlobal);
+            }
+            None => {
+                self.emit(Opcode::FetchGlobalQuiet);
+                let skip = self.emit_jump(Opcode::JumpIfNotNull(0));
+                self.emit(Opcode::Pop);
+                self.emit(Opcode::LoadFast(slot));
+                self.compile_expr(value)?;
+                self.emit(Opcode::AssignGlobal);
+                self.patch_jump(skip);
+            }
+        }
+        Ok(())
+    }
+}
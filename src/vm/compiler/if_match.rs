@@ -1,4 +1,4 @@
-use super::Compiler;
+use super::{CompileError, Compiler};
 
 impl Compiler {
     /// Compile an if statement with elseif and else branches (internal implementation)
@@ -8,7 +8,7 @@ impl Compiler {
         then_branch: &[crate::ast::Stmt],
         elseif_branches: &[(crate::ast::Expr, Vec<crate::ast::Stmt>)],
         else_branch: &Option<Vec<crate::ast::Stmt>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         // Compile condition
         self.compile_expr(condition)?;
 
@@ -62,11 +62,13 @@ impl Compiler {
         expr: &crate::ast::Expr,
         arms: &[crate::ast::MatchArm],
         default: &Option<Box<crate::ast::Expr>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         // Compile the subject expression and store it
         self.compile_expr(expr)?;
-        let subject_slot = self.allocate_local("__match_subject__".to_string());
+        let match_id = self.current_offset();
+        let subject_slot = self.allocate_local(format!("__match_subject_{}__", match_id));
         self.emit(crate::vm::opcode::Opcode::StoreFast(subject_slot));
+        self.emit(crate::vm::opcode::Opcode::Pop);
 
         let mut end_jumps = Vec::new();
 
@@ -104,14 +106,8 @@ impl Compiler {
         if let Some(default_expr) = default {
             self.compile_expr(default_expr)?;
         } else {
-            // Throw UnhandledMatchError exception
-            // Create new UnhandledMatchError("Unhandled match value")
-            let class_idx = self.intern_string("UnhandledMatchError".to_string());
-            self.emit(crate::vm::opcode::Opcode::NewObject(class_idx));
-            let msg_idx = self.intern_string("Unhandled match value".to_string());
-            self.emit(crate::vm::opcode::Opcode::PushString(msg_idx));
-            self.emit(crate::vm::opcode::Opcode::CallConstructor(1));
-            self.emit(crate::vm::opcode::Opcode::Throw);
+            self.emit(crate::vm::opcode::Opcode::LoadFast(subject_slot));
+            self.emit(crate::vm::opcode::Opcode::UnhandledMatch);
         }
 
         // Patch all end jumps
@@ -123,51 +119,78 @@ impl Compiler {
     }
 
     /// Compile a switch statement (internal implementation)
+    ///
+    /// The subject is evaluated once, then compared loosely (`==`) with
+    /// each case value in order; no match jumps to `default` wherever it
+    /// is, or past the switch. Bodies follow in source order, so one
+    /// without `break` falls through into the next. The switch counts as
+    /// a loop for `break` and `continue`, which both leave it.
+    ///
+    /// ```
+    /// use vhp::vm::opcode::Opcode;
+    ///
+    /// let source = "<?php switch ($x) { default: echo 'd'; case 1: echo 'one'; }";
+    /// let code = vhp::compile(source, "a.php").unwrap().main.bytecode.clone();
+    /// // `case 1` matches into its body; no match enters `default` above it,
+    /// // which falls through
+    /// assert_eq!(code[6], Opcode::JumpIfTrue(10));
+    /// assert_eq!(code[7], Opcode::Jump(8));
+    /// assert_eq!(code[12], Opcode::LoopEnd);
+    /// assert_eq!(code[2], Opcode::LoopStart(13, 13));
+    /// ```
     pub(crate) fn compile_switch_internal(
         &mut self,
         expr: &crate::ast::Expr,
         cases: &[crate::ast::SwitchCase],
-        default: &Option<Vec<crate::ast::Stmt>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_expr(expr)?;
         let switch_slot = self.allocate_local("__switch_expr__".to_string());
         self.emit(crate::vm::opcode::Opcode::StoreFast(switch_slot));
 
         let loop_start_idx = self.emit(crate::vm::opcode::Opcode::LoopStart(0, 0));
 
-        let mut case_jumps: Vec<usize> = Vec::new();
-
+        // One jump per case into its body: the test's, or for `default`
+        // the jump taken when no test matched
+        let mut case_jumps: Vec<Option<usize>> = Vec::new();
         for case in cases {
-            self.emit(crate::vm::opcode::Opcode::LoadFast(switch_slot));
-            self.compile_expr(&case.value)?;
-            self.emit(crate::vm::opcode::Opcode::Eq);
-            case_jumps.push(self.emit_jump(crate::vm::opcode::Opcode::JumpIfTrue(0)));
+            let jump = match &case.value {
+                Some(value) => {
+                    self.emit(crate::vm::opcode::Opcode::LoadFast(switch_slot));
+                    self.compile_expr(value)?;
+                    self.emit(crate::vm::opcode::Opcode::Eq);
+                    Some(self.emit_jump(crate::vm::opcode::Opcode::JumpIfTrue(0)))
+                }
+                None => None,
+            };
+            case_jumps.push(jump);
         }
-
-        let default_jump = self.emit_jump(crate::vm::opcode::Opcode::Jump(0));
-
-        for (i, case) in cases.iter().enumerate() {
-            self.patch_jump(case_jumps[i]);
-
+        let no_match_jump = self.emit_jump(crate::vm::opcode::Opcode::Jump(0));
+
+        let mut no_match_patched = false;
+        for (case, jump) in cases.iter().zip(case_jumps) {
+            match jump {
+                Some(jump) => self.patch_jump(jump),
+                None => {
+                    self.patch_jump(no_match_jump);
+                    no_match_patched = true;
+                }
+            }
             for stmt in &case.body {
                 self.compile_stmt(stmt)?;
             }
         }
-
-        self.patch_jump(default_jump);
-        if let Some(default_body) = default {
-            for stmt in default_body {
-                self.compile_stmt(stmt)?;
-            }
+        if !no_match_patched {
+            self.patch_jump(no_match_jump);
         }
 
         self.emit(crate::vm::opcode::Opcode::LoopEnd);
 
-        let end_offset = self.current_offset();
-        if let crate::vm::opcode::Opcode::LoopStart(_, ref mut break_target) =
+        let end_offset = self.current_offset() as u32;
+        if let crate::vm::opcode::Opcode::LoopStart(ref mut continue_target, ref mut break_target) =
             self.function.bytecode[loop_start_idx]
         {
-            *break_target = end_offset as u32;
+            *continue_target = end_offset;
+            *break_target = end_offset;
         }
 
         Ok(())
@@ -1,4 +1,5 @@
-use super::Compiler;
+use super::class_constants::ConstantScope;
+use super::{CompileError, Compiler};
 
 use crate::ast::{Attribute, QualifiedName};
 use std::sync::Arc;
@@ -11,7 +12,7 @@ impl Compiler {
         methods: &[crate::ast::InterfaceMethodSignature],
         constants: &[crate::ast::InterfaceConstant],
         attributes: &[Attribute],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         use crate::vm::class::CompiledInterface;
 
         let qualified_name = if let Some(ref ns) = self.current_namespace {
@@ -25,7 +26,17 @@ impl Compiler {
             .iter()
             .map(|p| self.resolve_qualified_name(p))
             .collect();
-        compiled_interface.attributes = attributes.to_vec();
+
+        compiled_interface.constant_order = constants.iter().map(|c| c.name.clone()).collect();
+        let inherited = self.inherited_constants(&compiled_interface.parents);
+        compiled_interface.constants =
+            self.constant_values(&qualified_name, None, inherited, constants)?;
+        let scope = ConstantScope {
+            name: &qualified_name,
+            parent: None,
+            constants: &compiled_interface.constants,
+        };
+        compiled_interface.attributes = self.link_attributes(attributes, Some(&scope));
 
         for method in methods {
             compiled_interface
@@ -33,10 +44,6 @@ impl Compiler {
                 .push((method.name.clone(), method.params.len() as u8));
         }
 
-        for constant in constants {
-            let _ = constant;
-        }
-
         self.interfaces
             .insert(qualified_name, Arc::new(compiled_interface));
         Ok(())
@@ -1,5 +1,5 @@
-use super::Compiler;
-use crate::ast::{Expr, Stmt};
+use super::{CompileError, Compiler};
+use crate::ast::{Expr, ForeachTarget, Stmt};
 use crate::vm::opcode::Opcode;
 
 impl Compiler {
@@ -7,7 +7,7 @@ impl Compiler {
         &mut self,
         condition: &Expr,
         body: &[Stmt],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         let loop_start = self.current_offset();
 
         self.compile_expr(condition)?;
@@ -38,10 +38,10 @@ impl Compiler {
         &mut self,
         body: &[Stmt],
         condition: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         let loop_start = self.current_offset();
 
-        let loop_start_idx = self.emit(Opcode::LoopStart(loop_start as u32, 0));
+        let loop_start_idx = self.emit(Opcode::LoopStart(0, 0));
 
         for stmt in body {
             self.compile_stmt(stmt)?;
@@ -49,12 +49,17 @@ impl Compiler {
 
         self.emit(Opcode::LoopEnd);
 
+        // `continue` still tests the condition before the next iteration
+        let condition_start = self.current_offset();
         self.compile_expr(condition)?;
 
         self.emit(Opcode::JumpIfTrue(loop_start as u32));
 
         let loop_end = self.current_offset();
-        if let Opcode::LoopStart(_, ref mut break_target) = self.function.bytecode[loop_start_idx] {
+        if let Opcode::LoopStart(ref mut continue_target, ref mut break_target) =
+            self.function.bytecode[loop_start_idx]
+        {
+            *continue_target = condition_start as u32;
             *break_target = loop_end as u32;
         }
 
@@ -67,7 +72,7 @@ impl Compiler {
         condition: &Option<Expr>,
         update: &Option<Expr>,
         body: &[Stmt],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         if let Some(init_expr) = init {
             self.compile_expr(init_expr)?;
             self.emit(Opcode::Pop);
@@ -114,56 +119,77 @@ impl Compiler {
         Ok(())
     }
 
+    /// Compile a foreach loop. The loop walks a copy of the array by
+    /// position, in temporaries of its own so that loops can nest.
+    ///
+    /// By reference, the value variable is bound to the element at each
+    /// key (`compile_foreach_ref`), and stays bound to the last one after
+    /// the loop. An array held in a variable, an element or a property is
+    /// read again before each iteration so that the loop sees the body's
+    /// other changes to it.
     pub(crate) fn compile_foreach_internal(
         &mut self,
         array: &Expr,
         key: &Option<String>,
-        value: &str,
+        value: &ForeachTarget,
+        by_ref: bool,
         body: &[Stmt],
-    ) -> Result<(), String> {
-        self.compile_expr(array)?;
-        // Convert to array (handles both arrays and generators)
-        self.emit(Opcode::ToArray);
-        let array_slot = self.allocate_local("__foreach_array__".to_string());
-        self.emit(Opcode::StoreFast(array_slot));
+    ) -> Result<(), CompileError> {
+        let loop_id = self.current_offset();
+        let by_ref = by_ref && matches!(value, ForeachTarget::Variable(_));
+        let reread = by_ref && self.binds_reference(array);
+
+        let array_slot = self.allocate_local(format!("__foreach_array_{}__", loop_id));
+        if !reread {
+            self.compile_foreach_source(array, array_slot)?;
+        }
 
-        let iter_slot = self.allocate_local("__foreach_iter__".to_string());
+        let iter_slot = self.allocate_local(format!("__foreach_iter_{}__", loop_id));
 
         self.emit(Opcode::PushInt(0));
         self.emit(Opcode::StoreFast(iter_slot));
 
-        let key_slot = key
-            .as_ref()
-            .map(|key_name| self.allocate_local(key_name.clone()));
-        let value_slot = self.allocate_local(value.to_string());
+        // Binding the element needs the key even if the loop doesn't name it
+        let key_name = match key {
+            Some(name) => Some(name.clone()),
+            None if by_ref => Some(format!("__foreach_key_{}__", loop_id)),
+            None => None,
+        };
+        let key_slot = key_name.map(|key_name| self.allocate_local(key_name));
+        // A destructured value goes through a temporary
+        let value_slot = match value {
+            ForeachTarget::Variable(name) => self.allocate_local(name.clone()),
+            ForeachTarget::List(_) => self.allocate_local(format!("__foreach_value_{}__", loop_id)),
+        };
 
         let loop_check = self.current_offset();
+        if reread {
+            self.compile_foreach_source(array, array_slot)?;
+        }
 
-        self.emit(Opcode::LoadFast(array_slot));
-        self.emit(Opcode::ArrayCount);
-
-        self.emit(Opcode::LoadFast(iter_slot));
-
-        self.emit(Opcode::Le);
-        let exit_jump = self.emit_jump(Opcode::JumpIfTrue(0));
-
-        self.emit(Opcode::LoadFast(array_slot));
-        self.emit(Opcode::LoadFast(iter_slot));
-        self.emit(Opcode::ArrayGetKeyAt);
+        self.emit(Opcode::ForeachValid(array_slot, iter_slot));
+        let exit_jump = self.emit_jump(Opcode::JumpIfFalse(0));
 
         if let Some(slot) = key_slot {
+            self.emit(Opcode::ForeachKey(array_slot, iter_slot));
             self.emit(Opcode::StoreFast(slot));
-        } else {
-            self.emit(Opcode::Pop);
         }
 
-        self.emit(Opcode::LoadFast(array_slot));
-        self.emit(Opcode::LoadFast(iter_slot));
-        self.emit(Opcode::ArrayGetValueAt);
-
-        self.emit(Opcode::StoreFast(value_slot));
+        match (by_ref, key_slot) {
+            (true, Some(key_slot)) => {
+                let source = if reread { Some(array) } else { None };
+                self.compile_foreach_ref(source, array_slot, key_slot, value_slot)?;
+            }
+            _ => {
+                self.emit(Opcode::ForeachValue(array_slot, iter_slot));
+                self.emit(Opcode::StoreFast(value_slot));
+            }
+        }
+        if let ForeachTarget::List(elements) = value {
+            self.compile_list_destructure_internal(elements, value_slot)?;
+        }
 
-        let loop_start_idx = self.emit(Opcode::LoopStart(loop_check as u32, 0));
+        let loop_start_idx = self.emit(Opcode::LoopStart(0, 0));
 
         for stmt in body {
             self.compile_stmt(stmt)?;
@@ -171,6 +197,8 @@ impl Compiler {
 
         self.emit(Opcode::LoopEnd);
 
+        let next_offset = self.current_offset();
+        self.emit(Opcode::ForeachNext(array_slot));
         self.emit(Opcode::LoadFast(iter_slot));
         self.emit(Opcode::PushInt(1));
         self.emit(Opcode::Add);
@@ -178,13 +206,24 @@ impl Compiler {
 
         self.emit_loop(loop_check);
 
-        let loop_end = self.current_offset();
         self.patch_jump(exit_jump);
+        let loop_end = self.current_offset();
 
-        if let Opcode::LoopStart(_, ref mut break_target) = self.function.bytecode[loop_start_idx] {
+        if let Opcode::LoopStart(ref mut continue_target, ref mut break_target) =
+            self.function.bytecode[loop_start_idx]
+        {
+            *continue_target = next_offset as u32;
             *break_target = loop_end as u32;
         }
 
         Ok(())
     }
+
+    /// Evaluate what a foreach loop walks into `slot`
+    fn compile_foreach_source(&mut self, array: &Expr, slot: u16) -> Result<(), CompileError> {
+        self.compile_expr(array)?;
+        self.emit(Opcode::ToArray);
+        self.emit(Opcode::StoreFast(slot));
+        Ok(())
+    }
 }
@@ -0,0 +1,72 @@
+//! The values of the magic constants known at compile time: `__FILE__`,
+//! `__DIR__`, `__FUNCTION__`, `__CLASS__`, `__METHOD__`, `__NAMESPACE__`
+//! and `__TRAIT__`
+
+use super::Compiler;
+
+impl Compiler {
+    /// Get the current file path for __FILE__ magic constant
+    pub(super) fn file_path(&self) -> String {
+        self.current_file_path.clone()
+    }
+
+    /// Get the directory of the current file for __DIR__ magic constant
+    pub(super) fn dir_path(&self) -> String {
+        let path = &self.current_file_path;
+        if path == "<main>" {
+            return ".".to_string();
+        }
+        // Get parent directory
+        if let Some(pos) = path.rfind('/') {
+            path[..pos].to_string()
+        } else if let Some(pos) = path.rfind('\\') {
+            path[..pos].to_string()
+        } else {
+            ".".to_string()
+        }
+    }
+
+    /// Get the current function name for __FUNCTION__ magic constant
+    pub(super) fn function_name(&self) -> String {
+        let name = &self.function.name;
+        if name == "<main>" || name == "<test>" {
+            return "".to_string();
+        }
+        name.clone()
+    }
+
+    /// Get the current class name for __CLASS__ magic constant
+    pub(super) fn class_name(&self) -> String {
+        self.current_class.clone().unwrap_or("".to_string())
+    }
+
+    /// Get the current method name for __METHOD__ magic constant
+    pub(super) fn method_name(&self) -> String {
+        if let Some(ref class) = self.current_class {
+            let func_name = self.function.name.clone();
+            if func_name == "<main>" || func_name == "<test>" {
+                return "".to_string();
+            }
+            // func_name is "ClassName::methodName", extract just the method name part
+            if let Some(pos) = func_name.find("::") {
+                let method_part = &func_name[pos + 2..];
+                format!("{}::{}", class, method_part)
+            } else {
+                // No :: separator, use as-is
+                format!("{}::{}", class, func_name)
+            }
+        } else {
+            "".to_string()
+        }
+    }
+
+    /// Get the current namespace for __NAMESPACE__ magic constant
+    pub(super) fn namespace(&self) -> String {
+        self.current_namespace.clone().unwrap_or("".to_string())
+    }
+
+    /// Get the current trait name for __TRAIT__ magic constant
+    pub(super) fn trait_name(&self) -> String {
+        self.current_trait.clone().unwrap_or("".to_string())
+    }
+}
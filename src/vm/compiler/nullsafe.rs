@@ -0,0 +1,80 @@
+//! Compiling nullsafe chains: `$a?->b`, `$a?->b()`
+//!
+//! A null object on the left of `?->` makes the whole chain it is part of
+//! null: `$a?->b->c()['d']` neither reads `c` nor calls anything when `$a`
+//! is null. Each `?->` jumps past the end of the chain with the null left
+//! on the stack as the result (`JumpIfNull`), which is where the chain's
+//! value would be. Parentheses end a chain.
+
+use super::{CompileError, Compiler};
+use crate::ast::Expr;
+use crate::vm::opcode::Opcode;
+
+impl Compiler {
+    /// Compile a chain `Expr::is_nullsafe_chain` is true for, quietly
+    /// (missing variables, properties and keys read as null without a
+    /// warning) for `isset()` and `??`
+    pub(crate) fn compile_nullsafe_chain(
+        &mut self,
+        chain: &Expr,
+        quiet: bool,
+    ) -> Result<(), CompileError> {
+        let mut jumps = Vec::new();
+        self.compile_chain_link(chain, quiet, &mut jumps)?;
+        for jump in jumps {
+            self.patch_jump(jump);
+        }
+        Ok(())
+    }
+
+    /// Compile one link of a chain, adding the jumps of its `?->` links to
+    /// `jumps`
+    fn compile_chain_link(
+        &mut self,
+        link: &Expr,
+        quiet: bool,
+        jumps: &mut Vec<usize>,
+    ) -> Result<(), CompileError> {
+        match link {
+            Expr::PropertyAccess { object, property }
+            | Expr::NullsafePropertyAccess { object, property } => {
+                self.compile_chain_link(object, quiet, jumps)?;
+                if matches!(link, Expr::NullsafePropertyAccess { .. }) {
+                    jumps.push(self.emit_jump(Opcode::JumpIfNull(0)));
+                }
+                let prop_idx = self.intern_string(property.clone());
+                self.emit(match quiet {
+                    true => Opcode::LoadPropertyQuiet(prop_idx),
+                    false => Opcode::LoadProperty(prop_idx),
+                });
+            }
+            Expr::MethodCall {
+                object,
+                method,
+                args,
+            }
+            | Expr::NullsafeMethodCall {
+                object,
+                method,
+                args,
+            } => {
+                self.compile_chain_link(object, quiet, jumps)?;
+                if matches!(link, Expr::NullsafeMethodCall { .. }) {
+                    jumps.push(self.emit_jump(Opcode::JumpIfNull(0)));
+                }
+                self.compile_method_call_on_stack(method, args)?;
+            }
+            Expr::ArrayAccess { array, index } if array.is_nullsafe_chain() => {
+                self.compile_chain_link(array, quiet, jumps)?;
+                self.compile_expr(index)?;
+                self.emit(match quiet {
+                    true => Opcode::ArrayGet,
+                    false => Opcode::ArrayGetChecked,
+                });
+            }
+            object if quiet => self.compile_quiet_fetch(object)?,
+            object => self.compile_expr(object)?,
+        }
+        Ok(())
+    }
+}
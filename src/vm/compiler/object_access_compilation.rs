@@ -1,7 +1,8 @@
-use super::Compiler;
+use super::class_constants::ConstantScope;
+use super::{scans, CompileError, Compiler};
 
-use crate::ast::{Argument, Expr};
-use crate::vm::class::{CompiledClass, CompiledProperty};
+use crate::ast::{Argument, AssignOp, Expr};
+use crate::vm::class::CompiledClass;
 use crate::vm::opcode::Opcode;
 use std::sync::Arc;
 
@@ -10,11 +11,51 @@ impl Compiler {
         &mut self,
         class_name: &str,
         args: &[Argument],
-    ) -> Result<(), String> {
-        let qualified_name = self.qualify_class_name(class_name);
-        let class_idx = self.intern_string(qualified_name);
+    ) -> Result<(), CompileError> {
+        let class_idx = self.intern_class_name(class_name);
         self.emit(Opcode::NewObject(class_idx));
+        self.compile_constructor_call(args)
+    }
+
+    /// Intern the name of the class an operation names: the qualified name,
+    /// or self, static or parent as they are
+    pub(crate) fn intern_class_name(&mut self, class_name: &str) -> u32 {
+        let name = match class_keyword(class_name) {
+            Some(keyword) => keyword.to_string(),
+            None => self.qualify_class_name(class_name),
+        };
+        self.intern_string(name)
+    }
+
+    /// `new $class(args)` or `new (expr)(args)`
+    pub(crate) fn compile_new_dynamic(
+        &mut self,
+        class: &Expr,
+        args: &[Argument],
+    ) -> Result<(), CompileError> {
+        self.compile_expr(class)?;
+        self.emit(Opcode::NewObjectDynamic);
+        self.compile_constructor_call(args)
+    }
 
+    /// `Class::class`: the qualified name, or for self, static and parent,
+    /// the class they refer to when the code runs
+    pub(crate) fn compile_class_reference(&mut self, class_name: &str) {
+        match class_keyword(class_name) {
+            Some(keyword) => {
+                let keyword_idx = self.intern_string(keyword.to_string());
+                self.emit(Opcode::FetchClassName(keyword_idx));
+            }
+            None => {
+                let qualified_name = self.qualify_class_name(class_name);
+                let name_idx = self.intern_string(qualified_name);
+                self.emit(Opcode::PushString(name_idx));
+            }
+        }
+    }
+
+    /// Call the constructor of the new object on the stack with `args`
+    fn compile_constructor_call(&mut self, args: &[Argument]) -> Result<(), CompileError> {
         let has_named = args.iter().any(|arg| arg.name.is_some());
 
         if has_named {
@@ -32,7 +73,7 @@ impl Compiler {
             self.emit(Opcode::CallConstructorNamed);
         } else {
             for arg in args {
-                self.compile_expr(&arg.value)?;
+                self.compile_arg(&arg.value)?;
             }
             self.emit(Opcode::CallConstructor(args.len() as u8));
         }
@@ -44,7 +85,7 @@ impl Compiler {
         &mut self,
         object: &Expr,
         property: &str,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         self.compile_expr(object)?;
         let prop_idx = self.intern_string(property.to_string());
         self.emit(Opcode::LoadProperty(prop_idx));
@@ -55,23 +96,23 @@ impl Compiler {
         &mut self,
         object: &Expr,
         property: &str,
+        op: &AssignOp,
         value: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
+        if *op != AssignOp::Assign {
+            let target = Expr::PropertyAccess {
+                object: Box::new(object.clone()),
+                property: property.to_string(),
+            };
+            return self.compile_compound_assign(target, op, value, |compiler, value| {
+                compiler.compile_property_assign(object, property, &AssignOp::Assign, value)
+            });
+        }
+
         if matches!(object, Expr::This) {
             self.compile_expr(value)?;
             let prop_idx = self.intern_string(property.to_string());
             self.emit(Opcode::StoreThisProperty(prop_idx));
-        } else if let Expr::Variable(var_name) = object {
-            self.compile_expr(object)?;
-            self.compile_expr(value)?;
-            let prop_idx = self.intern_string(property.to_string());
-            self.emit(Opcode::StoreProperty(prop_idx));
-            if let Some(&slot) = self.locals.get(var_name) {
-                self.emit(Opcode::StoreFast(slot));
-            } else {
-                let var_idx = self.intern_string(var_name.clone());
-                self.emit(Opcode::StoreVar(var_idx));
-            }
         } else {
             self.compile_expr(object)?;
             self.compile_expr(value)?;
@@ -86,39 +127,22 @@ impl Compiler {
         object: &Expr,
         method: &str,
         args: &[Argument],
-    ) -> Result<(), String> {
-        let method_idx = self.intern_string(method.to_string());
-
-        match object {
-            Expr::Variable(var_name) => {
-                for arg in args {
-                    self.compile_expr(&arg.value)?;
-                }
+    ) -> Result<(), CompileError> {
+        self.compile_expr(object)?;
+        self.compile_method_call_on_stack(method, args)
+    }
 
-                if let Some(&slot) = self.locals.get(var_name) {
-                    self.emit(Opcode::CallMethodOnLocal(
-                        slot,
-                        method_idx,
-                        args.len() as u8,
-                    ));
-                } else {
-                    let var_idx = self.intern_string(var_name.clone());
-                    self.emit(Opcode::CallMethodOnGlobal(
-                        var_idx,
-                        method_idx,
-                        args.len() as u8,
-                    ));
-                }
-            }
-            _ => {
-                self.compile_expr(object)?;
-                for arg in args {
-                    self.compile_expr(&arg.value)?;
-                }
-                self.emit(Opcode::CallMethod(method_idx, args.len() as u8));
-            }
+    /// Call `method` on the object on top of the stack
+    pub(crate) fn compile_method_call_on_stack(
+        &mut self,
+        method: &str,
+        args: &[Argument],
+    ) -> Result<(), CompileError> {
+        let method_idx = self.intern_string(method.to_string());
+        for (pos, arg) in args.iter().enumerate() {
+            self.compile_arg_checked(&arg.value, Opcode::IsMethodRefArg(method_idx, pos as u8))?;
         }
-
+        self.emit(Opcode::CallMethod(method_idx, args.len() as u8));
         Ok(())
     }
 
@@ -127,7 +151,7 @@ impl Compiler {
         class_name: &str,
         method: &str,
         args: &[Argument],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         let has_named = args.iter().any(|arg| arg.name.is_some());
 
         if has_named {
@@ -142,14 +166,14 @@ impl Compiler {
                 }
             }
             self.emit(Opcode::NewArray(args.len() as u16));
-            let class_idx = self.intern_string(class_name.to_string());
+            let class_idx = self.intern_class_name(class_name);
             let method_idx = self.intern_string(method.to_string());
             self.emit(Opcode::CallStaticMethodNamed(class_idx, method_idx));
         } else {
             for arg in args {
-                self.compile_expr(&arg.value)?;
+                self.compile_arg(&arg.value)?;
             }
-            let class_idx = self.intern_string(class_name.to_string());
+            let class_idx = self.intern_class_name(class_name);
             let method_idx = self.intern_string(method.to_string());
             self.emit(Opcode::CallStaticMethod(
                 class_idx,
@@ -165,8 +189,8 @@ impl Compiler {
         &mut self,
         class: &str,
         property: &str,
-    ) -> Result<(), String> {
-        let class_idx = self.intern_string(class.to_string());
+    ) -> Result<(), CompileError> {
+        let class_idx = self.intern_class_name(class);
         let prop_idx = self.intern_string(property.to_string());
         self.emit(Opcode::LoadStaticProp(class_idx, prop_idx));
         Ok(())
@@ -176,10 +200,21 @@ impl Compiler {
         &mut self,
         class: &str,
         property: &str,
+        op: &AssignOp,
         value: &Expr,
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
+        if *op != AssignOp::Assign {
+            let target = Expr::StaticPropertyAccess {
+                class: class.to_string(),
+                property: property.to_string(),
+            };
+            return self.compile_compound_assign(target, op, value, |compiler, value| {
+                compiler.compile_static_property_assign(class, property, &AssignOp::Assign, value)
+            });
+        }
+
         self.compile_expr(value)?;
-        let class_idx = self.intern_string(class.to_string());
+        let class_idx = self.intern_class_name(class);
         let prop_idx = self.intern_string(property.to_string());
         self.emit(Opcode::StoreStaticProp(class_idx, prop_idx));
         Ok(())
@@ -191,20 +226,28 @@ impl Compiler {
         parent: &Option<String>,
         properties: &[crate::ast::Property],
         methods: &[crate::ast::Method],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         let anon_name = format!("__anon_class_{}", self.classes.len());
         let mut anon_class = CompiledClass::new(anon_name.clone());
 
         anon_class.parent = parent.clone();
-
+        anon_class.method_order = methods.iter().map(|m| m.name.clone()).collect();
+
+        let constants = self.inherited_constants(parent);
+        let scope = ConstantScope {
+            name: &anon_name,
+            parent: parent.as_deref(),
+            constants: &constants,
+        };
         for prop in properties {
-            let compiled_prop = CompiledProperty::from_ast(prop, false);
+            let compiled_prop = self.compile_property(prop, false, &scope)?;
             anon_class.properties.push(compiled_prop);
         }
 
         for method in methods {
             let method_name = format!("{}::{}", anon_name, method.name);
-            let mut method_compiler = Compiler::new(method_name.clone());
+            self.check_parameter_defaults(&method_name, &method.params);
+            let mut method_compiler = self.child_compiler(method_name.clone());
 
             if !method.is_static {
                 method_compiler.locals.insert("this".to_string(), 0);
@@ -233,8 +276,9 @@ impl Compiler {
                 .filter(|p| p.default.is_none() && !p.is_variadic)
                 .count() as u8;
 
-            method_compiler.function.parameters = method.params.clone();
-            method_compiler.function.attributes = method.attributes.clone();
+            method_compiler.function.parameters = self.link_params(&method.params, Some(&scope));
+            method_compiler.function.attributes =
+                self.link_attributes(&method.attributes, Some(&scope));
 
             for param in &method.params {
                 method_compiler
@@ -246,11 +290,15 @@ impl Compiler {
             for stmt in &method.body {
                 method_compiler.compile_stmt(stmt)?;
             }
+
+            method_compiler.function.is_generator = scans::contains_yield(&method.body);
+
             method_compiler.emit(Opcode::ReturnNull);
 
             for (inner_name, inner_func) in method_compiler.functions.drain() {
                 self.functions.insert(inner_name, inner_func);
             }
+            self.adopt_deprecations(&mut method_compiler);
 
             let compiled = Arc::new(method_compiler.function);
             anon_class.methods.insert(method.name.clone(), compiled);
@@ -263,7 +311,7 @@ impl Compiler {
 
         let arg_count = constructor_args.len();
         for arg in constructor_args {
-            self.compile_expr(&arg.value)?;
+            self.compile_arg(&arg.value)?;
         }
 
         self.emit(Opcode::CallConstructor(arg_count as u8));
@@ -271,20 +319,44 @@ impl Compiler {
         Ok(())
     }
 
+    /// `clone $obj with { prop: value }` is `clone($obj, ['prop' => value])`
     pub(crate) fn compile_clone_with(
         &mut self,
         object: &Expr,
         modifications: &[crate::ast::PropertyModification],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
+        let properties = Expr::Array(
+            modifications
+                .iter()
+                .map(|modification| crate::ast::ArrayElement {
+                    key: Some(Box::new(Expr::String(modification.property.clone()))),
+                    value: modification.value.clone(),
+                })
+                .collect(),
+        );
+        self.compile_clone_with_properties(object, &properties)
+    }
+
+    /// Both arguments are evaluated before the object is cloned, and the new
+    /// values are set after its `__clone()` ran
+    pub(crate) fn compile_clone_with_properties(
+        &mut self,
+        object: &Expr,
+        properties: &Expr,
+    ) -> Result<(), CompileError> {
         self.compile_expr(object)?;
+        self.compile_expr(properties)?;
+        self.emit(Opcode::Swap);
         self.emit(Opcode::Clone);
-
-        for modification in modifications {
-            self.compile_expr(&modification.value)?;
-            let prop_idx = self.intern_string(modification.property.clone());
-            self.emit(Opcode::StoreCloneProperty(prop_idx));
-        }
-
+        self.emit(Opcode::Swap);
+        self.emit(Opcode::CloneWith);
         Ok(())
     }
 }
+
+/// `self`, `static` or `parent` in lowercase, if `class_name` is one of them
+fn class_keyword(class_name: &str) -> Option<&'static str> {
+    ["self", "static", "parent"]
+        .into_iter()
+        .find(|keyword| class_name.eq_ignore_ascii_case(keyword))
+}
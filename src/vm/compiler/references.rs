@@ -0,0 +1,171 @@
+//! Compiling references: `$a = &$b`, `global $x`, and variables passed to
+//! functions, which may take them by reference
+//!
+//! A reference binds a variable, an element of an array a variable holds
+//! (at any depth), or a property. Elements and properties are passed by
+//! reference only to functions and methods called by name, which are
+//! asked first whether the parameter takes one (`IsRefArg`). A
+//! by-reference foreach loop binds its value variable to each element.
+
+use super::definite_assignment::SUPERGLOBALS;
+use super::{CompileError, Compiler};
+use crate::ast::Expr;
+use crate::vm::opcode::Opcode;
+
+impl Compiler {
+    /// `$var = &$source`
+    pub(crate) fn compile_assign_ref(
+        &mut self,
+        var: &str,
+        source: &Expr,
+    ) -> Result<(), CompileError> {
+        if var == "GLOBALS" {
+            return Err(self.globals_write_error());
+        }
+        if var == "this" {
+            return Err(self.invalid("Cannot re-assign $this"));
+        }
+        if let Some(source) = self.referenceable(source) {
+            let source = self.allocate_local(source.to_string());
+            let target = self.allocate_local(var.to_string());
+            self.emit(Opcode::AssignRef(target, source));
+            return Ok(());
+        }
+        if !self.binds_reference(source) {
+            return Err(self.invalid("Only variables can be assigned by reference"));
+        }
+        self.compile_reference(source, false)?;
+        let target = self.allocate_local(var.to_string());
+        self.emit(Opcode::BindRef(target));
+        Ok(())
+    }
+
+    /// `global $a, $b;`
+    pub(crate) fn compile_global(&mut self, names: &[String]) -> Result<(), CompileError> {
+        for name in names {
+            if SUPERGLOBALS.contains(&name.as_str()) {
+                continue;
+            }
+            if name == "this" {
+                return Err(self.invalid("Cannot use $this as global variable"));
+            }
+            let name_idx = self.intern_string(name.clone());
+            let slot = self.allocate_local(name.clone());
+            self.emit(Opcode::BindGlobal(name_idx, slot));
+        }
+        Ok(())
+    }
+
+    /// Compile an argument of a call to a function that may take it by
+    /// reference: a variable is passed as a reference (`LoadRef`), which
+    /// the call turns back into its value for a by-value parameter
+    pub(crate) fn compile_arg(&mut self, arg: &Expr) -> Result<(), CompileError> {
+        match self.referenceable(arg) {
+            Some(_) => self.compile_reference(arg, true),
+            None => self.compile_expr(arg),
+        }
+    }
+
+    /// Compile an argument a built-in function takes by reference: passing
+    /// a variable, an element or a property defines it
+    pub(crate) fn compile_ref_arg(&mut self, arg: &Expr) -> Result<(), CompileError> {
+        match self.binds_reference(arg) {
+            true => self.compile_reference(arg, false),
+            false => self.compile_expr(arg),
+        }
+    }
+
+    /// Compile an argument of a call whose function `by_ref` (`IsRefArg`
+    /// or `IsMethodRefArg`) tells whether the parameter takes a reference,
+    /// passing an element or a property by reference only if it does
+    pub(crate) fn compile_arg_checked(
+        &mut self,
+        arg: &Expr,
+        by_ref: Opcode,
+    ) -> Result<(), CompileError> {
+        if self.referenceable(arg).is_some() || !self.binds_reference(arg) {
+            return self.compile_arg(arg);
+        }
+        self.emit(by_ref);
+        let by_value = self.emit_jump(Opcode::JumpIfFalse(0));
+        self.compile_reference(arg, false)?;
+        let end = self.emit_jump(Opcode::Jump(0));
+        self.patch_jump(by_value);
+        self.compile_expr(arg)?;
+        self.patch_jump(end);
+        Ok(())
+    }
+
+    /// Bind `value_slot` to the element at the key in `key_slot` of what a
+    /// by-reference foreach loop walks: `source` itself if given, or else
+    /// the loop's own copy in `array_slot`
+    pub(crate) fn compile_foreach_ref(
+        &mut self,
+        source: Option<&Expr>,
+        array_slot: u16,
+        key_slot: u16,
+        value_slot: u16,
+    ) -> Result<(), CompileError> {
+        match source {
+            Some(source) => self.compile_reference(source, false)?,
+            None => {
+                self.emit(Opcode::LoadRef(array_slot));
+            }
+        }
+        self.emit(Opcode::LoadFast(key_slot));
+        self.emit(Opcode::ElementRef);
+        self.emit(Opcode::BindRef(value_slot));
+        self.emit(Opcode::Pop);
+        Ok(())
+    }
+
+    /// Push a reference to what `expr` names. A variable read through it
+    /// warns if `checked` and it may not be assigned; otherwise binding it
+    /// defines it.
+    fn compile_reference(&mut self, expr: &Expr, checked: bool) -> Result<(), CompileError> {
+        match expr {
+            Expr::ArrayAccess { array, index } => {
+                self.compile_reference(array, false)?;
+                self.compile_expr(index)?;
+                self.emit(Opcode::ElementRef);
+            }
+            Expr::PropertyAccess { object, property } => {
+                self.compile_expr(object)?;
+                let name_idx = self.intern_string(property.clone());
+                self.emit(Opcode::PropertyRef(name_idx));
+            }
+            _ => {
+                let Some(name) = self.referenceable(expr) else {
+                    return Err(self.invalid("Only variables can be assigned by reference"));
+                };
+                let checked = checked && !self.is_definitely_assigned(name);
+                let slot = self.allocate_local(name.to_string());
+                self.emit(match checked {
+                    true => Opcode::LoadRefChecked(slot),
+                    false => Opcode::LoadRef(slot),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a reference can bind `expr`: a variable, an element of an
+    /// array one holds, or a property
+    pub(crate) fn binds_reference(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::ArrayAccess { array, .. } => self.binds_reference(array),
+            Expr::PropertyAccess { .. } => true,
+            expr => self.referenceable(expr).is_some(),
+        }
+    }
+
+    /// The variable `expr` names, if a reference can bind it
+    fn referenceable<'e>(&self, expr: &'e Expr) -> Option<&'e str> {
+        match expr {
+            Expr::Variable(name) if name != "this" && !SUPERGLOBALS.contains(&name.as_str()) => {
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Pre-compilation scans over function bodies
+
+use crate::ast::visit::{self, Visitor};
+use crate::ast::{Expr, FunctionParam, Method, Property, Stmt};
+use std::collections::HashSet;
+
+/// Whether a function body yields, which makes the function a generator
+///
+/// Nested functions, classes and arrow functions are their own scopes and
+/// are not searched.
+pub(super) fn contains_yield(body: &[Stmt]) -> bool {
+    let mut scan = YieldScan { found: false };
+    visit::walk_block(&mut scan, body);
+    scan.found
+}
+
+struct YieldScan {
+    found: bool,
+}
+
+impl Visitor for YieldScan {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if !self.found && !is_scope(stmt) {
+            visit::walk_stmt(self, stmt);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Yield { .. } | Expr::YieldFrom(_) => self.found = true,
+            Expr::ArrowFunction { .. } | Expr::NewAnonymousClass { .. } => {}
+            _ if !self.found => visit::walk_expr(self, expr),
+            _ => {}
+        }
+    }
+}
+
+/// Variables an arrow function captures by value from the enclosing scope:
+/// every variable its body reads that is not one of its parameters, in
+/// order of first use
+pub(super) fn captured_vars(params: &[FunctionParam], body: &Expr) -> Vec<String> {
+    let mut scan = CaptureScan {
+        params: params.iter().map(|p| p.name.clone()).collect(),
+        captured: Vec::new(),
+    };
+    scan.visit_expr(body);
+    scan.captured
+}
+
+struct CaptureScan {
+    params: HashSet<String>,
+    captured: Vec<String>,
+}
+
+impl Visitor for CaptureScan {
+    fn visit_stmt(&mut self, _stmt: &Stmt) {}
+
+    fn visit_method(&mut self, _method: &Method) {}
+
+    fn visit_property(&mut self, _property: &Property) {}
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(name) => {
+                if !self.params.contains(name) && !self.captured.contains(name) {
+                    self.captured.push(name.clone());
+                }
+            }
+            Expr::ArrowFunction { params, body } => {
+                // A nested arrow function captures from this one, so its
+                // parameters are not free variables here
+                let added: Vec<String> = params
+                    .iter()
+                    .filter(|p| self.params.insert(p.name.clone()))
+                    .map(|p| p.name.clone())
+                    .collect();
+                self.visit_expr(body);
+                for name in added {
+                    self.params.remove(&name);
+                }
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+/// Declarations whose bodies run in their own scope
+fn is_scope(stmt: &Stmt) -> bool {
+    matches!(
+        stmt,
+        Stmt::Function { .. }
+            | Stmt::Class { .. }
+            | Stmt::Trait { .. }
+            | Stmt::Interface { .. }
+            | Stmt::Enum { .. }
+    )
+}
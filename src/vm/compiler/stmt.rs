@@ -1,9 +1,17 @@
-use super::Compiler;
+use super::{CompileError, Compiler};
 
 impl Compiler {
     /// Compile a statement (internal implementation)
-    pub(crate) fn compile_stmt_internal(&mut self, stmt: &crate::ast::Stmt) -> Result<(), String> {
+    pub(crate) fn compile_stmt_internal(
+        &mut self,
+        stmt: &crate::ast::Stmt,
+    ) -> Result<(), CompileError> {
         match stmt {
+            crate::ast::Stmt::Line(line) => {
+                let offset = self.function.bytecode.len();
+                self.function.lines.push((offset, *line));
+                self.line = *line;
+            }
             crate::ast::Stmt::Echo(exprs) => {
                 for expr in exprs {
                     self.compile_expr(expr)?;
@@ -14,6 +22,7 @@ impl Compiler {
                 self.compile_expr(expr)?;
                 self.emit(crate::vm::opcode::Opcode::Pop);
             }
+            crate::ast::Stmt::Global(names) => self.compile_global(names)?,
             crate::ast::Stmt::Return(expr) => {
                 if let Some(expr) = expr {
                     self.compile_expr(expr)?;
@@ -48,9 +57,10 @@ impl Compiler {
                 array,
                 key,
                 value,
+                by_ref,
                 body,
             } => {
-                self.compile_foreach(array, key, value, body)?;
+                self.compile_foreach(array, key, value, *by_ref, body)?;
             }
             crate::ast::Stmt::Break => {
                 self.emit(crate::vm::opcode::Opcode::Break);
@@ -67,12 +77,8 @@ impl Compiler {
             } => {
                 self.compile_function(name, params, return_type, body, attributes)?;
             }
-            crate::ast::Stmt::Switch {
-                expr,
-                cases,
-                default,
-            } => {
-                self.compile_switch(expr, cases, default)?;
+            crate::ast::Stmt::Switch { expr, cases } => {
+                self.compile_switch(expr, cases)?;
             }
             crate::ast::Stmt::Html(content) => {
                 let idx = self.intern_string(content.clone());
@@ -83,12 +89,11 @@ impl Compiler {
                 for directive in directives {
                     if let crate::ast::DeclareDirective::StrictTypes(enabled) = directive {
                         self.strict_types = *enabled;
+                        self.function.strict_types = *enabled;
                     }
                 }
                 if let Some(stmts) = body {
-                    for stmt in stmts {
-                        self.compile_stmt(stmt)?;
-                    }
+                    self.compile_declaring_block(stmts)?;
                 }
             }
             crate::ast::Stmt::Namespace { name, body } => {
@@ -100,9 +105,7 @@ impl Compiler {
 
                 match body {
                     crate::ast::NamespaceBody::Braced(stmts) => {
-                        for stmt in stmts {
-                            self.compile_stmt(stmt)?;
-                        }
+                        self.compile_declaring_block(stmts)?;
                         self.current_namespace = prev_namespace;
                         self.use_aliases = prev_use_aliases;
                     }
@@ -155,6 +158,7 @@ impl Compiler {
                 parent,
                 interfaces,
                 trait_uses,
+                constants,
                 properties,
                 methods,
                 attributes,
@@ -167,6 +171,7 @@ impl Compiler {
                     parent,
                     interfaces,
                     trait_uses,
+                    constants,
                     properties,
                     methods,
                     attributes,
@@ -193,13 +198,37 @@ impl Compiler {
             crate::ast::Stmt::Enum {
                 name,
                 backing_type,
+                interfaces,
                 cases,
+                constants,
                 methods,
                 attributes,
             } => {
-                self.compile_enum(name, backing_type, cases, methods, attributes)?;
+                self.compile_enum(
+                    name,
+                    backing_type,
+                    interfaces,
+                    cases,
+                    constants,
+                    methods,
+                    attributes,
+                )?;
             }
         }
         Ok(())
     }
+
+    /// Compile the body of a braced namespace or declare block, whose
+    /// function declarations are as unconditional as the block's own
+    fn compile_declaring_block(&mut self, stmts: &[crate::ast::Stmt]) -> Result<(), CompileError> {
+        let unconditional = self.stmt_depth == self.declaration_depth;
+        if unconditional {
+            self.declaration_depth += 1;
+        }
+        let result = stmts.iter().try_for_each(|stmt| self.compile_stmt(stmt));
+        if unconditional {
+            self.declaration_depth -= 1;
+        }
+        result
+    }
 }
@@ -1,7 +1,10 @@
-use super::Compiler;
+use super::class_constants::ConstantScope;
+use super::{const_expr, scans, CompileError, Compiler};
 
-use crate::ast::{Attribute, Expr, Method};
+use crate::ast::{Attribute, InterfaceConstant, Method, QualifiedName};
+use crate::runtime::Value;
 use crate::vm::opcode::Opcode;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 impl Compiler {
@@ -12,21 +15,30 @@ impl Compiler {
         properties: &[crate::ast::Property],
         methods: &[Method],
         attributes: &[Attribute],
-    ) -> Result<(), String> {
-        use crate::vm::class::{CompiledProperty, CompiledTrait};
+    ) -> Result<(), CompileError> {
+        use crate::vm::class::CompiledTrait;
 
         let mut compiled_trait = CompiledTrait::new(name.to_string());
         compiled_trait.uses = uses.to_vec();
-        compiled_trait.attributes = attributes.to_vec();
+        compiled_trait.method_order = methods.iter().map(|m| m.name.clone()).collect();
+
+        let constants = HashMap::new();
+        let scope = ConstantScope {
+            name,
+            parent: None,
+            constants: &constants,
+        };
+        compiled_trait.attributes = self.link_attributes(attributes, Some(&scope));
 
         for prop in properties {
-            let compiled_prop = CompiledProperty::from_ast(prop, false);
+            let compiled_prop = self.compile_property(prop, false, &scope)?;
             compiled_trait.properties.push(compiled_prop);
         }
 
         for method in methods {
             let method_name = format!("{}::{}", name, method.name);
-            let mut method_compiler = Compiler::new(method_name.clone());
+            self.check_parameter_defaults(&method_name, &method.params);
+            let mut method_compiler = self.child_compiler(method_name.clone());
 
             // Set trait context for __TRAIT__ magic constant
             method_compiler.current_trait = Some(name.to_string());
@@ -62,25 +74,31 @@ impl Compiler {
                 .as_ref()
                 .map(|t| method_compiler.resolve_type_hint(t));
 
-            method_compiler.function.parameters = method.params.clone();
-            method_compiler.function.attributes = method.attributes.clone();
+            method_compiler.function.parameters = self.link_params(&method.params, Some(&scope));
+            method_compiler.function.attributes =
+                self.link_attributes(&method.attributes, Some(&scope));
 
             for param in &method.params {
-                method_compiler
-                    .function
-                    .param_types
-                    .push(param.type_hint.clone());
+                method_compiler.function.param_types.push(
+                    param
+                        .type_hint
+                        .as_ref()
+                        .map(|t| method_compiler.resolve_type_hint(t)),
+                );
             }
 
             for stmt in &method.body {
                 method_compiler.compile_stmt(stmt)?;
             }
 
+            method_compiler.function.is_generator = scans::contains_yield(&method.body);
+
             method_compiler.emit(Opcode::ReturnNull);
 
             for (inner_name, inner_func) in method_compiler.functions.drain() {
                 self.functions.insert(inner_name, inner_func);
             }
+            self.adopt_deprecations(&mut method_compiler);
 
             let compiled = Arc::new(method_compiler.function);
             compiled_trait.methods.insert(method.name.clone(), compiled);
@@ -91,32 +109,65 @@ impl Compiler {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn compile_enum_internal(
         &mut self,
         name: &str,
         backing_type: &crate::ast::EnumBackingType,
+        interfaces: &[QualifiedName],
         cases: &[crate::ast::EnumCase],
+        constants: &[InterfaceConstant],
         methods: &[Method],
         attributes: &[Attribute],
-    ) -> Result<(), String> {
+    ) -> Result<(), CompileError> {
         use crate::ast::EnumBackingType;
-        use crate::runtime::Value;
         use crate::vm::class::CompiledEnum;
 
         let mut compiled_enum = CompiledEnum::new(name.to_string(), *backing_type);
-        compiled_enum.attributes = attributes.to_vec();
+        compiled_enum.interfaces = interfaces
+            .iter()
+            .map(|i| self.resolve_qualified_name(i))
+            .collect();
+
+        for iface_name in &compiled_enum.interfaces {
+            if !self.interfaces.contains_key(iface_name) {
+                return Err(self.invalid(format!("Interface '{}' not found", iface_name)));
+            }
+        }
+
+        compiled_enum.constant_order = constants.iter().map(|c| c.name.clone()).collect();
+        compiled_enum.method_order = methods.iter().map(|m| m.name.clone()).collect();
+        let (backing_values, constant_values) = self.enum_values(name, cases, constants);
+        for constant in constants {
+            let value = constant_values.get(constant.name.as_str()).ok_or_else(|| {
+                self.invalid(format!(
+                    "Constant expression of {}::{} contains invalid operations",
+                    name, constant.name
+                ))
+            })?;
+            compiled_enum
+                .constants
+                .insert(constant.name.clone(), value.clone());
+        }
+        for (constant_name, value) in self.inherited_constants(&compiled_enum.interfaces) {
+            compiled_enum
+                .constants
+                .entry(constant_name)
+                .or_insert(value);
+        }
 
         let mut seen_values: std::collections::HashSet<String> = std::collections::HashSet::new();
         for case in cases {
-            let backing_value = if let Some(expr) = &case.value {
-                match expr {
-                    Expr::Integer(n) => Some(Value::Integer(*n)),
-                    Expr::Float(n) => Some(Value::Float(*n)),
-                    Expr::String(s) => Some(Value::String(s.clone())),
-                    _ => None,
-                }
-            } else {
-                None
+            let backing_value = match &case.value {
+                Some(_) => Some(backing_values.get(case.name.as_str()).cloned().ok_or_else(
+                    || {
+                        self.invalid(format!(
+                            "Enum case value of {}::{} must be compile-time evaluatable",
+                            name, case.name
+                        ))
+                    },
+                )?),
+                None => None,
             };
 
             if let Some(ref val) = backing_value {
@@ -132,15 +183,15 @@ impl Compiler {
                         EnumBackingType::String => "string",
                         EnumBackingType::None => "none",
                     };
-                    return Err(format!(
+                    return Err(self.invalid(format!(
                         "Enum case '{}::{}' must have {} backing value",
                         name, case.name, expected_type
-                    ));
+                    )));
                 }
 
                 let val_str = format!("{:?}", val);
                 if !seen_values.insert(val_str) {
-                    return Err("Duplicate case value in backed enum".to_string());
+                    return Err(self.invalid("Duplicate case value in backed enum"));
                 }
             }
 
@@ -148,9 +199,19 @@ impl Compiler {
             compiled_enum.case_order.push(case.name.clone());
         }
 
+        let constants = compiled_enum.constants.clone();
+        let scope = ConstantScope {
+            name,
+            parent: None,
+            constants: &constants,
+        };
+        compiled_enum.attributes = self.link_attributes(attributes, Some(&scope));
+
         for method in methods {
             let method_name = format!("{}::{}", name, method.name);
-            let mut method_compiler = Compiler::new(method_name.clone());
+            self.check_parameter_defaults(&method_name, &method.params);
+            let mut method_compiler = self.child_compiler(method_name.clone());
+            method_compiler.current_class = Some(name.to_string());
 
             if !method.is_static {
                 method_compiler.locals.insert("this".to_string(), 0);
@@ -183,35 +244,77 @@ impl Compiler {
                 .as_ref()
                 .map(|t| method_compiler.resolve_type_hint(t));
 
-            method_compiler.function.parameters = method.params.clone();
-            method_compiler.function.attributes = method.attributes.clone();
+            method_compiler.function.parameters = self.link_params(&method.params, Some(&scope));
+            method_compiler.function.attributes =
+                self.link_attributes(&method.attributes, Some(&scope));
 
             for param in &method.params {
-                method_compiler
-                    .function
-                    .param_types
-                    .push(param.type_hint.clone());
+                method_compiler.function.param_types.push(
+                    param
+                        .type_hint
+                        .as_ref()
+                        .map(|t| method_compiler.resolve_type_hint(t)),
+                );
+            }
+
+            for (i, param) in method.params.iter().enumerate() {
+                if let Some(default_expr) = &param.default {
+                    let slot = param_start + i as u16;
+                    method_compiler.emit(Opcode::LoadFast(slot));
+                    let skip_jump = method_compiler.emit_jump(Opcode::JumpIfNotNull(0));
+                    method_compiler.emit(Opcode::Pop);
+                    method_compiler.compile_expr(default_expr)?;
+                    method_compiler.emit(Opcode::StoreFast(slot));
+                    let end_jump = method_compiler.emit_jump(Opcode::Jump(0));
+                    method_compiler.patch_jump(skip_jump);
+                    method_compiler.emit(Opcode::Pop);
+                    method_compiler.patch_jump(end_jump);
+                }
             }
 
             for stmt in &method.body {
                 method_compiler.compile_stmt(stmt)?;
             }
 
+            method_compiler.function.is_generator = scans::contains_yield(&method.body);
+
             method_compiler.emit(Opcode::ReturnNull);
 
             for (inner_name, inner_func) in method_compiler.functions.drain() {
                 self.functions.insert(inner_name, inner_func);
             }
+            self.adopt_deprecations(&mut method_compiler);
 
             let compiled = Arc::new(method_compiler.function);
-            compiled_enum.methods.insert(method.name.clone(), compiled);
+            if method.is_static {
+                compiled_enum
+                    .static_methods
+                    .insert(method.name.clone(), compiled);
+            } else {
+                compiled_enum.methods.insert(method.name.clone(), compiled);
+            }
+        }
+
+        for interface in &compiled_enum.interfaces {
+            if let Some(iface_def) = self.interfaces.get(interface) {
+                for (method_name, _param_count) in &iface_def.method_signatures {
+                    let has_method = compiled_enum.methods.contains_key(method_name)
+                        || compiled_enum.static_methods.contains_key(method_name);
+                    if !has_method {
+                        return Err(self.invalid(format!(
+                            "Enum '{}' does not implement method '{}' from interface '{}'",
+                            name, method_name, interface
+                        )));
+                    }
+                }
+            }
         }
 
         // Add built-in static methods for backed enums: from() and tryFrom()
         if *backing_type != EnumBackingType::None {
             // Compile from() method (throws if not found)
             let from_method_name = format!("{}::from", name);
-            let mut from_compiler = Compiler::new(from_method_name);
+            let mut from_compiler = self.child_compiler(from_method_name);
 
             // Parameter: the value to search for
             from_compiler.locals.insert("value".to_string(), 0);
@@ -240,7 +343,7 @@ impl Compiler {
 
             // Compile tryFrom() method (returns null if not found)
             let try_from_method_name = format!("{}::tryFrom", name);
-            let mut try_from_compiler = Compiler::new(try_from_method_name);
+            let mut try_from_compiler = self.child_compiler(try_from_method_name);
 
             // Parameter: the value to search for
             try_from_compiler.locals.insert("value".to_string(), 0);
@@ -274,4 +377,65 @@ impl Compiler {
         self.enums.insert(name.to_string(), Arc::new(compiled_enum));
         Ok(())
     }
+
+    /// The backing values of an enum's cases and the values of its
+    /// constants, by name. Each may refer to the enum's other cases and
+    /// constants, declared before or after it, and to those of enums
+    /// compiled earlier; the ones that aren't constant expressions, or
+    /// depend on one that isn't, are left out.
+    fn enum_values<'a>(
+        &self,
+        name: &str,
+        cases: &'a [crate::ast::EnumCase],
+        constants: &'a [InterfaceConstant],
+    ) -> (HashMap<&'a str, Value>, HashMap<&'a str, Value>) {
+        let mut backing_values: HashMap<&str, Value> = HashMap::new();
+        let mut constant_values: HashMap<&str, Value> = HashMap::new();
+        loop {
+            let lookup = |class: &str, item: &str| -> Option<Value> {
+                if !matches!(class, "self" | "static") && !class.eq_ignore_ascii_case(name) {
+                    return self
+                        .class_constant(&self.qualify_class_name(class), item)
+                        .or_else(|| self.class_constant(class.trim_start_matches('\\'), item));
+                }
+                if let Some(case) = cases.iter().find(|case| case.name == item) {
+                    let backing_value = match case.value {
+                        Some(_) => Some(Box::new(backing_values.get(item)?.clone())),
+                        None => None,
+                    };
+                    return Some(Value::EnumCase {
+                        enum_name: name.to_string(),
+                        case_name: item.to_string(),
+                        backing_value,
+                    });
+                }
+                constant_values.get(item).cloned()
+            };
+
+            let mut new_backing_values = Vec::new();
+            for case in cases {
+                if let Some(expr) = &case.value {
+                    if !backing_values.contains_key(case.name.as_str()) {
+                        if let Some(value) = const_expr::evaluate(expr, &lookup) {
+                            new_backing_values.push((case.name.as_str(), value));
+                        }
+                    }
+                }
+            }
+            let mut new_constant_values = Vec::new();
+            for constant in constants {
+                if !constant_values.contains_key(constant.name.as_str()) {
+                    if let Some(value) = const_expr::evaluate(&constant.value, &lookup) {
+                        new_constant_values.push((constant.name.as_str(), value));
+                    }
+                }
+            }
+
+            if new_backing_values.is_empty() && new_constant_values.is_empty() {
+                return (backing_values, constant_values);
+            }
+            backing_values.extend(new_backing_values);
+            constant_values.extend(new_constant_values);
+        }
+    }
 }
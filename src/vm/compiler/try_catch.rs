@@ -1,64 +1,109 @@
-use super::Compiler;
+//! Try/catch/finally compilation
+//!
+//! `TryStart` pushes the statement's exception handler and `TryEnd` pops
+//! it once the try block is over (or, with a finally block, the catch
+//! block, so that exceptions and jumps out of the catch block still run
+//! the finally block). A caught exception lands at the catch clauses
+//! with the exception on the stack; each `Catch` tests it against one of
+//! a clause's types, and an exception no clause matches is thrown on.
+//!
+//! The finally block starts with `FinallyStart`, where the statement
+//! enters it after ending normally. A return, break, continue or
+//! exception leaving the statement enters it right after, and the VM
+//! finishes that jump at `FinallyEnd`.
+
+use super::{CompileError, Compiler};
+use crate::ast::{CatchClause, Stmt};
+use crate::vm::opcode::Opcode;
 
 impl Compiler {
     pub(crate) fn compile_try_catch_internal(
         &mut self,
-        try_body: &[crate::ast::Stmt],
-        catch_clauses: &[crate::ast::CatchClause],
-        finally_body: &Option<Vec<crate::ast::Stmt>>,
-    ) -> Result<(), String> {
-        let try_start = self.emit_jump(crate::vm::opcode::Opcode::TryStart(0, 0));
+        try_body: &[Stmt],
+        catch_clauses: &[CatchClause],
+        finally_body: &Option<Vec<Stmt>>,
+    ) -> Result<(), CompileError> {
+        let try_start = self.emit_jump(Opcode::TryStart(0, 0));
 
         for stmt in try_body {
             self.compile_stmt(stmt)?;
         }
 
-        self.emit(crate::vm::opcode::Opcode::TryEnd);
-
-        let skip_catch = self.emit_jump(crate::vm::opcode::Opcode::Jump(0));
-
-        let catch_offset = self.current_offset() as u32;
-        if let crate::vm::opcode::Opcode::TryStart(ref mut c, _) = self.function.bytecode[try_start]
-        {
-            *c = catch_offset;
-        }
-
-        let mut end_catch_jumps = Vec::new();
-        for (i, catch) in catch_clauses.iter().enumerate() {
-            let var_slot = self.allocate_local(catch.variable.clone());
+        self.emit(Opcode::TryEnd);
 
-            self.emit(crate::vm::opcode::Opcode::StoreFast(var_slot));
+        let mut end_jumps = vec![self.emit_jump(Opcode::Jump(0))];
 
-            for stmt in &catch.body {
-                self.compile_stmt(stmt)?;
+        if !catch_clauses.is_empty() {
+            let catch_offset = self.current_offset() as u32;
+            if let Opcode::TryStart(ref mut c, _) = self.function.bytecode[try_start] {
+                *c = catch_offset;
             }
 
-            if i < catch_clauses.len() - 1 {
-                let jump_to_end = self.emit_jump(crate::vm::opcode::Opcode::Jump(0));
-                end_catch_jumps.push(jump_to_end);
+            for catch in catch_clauses {
+                end_jumps.push(self.compile_catch_clause(catch, finally_body.is_some())?);
             }
+
+            // No clause catches the exception
+            self.emit(Opcode::Throw);
         }
 
-        self.patch_jump(skip_catch);
-        for jump in end_catch_jumps {
+        for jump in end_jumps {
             self.patch_jump(jump);
         }
 
         if let Some(finally) = finally_body {
             let finally_offset = self.current_offset() as u32;
-            if let crate::vm::opcode::Opcode::TryStart(_, ref mut f) =
-                self.function.bytecode[try_start]
-            {
+            if let Opcode::TryStart(_, ref mut f) = self.function.bytecode[try_start] {
                 *f = finally_offset;
             }
 
-            self.emit(crate::vm::opcode::Opcode::FinallyStart);
+            self.emit(Opcode::FinallyStart);
             for stmt in finally {
                 self.compile_stmt(stmt)?;
             }
-            self.emit(crate::vm::opcode::Opcode::FinallyEnd);
+            self.emit(Opcode::FinallyEnd);
         }
 
         Ok(())
     }
+
+    /// Compile a catch clause: the tests of its types, falling through to
+    /// the next clause if none matches, then its body. Returns the jump
+    /// past the statement that ends the body.
+    fn compile_catch_clause(
+        &mut self,
+        catch: &CatchClause,
+        has_finally: bool,
+    ) -> Result<usize, CompileError> {
+        let mut matched_jumps = Vec::new();
+        for exception_type in &catch.exception_types {
+            let class_name = self.qualify_class_name(exception_type);
+            let class_idx = self.intern_string(class_name);
+            self.emit(Opcode::Catch(class_idx));
+            matched_jumps.push(self.emit_jump(Opcode::JumpIfTrue(0)));
+        }
+        let next_clause = self.emit_jump(Opcode::Jump(0));
+
+        for jump in matched_jumps {
+            self.patch_jump(jump);
+        }
+        if catch.variable.is_empty() {
+            self.emit(Opcode::Pop);
+        } else {
+            let var_slot = self.allocate_local(catch.variable.clone());
+            self.emit(Opcode::StoreFast(var_slot));
+        }
+
+        for stmt in &catch.body {
+            self.compile_stmt(stmt)?;
+        }
+
+        if has_finally {
+            self.emit(Opcode::TryEnd);
+        }
+        let end_jump = self.emit_jump(Opcode::Jump(0));
+
+        self.patch_jump(next_clause);
+        Ok(end_jump)
+    }
 }
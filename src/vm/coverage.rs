@@ -0,0 +1,81 @@
+//! Line coverage collection
+//!
+//! Coverage is statement based: the compiler records the bytecode offset
+//! at which each statement starts (`CompiledFunction::lines`) and the VM
+//! counts a hit whenever execution reaches one of those offsets.
+
+use super::compiler::CompilationResult;
+use super::opcode::CompiledFunction;
+use std::collections::BTreeMap;
+
+/// Hit counts per source line, grouped by file
+///
+/// Executable lines that never ran are present with a count of zero.
+#[derive(Debug, Default, Clone)]
+pub struct Coverage {
+    pub files: BTreeMap<String, BTreeMap<usize, u64>>,
+}
+
+impl Coverage {
+    /// Mark every statement line of a function as executable
+    pub fn add_function(&mut self, function: &CompiledFunction) {
+        let lines = self.files.entry(function.file.clone()).or_default();
+        for (_, line) in &function.lines {
+            lines.entry(*line).or_insert(0);
+        }
+    }
+
+    /// Mark every statement line of a compilation unit as executable
+    pub fn add_compilation(&mut self, compilation: &CompilationResult) {
+        self.add_function(&compilation.main);
+        for function in compilation.functions.values() {
+            self.add_function(function);
+        }
+        for class in compilation.classes.values() {
+            for method in class.methods.values().chain(class.static_methods.values()) {
+                self.add_function(method);
+            }
+        }
+        for tr in compilation.traits.values() {
+            for method in tr.methods.values() {
+                self.add_function(method);
+            }
+        }
+        for en in compilation.enums.values() {
+            for method in en.methods.values().chain(en.static_methods.values()) {
+                self.add_function(method);
+            }
+        }
+    }
+
+    /// Count a hit for every statement starting at bytecode offset `ip`
+    pub fn record(&mut self, function: &CompiledFunction, ip: usize) {
+        for (_, line) in function.statements_at(ip) {
+            let lines = self.files.entry(function.file.clone()).or_default();
+            *lines.entry(*line).or_insert(0) += 1;
+        }
+    }
+
+    /// Add the counts of another coverage set to this one
+    pub fn merge(&mut self, other: Coverage) {
+        for (file, lines) in other.files {
+            let target = self.files.entry(file).or_default();
+            for (line, hits) in lines {
+                *target.entry(line).or_insert(0) += hits;
+            }
+        }
+    }
+
+    /// Shift all line numbers of `file` by `offset`
+    ///
+    /// Used when the compiled source is embedded in a larger file, such as
+    /// the --FILE-- section of a .vhpt test.
+    pub fn relocate(&mut self, file: &str, offset: usize) {
+        if let Some(lines) = self.files.get_mut(file) {
+            *lines = std::mem::take(lines)
+                .into_iter()
+                .map(|(line, hits)| (line + offset, hits))
+                .collect();
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Execution time limits
+//!
+//! Two deadlines stop runaway scripts. The host's, set with
+//! `VM::set_time_limit`, is out of the script's reach. The script's own
+//! comes from the `max_execution_time` directive and, as in PHP, restarts
+//! whenever the script calls `set_time_limit()` or changes the directive
+//! with `ini_set()`.
+//!
+//! Both are checked cooperatively every `DEADLINE_CHECK_INTERVAL` opcodes.
+//! Exceeding either aborts execution with a `VmError::TimeLimit`, whose
+//! message starts with `TIME_LIMIT_ERROR`: `finally` blocks and `catch`
+//! clauses don't run, so the script really stops, and the host gets a
+//! `RuntimeError::TimeLimit`. Waiting for a timer sleeps no longer than
+//! the time left.
+
+use super::VM;
+use crate::runtime::builtins::{ini, native};
+use crate::runtime::{clock, Value};
+use crate::vm::error::VmError;
+use std::io::Write;
+use std::time::Duration;
+
+/// Prefix of the fatal error raised when a time limit is exceeded
+pub const TIME_LIMIT_ERROR: &str = "Maximum execution time of";
+
+/// Opcodes executed between two deadline checks
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
+
+/// A time limit and the time it expires on the monotonic clock
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    limit: Duration,
+    expires: Duration,
+}
+
+impl Deadline {
+    /// A deadline `limit` from now, or None for no limit (or one too far
+    /// away to reach)
+    fn start(limit: Option<Duration>) -> Option<Self> {
+        let limit = limit?;
+        Some(Self {
+            limit,
+            expires: clock::monotonic().checked_add(limit)?,
+        })
+    }
+
+    fn error(&self) -> String {
+        let seconds = self.limit.as_secs_f64();
+        format!(
+            "{} {} second{} exceeded",
+            TIME_LIMIT_ERROR,
+            seconds,
+            if seconds == 1.0 { "" } else { "s" }
+        )
+    }
+}
+
+/// The limit a `max_execution_time` value in seconds sets (0, or more
+/// than a duration holds, for none)
+fn parse_limit(seconds: &str) -> Option<Duration> {
+    seconds
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|s| *s > 0.0)
+        .and_then(|s| Duration::try_from_secs_f64(s).ok())
+}
+
+impl<W: Write> VM<W> {
+    /// Limit wall-clock execution time from now on (None removes the limit)
+    ///
+    /// Scripts can't change this limit; exceeding it aborts execution with
+    /// a `RuntimeError::TimeLimit`.
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.deadline = Deadline::start(limit);
+    }
+
+    /// Start the script's time limit from the `max_execution_time`
+    /// directive, restarting it if one was running
+    pub fn apply_max_execution_time(&mut self) {
+        let seconds = ini::get_ini_value("max_execution_time").unwrap_or_default();
+        self.script_deadline = Deadline::start(parse_limit(&seconds));
+    }
+
+    /// Fail once either deadline has passed
+    #[inline]
+    pub(crate) fn check_deadline(&mut self) -> Result<(), VmError> {
+        if self.deadline.is_none() && self.script_deadline.is_none() {
+            return Ok(());
+        }
+        self.ticks += 1;
+        if self.ticks < DEADLINE_CHECK_INTERVAL {
+            return Ok(());
+        }
+        self.ticks = 0;
+        let now = clock::monotonic();
+        match [self.deadline, self.script_deadline]
+            .into_iter()
+            .flatten()
+            .find(|deadline| now >= deadline.expires)
+        {
+            Some(deadline) => Err(VmError::TimeLimit(deadline.error())),
+            None => Ok(()),
+        }
+    }
+
+    /// Sleep for `wait`, or only until a deadline expires and fail then
+    pub(crate) fn sleep(&mut self, wait: Duration) -> Result<(), VmError> {
+        let now = clock::monotonic();
+        let first = [self.deadline, self.script_deadline]
+            .into_iter()
+            .flatten()
+            .min_by_key(|deadline| deadline.expires);
+        match first {
+            Some(deadline) if deadline.expires.saturating_sub(now) <= wait => {
+                clock::sleep(deadline.expires.saturating_sub(now));
+                Err(VmError::TimeLimit(deadline.error()))
+            }
+            _ => {
+                clock::sleep(wait);
+                Ok(())
+            }
+        }
+    }
+
+    /// `set_time_limit()`: set `max_execution_time` and restart the
+    /// script's time limit (0 removes it)
+    pub(crate) fn set_script_time_limit(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        native::check_arity("set_time_limit", args.len(), 1, Some(1))?;
+        let seconds: i64 = native::arg("set_time_limit", args, 0, "seconds")?;
+        ini::set_ini_value("max_execution_time", &seconds.max(0).to_string());
+        self.apply_max_execution_time();
+        Ok(Value::Bool(true))
+    }
+
+    /// `ini_set()` and `ini_restore()`, restarting the script's time limit
+    /// when they change `max_execution_time`
+    pub(crate) fn set_ini_directive(
+        &mut self,
+        name: &str,
+        args: &[Value],
+    ) -> Result<Value, VmError> {
+        let result = if name == "ini_set" {
+            ini::ini_set(args)?
+        } else {
+            ini::ini_restore(args)?
+        };
+        if args
+            .first()
+            .is_some_and(|d| d.to_string_val() == "max_execution_time")
+        {
+            self.apply_max_execution_time();
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,151 @@
+//! Debugger hook interface
+//!
+//! When a `DebugHook` is attached, the VM calls it before each statement
+//! (statement starts come from the line table in `CompiledFunction::lines`)
+//! and whenever an exception is thrown, with a read-only view of the call
+//! stack and globals.
+
+use super::frame::CallFrame;
+use super::VM;
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Read-only view of the VM while it is paused at a statement
+pub struct DebugContext<'a> {
+    /// Call frames, outermost (the main script) first
+    pub frames: &'a [CallFrame],
+    /// Global variables (the main script's variables)
+    pub globals: &'a HashMap<String, Value>,
+    /// Line of the statement about to run
+    pub line: usize,
+}
+
+/// Receives control before each statement while attached to the VM
+pub trait DebugHook {
+    /// Called before a statement runs; returning Err aborts execution
+    fn statement(&mut self, context: &DebugContext) -> Result<(), VmError>;
+
+    /// Called when `throw` runs, before the stack unwinds; `uncaught` is
+    /// true when no enclosing try block will catch it
+    fn exception(
+        &mut self,
+        _context: &DebugContext,
+        _exception: &Value,
+        _uncaught: bool,
+    ) -> Result<(), VmError> {
+        Ok(())
+    }
+}
+
+impl DebugContext<'_> {
+    /// Number of active calls (1 while in the main script)
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// File of the statement about to run
+    pub fn file(&self) -> &str {
+        self.frames
+            .last()
+            .map(|frame| frame.function.file.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Line a frame is currently executing
+    pub fn frame_line(&self, index: usize) -> Option<usize> {
+        if index + 1 == self.frames.len() {
+            return Some(self.line);
+        }
+        let frame = self.frames.get(index)?;
+        // Callers have already advanced past their call instruction
+        frame.function.line_at(frame.ip.saturating_sub(1))
+    }
+
+    /// Variables visible in a frame, sorted by name
+    ///
+    /// Functions keep their variables in local slots; the main script may
+    /// also have variables in the globals table.
+    pub fn variables(&self, index: usize) -> Vec<(String, Value)> {
+        let Some(frame) = self.frames.get(index) else {
+            return Vec::new();
+        };
+        let mut variables: Vec<(String, Value)> = frame
+            .function
+            .local_names
+            .iter()
+            .zip(&frame.locals)
+            .map(|(name, value)| (name.clone(), value.clone().unref()))
+            .collect();
+        if index == 0 {
+            for (name, value) in self.globals {
+                if !variables.iter().any(|(n, _)| n == name) {
+                    variables.push((name.clone(), value.clone().unref()));
+                }
+            }
+        }
+        // Hide compiler temporaries such as __foreach_iter__
+        variables.retain(|(name, _)| !name.starts_with("__"));
+        variables.sort_by(|a, b| a.0.cmp(&b.0));
+        variables
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Attach a debugger that is called before every statement
+    pub fn attach_debugger(&mut self, hook: Box<dyn DebugHook>) {
+        self.debugger = Some(hook);
+    }
+
+    /// Coverage and debugger bookkeeping for the instruction about to run
+    pub(super) fn trace_instruction(&mut self) -> Result<(), VmError> {
+        let Some(frame) = self.frames.last() else {
+            return Ok(());
+        };
+        // The dispatch loops advance ip before executing the opcode
+        let ip = frame.ip.saturating_sub(1);
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(&frame.function, ip);
+        }
+
+        let Some(&(_, line)) = frame.function.statements_at(ip).last() else {
+            return Ok(());
+        };
+        if let Some(mut hook) = self.debugger.take() {
+            let context = DebugContext {
+                frames: &self.frames,
+                globals: &self.globals,
+                line,
+            };
+            let result = hook.statement(&context);
+            self.debugger = Some(hook);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Report a thrown exception to the attached debugger, if any
+    pub(crate) fn debug_exception(
+        &mut self,
+        exception: &Value,
+        uncaught: bool,
+    ) -> Result<(), VmError> {
+        let Some(mut hook) = self.debugger.take() else {
+            return Ok(());
+        };
+        let line = self
+            .frames
+            .last()
+            .and_then(|frame| frame.function.line_at(frame.ip.saturating_sub(1)))
+            .unwrap_or(0);
+        let context = DebugContext {
+            frames: &self.frames,
+            globals: &self.globals,
+            line,
+        };
+        let result = hook.exception(&context, exception, uncaught);
+        self.debugger = Some(hook);
+        result
+    }
+}
@@ -0,0 +1,128 @@
+//! Deprecation notices
+//!
+//! Modern PHP keeps running code that relies on deprecated behavior but
+//! prints an `E_DEPRECATED` notice ("Deprecated: ... in <file> on line
+//! <line>") about it, and test suites assert on those notices. The VM
+//! raises them through the warning channel (see `notices`), so
+//! `error_reporting` and `display_errors` apply to them too.
+//!
+//! Each deprecation can also be switched off on its own with its
+//! `vhp.deprecation.*` directive, which is on by default.
+//!
+//! Some deprecations are found while compiling (an implicitly nullable
+//! parameter); the compiler collects them into the `CompilationResult`
+//! and they are printed when the code is loaded, before it runs.
+
+use super::notices::{is_off, Level};
+use super::VM;
+use crate::runtime::builtins::ini;
+use crate::vm::cache::codec::Codec;
+use crate::vm::error::VmError;
+use std::io::Write;
+
+/// A deprecated behavior the engine reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Codec)]
+pub enum Deprecation {
+    /// Assigning a property a class doesn't declare (PHP 8.2)
+    DynamicProperty,
+    /// A typed parameter defaulting to `null` without a nullable type
+    /// (PHP 8.4)
+    ImplicitlyNullableParameter,
+}
+
+impl Deprecation {
+    /// The directive that toggles the notice
+    pub fn directive(self) -> &'static str {
+        match self {
+            Deprecation::DynamicProperty => "vhp.deprecation.dynamic_properties",
+            Deprecation::ImplicitlyNullableParameter => "vhp.deprecation.implicit_nullable",
+        }
+    }
+
+    /// Whether the notice is reported under the current directives
+    pub fn is_enabled(self) -> bool {
+        ini::get_ini_value(self.directive()).is_none_or(|value| !is_off(&value))
+    }
+}
+
+/// A deprecation found while compiling
+#[derive(Debug, Clone, PartialEq, Eq, Codec)]
+pub struct CompileDeprecation {
+    pub kind: Deprecation,
+    pub message: String,
+    pub file: String,
+    pub line: usize,
+}
+
+impl<W: Write> VM<W> {
+    /// Report a use of the deprecated behavior `kind` at the current
+    /// statement
+    pub(crate) fn deprecated(&mut self, kind: Deprecation, message: &str) -> Result<(), VmError> {
+        if !kind.is_enabled() {
+            return Ok(());
+        }
+        self.raise(Level::Deprecated, message)
+    }
+
+    /// Report the deprecations found while compiling code being loaded
+    pub(crate) fn report_compile_deprecations(
+        &mut self,
+        deprecations: &[CompileDeprecation],
+    ) -> Result<(), VmError> {
+        for deprecation in deprecations {
+            if deprecation.kind.is_enabled() {
+                self.raise_at(
+                    Level::Deprecated,
+                    &deprecation.message,
+                    &deprecation.file,
+                    deprecation.line,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Report creating the undeclared property `prop_name` on an instance
+    /// of `class_name`, unless the class allows dynamic properties
+    pub(crate) fn deprecated_dynamic_property(
+        &mut self,
+        class_name: &str,
+        prop_name: &str,
+    ) -> Result<(), VmError> {
+        if self.allows_dynamic_properties(class_name, prop_name) {
+            return Ok(());
+        }
+        self.deprecated(
+            Deprecation::DynamicProperty,
+            &format!(
+                "Creation of dynamic property {}::${} is deprecated",
+                class_name, prop_name
+            ),
+        )
+    }
+
+    /// Whether `prop_name` can be created on an instance of `class_name`
+    /// without a notice: the class or a parent declares it or is marked
+    /// `#[AllowDynamicProperties]`, or the class isn't user-defined (as
+    /// `stdClass`)
+    fn allows_dynamic_properties(&self, class_name: &str, prop_name: &str) -> bool {
+        let mut current = Some(class_name.to_string());
+        let mut user_defined = false;
+        while let Some(name) = current {
+            let Some(class) = self.classes.get(&name) else {
+                break;
+            };
+            user_defined = true;
+            if class.properties.iter().any(|p| p.name == prop_name)
+                || class
+                    .attributes
+                    .iter()
+                    .any(|a| a.name.trim_start_matches('\\') == "AllowDynamicProperties")
+            {
+                return true;
+            }
+            current = class.parent.clone();
+        }
+        !user_defined
+    }
+}
@@ -0,0 +1,479 @@
+//! Opcode dispatch
+//!
+//! Each opcode is handed to its handler in `ops` (or handled here when
+//! it is a line or two), after the deadline, the counters and any
+//! tracing, coverage or debugger had their turn.
+
+use super::execution::ControlFlow;
+use super::opcode::Opcode;
+use super::typed_properties::PropertyFetch;
+use super::{ops, VM};
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use std::io::Write;
+
+impl<W: Write> VM<W> {
+    /// Execute a single opcode
+    pub(super) fn execute_opcode(&mut self, opcode: Opcode) -> Result<ControlFlow, VmError> {
+        self.check_deadline()?;
+        self.record_opcode(&opcode);
+        if self.coverage.is_some() || self.debugger.is_some() {
+            self.trace_instruction()?;
+        }
+        if self.opcode_trace.is_some() {
+            self.trace_opcode(&opcode);
+        }
+        match opcode {
+            // ==================== Literals & Constants ====================
+            Opcode::PushNull => ops::execute_push_null(self),
+            Opcode::PushTrue => ops::execute_push_true(self),
+            Opcode::PushFalse => ops::execute_push_false(self),
+            Opcode::PushInt(n) => ops::execute_push_int(self, n),
+            Opcode::PushFloat(f) => ops::execute_push_float(self, f),
+            Opcode::PushString(idx) => {
+                let s = self.current_frame().get_string(idx).to_string();
+                ops::execute_push_string(self, s);
+            }
+            Opcode::LoadConst(idx) => {
+                let constant = self.current_frame().get_constant(idx).clone();
+                ops::execute_load_const(self, constant)?;
+            }
+            Opcode::FetchConstant(idx) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                let value = self.fetch_constant(&name)?;
+                self.stack.push(value);
+            }
+
+            // ==================== Variables ====================
+            Opcode::LoadVar(idx) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_load_var(self, name);
+            }
+            Opcode::StoreVar(idx) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_store_var(self, name)?;
+            }
+            Opcode::LoadVarChecked(idx) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_load_var_checked(self, name)?;
+            }
+            Opcode::LoadFast(slot) => ops::execute_load_fast(self, slot),
+            Opcode::LoadFastChecked(slot) => ops::execute_load_fast_checked(self, slot)?,
+            Opcode::StoreFast(slot) => ops::execute_store_fast(self, slot)?,
+            Opcode::LoadRef(slot) => ops::execute_load_ref(self, slot, false),
+            Opcode::LoadRefChecked(slot) => ops::execute_load_ref(self, slot, true),
+            Opcode::AssignRef(target, source) => ops::execute_assign_ref(self, target, source),
+            Opcode::ElementRef => ops::execute_element_ref(self)?,
+            Opcode::PropertyRef(idx) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_property_ref(self, name)?;
+            }
+            Opcode::BindRef(slot) => ops::execute_bind_ref(self, slot)?,
+            Opcode::IsRefArg(idx, pos) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_is_ref_arg(self, &name, pos);
+            }
+            Opcode::IsMethodRefArg(idx, pos) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_is_method_ref_arg(self, &name, pos)?;
+            }
+            Opcode::BindGlobal(idx, slot) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_bind_global(self, name, slot);
+            }
+            Opcode::LoadGlobal(idx) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_load_global(self, name);
+            }
+            Opcode::StoreGlobal(idx) => {
+                let name = self.current_frame().get_string(idx).to_string();
+                ops::execute_store_global(self, name)?;
+            }
+            Opcode::LoadGlobals => {
+                let globals = self.load_globals();
+                self.stack.push(globals);
+            }
+            Opcode::FetchGlobal | Opcode::FetchGlobalQuiet => {
+                let name = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+                let quiet = matches!(opcode, Opcode::FetchGlobalQuiet);
+                let value = self.fetch_global(&name, quiet)?;
+                self.stack.push(value);
+            }
+            Opcode::AssignGlobal => {
+                let value = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+                let name = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+                self.assign_global(&name, value.clone());
+                self.stack.push(value);
+            }
+            Opcode::UnsetGlobal => {
+                let name = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+                self.unset_global(&name);
+            }
+
+            // ==================== Arithmetic ====================
+            Opcode::Add => ops::execute_add(self)?,
+            Opcode::Sub => ops::execute_sub(self)?,
+            Opcode::Mul => ops::execute_mul(self)?,
+            Opcode::Div => ops::execute_div(self)?,
+            Opcode::Mod => ops::execute_mod(self)?,
+            Opcode::Pow => ops::execute_pow(self)?,
+            Opcode::Neg => ops::execute_neg(self)?,
+            Opcode::Increment => ops::execute_increment(self)?,
+            Opcode::Decrement => ops::execute_decrement(self)?,
+
+            // ==================== String Operations ====================
+            Opcode::Concat => ops::execute_concat(self)?,
+
+            // ==================== Comparison ====================
+            Opcode::Eq => ops::execute_eq(self)?,
+            Opcode::Ne => ops::execute_ne(self)?,
+            Opcode::Identical => ops::execute_identical(self)?,
+            Opcode::NotIdentical => ops::execute_not_identical(self)?,
+            Opcode::Lt => ops::execute_lt(self)?,
+            Opcode::Le => ops::execute_le(self)?,
+            Opcode::Gt => ops::execute_gt(self)?,
+            Opcode::Ge => ops::execute_ge(self)?,
+            Opcode::Spaceship => ops::execute_spaceship(self)?,
+
+            // ==================== Logical ====================
+            Opcode::Not => ops::execute_not(self)?,
+            Opcode::And => ops::execute_and(self)?,
+            Opcode::Or => ops::execute_or(self)?,
+            Opcode::Xor => ops::execute_xor(self)?,
+
+            // ==================== Bitwise ====================
+            Opcode::BitwiseAnd => ops::execute_bitwise_and(self)?,
+            Opcode::BitwiseOr => ops::execute_bitwise_or(self)?,
+            Opcode::BitwiseXor => ops::execute_bitwise_xor(self)?,
+            Opcode::BitwiseNot => ops::execute_bitwise_not(self)?,
+            Opcode::ShiftLeft => ops::execute_shift_left(self)?,
+            Opcode::ShiftRight => ops::execute_shift_right(self)?,
+
+            // ==================== Control Flow ====================
+            Opcode::Jump(offset) => ops::execute_jump(self, offset),
+            Opcode::JumpIfFalse(offset) => ops::execute_jump_if_false(self, offset)?,
+            Opcode::JumpIfTrue(offset) => ops::execute_jump_if_true(self, offset)?,
+            Opcode::JumpIfNull(offset) => ops::execute_jump_if_null(self, offset)?,
+            Opcode::JumpIfNotNull(offset) => ops::execute_jump_if_not_null(self, offset)?,
+            Opcode::Return => return ops::execute_return(self),
+            Opcode::Yield => return ops::execute_yield(self),
+            Opcode::YieldFrom => return ops::execute_yield_from(self),
+            Opcode::ReturnNull => return ops::execute_return_null(self),
+
+            // ==================== Loop Control ====================
+            Opcode::Break => return Ok(ControlFlow::Break),
+            Opcode::Continue => return Ok(ControlFlow::Continue),
+            Opcode::LoopStart(continue_target, break_target) => {
+                ops::execute_loop_start(self, continue_target, break_target);
+            }
+            Opcode::LoopEnd => ops::execute_loop_end(self),
+
+            // ==================== Arrays ====================
+            Opcode::NewArray(count) => ops::execute_new_array(self, count)?,
+            Opcode::ArrayGet => ops::execute_array_get(self)?,
+            Opcode::ArrayGetChecked => ops::execute_array_get_checked(self)?,
+            Opcode::ListGet => ops::execute_list_get(self)?,
+            Opcode::ArraySet => ops::execute_array_set(self)?,
+            Opcode::ArrayAppend => ops::execute_array_append(self)?,
+            Opcode::AssignDimFast(slot, depth, append) => {
+                ops::execute_assign_dim_fast(self, slot, depth, append)?
+            }
+            Opcode::AssignDim(depth, append) => ops::execute_assign_dim(self, depth, append)?,
+            Opcode::ArrayMerge => ops::execute_array_merge(self)?,
+            Opcode::ArrayCount => ops::execute_array_count(self)?,
+            Opcode::ArrayGetKeyAt => ops::execute_array_get_key_at(self)?,
+            Opcode::ArrayGetValueAt => ops::execute_array_get_value_at(self)?,
+            Opcode::ToArray => ops::execute_to_array(self)?,
+
+            // ==================== Stack Manipulation ====================
+            Opcode::Pop => ops::execute_pop(self),
+            Opcode::Dup => ops::execute_dup(self)?,
+            Opcode::Swap => ops::execute_swap(self)?,
+
+            // ==================== Type Operations ====================
+            Opcode::Cast(cast_type) => ops::execute_cast(self, cast_type)?,
+
+            // ==================== Null Coalescing ====================
+            Opcode::NullCoalesce => ops::execute_null_coalesce(self)?,
+
+            // ==================== Output ====================
+            Opcode::Echo => ops::execute_echo(self)?,
+            Opcode::Print => ops::execute_print(self)?,
+
+            // ==================== Function Calls ====================
+            Opcode::Call(name_idx, arg_count) => {
+                let func_name = self.current_frame().get_string(name_idx).to_string();
+                ops::execute_call(self, func_name, arg_count)?;
+            }
+
+            Opcode::CallSpread(name_idx) => {
+                crate::vm::ops::execute_call_spread(self, name_idx)?;
+            }
+
+            Opcode::CallNamed(name_idx) => {
+                crate::vm::ops::execute_call_named_args(self, name_idx)?;
+            }
+
+            Opcode::DeclareFunction(key_idx) => {
+                let key = self.current_frame().get_string(key_idx).to_string();
+                ops::execute_declare_function(self, &key)?;
+            }
+
+            Opcode::CallBuiltin(name_idx, arg_count) => {
+                let func_name = self.current_frame().get_string(name_idx).to_string();
+                ops::execute_call_builtin(self, func_name, arg_count)?;
+            }
+
+            Opcode::CallBuiltinSpread(name_idx) => {
+                ops::execute_call_builtin_spread(self, name_idx)?;
+            }
+
+            Opcode::CallBuiltinNamed(name_idx) => {
+                ops::execute_call_builtin_named(self, name_idx)?;
+            }
+
+            Opcode::CallCallable(arg_count) => {
+                ops::execute_call_callable(self, arg_count)?;
+            }
+
+            // ==================== OOP Opcodes ====================
+            Opcode::NewObject(class_idx) => {
+                let class_name = self.current_frame().get_string(class_idx).to_string();
+                let class_name =
+                    Self::normalize_class_name(&self.resolve_class_keyword(&class_name)?);
+                ops::execute_new_object(self, class_name)?
+            }
+
+            Opcode::NewObjectDynamic => ops::execute_new_object_dynamic(self)?,
+
+            Opcode::FetchClassName(keyword_idx) => {
+                let keyword = self.current_frame().get_string(keyword_idx).to_string();
+                let class_name = self.resolve_class_keyword(&keyword)?;
+                self.stack.push(Value::String(class_name));
+            }
+
+            Opcode::NewFiber => {
+                ops::execute_new_fiber(self)?;
+            }
+
+            Opcode::Reflect(method_idx) => {
+                let method = self.current_frame().get_string(method_idx).to_string();
+                self.execute_reflect(&method)?
+            }
+
+            Opcode::Xml(method_idx) => {
+                let method = self.current_frame().get_string(method_idx).to_string();
+                self.execute_xml(&method)?
+            }
+
+            Opcode::EventLoop(method_idx) => {
+                let method = self.current_frame().get_string(method_idx).to_string();
+                self.execute_event_loop(&method)?
+            }
+            Opcode::Fiber(method_idx) => {
+                let method = self.current_frame().get_string(method_idx).to_string();
+                self.execute_fiber(&method)?
+            }
+            Opcode::NativeMethod(method_idx) => {
+                let method = self.current_frame().get_string(method_idx).to_string();
+                self.execute_native_method(&method)?
+            }
+            Opcode::RunCallback(end) => self.run_next_callback(end)?,
+            Opcode::SettleTask => self.settle_task()?,
+            Opcode::RejectTask => self.reject_task()?,
+
+            Opcode::LoadProperty(prop_idx) => {
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_load_property(self, prop_name, PropertyFetch::Read)?
+            }
+
+            Opcode::LoadPropertyQuiet(prop_idx) => {
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_load_property(self, prop_name, PropertyFetch::Quiet)?
+            }
+
+            Opcode::LoadPropertyForWrite(prop_idx) => {
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_load_property(self, prop_name, PropertyFetch::Write)?
+            }
+
+            Opcode::StoreProperty(prop_idx) => {
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_store_property(self, prop_name)?
+            }
+
+            Opcode::UnsetProperty(prop_idx) => {
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_unset_property(self, prop_name)?
+            }
+
+            Opcode::IssetProperty(prop_idx) => {
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_isset_property(self, prop_name)?;
+            }
+
+            Opcode::UnsetVar(var_idx) => {
+                let var_name = self.current_frame().get_string(var_idx).to_string();
+                ops::execute_unset_var(self, var_name);
+            }
+
+            Opcode::UnsetFast(slot) => self.current_frame_mut().unset_local(slot),
+
+            Opcode::UnsetArrayElement => {
+                ops::execute_unset_array_element(self)?;
+            }
+
+            Opcode::UnsetArrayElementOnLocal(slot) => {
+                ops::execute_unset_array_element_on_local(self, slot)?;
+            }
+
+            Opcode::StoreThisProperty(prop_idx) => {
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_store_this_property(self, prop_name)?
+            }
+
+            Opcode::CallMethod(method_idx, arg_count) => {
+                let method_name = self.current_frame().get_string(method_idx).to_string();
+                return ops::execute_call_method(self, method_name, arg_count);
+            }
+
+            Opcode::CallStaticMethod(class_idx, method_idx, arg_count) => {
+                let class_name =
+                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
+                let method_name = self.current_frame().get_string(method_idx).to_string();
+                ops::execute_call_static_method(self, class_name, method_name, arg_count)?
+            }
+
+            Opcode::CallStaticMethodNamed(class_idx, method_idx) => {
+                let class_name =
+                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
+                let method_name = self.current_frame().get_string(method_idx).to_string();
+                ops::execute_call_static_method_named(self, class_name, method_name)?
+            }
+
+            Opcode::LoadStaticProp(class_idx, prop_idx) => {
+                let class_name =
+                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_load_static_prop(self, class_name, prop_name)?
+            }
+
+            Opcode::StoreStaticProp(class_idx, prop_idx) => {
+                let class_name =
+                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
+                let prop_name = self.current_frame().get_string(prop_idx).to_string();
+                ops::execute_store_static_prop(self, class_name, prop_name)?
+            }
+
+            Opcode::LoadThis => ops::execute_load_this(self)?,
+
+            Opcode::InstanceOf(class_idx) => {
+                let class_name =
+                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
+                ops::execute_instance_of(self, class_name)?;
+            }
+
+            Opcode::Clone => ops::execute_clone(self)?,
+            Opcode::CloneWith => ops::execute_clone_with(self)?,
+
+            Opcode::LoadEnumCase(enum_idx, case_idx) => {
+                let enum_name =
+                    Self::normalize_class_name(self.current_frame().get_string(enum_idx));
+                let case_name = self.current_frame().get_string(case_idx).to_string();
+                ops::execute_load_enum_case(self, enum_name, case_name)?
+            }
+
+            Opcode::CallConstructor(arg_count) => {
+                ops::execute_call_constructor(self, arg_count)?;
+            }
+
+            Opcode::CallConstructorNamed => ops::execute_call_constructor_named(self)?,
+
+            // ==================== Exception Handling ====================
+            Opcode::Throw => ops::execute_throw(self)?,
+            Opcode::UnhandledMatch => ops::execute_unhandled_match(self)?,
+
+            Opcode::TryStart(catch_offset, finally_offset) => {
+                ops::execute_try_start(self, catch_offset, finally_offset);
+            }
+
+            Opcode::TryEnd => {
+                ops::execute_try_end(self);
+            }
+
+            Opcode::Catch(class_idx) => {
+                let class_name =
+                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
+                ops::execute_catch(self, &class_name)?;
+            }
+
+            Opcode::FinallyStart => {
+                ops::execute_finally_start(self);
+            }
+
+            Opcode::FinallyEnd => return ops::execute_finally_end(self),
+
+            Opcode::FormatTrace => ops::execute_format_trace(self)?,
+
+            Opcode::FormatThrowable => ops::execute_format_throwable(self)?,
+
+            Opcode::BeginSilence => self.silence_depth += 1,
+
+            Opcode::EndSilence => self.silence_depth = self.silence_depth.saturating_sub(1),
+
+            // ==================== Closures ====================
+            Opcode::CreateClosure(func_idx, capture_count) => {
+                let func_name = self.current_frame().get_string(func_idx).to_string();
+                ops::execute_create_closure(self, func_name, capture_count)?;
+            }
+
+            Opcode::CaptureVar(var_idx) => {
+                let var_name = self.current_frame().get_string(var_idx).to_string();
+                ops::execute_capture_var(self, var_name);
+            }
+
+            Opcode::CreateMethodClosure => {
+                ops::execute_create_method_closure(self)?;
+            }
+
+            Opcode::CreateStaticMethodClosure => {
+                ops::execute_create_static_method_closure(self)?;
+            }
+
+            // ==================== Array Operations ====================
+            Opcode::ArrayUnpack => {
+                ops::execute_array_unpack(self)?;
+            }
+
+            // ==================== Generator Methods ====================
+            // These resume a generator's body, which may suspend the fiber
+            Opcode::GeneratorCurrent => return ops::execute_generator_current(self),
+            Opcode::GeneratorKey => return ops::execute_generator_key(self),
+            Opcode::GeneratorNext => return ops::execute_generator_next(self),
+            Opcode::GeneratorRewind => return ops::execute_generator_rewind(self),
+            Opcode::GeneratorValid => return ops::execute_generator_valid(self),
+            Opcode::ForeachValid(source, position) => {
+                return ops::execute_foreach_valid(self, source, position)
+            }
+            Opcode::ForeachKey(source, position) => {
+                return ops::execute_foreach_key(self, source, position)
+            }
+            Opcode::ForeachValue(source, position) => {
+                return ops::execute_foreach_value(self, source, position)
+            }
+            Opcode::ForeachNext(source) => return ops::execute_foreach_next(self, source),
+            Opcode::SuspendFiber => return ops::execute_fiber_suspend(self),
+            Opcode::GetCurrentFiber => {
+                ops::execute_get_current_fiber(self)?;
+            }
+            // ==================== Not Yet Implemented ====================
+            _ => {
+                return Err(VmError::fatal(format!(
+                    "Opcode not yet implemented: {:?}",
+                    opcode
+                )));
+            }
+        }
+        Ok(ControlFlow::Next)
+    }
+}
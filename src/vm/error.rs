@@ -0,0 +1,353 @@
+//! Runtime errors
+//!
+//! Inside the VM opcodes fail with a `VmError`, whose kind the code that
+//! raises it picks: a fatal error's message, an undefined function, an
+//! uncaught exception, `exit()`... `VM::execute` turns whatever escapes
+//! into the `RuntimeError` of that kind, carrying the file and line of
+//! the statement that raised it, and the calls that led there:
+//!
+//! ```
+//! use vhp::vm::{RuntimeError, VM};
+//!
+//! let compilation = vhp::compile("<?php\necho 1;\nmissing();", "a.php").unwrap();
+//! let mut vm = VM::new(std::io::sink());
+//! let main = vm.load(compilation);
+//! let Err(RuntimeError::UndefinedFunction {
+//!     location: Some(location),
+//!     ..
+//! }) = vm.execute(main)
+//! else {
+//!     panic!("expected an undefined function error");
+//! };
+//! assert_eq!(location.line, 3);
+//! assert_eq!(location.stack[0].to_string(), "<main> at a.php:3");
+//! ```
+
+use crate::diagnostic::{self, Diagnostic, Span};
+use std::fmt;
+
+/// Why an opcode, or what the VM runs for it, failed
+///
+/// `exit()` and an exception already thrown stop the opcode this way
+/// rather than with a `ControlFlow`, as they come out of built-in
+/// functions and nested calls, which give values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    /// A fatal error with the message, which no `catch` can handle
+    Fatal(String),
+    /// A call to a function that doesn't exist, which `ops::throw_raised`
+    /// throws as an `Error`
+    UndefinedFunction(String),
+    /// A call to a function the sandbox disables
+    DisabledFunction(String),
+    /// A time limit ran out (see `vm::deadline`)
+    TimeLimit(String),
+    /// An error to throw as an exception of the class, which scripts
+    /// can catch
+    Exception { class_name: String, message: String },
+    /// An exception no `catch` clause took: its message and the trace
+    Uncaught(String),
+    /// `exit()` or `die()` ended the script with this status
+    Exit(i32),
+    /// An exception was thrown and the frames unwound to the catch block
+    /// that takes it, where execution resumes
+    Thrown,
+}
+
+impl VmError {
+    /// The error of a call to the function `name`, which doesn't exist
+    pub(crate) fn undefined_function(name: &str) -> Self {
+        Self::UndefinedFunction(format!("Call to undefined function {}()", name))
+    }
+
+    /// The error of a use of the class `name`, which doesn't exist and
+    /// no autoloader declared
+    pub(crate) fn class_not_found(name: &str) -> Self {
+        Self::error(format!("Class \"{}\" not found", name))
+    }
+
+    /// The error of an opcode popping a value the stack lacks, which the
+    /// compiler never emits
+    pub(crate) fn stack_underflow() -> Self {
+        Self::Fatal("Stack underflow".to_string())
+    }
+
+    /// A fatal error, which stops the script
+    pub(crate) fn fatal(message: impl Into<String>) -> Self {
+        Self::Fatal(message.into())
+    }
+
+    /// An error to throw as an exception of `class_name`
+    pub(crate) fn exception(class_name: &str, message: impl Into<String>) -> Self {
+        Self::Exception {
+            class_name: class_name.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// An error to throw as PHP's base `Error` class
+    pub(crate) fn error(message: impl Into<String>) -> Self {
+        Self::exception("Error", message)
+    }
+
+    /// An error to throw as a `TypeError`
+    pub(crate) fn type_error(message: impl Into<String>) -> Self {
+        Self::exception("TypeError", message)
+    }
+
+    /// An error to throw as a `ValueError`
+    pub(crate) fn value_error(message: impl Into<String>) -> Self {
+        Self::exception("ValueError", message)
+    }
+
+    /// An error to throw as an `ArgumentCountError`
+    pub(crate) fn argument_count_error(message: impl Into<String>) -> Self {
+        Self::exception("ArgumentCountError", message)
+    }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fatal(message)
+            | Self::UndefinedFunction(message)
+            | Self::DisabledFunction(message)
+            | Self::TimeLimit(message)
+            | Self::Uncaught(message) => f.write_str(message),
+            Self::Exception {
+                class_name,
+                message,
+            } => write!(f, "Uncaught {}: {}", class_name, message),
+            Self::Exit(code) => write!(f, "exit({})", code),
+            Self::Thrown => f.write_str("exception thrown"),
+        }
+    }
+}
+
+/// Why a native function, added by an extension or the host, failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NativeError {
+    /// Throw an exception of the class, which scripts can catch
+    Exception { class_name: String, message: String },
+    /// Stop the script with a fatal error
+    Fatal(String),
+}
+
+impl NativeError {
+    /// Throw an exception of `class_name` with `message`
+    pub fn exception(class_name: &str, message: impl Into<String>) -> Self {
+        Self::Exception {
+            class_name: class_name.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Stop the script with a fatal error
+    pub fn fatal(message: impl Into<String>) -> Self {
+        Self::Fatal(message.into())
+    }
+
+    /// Throw an `ArgumentCountError`, for a call with too few or too many
+    /// arguments
+    pub fn argument_count_error(message: impl Into<String>) -> Self {
+        Self::exception("ArgumentCountError", message)
+    }
+
+    /// Throw a `TypeError`, for an argument of the wrong type
+    pub fn type_error(message: impl Into<String>) -> Self {
+        Self::exception("TypeError", message)
+    }
+
+    /// Throw a `ValueError`, for an argument of the right type but an
+    /// invalid value
+    pub fn value_error(message: impl Into<String>) -> Self {
+        Self::exception("ValueError", message)
+    }
+
+    /// The error's message, without the exception class
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Exception { message, .. } | Self::Fatal(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for NativeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for NativeError {}
+
+/// Failing to write the output is fatal
+impl From<std::io::Error> for NativeError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Fatal(error.to_string())
+    }
+}
+
+impl From<NativeError> for VmError {
+    fn from(error: NativeError) -> Self {
+        match error {
+            NativeError::Exception {
+                class_name,
+                message,
+            } => Self::Exception {
+                class_name,
+                message,
+            },
+            NativeError::Fatal(message) => Self::Fatal(message),
+        }
+    }
+}
+
+/// Where a runtime error was raised
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    /// The calls in progress, innermost first, ending with the script's
+    /// main code
+    pub stack: Vec<StackFrame>,
+}
+
+/// A call in progress when a runtime error was raised
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    /// Name of the function ("Class::method" for methods)
+    pub function: String,
+    pub file: String,
+    /// The line the call is at
+    pub line: usize,
+    /// Previews of the arguments, as the parameters held them ("5",
+    /// "'a long stri...'", "Array")
+    pub args: Vec<String>,
+}
+
+impl fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.function.starts_with('<') {
+            true => write!(f, "{}", self.function)?,
+            false => write!(f, "{}({})", self.function, self.args.join(", "))?,
+        }
+        write!(f, " at {}:{}", self.file, self.line)
+    }
+}
+
+/// Separates an uncaught exception's message from the trace appended to it
+const TRACE_SEPARATOR: &str = "\n\nStack trace:\n";
+
+/// Why `VM::execute` stopped before the end of the script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// `exit()` or `die()` ended the script with this status
+    Exit(i32),
+    /// An exception no `catch` clause handled
+    UncaughtException {
+        message: String,
+        location: Option<Location>,
+    },
+    /// A time limit ran out (see `vm::deadline`)
+    TimeLimit {
+        message: String,
+        location: Option<Location>,
+    },
+    /// A call to a function the sandbox disables
+    DisabledFunction {
+        message: String,
+        location: Option<Location>,
+    },
+    /// A call to a function that doesn't exist
+    UndefinedFunction {
+        message: String,
+        location: Option<Location>,
+    },
+    /// Any other fatal error
+    Fatal {
+        message: String,
+        location: Option<Location>,
+    },
+}
+
+impl RuntimeError {
+    /// The error of the same kind as one that escaped the VM
+    pub(crate) fn new(error: VmError, location: Option<Location>) -> Self {
+        let message = error.to_string();
+        match error {
+            VmError::Exit(code) => Self::Exit(code),
+            VmError::Exception { .. } | VmError::Uncaught(_) => {
+                Self::UncaughtException { message, location }
+            }
+            VmError::TimeLimit(_) => Self::TimeLimit { message, location },
+            VmError::DisabledFunction(_) => Self::DisabledFunction { message, location },
+            VmError::UndefinedFunction(_) => Self::UndefinedFunction { message, location },
+            VmError::Fatal(_) | VmError::Thrown => Self::Fatal { message, location },
+        }
+    }
+
+    /// The error message ("exit(N)" for `Exit`)
+    pub fn message(&self) -> String {
+        match self {
+            Self::Exit(code) => format!("exit({})", code),
+            Self::UncaughtException { message, .. }
+            | Self::TimeLimit { message, .. }
+            | Self::DisabledFunction { message, .. }
+            | Self::UndefinedFunction { message, .. }
+            | Self::Fatal { message, .. } => message.clone(),
+        }
+    }
+
+    /// Where the error was raised, if known
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Self::Exit(_) => None,
+            Self::UncaughtException { location, .. }
+            | Self::TimeLimit { location, .. }
+            | Self::DisabledFunction { location, .. }
+            | Self::UndefinedFunction { location, .. }
+            | Self::Fatal { location, .. } => location.as_ref(),
+        }
+    }
+}
+
+/// The diagnostic shows an uncaught exception's class and message
+/// without the trace in its message, as the stack is shown apart
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        let mut message = error.message();
+        if let RuntimeError::UncaughtException { .. } = error {
+            if let Some(end) = message.find(TRACE_SEPARATOR) {
+                message.truncate(end);
+            }
+            if !message.starts_with("Uncaught ") {
+                message.insert_str(0, "Uncaught ");
+            }
+        }
+        let mut diagnostic = Diagnostic::new(message.as_str()).with_code(diagnostic::RUNTIME);
+        if let Some(location) = error.location() {
+            diagnostic = diagnostic
+                .with_span(Span::line(location.line))
+                .with_stack(location.stack.iter().map(StackFrame::to_string).collect());
+        }
+        match error {
+            RuntimeError::UndefinedFunction { .. } => {
+                diagnostic.with_help(diagnostic::UNDEFINED_FUNCTION_HELP)
+            }
+            RuntimeError::TimeLimit { .. } => diagnostic.with_help(diagnostic::TIME_LIMIT_HELP),
+            RuntimeError::DisabledFunction { .. } => {
+                diagnostic.with_help(diagnostic::DISABLED_FUNCTION_HELP)
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+/// The message alone, as PHP prints it after "Fatal error: "
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
@@ -0,0 +1,145 @@
+//! Where a runtime error was raised
+//!
+//! Runtime errors (`VmError`) carry no position. When one escapes
+//! the dispatch loop, the VM remembers the file and line of the statement
+//! that raised it, and `VM::execute` attaches it to the `RuntimeError`
+//! (see `vm::error`), along with the calls in progress at that moment and
+//! short previews of their arguments.
+//! Errors unwind through nested dispatch loops, so only the innermost
+//! frame's position is kept for a given error.
+
+use super::error::{Location, RuntimeError, StackFrame, VmError};
+use super::frame::CallFrame;
+use super::VM;
+use crate::runtime::Value;
+use std::io::Write;
+
+/// Characters of a string argument shown before it is cut, as in PHP's
+/// stack traces
+const ARG_PREVIEW_CHARS: usize = 15;
+
+/// An error and the statement it was raised by
+#[derive(Debug, Clone)]
+pub(crate) struct ErrorLocation {
+    error: VmError,
+    file: String,
+    line: usize,
+    stack: Vec<StackFrame>,
+}
+
+/// Line of the statement a frame is executing
+fn frame_line(frame: &CallFrame) -> Option<usize> {
+    // The dispatch loop advances ip before executing the opcode
+    frame.function.line_at(frame.ip.saturating_sub(1))
+}
+
+/// Previews of the values a frame's parameters hold, the variadic one
+/// spread
+fn frame_args(frame: &CallFrame) -> Vec<String> {
+    let function = &frame.function;
+    let mut args = Vec::new();
+    for param in &function.parameters {
+        let Some(value) = function
+            .local_names
+            .iter()
+            .position(|name| *name == param.name)
+            .and_then(|slot| frame.locals.get(slot))
+        else {
+            continue;
+        };
+        match value {
+            Value::Array(items) if param.is_variadic => {
+                args.extend(items.iter().map(|(_, item)| preview(item)))
+            }
+            value => args.push(preview(value)),
+        }
+    }
+    args
+}
+
+/// A value as a stack trace shows it: scalars as literals, strings quoted
+/// and cut, anything else by its kind
+pub(crate) fn preview(value: &Value) -> String {
+    match value {
+        Value::Reference(r) => preview(&r.get()),
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Integer(_) | Value::Float(_) => value.to_string_val(),
+        Value::String(s) if s.chars().count() > ARG_PREVIEW_CHARS => {
+            let cut: String = s.chars().take(ARG_PREVIEW_CHARS).collect();
+            format!("'{}...'", cut)
+        }
+        Value::String(s) => format!("'{}'", s),
+        Value::Array(_) => "Array".to_string(),
+        Value::Object(object) => format!("Object({})", object.class_name()),
+        Value::Closure(_) => "Object(Closure)".to_string(),
+        Value::Fiber(_) => "Object(Fiber)".to_string(),
+        Value::Generator(_) => "Object(Generator)".to_string(),
+        Value::EnumCase {
+            enum_name,
+            case_name,
+            ..
+        } => format!("{}::{}", enum_name, case_name),
+        Value::Exception(exception) => format!("Object({})", exception.class_name),
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Remember the current statement as the origin of `error`, unless
+    /// a more deeply nested frame already did for the same error
+    pub(crate) fn record_error_location(&mut self, error: &VmError) {
+        if self
+            .error_location
+            .as_ref()
+            .is_some_and(|location| &location.error == error)
+        {
+            return;
+        }
+        let stack = self
+            .frames
+            .iter()
+            .rev()
+            .filter_map(|frame| {
+                Some(StackFrame {
+                    function: frame.function.name.clone(),
+                    file: frame.function.file.clone(),
+                    line: frame_line(frame)?,
+                    args: frame_args(frame),
+                })
+            })
+            .collect();
+        self.error_location = self.current_location().map(|location| ErrorLocation {
+            error: error.clone(),
+            file: location.file,
+            line: location.line,
+            stack,
+        });
+    }
+
+    /// File and line of the statement being executed (without the stack)
+    pub(crate) fn current_location(&self) -> Option<Location> {
+        // Native method stubs have no lines, so they report their caller's
+        self.frames.iter().rev().find_map(|frame| {
+            frame_line(frame).map(|line| Location {
+                file: frame.function.file.clone(),
+                line,
+                stack: Vec::new(),
+            })
+        })
+    }
+
+    /// The `RuntimeError` for an error that escaped `execute`,
+    /// located if it was recorded
+    pub(crate) fn runtime_error(&self, error: VmError) -> RuntimeError {
+        let location = self
+            .error_location
+            .as_ref()
+            .filter(|location| location.error == error)
+            .map(|location| Location {
+                file: location.file.clone(),
+                line: location.line,
+                stack: location.stack.clone(),
+            });
+        RuntimeError::new(error, location)
+    }
+}
@@ -0,0 +1,428 @@
+//! Event loop
+//!
+//! A small scheduler in the style of Revolt, so cooperative concurrency
+//! needs no library. `EventLoop::defer()` queues a callback for the next
+//! tick, `EventLoop::delay()` and `EventLoop::repeat()` set up timers, and
+//! `EventLoop::run()` calls them until none is left, sleeping until the
+//! next timer is due (or a time limit runs out). Callbacks get their id
+//! as argument, which `EventLoop::cancel()` takes. Whatever is left once the script's main
+//! code is over runs then, before the shutdown functions.
+//!
+//! `async()` queues a call as a task and gives the `Future` of what it
+//! returns or throws; `$future->await()` runs the loop until the future
+//! has settled. Tasks don't run in fibers of their own, so a task that
+//! awaits runs the loop in place rather than giving way to the others.
+//!
+//! `run()` and `await()` are bytecode (`driver`), so callbacks run as
+//! ordinary frames. Each runs in a try block: an exception a task throws
+//! settles its future, one a plain callback throws stops the loop and is
+//! thrown on from `run()`.
+
+use super::class::{CompiledClass, CompiledProperty};
+use super::opcode::{CompiledFunction, Opcode};
+use super::{ops, VM};
+use crate::ast::Visibility;
+use crate::runtime::builtins::native;
+use crate::runtime::{clock, Value};
+use crate::vm::error::VmError;
+use crate::vm::name_table::NameTable;
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A callback the loop will call
+#[derive(Clone)]
+struct Callback {
+    id: String,
+    callable: Value,
+    args: Vec<Value>,
+    /// The future a task settles
+    future: Option<usize>,
+}
+
+/// A callback due at a given time, and again every `interval` if set
+struct Timer {
+    callback: Callback,
+    due: Duration,
+    interval: Option<Duration>,
+}
+
+/// How a task ended
+enum Settlement {
+    Returned(Value),
+    Threw(Value),
+}
+
+/// The callbacks and timers waiting, and the futures of the tasks
+#[derive(Default)]
+pub(crate) struct EventLoop {
+    deferred: VecDeque<Callback>,
+    /// Timers in the order they were set up
+    timers: Vec<Timer>,
+    /// Futures by id, with how their task ended once it did
+    futures: HashMap<usize, Option<Settlement>>,
+    /// The futures of the callbacks running, innermost last; `None` for
+    /// a callback that isn't a task
+    running: Vec<Option<usize>>,
+    last_id: usize,
+}
+
+impl EventLoop {
+    fn next_id(&mut self) -> usize {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Whether no callback is waiting
+    fn is_idle(&self) -> bool {
+        self.deferred.is_empty() && self.timers.is_empty()
+    }
+
+    /// How long until the next callback is due: none while deferred ones
+    /// wait, or until the timer due first otherwise
+    fn wait(&self) -> Duration {
+        if !self.deferred.is_empty() {
+            return Duration::ZERO;
+        }
+        self.timers
+            .iter()
+            .map(|timer| timer.due)
+            .min()
+            .map_or(Duration::ZERO, |due| due.saturating_sub(clock::monotonic()))
+    }
+
+    /// The next callback to call, once `wait()` has passed: the deferred
+    /// ones first, then the timer due first
+    fn next_callback(&mut self) -> Option<Callback> {
+        if let Some(callback) = self.deferred.pop_front() {
+            return Some(callback);
+        }
+        let (index, _) = self
+            .timers
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, timer)| timer.due)?;
+        let timer = &mut self.timers[index];
+        match timer.interval {
+            Some(interval) => {
+                timer.due = clock::monotonic().saturating_add(interval);
+                Some(timer.callback.clone())
+            }
+            None => Some(self.timers.remove(index).callback),
+        }
+    }
+
+    /// Drop the callbacks of a script that stopped midway
+    pub(crate) fn clear_running(&mut self) {
+        self.running.clear();
+    }
+}
+
+/// The id of the future a `Future` object stands for
+fn future_id(future: &Value) -> Option<usize> {
+    match future {
+        Value::Object(object) if object.class_name() == "Future" => match object.get("__id") {
+            Some(Value::Integer(id)) => Some(id as usize),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The bytecode of `EventLoop::run()` and `Future::await()`: call the
+/// callbacks due, each in a try block, until `RunCallback` jumps out,
+/// then run `finish`
+fn driver(name: &str, finish: &[Opcode]) -> CompiledFunction {
+    let mut function = CompiledFunction::new(name.to_string());
+    function.bytecode = vec![
+        Opcode::TryStart(5, 0),
+        Opcode::RunCallback(7),
+        Opcode::SettleTask,
+        Opcode::TryEnd,
+        Opcode::Jump(0),
+        Opcode::RejectTask,
+        Opcode::Jump(0),
+        Opcode::TryEnd,
+    ];
+    function.bytecode.extend_from_slice(finish);
+    function
+}
+
+/// The methods run natively: class, method, parameters, how many are
+/// required and whether it is static
+const NATIVE_METHODS: &[(&str, &str, &[&str], u8, bool)] = &[
+    ("EventLoop", "defer", &["callback"], 1, true),
+    ("EventLoop", "delay", &["delay", "callback"], 2, true),
+    ("EventLoop", "repeat", &["interval", "callback"], 2, true),
+    ("EventLoop", "cancel", &["callbackId"], 1, true),
+    ("Future", "complete", &["value"], 0, true),
+    ("Future", "isComplete", &[], 0, false),
+];
+
+pub fn register_event_loop_classes(classes: &mut NameTable<Arc<CompiledClass>>) {
+    let mut event_loop = CompiledClass::new("EventLoop".to_string());
+    event_loop.is_final = true;
+    let run = driver("EventLoop::run", &[Opcode::ReturnNull]);
+    event_loop
+        .static_methods
+        .insert("run".to_string(), Arc::new(run));
+
+    let mut future = CompiledClass::new("Future".to_string());
+    future.is_final = true;
+    future.properties.push(CompiledProperty {
+        name: "__id".to_string(),
+        visibility: Visibility::Private,
+        write_visibility: None,
+        default: Some(Value::Null),
+        readonly: false,
+        is_static: false,
+        type_hint: None,
+        attributes: Vec::new(),
+        get_hook: None,
+        set_hook: None,
+    });
+    let mut await_method = driver("Future::await", &[Opcode::EventLoop(0), Opcode::Return]);
+    await_method.local_count = 1;
+    await_method.local_names = vec!["this".to_string()];
+    await_method.strings.push(await_method.name.clone());
+    future
+        .methods
+        .insert("await".to_string(), Arc::new(await_method));
+
+    let mut loop_classes = [event_loop, future];
+    for (class_name, method_name, params, required, is_static) in NATIVE_METHODS {
+        let mut method = CompiledFunction::new(format!("{}::{}", class_name, method_name));
+        method.param_count = params.len() as u8;
+        method.required_param_count = *required;
+        let this = (!is_static).then_some("this");
+        method.local_names = this
+            .into_iter()
+            .chain(params.iter().copied())
+            .map(str::to_string)
+            .collect();
+        method.local_count = method.local_names.len() as u16;
+        method.strings.push(method.name.clone());
+        method.bytecode.push(Opcode::EventLoop(0));
+        method.bytecode.push(Opcode::Return);
+        let class = loop_classes
+            .iter_mut()
+            .find(|class| class.name == *class_name)
+            .expect("event loop class");
+        let methods = if *is_static {
+            &mut class.static_methods
+        } else {
+            &mut class.methods
+        };
+        methods.insert(method_name.to_string(), Arc::new(method));
+    }
+    for class in loop_classes {
+        classes.insert(class.name.clone(), Arc::new(class));
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// `async()`: queue a call as a task and give its future
+    pub(crate) fn async_task(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        native::check_arity("async", args.len(), 1, None)?;
+        let callable = self.callback_argument("async", 1, &args[0])?;
+        let id = self.event_loop.next_id();
+        self.event_loop.futures.insert(id, None);
+        self.event_loop.deferred.push_back(Callback {
+            id: id.to_string(),
+            callable,
+            args: args[1..].to_vec(),
+            future: Some(id),
+        });
+        self.future_object(id)
+    }
+
+    /// Run a native method of EventLoop or Future ("Class::method") on
+    /// the current frame's arguments
+    pub(crate) fn execute_event_loop(&mut self, method: &str) -> Result<(), VmError> {
+        let args = self.current_frame().locals.clone();
+        let result = match method {
+            "EventLoop::defer" => {
+                let callable = self.callback_argument(method, 1, &args[0])?;
+                let id = self.event_loop.next_id().to_string();
+                self.event_loop.deferred.push_back(Callback {
+                    id: id.clone(),
+                    callable,
+                    args: vec![Value::String(id.clone())],
+                    future: None,
+                });
+                Value::String(id)
+            }
+            "EventLoop::delay" | "EventLoop::repeat" => {
+                let seconds = args[0].to_float();
+                if seconds < 0.0 || !seconds.is_finite() {
+                    let param = if method == "EventLoop::delay" {
+                        "delay"
+                    } else {
+                        "interval"
+                    };
+                    return Err(VmError::value_error(format!(
+                        "{}(): Argument #1 (${}) must be greater than or equal to 0",
+                        method, param
+                    )));
+                }
+                let callable = self.callback_argument(method, 2, &args[1])?;
+                let id = self.event_loop.next_id().to_string();
+                let duration = Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX);
+                self.event_loop.timers.push(Timer {
+                    callback: Callback {
+                        id: id.clone(),
+                        callable,
+                        args: vec![Value::String(id.clone())],
+                        future: None,
+                    },
+                    due: clock::monotonic().saturating_add(duration),
+                    interval: (method == "EventLoop::repeat").then_some(duration),
+                });
+                Value::String(id)
+            }
+            "EventLoop::cancel" => {
+                let id = args[0].to_string_val();
+                self.event_loop
+                    .deferred
+                    .retain(|callback| callback.id != id);
+                self.event_loop
+                    .timers
+                    .retain(|timer| timer.callback.id != id);
+                Value::Null
+            }
+            "Future::complete" => {
+                let id = self.event_loop.next_id();
+                let value = args.first().cloned().unwrap_or(Value::Null);
+                self.event_loop
+                    .futures
+                    .insert(id, Some(Settlement::Returned(value)));
+                self.future_object(id)?
+            }
+            "Future::isComplete" => {
+                let settled = future_id(&args[0])
+                    .and_then(|id| self.event_loop.futures.get(&id))
+                    .is_some_and(Option::is_some);
+                Value::Bool(settled)
+            }
+            "Future::await" => {
+                let settlement = future_id(&args[0])
+                    .and_then(|id| self.event_loop.futures.get(&id))
+                    .and_then(Option::as_ref);
+                match settlement {
+                    Some(Settlement::Returned(value)) => value.clone(),
+                    Some(Settlement::Threw(exception)) => {
+                        self.stack.push(exception.clone());
+                        return ops::execute_throw(self);
+                    }
+                    None => {
+                        return Err(
+                            VmError::fatal("Future::await(): The event loop has no callbacks left to complete the future"),
+                        )
+                    }
+                }
+            }
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method {}()",
+                    method
+                )))
+            }
+        };
+        self.stack.push(result);
+        Ok(())
+    }
+
+    /// Call the next callback due, or jump to `end` once there is none or
+    /// the future the current frame awaits has settled
+    pub(crate) fn run_next_callback(&mut self, end: u32) -> Result<(), VmError> {
+        let awaited = self.current_frame().locals.first().and_then(future_id);
+        let settled =
+            awaited.is_some_and(|id| self.event_loop.futures.get(&id).is_none_or(Option::is_some));
+        let next = if settled {
+            None
+        } else {
+            let wait = self.event_loop.wait();
+            if !wait.is_zero() {
+                self.sleep(wait)?;
+            }
+            self.event_loop.next_callback()
+        };
+        let Some(callback) = next else {
+            self.current_frame_mut().jump_to(end as usize);
+            return Ok(());
+        };
+        self.event_loop.running.push(callback.future);
+        let arg_count = callback.args.len() as u8;
+        self.stack.extend(callback.args);
+        self.stack.push(callback.callable);
+        ops::execute_call_callable(self, arg_count)
+    }
+
+    /// Settle the future of the callback that returned, if it is a task
+    pub(crate) fn settle_task(&mut self) -> Result<(), VmError> {
+        let value = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+        if let Some(Some(id)) = self.event_loop.running.pop() {
+            self.event_loop
+                .futures
+                .insert(id, Some(Settlement::Returned(value)));
+        }
+        Ok(())
+    }
+
+    /// Settle the future of the callback that threw, or throw the
+    /// exception on if it isn't a task
+    pub(crate) fn reject_task(&mut self) -> Result<(), VmError> {
+        let exception = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+        match self.event_loop.running.pop() {
+            Some(Some(id)) => {
+                self.event_loop
+                    .futures
+                    .insert(id, Some(Settlement::Threw(exception)));
+                Ok(())
+            }
+            _ => {
+                self.stack.push(exception);
+                ops::execute_throw(self)
+            }
+        }
+    }
+
+    /// Run what is left in the event loop once the main code is over
+    pub(crate) fn run_event_loop(&mut self) -> Result<(), VmError> {
+        if self.event_loop.is_idle() {
+            return Ok(());
+        }
+        let run = driver("EventLoop::run", &[Opcode::ReturnNull]);
+        self.execute_contained(Arc::new(run)).map(|_| ())
+    }
+
+    /// A callback argument, or the error for one that can't be called
+    pub(crate) fn callback_argument(
+        &mut self,
+        function: &str,
+        position: usize,
+        callback: &Value,
+    ) -> Result<Value, VmError> {
+        self.autoload_callable_class(callback);
+        if !self.is_callable_value(callback) {
+            return Err(VmError::type_error(format!(
+                "{}(): Argument #{} ($callback) must be of type callable, {} given",
+                function,
+                position,
+                callback.type_name()
+            )));
+        }
+        Ok(callback.clone())
+    }
+
+    /// A new `Future` object for the future `id`
+    fn future_object(&mut self, id: usize) -> Result<Value, VmError> {
+        ops::execute_new_object(self, "Future".to_string())?;
+        let future = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+        if let Value::Object(object) = &future {
+            object.set("__id", Value::Integer(id as i64));
+        }
+        Ok(future)
+    }
+}
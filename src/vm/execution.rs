@@ -4,17 +4,45 @@
 
 use super::VM;
 use crate::runtime::Value;
-use crate::vm::frame::{CallFrame, ThisSource};
+use crate::vm::error::VmError;
+use crate::vm::frame::{CallFrame, Completion, FrameResult};
 use crate::vm::opcode::CompiledFunction;
+use crate::vm::ops;
 use std::io::Write;
 use std::sync::Arc;
 
+/// What executing an opcode asks of the loop running it
+///
+/// Errors stay in the `Err` of `execute_opcode`, and so do `exit()`
+/// (`VmError::Exit`) and the exceptions warnings turn into
+/// (`VmError::Thrown`): those reach the loop from inside built-in
+/// functions and nested calls, which give values rather than a
+/// `ControlFlow`.
+#[derive(Debug)]
+pub(crate) enum ControlFlow {
+    /// Go on with the next opcode
+    Next,
+    /// Return the value on top of the stack from the current frame
+    Return,
+    /// Break out of the innermost loop
+    Break,
+    /// Continue the innermost loop
+    Continue,
+    /// The running generator yielded; its run loop stops
+    Yield,
+    /// The running fiber suspended; its run loop stops
+    Suspend,
+}
+
 pub fn execute_vm<W: Write>(
     vm: &mut VM<W>,
     function: Arc<CompiledFunction>,
-) -> Result<Value, String> {
-    let frame = CallFrame::new(function, 0);
-    vm.frames.push(frame);
+) -> Result<Value, VmError> {
+    let mut frame = CallFrame::new(function, 0);
+    if vm.frames.is_empty() {
+        vm.enter_main(&mut frame);
+    }
+    vm.push_frame(frame);
 
     loop {
         let frame = match vm.frames.last_mut() {
@@ -26,7 +54,7 @@ pub fn execute_vm<W: Write>(
 
         if frame.ip >= frame.function.bytecode.len() {
             let returned = vm.stack.pop().unwrap_or(Value::Null);
-            vm.frames.pop();
+            vm.pop_frame();
 
             if vm.frames.is_empty() {
                 return Ok(returned);
@@ -39,146 +67,88 @@ pub fn execute_vm<W: Write>(
         let opcode = frame.function.bytecode[frame.ip].clone();
         frame.ip += 1;
 
-        match vm.execute_opcode(opcode) {
-            Ok(()) => {}
-            Err(e) => {
-                if e.starts_with("__RETURN__") {
-                    let frame = vm.frames.last().expect("No frame");
-                    let current_ip = frame.ip as u32;
-                    let is_constructor = frame.is_constructor;
-                    let this_source = frame.this_source.clone();
-                    let modified_this =
-                        if !matches!(this_source, ThisSource::None) && !frame.locals.is_empty() {
-                            Some(frame.locals[0].clone())
-                        } else {
-                            None
-                        };
-
-                    let value =
-                        if is_constructor || matches!(this_source, ThisSource::PropertySetHook) {
-                            frame.locals[0].clone()
-                        } else {
-                            let value_str = e.strip_prefix("__RETURN__").unwrap();
-                            if value_str == "null" {
-                                Value::Null
-                            } else {
-                                vm.stack.pop().unwrap_or(Value::Null)
-                            }
-                        };
-
-                    let finally_jump = vm.handlers.iter().rev().find_map(|h| {
-                        if h.frame_depth == vm.frames.len()
-                            && h.finally_offset > 0
-                            && current_ip > h.try_start
-                            && current_ip <= h.finally_offset
-                        {
-                            Some(h.finally_offset as usize)
-                        } else {
-                            None
-                        }
-                    });
-
-                    if let Some(finally_offset) = finally_jump {
-                        vm.pending_return = Some(value);
-                        if let Some(frame) = vm.frames.last_mut() {
-                            frame.jump_to(finally_offset);
-                        }
-                        continue;
-                    }
-
-                    vm.frames.pop();
-
-                    if let Some(modified) = modified_this {
-                        match this_source {
-                            ThisSource::LocalSlot(slot) => {
-                                if let Some(caller_frame) = vm.frames.last_mut() {
-                                    caller_frame.set_local(slot, modified);
-                                }
-                            }
-                            ThisSource::GlobalVar(var_name) => {
-                                vm.globals.insert(var_name, modified);
-                            }
-                            ThisSource::None | ThisSource::PropertySetHook => {}
-                        }
-                    }
-
-                    if vm.frames.is_empty() {
-                        return Ok(value);
-                    }
-                    vm.stack.push(value);
-                    continue;
-                } else if e.starts_with("__BREAK__") {
-                    if let Some(loop_ctx) = vm.loops.last() {
-                        let break_target = loop_ctx.break_target as usize;
-                        if let Some(frame) = vm.frames.last_mut() {
-                            frame.jump_to(break_target);
-                        }
-                    } else {
-                        return Err("Cannot break outside of loop".to_string());
-                    }
+        let e = match vm.execute_opcode(opcode) {
+            Ok(ControlFlow::Next) => continue,
+            Ok(ControlFlow::Return) => {
+                let Some(value) = finish_return(vm)? else {
+                    // A finally block runs first
                     continue;
-                } else if e.starts_with("__CONTINUE__") {
-                    if let Some(loop_ctx) = vm.loops.last() {
-                        let continue_target = loop_ctx.continue_target as usize;
-                        if let Some(frame) = vm.frames.last_mut() {
-                            frame.jump_to(continue_target);
-                        }
-                    } else {
-                        return Err("Cannot continue outside of loop".to_string());
-                    }
-                    continue;
-                } else if e.starts_with("__EXCEPTION__") {
-                    let parts: Vec<&str> = e.splitn(3, ':').collect();
-                    if parts.len() >= 3 {
-                        let _class = parts[1];
-                        let _message = parts[2];
-                        return Err(e);
-                    }
-                    return Err(e);
-                } else if e == "__GENERATOR__" {
-                    vm.frames.pop();
-                    return Err("__GENERATOR__".to_string());
-                } else if e == "__FINALLY_RETURN__" {
-                    if let Some(value) = vm.pending_return.take() {
-                        let frame = vm.frames.last().expect("No frame");
-                        let this_source = frame.this_source.clone();
-                        let modified_this = if !matches!(this_source, ThisSource::None)
-                            && !frame.locals.is_empty()
-                        {
-                            Some(frame.locals[0].clone())
-                        } else {
-                            None
-                        };
-
-                        vm.frames.pop();
-
-                        if let Some(modified) = modified_this {
-                            match this_source {
-                                ThisSource::LocalSlot(slot) => {
-                                    if let Some(caller_frame) = vm.frames.last_mut() {
-                                        caller_frame.set_local(slot, modified);
-                                    }
-                                }
-                                ThisSource::GlobalVar(var_name) => {
-                                    vm.globals.insert(var_name, modified);
-                                }
-                                ThisSource::None | ThisSource::PropertySetHook => {}
-                            }
-                        }
-
-                        if vm.frames.is_empty() {
-                            return Ok(value);
-                        }
-                        vm.stack.push(value);
-                        continue;
-                    }
-                } else if e.starts_with("__EXIT__:") {
-                    // Handle exit() and die() calls
-                    return Err(e);
-                } else {
-                    return Err(e);
+                };
+                if vm.frames.is_empty() {
+                    return Ok(value);
                 }
+                vm.stack.push(value);
+                continue;
+            }
+            Ok(ControlFlow::Break) => {
+                ops::execute_loop_jump(vm, Completion::Break)?;
+                continue;
             }
+            Ok(ControlFlow::Continue) => {
+                ops::execute_loop_jump(vm, Completion::Continue)?;
+                continue;
+            }
+            Ok(flow @ (ControlFlow::Yield | ControlFlow::Suspend)) => flow.outside_its_loop(),
+            // A warning became an exception, already on its way to the
+            // catch block
+            Err(VmError::Thrown) => continue,
+            // Handle exit() and die() calls
+            Err(e @ VmError::Exit(_)) => return Err(e),
+            Err(e) => e,
+        };
+        let Err(e) = ops::throw_raised(vm, e) else {
+            continue;
+        };
+        vm.record_error_location(&e);
+        return Err(e);
+    }
+}
+
+impl ControlFlow {
+    /// The error for a yield or suspension reaching a loop other than
+    /// the generator's or fiber's own
+    pub(crate) fn outside_its_loop(self) -> VmError {
+        match self {
+            ControlFlow::Suspend => {
+                VmError::exception("FiberError", "Cannot suspend outside of fiber")
+            }
+            _ => VmError::fatal("Cannot yield outside of a generator"),
         }
     }
 }
+
+/// Return the value on top of the stack from the current frame. Gives the
+/// value returned, which is the object for a constructor (see also
+/// `FrameResult`), or `None` if a finally block runs first.
+pub(crate) fn finish_return<W: Write>(vm: &mut VM<W>) -> Result<Option<Value>, VmError> {
+    let returned = vm.stack.pop().unwrap_or(Value::Null);
+    let frame = vm
+        .frames
+        .last()
+        .ok_or_else(|| VmError::fatal("No call frame available"))?;
+    let this = || frame.locals.first().cloned().unwrap_or(Value::Null);
+    let value = match &frame.result {
+        _ if frame.is_constructor => this(),
+        FrameResult::Returned => returned,
+        FrameResult::Assigned(value) => value.clone(),
+        FrameResult::Clone(initialized) => {
+            let clone = this();
+            if let Value::Object(instance) = &clone {
+                let mut instance = instance.lock();
+                instance
+                    .initialized_readonly
+                    .extend(initialized.iter().cloned());
+            }
+            clone
+        }
+    };
+
+    let Some(Completion::Return(value)) =
+        ops::exit_through_finally(vm, Completion::Return(value), |_| true)
+    else {
+        return Ok(None);
+    };
+
+    vm.pop_frame();
+    Ok(Some(value))
+}
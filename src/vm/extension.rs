@@ -0,0 +1,371 @@
+//! Native extensions
+//!
+//! A `Registry` holds the functions scripts call, starting with the core
+//! built-ins, and the functions, constants and classes a host adds on top
+//! of the language: Rust closures called like built-ins, values read as
+//! bare constants or with `constant()` and `defined()`, and classes made
+//! of bytecode the way the built-in classes are, or of Rust closures as a
+//! `NativeClass`.
+//! Hosts fill one directly, bundle their additions as an `Extension`, or
+//! load C plugins with `ffi::load_plugin`, then hand it to the VM before
+//! `VM::load`.
+//!
+//! An extension function registered under the name of a core built-in
+//! replaces it, unless the VM runs that one itself (`VM_FUNCTIONS`);
+//! user-defined functions still come first.
+//!
+//! ```
+//! use vhp::runtime::Value;
+//! use vhp::vm::extension::{Extension, Registry};
+//! use vhp::vm::VM;
+//!
+//! struct Greeter;
+//!
+//! impl Extension for Greeter {
+//!     fn register(&self, registry: &mut Registry) {
+//!         registry.add_constant("GREETING", Value::String("Hello".into()));
+//!         registry.add_function("greet", |args, _output| {
+//!             let name = args.first().map(|v| v.to_string_val()).unwrap_or_default();
+//!             Ok(Value::String(format!("Hello, {}", name)))
+//!         });
+//!     }
+//! }
+//!
+//! let compilation = vhp::compile("<?php echo greet('vhp'), ' ', constant('GREETING');", "x.php").unwrap();
+//! let mut output = Vec::new();
+//! let mut vm = VM::new(&mut output);
+//! vm.load_extension(&Greeter);
+//! let main = vm.load(compilation);
+//! vm.execute(main).unwrap();
+//! assert_eq!(String::from_utf8(output).unwrap(), "Hello, vhp Hello");
+//! ```
+
+use super::class::{CompiledClass, CompiledProperty};
+use super::opcode::{CompiledFunction, Opcode};
+use super::VM;
+use crate::ast::Visibility;
+use crate::runtime::builtins;
+use crate::runtime::builtins::native::Builtin;
+use crate::runtime::Value;
+use crate::vm::error::{NativeError, VmError};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+/// A function implemented in Rust: PHP arguments and the script output
+pub type NativeFunction = Arc<dyn Fn(&[Value], &mut dyn Write) -> Result<Value, NativeError>>;
+
+/// A method implemented in Rust: `$this` (null for a static method),
+/// the PHP arguments and the script output
+pub type NativeMethod = Arc<dyn Fn(&Value, &[Value], &mut dyn Write) -> Result<Value, NativeError>>;
+
+/// A bundle of functions, constants and classes added to a registry
+pub trait Extension {
+    /// Add everything this extension provides to `registry`
+    fn register(&self, registry: &mut Registry);
+}
+
+/// Functions, constants and classes: the core built-ins, and what the
+/// host adds
+#[derive(Clone)]
+pub struct Registry {
+    /// Functions by lowercase name
+    functions: HashMap<String, NativeFunction>,
+    /// Constants by exact name
+    constants: HashMap<String, Value>,
+    classes: Vec<Arc<CompiledClass>>,
+    /// Methods of native classes by lowercase "class::method"
+    methods: HashMap<String, NativeMethod>,
+}
+
+impl Registry {
+    /// A registry holding the core built-in functions
+    pub fn new() -> Self {
+        let mut registry = Self {
+            functions: HashMap::new(),
+            constants: HashMap::new(),
+            classes: Vec::new(),
+            methods: HashMap::new(),
+        };
+        super::builtins::register_core(&mut registry);
+        registry
+    }
+
+    /// Make `function` callable from scripts as `name` (case-insensitive),
+    /// replacing any function registered under it
+    pub fn add_function<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[Value], &mut dyn Write) -> Result<Value, NativeError> + 'static,
+    {
+        self.functions
+            .insert(name.to_lowercase(), Arc::new(function));
+    }
+
+    /// Make a function declared with `#[vhp_builtin]` callable
+    pub fn add_builtin(&mut self, builtin: &Builtin) {
+        let call = builtin.call;
+        self.add_function(builtin.name, move |args, _| call(args));
+    }
+
+    /// Define the constant `name` (case-sensitive)
+    pub fn add_constant(&mut self, name: &str, value: Value) {
+        self.constants.insert(name.to_string(), value);
+    }
+
+    /// Declare `class`, replacing a built-in class of the same name
+    pub fn add_class(&mut self, class: CompiledClass) {
+        self.classes.retain(|c| c.name != class.name);
+        self.classes.push(Arc::new(class));
+    }
+
+    /// Declare a class whose methods are Rust closures, replacing a
+    /// built-in class of the same name
+    pub fn add_native_class(&mut self, class: NativeClass) {
+        let prefix = format!("{}::", class.class.name.to_lowercase());
+        self.methods.retain(|name, _| !name.starts_with(&prefix));
+        for (name, method) in class.natives {
+            self.methods
+                .insert(format!("{}{}", prefix, name.to_lowercase()), method);
+        }
+        self.add_class(class.class);
+    }
+
+    /// Add everything `extension` provides
+    pub fn load(&mut self, extension: &dyn Extension) {
+        extension.register(self);
+    }
+
+    pub fn function(&self, name: &str) -> Option<&NativeFunction> {
+        self.functions.get(&name.to_lowercase())
+    }
+
+    pub fn constant(&self, name: &str) -> Option<&Value> {
+        self.constants.get(name)
+    }
+
+    pub fn classes(&self) -> &[Arc<CompiledClass>] {
+        &self.classes
+    }
+
+    /// The method of a native class ("Class::method", case-insensitive)
+    pub fn method(&self, name: &str) -> Option<&NativeMethod> {
+        self.methods.get(&name.to_lowercase())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A class whose methods are Rust closures, for `Registry::add_native_class`
+///
+/// Each method takes any number of arguments. `$this` is the object the
+/// method was called on, whose properties the closure reads and writes;
+/// `__construct` sets it up.
+///
+/// ```
+/// use vhp::runtime::Value;
+/// use vhp::vm::error::NativeError;
+/// use vhp::vm::extension::NativeClass;
+/// use vhp::Engine;
+///
+/// let counter = NativeClass::new("Counter")
+///     .property("count", Value::Integer(0))
+///     .method("add", |this, args, _output| {
+///         let Value::Object(counter) = this else {
+///             return Err(NativeError::Fatal("Counter::add() needs an object".into()));
+///         };
+///         let step = args.first().map_or(1, |step| step.to_int());
+///         let count = counter.get("count").map_or(0, |count| count.to_int());
+///         counter.set("count", Value::Integer(count + step));
+///         Ok(Value::Null)
+///     })
+///     .static_method("zero", |_, _, _| Ok(Value::Integer(0)));
+///
+/// let mut engine = Engine::new();
+/// engine.register_class(counter);
+/// engine
+///     .eval("<?php $c = new Counter(); $c->add(); $c->add(5); echo $c->count, ' ', Counter::zero();")
+///     .unwrap();
+/// assert_eq!(engine.take_output(), "6 0");
+/// ```
+pub struct NativeClass {
+    class: CompiledClass,
+    natives: Vec<(String, NativeMethod)>,
+}
+
+impl NativeClass {
+    pub fn new(name: &str) -> Self {
+        Self {
+            class: CompiledClass::new(name.to_string()),
+            natives: Vec::new(),
+        }
+    }
+
+    /// Extend the class `parent`
+    pub fn extends(mut self, parent: &str) -> Self {
+        self.class.parent = Some(parent.to_string());
+        self
+    }
+
+    /// Declare a public property starting out as `default`
+    pub fn property(mut self, name: &str, default: Value) -> Self {
+        self.class.properties.push(CompiledProperty {
+            name: name.to_string(),
+            visibility: Visibility::Public,
+            write_visibility: None,
+            default: Some(default),
+            readonly: false,
+            is_static: false,
+            type_hint: None,
+            attributes: Vec::new(),
+            get_hook: None,
+            set_hook: None,
+        });
+        self
+    }
+
+    /// Declare a public method
+    pub fn method<F>(mut self, name: &str, method: F) -> Self
+    where
+        F: Fn(&Value, &[Value], &mut dyn Write) -> Result<Value, NativeError> + 'static,
+    {
+        let stub = self.stub(name, &["this", "args"]);
+        self.class.method_order.push(name.to_string());
+        self.class.methods.insert(name.to_string(), stub);
+        self.natives.push((name.to_string(), Arc::new(method)));
+        self
+    }
+
+    /// Declare a public static method, which gets null for `$this`
+    pub fn static_method<F>(mut self, name: &str, method: F) -> Self
+    where
+        F: Fn(&Value, &[Value], &mut dyn Write) -> Result<Value, NativeError> + 'static,
+    {
+        let stub = self.stub(name, &["args"]);
+        self.class.static_methods.insert(name.to_string(), stub);
+        self.natives.push((name.to_string(), Arc::new(method)));
+        self
+    }
+
+    /// The method's bytecode: `Opcode::NativeMethod` on its variadic
+    /// arguments
+    fn stub(&self, name: &str, locals: &[&str]) -> Arc<CompiledFunction> {
+        let mut stub = CompiledFunction::new(format!("{}::{}", self.class.name, name));
+        stub.param_count = 1;
+        stub.is_variadic = true;
+        stub.local_names = locals.iter().map(|local| local.to_string()).collect();
+        stub.local_count = locals.len() as u16;
+        stub.strings.push(stub.name.clone());
+        stub.bytecode
+            .extend([Opcode::NativeMethod(0), Opcode::Return]);
+        Arc::new(stub)
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Replace the registry of extension functions, constants and classes
+    pub fn set_registry(&mut self, registry: Registry) {
+        self.registry = registry;
+    }
+
+    /// The registry of extension functions, constants and classes
+    pub fn registry_mut(&mut self) -> &mut Registry {
+        &mut self.registry
+    }
+
+    /// Add a native function, as `Registry::add_function` does
+    pub fn register_function<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[Value], &mut dyn Write) -> Result<Value, NativeError> + 'static,
+    {
+        self.registry.add_function(name, function);
+    }
+
+    /// Add everything `extension` provides
+    pub fn load_extension(&mut self, extension: &dyn Extension) {
+        self.registry.load(extension);
+    }
+
+    /// Whether `name` is a core built-in or an extension function
+    pub(crate) fn is_native_function(&self, name: &str) -> bool {
+        super::builtins::is_builtin(name) || self.registry.function(name).is_some()
+    }
+
+    /// Call the function `name` of the registry: an extension function or
+    /// a core built-in
+    pub(crate) fn call_registered_function(
+        &mut self,
+        name: &str,
+        args: &[Value],
+    ) -> Result<Value, VmError> {
+        let function = self
+            .registry
+            .function(name)
+            .cloned()
+            .ok_or_else(|| VmError::undefined_function(name))?;
+        Ok(function(args, &mut self.output)?)
+    }
+
+    /// Run the native method "Class::method" on the current frame's
+    /// `$this` and arguments
+    pub(crate) fn execute_native_method(&mut self, name: &str) -> Result<(), VmError> {
+        let method = self
+            .registry
+            .method(name)
+            .cloned()
+            .ok_or_else(|| VmError::error(format!("Call to undefined method {}()", name)))?;
+        let locals = self.current_frame().locals.clone();
+        let (this, args) = match locals.as_slice() {
+            [this, args] => (this.clone(), args),
+            [args] => (Value::Null, args),
+            _ => (Value::Null, &Value::Null),
+        };
+        let args: Vec<Value> = match args {
+            Value::Array(args) => args.iter().map(|(_, arg)| arg.clone()).collect(),
+            _ => Vec::new(),
+        };
+        let result = method(&this, &args, &mut self.output);
+        self.stack.push(result?);
+        Ok(())
+    }
+
+    /// Declare the registry's classes
+    pub(crate) fn register_extension_classes(&mut self) {
+        for class in self.registry.classes() {
+            self.classes.insert(class.name.clone(), class.clone());
+        }
+    }
+
+    /// The value of an extension constant, or of a core one (the error
+    /// levels, the `LC_*` categories, the `T_*` token ids and the `PREG_*`
+    /// flags)
+    fn constant_value(&self, name: &str) -> Option<Value> {
+        let name = name.trim_start_matches('\\');
+        self.registry
+            .constant(name)
+            .cloned()
+            .or_else(|| builtins::core_constant(name))
+    }
+
+    /// The value of the extension or core constant `name`, as a bare name
+    /// or `constant()` reads it
+    pub(crate) fn fetch_constant(&self, name: &str) -> Result<Value, VmError> {
+        self.constant_value(name)
+            .ok_or_else(|| VmError::error(format!("Undefined constant \"{}\"", name)))
+    }
+
+    /// `constant()`: the value of an extension or core constant
+    pub(crate) fn constant(&self, args: &[Value]) -> Result<Value, VmError> {
+        let name = args.first().map(|v| v.to_string_val()).unwrap_or_default();
+        self.fetch_constant(&name)
+    }
+
+    /// `defined()`: whether an extension or core constant exists
+    pub(crate) fn defined(&self, args: &[Value]) -> Value {
+        let name = args.first().map(|v| v.to_string_val()).unwrap_or_default();
+        Value::Bool(self.constant_value(&name).is_some())
+    }
+}
@@ -0,0 +1,199 @@
+//! The Fiber class
+//!
+//! A `Fiber` object holds the id of its fiber, whose callback and call
+//! stack the VM keeps (see `ops::fiber`), so copies of the object share
+//! the fiber. The methods are stubs running `Opcode::Fiber` on their
+//! arguments; `Fiber::suspend()` and `Fiber::getCurrent()` have opcodes
+//! of their own.
+
+use super::class::{CompiledClass, CompiledProperty};
+use super::opcode::{CompiledFunction, Opcode};
+use super::ops::{self, Entry, FiberStatus};
+use super::VM;
+use crate::ast::Visibility;
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::name_table::NameTable;
+use std::io::Write;
+use std::sync::Arc;
+
+/// The methods `Opcode::Fiber` runs: name, parameters and how many are
+/// required
+const METHODS: &[(&str, &[&str], u8)] = &[
+    ("__construct", &["callback"], 1),
+    ("start", &["args"], 0),
+    ("resume", &["value"], 0),
+    ("throw", &["exception"], 1),
+    ("getReturn", &[], 0),
+    ("isStarted", &[], 0),
+    ("isSuspended", &[], 0),
+    ("isRunning", &[], 0),
+    ("isTerminated", &[], 0),
+];
+
+/// A method of Fiber with its parameters as locals, after `$this` unless
+/// it is static
+fn method(name: &str, params: &[&str], required: u8, is_static: bool) -> CompiledFunction {
+    let mut method = CompiledFunction::new(format!("Fiber::{}", name));
+    method.param_count = params.len() as u8;
+    method.required_param_count = required;
+    let this = (!is_static).then_some("this");
+    method.local_names = this
+        .into_iter()
+        .chain(params.iter().copied())
+        .map(str::to_string)
+        .collect();
+    method.local_count = method.local_names.len() as u16;
+    method
+}
+
+pub fn register_fiber_class(classes: &mut NameTable<Arc<CompiledClass>>) {
+    let mut fiber = CompiledClass::new("Fiber".to_string());
+    fiber.is_final = true;
+    fiber.properties.push(CompiledProperty {
+        name: "__id".to_string(),
+        visibility: Visibility::Private,
+        write_visibility: None,
+        default: Some(Value::Null),
+        readonly: false,
+        is_static: false,
+        type_hint: None,
+        attributes: Vec::new(),
+        get_hook: None,
+        set_hook: None,
+    });
+
+    for (name, params, required) in METHODS {
+        let mut stub = method(name, params, *required, false);
+        stub.is_variadic = *name == "start";
+        stub.strings.push(stub.name.clone());
+        stub.bytecode.extend([Opcode::Fiber(0), Opcode::Return]);
+        fiber.method_order.push(name.to_string());
+        fiber.methods.insert(name.to_string(), Arc::new(stub));
+    }
+
+    let mut suspend = method("suspend", &["value"], 0, true);
+    suspend
+        .bytecode
+        .extend([Opcode::LoadFast(0), Opcode::SuspendFiber, Opcode::Return]);
+    fiber
+        .static_methods
+        .insert("suspend".to_string(), Arc::new(suspend));
+
+    let mut get_current = method("getCurrent", &[], 0, true);
+    get_current
+        .bytecode
+        .extend([Opcode::GetCurrentFiber, Opcode::Return]);
+    fiber
+        .static_methods
+        .insert("getCurrent".to_string(), Arc::new(get_current));
+
+    classes.insert("Fiber".to_string(), Arc::new(fiber));
+}
+
+/// The id of the fiber a `Fiber` object stands for
+fn fiber_id(fiber: &Value) -> Result<usize, VmError> {
+    match fiber {
+        Value::Object(object) => match object.get("__id") {
+            Some(Value::Integer(id)) => Ok(id as usize),
+            _ => Err(VmError::fatal("Fiber has not been constructed")),
+        },
+        _ => Err(VmError::fatal("Fiber method called without a Fiber object")),
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Run a method of Fiber ("Fiber::method") on the current frame's
+    /// `$this` and arguments
+    pub(crate) fn execute_fiber(&mut self, method: &str) -> Result<(), VmError> {
+        let args = self.current_frame().locals.clone();
+        if method == "Fiber::__construct" {
+            let callback = self.callback_argument(method, 1, &args[1])?;
+            let id = ops::create_fiber(self, callback);
+            ops::set_fiber_id(&self.current_frame().locals[0], id);
+            self.stack.push(Value::Null);
+            return Ok(());
+        }
+
+        let id = fiber_id(&args[0])?;
+        let status = &ops::fiber_state(self, id)?.status;
+        let result = match method {
+            "Fiber::start" => {
+                if !matches!(status, FiberStatus::Init) {
+                    return Err(VmError::exception(
+                        "FiberError",
+                        "Cannot start a fiber that has already been started",
+                    ));
+                }
+                let params = match &args[1] {
+                    Value::Array(params) => params.iter().map(|(_, arg)| arg.clone()).collect(),
+                    _ => Vec::new(),
+                };
+                return self.switch_to_fiber(id, &args[0], Entry::Start(params));
+            }
+            "Fiber::resume" | "Fiber::throw" => {
+                if !matches!(status, FiberStatus::Suspended) {
+                    return Err(VmError::exception(
+                        "FiberError",
+                        "Cannot resume a fiber that is not suspended",
+                    ));
+                }
+                let entry = if method == "Fiber::resume" {
+                    Entry::Resume(args[1].clone())
+                } else if matches!(args[1], Value::Object(_)) {
+                    Entry::Throw(args[1].clone())
+                } else {
+                    return Err(VmError::type_error(format!(
+                        "Fiber::throw(): Argument #1 ($exception) must be of type Throwable, {} given",
+                        args[1].type_name()
+                    )));
+                };
+                return self.switch_to_fiber(id, &args[0], entry);
+            }
+            "Fiber::getReturn" => match status {
+                FiberStatus::Returned(value) => (**value).clone(),
+                FiberStatus::Init => {
+                    return Err(VmError::exception(
+                        "FiberError",
+                        "Cannot get fiber return value: The fiber has not been started",
+                    ))
+                }
+                FiberStatus::Threw => {
+                    return Err(VmError::exception(
+                        "FiberError",
+                        "Cannot get fiber return value: The fiber threw an exception",
+                    ))
+                }
+                _ => {
+                    return Err(VmError::exception(
+                        "FiberError",
+                        "Cannot get fiber return value: The fiber has not returned",
+                    ))
+                }
+            },
+            "Fiber::isStarted" => Value::Bool(!matches!(status, FiberStatus::Init)),
+            "Fiber::isSuspended" => Value::Bool(matches!(status, FiberStatus::Suspended)),
+            "Fiber::isRunning" => Value::Bool(matches!(status, FiberStatus::Running)),
+            "Fiber::isTerminated" => Value::Bool(matches!(
+                status,
+                FiberStatus::Returned(_) | FiberStatus::Threw
+            )),
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method {}()",
+                    method
+                )))
+            }
+        };
+        self.stack.push(result);
+        Ok(())
+    }
+
+    /// Switch to the fiber `id` and push what `start()`, `resume()` or
+    /// `throw()` gives, unless execution resumes in a catch block
+    fn switch_to_fiber(&mut self, id: usize, fiber: &Value, entry: Entry) -> Result<(), VmError> {
+        let result = ops::switch_to_fiber(self, id, fiber.clone(), entry)?;
+        self.stack.extend(result);
+        Ok(())
+    }
+}
@@ -3,22 +3,22 @@
 //! This module defines the call frame structure used to track
 //! function execution state in the VM.
 
-use crate::runtime::Value;
+use crate::runtime::{Reference, Value};
 use crate::vm::opcode::CompiledFunction;
 use std::collections::HashMap;
 use std::sync::Arc;
 
-/// Source of $this for method calls - tracks where to update after method returns
+/// What a frame gives its caller when it returns
 #[derive(Debug, Clone)]
-pub enum ThisSource {
-    /// No tracking needed (e.g., chained method calls)
-    None,
-    /// $this came from a local variable slot in the caller's frame
-    LocalSlot(u16),
-    /// $this came from a global variable
-    GlobalVar(String),
-    /// Property set hook - push modified $this to stack instead of return value
-    PropertySetHook,
+pub enum FrameResult {
+    /// The value it returns
+    Returned,
+    /// A property set hook or `__set()`: the value assigned, which is what
+    /// the assignment evaluates to
+    Assigned(Value),
+    /// `__clone()`: the clone; holds the readonly properties initialized
+    /// before cloning
+    Clone(std::collections::HashSet<String>),
 }
 
 /// A call frame represents a single function invocation
@@ -36,13 +36,20 @@ pub struct CallFrame {
     /// Saved global variables (for restoring after function call)
     pub saved_globals: Option<HashMap<String, Value>>,
     /// Current `$this` object (for methods)
-    pub this: Option<crate::runtime::ObjectInstance>,
+    pub this: Option<crate::runtime::ObjectRef>,
     /// Called class name (for late static binding)
     pub called_class: Option<String>,
     /// Whether this is a constructor frame (returns $this on completion)
     pub is_constructor: bool,
-    /// Tracks where $this came from so we can update source after method returns
-    pub this_source: ThisSource,
+    /// What the caller gets when the frame returns
+    pub result: FrameResult,
+    /// Bit per local slot written with `set_local` (slots past the 64th
+    /// always count as assigned)
+    assigned: u64,
+    /// Handlers of the try statements the frame is in, innermost last
+    pub handlers: Vec<ExceptionHandler>,
+    /// Finally blocks the frame is running, innermost last
+    pub finally_blocks: Vec<PendingFinally>,
 }
 
 impl CallFrame {
@@ -58,7 +65,10 @@ impl CallFrame {
             this: None,
             called_class: None,
             is_constructor: false,
-            this_source: ThisSource::None,
+            result: FrameResult::Returned,
+            assigned: 0,
+            handlers: Vec::new(),
+            finally_blocks: Vec::new(),
         }
     }
 
@@ -67,7 +77,7 @@ impl CallFrame {
     pub fn new_method(
         function: Arc<CompiledFunction>,
         stack_base: usize,
-        this: crate::runtime::ObjectInstance,
+        this: crate::runtime::ObjectRef,
         called_class: String,
     ) -> Self {
         let local_count = function.local_count as usize;
@@ -80,7 +90,10 @@ impl CallFrame {
             this: Some(this),
             called_class: Some(called_class),
             is_constructor: false,
-            this_source: ThisSource::None,
+            result: FrameResult::Returned,
+            assigned: 0,
+            handlers: Vec::new(),
+            finally_blocks: Vec::new(),
         }
     }
 
@@ -105,16 +118,125 @@ impl CallFrame {
         self.ip = offset;
     }
 
-    /// Get a local variable by slot index
+    /// Put a call's arguments in the parameter slots, starting at slot
+    /// `first`. The arguments for a variadic last parameter go in an array.
+    pub fn bind_args(&mut self, first: usize, args: Vec<Value>) {
+        let param_count = self.function.param_count as usize;
+        if !self.function.is_variadic || param_count == 0 {
+            for (slot, arg) in (first..).zip(args) {
+                if slot < self.locals.len() {
+                    self.locals[slot] = arg;
+                }
+            }
+            return;
+        }
+        let variadic_slot = first + param_count - 1;
+        let mut args = args.into_iter();
+        for slot in first..variadic_slot {
+            match args.next() {
+                Some(arg) => self.locals[slot] = arg,
+                None => break,
+            }
+        }
+        let rest = args
+            .enumerate()
+            .map(|(i, arg)| (crate::runtime::ArrayKey::Integer(i as i64), arg))
+            .collect();
+        self.locals[variadic_slot] = Value::Array(rest);
+    }
+
+    /// Get a local variable by slot index (the value a reference refers to)
     #[inline]
-    pub fn get_local(&self, slot: u16) -> &Value {
-        &self.locals[slot as usize]
+    pub fn get_local(&self, slot: u16) -> Value {
+        match &self.locals[slot as usize] {
+            Value::Reference(r) => r.get(),
+            value => value.clone(),
+        }
     }
 
-    /// Set a local variable by slot index
+    /// Set a local variable by slot index, through the reference the slot
+    /// holds if it is bound to other variables
     #[inline]
     pub fn set_local(&mut self, slot: u16, value: Value) {
-        self.locals[slot as usize] = value;
+        match &self.locals[slot as usize] {
+            Value::Reference(r) => r.set(value),
+            _ => self.locals[slot as usize] = value,
+        }
+        self.mark_assigned(slot);
+    }
+
+    /// Change a local variable in place, through its reference if it has
+    /// one
+    pub fn with_local<T>(&mut self, slot: u16, change: impl FnOnce(&mut Value) -> T) -> T {
+        match &mut self.locals[slot as usize] {
+            Value::Reference(r) => r.update(change),
+            value => change(value),
+        }
+    }
+
+    /// The reference a local variable is bound by, binding it to one of
+    /// its own first (`&$x`)
+    pub fn reference(&mut self, slot: u16) -> Reference {
+        let local = &mut self.locals[slot as usize];
+        if let Value::Reference(r) = local {
+            return r.clone();
+        }
+        let r = Reference::new(std::mem::replace(local, Value::Null));
+        *local = Value::Reference(r.clone());
+        r
+    }
+
+    /// Bind a local variable to `reference` (`$x = &...`, `global $x`),
+    /// letting go of whatever it was bound to before
+    pub fn bind_local(&mut self, slot: u16, reference: Reference) {
+        self.locals[slot as usize] = Value::Reference(reference);
+        self.mark_assigned(slot);
+    }
+
+    /// Put the value back in a slot bound by a reference no other variable
+    /// shares any more
+    pub fn unshare_local(&mut self, slot: u16) {
+        let local = &mut self.locals[slot as usize];
+        let value = match local {
+            Value::Reference(r) if !r.is_shared() => r.get(),
+            _ => return,
+        };
+        *local = value;
+    }
+
+    /// Bind the by-reference parameters, starting at slot `first`, to the
+    /// caller's variables, once `bind_args` has put the arguments (coerced
+    /// to the parameter types) in their slots
+    pub fn bind_refs(&mut self, first: usize, refs: Vec<Option<Reference>>) {
+        for (slot, r) in (first..).zip(refs) {
+            if let (Some(r), Some(local)) = (r, self.locals.get_mut(slot)) {
+                r.set(std::mem::replace(local, Value::Null).unref());
+                *local = Value::Reference(r);
+            }
+        }
+    }
+
+    /// Count a local slot as assigned, as the compiler knows it to be
+    #[inline]
+    pub fn mark_assigned(&mut self, slot: u16) {
+        if slot < 64 {
+            self.assigned |= 1 << slot;
+        }
+    }
+
+    /// Clear a local slot, which counts as unassigned again
+    #[inline]
+    pub fn unset_local(&mut self, slot: u16) {
+        self.locals[slot as usize] = Value::Null;
+        if slot < 64 {
+            self.assigned &= !(1 << slot);
+        }
+    }
+
+    /// Whether a local slot was ever written with `set_local`
+    #[inline]
+    pub fn is_assigned(&self, slot: u16) -> bool {
+        slot >= 64 || self.assigned & (1 << slot) != 0
     }
 
     /// Get a string from the function's string pool
@@ -140,16 +262,20 @@ pub struct LoopContext {
     pub break_target: u32,
     /// Stack depth at loop start (for proper cleanup)
     pub stack_depth: usize,
+    /// Call frame depth of the loop (dropped when the frame returns)
+    pub frame_depth: usize,
 }
 
 /// Exception handler for try/catch/finally
+///
+/// Each call frame has a stack of handlers: `TryStart` pushes one and
+/// `TryEnd` pops it when the try block (or, with a finally block, the
+/// catch block) ends. They go away with the frame.
 #[derive(Debug, Clone)]
-#[allow(dead_code)] // catch_class, catch_var, and stack_depth fields not yet used
+#[allow(dead_code)] // try_start, catch_class and catch_var fields not yet used
 pub struct ExceptionHandler {
     /// Start of try block
     pub try_start: u32,
-    /// End of try block
-    pub try_end: u32,
     /// Catch block offset (0 if no catch)
     pub catch_offset: u32,
     /// Exception class to catch (empty for catch-all)
@@ -160,6 +286,41 @@ pub struct ExceptionHandler {
     pub finally_offset: u32,
     /// Stack depth at handler entry (for proper cleanup)
     pub stack_depth: usize,
-    /// Call frame depth at handler entry (for exception propagation)
-    pub frame_depth: usize,
+    /// Loop depth at handler entry (break and continue leave the try
+    /// statement if they target a loop outside it)
+    pub loop_depth: usize,
+    /// Whether a catch block of the statement is running, so that only
+    /// the finally block is left to run
+    pub in_catch: bool,
+    /// `@` expressions being evaluated at handler entry (an exception
+    /// leaves those started since)
+    pub silence_depth: usize,
+}
+
+/// How a finally block ends: it carries on after the try statement, or
+/// finishes the jump that left the statement through it
+#[derive(Debug, Clone)]
+pub enum Completion {
+    /// The try statement ended normally
+    Normal,
+    /// Return the value from the function
+    Return(Value),
+    /// Throw the exception on
+    Throw(Value),
+    /// Break out of the innermost loop
+    Break,
+    /// Continue the innermost loop
+    Continue,
+}
+
+/// A finally block being run
+#[derive(Debug, Clone)]
+pub struct PendingFinally {
+    /// What happens when the block ends
+    pub completion: Completion,
+    /// Loop depth of the block
+    pub loop_depth: usize,
+    /// Number of handlers of the frame when the block started (an
+    /// exception caught by an outer handler abandons the block)
+    pub handler_depth: usize,
 }
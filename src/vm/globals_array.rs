@@ -0,0 +1,202 @@
+//! `$GLOBALS`
+//!
+//! The global variables are the locals of the script's main code, in the
+//! frame at the bottom of the stack, and the variables in `VM::globals`:
+//! the superglobals and those only named through `$GLOBALS`. Reading
+//! `$GLOBALS` builds an array of them, so a copy of it is a snapshot;
+//! `$GLOBALS['name']` reads, assigns and unsets the variable itself.
+//!
+//! Main code starts with the variables it names that are in
+//! `VM::globals`, and leaves its variables there when it ends, so code a
+//! host runs later in the same VM (see `Engine`) and shutdown functions
+//! see them.
+
+use crate::runtime::{ArrayKey, Reference, Value};
+use crate::vm::error::VmError;
+use crate::vm::frame::CallFrame;
+use crate::vm::notices::Level;
+use crate::vm::VM;
+use std::collections::HashSet;
+use std::io::Write;
+
+/// Whether a local is a temporary the compiler allocated, such as
+/// `__foreach_array_0__`, rather than a variable of the script
+fn is_temporary(name: &str) -> bool {
+    name.len() > 4 && name.starts_with("__") && name.ends_with("__")
+}
+
+impl<W: Write> VM<W> {
+    /// The global variable `name`, if it is set
+    ///
+    /// ```
+    /// use vhp::runtime::Value;
+    /// use vhp::vm::VM;
+    ///
+    /// let mut vm = VM::new(std::io::sink());
+    /// vm.set_global("limit", Value::Integer(3));
+    /// let main = vm.load(vhp::compile("<?php $total = $limit * 2;", "a.php").unwrap());
+    /// vm.execute(main).unwrap();
+    /// assert!(matches!(vm.global("total"), Some(Value::Integer(6))));
+    /// ```
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.global_variable(name)
+    }
+
+    /// Set the global variable `name`, as `$GLOBALS['name'] = $value` does
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.assign_global(&Value::String(name.to_string()), value);
+    }
+
+    /// Give main code about to run in `main` the global variables it
+    /// names
+    pub(crate) fn enter_main(&self, main: &mut CallFrame) {
+        let function = main.function.clone();
+        for (slot, name) in function.local_names.iter().enumerate() {
+            match self.globals.get(name) {
+                Some(Value::Reference(r)) => main.bind_local(slot as u16, r.clone()),
+                Some(value) => main.set_local(slot as u16, value.clone()),
+                None => {}
+            }
+        }
+    }
+
+    /// Keep the variables of main code that ended in `main` as global
+    /// variables
+    pub(crate) fn leave_main(&mut self, main: &CallFrame) {
+        for (slot, name) in main.function.local_names.iter().enumerate() {
+            if is_temporary(name) {
+                continue;
+            }
+            match main.is_assigned(slot as u16) {
+                true => self
+                    .globals
+                    .insert(name.clone(), main.get_local(slot as u16)),
+                false => self.globals.remove(name),
+            };
+        }
+    }
+
+    /// The main code's slot for the variable `name`
+    fn global_slot(&self, name: &str) -> Option<u16> {
+        let main = self.frames.first()?;
+        let slot = main.function.local_names.iter().position(|n| n == name)?;
+        Some(slot as u16)
+    }
+
+    /// The value of the global variable `name`, if it is set
+    fn global_variable(&self, name: &str) -> Option<Value> {
+        if let (Some(slot), Some(main)) = (self.global_slot(name), self.frames.first()) {
+            if main.is_assigned(slot) {
+                return Some(main.get_local(slot));
+            }
+        }
+        self.globals.get(name).cloned().map(Value::unref)
+    }
+
+    /// `$GLOBALS`: the superglobals, `$argc` and `$argv`, then the main
+    /// code's variables in the order it declares them, then the rest by
+    /// name
+    pub(crate) fn load_globals(&self) -> Value {
+        let mut names: Vec<&String> = self.globals.keys().collect();
+        names.sort();
+        let (superglobals, others): (Vec<_>, Vec<_>) = names
+            .into_iter()
+            .partition(|name| name.starts_with('_') || *name == "argc" || *name == "argv");
+        let main_names = self
+            .frames
+            .first()
+            .map_or(&[][..], |main| main.function.local_names.as_slice());
+        let mut seen = HashSet::new();
+        let mut globals = Vec::new();
+        for name in superglobals
+            .into_iter()
+            .chain(main_names.iter().filter(|name| !is_temporary(name)))
+            .chain(others)
+        {
+            if !seen.insert(name.as_str()) {
+                continue;
+            }
+            if let Some(value) = self.global_variable(name) {
+                let key = ArrayKey::from_value(&Value::String(name.clone()));
+                globals.push((key, value));
+            }
+        }
+        Value::Array(globals.into_iter().collect())
+    }
+
+    /// `$GLOBALS[$name]`: the global variable `name`, or null with a
+    /// warning (none when `quiet`, for `isset()` and `??`) if it is unset
+    pub(crate) fn fetch_global(&mut self, name: &Value, quiet: bool) -> Result<Value, VmError> {
+        let name = name.to_string_val();
+        match self.global_variable(&name) {
+            Some(value) => Ok(value),
+            None if quiet => Ok(Value::Null),
+            None => {
+                self.raise(
+                    Level::Warning,
+                    &format!("Undefined global variable ${}", name),
+                )?;
+                Ok(Value::Null)
+            }
+        }
+    }
+
+    /// `$GLOBALS[$name] = $value`
+    pub(crate) fn assign_global(&mut self, name: &Value, value: Value) {
+        let name = name.to_string_val();
+        if let Some(slot) = self.global_slot(&name) {
+            let main = &mut self.frames[0];
+            let assigned = main.is_assigned(slot);
+            main.set_local(slot, value.clone());
+            // Code compiled before the main code assigns the variable
+            // reads it by name
+            if assigned {
+                return;
+            }
+        }
+        self.set_global_entry(name, value);
+    }
+
+    /// Set the variable `name` in `VM::globals`, through the reference
+    /// `global` bound it by if there is one
+    pub(crate) fn set_global_entry(&mut self, name: String, value: Value) {
+        match self.globals.get(&name) {
+            Some(Value::Reference(r)) => r.set(value),
+            _ => {
+                self.globals.insert(name, value);
+            }
+        }
+    }
+
+    /// `global $name`: the reference a function binds its local to, which
+    /// the main code's variable, or else the one in `VM::globals`, is
+    /// bound by from then on. An undefined global variable is defined as
+    /// null.
+    pub(crate) fn global_reference(&mut self, name: &str) -> Reference {
+        let in_globals = self.globals.contains_key(name);
+        if let Some(slot) = self.global_slot(name) {
+            let main = &mut self.frames[0];
+            if main.is_assigned(slot) || !in_globals {
+                let reference = main.reference(slot);
+                main.bind_local(slot, reference.clone());
+                return reference;
+            }
+        }
+        let entry = self.globals.entry(name.to_string()).or_insert(Value::Null);
+        if let Value::Reference(r) = entry {
+            return r.clone();
+        }
+        let reference = Reference::new(std::mem::replace(entry, Value::Null));
+        *entry = Value::Reference(reference.clone());
+        reference
+    }
+
+    /// `unset($GLOBALS[$name])`
+    pub(crate) fn unset_global(&mut self, name: &Value) {
+        let name = name.to_string_val();
+        if let Some(slot) = self.global_slot(&name) {
+            self.frames[0].unset_local(slot);
+        }
+        self.globals.remove(&name);
+    }
+}
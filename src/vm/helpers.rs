@@ -5,23 +5,15 @@
 //! - Value comparison operations
 //! - Builtin function dispatcher
 
+use crate::runtime::builtins::encoding::{self, IconvFailure};
+use crate::runtime::builtins::output;
 use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::notices::Level;
 use crate::vm::{builtins, reflection, VM};
-use std::sync::{Arc, Mutex};
-
-lazy_static::lazy_static! {
-    pub static ref REQUIRED_FILES: Arc<Mutex<std::collections::HashSet<String>>> =
-        Arc::new(Mutex::new(std::collections::HashSet::new()));
-}
-
-/// Clear required files registry (useful for testing)
-pub fn clear_required_files() {
-    let mut required = REQUIRED_FILES.lock().unwrap();
-    required.clear();
-}
 
 impl<W: std::io::Write> VM<W> {
-    pub fn add_values(&self, left: Value, right: Value) -> Result<Value, String> {
+    pub fn add_values(&self, left: Value, right: Value) -> Result<Value, VmError> {
         match (&left, &right) {
             (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a + b)),
             (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
@@ -30,8 +22,8 @@ impl<W: std::io::Write> VM<W> {
             (Value::Array(a), Value::Array(b)) => {
                 let mut result = a.clone();
                 for (k, v) in b {
-                    if !result.iter().any(|(key, _)| key == k) {
-                        result.push((k.clone(), v.clone()));
+                    if !result.contains_key(k) {
+                        result.insert(k.clone(), v.clone());
                     }
                 }
                 Ok(Value::Array(result))
@@ -44,7 +36,7 @@ impl<W: std::io::Write> VM<W> {
         }
     }
 
-    pub fn compare_values(&self, left: &Value, right: &Value) -> Result<i64, String> {
+    pub fn compare_values(&self, left: &Value, right: &Value) -> Result<i64, VmError> {
         match (left, right) {
             (Value::Integer(a), Value::Integer(b)) => Ok((*a).cmp(b) as i64),
             (Value::Float(a), Value::Float(b)) => {
@@ -95,34 +87,59 @@ impl<W: std::io::Write> VM<W> {
         &mut self,
         func_name: &str,
         args: &[Value],
-    ) -> Result<Value, String> {
-        match func_name {
+    ) -> Result<Value, VmError> {
+        self.check_sandbox(func_name)?;
+        let result = self.dispatch_native(func_name, args);
+        self.record_native_call(&result);
+        result
+    }
+
+    fn dispatch_native(&mut self, func_name: &str, args: &[Value]) -> Result<Value, VmError> {
+        match func_name.to_lowercase().as_str() {
             "get_class_attributes" => {
                 if args.is_empty() {
-                    return Err("get_class_attributes() expects 1 argument".to_string());
+                    return Err(VmError::fatal("get_class_attributes() expects 1 argument"));
                 }
                 let class_name = args[0].to_string_val();
-                reflection::get_class_attributes(&class_name, &self.classes)
+                reflection::get_class_attributes(&class_name, &self.classes, &|class, name| {
+                    self.class_constant(class, name)
+                })
             }
             "get_property_attributes" => {
                 if args.len() < 2 {
-                    return Err("get_property_attributes() expects 2 arguments".to_string());
+                    return Err(VmError::fatal(
+                        "get_property_attributes() expects 2 arguments",
+                    ));
                 }
                 let class_name = args[0].to_string_val();
                 let property_name = args[1].to_string_val();
-                reflection::get_property_attributes(&class_name, &property_name, &self.classes)
+                reflection::get_property_attributes(
+                    &class_name,
+                    &property_name,
+                    &self.classes,
+                    &|class, name| self.class_constant(class, name),
+                )
             }
             "get_method_attributes" => {
                 if args.len() < 2 {
-                    return Err("get_method_attributes() expects 2 arguments".to_string());
+                    return Err(VmError::fatal(
+                        "get_method_attributes() expects 2 arguments",
+                    ));
                 }
                 let class_name = args[0].to_string_val();
                 let method_name = args[1].to_string_val();
-                reflection::get_method_attributes(&class_name, &method_name, &self.classes)
+                reflection::get_method_attributes(
+                    &class_name,
+                    &method_name,
+                    &self.classes,
+                    &|class, name| self.class_constant(class, name),
+                )
             }
             "get_method_parameter_attributes" => {
                 if args.len() < 3 {
-                    return Err("get_method_parameter_attributes() expects 3 arguments".to_string());
+                    return Err(VmError::fatal(
+                        "get_method_parameter_attributes() expects 3 arguments",
+                    ));
                 }
                 let class_name = args[0].to_string_val();
                 let method_name = args[1].to_string_val();
@@ -132,18 +149,27 @@ impl<W: std::io::Write> VM<W> {
                     &method_name,
                     &parameter_name,
                     &self.classes,
+                    &|class, name| self.class_constant(class, name),
                 )
             }
             "get_function_attributes" => {
                 if args.is_empty() {
-                    return Err("get_function_attributes() expects 1 argument".to_string());
+                    return Err(VmError::fatal(
+                        "get_function_attributes() expects 1 argument",
+                    ));
                 }
                 let function_name = args[0].to_string_val();
-                reflection::get_function_attributes(&function_name, &self.functions)
+                reflection::get_function_attributes(
+                    &function_name,
+                    &self.functions,
+                    &|class, name| self.class_constant(class, name),
+                )
             }
             "get_parameter_attributes" => {
                 if args.len() < 2 {
-                    return Err("get_parameter_attributes() expects 2 arguments".to_string());
+                    return Err(VmError::fatal(
+                        "get_parameter_attributes() expects 2 arguments",
+                    ));
                 }
                 let function_name = args[0].to_string_val();
                 let parameter_name = args[1].to_string_val();
@@ -151,63 +177,40 @@ impl<W: std::io::Write> VM<W> {
                     &function_name,
                     &parameter_name,
                     &self.functions,
+                    &|class, name| self.class_constant(class, name),
                 )
             }
             "get_interface_attributes" => {
                 if args.is_empty() {
-                    return Err("get_interface_attributes() expects 1 argument".to_string());
+                    return Err(VmError::fatal(
+                        "get_interface_attributes() expects 1 argument",
+                    ));
                 }
                 let interface_name = args[0].to_string_val();
-                reflection::get_interface_attributes(&interface_name, &self.interfaces)
+                reflection::get_interface_attributes(
+                    &interface_name,
+                    &self.interfaces,
+                    &|class, name| self.class_constant(class, name),
+                )
             }
             "get_trait_attributes" => {
                 if args.is_empty() {
-                    return Err("get_trait_attributes() expects 1 argument".to_string());
+                    return Err(VmError::fatal("get_trait_attributes() expects 1 argument"));
                 }
                 let trait_name = args[0].to_string_val();
-                reflection::get_trait_attributes(&trait_name, &self.traits)
-            }
-            "interface_exists" => {
-                if args.is_empty() {
-                    return Err("interface_exists() expects at least 1 parameter".to_string());
-                }
-                let interface_name = args[0].to_string_val();
-                // Check parameter is string
-                match &args[0] {
-                    Value::String(_) => {}
-                    _ => return Ok(Value::Bool(false)),
-                }
-                // Case-insensitive lookup
-                let name_lower = interface_name.to_lowercase();
-                let exists = self
-                    .interfaces
-                    .iter()
-                    .any(|(k, _)| k.to_lowercase() == name_lower);
-                Ok(Value::Bool(exists))
+                reflection::get_trait_attributes(&trait_name, &self.traits, &|class, name| {
+                    self.class_constant(class, name)
+                })
             }
-            "trait_exists" => {
-                if args.is_empty() {
-                    return Err("trait_exists() expects at least 1 parameter".to_string());
-                }
-                let trait_name = args[0].to_string_val();
-                // Check parameter is string
-                match &args[0] {
-                    Value::String(_) => {}
-                    _ => return Ok(Value::Bool(false)),
-                }
-                // Case-insensitive lookup
-                let name_lower = trait_name.to_lowercase();
-                let exists = self
-                    .traits
-                    .iter()
-                    .any(|(k, _)| k.to_lowercase() == name_lower);
-                Ok(Value::Bool(exists))
+            name @ ("class_exists" | "interface_exists" | "trait_exists" | "enum_exists") => {
+                self.declaration_exists(name, args)
             }
+            "function_exists" => self.function_exists(args),
             "require" => self.require(args),
             "require_once" => self.require_once(args),
             "load_psr4_class" => {
                 if args.is_empty() {
-                    return Err("load_psr4_class() expects 1 argument".to_string());
+                    return Err(VmError::fatal("load_psr4_class() expects 1 argument"));
                 }
                 let class_name = args[0].to_string_val();
                 match self.load_psr4_class(&class_name) {
@@ -217,299 +220,137 @@ impl<W: std::io::Write> VM<W> {
                 }
             }
             "eval" => self.eval(args),
-            _ => builtins::call_builtin(func_name, args, &mut self.output),
-        }
-    }
-
-    /// require - Include and evaluate a PHP file
-    /// Returns the return value of the included file, or false on failure
-    pub fn require(&mut self, args: &[Value]) -> Result<Value, String> {
-        use crate::lexer::Lexer;
-        use crate::parser::Parser;
-        use std::fs;
-
-        if args.is_empty() {
-            return Err("require() expects at least 1 argument".to_string());
-        }
-
-        let filename = args[0].to_string_val();
-
-        let source = match fs::read_to_string(&filename) {
-            Ok(content) => content,
-            Err(e) => {
-                return Err(format!("require(): Failed to open '{}': {}", filename, e));
+            "constant" => self.constant(args),
+            "defined" => Ok(self.defined(args)),
+            "set_time_limit" => self.set_script_time_limit(args),
+            "vhp_engine_stats" => Ok(self.engine_stats()),
+            "register_shutdown_function" => self.register_shutdown_function(args),
+            "async" => self.async_task(args),
+            "header" => self.header(args),
+            "header_remove" => Ok(self.header_remove(args)),
+            "headers_list" => Ok(self.headers_list()),
+            "http_response_code" => Ok(self.http_response_code(args)),
+            "iconv" => match encoding::iconv(args)? {
+                Ok(converted) => Ok(converted),
+                Err(failure) => {
+                    let level = match failure {
+                        IconvFailure::WrongEncoding(..) => Level::Warning,
+                        IconvFailure::IllegalCharacter => Level::Notice,
+                    };
+                    self.raise(level, &failure.message())?;
+                    Ok(Value::Bool(false))
+                }
+            },
+            name @ ("simplexml_load_string" | "simplexml_load_file" | "simplexml_import_dom") => {
+                self.xml_function(name, args)
             }
-        };
-
-        let mut lexer = Lexer::new(&source);
-        let tokens = lexer
-            .tokenize()
-            .map_err(|e| format!("Lexing error in {}: {}", filename, e))?;
-
-        let mut parser = Parser::new(tokens);
-        let program = parser
-            .parse()
-            .map_err(|e| format!("Parse error in {}: {}", filename, e))?;
-
-        let compiler = crate::vm::compiler::Compiler::new(filename.clone());
-        let compilation = compiler
-            .compile_program(&program)
-            .map_err(|e| format!("Compilation error in {}: {}", filename, e))?;
-
-        for (name, func) in compilation.functions {
-            self.functions.entry(name).or_insert(func);
-        }
-
-        for (name, class) in compilation.classes {
-            self.classes.entry(name).or_insert(class);
-        }
-
-        for (name, interface) in compilation.interfaces {
-            self.interfaces.entry(name).or_insert(interface);
-        }
-
-        for (name, trait_) in compilation.traits {
-            self.traits.entry(name).or_insert(trait_);
-        }
-
-        for (name, enum_) in compilation.enums {
-            self.enums.entry(name).or_insert(enum_);
-        }
-
-        // Execute the file's main function
-        let result = self.execute_simple_function(&compilation.main);
-        result.map_err(|e| format!("Runtime error in {}: {}", filename, e))
-    }
-
-    /// require_once - Include and evaluate a PHP file only once
-    /// Returns the return value of the included file, or false on failure
-    /// If the file has already been included, returns true without re-including
-    pub fn require_once(&mut self, args: &[Value]) -> Result<Value, String> {
-        if args.is_empty() {
-            return Err("require_once() expects at least 1 argument".to_string());
-        }
-
-        let filename = args[0].to_string_val();
-
-        // Check if already required
-        let required_files = REQUIRED_FILES.lock().unwrap();
-        if required_files.contains(&filename) {
-            drop(required_files);
-            return Ok(Value::Bool(true));
-        }
-        drop(required_files);
-
-        // Mark as required before including (in case of error, still marked)
-        {
-            let mut required_files = REQUIRED_FILES.lock().unwrap();
-            required_files.insert(filename.clone());
-        }
-
-        // Call require to do the actual inclusion
-        self.require(args)
-    }
-
-    /// eval - Execute a string as PHP code
-    ///
-    /// This function takes a string of PHP code, parses and compiles it,
-    /// then executes it in the current scope. Variables defined in the
-    /// eval'd code will be available in the calling scope.
-    ///
-    /// # Arguments
-    /// * `code` - The PHP code string to execute
-    ///
-    /// # Returns
-    /// The return value of the evaluated code (last expression), or 1 if
-    /// no return value is specified. Returns null for empty code.
-    ///
-    /// # Errors
-    /// Returns an error string if parsing or execution fails.
-    pub fn eval(&mut self, args: &[Value]) -> Result<Value, String> {
-        use crate::lexer::Lexer;
-        use crate::parser::Parser;
-        use crate::vm::compiler::Compiler;
-
-        if args.is_empty() {
-            return Err("eval() expects exactly 1 parameter".to_string());
-        }
-
-        let code = args[0].to_string_val();
-
-        // Empty string returns null
-        if code.trim().is_empty() {
-            return Ok(Value::Null);
+            name @ ("parse_ini_string" | "parse_ini_file") => self.parse_ini(name, args),
+            name @ ("yaml_parse" | "yaml_parse_file") => self.yaml_parse(name, args),
+            name @ ("highlight_file" | "show_source") => self.highlight_file(name, args),
+            name @ ("strval" | "intval" | "floatval")
+                if matches!(args.first(), Some(Value::Object(_))) =>
+            {
+                let mut args = args.to_vec();
+                args[0] = self.object_scalar(name, args[0].clone())?;
+                self.call_registered_function(name, &args)
+            }
+            "count" | "sizeof" if matches!(args.first(), Some(Value::Object(_))) => {
+                match self.count_object(&args[0])? {
+                    Some(count) => Ok(count),
+                    None => self.call_registered_function(func_name, args),
+                }
+            }
+            "is_callable" => self.is_callable(args),
+            "array_map" => self.array_map(args),
+            "array_filter" => self.array_filter(args),
+            "array_reduce" => self.array_reduce(args),
+            "usort" => self.usort(args),
+            "uasort" => self.uasort(args),
+            "uksort" => self.uksort(args),
+            "get_object_vars" => self.get_object_vars(args),
+            "get_class_methods" => self.get_class_methods(args),
+            "method_exists" => self.method_exists(args),
+            "property_exists" => self.property_exists(args),
+            "get_parent_class" => self.get_parent_class(args),
+            "class_implements" => self.class_implements(args),
+            "error_get_last" => Ok(self.error_get_last()),
+            "error_clear_last" => Ok(self.error_clear_last()),
+            name @ ("ini_set" | "ini_restore") => self.set_ini_directive(name, args),
+            "exit" => Err(VmError::Exit(output::exit(&mut self.output, args)?)),
+            "die" => Err(VmError::Exit(output::die(&mut self.output, args)?)),
+            name if self.registry.function(name).is_some() => {
+                self.call_registered_function(name, args)
+            }
+            name => match builtins::call_file_function(name, args, &mut *self.filesystem) {
+                Some(result) => Ok(result?),
+                None => Err(VmError::undefined_function(name)),
+            },
         }
-
-        // Prepend PHP opening tag if not present
-        let php_code = if code.trim_start().starts_with("<?php") {
-            code
-        } else {
-            format!("<?php {}", code)
-        };
-
-        // Lex the code
-        let mut lexer = Lexer::new(&php_code);
-        let tokens = lexer
-            .tokenize()
-            .map_err(|e| format!("Parse error: {}", e))?;
-
-        // Parse the tokens
-        let mut parser = Parser::new(tokens);
-        let program = parser.parse().map_err(|e| format!("Parse error: {}", e))?;
-
-        // Compile the program
-        let compiler = Compiler::with_file_path("<eval>".to_string(), "<eval>".to_string());
-        let compilation = compiler
-            .compile_program(&program)
-            .map_err(|e| format!("Compile error: {}", e))?;
-
-        // Execute the compiled code in the current scope
-        self.execute_simple_function(&compilation.main)
-            .map_err(|e| format!("Runtime error: {}", e))
     }
-
     /// Execute a function's bytecode without using the full VM loop
     /// This is used by require() to execute file bytecode
-    fn execute_simple_function(
+    pub(super) fn execute_simple_function(
         &mut self,
         function: &std::sync::Arc<crate::vm::opcode::CompiledFunction>,
-    ) -> Result<Value, String> {
-        use crate::vm::frame::CallFrame;
+    ) -> Result<Value, VmError> {
+        self.run_nested(|vm| vm.run_simple_function(function))
+    }
 
-        let stack_base = self.stack.len();
-        let frame = CallFrame::new(function.clone(), stack_base);
-        self.frames.push(frame);
+    fn run_simple_function(
+        &mut self,
+        function: &std::sync::Arc<crate::vm::opcode::CompiledFunction>,
+    ) -> Result<Value, VmError> {
+        use crate::vm::execution::ControlFlow;
+        use crate::vm::frame::{CallFrame, Completion};
+
+        let frame = CallFrame::new(function.clone(), self.stack.len());
+        self.push_frame(frame);
+        let depth = self.frames.len();
 
         loop {
-            // Check if we've returned to caller (our frame was popped)
-            if self.frames.len() <= stack_base.saturating_sub(1) {
-                return Ok(self.stack.pop().unwrap_or(Value::Null));
+            if self.frames.len() < depth {
+                // An exception left the code for a caller's catch block
+                return Err(VmError::Thrown);
             }
-
-            let frame = match self.frames.last_mut() {
-                Some(f) => f,
-                None => {
-                    return Ok(self.stack.pop().unwrap_or(Value::Null));
-                }
-            };
-
+            let frame = self
+                .frames
+                .last_mut()
+                .ok_or_else(|| VmError::fatal("No call frame available"))?;
             if frame.ip >= frame.function.bytecode.len() {
                 let returned = self.stack.pop().unwrap_or(Value::Null);
-                self.frames.pop();
-                return Ok(returned);
+                self.pop_frame();
+                if self.frames.len() < depth {
+                    return Ok(returned);
+                }
+                self.stack.push(returned);
+                continue;
             }
 
             let opcode = frame.function.bytecode[frame.ip].clone();
             frame.ip += 1;
 
             match self.execute_opcode(opcode) {
-                Ok(()) => {}
-                Err(e) => {
-                    if e.starts_with("__RETURN__") {
-                        let returned = if e == "__RETURN__" {
-                            self.stack.pop().unwrap_or(Value::Null)
-                        } else {
-                            let value_str = e.strip_prefix("__RETURN__").unwrap();
-                            if value_str == "null" {
-                                Value::Null
-                            } else {
-                                self.stack.pop().unwrap_or(Value::Null)
-                            }
-                        };
-                        self.frames.pop();
+                Ok(ControlFlow::Next) => {}
+                Ok(ControlFlow::Return) => {
+                    let Some(returned) = super::execution::finish_return(self)? else {
+                        // A finally block runs first
+                        continue;
+                    };
+                    if self.frames.len() < depth {
                         return Ok(returned);
-                    } else if e.starts_with("__BREAK__") {
-                        return Err("Cannot break outside of loop".to_string());
-                    } else if e.starts_with("__CONTINUE__") {
-                        return Err("Cannot continue outside of loop".to_string());
-                    } else if e.starts_with("__EXCEPTION__") {
-                        return Err(e);
-                    } else if e == "__FINALLY_RETURN__" {
-                        if let Some(value) = self.pending_return.take() {
-                            self.frames.pop();
-                            return Ok(value);
-                        }
-                    } else {
-                        return Err(e);
                     }
+                    self.stack.push(returned);
                 }
-            }
-        }
-    }
-
-    /// Load a class using PSR-4 autoloading
-    ///
-    /// This function finds the PSR-4 mapping for the given class name,
-    /// reads the corresponding file, compiles it, and registers the class.
-    ///
-    /// # Arguments
-    /// * `class_name` - The fully qualified class name
-    ///
-    /// # Returns
-    /// Ok(true) if the class was loaded, Ok(false) if no mapping was found,
-    /// or an error if loading failed
-    pub fn load_psr4_class(&mut self, class_name: &str) -> Result<bool, String> {
-        use crate::runtime::builtins::spl;
-        use crate::vm::compiler::Compiler;
-
-        let normalized = spl::normalize_class_name(class_name);
-
-        if let Some((prefix, base_dir)) = spl::find_psr4_mapping(&normalized) {
-            let file_path = spl::namespace_to_path(&normalized, &base_dir, &prefix);
-
-            let source = match std::fs::read_to_string(&file_path) {
-                Ok(content) => content,
-                Err(e) => {
-                    return Err(format!(
-                        "load_psr4_class(): Failed to open '{}': {}",
-                        file_path, e
-                    ));
+                Ok(ControlFlow::Break) => super::ops::execute_loop_jump(self, Completion::Break)?,
+                Ok(ControlFlow::Continue) => {
+                    super::ops::execute_loop_jump(self, Completion::Continue)?
                 }
-            };
-
-            let mut lexer = crate::lexer::Lexer::new(&source);
-            let tokens = lexer
-                .tokenize()
-                .map_err(|e| format!("Lexing error in {}: {}", file_path, e))?;
-
-            let mut parser = crate::parser::Parser::new(tokens);
-            let program = parser
-                .parse()
-                .map_err(|e| format!("Parse error in {}: {}", file_path, e))?;
-
-            let compiler = Compiler::new(file_path.clone());
-            let compilation = compiler
-                .compile_program(&program)
-                .map_err(|e| format!("Compilation error in {}: {}", file_path, e))?;
-
-            for (name, func) in compilation.functions {
-                self.functions.insert(name, func);
-            }
-
-            for (name, class) in compilation.classes {
-                self.classes.insert(name, class);
-            }
-
-            for (name, interface) in compilation.interfaces {
-                self.interfaces.insert(name, interface);
-            }
-
-            for (name, trait_) in compilation.traits {
-                self.traits.insert(name, trait_);
-            }
-
-            for (name, enum_) in compilation.enums {
-                self.enums.insert(name, enum_);
-            }
-
-            // Execute the file's main function
-            if let Err(e) = self.execute_simple_function(&compilation.main) {
-                return Err(format!("Runtime error in {}: {}", file_path, e));
+                Ok(flow @ (ControlFlow::Yield | ControlFlow::Suspend)) => {
+                    super::ops::throw_raised(self, flow.outside_its_loop())?
+                }
+                Err(VmError::Thrown) => {}
+                Err(e) => super::ops::throw_raised(self, e)?,
             }
-
-            return Ok(true);
         }
-
-        Ok(false)
     }
 }
@@ -0,0 +1,40 @@
+//! highlight_file and its alias show_source
+//!
+//! They need the VM to read the file and to warn when it can't; the
+//! highlighting is in `runtime::builtins::highlight`.
+
+use super::VM;
+use crate::runtime::builtins::highlight;
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::notices::Level;
+use std::io::Write;
+
+impl<W: Write> VM<W> {
+    /// `highlight_file($filename, $return = false)`: false with warnings
+    /// when the file can't be read
+    pub(crate) fn highlight_file(&mut self, name: &str, args: &[Value]) -> Result<Value, VmError> {
+        check_arity(name, args.len(), 1, Some(2))?;
+        let filename = args[0].to_string_val();
+        let source = match self.filesystem.read_to_string(&filename) {
+            Ok(source) => source,
+            Err(_) => {
+                let message = format!(
+                    "{}({}): Failed to open stream: No such file or directory",
+                    name, filename
+                );
+                self.raise(Level::Warning, &message)?;
+                let message = format!("{}(): Failed opening '{}' for highlighting", name, filename);
+                self.raise(Level::Warning, &message)?;
+                return Ok(Value::Bool(false));
+            }
+        };
+        let return_output = args.get(1).is_some_and(Value::to_bool);
+        Ok(highlight::output_highlighted(
+            &mut self.output,
+            &source,
+            return_output,
+        )?)
+    }
+}
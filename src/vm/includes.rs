@@ -0,0 +1,300 @@
+//! require, require_once, eval() and PSR-4 class loading: compiling
+//! more code into the running script
+//!
+//! A file's functions and classes join the VM's (the first declaration
+//! of a name wins for `require`), then its main code runs in a nested
+//! run loop, like `eval()`'d code does.
+
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::VM;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use thread_local::ThreadLocal;
+
+lazy_static::lazy_static! {
+    /// Files `require_once` ran, per thread like the script running them
+    static ref REQUIRED_FILES: ThreadLocal<Mutex<HashSet<String>>> = ThreadLocal::new();
+}
+
+/// This thread's required files
+fn required_files() -> &'static Mutex<HashSet<String>> {
+    REQUIRED_FILES.get_or_default()
+}
+
+/// Clear required files registry (useful for testing)
+pub fn clear_required_files() {
+    let mut required = required_files().lock().unwrap();
+    required.clear();
+}
+
+/// An error from code a file or `eval()` ran, after `context` if it is a
+/// fatal error
+fn runtime_error(context: &str, e: VmError) -> VmError {
+    match e {
+        VmError::Fatal(message) => VmError::Fatal(format!("{}: {}", context, message)),
+        e => e,
+    }
+}
+
+impl<W: std::io::Write> VM<W> {
+    /// require - Include and evaluate a PHP file
+    /// Returns the return value of the included file, or false on failure
+    pub fn require(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        if args.is_empty() {
+            return Err(VmError::argument_count_error(
+                "require() expects at least 1 argument",
+            ));
+        }
+
+        let filename = args[0].to_string_val();
+
+        let source = match self.filesystem.read_to_string(&filename) {
+            Ok(content) => content,
+            Err(e) => {
+                return Err(VmError::fatal(format!(
+                    "require(): Failed to open '{}': {}",
+                    filename, e
+                )));
+            }
+        };
+
+        let compilation = self.compile_file(&filename, &source)?;
+        self.report_compile_deprecations(&compilation.deprecations)?;
+
+        for (name, func) in compilation.functions {
+            self.functions.get_or_insert_with(name, || func);
+        }
+
+        for (name, class) in compilation.classes {
+            self.classes.get_or_insert_with(name, || class);
+        }
+
+        for (name, interface) in compilation.interfaces {
+            self.interfaces.get_or_insert_with(name, || interface);
+        }
+
+        for (name, trait_) in compilation.traits {
+            self.traits.get_or_insert_with(name, || trait_);
+        }
+
+        for (name, enum_) in compilation.enums {
+            self.enums.get_or_insert_with(name, || enum_);
+        }
+
+        // Execute the file's main function
+        let result = self.execute_simple_function(&compilation.main);
+        result.map_err(|e| runtime_error(&format!("Runtime error in {}", filename), e))
+    }
+
+    /// Lex, parse and compile the `source` read from `filename`, or load
+    /// its compilation from the bytecode cache
+    fn compile_file(
+        &self,
+        filename: &str,
+        source: &str,
+    ) -> Result<crate::vm::compiler::CompilationResult, VmError> {
+        use crate::lexer::Lexer;
+        use crate::parser::Parser;
+        use crate::vm::compiler::Compiler;
+
+        let file_path = self.source_path(filename);
+        let cache = self.bytecode_cache.as_ref();
+        if let Some(compilation) = cache.and_then(|cache| cache.load(filename, &file_path, source))
+        {
+            return Ok(compilation);
+        }
+
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer
+            .tokenize()
+            .map_err(|e| VmError::fatal(format!("Lexing error in {}: {}", filename, e)))?;
+
+        let mut parser = Parser::new(tokens);
+        let program = parser
+            .parse()
+            .map_err(|e| VmError::fatal(format!("Parse error in {}: {}", filename, e)))?;
+
+        let compiler = Compiler::with_file_path(filename.to_string(), file_path.clone());
+        let compilation = compiler
+            .compile_program(&program)
+            .map_err(|e| VmError::fatal(format!("Compilation error in {}: {}", filename, e)))?;
+        if let Some(cache) = cache {
+            // A cache that can't be written only costs the time it saves
+            let _ = cache.store(&file_path, source, &compilation);
+        }
+        Ok(compilation)
+    }
+
+    /// The path code read from `filename` is attributed to: the canonical
+    /// path where the filesystem has one, so that traces, coverage, the
+    /// profiler and breakpoints name a file the same way however it was
+    /// required
+    fn source_path(&self, filename: &str) -> String {
+        self.filesystem
+            .canonicalize(std::path::Path::new(filename))
+            .and_then(|path| path.to_str().map(str::to_string))
+            .unwrap_or_else(|| filename.to_string())
+    }
+
+    /// require_once - Include and evaluate a PHP file only once
+    /// Returns the return value of the included file, or false on failure
+    /// If the file has already been included, returns true without re-including
+    pub fn require_once(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        if args.is_empty() {
+            return Err(VmError::argument_count_error(
+                "require_once() expects at least 1 argument",
+            ));
+        }
+
+        let filename = self.source_path(&args[0].to_string_val());
+
+        // Check if already required
+        let required = required_files().lock().unwrap();
+        if required.contains(&filename) {
+            drop(required);
+            return Ok(Value::Bool(true));
+        }
+        drop(required);
+
+        // Mark as required before including (in case of error, still marked)
+        {
+            let mut required = required_files().lock().unwrap();
+            required.insert(filename.clone());
+        }
+
+        // Call require to do the actual inclusion
+        self.require(args)
+    }
+
+    /// eval - Execute a string as PHP code
+    ///
+    /// This function takes a string of PHP code, parses and compiles it,
+    /// then executes it in the current scope. Variables defined in the
+    /// eval'd code will be available in the calling scope.
+    ///
+    /// # Arguments
+    /// * `code` - The PHP code string to execute
+    ///
+    /// # Returns
+    /// The return value of the evaluated code (last expression), or 1 if
+    /// no return value is specified. Returns null for empty code.
+    ///
+    /// # Errors
+    /// Returns an error string if parsing or execution fails.
+    pub fn eval(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        use crate::lexer::Lexer;
+        use crate::parser::Parser;
+        use crate::vm::compiler::Compiler;
+
+        if args.is_empty() {
+            return Err(VmError::argument_count_error(
+                "eval() expects exactly 1 parameter",
+            ));
+        }
+
+        let code = args[0].to_string_val();
+
+        // Empty string returns null
+        if code.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+
+        // Prepend PHP opening tag if not present
+        let php_code = if code.trim_start().starts_with("<?php") {
+            code
+        } else {
+            format!("<?php {}", code)
+        };
+
+        // Lex the code
+        let mut lexer = Lexer::new(&php_code);
+        let tokens = lexer
+            .tokenize()
+            .map_err(|e| VmError::fatal(format!("Parse error: {}", e)))?;
+
+        // Parse the tokens
+        let mut parser = Parser::new(tokens);
+        let program = parser
+            .parse()
+            .map_err(|e| VmError::fatal(format!("Parse error: {}", e)))?;
+
+        // Compile the program, attributed to the line that evaluates it
+        let file_path = match self.current_location() {
+            Some(location) => format!("{}({}) : eval()'d code", location.file, location.line),
+            None => "<eval>".to_string(),
+        };
+        let compiler = Compiler::with_file_path("<eval>".to_string(), file_path);
+        let compilation = compiler
+            .compile_program(&program)
+            .map_err(|e| VmError::fatal(format!("Compile error: {}", e)))?;
+        self.report_compile_deprecations(&compilation.deprecations)?;
+
+        // Execute the compiled code in the current scope
+        self.execute_simple_function(&compilation.main)
+            .map_err(|e| runtime_error("Runtime error", e))
+    }
+
+    /// Load a class using PSR-4 autoloading
+    ///
+    /// This function finds the PSR-4 mapping for the given class name,
+    /// reads the corresponding file, compiles it, and registers the class.
+    ///
+    /// # Arguments
+    /// * `class_name` - The fully qualified class name
+    ///
+    /// # Returns
+    /// Ok(true) if the class was loaded, Ok(false) if no mapping was found,
+    /// or an error if loading failed
+    pub fn load_psr4_class(&mut self, class_name: &str) -> Result<bool, VmError> {
+        use crate::runtime::builtins::spl;
+
+        let normalized = spl::normalize_class_name(class_name);
+
+        if let Some((prefix, base_dir)) = spl::find_psr4_mapping(&normalized) {
+            let file_path = spl::namespace_to_path(&normalized, &base_dir, &prefix);
+
+            let source = match self.filesystem.read_to_string(&file_path) {
+                Ok(content) => content,
+                Err(e) => {
+                    return Err(VmError::fatal(format!(
+                        "load_psr4_class(): Failed to open '{}': {}",
+                        file_path, e
+                    )));
+                }
+            };
+
+            let compilation = self.compile_file(&file_path, &source)?;
+            self.report_compile_deprecations(&compilation.deprecations)?;
+
+            for (name, func) in compilation.functions {
+                self.functions.insert(name, func);
+            }
+
+            for (name, class) in compilation.classes {
+                self.classes.insert(name, class);
+            }
+
+            for (name, interface) in compilation.interfaces {
+                self.interfaces.insert(name, interface);
+            }
+
+            for (name, trait_) in compilation.traits {
+                self.traits.insert(name, trait_);
+            }
+
+            for (name, enum_) in compilation.enums {
+                self.enums.insert(name, enum_);
+            }
+
+            // Execute the file's main function
+            if let Err(e) = self.execute_simple_function(&compilation.main) {
+                return Err(runtime_error(&format!("Runtime error in {}", file_path), e));
+            }
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
@@ -0,0 +1,53 @@
+//! parse_ini_string and parse_ini_file
+//!
+//! The VM supplies the constants values may name (its extensions' and
+//! the error levels) and raises syntax errors as warnings; the parsing
+//! itself is in `runtime::builtins::ini_parser`.
+
+use super::VM;
+use crate::runtime::builtins::ini;
+use crate::runtime::builtins::ini_parser::{self, ScannerMode};
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::notices::Level;
+use std::io::Write;
+
+impl<W: Write> VM<W> {
+    /// `parse_ini_string($ini, $process_sections = false, $scanner_mode
+    /// = INI_SCANNER_NORMAL)` and `parse_ini_file($filename, ...)`
+    pub(crate) fn parse_ini(&mut self, name: &str, args: &[Value]) -> Result<Value, VmError> {
+        check_arity(name, args.len(), 1, Some(3))?;
+        let sections = args.get(1).is_some_and(Value::to_bool);
+        let mode = args.get(2).map_or(0, Value::to_int);
+        let Some(mode) = ScannerMode::from_int(mode) else {
+            return Err(VmError::value_error(format!(
+                "{}(): Argument #3 ($scanner_mode) must be one of INI_SCANNER_NORMAL, INI_SCANNER_RAW, or INI_SCANNER_TYPED",
+                name
+            )));
+        };
+        let (source, file) = if name == "parse_ini_file" {
+            let filename = args[0].to_string_val();
+            match self.filesystem.read_to_string(&filename) {
+                Ok(source) => (source, filename),
+                Err(_) => return Ok(Value::Bool(false)),
+            }
+        } else {
+            (args[0].to_string_val(), "Unknown".to_string())
+        };
+        let registry = &self.registry;
+        let constant = |name: &str| {
+            registry
+                .constant(name)
+                .cloned()
+                .or_else(|| ini::error_constant(name))
+        };
+        match ini_parser::parse(&source, sections, mode, &constant) {
+            Ok(entries) => Ok(Value::Array(entries)),
+            Err(error) => {
+                self.raise(Level::Warning, &error.describe(&file))?;
+                Ok(Value::Bool(false))
+            }
+        }
+    }
+}
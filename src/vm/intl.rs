@@ -0,0 +1,274 @@
+//! The intl classes, NumberFormatter and Collator
+//!
+//! Their methods are stubs calling the procedural functions in
+//! `runtime::builtins::intl` and `runtime::builtins::collator` with
+//! `$this` first. A constructor calls the `*_create()` function and
+//! copies the state of the object it gives onto `$this`; the setters
+//! update that state in place.
+
+use crate::ast::Visibility;
+use crate::runtime::builtins::{collator, intl};
+use crate::runtime::Value;
+use crate::vm::class::{CompiledClass, CompiledProperty};
+use crate::vm::name_table::NameTable;
+use crate::vm::opcode::{CompiledFunction, Opcode};
+use std::sync::Arc;
+
+/// The intl classes: name, function creating an instance, constructor
+/// parameters, how many are required, and internal properties
+type IntlClassSpec = (
+    &'static str,
+    &'static str,
+    &'static [&'static str],
+    u8,
+    &'static [&'static str],
+);
+
+const CLASSES: &[IntlClassSpec] = &[
+    (
+        "NumberFormatter",
+        "numfmt_create",
+        &["locale", "style", "pattern"],
+        2,
+        &["__locale", "__style", "__attributes"],
+    ),
+    (
+        "Collator",
+        "collator_create",
+        &["locale"],
+        1,
+        &["__locale", "__strength"],
+    ),
+];
+
+/// The methods calling a function: class, method, function, parameters
+/// and how many are required
+const METHODS: &[(&str, &str, &str, &[&str], u8)] = &[
+    (
+        "NumberFormatter",
+        "format",
+        "numfmt_format",
+        &["num", "type"],
+        1,
+    ),
+    (
+        "NumberFormatter",
+        "formatCurrency",
+        "numfmt_format_currency",
+        &["amount", "currency"],
+        2,
+    ),
+    (
+        "NumberFormatter",
+        "parse",
+        "numfmt_parse",
+        &["string", "type"],
+        1,
+    ),
+    (
+        "NumberFormatter",
+        "getAttribute",
+        "numfmt_get_attribute",
+        &["attribute"],
+        1,
+    ),
+    (
+        "NumberFormatter",
+        "getLocale",
+        "numfmt_get_locale",
+        &["type"],
+        0,
+    ),
+    (
+        "Collator",
+        "compare",
+        "collator_compare",
+        &["string1", "string2"],
+        2,
+    ),
+    ("Collator", "sort", "collator_sort", &["array", "flags"], 1),
+    (
+        "Collator",
+        "asort",
+        "collator_asort",
+        &["array", "flags"],
+        1,
+    ),
+    ("Collator", "getStrength", "collator_get_strength", &[], 0),
+    ("Collator", "getLocale", "collator_get_locale", &["type"], 0),
+];
+
+/// The setters: class, method, parameters and the property they update.
+/// With two parameters the property is an array set at the first.
+const SETTERS: &[(&str, &str, &[&str], &str)] = &[
+    (
+        "NumberFormatter",
+        "setAttribute",
+        &["attribute", "value"],
+        "__attributes",
+    ),
+    ("Collator", "setStrength", &["strength"], "__strength"),
+];
+
+/// The class constants
+const CONSTANTS: &[(&str, &str, i64)] = &[
+    ("NumberFormatter", "DECIMAL", intl::DECIMAL),
+    ("NumberFormatter", "CURRENCY", intl::CURRENCY),
+    ("NumberFormatter", "PERCENT", intl::PERCENT),
+    ("NumberFormatter", "GROUPING_USED", intl::GROUPING_USED),
+    (
+        "NumberFormatter",
+        "MAX_FRACTION_DIGITS",
+        intl::MAX_FRACTION_DIGITS,
+    ),
+    (
+        "NumberFormatter",
+        "MIN_FRACTION_DIGITS",
+        intl::MIN_FRACTION_DIGITS,
+    ),
+    ("NumberFormatter", "FRACTION_DIGITS", intl::FRACTION_DIGITS),
+    ("NumberFormatter", "TYPE_DEFAULT", intl::TYPE_DEFAULT),
+    ("NumberFormatter", "TYPE_INT32", intl::TYPE_INT32),
+    ("NumberFormatter", "TYPE_INT64", intl::TYPE_INT64),
+    ("NumberFormatter", "TYPE_DOUBLE", intl::TYPE_DOUBLE),
+    ("Collator", "PRIMARY", collator::PRIMARY),
+    ("Collator", "SECONDARY", collator::SECONDARY),
+    ("Collator", "TERTIARY", collator::TERTIARY),
+    ("Collator", "DEFAULT_STRENGTH", collator::TERTIARY),
+    ("Collator", "SORT_REGULAR", collator::SORT_REGULAR),
+    ("Collator", "SORT_STRING", collator::SORT_STRING),
+    ("Collator", "SORT_NUMERIC", collator::SORT_NUMERIC),
+];
+
+/// A method with `$this` and its parameters as locals
+fn method(class_name: &str, method_name: &str, params: &[&str], required: u8) -> CompiledFunction {
+    let mut method = CompiledFunction::new(format!("{}::{}", class_name, method_name));
+    method.param_count = params.len() as u8;
+    method.required_param_count = required;
+    method.local_names = std::iter::once("this")
+        .chain(params.iter().copied())
+        .map(str::to_string)
+        .collect();
+    method.local_count = method.local_names.len() as u16;
+    method
+}
+
+pub fn register_intl_classes(classes: &mut NameTable<Arc<CompiledClass>>) {
+    for (name, create, params, required, properties) in CLASSES {
+        let mut class = CompiledClass::new(name.to_string());
+        for prop_name in *properties {
+            class.properties.push(CompiledProperty {
+                name: prop_name.to_string(),
+                visibility: Visibility::Private,
+                write_visibility: None,
+                default: Some(Value::Null),
+                readonly: false,
+                is_static: false,
+                type_hint: None,
+                attributes: Vec::new(),
+                get_hook: None,
+                set_hook: None,
+            });
+        }
+
+        let mut construct = method(name, "__construct", params, *required);
+        construct.strings.push(create.to_string());
+        construct
+            .strings
+            .extend(properties.iter().map(|p| p.to_string()));
+        construct
+            .bytecode
+            .extend((1..=params.len() as u16).map(Opcode::LoadFast));
+        construct
+            .bytecode
+            .push(Opcode::CallBuiltin(0, params.len() as u8));
+        for index in 1..=properties.len() as u32 {
+            construct.bytecode.extend([
+                Opcode::Dup,
+                Opcode::LoadProperty(index),
+                Opcode::StoreThisProperty(index),
+                Opcode::Pop,
+            ]);
+        }
+        construct.bytecode.extend([Opcode::Pop, Opcode::ReturnNull]);
+        class.method_order.push("__construct".to_string());
+        class
+            .methods
+            .insert("__construct".to_string(), Arc::new(construct));
+
+        // `create()` is the static factory
+        let mut factory = method(name, "create", params, *required);
+        factory.local_names.remove(0);
+        factory.local_count -= 1;
+        factory.strings.push(create.to_string());
+        factory
+            .bytecode
+            .extend((0..params.len() as u16).map(Opcode::LoadFast));
+        factory
+            .bytecode
+            .extend([Opcode::CallBuiltin(0, params.len() as u8), Opcode::Return]);
+        class
+            .static_methods
+            .insert("create".to_string(), Arc::new(factory));
+
+        for (class_name, constant, value) in CONSTANTS {
+            if class_name == name {
+                class
+                    .constants
+                    .insert(constant.to_string(), Value::Integer(*value));
+                class.constant_order.push(constant.to_string());
+            }
+        }
+        classes.insert(name.to_string(), Arc::new(class));
+    }
+
+    for (class_name, method_name, function, params, required) in METHODS {
+        let mut stub = method(class_name, method_name, params, *required);
+        stub.strings.push(function.to_string());
+        stub.bytecode
+            .extend((0..=params.len() as u16).map(Opcode::LoadFast));
+        stub.bytecode.extend([
+            Opcode::CallBuiltin(0, params.len() as u8 + 1),
+            Opcode::Return,
+        ]);
+        add_method(classes, class_name, method_name, stub);
+    }
+
+    for (class_name, method_name, params, prop_name) in SETTERS {
+        let mut setter = method(class_name, method_name, params, params.len() as u8);
+        setter.strings.push(prop_name.to_string());
+        if params.len() == 2 {
+            setter.bytecode.extend([
+                Opcode::LoadThis,
+                Opcode::LoadProperty(0),
+                Opcode::LoadFast(1),
+                Opcode::LoadFast(2),
+                Opcode::ArraySet,
+            ]);
+        } else {
+            setter.bytecode.push(Opcode::LoadFast(1));
+        }
+        setter.bytecode.extend([
+            Opcode::StoreThisProperty(0),
+            Opcode::Pop,
+            Opcode::PushTrue,
+            Opcode::Return,
+        ]);
+        add_method(classes, class_name, method_name, setter);
+    }
+}
+
+fn add_method(
+    classes: &mut NameTable<Arc<CompiledClass>>,
+    class_name: &str,
+    method_name: &str,
+    method: CompiledFunction,
+) {
+    if let Some(class) = classes.get_mut(class_name) {
+        let class = Arc::make_mut(class);
+        class.method_order.push(method_name.to_string());
+        class
+            .methods
+            .insert(method_name.to_string(), Arc::new(method));
+    }
+}
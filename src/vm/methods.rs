@@ -5,9 +5,12 @@
 //! - Resolving static and instance methods
 //! - Calling methods synchronously (for magic methods like __toString)
 //! - Value to string conversion (with __toString support)
+//! - Counting Countable objects and converting objects to scalars
 
-use crate::runtime::ObjectInstance;
-use crate::vm::frame::CallFrame;
+use crate::runtime::ObjectRef;
+use crate::vm::error::VmError;
+use crate::vm::execution::ControlFlow;
+use crate::vm::frame::{CallFrame, Completion};
 use crate::vm::opcode::CompiledFunction;
 use std::io::Write;
 use std::sync::Arc;
@@ -106,24 +109,35 @@ impl<W: Write> super::VM<W> {
     /// This is used for magic methods like __toString that need immediate evaluation
     pub fn call_method_sync(
         &mut self,
-        instance: ObjectInstance,
+        instance: ObjectRef,
         method: Arc<CompiledFunction>,
-    ) -> Result<crate::runtime::Value, String> {
-        // Save current frame count to know when to stop
-        let initial_frame_count = self.frames.len();
+    ) -> Result<crate::runtime::Value, VmError> {
+        self.run_nested(|vm| vm.run_method_sync(instance, method))
+    }
 
+    fn run_method_sync(
+        &mut self,
+        instance: ObjectRef,
+        method: Arc<CompiledFunction>,
+    ) -> Result<crate::runtime::Value, VmError> {
         // Create call frame
         let mut frame = CallFrame::new(method.clone(), self.stack.len());
         frame.locals[0] = crate::runtime::Value::Object(instance); // Set $this
 
-        // Push frame
-        self.frames.push(frame);
+        self.push_frame(frame);
+        self.run_frame()
+    }
+
+    /// Execute the frame on top until it returns, and give what it
+    /// returns
+    pub(crate) fn run_frame(&mut self) -> Result<crate::runtime::Value, VmError> {
+        let depth = self.frames.len();
 
-        // Execute until we return to original frame level
+        // Execute until the frame is gone
         loop {
-            // Check if we've returned to original frame level
-            if self.frames.len() <= initial_frame_count {
-                return Ok(self.stack.pop().unwrap_or(crate::runtime::Value::Null));
+            if self.frames.len() < depth {
+                // An exception left the frame for a caller's catch block
+                return Err(VmError::Thrown);
             }
 
             // Get frame info without holding borrow
@@ -135,9 +149,9 @@ impl<W: Write> super::VM<W> {
             // Check if current frame is done
             if ip >= bytecode_len {
                 let returned = self.stack.pop().unwrap_or(crate::runtime::Value::Null);
-                self.frames.pop();
+                self.pop_frame();
 
-                if self.frames.len() <= initial_frame_count {
+                if self.frames.len() < depth {
                     return Ok(returned);
                 }
 
@@ -153,65 +167,92 @@ impl<W: Write> super::VM<W> {
                 op
             };
 
-            // Handle return separately since execute_opcode returns Err for returns
             match self.execute_opcode(opcode) {
-                Ok(()) => {}
-                Err(e) if e == "__RETURN__" => {
-                    // Return with value on stack
-                    let returned = self.stack.pop().unwrap_or(crate::runtime::Value::Null);
-                    self.frames.pop();
-
-                    if self.frames.len() <= initial_frame_count {
+                Ok(ControlFlow::Next) => {}
+                Ok(ControlFlow::Return) => {
+                    let Some(returned) = super::execution::finish_return(self)? else {
+                        // A finally block runs first
+                        continue;
+                    };
+                    if self.frames.len() < depth {
                         return Ok(returned);
                     }
                     self.stack.push(returned);
                 }
-                Err(e) if e == "__RETURN__null" => {
-                    // Return null
-                    self.frames.pop();
-
-                    if self.frames.len() <= initial_frame_count {
-                        return Ok(crate::runtime::Value::Null);
-                    }
-                    self.stack.push(crate::runtime::Value::Null);
+                Ok(ControlFlow::Break) => super::ops::execute_loop_jump(self, Completion::Break)?,
+                Ok(ControlFlow::Continue) => {
+                    super::ops::execute_loop_jump(self, Completion::Continue)?
                 }
-                Err(e) if e == "__GENERATOR__" => {
-                    // Generator yield - return generator object
-                    let generator = self.stack.pop().unwrap_or(crate::runtime::Value::Null);
-                    self.frames.pop();
-
-                    if self.frames.len() <= initial_frame_count {
-                        return Ok(generator);
-                    }
-                    self.stack.push(generator);
+                Ok(flow @ (ControlFlow::Yield | ControlFlow::Suspend)) => {
+                    super::ops::throw_raised(self, flow.outside_its_loop())?
                 }
-                Err(e) => return Err(e),
+                Err(VmError::Thrown) => {}
+                Err(e) => super::ops::throw_raised(self, e)?,
             }
         }
     }
 
     /// Convert a value to string, calling __toString for objects if available
-    pub fn value_to_string(&mut self, value: crate::runtime::Value) -> Result<String, String> {
+    pub fn value_to_string(&mut self, value: crate::runtime::Value) -> Result<String, VmError> {
         match value {
             crate::runtime::Value::Object(ref instance) => {
-                let class_name = instance.class_name.clone();
+                let class_name = instance.class_name().to_string();
                 // Look for __toString method
                 if let Some(to_string_method) = self.find_method_in_chain(&class_name, "__toString")
                 {
                     let result = self.call_method_sync(instance.clone(), to_string_method)?;
                     match result {
                         crate::runtime::Value::String(s) => Ok(s),
-                        _ => Err("__toString must return a string".to_string()),
+                        _ => Err(VmError::fatal("__toString must return a string")),
                     }
                 } else {
                     // No __toString method - this is an error in PHP
-                    Err(format!(
+                    Err(VmError::fatal(format!(
                         "Object of class {} could not be converted to string",
                         class_name
-                    ))
+                    )))
                 }
             }
             _ => Ok(value.to_string_val()),
         }
     }
+
+    /// `count()` of an object: what its `count()` method gives, as for a
+    /// Countable object, or `None` if it has none
+    pub(crate) fn count_object(
+        &mut self,
+        value: &crate::runtime::Value,
+    ) -> Result<Option<crate::runtime::Value>, VmError> {
+        let crate::runtime::Value::Object(instance) = value else {
+            return Ok(None);
+        };
+        match self.find_method_in_chain(instance.class_name(), "count") {
+            Some(method) => {
+                let count = self.call_method_sync(instance.clone(), method)?;
+                Ok(Some(crate::runtime::Value::Integer(count.to_int())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The value `strval()`, `intval()` or `floatval()` converts for an
+    /// object: the string `__toString()` gives for `strval()`, and for
+    /// the numbers of a SimpleXMLElement, which converts through its text
+    pub(crate) fn object_scalar(
+        &mut self,
+        function: &str,
+        object: crate::runtime::Value,
+    ) -> Result<crate::runtime::Value, VmError> {
+        let converts = match &object {
+            crate::runtime::Value::Object(instance) => {
+                function == "strval" || instance.class_name() == "SimpleXMLElement"
+            }
+            _ => false,
+        };
+        if converts {
+            Ok(crate::runtime::Value::String(self.value_to_string(object)?))
+        } else {
+            Ok(object)
+        }
+    }
 }
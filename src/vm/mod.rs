@@ -6,34 +6,68 @@
 
 pub mod autoload;
 pub mod builtins;
+pub mod cache;
 pub mod class;
 pub mod class_registration;
 pub mod compiled_types;
 pub mod compiler;
+pub mod coverage;
+pub mod deadline;
+pub mod debug;
+pub mod deprecation;
+pub mod error;
+pub mod error_location;
 pub mod execution;
+pub mod extension;
 pub mod frame;
+pub mod intl;
 pub mod methods;
+pub mod notices;
 pub mod objects;
 pub mod opcode;
+pub mod panics;
+pub mod profiler;
 pub mod reflection;
+pub mod sandbox;
+pub mod shutdown;
 pub mod spl_interfaces;
-
+pub mod stats;
+pub mod superglobals;
+pub mod tokenizer;
+pub mod trace;
+
+mod array_callbacks;
+mod callables;
+mod dispatch;
+mod event_loop;
+mod fiber;
+mod globals_array;
 mod helpers;
+mod highlight;
+mod includes;
+mod ini_files;
+pub mod name_table;
 mod ops;
 mod type_validation;
+mod typed_properties;
+mod xml;
+mod yaml;
 
-pub use helpers::clear_required_files;
+pub use deadline::TIME_LIMIT_ERROR;
+pub use error::RuntimeError;
+pub use includes::clear_required_files;
 
+use crate::runtime::filesystem::{self, Filesystem};
 use crate::runtime::Value;
 use class::{CompiledClass, CompiledEnum, CompiledInterface, CompiledTrait};
-use frame::{CallFrame, ExceptionHandler, LoopContext};
+use frame::{CallFrame, LoopContext};
+use name_table::NameTable;
 use opcode::{CompiledFunction, Opcode};
 use std::collections::HashMap;
 use std::io::Write;
 use std::sync::Arc;
 
 /// The bytecode virtual machine
-#[allow(dead_code)] // current_fiber field not yet used
 pub struct VM<W: Write> {
     /// Value stack for operands
     stack: Vec<Value>,
@@ -43,24 +77,69 @@ pub struct VM<W: Write> {
     globals: HashMap<String, Value>,
     /// Loop contexts for break/continue
     loops: Vec<LoopContext>,
-    /// Exception handlers for try/catch/finally
-    handlers: Vec<ExceptionHandler>,
-    /// Pending return value (saved while executing finally block)
-    pending_return: Option<Value>,
-    /// Current running fiber (for Fiber::getCurrent())
-    current_fiber: Option<Value>,
+    /// Callbacks, timers and task futures of the event loop
+    event_loop: event_loop::EventLoop,
+    /// The XML documents SimpleXML and DOM objects stand for nodes of
+    xml_documents: Vec<crate::runtime::builtins::xml::Document>,
+    /// Generators by id, with their suspended bodies
+    generators: HashMap<usize, ops::GeneratorState>,
+    /// Ids of the generators whose bodies are running, innermost last
+    running_generators: Vec<usize>,
+    /// Fibers by id, with their suspended call stacks
+    fibers: HashMap<usize, ops::FiberState>,
+    /// Ids of the fibers running, innermost last
+    running_fibers: Vec<usize>,
+    /// How many nested run loops, which a fiber can't be suspended
+    /// through, are running
+    nested_runs: usize,
     /// Output writer
     output: W,
+    /// Files seen by file I/O built-ins, `require` and autoloading
+    filesystem: Box<dyn Filesystem>,
+    /// Disabled functions and allowed directories
+    sandbox: sandbox::Sandbox,
+    /// Functions, constants and classes added by extensions
+    registry: extension::Registry,
     /// User-defined functions
-    functions: HashMap<String, Arc<CompiledFunction>>,
+    functions: NameTable<Arc<CompiledFunction>>,
     /// Class definitions
-    classes: HashMap<String, Arc<CompiledClass>>,
+    classes: NameTable<Arc<CompiledClass>>,
     /// Interface definitions
-    interfaces: HashMap<String, Arc<CompiledInterface>>,
+    interfaces: NameTable<Arc<CompiledInterface>>,
     /// Trait definitions
-    traits: HashMap<String, Arc<CompiledTrait>>,
+    traits: NameTable<Arc<CompiledTrait>>,
     /// Enum definitions
-    enums: HashMap<String, Arc<CompiledEnum>>,
+    enums: NameTable<Arc<CompiledEnum>>,
+    /// Where required and autoloaded files are compiled to, if anywhere
+    bytecode_cache: Option<cache::BytecodeCache>,
+    /// Time limit set by the host
+    deadline: Option<deadline::Deadline>,
+    /// Time limit from `max_execution_time`, which scripts may change
+    script_deadline: Option<deadline::Deadline>,
+    /// Opcodes executed since the last deadline check
+    ticks: u32,
+    /// Line hits, collected only when coverage is enabled
+    coverage: Option<coverage::Coverage>,
+    /// Attached debugger, called before each statement
+    debugger: Option<Box<dyn debug::DebugHook>>,
+    /// Per-function call statistics, collected only when profiling
+    profiler: Option<profiler::Profiler>,
+    /// Runtime counters, collected only when enabled
+    stats: Option<stats::Stats>,
+    /// File and line where the last uncaught runtime error was raised
+    error_location: Option<error_location::ErrorLocation>,
+    /// Destination of the per-opcode log, when tracing
+    opcode_trace: Option<trace::OpcodeTrace>,
+    /// Callbacks and arguments queued by `register_shutdown_function()`
+    shutdown_functions: Vec<(Value, Vec<Value>)>,
+    /// The error `error_get_last()` returns
+    last_error: Option<notices::LastError>,
+    /// How many `@` expressions are being evaluated
+    silence_depth: usize,
+    /// Where errors go apart from the script output (see `notices`)
+    error_output: Box<dyn Write>,
+    /// The status and headers `header()` and `http_response_code()` set
+    response: superglobals::Response,
 }
 
 impl<W: Write> VM<W> {
@@ -69,27 +148,72 @@ impl<W: Write> VM<W> {
         Self {
             stack: Vec::with_capacity(256),
             frames: Vec::with_capacity(64),
-            globals: HashMap::new(),
+            globals: superglobals::initial_globals(),
             loops: Vec::new(),
-            handlers: Vec::new(),
-            pending_return: None,
-            current_fiber: None,
+            event_loop: event_loop::EventLoop::default(),
+            xml_documents: Vec::new(),
+            generators: HashMap::new(),
+            running_generators: Vec::new(),
+            fibers: HashMap::new(),
+            running_fibers: Vec::new(),
+            nested_runs: 0,
             output,
-            functions: HashMap::new(),
-            classes: HashMap::new(),
-            interfaces: HashMap::new(),
-            traits: HashMap::new(),
-            enums: HashMap::new(),
+            filesystem: filesystem::default_filesystem(),
+            sandbox: sandbox::Sandbox::new(),
+            registry: extension::Registry::new(),
+            functions: NameTable::new(),
+            classes: NameTable::new(),
+            interfaces: NameTable::new(),
+            traits: NameTable::new(),
+            enums: NameTable::new(),
+            bytecode_cache: None,
+            deadline: None,
+            script_deadline: None,
+            ticks: 0,
+            coverage: None,
+            debugger: None,
+            profiler: None,
+            stats: None,
+            error_location: None,
+            opcode_trace: None,
+            shutdown_functions: Vec::new(),
+            last_error: None,
+            silence_depth: 0,
+            error_output: Box::new(std::io::stderr()),
+            response: superglobals::Response::default(),
         }
     }
 
-    /// Register user-defined functions
-    pub fn register_functions(&mut self, functions: HashMap<String, Arc<CompiledFunction>>) {
-        self.functions = functions;
+    /// Replace the filesystem scripts read and write (still confined by
+    /// the sandbox)
+    pub fn set_filesystem(&mut self, filesystem: Box<dyn Filesystem>) {
+        self.filesystem = self.sandbox.restrict(filesystem);
+    }
+
+    /// Keep the compilations of required and autoloaded files in `cache`
+    pub fn set_bytecode_cache(&mut self, cache: cache::BytecodeCache) {
+        self.bytecode_cache = Some(cache);
+    }
+
+    /// Start counting executed statement lines
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(coverage::Coverage::default());
+    }
+
+    /// Take the line hits collected since `enable_coverage`
+    pub fn take_coverage(&mut self) -> Option<coverage::Coverage> {
+        self.coverage.take()
+    }
+
+    /// Register user-defined functions, replacing any of the same name
+    pub fn register_functions(&mut self, functions: NameTable<Arc<CompiledFunction>>) {
+        for (name, function) in functions {
+            self.functions.insert(name, function);
+        }
     }
 
     /// Register class definitions (merges with existing built-in classes)
-    pub fn register_classes(&mut self, classes: HashMap<String, Arc<CompiledClass>>) {
+    pub fn register_classes(&mut self, classes: NameTable<Arc<CompiledClass>>) {
         // Merge user classes into existing (preserves built-ins)
         for (name, class) in classes {
             self.classes.insert(name, class);
@@ -97,387 +221,78 @@ impl<W: Write> VM<W> {
     }
 
     /// Register interface definitions (merges with existing built-in interfaces)
-    pub fn register_interfaces(&mut self, interfaces: HashMap<String, Arc<CompiledInterface>>) {
+    pub fn register_interfaces(&mut self, interfaces: NameTable<Arc<CompiledInterface>>) {
         // Merge user interfaces into existing (preserves built-ins)
         for (name, interface) in interfaces {
             self.interfaces.insert(name, interface);
         }
     }
 
-    /// Register trait definitions
-    pub fn register_traits(&mut self, traits: HashMap<String, Arc<CompiledTrait>>) {
-        self.traits = traits;
+    /// Register trait definitions, replacing any of the same name
+    pub fn register_traits(&mut self, traits: NameTable<Arc<CompiledTrait>>) {
+        for (name, trait_) in traits {
+            self.traits.insert(name, trait_);
+        }
     }
 
-    /// Register enum definitions
-    pub fn register_enums(&mut self, enums: HashMap<String, Arc<CompiledEnum>>) {
-        self.enums = enums;
+    /// Register enum definitions, replacing any of the same name
+    pub fn register_enums(&mut self, enums: NameTable<Arc<CompiledEnum>>) {
+        for (name, enum_) in enums {
+            self.enums.insert(name, enum_);
+        }
     }
 
     /// Register built-in classes like Exception
     pub fn register_builtins(&mut self) {
         class_registration::register_builtin_classes(&mut self.classes);
         spl_interfaces::register_builtin_interfaces(&mut self.interfaces);
+        self.register_extension_classes();
     }
 
-    /// Execute a compiled function
-    pub fn execute(&mut self, function: Arc<CompiledFunction>) -> Result<Value, String> {
-        execution::execute_vm(self, function)
+    /// Execute a compiled function, then what is left in the event loop
+    /// and the shutdown functions if it is the script's main code
+    pub fn execute(&mut self, function: Arc<CompiledFunction>) -> Result<Value, RuntimeError> {
+        let main = self.frames.is_empty();
+        if main {
+            self.error_location = None;
+        }
+        let result = self.execute_contained(function);
+        if !main {
+            return result.map_err(|e| self.runtime_error(e));
+        }
+        let result = result
+            .and_then(|value| self.run_event_loop().map(|()| value))
+            .map_err(|e| self.runtime_error(e));
+        self.run_shutdown_functions(result)
     }
 
-    /// Execute a single opcode
-    fn execute_opcode(&mut self, opcode: Opcode) -> Result<(), String> {
-        match opcode {
-            // ==================== Literals & Constants ====================
-            Opcode::PushNull => ops::execute_push_null(self),
-            Opcode::PushTrue => ops::execute_push_true(self),
-            Opcode::PushFalse => ops::execute_push_false(self),
-            Opcode::PushInt(n) => ops::execute_push_int(self, n),
-            Opcode::PushFloat(f) => ops::execute_push_float(self, f),
-            Opcode::PushString(idx) => {
-                let s = self.current_frame().get_string(idx).to_string();
-                ops::execute_push_string(self, s);
-            }
-            Opcode::LoadConst(idx) => {
-                let constant = self.current_frame().get_constant(idx).clone();
-                ops::execute_load_const(self, constant)?;
-            }
-
-            // ==================== Variables ====================
-            Opcode::LoadVar(idx) => {
-                let name = self.current_frame().get_string(idx).to_string();
-                ops::execute_load_var(self, name);
-            }
-            Opcode::StoreVar(idx) => {
-                let name = self.current_frame().get_string(idx).to_string();
-                ops::execute_store_var(self, name)?;
-            }
-            Opcode::LoadFast(slot) => ops::execute_load_fast(self, slot),
-            Opcode::StoreFast(slot) => ops::execute_store_fast(self, slot)?,
-            Opcode::LoadGlobal(idx) => {
-                let name = self.current_frame().get_string(idx).to_string();
-                ops::execute_load_global(self, name);
-            }
-            Opcode::StoreGlobal(idx) => {
-                let name = self.current_frame().get_string(idx).to_string();
-                ops::execute_store_global(self, name)?;
-            }
-
-            // ==================== Arithmetic ====================
-            Opcode::Add => ops::execute_add(self)?,
-            Opcode::Sub => ops::execute_sub(self)?,
-            Opcode::Mul => ops::execute_mul(self)?,
-            Opcode::Div => ops::execute_div(self)?,
-            Opcode::Mod => ops::execute_mod(self)?,
-            Opcode::Pow => ops::execute_pow(self)?,
-            Opcode::Neg => ops::execute_neg(self)?,
-
-            // ==================== String Operations ====================
-            Opcode::Concat => ops::execute_concat(self)?,
-            Opcode::HeredocInterpolate(var_count) => {
-                ops::execute_heredoc_interpolate(self, var_count)?
-            }
-
-            // ==================== Comparison ====================
-            Opcode::Eq => ops::execute_eq(self)?,
-            Opcode::Ne => ops::execute_ne(self)?,
-            Opcode::Identical => ops::execute_identical(self)?,
-            Opcode::NotIdentical => ops::execute_not_identical(self)?,
-            Opcode::Lt => ops::execute_lt(self)?,
-            Opcode::Le => ops::execute_le(self)?,
-            Opcode::Gt => ops::execute_gt(self)?,
-            Opcode::Ge => ops::execute_ge(self)?,
-            Opcode::Spaceship => ops::execute_spaceship(self)?,
-
-            // ==================== Logical ====================
-            Opcode::Not => ops::execute_not(self)?,
-            Opcode::And => ops::execute_and(self)?,
-            Opcode::Or => ops::execute_or(self)?,
-            Opcode::Xor => ops::execute_xor(self)?,
-
-            // ==================== Control Flow ====================
-            Opcode::Jump(offset) => ops::execute_jump(self, offset),
-            Opcode::JumpIfFalse(offset) => ops::execute_jump_if_false(self, offset)?,
-            Opcode::JumpIfTrue(offset) => ops::execute_jump_if_true(self, offset)?,
-            Opcode::JumpIfNull(offset) => ops::execute_jump_if_null(self, offset)?,
-            Opcode::JumpIfNotNull(offset) => ops::execute_jump_if_not_null(self, offset)?,
-            Opcode::Return => ops::execute_return(self)?,
-            Opcode::Yield => ops::execute_yield(self)?,
-            Opcode::YieldFrom => ops::execute_yield_from(self)?,
-            Opcode::ReturnNull => ops::execute_return_null(self)?,
-
-            // ==================== Loop Control ====================
-            Opcode::Break => ops::execute_break(self)?,
-            Opcode::Continue => ops::execute_continue(self)?,
-            Opcode::LoopStart(continue_target, break_target) => {
-                ops::execute_loop_start(self, continue_target, break_target);
-            }
-            Opcode::LoopEnd => ops::execute_loop_end(self),
-
-            // ==================== Arrays ====================
-            Opcode::NewArray(count) => ops::execute_new_array(self, count)?,
-            Opcode::ArrayGet => ops::execute_array_get(self)?,
-            Opcode::ArraySet => ops::execute_array_set(self)?,
-            Opcode::ArrayAppend => ops::execute_array_append(self)?,
-            Opcode::ArrayMerge => ops::execute_array_merge(self)?,
-            Opcode::ArrayCount => ops::execute_array_count(self)?,
-            Opcode::ArrayGetKeyAt => ops::execute_array_get_key_at(self)?,
-            Opcode::ArrayGetValueAt => ops::execute_array_get_value_at(self)?,
-            Opcode::ToArray => ops::execute_to_array(self)?,
-
-            // ==================== Stack Manipulation ====================
-            Opcode::Pop => ops::execute_pop(self),
-            Opcode::Dup => ops::execute_dup(self)?,
-            Opcode::Swap => ops::execute_swap(self)?,
-
-            // ==================== Type Operations ====================
-            Opcode::Cast(cast_type) => ops::execute_cast(self, cast_type)?,
-
-            // ==================== Null Coalescing ====================
-            Opcode::NullCoalesce => ops::execute_null_coalesce(self)?,
-
-            // ==================== Output ====================
-            Opcode::Echo => ops::execute_echo(self)?,
-            Opcode::Print => ops::execute_print(self)?,
-
-            // ==================== Function Calls ====================
-            Opcode::Call(name_idx, arg_count) => {
-                let func_name = self.current_frame().get_string(name_idx).to_string();
-                ops::execute_call(self, func_name, arg_count)?;
-            }
-
-            Opcode::CallSpread(name_idx) => {
-                crate::vm::ops::execute_call_spread(self, name_idx)?;
-            }
-
-            Opcode::CallNamed(name_idx) => {
-                crate::vm::ops::execute_call_named_args(self, name_idx)?;
-            }
-
-            Opcode::CallBuiltin(name_idx, arg_count) => {
-                let func_name = self.current_frame().get_string(name_idx).to_string();
-                ops::execute_call_builtin(self, func_name, arg_count)?;
-            }
-
-            Opcode::CallBuiltinSpread(name_idx) => {
-                ops::execute_call_builtin_spread(self, name_idx)?;
-            }
-
-            Opcode::CallBuiltinNamed(name_idx) => {
-                ops::execute_call_builtin_named(self, name_idx)?;
-            }
-
-            Opcode::CallCallable(arg_count) => {
-                ops::execute_call_callable(self, arg_count)?;
-            }
-
-            // ==================== OOP Opcodes ====================
-            Opcode::NewObject(class_idx) => {
-                let class_name =
-                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
-                ops::execute_new_object(self, class_name)?
-            }
-
-            Opcode::NewFiber => {
-                ops::execute_new_fiber(self)?;
-            }
-
-            Opcode::LoadProperty(prop_idx) => {
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_load_property(self, prop_name)?
-            }
-
-            Opcode::StoreProperty(prop_idx) => {
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_store_property(self, prop_name)?
-            }
-
-            Opcode::StoreCloneProperty(prop_idx) => {
-                ops::execute_store_clone_property(self, prop_idx)?
-            }
-
-            Opcode::UnsetProperty(prop_idx) => {
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_unset_property(self, prop_name)?
-            }
-
-            Opcode::IssetProperty(prop_idx) => {
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_isset_property(self, prop_name)?;
-            }
-
-            Opcode::UnsetPropertyOnLocal(slot, prop_idx) => {
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_unset_property_on_local(self, slot, prop_name)?
-            }
-
-            Opcode::UnsetPropertyOnGlobal(var_idx, prop_idx) => {
-                let var_name = self.current_frame().get_string(var_idx).to_string();
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_unset_property_on_global(self, var_name, prop_name)?
-            }
-
-            Opcode::IssetPropertyOnLocal(slot, prop_idx) => {
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_isset_property_on_local(self, slot, prop_name);
-            }
-
-            Opcode::IssetPropertyOnGlobal(var_idx, prop_idx) => {
-                let var_name = self.current_frame().get_string(var_idx).to_string();
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_isset_property_on_global(self, var_name, prop_name);
-            }
-
-            Opcode::UnsetVar(var_idx) => {
-                let var_name = self.current_frame().get_string(var_idx).to_string();
-                ops::execute_unset_var(self, var_name);
-            }
-
-            Opcode::UnsetArrayElement => {
-                ops::execute_unset_array_element(self)?;
-            }
-
-            Opcode::StoreThisProperty(prop_idx) => {
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_store_this_property(self, prop_name)?
-            }
-
-            Opcode::CallMethod(method_idx, arg_count) => {
-                let method_name = self.current_frame().get_string(method_idx).to_string();
-                ops::execute_call_method(self, method_name, arg_count)?
-            }
-
-            Opcode::CallMethodOnLocal(var_slot, method_idx, arg_count) => {
-                let method_name = self.current_frame().get_string(method_idx).to_string();
-                ops::execute_call_method_on_local(self, var_slot, method_name, arg_count)?
-            }
-
-            Opcode::CallMethodOnGlobal(var_idx, method_idx, arg_count) => {
-                let var_name = self.current_frame().get_string(var_idx).to_string();
-                let method_name = self.current_frame().get_string(method_idx).to_string();
-                ops::execute_call_method_on_global(self, var_name, method_name, arg_count)?
-            }
-
-            Opcode::CallStaticMethod(class_idx, method_idx, arg_count) => {
-                let class_name =
-                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
-                let method_name = self.current_frame().get_string(method_idx).to_string();
-                ops::execute_call_static_method(self, class_name, method_name, arg_count)?
-            }
-
-            Opcode::CallStaticMethodNamed(class_idx, method_idx) => {
-                let class_name =
-                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
-                let method_name = self.current_frame().get_string(method_idx).to_string();
-                ops::execute_call_static_method_named(self, class_name, method_name)?
-            }
-
-            Opcode::LoadStaticProp(class_idx, prop_idx) => {
-                let class_name =
-                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_load_static_prop(self, class_name, prop_name)?
-            }
-
-            Opcode::StoreStaticProp(class_idx, prop_idx) => {
-                let class_name =
-                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
-                let prop_name = self.current_frame().get_string(prop_idx).to_string();
-                ops::execute_store_static_prop(self, class_name, prop_name)?
-            }
-
-            Opcode::LoadThis => ops::execute_load_this(self)?,
-
-            Opcode::InstanceOf(class_idx) => {
-                let class_name =
-                    Self::normalize_class_name(self.current_frame().get_string(class_idx));
-                ops::execute_instance_of(self, class_name)?;
-            }
-
-            Opcode::Clone => ops::execute_clone(self)?,
-
-            Opcode::LoadEnumCase(enum_idx, case_idx) => {
-                let enum_name =
-                    Self::normalize_class_name(self.current_frame().get_string(enum_idx));
-                let case_name = self.current_frame().get_string(case_idx).to_string();
-                ops::execute_load_enum_case(self, enum_name, case_name)?
-            }
-
-            Opcode::CallConstructor(arg_count) => {
-                ops::execute_call_constructor(self, arg_count)?;
-            }
-
-            Opcode::CallConstructorNamed => ops::execute_call_constructor_named(self)?,
-
-            // ==================== Exception Handling ====================
-            Opcode::Throw => ops::execute_throw(self)?,
-
-            Opcode::TryStart(catch_offset, finally_offset) => {
-                ops::execute_try_start(self, catch_offset, finally_offset);
-            }
-
-            Opcode::TryEnd => {
-                ops::execute_try_end(self);
-            }
-
-            Opcode::FinallyStart => {
-                ops::execute_finally_start(self);
-            }
-
-            Opcode::FinallyEnd => ops::execute_finally_end(self)?,
-
-            // ==================== Closures ====================
-            Opcode::CreateClosure(func_idx, capture_count) => {
-                let func_name = self.current_frame().get_string(func_idx).to_string();
-                ops::execute_create_closure(self, func_name, capture_count)?;
-            }
-
-            Opcode::CaptureVar(var_idx) => {
-                let var_name = self.current_frame().get_string(var_idx).to_string();
-                ops::execute_capture_var(self, var_name);
-            }
-
-            Opcode::CreateMethodClosure => {
-                ops::execute_create_method_closure(self)?;
-            }
-
-            Opcode::CreateStaticMethodClosure => {
-                ops::execute_create_static_method_closure(self)?;
-            }
-
-            // ==================== Array Operations ====================
-            Opcode::ArrayUnpack => {
-                ops::execute_array_unpack(self)?;
-            }
+    /// Call a function, or any callable value, with `args` and run it to
+    /// the end, as a host does once the script's main code is over
+    ///
+    /// ```
+    /// use vhp::runtime::Value;
+    /// use vhp::vm::VM;
+    ///
+    /// let mut vm = VM::new(std::io::sink());
+    /// let main = vm.load(vhp::compile("<?php function add($a, $b) { return $a + $b; }", "a.php").unwrap());
+    /// vm.execute(main).unwrap();
+    /// let sum = vm.call(Value::String("add".into()), vec![Value::Integer(2), Value::Integer(3)]);
+    /// assert!(matches!(sum, Ok(Value::Integer(5))));
+    /// let length = vm.call(Value::String("strlen".into()), vec![Value::String("vhp".into())]);
+    /// assert!(matches!(length, Ok(Value::Integer(3))));
+    /// ```
+    pub fn call(&mut self, callable: Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut call = CompiledFunction::new("{call}".to_string());
+        call.bytecode.push(Opcode::CallCallable(args.len() as u8));
+        self.stack.extend(args);
+        self.stack.push(callable);
+        self.execute_contained(Arc::new(call))
+            .map_err(|e| self.runtime_error(e))
+    }
 
-            // ==================== Generator Methods ====================
-            Opcode::GeneratorCurrent => {
-                ops::execute_generator_current(self)?;
-            }
-            Opcode::GeneratorKey => {
-                ops::execute_generator_key(self)?;
-            }
-            Opcode::GeneratorNext => {
-                ops::execute_generator_next(self)?;
-            }
-            Opcode::GeneratorRewind => {
-                ops::execute_generator_rewind(self)?;
-            }
-            Opcode::GeneratorValid => {
-                ops::execute_generator_valid(self)?;
-            }
-            Opcode::SetCurrentFiber => {
-                ops::execute_set_current_fiber(self)?;
-            }
-            Opcode::GetCurrentFiber => {
-                ops::execute_get_current_fiber(self)?;
-            }
-            // ==================== Not Yet Implemented ====================
-            _ => {
-                return Err(format!("Opcode not yet implemented: {:?}", opcode));
-            }
-        }
-        Ok(())
+    /// Where the script output goes
+    pub fn output_mut(&mut self) -> &mut W {
+        &mut self.output
     }
 
     /// Get the current call frame (immutable)
@@ -494,16 +309,7 @@ impl<W: Write> VM<W> {
 
     /// Look up function case-insensitively (PHP functions are case-insensitive)
     fn get_function(&self, name: &str) -> Option<Arc<CompiledFunction>> {
-        // Try exact match first
-        if let Some(func) = self.functions.get(name) {
-            return Some(func.clone());
-        }
-        // Try case-insensitive match
-        let name_lower = name.to_lowercase();
-        self.functions
-            .iter()
-            .find(|(k, _)| k.to_lowercase() == name_lower)
-            .map(|(_, v)| v.clone())
+        self.functions.get(name).cloned()
     }
 
     /// Get the current class name from the function name (format: "ClassName::methodName")
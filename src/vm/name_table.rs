@@ -0,0 +1,165 @@
+//! Case-insensitive tables of functions and classes
+//!
+//! PHP looks up functions, classes, interfaces, traits and enums without
+//! regard to case. A `NameTable` keys its entries by the ASCII-lowercased
+//! name, so a lookup is a single hash probe, and keeps the name each
+//! entry was declared with for messages and reflection.
+
+use std::collections::hash_map::{self, HashMap};
+
+/// Entries by name, looked up case-insensitively
+#[derive(Debug, Clone)]
+pub struct NameTable<T> {
+    /// The declared name and the value, by lowercased name
+    entries: HashMap<String, (String, T)>,
+}
+
+impl<T> Default for NameTable<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+/// The key a name is stored under
+fn key(name: &str) -> std::borrow::Cow<'_, str> {
+    if name.bytes().any(|b| b.is_ascii_uppercase()) {
+        name.to_ascii_lowercase().into()
+    } else {
+        name.into()
+    }
+}
+
+impl<T> NameTable<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace an entry, returning the value it replaced; the
+    /// entry takes the new name
+    pub fn insert(&mut self, name: String, value: T) -> Option<T> {
+        self.entries
+            .insert(key(&name).into_owned(), (name, value))
+            .map(|(_, value)| value)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries.get(key(name).as_ref()).map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut T> {
+        self.entries
+            .get_mut(key(name).as_ref())
+            .map(|(_, value)| value)
+    }
+
+    /// An entry with the name it was declared with
+    pub fn get_key_value(&self, name: &str) -> Option<(&String, &T)> {
+        self.entries
+            .get(key(name).as_ref())
+            .map(|(name, value)| (name, value))
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.entries.contains_key(key(name).as_ref())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<T> {
+        self.entries
+            .remove(key(name).as_ref())
+            .map(|(_, value)| value)
+    }
+
+    /// The value for `name`, added by `value` if there is none
+    pub fn get_or_insert_with(&mut self, name: String, value: impl FnOnce() -> T) -> &mut T {
+        let (_, value) = self
+            .entries
+            .entry(key(&name).into_owned())
+            .or_insert_with(|| (name, value()));
+        value
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries, with the names they were declared with
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            entries: self.entries.values(),
+        }
+    }
+
+    /// The declared names
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.values().map(|(name, _)| name)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.entries.values().map(|(_, value)| value)
+    }
+
+    /// Take out the entries, with the names they were declared with
+    pub fn drain(&mut self) -> impl Iterator<Item = (String, T)> + '_ {
+        self.entries.drain().map(|(_, entry)| entry)
+    }
+
+    /// Keep the entries `keep` accepts
+    pub fn retain(&mut self, mut keep: impl FnMut(&String, &mut T) -> bool) {
+        self.entries.retain(|_, (name, value)| keep(name, value));
+    }
+}
+
+/// Iterator over a table's declared names and values
+pub struct Iter<'a, T> {
+    entries: hash_map::Values<'a, String, (String, T)>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (&'a String, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|(name, value)| (name, value))
+    }
+}
+
+impl<'a, T> IntoIterator for &'a NameTable<T> {
+    type Item = (&'a String, &'a T);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for NameTable<T> {
+    type Item = (String, T);
+    type IntoIter = hash_map::IntoValues<String, (String, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_values()
+    }
+}
+
+impl<T> FromIterator<(String, T)> for NameTable<T> {
+    fn from_iter<I: IntoIterator<Item = (String, T)>>(entries: I) -> Self {
+        let mut table = Self::new();
+        for (name, value) in entries {
+            table.insert(name, value);
+        }
+        table
+    }
+}
+
+impl<T> Extend<(String, T)> for NameTable<T> {
+    fn extend<I: IntoIterator<Item = (String, T)>>(&mut self, entries: I) {
+        for (name, value) in entries {
+            self.insert(name, value);
+        }
+    }
+}
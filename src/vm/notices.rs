@@ -0,0 +1,293 @@
+//! Non-fatal errors
+//!
+//! Unlike fatal errors, warnings don't stop the script. As PHP's CLI
+//! does, the VM prints them into the script output as
+//! "Warning: <message> in <file> on line <line>" and carries on. The
+//! `error_reporting` directive picks the levels that are shown, and
+//! `display_errors=0` hides them all. Deprecation notices go through
+//! the same channel (see `deprecation`).
+//!
+//! Hosts that can't mix errors with the output, like a web server whose
+//! output is the response, send them to the VM's error output instead
+//! (stderr unless `VM::set_error_output` replaces it):
+//! `display_errors=stderr` shows them there rather than in the output,
+//! and `log_errors=1` also logs them there as
+//! "PHP Warning:  <message> in <file> on line <line>".
+//!
+//! ```
+//! use std::cell::RefCell;
+//! use std::io::Write;
+//! use std::rc::Rc;
+//! use vhp::vm::VM;
+//!
+//! #[derive(Clone, Default)]
+//! struct Log(Rc<RefCell<Vec<u8>>>);
+//!
+//! impl Write for Log {
+//!     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+//!         self.0.borrow_mut().write(buf)
+//!     }
+//!     fn flush(&mut self) -> std::io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let source = "<?php ini_set('display_errors', 'stderr'); echo $missing, 'done';";
+//! let compilation = vhp::compile(source, "a.php").unwrap();
+//! let log = Log::default();
+//! let mut output = Vec::new();
+//! let mut vm = VM::new(&mut output);
+//! vm.set_error_output(Box::new(log.clone()));
+//! let main = vm.load(compilation);
+//! vm.execute(main).unwrap();
+//! drop(vm);
+//! assert_eq!(String::from_utf8(output).unwrap(), "done");
+//! let errors = String::from_utf8(log.0.take()).unwrap();
+//! assert!(errors.contains("Warning: Undefined variable $missing in a.php on line 1"));
+//! ```
+//!
+//! With `vhp.errors_as_exceptions=1`, a reported error is thrown as an
+//! `ErrorException` instead, for suites that treat every warning as a
+//! failure.
+//!
+//! Errors raised while an `@` expression is evaluated are neither shown
+//! nor thrown.
+//!
+//! Whether shown or not, the last error is kept for `error_get_last()`,
+//! fatal errors included (see `shutdown`).
+
+use super::VM;
+use crate::runtime::builtins::ini;
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::VmError;
+use crate::vm::ops;
+use std::io::Write;
+
+/// Level of a non-fatal error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// `E_WARNING`
+    Warning,
+    /// `E_NOTICE`
+    Notice,
+    /// `E_DEPRECATED`
+    Deprecated,
+}
+
+impl Level {
+    /// The level's bit in `error_reporting`
+    pub fn bit(self) -> i64 {
+        match self {
+            Level::Warning => 2,
+            Level::Notice => 8,
+            Level::Deprecated => 8192,
+        }
+    }
+
+    /// How the level is labelled in the output
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::Warning => "Warning",
+            Level::Notice => "Notice",
+            Level::Deprecated => "Deprecated",
+        }
+    }
+}
+
+/// `E_ERROR`, the type `error_get_last()` gives fatal errors
+pub const E_ERROR: i64 = 1;
+
+/// The error `error_get_last()` describes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LastError {
+    pub kind: i64,
+    pub message: String,
+    pub file: String,
+    pub line: usize,
+}
+
+impl LastError {
+    /// The array `error_get_last()` returns
+    fn to_value(&self) -> Value {
+        Value::Array(
+            vec![
+                (
+                    ArrayKey::String("type".to_string()),
+                    Value::Integer(self.kind),
+                ),
+                (
+                    ArrayKey::String("message".to_string()),
+                    Value::String(self.message.clone()),
+                ),
+                (
+                    ArrayKey::String("file".to_string()),
+                    Value::String(self.file.clone()),
+                ),
+                (
+                    ArrayKey::String("line".to_string()),
+                    Value::Integer(self.line as i64),
+                ),
+            ]
+            .into(),
+        )
+    }
+}
+
+/// Whether a boolean directive's value turns it off
+pub(crate) fn is_off(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "0" | "" | "off" | "false" | "no"
+    )
+}
+
+/// Whether `error_reporting` includes errors of `level`
+fn is_reported(level: Level) -> bool {
+    let reporting = ini::get_ini_value("error_reporting")
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(ini::E_ALL);
+    reporting & level.bit() != 0
+}
+
+/// Where errors are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Display {
+    /// In the script output
+    Output,
+    /// In the error output
+    Errors,
+}
+
+/// Where errors of `level` are displayed under the current directives,
+/// if they are
+fn display(level: Level) -> Option<Display> {
+    let value = ini::get_ini_value("display_errors").unwrap_or_default();
+    if is_off(&value) || !is_reported(level) {
+        None
+    } else if value.trim().eq_ignore_ascii_case("stderr") {
+        Some(Display::Errors)
+    } else {
+        Some(Display::Output)
+    }
+}
+
+/// Whether errors of `level` are logged to the error output
+fn is_logged(level: Level) -> bool {
+    !is_off(&ini::get_ini_value("log_errors").unwrap_or_default()) && is_reported(level)
+}
+
+/// Whether reported errors are thrown as `ErrorException`s
+fn errors_as_exceptions() -> bool {
+    ini::get_ini_value("vhp.errors_as_exceptions").is_some_and(|value| !is_off(&value))
+}
+
+/// The message of the warning for reading a missing array key
+pub fn undefined_key_message(key: &ArrayKey) -> String {
+    match key {
+        ArrayKey::Integer(n) => format!("Undefined array key {}", n),
+        ArrayKey::String(s) => format!("Undefined array key \"{}\"", s),
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Report a non-fatal error at the current statement
+    pub(crate) fn raise(&mut self, level: Level, message: &str) -> Result<(), VmError> {
+        let (file, line) = match self.current_location() {
+            Some(location) => (location.file, location.line),
+            None => (String::from("Unknown"), 0),
+        };
+        if errors_as_exceptions() && is_reported(level) && self.silence_depth == 0 {
+            return self.throw_error_exception(level, message, file, line);
+        }
+        self.raise_at(level, message, &file, line)
+    }
+
+    /// Throw an `ErrorException` for an error instead of printing it
+    fn throw_error_exception(
+        &mut self,
+        level: Level,
+        message: &str,
+        file: String,
+        line: usize,
+    ) -> Result<(), VmError> {
+        let exception = ops::new_throwable(self, "ErrorException", message)?;
+        let properties = [
+            ("severity", Value::Integer(level.bit())),
+            ("file", Value::String(file)),
+            ("line", Value::Integer(line as i64)),
+        ];
+        for (name, value) in properties {
+            exception.set(name, value);
+        }
+        self.stack.push(Value::Object(exception));
+        ops::execute_throw(self)?;
+        Err(VmError::Thrown)
+    }
+
+    /// Report a non-fatal error at `line` of `file`
+    pub(crate) fn raise_at(
+        &mut self,
+        level: Level,
+        message: &str,
+        file: &str,
+        line: usize,
+    ) -> Result<(), VmError> {
+        self.last_error = Some(LastError {
+            kind: level.bit(),
+            message: message.to_string(),
+            file: file.to_string(),
+            line,
+        });
+        if self.silence_depth > 0 {
+            return Ok(());
+        }
+        if is_logged(level) {
+            writeln!(
+                self.error_output,
+                "PHP {}:  {} in {} on line {}",
+                level.label(),
+                message,
+                file,
+                line
+            )
+            .map_err(|e| VmError::fatal(e.to_string()))?;
+        }
+        let shown = format!(
+            "\n{}: {} in {} on line {}\n",
+            level.label(),
+            message,
+            file,
+            line
+        );
+        match display(level) {
+            Some(Display::Output) => self.output.write_all(shown.as_bytes()),
+            Some(Display::Errors) => self.error_output.write_all(shown.as_bytes()),
+            None => Ok(()),
+        }
+        .map_err(|e| VmError::fatal(e.to_string()))
+    }
+
+    /// Send the errors that don't go into the script output to `out`
+    /// rather than stderr
+    pub fn set_error_output(&mut self, out: Box<dyn Write>) {
+        self.error_output = out;
+    }
+
+    /// `error_get_last()`: the last error raised, or `null`
+    pub(crate) fn error_get_last(&self) -> Value {
+        self.last_error
+            .as_ref()
+            .map_or(Value::Null, LastError::to_value)
+    }
+
+    /// `error_clear_last()`
+    pub(crate) fn error_clear_last(&mut self) -> Value {
+        self.last_error = None;
+        Value::Null
+    }
+
+    /// Warn that the variable `name` is read before it was assigned
+    pub(crate) fn warn_undefined_variable(&mut self, name: &str) -> Result<(), VmError> {
+        self.raise(Level::Warning, &format!("Undefined variable ${}", name))
+    }
+}
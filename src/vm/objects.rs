@@ -5,8 +5,12 @@
 //! - Class inheritance checking (is_subclass_of)
 //! - Interface inheritance checking (interface_extends)
 //! - Class keyword resolution (self, parent, static)
+//! - Class constant lookup
 //! - Class name normalization
+//! - Existence checks (class_exists, function_exists, ...)
 
+use crate::runtime::Value;
+use crate::vm::error::VmError;
 use std::io::Write;
 
 impl<W: Write> super::VM<W> {
@@ -36,6 +40,14 @@ impl<W: Write> super::VM<W> {
             }
         }
 
+        // Enums can implement interfaces too
+        if let Some(enum_def) = self.enums.get(obj_class) {
+            return enum_def.interfaces.iter().any(|interface| {
+                interface.eq_ignore_ascii_case(target_class)
+                    || self.interface_extends(interface, target_class)
+            });
+        }
+
         false
     }
 
@@ -87,38 +99,68 @@ impl<W: Write> super::VM<W> {
 
     /// Resolve self/static/parent to actual class name
     /// Returns appropriate class name based on keyword and current context
-    pub fn resolve_class_keyword(&self, keyword: &str) -> Result<String, String> {
+    pub fn resolve_class_keyword(&self, keyword: &str) -> Result<String, VmError> {
         match keyword {
             "self" => self
                 .get_current_class()
-                .ok_or_else(|| "Cannot use self:: outside of class".to_string()),
+                .ok_or_else(|| VmError::fatal("Cannot use self:: outside of class")),
             "static" => {
-                // Late static binding: use called_class if available, otherwise fall back to current class
+                // Late static binding: use called_class if available, then the class of $this,
+                // otherwise fall back to current class
                 if let Some(frame) = self.frames.last() {
                     if let Some(called_class) = &frame.called_class {
                         return Ok(called_class.clone());
                     }
+                    if let (Some(this), Some(crate::runtime::Value::Object(instance))) =
+                        (frame.function.local_names.first(), frame.locals.first())
+                    {
+                        if this == "this" {
+                            return Ok(instance.class_name().to_string());
+                        }
+                    }
                 }
                 self.get_current_class()
-                    .ok_or_else(|| "Cannot use static:: outside of class".to_string())
+                    .ok_or_else(|| VmError::fatal("Cannot use static:: outside of class"))
             }
             "parent" => {
                 let current_class = self
                     .get_current_class()
-                    .ok_or_else(|| "Cannot use parent:: outside of class".to_string())?;
+                    .ok_or_else(|| VmError::fatal("Cannot use parent:: outside of class"))?;
                 let class_def = self
                     .classes
                     .get(&current_class)
-                    .ok_or_else(|| format!("Class '{}' not found", current_class))?;
-                class_def
-                    .parent
-                    .clone()
-                    .ok_or_else(|| format!("Class '{}' has no parent", current_class))
+                    .ok_or_else(|| VmError::class_not_found(&current_class))?;
+                class_def.parent.clone().ok_or_else(|| {
+                    VmError::fatal(format!("Class '{}' has no parent", current_class))
+                })
             }
             other => Ok(other.to_string()),
         }
     }
 
+    /// The constant `name` of a class or interface, inherited ones
+    /// included, or the case or constant `name` of an enum
+    pub fn class_constant(&self, class_name: &str, name: &str) -> Option<crate::runtime::Value> {
+        if let Some(enum_def) = self.enums.get(class_name) {
+            if let Some(backing) = enum_def.cases.get(name) {
+                return Some(crate::runtime::Value::EnumCase {
+                    enum_name: enum_def.name.clone(),
+                    case_name: name.to_string(),
+                    backing_value: backing.clone().map(Box::new),
+                });
+            }
+            return enum_def.constants.get(name).cloned();
+        }
+        if let Some(class_def) = self.classes.get(class_name) {
+            return class_def.constants.get(name).cloned();
+        }
+        self.interfaces
+            .get(class_name)?
+            .constants
+            .get(name)
+            .cloned()
+    }
+
     /// Try to autoload a class by calling registered autoloaders
     /// Returns true if the class was successfully loaded, false otherwise
     #[allow(dead_code)]
@@ -169,4 +211,62 @@ impl<W: Write> super::VM<W> {
             None
         }
     }
+
+    /// class_exists(), interface_exists(), trait_exists() and
+    /// enum_exists(): whether the first argument names a declaration of
+    /// that kind, after giving the autoloaders a chance to load it unless
+    /// the second argument is false. Enums are classes too.
+    pub(crate) fn declaration_exists(
+        &mut self,
+        func_name: &str,
+        args: &[Value],
+    ) -> Result<Value, VmError> {
+        let Some(name) = args.first() else {
+            return Err(VmError::argument_count_error(format!(
+                "{}() expects at least 1 argument, 0 given",
+                func_name
+            )));
+        };
+        let Value::String(name) = name else {
+            return Ok(Value::Bool(false));
+        };
+        let name = Self::normalize_class_name(name);
+        let autoload = args.get(1).is_none_or(Value::to_bool);
+        if !self.is_declared(func_name, &name) && autoload {
+            self.try_autoload_class(&name);
+        }
+        Ok(Value::Bool(self.is_declared(func_name, &name)))
+    }
+
+    /// Whether `name` is declared as the kind `func_name` checks for, any
+    /// class, interface, trait or enum for other names
+    pub(crate) fn is_declared(&self, func_name: &str, name: &str) -> bool {
+        match func_name {
+            "interface_exists" => self.interfaces.contains_key(name),
+            "trait_exists" => self.traits.contains_key(name),
+            "enum_exists" => self.enums.contains_key(name),
+            "class_exists" => self.classes.contains_key(name) || self.enums.contains_key(name),
+            _ => {
+                self.classes.contains_key(name)
+                    || self.enums.contains_key(name)
+                    || self.interfaces.contains_key(name)
+                    || self.traits.contains_key(name)
+            }
+        }
+    }
+
+    /// function_exists(): whether a user or built-in function is declared.
+    /// Functions aren't autoloaded.
+    pub(crate) fn function_exists(&self, args: &[Value]) -> Result<Value, VmError> {
+        let Some(name) = args.first() else {
+            return Err(VmError::argument_count_error(
+                "function_exists() expects exactly 1 argument, 0 given",
+            ));
+        };
+        let name = name.to_string_val();
+        let name = name.trim_start_matches('\\');
+        Ok(Value::Bool(
+            self.get_function(name).is_some() || self.is_native_function(name),
+        ))
+    }
 }
@@ -3,13 +3,14 @@
 //! This module defines the complete instruction set for the bytecode VM.
 //! The VM uses a stack-based architecture with ~70 core opcodes.
 
+use super::cache::codec::Codec;
 pub use super::compiled_types::{CompiledFunction, Constant};
 
 /// Bytecode instruction type
 ///
 /// Stack-based VM instructions. Most instructions operate on values
 /// at the top of the operand stack.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Codec)]
 #[allow(dead_code)] // Many opcode variants are defined for future use
 pub enum Opcode {
     // ==================== Literals & Constants ====================
@@ -27,19 +28,64 @@ pub enum Opcode {
     PushString(u32),
     /// Load constant from pool by index
     LoadConst(u32),
+    /// Push the value of the constant named by the name index, throwing
+    /// Error if there is none
+    FetchConstant(u32),
     // ==================== Variables ====================
     /// Load variable by name index (from string pool)
     LoadVar(u32),
     /// Store top of stack to variable by name index
     StoreVar(u32),
+    /// Load variable by name index, warning if it is undefined
+    LoadVarChecked(u32),
     /// Load local variable by slot index (fast path for known locals)
     LoadFast(u16),
+    /// Load local variable by slot index, warning if it was never assigned
+    LoadFastChecked(u16),
     /// Store to local variable slot (fast path)
     StoreFast(u16),
+    /// Push a reference to a local variable, which a call passes to a
+    /// by-reference parameter and reads otherwise (see `ops::references`)
+    LoadRef(u16),
+    /// Push a reference to a local variable that may not be assigned yet;
+    /// the call warns if it reads the variable
+    LoadRefChecked(u16),
+    /// `$a = &$b`: bind the first local to the second one (stack: -> value)
+    AssignRef(u16, u16),
+    /// `&$array[$key]`: a reference to an element, defining it as null if
+    /// it isn't set (stack: reference, key -> reference)
+    ElementRef,
+    /// `&$object->name`: a reference to a property by name index (stack:
+    /// object -> reference)
+    PropertyRef(u32),
+    /// `$a = &...`: bind a local to the reference on the stack (stack:
+    /// reference -> value)
+    BindRef(u16),
+    /// Whether parameter `pos` of the function named by the name index
+    /// takes a reference (stack: -> bool)
+    IsRefArg(u32, u8),
+    /// Whether parameter `pos` of the method named by the name index takes
+    /// a reference, the object being under the `pos` arguments before it
+    /// (stack: -> bool)
+    IsMethodRefArg(u32, u8),
+    /// `global $x`: bind a local to the global variable by name index
+    BindGlobal(u32, u16),
     /// Load from global scope by name index
     LoadGlobal(u32),
     /// Store to global scope by name index
     StoreGlobal(u32),
+    /// Push `$GLOBALS`, an array of the global variables
+    LoadGlobals,
+    /// Load the global variable a name names, warning if it is unset
+    /// (stack: name -> value)
+    FetchGlobal,
+    /// Load the global variable a name names, null if it is unset
+    /// (stack: name -> value)
+    FetchGlobalQuiet,
+    /// Assign the global variable a name names (stack: name, value -> value)
+    AssignGlobal,
+    /// Unset the global variable a name names (stack: name -> void)
+    UnsetGlobal,
 
     // ==================== Arithmetic ====================
     /// Add: pop two values, push sum
@@ -60,8 +106,6 @@ pub enum Opcode {
     // ==================== String Operations ====================
     /// String concatenation: pop two values, push concatenated string
     Concat,
-    /// Heredoc interpolation: count of variable placeholders (stack: strings... -> result)
-    HeredocInterpolate(u16),
 
     // ==================== Comparison ====================
     /// Equal (==): pop two values, push bool result
@@ -138,9 +182,12 @@ pub enum Opcode {
     Return,
     /// Return null from function
     ReturnNull,
+    /// Declare a function whose declaration is nested in a block or a
+    /// function body: index of the name it was compiled under
+    DeclareFunction(u32),
 
     // ==================== Generators ====================
-    /// Yield value from generator
+    /// Yield value from generator (stack: key or null, value -> sent value)
     Yield,
     /// Yield from iterator
     YieldFrom,
@@ -154,6 +201,19 @@ pub enum Opcode {
     GeneratorRewind,
     /// Generator::valid() - check if generator is still valid
     GeneratorValid,
+    /// Whether a foreach loop has an element at its position, rewinding
+    /// a generator at the start: source slot, position slot (stack: ->
+    /// bool)
+    ForeachValid(u16, u16),
+    /// The key of the element at a foreach loop's position: source slot,
+    /// position slot (stack: -> key)
+    ForeachKey(u16, u16),
+    /// The element at a foreach loop's position: source slot, position
+    /// slot (stack: -> value)
+    ForeachValue(u16, u16),
+    /// Move a generator a foreach loop walks to its next yield: source
+    /// slot
+    ForeachNext(u16),
 
     // ==================== Loop Control ====================
     /// Break out of loop
@@ -174,8 +234,21 @@ pub enum Opcode {
     ArraySet,
     /// Get array element (stack: array, key -> value)
     ArrayGet,
+    /// Get array element, warning if the key is missing (stack: array, key -> value)
+    ArrayGetChecked,
+    /// Get a list() target's element: as ArrayGetChecked from an array or
+    /// an object, and null from anything else (stack: array, key -> value)
+    ListGet,
     /// Append to array (stack: array, value -> array)
     ArrayAppend,
+    /// Assign to the element of the array in a local slot at the keys
+    /// on the stack, in place: slot, number of keys, whether to append
+    /// after the last one (stack: keys, value -> value)
+    AssignDimFast(u16, u8, bool),
+    /// Assign to the element of the container on the stack at the keys
+    /// under the value, for the caller to store back: number of keys,
+    /// whether to append (stack: keys, value, container -> value, container)
+    AssignDim(u8, bool),
     /// Unpack/spread array onto stack
     ArrayUnpack,
     /// Get array length (optimized count())
@@ -184,57 +257,80 @@ pub enum Opcode {
     ArrayGetKeyAt,
     /// Get value at iterator index (stack: array, index -> value)
     ArrayGetValueAt,
-    /// Convert iterable to array (handles arrays and generators)
-    /// Stack: iterable -> array
+    /// What a foreach loop walks: arrays and generators as they are,
+    /// other objects as an array of what they iterate over
+    /// Stack: iterable -> array or generator
     ToArray,
 
     // ==================== Objects ====================
-    /// Create new object: class name index
+    /// Create new object: class name index (self, static and parent are resolved at runtime)
     NewObject(u32),
+    /// Create new object of a class named at runtime (stack: class name or object -> object)
+    NewObjectDynamic,
+    /// Push the name of the class self, static or parent refers to: keyword index
+    FetchClassName(u32),
     /// Create new Fiber with callback (stack: callback -> Fiber object)
     NewFiber,
+    /// Run a method of a built-in Reflection class on the current frame's
+    /// `$this` and arguments: "Class::method" name index
+    Reflect(u32),
+    /// Run a method of a built-in SimpleXML or DOM class on the current
+    /// frame's `$this` and arguments: "Class::method" name index
+    Xml(u32),
+    /// Run a method of the built-in EventLoop or Future class on the
+    /// current frame's arguments: "Class::method" name index
+    EventLoop(u32),
+    /// Run a method of the built-in Fiber class on the current frame's
+    /// `$this` and arguments: "Fiber::method" name index
+    Fiber(u32),
+    /// Run a method of a host's native class on the current frame's
+    /// `$this` and arguments: "Class::method" name index
+    NativeMethod(u32),
+    /// Call the next callback the event loop has due, or jump to the
+    /// offset once there is none or the future being awaited has settled
+    RunCallback(u32),
+    /// Settle the future of the task the event loop called (stack: return
+    /// value ->)
+    SettleTask,
+    /// Settle the future of the task the event loop called with the
+    /// exception it threw, or throw it on for a plain callback (stack:
+    /// exception ->)
+    RejectTask,
     /// Load property: property name index (stack: object -> value)
     LoadProperty(u32),
-    /// Store property: property name index (stack: object, value -> object)
+    /// Load property for `??`, with an uninitialized typed property as
+    /// null: property name index (stack: object -> value)
+    LoadPropertyQuiet(u32),
+    /// Load property to write one of its elements, with an uninitialized
+    /// typed property as an empty array: property name index (stack:
+    /// object -> value)
+    LoadPropertyForWrite(u32),
+    /// Store property: property name index (stack: object, value -> value)
     StoreProperty(u32),
-    /// Store property on $this and update local slot 0: property name index (stack: value -> void)
+    /// Store property on $this: property name index (stack: value -> value)
     StoreThisProperty(u32),
-    /// Store property in clone with - validates property exists (stack: object, value -> object)
-    StoreCloneProperty(u32),
-    /// Unset property: property name index (stack: object -> void)
+    /// Unset property: property name index (stack: object -> null)
     /// Calls __unset magic method if property doesn't exist or can't be unset
     UnsetProperty(u32),
-    /// Unset property on a local variable: var slot, property name index
-    /// Tracks source variable so __unset modifications persist
-    UnsetPropertyOnLocal(u16, u32),
-    /// Unset property on a global variable: var name index, property name index
-    /// Tracks source variable so __unset modifications persist
-    UnsetPropertyOnGlobal(u32, u32),
     /// Check if property is set: property name index (stack: object -> bool)
     /// Calls __isset magic method if property doesn't exist on object
     IssetProperty(u32),
-    /// Check if property is set on local variable: var slot, property name index
-    /// Tracks source so __isset can be called with proper context
-    IssetPropertyOnLocal(u16, u32),
-    /// Check if property is set on global variable: var name index, property name index
-    /// Tracks source so __isset can be called with proper context
-    IssetPropertyOnGlobal(u32, u32),
     /// Unset variable: variable name index (removes from global scope)
     UnsetVar(u32),
+    /// Unset a local variable slot, unbinding it from the variables it
+    /// shared a reference with
+    UnsetFast(u16),
     /// Unset array element (stack: array, key -> void)
     UnsetArrayElement,
+    /// Unset an element of the array in a local slot: slot index (stack:
+    /// key -> void)
+    UnsetArrayElementOnLocal(u16),
     /// Load static property: class name index, property name index
     LoadStaticProp(u32, u32),
     /// Store static property: class name index, property name index
     StoreStaticProp(u32, u32),
     /// Call method: method name index, arg count (stack: object, args... -> result)
     CallMethod(u32, u8),
-    /// Call method on a local variable: var slot, method name index, arg count
-    /// This tracks the source variable so $this modifications persist
-    CallMethodOnLocal(u16, u32, u8),
-    /// Call method on a global variable: var name index, method name index, arg count
-    /// This tracks the source variable so $this modifications persist
-    CallMethodOnGlobal(u32, u32, u8),
     /// Call static method: class name index, method name index, arg count
     CallStaticMethod(u32, u32, u8),
     /// Call static method with named arguments: class name index, method name index (stack: args_array -> result)
@@ -245,10 +341,13 @@ pub enum Opcode {
     InstanceOf(u32),
     /// Get current Fiber for Fiber::getCurrent() (stack: -> Fiber|null)
     GetCurrentFiber,
-    /// Set current Fiber (stack: Fiber|null ->)
-    SetCurrentFiber,
-    /// Clone object (stack: object -> cloned_object)
+    /// Suspend the running fiber for Fiber::suspend() (stack: value ->
+    /// value it is resumed with)
+    SuspendFiber,
+    /// Clone object, running __clone on the copy (stack: object -> cloned_object)
     Clone,
+    /// Set the properties of a clone from an array of new values (stack: clone, properties -> clone)
+    CloneWith,
     /// Call constructor on object: arg count (stack: object, args... -> object)
     CallConstructor(u8),
     /// Call constructor with named arguments (stack: object, args_array -> object)
@@ -283,12 +382,9 @@ pub enum Opcode {
     Ternary,
 
     // ==================== Match Expression ====================
-    /// Match expression start: number of arms
-    MatchStart(u16),
-    /// Match arm check (stack: subject, value -> bool)
-    MatchArm,
-    /// Match default arm
-    MatchDefault,
+    /// No arm matched and there is no default (stack: subject -> throws
+    /// UnhandledMatchError)
+    UnhandledMatch,
 
     // ==================== Exception Handling ====================
     /// Set up try block: catch offset, finally offset (0 if none)
@@ -297,12 +393,22 @@ pub enum Opcode {
     TryEnd,
     /// Throw exception (stack: exception -> throws)
     Throw,
-    /// Catch block: exception class name index, variable name index
-    Catch(u32, u32),
+    /// Test a caught exception against a catch clause type: class name
+    /// index (stack: exception -> exception, bool)
+    Catch(u32),
     /// Finally block start
     FinallyStart,
     /// Finally block end
     FinallyEnd,
+    /// Format a trace for getTraceAsString() (stack: trace array -> string)
+    FormatTrace,
+    /// Format a Throwable and its previous ones for __toString()
+    /// (stack: throwable -> string)
+    FormatThrowable,
+    /// Start an `@` expression, whose warnings are not shown
+    BeginSilence,
+    /// End an `@` expression
+    EndSilence,
 
     // ==================== Closure ====================
     /// Create closure: function index, captured var count
@@ -317,14 +423,10 @@ pub enum Opcode {
     CreateStaticMethodClosure,
 
     // ==================== Increment/Decrement ====================
-    /// Pre-increment (++$x)
-    PreInc,
-    /// Pre-decrement (--$x)
-    PreDec,
-    /// Post-increment ($x++)
-    PostInc,
-    /// Post-decrement ($x--)
-    PostDec,
+    /// Increment the value on top of the stack, as `++` does
+    Increment,
+    /// Decrement the value on top of the stack, as `--` does
+    Decrement,
 
     // ==================== Utility ====================
     /// No operation
@@ -336,7 +438,7 @@ pub enum Opcode {
 }
 
 /// Cast type for Cast opcode
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Codec)]
 #[allow(dead_code)] // All variants defined for future use
 pub enum CastType {
     Int,
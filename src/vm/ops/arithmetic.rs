@@ -1,16 +1,17 @@
 use crate::runtime::Value;
+use crate::vm::error::VmError;
 
-pub fn execute_add<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_add<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = vm.add_values(left, right)?;
     vm.stack.push(result);
     Ok(())
 }
 
-pub fn execute_sub<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_sub<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = match (&left, &right) {
         (Value::Integer(a), Value::Integer(b)) => Value::Integer(a - b),
         (Value::Float(a), Value::Float(b)) => Value::Float(a - b),
@@ -26,9 +27,9 @@ pub fn execute_sub<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<()
     Ok(())
 }
 
-pub fn execute_mul<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_mul<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = match (&left, &right) {
         (Value::Integer(a), Value::Integer(b)) => Value::Integer(a * b),
         (Value::Float(a), Value::Float(b)) => Value::Float(a * b),
@@ -44,41 +45,47 @@ pub fn execute_mul<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<()
     Ok(())
 }
 
-pub fn execute_div<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_div<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let a = left.to_float();
     let b = right.to_float();
     if b == 0.0 {
-        return Err("Division by zero".to_string());
+        return Err(VmError::exception(
+            "DivisionByZeroError",
+            "Division by zero",
+        ));
     }
     vm.stack.push(Value::Float(a / b));
     Ok(())
 }
 
-pub fn execute_mod<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_mod<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let a = left.to_int();
     let b = right.to_int();
     if b == 0 {
-        return Err("Division by zero".to_string());
+        return Err(VmError::exception(
+            "DivisionByZeroError",
+            "Division by zero",
+        ));
     }
     vm.stack.push(Value::Integer(a % b));
     Ok(())
 }
 
-pub fn execute_pow<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_pow<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let base = left.to_float();
     let exp = right.to_float();
     vm.stack.push(Value::Float(base.powf(exp)));
     Ok(())
 }
 
-pub fn execute_neg<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_neg<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = match value {
         Value::Integer(n) => Value::Integer(-n),
         Value::Float(f) => Value::Float(-f),
@@ -88,6 +95,20 @@ pub fn execute_neg<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<()
     Ok(())
 }
 
+pub fn execute_increment<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    vm.stack
+        .push(value.increment().map_err(VmError::type_error)?);
+    Ok(())
+}
+
+pub fn execute_decrement<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    vm.stack
+        .push(value.decrement().map_err(VmError::type_error)?);
+    Ok(())
+}
+
 pub fn execute_push_null<W: std::io::Write>(vm: &mut super::super::VM<W>) {
     vm.stack.push(Value::Null);
 }
@@ -115,7 +136,7 @@ pub fn execute_push_string<W: std::io::Write>(vm: &mut super::super::VM<W>, s: S
 pub fn execute_load_const<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     constant: crate::vm::opcode::Constant,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let value = match constant {
         crate::vm::opcode::Constant::Null => Value::Null,
         crate::vm::opcode::Constant::Bool(b) => Value::Bool(b),
@@ -1,106 +1,262 @@
-use crate::runtime::{ArrayKey, Value};
+use crate::runtime::{ArrayKey, ObjectRef, Value};
+use crate::vm::error::VmError;
+use crate::vm::notices::{undefined_key_message, Level};
 
 pub fn execute_new_array<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     count: u16,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let mut arr = Vec::new();
     for _ in 0..count {
-        let value = vm.stack.pop().ok_or("Stack underflow")?;
-        let key = vm.stack.pop().ok_or("Stack underflow")?;
+        let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+        let key = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
         arr.push((ArrayKey::from_value(&key), value));
     }
     arr.reverse();
-    vm.stack.push(Value::Array(arr));
+    vm.stack.push(Value::Array(arr.into()));
     Ok(())
 }
 
-pub fn execute_array_get<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let key = vm.stack.pop().ok_or("Stack underflow")?;
-    let array = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_array_get<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let key = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     match array {
         Value::Array(arr) => {
             let array_key = ArrayKey::from_value(&key);
-            let value = arr
-                .iter()
-                .find(|(k, _)| k == &array_key)
-                .map(|(_, v)| v.clone())
-                .unwrap_or(Value::Null);
+            let value = arr.get(&array_key).cloned().unwrap_or(Value::Null);
             vm.stack.push(value);
         }
-        _ => return Err("Cannot use [] on non-array".to_string()),
+        Value::String(s) => {
+            // Read quietly, as `isset()` and `??` do: a missing or invalid
+            // offset gives null
+            let value = integer_offset(&key)
+                .and_then(|offset| string_byte(&s, offset))
+                .map_or(Value::Null, Value::String);
+            vm.stack.push(value);
+        }
+        Value::Object(instance) => return offset_get(vm, instance, key),
+        Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Float(_) => {
+            vm.stack.push(Value::Null)
+        }
+        _ => return Err(VmError::fatal("Cannot use [] on non-array")),
     }
     Ok(())
 }
 
-pub fn execute_array_set<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
-    let key = vm.stack.pop().ok_or("Stack underflow")?;
-    let array = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_array_get_checked<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<(), VmError> {
+    let key = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     match array {
-        Value::Array(mut arr) => {
+        Value::Array(arr) => {
             let array_key = ArrayKey::from_value(&key);
-            if let Some(pos) = arr.iter().position(|(k, _)| k == &array_key) {
-                arr[pos] = (array_key, value);
-            } else {
-                arr.push((array_key, value));
-            }
+            let value = match arr.get(&array_key) {
+                Some(v) => v.clone(),
+                None => {
+                    vm.raise(Level::Warning, &undefined_key_message(&array_key))?;
+                    Value::Null
+                }
+            };
+            vm.stack.push(value);
+        }
+        Value::String(s) => {
+            let offset = string_offset(vm, &key)?;
+            let value = match string_byte(&s, offset) {
+                Some(byte) => byte,
+                None => {
+                    vm.raise(
+                        Level::Warning,
+                        &format!("Uninitialized string offset {}", offset),
+                    )?;
+                    String::new()
+                }
+            };
+            vm.stack.push(Value::String(value));
+        }
+        Value::Object(instance) => return offset_get(vm, instance, key),
+        Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Float(_) => {
+            vm.raise(
+                Level::Warning,
+                &format!(
+                    "Trying to access array offset on value of type {}",
+                    array.type_name()
+                ),
+            )?;
+            vm.stack.push(Value::Null);
+        }
+        _ => return Err(VmError::fatal("Cannot use [] on non-array")),
+    }
+    Ok(())
+}
+
+/// A list() target's element: null without a warning unless the value
+/// destructured is an array or an object
+pub fn execute_list_get<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let container = vm.stack.len().checked_sub(2).and_then(|i| vm.stack.get(i));
+    if matches!(container, Some(Value::Array(_) | Value::Object(_))) {
+        return execute_array_get_checked(vm);
+    }
+    vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    vm.stack.push(Value::Null);
+    Ok(())
+}
+
+/// `$object[$key]` on an ArrayAccess object: call its offsetGet()
+fn offset_get<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    instance: ObjectRef,
+    key: Value,
+) -> Result<(), VmError> {
+    let Some(method) = vm.find_method_in_chain(instance.class_name(), "offsetGet") else {
+        return Err(VmError::fatal(format!(
+            "Cannot use object of type {} as array",
+            instance.class_name()
+        )));
+    };
+    let stack_base = vm.stack.len();
+    let mut frame = super::super::frame::CallFrame::new(method, stack_base);
+    frame.locals[0] = Value::Object(instance);
+    frame.locals[1] = key;
+    vm.push_frame(frame);
+    Ok(())
+}
+
+pub fn execute_array_set<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let key = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    match array {
+        Value::Array(mut arr) => {
+            arr.insert(ArrayKey::from_value(&key), value);
             vm.stack.push(Value::Array(arr));
         }
-        _ => return Err("Cannot use [] on non-array".to_string()),
+        Value::String(s) => {
+            let s = assign_string_offset(vm, s, &key, &value)?;
+            vm.stack.push(Value::String(s));
+        }
+        _ => return Err(VmError::fatal("Cannot use [] on non-array")),
     }
     Ok(())
 }
 
-pub fn execute_array_append<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
-    let array = vm.stack.pop().ok_or("Stack underflow")?;
+/// The offset an integer or integer string key gives into a string
+fn integer_offset(key: &Value) -> Option<i64> {
+    match key {
+        Value::Integer(n) => Some(*n),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// The offset `key` gives into a string. Other scalars than integers and
+/// integer strings are cast, with a warning.
+fn string_offset<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    key: &Value,
+) -> Result<i64, VmError> {
+    if let Some(offset) = integer_offset(key) {
+        return Ok(offset);
+    }
+    match key {
+        Value::Float(_) | Value::Bool(_) | Value::Null => {
+            vm.raise(Level::Warning, "String offset cast occurred")?;
+            Ok(key.to_int())
+        }
+        _ => Err(VmError::type_error(format!(
+            "Cannot access offset of type {} on string",
+            key.type_name()
+        ))),
+    }
+}
+
+/// The byte at `offset` of `s` (counting from the end if it's negative),
+/// as a string, if the string is that long
+fn string_byte(s: &str, offset: i64) -> Option<String> {
+    let len = s.len() as i64;
+    let index = if offset < 0 { len + offset } else { offset };
+    if !(0..len).contains(&index) {
+        return None;
+    }
+    let byte = s.as_bytes()[index as usize];
+    Some(String::from_utf8_lossy(&[byte]).into_owned())
+}
+
+/// `$s[$offset] = $value` on a string: the byte at the offset becomes the
+/// value's first one, padding the string with spaces up to it if needed
+pub(super) fn assign_string_offset<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    s: String,
+    key: &Value,
+    value: &Value,
+) -> Result<String, VmError> {
+    let offset = string_offset(vm, key)?;
+    let index = if offset < 0 {
+        s.len() as i64 + offset
+    } else {
+        offset
+    };
+    if index < 0 {
+        vm.raise(Level::Warning, &format!("Illegal string offset {}", offset))?;
+        return Ok(s);
+    }
+
+    let value = value.to_string_val();
+    let Some(&byte) = value.as_bytes().first() else {
+        return Err(VmError::error(
+            "Cannot assign an empty string to a string offset",
+        ));
+    };
+    if value.len() > 1 {
+        vm.raise(
+            Level::Warning,
+            "Only the first byte will be assigned to the string offset",
+        )?;
+    }
+
+    let mut bytes = s.into_bytes();
+    let index = index as usize;
+    if index >= bytes.len() {
+        bytes.resize(index + 1, b' ');
+    }
+    bytes[index] = byte;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn execute_array_append<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     match array {
         Value::Array(mut arr) => {
-            let next_idx = arr
-                .iter()
-                .filter_map(|(k, _)| match k {
-                    ArrayKey::Integer(n) => Some(*n),
-                    _ => None,
-                })
-                .max()
-                .unwrap_or(-1)
-                + 1;
-            arr.push((ArrayKey::Integer(next_idx), value));
+            arr.push(value).map_err(VmError::error)?;
             vm.stack.push(Value::Array(arr));
         }
-        _ => return Err("Cannot append to non-array".to_string()),
+        Value::String(_) => return Err(VmError::error("[] operator not supported for strings")),
+        _ => return Err(VmError::fatal("Cannot append to non-array")),
     }
     Ok(())
 }
 
-pub fn execute_array_merge<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let array2 = vm.stack.pop().ok_or("Stack underflow")?;
-    let array1 = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_array_merge<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let array2 = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let array1 = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     match (array1, array2) {
         (Value::Array(mut arr1), Value::Array(arr2)) => {
-            let next_idx = arr1
-                .iter()
-                .filter_map(|(k, _)| match k {
-                    ArrayKey::Integer(n) => Some(*n),
-                    _ => None,
-                })
-                .max()
-                .unwrap_or(-1)
-                + 1;
-
-            for (i, (_, value)) in arr2.into_iter().enumerate() {
-                arr1.push((ArrayKey::Integer(next_idx + i as i64), value));
+            for (_, value) in arr2 {
+                arr1.push(value).map_err(VmError::error)?;
             }
             vm.stack.push(Value::Array(arr1));
         }
-        _ => return Err("Cannot merge non-arrays".to_string()),
+        _ => return Err(VmError::fatal("Cannot merge non-arrays")),
     }
     Ok(())
 }
 
-pub fn execute_array_count<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let array = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_array_count<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     match array {
         Value::Array(arr) => {
             vm.stack.push(Value::Integer(arr.len() as i64));
@@ -112,9 +268,9 @@ pub fn execute_array_count<W: std::io::Write>(vm: &mut super::super::VM<W>) -> R
 
 pub fn execute_array_get_key_at<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let index = vm.stack.pop().ok_or("Stack underflow")?;
-    let array = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let index = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     match (array, index) {
         (Value::Array(arr), Value::Integer(idx)) => {
             if idx >= 0 && (idx as usize) < arr.len() {
@@ -131,9 +287,9 @@ pub fn execute_array_get_key_at<W: std::io::Write>(
 
 pub fn execute_array_get_value_at<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let index = vm.stack.pop().ok_or("Stack underflow")?;
-    let array = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let index = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     match (array, index) {
         (Value::Array(arr), Value::Integer(idx)) => {
             if idx >= 0 && (idx as usize) < arr.len() {
@@ -148,8 +304,10 @@ pub fn execute_array_get_value_at<W: std::io::Write>(
     Ok(())
 }
 
-pub fn execute_array_unpack<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let array = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_array_unpack<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<(), VmError> {
+    let array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     if let Value::Array(elements) = array {
         for (_, value) in elements {
             vm.stack.push(value);
@@ -157,29 +315,3 @@ pub fn execute_array_unpack<W: std::io::Write>(vm: &mut super::super::VM<W>) ->
     }
     Ok(())
 }
-
-pub fn execute_to_array<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let iterable = vm.stack.pop().ok_or("Stack underflow")?;
-
-    match iterable {
-        Value::Array(arr) => {
-            vm.stack.push(Value::Array(arr));
-        }
-        Value::Generator(gen) => {
-            let arr: Vec<(ArrayKey, Value)> = gen
-                .yielded_values
-                .into_iter()
-                .enumerate()
-                .map(|(i, (k, v))| {
-                    let key = k.map_or(ArrayKey::Integer(i as i64), |kv| ArrayKey::from_value(&kv));
-                    (key, v.unwrap_or(Value::Null))
-                })
-                .collect();
-            vm.stack.push(Value::Array(arr));
-        }
-        _ => {
-            vm.stack.push(Value::Array(Vec::new()));
-        }
-    }
-    Ok(())
-}
@@ -1,61 +1,59 @@
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::VmError;
 use crate::vm::frame::CallFrame;
 
 pub fn execute_call<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     func_name: String,
     arg_count: u8,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let mut args = Vec::with_capacity(arg_count as usize);
     for _ in 0..arg_count {
-        args.push(vm.stack.pop().ok_or("Stack underflow")?);
+        args.push(vm.stack.pop().ok_or_else(VmError::stack_underflow)?);
     }
     args.reverse();
-
-    if let Some(func) = vm.get_function(&func_name) {
-        if func.is_generator {
-            let mut args_vec = Vec::with_capacity(arg_count as usize);
-            for _ in 0..arg_count {
-                args_vec.push(vm.stack.pop().ok_or("Stack underflow")?);
-            }
-            args_vec.reverse();
-            return execute_generator_call(vm, func_name, args_vec);
+    let function = vm.get_function(&func_name);
+    let refs = match function {
+        Some(_) => vm.pass_args(&mut args, function.as_deref())?,
+        None => {
+            vm.pass_native_args(&func_name, &mut args)?;
+            Vec::new()
         }
+    };
 
+    if let Some(func) = function {
         if arg_count < func.required_param_count {
-            return Err(format!(
+            return Err(VmError::argument_count_error(format!(
                 "Too few arguments to function {}(), {} passed in, at least {} expected",
                 func.name, arg_count, func.required_param_count
-            ));
+            )));
         }
 
         for (i, arg) in args.iter().enumerate() {
-            if i < func.param_types.len() {
-                if let Some(ref type_hint) = func.param_types[i] {
-                    let use_strict = func.strict_types || vm.requires_strict_type_check(type_hint);
-                    if use_strict {
-                        if !vm.value_matches_type_strict(arg, type_hint) {
-                            let type_name = vm.format_type_hint(type_hint);
-                            let given_type = vm.get_value_type_name(arg);
-                            return Err(format!(
-                                "Argument {} passed to {}() must be of type {}, {} given",
-                                i + 1,
-                                func.name,
-                                type_name,
-                                given_type
-                            ));
-                        }
-                    } else if !vm.value_matches_type(arg, type_hint) {
+            if let Some(type_hint) = func.param_type(i) {
+                let use_strict = func.strict_types || vm.requires_strict_type_check(type_hint);
+                if use_strict {
+                    if !vm.value_matches_type_strict(arg, type_hint) {
                         let type_name = vm.format_type_hint(type_hint);
                         let given_type = vm.get_value_type_name(arg);
-                        return Err(format!(
+                        return Err(VmError::type_error(format!(
                             "Argument {} passed to {}() must be of type {}, {} given",
                             i + 1,
                             func.name,
                             type_name,
                             given_type
-                        ));
+                        )));
                     }
+                } else if !vm.value_matches_type(arg, type_hint) {
+                    let type_name = vm.format_type_hint(type_hint);
+                    let given_type = vm.get_value_type_name(arg);
+                    return Err(VmError::type_error(format!(
+                        "Argument {} passed to {}() must be of type {}, {} given",
+                        i + 1,
+                        func.name,
+                        type_name,
+                        given_type
+                    )));
                 }
             }
         }
@@ -76,10 +74,10 @@ pub fn execute_call<W: std::io::Write>(
                                 if !vm.value_matches_type(&coerced, type_hint) {
                                     let type_name = vm.format_type_hint(type_hint);
                                     let given_type = vm.get_value_type_name(&args[i]);
-                                    return Err(format!(
+                                    return Err(VmError::type_error(format!(
                                         "must be of type {}, {} given",
                                         type_name, given_type
-                                    ));
+                                    )));
                                 }
                                 coerced
                             } else {
@@ -94,13 +92,24 @@ pub fn execute_call<W: std::io::Write>(
                     frame.locals[i] = coerced_arg;
                 }
             }
+            let variadic_type = func.param_type(variadic_slot);
             let variadic_args: Vec<(ArrayKey, Value)> = args
                 .into_iter()
                 .skip(variadic_slot)
                 .enumerate()
-                .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
+                .map(|(i, v)| {
+                    let v = match variadic_type {
+                        Some(type_hint)
+                            if !func.strict_types && !vm.requires_strict_type_check(type_hint) =>
+                        {
+                            vm.coerce_value_to_type(v, type_hint)
+                        }
+                        _ => v,
+                    };
+                    (ArrayKey::Integer(i as i64), v)
+                })
                 .collect();
-            frame.locals[variadic_slot] = Value::Array(variadic_args);
+            frame.locals[variadic_slot] = Value::Array(variadic_args.into());
         } else {
             for (i, arg) in args.into_iter().enumerate() {
                 if i < frame.locals.len() {
@@ -113,10 +122,10 @@ pub fn execute_call<W: std::io::Write>(
                                 if !vm.value_matches_type(&coerced, type_hint) {
                                     let type_name = vm.format_type_hint(type_hint);
                                     let given_type = vm.get_value_type_name(&arg);
-                                    return Err(format!(
+                                    return Err(VmError::type_error(format!(
                                         "must be of type {}, {} given",
                                         type_name, given_type
-                                    ));
+                                    )));
                                 }
                                 coerced
                             } else {
@@ -133,106 +142,46 @@ pub fn execute_call<W: std::io::Write>(
             }
         }
 
-        vm.frames.push(frame);
-    } else if super::super::builtins::is_builtin(&func_name) {
+        frame.bind_refs(0, refs);
+        vm.push_frame(frame);
+    } else if vm.is_native_function(&func_name) {
         let result = vm.call_reflection_or_builtin(&func_name, &args)?;
         vm.stack.push(result);
     } else {
-        return Err(format!("undefined function: {}", func_name));
+        return Err(VmError::undefined_function(&func_name));
     }
     Ok(())
 }
 
-fn execute_generator_call<W: std::io::Write>(
+/// Declare a function compiled under `key` by its own name, unless a
+/// function of that name exists already
+pub fn execute_declare_function<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-    func_name: String,
-    args: Vec<Value>,
-) -> Result<(), String> {
-    use crate::runtime::YIELD_COLLECTOR;
-
-    if let Some(func) = vm.get_function(&func_name) {
-        let func = func.clone();
-
-        YIELD_COLLECTOR.with(|collector| {
-            let saved_yields = collector.borrow().yielded_values.clone();
-            let saved_return = collector.borrow().return_value.clone();
-            collector.borrow_mut().clear();
-
-            let stack_base = vm.stack.len();
-            let mut frame = CallFrame::new(func.clone(), stack_base);
-
-            for (i, arg) in args.into_iter().enumerate() {
-                if i < frame.locals.len() {
-                    frame.locals[i] = arg;
-                }
-            }
-
-            vm.frames.push(frame);
-
-            while let Some(current_frame) = vm.frames.last_mut() {
-                if current_frame.ip >= current_frame.function.bytecode.len() {
-                    vm.frames.pop();
-                    break;
-                }
-
-                let opcode = current_frame.function.bytecode[current_frame.ip].clone();
-                current_frame.ip += 1;
-
-                match vm.execute_opcode(opcode) {
-                    Ok(()) => {}
-                    Err(e) => {
-                        if e == "__GENERATOR__" {
-                            continue;
-                        }
-                        if e.starts_with("__RETURN__") {
-                            let return_value = if e == "__RETURN__null" {
-                                Value::Null
-                            } else {
-                                vm.stack.pop().unwrap_or(Value::Null)
-                            };
-                            collector.borrow_mut().return_value = Some(return_value);
-                            vm.frames.pop();
-                            break;
-                        }
-                        vm.frames.pop();
-                        break;
-                    }
-                }
-            }
-
-            let yielded_values = collector.borrow().yielded_values.clone();
-            let return_value = collector.borrow().return_value.clone();
-
-            collector.borrow_mut().yielded_values = saved_yields;
-            collector.borrow_mut().return_value = saved_return;
-
-            let gen = crate::runtime::GeneratorInstance {
-                id: 0,
-                function_name: func_name.clone(),
-                yielded_values,
-                current_index: 0,
-                is_rewound: false,
-                finished: false,
-                return_value,
-                sent_value: None,
-            };
-            vm.stack
-                .push(crate::runtime::Value::Generator(Box::new(gen)));
-            Ok(())
-        })
-    } else {
-        Err(format!("undefined function: {}", func_name))
+    key: &str,
+) -> Result<(), VmError> {
+    let function = vm
+        .functions
+        .get(key)
+        .cloned()
+        .ok_or_else(|| VmError::fatal(format!("Undefined function declaration {}", key)))?;
+    if vm.get_function(&function.name).is_some() || vm.is_native_function(&function.name) {
+        return Err(VmError::fatal(format!(
+            "Cannot redeclare function {}()",
+            function.name
+        )));
     }
+    vm.functions.insert(function.name.clone(), function);
+    Ok(())
 }
 
 pub fn execute_call_builtin<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     func_name: String,
     arg_count: u8,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let mut args = Vec::with_capacity(arg_count as usize);
     for _ in 0..arg_count {
-        args.push(vm.stack.pop().ok_or("Stack underflow")?);
+        args.push(vm.stack.pop().ok_or_else(VmError::stack_underflow)?);
     }
     args.reverse();
 
@@ -244,13 +193,17 @@ pub fn execute_call_builtin<W: std::io::Write>(
 pub fn execute_call_builtin_spread<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     func_name_idx: u32,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let func_name = vm.current_frame().get_string(func_name_idx).to_string();
 
-    let args_array = vm.stack.pop().ok_or("Stack underflow")?;
+    let args_array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let args = match args_array {
         Value::Array(arr) => arr.into_iter().map(|(_, v)| v).collect::<Vec<_>>(),
-        _ => return Err("CallBuiltinSpread expects an array of arguments".to_string()),
+        _ => {
+            return Err(VmError::fatal(
+                "CallBuiltinSpread expects an array of arguments",
+            ))
+        }
     };
 
     let result = vm.call_reflection_or_builtin(&func_name, &args)?;
@@ -261,10 +214,10 @@ pub fn execute_call_builtin_spread<W: std::io::Write>(
 pub fn execute_call_builtin_named<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     func_name_idx: u32,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let func_name = vm.current_frame().get_string(func_name_idx).to_string();
 
-    let args_array = vm.stack.pop().ok_or("Stack underflow")?;
+    let args_array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let named_args = match args_array {
         Value::Array(arr) => arr
             .into_iter()
@@ -276,7 +229,11 @@ pub fn execute_call_builtin_named<W: std::io::Write>(
                 }
             })
             .collect::<Vec<(String, Value)>>(),
-        _ => return Err("CallBuiltinNamed expects an associative array of arguments".to_string()),
+        _ => {
+            return Err(VmError::fatal(
+                "CallBuiltinNamed expects an associative array of arguments",
+            ))
+        }
     };
 
     let args: Vec<Value> = named_args.into_iter().map(|(_, v)| v).collect();
@@ -1,56 +1,90 @@
 use crate::runtime::{ArrayKey, ClosureBody, Value};
+use crate::vm::callables::CallTarget;
+use crate::vm::error::VmError;
 use crate::vm::frame::CallFrame;
 
 pub fn execute_call_callable<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     arg_count: u8,
-) -> Result<(), String> {
-    let callable = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let callable = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
 
     let mut args = Vec::with_capacity(arg_count as usize);
     for _ in 0..arg_count {
-        args.push(vm.stack.pop().ok_or("Stack underflow")?);
+        args.push(vm.stack.pop().ok_or_else(VmError::stack_underflow)?);
     }
     args.reverse();
 
-    match callable {
-        Value::String(func_name) => {
-            if let Some(func) = vm.get_function(&func_name) {
-                let stack_base = vm.stack.len();
-                let mut frame = CallFrame::new(func.clone(), stack_base);
+    vm.autoload_callable_class(&callable);
+    let target = vm.resolve_callable(&callable)?;
+    let function = match &target {
+        CallTarget::Function(func) => Some(func.clone()),
+        CallTarget::Method { method, .. } | CallTarget::StaticMethod { method, .. } => {
+            Some(method.clone())
+        }
+        CallTarget::Closure(closure) => match &closure.body {
+            ClosureBody::FunctionRef(func_name) => vm.get_function(func_name),
+            ClosureBody::MethodRef {
+                class_name,
+                method_name,
+                ..
+            } => vm.find_method_in_chain(class_name, method_name),
+            _ => None,
+        },
+        CallTarget::Native(_) => None,
+    };
+    let refs = vm.pass_args(&mut args, function.as_deref())?;
+    match target {
+        CallTarget::Function(func) => {
+            let stack_base = vm.stack.len();
+            let mut frame = CallFrame::new(func.clone(), stack_base);
 
-                if func.is_variadic && func.param_count > 0 {
-                    let variadic_slot = (func.param_count - 1) as usize;
-                    for i in 0..variadic_slot {
-                        if i < args.len() {
-                            frame.locals[i] = args[i].clone();
-                        }
-                    }
-                    let variadic_args: Vec<(ArrayKey, Value)> = args
-                        .into_iter()
-                        .skip(variadic_slot)
-                        .enumerate()
-                        .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
-                        .collect();
-                    frame.locals[variadic_slot] = Value::Array(variadic_args);
-                } else {
-                    for (i, arg) in args.into_iter().enumerate() {
-                        if i < frame.locals.len() {
-                            frame.locals[i] = arg;
-                        }
+            if func.is_variadic && func.param_count > 0 {
+                let variadic_slot = (func.param_count - 1) as usize;
+                for i in 0..variadic_slot {
+                    if i < args.len() {
+                        frame.locals[i] = args[i].clone();
                     }
                 }
-
-                vm.frames.push(frame);
-            } else if super::super::builtins::is_builtin(&func_name) {
-                let result =
-                    super::super::builtins::call_builtin(&func_name, &args, &mut vm.output)?;
-                vm.stack.push(result);
+                let variadic_args: Vec<(ArrayKey, Value)> = args
+                    .into_iter()
+                    .skip(variadic_slot)
+                    .enumerate()
+                    .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
+                    .collect();
+                frame.locals[variadic_slot] = Value::Array(variadic_args.into());
             } else {
-                return Err(format!("undefined function: {}", func_name));
+                for (i, arg) in args.into_iter().enumerate() {
+                    if i < frame.locals.len() {
+                        frame.locals[i] = arg;
+                    }
+                }
             }
+
+            frame.bind_refs(0, refs);
+            vm.push_frame(frame);
+        }
+        CallTarget::Native(func_name) => {
+            let result = vm.call_reflection_or_builtin(&func_name, &args)?;
+            vm.stack.push(result);
+        }
+        CallTarget::Method { object, method } => {
+            let stack_base = vm.stack.len();
+            let mut frame = CallFrame::new(method, stack_base);
+            frame.locals[0] = object;
+            frame.bind_args(1, args);
+            frame.bind_refs(1, refs);
+            vm.push_frame(frame);
         }
-        Value::Closure(closure) => match &closure.body {
+        CallTarget::StaticMethod { class_name, method } => {
+            let stack_base = vm.stack.len();
+            let mut frame = CallFrame::new(method, stack_base);
+            frame.called_class = Some(class_name);
+            frame.bind_args(0, args);
+            frame.bind_refs(0, refs);
+            vm.push_frame(frame);
+        }
+        CallTarget::Closure(closure) => match &closure.body {
             ClosureBody::FunctionRef(func_name) => {
                 if let Some(func) = vm.get_function(func_name) {
                     let stack_base = vm.stack.len();
@@ -75,19 +109,19 @@ pub fn execute_call_callable<W: std::io::Write>(
                         }
                     }
 
-                    vm.frames.push(frame);
-                } else if super::super::builtins::is_builtin(func_name) {
-                    let result =
-                        super::super::builtins::call_builtin(func_name, &args, &mut vm.output)?;
+                    frame.bind_refs(next_slot, refs);
+                    vm.push_frame(frame);
+                } else if vm.is_native_function(func_name) {
+                    let result = vm.call_reflection_or_builtin(func_name, &args)?;
                     vm.stack.push(result);
                 } else {
-                    return Err(format!("undefined function: {}", func_name));
+                    return Err(VmError::undefined_function(func_name));
                 }
             }
             ClosureBody::Expression(_body_expr) => {
-                return Err(
-                    "Arrow function expression evaluation not yet supported in VM".to_string(),
-                );
+                return Err(VmError::fatal(
+                    "Arrow function expression evaluation not yet supported in VM",
+                ));
             }
             ClosureBody::MethodRef {
                 class_name,
@@ -98,20 +132,14 @@ pub fn execute_call_callable<W: std::io::Write>(
                     let stack_base = vm.stack.len();
                     let mut frame = CallFrame::new(method, stack_base);
                     frame.locals[0] = *object.clone();
-
-                    // Arguments start at index 1 (after $this)
-                    for (i, arg) in args.into_iter().enumerate() {
-                        let slot = i + 1;
-                        if slot < frame.locals.len() {
-                            frame.locals[slot] = arg;
-                        }
-                    }
-                    vm.frames.push(frame);
+                    frame.bind_args(1, args);
+                    frame.bind_refs(1, refs);
+                    vm.push_frame(frame);
                 } else {
-                    return Err(format!(
-                        "Call to undefined method {}::{}",
+                    return Err(VmError::error(format!(
+                        "Call to undefined method {}::{}()",
                         class_name, method_name
-                    ));
+                    )));
                 }
             }
             ClosureBody::StaticMethodRef {
@@ -133,37 +161,16 @@ pub fn execute_call_callable<W: std::io::Write>(
                 {
                     let stack_base = vm.stack.len();
                     let mut frame = CallFrame::new(method, stack_base);
-                    for (i, arg) in args.into_iter().enumerate() {
-                        if i < frame.locals.len() {
-                            frame.locals[i] = arg;
-                        }
-                    }
-                    vm.frames.push(frame);
+                    frame.bind_args(0, args);
+                    vm.push_frame(frame);
                 } else {
-                    return Err(format!(
+                    return Err(VmError::fatal(format!(
                         "Call to undefined static method {}::{}",
                         class_name, method_name
-                    ));
+                    )));
                 }
             }
         },
-        Value::Object(instance) => {
-            let class_name = instance.class_name.clone();
-            if let Some(method) = vm.find_method_in_chain(&class_name, "__invoke") {
-                let stack_base = vm.stack.len();
-                let mut frame = CallFrame::new(method, stack_base);
-                frame.locals[0] = Value::Object(instance.clone());
-                for (i, arg) in args.into_iter().enumerate() {
-                    if i + 1 < frame.locals.len() {
-                        frame.locals[i + 1] = arg;
-                    }
-                }
-                vm.frames.push(frame);
-            } else {
-                return Err(format!("Object of class {} is not callable", class_name));
-            }
-        }
-        _ => return Err(format!("Value is not callable: {:?}", callable)),
     }
     Ok(())
 }
@@ -171,52 +178,50 @@ pub fn execute_call_callable<W: std::io::Write>(
 pub fn execute_call_spread<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     func_name_idx: u32,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let func_name = vm.current_frame().get_string(func_name_idx).to_string();
 
-    let args_array = vm.stack.pop().ok_or("Stack underflow")?;
+    let args_array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let args = match args_array {
         Value::Array(arr) => arr.into_iter().map(|(_, v)| v).collect::<Vec<_>>(),
-        _ => return Err("CallSpread expects an array of arguments".to_string()),
+        _ => return Err(VmError::fatal("CallSpread expects an array of arguments")),
     };
 
     let arg_count = args.len();
 
     if let Some(func) = vm.get_function(&func_name) {
         if (arg_count as u8) < func.required_param_count {
-            return Err(format!(
+            return Err(VmError::argument_count_error(format!(
                 "Too few arguments to function {}(), {} passed in, at least {} expected",
                 func.name, arg_count, func.required_param_count
-            ));
+            )));
         }
 
         for (i, arg) in args.iter().enumerate() {
-            if i < func.param_types.len() {
-                if let Some(ref type_hint) = func.param_types[i] {
-                    let use_strict = func.strict_types || vm.requires_strict_type_check(type_hint);
-                    if use_strict {
-                        if !vm.value_matches_type_strict(arg, type_hint) {
-                            let type_name = vm.format_type_hint(type_hint);
-                            let given_type = vm.get_value_type_name(arg);
-                            return Err(format!(
-                                "Argument {} passed to {}() must be of type {}, {} given",
-                                i + 1,
-                                func.name,
-                                type_name,
-                                given_type
-                            ));
-                        }
-                    } else if !vm.value_matches_type(arg, type_hint) {
+            if let Some(type_hint) = func.param_type(i) {
+                let use_strict = func.strict_types || vm.requires_strict_type_check(type_hint);
+                if use_strict {
+                    if !vm.value_matches_type_strict(arg, type_hint) {
                         let type_name = vm.format_type_hint(type_hint);
                         let given_type = vm.get_value_type_name(arg);
-                        return Err(format!(
+                        return Err(VmError::type_error(format!(
                             "Argument {} passed to {}() must be of type {}, {} given",
                             i + 1,
                             func.name,
                             type_name,
                             given_type
-                        ));
+                        )));
                     }
+                } else if !vm.value_matches_type(arg, type_hint) {
+                    let type_name = vm.format_type_hint(type_hint);
+                    let given_type = vm.get_value_type_name(arg);
+                    return Err(VmError::type_error(format!(
+                        "Argument {} passed to {}() must be of type {}, {} given",
+                        i + 1,
+                        func.name,
+                        type_name,
+                        given_type
+                    )));
                 }
             }
         }
@@ -246,13 +251,24 @@ pub fn execute_call_spread<W: std::io::Write>(
                     frame.locals[i] = coerced_arg;
                 }
             }
+            let variadic_type = func.param_type(variadic_slot);
             let variadic_args: Vec<(ArrayKey, Value)> = args
                 .into_iter()
                 .skip(variadic_slot)
                 .enumerate()
-                .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
+                .map(|(i, v)| {
+                    let v = match variadic_type {
+                        Some(type_hint)
+                            if !func.strict_types && !vm.requires_strict_type_check(type_hint) =>
+                        {
+                            vm.coerce_value_to_type(v, type_hint)
+                        }
+                        _ => v,
+                    };
+                    (ArrayKey::Integer(i as i64), v)
+                })
                 .collect();
-            frame.locals[variadic_slot] = Value::Array(variadic_args);
+            frame.locals[variadic_slot] = Value::Array(variadic_args.into());
         } else {
             for (i, arg) in args.into_iter().enumerate() {
                 if i < frame.locals.len() {
@@ -276,30 +292,38 @@ pub fn execute_call_spread<W: std::io::Write>(
             }
         }
 
-        vm.frames.push(frame);
-    } else if super::super::builtins::is_builtin(&func_name) {
+        vm.push_frame(frame);
+    } else if vm.is_native_function(&func_name) {
         let result = vm.call_reflection_or_builtin(&func_name, &args)?;
         vm.stack.push(result);
     } else {
-        return Err(format!("undefined function: {}", func_name));
+        return Err(VmError::undefined_function(&func_name));
     }
     Ok(())
 }
 
 pub fn execute_create_method_closure<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let method_name = vm.stack.pop().ok_or("Stack underflow")?;
-    let object = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let method_name = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let object = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
 
     let method_name_str = match method_name {
         Value::String(s) => s,
-        _ => return Err("CreateMethodClosure expects method name as string".to_string()),
+        _ => {
+            return Err(VmError::fatal(
+                "CreateMethodClosure expects method name as string",
+            ))
+        }
     };
 
     let class_name = match &object {
-        Value::Object(instance) => instance.class_name.clone(),
-        _ => return Err("CreateMethodClosure expects object on stack".to_string()),
+        Value::Object(instance) => instance.class_name().to_string(),
+        _ => {
+            return Err(VmError::fatal(
+                "CreateMethodClosure expects object on stack",
+            ))
+        }
     };
 
     let closure = crate::runtime::Closure {
@@ -318,18 +342,26 @@ pub fn execute_create_method_closure<W: std::io::Write>(
 
 pub fn execute_create_static_method_closure<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let method_name = vm.stack.pop().ok_or("Stack underflow")?;
-    let class_name = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let method_name = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let class_name = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
 
     let method_name_str = match method_name {
         Value::String(s) => s,
-        _ => return Err("CreateStaticMethodClosure expects method name as string".to_string()),
+        _ => {
+            return Err(VmError::fatal(
+                "CreateStaticMethodClosure expects method name as string",
+            ))
+        }
     };
 
     let class_name_str = match class_name {
         Value::String(s) => s,
-        _ => return Err("CreateStaticMethodClosure expects class name as string".to_string()),
+        _ => {
+            return Err(VmError::fatal(
+                "CreateStaticMethodClosure expects class name as string",
+            ))
+        }
     };
 
     let closure = crate::runtime::Closure {
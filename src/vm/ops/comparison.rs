@@ -1,70 +1,71 @@
 use crate::runtime::Value;
+use crate::vm::error::VmError;
 
-pub fn execute_eq<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_eq<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     vm.stack.push(Value::Bool(left.loose_equals(&right)));
     Ok(())
 }
 
-pub fn execute_ne<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_ne<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     vm.stack.push(Value::Bool(!left.loose_equals(&right)));
     Ok(())
 }
 
-pub fn execute_identical<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_identical<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     vm.stack.push(Value::Bool(left.type_equals(&right)));
     Ok(())
 }
 
 pub fn execute_not_identical<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     vm.stack.push(Value::Bool(!left.type_equals(&right)));
     Ok(())
 }
 
-pub fn execute_lt<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_lt<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = vm.compare_values(&left, &right)? < 0;
     vm.stack.push(Value::Bool(result));
     Ok(())
 }
 
-pub fn execute_le<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_le<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = vm.compare_values(&left, &right)? <= 0;
     vm.stack.push(Value::Bool(result));
     Ok(())
 }
 
-pub fn execute_gt<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_gt<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = vm.compare_values(&left, &right)? > 0;
     vm.stack.push(Value::Bool(result));
     Ok(())
 }
 
-pub fn execute_ge<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_ge<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = vm.compare_values(&left, &right)? >= 0;
     vm.stack.push(Value::Bool(result));
     Ok(())
 }
 
-pub fn execute_spaceship<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_spaceship<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let result = vm.compare_values(&left, &right)?;
     vm.stack.push(Value::Integer(result));
     Ok(())
@@ -1,6 +1,8 @@
 use crate::ast::TypeHint;
 use crate::runtime::Value;
-use crate::runtime::YIELD_COLLECTOR;
+use crate::vm::error::VmError;
+use crate::vm::execution::ControlFlow;
+use crate::vm::frame::Completion;
 
 pub fn execute_jump<W: std::io::Write>(vm: &mut super::super::VM<W>, offset: u32) {
     vm.current_frame_mut().jump_to(offset as usize);
@@ -9,8 +11,8 @@ pub fn execute_jump<W: std::io::Write>(vm: &mut super::super::VM<W>, offset: u32
 pub fn execute_jump_if_false<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     offset: u32,
-) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     if !value.to_bool() {
         vm.current_frame_mut().jump_to(offset as usize);
     }
@@ -20,8 +22,8 @@ pub fn execute_jump_if_false<W: std::io::Write>(
 pub fn execute_jump_if_true<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     offset: u32,
-) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     if value.to_bool() {
         vm.current_frame_mut().jump_to(offset as usize);
     }
@@ -31,8 +33,11 @@ pub fn execute_jump_if_true<W: std::io::Write>(
 pub fn execute_jump_if_null<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     offset: u32,
-) -> Result<(), String> {
-    let value = vm.stack.last().ok_or("Stack is empty")?;
+) -> Result<(), VmError> {
+    let value = vm
+        .stack
+        .last()
+        .ok_or_else(|| VmError::fatal("Stack is empty"))?;
     if matches!(value, Value::Null) {
         vm.current_frame_mut().jump_to(offset as usize);
     }
@@ -42,105 +47,56 @@ pub fn execute_jump_if_null<W: std::io::Write>(
 pub fn execute_jump_if_not_null<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     offset: u32,
-) -> Result<(), String> {
-    let value = vm.stack.last().ok_or("Stack is empty")?;
+) -> Result<(), VmError> {
+    let value = vm
+        .stack
+        .last()
+        .ok_or_else(|| VmError::fatal("Stack is empty"))?;
     if !matches!(value, Value::Null) {
         vm.current_frame_mut().jump_to(offset as usize);
     }
     Ok(())
 }
 
-pub fn execute_return<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
+pub fn execute_return<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<ControlFlow, VmError> {
     if let Some(ref return_type) = vm.current_frame().function.return_type.clone() {
         if matches!(return_type, TypeHint::Void) {
-            return Err(format!(
+            return Err(VmError::type_error(format!(
                 "{}(): Return value must be of type void",
                 vm.current_frame().function.name
-            ));
+            )));
         }
         let return_value = vm.stack.last().cloned().unwrap_or(Value::Null);
         if !vm.value_matches_type_strict(&return_value, return_type) {
             let type_name = vm.format_type_hint(return_type);
             let given_type = vm.get_value_type_name(&return_value);
-            return Err(format!(
+            return Err(VmError::type_error(format!(
                 "Return value must be of type {}, {} returned",
                 type_name, given_type
-            ));
+            )));
         }
     }
-    Err("__RETURN__".to_string())
-}
-
-pub fn execute_yield<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let value = vm.stack.pop().unwrap_or(Value::Null);
-
-    let key = if vm
-        .stack
-        .last()
-        .map(|v| !matches!(v, Value::Null))
-        .unwrap_or(false)
-    {
-        Some(vm.stack.pop().ok_or("Stack underflow")?)
-    } else {
-        None
-    };
-
-    // Store the yielded value in the collector
-    YIELD_COLLECTOR.with(|collector| {
-        collector
-            .borrow_mut()
-            .yielded_values
-            .push((key, Some(value.clone())));
-    });
-
-    // Push the sent value onto the stack (for $sent = yield $value syntax)
-    // The sent value was stored in the generator's sent_value field
-    // For now, we just push null as the sent value result
-    vm.stack.push(Value::Null);
-
-    Err("__GENERATOR__".to_string())
-}
-
-pub fn execute_yield_from<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let iterable = vm.stack.pop().unwrap_or(Value::Null);
-
-    let yielded_values: Vec<(Option<Value>, Option<Value>)> = match iterable {
-        Value::Array(arr) => arr
-            .into_iter()
-            .map(|(k, v)| (Some(k.to_value()), Some(v)))
-            .collect(),
-        Value::Generator(gen) => gen.yielded_values.into_iter().collect(),
-        _ => Vec::new(),
-    };
-
-    YIELD_COLLECTOR.with(|collector| {
-        collector.borrow_mut().yielded_values.extend(yielded_values);
-    });
-
-    Ok(())
+    Ok(ControlFlow::Return)
 }
 
-pub fn execute_return_null<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
+pub fn execute_return_null<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<ControlFlow, VmError> {
     if let Some(ref return_type) = vm.current_frame().function.return_type.clone() {
         if !matches!(return_type, TypeHint::Void)
             && !vm.value_matches_type_strict(&Value::Null, return_type)
         {
             let type_name = vm.format_type_hint(return_type);
-            return Err(format!(
+            return Err(VmError::type_error(format!(
                 "Return value must be of type {}, null returned",
                 type_name
-            ));
+            )));
         }
     }
-    Err("__RETURN__null".to_string())
-}
-
-pub fn execute_break<W: std::io::Write>(_vm: &mut super::super::VM<W>) -> Result<(), String> {
-    Err("__BREAK__".to_string())
-}
-
-pub fn execute_continue<W: std::io::Write>(_vm: &mut super::super::VM<W>) -> Result<(), String> {
-    Err("__CONTINUE__".to_string())
+    vm.stack.push(Value::Null);
+    Ok(ControlFlow::Return)
 }
 
 pub fn execute_loop_start<W: std::io::Write>(
@@ -152,9 +108,71 @@ pub fn execute_loop_start<W: std::io::Write>(
         continue_target,
         break_target,
         stack_depth: vm.stack.len(),
+        frame_depth: vm.frames.len(),
     });
 }
 
 pub fn execute_loop_end<W: std::io::Write>(vm: &mut super::super::VM<W>) {
     vm.loops.pop();
 }
+
+/// Finish a `break` or `continue`: run the finally blocks of the try
+/// statements it leaves inside the loop, then jump out of the iteration
+pub fn execute_loop_jump<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    completion: Completion,
+) -> Result<(), VmError> {
+    let loop_depth = vm.loops.len();
+    let Some(completion) =
+        super::exit_through_finally(vm, completion, |handler| handler.loop_depth >= loop_depth)
+    else {
+        return Ok(());
+    };
+    let is_break = matches!(completion, Completion::Break);
+    let depth = vm.frames.len();
+    let in_loop = vm.loops.last().is_some_and(|l| l.frame_depth == depth);
+    if !in_loop {
+        let keyword = if is_break { "break" } else { "continue" };
+        return Err(VmError::fatal(format!(
+            "Cannot {} outside of loop",
+            keyword
+        )));
+    }
+    let loop_ctx = vm
+        .loops
+        .pop()
+        .ok_or_else(|| VmError::fatal("No loop context"))?;
+    // Finally blocks inside the loop are left unfinished
+    let frame = vm.current_frame_mut();
+    while frame
+        .finally_blocks
+        .last()
+        .is_some_and(|block| block.loop_depth >= loop_depth)
+    {
+        frame.finally_blocks.pop();
+    }
+    let target = if is_break {
+        loop_ctx.break_target
+    } else {
+        loop_ctx.continue_target
+    };
+    frame.jump_to(target as usize);
+    Ok(())
+}
+
+/// No arm of a match took the subject: throw UnhandledMatchError, showing
+/// a scalar subject as a stack trace would and anything else by its type
+pub fn execute_unhandled_match<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<(), VmError> {
+    let subject = vm.stack.pop().ok_or_else(VmError::stack_underflow)?.unref();
+    let case = match &subject {
+        Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Float(_) | Value::String(_) => {
+            crate::vm::error_location::preview(&subject)
+        }
+        Value::Object(object) => format!("of type {}", object.class_name()),
+        other => format!("of type {}", other.type_name()),
+    };
+    let message = format!("Unhandled match case {}", case);
+    super::throw_error(vm, "UnhandledMatchError", &message)
+}
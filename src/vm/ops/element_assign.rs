@@ -0,0 +1,130 @@
+//! Assigning to array elements at any depth: `$a[$k] = $v`, `$a[] = $v`
+//! and `$a['x']['y'] = $v`
+//!
+//! The array is changed where it is, level by level, so an element copies
+//! the array holding it only while another value shares that array. A
+//! null container becomes an empty array, as does `false`, with a
+//! deprecation; a string takes the value's first byte at an offset.
+
+use super::arrays::assign_string_offset;
+use crate::runtime::{ArrayKey, PhpArray, Value};
+use crate::vm::error::VmError;
+use crate::vm::notices::Level;
+
+/// `$local[$k1]...[$kn] = $value` (or `[]` last if `append`), in place
+/// (stack: keys, value -> value)
+pub fn execute_assign_dim_fast<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    slot: u16,
+    depth: u8,
+    append: bool,
+) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let keys = pop_keys(vm, depth)?;
+    let mut container = vm
+        .current_frame_mut()
+        .with_local(slot, |local| std::mem::replace(local, Value::Null));
+    let assigned = assign_dim(vm, &mut container, &keys, append, value.clone());
+    vm.current_frame_mut().set_local(slot, container);
+    assigned?;
+    vm.stack.push(value);
+    Ok(())
+}
+
+/// The same on a container to store back (stack: keys, value, container
+/// -> value, container)
+pub fn execute_assign_dim<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    depth: u8,
+    append: bool,
+) -> Result<(), VmError> {
+    let mut container = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let keys = pop_keys(vm, depth)?;
+    assign_dim(vm, &mut container, &keys, append, value.clone())?;
+    vm.stack.extend([value, container]);
+    Ok(())
+}
+
+/// The `depth` keys on top of the stack, outermost first
+fn pop_keys<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    depth: u8,
+) -> Result<Vec<Value>, VmError> {
+    let start = vm
+        .stack
+        .len()
+        .checked_sub(depth as usize)
+        .ok_or_else(VmError::stack_underflow)?;
+    Ok(vm.stack.split_off(start))
+}
+
+/// Assign `value` to the element of `container` at `keys`, appending it
+/// to the innermost array if `append`
+fn assign_dim<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    container: &mut Value,
+    keys: &[Value],
+    append: bool,
+    value: Value,
+) -> Result<(), VmError> {
+    let Some((key, rest)) = keys.split_first() else {
+        if matches!(container, Value::String(_)) {
+            return Err(VmError::error("[] operator not supported for strings"));
+        }
+        return array_for_write(vm, container)?
+            .push(value)
+            .map_err(VmError::error);
+    };
+    let last = rest.is_empty() && !append;
+    if let Value::String(s) = container {
+        if !last {
+            return Err(VmError::fatal("Cannot use string offset as an array"));
+        }
+        *container = Value::String(assign_string_offset(vm, s.clone(), key, &value)?);
+        return Ok(());
+    }
+    let array = array_for_write(vm, container)?;
+    let key = ArrayKey::from_value(key);
+    if last {
+        array.insert(key, value);
+        return Ok(());
+    }
+    // The element is taken out while it changes, so that nothing else
+    // shares it
+    let mut element = array.get_mut(&key).map_or(Value::Null, |element| {
+        std::mem::replace(element, Value::Null)
+    });
+    let assigned = assign_dim(vm, &mut element, rest, append, value);
+    array.insert(key, element);
+    assigned
+}
+
+/// The array `container` holds, making one of null or `false`
+fn array_for_write<'c, W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    container: &'c mut Value,
+) -> Result<&'c mut PhpArray, VmError> {
+    match container {
+        Value::Null => *container = Value::Array(PhpArray::new()),
+        Value::Bool(false) => {
+            vm.raise(
+                Level::Deprecated,
+                "Automatic conversion of false to array is deprecated",
+            )?;
+            *container = Value::Array(PhpArray::new());
+        }
+        Value::Array(_) => {}
+        Value::Object(instance) => {
+            return Err(VmError::fatal(format!(
+                "Cannot use object of type {} as array",
+                instance.class_name()
+            )))
+        }
+        _ => return Err(VmError::fatal("Cannot use a scalar value as an array")),
+    }
+    match container {
+        Value::Array(array) => Ok(array),
+        _ => unreachable!("the container was made an array"),
+    }
+}
@@ -0,0 +1,123 @@
+//! Methods of enums
+//!
+//! A case's methods run with the case as `$this`. The static `cases()`,
+//! `from()` and `tryFrom()` are built in; the enum's own static methods
+//! come after them.
+
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::VmError;
+use crate::vm::frame::CallFrame;
+
+/// Call the method `method_name` of the enum case `case`
+pub fn call_enum_method<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    case: Value,
+    method_name: &str,
+    args: Vec<Value>,
+) -> Result<(), VmError> {
+    let Value::EnumCase { enum_name, .. } = &case else {
+        return Err(VmError::fatal("Cannot call method on non-object"));
+    };
+    let enum_name = enum_name.clone();
+    let method = vm
+        .enums
+        .get(&enum_name)
+        .and_then(|enum_def| {
+            enum_def
+                .methods
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(method_name))
+                .map(|(_, method)| method.clone())
+        })
+        .ok_or_else(|| {
+            VmError::error(format!(
+                "Call to undefined method {}::{}()",
+                enum_name, method_name
+            ))
+        })?;
+
+    let stack_base = vm.stack.len();
+    let mut frame = CallFrame::new(method, stack_base);
+    frame.called_class = Some(enum_name);
+    frame.locals[0] = case;
+    frame.bind_args(1, args);
+    vm.push_frame(frame);
+    Ok(())
+}
+
+/// Call the static method `method_name` of the enum `enum_name`
+pub fn call_static_enum_method<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    enum_name: &str,
+    method_name: &str,
+    args: Vec<Value>,
+) -> Result<(), VmError> {
+    let enum_def = vm
+        .enums
+        .get(enum_name)
+        .cloned()
+        .ok_or_else(|| VmError::fatal(format!("Enum '{}' not found", enum_name)))?;
+    let case = |name: &String, backing: &Option<Value>| Value::EnumCase {
+        enum_name: enum_name.to_string(),
+        case_name: name.clone(),
+        backing_value: backing.clone().map(Box::new),
+    };
+    match method_name {
+        "cases" => {
+            let cases: Vec<(ArrayKey, Value)> = enum_def
+                .case_order
+                .iter()
+                .enumerate()
+                .filter_map(|(i, name)| {
+                    enum_def
+                        .cases
+                        .get(name)
+                        .map(|value| (ArrayKey::Integer(i as i64), case(name, value)))
+                })
+                .collect();
+            vm.stack.push(Value::Array(cases.into()));
+        }
+        "from" | "tryFrom" => {
+            let search_value = args.first().ok_or_else(|| {
+                VmError::fatal(format!("{}() requires exactly one argument", method_name))
+            })?;
+            let found = enum_def
+                .cases
+                .iter()
+                .find(|(_, backing)| backing.as_ref() == Some(search_value));
+            match found {
+                Some((name, backing)) => vm.stack.push(case(name, backing)),
+                None if method_name == "tryFrom" => vm.stack.push(Value::Null),
+                None => {
+                    let value_str = match search_value {
+                        Value::String(s) => format!("\"{}\"", s),
+                        other => other.to_string_val(),
+                    };
+                    return Err(VmError::value_error(format!(
+                        "{} is not a valid backing value for enum {}",
+                        value_str, enum_name
+                    )));
+                }
+            }
+        }
+        _ => {
+            let method = enum_def
+                .static_methods
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(method_name))
+                .map(|(_, method)| method.clone())
+                .ok_or_else(|| {
+                    VmError::error(format!(
+                        "Call to undefined method {}::{}()",
+                        enum_name, method_name
+                    ))
+                })?;
+            let stack_base = vm.stack.len();
+            let mut frame = CallFrame::new(method, stack_base);
+            frame.called_class = Some(enum_name.to_string());
+            frame.bind_args(0, args);
+            vm.push_frame(frame);
+        }
+    }
+    Ok(())
+}
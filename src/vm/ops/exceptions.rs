@@ -1,5 +1,12 @@
-use crate::runtime::Value;
-use crate::vm::frame::ExceptionHandler;
+mod chaining;
+mod engine_errors;
+
+pub use engine_errors::*;
+
+use crate::runtime::{ArrayKey, ObjectRef, PhpArray, Value};
+use crate::vm::error::VmError;
+use crate::vm::execution::ControlFlow;
+use crate::vm::frame::{Completion, ExceptionHandler, PendingFinally};
 
 /// Capture backtrace information from the current call stack
 pub fn capture_backtrace(frames: &[super::super::CallFrame]) -> Value {
@@ -24,16 +31,15 @@ pub fn capture_backtrace(frames: &[super::super::CallFrame]) -> Value {
         // Create trace frame array with file, line, function, class, type, args
         let mut frame_array: Vec<(crate::runtime::ArrayKey, Value)> = Vec::new();
 
-        // File - use placeholder for now (would need debug info to get actual file)
+        // Where execution is in the frame's function
+        let line = frame.function.line_at(frame.ip.saturating_sub(1));
         frame_array.push((
             crate::runtime::ArrayKey::String("file".to_string()),
-            Value::String("".to_string()),
+            Value::String(frame.function.file.clone()),
         ));
-
-        // Line - use placeholder for now
         frame_array.push((
             crate::runtime::ArrayKey::String("line".to_string()),
-            Value::Integer(0),
+            Value::Integer(line.unwrap_or(0) as i64),
         ));
 
         // Function name
@@ -59,169 +65,406 @@ pub fn capture_backtrace(frames: &[super::super::CallFrame]) -> Value {
         // Args - empty array for now (capturing actual args is more complex)
         frame_array.push((
             crate::runtime::ArrayKey::String("args".to_string()),
-            Value::Array(Vec::new()),
+            Value::Array(PhpArray::new()),
         ));
 
         trace_array.push((
             crate::runtime::ArrayKey::Integer(idx as i64),
-            Value::Array(frame_array),
+            Value::Array(frame_array.into()),
         ));
     }
 
-    Value::Array(trace_array)
+    Value::Array(trace_array.into())
 }
 
-pub fn execute_throw<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let mut exception = vm.stack.pop().ok_or("Stack underflow")?;
-
-    // Capture backtrace before modifying frames
-    let backtrace = capture_backtrace(&vm.frames);
-
-    // Store backtrace in the exception object
-    if let Value::Object(ref mut obj) = &mut exception {
-        obj.properties.insert("trace".to_string(), backtrace);
+pub fn execute_throw<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let exception = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+
+    // An exception records where it was created; one native code made
+    // outside any frame takes where it is first thrown, before unwinding
+    // the frames
+    if let Value::Object(obj) = &exception {
+        if string_property(obj, "file").is_empty() {
+            let (file, line) = match vm.current_location() {
+                Some(location) => (location.file, location.line),
+                None => (String::from("Unknown"), 0),
+            };
+            obj.set("file", Value::String(file));
+            obj.set("line", Value::Integer(line as i64));
+        }
+        let traced = matches!(obj.get("trace"), Some(Value::Array(frames)) if !frames.is_empty());
+        if !traced {
+            obj.set("trace", capture_backtrace(&vm.frames));
+        }
     }
 
-    let current_frame_depth = vm.frames.len();
-    let current_ip = vm.current_frame().ip;
+    let caught = vm
+        .frames
+        .iter()
+        .flat_map(|frame| &frame.handlers)
+        .any(|handler| handler.catch_offset != 0 && !handler.in_catch);
+    vm.debug_exception(&exception, !caught)?;
 
-    let mut handler_info: Option<(usize, usize, usize)> = None;
+    throw_value(vm, exception)
+}
 
-    for (handler_idx, handler) in vm.handlers.iter().enumerate().rev() {
-        if handler.frame_depth > current_frame_depth {
-            continue;
-        }
+/// Create a built-in Throwable with a message, as `new $class($message)`
+/// would
+pub fn new_throwable<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    class_name: &str,
+    message: &str,
+) -> Result<ObjectRef, VmError> {
+    super::execute_new_object(vm, class_name.to_string())?;
+    let Some(Value::Object(throwable)) = vm.stack.pop() else {
+        return Err(VmError::fatal(format!("Cannot create {}", class_name)));
+    };
+    throwable.set("message", Value::String(message.to_string()));
+    Ok(throwable)
+}
 
-        let handler_is_active = if handler.frame_depth == current_frame_depth {
-            current_ip >= handler.try_start as usize
-                && (handler.try_end == 0 || current_ip < handler.try_end as usize)
-        } else {
-            handler.try_end == 0 || handler.try_end > handler.try_start
-        };
+/// Hand a thrown exception to the innermost handler: a catch block that
+/// isn't running yet takes it, otherwise the handler's finally block runs
+/// and throws it on when it ends. Fails with the error for an uncaught
+/// exception if no frame has a handler left.
+pub fn throw_value<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    exception: Value,
+) -> Result<(), VmError> {
+    let target = vm.frames.iter().rposition(|f| !f.handlers.is_empty());
+    chaining::chain_abandoned(vm, &exception, target);
+    let Some(frame_depth) = target else {
+        return Err(VmError::Uncaught(uncaught_error(vm, &exception)));
+    };
+    while vm.frames.len() > frame_depth + 1 {
+        vm.pop_frame();
+    }
 
-        if handler_is_active {
-            handler_info = Some((
-                handler.catch_offset as usize,
-                handler.frame_depth,
-                handler_idx,
-            ));
-            break;
-        }
+    let frame = vm.current_frame_mut();
+    let Some(handler) = frame.handlers.last().cloned() else {
+        return Err(VmError::Uncaught(uncaught_error(vm, &exception)));
+    };
+    // Finally blocks the exception jumps out of won't end
+    let depth = frame.handlers.len() - 1;
+    while frame
+        .finally_blocks
+        .last()
+        .is_some_and(|block| block.handler_depth > depth)
+    {
+        frame.finally_blocks.pop();
     }
 
-    if let Some((catch_offset, target_frame_depth, handler_idx)) = handler_info {
-        while vm.frames.len() > target_frame_depth {
-            vm.frames.pop();
+    if handler.catch_offset != 0 && !handler.in_catch {
+        if handler.finally_offset == 0 {
+            frame.handlers.pop();
+        } else {
+            frame.handlers[depth].in_catch = true;
         }
+        frame.jump_to(handler.catch_offset as usize);
+        vm.stack.truncate(handler.stack_depth);
+        vm.loops.truncate(handler.loop_depth);
+        vm.silence_depth = handler.silence_depth;
+        vm.stack.push(exception);
+    } else {
+        frame.handlers.pop();
+        vm.silence_depth = handler.silence_depth;
+        enter_finally(vm, &handler, Completion::Throw(exception));
+    }
+    Ok(())
+}
 
-        if let Some(handler) = vm.handlers.get_mut(handler_idx) {
-            if handler.try_end == 0 {
-                handler.try_end = current_ip as u32;
-            }
+/// Leave the try statements of the current frame that `leaves` picks,
+/// innermost first, through the first finally block among them, which
+/// ends with `completion`. Gives `completion` back if there is none.
+pub fn exit_through_finally<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    completion: Completion,
+    leaves: impl Fn(&ExceptionHandler) -> bool,
+) -> Option<Completion> {
+    let frame = vm.frames.last_mut()?;
+    while frame.handlers.last().is_some_and(&leaves) {
+        let handler = frame.handlers.pop()?;
+        if handler.finally_offset != 0 {
+            enter_finally(vm, &handler, completion);
+            return None;
         }
+    }
+    Some(completion)
+}
 
-        vm.stack.push(exception);
-        if let Some(frame) = vm.frames.last_mut() {
-            frame.jump_to(catch_offset);
-        }
-    } else {
-        let (error_msg, trace_output) = if let Value::Object(ref obj) = exception {
-            let message = if let Some(msg_value) = obj.properties.get("message") {
-                match msg_value {
-                    Value::String(s) if !s.is_empty() => s.clone(),
-                    _ => String::new(),
-                }
-            } else {
-                String::new()
-            };
+/// Jump past the `FinallyStart` of a handler that was just popped, to
+/// run its finally block for a jump out of the try statement
+fn enter_finally<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    handler: &ExceptionHandler,
+    completion: Completion,
+) {
+    vm.stack.truncate(handler.stack_depth);
+    vm.loops.truncate(handler.loop_depth);
+    let frame = vm.current_frame_mut();
+    let handler_depth = frame.handlers.len();
+    frame.finally_blocks.push(PendingFinally {
+        completion,
+        loop_depth: handler.loop_depth,
+        handler_depth,
+    });
+    frame.jump_to(handler.finally_offset as usize + 1);
+}
 
-            let file = if let Some(file_value) = obj.properties.get("__file") {
-                match file_value {
-                    Value::String(s) if !s.is_empty() => s.clone(),
-                    _ => "unknown".to_string(),
-                }
-            } else {
-                "unknown".to_string()
-            };
+/// The error an uncaught exception stops the script with
+fn uncaught_error<W: std::io::Write>(vm: &super::super::VM<W>, exception: &Value) -> String {
+    let (error_msg, trace_output) = if let Value::Object(obj) = exception {
+        let message = match obj.get("message") {
+            Some(Value::String(s)) if !s.is_empty() => s,
+            _ => String::new(),
+        };
 
-            let line = if let Some(line_value) = obj.properties.get("__line") {
-                match line_value {
-                    Value::Integer(n) => *n,
-                    _ => 0,
-                }
-            } else {
-                0
-            };
+        let file = match obj.get("file") {
+            Some(Value::String(s)) if !s.is_empty() => s,
+            _ => "unknown".to_string(),
+        };
 
-            let class_name = obj.class_name.clone();
-
-            // Get the call stack - format each frame
-            let mut trace_lines: Vec<String> = Vec::new();
-            for (i, frame) in vm.frames.iter().enumerate() {
-                let func_name = frame.function.name.clone();
-                let loc = if let Some(pos) = func_name.rfind("::") {
-                    let class_part = &func_name[..pos];
-                    let method_part = &func_name[pos + 2..];
-                    format!("{}->{}", class_part, method_part)
-                } else {
-                    func_name
-                };
-                trace_lines.push(format!("#{} [{}:{}] {}", i, file, line, loc));
-            }
+        let line = match obj.get("line") {
+            Some(Value::Integer(n)) => n,
+            _ => 0,
+        };
 
-            let trace_output = trace_lines.join("\n");
+        let class_name = obj.class_name().to_string();
 
-            let base_msg = if !message.is_empty() {
-                format!("{}: {} in {} on line {}", class_name, message, file, line)
+        // Get the call stack - format each frame
+        let mut trace_lines: Vec<String> = Vec::new();
+        for (i, frame) in vm.frames.iter().enumerate() {
+            let func_name = frame.function.name.clone();
+            let loc = if let Some(pos) = func_name.rfind("::") {
+                let class_part = &func_name[..pos];
+                let method_part = &func_name[pos + 2..];
+                format!("{}->{}", class_part, method_part)
             } else {
-                format!("Uncaught {}", class_name)
+                func_name
             };
+            trace_lines.push(format!("#{} [{}:{}] {}", i, file, line, loc));
+        }
 
-            (base_msg, Some(trace_output))
+        let trace_output = trace_lines.join("\n");
+
+        let base_msg = if !message.is_empty() {
+            format!("{}: {} in {} on line {}", class_name, message, file, line)
         } else {
-            (format!("Uncaught exception: {:?}", exception), None)
+            format!("Uncaught {}", class_name)
         };
 
-        if let Some(trace) = trace_output {
-            return Err(format!("{}\n\nStack trace:\n{}", error_msg, trace));
-        } else {
-            return Err(error_msg);
+        (base_msg, Some(trace_output))
+    } else {
+        (format!("Uncaught exception: {:?}", exception), None)
+    };
+
+    match trace_output {
+        Some(trace) => format!("{}\n\nStack trace:\n{}", error_msg, trace),
+        None => error_msg,
+    }
+}
+
+/// Format trace array as a string for Exception::getTraceAsString()
+pub fn format_trace_as_string(trace: &Value) -> String {
+    match trace {
+        Value::Array(frames) => {
+            let mut lines = Vec::new();
+            for (i, (_, frame_value)) in frames.iter().enumerate() {
+                if let Value::Array(frame) = frame_value {
+                    let mut line = format!("#{} ", i);
+
+                    // Helper function to get string value from frame array
+                    let get_string = |key: &str| -> &str {
+                        for (k, v) in frame.iter() {
+                            if k == &ArrayKey::String(key.to_string()) {
+                                if let Value::String(s) = v {
+                                    return s.as_str();
+                                }
+                            }
+                        }
+                        ""
+                    };
+
+                    let get_int = |key: &str| -> i64 {
+                        for (k, v) in frame.iter() {
+                            if k == &ArrayKey::String(key.to_string()) {
+                                if let Value::Integer(n) = v {
+                                    return *n;
+                                }
+                            }
+                        }
+                        0
+                    };
+
+                    // Get class and type
+                    let class_name = get_string("class");
+                    let type_sep = get_string("type");
+
+                    // Get function name
+                    let function_name = get_string("function");
+
+                    // Format class::method() or class->method() or just function()
+                    if !class_name.is_empty() {
+                        line.push_str(class_name);
+                        if !type_sep.is_empty() {
+                            line.push_str(type_sep);
+                        } else {
+                            line.push_str("::");
+                        }
+                    }
+                    line.push_str(function_name);
+                    line.push_str("()");
+
+                    // Get file and line
+                    let file = get_string("file");
+                    let line_num = get_int("line");
+
+                    line.push_str(&format!(" at {}:{}", file, line_num));
+                    lines.push(line);
+                }
+            }
+            lines.join("\n")
         }
+        _ => String::new(),
     }
+}
+
+/// Format a Throwable as its __toString() does: the previous ones first,
+/// each followed by "Next" and the exception it led to
+pub fn format_throwable(throwable: &ObjectRef) -> String {
+    let mut chain = vec![throwable.clone()];
+    while let Some(Value::Object(previous)) = chain[chain.len() - 1].get("previous") {
+        if chain.iter().any(|throwable| throwable.ptr_eq(&previous)) {
+            break;
+        }
+        chain.push(previous);
+    }
+    chain
+        .iter()
+        .rev()
+        .map(|throwable| {
+            let message = string_property(throwable, "message");
+            let line = throwable.get("line").map_or(0, |line| line.to_int());
+            let origin = format!("{}:{}", string_property(throwable, "file"), line);
+            let trace = throwable
+                .get("trace")
+                .map(|trace| format_trace_as_string(&trace))
+                .unwrap_or_default();
+            if message.is_empty() {
+                format!(
+                    "{} in {}\nStack trace:\n{}",
+                    throwable.class_name(),
+                    origin,
+                    trace
+                )
+            } else {
+                format!(
+                    "{}: {} in {}\nStack trace:\n{}",
+                    throwable.class_name(),
+                    message,
+                    origin,
+                    trace
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\nNext ")
+}
+
+/// A property of a Throwable as a string (empty if it isn't set)
+fn string_property(throwable: &ObjectRef, name: &str) -> String {
+    throwable
+        .get(name)
+        .map(|value| value.to_string_val())
+        .unwrap_or_default()
+}
+
+pub fn execute_format_trace<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<(), VmError> {
+    let trace = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    vm.stack.push(Value::String(format_trace_as_string(&trace)));
     Ok(())
 }
 
+pub fn execute_format_throwable<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<(), VmError> {
+    match vm.stack.pop().ok_or_else(VmError::stack_underflow)? {
+        Value::Object(throwable) => {
+            vm.stack.push(Value::String(format_throwable(&throwable)));
+            Ok(())
+        }
+        _ => Err(VmError::fatal("Cannot format a non-object as a Throwable")),
+    }
+}
+
 pub fn execute_try_start<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     catch_offset: u32,
     finally_offset: u32,
 ) {
-    let try_start = vm.current_frame().ip as u32;
-    let frame_depth = vm.frames.len();
-    vm.handlers.push(ExceptionHandler {
-        try_start,
-        try_end: 0,
+    let handler = ExceptionHandler {
+        try_start: vm.current_frame().ip as u32,
         catch_offset,
         catch_class: String::new(),
         catch_var: String::new(),
         finally_offset,
         stack_depth: vm.stack.len(),
-        frame_depth,
-    });
+        loop_depth: vm.loops.len(),
+        in_catch: false,
+        silence_depth: vm.silence_depth,
+    };
+    vm.current_frame_mut().handlers.push(handler);
 }
 
 pub fn execute_try_end<W: std::io::Write>(vm: &mut super::super::VM<W>) {
-    let current_ip = vm.current_frame().ip as u32;
-    if let Some(handler) = vm.handlers.last_mut() {
-        handler.try_end = current_ip;
-    }
+    vm.current_frame_mut().handlers.pop();
 }
 
-pub fn execute_finally_start<W: std::io::Write>(_vm: &mut super::super::VM<W>) {}
+/// Whether a caught exception is of a catch clause's type; every
+/// exception is a `Throwable`
+pub fn execute_catch<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    class_name: &str,
+) -> Result<(), VmError> {
+    let matches = match vm.stack.last().ok_or_else(VmError::stack_underflow)? {
+        Value::Object(exception) => {
+            class_name.eq_ignore_ascii_case("Throwable")
+                || vm.is_instance_of(exception.class_name(), class_name)
+        }
+        _ => false,
+    };
+    vm.stack.push(Value::Bool(matches));
+    Ok(())
+}
 
-pub fn execute_finally_end<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    if vm.pending_return.is_some() {
-        return Err("__FINALLY_RETURN__".to_string());
+pub fn execute_finally_start<W: std::io::Write>(vm: &mut super::super::VM<W>) {
+    let loop_depth = vm.loops.len();
+    let frame = vm.current_frame_mut();
+    let handler_depth = frame.handlers.len();
+    frame.finally_blocks.push(PendingFinally {
+        completion: Completion::Normal,
+        loop_depth,
+        handler_depth,
+    });
+}
+
+/// End a finally block, finishing the jump that ran it
+pub fn execute_finally_end<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<ControlFlow, VmError> {
+    let Some(block) = vm.current_frame_mut().finally_blocks.pop() else {
+        return Ok(ControlFlow::Next);
+    };
+    match block.completion {
+        Completion::Normal => Ok(ControlFlow::Next),
+        Completion::Return(value) => {
+            vm.stack.push(value);
+            Ok(ControlFlow::Return)
+        }
+        Completion::Throw(exception) => throw_value(vm, exception).map(|()| ControlFlow::Next),
+        Completion::Break => Ok(ControlFlow::Break),
+        Completion::Continue => Ok(ControlFlow::Continue),
     }
-    Ok(())
 }
@@ -0,0 +1,48 @@
+//! Exceptions thrown out of finally blocks
+//!
+//! A finally block run for an exception throws it on when it ends. If
+//! the block throws an exception of its own instead, that one replaces
+//! the pending one, which becomes its previous exception, as in PHP.
+
+use crate::runtime::{ObjectRef, Value};
+use crate::vm::frame::Completion;
+
+/// Make the exceptions that the finally blocks `exception` jumps out of
+/// were throwing on its previous ones, innermost first. The frame at
+/// `target` keeps the blocks of its handlers but the last, and the
+/// frames above it lose all of theirs.
+pub(super) fn chain_abandoned<W: std::io::Write>(
+    vm: &crate::vm::VM<W>,
+    exception: &Value,
+    target: Option<usize>,
+) {
+    let Value::Object(exception) = exception else {
+        return;
+    };
+    let frames = vm.frames.iter().enumerate().skip(target.unwrap_or(0));
+    for (i, frame) in frames.rev() {
+        let kept = (target == Some(i)).then(|| frame.handlers.len() - 1);
+        let blocks = frame.finally_blocks.iter().rev();
+        for block in blocks.filter(|block| kept.is_none_or(|kept| block.handler_depth > kept)) {
+            if let Completion::Throw(Value::Object(pending)) = &block.completion {
+                set_previous(exception, pending);
+            }
+        }
+    }
+}
+
+/// Put `previous` at the end of the chain of previous exceptions of
+/// `exception`, unless it is in the chain already
+fn set_previous(exception: &ObjectRef, previous: &ObjectRef) {
+    let mut last = exception.clone();
+    loop {
+        if last.ptr_eq(previous) {
+            return;
+        }
+        match last.get("previous") {
+            Some(Value::Object(next)) => last = next,
+            _ => break,
+        }
+    }
+    last.set("previous", Value::Object(previous.clone()));
+}
@@ -0,0 +1,45 @@
+//! Engine and built-in errors thrown as exceptions
+//!
+//! The code raising an error scripts can catch fails with a
+//! `VmError::Exception` naming the built-in Error class PHP throws it
+//! as; the opcode's caller throws it, so that a catch block may handle
+//! it.
+
+use super::{execute_throw, new_throwable};
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+
+/// Throw an error of a built-in Error class, where a catch block may
+/// handle it like any exception
+pub fn throw_error<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+    class_name: &str,
+    message: &str,
+) -> Result<(), VmError> {
+    let error = new_throwable(vm, class_name, message)?;
+    vm.stack.push(Value::Object(error));
+    execute_throw(vm)
+}
+
+/// Throw the exception of a `VmError::Exception` an opcode failed with,
+/// or the `Error` of an undefined function. Succeeds at once for an
+/// exception already thrown. Fails with any other error, or with the
+/// uncaught exception's error if no catch block takes it; an undefined
+/// function's stays one, so that it is reported as such.
+pub fn throw_raised<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+    e: VmError,
+) -> Result<(), VmError> {
+    match e {
+        VmError::Exception {
+            class_name,
+            message,
+        } => throw_error(vm, &class_name, &message),
+        VmError::UndefinedFunction(message) => match throw_error(vm, "Error", &message) {
+            Err(VmError::Uncaught(_)) => Err(VmError::UndefinedFunction(message)),
+            result => result,
+        },
+        VmError::Thrown => Ok(()),
+        e => Err(e),
+    }
+}
@@ -1,14 +1,374 @@
+//! Fiber switching
+//!
+//! A fiber's call stack runs on top of the frames of whoever started or
+//! resumed it, in a run loop of its own. `Fiber::suspend()` stops that
+//! loop, which moves the fiber's frames, the operands above them, its
+//! loops and its `@` depth into the fiber's state; resuming moves them
+//! back on top of the resumer's, as generators do with their one frame.
+//!
+//! Only the fiber's own run loop can be suspended, along with a generator
+//! body the loop resumed, which puts its frames aside first (see
+//! `ops::generator`). Code run by any other nested loop of the VM, such
+//! as a `__toString()` call, counts in `VM::nested_runs` and can't
+//! suspend the fiber it runs in.
+
+use super::generator::Sent;
 use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::execution::{self, ControlFlow};
+use crate::vm::frame::{CallFrame, Completion, LoopContext};
 use crate::vm::VM;
+use std::io::Write;
+
+/// Where a fiber stands
+pub enum FiberStatus {
+    /// Not started yet
+    Init,
+    Running,
+    Suspended,
+    /// The callback returned
+    Returned(Box<Value>),
+    /// An exception left the callback
+    Threw,
+}
+
+/// Stack length, loop count, `@` depth and frame count under a fiber's
+/// frames
+#[derive(Clone, Copy, Default)]
+struct Bases {
+    stack: usize,
+    loops: usize,
+    silence: usize,
+    frames: usize,
+}
+
+impl Bases {
+    fn of<W: Write>(vm: &VM<W>) -> Self {
+        Self {
+            stack: vm.stack.len(),
+            loops: vm.loops.len(),
+            silence: vm.silence_depth,
+            frames: vm.frames.len(),
+        }
+    }
+}
+
+/// A fiber's callback and, while it is suspended, its call stack
+pub struct FiberState {
+    callback: Value,
+    /// The `Fiber` object, which `Fiber::getCurrent()` gives
+    object: Value,
+    pub(crate) status: FiberStatus,
+    /// The suspended frames, innermost last
+    frames: Vec<CallFrame>,
+    /// The operands the frames had on the stack
+    operands: Vec<Value>,
+    /// The loops the frames were in, innermost last
+    loops: Vec<LoopContext>,
+    /// `@` expressions the fiber was evaluating
+    silence: usize,
+    /// What was under the frames when the fiber was suspended
+    bases: Bases,
+    /// The value passed to `Fiber::suspend()`
+    suspended_with: Value,
+    /// `VM::nested_runs` when the fiber was last started or resumed
+    nested_runs: usize,
+    /// The generator whose body the fiber was suspended in
+    generator: Option<usize>,
+}
+
+/// How a fiber is switched to
+pub enum Entry {
+    /// Call the callback with the arguments
+    Start(Vec<Value>),
+    /// Give the value from the suspended `Fiber::suspend()`
+    Resume(Value),
+    /// Throw the exception from the suspended `Fiber::suspend()`
+    Throw(Value),
+}
+
+/// Add a fiber for the callback, giving its id
+pub fn create_fiber<W: Write>(vm: &mut VM<W>, callback: Value) -> usize {
+    let id = vm.fibers.len() + 1;
+    vm.fibers.insert(
+        id,
+        FiberState {
+            callback,
+            object: Value::Null,
+            status: FiberStatus::Init,
+            frames: Vec::new(),
+            operands: Vec::new(),
+            loops: Vec::new(),
+            silence: 0,
+            bases: Bases::default(),
+            suspended_with: Value::Null,
+            nested_runs: 0,
+            generator: None,
+        },
+    );
+    id
+}
+
+/// The state of the fiber `id`
+pub fn fiber_state<W: Write>(vm: &mut VM<W>, id: usize) -> Result<&mut FiberState, VmError> {
+    vm.fibers
+        .get_mut(&id)
+        .ok_or_else(|| VmError::fatal("Fiber not found"))
+}
+
+/// Run the fiber `id`, whose `Fiber` object is `object`, until it
+/// suspends or ends. Gives what `start()`, `resume()` or `throw()`
+/// returns: the value the fiber suspends with, or null once it returned;
+/// `None` if an exception left the fiber for a caller's catch block,
+/// where execution then resumes.
+pub fn switch_to_fiber<W: Write>(
+    vm: &mut VM<W>,
+    id: usize,
+    object: Value,
+    entry: Entry,
+) -> Result<Option<Value>, VmError> {
+    let bases = Bases::of(vm);
+    let nested_runs = vm.nested_runs;
+    let state = fiber_state(vm, id)?;
+    state.status = FiberStatus::Running;
+    state.object = object;
+    state.nested_runs = nested_runs;
+    let callback = state.callback.clone();
+
+    vm.running_fibers.push(id);
+    let mut pending = None;
+    match entry {
+        Entry::Start(args) => {
+            let arg_count = args.len() as u8;
+            vm.stack.extend(args);
+            vm.stack.push(callback);
+            let called = super::execute_call_callable(vm, arg_count);
+            if called.is_ok() && vm.frames.len() == bases.frames {
+                // A built-in function returned at once
+                vm.running_fibers.pop();
+                let value = vm.stack.pop().unwrap_or(Value::Null);
+                fiber_state(vm, id)?.status = FiberStatus::Returned(Box::new(value));
+                return Ok(Some(Value::Null));
+            }
+            pending = called.err().map(Err);
+        }
+        Entry::Resume(value) => {
+            restore(vm, id, bases)?;
+            match fiber_state(vm, id)?.generator.take() {
+                Some(generator) => super::give_interrupted(vm, generator, Sent::Value(value))?,
+                None => vm.stack.push(value),
+            }
+        }
+        Entry::Throw(exception) => {
+            restore(vm, id, bases)?;
+            match fiber_state(vm, id)?.generator.take() {
+                Some(generator) => {
+                    super::give_interrupted(vm, generator, Sent::Exception(exception))?
+                }
+                None => {
+                    vm.stack.push(exception);
+                    pending = Some(super::execute_throw(vm).map(|()| ControlFlow::Next));
+                }
+            }
+        }
+    }
+    let step = run_fiber(vm, bases.frames + 1, pending);
+    vm.running_fibers.pop();
 
-pub fn execute_set_current_fiber<W: std::io::Write>(vm: &mut VM<W>) -> Result<(), String> {
-    let fiber = vm.stack.pop().ok_or("Stack underflow")?;
-    vm.current_fiber = Some(fiber);
+    let (status, switch) = match step {
+        Ok(Step::Suspended) => {
+            let frames = vm.frames.split_off(bases.frames);
+            let operands = vm.stack.split_off(bases.stack.min(vm.stack.len()));
+            let loops = vm.loops.split_off(bases.loops.min(vm.loops.len()));
+            let silence = vm.silence_depth.saturating_sub(bases.silence);
+            vm.silence_depth = bases.silence;
+            let state = fiber_state(vm, id)?;
+            state.frames = frames;
+            state.operands = operands;
+            state.loops = loops;
+            state.silence = silence;
+            state.bases = bases;
+            state.status = FiberStatus::Suspended;
+            let value = std::mem::replace(&mut state.suspended_with, Value::Null);
+            return Ok(Some(value));
+        }
+        Ok(Step::Returned(value)) => {
+            vm.stack.truncate(bases.stack);
+            (FiberStatus::Returned(value), Ok(Some(Value::Null)))
+        }
+        Ok(Step::Caught) => (FiberStatus::Threw, Ok(None)),
+        Err(e) => (FiberStatus::Threw, Err(e)),
+    };
+    fiber_state(vm, id)?.status = status;
+    switch
+}
+
+/// Move a suspended fiber's call stack back on top of what is at `bases`
+fn restore<W: Write>(vm: &mut VM<W>, id: usize, bases: Bases) -> Result<(), VmError> {
+    let state = fiber_state(vm, id)?;
+    let old = state.bases;
+    let mut frames = std::mem::take(&mut state.frames);
+    let operands = std::mem::take(&mut state.operands);
+    let loops = std::mem::take(&mut state.loops);
+    let silence = state.silence;
+
+    let from = (old.stack, old.loops, old.silence);
+    let to = (bases.stack, bases.loops, bases.silence);
+    for frame in &mut frames {
+        frame.stack_base = frame.stack_base - old.stack + bases.stack;
+        super::generator::rebase(frame, from, to);
+    }
+    vm.stack.extend(operands);
+    vm.loops.extend(loops.into_iter().map(|mut context| {
+        context.stack_depth = context.stack_depth - old.stack + bases.stack;
+        context.frame_depth = context.frame_depth - old.frames + bases.frames;
+        context
+    }));
+    vm.silence_depth += silence;
+    vm.frames.extend(frames);
     Ok(())
 }
 
-pub fn execute_get_current_fiber<W: std::io::Write>(vm: &mut VM<W>) -> Result<(), String> {
-    let current = vm.current_fiber.clone().unwrap_or(Value::Null);
+/// How a fiber's run loop stopped
+enum Step {
+    Suspended,
+    /// The callback returned the value
+    Returned(Box<Value>),
+    /// An exception left the fiber for a caller's catch block
+    Caught,
+}
+
+/// Run the fiber whose callback is the frame at `depth`, after handling
+/// what the switch to it gave, until it suspends or ends
+fn run_fiber<W: Write>(
+    vm: &mut VM<W>,
+    depth: usize,
+    mut pending: Option<Result<ControlFlow, VmError>>,
+) -> Result<Step, VmError> {
+    loop {
+        let result = match pending.take() {
+            Some(result) => result,
+            None => {
+                if vm.frames.len() < depth {
+                    return Ok(Step::Caught);
+                }
+                let frame = vm
+                    .frames
+                    .last_mut()
+                    .ok_or_else(|| VmError::fatal("No call frame available"))?;
+                if frame.ip >= frame.function.bytecode.len() {
+                    let returned = vm.stack.pop().unwrap_or(Value::Null);
+                    vm.pop_frame();
+                    if vm.frames.len() < depth {
+                        return Ok(Step::Returned(Box::new(returned)));
+                    }
+                    vm.stack.push(returned);
+                    continue;
+                }
+                let opcode = frame.function.bytecode[frame.ip].clone();
+                frame.ip += 1;
+                vm.execute_opcode(opcode)
+            }
+        };
+
+        let e = match result {
+            Ok(ControlFlow::Next) => continue,
+            Ok(ControlFlow::Suspend) => return Ok(Step::Suspended),
+            Ok(ControlFlow::Return) => {
+                let Some(value) = execution::finish_return(vm)? else {
+                    continue;
+                };
+                if vm.frames.len() < depth {
+                    return Ok(Step::Returned(Box::new(value)));
+                }
+                vm.stack.push(value);
+                continue;
+            }
+            Ok(ControlFlow::Break) => {
+                super::execute_loop_jump(vm, Completion::Break)?;
+                continue;
+            }
+            Ok(ControlFlow::Continue) => {
+                super::execute_loop_jump(vm, Completion::Continue)?;
+                continue;
+            }
+            Ok(ControlFlow::Yield) => ControlFlow::Yield.outside_its_loop(),
+            Err(VmError::Thrown) => continue,
+            Err(e) => e,
+        };
+        let Err(e) = super::throw_raised(vm, e) else {
+            continue;
+        };
+        vm.record_error_location(&e);
+        while vm.frames.len() >= depth {
+            vm.pop_frame();
+        }
+        return Err(e);
+    }
+}
+
+/// `Fiber::suspend()`: stop the running fiber with the value on the
+/// stack, which its `start()` or `resume()` gives
+pub fn execute_fiber_suspend<W: Write>(vm: &mut VM<W>) -> Result<ControlFlow, VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let Some(&id) = vm.running_fibers.last() else {
+        return Err(VmError::exception(
+            "FiberError",
+            "Cannot suspend outside of fiber",
+        ));
+    };
+    let nested_runs = vm.nested_runs;
+    let generator = super::innermost_body(vm);
+    let state = fiber_state(vm, id)?;
+    if state.nested_runs != nested_runs {
+        if generator.is_none() || state.nested_runs + 1 != nested_runs {
+            return Err(VmError::exception(
+                "FiberError",
+                "Cannot switch fibers in current execution context",
+            ));
+        }
+        state.generator = generator;
+    }
+    state.suspended_with = value;
+    Ok(ControlFlow::Suspend)
+}
+
+/// `Fiber::getCurrent()`: the running fiber, or null
+pub fn execute_get_current_fiber<W: Write>(vm: &mut VM<W>) -> Result<(), VmError> {
+    let current = match vm.running_fibers.last() {
+        Some(id) => fiber_state(vm, *id)?.object.clone(),
+        None => Value::Null,
+    };
     vm.stack.push(current);
     Ok(())
 }
+
+impl<W: Write> VM<W> {
+    /// Run code in a nested run loop of the VM, which a fiber can't be
+    /// suspended through
+    pub(crate) fn run_nested<T>(&mut self, run: impl FnOnce(&mut Self) -> T) -> T {
+        self.nested_runs += 1;
+        let result = run(self);
+        self.nested_runs -= 1;
+        result
+    }
+}
+
+/// Record the fiber `id` in a `Fiber` object
+pub fn set_fiber_id(fiber: &Value, id: usize) {
+    if let Value::Object(object) = fiber {
+        object.set("__id", Value::Integer(id as i64));
+    }
+}
+
+/// `new Fiber($callback)`
+pub fn execute_new_fiber<W: Write>(vm: &mut VM<W>) -> Result<(), VmError> {
+    let callback = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let callback = vm.callback_argument("Fiber::__construct", 1, &callback)?;
+    super::execute_new_object(vm, "Fiber".to_string())?;
+    let fiber = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let id = create_fiber(vm, callback);
+    set_fiber_id(&fiber, id);
+    vm.stack.push(fiber);
+    Ok(())
+}
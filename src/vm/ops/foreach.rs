@@ -0,0 +1,135 @@
+//! Foreach iteration
+//!
+//! A foreach loop keeps what it walks and its position in locals of its
+//! own. An array is walked by position; a generator is rewound when the
+//! loop starts and resumed once per iteration, so its body runs no
+//! further ahead than the loop.
+
+use super::generator::{
+    call_generator_method, finish_generator_call, is_generator_finished, GeneratorCall,
+};
+use crate::runtime::{PhpArray, Value};
+use crate::vm::error::VmError;
+use crate::vm::execution::ControlFlow;
+use crate::vm::VM;
+use std::io::Write;
+
+/// The value a foreach loop walks: arrays and generators as they are, an
+/// XML object as the array of what it iterates over, nothing otherwise
+pub fn execute_to_array<W: Write>(vm: &mut VM<W>) -> Result<(), VmError> {
+    let iterable = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let iterable = match iterable {
+        Value::Array(_) | Value::Generator(_) => iterable,
+        Value::Object(instance) => vm
+            .xml_iteration(&instance)?
+            .unwrap_or(Value::Array(PhpArray::new())),
+        _ => Value::Array(PhpArray::new()),
+    };
+    vm.stack.push(iterable);
+    Ok(())
+}
+
+/// The loop's position
+fn position<W: Write>(vm: &VM<W>, iter_slot: u16) -> usize {
+    vm.current_frame().get_local(iter_slot).to_int().max(0) as usize
+}
+
+/// Call a method of the generator a loop walks and push what it returns,
+/// unless `discard`. Gives `None` if it returned, or how to go on if it
+/// threw or suspended the fiber instead.
+fn generator_step<W: Write>(
+    vm: &mut VM<W>,
+    source_slot: u16,
+    method_name: &str,
+    discard: bool,
+) -> Result<Option<ControlFlow>, VmError> {
+    let Value::Generator(gen) = vm.current_frame().get_local(source_slot) else {
+        return Ok(None);
+    };
+    match call_generator_method(vm, &gen, method_name, &[])? {
+        GeneratorCall::Return(_) if discard => Ok(None),
+        call @ GeneratorCall::Return(_) => finish_generator_call(vm, call).map(|_| None),
+        call => finish_generator_call(vm, call).map(Some),
+    }
+}
+
+/// Push whether the loop has an element at its position, rewinding a
+/// generator at the start
+pub fn execute_foreach_valid<W: Write>(
+    vm: &mut VM<W>,
+    source_slot: u16,
+    iter_slot: u16,
+) -> Result<ControlFlow, VmError> {
+    let position = position(vm, iter_slot);
+    let valid = match vm.current_frame().get_local(source_slot) {
+        Value::Array(arr) => position < arr.len(),
+        Value::Generator(gen) => {
+            if position == 0 {
+                let gen = gen.clone();
+                if is_generator_finished(vm, &gen)? {
+                    let exception = super::new_throwable(
+                        vm,
+                        "Exception",
+                        "Cannot traverse an already closed generator",
+                    )?;
+                    vm.stack.push(Value::Object(exception));
+                    return super::execute_throw(vm).map(|()| ControlFlow::Next);
+                }
+                if let Some(flow) = generator_step(vm, source_slot, "rewind", true)? {
+                    return Ok(flow);
+                }
+            }
+            return generator_step(vm, source_slot, "valid", false)
+                .map(|step| step.unwrap_or(ControlFlow::Next));
+        }
+        _ => false,
+    };
+    vm.stack.push(Value::Bool(valid));
+    Ok(ControlFlow::Next)
+}
+
+/// Push the key of the element at the loop's position
+pub fn execute_foreach_key<W: Write>(
+    vm: &mut VM<W>,
+    source_slot: u16,
+    iter_slot: u16,
+) -> Result<ControlFlow, VmError> {
+    let position = position(vm, iter_slot);
+    let key = match vm.current_frame().get_local(source_slot) {
+        Value::Array(arr) => arr.as_slice().get(position).map(|(key, _)| key.to_value()),
+        Value::Generator(_) => {
+            return generator_step(vm, source_slot, "key", false)
+                .map(|step| step.unwrap_or(ControlFlow::Next))
+        }
+        _ => None,
+    };
+    vm.stack.push(key.unwrap_or(Value::Null));
+    Ok(ControlFlow::Next)
+}
+
+/// Push the element at the loop's position
+pub fn execute_foreach_value<W: Write>(
+    vm: &mut VM<W>,
+    source_slot: u16,
+    iter_slot: u16,
+) -> Result<ControlFlow, VmError> {
+    let position = position(vm, iter_slot);
+    let value = match vm.current_frame().get_local(source_slot) {
+        Value::Array(arr) => arr.as_slice().get(position).map(|(_, value)| value.clone()),
+        Value::Generator(_) => {
+            return generator_step(vm, source_slot, "current", false)
+                .map(|step| step.unwrap_or(ControlFlow::Next))
+        }
+        _ => None,
+    };
+    vm.stack.push(value.unwrap_or(Value::Null));
+    Ok(ControlFlow::Next)
+}
+
+/// Move a generator the loop walks on to its next yield
+pub fn execute_foreach_next<W: Write>(
+    vm: &mut VM<W>,
+    source_slot: u16,
+) -> Result<ControlFlow, VmError> {
+    generator_step(vm, source_slot, "next", true).map(|step| step.unwrap_or(ControlFlow::Next))
+}
@@ -1,91 +1,466 @@
-use crate::runtime::Value;
+//! Generators
+//!
+//! Calling a generator function doesn't run its body: the frame is kept
+//! suspended in the VM under the id of the `Generator` it becomes, so
+//! copies of the value all share it. The generator's methods resume the
+//! body up to its next `yield`, which suspends it again, putting aside
+//! the operands it had on the stack and the loops it was in. Resuming
+//! moves them, and what the frame's handlers recorded of the stack and
+//! loops, to wherever the body resumes. The value passed to `send()` is
+//! what the suspended `yield` gives.
+//!
+//! `yield from` an inner generator suspends the body for as long as the
+//! inner one runs: resuming the body resumes the inner generator in its
+//! place, whose yields the body gives, until it returns. A `foreach` loop
+//! resumes the generator once per iteration (see `ops::foreach`).
+//!
+//! A body resumed from a fiber's own run loop may suspend the fiber. The
+//! body's frame and the calls above it are put aside as at a `yield`, and
+//! the opcode that resumed the body runs again once the fiber is resumed,
+//! this time continuing the body with what `Fiber::suspend()` gives.
 
-pub fn execute_generator_current<W: std::io::Write>(
-    vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let gen_value = vm.stack.pop().ok_or("Stack underflow")?;
-    match gen_value {
-        Value::Generator(gen) => {
-            let current = if gen.current_index < gen.yielded_values.len() {
-                gen.yielded_values[gen.current_index]
-                    .1
-                    .clone()
-                    .unwrap_or(Value::Null)
-            } else {
-                Value::Null
-            };
-            vm.stack.push(Value::Generator(gen));
-            vm.stack.push(current);
-            Ok(())
+mod body;
+mod methods;
+
+pub use methods::*;
+
+use crate::runtime::{GeneratorInstance, Value};
+use crate::vm::error::VmError;
+use crate::vm::execution::ControlFlow;
+use crate::vm::frame::{CallFrame, LoopContext};
+use body::{run_body, run_delegate, Resumption, Step};
+use std::collections::VecDeque;
+
+/// What a suspended `yield` gives when the body is resumed
+pub(super) enum Sent {
+    /// The `yield` gives the value
+    Value(Value),
+    /// The `yield` throws the exception
+    Exception(Value),
+}
+
+/// A generator's body and where it stands
+pub struct GeneratorState {
+    /// The suspended frame; `None` while the body runs and once it ended
+    frame: Option<CallFrame>,
+    /// The operands the body had on the stack when it yielded
+    operands: Vec<Value>,
+    /// The loops the body was in when it yielded, innermost last
+    loops: Vec<LoopContext>,
+    /// Stack length, loop count and `@` depth under the frame when it was
+    /// suspended
+    bases: (usize, usize, usize),
+    /// Key and value of the current yield
+    current: Option<(Value, Value)>,
+    /// Yields of a `yield from` an array still to come
+    delegated: VecDeque<(Value, Value)>,
+    /// The inner generator of the `yield from` the body is suspended at
+    delegate: Option<usize>,
+    /// What the `yield from` gives once its yields are over
+    delegate_result: Option<Value>,
+    /// The largest integer key yielded, which automatic keys follow
+    largest_key: i64,
+    /// Whether the body ran up to its first yield
+    started: bool,
+    /// Whether the body was resumed past its first yield
+    advanced: bool,
+    /// Whether the body is running
+    running: bool,
+    /// What the body returned, once it did
+    return_value: Option<Value>,
+    /// `VM::nested_runs` while the body runs
+    nested_runs: usize,
+    /// Where the body stopped if it suspended a fiber
+    interrupted: Option<Interrupted>,
+}
+
+/// A body that suspended the fiber it ran in, which still counts as
+/// running
+struct Interrupted {
+    /// The frames of the calls above the body's, innermost last
+    calls: Vec<CallFrame>,
+    /// Frame count with the body's frame on top
+    depth: usize,
+    /// `@` expressions the body was evaluating
+    silence: usize,
+    /// Whether the body was running up to its first yield
+    first: bool,
+    /// What the suspended `Fiber::suspend()` gives, once the fiber resumed
+    sent: Option<Sent>,
+}
+
+impl GeneratorState {
+    /// Whether the body ended
+    fn is_finished(&self) -> bool {
+        self.started && !self.running && self.current.is_none()
+    }
+
+    /// How to resume the body with what the suspended `yield` gives, if
+    /// there is a body left to resume, marking it running
+    fn resumption(&mut self, sent: Sent) -> Result<Option<Resumption>, VmError> {
+        if self.running {
+            return Err(VmError::error("Cannot resume an already running generator"));
+        }
+        if self.started {
+            self.advanced = true;
+        }
+        if let Sent::Exception(_) = sent {
+            self.delegated.clear();
+        } else if let Some(entry) = self.delegated.pop_front() {
+            self.current = Some(entry);
+            return Ok(None);
         }
-        _ => Err("Generator::current() requires a Generator object".to_string()),
+        if self.frame.is_none() {
+            self.current = None;
+            return Ok(None);
+        }
+        let delegate_result = self.delegate_result.take();
+        let resumption = match (self.delegate, sent) {
+            (Some(inner), sent) => Resumption::Delegate(inner, sent),
+            (None, Sent::Exception(exception)) => Resumption::Throw(exception),
+            (None, Sent::Value(value)) => match delegate_result {
+                Some(result) => Resumption::Give(Some(result)),
+                None if self.started => Resumption::Give(Some(value)),
+                None => Resumption::Give(None),
+            },
+        };
+        self.started = true;
+        self.running = true;
+        self.current = None;
+        Ok(Some(resumption))
+    }
+
+    /// End the generator where it stands
+    fn finish(&mut self) {
+        self.frame = None;
+        self.operands.clear();
+        self.loops.clear();
+        self.current = None;
+        self.delegated.clear();
+        self.delegate = None;
+        self.delegate_result = None;
+        self.interrupted = None;
+        self.started = true;
     }
 }
 
-pub fn execute_generator_key<W: std::io::Write>(
+/// Turn the frame of a call to a generator function into a `Generator`
+pub fn create_generator<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    frame: CallFrame,
+) -> Value {
+    let id = vm.generators.len() + 1;
+    let function_name = frame.function.name.clone();
+    vm.generators.insert(
+        id,
+        GeneratorState {
+            frame: Some(frame),
+            operands: Vec::new(),
+            loops: Vec::new(),
+            bases: (0, 0, 0),
+            current: None,
+            delegated: VecDeque::new(),
+            delegate: None,
+            delegate_result: None,
+            largest_key: -1,
+            started: false,
+            advanced: false,
+            running: false,
+            return_value: None,
+            nested_runs: 0,
+            interrupted: None,
+        },
+    );
+    Value::Generator(Box::new(GeneratorInstance { id, function_name }))
+}
+
+/// The state of the generator the running body belongs to
+fn running_state<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let gen_value = vm.stack.pop().ok_or("Stack underflow")?;
-    match gen_value {
-        Value::Generator(gen) => {
-            let key = if gen.current_index < gen.yielded_values.len() {
-                gen.yielded_values[gen.current_index]
-                    .0
-                    .clone()
-                    .unwrap_or(Value::Null)
-            } else {
-                Value::Null
+) -> Result<&mut GeneratorState, VmError> {
+    let id = vm
+        .running_generators
+        .last()
+        .ok_or_else(|| VmError::fatal("Cannot yield outside of a generator"))?;
+    vm.generators
+        .get_mut(id)
+        .ok_or_else(|| VmError::fatal("Cannot yield outside of a generator"))
+}
+
+/// `yield`: suspend the body with the key (or null) and value on the
+/// stack as the current yield
+pub fn execute_yield<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<ControlFlow, VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let key = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let state = running_state(vm)?;
+    let key = match key {
+        Value::Null => {
+            state.largest_key += 1;
+            Value::Integer(state.largest_key)
+        }
+        Value::Integer(n) => {
+            state.largest_key = state.largest_key.max(n);
+            Value::Integer(n)
+        }
+        key => key,
+    };
+    state.current = Some((key, value));
+    Ok(ControlFlow::Yield)
+}
+
+/// `yield from`: yield what an array holds or an inner generator yields,
+/// keys included. The expression gives the inner generator's return
+/// value, or null for an array.
+pub fn execute_yield_from<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<ControlFlow, VmError> {
+    let iterable = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let mut entries: VecDeque<(Value, Value)> = match iterable {
+        Value::Array(arr) => arr.into_iter().map(|(k, v)| (k.to_value(), v)).collect(),
+        Value::Generator(inner) => {
+            match start(vm, inner.id)? {
+                Resumed::Stopped => {}
+                // An exception left the inner generator for a catch block
+                Resumed::Caught => return Ok(ControlFlow::Next),
+                Resumed::Suspended => unreachable!("an inner generator runs in a nested loop"),
+            }
+            let inner_state = state_mut(vm, inner.id)?;
+            let Some(first) = inner_state.current.clone() else {
+                let result = inner_state.return_value.clone().unwrap_or(Value::Null);
+                vm.stack.push(result);
+                return Ok(ControlFlow::Next);
             };
-            vm.stack.push(Value::Generator(gen));
-            vm.stack.push(key);
-            Ok(())
+            let state = running_state(vm)?;
+            state.current = Some(first);
+            state.delegate = Some(inner.id);
+            return Ok(ControlFlow::Yield);
         }
-        _ => Err("Generator::key() requires a Generator object".to_string()),
-    }
+        _ => {
+            return Err(VmError::error(
+                "Can use \"yield from\" only with arrays and Traversables",
+            ));
+        }
+    };
+
+    let Some(first) = entries.pop_front() else {
+        vm.stack.push(Value::Null);
+        return Ok(ControlFlow::Next);
+    };
+    let state = running_state(vm)?;
+    state.current = Some(first);
+    state.delegated = entries;
+    state.delegate_result = Some(Value::Null);
+    Ok(ControlFlow::Yield)
+}
+
+/// How resuming a generator's body ended
+pub(super) enum Resumed {
+    /// It yielded or returned
+    Stopped,
+    /// An exception left it and a caller caught it, where execution
+    /// resumes
+    Caught,
+    /// It suspended the fiber it ran in (see `Interrupted`)
+    Suspended,
 }
 
-pub fn execute_generator_next<W: std::io::Write>(
+/// Resume a generator's body with what the suspended `yield` gives,
+/// until it yields again, ends or suspends the running fiber. A body
+/// that suspended the fiber carries on with what the fiber was resumed
+/// with instead.
+fn resume<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let gen_value = vm.stack.pop().ok_or("Stack underflow")?;
-    match gen_value {
-        Value::Generator(mut gen) => {
-            gen.current_index += 1;
-            let result = Value::Bool(gen.current_index < gen.yielded_values.len());
-            vm.stack.push(Value::Generator(gen));
-            vm.stack.push(result);
-            Ok(())
-        }
-        _ => Err("Generator::next() requires a Generator object".to_string()),
+    id: usize,
+    sent: Sent,
+) -> Result<Resumed, VmError> {
+    let bases = (vm.stack.len(), vm.loops.len(), vm.silence_depth);
+    let depth = vm.frames.len() + 1;
+    let nested_runs = vm.nested_runs + 1;
+    let state = state_mut(vm, id)?;
+    let first = !state.started;
+    let mut interrupted = None;
+    let resumption = match state.interrupted.as_mut().and_then(|i| i.sent.take()) {
+        Some(sent) => {
+            interrupted = state.interrupted.take();
+            match sent {
+                Sent::Value(value) => Resumption::Give(Some(value)),
+                Sent::Exception(exception) => Resumption::Throw(exception),
+            }
+        }
+        None => match state.resumption(sent)? {
+            Some(resumption) => resumption,
+            None => return Ok(Resumed::Stopped),
+        },
+    };
+    let mut frame = state
+        .frame
+        .take()
+        .ok_or_else(|| VmError::fatal("Generator not found"))?;
+    let operands = std::mem::take(&mut state.operands);
+    let loops = std::mem::take(&mut state.loops);
+    let old_bases = state.bases;
+
+    state.nested_runs = nested_runs;
+
+    // Move what the frames recorded of the stack and loops under them
+    rebase(&mut frame, old_bases, bases);
+    vm.stack.extend(operands);
+    vm.loops.extend(loops.into_iter().map(|mut context| {
+        context.stack_depth = context.stack_depth - old_bases.0 + bases.0;
+        context.frame_depth = match &interrupted {
+            Some(interrupted) => context.frame_depth - interrupted.depth + depth,
+            None => depth,
+        };
+        context
+    }));
+
+    vm.running_generators.push(id);
+    vm.enter_frame(frame);
+    let first = interrupted.as_ref().map_or(first, |i| i.first);
+    if let Some(interrupted) = interrupted {
+        for mut call in interrupted.calls {
+            call.stack_base = call.stack_base - old_bases.0 + bases.0;
+            rebase(&mut call, old_bases, bases);
+            vm.frames.push(call);
+        }
+        vm.silence_depth += interrupted.silence;
+    }
+    let step = vm.run_nested(|vm| match resumption {
+        Resumption::Give(value) => {
+            vm.stack.extend(value);
+            run_body(vm, depth, None)
+        }
+        Resumption::Throw(exception) => {
+            vm.stack.push(exception);
+            let thrown = super::execute_throw(vm);
+            run_body(vm, depth, Some(thrown.map(|()| ControlFlow::Next)))
+        }
+        Resumption::Delegate(inner, sent) => run_delegate(vm, id, inner, sent, depth),
+    });
+    vm.running_generators.pop();
+
+    match step {
+        Ok(Step::Yielded) => {
+            let operands = vm.stack.split_off(bases.0.min(vm.stack.len()));
+            let loops = vm.loops.split_off(bases.1.min(vm.loops.len()));
+            let frame = vm.pop_frame();
+            let state = state_mut(vm, id)?;
+            state.running = false;
+            state.frame = frame;
+            state.operands = operands;
+            state.loops = loops;
+            state.bases = bases;
+            Ok(Resumed::Stopped)
+        }
+        Ok(Step::Suspended) => {
+            let calls = vm.frames.split_off(depth.min(vm.frames.len()));
+            let operands = vm.stack.split_off(bases.0.min(vm.stack.len()));
+            let loops = vm.loops.split_off(bases.1.min(vm.loops.len()));
+            let silence = vm.silence_depth.saturating_sub(bases.2);
+            vm.silence_depth = bases.2;
+            let frame = vm.pop_frame();
+            let state = state_mut(vm, id)?;
+            state.frame = frame;
+            state.operands = operands;
+            state.loops = loops;
+            state.bases = bases;
+            state.interrupted = Some(Interrupted {
+                calls,
+                depth,
+                silence,
+                first,
+                sent: None,
+            });
+            Ok(Resumed::Suspended)
+        }
+        Ok(Step::Returned(value)) => {
+            vm.stack.truncate(bases.0);
+            let state = state_mut(vm, id)?;
+            state.running = false;
+            state.finish();
+            state.return_value = Some(value);
+            Ok(Resumed::Stopped)
+        }
+        Ok(Step::Caught) => {
+            let state = state_mut(vm, id)?;
+            state.running = false;
+            state.finish();
+            Ok(Resumed::Caught)
+        }
+        Err(e) => {
+            let state = state_mut(vm, id)?;
+            state.running = false;
+            state.finish();
+            Err(e)
+        }
     }
 }
 
-pub fn execute_generator_rewind<W: std::io::Write>(
+/// Give the generator `id`, whose body suspended a fiber, what the
+/// fiber's `Fiber::suspend()` gives now that the fiber is resumed. The
+/// body carries on with it when the opcode that resumed it runs again.
+pub(super) fn give_interrupted<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let gen_value = vm.stack.pop().ok_or("Stack underflow")?;
-    match gen_value {
-        Value::Generator(mut gen) => {
-            gen.current_index = 0;
-            gen.is_rewound = true;
-            vm.stack.push(Value::Generator(gen));
-            Ok(())
-        }
-        _ => Err("Generator::rewind() requires a Generator object".to_string()),
+    id: usize,
+    sent: Sent,
+) -> Result<(), VmError> {
+    if let Some(interrupted) = &mut state_mut(vm, id)?.interrupted {
+        interrupted.sent = Some(sent);
+    }
+    Ok(())
+}
+
+/// The generator whose body the innermost run loop of the VM runs, if
+/// any
+pub(super) fn innermost_body<W: std::io::Write>(vm: &super::super::VM<W>) -> Option<usize> {
+    let id = *vm.running_generators.last()?;
+    let state = vm.generators.get(&id)?;
+    (state.nested_runs == vm.nested_runs).then_some(id)
+}
+
+/// Move what a frame recorded of the stack length, loop count and `@`
+/// depth from the values under it at `from` to those at `to`
+pub(super) fn rebase(
+    frame: &mut CallFrame,
+    from: (usize, usize, usize),
+    to: (usize, usize, usize),
+) {
+    for handler in &mut frame.handlers {
+        handler.stack_depth = handler.stack_depth - from.0 + to.0;
+        handler.loop_depth = handler.loop_depth - from.1 + to.1;
+        handler.silence_depth = handler.silence_depth - from.2 + to.2;
+    }
+    for block in &mut frame.finally_blocks {
+        block.loop_depth = block.loop_depth - from.1 + to.1;
     }
 }
 
-pub fn execute_generator_valid<W: std::io::Write>(
+/// The state of the generator `id`
+fn state_mut<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let gen_value = vm.stack.pop().ok_or("Stack underflow")?;
-    match gen_value {
-        Value::Generator(gen) => {
-            let valid = gen.current_index < gen.yielded_values.len() && !gen.finished;
-            vm.stack.push(Value::Generator(gen));
-            vm.stack.push(Value::Bool(valid));
-            Ok(())
-        }
-        _ => Err("Generator::valid() requires a Generator object".to_string()),
+    id: usize,
+) -> Result<&mut GeneratorState, VmError> {
+    vm.generators
+        .get_mut(&id)
+        .ok_or_else(|| VmError::fatal("Generator not found"))
+}
+
+/// Run a generator's body up to its first yield, if it hasn't run yet
+/// or suspended a fiber before it got there
+fn start<W: std::io::Write>(vm: &mut super::super::VM<W>, id: usize) -> Result<Resumed, VmError> {
+    let state = state_mut(vm, id)?;
+    if state.started && !state.interrupted.as_ref().is_some_and(|i| i.first) {
+        return Ok(Resumed::Stopped);
     }
+    resume(vm, id, Sent::Value(Value::Null))
+}
+
+/// Whether a generator's body ended
+pub fn is_generator_finished<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    gen: &GeneratorInstance,
+) -> Result<bool, VmError> {
+    Ok(state_mut(vm, gen.id)?.is_finished())
 }
@@ -0,0 +1,130 @@
+//! Running a resumed generator body until it yields, returns, throws
+//! to a caller's catch block or suspends the fiber it runs in
+
+use super::{resume, state_mut, Resumed, Sent};
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::execution::{self, ControlFlow};
+use crate::vm::frame::Completion;
+
+/// How a suspended body is resumed
+pub(super) enum Resumption {
+    /// The `yield` gives the value, if the body started
+    Give(Option<Value>),
+    /// The `yield` throws the exception
+    Throw(Value),
+    /// Resume the inner generator of the `yield from` with what it gives
+    Delegate(usize, Sent),
+}
+
+/// Resume the inner generator of the `yield from` the body of `id`,
+/// resumed as the frame at `depth`, is suspended at. The body yields what
+/// the inner generator yields, and carries on with its return value once
+/// it returned.
+pub(super) fn run_delegate<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+    id: usize,
+    inner: usize,
+    sent: Sent,
+    depth: usize,
+) -> Result<Step, VmError> {
+    let pending = match resume(vm, inner, sent) {
+        Ok(Resumed::Stopped) => {
+            let inner_state = state_mut(vm, inner)?;
+            if let Some(entry) = inner_state.current.clone() {
+                state_mut(vm, id)?.current = Some(entry);
+                return Ok(Step::Yielded);
+            }
+            let result = inner_state.return_value.clone().unwrap_or(Value::Null);
+            vm.stack.push(result);
+            None
+        }
+        // The catch block may be the body's
+        Ok(Resumed::Caught) => None,
+        Ok(Resumed::Suspended) => unreachable!("an inner generator runs in a nested loop"),
+        Err(e) => Some(Err(e)),
+    };
+    state_mut(vm, id)?.delegate = None;
+    run_body(vm, depth, pending)
+}
+
+/// Run a generator's body, resumed as the frame at `depth`, until it
+/// yields or ends, handling what resuming it gave first if `pending`
+pub(super) fn run_body<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+    depth: usize,
+    mut pending: Option<Result<ControlFlow, VmError>>,
+) -> Result<Step, VmError> {
+    loop {
+        let result = match pending.take() {
+            Some(result) => result,
+            None => {
+                if vm.frames.len() < depth {
+                    return Ok(Step::Caught);
+                }
+                let frame = vm
+                    .frames
+                    .last_mut()
+                    .ok_or_else(|| VmError::fatal("No call frame available"))?;
+                if frame.ip >= frame.function.bytecode.len() {
+                    let returned = vm.stack.pop().unwrap_or(Value::Null);
+                    vm.pop_frame();
+                    if vm.frames.len() < depth {
+                        return Ok(Step::Returned(returned));
+                    }
+                    vm.stack.push(returned);
+                    continue;
+                }
+                let opcode = frame.function.bytecode[frame.ip].clone();
+                frame.ip += 1;
+                vm.execute_opcode(opcode)
+            }
+        };
+
+        let e = match result {
+            Ok(ControlFlow::Next) => continue,
+            Ok(ControlFlow::Yield) => return Ok(Step::Yielded),
+            Ok(ControlFlow::Return) => {
+                let Some(value) = execution::finish_return(vm)? else {
+                    continue;
+                };
+                if vm.frames.len() < depth {
+                    return Ok(Step::Returned(value));
+                }
+                vm.stack.push(value);
+                continue;
+            }
+            Ok(ControlFlow::Break) => {
+                super::super::execute_loop_jump(vm, Completion::Break)?;
+                continue;
+            }
+            Ok(ControlFlow::Continue) => {
+                super::super::execute_loop_jump(vm, Completion::Continue)?;
+                continue;
+            }
+            Ok(ControlFlow::Suspend) => return Ok(Step::Suspended),
+            Err(VmError::Thrown) => continue,
+            Err(e) => e,
+        };
+        let Err(e) = super::super::throw_raised(vm, e) else {
+            continue;
+        };
+        vm.record_error_location(&e);
+        while vm.frames.len() >= depth {
+            vm.pop_frame();
+        }
+        return Err(e);
+    }
+}
+
+/// How a resumed body stopped
+pub(super) enum Step {
+    /// It yielded, and its frame is still on top
+    Yielded,
+    /// It returned the value
+    Returned(Value),
+    /// An exception left it for a caller's catch block
+    Caught,
+    /// It suspended the fiber it ran in
+    Suspended,
+}
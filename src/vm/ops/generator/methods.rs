@@ -0,0 +1,218 @@
+//! The methods of `Generator`: `current()`, `key()`, `next()`, `send()`,
+//! `throw()`, `valid()`, `rewind()` and `getReturn()`
+//!
+//! Each but `getReturn()` runs the body up to its first yield if it
+//! hasn't run yet.
+
+use super::{resume, start, state_mut, Resumed, Sent};
+use crate::runtime::{GeneratorInstance, Value};
+use crate::vm::error::VmError;
+use crate::vm::execution::ControlFlow;
+
+/// What a call to a method of a generator ends with
+pub enum GeneratorCall {
+    /// The method returns the value
+    Return(Value),
+    /// The method throws the exception
+    Throw(Value),
+    /// An exception left the body for a catch block, where execution
+    /// resumes
+    Caught,
+    /// The body suspended the fiber it ran in, and the call is made again
+    /// once the fiber resumes
+    Suspended,
+}
+
+/// What the call ends with if resuming the body didn't get to a yield or
+/// return
+fn cut_short(resumed: Resumed) -> Option<GeneratorCall> {
+    match resumed {
+        Resumed::Stopped => None,
+        Resumed::Caught => Some(GeneratorCall::Caught),
+        Resumed::Suspended => Some(GeneratorCall::Suspended),
+    }
+}
+
+/// Call a method of a generator, then pass the outcome to
+/// `finish_generator_call`
+///
+/// `throw()` throws the exception from the `yield` the body is suspended
+/// at, once it ran up to its first; it throws it where the method was
+/// called if the body ended.
+pub fn call_generator_method<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+    gen: &GeneratorInstance,
+    method_name: &str,
+    args: &[Value],
+) -> Result<GeneratorCall, VmError> {
+    let id = gen.id;
+    let method_name = method_name.to_ascii_lowercase();
+    if matches!(
+        method_name.as_str(),
+        "current" | "key" | "next" | "valid" | "rewind" | "send"
+    ) {
+        if let Some(call) = cut_short(start(vm, id)?) {
+            return Ok(call);
+        }
+    }
+    let result = match method_name.as_str() {
+        "current" => state_mut(vm, id)?
+            .current
+            .as_ref()
+            .map_or(Value::Null, |(_, value)| value.clone()),
+        "key" => state_mut(vm, id)?
+            .current
+            .as_ref()
+            .map_or(Value::Null, |(key, _)| key.clone()),
+        "next" => {
+            if let Some(call) = cut_short(resume(vm, id, Sent::Value(Value::Null))?) {
+                return Ok(call);
+            }
+            Value::Null
+        }
+        "valid" => Value::Bool(!state_mut(vm, id)?.is_finished()),
+        "rewind" => {
+            if state_mut(vm, id)?.advanced {
+                let exception = super::super::new_throwable(
+                    vm,
+                    "Exception",
+                    "Cannot rewind a generator that was already run",
+                )?;
+                return Ok(GeneratorCall::Throw(Value::Object(exception)));
+            }
+            Value::Null
+        }
+        "getreturn" => {
+            let state = state_mut(vm, id)?;
+            match state.return_value.clone() {
+                Some(value) if state.is_finished() => value,
+                _ => {
+                    let exception = super::super::new_throwable(
+                        vm,
+                        "Exception",
+                        "Cannot get return value of a generator that hasn't returned",
+                    )?;
+                    return Ok(GeneratorCall::Throw(Value::Object(exception)));
+                }
+            }
+        }
+        "send" => {
+            let sent = args.first().cloned().unwrap_or(Value::Null);
+            if let Some(call) = cut_short(resume(vm, id, Sent::Value(sent))?) {
+                return Ok(call);
+            }
+            state_mut(vm, id)?
+                .current
+                .as_ref()
+                .map_or(Value::Null, |(_, value)| value.clone())
+        }
+        "throw" => {
+            let exception = match args.first() {
+                Some(exception @ Value::Object(_)) => exception.clone(),
+                other => {
+                    return Err(VmError::type_error(format!(
+                        "Generator::throw(): Argument #1 ($exception) must be of type Throwable, {} given",
+                        other.map_or("none", |value| value.type_name())
+                    )))
+                }
+            };
+            if let Some(call) = cut_short(start(vm, id)?) {
+                return Ok(call);
+            }
+            if state_mut(vm, id)?.is_finished() {
+                return Ok(GeneratorCall::Throw(exception));
+            }
+            if let Some(call) = cut_short(resume(vm, id, Sent::Exception(exception))?) {
+                return Ok(call);
+            }
+            state_mut(vm, id)?
+                .current
+                .as_ref()
+                .map_or(Value::Null, |(_, value)| value.clone())
+        }
+        _ => {
+            return Err(VmError::fatal(format!(
+                "Method '{}' not found on Generator",
+                method_name
+            )))
+        }
+    };
+    Ok(GeneratorCall::Return(result))
+}
+
+/// Push what a generator method returned, or throw its exception. A
+/// call that suspended the fiber steps the current frame back to the
+/// opcode that made it, whose operands must be back on the stack.
+pub fn finish_generator_call<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+    call: GeneratorCall,
+) -> Result<ControlFlow, VmError> {
+    match call {
+        GeneratorCall::Return(value) => vm.stack.push(value),
+        GeneratorCall::Throw(exception) => {
+            vm.stack.push(exception);
+            super::super::execute_throw(vm)?;
+        }
+        GeneratorCall::Caught => {}
+        GeneratorCall::Suspended => {
+            vm.current_frame_mut().ip -= 1;
+            return Ok(ControlFlow::Suspend);
+        }
+    }
+    Ok(ControlFlow::Next)
+}
+
+/// Call a method of the generator on top of the stack, which stays there
+/// under the result
+fn execute_generator_method<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+    method_name: &str,
+) -> Result<ControlFlow, VmError> {
+    let Some(Value::Generator(gen)) = vm.stack.last().cloned() else {
+        return Err(VmError::fatal(format!(
+            "Generator::{}() requires a Generator object",
+            method_name
+        )));
+    };
+    let call = call_generator_method(vm, &gen, method_name, &[])?;
+    finish_generator_call(vm, call)
+}
+
+pub fn execute_generator_current<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+) -> Result<ControlFlow, VmError> {
+    execute_generator_method(vm, "current")
+}
+
+pub fn execute_generator_key<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+) -> Result<ControlFlow, VmError> {
+    execute_generator_method(vm, "key")
+}
+
+pub fn execute_generator_next<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+) -> Result<ControlFlow, VmError> {
+    let flow = execute_generator_method(vm, "next")?;
+    if let ControlFlow::Suspend = flow {
+        return Ok(flow);
+    }
+    vm.stack.pop();
+    execute_generator_method(vm, "valid")
+}
+
+pub fn execute_generator_rewind<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+) -> Result<ControlFlow, VmError> {
+    let flow = execute_generator_method(vm, "rewind")?;
+    if let ControlFlow::Next = flow {
+        vm.stack.pop();
+    }
+    Ok(flow)
+}
+
+pub fn execute_generator_valid<W: std::io::Write>(
+    vm: &mut crate::vm::VM<W>,
+) -> Result<ControlFlow, VmError> {
+    execute_generator_method(vm, "valid")
+}
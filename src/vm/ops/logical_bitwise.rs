@@ -1,30 +1,147 @@
 use crate::runtime::Value;
+use crate::vm::error::VmError;
 
-pub fn execute_not<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_not<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     vm.stack.push(Value::Bool(!value.to_bool()));
     Ok(())
 }
 
-pub fn execute_and<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_and<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     vm.stack
         .push(Value::Bool(left.to_bool() && right.to_bool()));
     Ok(())
 }
 
-pub fn execute_or<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_or<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     vm.stack
         .push(Value::Bool(left.to_bool() || right.to_bool()));
     Ok(())
 }
 
-pub fn execute_xor<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let right = vm.stack.pop().ok_or("Stack underflow")?;
-    let left = vm.stack.pop().ok_or("Stack underflow")?;
+pub fn execute_xor<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     vm.stack.push(Value::Bool(left.to_bool() ^ right.to_bool()));
     Ok(())
 }
+
+/// The integer values of a bitwise operator's operands
+fn integer_operands(left: &Value, right: &Value, symbol: &str) -> Result<(i64, i64), VmError> {
+    let is_scalar = |value: &Value| {
+        matches!(
+            value,
+            Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Float(_) | Value::String(_)
+        )
+    };
+    if !is_scalar(left) || !is_scalar(right) {
+        return Err(VmError::type_error(format!(
+            "Unsupported operand types: {} {} {}",
+            left.type_name(),
+            symbol,
+            right.type_name()
+        )));
+    }
+    Ok((left.to_int(), right.to_int()))
+}
+
+/// `&`, `|` or `^`: byte by byte when both operands are strings, as far
+/// as the shorter one goes (`|` keeps the rest of the longer one), and on
+/// the integer values otherwise
+fn execute_bitwise<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    symbol: &str,
+    op: fn(i64, i64) -> i64,
+) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let result = match (&left, &right) {
+        (Value::String(a), Value::String(b)) => {
+            let (a, b) = (a.as_bytes(), b.as_bytes());
+            let mut bytes: Vec<u8> = a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| op(*x as i64, *y as i64) as u8)
+                .collect();
+            if symbol == "|" {
+                let longer = if a.len() > b.len() { a } else { b };
+                bytes.extend_from_slice(&longer[bytes.len()..]);
+            }
+            Value::String(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => {
+            let (a, b) = integer_operands(&left, &right, symbol)?;
+            Value::Integer(op(a, b))
+        }
+    };
+    vm.stack.push(result);
+    Ok(())
+}
+
+pub fn execute_bitwise_and<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    execute_bitwise(vm, "&", |a, b| a & b)
+}
+
+pub fn execute_bitwise_or<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    execute_bitwise(vm, "|", |a, b| a | b)
+}
+
+pub fn execute_bitwise_xor<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    execute_bitwise(vm, "^", |a, b| a ^ b)
+}
+
+pub fn execute_bitwise_not<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let result = match &value {
+        Value::Integer(n) => Value::Integer(!n),
+        Value::Float(f) => Value::Integer(!(*f as i64)),
+        Value::String(s) => {
+            let bytes: Vec<u8> = s.bytes().map(|b| !b).collect();
+            Value::String(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => {
+            return Err(VmError::type_error(format!(
+                "Cannot perform bitwise not on {}",
+                value.type_name()
+            )))
+        }
+    };
+    vm.stack.push(result);
+    Ok(())
+}
+
+/// `<<` or `>>`; shifting by 64 bits or more shifts every bit out
+fn execute_shift<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    symbol: &str,
+) -> Result<(), VmError> {
+    let right = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let left = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let (value, count) = integer_operands(&left, &right, symbol)?;
+    if count < 0 {
+        return Err(VmError::exception(
+            "ArithmeticError",
+            "Bit shift by negative number",
+        ));
+    }
+    let result = match (symbol, count) {
+        ("<<", 64..) => 0,
+        ("<<", _) => value << count,
+        (_, 64..) => value >> 63,
+        _ => value >> count,
+    };
+    vm.stack.push(Value::Integer(result));
+    Ok(())
+}
+
+pub fn execute_shift_left<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    execute_shift(vm, "<<")
+}
+
+pub fn execute_shift_right<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    execute_shift(vm, ">>")
+}
@@ -9,8 +9,11 @@ mod call_ops;
 mod callable_ops;
 mod comparison;
 mod control_flow;
+mod element_assign;
+mod enums;
 mod exceptions;
 mod fiber;
+mod foreach;
 mod generator;
 mod logical_bitwise;
 mod method_calls;
@@ -19,6 +22,7 @@ mod named_call_ops;
 mod object_creation;
 mod property_access;
 mod property_ops;
+mod references;
 mod static_ops;
 mod strings;
 
@@ -28,8 +32,11 @@ pub use call_ops::*;
 pub use callable_ops::*;
 pub use comparison::*;
 pub use control_flow::*;
+pub use element_assign::*;
+pub use enums::*;
 pub use exceptions::*;
 pub use fiber::*;
+pub use foreach::*;
 pub use generator::*;
 pub use logical_bitwise::*;
 pub use method_calls::*;
@@ -38,5 +45,6 @@ pub use named_call_ops::*;
 pub use object_creation::*;
 pub use property_access::*;
 pub use property_ops::*;
+pub use references::*;
 pub use static_ops::*;
 pub use strings::*;
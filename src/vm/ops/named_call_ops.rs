@@ -1,13 +1,14 @@
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::VmError;
 use crate::vm::frame::CallFrame;
 
 pub fn execute_call_named_args<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     func_name_idx: u32,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let func_name = vm.current_frame().get_string(func_name_idx).to_string();
 
-    let args_array = vm.stack.pop().ok_or("Stack underflow")?;
+    let args_array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let (positional_args, named_args) = match args_array {
         Value::Array(arr) => {
             let mut positional = Vec::new();
@@ -29,7 +30,7 @@ pub fn execute_call_named_args<W: std::io::Write>(
 
             (positional, named)
         }
-        _ => return Err("CallNamed expects an array of arguments".to_string()),
+        _ => return Err(VmError::fatal("CallNamed expects an array of arguments")),
     };
 
     if let Some(func) = vm.get_function(&func_name) {
@@ -46,10 +47,10 @@ pub fn execute_call_named_args<W: std::io::Write>(
                 } else if func.parameters[i].default.is_some() {
                     args.push(Value::Null);
                 } else if i < func.required_param_count as usize {
-                    return Err(format!(
+                    return Err(VmError::fatal(format!(
                         "Missing required argument '{}' for function {}()",
                         param_name, func.name
-                    ));
+                    )));
                 } else {
                     args.push(Value::Null);
                 }
@@ -58,40 +59,38 @@ pub fn execute_call_named_args<W: std::io::Write>(
 
         for name in named_args.keys() {
             if !func.parameters.iter().any(|p| &p.name == name) {
-                return Err(format!(
+                return Err(VmError::fatal(format!(
                     "Unknown named parameter '{}' for function {}()",
                     name, func.name
-                ));
+                )));
             }
         }
 
         for (i, arg) in args.iter().enumerate() {
-            if i < func.param_types.len() {
-                if let Some(ref type_hint) = func.param_types[i] {
-                    let use_strict = func.strict_types || vm.requires_strict_type_check(type_hint);
-                    if use_strict {
-                        if !vm.value_matches_type_strict(arg, type_hint) {
-                            let type_name = vm.format_type_hint(type_hint);
-                            let given_type = vm.get_value_type_name(arg);
-                            return Err(format!(
-                                "Argument {} passed to {}() must be of type {}, {} given",
-                                i + 1,
-                                func.name,
-                                type_name,
-                                given_type
-                            ));
-                        }
-                    } else if !vm.value_matches_type(arg, type_hint) {
+            if let Some(type_hint) = func.param_type(i) {
+                let use_strict = func.strict_types || vm.requires_strict_type_check(type_hint);
+                if use_strict {
+                    if !vm.value_matches_type_strict(arg, type_hint) {
                         let type_name = vm.format_type_hint(type_hint);
                         let given_type = vm.get_value_type_name(arg);
-                        return Err(format!(
+                        return Err(VmError::type_error(format!(
                             "Argument {} passed to {}() must be of type {}, {} given",
                             i + 1,
                             func.name,
                             type_name,
                             given_type
-                        ));
+                        )));
                     }
+                } else if !vm.value_matches_type(arg, type_hint) {
+                    let type_name = vm.format_type_hint(type_hint);
+                    let given_type = vm.get_value_type_name(arg);
+                    return Err(VmError::type_error(format!(
+                        "Argument {} passed to {}() must be of type {}, {} given",
+                        i + 1,
+                        func.name,
+                        type_name,
+                        given_type
+                    )));
                 }
             }
         }
@@ -121,13 +120,24 @@ pub fn execute_call_named_args<W: std::io::Write>(
                     frame.locals[i] = coerced_arg;
                 }
             }
+            let variadic_type = func.param_type(variadic_slot);
             let variadic_args: Vec<(ArrayKey, Value)> = args
                 .into_iter()
                 .skip(variadic_slot)
                 .enumerate()
-                .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
+                .map(|(i, v)| {
+                    let v = match variadic_type {
+                        Some(type_hint)
+                            if !func.strict_types && !vm.requires_strict_type_check(type_hint) =>
+                        {
+                            vm.coerce_value_to_type(v, type_hint)
+                        }
+                        _ => v,
+                    };
+                    (ArrayKey::Integer(i as i64), v)
+                })
                 .collect();
-            frame.locals[variadic_slot] = Value::Array(variadic_args);
+            frame.locals[variadic_slot] = Value::Array(variadic_args.into());
         } else {
             for (i, arg) in args.into_iter().enumerate() {
                 if i < frame.locals.len() {
@@ -151,8 +161,8 @@ pub fn execute_call_named_args<W: std::io::Write>(
             }
         }
 
-        vm.frames.push(frame);
-    } else if super::super::builtins::is_builtin(&func_name) {
+        vm.push_frame(frame);
+    } else if vm.is_native_function(&func_name) {
         let mut args = positional_args;
         for (_, v) in named_args {
             args.push(v);
@@ -160,7 +170,7 @@ pub fn execute_call_named_args<W: std::io::Write>(
         let result = vm.call_reflection_or_builtin(&func_name, &args)?;
         vm.stack.push(result);
     } else {
-        return Err(format!("undefined function: {}", func_name));
+        return Err(VmError::undefined_function(&func_name));
     }
     Ok(())
 }
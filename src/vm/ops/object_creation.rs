@@ -1,22 +1,30 @@
 use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::frame::FrameResult;
+use crate::vm::typed_properties::starts_uninitialized;
 use crate::vm::VM;
 
 pub fn execute_new_object<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     class_name: String,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let class_name = VM::<W>::normalize_class_name(&class_name);
     let class_def = vm
         .get_class_with_autoload(&class_name)
-        .ok_or_else(|| format!("Class '{}' not found", class_name))?
+        .ok_or_else(|| VmError::class_not_found(&class_name))?
         .clone();
 
     if class_def.is_abstract {
-        return Err(format!("Cannot instantiate abstract class {}", class_name));
+        return Err(VmError::error(format!(
+            "Cannot instantiate abstract class {}",
+            class_def.name
+        )));
     }
 
+    // The instance takes the name the class was declared with, whatever
+    // case `new` used
     let mut instance = crate::runtime::ObjectInstance::with_hierarchy(
-        class_name.clone(),
+        class_def.name.clone(),
         class_def.parent.clone(),
         class_def.interfaces.clone(),
     );
@@ -34,101 +42,168 @@ pub fn execute_new_object<W: std::io::Write>(
 
     for parent_def in parent_chain.iter().rev() {
         for prop in &parent_def.properties {
+            if prop.readonly {
+                instance.readonly_properties.insert(prop.name.clone());
+            }
+            if starts_uninitialized(prop) {
+                continue;
+            }
             let default_val = prop.default.clone().unwrap_or(Value::Null);
             instance
                 .properties
                 .insert(prop.name.clone(), default_val.clone());
-            if prop.readonly {
-                instance.readonly_properties.insert(prop.name.clone());
-                if prop.default.is_some() {
-                    instance.initialized_readonly.insert(prop.name.clone());
-                }
+            if prop.readonly && prop.default.is_some() {
+                instance.initialized_readonly.insert(prop.name.clone());
             }
         }
     }
 
     for prop in &class_def.properties {
+        if prop.readonly {
+            instance.readonly_properties.insert(prop.name.clone());
+        }
+        if starts_uninitialized(prop) {
+            continue;
+        }
         let default_val = prop.default.clone().unwrap_or(Value::Null);
         instance
             .properties
             .insert(prop.name.clone(), default_val.clone());
-        if prop.readonly {
-            instance.readonly_properties.insert(prop.name.clone());
-            if prop.default.is_some() {
-                instance.initialized_readonly.insert(prop.name.clone());
-            }
+        if prop.readonly && prop.default.is_some() {
+            instance.initialized_readonly.insert(prop.name.clone());
         }
     }
 
-    vm.stack.push(Value::Object(instance));
+    // A Throwable records where it was created, which throwing it keeps
+    let mut classes = std::iter::once(&class_def).chain(&parent_chain);
+    if classes.any(|class| matches!(class.name.as_str(), "Exception" | "Error")) {
+        if let Some(location) = vm.current_location() {
+            let line = Value::Integer(location.line as i64);
+            instance
+                .properties
+                .insert("file".to_string(), Value::String(location.file));
+            instance.properties.insert("line".to_string(), line);
+        }
+        let trace = super::capture_backtrace(&vm.frames);
+        instance.properties.insert("trace".to_string(), trace);
+    }
+
+    vm.stack.push(Value::Object(instance.into()));
     Ok(())
 }
 
 pub fn execute_instance_of<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     class_name: String,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let class_name = VM::<W>::normalize_class_name(&class_name);
-    let object = vm.stack.pop().ok_or("Stack underflow")?;
+    let object = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
 
     let result = match object {
         Value::Object(instance) => {
+            let instance = instance.lock();
             instance.class_name == class_name
                 || instance.parent_class.as_ref() == Some(&class_name)
                 || instance.interfaces.contains(&class_name)
         }
+        Value::EnumCase { enum_name, .. } => vm.is_instance_of(&enum_name, &class_name),
         _ => false,
     };
     vm.stack.push(Value::Bool(result));
     Ok(())
 }
 
-pub fn execute_clone<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
-    let object = vm.stack.pop().ok_or("Stack underflow")?;
-    match object {
+/// Copy an object and run its `__clone()` on the copy. Readonly properties
+/// may be set once more until `__clone()` returns.
+pub fn execute_clone<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let object = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let uncloneable = match &object {
         Value::Object(instance) => {
-            let cloned = instance.clone();
-            vm.stack.push(Value::Object(cloned));
+            let cloned = instance.duplicate();
+            if let Some(method) = vm.find_method_in_chain(cloned.class_name(), "__clone") {
+                let initialized = std::mem::take(&mut cloned.lock().initialized_readonly);
+                let stack_base = vm.stack.len();
+                let mut frame = super::super::frame::CallFrame::new(method, stack_base);
+                frame.called_class = Some(cloned.class_name().to_string());
+                frame.locals[0] = Value::Object(cloned);
+                frame.result = FrameResult::Clone(initialized);
+                vm.push_frame(frame);
+            } else {
+                vm.stack.push(Value::Object(cloned));
+            }
+            return Ok(());
         }
-        _ => return Err("__clone method called on non-object".to_string()),
-    }
-    Ok(())
+        Value::Closure(_) | Value::Exception(_) => {
+            vm.stack.push(object);
+            return Ok(());
+        }
+        Value::EnumCase { enum_name, .. } => enum_name.clone(),
+        Value::Generator(_) => "Generator".to_string(),
+        Value::Fiber(_) => "Fiber".to_string(),
+        _ => return Err(VmError::fatal("__clone method called on non-object")),
+    };
+    Err(VmError::error(format!(
+        "Trying to clone an uncloneable object of class {}",
+        uncloneable
+    )))
+}
+
+/// `new $class`: instantiate the class a string names, or the class of
+/// an object
+pub fn execute_new_object_dynamic<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+) -> Result<(), VmError> {
+    let class = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let class_name = match class {
+        Value::String(name) => vm.resolve_class_keyword(&name)?,
+        Value::Object(instance) => instance.class_name().to_string(),
+        _ => {
+            return Err(VmError::fatal(
+                "Class name must be a valid object or a string",
+            ))
+        }
+    };
+    execute_new_object(vm, VM::<W>::normalize_class_name(&class_name))
 }
 
+/// `Class::NAME`: an enum case, or a class, interface or enum constant
 pub fn execute_load_enum_case<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     enum_name: String,
     case_name: String,
-) -> Result<(), String> {
-    let enum_name = VM::<W>::normalize_class_name(&enum_name);
-    let enum_def = vm
-        .enums
-        .get(&enum_name)
-        .ok_or_else(|| format!("Enum '{}' not found", enum_name))?
-        .clone();
-
-    let backing_value = enum_def
-        .cases
-        .get(&case_name)
-        .ok_or_else(|| format!("Undefined case '{}' for enum '{}'", case_name, enum_name))?
-        .clone()
-        .map(Box::new);
-
-    vm.stack.push(Value::EnumCase {
-        enum_name,
-        case_name,
-        backing_value,
-    });
-    Ok(())
+) -> Result<(), VmError> {
+    let class_name = VM::<W>::normalize_class_name(&vm.resolve_class_keyword(&enum_name)?);
+    let is_declared = |vm: &VM<W>| {
+        vm.enums.contains_key(&class_name)
+            || vm.classes.contains_key(&class_name)
+            || vm.interfaces.contains_key(&class_name)
+    };
+    if !is_declared(vm) {
+        vm.get_class_with_autoload(&class_name);
+    }
+    if let Some(value) = vm.class_constant(&class_name, &case_name) {
+        vm.stack.push(value);
+        return Ok(());
+    }
+    Err(if vm.enums.contains_key(&class_name) {
+        VmError::fatal(format!(
+            "Undefined case '{}' for enum '{}'",
+            case_name, class_name
+        ))
+    } else if is_declared(vm) {
+        VmError::error(format!("Undefined constant {}::{}", class_name, case_name))
+    } else {
+        VmError::class_not_found(&class_name)
+    })
 }
 
-pub fn execute_load_this<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), String> {
+pub fn execute_load_this<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
     let frame = vm.current_frame();
     let this = frame
         .locals
         .first()
         .cloned()
-        .ok_or("No $this available in current context")?;
+        .ok_or_else(|| VmError::fatal("No $this available in current context"))?;
     vm.stack.push(this);
     Ok(())
 }
@@ -1,74 +1,77 @@
-use crate::runtime::Value;
-use crate::vm::frame::ThisSource;
+use crate::runtime::{ArrayKey, ObjectRef, Value};
+use crate::vm::error::VmError;
+use crate::vm::frame::FrameResult;
 
 pub fn execute_store_this_property<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     prop_name: String,
-) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
 
-    let this = vm.current_frame().get_local(0).clone();
-    match this {
-        Value::Object(mut instance) => {
-            if instance.readonly_properties.contains(&prop_name)
-                && instance.initialized_readonly.contains(&prop_name)
-            {
-                return Err(format!("Cannot modify readonly property {}", prop_name));
-            }
-            instance.properties.insert(prop_name.clone(), value.clone());
-            if instance.readonly_properties.contains(&prop_name) {
-                instance.initialized_readonly.insert(prop_name);
-            }
-            vm.current_frame_mut().set_local(0, Value::Object(instance));
+    // Outside a method there is no slot 0 to hold $this
+    let this = vm.current_frame().locals.first().cloned();
+    match this.unwrap_or(Value::Null) {
+        Value::Object(instance) => {
+            let value = assign_property(vm, &instance, prop_name, value)?;
             vm.stack.push(value);
         }
-        _ => return Err("$this is not an object".to_string()),
+        _ => return Err(VmError::fatal("$this is not an object")),
     }
     Ok(())
 }
 
+/// Set a property without hooks, unless it is readonly and set already,
+/// giving the value stored
+fn assign_property<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    instance: &ObjectRef,
+    prop_name: String,
+    value: Value,
+) -> Result<Value, VmError> {
+    let (is_readonly, is_set) = {
+        let instance = instance.lock();
+        (
+            instance.readonly_properties.contains(&prop_name),
+            instance.properties.contains_key(&prop_name),
+        )
+    };
+    if is_readonly && instance.lock().initialized_readonly.contains(&prop_name) {
+        return Err(VmError::error(format!(
+            "Cannot modify readonly property {}::${}",
+            instance.class_name(),
+            prop_name
+        )));
+    }
+    if !is_set {
+        vm.deprecated_dynamic_property(instance.class_name(), &prop_name)?;
+    }
+    let value = vm.check_property_assignment(instance.class_name(), &prop_name, value)?;
+    let mut instance = instance.lock();
+    instance.properties.insert(prop_name.clone(), value.clone());
+    if is_readonly {
+        instance.initialized_readonly.insert(prop_name);
+    }
+    Ok(value)
+}
+
 pub fn execute_store_property<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     prop_name: String,
-) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
-    let object = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let object = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
 
     match object {
         Value::Object(instance) => {
-            if let Some(class) = vm.classes.get(&instance.class_name).cloned() {
+            if let Some(class) = vm.classes.get(instance.class_name()).cloned() {
                 if let Some(prop_def) = class.properties.iter().find(|p| p.name == prop_name) {
-                    if let Some(write_vis) = &prop_def.write_visibility {
-                        let current_class = vm.get_current_class();
-                        let can_write = match write_vis {
-                            crate::ast::Visibility::Private => {
-                                current_class.as_ref() == Some(&instance.class_name)
-                            }
-                            crate::ast::Visibility::Protected => {
-                                if let Some(ref curr) = current_class {
-                                    curr == &instance.class_name
-                                        || vm.is_subclass_of(curr, &instance.class_name)
-                                } else {
-                                    false
-                                }
-                            }
-                            crate::ast::Visibility::Public => true,
-                        };
-                        if !can_write {
-                            let vis_str = match write_vis {
-                                crate::ast::Visibility::Private => "private",
-                                crate::ast::Visibility::Protected => "protected",
-                                crate::ast::Visibility::Public => "public",
-                            };
-                            return Err(format!(
-                                "Cannot modify {} property {}",
-                                vis_str, prop_name
-                            ));
-                        }
-                    }
+                    check_write_visibility(vm, instance.class_name(), prop_def)?;
 
                     if prop_def.get_hook.is_some() && prop_def.set_hook.is_none() {
-                        return Err(format!("Cannot write to read-only property {}", prop_name));
+                        return Err(VmError::fatal(format!(
+                            "Cannot write to read-only property {}",
+                            prop_name
+                        )));
                     }
 
                     if let Some(ref hook_method_name) = prop_def.set_hook {
@@ -77,75 +80,121 @@ pub fn execute_store_property<W: std::io::Write>(
                             let mut frame =
                                 super::super::frame::CallFrame::new(hook_method, stack_base);
                             frame.locals[0] = Value::Object(instance);
-                            frame.locals[1] = value;
-                            frame.this_source = ThisSource::PropertySetHook;
-                            vm.frames.push(frame);
+                            frame.locals[1] = value.clone();
+                            frame.result = FrameResult::Assigned(value);
+                            vm.push_frame(frame);
                             return Ok(());
                         }
                     }
                 }
             }
 
-            let prop_defined_in_class = if let Some(class) = vm.classes.get(&instance.class_name) {
+            let prop_defined_in_class = if let Some(class) = vm.classes.get(instance.class_name()) {
                 class.properties.iter().any(|p| p.name == prop_name)
             } else {
                 false
             };
 
-            if !prop_defined_in_class && !instance.properties.contains_key(&prop_name) {
-                if let Some(set_method) = vm.find_method_in_chain(&instance.class_name, "__set") {
+            if !prop_defined_in_class && instance.get(&prop_name).is_none() {
+                if let Some(set_method) = vm.find_method_in_chain(instance.class_name(), "__set") {
                     let stack_base = vm.stack.len();
                     let mut frame = super::super::frame::CallFrame::new(set_method, stack_base);
                     frame.locals[0] = Value::Object(instance);
                     frame.locals[1] = Value::String(prop_name);
-                    frame.locals[2] = value;
-                    frame.this_source = ThisSource::PropertySetHook;
-                    vm.frames.push(frame);
+                    frame.locals[2] = value.clone();
+                    frame.result = FrameResult::Assigned(value);
+                    vm.push_frame(frame);
                     return Ok(());
                 }
             }
 
-            let mut instance = instance;
-            if instance.readonly_properties.contains(&prop_name)
-                && instance.initialized_readonly.contains(&prop_name)
-            {
-                return Err(format!("Cannot modify readonly property {}", prop_name));
-            }
-            instance.properties.insert(prop_name.clone(), value.clone());
-            if instance.readonly_properties.contains(&prop_name) {
-                instance.initialized_readonly.insert(prop_name);
-            }
-            vm.stack.push(Value::Object(instance));
+            let value = assign_property(vm, &instance, prop_name, value)?;
+            vm.stack.push(value);
         }
-        _ => return Err("Cannot set property on non-object".to_string()),
+        _ => return Err(VmError::fatal("Cannot set property on non-object")),
     }
     Ok(())
 }
 
-pub fn execute_store_clone_property<W: std::io::Write>(
-    vm: &mut super::super::VM<W>,
-    prop_idx: u32,
-) -> Result<(), String> {
-    let prop_name = vm.current_frame().get_string(prop_idx).to_string();
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
-    let object = vm.stack.pop().ok_or("Stack underflow")?;
+/// Give a fresh clone the property values of a clone-with array. These
+/// skip hooks and may overwrite readonly properties, but the properties
+/// must be declared and writable from the calling scope.
+pub fn execute_clone_with<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let properties = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let object = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
 
-    match object {
-        Value::Object(mut instance) => {
-            if !instance.properties.contains_key(&prop_name) {
-                return Err(format!(
-                    "Property '{}' does not exist on class '{}'",
-                    prop_name, instance.class_name
-                ));
-            }
+    let Value::Array(properties) = properties else {
+        return Err(VmError::type_error(format!(
+            "clone(): Argument #2 ($withProperties) must be of type array, {} given",
+            properties.type_name()
+        )));
+    };
+    let Value::Object(instance) = object else {
+        return Err(VmError::fatal("Cannot set property on non-object"));
+    };
+    let class = vm.classes.get(instance.class_name()).cloned();
+    for (key, value) in properties {
+        let ArrayKey::String(prop_name) = key else {
+            return Err(VmError::type_error(
+                "Property name must be of type string, int given",
+            ));
+        };
+        if instance.get(&prop_name).is_none() {
+            return Err(VmError::fatal(format!(
+                "Property '{}' does not exist on class '{}'",
+                prop_name,
+                instance.class_name()
+            )));
+        }
+        if let Some(prop_def) = class
+            .as_ref()
+            .and_then(|class| class.properties.iter().find(|p| p.name == prop_name))
+        {
+            check_write_visibility(vm, instance.class_name(), prop_def)?;
+        }
+        let value = vm.check_property_assignment(instance.class_name(), &prop_name, value)?;
+        let mut instance = instance.lock();
+        instance.properties.insert(prop_name.clone(), value);
+        if instance.readonly_properties.contains(&prop_name) {
+            instance.initialized_readonly.insert(prop_name);
+        }
+    }
+    vm.stack.push(Value::Object(instance));
+    Ok(())
+}
 
-            instance.properties.insert(prop_name.clone(), value.clone());
-            if instance.readonly_properties.contains(&prop_name) {
-                instance.initialized_readonly.insert(prop_name);
+/// Fail unless the calling scope may write `prop_def`, a property of an
+/// object of class `class_name`, under its asymmetric visibility
+fn check_write_visibility<W: std::io::Write>(
+    vm: &super::super::VM<W>,
+    class_name: &str,
+    prop_def: &crate::vm::class::CompiledProperty,
+) -> Result<(), VmError> {
+    let Some(write_vis) = &prop_def.write_visibility else {
+        return Ok(());
+    };
+    let current_class = vm.get_current_class();
+    let can_write = match write_vis {
+        crate::ast::Visibility::Private => current_class.as_deref() == Some(class_name),
+        crate::ast::Visibility::Protected => {
+            if let Some(ref curr) = current_class {
+                curr == class_name || vm.is_subclass_of(curr, class_name)
+            } else {
+                false
             }
-            vm.stack.push(Value::Object(instance));
         }
-        _ => return Err("Cannot set property on non-object".to_string()),
+        crate::ast::Visibility::Public => true,
+    };
+    if !can_write {
+        let vis_str = match write_vis {
+            crate::ast::Visibility::Private => "private",
+            crate::ast::Visibility::Protected => "protected",
+            crate::ast::Visibility::Public => "public",
+        };
+        return Err(VmError::fatal(format!(
+            "Cannot modify {} property {}",
+            vis_str, prop_def.name
+        )));
     }
     Ok(())
 }
@@ -0,0 +1,248 @@
+//! References between variables
+//!
+//! A variable passed straight to a function is loaded as a reference to
+//! its slot (`LoadRef`), since which parameters take a reference is only
+//! known once the call finds its function. The call then hands the
+//! by-reference parameters the references and every other parameter the
+//! value (`pass_args`), putting a variable that turned out to be passed by
+//! value back the way it was.
+//!
+//! An element or a property passed to a function or a method is only
+//! taken by reference if the parameter asks for one (`IsRefArg`), so that
+//! passing it by value reads it the usual way.
+
+use crate::runtime::{ArrayKey, Reference, Value};
+use crate::vm::builtins;
+use crate::vm::error::VmError;
+use crate::vm::opcode::CompiledFunction;
+
+/// `&$local`: push a reference to a local, to pass it to a function. A
+/// local the compiler knows is assigned is marked so (parameters are put
+/// in their slots unmarked), for `pass_args` to tell which are not.
+pub fn execute_load_ref<W: std::io::Write>(vm: &mut super::super::VM<W>, slot: u16, checked: bool) {
+    let frame = vm.current_frame_mut();
+    if !checked {
+        frame.mark_assigned(slot);
+    }
+    let reference = frame.reference(slot);
+    vm.stack.push(Value::Reference(reference));
+}
+
+/// `$target = &$source`: bind both locals to one value, and push it
+pub fn execute_assign_ref<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    target: u16,
+    source: u16,
+) {
+    let frame = vm.current_frame_mut();
+    if !frame.is_assigned(source) {
+        frame.set_local(source, Value::Null);
+    }
+    let reference = frame.reference(source);
+    let value = reference.get();
+    frame.bind_local(target, reference);
+    vm.stack.push(value);
+}
+
+/// `&$array[$key]`: a reference to an element of the array the reference
+/// on the stack refers to. Binding an element defines it, as null.
+pub fn execute_element_ref<W: std::io::Write>(vm: &mut super::super::VM<W>) -> Result<(), VmError> {
+    let key = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let Some(Value::Reference(array)) = vm.stack.pop() else {
+        return Err(VmError::fatal(
+            "Cannot create a reference to a temporary value",
+        ));
+    };
+    let key = ArrayKey::from_value(&key);
+    let defined = match array.get() {
+        Value::Array(arr) => arr.get(&key).is_some(),
+        Value::Null => false,
+        Value::String(_) => {
+            return Err(VmError::fatal(
+                "Cannot create references to/from string offsets",
+            ))
+        }
+        Value::Object(instance) => {
+            return Err(VmError::fatal(format!(
+                "Indirect modification of overloaded element of {}",
+                instance.class_name()
+            )))
+        }
+        _ => return Err(VmError::fatal("Cannot use a scalar value as an array")),
+    };
+    let element = Reference::element(array, key);
+    if !defined {
+        element.set(Value::Null);
+    }
+    vm.stack.push(Value::Reference(element));
+    Ok(())
+}
+
+/// `&$object->name`: a reference to a property, defining it as null if
+/// the object has none by that name
+pub fn execute_property_ref<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    name: String,
+) -> Result<(), VmError> {
+    let object = vm.stack.pop().ok_or_else(VmError::stack_underflow)?.unref();
+    let Value::Object(object) = object else {
+        return Err(VmError::fatal(format!(
+            "Attempt to modify property \"{}\" on {}",
+            name,
+            object.get_type()
+        )));
+    };
+    if object.get(&name).is_none() {
+        object.set(name.clone(), Value::Null);
+    }
+    vm.stack
+        .push(Value::Reference(Reference::property(object, name)));
+    Ok(())
+}
+
+/// `$local = &...`: bind a local to the reference on the stack, and push
+/// its value
+pub fn execute_bind_ref<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    slot: u16,
+) -> Result<(), VmError> {
+    let Some(Value::Reference(reference)) = vm.stack.pop() else {
+        return Err(VmError::stack_underflow());
+    };
+    let value = reference.get();
+    vm.current_frame_mut().bind_local(slot, reference);
+    vm.stack.push(value);
+    Ok(())
+}
+
+/// Push whether parameter `pos` of the function `name` takes a reference
+pub fn execute_is_ref_arg<W: std::io::Write>(vm: &mut super::super::VM<W>, name: &str, pos: u8) {
+    let function = vm.get_function(name);
+    vm.stack
+        .push(Value::Bool(takes_ref(function.as_deref(), pos as usize)));
+}
+
+/// Push whether parameter `pos` of the method `name` of the object under
+/// the arguments before it takes a reference
+pub fn execute_is_method_ref_arg<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    name: &str,
+    pos: u8,
+) -> Result<(), VmError> {
+    let depth = pos as usize + 1;
+    let object = vm
+        .stack
+        .len()
+        .checked_sub(depth)
+        .map(|index| vm.stack[index].clone())
+        .ok_or_else(VmError::stack_underflow)?;
+    let method = match object {
+        Value::Object(instance) => vm.find_method_in_chain(instance.class_name(), name),
+        _ => None,
+    };
+    vm.stack
+        .push(Value::Bool(takes_ref(method.as_deref(), pos as usize)));
+    Ok(())
+}
+
+/// Whether parameter `pos` of `function` takes a reference
+fn takes_ref(function: Option<&CompiledFunction>, pos: usize) -> bool {
+    function
+        .and_then(|function| function.parameters.get(pos))
+        .is_some_and(|param| param.by_ref && !param.is_variadic)
+}
+
+/// `global $name`: bind a local to the global variable, unless the code
+/// is the script's main code, whose locals are the global variables
+pub fn execute_bind_global<W: std::io::Write>(
+    vm: &mut super::super::VM<W>,
+    name: String,
+    slot: u16,
+) {
+    if vm.frames.len() == 1 {
+        return;
+    }
+    let reference = vm.global_reference(&name);
+    vm.current_frame_mut().bind_local(slot, reference);
+}
+
+impl<W: std::io::Write> super::super::VM<W> {
+    /// Turn the references among a call's arguments into the values the
+    /// parameters take, giving the references for the by-reference
+    /// parameters of `function` (none for a built-in) to bind with
+    /// `CallFrame::bind_refs`. A variable passed by value that was never
+    /// assigned warns here, where the call knows it was read.
+    pub(crate) fn pass_args(
+        &mut self,
+        args: &mut [Value],
+        function: Option<&CompiledFunction>,
+    ) -> Result<Vec<Option<Reference>>, VmError> {
+        self.pass_args_by(args, |pos| takes_ref(function, pos))
+    }
+
+    /// The same for the built-in function `name`, which is given the
+    /// references for its by-reference parameters as its arguments
+    pub(crate) fn pass_native_args(
+        &mut self,
+        name: &str,
+        args: &mut [Value],
+    ) -> Result<(), VmError> {
+        let refs = self.pass_args_by(args, |pos| builtins::takes_ref(name, pos))?;
+        for (arg, reference) in args.iter_mut().zip(refs) {
+            if let Some(reference) = reference {
+                *arg = Value::Reference(reference);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pass the arguments to parameters that take a reference if `by_ref`
+    fn pass_args_by(
+        &mut self,
+        args: &mut [Value],
+        by_ref: impl Fn(usize) -> bool,
+    ) -> Result<Vec<Option<Reference>>, VmError> {
+        let mut refs = Vec::new();
+        for (i, arg) in args.iter_mut().enumerate() {
+            let Value::Reference(reference) = arg else {
+                continue;
+            };
+            let by_ref = by_ref(i);
+            let reference = reference.clone();
+            *arg = reference.get();
+            let slot = self.referenced_slot(&reference);
+            if refs.len() <= i {
+                refs.resize(i + 1, None);
+            }
+            if by_ref {
+                // Passing a variable by reference defines it
+                if let Some(slot) = slot.filter(|&slot| !self.current_frame().is_assigned(slot)) {
+                    self.current_frame_mut().set_local(slot, Value::Null);
+                }
+                refs[i] = Some(reference);
+                continue;
+            }
+            drop(reference);
+            let Some(slot) = slot else {
+                continue;
+            };
+            let frame = self.current_frame_mut();
+            frame.unshare_local(slot);
+            if !frame.is_assigned(slot) {
+                let name = frame.function.local_names[slot as usize].clone();
+                self.warn_undefined_variable(&name)?;
+            }
+        }
+        Ok(refs)
+    }
+
+    /// The slot of the calling frame that `LoadRef` took `reference` from
+    fn referenced_slot(&self, reference: &Reference) -> Option<u16> {
+        let frame = self.current_frame();
+        let slot = frame
+            .locals
+            .iter()
+            .position(|local| matches!(local, Value::Reference(r) if r.ptr_eq(reference)))?;
+        Some(slot as u16)
+    }
+}
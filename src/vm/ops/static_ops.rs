@@ -1,4 +1,5 @@
 use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::VmError;
 use crate::vm::frame::CallFrame;
 
 pub fn execute_call_static_method<W: std::io::Write>(
@@ -6,125 +7,38 @@ pub fn execute_call_static_method<W: std::io::Write>(
     class_name: String,
     method_name: String,
     arg_count: u8,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let mut args = Vec::with_capacity(arg_count as usize);
     for _ in 0..arg_count {
-        args.push(vm.stack.pop().ok_or("Stack underflow")?);
+        args.push(vm.stack.pop().ok_or_else(VmError::stack_underflow)?);
     }
     args.reverse();
 
     let resolved_class = vm.resolve_class_keyword(&class_name)?;
 
     // First check if it's an enum - don't try autoloading for enums
-    if let Some(enum_def) = vm.enums.get(&resolved_class).cloned() {
-        match method_name.as_str() {
-            "cases" => {
-                let cases: Vec<(ArrayKey, Value)> = enum_def
-                    .case_order
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(i, name)| {
-                        enum_def.cases.get(name).map(|value| {
-                            (
-                                ArrayKey::Integer(i as i64),
-                                Value::EnumCase {
-                                    enum_name: resolved_class.clone(),
-                                    case_name: name.clone(),
-                                    backing_value: value.clone().map(Box::new),
-                                },
-                            )
-                        })
-                    })
-                    .collect();
-                vm.stack.push(Value::Array(cases));
-            }
-            "from" => {
-                if args.is_empty() {
-                    return Err("from() requires exactly one argument".to_string());
-                }
-                let search_value = &args[0];
-                let mut found = None;
-                for (name, backing) in &enum_def.cases {
-                    if let Some(bv) = backing {
-                        if bv == search_value {
-                            found = Some((name.clone(), backing.clone()));
-                            break;
-                        }
-                    }
-                }
-                if let Some((name, backing)) = found {
-                    vm.stack.push(Value::EnumCase {
-                        enum_name: resolved_class.clone(),
-                        case_name: name,
-                        backing_value: backing.map(Box::new),
-                    });
-                } else {
-                    let value_str = match &search_value {
-                        Value::Integer(n) => n.to_string(),
-                        Value::String(s) => format!("'{}'", s),
-                        other => format!("{:?}", other),
-                    };
-                    return Err(format!(
-                        "Value '{}' is not a valid backing value for enum {}",
-                        value_str, resolved_class
-                    ));
-                }
-            }
-            "tryFrom" => {
-                if args.is_empty() {
-                    return Err("tryFrom() requires exactly one argument".to_string());
-                }
-                let search_value = &args[0];
-                let mut found = None;
-                for (name, backing) in &enum_def.cases {
-                    if let Some(bv) = backing {
-                        if bv == search_value {
-                            found = Some((name.clone(), backing.clone()));
-                            break;
-                        }
-                    }
-                }
-                if let Some((name, backing)) = found {
-                    vm.stack.push(Value::EnumCase {
-                        enum_name: resolved_class.clone(),
-                        case_name: name,
-                        backing_value: backing.map(Box::new),
-                    });
-                } else {
-                    vm.stack.push(Value::Null);
-                }
-            }
-            _ => {
-                return Err(format!(
-                    "Undefined enum method '{}' on enum '{}'",
-                    method_name, resolved_class
-                ));
-            }
-        }
-        return Ok(());
+    if vm.enums.contains_key(&resolved_class) {
+        vm.pass_args(&mut args, None)?;
+        return super::call_static_enum_method(vm, &resolved_class, &method_name, args);
     }
 
     // For classes, try autoloading if class doesn't exist
     let _class_def = vm
         .get_class_with_autoload(&resolved_class)
-        .ok_or_else(|| format!("Class '{}' not found", resolved_class))?;
+        .ok_or_else(|| VmError::class_not_found(&resolved_class))?;
 
-    if let Some((method, is_instance_method)) =
-        vm.find_static_method_in_chain(&resolved_class, &method_name)
-    {
+    let found = vm.find_static_method_in_chain(&resolved_class, &method_name);
+    let refs = vm.pass_args(&mut args, found.as_ref().map(|(method, _)| &**method))?;
+    if let Some((method, is_instance_method)) = found {
         let stack_base = vm.stack.len();
         let mut frame = CallFrame::new(method, stack_base);
         frame.called_class = Some(resolved_class.clone());
 
         let param_start = if is_instance_method { 1 } else { 0 };
-        for (i, arg) in args.into_iter().enumerate() {
-            let slot = param_start + i;
-            if slot < frame.locals.len() {
-                frame.locals[slot] = arg;
-            }
-        }
+        frame.bind_args(param_start, args);
+        frame.bind_refs(param_start, refs);
 
-        vm.frames.push(frame);
+        vm.push_frame(frame);
     } else if let Some((magic_call_static, _)) =
         vm.find_static_method_in_chain(&resolved_class, "__callStatic")
     {
@@ -137,14 +51,14 @@ pub fn execute_call_static_method<W: std::io::Write>(
             .enumerate()
             .map(|(i, v)| (ArrayKey::Integer(i as i64), v))
             .collect();
-        frame.locals[1] = Value::Array(args_array);
+        frame.locals[1] = Value::Array(args_array.into());
 
-        vm.frames.push(frame);
+        vm.push_frame(frame);
     } else {
-        return Err(format!(
-            "Static method '{}' not found on class '{}'",
-            method_name, resolved_class
-        ));
+        return Err(VmError::error(format!(
+            "Call to undefined method {}::{}()",
+            resolved_class, method_name
+        )));
     }
 
     Ok(())
@@ -154,18 +68,18 @@ pub fn execute_call_static_method_named<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     class_name: String,
     method_name: String,
-) -> Result<(), String> {
-    let args_array = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let args_array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let resolved_class = vm.resolve_class_keyword(&class_name)?;
 
     let _class_def = vm
         .get_class_with_autoload(&resolved_class)
-        .ok_or_else(|| format!("Class '{}' not found", resolved_class))?;
+        .ok_or_else(|| VmError::class_not_found(&resolved_class))?;
 
     let args_map = if let Value::Array(arr) = args_array {
         arr
     } else {
-        return Err("Named static method args must be an array".to_string());
+        return Err(VmError::fatal("Named static method args must be an array"));
     };
 
     let mut positional = Vec::new();
@@ -216,12 +130,12 @@ pub fn execute_call_static_method_named<W: std::io::Write>(
             }
         }
 
-        vm.frames.push(frame);
+        vm.push_frame(frame);
     } else {
-        return Err(format!(
-            "Static method '{}' not found on class '{}'",
-            method_name, resolved_class
-        ));
+        return Err(VmError::error(format!(
+            "Call to undefined method {}::{}()",
+            resolved_class, method_name
+        )));
     }
 
     Ok(())
@@ -231,23 +145,23 @@ pub fn execute_load_static_prop<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     class_name: String,
     prop_name: String,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let resolved_class = vm.resolve_class_keyword(&class_name)?;
 
     let class_def = vm
         .classes
         .get(&resolved_class)
-        .ok_or_else(|| format!("Class '{}' not found", resolved_class))?;
+        .ok_or_else(|| VmError::class_not_found(&resolved_class))?;
 
     let value = class_def
         .static_properties
         .get(&prop_name)
         .cloned()
         .ok_or_else(|| {
-            format!(
+            VmError::fatal(format!(
                 "Access to undeclared static property {}::${}",
                 resolved_class, prop_name
-            )
+            ))
         })?;
     vm.stack.push(value);
 
@@ -258,16 +172,16 @@ pub fn execute_store_static_prop<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     class_name: String,
     prop_name: String,
-) -> Result<(), String> {
-    let value = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let value = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
     let resolved_class = vm.resolve_class_keyword(&class_name)?;
 
     if let Some(class_def) = vm.classes.get(&resolved_class) {
         if class_def.readonly_static_properties.contains(&prop_name) {
-            return Err(format!(
+            return Err(VmError::error(format!(
                 "Cannot modify readonly property {}::${}",
                 resolved_class, prop_name
-            ));
+            )));
         }
 
         if let Some(prop_def) = class_def
@@ -296,10 +210,10 @@ pub fn execute_store_static_prop<W: std::io::Write>(
                         crate::ast::Visibility::Protected => "protected",
                         crate::ast::Visibility::Public => "public",
                     };
-                    return Err(format!(
+                    return Err(VmError::fatal(format!(
                         "Cannot modify {} property {}::${}",
                         vis_str, resolved_class, prop_name
-                    ));
+                    )));
                 }
             }
         }
@@ -308,7 +222,7 @@ pub fn execute_store_static_prop<W: std::io::Write>(
     let class_def = vm
         .classes
         .get_mut(&resolved_class)
-        .ok_or_else(|| format!("Class '{}' not found", resolved_class))?;
+        .ok_or_else(|| VmError::class_not_found(&resolved_class))?;
     std::sync::Arc::make_mut(class_def)
         .static_properties
         .insert(prop_name, value.clone());
@@ -321,22 +235,23 @@ pub fn execute_store_static_prop<W: std::io::Write>(
 pub fn execute_call_constructor<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
     arg_count: u8,
-) -> Result<(), String> {
+) -> Result<(), VmError> {
     let mut args = Vec::with_capacity(arg_count as usize);
     for _ in 0..arg_count {
-        args.push(vm.stack.pop().ok_or("Stack underflow")?);
+        args.push(vm.stack.pop().ok_or_else(VmError::stack_underflow)?);
     }
     args.reverse();
 
-    let object = vm.stack.pop().ok_or("Stack underflow")?;
+    let object = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let constructor = match &object {
+        Value::Object(instance) => vm.find_method_in_chain(instance.class_name(), "__construct"),
+        _ => None,
+    };
+    let refs = vm.pass_args(&mut args, constructor.as_deref())?;
 
     match object {
         Value::Object(instance) => {
-            let class_name = instance.class_name.clone();
-
-            if let Some(constructor) = vm.find_method_in_chain(&class_name, "__construct") {
-                let constructor = constructor.clone();
-
+            if let Some(constructor) = constructor {
                 let stack_base = vm.stack.len();
                 let mut frame = CallFrame::new(constructor, stack_base);
 
@@ -350,20 +265,20 @@ pub fn execute_call_constructor<W: std::io::Write>(
                                     if !vm.value_matches_type(&arg, type_hint) {
                                         let type_name = vm.format_type_hint(type_hint);
                                         let given_type = vm.get_value_type_name(&arg);
-                                        return Err(format!(
+                                        return Err(VmError::type_error(format!(
                                             "must be of type {}, {} given",
                                             type_name, given_type
-                                        ));
+                                        )));
                                     }
                                     vm.coerce_value_to_type(arg, type_hint)
                                 } else {
                                     if !vm.value_matches_type_strict(&arg, type_hint) {
                                         let type_name = vm.format_type_hint(type_hint);
                                         let given_type = vm.get_value_type_name(&arg);
-                                        return Err(format!(
+                                        return Err(VmError::type_error(format!(
                                             "must be of type {}, {} given",
                                             type_name, given_type
-                                        ));
+                                        )));
                                     }
                                     arg
                                 }
@@ -377,14 +292,15 @@ pub fn execute_call_constructor<W: std::io::Write>(
                     }
                 }
 
+                frame.bind_refs(1, refs);
                 frame.is_constructor = true;
 
-                vm.frames.push(frame);
+                vm.push_frame(frame);
             } else {
                 vm.stack.push(Value::Object(instance));
             }
         }
-        _ => return Err("Cannot call constructor on non-object".to_string()),
+        _ => return Err(VmError::fatal("Cannot call constructor on non-object")),
     }
 
     Ok(())
@@ -392,13 +308,13 @@ pub fn execute_call_constructor<W: std::io::Write>(
 
 pub fn execute_call_constructor_named<W: std::io::Write>(
     vm: &mut super::super::VM<W>,
-) -> Result<(), String> {
-    let args_array = vm.stack.pop().ok_or("Stack underflow")?;
-    let object = vm.stack.pop().ok_or("Stack underflow")?;
+) -> Result<(), VmError> {
+    let args_array = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
+    let object = vm.stack.pop().ok_or_else(VmError::stack_underflow)?;
 
     match object {
         Value::Object(instance) => {
-            let class_name = instance.class_name.clone();
+            let class_name = instance.class_name().to_string();
 
             if let Some(constructor) = vm.find_method_in_chain(&class_name, "__construct") {
                 let constructor = constructor.clone();
@@ -406,7 +322,7 @@ pub fn execute_call_constructor_named<W: std::io::Write>(
                 let args_map = if let Value::Array(arr) = args_array {
                     arr
                 } else {
-                    return Err("Named constructor args must be an array".to_string());
+                    return Err(VmError::fatal("Named constructor args must be an array"));
                 };
 
                 let mut positional = Vec::new();
@@ -455,12 +371,12 @@ pub fn execute_call_constructor_named<W: std::io::Write>(
 
                 frame.is_constructor = true;
 
-                vm.frames.push(frame);
+                vm.push_frame(frame);
             } else {
                 vm.stack.push(Value::Object(instance));
             }
         }
-        _ => return Err("Cannot call constructor on non-object".to_string()),
+        _ => return Err(VmError::fatal("Cannot call constructor on non-object")),
     }
 
     Ok(())
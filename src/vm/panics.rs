@@ -0,0 +1,69 @@
+//! Panic containment
+//!
+//! An engine bug or malformed bytecode can still panic deep in the VM
+//! (a pool index out of range, a missing call frame). `VM::execute` runs
+//! code behind a boundary that catches the panic and turns it into a
+//! fatal error starting with `ENGINE_ERROR`, located at the statement
+//! that was running, so the host gets an error back instead of losing
+//! its thread:
+//!
+//! ```
+//! use std::sync::Arc;
+//! use vhp::vm::opcode::{CompiledFunction, Opcode};
+//! use vhp::vm::{RuntimeError, VM};
+//!
+//! // Reads a variable named by a string the pool doesn't have
+//! let mut function = CompiledFunction::new("<main>".to_string());
+//! function.bytecode.push(Opcode::LoadVar(7));
+//! let mut vm = VM::new(std::io::sink());
+//! let Err(RuntimeError::Fatal { message, .. }) = vm.execute(Arc::new(function)) else {
+//!     panic!("expected a fatal error");
+//! };
+//! assert!(message.starts_with(vhp::vm::panics::ENGINE_ERROR));
+//! ```
+//!
+//! What the panic interrupted is left as it was; shutdown functions
+//! still run, on a reset stack (see `shutdown`).
+
+use super::execution;
+use super::opcode::CompiledFunction;
+use super::VM;
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use std::any::Any;
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+/// Prefix of the fatal error a panic in the engine becomes
+pub const ENGINE_ERROR: &str = "Internal engine error:";
+
+impl<W: Write> VM<W> {
+    /// Run `function` to the end, failing with an `ENGINE_ERROR` if the
+    /// engine panics
+    pub(crate) fn execute_contained(
+        &mut self,
+        function: Arc<CompiledFunction>,
+    ) -> Result<Value, VmError> {
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| execution::execute_vm(self, function)));
+        result.unwrap_or_else(|payload| {
+            let error = VmError::fatal(format!(
+                "{} {}",
+                ENGINE_ERROR,
+                panic_message(payload.as_ref())
+            ));
+            self.record_error_location(&error);
+            Err(error)
+        })
+    }
+}
+
+/// The message a panic was raised with
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
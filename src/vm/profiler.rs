@@ -0,0 +1,314 @@
+//! Function-level profiling
+//!
+//! While a `Profiler` is enabled, the VM reports every call frame push and
+//! pop. Each call is timed and charged with the bytes allocated while it
+//! ran, and the totals are written in callgrind format so that tools such
+//! as KCachegrind/QCachegrind can display them.
+//!
+//! Memory is measured with `CountingAllocator`, which the embedding binary
+//! has to install as the global allocator; without it every memory cost is
+//! zero.
+
+use super::frame::CallFrame;
+use super::opcode::CompiledFunction;
+use super::ops;
+use super::VM;
+use crate::runtime::clock;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+/// System allocator that counts the bytes it hands out
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let grown = new_size.saturating_sub(layout.size());
+        ALLOCATED.fetch_add(grown as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Bytes allocated through `CountingAllocator` since the process started
+pub fn allocated_bytes() -> u64 {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// A function, identified by source file and name
+type FunctionKey = (String, String);
+
+/// Time and allocated bytes
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Cost {
+    pub time: Duration,
+    pub memory: u64,
+}
+
+impl Cost {
+    fn add(&mut self, other: Cost) {
+        self.time += other.time;
+        self.memory += other.memory;
+    }
+}
+
+/// Calls from one function to another at one call site
+#[derive(Debug, Default, Clone)]
+pub struct CallStats {
+    pub count: u64,
+    /// Inclusive cost of the calls
+    pub cost: Cost,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FunctionStats {
+    /// Line of the function's first statement
+    pub line: usize,
+    pub calls: u64,
+    /// Exclusive cost: time and memory not spent in callees
+    pub own: Cost,
+    pub inclusive: Cost,
+    /// Outgoing calls by callee and call-site line
+    pub callees: BTreeMap<(FunctionKey, usize), CallStats>,
+}
+
+/// A call that has not returned yet
+struct OpenCall {
+    key: FunctionKey,
+    /// Line in the caller the call was made from
+    call_line: usize,
+    start: Duration,
+    memory_start: u64,
+    children: Cost,
+}
+
+/// Per-function call counts and costs
+#[derive(Default)]
+pub struct Profiler {
+    /// Open calls, parallel to the VM's frame stack
+    open: Vec<OpenCall>,
+    functions: HashMap<FunctionKey, FunctionStats>,
+}
+
+impl Profiler {
+    /// A frame for `function` is being pushed on top of `depth` frames
+    pub fn enter(&mut self, function: &CompiledFunction, call_line: usize, depth: usize) {
+        // Frames dropped while an error propagated were never popped
+        self.leave(depth);
+        let key = (function.file.clone(), function.name.clone());
+        self.functions
+            .entry(key.clone())
+            .or_insert_with(|| FunctionStats {
+                line: function.lines.first().map_or(0, |(_, line)| *line),
+                ..FunctionStats::default()
+            });
+        self.open.push(OpenCall {
+            key,
+            call_line,
+            start: clock::monotonic(),
+            memory_start: allocated_bytes(),
+            children: Cost::default(),
+        });
+    }
+
+    /// Close every open call above `depth` frames
+    pub fn leave(&mut self, depth: usize) {
+        while self.open.len() > depth {
+            let Some(call) = self.open.pop() else {
+                break;
+            };
+            let cost = Cost {
+                time: clock::monotonic().saturating_sub(call.start),
+                memory: allocated_bytes().saturating_sub(call.memory_start),
+            };
+            if let Some(stats) = self.functions.get_mut(&call.key) {
+                stats.calls += 1;
+                stats.inclusive.add(cost);
+                stats.own.time += cost.time.saturating_sub(call.children.time);
+                stats.own.memory += cost.memory.saturating_sub(call.children.memory);
+            }
+            if let Some(parent) = self.open.last_mut() {
+                parent.children.add(cost);
+                if let Some(stats) = self.functions.get_mut(&parent.key) {
+                    let calls = stats.callees.entry((call.key, call.call_line)).or_default();
+                    calls.count += 1;
+                    calls.cost.add(cost);
+                }
+            }
+        }
+    }
+
+    /// Close all open calls and return the collected statistics
+    pub fn finish(mut self) -> Profile {
+        self.leave(0);
+        let mut functions: Vec<(FunctionKey, FunctionStats)> = self.functions.into_iter().collect();
+        functions.sort_by(|a, b| a.0.cmp(&b.0));
+        Profile { functions }
+    }
+}
+
+/// Statistics of a finished profiling run
+#[derive(Debug, Default, Clone)]
+pub struct Profile {
+    /// (file, function name) and its statistics, sorted by file and name
+    pub functions: Vec<(FunctionKey, FunctionStats)>,
+}
+
+impl Profile {
+    /// Write the profile in callgrind format
+    ///
+    /// Time is in microseconds and memory in bytes allocated. Files and
+    /// functions use callgrind's name compression (`fl=(1) name` once,
+    /// then `fl=(1)`).
+    pub fn write_callgrind<O: Write>(&self, out: &mut O, command: &str) -> io::Result<()> {
+        let total = self
+            .functions
+            .iter()
+            .fold(Cost::default(), |mut total, (_, stats)| {
+                total.add(stats.own);
+                total
+            });
+        writeln!(out, "# callgrind format")?;
+        writeln!(out, "version: 1")?;
+        writeln!(out, "creator: vhp {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(out, "cmd: {}", command)?;
+        writeln!(out, "positions: line")?;
+        writeln!(out, "events: Time_(us) Memory_(bytes)")?;
+        writeln!(out, "summary: {} {}", micros(total.time), total.memory)?;
+
+        let mut names = Names::default();
+        for ((file, name), stats) in &self.functions {
+            writeln!(out)?;
+            writeln!(out, "fl={}", names.file(file))?;
+            writeln!(out, "fn={}", names.function(name))?;
+            writeln!(
+                out,
+                "{} {} {}",
+                stats.line,
+                micros(stats.own.time),
+                stats.own.memory
+            )?;
+            for (((callee_file, callee), call_line), calls) in &stats.callees {
+                let callee_line = self
+                    .functions
+                    .iter()
+                    .find(|(key, _)| key.0 == *callee_file && key.1 == *callee)
+                    .map_or(0, |(_, stats)| stats.line);
+                writeln!(out, "cfl={}", names.file(callee_file))?;
+                writeln!(out, "cfn={}", names.function(callee))?;
+                writeln!(out, "calls={} {}", calls.count, callee_line)?;
+                writeln!(
+                    out,
+                    "{} {} {}",
+                    call_line,
+                    micros(calls.cost.time),
+                    calls.cost.memory
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn micros(duration: Duration) -> u128 {
+    duration.as_micros()
+}
+
+/// Callgrind name compression tables
+#[derive(Default)]
+struct Names {
+    files: HashMap<String, usize>,
+    functions: HashMap<String, usize>,
+}
+
+impl Names {
+    fn file(&mut self, name: &str) -> String {
+        Self::compress(&mut self.files, name)
+    }
+
+    fn function(&mut self, name: &str) -> String {
+        Self::compress(&mut self.functions, name)
+    }
+
+    fn compress(table: &mut HashMap<String, usize>, name: &str) -> String {
+        if let Some(id) = table.get(name) {
+            return format!("({})", id);
+        }
+        let id = table.len() + 1;
+        table.insert(name.to_string(), id);
+        format!("({}) {}", id, name)
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Start recording per-function call statistics
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::default());
+    }
+
+    /// Stop profiling and return what was recorded
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profiler.take().map(Profiler::finish)
+    }
+
+    /// Push a call frame, reporting the call to the profiler and stats.
+    /// A generator function's frame doesn't run yet: the `Generator` it
+    /// becomes is pushed on the stack instead.
+    pub(crate) fn push_frame(&mut self, frame: CallFrame) {
+        if frame.function.is_generator {
+            let generator = ops::create_generator(self, frame);
+            self.stack.push(generator);
+            return;
+        }
+        self.enter_frame(frame);
+    }
+
+    /// Push a call frame to run, reporting the call to the profiler and
+    /// stats
+    pub(crate) fn enter_frame(&mut self, frame: CallFrame) {
+        self.record_call();
+        if let Some(profiler) = &mut self.profiler {
+            let call_line = self
+                .frames
+                .last()
+                .and_then(|caller| caller.function.line_at(caller.ip.saturating_sub(1)))
+                .unwrap_or(0);
+            profiler.enter(&frame.function, call_line, self.frames.len());
+        }
+        self.frames.push(frame);
+    }
+
+    /// Pop the current call frame, dropping the loops it left behind, and
+    /// reporting the return to the profiler
+    pub(crate) fn pop_frame(&mut self) -> Option<CallFrame> {
+        let frame = self.frames.pop();
+        let depth = self.frames.len();
+        if let (0, Some(main)) = (depth, &frame) {
+            self.leave_main(main);
+        }
+        while self.loops.last().is_some_and(|l| l.frame_depth > depth) {
+            self.loops.pop();
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.leave(self.frames.len());
+        }
+        frame
+    }
+}
@@ -1,16 +1,95 @@
-//! Reflection functions for the VM
+//! Reflection for the VM
 //!
-//! This module provides reflection functions that allow inspecting
-//! attributes on classes, interfaces, traits, functions, methods, etc.
+//! The attribute functions inspect the attributes on classes, interfaces,
+//! traits, functions, methods, etc. The compiler has replaced the
+//! arguments that are constant expressions with their values already;
+//! `lookup` resolves the enum cases among them.
+//!
+//! The Reflection classes are built in; the VM runs their methods
+//! natively (`execute_reflect`). The introspection functions
+//! (get_object_vars(), method_exists(), ...) share their class lookups.
+
+mod attribute;
+mod class;
+mod function;
+mod introspection;
+mod members;
+mod property;
+mod registration;
+
+pub use registration::register_reflection_classes;
 
 use crate::ast::Attribute;
-use crate::runtime::{ArrayKey, Value};
+use crate::runtime::{ArrayKey, ObjectRef, Value};
 use crate::vm::class::{CompiledClass, CompiledInterface, CompiledTrait};
+use crate::vm::compiler::const_expr;
+use crate::vm::error::VmError;
+use crate::vm::name_table::NameTable;
 use crate::vm::opcode::CompiledFunction;
+use crate::vm::{ops, VM};
+use std::io::Write;
 use std::sync::Arc;
 
+/// The value a Reflection method returns, or `None` if it has pushed a
+/// frame that will
+type ReflectionResult = Result<Option<Value>, VmError>;
+
+/// A property of a Reflection object
+fn property(this: &ObjectRef, name: &str) -> Value {
+    this.get(name).unwrap_or(Value::Null)
+}
+
+impl<W: Write> VM<W> {
+    /// Run a native method of a Reflection class ("Class::method") on the
+    /// current frame's `$this` and arguments
+    pub(crate) fn execute_reflect(&mut self, method: &str) -> Result<(), VmError> {
+        let frame = self.current_frame();
+        let this = match frame.locals.first() {
+            Some(Value::Object(this)) => this.clone(),
+            _ => {
+                return Err(VmError::fatal(format!(
+                    "Non-static method {}() cannot be called statically",
+                    method
+                )))
+            }
+        };
+        let args = frame.locals[1..].to_vec();
+        let (class, method) = method.split_once("::").unwrap_or(("", method));
+        let result = match class {
+            "ReflectionClass" => self.reflect_class(method, this, args)?,
+            "ReflectionProperty" => self.reflect_property(method, this, args)?,
+            "ReflectionParameter" => self.reflect_parameter(method, this, args)?,
+            "ReflectionAttribute" => self.reflect_attribute(method, this)?,
+            _ => self.reflect_function(method, this, args)?,
+        };
+        if let Some(value) = result {
+            self.stack.push(value);
+        }
+        Ok(())
+    }
+
+    /// A new object of a Reflection class with the given properties
+    fn reflection_object(
+        &mut self,
+        class: &str,
+        properties: Vec<(&str, Value)>,
+    ) -> Result<Value, VmError> {
+        ops::execute_new_object(self, class.to_string())?;
+        let object = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+        if let Value::Object(instance) = &object {
+            for (name, value) in properties {
+                instance.set(name, value);
+            }
+        }
+        Ok(object)
+    }
+}
+
+/// Resolves a `Class::NAME` in an attribute argument
+pub type ConstantLookup<'a> = dyn Fn(&str, &str) -> Option<Value> + 'a;
+
 /// Convert an attribute to a Value (associative array)
-fn attribute_to_value(attr: &Attribute) -> Value {
+fn attribute_to_value(attr: &Attribute, lookup: &ConstantLookup) -> Value {
     let mut entries = Vec::new();
 
     // Add attribute name
@@ -35,121 +114,119 @@ fn attribute_to_value(attr: &Attribute) -> Value {
                 ));
             }
 
-            // Convert the argument value expression to a Value
-            // For now, we support simple literal expressions
-            let value = expr_to_value(&arg.value);
+            // Arguments that aren't constant expressions have no value
+            let value = const_expr::evaluate(&arg.value, lookup).unwrap_or(Value::Null);
             arg_entries.push((ArrayKey::String("value".to_string()), value));
 
-            (ArrayKey::Integer(i as i64), Value::Array(arg_entries))
+            (
+                ArrayKey::Integer(i as i64),
+                Value::Array(arg_entries.into()),
+            )
         })
         .collect();
 
     entries.push((
         ArrayKey::String("arguments".to_string()),
-        Value::Array(args),
+        Value::Array(args.into()),
     ));
 
-    Value::Array(entries)
-}
-
-/// Convert a simple expression to a Value
-/// This only handles literal expressions that can be in attributes
-fn expr_to_value(expr: &crate::ast::Expr) -> Value {
-    use crate::ast::Expr;
-    match expr {
-        Expr::Integer(n) => Value::Integer(*n),
-        Expr::Float(f) => Value::Float(*f),
-        Expr::String(s) => Value::String(s.clone()),
-        Expr::Bool(b) => Value::Bool(*b),
-        Expr::Null => Value::Null,
-        Expr::Array(elements) => {
-            let values: Vec<(ArrayKey, Value)> = elements
-                .iter()
-                .enumerate()
-                .map(|(i, elem)| (ArrayKey::Integer(i as i64), expr_to_value(&elem.value)))
-                .collect();
-            Value::Array(values)
-        }
-        _ => Value::Null, // Unsupported expressions default to null
-    }
+    Value::Array(entries.into())
 }
 
 /// Get attributes for a class
 pub fn get_class_attributes(
     class_name: &str,
-    classes: &std::collections::HashMap<String, Arc<CompiledClass>>,
-) -> Result<Value, String> {
+    classes: &NameTable<Arc<CompiledClass>>,
+    lookup: &ConstantLookup,
+) -> Result<Value, VmError> {
     let class = classes
         .get(class_name)
-        .ok_or_else(|| format!("Class '{}' not found", class_name))?;
+        .ok_or_else(|| VmError::fatal(format!("Class '{}' not found", class_name)))?;
 
     let attrs: Vec<(ArrayKey, Value)> = class
         .attributes
         .iter()
         .enumerate()
-        .map(|(i, attr)| (ArrayKey::Integer(i as i64), attribute_to_value(attr)))
+        .map(|(i, attr)| {
+            (
+                ArrayKey::Integer(i as i64),
+                attribute_to_value(attr, lookup),
+            )
+        })
         .collect();
-    Ok(Value::Array(attrs))
+    Ok(Value::Array(attrs.into()))
 }
 
 /// Get attributes for a property
 pub fn get_property_attributes(
     class_name: &str,
     property_name: &str,
-    classes: &std::collections::HashMap<String, Arc<CompiledClass>>,
-) -> Result<Value, String> {
+    classes: &NameTable<Arc<CompiledClass>>,
+    lookup: &ConstantLookup,
+) -> Result<Value, VmError> {
     let class = classes
         .get(class_name)
-        .ok_or_else(|| format!("Class '{}' not found", class_name))?;
+        .ok_or_else(|| VmError::fatal(format!("Class '{}' not found", class_name)))?;
 
     let prop = class
         .properties
         .iter()
         .find(|p| p.name == property_name)
         .ok_or_else(|| {
-            format!(
+            VmError::fatal(format!(
                 "Property '{}' not found in class '{}'",
                 property_name, class_name
-            )
+            ))
         })?;
 
     let attrs: Vec<(ArrayKey, Value)> = prop
         .attributes
         .iter()
         .enumerate()
-        .map(|(i, attr)| (ArrayKey::Integer(i as i64), attribute_to_value(attr)))
+        .map(|(i, attr)| {
+            (
+                ArrayKey::Integer(i as i64),
+                attribute_to_value(attr, lookup),
+            )
+        })
         .collect();
-    Ok(Value::Array(attrs))
+    Ok(Value::Array(attrs.into()))
 }
 
 /// Get attributes for a method
 pub fn get_method_attributes(
     class_name: &str,
     method_name: &str,
-    classes: &std::collections::HashMap<String, Arc<CompiledClass>>,
-) -> Result<Value, String> {
+    classes: &NameTable<Arc<CompiledClass>>,
+    lookup: &ConstantLookup,
+) -> Result<Value, VmError> {
     let class = classes
         .get(class_name)
-        .ok_or_else(|| format!("Class '{}' not found", class_name))?;
+        .ok_or_else(|| VmError::fatal(format!("Class '{}' not found", class_name)))?;
 
     let method = class
         .methods
         .get(method_name)
         .or_else(|| class.static_methods.get(method_name))
         .ok_or_else(|| {
-            format!(
+            VmError::fatal(format!(
                 "Method '{}' not found in class '{}'",
                 method_name, class_name
-            )
+            ))
         })?;
 
     let attrs: Vec<(ArrayKey, Value)> = method
         .attributes
         .iter()
         .enumerate()
-        .map(|(i, attr)| (ArrayKey::Integer(i as i64), attribute_to_value(attr)))
+        .map(|(i, attr)| {
+            (
+                ArrayKey::Integer(i as i64),
+                attribute_to_value(attr, lookup),
+            )
+        })
         .collect();
-    Ok(Value::Array(attrs))
+    Ok(Value::Array(attrs.into()))
 }
 
 /// Get attributes for a method parameter
@@ -157,21 +234,22 @@ pub fn get_method_parameter_attributes(
     class_name: &str,
     method_name: &str,
     parameter_name: &str,
-    classes: &std::collections::HashMap<String, Arc<CompiledClass>>,
-) -> Result<Value, String> {
+    classes: &NameTable<Arc<CompiledClass>>,
+    lookup: &ConstantLookup,
+) -> Result<Value, VmError> {
     let class = classes
         .get(class_name)
-        .ok_or_else(|| format!("Class '{}' not found", class_name))?;
+        .ok_or_else(|| VmError::fatal(format!("Class '{}' not found", class_name)))?;
 
     let method = class
         .methods
         .get(method_name)
         .or_else(|| class.static_methods.get(method_name))
         .ok_or_else(|| {
-            format!(
+            VmError::fatal(format!(
                 "Method '{}' not found in class '{}'",
                 method_name, class_name
-            )
+            ))
         })?;
 
     let param = method
@@ -179,101 +257,130 @@ pub fn get_method_parameter_attributes(
         .iter()
         .find(|p| p.name == parameter_name)
         .ok_or_else(|| {
-            format!(
+            VmError::fatal(format!(
                 "Parameter '{}' not found in method '{}'",
                 parameter_name, method_name
-            )
+            ))
         })?;
 
     let attrs: Vec<(ArrayKey, Value)> = param
         .attributes
         .iter()
         .enumerate()
-        .map(|(i, attr)| (ArrayKey::Integer(i as i64), attribute_to_value(attr)))
+        .map(|(i, attr)| {
+            (
+                ArrayKey::Integer(i as i64),
+                attribute_to_value(attr, lookup),
+            )
+        })
         .collect();
-    Ok(Value::Array(attrs))
+    Ok(Value::Array(attrs.into()))
 }
 
 /// Get attributes for a function
 pub fn get_function_attributes(
     function_name: &str,
-    functions: &std::collections::HashMap<String, Arc<CompiledFunction>>,
-) -> Result<Value, String> {
+    functions: &NameTable<Arc<CompiledFunction>>,
+    lookup: &ConstantLookup,
+) -> Result<Value, VmError> {
     let func = functions
         .get(function_name)
-        .ok_or_else(|| format!("Function '{}' not found", function_name))?;
+        .ok_or_else(|| VmError::fatal(format!("Function '{}' not found", function_name)))?;
 
     let attrs: Vec<(ArrayKey, Value)> = func
         .attributes
         .iter()
         .enumerate()
-        .map(|(i, attr)| (ArrayKey::Integer(i as i64), attribute_to_value(attr)))
+        .map(|(i, attr)| {
+            (
+                ArrayKey::Integer(i as i64),
+                attribute_to_value(attr, lookup),
+            )
+        })
         .collect();
-    Ok(Value::Array(attrs))
+    Ok(Value::Array(attrs.into()))
 }
 
 /// Get attributes for a function parameter
 pub fn get_parameter_attributes(
     function_name: &str,
     parameter_name: &str,
-    functions: &std::collections::HashMap<String, Arc<CompiledFunction>>,
-) -> Result<Value, String> {
+    functions: &NameTable<Arc<CompiledFunction>>,
+    lookup: &ConstantLookup,
+) -> Result<Value, VmError> {
     let func = functions
         .get(function_name)
-        .ok_or_else(|| format!("Function '{}' not found", function_name))?;
+        .ok_or_else(|| VmError::fatal(format!("Function '{}' not found", function_name)))?;
 
     let param = func
         .parameters
         .iter()
         .find(|p| p.name == parameter_name)
         .ok_or_else(|| {
-            format!(
+            VmError::fatal(format!(
                 "Parameter '{}' not found in function '{}'",
                 parameter_name, function_name
-            )
+            ))
         })?;
 
     let attrs: Vec<(ArrayKey, Value)> = param
         .attributes
         .iter()
         .enumerate()
-        .map(|(i, attr)| (ArrayKey::Integer(i as i64), attribute_to_value(attr)))
+        .map(|(i, attr)| {
+            (
+                ArrayKey::Integer(i as i64),
+                attribute_to_value(attr, lookup),
+            )
+        })
         .collect();
-    Ok(Value::Array(attrs))
+    Ok(Value::Array(attrs.into()))
 }
 
 /// Get attributes for an interface
 pub fn get_interface_attributes(
     interface_name: &str,
-    interfaces: &std::collections::HashMap<String, Arc<CompiledInterface>>,
-) -> Result<Value, String> {
+    interfaces: &NameTable<Arc<CompiledInterface>>,
+    lookup: &ConstantLookup,
+) -> Result<Value, VmError> {
     let interface = interfaces
         .get(interface_name)
-        .ok_or_else(|| format!("Interface '{}' not found", interface_name))?;
+        .ok_or_else(|| VmError::fatal(format!("Interface '{}' not found", interface_name)))?;
 
     let attrs: Vec<(ArrayKey, Value)> = interface
         .attributes
         .iter()
         .enumerate()
-        .map(|(i, attr)| (ArrayKey::Integer(i as i64), attribute_to_value(attr)))
+        .map(|(i, attr)| {
+            (
+                ArrayKey::Integer(i as i64),
+                attribute_to_value(attr, lookup),
+            )
+        })
         .collect();
-    Ok(Value::Array(attrs))
+    Ok(Value::Array(attrs.into()))
 }
 
 /// Get attributes for a trait
 pub fn get_trait_attributes(
     trait_name: &str,
-    traits: &std::collections::HashMap<String, Arc<CompiledTrait>>,
-) -> Result<Value, String> {
+    traits: &NameTable<Arc<CompiledTrait>>,
+    lookup: &ConstantLookup,
+) -> Result<Value, VmError> {
     let trait_def = traits
         .get(trait_name)
-        .ok_or_else(|| format!("Trait '{}' not found", trait_name))?;
+        .ok_or_else(|| VmError::fatal(format!("Trait '{}' not found", trait_name)))?;
 
     let attrs: Vec<(ArrayKey, Value)> = trait_def
         .attributes
         .iter()
         .enumerate()
-        .map(|(i, attr)| (ArrayKey::Integer(i as i64), attribute_to_value(attr)))
+        .map(|(i, attr)| {
+            (
+                ArrayKey::Integer(i as i64),
+                attribute_to_value(attr, lookup),
+            )
+        })
         .collect();
-    Ok(Value::Array(attrs))
+    Ok(Value::Array(attrs.into()))
 }
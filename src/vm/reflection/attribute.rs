@@ -0,0 +1,82 @@
+//! ReflectionAttribute
+
+use super::class::list;
+use super::{property, ReflectionResult};
+use crate::ast::Attribute;
+use crate::runtime::{ArrayKey, ObjectRef, Value};
+use crate::vm::compiler::const_expr;
+use crate::vm::error::VmError;
+use crate::vm::{ops, VM};
+use std::io::Write;
+
+impl<W: Write> VM<W> {
+    /// ReflectionAttribute objects for the attributes named `filter`, or
+    /// for all of them if it is null
+    pub(super) fn attribute_objects(
+        &mut self,
+        attributes: &[Attribute],
+        filter: &Value,
+    ) -> Result<Value, VmError> {
+        let filter = match filter {
+            Value::Null => None,
+            name => Some(name.to_string_val().trim_start_matches('\\').to_string()),
+        };
+        let mut objects = Vec::new();
+        for attribute in attributes {
+            let name = attribute.name.trim_start_matches('\\');
+            if filter
+                .as_ref()
+                .is_some_and(|filter| !filter.eq_ignore_ascii_case(name))
+            {
+                continue;
+            }
+            let mut arguments = Vec::new();
+            let mut next_index = 0;
+            for argument in &attribute.arguments {
+                let value = const_expr::evaluate(&argument.value, &|class, name| {
+                    self.class_constant(class, name)
+                })
+                .unwrap_or(Value::Null);
+                let key = match &argument.name {
+                    Some(name) => ArrayKey::String(name.clone()),
+                    None => {
+                        next_index += 1;
+                        ArrayKey::Integer(next_index - 1)
+                    }
+                };
+                arguments.push((key, value));
+            }
+            objects.push(self.reflection_object(
+                "ReflectionAttribute",
+                vec![
+                    ("__name", Value::String(name.to_string())),
+                    ("__arguments", Value::Array(arguments.into())),
+                ],
+            )?);
+        }
+        Ok(list(objects))
+    }
+
+    /// A method of ReflectionAttribute
+    pub(super) fn reflect_attribute(&mut self, method: &str, this: ObjectRef) -> ReflectionResult {
+        match method {
+            "newInstance" => {
+                let name = property(&this, "__name").to_string_val();
+                if self.get_class_with_autoload(&name).is_none() {
+                    return Err(VmError::fatal(format!(
+                        "Attribute class \"{}\" not found",
+                        name
+                    )));
+                }
+                ops::execute_new_object(self, name)?;
+                self.stack.push(property(&this, "__arguments"));
+                ops::execute_call_constructor_named(self)?;
+                Ok(None)
+            }
+            _ => Err(VmError::error(format!(
+                "Call to undefined method ReflectionAttribute::{}()",
+                method
+            ))),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! ReflectionClass
+//!
+//! A ReflectionClass keeps the name of the class, interface, trait or
+//! enum it reflects, as declared, and looks the declaration up again on
+//! each call.
+
+use super::members::{MethodInfo, PropertyInfo};
+use super::{property, ReflectionResult};
+use crate::ast::Visibility;
+use crate::runtime::{ArrayKey, ObjectRef, PhpArray, Value};
+use crate::vm::class::CompiledTrait;
+use crate::vm::class::{CompiledClass, CompiledEnum, CompiledInterface};
+use crate::vm::error::VmError;
+use crate::vm::{ops, VM};
+use std::io::Write;
+use std::sync::Arc;
+
+/// What a reflected class name declares
+pub(super) enum Declaration {
+    Class(Arc<CompiledClass>),
+    Interface(Arc<CompiledInterface>),
+    Trait(Arc<CompiledTrait>),
+    Enum(Arc<CompiledEnum>),
+}
+
+/// The part of a qualified name after the last backslash
+pub(super) fn short_name(name: &str) -> &str {
+    name.rsplit_once('\\').map_or(name, |(_, short)| short)
+}
+
+/// The namespace of a qualified name
+pub(super) fn namespace_name(name: &str) -> &str {
+    name.rsplit_once('\\')
+        .map_or("", |(namespace, _)| namespace)
+}
+
+impl<W: Write> VM<W> {
+    /// The declaration named `name` and its name as declared, after
+    /// giving the autoloaders a chance to load it
+    pub(super) fn declaration(&mut self, name: &str) -> Option<(String, Declaration)> {
+        let name = Self::normalize_class_name(name);
+        if let Some(found) = self.find_declaration(&name) {
+            return Some(found);
+        }
+        self.try_autoload_class(&name);
+        self.find_declaration(&name)
+    }
+
+    pub(super) fn find_declaration(&self, name: &str) -> Option<(String, Declaration)> {
+        if let Some((key, class)) = self.classes.get_key_value(name) {
+            return Some((key.clone(), Declaration::Class(class.clone())));
+        }
+        if let Some((key, interface)) = self.interfaces.get_key_value(name) {
+            return Some((key.clone(), Declaration::Interface(interface.clone())));
+        }
+        if let Some((key, trait_def)) = self.traits.get_key_value(name) {
+            return Some((key.clone(), Declaration::Trait(trait_def.clone())));
+        }
+        let (key, enum_def) = self.enums.get_key_value(name)?;
+        Some((key.clone(), Declaration::Enum(enum_def.clone())))
+    }
+
+    /// The declaration a class argument names: an object's class, or a
+    /// class name
+    pub(super) fn declaration_of(
+        &mut self,
+        class: &Value,
+    ) -> Result<(String, Declaration), VmError> {
+        let name = match class {
+            Value::Object(instance) => instance.class_name().to_string(),
+            Value::EnumCase { enum_name, .. } => enum_name.clone(),
+            other => other.to_string_val(),
+        };
+        self.declaration(&name).ok_or_else(|| {
+            VmError::exception(
+                "ReflectionException",
+                format!("Class \"{}\" does not exist", name),
+            )
+        })
+    }
+
+    /// A ReflectionClass for the class, interface, trait or enum `name`
+    pub(super) fn class_object(&mut self, name: &str) -> Result<Value, VmError> {
+        self.reflection_object(
+            "ReflectionClass",
+            vec![("name", Value::String(name.to_string()))],
+        )
+    }
+
+    pub(super) fn method_object(&mut self, method: &MethodInfo) -> Result<Value, VmError> {
+        self.reflection_object(
+            "ReflectionMethod",
+            vec![
+                ("name", Value::String(method.name.clone())),
+                ("class", Value::String(method.class.clone())),
+            ],
+        )
+    }
+
+    fn property_object(&mut self, info: &PropertyInfo) -> Result<Value, VmError> {
+        self.reflection_object(
+            "ReflectionProperty",
+            vec![
+                ("name", Value::String(info.property.name.clone())),
+                ("class", Value::String(info.class.clone())),
+            ],
+        )
+    }
+
+    /// A method of ReflectionClass
+    pub(super) fn reflect_class(
+        &mut self,
+        method: &str,
+        this: ObjectRef,
+        args: Vec<Value>,
+    ) -> ReflectionResult {
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+        if method == "__construct" {
+            let (name, _) = self.declaration_of(&arg(0))?;
+            this.set("name", Value::String(name));
+            return Ok(Some(Value::Null));
+        }
+        let (name, declaration) = self.declaration_of(&property(&this, "name"))?;
+        let class = match &declaration {
+            Declaration::Class(class) => Some(class.clone()),
+            _ => None,
+        };
+        let value = match method {
+            "getName" => Value::String(name),
+            "getShortName" => Value::String(short_name(&name).to_string()),
+            "getNamespaceName" => Value::String(namespace_name(&name).to_string()),
+            "inNamespace" => Value::Bool(name.contains('\\')),
+            "isInterface" => Value::Bool(matches!(declaration, Declaration::Interface(_))),
+            "isTrait" => Value::Bool(matches!(declaration, Declaration::Trait(_))),
+            "isEnum" => Value::Bool(matches!(declaration, Declaration::Enum(_))),
+            "isAbstract" => Value::Bool(class.is_some_and(|class| class.is_abstract)),
+            "isFinal" => Value::Bool(match &declaration {
+                Declaration::Class(class) => class.is_final,
+                Declaration::Enum(_) => true,
+                _ => false,
+            }),
+            "isReadOnly" => Value::Bool(class.is_some_and(|class| class.readonly)),
+            "isAnonymous" => Value::Bool(name.starts_with("__anon_class_")),
+            "isInstantiable" => Value::Bool(
+                class.is_some_and(|class| !class.is_abstract)
+                    && self
+                        .reflected_method(&name, "__construct")
+                        .is_none_or(|constructor| constructor.visibility == Visibility::Public),
+            ),
+            "isInstance" => Value::Bool(match arg(0) {
+                Value::Object(instance) => self.is_instance_of(instance.class_name(), &name),
+                Value::EnumCase { enum_name, .. } => self.is_instance_of(&enum_name, &name),
+                _ => false,
+            }),
+            "getParentClass" => match class.and_then(|class| class.parent.clone()) {
+                Some(parent) => self.class_object(&parent)?,
+                None => Value::Bool(false),
+            },
+            "isSubclassOf" | "implementsInterface" => {
+                let (other, other_declaration) = self.declaration_of(&arg(0))?;
+                if method == "implementsInterface"
+                    && !matches!(other_declaration, Declaration::Interface(_))
+                {
+                    return Err(VmError::exception(
+                        "ReflectionException",
+                        format!("{} is not an interface", other),
+                    ));
+                }
+                Value::Bool(other != name && self.is_instance_of(&name, &other))
+            }
+            "getInterfaceNames" => name_list(self.interface_names(&name)),
+            "getTraitNames" => name_list(match &declaration {
+                Declaration::Class(class) => class.traits.clone(),
+                Declaration::Trait(trait_def) => trait_def.uses.clone(),
+                _ => Vec::new(),
+            }),
+            "hasMethod" => {
+                let method_name = arg(0).to_string_val();
+                Value::Bool(self.reflected_method(&name, &method_name).is_some())
+            }
+            "getMethod" => {
+                let method_name = arg(0).to_string_val();
+                let info = self.reflected_method(&name, &method_name).ok_or_else(|| {
+                    VmError::exception(
+                        "ReflectionException",
+                        format!("Method {}::{}() does not exist", name, method_name),
+                    )
+                })?;
+                self.method_object(&info)?
+            }
+            "getConstructor" => match self.reflected_method(&name, "__construct") {
+                Some(info) => self.method_object(&info)?,
+                None => Value::Null,
+            },
+            "getMethods" => {
+                let mut methods = Vec::new();
+                for info in self.reflected_methods(&name) {
+                    methods.push(self.method_object(&info)?);
+                }
+                list(methods)
+            }
+            "hasProperty" => {
+                let prop_name = arg(0).to_string_val();
+                Value::Bool(
+                    self.reflected_properties(&name)
+                        .iter()
+                        .any(|info| info.property.name == prop_name),
+                )
+            }
+            "getProperty" => {
+                let info = self.reflected_property(&name, &arg(0).to_string_val())?;
+                self.property_object(&info)?
+            }
+            "getProperties" => {
+                let mut properties = Vec::new();
+                for info in self.reflected_properties(&name) {
+                    properties.push(self.property_object(&info)?);
+                }
+                list(properties)
+            }
+            "hasConstant" => Value::Bool(
+                self.class_constant(&name, &arg(0).to_string_val())
+                    .is_some(),
+            ),
+            "getConstant" => self
+                .class_constant(&name, &arg(0).to_string_val())
+                .unwrap_or(Value::Bool(false)),
+            "getConstants" => Value::Array(
+                self.reflected_constants(&name)
+                    .into_iter()
+                    .map(|(constant, value)| (ArrayKey::String(constant), value))
+                    .collect(),
+            ),
+            "getAttributes" => {
+                let attributes = match &declaration {
+                    Declaration::Class(class) => &class.attributes,
+                    Declaration::Interface(interface) => &interface.attributes,
+                    Declaration::Trait(trait_def) => &trait_def.attributes,
+                    Declaration::Enum(enum_def) => &enum_def.attributes,
+                };
+                self.attribute_objects(&attributes.clone(), &arg(0))?
+            }
+            "newInstance" | "newInstanceArgs" | "newInstanceWithoutConstructor" => {
+                if class.is_none() {
+                    let kind = match declaration {
+                        Declaration::Interface(_) => "interface",
+                        Declaration::Trait(_) => "trait",
+                        _ => "enum",
+                    };
+                    return Err(VmError::error(format!(
+                        "Cannot instantiate {} {}",
+                        kind, name
+                    )));
+                }
+                ops::execute_new_object(self, name)?;
+                if method != "newInstanceWithoutConstructor" {
+                    let args = match arg(0) {
+                        Value::Null => Value::Array(PhpArray::new()),
+                        args => args,
+                    };
+                    self.stack.push(args);
+                    ops::execute_call_constructor_named(self)?;
+                }
+                return Ok(None);
+            }
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method ReflectionClass::{}()",
+                    method
+                )))
+            }
+        };
+        Ok(Some(value))
+    }
+}
+
+/// A list of values
+pub(super) fn list(items: Vec<Value>) -> Value {
+    Value::Array(
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| (ArrayKey::Integer(i as i64), item))
+            .collect(),
+    )
+}
+
+/// A list of names
+fn name_list(names: Vec<String>) -> Value {
+    list(names.into_iter().map(Value::String).collect())
+}
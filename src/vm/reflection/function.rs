@@ -0,0 +1,484 @@
+//! ReflectionFunction, ReflectionMethod, ReflectionParameter and the
+//! Reflection types
+//!
+//! A ReflectionFunction keeps the function it reflects as a callable: a
+//! function name or a closure. A ReflectionParameter keeps its function
+//! the same way, or as a `[class, method]` array for a method.
+
+use super::class::{list, namespace_name, short_name};
+use super::members::MethodInfo;
+use super::{property, ReflectionResult};
+use crate::ast::{TypeHint, Visibility};
+use crate::runtime::{ClosureBody, ObjectRef, Value};
+use crate::vm::compiler::const_expr;
+use crate::vm::error::VmError;
+use crate::vm::frame::CallFrame;
+use crate::vm::opcode::CompiledFunction;
+use crate::vm::{ops, VM};
+use std::io::Write;
+use std::sync::Arc;
+
+/// The types `isBuiltin()` is true for
+const BUILTIN_TYPES: &[&str] = &[
+    "int", "float", "string", "bool", "array", "object", "callable", "iterable", "mixed", "void",
+    "never", "null", "false", "true", "static",
+];
+
+/// A reflected function or method
+struct FunctionInfo {
+    name: String,
+    /// Its code, or `None` for a built-in function
+    function: Option<Arc<CompiledFunction>>,
+    /// The method it is, for a method
+    method: Option<MethodInfo>,
+    /// How a ReflectionParameter refers to it
+    reference: Value,
+}
+
+/// The values of an array of arguments, in order
+fn arguments(args: Value) -> Vec<Value> {
+    match args {
+        Value::Array(entries) => entries.into_iter().map(|(_, value)| value).collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// The function a callable or a `[class, method]` array refers to
+    fn reflected_function(&self, reference: &Value) -> Result<FunctionInfo, VmError> {
+        let method = |class: &str, method: &str| {
+            let info = self.reflected_method(class, method).ok_or_else(|| {
+                VmError::exception(
+                    "ReflectionException",
+                    format!("Method {}::{}() does not exist", class, method),
+                )
+            })?;
+            Ok(FunctionInfo {
+                name: info.name.clone(),
+                function: Some(info.function.clone()),
+                reference: list(vec![
+                    Value::String(info.class.clone()),
+                    Value::String(info.name.clone()),
+                ]),
+                method: Some(info),
+            })
+        };
+        match reference {
+            Value::String(name) => {
+                let name = name.trim_start_matches('\\');
+                let function = self.get_function(name);
+                if function.is_none() && !self.is_native_function(name) {
+                    return Err(VmError::exception(
+                        "ReflectionException",
+                        format!("Function {}() does not exist", name),
+                    ));
+                }
+                Ok(FunctionInfo {
+                    name: function
+                        .as_ref()
+                        .map_or_else(|| name.to_string(), |f| f.name.clone()),
+                    function,
+                    method: None,
+                    reference: reference.clone(),
+                })
+            }
+            Value::Array(elements) => match elements.as_slice() {
+                [(_, class), (_, name)] => method(&class.to_string_val(), &name.to_string_val()),
+                _ => Err(VmError::fatal(
+                    "Function reference must be a [class, method] array",
+                )),
+            },
+            Value::Closure(closure) => match &closure.body {
+                ClosureBody::FunctionRef(name) => Ok(FunctionInfo {
+                    reference: reference.clone(),
+                    ..self.reflected_function(&Value::String(name.clone()))?
+                }),
+                ClosureBody::MethodRef {
+                    class_name,
+                    method_name,
+                    ..
+                }
+                | ClosureBody::StaticMethodRef {
+                    class_name,
+                    method_name,
+                } => method(&Self::normalize_class_name(class_name), method_name),
+                ClosureBody::Expression(_) => {
+                    Err(VmError::fatal("Cannot reflect an arrow function"))
+                }
+            },
+            _ => Err(VmError::fatal("Function reference must be a callable")),
+        }
+    }
+
+    /// The function a ReflectionFunction or ReflectionMethod reflects
+    fn function_of(&self, this: &ObjectRef) -> Result<FunctionInfo, VmError> {
+        if this.class_name() == "ReflectionMethod" {
+            let class = property(this, "class").to_string_val();
+            let name = property(this, "name").to_string_val();
+            self.reflected_function(&list(vec![Value::String(class), Value::String(name)]))
+        } else {
+            self.reflected_function(&property(this, "__function"))
+        }
+    }
+
+    fn function_object(&mut self, info: &FunctionInfo) -> Result<Value, VmError> {
+        match &info.method {
+            Some(method) => self.method_object(method),
+            None => self.reflection_object(
+                "ReflectionFunction",
+                vec![
+                    ("name", Value::String(info.name.clone())),
+                    ("__function", info.reference.clone()),
+                ],
+            ),
+        }
+    }
+
+    /// A Reflection type for a type declaration
+    pub(super) fn type_object(&mut self, type_hint: &TypeHint) -> Result<Value, VmError> {
+        let is_null = |hint: &TypeHint| matches!(hint, TypeHint::Simple(name) if name == "null");
+        match type_hint {
+            TypeHint::Nullable(inner) => self.named_type_object(inner, true),
+            TypeHint::Union(types) => {
+                let others: Vec<&TypeHint> = types.iter().filter(|hint| !is_null(hint)).collect();
+                let nullable = others.len() < types.len();
+                if let ([single], true) = (others.as_slice(), nullable) {
+                    return self.named_type_object(single, true);
+                }
+                self.composite_type_object("ReflectionUnionType", type_hint, types, nullable)
+            }
+            TypeHint::Intersection(types) => {
+                self.composite_type_object("ReflectionIntersectionType", type_hint, types, false)
+            }
+            TypeHint::DNF(groups) => {
+                let types: Vec<TypeHint> = groups
+                    .iter()
+                    .map(|group| match group.as_slice() {
+                        [single] => single.clone(),
+                        _ => TypeHint::Intersection(group.clone()),
+                    })
+                    .collect();
+                let nullable = types.iter().any(is_null);
+                self.composite_type_object("ReflectionUnionType", type_hint, &types, nullable)
+            }
+            _ => {
+                let name = self.format_type_hint(type_hint);
+                self.named_type_object(type_hint, matches!(name.as_str(), "mixed" | "null"))
+            }
+        }
+    }
+
+    fn named_type_object(
+        &mut self,
+        type_hint: &TypeHint,
+        nullable: bool,
+    ) -> Result<Value, VmError> {
+        let name = self.format_type_hint(type_hint);
+        let string = match name.as_str() {
+            "mixed" | "null" => name.clone(),
+            _ if nullable => format!("?{}", name),
+            _ => name.clone(),
+        };
+        let builtin = BUILTIN_TYPES.contains(&name.to_ascii_lowercase().as_str());
+        self.reflection_object(
+            "ReflectionNamedType",
+            vec![
+                ("__name", Value::String(name)),
+                ("__builtin", Value::Bool(builtin)),
+                ("__nullable", Value::Bool(nullable)),
+                ("__string", Value::String(string)),
+            ],
+        )
+    }
+
+    fn composite_type_object(
+        &mut self,
+        class: &str,
+        type_hint: &TypeHint,
+        types: &[TypeHint],
+        nullable: bool,
+    ) -> Result<Value, VmError> {
+        let mut members = Vec::new();
+        for member in types {
+            members.push(self.type_object(member)?);
+        }
+        let string = self.format_type_hint(type_hint);
+        self.reflection_object(
+            class,
+            vec![
+                ("__types", list(members)),
+                ("__nullable", Value::Bool(nullable)),
+                ("__string", Value::String(string)),
+            ],
+        )
+    }
+
+    /// A method of ReflectionFunctionAbstract, ReflectionFunction or
+    /// ReflectionMethod
+    pub(super) fn reflect_function(
+        &mut self,
+        method: &str,
+        this: ObjectRef,
+        args: Vec<Value>,
+    ) -> ReflectionResult {
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+        if method == "__construct" {
+            let info = if this.class_name() == "ReflectionMethod" {
+                let (class, method_name) = match (arg(0), arg(1)) {
+                    (Value::String(name), Value::Null) => match name.split_once("::") {
+                        Some((class, method_name)) => {
+                            (Value::String(class.to_string()), method_name.to_string())
+                        }
+                        None => {
+                            return Err(
+                                VmError::exception("ReflectionException", "ReflectionMethod::__construct(): Argument #1 ($objectOrMethod) must be a valid method name"),
+                            )
+                        }
+                    },
+                    (class, method_name) => (class, method_name.to_string_val()),
+                };
+                let (class, _) = self.declaration_of(&class)?;
+                self.reflected_function(&list(vec![
+                    Value::String(class),
+                    Value::String(method_name),
+                ]))?
+            } else {
+                self.reflected_function(&arg(0))?
+            };
+            this.set("name", Value::String(info.name.clone()));
+            match &info.method {
+                Some(method) => this.set("class", Value::String(method.class.clone())),
+                None => this.set("__function", info.reference.clone()),
+            }
+            return Ok(Some(Value::Null));
+        }
+
+        let info = self.function_of(&this)?;
+        let function = info.function.clone();
+        let params = function
+            .as_ref()
+            .map_or(&[][..], |function| &function.parameters[..]);
+        let value = match method {
+            "getName" => Value::String(info.name.clone()),
+            "getShortName" => Value::String(short_name(&info.name).to_string()),
+            "getNamespaceName" => Value::String(namespace_name(&info.name).to_string()),
+            "inNamespace" => Value::Bool(info.name.contains('\\')),
+            // Built-in classes' methods are compiled with no source file
+            "isInternal" => Value::Bool(function.as_ref().is_none_or(|f| f.file.is_empty())),
+            "isUserDefined" => Value::Bool(function.as_ref().is_some_and(|f| !f.file.is_empty())),
+            "isVariadic" => Value::Bool(function.as_ref().is_some_and(|f| f.is_variadic)),
+            "getNumberOfParameters" => {
+                Value::Integer(function.as_ref().map_or(0, |f| f.param_count as i64))
+            }
+            "getNumberOfRequiredParameters" => Value::Integer(
+                function
+                    .as_ref()
+                    .map_or(0, |f| f.required_param_count as i64),
+            ),
+            "getParameters" => {
+                let names: Vec<String> = params.iter().map(|param| param.name.clone()).collect();
+                let mut parameters = Vec::new();
+                for (position, name) in names.into_iter().enumerate() {
+                    parameters.push(self.reflection_object(
+                        "ReflectionParameter",
+                        vec![
+                            ("name", Value::String(name)),
+                            ("__function", info.reference.clone()),
+                            ("__position", Value::Integer(position as i64)),
+                        ],
+                    )?);
+                }
+                list(parameters)
+            }
+            "hasReturnType" => {
+                Value::Bool(function.as_ref().is_some_and(|f| f.return_type.is_some()))
+            }
+            "getReturnType" => match function.as_ref().and_then(|f| f.return_type.clone()) {
+                Some(return_type) => self.type_object(&return_type)?,
+                None => Value::Null,
+            },
+            "getAttributes" => {
+                let attributes = function
+                    .as_ref()
+                    .map(|f| f.attributes.clone())
+                    .unwrap_or_default();
+                self.attribute_objects(&attributes, &arg(0))?
+            }
+            "invoke" | "invokeArgs" if this.class_name() == "ReflectionFunction" => {
+                let args = arguments(arg(0));
+                let arg_count = args.len();
+                self.stack.extend(args);
+                self.stack.push(info.reference.clone());
+                ops::execute_call_callable(self, arg_count as u8)?;
+                return Ok(None);
+            }
+            _ => {
+                let Some(method_info) = info.method else {
+                    return Err(VmError::error(format!(
+                        "Call to undefined method {}::{}()",
+                        this.class_name(),
+                        method
+                    )));
+                };
+                return self.reflect_method(method, method_info, args);
+            }
+        };
+        Ok(Some(value))
+    }
+
+    /// A method of ReflectionMethod alone
+    fn reflect_method(
+        &mut self,
+        method: &str,
+        info: MethodInfo,
+        args: Vec<Value>,
+    ) -> ReflectionResult {
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+        let value = match method {
+            "getDeclaringClass" => self.class_object(&info.class)?,
+            "isPublic" => Value::Bool(info.visibility == Visibility::Public),
+            "isPrivate" => Value::Bool(info.visibility == Visibility::Private),
+            "isProtected" => Value::Bool(info.visibility == Visibility::Protected),
+            "isStatic" => Value::Bool(info.is_static),
+            "isAbstract" => Value::Bool(info.is_abstract),
+            "isFinal" => Value::Bool(info.is_final),
+            "isConstructor" => Value::Bool(info.name.eq_ignore_ascii_case("__construct")),
+            "setAccessible" => Value::Null,
+            "invoke" | "invokeArgs" => {
+                let object = arg(0);
+                let mut frame = CallFrame::new(info.function.clone(), self.stack.len());
+                let first = if info.is_static {
+                    frame.called_class = Some(match &object {
+                        Value::Object(instance) => instance.class_name().to_string(),
+                        _ => info.class.clone(),
+                    });
+                    0
+                } else {
+                    let class = match &object {
+                        Value::Object(instance) => instance.class_name(),
+                        Value::EnumCase { enum_name, .. } => enum_name.as_str(),
+                        _ => {
+                            return Err(VmError::exception(
+                                "ReflectionException",
+                                format!(
+                                    "Trying to invoke non static method {}::{}() without an object",
+                                    info.class, info.name
+                                ),
+                            ))
+                        }
+                    };
+                    if !self.is_instance_of(class, &info.class)
+                        && !self.uses_trait(class, &info.class)
+                    {
+                        return Err(
+                            VmError::exception("ReflectionException", "Given object is not an instance of the class this method was declared in"),
+                        );
+                    }
+                    frame.locals[0] = object;
+                    1
+                };
+                frame.bind_args(first, arguments(arg(1)));
+                self.push_frame(frame);
+                return Ok(None);
+            }
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method ReflectionMethod::{}()",
+                    method
+                )))
+            }
+        };
+        Ok(Some(value))
+    }
+
+    /// Whether a trait's methods, reflected as methods of the class using
+    /// it, may run on an object of `class`
+    fn uses_trait(&self, class: &str, declaring: &str) -> bool {
+        self.reflected_method(class, "__construct")
+            .is_some_and(|m| m.class == declaring)
+            || self
+                .reflected_methods(class)
+                .iter()
+                .any(|method| method.class == declaring)
+    }
+
+    /// A method of ReflectionParameter
+    pub(super) fn reflect_parameter(
+        &mut self,
+        method: &str,
+        this: ObjectRef,
+        args: Vec<Value>,
+    ) -> ReflectionResult {
+        let info = self.reflected_function(&property(&this, "__function"))?;
+        let position = property(&this, "__position").to_int() as usize;
+        let params = info
+            .function
+            .as_ref()
+            .map_or(&[][..], |function| &function.parameters[..]);
+        let param = params.get(position).cloned().ok_or_else(|| {
+            VmError::fatal("The parameter specified by its offset could not be found")
+        })?;
+        let value = match method {
+            "getName" => Value::String(param.name.clone()),
+            "getPosition" => Value::Integer(position as i64),
+            "isOptional" => Value::Bool(
+                params[position..]
+                    .iter()
+                    .all(|param| param.default.is_some() || param.is_variadic),
+            ),
+            "isVariadic" => Value::Bool(param.is_variadic),
+            "isPromoted" => Value::Bool(
+                param.visibility.is_some()
+                    && info
+                        .method
+                        .as_ref()
+                        .is_some_and(|method| method.name.eq_ignore_ascii_case("__construct")),
+            ),
+            "isDefaultValueAvailable" => Value::Bool(param.default.is_some()),
+            "getDefaultValue" => {
+                let class = info.method.as_ref().map(|method| method.class.clone());
+                param
+                    .default
+                    .as_ref()
+                    .and_then(|default| {
+                        const_expr::evaluate(default, &|name, item| {
+                            let name = match (name, &class) {
+                                ("self" | "static", Some(class)) => class.as_str(),
+                                _ => name.trim_start_matches('\\'),
+                            };
+                            match item {
+                                "class" => Some(Value::String(name.to_string())),
+                                _ => self.class_constant(name, item),
+                            }
+                        })
+                    })
+                    .ok_or_else(|| VmError::exception("ReflectionException", "Internal error: Failed to retrieve the default value"))?
+            }
+            "hasType" => Value::Bool(param.type_hint.is_some()),
+            "getType" => match &param.type_hint {
+                Some(type_hint) => self.type_object(type_hint)?,
+                None => Value::Null,
+            },
+            "allowsNull" => Value::Bool(param.type_hint.as_ref().is_none_or(|type_hint| {
+                type_hint.is_nullable()
+                    || matches!(type_hint, TypeHint::Simple(name) if name == "mixed" || name == "null")
+            })),
+            "getDeclaringFunction" => self.function_object(&info)?,
+            "getDeclaringClass" => match &info.method {
+                Some(method) => self.class_object(&method.class.clone())?,
+                None => Value::Null,
+            },
+            "getAttributes" => {
+                let filter = args.first().cloned().unwrap_or(Value::Null);
+                self.attribute_objects(&param.attributes, &filter)?
+            }
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method ReflectionParameter::{}()",
+                    method
+                )))
+            }
+        };
+        Ok(Some(value))
+    }
+}
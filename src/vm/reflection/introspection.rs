@@ -0,0 +1,232 @@
+//! Class and object introspection functions
+//!
+//! get_object_vars(), get_class_methods(), method_exists(),
+//! property_exists(), get_parent_class() and class_implements(). What
+//! they list goes by the calling scope, the class of the method calling
+//! them, as member access does: private members are visible from their
+//! own class, protected ones from related classes.
+
+use super::class::Declaration;
+use crate::ast::Visibility;
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::VmError;
+use crate::vm::notices::Level;
+use crate::vm::VM;
+use std::io::Write;
+
+impl<W: Write> VM<W> {
+    /// Whether the calling scope may access a member of class `declaring`
+    fn is_visible(&self, declaring: &str, visibility: Visibility) -> bool {
+        let scope = self.get_current_class();
+        match (visibility, scope.as_deref()) {
+            (Visibility::Public, _) => true,
+            (Visibility::Private, Some(scope)) => scope.eq_ignore_ascii_case(declaring),
+            (Visibility::Protected, Some(scope)) => {
+                scope.eq_ignore_ascii_case(declaring)
+                    || self.is_subclass_of(scope, declaring)
+                    || self.is_subclass_of(declaring, scope)
+            }
+            (_, None) => false,
+        }
+    }
+
+    /// The class an object-or-class argument names, if it exists
+    fn named_class(&mut self, object_or_class: &Value) -> Option<(String, Declaration)> {
+        match object_or_class {
+            Value::Object(instance) => self.declaration(instance.class_name()),
+            Value::EnumCase { enum_name, .. } => self.declaration(enum_name),
+            Value::String(name) => self.declaration(name),
+            _ => None,
+        }
+    }
+
+    /// get_object_vars - The properties of an object the calling scope
+    /// may access: the declared ones, base class first, then dynamic ones
+    pub(crate) fn get_object_vars(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        let Some(Value::Object(object)) = args.first() else {
+            return Err(VmError::type_error(format!(
+                "get_object_vars(): Argument #1 ($object) must be of type object, {} given",
+                args.first().map_or("null", |arg| arg.type_name())
+            )));
+        };
+        let mut chain = Vec::new();
+        let mut current = Some(object.class_name().to_string());
+        while let Some(class) = current.and_then(|name| self.classes.get(&name).cloned()) {
+            current = class.parent.clone();
+            chain.push(class);
+        }
+
+        let properties = object.properties();
+        let mut vars: Vec<(ArrayKey, Value)> = Vec::new();
+        let mut declared: Vec<&str> = Vec::new();
+        for class in chain.iter().rev() {
+            for prop in &class.properties {
+                if declared.contains(&prop.name.as_str()) {
+                    continue;
+                }
+                declared.push(&prop.name);
+                if prop.is_static || !self.is_visible(&class.name, prop.visibility) {
+                    continue;
+                }
+                if let Some(value) = properties.get(&prop.name) {
+                    vars.push((ArrayKey::String(prop.name.clone()), value.clone()));
+                }
+            }
+        }
+        let mut dynamic: Vec<(&String, &Value)> = properties
+            .iter()
+            .filter(|(name, _)| !declared.contains(&name.as_str()))
+            .collect();
+        dynamic.sort_by(|a, b| a.0.cmp(b.0));
+        vars.extend(
+            dynamic
+                .into_iter()
+                .map(|(name, value)| (ArrayKey::String(name.clone()), value.clone())),
+        );
+        Ok(Value::Array(vars.into()))
+    }
+
+    /// get_class_methods - The names of the methods of a class the calling
+    /// scope may call, its own first
+    pub(crate) fn get_class_methods(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        let object_or_class = args.first().cloned().unwrap_or(Value::Null);
+        let Some((name, _)) = self.named_class(&object_or_class) else {
+            return Err(VmError::type_error(format!(
+                "get_class_methods(): Argument #1 ($object_or_class) must be an object or a valid class name, {} given",
+                object_or_class.type_name()
+            )));
+        };
+        let names = self
+            .reflected_methods(&name)
+            .into_iter()
+            .filter(|method| self.is_visible(&method.class, method.visibility))
+            .enumerate()
+            .map(|(i, method)| (ArrayKey::Integer(i as i64), Value::String(method.name)))
+            .collect();
+        Ok(Value::Array(names))
+    }
+
+    /// method_exists - Whether a class has a method, whatever its
+    /// visibility
+    pub(crate) fn method_exists(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        if args.len() < 2 {
+            return Err(VmError::argument_count_error(format!(
+                "method_exists() expects exactly 2 arguments, {} given",
+                args.len()
+            )));
+        }
+        let Some((name, declaration)) = self.named_class(&args[0]) else {
+            return Ok(Value::Bool(false));
+        };
+        let method = args[1].to_string_val();
+        let exists = match declaration {
+            Declaration::Interface(interface) => interface
+                .method_signatures
+                .iter()
+                .any(|(m, _)| m.eq_ignore_ascii_case(&method)),
+            _ => self.reflected_method(&name, &method).is_some(),
+        };
+        Ok(Value::Bool(exists))
+    }
+
+    /// property_exists - Whether a class declares a property, whatever
+    /// its visibility, or an object has it
+    pub(crate) fn property_exists(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        if args.len() < 2 {
+            return Err(VmError::argument_count_error(format!(
+                "property_exists() expects exactly 2 arguments, {} given",
+                args.len()
+            )));
+        }
+        let property = args[1].to_string_val();
+        if let Value::Object(object) = &args[0] {
+            if object.get(&property).is_some() {
+                return Ok(Value::Bool(true));
+            }
+        }
+        let Some((name, _)) = self.named_class(&args[0]) else {
+            return Ok(Value::Bool(false));
+        };
+        let mut current = Some(name);
+        while let Some(class_name) = current {
+            let (declared, parent) = match self.classes.get(&class_name) {
+                Some(class) => (&class.properties, class.parent.clone()),
+                None => match self.traits.get(&class_name) {
+                    Some(trait_def) => (&trait_def.properties, None),
+                    None => break,
+                },
+            };
+            if declared.iter().any(|prop| prop.name == property) {
+                return Ok(Value::Bool(true));
+            }
+            current = parent;
+        }
+        Ok(Value::Bool(false))
+    }
+
+    /// get_parent_class - The name of the parent of a class, or of the
+    /// calling scope with no argument; false if it has none
+    pub(crate) fn get_parent_class(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        let name = match args.first() {
+            None => self.get_current_class(),
+            Some(object_or_class) => match self.named_class(object_or_class) {
+                Some((name, _)) => Some(name),
+                None => {
+                    return Err(VmError::type_error(format!(
+                        "get_parent_class(): Argument #1 ($object_or_class) must be an object or a valid class name, {} given",
+                        object_or_class.type_name()
+                    )))
+                }
+            },
+        };
+        let parent = name
+            .and_then(|name| self.classes.get(&name))
+            .and_then(|class| class.parent.clone());
+        Ok(parent.map_or(Value::Bool(false), Value::String))
+    }
+
+    /// class_implements - The interfaces a class or interface implements,
+    /// directly or not, keyed by name
+    pub(crate) fn class_implements(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        let object_or_class = args.first().cloned().unwrap_or(Value::Null);
+        let autoload = args.get(1).is_none_or(Value::to_bool);
+        let name = match &object_or_class {
+            Value::Object(instance) => Some(instance.class_name().to_string()),
+            Value::EnumCase { enum_name, .. } => Some(enum_name.clone()),
+            Value::String(name) if autoload => self.declaration(name).map(|(name, _)| name),
+            Value::String(name) => self
+                .find_declaration(&Self::normalize_class_name(name))
+                .map(|(name, _)| name),
+            other => {
+                return Err(VmError::type_error(format!(
+                    "class_implements(): Argument #1 ($object_or_class) must be of type object|string, {} given",
+                    other.type_name()
+                )))
+            }
+        };
+        let Some(name) = name else {
+            let message = format!(
+                "class_implements(): Class {} does not exist{}",
+                object_or_class.to_string_val(),
+                if autoload {
+                    " and could not be loaded"
+                } else {
+                    ""
+                }
+            );
+            self.raise(Level::Warning, &message)?;
+            return Ok(Value::Bool(false));
+        };
+        let interfaces = self
+            .interface_names(&name)
+            .into_iter()
+            .map(|interface| {
+                (
+                    ArrayKey::String(interface.clone()),
+                    Value::String(interface),
+                )
+            })
+            .collect();
+        Ok(Value::Array(interfaces))
+    }
+}
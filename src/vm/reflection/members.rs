@@ -0,0 +1,271 @@
+//! The members reflection lists: a class's methods, properties,
+//! constants and interfaces
+//!
+//! A class's methods are its own in declaration order, then those of its
+//! traits, then those it inherits; its properties and constants are
+//! listed the same way. Interfaces have no methods here: only their
+//! signatures are compiled.
+
+use crate::ast::Visibility;
+use crate::runtime::Value;
+use crate::vm::class::CompiledProperty;
+use crate::vm::opcode::CompiledFunction;
+use crate::vm::VM;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+/// A method, as reflection sees it
+#[derive(Clone)]
+pub(super) struct MethodInfo {
+    pub name: String,
+    /// The class, trait or enum declaring it
+    pub class: String,
+    pub function: Arc<CompiledFunction>,
+    pub visibility: Visibility,
+    pub is_static: bool,
+    pub is_abstract: bool,
+    pub is_final: bool,
+}
+
+/// A property, with the class declaring it
+pub(super) struct PropertyInfo {
+    pub class: String,
+    pub property: CompiledProperty,
+}
+
+/// `names` in order, then the rest of `all` by name
+fn ordered<'a>(names: &[String], all: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut rest: Vec<String> = all.filter(|name| !names.contains(name)).cloned().collect();
+    rest.sort();
+    names.iter().cloned().chain(rest).collect()
+}
+
+fn push_unique(names: &mut Vec<String>, name: &str) {
+    if !names.iter().any(|n| n == name) {
+        names.push(name.to_string());
+    }
+}
+
+fn push_method(methods: &mut Vec<MethodInfo>, method: MethodInfo) {
+    if !methods
+        .iter()
+        .any(|m| m.name.eq_ignore_ascii_case(&method.name))
+    {
+        methods.push(method);
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// The methods of a class, trait or enum
+    pub(super) fn reflected_methods(&self, name: &str) -> Vec<MethodInfo> {
+        let mut methods = Vec::new();
+        self.collect_methods(name, name, &mut methods);
+        methods
+    }
+
+    /// The method `method` of a class, trait or enum
+    pub(super) fn reflected_method(&self, class: &str, method: &str) -> Option<MethodInfo> {
+        self.reflected_methods(class)
+            .into_iter()
+            .find(|info| info.name.eq_ignore_ascii_case(method))
+    }
+
+    /// Add the methods `name` declares, with `declaring` as their class
+    /// if `name` is a trait, then those it gets from traits and parents
+    fn collect_methods(&self, name: &str, declaring: &str, methods: &mut Vec<MethodInfo>) {
+        if let Some(class) = self.classes.get(name) {
+            let all = class.methods.keys().chain(class.static_methods.keys());
+            for method_name in ordered(&class.method_order, all) {
+                // Property hooks are compiled as methods too
+                if method_name.starts_with("__prop_") {
+                    continue;
+                }
+                let (function, is_static) = match class.methods.get(&method_name) {
+                    Some(function) => (function.clone(), false),
+                    None => match class.static_methods.get(&method_name) {
+                        Some(function) => (function.clone(), true),
+                        None => continue,
+                    },
+                };
+                let flag = |flags: &HashMap<String, bool>| flags.get(&method_name) == Some(&true);
+                push_method(
+                    methods,
+                    MethodInfo {
+                        visibility: class
+                            .method_visibility
+                            .get(&method_name)
+                            .copied()
+                            .unwrap_or(Visibility::Public),
+                        is_abstract: flag(&class.method_abstracts),
+                        is_final: flag(&class.method_finals),
+                        is_static,
+                        function,
+                        class: name.to_string(),
+                        name: method_name,
+                    },
+                );
+            }
+            for trait_name in &class.traits {
+                self.collect_methods(trait_name, name, methods);
+            }
+            if let Some(parent) = &class.parent {
+                self.collect_methods(parent, parent, methods);
+            }
+        } else if let Some(trait_def) = self.traits.get(name) {
+            for method_name in ordered(&trait_def.method_order, trait_def.methods.keys()) {
+                let Some(function) = trait_def.methods.get(&method_name) else {
+                    continue;
+                };
+                push_method(
+                    methods,
+                    MethodInfo {
+                        name: method_name.clone(),
+                        class: declaring.to_string(),
+                        function: function.clone(),
+                        visibility: Visibility::Public,
+                        is_static: false,
+                        is_abstract: false,
+                        is_final: false,
+                    },
+                );
+            }
+            for used in &trait_def.uses {
+                self.collect_methods(used, declaring, methods);
+            }
+        } else if let Some(enum_def) = self.enums.get(name) {
+            let all = enum_def
+                .methods
+                .keys()
+                .chain(enum_def.static_methods.keys());
+            for method_name in ordered(&enum_def.method_order, all) {
+                let (function, is_static) = match enum_def.methods.get(&method_name) {
+                    Some(function) => (function.clone(), false),
+                    None => match enum_def.static_methods.get(&method_name) {
+                        Some(function) => (function.clone(), true),
+                        None => continue,
+                    },
+                };
+                push_method(
+                    methods,
+                    MethodInfo {
+                        name: method_name,
+                        class: name.to_string(),
+                        function,
+                        visibility: Visibility::Public,
+                        is_static,
+                        is_abstract: false,
+                        is_final: true,
+                    },
+                );
+            }
+        }
+    }
+
+    /// The properties of a class or trait: its own, then the ones it
+    /// inherits that aren't private
+    pub(super) fn reflected_properties(&self, name: &str) -> Vec<PropertyInfo> {
+        let mut properties: Vec<PropertyInfo> = Vec::new();
+        let mut current = Some(name.to_string());
+        let mut own = true;
+        while let Some(class_name) = current {
+            let (declared, parent) = match self.classes.get(&class_name) {
+                Some(class) => (&class.properties, class.parent.clone()),
+                None => match self.traits.get(&class_name) {
+                    Some(trait_def) => (&trait_def.properties, None),
+                    None => break,
+                },
+            };
+            for prop in declared {
+                let inherited = !own && prop.visibility == Visibility::Private;
+                if !inherited && !properties.iter().any(|p| p.property.name == prop.name) {
+                    properties.push(PropertyInfo {
+                        class: class_name.clone(),
+                        property: prop.clone(),
+                    });
+                }
+            }
+            current = parent;
+            own = false;
+        }
+        properties
+    }
+
+    /// The constants of a class, interface or enum, an enum's cases
+    /// first, by name
+    pub(super) fn reflected_constants(&self, name: &str) -> Vec<(String, Value)> {
+        let mut names = Vec::new();
+        self.collect_constant_names(name, &mut names);
+        names
+            .into_iter()
+            .filter_map(|constant| {
+                let value = self.class_constant(name, &constant)?;
+                Some((constant, value))
+            })
+            .collect()
+    }
+
+    fn collect_constant_names(&self, name: &str, names: &mut Vec<String>) {
+        if let Some(class) = self.classes.get(name) {
+            for constant in &class.constant_order {
+                push_unique(names, constant);
+            }
+            if let Some(parent) = &class.parent {
+                self.collect_constant_names(parent, names);
+            }
+            for interface in &class.interfaces {
+                self.collect_constant_names(interface, names);
+            }
+            for constant in ordered(&[], class.constants.keys()) {
+                push_unique(names, &constant);
+            }
+        } else if let Some(interface) = self.interfaces.get(name) {
+            for constant in &interface.constant_order {
+                push_unique(names, constant);
+            }
+            for parent in &interface.parents {
+                self.collect_constant_names(parent, names);
+            }
+        } else if let Some(enum_def) = self.enums.get(name) {
+            for constant in enum_def.case_order.iter().chain(&enum_def.constant_order) {
+                push_unique(names, constant);
+            }
+            for interface in &enum_def.interfaces {
+                self.collect_constant_names(interface, names);
+            }
+        }
+    }
+
+    /// The interfaces a class, interface or enum implements, directly or
+    /// not
+    pub(super) fn interface_names(&self, name: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut pending = match self.classes.get(name) {
+            Some(class) => {
+                let mut pending = class.interfaces.clone();
+                let mut parent = class.parent.clone();
+                while let Some(parent_class) = parent.and_then(|p| self.classes.get(&p)) {
+                    pending.extend(parent_class.interfaces.iter().cloned());
+                    parent = parent_class.parent.clone();
+                }
+                pending
+            }
+            None => match (self.interfaces.get(name), self.enums.get(name)) {
+                (Some(interface), _) => interface.parents.clone(),
+                (_, Some(enum_def)) => enum_def.interfaces.clone(),
+                _ => Vec::new(),
+            },
+        };
+        pending.reverse();
+        while let Some(interface) = pending.pop() {
+            if names.contains(&interface) {
+                continue;
+            }
+            if let Some(def) = self.interfaces.get(&interface) {
+                pending.extend(def.parents.iter().rev().cloned());
+            }
+            names.push(interface);
+        }
+        names
+    }
+}
@@ -0,0 +1,140 @@
+//! ReflectionProperty
+//!
+//! A ReflectionProperty keeps the names of its class and property, and
+//! finds the property again among the class's reflected properties on
+//! each call.
+
+use super::members::PropertyInfo;
+use super::{property, ReflectionResult};
+use crate::ast::Visibility;
+use crate::runtime::{ObjectRef, Value};
+use crate::vm::error::VmError;
+use crate::vm::VM;
+use std::io::Write;
+use std::sync::Arc;
+
+impl<W: Write> VM<W> {
+    /// The property `prop_name` of the class or trait `class`
+    pub(super) fn reflected_property(
+        &self,
+        class: &str,
+        prop_name: &str,
+    ) -> Result<PropertyInfo, VmError> {
+        self.reflected_properties(class)
+            .into_iter()
+            .find(|info| info.property.name == prop_name)
+            .ok_or_else(|| {
+                VmError::exception(
+                    "ReflectionException",
+                    format!("Property {}::${} does not exist", class, prop_name),
+                )
+            })
+    }
+
+    /// Whether the property is a promoted constructor parameter
+    fn is_promoted(&self, info: &PropertyInfo) -> bool {
+        self.reflected_method(&info.class, "__construct")
+            .is_some_and(|constructor| {
+                constructor.class == info.class
+                    && constructor
+                        .function
+                        .parameters
+                        .iter()
+                        .any(|param| param.name == info.property.name && param.visibility.is_some())
+            })
+    }
+
+    /// A method of ReflectionProperty
+    pub(super) fn reflect_property(
+        &mut self,
+        method: &str,
+        this: ObjectRef,
+        args: Vec<Value>,
+    ) -> ReflectionResult {
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+        if method == "__construct" {
+            let (class, _) = self.declaration_of(&arg(0))?;
+            let info = self.reflected_property(&class, &arg(1).to_string_val())?;
+            this.set("name", Value::String(info.property.name));
+            this.set("class", Value::String(info.class));
+            return Ok(Some(Value::Null));
+        }
+        let class = property(&this, "class").to_string_val();
+        let info = self.reflected_property(&class, &property(&this, "name").to_string_val())?;
+        let prop = &info.property;
+        let value = match method {
+            "getName" => Value::String(prop.name.clone()),
+            "getValue" if prop.is_static => self.static_property(&info.class, &prop.name),
+            "getValue" => match arg(0) {
+                Value::Object(instance) => property(&instance, &prop.name),
+                _ => {
+                    return Err(
+                        VmError::type_error("ReflectionProperty::getValue(): Argument #1 ($object) must be provided for instance properties"),
+                    )
+                }
+            },
+            "setValue" if prop.is_static => {
+                // setValue($value) or setValue(null, $value)
+                let value = match arg(1) {
+                    Value::Null => arg(0),
+                    value => value,
+                };
+                if let Some(class) = self.classes.get_mut(&info.class) {
+                    Arc::make_mut(class)
+                        .static_properties
+                        .insert(prop.name.clone(), value);
+                }
+                Value::Null
+            }
+            "setValue" => match arg(0) {
+                Value::Object(instance) => {
+                    instance.set(prop.name.clone(), arg(1));
+                    Value::Null
+                }
+                _ => {
+                    return Err(
+                        VmError::type_error("ReflectionProperty::setValue(): Argument #1 ($objectOrValue) must be of type object"),
+                    )
+                }
+            },
+            "isInitialized" => Value::Bool(
+                prop.is_static
+                    || matches!(arg(0), Value::Object(instance) if instance.get(&prop.name).is_some()),
+            ),
+            "isPublic" => Value::Bool(prop.visibility == Visibility::Public),
+            "isPrivate" => Value::Bool(prop.visibility == Visibility::Private),
+            "isProtected" => Value::Bool(prop.visibility == Visibility::Protected),
+            "isStatic" => Value::Bool(prop.is_static),
+            "isReadOnly" => Value::Bool(prop.readonly),
+            "isPromoted" => Value::Bool(self.is_promoted(&info)),
+            // An untyped property without a default defaults to null
+            "hasDefaultValue" => Value::Bool(
+                prop.default.is_some() || (prop.type_hint.is_none() && !self.is_promoted(&info)),
+            ),
+            "getDefaultValue" => prop.default.clone().unwrap_or(Value::Null),
+            "hasType" => Value::Bool(prop.type_hint.is_some()),
+            "getType" => match &prop.type_hint {
+                Some(type_hint) => self.type_object(&type_hint.clone())?,
+                None => Value::Null,
+            },
+            "getDeclaringClass" => self.class_object(&info.class)?,
+            "getAttributes" => self.attribute_objects(&prop.attributes.clone(), &arg(0))?,
+            "setAccessible" => Value::Null,
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method ReflectionProperty::{}()",
+                    method
+                )))
+            }
+        };
+        Ok(Some(value))
+    }
+
+    /// The value of a static property, from the class declaring it
+    fn static_property(&self, class: &str, name: &str) -> Value {
+        self.classes
+            .get(class)
+            .and_then(|class| class.static_properties.get(name).cloned())
+            .unwrap_or(Value::Null)
+    }
+}
@@ -0,0 +1,268 @@
+//! The Reflection classes
+//!
+//! Their methods are stubs: a `Reflect` instruction naming the method,
+//! which the VM runs natively on the stub's `$this` and arguments, or a
+//! getter for one of the properties the object was created with.
+
+use crate::ast::Visibility;
+use crate::runtime::Value;
+use crate::vm::class::{CompiledClass, CompiledProperty};
+use crate::vm::name_table::NameTable;
+use crate::vm::opcode::{CompiledFunction, Opcode};
+use std::sync::Arc;
+
+/// A Reflection class: name, parent, public properties and internal ones
+type ReflectionClassSpec = (
+    &'static str,
+    Option<&'static str>,
+    &'static [&'static str],
+    &'static [&'static str],
+);
+
+const CLASSES: &[ReflectionClassSpec] = &[
+    ("ReflectionClass", None, &["name"], &[]),
+    ("ReflectionFunctionAbstract", None, &["name"], &[]),
+    (
+        "ReflectionFunction",
+        Some("ReflectionFunctionAbstract"),
+        &[],
+        &["__function"],
+    ),
+    (
+        "ReflectionMethod",
+        Some("ReflectionFunctionAbstract"),
+        &["class"],
+        &[],
+    ),
+    (
+        "ReflectionParameter",
+        None,
+        &["name"],
+        &["__function", "__position"],
+    ),
+    ("ReflectionProperty", None, &["name", "class"], &[]),
+    ("ReflectionType", None, &[], &["__nullable", "__string"]),
+    (
+        "ReflectionNamedType",
+        Some("ReflectionType"),
+        &[],
+        &["__name", "__builtin"],
+    ),
+    (
+        "ReflectionUnionType",
+        Some("ReflectionType"),
+        &[],
+        &["__types"],
+    ),
+    (
+        "ReflectionIntersectionType",
+        Some("ReflectionType"),
+        &[],
+        &["__types"],
+    ),
+    ("ReflectionAttribute", None, &[], &["__name", "__arguments"]),
+];
+
+/// The methods run natively: class, method, parameters and how many are
+/// required. A parameter starting with `...` is variadic.
+const NATIVE_METHODS: &[(&str, &str, &[&str], u8)] = &[
+    ("ReflectionClass", "__construct", &["objectOrClass"], 1),
+    ("ReflectionClass", "getName", &[], 0),
+    ("ReflectionClass", "getShortName", &[], 0),
+    ("ReflectionClass", "getNamespaceName", &[], 0),
+    ("ReflectionClass", "inNamespace", &[], 0),
+    ("ReflectionClass", "isInterface", &[], 0),
+    ("ReflectionClass", "isTrait", &[], 0),
+    ("ReflectionClass", "isEnum", &[], 0),
+    ("ReflectionClass", "isAbstract", &[], 0),
+    ("ReflectionClass", "isFinal", &[], 0),
+    ("ReflectionClass", "isReadOnly", &[], 0),
+    ("ReflectionClass", "isAnonymous", &[], 0),
+    ("ReflectionClass", "isInstantiable", &[], 0),
+    ("ReflectionClass", "isInstance", &["object"], 1),
+    ("ReflectionClass", "getParentClass", &[], 0),
+    ("ReflectionClass", "isSubclassOf", &["class"], 1),
+    ("ReflectionClass", "implementsInterface", &["interface"], 1),
+    ("ReflectionClass", "getInterfaceNames", &[], 0),
+    ("ReflectionClass", "getTraitNames", &[], 0),
+    ("ReflectionClass", "hasMethod", &["name"], 1),
+    ("ReflectionClass", "getMethod", &["name"], 1),
+    ("ReflectionClass", "getMethods", &[], 0),
+    ("ReflectionClass", "getConstructor", &[], 0),
+    ("ReflectionClass", "hasProperty", &["name"], 1),
+    ("ReflectionClass", "getProperty", &["name"], 1),
+    ("ReflectionClass", "getProperties", &[], 0),
+    ("ReflectionClass", "hasConstant", &["name"], 1),
+    ("ReflectionClass", "getConstant", &["name"], 1),
+    ("ReflectionClass", "getConstants", &[], 0),
+    ("ReflectionClass", "getAttributes", &["name"], 0),
+    ("ReflectionClass", "newInstance", &["...args"], 0),
+    ("ReflectionClass", "newInstanceArgs", &["args"], 0),
+    ("ReflectionClass", "newInstanceWithoutConstructor", &[], 0),
+    ("ReflectionFunctionAbstract", "getName", &[], 0),
+    ("ReflectionFunctionAbstract", "getShortName", &[], 0),
+    ("ReflectionFunctionAbstract", "getNamespaceName", &[], 0),
+    ("ReflectionFunctionAbstract", "inNamespace", &[], 0),
+    ("ReflectionFunctionAbstract", "isInternal", &[], 0),
+    ("ReflectionFunctionAbstract", "isUserDefined", &[], 0),
+    ("ReflectionFunctionAbstract", "isVariadic", &[], 0),
+    (
+        "ReflectionFunctionAbstract",
+        "getNumberOfParameters",
+        &[],
+        0,
+    ),
+    (
+        "ReflectionFunctionAbstract",
+        "getNumberOfRequiredParameters",
+        &[],
+        0,
+    ),
+    ("ReflectionFunctionAbstract", "getParameters", &[], 0),
+    ("ReflectionFunctionAbstract", "hasReturnType", &[], 0),
+    ("ReflectionFunctionAbstract", "getReturnType", &[], 0),
+    ("ReflectionFunctionAbstract", "getAttributes", &["name"], 0),
+    ("ReflectionFunction", "__construct", &["function"], 1),
+    ("ReflectionFunction", "invoke", &["...args"], 0),
+    ("ReflectionFunction", "invokeArgs", &["args"], 0),
+    (
+        "ReflectionMethod",
+        "__construct",
+        &["objectOrMethod", "method"],
+        1,
+    ),
+    ("ReflectionMethod", "getDeclaringClass", &[], 0),
+    ("ReflectionMethod", "isPublic", &[], 0),
+    ("ReflectionMethod", "isPrivate", &[], 0),
+    ("ReflectionMethod", "isProtected", &[], 0),
+    ("ReflectionMethod", "isStatic", &[], 0),
+    ("ReflectionMethod", "isAbstract", &[], 0),
+    ("ReflectionMethod", "isFinal", &[], 0),
+    ("ReflectionMethod", "isConstructor", &[], 0),
+    ("ReflectionMethod", "invoke", &["object", "...args"], 0),
+    ("ReflectionMethod", "invokeArgs", &["object", "args"], 0),
+    ("ReflectionMethod", "setAccessible", &["accessible"], 1),
+    ("ReflectionParameter", "getName", &[], 0),
+    ("ReflectionParameter", "getPosition", &[], 0),
+    ("ReflectionParameter", "isOptional", &[], 0),
+    ("ReflectionParameter", "isVariadic", &[], 0),
+    ("ReflectionParameter", "isPromoted", &[], 0),
+    ("ReflectionParameter", "isDefaultValueAvailable", &[], 0),
+    ("ReflectionParameter", "getDefaultValue", &[], 0),
+    ("ReflectionParameter", "hasType", &[], 0),
+    ("ReflectionParameter", "getType", &[], 0),
+    ("ReflectionParameter", "allowsNull", &[], 0),
+    ("ReflectionParameter", "getDeclaringFunction", &[], 0),
+    ("ReflectionParameter", "getDeclaringClass", &[], 0),
+    ("ReflectionParameter", "getAttributes", &["name"], 0),
+    (
+        "ReflectionProperty",
+        "__construct",
+        &["class", "property"],
+        2,
+    ),
+    ("ReflectionProperty", "getName", &[], 0),
+    ("ReflectionProperty", "getValue", &["object"], 0),
+    (
+        "ReflectionProperty",
+        "setValue",
+        &["objectOrValue", "value"],
+        1,
+    ),
+    ("ReflectionProperty", "isInitialized", &["object"], 0),
+    ("ReflectionProperty", "isPublic", &[], 0),
+    ("ReflectionProperty", "isPrivate", &[], 0),
+    ("ReflectionProperty", "isProtected", &[], 0),
+    ("ReflectionProperty", "isStatic", &[], 0),
+    ("ReflectionProperty", "isReadOnly", &[], 0),
+    ("ReflectionProperty", "isPromoted", &[], 0),
+    ("ReflectionProperty", "hasDefaultValue", &[], 0),
+    ("ReflectionProperty", "getDefaultValue", &[], 0),
+    ("ReflectionProperty", "hasType", &[], 0),
+    ("ReflectionProperty", "getType", &[], 0),
+    ("ReflectionProperty", "getDeclaringClass", &[], 0),
+    ("ReflectionProperty", "getAttributes", &["name"], 0),
+    ("ReflectionProperty", "setAccessible", &["accessible"], 1),
+    ("ReflectionAttribute", "newInstance", &[], 0),
+];
+
+/// The methods that read back a property: class, method, property
+const GETTERS: &[(&str, &str, &str)] = &[
+    ("ReflectionType", "allowsNull", "__nullable"),
+    ("ReflectionType", "__toString", "__string"),
+    ("ReflectionNamedType", "getName", "__name"),
+    ("ReflectionNamedType", "isBuiltin", "__builtin"),
+    ("ReflectionUnionType", "getTypes", "__types"),
+    ("ReflectionIntersectionType", "getTypes", "__types"),
+    ("ReflectionAttribute", "getName", "__name"),
+    ("ReflectionAttribute", "getArguments", "__arguments"),
+];
+
+pub fn register_reflection_classes(classes: &mut NameTable<Arc<CompiledClass>>) {
+    for (name, parent, public, internal) in CLASSES {
+        let mut class = CompiledClass::new(name.to_string());
+        class.parent = parent.map(str::to_string);
+        class.is_abstract = matches!(*name, "ReflectionFunctionAbstract" | "ReflectionType");
+        let properties = public
+            .iter()
+            .map(|prop| (prop, Visibility::Public))
+            .chain(internal.iter().map(|prop| (prop, Visibility::Private)));
+        for (prop_name, visibility) in properties {
+            class.properties.push(CompiledProperty {
+                name: prop_name.to_string(),
+                visibility,
+                write_visibility: None,
+                default: Some(Value::Null),
+                readonly: false,
+                is_static: false,
+                type_hint: None,
+                attributes: Vec::new(),
+                get_hook: None,
+                set_hook: None,
+            });
+        }
+        classes.insert(name.to_string(), Arc::new(class));
+    }
+
+    for (class_name, method_name, params, required) in NATIVE_METHODS {
+        let mut method = CompiledFunction::new(format!("{}::{}", class_name, method_name));
+        method.param_count = params.len() as u8;
+        method.required_param_count = *required;
+        method.is_variadic = params.iter().any(|param| param.starts_with("..."));
+        method.local_count = params.len() as u16 + 1;
+        method.local_names = std::iter::once("this")
+            .chain(params.iter().map(|param| param.trim_start_matches("...")))
+            .map(str::to_string)
+            .collect();
+        method.strings.push(method.name.clone());
+        method.bytecode.push(Opcode::Reflect(0));
+        method.bytecode.push(Opcode::Return);
+        add_method(classes, class_name, method_name, method);
+    }
+
+    for (class_name, method_name, prop_name) in GETTERS {
+        let mut getter = CompiledFunction::new(format!("{}::{}", class_name, method_name));
+        getter.local_count = 1;
+        getter.local_names = vec!["this".to_string()];
+        getter.strings.push(prop_name.to_string());
+        getter.bytecode.push(Opcode::LoadThis);
+        getter.bytecode.push(Opcode::LoadProperty(0));
+        getter.bytecode.push(Opcode::Return);
+        add_method(classes, class_name, method_name, getter);
+    }
+}
+
+fn add_method(
+    classes: &mut NameTable<Arc<CompiledClass>>,
+    class_name: &str,
+    method_name: &str,
+    method: CompiledFunction,
+) {
+    if let Some(class) = classes.get_mut(class_name) {
+        let class = Arc::make_mut(class);
+        class.method_order.push(method_name.to_string());
+        class
+            .methods
+            .insert(method_name.to_string(), Arc::new(method));
+    }
+}
@@ -0,0 +1,103 @@
+//! Sandboxing policy for running untrusted scripts
+//!
+//! A `Sandbox` disables functions by name (`disable_functions`) and
+//! confines file access to base directories (`open_basedir`). Both are
+//! enforced where every script reaches the host: disabled functions in
+//! `VM::call_reflection_or_builtin`, the single path to native functions,
+//! and directories by wrapping the VM's filesystem, which file built-ins,
+//! `require` and autoloading all go through.
+//!
+//! Scripts cannot loosen the policy: `ini_set()` refuses both directives.
+
+use super::VM;
+use crate::runtime::filesystem::{Filesystem, RestrictedFilesystem, PATH_LIST_SEPARATOR};
+use crate::vm::error::VmError;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// What scripts run by a VM may not do
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    /// Lowercase names of disabled functions
+    disabled_functions: HashSet<String>,
+    /// Directories files must be under, or None for no restriction
+    base_dirs: Option<Vec<PathBuf>>,
+}
+
+impl Sandbox {
+    /// A policy that allows everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy from the `disable_functions` (comma-separated) and
+    /// `open_basedir` (path list) directives; empty values allow all
+    pub fn from_directives(disable_functions: &str, open_basedir: &str) -> Self {
+        let mut sandbox = Self::new();
+        for name in disable_functions.split(',').map(str::trim) {
+            if !name.is_empty() {
+                sandbox = sandbox.disable_function(name);
+            }
+        }
+        for dir in open_basedir.split(PATH_LIST_SEPARATOR) {
+            if !dir.is_empty() {
+                sandbox = sandbox.allow_dir(dir);
+            }
+        }
+        sandbox
+    }
+
+    /// Make the native function `name` unavailable to scripts
+    pub fn disable_function(mut self, name: &str) -> Self {
+        self.disabled_functions.insert(name.to_lowercase());
+        self
+    }
+
+    /// Allow file access under `dir`; once any directory is allowed,
+    /// files elsewhere are off limits
+    pub fn allow_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.base_dirs.get_or_insert_with(Vec::new).push(dir.into());
+        self
+    }
+
+    pub fn is_disabled(&self, name: &str) -> bool {
+        !self.disabled_functions.is_empty()
+            && self.disabled_functions.contains(&name.to_lowercase())
+    }
+
+    /// `filesystem` confined to the allowed directories, if there are any
+    pub fn restrict(&self, filesystem: Box<dyn Filesystem>) -> Box<dyn Filesystem> {
+        match &self.base_dirs {
+            Some(dirs) => Box::new(RestrictedFilesystem::new(filesystem, dirs)),
+            None => filesystem,
+        }
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Apply `sandbox` to everything the VM runs from now on
+    ///
+    /// The current filesystem, and any set later, is confined to the
+    /// sandbox's directories. Set the sandbox once: restrictions from an
+    /// earlier one stay on the filesystem.
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        let filesystem = std::mem::replace(
+            &mut self.filesystem,
+            Box::new(crate::runtime::filesystem::MemoryFilesystem::default()),
+        );
+        self.filesystem = sandbox.restrict(filesystem);
+        self.sandbox = sandbox;
+    }
+
+    /// Fail if the sandbox disables the native function `name`
+    pub(crate) fn check_sandbox(&self, name: &str) -> Result<(), VmError> {
+        if self.sandbox.is_disabled(name) {
+            return Err(VmError::DisabledFunction(format!(
+                "{}() has been disabled for security reasons",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//! Shutdown functions
+//!
+//! `register_shutdown_function()` queues callbacks that run once the
+//! script's main code is over, however it ended: after its last
+//! statement, after `exit()`, or after a fatal error or an uncaught
+//! exception, which `error_get_last()` then describes. The fatal error
+//! itself is still what `VM::execute` returns:
+//!
+//! ```
+//! use vhp::vm::{RuntimeError, VM};
+//!
+//! let source = "<?php
+//! function report() { echo error_get_last()['message']; }
+//! register_shutdown_function('report');
+//! missing();";
+//! let compilation = vhp::compile(source, "a.php").unwrap();
+//! let mut output = Vec::new();
+//! let mut vm = VM::new(&mut output);
+//! let main = vm.load(compilation);
+//! let result = vm.execute(main);
+//! drop(vm);
+//! assert!(matches!(result, Err(RuntimeError::UndefinedFunction { .. })));
+//! assert!(String::from_utf8(output).unwrap().contains("missing"));
+//! ```
+//!
+//! Handlers run in the order they were registered, including those
+//! registered by another handler. One that calls `exit()` or fails stops
+//! the rest, as in PHP.
+
+use super::error::RuntimeError;
+use super::notices::{LastError, E_ERROR};
+use super::opcode::{CompiledFunction, Opcode};
+use super::VM;
+use crate::runtime::builtins::native;
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use std::io::Write;
+use std::sync::Arc;
+
+/// The code that calls a handler: the callback sits on the stack above
+/// its `arg_count` arguments
+fn handler_call(arg_count: usize) -> CompiledFunction {
+    let mut function = CompiledFunction::new("{shutdown}".to_string());
+    function
+        .bytecode
+        .push(Opcode::CallCallable(arg_count as u8));
+    function
+}
+
+impl<W: Write> VM<W> {
+    /// `register_shutdown_function()`: queue a callback and the arguments
+    /// to call it with
+    pub(crate) fn register_shutdown_function(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        native::check_arity("register_shutdown_function", args.len(), 1, None)?;
+        if !matches!(
+            &args[0],
+            Value::String(_) | Value::Closure(_) | Value::Array(_)
+        ) {
+            return Err(VmError::type_error(
+                "register_shutdown_function(): Argument #1 ($callback) must be a valid callback",
+            ));
+        }
+        self.shutdown_functions
+            .push((args[0].clone(), args[1..].to_vec()));
+        Ok(Value::Null)
+    }
+
+    /// Run the queued shutdown functions after the main code ended with
+    /// `result`, which is kept unless it was a success or `exit()` and a
+    /// handler exits or fails
+    pub(crate) fn run_shutdown_functions(
+        &mut self,
+        result: Result<Value, RuntimeError>,
+    ) -> Result<Value, RuntimeError> {
+        if let Err(error) = &result {
+            self.record_fatal_error(error);
+        }
+        let mut result = result;
+        while !self.shutdown_functions.is_empty() {
+            let (callback, args) = self.shutdown_functions.remove(0);
+            self.reset_execution_state();
+            let call = handler_call(args.len());
+            self.stack.extend(args);
+            self.stack.push(callback);
+            let Err(error) = self
+                .execute_contained(Arc::new(call))
+                .map_err(|e| self.runtime_error(e))
+            else {
+                continue;
+            };
+            self.shutdown_functions.clear();
+            self.record_fatal_error(&error);
+            if matches!(result, Ok(_) | Err(RuntimeError::Exit(_))) {
+                result = Err(error);
+            }
+        }
+        result
+    }
+
+    /// Drop what the code that just ended left behind (it may have
+    /// stopped midway)
+    fn reset_execution_state(&mut self) {
+        self.stack.clear();
+        self.frames.clear();
+        self.loops.clear();
+        self.running_generators.clear();
+        self.running_fibers.clear();
+        self.nested_runs = 0;
+        self.event_loop.clear_running();
+        self.error_location = None;
+        self.silence_depth = 0;
+    }
+
+    /// Keep a fatal error for `error_get_last()`
+    fn record_fatal_error(&mut self, error: &RuntimeError) {
+        if matches!(error, RuntimeError::Exit(_)) {
+            return;
+        }
+        let (file, line) = error
+            .location()
+            .map_or((String::from("Unknown"), 0), |location| {
+                (location.file.clone(), location.line)
+            });
+        self.last_error = Some(LastError {
+            kind: E_ERROR,
+            message: error.message(),
+            file,
+            line,
+        });
+    }
+}
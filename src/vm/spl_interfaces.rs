@@ -5,13 +5,13 @@
 //! Countable, ArrayAccess, and Stringable.
 
 use crate::vm::class::CompiledInterface;
+use crate::vm::name_table::NameTable;
 use lazy_static::lazy_static;
-use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 lazy_static! {
-    pub static ref BUILTIN_INTERFACES: Mutex<HashMap<String, Arc<CompiledInterface>>> =
-        Mutex::new(HashMap::new());
+    pub static ref BUILTIN_INTERFACES: Mutex<NameTable<Arc<CompiledInterface>>> =
+        Mutex::new(NameTable::new());
 }
 
 pub fn initialize_builtin_interfaces() {
@@ -24,7 +24,7 @@ pub fn initialize_builtin_interfaces() {
     register_stringable_interface(&mut interfaces);
 }
 
-pub fn register_builtin_interfaces(interfaces: &mut HashMap<String, Arc<CompiledInterface>>) {
+pub fn register_builtin_interfaces(interfaces: &mut NameTable<Arc<CompiledInterface>>) {
     let mut builtin = BUILTIN_INTERFACES.lock().unwrap();
     register_traversable_interface(&mut builtin);
     register_iterator_interface(&mut builtin);
@@ -35,14 +35,14 @@ pub fn register_builtin_interfaces(interfaces: &mut HashMap<String, Arc<Compiled
     interfaces.extend(builtin.clone());
 }
 
-fn register_traversable_interface(interfaces: &mut HashMap<String, Arc<CompiledInterface>>) {
+fn register_traversable_interface(interfaces: &mut NameTable<Arc<CompiledInterface>>) {
     let traversable = CompiledInterface::new("Traversable".to_string());
     let traversable_arc = Arc::new(traversable);
     interfaces.insert("Traversable".to_string(), Arc::clone(&traversable_arc));
     interfaces.insert("\\Traversable".to_string(), traversable_arc);
 }
 
-fn register_iterator_interface(interfaces: &mut HashMap<String, Arc<CompiledInterface>>) {
+fn register_iterator_interface(interfaces: &mut NameTable<Arc<CompiledInterface>>) {
     let mut iterator = CompiledInterface::new("Iterator".to_string());
     iterator.parents = vec!["Traversable".to_string()];
     iterator.method_signatures = vec![
@@ -57,7 +57,7 @@ fn register_iterator_interface(interfaces: &mut HashMap<String, Arc<CompiledInte
     interfaces.insert("\\Iterator".to_string(), iterator_arc);
 }
 
-fn register_iterator_aggregate_interface(interfaces: &mut HashMap<String, Arc<CompiledInterface>>) {
+fn register_iterator_aggregate_interface(interfaces: &mut NameTable<Arc<CompiledInterface>>) {
     let mut aggregate = CompiledInterface::new("IteratorAggregate".to_string());
     aggregate.parents = vec!["Traversable".to_string()];
     aggregate.method_signatures = vec![("getIterator".to_string(), 0)];
@@ -66,7 +66,7 @@ fn register_iterator_aggregate_interface(interfaces: &mut HashMap<String, Arc<Co
     interfaces.insert("\\IteratorAggregate".to_string(), aggregate_arc);
 }
 
-fn register_countable_interface(interfaces: &mut HashMap<String, Arc<CompiledInterface>>) {
+fn register_countable_interface(interfaces: &mut NameTable<Arc<CompiledInterface>>) {
     let mut countable = CompiledInterface::new("Countable".to_string());
     countable.method_signatures = vec![("count".to_string(), 0)];
     let countable_arc = Arc::new(countable);
@@ -74,7 +74,7 @@ fn register_countable_interface(interfaces: &mut HashMap<String, Arc<CompiledInt
     interfaces.insert("\\Countable".to_string(), countable_arc);
 }
 
-fn register_array_access_interface(interfaces: &mut HashMap<String, Arc<CompiledInterface>>) {
+fn register_array_access_interface(interfaces: &mut NameTable<Arc<CompiledInterface>>) {
     let mut access = CompiledInterface::new("ArrayAccess".to_string());
     access.method_signatures = vec![
         ("offsetExists".to_string(), 1),
@@ -87,7 +87,7 @@ fn register_array_access_interface(interfaces: &mut HashMap<String, Arc<Compiled
     interfaces.insert("\\ArrayAccess".to_string(), access_arc);
 }
 
-fn register_stringable_interface(interfaces: &mut HashMap<String, Arc<CompiledInterface>>) {
+fn register_stringable_interface(interfaces: &mut NameTable<Arc<CompiledInterface>>) {
     let mut stringable = CompiledInterface::new("Stringable".to_string());
     stringable.method_signatures = vec![("__toString".to_string(), 0)];
     let stringable_arc = Arc::new(stringable);
@@ -0,0 +1,116 @@
+//! Runtime counters for performance regression tracking
+//!
+//! While `Stats` are enabled the VM counts retired opcodes, user and
+//! native function calls and array allocations, and tracks the deepest
+//! operand stack and call stack it reached. Hosts read them with
+//! `VM::stats`, scripts with `vhp_engine_stats()`, and `vhp --stats`
+//! prints them when the script ends. Disabled, they cost one branch per
+//! opcode.
+
+use super::opcode::Opcode;
+use super::VM;
+use crate::runtime::{ArrayKey, Value};
+use crate::vm::error::VmError;
+use std::io::{self, Write};
+
+/// Counters collected since `VM::enable_stats`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Opcodes executed
+    pub opcodes: u64,
+    /// Calls to user-defined functions, methods and closures
+    pub calls: u64,
+    /// Calls to built-in and extension functions
+    pub native_calls: u64,
+    /// Arrays created by array literals or returned by native functions
+    pub array_allocations: u64,
+    /// Most values on the operand stack at once
+    pub peak_stack_depth: usize,
+    /// Most call frames at once
+    pub peak_frame_depth: usize,
+}
+
+impl Stats {
+    /// The counters as name/value pairs, in a fixed order
+    pub fn counters(&self) -> [(&'static str, u64); 6] {
+        [
+            ("opcodes", self.opcodes),
+            ("calls", self.calls),
+            ("native_calls", self.native_calls),
+            ("array_allocations", self.array_allocations),
+            ("peak_stack_depth", self.peak_stack_depth as u64),
+            ("peak_frame_depth", self.peak_frame_depth as u64),
+        ]
+    }
+
+    /// Write one `name value` line per counter
+    pub fn write_report<O: Write>(&self, out: &mut O) -> io::Result<()> {
+        for (name, value) in self.counters() {
+            writeln!(out, "{:<20}{}", name, value)?;
+        }
+        Ok(())
+    }
+
+    /// The counters as the array `vhp_engine_stats()` returns
+    pub fn to_value(&self) -> Value {
+        Value::Array(
+            self.counters()
+                .into_iter()
+                .map(|(name, value)| {
+                    (
+                        ArrayKey::String(name.to_string()),
+                        Value::Integer(value as i64),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// Start counting from zero
+    pub fn enable_stats(&mut self) {
+        self.stats = Some(Stats::default());
+    }
+
+    /// The counters so far, if enabled
+    pub fn stats(&self) -> Option<Stats> {
+        self.stats
+    }
+
+    /// Count an opcode about to run
+    #[inline]
+    pub(crate) fn record_opcode(&mut self, opcode: &Opcode) {
+        if let Some(stats) = &mut self.stats {
+            stats.opcodes += 1;
+            stats.peak_stack_depth = stats.peak_stack_depth.max(self.stack.len());
+            if matches!(opcode, Opcode::NewArray(_)) {
+                stats.array_allocations += 1;
+            }
+        }
+    }
+
+    /// Count a call frame about to be pushed
+    pub(crate) fn record_call(&mut self) {
+        if let Some(stats) = &mut self.stats {
+            stats.calls += 1;
+            stats.peak_frame_depth = stats.peak_frame_depth.max(self.frames.len() + 1);
+        }
+    }
+
+    /// Count a native function call that returned `result`
+    pub(crate) fn record_native_call(&mut self, result: &Result<Value, VmError>) {
+        if let Some(stats) = &mut self.stats {
+            stats.native_calls += 1;
+            if let Ok(Value::Array(_)) = result {
+                stats.array_allocations += 1;
+            }
+        }
+    }
+
+    /// `vhp_engine_stats()`: the counters so far, or false when disabled
+    pub(crate) fn engine_stats(&self) -> Value {
+        self.stats
+            .map_or(Value::Bool(false), |stats| stats.to_value())
+    }
+}
@@ -0,0 +1,238 @@
+//! Superglobals and the HTTP response
+//!
+//! `$_SERVER`, `$_ENV` and the request arrays are in `VM::globals`, where
+//! main code and functions alike find them. A VM starts with `$_ENV` and
+//! `$_SERVER` from the process environment and empty `$_GET`, `$_POST`,
+//! `$_COOKIE`, `$_FILES` and `$_REQUEST`; the host then fills in the
+//! command line (`set_cli_arguments`) or a CGI request (`set_request`).
+//!
+//! `header()` and `http_response_code()` collect the response a CGI host
+//! sends ahead of the output (see `cgi::write_response`).
+
+use super::VM;
+use crate::cgi::{self, Request};
+use crate::runtime::builtins::env;
+use crate::runtime::clock;
+use crate::runtime::{ArrayKey, PhpArray, Value};
+use crate::vm::error::VmError;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// The request arrays, empty outside a request
+const REQUEST_ARRAYS: &[&str] = &["_GET", "_POST", "_COOKIE", "_FILES", "_REQUEST"];
+
+/// The status and headers of the response to a request
+#[derive(Debug, Clone, Default)]
+pub struct Response {
+    /// The status code, if the host or the script set one
+    pub status: Option<u16>,
+    /// `Name: value` lines in the order they were set
+    pub headers: Vec<String>,
+}
+
+/// The superglobals a VM starts with
+pub(crate) fn initial_globals() -> HashMap<String, Value> {
+    let mut globals = HashMap::new();
+    let environment = env::env_array();
+    let mut server = match &environment {
+        Value::Array(entries) => entries.clone(),
+        _ => PhpArray::new(),
+    };
+    add_request_time(&mut server);
+    globals.insert("_ENV".to_string(), environment);
+    globals.insert("_SERVER".to_string(), Value::Array(server));
+    for name in REQUEST_ARRAYS {
+        globals.insert(name.to_string(), Value::Array(PhpArray::new()));
+    }
+    globals
+}
+
+/// Set `key` in `entries`, where it stays if it is there already
+fn set(entries: &mut PhpArray, key: &str, value: Value) {
+    entries.insert(ArrayKey::String(key.to_string()), value);
+}
+
+/// `REQUEST_TIME_FLOAT` and `REQUEST_TIME`: when the script started
+fn add_request_time(server: &mut PhpArray) {
+    let now = clock::since_epoch();
+    set(
+        server,
+        "REQUEST_TIME_FLOAT",
+        Value::Float(now.as_secs_f64()),
+    );
+    set(server, "REQUEST_TIME", Value::Integer(now.as_secs() as i64));
+}
+
+impl<W: Write> VM<W> {
+    /// Give the script the command line it was run with: `argv` holds the
+    /// script, then its arguments, in `$argv` and `$_SERVER['argv']`
+    ///
+    /// ```
+    /// use vhp::vm::VM;
+    ///
+    /// let mut output = Vec::new();
+    /// let mut vm = VM::new(&mut output);
+    /// vm.set_cli_arguments(&["hello.php".to_string(), "world".to_string()]);
+    /// let main = vm.load(vhp::compile("<?php echo $argc, ' ', $_SERVER['argv'][1];", "hello.php").unwrap());
+    /// vm.execute(main).unwrap();
+    /// drop(vm);
+    /// assert_eq!(output, b"2 world");
+    /// ```
+    pub fn set_cli_arguments(&mut self, argv: &[String]) {
+        let script = argv.first().cloned().unwrap_or_default();
+        let args = Value::Array(
+            argv.iter()
+                .enumerate()
+                .map(|(i, arg)| (ArrayKey::Integer(i as i64), Value::String(arg.clone())))
+                .collect(),
+        );
+        let argc = Value::Integer(argv.len() as i64);
+        let mut server = self.server_entries();
+        for name in [
+            "PHP_SELF",
+            "SCRIPT_NAME",
+            "SCRIPT_FILENAME",
+            "PATH_TRANSLATED",
+        ] {
+            set(&mut server, name, Value::String(script.clone()));
+        }
+        set(&mut server, "DOCUMENT_ROOT", Value::String(String::new()));
+        set(&mut server, "argv", args.clone());
+        set(&mut server, "argc", argc.clone());
+        self.globals
+            .insert("_SERVER".to_string(), Value::Array(server));
+        self.globals.insert("argv".to_string(), args);
+        self.globals.insert("argc".to_string(), argc);
+    }
+
+    /// Give the script the CGI request it answers: `$_SERVER` holds its
+    /// meta-variables, `$_GET`, `$_POST`, `$_COOKIE`, `$_FILES` and
+    /// `$_REQUEST` what it sends. Gives the temporary files uploads were
+    /// saved in, which the host removes when the script ends.
+    pub fn set_request(&mut self, request: &Request) -> io::Result<Vec<PathBuf>> {
+        let query = request.query();
+        let (post, uploads) = request.form();
+        let (files, paths) = cgi::save_uploads(uploads)?;
+
+        let mut server: PhpArray = request
+            .variables
+            .iter()
+            .map(|(name, value)| (ArrayKey::String(name.clone()), Value::String(value.clone())))
+            .collect();
+        let script_name = request.variable("SCRIPT_NAME").unwrap_or("");
+        let path_info = request.variable("PATH_INFO").unwrap_or("");
+        let php_self = format!("{}{}", script_name, path_info);
+        set(&mut server, "PHP_SELF", Value::String(php_self));
+        add_request_time(&mut server);
+
+        self.globals
+            .insert("_SERVER".to_string(), Value::Array(server));
+        self.globals
+            .insert("_REQUEST".to_string(), cgi::merge_request(&query, &post));
+        self.globals.insert("_GET".to_string(), query);
+        self.globals.insert("_POST".to_string(), post);
+        self.globals
+            .insert("_COOKIE".to_string(), request.cookies());
+        self.globals.insert("_FILES".to_string(), files);
+        self.response.status = Some(200);
+        Ok(paths)
+    }
+
+    /// The status and headers the script set
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    /// The elements of `$_SERVER`
+    fn server_entries(&self) -> PhpArray {
+        match self.globals.get("_SERVER") {
+            Some(Value::Array(entries)) => entries.clone(),
+            _ => PhpArray::new(),
+        }
+    }
+
+    /// header(): add a response header, replacing those of the same name
+    /// unless the second argument is false, or set the status with an
+    /// `HTTP/` status line
+    pub(crate) fn header(&mut self, args: &[Value]) -> Result<Value, VmError> {
+        let Some(header) = args.first() else {
+            return Err(VmError::argument_count_error(
+                "header() expects at least 1 argument, 0 given",
+            ));
+        };
+        let header = header.to_string_val().trim().to_string();
+        let replace = args.get(1).is_none_or(Value::to_bool);
+        let code = args.get(2).map_or(0, Value::to_int);
+
+        if header.len() >= 5 && header[..5].eq_ignore_ascii_case("HTTP/") {
+            let status = header
+                .split_whitespace()
+                .nth(1)
+                .and_then(|s| s.parse().ok());
+            if let Some(status) = status {
+                self.response.status = Some(status);
+            }
+        } else if let Some((name, _)) = header.split_once(':') {
+            let name = name.trim().to_string();
+            if replace {
+                self.remove_headers(&name);
+            }
+            let keeps_status = matches!(self.response.status, Some(201 | 300..=399));
+            if name.eq_ignore_ascii_case("location") && !keeps_status {
+                self.response.status = Some(302);
+            }
+            self.response.headers.push(header);
+        } else if !header.is_empty() {
+            return Err(VmError::fatal("header(): Header must contain a colon"));
+        }
+        if code > 0 {
+            self.response.status = Some(code as u16);
+        }
+        Ok(Value::Null)
+    }
+
+    /// header_remove(): remove the headers of the name given, or all
+    pub(crate) fn header_remove(&mut self, args: &[Value]) -> Value {
+        match args.first() {
+            Some(name) if !matches!(name, Value::Null) => {
+                self.remove_headers(&name.to_string_val());
+            }
+            _ => self.response.headers.clear(),
+        }
+        Value::Null
+    }
+
+    fn remove_headers(&mut self, name: &str) {
+        self.response.headers.retain(|header| {
+            let header_name = header.split(':').next().unwrap_or("");
+            !header_name.trim().eq_ignore_ascii_case(name.trim())
+        });
+    }
+
+    /// headers_list(): the headers set so far
+    pub(crate) fn headers_list(&self) -> Value {
+        Value::Array(
+            self.response
+                .headers
+                .iter()
+                .enumerate()
+                .map(|(i, header)| (ArrayKey::Integer(i as i64), Value::String(header.clone())))
+                .collect(),
+        )
+    }
+
+    /// http_response_code(): the status before setting it to the code
+    /// given; without a status, false, or true when setting one
+    pub(crate) fn http_response_code(&mut self, args: &[Value]) -> Value {
+        let previous = self.response.status;
+        let code = args.first().map_or(0, Value::to_int);
+        if code > 0 {
+            self.response.status = Some(code as u16);
+        }
+        match previous {
+            Some(status) => Value::Integer(status as i64),
+            None => Value::Bool(code > 0),
+        }
+    }
+}
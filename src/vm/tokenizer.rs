@@ -0,0 +1,110 @@
+//! The PhpToken class
+//!
+//! Its methods are stubs calling the `phptoken_*` functions in
+//! `runtime::builtins::tokenizer` with `$this` first, as the intl
+//! classes' do. Those functions are in `PHPTOKEN_FUNCTIONS` rather than
+//! `BUILTIN_FUNCTIONS`, so scripts don't call them. The constructor copies the properties of the
+//! token `phptoken_create()` gives onto `$this`.
+
+use crate::ast::Visibility;
+use crate::vm::class::{CompiledClass, CompiledProperty};
+use crate::vm::name_table::NameTable;
+use crate::vm::opcode::{CompiledFunction, Opcode};
+use std::sync::Arc;
+
+/// The public properties, which the constructor sets
+const PROPERTIES: &[&str] = &["id", "text", "line", "pos"];
+
+/// The methods: name, function, parameters and how many are required
+const METHODS: &[(&str, &str, &[&str], u8)] = &[
+    ("is", "phptoken_is", &["kind"], 1),
+    ("isIgnorable", "phptoken_is_ignorable", &[], 0),
+    ("getTokenName", "phptoken_get_token_name", &[], 0),
+    ("__toString", "phptoken_to_string", &[], 0),
+];
+
+/// A method of PhpToken with its parameters as locals, after `$this`
+/// unless it is static
+fn method(name: &str, params: &[&str], required: u8, is_static: bool) -> CompiledFunction {
+    let mut method = CompiledFunction::new(format!("PhpToken::{}", name));
+    method.param_count = params.len() as u8;
+    method.required_param_count = required;
+    let this = (!is_static).then_some("this");
+    method.local_names = this
+        .into_iter()
+        .chain(params.iter().copied())
+        .map(str::to_string)
+        .collect();
+    method.local_count = method.local_names.len() as u16;
+    method
+}
+
+pub fn register_tokenizer_classes(classes: &mut NameTable<Arc<CompiledClass>>) {
+    let mut class = CompiledClass::new("PhpToken".to_string());
+    class.interfaces.push("Stringable".to_string());
+    for name in PROPERTIES {
+        class.properties.push(CompiledProperty {
+            name: name.to_string(),
+            visibility: Visibility::Public,
+            write_visibility: None,
+            default: None,
+            readonly: false,
+            is_static: false,
+            type_hint: None,
+            attributes: Vec::new(),
+            get_hook: None,
+            set_hook: None,
+        });
+    }
+
+    let mut construct = method("__construct", PROPERTIES, 2, false);
+    construct.strings.push("phptoken_create".to_string());
+    construct
+        .strings
+        .extend(PROPERTIES.iter().map(|p| p.to_string()));
+    construct
+        .bytecode
+        .extend((1..=PROPERTIES.len() as u16).map(Opcode::LoadFast));
+    construct
+        .bytecode
+        .push(Opcode::CallBuiltin(0, PROPERTIES.len() as u8));
+    for index in 1..=PROPERTIES.len() as u32 {
+        construct.bytecode.extend([
+            Opcode::Dup,
+            Opcode::LoadProperty(index),
+            Opcode::StoreThisProperty(index),
+            Opcode::Pop,
+        ]);
+    }
+    construct.bytecode.extend([Opcode::Pop, Opcode::ReturnNull]);
+    class.method_order.push("__construct".to_string());
+    class
+        .methods
+        .insert("__construct".to_string(), Arc::new(construct));
+
+    let mut tokenize = method("tokenize", &["code", "flags"], 1, true);
+    tokenize.strings.push("phptoken_tokenize".to_string());
+    tokenize.bytecode.extend([
+        Opcode::LoadFast(0),
+        Opcode::LoadFast(1),
+        Opcode::CallBuiltin(0, 2),
+        Opcode::Return,
+    ]);
+    class
+        .static_methods
+        .insert("tokenize".to_string(), Arc::new(tokenize));
+
+    for (name, function, params, required) in METHODS {
+        let mut stub = method(name, params, *required, false);
+        stub.strings.push(function.to_string());
+        stub.bytecode
+            .extend((0..=params.len() as u16).map(Opcode::LoadFast));
+        stub.bytecode.extend([
+            Opcode::CallBuiltin(0, params.len() as u8 + 1),
+            Opcode::Return,
+        ]);
+        class.method_order.push(name.to_string());
+        class.methods.insert(name.to_string(), Arc::new(stub));
+    }
+    classes.insert("PhpToken".to_string(), Arc::new(class));
+}
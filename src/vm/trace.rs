@@ -0,0 +1,90 @@
+//! Opcode execution tracing
+//!
+//! When enabled, the VM writes one line per executed opcode with the frame
+//! depth, function, instruction pointer and the top of the value stack,
+//! topmost value first:
+//!
+//! ```text
+//! [2] add@2 Add | stack(4): 2, 1 ...
+//! ```
+
+use super::opcode::Opcode;
+use super::VM;
+use crate::runtime::Value;
+use std::io::Write;
+
+/// Longest string shown in a stack snapshot before it is cut
+const MAX_STRING: usize = 32;
+
+pub struct OpcodeTrace {
+    out: Box<dyn Write>,
+    /// Number of values shown from the top of the stack
+    stack_depth: usize,
+}
+
+impl<W: Write> VM<W> {
+    /// Log every executed opcode to `out`, showing at most `stack_depth`
+    /// values from the top of the stack
+    pub fn enable_opcode_trace(&mut self, out: Box<dyn Write>, stack_depth: usize) {
+        self.opcode_trace = Some(OpcodeTrace { out, stack_depth });
+    }
+
+    pub(super) fn trace_opcode(&mut self, opcode: &Opcode) {
+        let (Some(trace), Some(frame)) = (&mut self.opcode_trace, self.frames.last()) else {
+            return;
+        };
+        // The dispatch loops advance ip before executing the opcode
+        let ip = frame.ip.saturating_sub(1);
+        let shown: Vec<String> = self
+            .stack
+            .iter()
+            .rev()
+            .take(trace.stack_depth)
+            .map(brief)
+            .collect();
+        let more = if self.stack.len() > shown.len() {
+            " ..."
+        } else {
+            ""
+        };
+        // Tracing is best effort; a closed pipe must not stop the script
+        let _ = writeln!(
+            trace.out,
+            "[{}] {}@{} {:?} | stack({}): {}{}",
+            self.frames.len(),
+            frame.function.name,
+            ip,
+            opcode,
+            self.stack.len(),
+            shown.join(", "),
+            more
+        );
+    }
+}
+
+/// Short single-line form of a stack value
+fn brief(value: &Value) -> String {
+    match value {
+        Value::Reference(r) => brief(&r.get()),
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Integer(n) => n.to_string(),
+        Value::Float(f) => format!("{:?}", f),
+        Value::String(s) if s.chars().count() > MAX_STRING => {
+            let cut: String = s.chars().take(MAX_STRING).collect();
+            format!("{:?}...", cut)
+        }
+        Value::String(s) => format!("{:?}", s),
+        Value::Array(items) => format!("array({})", items.len()),
+        Value::Object(object) => format!("object({})", object.class_name()),
+        Value::Exception(exception) => format!("object({})", exception.class_name),
+        Value::EnumCase {
+            enum_name,
+            case_name,
+            ..
+        } => format!("{}::{}", enum_name, case_name),
+        Value::Closure(_) => "Closure".to_string(),
+        Value::Fiber(_) => "Fiber".to_string(),
+        Value::Generator(_) => "Generator".to_string(),
+    }
+}
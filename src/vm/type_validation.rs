@@ -31,21 +31,32 @@ impl<W: Write> VM<W> {
                     .iter()
                     .all(|t| self.value_matches_type_strict(value, t))
             }),
-            TypeHint::Class(class_name) => {
-                if let Value::Object(obj) = value {
-                    self.is_instance_of(&obj.class_name, class_name)
-                } else {
-                    false
-                }
-            }
+            TypeHint::Class(class_name) => match value {
+                Value::Object(obj) => self.is_instance_of(obj.class_name(), class_name),
+                Value::EnumCase { enum_name, .. } => self.is_instance_of(enum_name, class_name),
+                _ => false,
+            },
             TypeHint::Void => false,
             TypeHint::Never => false,
             TypeHint::Static => false,
-            TypeHint::SelfType => false,
+            TypeHint::SelfType => self.is_current_class(value),
             TypeHint::ParentType => false,
         }
     }
 
+    /// Whether the value is an instance of the class of the running
+    /// method, what `self` resolves to in a trait's methods
+    fn is_current_class(&self, value: &Value) -> bool {
+        let Some(class) = self.get_current_class() else {
+            return false;
+        };
+        match value {
+            Value::Object(obj) => self.is_instance_of(obj.class_name(), &class),
+            Value::EnumCase { enum_name, .. } => self.is_instance_of(enum_name, &class),
+            _ => false,
+        }
+    }
+
     /// Helper for strict type matching (no coercion) - used for return types
     pub(crate) fn value_matches_simple_type_strict(&self, value: &Value, type_name: &str) -> bool {
         match (type_name, value) {
@@ -59,16 +70,15 @@ impl<W: Write> VM<W> {
             ("object", Value::Fiber(_)) => true,
             ("object", Value::Closure(_)) => true,
             ("object", Value::EnumCase { .. }) => true,
-            ("callable", Value::Closure(_)) => true,
-            ("callable", Value::String(_)) => true,
+            ("callable", _) => self.is_callable_value(value),
             ("iterable", Value::Array(_)) => true,
             ("null", Value::Null) => true,
             ("mixed", _) => true,
             _ => {
                 if let Value::Object(obj) = value {
-                    self.is_instance_of(&obj.class_name, type_name)
+                    self.is_instance_of(obj.class_name(), type_name)
                 } else if let Value::EnumCase { enum_name, .. } = value {
-                    enum_name == type_name
+                    self.is_instance_of(enum_name, type_name)
                 } else {
                     false
                 }
@@ -90,17 +100,15 @@ impl<W: Write> VM<W> {
             TypeHint::DNF(intersections) => intersections
                 .iter()
                 .any(|group| group.iter().all(|t| self.value_matches_type(value, t))),
-            TypeHint::Class(class_name) => {
-                if let Value::Object(obj) = value {
-                    self.is_instance_of(&obj.class_name, class_name)
-                } else {
-                    false
-                }
-            }
+            TypeHint::Class(class_name) => match value {
+                Value::Object(obj) => self.is_instance_of(obj.class_name(), class_name),
+                Value::EnumCase { enum_name, .. } => self.is_instance_of(enum_name, class_name),
+                _ => false,
+            },
             TypeHint::Void => false,
             TypeHint::Never => false,
             TypeHint::Static => false,
-            TypeHint::SelfType => false,
+            TypeHint::SelfType => self.is_current_class(value),
             TypeHint::ParentType => false,
         }
     }
@@ -129,8 +137,7 @@ impl<W: Write> VM<W> {
             ("object", Value::Fiber(_)) => true,
             ("object", Value::Closure(_)) => true,
             ("object", Value::EnumCase { .. }) => true,
-            ("callable", Value::Closure(_)) => true,
-            ("callable", Value::String(_)) => true,
+            ("callable", _) => self.is_callable_value(value),
             ("iterable", Value::Array(_)) => true,
             ("mixed", _) => true,
             ("null", Value::Null) => true,
@@ -192,6 +199,7 @@ impl<W: Write> VM<W> {
     /// Get the type name for error messages
     pub(crate) fn get_value_type_name(&self, value: &Value) -> &'static str {
         match value {
+            Value::Reference(r) => self.get_value_type_name(&r.get()),
             Value::Null => "null",
             Value::Bool(_) => "bool",
             Value::Integer(_) => "int",
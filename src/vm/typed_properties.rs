@@ -0,0 +1,144 @@
+//! Typed properties
+//!
+//! A write to a property declared with a type must match it: outside
+//! `strict_types` a scalar is coerced the way an argument is, and any
+//! other mismatch is a TypeError. A typed property without a default
+//! starts out uninitialized, absent from the instance until something
+//! assigns it, and reading it before then is an Error.
+
+use crate::ast::TypeHint;
+use crate::runtime::Value;
+use crate::vm::class::CompiledProperty;
+use crate::vm::error::VmError;
+use crate::vm::VM;
+use std::io::Write;
+
+/// How a property is read
+#[derive(Clone, Copy)]
+pub enum PropertyFetch {
+    Read,
+    /// For `??` and `??=`
+    Quiet,
+    /// For a write to one of its elements
+    Write,
+}
+
+/// Whether a new instance leaves the property out until it is assigned
+pub(crate) fn starts_uninitialized(prop: &CompiledProperty) -> bool {
+    prop.type_hint.is_some() && prop.default.is_none() && !prop.is_static
+}
+
+impl<W: Write> VM<W> {
+    /// The type of `class_name`'s property `prop_name`, if the class or
+    /// a parent declares it with one, and the class declaring it
+    fn property_type(&self, class_name: &str, prop_name: &str) -> Option<(String, TypeHint)> {
+        let mut current = Some(class_name.to_string());
+        while let Some(name) = current {
+            let class = self.classes.get(&name)?;
+            if let Some(prop) = class.properties.iter().find(|p| p.name == prop_name) {
+                return prop.type_hint.clone().map(|hint| (name, hint));
+            }
+            current = class.parent.clone();
+        }
+        None
+    }
+
+    /// `hint` with `self`, `parent` and `static` replaced by the classes
+    /// they name for a property `declaring` declares on a `class_name`
+    fn resolve_property_type(&self, hint: TypeHint, declaring: &str, class_name: &str) -> TypeHint {
+        let resolve = |hint| self.resolve_property_type(hint, declaring, class_name);
+        match hint {
+            TypeHint::SelfType => TypeHint::Class(declaring.to_string()),
+            TypeHint::Static => TypeHint::Class(class_name.to_string()),
+            TypeHint::ParentType => match self.classes.get(declaring) {
+                Some(class) => TypeHint::Class(class.parent.clone().unwrap_or_default()),
+                None => TypeHint::ParentType,
+            },
+            TypeHint::Nullable(inner) => TypeHint::Nullable(Box::new(resolve(*inner))),
+            TypeHint::Union(types) => TypeHint::Union(types.into_iter().map(resolve).collect()),
+            hint => hint,
+        }
+    }
+
+    /// The value a write of `value` to `class_name`'s property
+    /// `prop_name` stores: as it is for an untyped property, coerced to a
+    /// scalar type outside `strict_types`, or a TypeError
+    pub(crate) fn check_property_assignment(
+        &self,
+        class_name: &str,
+        prop_name: &str,
+        value: Value,
+    ) -> Result<Value, VmError> {
+        let Some((declaring, hint)) = self.property_type(class_name, prop_name) else {
+            return Ok(value);
+        };
+        let hint = self.resolve_property_type(hint, &declaring, class_name);
+        let strict =
+            self.current_frame().function.strict_types || self.requires_strict_type_check(&hint);
+        // Null is never coerced, not even to bool
+        let matches = if strict || matches!(value, Value::Null) {
+            self.value_matches_type_strict(&value, &hint)
+        } else {
+            self.value_matches_type(&value, &hint)
+        };
+        if !matches {
+            let given = match &value {
+                Value::Object(instance) => instance.class_name().to_string(),
+                value => self.get_value_type_name(value).to_string(),
+            };
+            return Err(VmError::type_error(format!(
+                "Cannot assign {} to property {}::${} of type {}",
+                given,
+                declaring,
+                prop_name,
+                self.format_type_hint(&hint)
+            )));
+        }
+        // An int stored in a float property becomes a float even in
+        // strict mode
+        let is_float = |hint: &TypeHint| matches!(hint, TypeHint::Simple(name) if name == "float");
+        let int_to_float = matches!(value, Value::Integer(_))
+            && match &hint {
+                TypeHint::Nullable(inner) => is_float(inner),
+                hint => is_float(hint),
+            };
+        if strict && !int_to_float {
+            return Ok(value);
+        }
+        Ok(self.coerce_value_to_type(value, &hint))
+    }
+
+    /// What reading `class_name`'s property `prop_name` gives when the
+    /// instance doesn't have it, if the property is typed and so
+    /// uninitialized: an Error, null for `??`, or for a write to one of
+    /// its elements an empty array, if the type allows one
+    pub(crate) fn read_uninitialized(
+        &self,
+        class_name: &str,
+        prop_name: &str,
+        fetch: PropertyFetch,
+    ) -> Option<Result<Value, VmError>> {
+        let (declaring, hint) = self.property_type(class_name, prop_name)?;
+        Some(match fetch {
+            PropertyFetch::Read => Err(VmError::error(format!(
+                "Typed property {}::${} must not be accessed before initialization",
+                declaring, prop_name
+            ))),
+            PropertyFetch::Quiet => Ok(Value::Null),
+            PropertyFetch::Write => {
+                let hint = self.resolve_property_type(hint, &declaring, class_name);
+                let array = Value::Array(Default::default());
+                if self.value_matches_type_strict(&array, &hint) {
+                    Ok(array)
+                } else {
+                    Err(VmError::error(format!(
+                        "Cannot auto-initialize an array inside property {}::${} of type {}",
+                        declaring,
+                        prop_name,
+                        self.format_type_hint(&hint)
+                    )))
+                }
+            }
+        })
+    }
+}
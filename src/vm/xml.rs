@@ -0,0 +1,358 @@
+//! The XML classes: SimpleXMLElement and a DOM subset
+//!
+//! Their methods are stubs: an `Xml` instruction naming the method, which
+//! the VM runs natively on the stub's `$this` and arguments. The
+//! documents live in the VM (`xml_documents`); an object holds the index
+//! of its document in `__doc` and the nodes it stands for in `__node` or
+//! `__nodes`, so every copy of it works on the same tree.
+//!
+//! A SimpleXMLElement stands for one element, or for the list of
+//! elements a property read found (`__list`): iterating the first walks
+//! its children, iterating a list walks the elements in it.
+
+mod dom;
+mod simplexml;
+
+use super::class::{CompiledClass, CompiledProperty};
+use super::opcode::{CompiledFunction, Opcode};
+use super::{ops, VM};
+use crate::ast::Visibility;
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::builtins::xml::Document;
+use crate::runtime::builtins::xml_parser::{self, XmlError};
+use crate::runtime::{ArrayKey, ObjectRef, PhpArray, Value};
+use crate::vm::error::VmError;
+use crate::vm::name_table::NameTable;
+use crate::vm::notices::Level;
+use std::io::Write;
+use std::sync::Arc;
+
+/// The XML classes: name, parent, public properties with their defaults,
+/// and internal properties
+type XmlClassSpec = (
+    &'static str,
+    Option<&'static str>,
+    &'static [(&'static str, bool)],
+    &'static [&'static str],
+);
+
+const CLASSES: &[XmlClassSpec] = &[
+    (
+        "SimpleXMLElement",
+        None,
+        &[],
+        &["__doc", "__nodes", "__list"],
+    ),
+    ("DOMNode", None, &[], &["__doc", "__node"]),
+    (
+        "DOMDocument",
+        Some("DOMNode"),
+        &[("formatOutput", false), ("preserveWhiteSpace", true)],
+        &[],
+    ),
+    ("DOMElement", Some("DOMNode"), &[], &[]),
+    ("DOMCharacterData", Some("DOMNode"), &[], &[]),
+    ("DOMText", Some("DOMCharacterData"), &[], &[]),
+    ("DOMCdataSection", Some("DOMText"), &[], &[]),
+    ("DOMComment", Some("DOMCharacterData"), &[], &[]),
+    ("DOMNodeList", None, &[], &["__doc", "__nodes"]),
+    ("DOMXPath", None, &[], &["__doc"]),
+];
+
+/// The methods run natively: class, method, parameters and how many are
+/// required
+const NATIVE_METHODS: &[(&str, &str, &[&str], u8)] = &[
+    (
+        "SimpleXMLElement",
+        "__construct",
+        &["data", "options", "dataIsURL"],
+        1,
+    ),
+    ("SimpleXMLElement", "__get", &["name"], 1),
+    ("SimpleXMLElement", "__set", &["name", "value"], 2),
+    ("SimpleXMLElement", "__isset", &["name"], 1),
+    ("SimpleXMLElement", "__toString", &[], 0),
+    ("SimpleXMLElement", "offsetGet", &["offset"], 1),
+    ("SimpleXMLElement", "count", &[], 0),
+    ("SimpleXMLElement", "getName", &[], 0),
+    ("SimpleXMLElement", "children", &[], 0),
+    ("SimpleXMLElement", "attributes", &[], 0),
+    ("SimpleXMLElement", "xpath", &["expression"], 1),
+    ("SimpleXMLElement", "asXML", &["filename"], 0),
+    ("SimpleXMLElement", "saveXML", &["filename"], 0),
+    (
+        "SimpleXMLElement",
+        "addChild",
+        &["qualifiedName", "value"],
+        1,
+    ),
+    (
+        "SimpleXMLElement",
+        "addAttribute",
+        &["qualifiedName", "value"],
+        2,
+    ),
+    ("DOMNode", "__get", &["name"], 1),
+    ("DOMNode", "__set", &["name", "value"], 2),
+    ("DOMNode", "appendChild", &["node"], 1),
+    ("DOMNode", "removeChild", &["child"], 1),
+    ("DOMNode", "insertBefore", &["node", "child"], 1),
+    ("DOMNode", "hasChildNodes", &[], 0),
+    ("DOMDocument", "__construct", &["version", "encoding"], 0),
+    ("DOMDocument", "createElement", &["localName", "value"], 1),
+    ("DOMDocument", "createTextNode", &["data"], 1),
+    ("DOMDocument", "createComment", &["data"], 1),
+    ("DOMDocument", "createCDATASection", &["data"], 1),
+    ("DOMDocument", "loadXML", &["source", "options"], 1),
+    ("DOMDocument", "load", &["filename", "options"], 1),
+    ("DOMDocument", "saveXML", &["node", "options"], 0),
+    ("DOMDocument", "save", &["filename", "options"], 1),
+    ("DOMDocument", "getElementsByTagName", &["qualifiedName"], 1),
+    ("DOMElement", "getAttribute", &["qualifiedName"], 1),
+    ("DOMElement", "setAttribute", &["qualifiedName", "value"], 2),
+    ("DOMElement", "hasAttribute", &["qualifiedName"], 1),
+    ("DOMElement", "removeAttribute", &["qualifiedName"], 1),
+    ("DOMElement", "getElementsByTagName", &["qualifiedName"], 1),
+    ("DOMNodeList", "__get", &["name"], 1),
+    ("DOMNodeList", "item", &["index"], 1),
+    ("DOMNodeList", "count", &[], 0),
+    ("DOMXPath", "__construct", &["document"], 1),
+    ("DOMXPath", "query", &["expression", "contextNode"], 1),
+];
+
+pub fn register_xml_classes(classes: &mut NameTable<Arc<CompiledClass>>) {
+    for (name, parent, public, internal) in CLASSES {
+        let mut class = CompiledClass::new(name.to_string());
+        class.parent = parent.map(str::to_string);
+        if *name == "SimpleXMLElement" || *name == "DOMNodeList" {
+            class.interfaces = vec!["Countable".to_string(), "Traversable".to_string()];
+        }
+        let properties = public
+            .iter()
+            .map(|(prop, default)| (prop, Visibility::Public, Value::Bool(*default)))
+            .chain(
+                internal
+                    .iter()
+                    .map(|prop| (prop, Visibility::Private, Value::Null)),
+            );
+        for (prop_name, visibility, default) in properties {
+            class.properties.push(CompiledProperty {
+                name: prop_name.to_string(),
+                visibility,
+                write_visibility: None,
+                default: Some(default),
+                readonly: false,
+                is_static: false,
+                type_hint: None,
+                attributes: Vec::new(),
+                get_hook: None,
+                set_hook: None,
+            });
+        }
+        classes.insert(name.to_string(), Arc::new(class));
+    }
+
+    for (class_name, method_name, params, required) in NATIVE_METHODS {
+        let mut method = CompiledFunction::new(format!("{}::{}", class_name, method_name));
+        method.param_count = params.len() as u8;
+        method.required_param_count = *required;
+        method.local_count = params.len() as u16 + 1;
+        method.local_names = std::iter::once("this")
+            .chain(params.iter().copied())
+            .map(str::to_string)
+            .collect();
+        method.strings.push(method.name.clone());
+        method.bytecode.push(Opcode::Xml(0));
+        method.bytecode.push(Opcode::Return);
+        if let Some(class) = classes.get_mut(class_name) {
+            let class = Arc::make_mut(class);
+            class.method_order.push(method_name.to_string());
+            class
+                .methods
+                .insert(method_name.to_string(), Arc::new(method));
+        }
+    }
+}
+
+/// An integer property of an XML object, as an index
+fn index_property(object: &ObjectRef, name: &str) -> Option<usize> {
+    match object.get(name) {
+        Some(Value::Integer(index)) => Some(index as usize),
+        _ => None,
+    }
+}
+
+/// The indexes an array property of an XML object lists
+fn indexes_property(object: &ObjectRef, name: &str) -> Vec<usize> {
+    match object.get(name) {
+        Some(Value::Array(items)) => items.iter().map(|(_, v)| v.to_int() as usize).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// An array of node indexes, for `__nodes`
+fn index_array(nodes: &[usize]) -> Value {
+    Value::Array(
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| (ArrayKey::Integer(i as i64), Value::Integer(n as i64)))
+            .collect(),
+    )
+}
+
+impl<W: Write> VM<W> {
+    /// Run a native method of an XML class ("Class::method") on the
+    /// current frame's `$this` and arguments
+    pub(crate) fn execute_xml(&mut self, method: &str) -> Result<(), VmError> {
+        let frame = self.current_frame();
+        let this = match frame.locals.first() {
+            Some(Value::Object(this)) => this.clone(),
+            _ => {
+                return Err(VmError::fatal(format!(
+                    "Non-static method {}() cannot be called statically",
+                    method
+                )))
+            }
+        };
+        let args = frame.locals[1..].to_vec();
+        let (class, name) = method.split_once("::").unwrap_or(("", method));
+        let result = match class {
+            "SimpleXMLElement" => self.simplexml_method(name, this, &args)?,
+            "DOMNodeList" => self.node_list_method(name, &this, &args)?,
+            "DOMXPath" => self.xpath_method(name, this, &args)?,
+            _ => self.dom_method(method, name, this, &args)?,
+        };
+        if let Some(value) = result {
+            self.stack.push(value);
+        }
+        Ok(())
+    }
+
+    /// `simplexml_load_string()`, `simplexml_load_file()` and
+    /// `simplexml_import_dom()`
+    pub(crate) fn xml_function(&mut self, name: &str, args: &[Value]) -> Result<Value, VmError> {
+        check_arity(name, args.len(), 1, Some(5))?;
+        let source = match name {
+            "simplexml_import_dom" => {
+                let node = match &args[0] {
+                    Value::Object(object) => self.dom_handle(object),
+                    _ => None,
+                };
+                return match node {
+                    Some((doc, node)) if self.xml_documents[doc].is_element(node) => {
+                        self.simplexml_object(doc, &[node], false)
+                    }
+                    Some((doc, 0)) => match self.xml_documents[doc].root() {
+                        Some(root) => self.simplexml_object(doc, &[root], false),
+                        None => Ok(Value::Null),
+                    },
+                    _ => Err(VmError::type_error(
+                        "simplexml_import_dom(): Argument #1 ($node) must be of type DOMNode",
+                    )),
+                };
+            }
+            "simplexml_load_file" => {
+                let filename = args[0].to_string_val();
+                match self.filesystem.read_to_string(&filename) {
+                    Ok(source) => source,
+                    Err(_) => {
+                        self.raise(
+                            Level::Warning,
+                            &format!(
+                                "{}(): I/O warning : failed to load external entity \"{}\"",
+                                name, filename
+                            ),
+                        )?;
+                        return Ok(Value::Bool(false));
+                    }
+                }
+            }
+            _ => args[0].to_string_val(),
+        };
+        match self.load_xml(name, &source, true)? {
+            Some(doc) => match self.xml_documents[doc].root() {
+                Some(root) => self.simplexml_object(doc, &[root], false),
+                None => Ok(Value::Bool(false)),
+            },
+            None => Ok(Value::Bool(false)),
+        }
+    }
+
+    /// Parse a document into the VM and give its index, or warn about
+    /// the error as `function()` and give `None`
+    fn load_xml(
+        &mut self,
+        function: &str,
+        source: &str,
+        keep_blanks: bool,
+    ) -> Result<Option<usize>, VmError> {
+        match xml_parser::parse(source, keep_blanks) {
+            Ok(document) => Ok(Some(self.add_document(document))),
+            Err(error) => {
+                self.warn_xml_error(function, &error)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn warn_xml_error(&mut self, function: &str, error: &XmlError) -> Result<(), VmError> {
+        self.raise(Level::Warning, &format!("{}(): {}", function, error))
+    }
+
+    fn add_document(&mut self, document: Document) -> usize {
+        self.xml_documents.push(document);
+        self.xml_documents.len() - 1
+    }
+
+    /// A new object of an XML class with the given properties
+    fn xml_object(
+        &mut self,
+        class: &str,
+        properties: Vec<(&str, Value)>,
+    ) -> Result<Value, VmError> {
+        ops::execute_new_object(self, class.to_string())?;
+        let object = self.stack.pop().ok_or_else(VmError::stack_underflow)?;
+        if let Value::Object(instance) = &object {
+            for (name, value) in properties {
+                instance.set(name, value);
+            }
+        }
+        Ok(object)
+    }
+
+    /// What a foreach loop over an XML object walks: the elements of a
+    /// SimpleXMLElement list or the children of an element, by name, or
+    /// the nodes of a DOMNodeList
+    pub(crate) fn xml_iteration(&mut self, object: &ObjectRef) -> Result<Option<Value>, VmError> {
+        let Some(doc) = index_property(object, "__doc") else {
+            return Ok(None);
+        };
+        let nodes = indexes_property(object, "__nodes");
+        let mut items = Vec::new();
+        match object.class_name() {
+            "SimpleXMLElement" => {
+                let elements = if object.get("__list").is_some_and(|value| value.to_bool()) {
+                    nodes
+                } else {
+                    nodes
+                        .first()
+                        .map(|&n| self.xml_documents[doc].child_elements(n, None))
+                        .unwrap_or_default()
+                };
+                for element in elements {
+                    let name = self.xml_documents[doc].name(element).to_string();
+                    let value = self.simplexml_object(doc, &[element], false)?;
+                    items.push((ArrayKey::String(name), value));
+                }
+            }
+            "DOMNodeList" => {
+                for (i, node) in nodes.into_iter().enumerate() {
+                    items.push((ArrayKey::Integer(i as i64), self.node_object(doc, node)?));
+                }
+            }
+            _ => return Ok(None),
+        }
+        Ok(Some(Value::Array(PhpArray::walk(items))))
+    }
+}
@@ -0,0 +1,452 @@
+//! The DOM classes' methods
+//!
+//! DOMDocument, DOMElement and the character data nodes share DOMNode's
+//! properties, read through `__get`: `nodeName`, `nodeValue`,
+//! `textContent`, the parent, child and sibling links, and the document's
+//! `documentElement`. `getElementsByTagName()`, `childNodes` and
+//! `DOMXPath::query()` give a DOMNodeList.
+
+use super::{index_array, index_property, indexes_property};
+use crate::runtime::builtins::xml::{Document, NodeKind};
+use crate::runtime::builtins::xml_parser;
+use crate::runtime::builtins::xpath::{self, Item};
+use crate::runtime::{ObjectRef, Value};
+use crate::vm::error::VmError;
+use crate::vm::notices::Level;
+use crate::vm::{ops, VM};
+use std::io::Write;
+
+/// The DOM class of a node
+fn node_class(kind: &NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Document => "DOMDocument",
+        NodeKind::Element { .. } => "DOMElement",
+        NodeKind::Text(_) => "DOMText",
+        NodeKind::CData(_) => "DOMCdataSection",
+        NodeKind::Comment(_) => "DOMComment",
+        NodeKind::ProcessingInstruction { .. } => "DOMNode",
+    }
+}
+
+/// DOM's `nodeType` of a node
+fn node_type(kind: &NodeKind) -> i64 {
+    match kind {
+        NodeKind::Element { .. } => 1,
+        NodeKind::Text(_) => 3,
+        NodeKind::CData(_) => 4,
+        NodeKind::ProcessingInstruction { .. } => 7,
+        NodeKind::Comment(_) => 8,
+        NodeKind::Document => 9,
+    }
+}
+
+impl<W: Write> VM<W> {
+    /// The document and node a DOM object stands for
+    pub(super) fn dom_handle(&self, object: &ObjectRef) -> Option<(usize, usize)> {
+        let doc = index_property(object, "__doc")?;
+        let node = index_property(object, "__node")?;
+        (doc < self.xml_documents.len() && node < self.xml_documents[doc].nodes.len())
+            .then_some((doc, node))
+    }
+
+    /// The DOM object of a node
+    pub(super) fn node_object(&mut self, doc: usize, node: usize) -> Result<Value, VmError> {
+        let class = node_class(&self.xml_documents[doc].nodes[node].kind);
+        self.xml_object(
+            class,
+            vec![
+                ("__doc", Value::Integer(doc as i64)),
+                ("__node", Value::Integer(node as i64)),
+            ],
+        )
+    }
+
+    fn optional_node_object(&mut self, doc: usize, node: Option<usize>) -> Result<Value, VmError> {
+        match node {
+            Some(node) => self.node_object(doc, node),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn node_list(&mut self, doc: usize, nodes: &[usize]) -> Result<Value, VmError> {
+        self.xml_object(
+            "DOMNodeList",
+            vec![
+                ("__doc", Value::Integer(doc as i64)),
+                ("__nodes", index_array(nodes)),
+            ],
+        )
+    }
+
+    /// The node a DOM object argument of `method` stands for, in `doc`
+    fn node_argument(
+        &mut self,
+        method: &str,
+        doc: usize,
+        arg: &Value,
+    ) -> Result<Option<usize>, VmError> {
+        let handle = match arg {
+            Value::Object(object) => self.dom_handle(object),
+            _ => None,
+        };
+        match handle {
+            Some((node_doc, node)) if node_doc == doc => Ok(Some(node)),
+            Some(_) => {
+                ops::throw_error(self, "DOMException", "Wrong Document Error")?;
+                Ok(None)
+            }
+            None => Err(VmError::type_error(format!(
+                "{}(): Argument #1 ($node) must be of type DOMNode, {} given",
+                method,
+                arg.type_name()
+            ))),
+        }
+    }
+
+    pub(super) fn dom_method(
+        &mut self,
+        method: &str,
+        name: &str,
+        this: ObjectRef,
+        args: &[Value],
+    ) -> Result<Option<Value>, VmError> {
+        if method == "DOMDocument::__construct" {
+            let version = match args.first() {
+                Some(Value::Null) | None => "1.0".to_string(),
+                Some(version) => version.to_string_val(),
+            };
+            let encoding = args
+                .get(1)
+                .map(Value::to_string_val)
+                .filter(|encoding| !encoding.is_empty());
+            let doc = self.add_document(Document::new(&version, encoding));
+            this.set("__doc", Value::Integer(doc as i64));
+            this.set("__node", Value::Integer(0));
+            return Ok(Some(Value::Null));
+        }
+
+        let (doc, node) = self
+            .dom_handle(&this)
+            .ok_or_else(|| VmError::fatal(format!("Couldn't fetch {}", this.class_name())))?;
+        let text_arg = |i: usize| args.get(i).map(Value::to_string_val).unwrap_or_default();
+        let value = match name {
+            "__get" => return self.node_property(doc, node, &text_arg(0)).map(Some),
+            "__set" => {
+                if matches!(text_arg(0).as_str(), "nodeValue" | "textContent") {
+                    let text = self.value_to_string(args[1].clone())?;
+                    self.xml_documents[doc].set_text(node, &text);
+                }
+                Value::Null
+            }
+            "appendChild" | "insertBefore" => {
+                let Some(child) = self.node_argument(method, doc, &args[0])? else {
+                    return Ok(None);
+                };
+                let before = match args.get(1) {
+                    Some(Value::Null) | None => None,
+                    Some(reference) => match self.node_argument(method, doc, reference)? {
+                        Some(reference) => Some(reference),
+                        None => return Ok(None),
+                    },
+                };
+                let mut ancestor = Some(node);
+                while let Some(current) = ancestor {
+                    if current == child {
+                        ops::throw_error(self, "DOMException", "Hierarchy Request Error")?;
+                        return Ok(None);
+                    }
+                    ancestor = self.xml_documents[doc].nodes[current].parent;
+                }
+                let document = &mut self.xml_documents[doc];
+                document.append(node, child);
+                if let Some(reference) = before {
+                    let children = &mut document.nodes[node].children;
+                    children.pop();
+                    match children.iter().position(|&c| c == reference) {
+                        Some(at) => children.insert(at, child),
+                        None => {
+                            children.push(child);
+                            ops::throw_error(self, "DOMException", "Not Found Error")?;
+                            return Ok(None);
+                        }
+                    }
+                }
+                args[0].clone()
+            }
+            "removeChild" => {
+                let Some(child) = self.node_argument(method, doc, &args[0])? else {
+                    return Ok(None);
+                };
+                if self.xml_documents[doc].nodes[child].parent != Some(node) {
+                    ops::throw_error(self, "DOMException", "Not Found Error")?;
+                    return Ok(None);
+                }
+                self.xml_documents[doc].detach(child);
+                args[0].clone()
+            }
+            "hasChildNodes" => {
+                Value::Bool(!self.xml_documents[doc].nodes[node].children.is_empty())
+            }
+            "createElement" => {
+                let document = &mut self.xml_documents[doc];
+                let element = document.create_element(&text_arg(0));
+                document.set_text(element, &text_arg(1));
+                self.node_object(doc, element)?
+            }
+            "createTextNode" | "createComment" | "createCDATASection" => {
+                let text = text_arg(0);
+                let kind = match name {
+                    "createTextNode" => NodeKind::Text(text),
+                    "createComment" => NodeKind::Comment(text),
+                    _ => NodeKind::CData(text),
+                };
+                let created = self.xml_documents[doc].create(kind);
+                self.node_object(doc, created)?
+            }
+            "loadXML" | "load" => {
+                let source = if name == "load" {
+                    match self.filesystem.read_to_string(&text_arg(0)) {
+                        Ok(source) => source,
+                        Err(_) => {
+                            let message = format!(
+                                "{}(): I/O warning : failed to load external entity \"{}\"",
+                                method,
+                                text_arg(0)
+                            );
+                            self.raise(Level::Warning, &message)?;
+                            return Ok(Some(Value::Bool(false)));
+                        }
+                    }
+                } else {
+                    text_arg(0)
+                };
+                let keep_blanks = this
+                    .get("preserveWhiteSpace")
+                    .is_none_or(|value| value.to_bool());
+                match xml_parser::parse(&source, keep_blanks) {
+                    Ok(document) => {
+                        self.xml_documents[doc] = document;
+                        Value::Bool(true)
+                    }
+                    Err(error) => {
+                        self.warn_xml_error(method, &error)?;
+                        Value::Bool(false)
+                    }
+                }
+            }
+            "saveXML" | "save" => {
+                let format = this
+                    .get("formatOutput")
+                    .is_some_and(|value| value.to_bool());
+                let target = match (name, args.first()) {
+                    ("saveXML", Some(Value::Object(object))) => match self.dom_handle(object) {
+                        Some((node_doc, target)) if node_doc == doc => target,
+                        _ => {
+                            ops::throw_error(self, "DOMException", "Wrong Document Error")?;
+                            return Ok(None);
+                        }
+                    },
+                    _ => 0,
+                };
+                let xml = self.xml_documents[doc].serialize(target, format);
+                if name == "saveXML" {
+                    Value::String(xml)
+                } else {
+                    match self.filesystem.write(&text_arg(0), &xml) {
+                        Ok(()) => Value::Integer(xml.len() as i64),
+                        Err(_) => Value::Bool(false),
+                    }
+                }
+            }
+            "getElementsByTagName" => {
+                let elements = self.xml_documents[doc].descendant_elements(node, &text_arg(0));
+                self.node_list(doc, &elements)?
+            }
+            "getAttribute" => {
+                let value = self.xml_documents[doc].attribute(node, &text_arg(0));
+                Value::String(value.unwrap_or_default().to_string())
+            }
+            "hasAttribute" => Value::Bool(
+                self.xml_documents[doc]
+                    .attribute(node, &text_arg(0))
+                    .is_some(),
+            ),
+            "setAttribute" => {
+                let value = self.value_to_string(args[1].clone())?;
+                self.xml_documents[doc].set_attribute(node, &text_arg(0), &value);
+                Value::Bool(true)
+            }
+            "removeAttribute" => {
+                let had = self.xml_documents[doc]
+                    .attribute(node, &text_arg(0))
+                    .is_some();
+                self.xml_documents[doc].remove_attribute(node, &text_arg(0));
+                Value::Bool(had)
+            }
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method {}()",
+                    method
+                )))
+            }
+        };
+        Ok(Some(value))
+    }
+
+    /// A DOMNode property
+    fn node_property(&mut self, doc: usize, node: usize, name: &str) -> Result<Value, VmError> {
+        let document = &self.xml_documents[doc];
+        let kind = &document.nodes[node].kind;
+        let parent = document.nodes[node].parent;
+        let siblings = parent
+            .map(|p| document.nodes[p].children.clone())
+            .unwrap_or_default();
+        let at = siblings.iter().position(|&s| s == node);
+        Ok(match name {
+            "nodeName" => Value::String(document.name(node).to_string()),
+            "tagName" | "localName" if document.is_element(node) => {
+                let qualified = document.name(node);
+                let local = match qualified.split_once(':') {
+                    Some((_, local)) if name == "localName" => local,
+                    _ => qualified,
+                };
+                Value::String(local.to_string())
+            }
+            "nodeType" => Value::Integer(node_type(kind)),
+            "nodeValue" => match kind {
+                NodeKind::Document => Value::Null,
+                _ => Value::String(document.text_content(node)),
+            },
+            "textContent" | "data" | "wholeText" => Value::String(document.text_content(node)),
+            "documentElement" => {
+                let root = document.root();
+                return self.optional_node_object(doc, root);
+            }
+            "parentNode" => return self.optional_node_object(doc, parent),
+            "ownerDocument" => return self.node_object(doc, 0),
+            "firstChild" | "lastChild" => {
+                let children = &document.nodes[node].children;
+                let child = if name == "firstChild" {
+                    children.first()
+                } else {
+                    children.last()
+                };
+                let child = child.copied();
+                return self.optional_node_object(doc, child);
+            }
+            "previousSibling" | "nextSibling" => {
+                let sibling = at.and_then(|at| {
+                    if name == "previousSibling" {
+                        at.checked_sub(1).map(|i| siblings[i])
+                    } else {
+                        siblings.get(at + 1).copied()
+                    }
+                });
+                return self.optional_node_object(doc, sibling);
+            }
+            "childNodes" => {
+                let children = document.nodes[node].children.clone();
+                return self.node_list(doc, &children);
+            }
+            "version" | "xmlVersion" => Value::String(document.version.clone()),
+            "encoding" | "xmlEncoding" => match &document.encoding {
+                Some(encoding) => Value::String(encoding.clone()),
+                None => Value::Null,
+            },
+            _ => {
+                let class = node_class(kind);
+                self.raise(
+                    Level::Warning,
+                    &format!("Undefined property: {}::${}", class, name),
+                )?;
+                Value::Null
+            }
+        })
+    }
+
+    pub(super) fn node_list_method(
+        &mut self,
+        method: &str,
+        this: &ObjectRef,
+        args: &[Value],
+    ) -> Result<Option<Value>, VmError> {
+        let doc = index_property(this, "__doc")
+            .ok_or_else(|| VmError::fatal("Couldn't fetch DOMNodeList"))?;
+        let nodes = indexes_property(this, "__nodes");
+        let value = match method {
+            "__get" if args[0].to_string_val() == "length" => Value::Integer(nodes.len() as i64),
+            "__get" => Value::Null,
+            "count" => Value::Integer(nodes.len() as i64),
+            "item" => {
+                let node = usize::try_from(args[0].to_int())
+                    .ok()
+                    .and_then(|i| nodes.get(i))
+                    .copied();
+                self.optional_node_object(doc, node)?
+            }
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method DOMNodeList::{}()",
+                    method
+                )))
+            }
+        };
+        Ok(Some(value))
+    }
+
+    pub(super) fn xpath_method(
+        &mut self,
+        method: &str,
+        this: ObjectRef,
+        args: &[Value],
+    ) -> Result<Option<Value>, VmError> {
+        if method == "__construct" {
+            let doc = match &args[0] {
+                Value::Object(object) if object.class_name() == "DOMDocument" => {
+                    self.dom_handle(object)
+                }
+                _ => None,
+            };
+            let Some((doc, _)) = doc else {
+                return Err(VmError::type_error(format!(
+                    "DOMXPath::__construct(): Argument #1 ($document) must be of type DOMDocument, {} given",
+                    args[0].type_name()
+                )));
+            };
+            this.set("__doc", Value::Integer(doc as i64));
+            return Ok(Some(Value::Null));
+        }
+        let doc = index_property(&this, "__doc")
+            .ok_or_else(|| VmError::fatal("Couldn't fetch DOMXPath"))?;
+        if method != "query" {
+            return Err(VmError::error(format!(
+                "Call to undefined method DOMXPath::{}()",
+                method
+            )));
+        }
+        let context = match args.get(1) {
+            Some(Value::Null) | None => 0,
+            Some(node) => match self.node_argument("DOMXPath::query", doc, node)? {
+                Some(node) => node,
+                None => return Ok(None),
+            },
+        };
+        let expression = args[0].to_string_val();
+        match xpath::select(&self.xml_documents[doc], context, &expression) {
+            Ok(items) => {
+                let nodes: Vec<usize> = items
+                    .into_iter()
+                    .filter_map(|item| match item {
+                        Item::Node(node) => Some(node),
+                        Item::Attribute(..) => None,
+                    })
+                    .collect();
+                self.node_list(doc, &nodes).map(Some)
+            }
+            Err(error) => {
+                self.raise(Level::Warning, &format!("DOMXPath::query(): {}", error))?;
+                Ok(Some(Value::Bool(false)))
+            }
+        }
+    }
+}
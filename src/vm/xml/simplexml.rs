@@ -0,0 +1,222 @@
+//! SimpleXMLElement's methods
+//!
+//! Reading a property gives the child elements of that name as a list,
+//! or null when there is none; an integer offset picks an element of a
+//! list and a string offset reads an attribute. Attributes come back as
+//! strings, and `attributes()` gives them as an array.
+
+use super::{index_array, index_property, indexes_property};
+use crate::runtime::builtins::xml_parser;
+use crate::runtime::builtins::xpath::{self, Item};
+use crate::runtime::{ArrayKey, ObjectRef, Value};
+use crate::vm::error::VmError;
+use crate::vm::notices::Level;
+use crate::vm::{ops, VM};
+use std::io::Write;
+
+impl<W: Write> VM<W> {
+    /// A SimpleXMLElement for elements of document `doc`: a list of them,
+    /// or one element
+    pub(super) fn simplexml_object(
+        &mut self,
+        doc: usize,
+        nodes: &[usize],
+        list: bool,
+    ) -> Result<Value, VmError> {
+        self.xml_object(
+            "SimpleXMLElement",
+            vec![
+                ("__doc", Value::Integer(doc as i64)),
+                ("__nodes", index_array(nodes)),
+                ("__list", Value::Bool(list)),
+            ],
+        )
+    }
+
+    pub(super) fn simplexml_method(
+        &mut self,
+        method: &str,
+        this: ObjectRef,
+        args: &[Value],
+    ) -> Result<Option<Value>, VmError> {
+        if method == "__construct" {
+            let source = args[0].to_string_val();
+            let document = match xml_parser::parse(&source, true) {
+                Ok(document) => document,
+                Err(error) => {
+                    self.warn_xml_error("SimpleXMLElement::__construct", &error)?;
+                    ops::throw_error(self, "Exception", "String could not be parsed as XML")?;
+                    return Ok(None);
+                }
+            };
+            let root = document.root().unwrap_or(0);
+            let doc = self.add_document(document);
+            this.set("__doc", Value::Integer(doc as i64));
+            this.set("__nodes", index_array(&[root]));
+            this.set("__list", Value::Bool(false));
+            return Ok(Some(Value::Null));
+        }
+
+        let doc = index_property(&this, "__doc")
+            .ok_or_else(|| VmError::fatal("SimpleXMLElement is not initialized"))?;
+        let nodes = indexes_property(&this, "__nodes");
+        let is_list = this.get("__list").is_some_and(|value| value.to_bool());
+        let Some(&first) = nodes.first() else {
+            return Ok(Some(match method {
+                "__toString" => Value::String(String::new()),
+                "count" => Value::Integer(0),
+                _ => Value::Null,
+            }));
+        };
+        let name_arg = || args.first().map(Value::to_string_val).unwrap_or_default();
+        let value = match method {
+            "__get" => {
+                let children = self.xml_documents[doc].child_elements(first, Some(&name_arg()));
+                if children.is_empty() {
+                    Value::Null
+                } else {
+                    self.simplexml_object(doc, &children, true)?
+                }
+            }
+            "__isset" => {
+                let children = self.xml_documents[doc].child_elements(first, Some(&name_arg()));
+                Value::Bool(!children.is_empty())
+            }
+            "__set" => {
+                let name = name_arg();
+                let text = self.value_to_string(args[1].clone())?;
+                let document = &mut self.xml_documents[doc];
+                let child = match document.child_elements(first, Some(&name)).first() {
+                    Some(&child) => child,
+                    None => {
+                        let child = document.create_element(&name);
+                        document.append(first, child);
+                        child
+                    }
+                };
+                document.set_text(child, &text);
+                Value::Null
+            }
+            "__toString" => Value::String(self.xml_documents[doc].direct_text(first)),
+            "offsetGet" => match &args[0] {
+                Value::Integer(index) => {
+                    let element = if is_list {
+                        usize::try_from(*index)
+                            .ok()
+                            .and_then(|i| nodes.get(i))
+                            .copied()
+                    } else {
+                        (*index == 0).then_some(first)
+                    };
+                    match element {
+                        Some(element) => self.simplexml_object(doc, &[element], false)?,
+                        None => Value::Null,
+                    }
+                }
+                offset => {
+                    let name = offset.to_string_val();
+                    match self.xml_documents[doc].attribute(first, &name) {
+                        Some(value) => Value::String(value.to_string()),
+                        None => Value::Null,
+                    }
+                }
+            },
+            "count" => {
+                let count = if is_list {
+                    nodes.len()
+                } else {
+                    self.xml_documents[doc].child_elements(first, None).len()
+                };
+                Value::Integer(count as i64)
+            }
+            "getName" => Value::String(self.xml_documents[doc].name(first).to_string()),
+            "children" => {
+                let children = self.xml_documents[doc].child_elements(first, None);
+                self.simplexml_object(doc, &children, true)?
+            }
+            "attributes" => Value::Array(
+                self.xml_documents[doc]
+                    .attributes(first)
+                    .iter()
+                    .map(|(name, value)| {
+                        (ArrayKey::String(name.clone()), Value::String(value.clone()))
+                    })
+                    .collect(),
+            ),
+            "xpath" => {
+                let expression = args[0].to_string_val();
+                match xpath::select(&self.xml_documents[doc], first, &expression) {
+                    Ok(items) => {
+                        let mut results = Vec::new();
+                        for item in items {
+                            let value = match item {
+                                Item::Node(node) if self.xml_documents[doc].is_element(node) => {
+                                    self.simplexml_object(doc, &[node], false)?
+                                }
+                                item => Value::String(self.item_text(doc, &item)),
+                            };
+                            results.push((ArrayKey::Integer(results.len() as i64), value));
+                        }
+                        Value::Array(results.into())
+                    }
+                    Err(error) => {
+                        self.raise(
+                            Level::Warning,
+                            &format!("SimpleXMLElement::xpath(): {}", error),
+                        )?;
+                        Value::Bool(false)
+                    }
+                }
+            }
+            "asXML" | "saveXML" => {
+                let document = &self.xml_documents[doc];
+                let is_root = document.nodes[first].parent == Some(0);
+                let xml = document.serialize(if is_root { 0 } else { first }, false);
+                match args.first() {
+                    Some(Value::Null) | None => Value::String(xml),
+                    Some(filename) => {
+                        let written = self.filesystem.write(&filename.to_string_val(), &xml);
+                        Value::Bool(written.is_ok())
+                    }
+                }
+            }
+            "addChild" => {
+                let text = match args.get(1) {
+                    Some(Value::Null) | None => None,
+                    Some(value) => Some(self.value_to_string(value.clone())?),
+                };
+                let document = &mut self.xml_documents[doc];
+                let child = document.create_element(&name_arg());
+                document.append(first, child);
+                if let Some(text) = text {
+                    document.set_text(child, &text);
+                }
+                self.simplexml_object(doc, &[child], false)?
+            }
+            "addAttribute" => {
+                let value = self.value_to_string(args[1].clone())?;
+                self.xml_documents[doc].set_attribute(first, &name_arg(), &value);
+                Value::Null
+            }
+            _ => {
+                return Err(VmError::error(format!(
+                    "Call to undefined method SimpleXMLElement::{}()",
+                    method
+                )))
+            }
+        };
+        Ok(Some(value))
+    }
+
+    /// The text of an XPath result that isn't an element
+    fn item_text(&self, doc: usize, item: &Item) -> String {
+        let document = &self.xml_documents[doc];
+        match item {
+            Item::Attribute(node, name) => document
+                .attribute(*node, name)
+                .unwrap_or_default()
+                .to_string(),
+            Item::Node(node) => document.text_content(*node),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! yaml_parse and yaml_parse_file
+//!
+//! They only need the VM to read files and to raise the parser's errors
+//! as warnings; the parsing itself is in `runtime::builtins::yaml`.
+
+use super::VM;
+use crate::runtime::builtins::native::check_arity;
+use crate::runtime::builtins::yaml;
+use crate::runtime::Value;
+use crate::vm::error::VmError;
+use crate::vm::notices::Level;
+use std::io::Write;
+
+impl<W: Write> VM<W> {
+    /// `yaml_parse($input, $pos = 0)` and `yaml_parse_file($filename,
+    /// $pos = 0)`: false with a warning for invalid YAML
+    pub(crate) fn yaml_parse(&mut self, name: &str, args: &[Value]) -> Result<Value, VmError> {
+        check_arity(name, args.len(), 1, Some(4))?;
+        let source = if name == "yaml_parse_file" {
+            let filename = args[0].to_string_val();
+            match self.filesystem.read_to_string(&filename) {
+                Ok(source) => source,
+                Err(_) => {
+                    let message = format!(
+                        "{}({}): Failed to open stream: No such file or directory",
+                        name, filename
+                    );
+                    self.raise(Level::Warning, &message)?;
+                    return Ok(Value::Bool(false));
+                }
+            }
+        } else {
+            args[0].to_string_val()
+        };
+        let pos = args.get(1).map_or(0, Value::to_int);
+        match yaml::yaml_parse(&source, pos) {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                self.raise(Level::Warning, &format!("{}(): {}", name, error))?;
+                Ok(Value::Bool(false))
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! WebAssembly entry point
+//!
+//! `run` executes a script the way a browser playground needs it: output
+//! is captured instead of written to stdout, files live in a
+//! `MemoryFilesystem`, and errors come back as a message rather than a
+//! process exit. It works natively too, which is how it is tested.
+//!
+//! On wasm32 the crate also exports a small C ABI that `wasm/vhp.js`
+//! wraps into `run(source) -> {output, error}` for JavaScript:
+//!
+//! - `vhp_alloc(len) -> ptr` and `vhp_dealloc(ptr, len)` manage the
+//!   buffer the source is copied into
+//! - `vhp_run(ptr, len) -> ptr` runs it and returns the result as a JSON
+//!   object `{"output": ..., "error": ...}`, whose length is
+//!   `vhp_result_len()`
+//!
+//! ```
+//! let result = vhp::wasm::run("<?php echo 6 * 7;");
+//! assert_eq!(result.output, "42");
+//! assert_eq!(result.error, None);
+//! ```
+
+use crate::runtime::filesystem::MemoryFilesystem;
+use crate::vm::VM;
+
+/// What a script printed, and why it stopped if it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunResult {
+    pub output: String,
+    /// The error that ended the script; `None` on success or `exit()`
+    pub error: Option<String>,
+}
+
+/// Run `source` with an empty in-memory filesystem
+pub fn run(source: &str) -> RunResult {
+    run_with_files(source, MemoryFilesystem::default())
+}
+
+/// Run `source` with `files` as the filesystem it can read and write
+pub fn run_with_files(source: &str, files: MemoryFilesystem) -> RunResult {
+    let mut output = Vec::new();
+    let error = execute(source, files, &mut output).err();
+    RunResult {
+        output: String::from_utf8_lossy(&output).into_owned(),
+        error,
+    }
+}
+
+fn execute(source: &str, files: MemoryFilesystem, output: &mut Vec<u8>) -> Result<(), String> {
+    let compilation = crate::compile(source, "main.php")?;
+    let mut vm = VM::new(output);
+    vm.set_filesystem(Box::new(files));
+    let main = vm.load(compilation);
+    crate::exit_status(vm.execute(main)).map_err(crate::Error::Runtime)?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+mod exports {
+    use super::RunResult;
+    use std::cell::RefCell;
+
+    thread_local! {
+        /// The last `vhp_run` result, kept alive until the next call
+        static RESULT: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Allocate `len` bytes for the host to write a script into
+    #[no_mangle]
+    pub extern "C" fn vhp_alloc(len: usize) -> *mut u8 {
+        let mut buffer = Vec::<u8>::with_capacity(len);
+        let ptr = buffer.as_mut_ptr();
+        std::mem::forget(buffer);
+        ptr
+    }
+
+    /// Free a buffer returned by `vhp_alloc`
+    ///
+    /// # Safety
+    /// `ptr` and `len` must come from one `vhp_alloc` call.
+    #[no_mangle]
+    pub unsafe extern "C" fn vhp_dealloc(ptr: *mut u8, len: usize) {
+        drop(Vec::from_raw_parts(ptr, 0, len));
+    }
+
+    /// Run the UTF-8 script at `ptr` and return the JSON result
+    ///
+    /// # Safety
+    /// `ptr` must point to `len` initialized bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn vhp_run(ptr: *const u8, len: usize) -> *const u8 {
+        let bytes = std::slice::from_raw_parts(ptr, len);
+        let source = String::from_utf8_lossy(bytes);
+        let json = result_json(&super::run(&source));
+        RESULT.with(|result| {
+            let mut result = result.borrow_mut();
+            *result = json.into_bytes();
+            result.as_ptr()
+        })
+    }
+
+    /// Length in bytes of the result returned by the last `vhp_run`
+    #[no_mangle]
+    pub extern "C" fn vhp_result_len() -> usize {
+        RESULT.with(|result| result.borrow().len())
+    }
+
+    /// The JSON object handed to JavaScript
+    fn result_json(result: &RunResult) -> String {
+        use crate::runtime::builtins::json::json_encode;
+        use crate::runtime::{ArrayKey, Value};
+
+        let error = match &result.error {
+            Some(error) => Value::String(error.clone()),
+            None => Value::Null,
+        };
+        let object = Value::Array(vec![
+            (
+                ArrayKey::String("output".to_string()),
+                Value::String(result.output.clone()),
+            ),
+            (ArrayKey::String("error".to_string()), error),
+        ]);
+        match json_encode(&[object]) {
+            Ok(Value::String(json)) => json,
+            _ => r#"{"output":"","error":"Could not encode the result"}"#.to_string(),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+<?php
+class StaticCounter {
+    private static $count = 0;
+
+    public static function next() {
+        self::$count = self::$count + 1;
+        return self::$count;
+    }
+}
@@ -0,0 +1,144 @@
+//! `#[derive(Codec)]`: encode a type for the bytecode cache
+//!
+//! A struct is its fields in order; an enum is the index of its variant
+//! followed by that variant's fields. `SHAPE` spells out the names and
+//! field types, so that a change to the type changes the fingerprint of
+//! the cache format.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields};
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "Codec cannot be derived for generic types",
+        ));
+    }
+    let name = &input.ident;
+    let codec = quote! { ::vhp::vm::cache::codec };
+    let (shape, encode, decode) = match &input.data {
+        Data::Struct(data) => {
+            let (bindings, pattern) = bind(&data.fields);
+            let build = build(quote! { Self }, &data.fields);
+            (
+                format!("{}{}", name, shape(&data.fields)),
+                quote! {
+                    let Self #pattern = self;
+                    #(#codec::Codec::encode(#bindings, out)?;)*
+                },
+                quote! { Some(#build) },
+            )
+        }
+        Data::Enum(data) => {
+            let mut shapes = Vec::new();
+            let mut encode_arms = Vec::new();
+            let mut decode_arms = Vec::new();
+            for (index, variant) in data.variants.iter().enumerate() {
+                let ident = &variant.ident;
+                let tag = index as u32;
+                let (bindings, pattern) = bind(&variant.fields);
+                let build = build(quote! { Self::#ident }, &variant.fields);
+                shapes.push(format!("{}{}", ident, shape(&variant.fields)));
+                encode_arms.push(quote! {
+                    Self::#ident #pattern => {
+                        out.tag(#tag);
+                        #(#codec::Codec::encode(#bindings, out)?;)*
+                    }
+                });
+                decode_arms.push(quote! { #tag => #build, });
+            }
+            (
+                format!("{}{{{}}}", name, shapes.join("|")),
+                quote! {
+                    match self {
+                        #(#encode_arms)*
+                    }
+                },
+                quote! {
+                    Some(match input.tag()? {
+                        #(#decode_arms)*
+                        _ => return None,
+                    })
+                },
+            )
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "Codec cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #codec::Codec for #name {
+            const SHAPE: &'static str = #shape;
+
+            #[allow(unused_variables)]
+            fn encode(&self, out: &mut #codec::Encoder) -> Result<(), #codec::Unencodable> {
+                #encode
+                Ok(())
+            }
+
+            #[allow(unused_variables)]
+            fn decode(input: &mut #codec::Decoder<'_>) -> Option<Self> {
+                #decode
+            }
+        }
+    })
+}
+
+/// Names for the fields and the pattern that binds them
+fn bind(fields: &Fields) -> (Vec<syn::Ident>, TokenStream) {
+    match fields {
+        Fields::Named(named) => {
+            let idents: Vec<_> = named.named.iter().filter_map(|f| f.ident.clone()).collect();
+            let pattern = quote! { { #(#idents),* } };
+            (idents, pattern)
+        }
+        Fields::Unnamed(unnamed) => {
+            let idents: Vec<_> = (0..unnamed.unnamed.len())
+                .map(|i| format_ident!("field{}", i))
+                .collect();
+            let pattern = quote! { ( #(#idents),* ) };
+            (idents, pattern)
+        }
+        Fields::Unit => (Vec::new(), TokenStream::new()),
+    }
+}
+
+/// `path` built from fields decoded in order
+fn build(path: TokenStream, fields: &Fields) -> TokenStream {
+    let decode = quote! { ::vhp::vm::cache::codec::Codec::decode(input)? };
+    match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().filter_map(|f| f.ident.as_ref());
+            quote! { #path { #(#idents: #decode),* } }
+        }
+        Fields::Unnamed(unnamed) => {
+            let decodes = unnamed.unnamed.iter().map(|_| &decode);
+            quote! { #path ( #(#decodes),* ) }
+        }
+        Fields::Unit => path,
+    }
+}
+
+/// The field names and types, as written
+fn shape(fields: &Fields) -> String {
+    let types = fields.iter().map(|field| {
+        let ty = &field.ty;
+        let ty = quote! { #ty }.to_string().replace(' ', "");
+        match &field.ident {
+            Some(ident) => format!("{}:{}", ident, ty),
+            None => ty,
+        }
+    });
+    let types: Vec<_> = types.collect();
+    match fields {
+        Fields::Named(_) => format!("{{{}}}", types.join(",")),
+        Fields::Unnamed(_) => format!("({})", types.join(",")),
+        Fields::Unit => String::new(),
+    }
+}
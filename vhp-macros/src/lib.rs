@@ -0,0 +1,190 @@
+//! `#[vhp_builtin]`: declare a VHP built-in function as a typed Rust function
+//!
+//! ```ignore
+//! use vhp::vhp_builtin;
+//!
+//! /// fdiv - Divides two numbers according to IEEE 754
+//! #[vhp_builtin("fdiv")]
+//! pub fn fdiv(num1: f64, num2: f64) -> f64 {
+//!     num1 / num2
+//! }
+//! ```
+//!
+//! The function is kept as written. Next to it the macro emits a constant
+//! named after it in upper case (`FDIV`), a
+//! `vhp::runtime::builtins::native::Builtin` whose wrapper takes the PHP
+//! arguments and:
+//!
+//! - checks their count: every parameter is required except `Option<T>`
+//!   ones, and a last `Variadic<T>` parameter takes any number of
+//!   remaining arguments
+//! - converts each one with `FromArg`, failing with PHP's
+//!   "must be of type" error when it doesn't fit
+//! - converts the return value (`T` or `Result<T, NativeError>`) with
+//!   `IntoValue`
+//!
+//! List the constant in `DECLARED_BUILTINS` to make it a core built-in,
+//! or hand it to `Registry::add_builtin` from an extension.
+//!
+//! `#[derive(Codec)]` implements `vhp::vm::cache::codec::Codec` for the
+//! compiled code and the AST it holds, which the bytecode cache stores.
+
+mod codec;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, DeriveInput, FnArg, Ident, ItemFn, LitStr, Pat, ReturnType, Type};
+
+#[proc_macro_attribute]
+pub fn vhp_builtin(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let name = parse_macro_input!(attr as LitStr);
+    let function = parse_macro_input!(item as ItemFn);
+    match expand(&name, &function) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(Codec)]
+pub fn derive_codec(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match codec::expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// How a parameter takes its argument
+enum Kind {
+    Required,
+    Optional,
+    Variadic,
+}
+
+fn expand(name: &LitStr, function: &ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let php_name = name.value();
+    if php_name.is_empty() || php_name.to_lowercase() != php_name {
+        return Err(syn::Error::new(
+            name.span(),
+            "built-in names must be non-empty and lowercase",
+        ));
+    }
+
+    let mut conversions = Vec::new();
+    let mut idents = Vec::new();
+    let mut required = 0usize;
+    let mut variadic = false;
+    let params = &function.sig.inputs;
+    for (index, input) in params.iter().enumerate() {
+        let FnArg::Typed(param) = input else {
+            return Err(syn::Error::new_spanned(input, "built-ins cannot take self"));
+        };
+        let Pat::Ident(pat) = &*param.pat else {
+            return Err(syn::Error::new_spanned(
+                &param.pat,
+                "built-in parameters must be plain names",
+            ));
+        };
+        let ident = &pat.ident;
+        let param_name = ident.to_string();
+        let param_name = param_name.trim_start_matches('_');
+        let ty = &param.ty;
+        let conversion = match kind(ty) {
+            Kind::Variadic if index + 1 != params.len() => {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "only the last parameter can be Variadic",
+                ));
+            }
+            Kind::Variadic => {
+                variadic = true;
+                quote! {
+                    ::vhp::runtime::builtins::native::variadic(#php_name, args, #index, #param_name)?
+                }
+            }
+            Kind::Required if required != index => {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "required parameters must come before optional ones",
+                ));
+            }
+            kind => {
+                if matches!(kind, Kind::Required) {
+                    required += 1;
+                }
+                quote! {
+                    ::vhp::runtime::builtins::native::arg::<#ty>(#php_name, args, #index, #param_name)?
+                }
+            }
+        };
+        let local = format_ident!("__{}", ident);
+        conversions.push(quote! { let #local: #ty = #conversion; });
+        idents.push(local);
+    }
+
+    let max = if variadic {
+        quote! { None }
+    } else {
+        let count = params.len();
+        quote! { Some(#count) }
+    };
+    let fn_name = &function.sig.ident;
+    let call = quote! { #fn_name(#(#idents),*) };
+    let result = if returns_result(&function.sig.output) {
+        quote! { #call.map(::vhp::runtime::builtins::native::IntoValue::into_value) }
+    } else {
+        quote! { Ok(::vhp::runtime::builtins::native::IntoValue::into_value(#call)) }
+    };
+
+    let vis = &function.vis;
+    let constant = Ident::new(&fn_name.to_string().to_uppercase(), Span::call_site());
+    let doc = format!("Declaration of the `{}()` built-in", php_name);
+    Ok(quote! {
+        #function
+
+        #[doc = #doc]
+        #vis const #constant: ::vhp::runtime::builtins::native::Builtin =
+            ::vhp::runtime::builtins::native::Builtin {
+                name: #php_name,
+                call: {
+                    fn call(
+                        args: &[::vhp::runtime::Value],
+                    ) -> Result<::vhp::runtime::Value, ::vhp::vm::error::NativeError> {
+                        ::vhp::runtime::builtins::native::check_arity(
+                            #php_name,
+                            args.len(),
+                            #required,
+                            #max,
+                        )?;
+                        #(#conversions)*
+                        #result
+                    }
+                    call
+                },
+            };
+    })
+}
+
+/// The last path segment of `ty`, if it is a path
+fn last_segment(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn kind(ty: &Type) -> Kind {
+    match last_segment(ty).as_deref() {
+        Some("Option") => Kind::Optional,
+        Some("Variadic") => Kind::Variadic,
+        _ => Kind::Required,
+    }
+}
+
+fn returns_result(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => last_segment(ty).as_deref() == Some("Result"),
+        ReturnType::Default => false,
+    }
+}
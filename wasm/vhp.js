@@ -0,0 +1,38 @@
+// JavaScript API for the VHP WebAssembly build
+//
+//   import { load } from "./vhp.js";
+//   const vhp = await load(fetch("vhp.wasm"));
+//   const { output, error } = vhp.run("<?php echo 'Hello';");
+//
+// `load` accepts anything WebAssembly.instantiate accepts (bytes or a
+// compiled module) or a fetch Response / Promise of one.
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+/** Instantiate the module and return `{ run(source) }` */
+export async function load(source) {
+  const imports = { env: { vhp_now_ms: () => Date.now() } };
+  const resolved = await source;
+  const result =
+    typeof Response !== "undefined" && resolved instanceof Response
+      ? await WebAssembly.instantiate(await resolved.arrayBuffer(), imports)
+      : await WebAssembly.instantiate(resolved, imports);
+  const exports = (result.instance ?? result).exports;
+
+  return {
+    /** Run a PHP script, returning `{ output, error }` */
+    run(source) {
+      const bytes = encoder.encode(source);
+      const ptr = exports.vhp_alloc(bytes.length);
+      new Uint8Array(exports.memory.buffer, ptr, bytes.length).set(bytes);
+      const resultPtr = exports.vhp_run(ptr, bytes.length);
+      exports.vhp_dealloc(ptr, bytes.length);
+      const length = exports.vhp_result_len();
+      const json = decoder.decode(
+        new Uint8Array(exports.memory.buffer, resultPtr, length),
+      );
+      return JSON.parse(json);
+    },
+  };
+}